@@ -24,18 +24,32 @@ use crate::AttestationConfig;
 use crate::AttestedTlsConfig;
 use crate::EndorsedAttestationReport;
 
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, SgxRwLock as RwLock};
 use std::thread;
-use std::time::{Duration, SystemTime};
-use std::untrusted::time::SystemTimeEx;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Result};
 use log::debug;
 use teaclave_config::build::ATTESTATION_VALIDITY_SECS;
+use teaclave_types::platform;
 
 const CERT_ISSUER: &str = "Teaclave";
 const CERT_SUBJECT: &str = "CN=Teaclave";
 
+// Seconds since the Unix epoch of the latest endorsement of an attestation
+// report in the enclave, or zero before the first one.
+static LAST_ENDORSEMENT: AtomicU64 = AtomicU64::new(0);
+
+/// Time of the latest endorsement of an attestation report in the enclave,
+/// which is refreshed periodically by `RemoteAttestation`.
+pub fn last_endorsement() -> Option<SystemTime> {
+    match LAST_ENDORSEMENT.load(Ordering::SeqCst) {
+        0 => None,
+        secs => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+    }
+}
+
 pub struct RemoteAttestation {
     attestation_config: Arc<AttestationConfig>,
     attested_tls_config: Option<Arc<RwLock<AttestedTlsConfig>>>,
@@ -85,8 +99,11 @@ impl AttestedTlsConfig {
         let extension = serde_json::to_vec(&report)?;
         let cert = key_pair.create_cert_with_extension(CERT_ISSUER, CERT_SUBJECT, &extension);
         let private_key = key_pair.private_key_into_der();
-        let time = SystemTime::now();
+        let time = platform::time::now();
         let validity = Duration::from_secs(ATTESTATION_VALIDITY_SECS);
+        if let Ok(elapsed) = time.duration_since(UNIX_EPOCH) {
+            LAST_ENDORSEMENT.store(elapsed.as_secs(), Ordering::SeqCst);
+        }
 
         let attested_tls_config = AttestedTlsConfig {
             cert,
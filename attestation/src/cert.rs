@@ -351,7 +351,7 @@ macro_rules! asn1_set_ty {
     };
 }
 
-#[cfg(feature = "mesalock_sgx")]
+#[cfg(feature = "sgx")]
 macro_rules! asn1_seq {
     () => { () };
     ($e: expr) => {
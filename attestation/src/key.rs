@@ -100,9 +100,7 @@ impl NistP256KeyPair {
         use bit_vec::BitVec;
         use chrono::TimeZone;
         use num_bigint::BigUint;
-        use std::time::SystemTime;
-        use std::time::UNIX_EPOCH;
-        use std::untrusted::time::SystemTimeEx;
+        use teaclave_types::platform;
         use yasna::construct_der;
         use yasna::models::{ObjectIdentifier, UTCTime};
 
@@ -115,8 +113,7 @@ impl NistP256KeyPair {
 
         let pub_key_bytes = self.public_key_into_bytes();
 
-        // UNIX_EPOCH is the earliest time stamp. This unwrap should constantly succeed.
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let now = platform::time::since_epoch();
         let issue_ts = chrono::Utc.timestamp(now.as_secs() as i64, 0);
 
         // This is guaranteed to be a valid duration.
@@ -19,16 +19,20 @@
 //! supporting both EPID and ECDSA attestation. By default, Intel Attestation
 //! Service is used for RA.
 
-#![cfg_attr(feature = "mesalock_sgx", no_std)]
-#[cfg(feature = "mesalock_sgx")]
+#![cfg_attr(feature = "sgx", no_std)]
+#[cfg(feature = "sgx")]
 #[macro_use]
 extern crate sgx_tstd as std;
 
+#[cfg(all(feature = "sgx", feature = "libos"))]
+compile_error!("feature \"sgx\" and feature \"libos\" cannot be enabled at the same time");
+
 use std::prelude::v1::*;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use teaclave_types::{AttestationSummary, EnclaveMeasurement};
 
 /// Errors that can happen during attestation and verification process
 #[derive(thiserror::Error, Debug)]
@@ -155,27 +159,49 @@ pub struct AttestedTlsConfig {
     pub validity: std::time::Duration,
 }
 
+impl AttestedTlsConfig {
+    /// Summary of the attestation report in the certificate, carried in the
+    /// claims of the tokens signed with the key of the certificate.
+    pub fn attestation_summary(&self) -> Result<AttestationSummary> {
+        let endorsed_report = EndorsedAttestationReport::from_cert(&self.cert)?;
+        let report = report::AttestationReport::from_attn_report(&endorsed_report.report)?;
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        Ok(AttestationSummary {
+            quote_status: format!("{:?}", report.sgx_quote_status),
+            advisory_ids: report.advisory_ids.clone(),
+            measurement: EnclaveMeasurement::new(
+                enclave_report.mr_enclave,
+                enclave_report.mr_signer,
+            ),
+        })
+    }
+}
+
 #[macro_use]
 mod cert;
 pub mod report;
 pub mod verifier;
 
 cfg_if::cfg_if! {
-    if #[cfg(feature = "mesalock_sgx")]  {
+    if #[cfg(feature = "sgx")]  {
         mod service;
         pub mod key;
         mod platform;
         mod attestation;
-        pub use attestation::RemoteAttestation;
+        pub use attestation::{last_endorsement, RemoteAttestation};
     }
 }
 
-#[cfg(all(feature = "enclave_unit_test", feature = "mesalock_sgx"))]
+#[cfg(all(feature = "enclave_unit_test", feature = "sgx"))]
 pub mod tests {
     use super::*;
     use teaclave_test_utils::*;
 
     pub fn run_tests() -> bool {
-        run_tests!(platform::tests::run_tests, report::tests::run_tests)
+        run_tests!(
+            platform::tests::run_tests,
+            report::tests::run_tests,
+            verifier::tests::run_tests
+        )
     }
 }
@@ -21,11 +21,11 @@
 use std::prelude::v1::*;
 
 use log::debug;
-use sgx_rand::{os::SgxRng, Rng};
 use sgx_tcrypto::rsgx_sha256_slice;
 use sgx_tse::{rsgx_create_report, rsgx_verify_report};
 use sgx_types::sgx_status_t::SGX_SUCCESS;
 use sgx_types::*;
+use teaclave_types::platform;
 
 type SgxStatus = sgx_types::sgx_status_t;
 type Result<T> = std::result::Result<T, PlatformError>;
@@ -49,8 +49,6 @@ pub enum PlatformError {
         qe_report.body.report_data.d[..32] {1:?}"
     )]
     ReportReplay(Vec<u8>, Vec<u8>),
-    #[error("Failed to use SGX rng to generate random number: {0}")]
-    SgxRngError(std::io::Error),
     #[error("Other SGX platform error: {0}")]
     Others(SgxStatus),
 }
@@ -147,8 +145,7 @@ pub(crate) fn get_sgx_quote(ak_id: &sgx_att_key_id_t, report: sgx_report_t) -> R
     let mut qe_report_info = sgx_qe_report_info_t::default();
     let mut quote_nonce = sgx_quote_nonce_t::default();
 
-    let mut rng = SgxRng::new().map_err(PlatformError::SgxRngError)?;
-    rng.fill_bytes(&mut quote_nonce.rand);
+    platform::rand::fill_bytes(&mut quote_nonce.rand);
     qe_report_info.nonce = quote_nonce;
 
     debug!("sgx_self_target");
@@ -212,7 +209,7 @@ pub(crate) fn get_sgx_quote(ak_id: &sgx_att_key_id_t, report: sgx_report_t) -> R
     Ok(quote)
 }
 
-#[cfg(all(feature = "enclave_unit_test", feature = "mesalock_sgx"))]
+#[cfg(all(feature = "enclave_unit_test", feature = "sgx"))]
 pub mod tests {
     use super::*;
     use crate::key;
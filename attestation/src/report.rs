@@ -19,7 +19,7 @@
 //! The implementation is based on Attestation Service API version 4.
 //! https://api.trustedservices.intel.com/documents/sgx-attestation-api-spec.pdf
 
-#[cfg(feature = "mesalock_sgx")]
+#[cfg(feature = "sgx")]
 use std::prelude::v1::*;
 
 use crate::AttestationError;
@@ -28,12 +28,12 @@ use crate::EndorsedAttestationReport;
 use std::convert::TryFrom;
 use std::fmt;
 use std::time::*;
-#[cfg(feature = "mesalock_sgx")]
-use std::untrusted::time::SystemTimeEx;
 
 use anyhow::{anyhow, bail, ensure, Error, Result};
 use chrono::DateTime;
 use serde_json::Value;
+use teaclave_types::platform;
+use teaclave_types::{CpuSvn, MrEnclave, MrSigner, ReportData};
 use uuid::Uuid;
 
 type SignatureAlgorithms = &'static [&'static webpki::SignatureAlgorithm];
@@ -64,7 +64,7 @@ static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
 /// attestation service's private key, a.k.a., `EndorsedAttestationReport`.
 pub struct SgxEnclaveReport {
     /// Security version number of host system's CPU
-    pub cpu_svn: [u8; 16],
+    pub cpu_svn: CpuSvn,
     /// Misc select bits for the target enclave. Reserved for future function
     /// extension.
     pub misc_select: u32,
@@ -73,16 +73,16 @@ pub struct SgxEnclaveReport {
     pub attributes: [u8; 16],
     /// Measurement value of the enclave. See
     /// [`EnclaveMeasurement`](../types/struct.EnclaveMeasurement.html)
-    pub mr_enclave: [u8; 32],
+    pub mr_enclave: MrEnclave,
     /// Measurement value of the public key that verified the enclave. See
     /// [`EnclaveMeasurement`](../types/struct.EnclaveMeasurement.html)
-    pub mr_signer: [u8; 32],
+    pub mr_signer: MrSigner,
     /// Product ID of the enclave
     pub isv_prod_id: u16,
     /// Security version number of the enclave
     pub isv_svn: u16,
     /// Set of data used for communication between enclave and target enclave
-    pub report_data: [u8; 64],
+    pub report_data: ReportData,
 }
 
 impl std::fmt::Debug for SgxEnclaveReport {
@@ -94,36 +94,28 @@ impl std::fmt::Debug for SgxEnclaveReport {
         writeln!(f, "mr_signer: {:?}", self.mr_signer)?;
         writeln!(f, "isv_prod_id: {}", self.isv_prod_id)?;
         writeln!(f, "isv_svn: {}", self.isv_svn)?;
-        writeln!(f, "report_data: {:?}", &self.report_data.to_vec())
+        writeln!(f, "report_data: {:?}", self.report_data)
     }
 }
 
 impl fmt::Display for SgxEnclaveReport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "CPU version (hex): {}", hex::encode(self.cpu_svn))?;
+        writeln!(f, "CPU version (hex): {}", self.cpu_svn)?;
         writeln!(f, "SSA Frame extended feature set: {}", self.misc_select)?;
         writeln!(
             f,
             "Attributes of the enclave (hex): {}",
             hex::encode(self.attributes)
         )?;
-        writeln!(
-            f,
-            "Enclave measurement (hex): {}",
-            hex::encode(self.mr_enclave)
-        )?;
+        writeln!(f, "Enclave measurement (hex): {}", self.mr_enclave)?;
         writeln!(
             f,
             "Hash of the enclave singing key (hex): {}",
-            hex::encode(self.mr_signer)
+            self.mr_signer
         )?;
         writeln!(f, "Enclave product ID: {}", self.isv_prod_id)?;
         writeln!(f, "Security version of the enclave: {}", self.isv_svn)?;
-        writeln!(
-            f,
-            "The value of REPORT (hex): {}",
-            hex::encode(&self.report_data.to_vec())
-        )
+        writeln!(f, "The value of REPORT (hex): {}", self.report_data)
     }
 }
 
@@ -144,7 +136,7 @@ impl SgxEnclaveReport {
         // Start parsing report by bytes following specifications. Don't
         // transmute directly, since there may cause endianness issue.
         // off 48, size 16
-        let cpu_svn = <[u8; 16]>::try_from(take(16)?)?;
+        let cpu_svn = CpuSvn::try_from(take(CpuSvn::LENGTH)?)?;
 
         // off 64, size 4
         let misc_select = u32::from_le_bytes(<[u8; 4]>::try_from(take(4)?)?);
@@ -156,13 +148,13 @@ impl SgxEnclaveReport {
         let attributes = <[u8; 16]>::try_from(take(16)?)?;
 
         // off 112, size 32
-        let mr_enclave = <[u8; 32]>::try_from(take(32)?)?;
+        let mr_enclave = MrEnclave::try_from(take(MrEnclave::LENGTH)?)?;
 
         // off 144, size 32
         let _reserved = take(32)?;
 
         // off 176, size 32
-        let mr_signer = <[u8; 32]>::try_from(take(32)?)?;
+        let mr_signer = MrSigner::try_from(take(MrSigner::LENGTH)?)?;
 
         // off 208, size 96
         let _reserved = take(96)?;
@@ -177,12 +169,7 @@ impl SgxEnclaveReport {
         let _reserved = take(60)?;
 
         // off 368, size 64
-        let mut report_data = [0u8; 64];
-        let _report_data = take(64)?;
-        let mut _it = _report_data.iter();
-        for i in report_data.iter_mut() {
-            *i = *_it.next().ok_or_else(|| anyhow!("Quote parsing error."))?;
-        }
+        let report_data = ReportData::try_from(take(ReportData::LENGTH)?)?;
 
         ensure!(pos == bytes.len(), "Quote parsing error.");
 
@@ -239,7 +226,7 @@ impl std::fmt::Display for SgxQuoteVersion {
 }
 
 /// SGX Quote status
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum SgxQuoteStatus {
     /// EPID signature of the ISV enclave QUOTE was verified correctly and the
     /// TCB level of the SGX platform is up-to-date.
@@ -486,6 +473,9 @@ pub struct AttestationReport {
     pub freshness: Duration,
     /// Quote status
     pub sgx_quote_status: SgxQuoteStatus,
+    /// Advisory IDs of the security issues affecting the platform, e.g.,
+    /// "INTEL-SA-00334"
+    pub advisory_ids: Vec<String>,
     /// Content of the quote
     pub sgx_quote_body: SgxQuote,
 }
@@ -494,6 +484,7 @@ impl fmt::Display for AttestationReport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Report Freshness: {:?}", self.freshness)?;
         writeln!(f, "SGX Quote status: {:?}", self.sgx_quote_status)?;
+        writeln!(f, "Advisory IDs: {}", self.advisory_ids.join(", "))?;
         write!(f, "{}", self.sgx_quote_body)
     }
 }
@@ -511,11 +502,9 @@ impl AttestationReport {
         let tbs_cert: <TbsCert as Asn1Ty>::ValueTy = x509.0;
         let pub_key: <PubKey as Asn1Ty>::ValueTy = ((((((tbs_cert.1).1).1).1).1).1).0;
         let pub_k = (pub_key.1).0;
-        let cert_ext: <SgxRaCertExt as Asn1Ty>::ValueTy = (((((((tbs_cert.1).1).1).1).1).1).1).0;
-        let cert_ext_payload: Vec<u8> = ((cert_ext.0).1).0;
 
         // Convert to endorsed report
-        let report: EndorsedAttestationReport = serde_json::from_slice(&cert_ext_payload)?;
+        let report = EndorsedAttestationReport::from_cert(cert)?;
 
         // Verify report's signature
         let signing_cert = webpki::EndEntityCert::from(&report.signing_cert)?;
@@ -530,7 +519,7 @@ impl AttestationReport {
             .map(|cert| cert.to_trust_anchor())
             .collect();
         let chain = vec![report_ca_cert];
-        let time = webpki::Time::try_from(SystemTime::now())
+        let time = webpki::Time::try_from(platform::time::now())
             .map_err(|_| anyhow!("Cannot convert time."))?;
         signing_cert.verify_is_valid_tls_server_cert(
             SUPPORTED_SIG_ALGS,
@@ -546,8 +535,40 @@ impl AttestationReport {
             &report.signature,
         )?;
 
+        let attestation_report = Self::from_attn_report(&report.report)?;
+
+        // According to RFC 5480 `Elliptic Curve Cryptography Subject Public Key
+        // Information', SEC 2.2: ``The first octet of the OCTET STRING
+        // indicates whether the key is compressed or uncompressed. The
+        // uncompressed form is indicated by 0x04 and the compressed form is
+        // indicated by either 0x02 or 0x03 (see 2.3.3 in [SEC1]). The public
+        // key MUST be rejected if any other value is included in the first
+        // octet.''
+        //
+        // We only accept the uncompressed form here.
+        let raw_pub_k = pub_k.to_bytes();
+        let is_uncompressed = raw_pub_k[0] == 4;
+        let pub_k = &raw_pub_k.as_slice()[1..];
+        let report_data = &attestation_report
+            .sgx_quote_body
+            .isv_enclave_report
+            .report_data;
+        if !is_uncompressed || report_data != pub_k {
+            bail!(AttestationError::ReportError);
+        }
+
+        Ok(attestation_report)
+    }
+
+    /// Parse the attestation report returned by the attestation service.
+    ///
+    /// # Note
+    ///
+    /// This function does NOT verify the signature of the report, use
+    /// `from_cert` to get a verified report.
+    pub fn from_attn_report(report: &[u8]) -> Result<Self> {
         // Verify and extract information from attestation report
-        let attn_report: Value = serde_json::from_slice(&report.report)?;
+        let attn_report: Value = serde_json::from_slice(report)?;
         log::trace!("attn_report: {}", attn_report);
 
         // Verify API version is supported
@@ -564,7 +585,7 @@ impl AttestationReport {
             let time_fixed = String::from(time) + "+0000";
             let date_time = DateTime::parse_from_str(&time_fixed, "%Y-%m-%dT%H:%M:%S%.f%z")?;
             let ts = date_time.naive_utc();
-            let now = DateTime::<chrono::offset::Utc>::from(SystemTime::now()).naive_utc();
+            let now = DateTime::<chrono::offset::Utc>::from(platform::time::now()).naive_utc();
             let quote_freshness = u64::try_from((now - ts).num_seconds())?;
             std::time::Duration::from_secs(quote_freshness)
         };
@@ -577,6 +598,20 @@ impl AttestationReport {
             SgxQuoteStatus::from(status_string)
         };
 
+        // Get advisory IDs, which are only present when the platform is
+        // affected by some security advisories
+        let advisory_ids = match attn_report["advisoryIDs"].as_array() {
+            Some(ids) => ids
+                .iter()
+                .map(|id| {
+                    id.as_str()
+                        .map(|id| id.to_string())
+                        .ok_or_else(|| Error::new(AttestationError::ReportError))
+                })
+                .collect::<Result<Vec<String>>>()?,
+            None => Vec::new(),
+        };
+
         // Get quote body
         let sgx_quote_body = {
             let quote_encoded = attn_report["isvEnclaveQuoteBody"]
@@ -586,37 +621,39 @@ impl AttestationReport {
             SgxQuote::parse_from(quote_raw.as_slice())?
         };
 
-        // According to RFC 5480 `Elliptic Curve Cryptography Subject Public Key
-        // Information', SEC 2.2: ``The first octet of the OCTET STRING
-        // indicates whether the key is compressed or uncompressed. The
-        // uncompressed form is indicated by 0x04 and the compressed form is
-        // indicated by either 0x02 or 0x03 (see 2.3.3 in [SEC1]). The public
-        // key MUST be rejected if any other value is included in the first
-        // octet.''
-        //
-        // We only accept the uncompressed form here.
-        let raw_pub_k = pub_k.to_bytes();
-        let is_uncompressed = raw_pub_k[0] == 4;
-        let pub_k = &raw_pub_k.as_slice()[1..];
-        if !is_uncompressed || pub_k != &sgx_quote_body.isv_enclave_report.report_data[..] {
-            bail!(AttestationError::ReportError);
-        }
-
         Ok(Self {
             freshness,
             sgx_quote_status,
+            advisory_ids,
             sgx_quote_body,
         })
     }
 }
 
-#[cfg(all(feature = "enclave_unit_test", feature = "mesalock_sgx"))]
+impl EndorsedAttestationReport {
+    /// Extract the endorsed attestation report from the extension of a TLS
+    /// certificate generated by `RemoteAttestation`. The report is not
+    /// verified.
+    pub fn from_cert(cert: &[u8]) -> Result<Self> {
+        use crate::cert::*;
+
+        let x509 = yasna::parse_der(cert, X509::load)?;
+        let tbs_cert: <TbsCert as Asn1Ty>::ValueTy = x509.0;
+        let cert_ext: <SgxRaCertExt as Asn1Ty>::ValueTy = (((((((tbs_cert.1).1).1).1).1).1).1).0;
+        let cert_ext_payload: Vec<u8> = ((cert_ext.0).1).0;
+        let report = serde_json::from_slice(&cert_ext_payload)?;
+
+        Ok(report)
+    }
+}
+
+#[cfg(all(feature = "enclave_unit_test", feature = "sgx"))]
 pub mod tests {
     use super::*;
     use serde_json::json;
     use std::io::Read;
-    use std::untrusted::fs::File;
     use teaclave_test_utils::*;
+    use teaclave_types::platform::fs::File;
 
     fn tls_ra_cert_der_v3() -> Vec<u8> {
         let mut cert = vec![];
@@ -731,7 +768,7 @@ pub mod tests {
         assert_eq!(isv_enclave_report.isv_prod_id, 0);
         assert_eq!(isv_enclave_report.isv_svn, 0);
         assert_eq!(
-            isv_enclave_report.report_data.to_vec(),
+            isv_enclave_report.report_data.as_bytes().to_vec(),
             [
                 216, 33, 143, 100, 208, 197, 102, 9, 210, 3, 82, 225, 75, 127, 253, 155, 24, 129,
                 192, 124, 248, 206, 246, 123, 194, 237, 248, 51, 173, 7, 157, 120, 13, 214, 91,
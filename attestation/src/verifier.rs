@@ -17,11 +17,14 @@
 
 //! This module provides types used to verify attestation reports.
 
-use crate::report::AttestationReport;
+use crate::report::{AttestationReport, SgxQuoteStatus};
 
+#[cfg(feature = "sgx")]
+use std::prelude::v1::*;
 use std::vec::Vec;
 
-use log::{debug, error};
+use log::{debug, error, info};
+use teaclave_config::QuoteStatusConfig;
 use teaclave_types::EnclaveAttr;
 
 /// User defined verification function to further verify the attestation report.
@@ -38,6 +41,87 @@ pub struct AttestationReportVerifier {
     pub root_ca: Vec<u8>,
     /// User defined function to verify the attestation report.
     pub verifier: AttestationReportVerificationFn,
+    /// Per-advisory overrides of the quote status matching.
+    pub quote_status_policy: QuoteStatusPolicy,
+}
+
+/// Result of checking a quote status against a `QuoteStatusPolicy`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QuoteVerdict {
+    /// The quote status is accepted. `with_exceptions` contains the advisory
+    /// IDs which are tolerated by the policy.
+    Accepted { with_exceptions: Vec<String> },
+    /// The status has no override in the policy, and is left to the user
+    /// defined verification function.
+    NotOverridden,
+    /// The quote status is rejected because of the listed advisory IDs which
+    /// are not in the allow-list.
+    Rejected { advisory_ids: Vec<String> },
+}
+
+/// Allow-lists of advisory IDs for quote statuses which indicate that the
+/// platform is affected by some security advisories.
+#[derive(Clone, Debug, Default)]
+pub struct QuoteStatusPolicy {
+    config: QuoteStatusConfig,
+}
+
+impl QuoteStatusPolicy {
+    pub fn new(config: QuoteStatusConfig) -> Self {
+        Self { config }
+    }
+
+    /// Create quote status policy from Teaclave runtime configuration.
+    pub fn from_teaclave_config(config: &teaclave_config::RuntimeConfig) -> Self {
+        Self::new(config.attestation.quote_status.clone())
+    }
+
+    fn allow_list(&self, status: &SgxQuoteStatus) -> Option<&Vec<String>> {
+        match status {
+            SgxQuoteStatus::GroupOutOfDate => self.config.accept_group_out_of_date_if_only.as_ref(),
+            SgxQuoteStatus::ConfigurationNeeded => {
+                self.config.accept_configuration_needed_if_only.as_ref()
+            }
+            SgxQuoteStatus::SwHardeningNeeded => {
+                self.config.accept_sw_hardening_needed_if_only.as_ref()
+            }
+            SgxQuoteStatus::ConfigurationAndSwHardeningNeeded => self
+                .config
+                .accept_configuration_and_sw_hardening_needed_if_only
+                .as_ref(),
+            SgxQuoteStatus::OutOfDate => self.config.accept_out_of_date_if_only.as_ref(),
+            SgxQuoteStatus::OutOfDateConfigurationNeeded => self
+                .config
+                .accept_out_of_date_configuration_needed_if_only
+                .as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Cross-reference the advisory IDs of the report against the allow-list
+    /// of its quote status.
+    pub fn check(&self, report: &AttestationReport) -> QuoteVerdict {
+        let allow_list = match self.allow_list(&report.sgx_quote_status) {
+            Some(allow_list) => allow_list,
+            None => return QuoteVerdict::NotOverridden,
+        };
+
+        let (accepted, rejected): (Vec<String>, Vec<String>) = report
+            .advisory_ids
+            .iter()
+            .cloned()
+            .partition(|id| allow_list.contains(id));
+
+        if rejected.is_empty() {
+            QuoteVerdict::Accepted {
+                with_exceptions: accepted,
+            }
+        } else {
+            QuoteVerdict::Rejected {
+                advisory_ids: rejected,
+            }
+        }
+    }
 }
 
 /// Checks if he quote's status is not `UnknownBadStatus`
@@ -56,9 +140,37 @@ impl AttestationReportVerifier {
             accepted_enclave_attrs,
             root_ca: root_ca.to_vec(),
             verifier,
+            quote_status_policy: QuoteStatusPolicy::default(),
         }
     }
 
+    pub fn quote_status_policy(self, quote_status_policy: QuoteStatusPolicy) -> Self {
+        Self {
+            quote_status_policy,
+            ..self
+        }
+    }
+
+    /// Verify the quote status with the quote status policy first, and fall
+    /// back to the user defined verification function if the status is not
+    /// overridden. Overridden verdicts are logged to the `audit` target but
+    /// not to the sealed audit log: the verifier runs in the TLS handshake of
+    /// every attested channel, including those to the storage service which
+    /// keeps the sealed log, and holds no storage client.
+    fn verify_quote_status(&self, attestation_report: &AttestationReport) -> bool {
+        let verdict = self.quote_status_policy.check(attestation_report);
+        if verdict == QuoteVerdict::NotOverridden {
+            return (self.verifier)(attestation_report);
+        }
+
+        info!(
+            target: "audit",
+            "quote status {:?} verdict: {:?}",
+            attestation_report.sgx_quote_status, verdict
+        );
+        matches!(verdict, QuoteVerdict::Accepted { .. })
+    }
+
     /// Verify whether the `MR_SIGNER` and `MR_ENCLAVE` in the attestation report is
     /// accepted by us, which are defined in `accepted_enclave_attrs`.
     fn verify_measures(&self, attestation_report: &AttestationReport) -> bool {
@@ -95,10 +207,10 @@ impl AttestationReportVerifier {
         // Enclave measures are not tested in test mode since we have
         // a dedicated test enclave not known to production enclaves
         if cfg!(test_mode) {
-            return (self.verifier)(&report);
+            return self.verify_quote_status(&report);
         }
 
-        self.verify_measures(&report) && (self.verifier)(&report)
+        self.verify_measures(&report) && self.verify_quote_status(&report)
     }
 }
 
@@ -153,3 +265,85 @@ impl rustls::ClientCertVerifier for AttestationReportVerifier {
         }
     }
 }
+
+#[cfg(all(feature = "enclave_unit_test", feature = "sgx"))]
+pub mod tests {
+    use super::*;
+    use crate::report::SgxQuote;
+    use std::time::Duration;
+    use teaclave_test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_quote_status_policy_not_overridden,
+            test_quote_status_policy_accepted_with_exceptions,
+            test_quote_status_policy_rejected
+        )
+    }
+
+    fn attestation_report(
+        sgx_quote_status: SgxQuoteStatus,
+        advisory_ids: Vec<String>,
+    ) -> AttestationReport {
+        let quote_encoded = "AgABAC8LAAAKAAkAAAAAAK1zRQOIpndiP4IhlnW2AkwAAAAA\
+                             AAAAAAAAAAAAAAAABQ4CBf+AAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABwAAAAAAAAAHAAAA\
+                             AAAAADMKqRCjd2eA4gAmrj2sB68OWpMfhPH4MH27hZAvWGlT\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACD1xnn\
+                             ferKFHD2uvYqTXdDA8iZ22kCD5xw7h38CMfOngAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAADYIY9k0MVmCdIDUuFLf/2bGIHAfPjO9nvC7fgz\
+                             rQedeA3WW4dFeI6oe+RCLdV3XYD1n6lEZjITOzPPLWDxulGz";
+        let quote_raw = base64::decode(quote_encoded.as_bytes()).unwrap();
+        AttestationReport {
+            freshness: Duration::from_secs(0),
+            sgx_quote_status,
+            advisory_ids,
+            sgx_quote_body: SgxQuote::parse_from(quote_raw.as_slice()).unwrap(),
+        }
+    }
+
+    fn test_quote_status_policy_not_overridden() {
+        let policy = QuoteStatusPolicy::default();
+        let report = attestation_report(
+            SgxQuoteStatus::SwHardeningNeeded,
+            vec!["INTEL-SA-00334".to_string()],
+        );
+        assert_eq!(policy.check(&report), QuoteVerdict::NotOverridden);
+    }
+
+    fn test_quote_status_policy_accepted_with_exceptions() {
+        let mut config = QuoteStatusConfig::default();
+        config.accept_sw_hardening_needed_if_only = Some(vec!["INTEL-SA-00334".to_string()]);
+        let policy = QuoteStatusPolicy::new(config);
+        let report = attestation_report(
+            SgxQuoteStatus::SwHardeningNeeded,
+            vec!["INTEL-SA-00334".to_string()],
+        );
+        assert_eq!(
+            policy.check(&report),
+            QuoteVerdict::Accepted {
+                with_exceptions: vec!["INTEL-SA-00334".to_string()]
+            }
+        );
+    }
+
+    fn test_quote_status_policy_rejected() {
+        let mut config = QuoteStatusConfig::default();
+        config.accept_sw_hardening_needed_if_only = Some(vec!["INTEL-SA-00334".to_string()]);
+        let policy = QuoteStatusPolicy::new(config);
+        let report = attestation_report(
+            SgxQuoteStatus::SwHardeningNeeded,
+            vec!["INTEL-SA-00334".to_string(), "INTEL-SA-00615".to_string()],
+        );
+        assert_eq!(
+            policy.check(&report),
+            QuoteVerdict::Rejected {
+                advisory_ids: vec!["INTEL-SA-00615".to_string()]
+            }
+        );
+    }
+}
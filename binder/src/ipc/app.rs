@@ -31,7 +31,7 @@ use teaclave_types::ECallStatus;
 // fn enclave_init(eid: sgx_enclave_id_t, retval: *mut ECallStatus) -> sgx_status_t;
 #[no_mangle]
 extern "C" {
-    fn ecall_ipc_entry_point(
+    pub(crate) fn ecall_ipc_entry_point(
         eid: sgx_enclave_id_t,
         retval: *mut ECallStatus,
         cmd: u32,
@@ -146,6 +146,9 @@ impl IpcSender for ECallChannel {
 #[cfg(feature = "app_unit_test")]
 pub mod tests {
     use super::*;
+    use crate::ipc::fuzz::{self, ECallFuzzCorpus};
+
+    const FUZZ_CORPUS: &str = "./fixtures/ecall_fuzz_corpus.json";
 
     pub fn run_tests(eid: sgx_enclave_id_t) -> bool {
         let mut ecall_ret = ECallStatus::default();
@@ -166,6 +169,7 @@ pub mod tests {
         assert_eq!(sgx_status, sgx_status_t::SGX_SUCCESS);
         assert!(ecall_ret.is_err());
 
-        true
+        let corpus = ECallFuzzCorpus::from_file(FUZZ_CORPUS).unwrap();
+        fuzz::run_corpus(eid, &corpus)
     }
 }
@@ -0,0 +1,174 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Boundary tests of the ECALL interface. The app feeds malformed, truncated
+//! and oversized payloads from a corpus into the entry point of an enclave,
+//! which must reject every one of them with an error status instead of
+//! aborting.
+
+use std::prelude::v1::*;
+
+use serde::Deserialize;
+use sgx_types::{sgx_enclave_id_t, sgx_status_t};
+
+use super::app::ecall_ipc_entry_point;
+use crate::proto::ECallCommand;
+use log::debug;
+use teaclave_types::ECallStatus;
+
+const OUT_BUF_SIZE: usize = 256;
+
+/// A corpus of invalid ECALL payloads, stored as JSON, e.g.:
+///
+/// ```json
+/// {
+///   "cases": [
+///     { "name": "truncated_object", "input": "{\"test_names\": [", "truncate": true },
+///     { "name": "deep_nesting", "input": "[", "repeat": 1048576 },
+///     { "name": "invalid_utf8", "command": 4099, "input": "\"", "bytes": [255, 34] }
+///   ]
+/// }
+/// ```
+///
+/// Every payload must be invalid for the commands it is sent to, since valid
+/// ones are handled by the enclave (and fail the test).
+#[derive(Debug, Deserialize)]
+pub struct ECallFuzzCorpus {
+    pub cases: Vec<ECallFuzzCase>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ECallFuzzCase {
+    pub name: String,
+    /// Command the payload is sent to, or all commands (including an
+    /// unregistered one) if absent.
+    #[serde(default)]
+    pub command: Option<u32>,
+    /// Payload of the case, followed by `bytes`.
+    #[serde(default)]
+    pub input: String,
+    /// Raw bytes appended to `input`, e.g., for invalid UTF-8.
+    #[serde(default)]
+    pub bytes: Vec<u8>,
+    /// Number of times the payload is repeated, to build oversized ones.
+    #[serde(default = "default_repeat")]
+    pub repeat: usize,
+    /// Also send every proper prefix of the payload.
+    #[serde(default)]
+    pub truncate: bool,
+}
+
+fn default_repeat() -> usize {
+    1
+}
+
+impl ECallFuzzCorpus {
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let content = std::fs::read(path)?;
+        let corpus = serde_json::from_slice(&content)?;
+        Ok(corpus)
+    }
+}
+
+impl ECallFuzzCase {
+    fn commands(&self) -> Vec<u32> {
+        match self.command {
+            Some(command) => vec![command],
+            None => vec![
+                ECallCommand::StartService.into(),
+                ECallCommand::InitEnclave.into(),
+                ECallCommand::FinalizeEnclave.into(),
+                ECallCommand::RunTest.into(),
+                ECallCommand::Raw.into(),
+                ECallCommand::Unimplemented.into(),
+            ],
+        }
+    }
+
+    fn payloads(&self) -> Vec<Vec<u8>> {
+        let mut unit = self.input.as_bytes().to_vec();
+        unit.extend_from_slice(&self.bytes);
+        let payload = unit.repeat(self.repeat);
+        let mut payloads = Vec::new();
+        if self.truncate {
+            payloads.extend((0..payload.len()).map(|len| payload[..len].to_vec()));
+        }
+        payloads.push(payload);
+        payloads
+    }
+}
+
+/// Sends every payload of the corpus to the enclave, and checks that each is
+/// rejected with an error status and that the enclave is still alive.
+/// Payloads which cannot be copied into the enclave heap are rejected by the
+/// SGX runtime instead.
+pub fn run_corpus(eid: sgx_enclave_id_t, corpus: &ECallFuzzCorpus) -> bool {
+    for case in corpus.cases.iter() {
+        for command in case.commands() {
+            for payload in case.payloads() {
+                let (sgx_status, ecall_ret) = ecall(eid, command, &payload);
+                debug!(
+                    "fuzz case {}, cmd: {:x}, {:x} bytes: {}, {:?}",
+                    case.name,
+                    command,
+                    payload.len(),
+                    sgx_status,
+                    ecall_ret
+                );
+                match sgx_status {
+                    sgx_status_t::SGX_SUCCESS => assert!(
+                        ecall_ret.is_err(),
+                        "fuzz case {} is accepted by cmd {:x}",
+                        case.name,
+                        command
+                    ),
+                    sgx_status_t::SGX_ERROR_OUT_OF_MEMORY => (),
+                    status => panic!(
+                        "fuzz case {} fails cmd {:x} with {}",
+                        case.name, command, status
+                    ),
+                }
+            }
+        }
+    }
+
+    // A crashed enclave rejects any further ECALL.
+    let (sgx_status, _) = ecall(eid, ECallCommand::Unimplemented.into(), b"null");
+    assert_eq!(sgx_status, sgx_status_t::SGX_SUCCESS);
+
+    true
+}
+
+fn ecall(eid: sgx_enclave_id_t, cmd: u32, payload: &[u8]) -> (sgx_status_t, ECallStatus) {
+    let mut ecall_ret = ECallStatus::default();
+    let mut out_buf = vec![0; OUT_BUF_SIZE];
+    let mut out_len = 0usize;
+    let sgx_status = unsafe {
+        ecall_ipc_entry_point(
+            eid,
+            &mut ecall_ret,
+            cmd,
+            payload.as_ptr(),
+            payload.len(),
+            out_buf.as_mut_ptr(),
+            OUT_BUF_SIZE,
+            &mut out_len,
+        )
+    };
+
+    (sgx_status, ecall_ret)
+}
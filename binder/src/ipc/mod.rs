@@ -61,6 +61,8 @@ cfg_if::cfg_if! {
     if #[cfg(feature = "app")]  {
         pub(crate) mod app;
         pub use app::ECallChannel;
+        #[cfg(feature = "app_unit_test")]
+        pub mod fuzz;
     } else if #[cfg(feature = "mesalock_sgx")] {
         mod enclave;
         pub use enclave::ECallReceiver;
@@ -0,0 +1,577 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+//! `teaclave-admin`, the command line tool of platform operators. Commands
+//! connect to the services with a named profile, which pins the measurements
+//! of the services when it is added, so that a replaced enclave info file
+//! does not change the enclaves trusted by later commands.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use teaclave_client_sdk::{
+    AuthenticationClient, AuthenticationService, EnclaveInfo, EnclaveMeasurement, FrontendClient,
+    FrontendService,
+};
+
+mod measurement_log;
+
+const AUTHENTICATION_SERVICE: &str = "teaclave_authentication_service";
+const FRONTEND_SERVICE: &str = "teaclave_frontend_service";
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "teaclave-admin", about = "Teaclave platform operator tool.")]
+struct Opt {
+    /// Path of the profiles, by default ~/.teaclave/profiles.json
+    #[structopt(long, env = "TEACLAVE_PROFILES")]
+    profiles: Option<PathBuf>,
+
+    /// Name of the profile to connect with
+    #[structopt(short, long, default_value = "default")]
+    profile: String,
+
+    /// Print JSON instead of the human-readable output, e.g., for scripts
+    #[structopt(long)]
+    json: bool,
+
+    /// Do not ask for confirmation of destructive commands
+    #[structopt(short, long)]
+    yes: bool,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Manage the connection profiles
+    #[structopt(name = "profile")]
+    Profile(ProfileCommand),
+
+    /// Display the health checks of the frontend service and its dependencies
+    #[structopt(name = "health")]
+    Health,
+
+    /// List the execution nodes with their utilization
+    #[structopt(name = "nodes")]
+    Nodes,
+
+    /// List the executors and builtin functions of the execution nodes
+    #[structopt(name = "executors")]
+    Executors,
+
+    /// Drain the platform: reject mutating requests (read-only mode)
+    #[structopt(name = "drain")]
+    Drain(DrainOpt),
+
+    /// Accept mutating requests again after a drain
+    #[structopt(name = "undrain")]
+    Undrain,
+
+    /// Check the records of the management service for inconsistencies
+    #[structopt(name = "check-consistency")]
+    CheckConsistency(CheckConsistencyOpt),
+
+    /// Export and verify entries of the audit log
+    #[structopt(name = "audit")]
+    Audit(AuditOpt),
+
+    /// Append the measurements of released enclaves to the transparency log
+    #[structopt(name = "measurements")]
+    Measurements(MeasurementsOpt),
+}
+
+#[derive(Debug, StructOpt)]
+enum ProfileCommand {
+    /// Add a profile, or replace the profile with the same name
+    #[structopt(name = "add")]
+    Add(ProfileAddOpt),
+
+    /// List the profiles
+    #[structopt(name = "list")]
+    List,
+
+    /// Remove a profile
+    #[structopt(name = "remove")]
+    Remove { name: String },
+}
+
+#[derive(Debug, StructOpt)]
+struct ProfileAddOpt {
+    name: String,
+
+    /// Address of the authentication service
+    #[structopt(long, default_value = "localhost:7776")]
+    authentication_address: String,
+
+    /// Address of the frontend service
+    #[structopt(long, default_value = "localhost:7777")]
+    frontend_address: String,
+
+    /// Path of enclave info, whose measurements of the services are pinned
+    #[structopt(short, long = "enclave-info")]
+    enclave_info: PathBuf,
+
+    /// CA cert of attestation service for verifying the attestation report
+    #[structopt(short = "c", long)]
+    as_ca_cert: PathBuf,
+
+    /// User with the manage_users permission, whose password is read from
+    /// the TEACLAVE_PASSWORD environment variable
+    #[structopt(short, long = "user-id")]
+    user_id: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct DrainOpt {
+    /// Seconds until the platform accepts mutating requests again, at most a
+    /// day
+    #[structopt(short, long, default_value = "3600")]
+    duration_secs: u64,
+
+    /// Reason recorded with the drain
+    #[structopt(short, long)]
+    reason: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct CheckConsistencyOpt {
+    /// Repair the inconsistencies found, all or none of them
+    #[structopt(short, long)]
+    repair: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct AuditOpt {
+    /// Sequence number of the first entry
+    #[structopt(short, long, default_value = "0")]
+    start_seq: u64,
+
+    /// Maximum number of entries
+    #[structopt(short, long, default_value = "100")]
+    limit: u32,
+}
+
+#[derive(Debug, StructOpt)]
+struct MeasurementsOpt {
+    /// Path of the measurement log, created if it does not exist
+    #[structopt(short, long)]
+    log: PathBuf,
+
+    /// Path of the Ed25519 private key of the log in the PKCS#8 DER format
+    #[structopt(short, long)]
+    key: PathBuf,
+
+    /// Path of enclave info
+    #[structopt(short, long = "enclave-info")]
+    enclave_info: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Profile {
+    authentication_address: String,
+    frontend_address: String,
+    as_ca_cert: PathBuf,
+    user_id: String,
+    /// Measurements of the services, pinned when the profile was added.
+    measurements: HashMap<String, EnclaveMeasurement>,
+}
+
+impl Profile {
+    fn enclave_info(&self) -> EnclaveInfo {
+        EnclaveInfo {
+            measurements: self.measurements.clone(),
+        }
+    }
+
+    fn as_ca_cert(&self) -> Result<Vec<u8>> {
+        Ok(pem::parse(fs::read(&self.as_ca_cert)?)?.contents)
+    }
+
+    fn connect_authentication(&self) -> Result<(AuthenticationClient, String)> {
+        let password = std::env::var("TEACLAVE_PASSWORD")
+            .map_err(|_| anyhow!("TEACLAVE_PASSWORD is not set"))?;
+        let mut client = AuthenticationService::connect(
+            &self.authentication_address,
+            &self.enclave_info(),
+            &self.as_ca_cert()?,
+        )?;
+        let token = client.user_login(&self.user_id, &password)?;
+        client.set_credential(&self.user_id, &token);
+        Ok((client, token))
+    }
+
+    fn connect_frontend(&self) -> Result<FrontendClient> {
+        let (_, token) = self.connect_authentication()?;
+        let mut client = FrontendService::connect(
+            &self.frontend_address,
+            &self.enclave_info(),
+            &self.as_ca_cert()?,
+        )?;
+        client.set_credential(&self.user_id, &token);
+        Ok(client)
+    }
+}
+
+struct Profiles {
+    path: PathBuf,
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    fn load(path: Option<PathBuf>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => {
+                let home = std::env::var("HOME").map_err(|_| anyhow!("HOME is not set"))?;
+                PathBuf::from(home).join(".teaclave").join("profiles.json")
+            }
+        };
+        let profiles = if path.exists() {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, profiles })
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&self.profiles)?)?;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| {
+            anyhow!(
+                "No profile {:?} in {}, add it with `profile add`",
+                name,
+                self.path.display()
+            )
+        })
+    }
+}
+
+// Asks the operator to confirm a destructive command, unless --yes is given.
+fn confirm(opt: &Opt, action: &str) -> Result<()> {
+    if opt.yes {
+        return Ok(());
+    }
+    eprint!(
+        "{} with profile {:?}? Type \"yes\" to continue: ",
+        action, opt.profile
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if answer.trim() != "yes" {
+        bail!("Aborted");
+    }
+    Ok(())
+}
+
+fn profile(opt: &Opt, command: &ProfileCommand) -> Result<Value> {
+    let mut profiles = Profiles::load(opt.profiles.clone())?;
+    match command {
+        ProfileCommand::Add(add) => {
+            let enclave_info = EnclaveInfo::from_bytes(&fs::read(&add.enclave_info)?);
+            let mut measurements = HashMap::new();
+            for service in &[AUTHENTICATION_SERVICE, FRONTEND_SERVICE] {
+                let measurement = enclave_info
+                    .measurements
+                    .get(*service)
+                    .ok_or_else(|| anyhow!("No measurement of {} in enclave info", service))?;
+                measurements.insert(service.to_string(), *measurement);
+            }
+            let profile = Profile {
+                authentication_address: add.authentication_address.clone(),
+                frontend_address: add.frontend_address.clone(),
+                as_ca_cert: fs::canonicalize(&add.as_ca_cert)?,
+                user_id: add.user_id.clone(),
+                measurements,
+            };
+            profiles.profiles.insert(add.name.clone(), profile);
+            profiles.save()?;
+            Ok(json!({ "added": add.name }))
+        }
+        ProfileCommand::List => Ok(json!(profiles.profiles)),
+        ProfileCommand::Remove { name } => {
+            if profiles.profiles.remove(name).is_none() {
+                bail!("No profile {:?}", name);
+            }
+            profiles.save()?;
+            Ok(json!({ "removed": name }))
+        }
+    }
+}
+
+fn run(opt: &Opt) -> Result<Value> {
+    if let Command::Profile(command) = &opt.command {
+        return profile(opt, command);
+    }
+    if let Command::Measurements(measurements) = &opt.command {
+        confirm(opt, "Append the measurements to the transparency log")?;
+        let result = measurement_log::append(
+            &measurements.log,
+            &measurements.key,
+            &measurements.enclave_info,
+        )?;
+        return Ok(json!({
+            "appended": result.appended,
+            "skipped": result.skipped,
+            "public_key": result.public_key,
+        }));
+    }
+
+    let profiles = Profiles::load(opt.profiles.clone())?;
+    let profile = profiles.get(&opt.profile)?;
+    let value = match &opt.command {
+        Command::Health => {
+            let response = profile.connect_frontend()?.health()?;
+            let checks: Vec<Value> = response
+                .checks
+                .iter()
+                .map(|check| {
+                    json!({
+                        "name": check.name,
+                        "healthy": check.healthy,
+                        "detail": check.detail,
+                    })
+                })
+                .collect();
+            json!({
+                "healthy": response.checks.iter().all(|check| check.healthy),
+                "checks": checks,
+            })
+        }
+        Command::Nodes => {
+            let nodes: Vec<Value> = profile
+                .connect_frontend()?
+                .list_nodes()?
+                .iter()
+                .map(|node| {
+                    json!({
+                        "worker_id": node.worker_id,
+                        "cpus": node.capacity.cpus,
+                        "memory_mb": node.capacity.memory_mb,
+                        "slots": node.capacity.slots,
+                        "running": node.running,
+                        "utilization": node.utilization(),
+                        "last_seen": node.last_seen,
+                        "labels": node.labels,
+                    })
+                })
+                .collect();
+            json!(nodes)
+        }
+        Command::Executors => {
+            let nodes: Vec<Value> = profile
+                .connect_frontend()?
+                .list_executors()?
+                .iter()
+                .map(|node| {
+                    json!({
+                        "worker_id": node.worker_id,
+                        "labels": node.labels,
+                        "executors": node.executors,
+                        "builtin_functions": node.builtin_functions,
+                    })
+                })
+                .collect();
+            json!(nodes)
+        }
+        Command::Drain(drain) => {
+            confirm(opt, "Reject mutating requests of every user")?;
+            let expires_at = profile
+                .connect_frontend()?
+                .enter_read_only_mode(drain.duration_secs, &drain.reason)?;
+            json!({ "expires_at": expires_at })
+        }
+        Command::Undrain => {
+            profile.connect_frontend()?.exit_read_only_mode()?;
+            json!({})
+        }
+        Command::CheckConsistency(check) => {
+            if check.repair {
+                confirm(opt, "Repair the inconsistent records")?;
+            }
+            let findings: Vec<Value> = profile
+                .connect_frontend()?
+                .check_consistency(check.repair)?
+                .iter()
+                .map(|finding| {
+                    json!({
+                        "kind": finding.kind.as_str(),
+                        "key": finding.key,
+                        "detail": finding.detail,
+                        "repaired": finding.repaired,
+                    })
+                })
+                .collect();
+            json!(findings)
+        }
+        Command::Audit(audit) => {
+            let (mut client, _) = profile.connect_authentication()?;
+            let response = client.export_audit_log(audit.start_seq, audit.limit)?;
+            let entries: Vec<Value> = response
+                .entries
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "seq": entry.seq,
+                        "kind": entry.event.kind.to_string(),
+                        "service": entry.event.service,
+                        "user_id": entry.event.user_id,
+                        "detail": entry.event.detail,
+                        "timestamp": entry.event.timestamp,
+                    })
+                })
+                .collect();
+            json!({
+                "entries": entries,
+                "log_len": response.log_len,
+                "broken_seq": response.broken_seq,
+            })
+        }
+        Command::Profile(_) | Command::Measurements(_) => unreachable!(),
+    };
+    Ok(value)
+}
+
+// Renders the JSON output of a command for humans.
+fn print_human(command: &Command, value: &Value) {
+    match command {
+        Command::Health => {
+            for check in value["checks"].as_array().into_iter().flatten() {
+                let status = if check["healthy"] == true {
+                    "ok"
+                } else {
+                    "FAIL"
+                };
+                println!(
+                    "{:<4} {}: {}",
+                    status,
+                    check["name"].as_str().unwrap_or_default(),
+                    check["detail"].as_str().unwrap_or_default()
+                );
+            }
+        }
+        Command::Nodes => {
+            println!(
+                "{:<38} {:>4} {:>7} {:>6} {:>8} {:>5}  LABELS",
+                "WORKER", "CPUS", "MEMORY", "SLOTS", "RUNNING", "UTIL"
+            );
+            for node in value.as_array().into_iter().flatten() {
+                let labels: Vec<&str> = node["labels"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect();
+                println!(
+                    "{:<38} {:>4} {:>7} {:>6} {:>8} {:>4.0}%  {}",
+                    node["worker_id"].as_str().unwrap_or_default(),
+                    node["cpus"].as_u64().unwrap_or_default(),
+                    node["memory_mb"].as_u64().unwrap_or_default(),
+                    node["slots"].as_u64().unwrap_or_default(),
+                    node["running"].as_u64().unwrap_or_default(),
+                    node["utilization"].as_f64().unwrap_or_default() * 100.0,
+                    labels.join(",")
+                );
+            }
+        }
+        Command::Executors => {
+            for node in value.as_array().into_iter().flatten() {
+                let names = |key: &str| -> Vec<&str> {
+                    node[key]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Value::as_str)
+                        .collect()
+                };
+                println!("{}", node["worker_id"].as_str().unwrap_or_default());
+                println!("  executors: {}", names("executors").join(", "));
+                println!(
+                    "  builtin functions: {}",
+                    names("builtin_functions").join(", ")
+                );
+            }
+        }
+        Command::CheckConsistency(_) => {
+            let findings = value.as_array().cloned().unwrap_or_default();
+            for finding in &findings {
+                println!(
+                    "{}{} {}: {}",
+                    if finding["repaired"] == true {
+                        "[repaired] "
+                    } else {
+                        ""
+                    },
+                    finding["kind"].as_str().unwrap_or_default(),
+                    finding["key"].as_str().unwrap_or_default(),
+                    finding["detail"].as_str().unwrap_or_default()
+                );
+            }
+            println!("{} inconsistencies found", findings.len());
+        }
+        Command::Audit(_) => {
+            for entry in value["entries"].as_array().into_iter().flatten() {
+                println!(
+                    "{:>8} {} {} {} {}: {}",
+                    entry["seq"].as_u64().unwrap_or_default(),
+                    entry["timestamp"].as_u64().unwrap_or_default(),
+                    entry["service"].as_str().unwrap_or_default(),
+                    entry["kind"].as_str().unwrap_or_default(),
+                    entry["user_id"].as_str().unwrap_or_default(),
+                    entry["detail"].as_str().unwrap_or_default()
+                );
+            }
+            if let Some(seq) = value["broken_seq"].as_u64() {
+                println!("Chain or seal broken at entry {}", seq);
+            }
+        }
+        _ => match serde_json::to_string_pretty(value) {
+            Ok(value) => println!("{}", value),
+            Err(e) => println!("{:?}", e),
+        },
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let opt = Opt::from_args();
+    let value = run(&opt)?;
+    if opt.json {
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        print_human(&opt.command, &value);
+    }
+    // Scripts tell unhealthy platforms and broken audit chains by the exit
+    // status.
+    if value["healthy"] == false || !value["broken_seq"].is_null() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
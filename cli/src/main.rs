@@ -21,12 +21,16 @@ use anyhow::Result;
 use http::Uri;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use structopt::StructOpt;
 use teaclave_attestation::report::AttestationReport;
 
-use teaclave_crypto::{AesGcm128Key, AesGcm256Key, TeaclaveFile128Key};
+use teaclave_crypto::{
+    AesGcm128Key, AesGcm256Key, AesGcmSiv256Key, TeaclaveFile128Key, XChaCha20Poly1305Key,
+};
+
+mod measurement_log;
 
 const FILE_AUTH_TAG_LENGTH: usize = 16;
 type CMac = [u8; FILE_AUTH_TAG_LENGTH];
@@ -39,7 +43,7 @@ fn decode_hex(src: &str) -> Result<Vec<u8>, hex::FromHexError> {
 #[derive(Debug, StructOpt)]
 struct EncryptDecryptOpt {
     /// Crypto algorithm, supported algorithms are "aes-gcm-128", "aes-gcm-256",
-    /// "teaclave-file-128".
+    /// "aes-gcm-siv-256", "xchacha20-poly1305", "teaclave-file-128".
     #[structopt(short, long)]
     algorithm: String,
 
@@ -90,6 +94,60 @@ struct AttestOpt {
     as_ca_cert: PathBuf,
 }
 
+#[derive(Debug, StructOpt)]
+struct LogAppendOpt {
+    /// Path of the measurement log, created if it does not exist
+    #[structopt(short, long)]
+    log: PathBuf,
+
+    /// Path of the Ed25519 private key of the log in the PKCS#8 DER format
+    #[structopt(short, long)]
+    key: PathBuf,
+
+    /// Path of enclave info
+    #[structopt(short, long = "enclave-info")]
+    enclave_info: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct PackageOpt {
+    /// Path of the function manifest
+    #[structopt(short, long, default_value = "function.toml")]
+    manifest: PathBuf,
+
+    /// Path of the serialized registration request
+    #[structopt(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct CheckConsistencyOpt {
+    /// Address of the authentication service
+    #[structopt(long, default_value = "localhost:7776")]
+    authentication_address: String,
+
+    /// Address of the frontend service
+    #[structopt(long, default_value = "localhost:7777")]
+    frontend_address: String,
+
+    /// Path of enclave info
+    #[structopt(short, long = "enclave-info")]
+    enclave_info: PathBuf,
+
+    /// CA cert of attestation service for verifying the attestation report
+    #[structopt(short = "c", long)]
+    as_ca_cert: PathBuf,
+
+    /// User with the manage_users permission, whose password is read from
+    /// the TEACLAVE_PASSWORD environment variable
+    #[structopt(short, long = "user-id")]
+    user_id: String,
+
+    /// Repair the inconsistencies found, all or none of them
+    #[structopt(short, long)]
+    repair: bool,
+}
+
 #[derive(Debug, StructOpt)]
 enum Command {
     /// Encrypt file
@@ -107,6 +165,18 @@ enum Command {
     /// Display the attestation report of remote Teaclave services
     #[structopt(name = "attest")]
     Attest(AttestOpt),
+
+    /// Append the measurements of enclave info to the transparency log
+    #[structopt(name = "log-append")]
+    LogAppend(LogAppendOpt),
+
+    /// Validate a function manifest and build the registration request
+    #[structopt(name = "package")]
+    Package(PackageOpt),
+
+    /// Check the records of the management service for inconsistencies
+    #[structopt(name = "check-consistency")]
+    CheckConsistency(CheckConsistencyOpt),
 }
 
 #[derive(Debug, StructOpt)]
@@ -136,6 +206,22 @@ fn decrypt(opt: EncryptDecryptOpt) -> Result<CMac> {
             cmac.copy_from_slice(&res);
             fs::write(opt.output_file, content)?;
         }
+        AesGcmSiv256Key::SCHEMA => {
+            let iv = opt.iv.expect("IV is required.");
+            let key = AesGcmSiv256Key::new(&key, &iv)?;
+            let mut content = fs::read(opt.input_file)?;
+            let res = key.decrypt(&mut content)?;
+            cmac.copy_from_slice(&res);
+            fs::write(opt.output_file, content)?;
+        }
+        XChaCha20Poly1305Key::SCHEMA => {
+            let iv = opt.iv.expect("IV is required.");
+            let key = XChaCha20Poly1305Key::new(&key, &iv)?;
+            let mut content = fs::read(opt.input_file)?;
+            let res = key.decrypt(&mut content)?;
+            cmac.copy_from_slice(&res);
+            fs::write(opt.output_file, content)?;
+        }
         TeaclaveFile128Key::SCHEMA => {
             let key = TeaclaveFile128Key::new(&key)?;
             let mut output_file = fs::File::create(opt.output_file)?;
@@ -168,6 +254,22 @@ fn encrypt(opt: EncryptDecryptOpt) -> Result<CMac> {
             cmac.copy_from_slice(&res);
             fs::write(opt.output_file, content)?;
         }
+        AesGcmSiv256Key::SCHEMA => {
+            let iv = opt.iv.expect("IV is required.");
+            let key = AesGcmSiv256Key::new(&key, &iv)?;
+            let mut content = fs::read(opt.input_file)?;
+            let res = key.encrypt(&mut content)?;
+            cmac.copy_from_slice(&res);
+            fs::write(opt.output_file, content)?;
+        }
+        XChaCha20Poly1305Key::SCHEMA => {
+            let iv = opt.iv.expect("IV is required.");
+            let key = XChaCha20Poly1305Key::new(&key, &iv)?;
+            let mut content = fs::read(opt.input_file)?;
+            let res = key.encrypt(&mut content)?;
+            cmac.copy_from_slice(&res);
+            fs::write(opt.output_file, content)?;
+        }
         TeaclaveFile128Key::SCHEMA => {
             let key = TeaclaveFile128Key::new(&key)?;
             let content = fs::File::open(opt.input_file)?;
@@ -265,6 +367,72 @@ fn attest(opt: AttestOpt) -> Result<()> {
     Ok(())
 }
 
+fn log_append(opt: LogAppendOpt) -> Result<()> {
+    let result = measurement_log::append(&opt.log, &opt.key, &opt.enclave_info)?;
+    for name in result.skipped {
+        println!("Skipped {}: already in the log", name);
+    }
+    for name in result.appended {
+        println!("Appended {}", name);
+    }
+    println!("Public key of the log: {}", result.public_key);
+
+    Ok(())
+}
+
+fn package(opt: PackageOpt) -> Result<()> {
+    use teaclave_proto::teaclave_frontend_service::RegisterFunctionRequest;
+    use teaclave_proto::teaclave_frontend_service_proto as proto;
+    use teaclave_types::FunctionManifest;
+
+    let manifest = FunctionManifest::from_toml(&fs::read_to_string(&opt.manifest)?)?;
+    let dir = opt.manifest.parent().unwrap_or_else(|| Path::new("."));
+    let payload = match &manifest.entry_point {
+        Some(entry_point) => fs::read(dir.join(entry_point))?,
+        None => Vec::new(),
+    };
+    let bundle = match &manifest.bundle {
+        Some(bundle) => fs::read(dir.join(bundle))?,
+        None => Vec::new(),
+    };
+    let name = manifest.name.clone();
+    let request = RegisterFunctionRequest::from_manifest(manifest, payload)?.bundle(bundle);
+    let request = proto::RegisterFunctionRequest::from(request);
+    fs::write(&opt.output, serde_json::to_string(&request)?)?;
+    println!("Packaged {}", name);
+
+    Ok(())
+}
+
+fn check_consistency(opt: CheckConsistencyOpt) -> Result<()> {
+    use teaclave_client_sdk::{AuthenticationService, FrontendService};
+    use teaclave_types::EnclaveInfo;
+
+    let password =
+        std::env::var("TEACLAVE_PASSWORD").map_err(|_| anyhow!("TEACLAVE_PASSWORD is not set"))?;
+    let enclave_info = EnclaveInfo::from_bytes(&fs::read(opt.enclave_info)?);
+    let as_ca_cert = pem::parse(fs::read(opt.as_ca_cert)?)?.contents;
+    let mut client =
+        AuthenticationService::connect(&opt.authentication_address, &enclave_info, &as_ca_cert)?;
+    let token = client.user_login(&opt.user_id, &password)?;
+    let mut client = FrontendService::connect(&opt.frontend_address, &enclave_info, &as_ca_cert)?;
+    client.set_credential(&opt.user_id, &token);
+
+    let findings = client.check_consistency(opt.repair)?;
+    for finding in &findings {
+        println!(
+            "{}{} {}: {}",
+            if finding.repaired { "[repaired] " } else { "" },
+            finding.kind.as_str(),
+            finding.key,
+            finding.detail
+        );
+    }
+    println!("{} inconsistencies found", findings.len());
+
+    Ok(())
+}
+
 fn main() -> Result<()> {
     env_logger::init();
     let args = Opt::from_args();
@@ -293,6 +461,9 @@ fn main() -> Result<()> {
             }
         },
         Command::Attest(opt) => attest(opt)?,
+        Command::LogAppend(opt) => log_append(opt)?,
+        Command::Package(opt) => package(opt)?,
+        Command::CheckConsistency(opt) => check_consistency(opt)?,
     };
 
     Ok(())
@@ -0,0 +1,72 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+//! Appending the measurements of released enclaves to the transparency log,
+//! shared by `teaclave_cli log-append` and `teaclave-admin measurements`.
+
+use anyhow::{anyhow, Result};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use std::fs;
+use std::path::Path;
+use teaclave_types::{EnclaveInfo, MeasurementLog, MeasurementLogEntry};
+
+pub(crate) struct Appended {
+    /// Names of the services whose measurements were appended.
+    pub(crate) appended: Vec<String>,
+    /// Names of the services whose measurements were already in the log.
+    pub(crate) skipped: Vec<String>,
+    /// Hex-encoded public key of the log.
+    pub(crate) public_key: String,
+}
+
+/// Appends the measurements of the enclave info to the log at `log_path`,
+/// created if it does not exist, signing the new tree head with the Ed25519
+/// private key (PKCS#8 DER) at `key_path`.
+pub(crate) fn append(
+    log_path: &Path,
+    key_path: &Path,
+    enclave_info_path: &Path,
+) -> Result<Appended> {
+    let mut log = if log_path.exists() {
+        MeasurementLog::from_bytes(&fs::read(log_path)?)?
+    } else {
+        MeasurementLog::new()
+    };
+    let key = fs::read(key_path)?;
+    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&key)
+        .map_err(|_| anyhow!("Invalid Ed25519 private key"))?;
+    let enclave_info = EnclaveInfo::from_bytes(&fs::read(enclave_info_path)?);
+    let mut service_names: Vec<&String> = enclave_info.measurements.keys().collect();
+    service_names.sort();
+    let mut appended = Vec::new();
+    let mut skipped = Vec::new();
+    for name in service_names {
+        let entry = MeasurementLogEntry::new(name.as_str(), enclave_info.measurements[name]);
+        if log.contains(&entry) {
+            skipped.push(name.to_string());
+            continue;
+        }
+        log.append(entry, &key_pair)?;
+        appended.push(name.to_string());
+    }
+    fs::write(log_path, log.to_vec()?)?;
+
+    Ok(Appended {
+        appended,
+        skipped,
+        public_key: hex::encode(key_pair.public_key().as_ref()),
+    })
+}
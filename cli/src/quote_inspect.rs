@@ -0,0 +1,142 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A tool to inspect attestation artifacts, i.e., TLS certificates with
+//! attestation reports, raw SGX quotes, and endorsed attestation reports
+//! (evidence bundles), for triaging attestation failures.
+
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use teaclave_attestation::report::{AttestationReport, SgxEnclaveReport, SgxQuote};
+use teaclave_attestation::EndorsedAttestationReport;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "teaclave-quote-inspect",
+    about = "Inspect attestation certificates, quotes and evidence bundles."
+)]
+struct Opt {
+    /// Path of a DER-encoded TLS certificate with an attestation report.
+    #[structopt(long, conflicts_with_all = &["quote", "evidence"])]
+    cert: Option<PathBuf>,
+
+    /// Path of a raw SGX quote.
+    #[structopt(long, conflicts_with_all = &["cert", "evidence"])]
+    quote: Option<PathBuf>,
+
+    /// Path of an evidence bundle, i.e., a JSON serialized endorsed
+    /// attestation report.
+    #[structopt(long, conflicts_with_all = &["cert", "quote"])]
+    evidence: Option<PathBuf>,
+
+    /// CA cert (PEM) of attestation service for verifying the attestation
+    /// report in the certificate. The report is only parsed if absent.
+    #[structopt(short = "c", long)]
+    as_ca_cert: Option<PathBuf>,
+
+    /// Print the JSON rendering instead of the human-readable one.
+    #[structopt(long)]
+    json: bool,
+}
+
+enum Inspected {
+    Quote(SgxQuote),
+    Report(AttestationReport),
+}
+
+fn enclave_report_to_json(report: &SgxEnclaveReport) -> Value {
+    json!({
+        "cpu_svn": report.cpu_svn.to_hex(),
+        "misc_select": report.misc_select,
+        "attributes": hex::encode(report.attributes),
+        "mr_enclave": report.mr_enclave.to_hex(),
+        "mr_signer": report.mr_signer.to_hex(),
+        "isv_prod_id": report.isv_prod_id,
+        "isv_svn": report.isv_svn,
+        "report_data": report.report_data.to_hex(),
+    })
+}
+
+fn quote_to_json(quote: &SgxQuote) -> Value {
+    json!({
+        "version": quote.version.to_string(),
+        "gid": quote.gid,
+        "isv_svn_qe": quote.isv_svn_qe,
+        "isv_svn_pce": quote.isv_svn_pce,
+        "qe_vendor_id": quote.qe_vendor_id.to_string(),
+        "user_data": hex::encode(quote.user_data),
+        "isv_enclave_report": enclave_report_to_json(&quote.isv_enclave_report),
+    })
+}
+
+fn report_to_json(report: &AttestationReport) -> Value {
+    json!({
+        "freshness_secs": report.freshness.as_secs(),
+        "sgx_quote_status": format!("{:?}", report.sgx_quote_status),
+        "advisory_ids": report.advisory_ids,
+        "sgx_quote_body": quote_to_json(&report.sgx_quote_body),
+    })
+}
+
+fn inspect(opt: &Opt) -> Result<Inspected> {
+    if let Some(path) = &opt.quote {
+        let quote = fs::read(path)?;
+        return Ok(Inspected::Quote(SgxQuote::parse_from(&quote)?));
+    }
+
+    let endorsed_report: EndorsedAttestationReport = match (&opt.cert, &opt.evidence) {
+        (Some(path), _) => {
+            let cert = fs::read(path)?;
+            if let Some(as_ca_cert) = &opt.as_ca_cert {
+                let content = fs::read(as_ca_cert)?;
+                let pem = pem::parse(content)?;
+                let report = AttestationReport::from_cert(&cert, &pem.contents)?;
+                return Ok(Inspected::Report(report));
+            }
+            EndorsedAttestationReport::from_cert(&cert)?
+        }
+        (None, Some(path)) => serde_json::from_slice(&fs::read(path)?)?,
+        (None, None) => bail!("One of --cert, --quote and --evidence is required."),
+    };
+
+    let report = AttestationReport::from_attn_report(&endorsed_report.report)?;
+    Ok(Inspected::Report(report))
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let opt = Opt::from_args();
+    let inspected = inspect(&opt)?;
+    match (inspected, opt.json) {
+        (Inspected::Quote(quote), false) => println!("{}", quote),
+        (Inspected::Quote(quote), true) => {
+            println!("{}", serde_json::to_string_pretty(&quote_to_json(&quote))?)
+        }
+        (Inspected::Report(report), false) => println!("{}", report),
+        (Inspected::Report(report), true) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report_to_json(&report))?
+            )
+        }
+    }
+
+    Ok(())
+}
@@ -69,6 +69,14 @@ function(init_submodules)
         "The submodules were not downloaded! GIT_SUBMODULE was turned off or failed. Please update submodules and try again."
     )
   endif()
+
+  if(WAMR AND NOT EXISTS
+              "${PROJECT_SOURCE_DIR}/third_party/wasm-micro-runtime/core")
+    message(
+      FATAL_ERROR
+        "WAMR is turned on, but third_party/wasm-micro-runtime is not checked out. Please add the submodule at a WAMR release and try again."
+    )
+  endif()
 endfunction()
 
 macro(rm_trailing_enclave src_str dest_name)
@@ -77,12 +85,14 @@ endmacro()
 
 # add_cargo_build_target(package_name [TARGET_NAME target_name] # default to
 # cg_${package_name} TOML_DIR toml_dir TARGET_DIR target_dir [DEPENDS [dep]...]
-# [NOT_SET_COMMON_ENV] [EXTRA_CARGO_FLAGS flg...] )
+# [NOT_SET_COMMON_ENV] [EXTRA_CARGO_FLAGS flg...] [EXTRA_BINS [bin]...] )
+# EXTRA_BINS are the names of the other [[bin]] targets of the package, which
+# are installed along with ${package_name}.
 function(add_cargo_build_target package_name)
   set(options NOT_SET_COMMON_ENV)
   set(oneValueArgs TARGET_NAME TOML_DIR TARGET_DIR INSTALL_DIR
                    EXTRA_CARGO_FLAGS)
-  set(multiValueArgs DEPENDS)
+  set(multiValueArgs DEPENDS EXTRA_BINS)
   cmake_parse_arguments(MTEE "${options}" "${oneValueArgs}" "${multiValueArgs}"
                         ${ARGN})
 
@@ -110,13 +120,18 @@ function(add_cargo_build_target package_name)
     set(_depends)
   endif()
 
+  set(_bins)
+  foreach(bin ${package_name} ${MTEE_EXTRA_BINS})
+    list(APPEND _bins ${MTEE_TARGET_DIR}/${TARGET}/${bin})
+  endforeach()
+
   add_custom_target(
     ${_target_name} ALL
     COMMAND
       ${CMAKE_COMMAND} -E env ${_envs} RUSTFLAGS=${RUSTFLAGS}
       ${MT_SCRIPT_DIR}/cargo_build_ex.sh -p ${package_name} --target-dir
       ${MTEE_TARGET_DIR} ${CARGO_BUILD_FLAGS} ${MTEE_EXTRA_CARGO_FLAGS} && cp
-      ${MTEE_TARGET_DIR}/${TARGET}/${package_name} ${_copy_dir} ${_depends}
+      ${_bins} ${_copy_dir} ${_depends}
     COMMENT "Building ${_target_name}"
     WORKING_DIRECTORY ${MTEE_TOML_DIR})
 endfunction()
@@ -163,10 +178,10 @@ function(add_cargo_build_dylib_staticlib_target package_name)
 endfunction()
 
 # add_sgx_build_target(sgx_lib_path pkg_name [DEPENDS [dep]...] [INSTALL_DIR
-# dir] [EXTRA_CARGO_FLAGS flg...] )
+# dir] [EDL_LIB_NAME name] [VM_LIB_NAME name] [EXTRA_CARGO_FLAGS flg...] )
 function(add_sgx_build_target sgx_lib_path pkg_name)
   set(options)
-  set(oneValueArgs INSTALL_DIR EDL_LIB_NAME)
+  set(oneValueArgs INSTALL_DIR EDL_LIB_NAME VM_LIB_NAME)
   set(multiValueArgs DEPENDS EXTRA_CARGO_FLAGS)
   cmake_parse_arguments(MTEE "${options}" "${oneValueArgs}" "${multiValueArgs}"
                         ${ARGN})
@@ -189,6 +204,12 @@ function(add_sgx_build_target sgx_lib_path pkg_name)
     set(_edl_lib_name)
   endif()
 
+  if(DEFINED MTEE_VM_LIB_NAME)
+    set(_vm_lib_name ${MTEE_VM_LIB_NAME})
+  else()
+    set(_vm_lib_name none)
+  endif()
+
   rm_trailing_enclave(${pkg_name} pkg_name_no_enclave)
 
   set(_target_name ${SGXLIB_PREFIX}-${pkg_name_no_enclave})
@@ -219,7 +240,8 @@ function(add_sgx_build_target sgx_lib_path pkg_name)
       ${CMAKE_COMMAND} -E env ${TARGET_SGXLIB_ENVS}
       SGX_COMMON_CFLAGS=${STR_SGX_COMMON_CFLAGS} CUR_PKG_NAME=${pkg_name}
       CUR_PKG_PATH=${sgx_lib_path} CUR_INSTALL_DIR=${_copy_dir}
-      ${MT_SCRIPT_DIR}/sgx_link_sign.sh ${_edl_lib_name} ${_depends}
+      ${MT_SCRIPT_DIR}/sgx_link_sign.sh ${_edl_lib_name} ${_vm_lib_name}
+      ${_depends}
     COMMAND
       cat ${TEACLAVE_OUT_DIR}/${pkg_name}.meta.txt | python
       ${MT_SCRIPT_DIR}/gen_enclave_info_toml.py ${pkg_name_no_enclave} >
@@ -307,7 +329,7 @@ endfunction()
 
 function(parse_cargo_packages pkg_names)
   set(options)
-  set(oneValueArgs CARGO_TOML_PATH PKG_PATHS CATEGORIES EDL_NAMES)
+  set(oneValueArgs CARGO_TOML_PATH PKG_PATHS CATEGORIES EDL_NAMES VM_LIB_NAMES)
   set(multiValueArgs)
 
   cmake_parse_arguments(MTEE "${options}" "${oneValueArgs}" "${multiValueArgs}"
@@ -329,6 +351,15 @@ function(parse_cargo_packages pkg_names)
   string(REGEX REPLACE "\n" ";" _out_list ${_output})
   list(LENGTH _out_list LLEN)
 
+  if(DEFINED MTEE_VM_LIB_NAMES)
+    list(GET _out_list 4 _vm_lib_names)
+    string(REPLACE ":" ";" _vm_lib_names ${_vm_lib_names})
+    set(${MTEE_VM_LIB_NAMES}
+        ${_vm_lib_names}
+        PARENT_SCOPE)
+    dbg_message("${MTEE_VM_LIB_NAMES}=${_vm_lib_names}\n")
+  endif()
+
   if(DEFINED MTEE_EDL_NAMES)
     list(GET _out_list 3 _edl_names)
     string(REPLACE ":" ";" _edl_names ${_edl_names})
@@ -96,6 +96,17 @@ DEFAULT_EDL_LIB = "Enclave_common_t"
 PKG_NAME_TO_EDL_LIB = {
     "teaclave_unit_tests_enclave": "Enclave_fa_t",
     "teaclave_execution_service_enclave": "Enclave_fa_t",
+    "teaclave_storage_service_enclave": "Enclave_fa_t",
+}
+
+
+# Packages running the worker, which are linked with the WebAssembly Micro
+# Runtime (libvmlib.a) for the wasm executor when it is enabled.
+NO_VM_LIB = "none"
+PKG_NAME_TO_VM_LIB = {
+    "teaclave_unit_tests_enclave": "vmlib",
+    "teaclave_integration_tests_enclave": "vmlib",
+    "teaclave_execution_service_enclave": "vmlib",
 }
 
 
@@ -106,6 +117,13 @@ def pkg_name_2_edl_lib_name(pkg_name):
     return PKG_NAME_TO_EDL_LIB.get(pkg_name, DEFAULT_EDL_LIB)
 
 
+def pkg_name_2_vm_lib_name(pkg_name):
+    """
+    Take pkg_name and return the name of the vm library it is linked with, default is NO_VM_LIB.
+    """
+    return PKG_NAME_TO_VM_LIB.get(pkg_name, NO_VM_LIB)
+
+
 def main():
     """parses Cargo.toml to generate a list of package to be built"""
     if len(sys.argv) < 3:
@@ -120,6 +138,7 @@ def main():
     pkg_paths = []
     pkg_categories = []
     edl_lib_names = []
+    vm_lib_names = []
 
     members = parse_members_for_workspace(toml_path)
     for pkg_path in members:
@@ -130,10 +149,11 @@ def main():
         pkg_paths.append(pkg_path)
         pkg_categories.append(pkg_path_2_category(pkg_path))
         edl_lib_names.append(pkg_name_2_edl_lib_name(pkg_name))
+        vm_lib_names.append(pkg_name_2_vm_lib_name(pkg_name))
 
     out = [
         ":".join(pkg_names), ":".join(pkg_paths), ":".join(pkg_categories),
-        ":".join(edl_lib_names)
+        ":".join(edl_lib_names), ":".join(vm_lib_names)
     ]
     sys.stdout.write("\n".join(out))
 
@@ -31,6 +31,12 @@ if [ $# -eq 0 ]; then
     exit 1
 fi
 edl_lib_name="$1"
+# "none" unless the enclave runs the wasm executor
+vm_lib_name="${2:-none}"
+vm_lib_flags=""
+if [ "$vm_lib_name" != "none" ]; then
+    vm_lib_flags="-l${vm_lib_name}"
+fi
 
 LIBENCLAVE_PATH="${TRUSTED_TARGET_DIR}/${TARGET}/lib${CUR_PKG_NAME}.a"
 CONFIG_PATH="${TEACLAVE_PROJECT_ROOT}/${CUR_PKG_PATH}/Enclave.config.xml"
@@ -52,7 +58,7 @@ ${CMAKE_C_COMPILER} "lib${edl_lib_name}.o" -o \
     -Wl,--no-whole-archive -Wl,--start-group \
     -l${Service_Library_Name} -lsgx_tprotected_fs -lsgx_tkey_exchange \
     -lsgx_tstdc -lsgx_tcxx -lsgx_tservice -lsgx_tcrypto \
-    -L${TEACLAVE_OUT_DIR} -lpycomponent ffi.o -lpypy-c -lsgx_tlibc_ext -lffi \
+    -L${TEACLAVE_OUT_DIR} -lpycomponent ffi.o -lpypy-c ${vm_lib_flags} -lsgx_tlibc_ext -lffi \
     -L${TRUSTED_TARGET_DIR}/${TARGET} -l${CUR_PKG_NAME} -Wl,--end-group \
     -Wl,-Bstatic -Wl,-Bsymbolic -Wl,--no-undefined \
     -Wl,-pie,-eenclave_entry -Wl,--export-dynamic  \
@@ -33,6 +33,71 @@ struct BuildConfigToml {
     rpc_max_message_size: u64,
     attestation_validity_secs: u64,
     inbound: Inbound,
+    #[serde(default)]
+    oidc_providers: Vec<OidcProviderToml>,
+    #[serde(default)]
+    ldap: LdapToml,
+    #[serde(default)]
+    access_control_policy_signers: Vec<ConfigSource>,
+    #[serde(default)]
+    platform_admins: Vec<String>,
+    #[serde(default)]
+    attestation_gate: AttestationGateToml,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct AttestationGateToml {
+    methods: Vec<String>,
+    enclaves: Vec<AcceptedEnclaveToml>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AcceptedEnclaveToml {
+    mr_signer: String,
+    #[serde(default)]
+    mr_enclave: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LdapToml {
+    #[serde(default)]
+    root_ca_certs: Vec<ConfigSource>,
+    #[serde(default)]
+    user_dn_template: String,
+    #[serde(default = "default_group_attribute")]
+    group_attribute: String,
+    #[serde(default)]
+    admin_groups: Vec<String>,
+    #[serde(default = "default_group_cache_secs")]
+    group_cache_secs: u64,
+}
+
+impl Default for LdapToml {
+    fn default() -> Self {
+        Self {
+            root_ca_certs: Vec::new(),
+            user_dn_template: String::new(),
+            group_attribute: default_group_attribute(),
+            admin_groups: Vec::new(),
+            group_cache_secs: default_group_cache_secs(),
+        }
+    }
+}
+
+fn default_group_attribute() -> String {
+    "memberOf".to_string()
+}
+
+fn default_group_cache_secs() -> u64 {
+    3600
+}
+
+#[derive(Serialize, Deserialize)]
+struct OidcProviderToml {
+    name: String,
+    issuer: String,
+    audience: String,
+    public_keys: Vec<ConfigSource>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -74,6 +139,33 @@ struct ConfigTemplate {
     rpc_max_message_size: u64,
     attestation_validity_secs: u64,
     inbound: Inbound,
+    oidc_providers: Vec<OidcProviderTemplate>,
+    ldap: LdapTemplate,
+    access_control_policy_signers: Vec<String>,
+    platform_admins: Vec<String>,
+    gated_methods: Vec<String>,
+    gate_enclaves: Vec<AcceptedEnclaveTemplate>,
+}
+
+struct AcceptedEnclaveTemplate {
+    mr_signer: String,
+    // Rust expression of the Option
+    mr_enclave: String,
+}
+
+struct LdapTemplate {
+    root_ca_certs: Vec<String>,
+    user_dn_template: String,
+    group_attribute: String,
+    admin_groups: Vec<String>,
+    group_cache_secs: u64,
+}
+
+struct OidcProviderTemplate {
+    name: String,
+    issuer: String,
+    audience: String,
+    public_keys: Vec<String>,
 }
 
 fn generate_build_config(toml: &Path, out: &Path) {
@@ -87,12 +179,66 @@ fn generate_build_config(toml: &Path, out: &Path) {
         let auditor_pulic_key = display_config_source(key);
         auditor_public_keys.push(auditor_pulic_key);
     }
+
+    let oidc_providers = config
+        .oidc_providers
+        .iter()
+        .map(|provider| OidcProviderTemplate {
+            name: provider.name.clone(),
+            issuer: provider.issuer.clone(),
+            audience: provider.audience.clone(),
+            public_keys: provider
+                .public_keys
+                .iter()
+                .map(display_config_source)
+                .collect(),
+        })
+        .collect();
+    if !config.ldap.root_ca_certs.is_empty() && !config.ldap.user_dn_template.contains("{username}")
+    {
+        panic!("Invalid user DN template of LDAP server");
+    }
+    let ldap = LdapTemplate {
+        root_ca_certs: config
+            .ldap
+            .root_ca_certs
+            .iter()
+            .map(display_config_source)
+            .collect(),
+        user_dn_template: config.ldap.user_dn_template,
+        group_attribute: config.ldap.group_attribute,
+        admin_groups: config.ldap.admin_groups,
+        group_cache_secs: config.ldap.group_cache_secs,
+    };
+    let access_control_policy_signers = config
+        .access_control_policy_signers
+        .iter()
+        .map(display_config_source)
+        .collect();
+    let gate_enclaves = config
+        .attestation_gate
+        .enclaves
+        .iter()
+        .map(|enclave| AcceptedEnclaveTemplate {
+            mr_signer: enclave.mr_signer.clone(),
+            mr_enclave: match &enclave.mr_enclave {
+                Some(mr_enclave) => format!("Some({:?})", mr_enclave),
+                None => "None".to_string(),
+            },
+        })
+        .collect();
     let config_template = ConfigTemplate {
         as_root_ca_cert,
         auditor_public_keys,
         rpc_max_message_size: config.rpc_max_message_size,
         attestation_validity_secs: config.attestation_validity_secs,
         inbound: config.inbound,
+        oidc_providers,
+        ldap,
+        access_control_policy_signers,
+        platform_admins: config.platform_admins,
+        gated_methods: config.attestation_gate.methods,
+        gate_enclaves,
     };
     let mut f = File::create(out).expect(&format!("Failed to create file: {}", out.display()));
     f.write_all(&config_template.render().unwrap().as_bytes())
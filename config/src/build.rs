@@ -27,6 +27,25 @@ pub const AS_ROOT_CA_CERT: &[u8] = BUILD_CONFIG.as_root_ca_cert;
 /// verify signatures of `enaclave_info.toml`.
 pub const AUDITOR_PUBLIC_KEYS: &[&[u8]; AUDITOR_PUBLIC_KEYS_LEN] = BUILD_CONFIG.auditor_public_keys;
 
+/// OpenID Connect providers whose ID tokens are accepted by the
+/// authentication service.
+pub const OIDC_PROVIDERS: &[OidcProvider] = BUILD_CONFIG.oidc_providers;
+
+/// LDAP server of the authentication service: its CA certificates in binary
+/// (DER format), the DNs of the users and the groups of the platform admins.
+pub const LDAP_DIRECTORY: &LdapDirectory = &BUILD_CONFIG.ldap;
+
+/// RSA public keys in binary (PKCS#1 DER format) verifying the signatures of
+/// the policy bundles of the access control service.
+pub const ACCESS_CONTROL_POLICY_SIGNERS: &[&[u8]] = BUILD_CONFIG.access_control_policy_signers;
+
+/// IDs of the users who are platform admins.
+pub const PLATFORM_ADMINS: &[&str] = BUILD_CONFIG.platform_admins;
+
+/// Requests of the API endpoints reserved for the accepted enclaves, e.g., an
+/// admin tool.
+pub const ATTESTATION_GATE: &GatedRequests = &BUILD_CONFIG.attestation_gate;
+
 /// The valid duration of one attestation report in seconds.
 pub const ATTESTATION_VALIDITY_SECS: u64 = BUILD_CONFIG.attestation_validity_secs;
 
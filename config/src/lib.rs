@@ -33,4 +33,12 @@ pub(crate) enum ConfigSource {
 pub mod build;
 mod runtime;
 
-pub use runtime::RuntimeConfig;
+pub use runtime::{
+    AcceptedEnclaveConfig, AccessControlConfig, AccessControlEngine, AttestationVerifierConfig,
+    ExecutionNodeConfig, ExecutorEnclaveConfig, FunctionEnvConfig, ImpersonationConfig, LdapConfig,
+    LimitsConfig, MeasurementLogConfig, MessageLimitsConfig, OutputScanConfig,
+    PasswordHashingConfig, PreemptionPolicyKind, QuoteStatusConfig, RateLimitConfig, RuntimeConfig,
+    SchedulingConfig, SchedulingPolicyKind, StorageBackendConfig, StorageBackendKind,
+    StorageCompactionConfig, StorageEncryptionConfig, StorageExpirationConfig,
+    StorageReplicationConfig, StorageSnapshotConfig, TlsConfig, VerificationPolicyConfig,
+};
@@ -25,6 +25,7 @@ use std::untrusted::fs;
 
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 use std::net;
 use std::path::{Path, PathBuf};
@@ -38,12 +39,55 @@ pub struct RuntimeConfig {
     pub audit: AuditConfig,
     pub attestation: AttestationServiceConfig,
     pub mount: MountConfig,
+    #[serde(default = "Default::default")]
+    pub limits: LimitsConfig,
+    #[serde(default = "Default::default")]
+    pub storage_replication: StorageReplicationConfig,
+    #[serde(default = "Default::default")]
+    pub storage_compaction: StorageCompactionConfig,
+    #[serde(default = "Default::default")]
+    pub storage_expiration: StorageExpirationConfig,
+    #[serde(default = "Default::default")]
+    pub storage_encryption: StorageEncryptionConfig,
+    #[serde(default = "Default::default")]
+    pub storage_backend: StorageBackendConfig,
+    #[serde(default = "Default::default")]
+    pub storage_snapshot: StorageSnapshotConfig,
+    #[serde(default = "Default::default")]
+    pub tls: TlsConfig,
+    #[serde(default = "Default::default")]
+    pub impersonation: ImpersonationConfig,
+    #[serde(default = "Default::default")]
+    pub ldap: Option<LdapConfig>,
+    #[serde(default = "Default::default")]
+    pub password_hashing: PasswordHashingConfig,
+    #[serde(default = "Default::default")]
+    pub measurement_log: Option<MeasurementLogConfig>,
+    #[serde(default = "Default::default")]
+    pub access_control: AccessControlConfig,
+    #[serde(default = "Default::default")]
+    pub attestation_verifier: AttestationVerifierConfig,
+    #[serde(default = "Default::default")]
+    pub function_env: FunctionEnvConfig,
+    #[serde(default = "Default::default")]
+    pub scheduling: SchedulingConfig,
+    #[serde(default = "Default::default")]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default = "Default::default")]
+    pub output_scan: OutputScanConfig,
+    #[serde(default = "Default::default")]
+    pub executor_enclaves: Vec<ExecutorEnclaveConfig>,
+    #[serde(default = "Default::default")]
+    pub execution_node: ExecutionNodeConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiEndpointsConfig {
     pub frontend: ApiEndpoint,
     pub authentication: ApiEndpoint,
+    /// Only needed by the attestation verifier service.
+    #[serde(default = "Default::default")]
+    pub attestation_verifier: Option<ApiEndpoint>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -59,12 +103,43 @@ pub struct InternalEndpointsConfig {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiEndpoint {
     pub listen_address: net::SocketAddr,
+    #[serde(default = "Default::default")]
+    pub message_limits: MessageLimitsConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct InternalEndpoint {
     pub listen_address: net::SocketAddr,
+    /// Address clients connect to, either `<host>:<port>` or `unix:<path>`
+    /// for services on the same host listening on `unix_socket`.
     pub advertised_address: String,
+    /// Path of a Unix domain socket the service also listens on.
+    #[serde(default = "Default::default")]
+    pub unix_socket: Option<PathBuf>,
+    #[serde(default = "Default::default")]
+    pub message_limits: MessageLimitsConfig,
+}
+
+/// Size limits of the RPC messages of a service, applied both by the service
+/// and by its clients.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MessageLimitsConfig {
+    /// Maximum size in bytes of a request or response. Larger messages are
+    /// rejected with a `MessageTooLarge` error.
+    pub max_message_len: u64,
+    /// Size in bytes of the frames larger messages are split into, at least
+    /// 4KB and at most 32MB.
+    pub frame_len: u64,
+}
+
+impl Default for MessageLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_message_len: 32 * 1024 * 1024,
+            frame_len: 1024 * 1024,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -85,6 +160,22 @@ pub struct AttestationServiceConfig {
     pub url: String,
     pub key: String,
     pub spid: String,
+    #[serde(default = "Default::default")]
+    pub quote_status: QuoteStatusConfig,
+}
+
+/// Overrides of the quote status matching. Each entry is an allow-list of
+/// advisory IDs (e.g., `["INTEL-SA-00334"]`). A report with the corresponding
+/// status is accepted only if all of its advisory IDs are in the list. If an
+/// entry is absent, the status is left to the verification function.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct QuoteStatusConfig {
+    pub accept_group_out_of_date_if_only: Option<Vec<String>>,
+    pub accept_configuration_needed_if_only: Option<Vec<String>>,
+    pub accept_sw_hardening_needed_if_only: Option<Vec<String>>,
+    pub accept_configuration_and_sw_hardening_needed_if_only: Option<Vec<String>>,
+    pub accept_out_of_date_if_only: Option<Vec<String>>,
+    pub accept_out_of_date_configuration_needed_if_only: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -92,6 +183,517 @@ pub struct MountConfig {
     pub fusion_base_dir: PathBuf,
 }
 
+/// Size limits of requests handled by the services.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Maximum size in bytes of input data registered inline in a request.
+    pub inline_data_max_size: usize,
+    /// Maximum size in bytes of a function payload uploaded in parts.
+    pub payload_max_size: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            inline_data_max_size: 64 * 1024,
+            payload_max_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Read-only replicas of the storage service. A storage instance with
+/// `primary_address` set runs as a replica following that primary; services
+/// reading from storage direct queries to the addresses in `replicas`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageReplicationConfig {
+    /// Advertised address of the primary to follow.
+    pub primary_address: Option<String>,
+    /// Advertised addresses of the replicas serving queries.
+    pub replicas: Vec<String>,
+    /// Interval in milliseconds between two syncs of a replica.
+    pub sync_interval_ms: u64,
+    /// Maximum staleness in milliseconds of a read served by a replica.
+    pub max_staleness_ms: u64,
+    /// Number of recent changes kept by the primary for incremental syncs.
+    pub change_log_capacity: usize,
+}
+
+impl Default for StorageReplicationConfig {
+    fn default() -> Self {
+        Self {
+            primary_address: None,
+            replicas: Vec::new(),
+            sync_interval_ms: 500,
+            max_staleness_ms: 2000,
+            change_log_capacity: 4096,
+        }
+    }
+}
+
+/// Background compaction of the storage database.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageCompactionConfig {
+    /// Interval in seconds between two checks whether compaction is due.
+    pub check_interval_secs: u64,
+    /// Number of writes since the last compaction which triggers compaction.
+    pub write_threshold: u64,
+    /// Number of writes since the last compaction above which compaction is
+    /// reported as falling behind.
+    pub lag_threshold: u64,
+    /// Number of keys compacted at a time.
+    pub batch_size: u64,
+    /// Interval in milliseconds between two batches of a compaction.
+    pub step_interval_ms: u64,
+}
+
+impl Default for StorageCompactionConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 60,
+            write_threshold: 10_000,
+            lag_threshold: 100_000,
+            batch_size: 10_000,
+            step_interval_ms: 100,
+        }
+    }
+}
+
+/// Deletion of the storage keys put with a TTL once they expire.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageExpirationConfig {
+    /// Interval in seconds between two sweeps of the expired keys.
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for StorageExpirationConfig {
+    fn default() -> Self {
+        Self {
+            sweep_interval_secs: 60,
+        }
+    }
+}
+
+/// Files of the storage database and the rotation of their encryption key.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageEncryptionConfig {
+    /// Directory of the database files of the primary, encrypted with a key
+    /// sealed to the storage enclave. The database is kept in memory if not
+    /// set.
+    pub data_dir: Option<String>,
+    /// Number of keys re-encrypted in each step of a key rotation.
+    pub rotation_batch_size: u64,
+    /// Interval in milliseconds between two steps of a key rotation.
+    pub rotation_interval_ms: u64,
+}
+
+impl Default for StorageEncryptionConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: None,
+            rotation_batch_size: 1000,
+            rotation_interval_ms: 100,
+        }
+    }
+}
+
+/// Key-value engine under the storage service.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageBackendConfig {
+    pub backend: StorageBackendKind,
+    /// Address of the key-value server of the remote backend.
+    pub remote_address: Option<String>,
+    /// Prefix of the keys of this storage instance on the key-value server of
+    /// the remote backend.
+    pub remote_namespace: String,
+}
+
+impl Default for StorageBackendConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackendKind::LevelDb,
+            remote_address: None,
+            remote_namespace: "teaclave".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackendKind {
+    /// LevelDB in memory, or in protected files under the `data_dir` of
+    /// `[storage_encryption]`.
+    LevelDb,
+    /// A key-value server speaking the Redis protocol, holding keys and values
+    /// encrypted in the storage enclave.
+    Remote,
+}
+
+/// Snapshots of the storage database exported with the CreateSnapshot RPC,
+/// and the snapshot restored at startup.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageSnapshotConfig {
+    /// Directory where the storage service stages snapshots for the file
+    /// agent, outside of the enclave.
+    pub staging_dir: String,
+    /// URL of a snapshot restored into the database of the primary at
+    /// startup, if the database is empty. Only snapshots created by storage
+    /// enclaves of the same signer on the platform can be restored.
+    pub restore_url: Option<String>,
+}
+
+impl Default for StorageSnapshotConfig {
+    fn default() -> Self {
+        Self {
+            staging_dir: "/tmp/teaclave_storage_snapshots".to_string(),
+            restore_url: None,
+        }
+    }
+}
+
+/// TLS settings of the attested TLS connections of the services.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Names of the allowed cipher suites (e.g., "TLS13_AES_256_GCM_SHA384").
+    /// All cipher suites supported by rustls are allowed if empty.
+    pub cipher_suites: Vec<String>,
+    /// Only accept TLS 1.3 connections.
+    pub tls13_only: bool,
+    /// Number of sessions kept for resumption. Zero disables session
+    /// resumption.
+    pub session_cache_size: usize,
+    /// Resume sessions with stateless tickets issued by the servers.
+    pub session_tickets: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            cipher_suites: Vec::new(),
+            tls13_only: false,
+            session_cache_size: 256,
+            session_tickets: false,
+        }
+    }
+}
+
+/// Platform admins (pinned in the build config) acting as users for read-only
+/// requests, with the consent of the users.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ImpersonationConfig {
+    /// Maximum validity in seconds of a consent granted by a user.
+    pub max_consent_secs: u64,
+}
+
+impl Default for ImpersonationConfig {
+    fn default() -> Self {
+        Self {
+            max_consent_secs: 3600,
+        }
+    }
+}
+
+/// Argon2id parameters for the password hashes of the authentication
+/// service. Hashes made with other parameters, or with PBKDF2 by earlier
+/// versions, are replaced on the next successful login of the user.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PasswordHashingConfig {
+    /// Memory in KiB, at least 19456 (and 8 times the parallelism) and at
+    /// most 98304. Iterations are at least 2; the floor is compiled into the
+    /// authentication service.
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashingConfig {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Engine evaluating the access control model of the access control
+/// service. Both implement the rules of the same model.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessControlEngine {
+    /// Evaluator written in Rust.
+    Native,
+    /// Evaluator written in Python, run by the embedded MesaPy.
+    Python,
+}
+
+impl Default for AccessControlEngine {
+    fn default() -> Self {
+        AccessControlEngine::Native
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AccessControlConfig {
+    pub engine: AccessControlEngine,
+}
+
+/// Policy of the scheduler placing staged tasks on the workers pulling them.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingPolicyKind {
+    /// Tasks are dispatched to any worker, by priority.
+    Priority,
+    /// Tasks are left to the workers on which tasks of the same function ran
+    /// fastest, by priority otherwise.
+    CostBased,
+}
+
+impl Default for SchedulingPolicyKind {
+    fn default() -> Self {
+        SchedulingPolicyKind::Priority
+    }
+}
+
+/// Running tasks which the scheduler cancels, and stages again, when a task
+/// of higher priority is waiting and no node has a free slot.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PreemptionPolicyKind {
+    /// Running tasks are never preempted.
+    None,
+    /// Batch tasks are preempted for interactive tasks.
+    BatchForInteractive,
+    /// Tasks are preempted for any task of higher priority.
+    LowerPriority,
+}
+
+impl Default for PreemptionPolicyKind {
+    fn default() -> Self {
+        PreemptionPolicyKind::None
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SchedulingConfig {
+    pub policy: SchedulingPolicyKind,
+    /// Fraction of the tasks dispatched to the worker pulling them regardless
+    /// of its runtimes, so that the statistics of all workers stay current.
+    pub exploration: f64,
+    /// Fraction by which the mean runtime of a worker may exceed that of the
+    /// fastest worker for the worker to be dispatched the task.
+    pub tolerance: f64,
+    /// Seconds without a heartbeat after which a worker is dead, and the
+    /// tasks dispatched to it are queued again.
+    pub node_timeout_secs: u64,
+    /// Times a task is queued again before it fails instead.
+    pub max_requeues: u32,
+    pub preemption: PreemptionPolicyKind,
+    /// Seconds a task runs before it may be preempted, so that short tasks
+    /// finish instead.
+    pub min_runtime_before_preemption_secs: u64,
+    /// Times a task is preempted at most, so that it is not starved.
+    pub max_preemptions: u32,
+}
+
+impl Default for SchedulingConfig {
+    fn default() -> Self {
+        Self {
+            policy: SchedulingPolicyKind::default(),
+            exploration: 0.1,
+            tolerance: 0.2,
+            node_timeout_secs: 60,
+            max_requeues: 3,
+            preemption: PreemptionPolicyKind::default(),
+            min_runtime_before_preemption_secs: 30,
+            max_preemptions: 2,
+        }
+    }
+}
+
+/// Verification policies of the attestation verifier service, selected by
+/// name in its requests.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AttestationVerifierConfig {
+    pub policies: Vec<VerificationPolicyConfig>,
+    /// Maximum number of verified attestation reports kept, so that the
+    /// evidence of a known enclave is not verified again on every request.
+    pub cache_size: usize,
+    /// Seconds a verified report is kept before its signature and
+    /// certificate chain are verified again.
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for AttestationVerifierConfig {
+    fn default() -> Self {
+        Self {
+            policies: Vec::new(),
+            cache_size: 1024,
+            cache_ttl_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerificationPolicyConfig {
+    pub name: String,
+    /// Accepted enclaves. Reports of other enclaves are rejected.
+    pub enclaves: Vec<AcceptedEnclaveConfig>,
+    /// Maximum age in seconds of the attestation report, if any.
+    #[serde(default = "Default::default")]
+    pub max_report_age_secs: Option<u64>,
+    /// Quote statuses other than OK are rejected unless allowed here.
+    #[serde(default = "Default::default")]
+    pub quote_status: QuoteStatusConfig,
+    /// Accept enclaves launched in debug mode, whose memory the host can
+    /// read.
+    #[serde(default = "Default::default")]
+    pub allow_debug: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AcceptedEnclaveConfig {
+    /// Hex-encoded MRSIGNER.
+    pub mr_signer: String,
+    /// Hex-encoded MRENCLAVE, or any enclave of the signer if absent.
+    #[serde(default = "Default::default")]
+    pub mr_enclave: Option<String>,
+}
+
+/// Rate limits of the requests to the frontend service, enforced with a
+/// token bucket for each user and for each client IP address. A bucket holds
+/// up to `burst` requests and is refilled at `requests_per_sec`; zero
+/// disables the limit.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub user_requests_per_sec: f64,
+    pub user_burst: u32,
+    pub ip_requests_per_sec: f64,
+    pub ip_burst: u32,
+}
+
+/// Content scanners run by the execution service on the outputs of a task
+/// before they are uploaded. Outputs failing a scan are held until the owners
+/// of the input data review them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OutputScanConfig {
+    /// Names of the scanners, "pii" or "min_aggregation". Outputs are not
+    /// scanned if empty.
+    pub scanners: Vec<String>,
+    /// Least value in the count column of every row of CSV outputs, checked
+    /// by "min_aggregation".
+    pub min_count: u64,
+    /// Name of the count column in the header of CSV outputs.
+    pub count_column: String,
+}
+
+impl Default for OutputScanConfig {
+    fn default() -> Self {
+        Self {
+            scanners: Vec::new(),
+            min_count: 10,
+            count_column: "count".to_string(),
+        }
+    }
+}
+
+/// Resources of the node of the execution service, reported to the
+/// scheduler service, which dispatches tasks to the least loaded nodes. Zero
+/// stands for unknown.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExecutionNodeConfig {
+    pub cpus: u32,
+    pub memory_mb: u64,
+    /// Labels of the node matched against the affinity of tasks, e.g.,
+    /// `icelake`, `has-large-epc` or `zone=a`.
+    pub labels: Vec<String>,
+    /// Tasks run at once by the execution service, each in a slot of its
+    /// own.
+    pub slots: u32,
+    /// Memory reserved by the function running in each slot, so that no
+    /// more slots run at once than fit in `memory_mb`. Zero for no
+    /// accounting.
+    pub slot_memory_mb: u64,
+    /// Builtin functions enabled on the node, among those registered with
+    /// the builtin executor (e.g., by the `builtin_*` features of the
+    /// executor crate). All of them are enabled if empty.
+    pub builtin_functions: Vec<String>,
+}
+
+impl Default for ExecutionNodeConfig {
+    fn default() -> Self {
+        Self {
+            cpus: 0,
+            memory_mb: 0,
+            labels: Vec::new(),
+            slots: 1,
+            slot_memory_mb: 0,
+            builtin_functions: Vec::new(),
+        }
+    }
+}
+
+/// An executor enclave of a function provider, launched by the host of the
+/// execution service with the same runtime config. The execution service
+/// pulls the tasks of the functions registered with an enclave of the
+/// measurement, and hands them over to it once it is attested.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExecutorEnclaveConfig {
+    /// Name of the signed enclave file, without ".signed.so".
+    pub name: String,
+    /// Hex-encoded MRENCLAVE of the enclave.
+    pub mr_enclave: String,
+    /// Address the enclave listens at, e.g., "localhost:7900".
+    pub address: String,
+}
+
+/// Environment of the functions run on the platform (`context.env()`), read
+/// by the management service when tasks are created and invoked. Values are
+/// not secret: they come from the host, like the rest of this config.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FunctionEnvConfig {
+    /// Keys which tasks may set, e.g., "batch_size". Tasks cannot set any key
+    /// if empty.
+    pub allowed_keys: Vec<String>,
+    /// Values of the deployment, overridden by those of a task.
+    pub defaults: HashMap<String, String>,
+}
+
+/// Transparency log of released enclave measurements (a JSON
+/// `teaclave_types::MeasurementLog`), served by the management service.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MeasurementLogConfig {
+    /// Path of the log on the host. Its integrity is verified by clients with
+    /// the public key of the log.
+    pub path: String,
+}
+
+/// LDAP or Active Directory server validating the passwords of the users
+/// with ids "ldap:<username>". The server is authenticated, and the DNs of
+/// the users and their groups are read, with the settings in the build
+/// config.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LdapConfig {
+    /// URL of the server, e.g., "ldaps://ldap.example.com:636".
+    pub url: String,
+}
+
 impl RuntimeConfig {
     pub fn from_toml<T: AsRef<Path>>(path: T) -> Result<Self> {
         let contents = fs::read_to_string(path.as_ref())
@@ -126,11 +728,13 @@ impl RuntimeConfig {
             let url = env::var("AS_URL").unwrap();
             let spid = env::var("AS_SPID").unwrap();
             let key = env::var("AS_KEY").unwrap();
+            let quote_status = config.attestation.quote_status.clone();
             config.attestation = AttestationServiceConfig {
                 algorithm,
                 url,
                 key,
                 spid,
+                quote_status,
             };
         }
 
@@ -162,5 +766,12 @@ fn validate_config(config: &RuntimeConfig) -> Result<()> {
         bail!("Invalid URL of attestation service");
     }
 
+    if let Some(ldap) = &config.ldap {
+        match url::Url::parse(&ldap.url) {
+            Ok(url) if url.scheme() == "ldaps" => (),
+            _ => bail!("Invalid URL of LDAP server, ldaps:// is required"),
+        }
+    }
+
     Ok(())
 }
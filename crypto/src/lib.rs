@@ -15,27 +15,39 @@
 // specific language governing permissions and limitations
 // under the License.
 
-#![cfg_attr(feature = "mesalock_sgx", no_std)]
-#[cfg(feature = "mesalock_sgx")]
+#![cfg_attr(feature = "sgx", no_std)]
+#[cfg(feature = "sgx")]
 extern crate sgx_tstd as std;
 
-#[cfg(feature = "mesalock_sgx")]
+#[cfg(all(feature = "sgx", feature = "libos"))]
+compile_error!("feature \"sgx\" and feature \"libos\" cannot be enabled at the same time");
+
+#[cfg(feature = "sgx")]
 use std::prelude::v1::*;
 
+use aes_gcm_siv::Aes256GcmSiv;
 use anyhow::{anyhow, ensure, Context, Result};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{AeadInPlace, NewAead};
+use chacha20poly1305::XChaCha20Poly1305;
 use protected_fs::ProtectedFile;
-use rand::prelude::RngCore;
 use ring::aead;
 use serde::{Deserialize, Serialize};
 use std::format;
 use std::io::{Read, Write};
 use std::path::Path;
 
+pub mod rng;
+
 const AES_GCM_128_KEY_LENGTH: usize = 16;
 const AES_GCM_128_IV_LENGTH: usize = 12;
 
 const AES_GCM_256_KEY_LENGTH: usize = 32;
 const AES_GCM_256_IV_LENGTH: usize = 12;
+const AES_GCM_SIV_256_KEY_LENGTH: usize = 32;
+const AES_GCM_SIV_256_IV_LENGTH: usize = 12;
+const XCHACHA20_POLY1305_KEY_LENGTH: usize = 32;
+const XCHACHA20_POLY1305_IV_LENGTH: usize = 24;
 const TEACLAVE_FILE_128_ROOT_KEY_LENGTH: usize = 16;
 const CMAC_LENGTH: usize = 16;
 const FILE_CHUNK_SIZE: usize = 1024 * 1024;
@@ -102,9 +114,8 @@ impl Default for AesGcm256Key {
     fn default() -> Self {
         let mut key = [0u8; AES_GCM_256_KEY_LENGTH];
         let mut iv = [0u8; AES_GCM_256_IV_LENGTH];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut key);
-        rng.fill_bytes(&mut iv);
+        rng::fill_bytes(&mut key);
+        rng::fill_bytes(&mut iv);
 
         Self { key, iv }
     }
@@ -172,9 +183,132 @@ impl Default for AesGcm128Key {
     fn default() -> Self {
         let mut key = [0u8; AES_GCM_128_KEY_LENGTH];
         let mut iv = [0u8; AES_GCM_128_IV_LENGTH];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut key);
-        rng.fill_bytes(&mut iv);
+        rng::fill_bytes(&mut key);
+        rng::fill_bytes(&mut iv);
+
+        Self { key, iv }
+    }
+}
+
+/// AES-256-GCM-SIV, which stays secure (apart from revealing equal
+/// plaintexts) if an IV is reused with the same key.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AesGcmSiv256Key {
+    pub key: [u8; AES_GCM_SIV_256_KEY_LENGTH],
+    pub iv: [u8; AES_GCM_SIV_256_IV_LENGTH],
+}
+
+impl AesGcmSiv256Key {
+    pub const SCHEMA: &'static str = "aes-gcm-siv-256";
+
+    pub fn new(in_key: &[u8], in_iv: &[u8]) -> Result<Self> {
+        ensure!(
+            in_key.len() == AES_GCM_SIV_256_KEY_LENGTH,
+            "Invalid key length for AesGcmSiv256: {}",
+            in_key.len()
+        );
+        ensure!(
+            in_iv.len() == AES_GCM_SIV_256_IV_LENGTH,
+            "Invalid iv length for AesGcmSiv256: {}",
+            in_iv.len()
+        );
+        let mut key = [0u8; AES_GCM_SIV_256_KEY_LENGTH];
+        let mut iv = [0u8; AES_GCM_SIV_256_IV_LENGTH];
+        key.copy_from_slice(in_key);
+        iv.copy_from_slice(in_iv);
+
+        Ok(AesGcmSiv256Key { key, iv })
+    }
+
+    pub fn from_hex(in_key: impl AsRef<str>, in_iv: impl AsRef<str>) -> Result<Self> {
+        let key = hex::decode(in_key.as_ref()).context("Illegal AesGcmSiv256 key provided")?;
+        let iv = hex::decode(in_iv.as_ref()).context("Illegal AesGcmSiv256 iv provided")?;
+        Self::new(&key, &iv)
+    }
+
+    pub fn random() -> Self {
+        Self::default()
+    }
+
+    pub fn decrypt(&self, in_out: &mut Vec<u8>) -> Result<CMac> {
+        let cipher = Aes256GcmSiv::new(GenericArray::from_slice(&self.key));
+        decrypt_in_place(&cipher, in_out, &self.iv)
+    }
+
+    pub fn encrypt(&self, in_out: &mut Vec<u8>) -> Result<CMac> {
+        let cipher = Aes256GcmSiv::new(GenericArray::from_slice(&self.key));
+        encrypt_in_place(&cipher, in_out, &self.iv)
+    }
+}
+
+impl Default for AesGcmSiv256Key {
+    fn default() -> Self {
+        let mut key = [0u8; AES_GCM_SIV_256_KEY_LENGTH];
+        let mut iv = [0u8; AES_GCM_SIV_256_IV_LENGTH];
+        rng::fill_bytes(&mut key);
+        rng::fill_bytes(&mut iv);
+
+        Self { key, iv }
+    }
+}
+
+/// XChaCha20-Poly1305, for clients without AES in hardware. Its 24-byte IVs
+/// can be chosen at random.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct XChaCha20Poly1305Key {
+    pub key: [u8; XCHACHA20_POLY1305_KEY_LENGTH],
+    pub iv: [u8; XCHACHA20_POLY1305_IV_LENGTH],
+}
+
+impl XChaCha20Poly1305Key {
+    pub const SCHEMA: &'static str = "xchacha20-poly1305";
+
+    pub fn new(in_key: &[u8], in_iv: &[u8]) -> Result<Self> {
+        ensure!(
+            in_key.len() == XCHACHA20_POLY1305_KEY_LENGTH,
+            "Invalid key length for XChaCha20Poly1305: {}",
+            in_key.len()
+        );
+        ensure!(
+            in_iv.len() == XCHACHA20_POLY1305_IV_LENGTH,
+            "Invalid iv length for XChaCha20Poly1305: {}",
+            in_iv.len()
+        );
+        let mut key = [0u8; XCHACHA20_POLY1305_KEY_LENGTH];
+        let mut iv = [0u8; XCHACHA20_POLY1305_IV_LENGTH];
+        key.copy_from_slice(in_key);
+        iv.copy_from_slice(in_iv);
+
+        Ok(XChaCha20Poly1305Key { key, iv })
+    }
+
+    pub fn from_hex(in_key: impl AsRef<str>, in_iv: impl AsRef<str>) -> Result<Self> {
+        let key = hex::decode(in_key.as_ref()).context("Illegal XChaCha20Poly1305 key provided")?;
+        let iv = hex::decode(in_iv.as_ref()).context("Illegal XChaCha20Poly1305 iv provided")?;
+        Self::new(&key, &iv)
+    }
+
+    pub fn random() -> Self {
+        Self::default()
+    }
+
+    pub fn decrypt(&self, in_out: &mut Vec<u8>) -> Result<CMac> {
+        let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(&self.key));
+        decrypt_in_place(&cipher, in_out, &self.iv)
+    }
+
+    pub fn encrypt(&self, in_out: &mut Vec<u8>) -> Result<CMac> {
+        let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(&self.key));
+        encrypt_in_place(&cipher, in_out, &self.iv)
+    }
+}
+
+impl Default for XChaCha20Poly1305Key {
+    fn default() -> Self {
+        let mut key = [0u8; XCHACHA20_POLY1305_KEY_LENGTH];
+        let mut iv = [0u8; XCHACHA20_POLY1305_IV_LENGTH];
+        rng::fill_bytes(&mut key);
+        rng::fill_bytes(&mut iv);
 
         Self { key, iv }
     }
@@ -239,8 +373,7 @@ impl TeaclaveFile128Key {
 impl Default for TeaclaveFile128Key {
     fn default() -> Self {
         let mut key = [0u8; TEACLAVE_FILE_128_ROOT_KEY_LENGTH];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut key);
+        rng::fill_bytes(&mut key);
 
         TeaclaveFile128Key { key }
     }
@@ -284,13 +417,41 @@ pub fn aead_encrypt(
     Ok(())
 }
 
+// Ciphers which are not in ring, with the same AAD and layout (the tag
+// appended to the ciphertext) as aead_encrypt and aead_decrypt.
+fn encrypt_in_place<A: AeadInPlace>(cipher: &A, in_out: &mut Vec<u8>, iv: &[u8]) -> Result<CMac> {
+    let nonce = GenericArray::from_slice(iv);
+    cipher
+        .encrypt_in_place(nonce, &[0u8; 8], in_out)
+        .map_err(|_| anyhow!("Aead encrypt_in_place error"))?;
+    let mut cmac: CMac = [0u8; CMAC_LENGTH];
+    cmac.copy_from_slice(&in_out[in_out.len() - CMAC_LENGTH..]);
+    Ok(cmac)
+}
+
+fn decrypt_in_place<A: AeadInPlace>(cipher: &A, in_out: &mut Vec<u8>, iv: &[u8]) -> Result<CMac> {
+    ensure!(in_out.len() >= CMAC_LENGTH, "Aead ciphertext too short");
+    let mut cmac: CMac = [0u8; CMAC_LENGTH];
+    cmac.copy_from_slice(&in_out[in_out.len() - CMAC_LENGTH..]);
+    let nonce = GenericArray::from_slice(iv);
+    cipher
+        .decrypt_in_place(nonce, &[0u8; 8], in_out)
+        .map_err(|_| anyhow!("Aead decrypt_in_place error"))?;
+    Ok(cmac)
+}
+
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
     use teaclave_test_utils::*;
 
     pub fn run_tests() -> bool {
-        run_tests!(test_aead_enc_then_dec, test_crypto_info,)
+        run_tests!(
+            test_aead_enc_then_dec,
+            test_crypto_info,
+            test_misuse_resistant_crypto_info,
+            rng::tests::test_seeded_rng,
+        )
     }
 
     fn test_aead_enc_then_dec() {
@@ -318,4 +479,23 @@ pub mod tests {
         crypto_info.decrypt(&mut buf).unwrap();
         assert_eq!(&buf[..], &plain_text[..]);
     }
+
+    fn test_misuse_resistant_crypto_info() {
+        let plain_text: [u8; 5] = [0xde, 0xff, 0xab, 0xcd, 0x90];
+
+        let crypto_info = AesGcmSiv256Key::new(&[0x90u8; 32], &[0x89u8; 12]).unwrap();
+        let mut buf = plain_text.to_vec();
+        let cmac = crypto_info.encrypt(&mut buf).unwrap();
+        assert_eq!(buf.len(), plain_text.len() + CMAC_LENGTH);
+        assert_eq!(crypto_info.decrypt(&mut buf).unwrap(), cmac);
+        assert_eq!(&buf[..], &plain_text[..]);
+
+        let crypto_info = XChaCha20Poly1305Key::new(&[0x90u8; 32], &[0x89u8; 24]).unwrap();
+        let mut buf = plain_text.to_vec();
+        crypto_info.encrypt(&mut buf).unwrap();
+        assert_ne!(&buf[..plain_text.len()], &plain_text[..]);
+        buf[0] ^= 1;
+        assert!(crypto_info.decrypt(&mut buf).is_err());
+        assert!(XChaCha20Poly1305Key::new(&[0x90u8; 32], &[0x89u8; 12]).is_err());
+    }
 }
@@ -0,0 +1,153 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Random source of keys, nonces and identifiers. All random bytes of
+//! Teaclave are drawn from the hardware source in enclaves and the OS source
+//! otherwise. Only test builds can replace it with a seeded deterministic one
+//! through `set_rng`, so no production binary can be made to use predictable
+//! keys.
+
+#[cfg(feature = "sgx")]
+use std::prelude::v1::*;
+
+use lazy_static::lazy_static;
+#[cfg(any(test, feature = "enclave_unit_test"))]
+use rand::rngs::StdRng;
+#[cfg(any(not(feature = "sgx"), test, feature = "enclave_unit_test"))]
+use rand::RngCore;
+#[cfg(any(test, feature = "enclave_unit_test"))]
+use rand::SeedableRng;
+use std::sync::Arc;
+#[cfg(all(not(feature = "sgx"), any(test, feature = "enclave_unit_test")))]
+use std::sync::Mutex;
+#[cfg(not(feature = "sgx"))]
+use std::sync::RwLock;
+#[cfg(all(feature = "sgx", any(test, feature = "enclave_unit_test")))]
+use std::sync::SgxMutex as Mutex;
+#[cfg(feature = "sgx")]
+use std::sync::SgxRwLock as RwLock;
+
+pub trait TeaclaveRng: Send + Sync {
+    fn fill_bytes(&self, dest: &mut [u8]);
+}
+
+/// Random numbers from the RDRAND instruction, as the OS source is not
+/// trusted in enclaves.
+#[cfg(feature = "sgx")]
+#[derive(Default)]
+pub struct HardwareRng;
+
+#[cfg(feature = "sgx")]
+impl TeaclaveRng for HardwareRng {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        // RDRAND only fails on faulty CPUs, where no key can be trusted.
+        sgx_trts::trts::rsgx_read_rand(dest).expect("RDRAND failed");
+    }
+}
+
+/// Random numbers from the OS.
+#[cfg(not(feature = "sgx"))]
+#[derive(Default)]
+pub struct OsRng;
+
+#[cfg(not(feature = "sgx"))]
+impl TeaclaveRng for OsRng {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        rand::rngs::OsRng.fill_bytes(dest);
+    }
+}
+
+#[cfg(feature = "sgx")]
+pub type DefaultRng = HardwareRng;
+#[cfg(not(feature = "sgx"))]
+pub type DefaultRng = OsRng;
+
+/// Deterministic random numbers generated from a seed, for tests. Keys drawn
+/// from it are predictable, so it is not compiled into production builds.
+#[cfg(any(test, feature = "enclave_unit_test"))]
+pub struct SeededRng {
+    rng: Mutex<StdRng>,
+}
+
+#[cfg(any(test, feature = "enclave_unit_test"))]
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "enclave_unit_test"))]
+impl TeaclaveRng for SeededRng {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        // The lock is only poisoned by a panic while generating, which leaves
+        // the generator in a valid state.
+        let mut rng = match self.rng.lock() {
+            Ok(rng) => rng,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        rng.fill_bytes(dest);
+    }
+}
+
+lazy_static! {
+    static ref RNG: RwLock<Arc<dyn TeaclaveRng>> = RwLock::new(Arc::new(DefaultRng::default()));
+}
+
+/// Installs `rng` as the random source, returning the previous one. Only
+/// available to tests.
+#[cfg(any(test, feature = "enclave_unit_test"))]
+pub fn set_rng(rng: Arc<dyn TeaclaveRng>) -> Arc<dyn TeaclaveRng> {
+    let mut current = match RNG.write() {
+        Ok(current) => current,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    std::mem::replace(&mut *current, rng)
+}
+
+/// The random source installed with `set_rng` in tests, or the default one.
+pub fn rng() -> Arc<dyn TeaclaveRng> {
+    match RNG.read() {
+        Ok(current) => current.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+pub fn fill_bytes(dest: &mut [u8]) {
+    rng().fill_bytes(dest);
+}
+
+pub fn random_u64() -> u64 {
+    let mut bytes = [0; 8];
+    fill_bytes(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_seeded_rng() {
+        let previous = set_rng(Arc::new(SeededRng::new(42)));
+        let first = random_u64();
+        set_rng(Arc::new(SeededRng::new(42)));
+        assert_eq!(random_u64(), first);
+        set_rng(previous);
+        assert_ne!(random_u64(), random_u64());
+    }
+}
@@ -18,63 +18,205 @@
 #[cfg(feature = "mesalock_sgx")]
 use std::prelude::v1::*;
 
+use std::collections::HashMap;
+
 use teaclave_function::{
-    Echo, FaceDetection, GbdtPredict, GbdtTrain, LogisticRegressionPredict,
-    LogisticRegressionTrain, OnlineDecrypt, OrderedSetIntersect, PasswordCheck,
-    PrincipalComponentsAnalysis, PrivateJoinAndCompute, RsaSign,
+    DpAggregate, Echo, FaceDetection, GbdtPredict, GbdtTrain, LogisticRegressionPredict,
+    LogisticRegressionTrain, OnlineDecrypt, OnnxInference, OrderedSetIntersect, PasswordCheck,
+    PrincipalComponentsAnalysis, PrivateJoinAndCompute, PsiCardinality, PsiJoin, RsaSign,
+    SecureAggregation, SqlQuery,
 };
 use teaclave_types::{FunctionArguments, FunctionRuntime, TeaclaveExecutor};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Result};
+
+/// A native function run by the builtin executor.
+pub type BuiltinFunction = fn(FunctionArguments, FunctionRuntime) -> Result<String>;
+
+/// Registry of the builtin functions, by default those enabled with the
+/// `builtin_*` features. Functions of other crates are added with
+/// `register`, and the functions of a node are limited with `retain`.
+#[derive(Clone)]
+pub struct BuiltinFunctionExecutor {
+    functions: HashMap<String, BuiltinFunction>,
+}
+
+impl Default for BuiltinFunctionExecutor {
+    fn default() -> Self {
+        let mut executor = Self::new();
+        #[cfg(feature = "builtin_echo")]
+        executor.register(Echo::NAME, |arguments, runtime| {
+            Echo::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_dp_aggregate")]
+        executor.register(DpAggregate::NAME, |arguments, runtime| {
+            DpAggregate::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_gbdt_predict")]
+        executor.register(GbdtPredict::NAME, |arguments, runtime| {
+            GbdtPredict::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_gbdt_train")]
+        executor.register(GbdtTrain::NAME, |arguments, runtime| {
+            GbdtTrain::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_logistic_regression_train")]
+        executor.register(LogisticRegressionTrain::NAME, |arguments, runtime| {
+            LogisticRegressionTrain::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_logistic_regression_predict")]
+        executor.register(LogisticRegressionPredict::NAME, |arguments, runtime| {
+            LogisticRegressionPredict::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_online_decrypt")]
+        executor.register(OnlineDecrypt::NAME, |arguments, runtime| {
+            OnlineDecrypt::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_onnx_inference")]
+        executor.register(OnnxInference::NAME, |arguments, runtime| {
+            OnnxInference::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_private_join_and_compute")]
+        executor.register(PrivateJoinAndCompute::NAME, |arguments, runtime| {
+            PrivateJoinAndCompute::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_ordered_set_intersect")]
+        executor.register(OrderedSetIntersect::NAME, |arguments, runtime| {
+            OrderedSetIntersect::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_psi_cardinality")]
+        executor.register(PsiCardinality::NAME, |arguments, runtime| {
+            PsiCardinality::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_psi_join")]
+        executor.register(PsiJoin::NAME, |arguments, runtime| {
+            PsiJoin::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_rsa_sign")]
+        executor.register(RsaSign::NAME, |arguments, runtime| {
+            RsaSign::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_secure_aggregation")]
+        executor.register(SecureAggregation::NAME, |arguments, runtime| {
+            SecureAggregation::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_sql_query")]
+        executor.register(SqlQuery::NAME, |arguments, runtime| {
+            SqlQuery::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_principal_components_analysis")]
+        executor.register(PrincipalComponentsAnalysis::NAME, |arguments, runtime| {
+            PrincipalComponentsAnalysis::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_face_detection")]
+        executor.register(FaceDetection::NAME, |arguments, runtime| {
+            FaceDetection::new().run(arguments, runtime)
+        });
+        #[cfg(feature = "builtin_password_check")]
+        executor.register(PasswordCheck::NAME, |arguments, runtime| {
+            PasswordCheck::new().run(arguments, runtime)
+        });
+        executor
+    }
+}
+
+impl BuiltinFunctionExecutor {
+    /// An executor without functions.
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Registers a function, replacing the function of the same name.
+    pub fn register(&mut self, name: impl ToString, function: BuiltinFunction) {
+        self.functions.insert(name.to_string(), function);
+    }
+
+    /// Keeps only the functions of the names, e.g., those enabled in the
+    /// runtime config. Fails if any of them is not registered.
+    pub fn retain(&mut self, names: &[String]) -> Result<()> {
+        for name in names {
+            ensure!(
+                self.functions.contains_key(name),
+                "Builtin function not registered: {}",
+                name
+            );
+        }
+        self.functions.retain(|name, _| names.contains(name));
+        Ok(())
+    }
 
-#[derive(Default)]
-pub struct BuiltinFunctionExecutor;
+    /// Names of the registered functions, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
 
 impl TeaclaveExecutor for BuiltinFunctionExecutor {
     fn execute(
         &self,
         name: String,
         arguments: FunctionArguments,
-        _payload: String,
+        _payload: Vec<u8>,
         runtime: FunctionRuntime,
     ) -> Result<String> {
-        match name.as_str() {
-            #[cfg(feature = "builtin_echo")]
-            Echo::NAME => Echo::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_gbdt_predict")]
-            GbdtPredict::NAME => GbdtPredict::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_gbdt_train")]
-            GbdtTrain::NAME => GbdtTrain::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_logistic_regression_train")]
-            LogisticRegressionTrain::NAME => LogisticRegressionTrain::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_logistic_regression_predict")]
-            LogisticRegressionPredict::NAME => {
-                LogisticRegressionPredict::new().run(arguments, runtime)
-            }
-            #[cfg(feature = "builtin_online_decrypt")]
-            OnlineDecrypt::NAME => OnlineDecrypt::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_private_join_and_compute")]
-            PrivateJoinAndCompute::NAME => PrivateJoinAndCompute::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_ordered_set_intersect")]
-            OrderedSetIntersect::NAME => OrderedSetIntersect::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_rsa_sign")]
-            RsaSign::NAME => RsaSign::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_principal_components_analysis")]
-            PrincipalComponentsAnalysis::NAME => {
-                PrincipalComponentsAnalysis::new().run(arguments, runtime)
-            }
-            #[cfg(feature = "builtin_face_detection")]
-            FaceDetection::NAME => FaceDetection::new().run(arguments, runtime),
-            #[cfg(feature = "builtin_password_check")]
-            PasswordCheck::NAME => PasswordCheck::new().run(arguments, runtime),
-            _ => bail!("Function not found."),
+        match self.functions.get(&name) {
+            Some(function) => function(arguments, runtime),
+            None => bail!("Function not found."),
         }
     }
 }
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
+    use super::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
     pub fn run_tests() -> bool {
-        true
+        run_tests!(test_registry)
+    }
+
+    fn answer(_arguments: FunctionArguments, _runtime: FunctionRuntime) -> Result<String> {
+        Ok("42".to_string())
+    }
+
+    fn test_registry() {
+        let runtime = || -> FunctionRuntime {
+            Box::new(RawIoRuntime::new(
+                StagedFiles::default(),
+                StagedFiles::default(),
+            ))
+        };
+
+        let mut executor = BuiltinFunctionExecutor::new();
+        executor.register("plugin-answer", answer);
+        executor.register("plugin-other", answer);
+        assert_eq!(executor.names(), vec!["plugin-answer", "plugin-other"]);
+        let summary = executor
+            .execute(
+                "plugin-answer".to_string(),
+                FunctionArguments::default(),
+                vec![],
+                runtime(),
+            )
+            .unwrap();
+        assert_eq!(summary, "42");
+
+        assert!(executor.retain(&["plugin-unknown".to_string()]).is_err());
+        executor.retain(&["plugin-other".to_string()]).unwrap();
+        assert_eq!(executor.names(), vec!["plugin-other"]);
+        assert!(executor
+            .execute(
+                "plugin-answer".to_string(),
+                FunctionArguments::default(),
+                vec![],
+                runtime(),
+            )
+            .is_err());
     }
 }
@@ -31,6 +31,10 @@ use teaclave_types::TeaclaveRuntime;
 
 const FFI_OK: c_uint = 0;
 const FFI_FILE_ERROR: c_uint = 1;
+const FFI_ENV_NOT_FOUND: c_uint = 2;
+const FFI_BUFFER_TOO_SHORT: c_uint = 3;
+const FFI_LOG_ERROR: c_uint = 4;
+const FFI_RESULT_ERROR: c_uint = 5;
 
 pub struct Context {
     runtime: Box<dyn TeaclaveRuntime + Send + Sync>,
@@ -75,6 +79,18 @@ impl Context {
         Ok(size)
     }
 
+    fn env(&self, key: &str) -> Option<String> {
+        self.runtime.env().get(key).cloned()
+    }
+
+    fn log(&self, message: &str) {
+        self.runtime.log(message)
+    }
+
+    fn emit_result(&self, chunk: &[u8]) {
+        self.runtime.emit_result(chunk)
+    }
+
     fn close_handle(&mut self, handle: FileHandle) -> anyhow::Result<()> {
         if handle.is_read_handle() {
             self.read_handles.remove(handle)?;
@@ -232,6 +248,32 @@ pub fn rtc_close_handle(f: FileHandle) -> anyhow::Result<()> {
     })
 }
 
+pub fn rtc_get_env(key: &str) -> anyhow::Result<Option<String>> {
+    CONTEXT.with(|ctx| {
+        let ctx = ctx.borrow();
+        anyhow::ensure!(ctx.is_some(), "Context not initialized");
+        Ok(ctx.as_ref().unwrap().env(key))
+    })
+}
+
+pub fn rtc_log(message: &str) -> anyhow::Result<()> {
+    CONTEXT.with(|ctx| {
+        let ctx = ctx.borrow();
+        anyhow::ensure!(ctx.is_some(), "Context not initialized");
+        ctx.as_ref().unwrap().log(message);
+        Ok(())
+    })
+}
+
+pub fn rtc_emit_result(chunk: &[u8]) -> anyhow::Result<()> {
+    CONTEXT.with(|ctx| {
+        let ctx = ctx.borrow();
+        anyhow::ensure!(ctx.is_some(), "Context not initialized");
+        ctx.as_ref().unwrap().emit_result(chunk);
+        Ok(())
+    })
+}
+
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
@@ -242,11 +284,19 @@ pub mod tests {
     use teaclave_test_utils::*;
     use teaclave_types::hashmap;
     use teaclave_types::FileAuthTag;
+    use teaclave_types::FunctionLog;
+    use teaclave_types::ResultStream;
     use teaclave_types::StagedFileInfo;
     use teaclave_types::StagedFiles;
 
     pub fn run_tests() -> bool {
-        run_tests!(test_file_handle_encoding, test_rtc_api,)
+        run_tests!(
+            test_file_handle_encoding,
+            test_rtc_api,
+            test_rtc_env,
+            test_rtc_log,
+            test_rtc_emit_result
+        )
     }
 
     fn test_file_handle_encoding() {
@@ -292,6 +342,45 @@ pub mod tests {
         assert!(rtc_close_handle(f).is_err());
         reset_thread_context().unwrap();
     }
+
+    fn test_rtc_env() {
+        assert!(rtc_get_env("batch_size").is_err());
+
+        let runtime = RawIoRuntime::new(StagedFiles::default(), StagedFiles::default())
+            .with_env(hashmap!("batch_size" => "64"));
+        set_thread_context(Context::new(Box::new(runtime))).unwrap();
+
+        assert_eq!(rtc_get_env("batch_size").unwrap(), Some("64".to_string()));
+        assert_eq!(rtc_get_env("epochs").unwrap(), None);
+        reset_thread_context().unwrap();
+    }
+
+    fn test_rtc_log() {
+        assert!(rtc_log("lost").is_err());
+
+        let log = FunctionLog::new();
+        let runtime =
+            RawIoRuntime::new(StagedFiles::default(), StagedFiles::default()).with_log(log.clone());
+        set_thread_context(Context::new(Box::new(runtime))).unwrap();
+
+        rtc_log("epoch 1").unwrap();
+        rtc_log("epoch 2").unwrap();
+        assert_eq!(log.contents(), (b"epoch 1\nepoch 2\n".to_vec(), false));
+        reset_thread_context().unwrap();
+    }
+
+    fn test_rtc_emit_result() {
+        assert!(rtc_emit_result(b"lost").is_err());
+
+        let stream = ResultStream::new();
+        let runtime = RawIoRuntime::new(StagedFiles::default(), StagedFiles::default())
+            .with_result_stream(stream.clone());
+        set_thread_context(Context::new(Box::new(runtime))).unwrap();
+
+        rtc_emit_result(b"epoch 1").unwrap();
+        assert_eq!(stream.take(), (vec![b"epoch 1".to_vec()], false));
+        reset_thread_context().unwrap();
+    }
 }
 
 use std::ffi::CStr;
@@ -416,3 +505,70 @@ extern "C" fn c_close_file(handle: c_int) -> c_uint {
         }
     }
 }
+
+/*
+ * uint c_get_env(char* key, void* out_buf, size_t buf_size, size_t* out_size);
+ *
+ * The size of the value is written to out_size also if the buffer is too
+ * short, for the caller to retry.
+ */
+#[allow(unused)]
+#[no_mangle]
+extern "C" fn c_get_env(
+    key: *mut c_char,
+    out_buf: *mut c_uchar,
+    buf_size: size_t,
+    out_size_p: *mut size_t,
+) -> c_uint {
+    debug!("c_get_env");
+    let key = unsafe { CStr::from_ptr(key).to_string_lossy().into_owned() };
+    let value = match rtc_get_env(&key) {
+        Ok(Some(value)) => value,
+        Ok(None) => return FFI_ENV_NOT_FOUND,
+        Err(e) => {
+            error!("c_get_env: {:?}", e);
+            return FFI_ENV_NOT_FOUND;
+        }
+    };
+    unsafe {
+        *out_size_p = value.len();
+    }
+    if value.len() > buf_size {
+        return FFI_BUFFER_TOO_SHORT;
+    }
+    let out: &mut [u8] = unsafe { slice::from_raw_parts_mut(out_buf, buf_size) };
+    out[..value.len()].copy_from_slice(value.as_bytes());
+    FFI_OK
+}
+
+/*
+ * uint c_log(char* message);
+ */
+#[allow(unused)]
+#[no_mangle]
+extern "C" fn c_log(message: *mut c_char) -> c_uint {
+    let message = unsafe { CStr::from_ptr(message).to_string_lossy() };
+    match rtc_log(&message) {
+        Ok(_) => FFI_OK,
+        Err(e) => {
+            error!("c_log: {:?}", e);
+            FFI_LOG_ERROR
+        }
+    }
+}
+
+/*
+ * uint c_emit_result(void* buf, size_t buf_size);
+ */
+#[allow(unused)]
+#[no_mangle]
+extern "C" fn c_emit_result(in_buf: *mut c_uchar, buf_size: size_t) -> c_uint {
+    let chunk: &[u8] = unsafe { slice::from_raw_parts(in_buf, buf_size) };
+    match rtc_emit_result(chunk) {
+        Ok(_) => FFI_OK,
+        Err(e) => {
+            error!("c_emit_result: {:?}", e);
+            FFI_RESULT_ERROR
+        }
+    }
+}
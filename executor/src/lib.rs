@@ -28,9 +28,13 @@ extern crate log;
 mod builtin;
 mod context;
 mod mesapy;
+#[cfg(feature = "wamr")]
+mod wamr;
 
-pub use builtin::BuiltinFunctionExecutor;
+pub use builtin::{BuiltinFunction, BuiltinFunctionExecutor};
 pub use mesapy::MesaPy;
+#[cfg(feature = "wamr")]
+pub use wamr::WAMicroRuntime;
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
@@ -38,10 +42,15 @@ pub mod tests {
     use teaclave_test_utils::check_all_passed;
 
     pub fn run_tests() -> bool {
+        #[cfg(feature = "wamr")]
+        let wamr_passed = wamr::tests::run_tests();
+        #[cfg(not(feature = "wamr"))]
+        let wamr_passed = true;
         check_all_passed!(
             context::tests::run_tests(),
             mesapy::tests::run_tests(),
             builtin::tests::run_tests(),
+            wamr_passed,
         )
     }
 }
@@ -23,12 +23,62 @@ use crate::context::Context;
 
 use std::ffi::CString;
 
-use teaclave_types::{FunctionArguments, FunctionRuntime, TeaclaveExecutor};
+use teaclave_types::{FunctionArguments, FunctionRuntime, PythonBundle, TeaclaveExecutor};
 
 const MAXPYBUFLEN: usize = 20480;
 const MESAPY_ERROR_BUFFER_TOO_SHORT: i64 = -1i64;
 const MESAPY_EXEC_ERROR: i64 = -2i64;
 
+// Appended to the script of the function: while the entrypoint runs, lines
+// written to stdout and stderr, including the traceback of an uncaught
+// exception, are passed to `c_log` as they are printed, so that the log of
+// the task shows the progress of the function. Appended rather than
+// prepended to keep the line numbers of the script.
+const MESAPY_STDIO_WRAPPER: &str = r#"
+
+import sys as _teaclave_sys
+
+
+class _TeaclaveStdio(object):
+    def __init__(self, prefix):
+        self.prefix = prefix
+        self.buffer = ""
+
+    def write(self, data):
+        self.buffer += data
+        while "\n" in self.buffer:
+            line, self.buffer = self.buffer.split("\n", 1)
+            c_log(self.prefix + line)
+
+    def writelines(self, lines):
+        for line in lines:
+            self.write(line)
+
+    def flush(self):
+        if self.buffer:
+            c_log(self.prefix + self.buffer)
+            self.buffer = ""
+
+
+_teaclave_entrypoint = entrypoint
+
+
+def entrypoint(argv):
+    stdout, stderr = _teaclave_sys.stdout, _teaclave_sys.stderr
+    _teaclave_sys.stdout = _TeaclaveStdio("")
+    _teaclave_sys.stderr = _TeaclaveStdio("[stderr] ")
+    try:
+        return _teaclave_entrypoint(argv)
+    except:
+        import traceback
+        traceback.print_exc()
+        raise
+    finally:
+        _teaclave_sys.stdout.flush()
+        _teaclave_sys.stderr.flush()
+        _teaclave_sys.stdout, _teaclave_sys.stderr = stdout, stderr
+"#;
+
 extern "C" {
     fn mesapy_exec(
         input: *const u8,
@@ -39,15 +89,65 @@ extern "C" {
     ) -> i64;
 }
 
+// Prepended to the script of a function with a bundle: modules of the bundle
+// are imported from memory by a hook on `sys.meta_path`, before the script,
+// which is compiled on its own to keep its line numbers in tracebacks.
+const MESAPY_BUNDLE_IMPORTER: &str = r#"import binascii as _teaclave_binascii
+import sys as _teaclave_sys
+
+
+class _TeaclaveBundleImporter(object):
+    def __init__(self, modules):
+        self.modules = modules
+
+    def find_module(self, fullname, path=None):
+        if fullname in self.modules:
+            return self
+        return None
+
+    def load_module(self, fullname):
+        if fullname in _teaclave_sys.modules:
+            return _teaclave_sys.modules[fullname]
+        path, is_package, source = self.modules[fullname]
+        module = type(_teaclave_sys)(fullname)
+        module.__file__ = path
+        module.__loader__ = self
+        if is_package:
+            module.__path__ = [path]
+            module.__package__ = fullname
+        else:
+            module.__package__ = fullname.rpartition(".")[0]
+        _teaclave_sys.modules[fullname] = module
+        try:
+            code = compile(_teaclave_binascii.unhexlify(source), path, "exec")
+            exec code in module.__dict__
+        except:
+            del _teaclave_sys.modules[fullname]
+            raise
+        return module
+
+"#;
+
 #[derive(Default)]
 pub struct MesaPy;
 
 impl TeaclaveExecutor for MesaPy {
     fn execute(
+        &self,
+        name: String,
+        arguments: FunctionArguments,
+        payload: Vec<u8>,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        self.execute_with_bundle(name, arguments, payload, Vec::new(), runtime)
+    }
+
+    fn execute_with_bundle(
         &self,
         _name: String,
         arguments: FunctionArguments,
-        payload: String,
+        payload: Vec<u8>,
+        bundle: Vec<u8>,
         runtime: FunctionRuntime,
     ) -> anyhow::Result<String> {
         let py_argv = arguments.into_vec();
@@ -56,7 +156,12 @@ impl TeaclaveExecutor for MesaPy {
             .map(|arg| CString::new(arg.as_str()).unwrap())
             .collect();
 
-        let mut script_bytes = payload.into_bytes();
+        let mut script_bytes = if bundle.is_empty() {
+            payload
+        } else {
+            bundle_script(&PythonBundle::from_zip(&bundle)?, &payload)
+        };
+        script_bytes.extend_from_slice(MESAPY_STDIO_WRAPPER.as_bytes());
         script_bytes.push(0u8);
 
         let mut p_argv: Vec<_> = cstr_argv
@@ -93,6 +198,32 @@ impl TeaclaveExecutor for MesaPy {
     }
 }
 
+fn bundle_script(bundle: &PythonBundle, payload: &[u8]) -> Vec<u8> {
+    let mut script = MESAPY_BUNDLE_IMPORTER.to_string();
+    script.push_str("_teaclave_sys.meta_path.insert(0, _TeaclaveBundleImporter({\n");
+    for module in bundle.modules.iter() {
+        // Names and paths of modules are identifiers joined by dots and
+        // slashes, which need no escaping.
+        script.push_str(&format!(
+            "    \"{}\": (\"{}\", {}, \"{}\"),\n",
+            module.name,
+            module.path,
+            if module.is_package { "True" } else { "False" },
+            to_hex(&module.source)
+        ));
+    }
+    script.push_str("}))\n");
+    script.push_str(&format!(
+        "exec compile(_teaclave_binascii.unhexlify(\"{}\"), \"<function>\", \"exec\")\n",
+        to_hex(payload)
+    ));
+    script.into_bytes()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
@@ -102,7 +233,7 @@ pub mod tests {
     use teaclave_types::*;
 
     pub fn run_tests() -> bool {
-        run_tests!(test_mesapy,)
+        run_tests!(test_mesapy, test_mesapy_stdio, test_mesapy_bundle)
     }
 
     fn test_mesapy() {
@@ -169,8 +300,63 @@ def entrypoint(argv):
 
         let function = MesaPy::default();
         let summary = function
-            .execute("".to_string(), py_args, py_payload.to_string(), runtime)
+            .execute("".to_string(), py_args, py_payload.into(), runtime)
             .unwrap();
         assert_eq!(summary, "");
     }
+
+    fn test_mesapy_stdio() {
+        let py_payload = r#"
+import sys
+
+def entrypoint(argv):
+    print "epoch", 1
+    sys.stdout.write("epoch 2")
+    sys.stderr.write("loss not converging\n")
+    return "done"
+"#;
+        let log = FunctionLog::new();
+        let runtime = Box::new(
+            RawIoRuntime::new(StagedFiles::default(), StagedFiles::default()).with_log(log.clone()),
+        );
+
+        let summary = MesaPy::default()
+            .execute(
+                "".to_string(),
+                FunctionArguments::default(),
+                py_payload.into(),
+                runtime,
+            )
+            .unwrap();
+        assert_eq!(summary, "done");
+        let expected = "epoch 1\n[stderr] loss not converging\nepoch 2\n";
+        assert_eq!(log.contents(), (expected.as_bytes().to_vec(), false));
+    }
+
+    fn test_mesapy_bundle() {
+        let py_payload = r#"
+import greeting
+from stats import mean
+
+def entrypoint(argv):
+    assert mean([1, 2, 3, 6]) == 3.0
+    return greeting.greet(argv[1])
+"#;
+        let bundle = platform::fs::read("fixtures/functions/mesapy/bundle.zip").unwrap();
+        let runtime = Box::new(RawIoRuntime::new(
+            StagedFiles::default(),
+            StagedFiles::default(),
+        ));
+
+        let summary = MesaPy::default()
+            .execute_with_bundle(
+                "".to_string(),
+                FunctionArguments::from_map(hashmap!("name" => "Teaclave")),
+                py_payload.into(),
+                bundle,
+                runtime,
+            )
+            .unwrap();
+        assert_eq!(summary, "Hello, Teaclave!");
+    }
 }
@@ -0,0 +1,522 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Executor of WebAssembly modules on the WebAssembly Micro Runtime (WAMR),
+//! interpreting the module in the worker enclave. Modules reach the files of
+//! the task through functions imported from the `env` module, a WASI-like
+//! interface to the file handles of the runtime:
+//!
+//! ```c
+//! int teaclave_open_input(const char *file_id);
+//! int teaclave_create_output(const char *file_id);
+//! int teaclave_read_file(int handle, void *buf, int size);
+//! int teaclave_write_file(int handle, const void *buf, int size);
+//! int teaclave_close_file(int handle);
+//! int teaclave_get_env(const char *key, void *buf, int size);
+//! int teaclave_log(const char *message);
+//! int teaclave_emit_result(const void *buf, int size);
+//! ```
+//!
+//! The functions return -1 on errors. Reads and writes return the bytes read
+//! or written, and `teaclave_get_env` the length of the value, which is not
+//! copied if longer than the buffer.
+
+use std::prelude::v1::*;
+
+use crate::context::{
+    reset_thread_context, rtc_close_handle, rtc_create_output, rtc_emit_result, rtc_get_env,
+    rtc_log, rtc_open_input, rtc_read_handle, rtc_write_handle, set_thread_context, Context,
+};
+
+use std::ffi::{CStr, CString};
+use std::slice;
+#[cfg(not(feature = "mesalock_sgx"))]
+use std::sync::Mutex;
+#[cfg(feature = "mesalock_sgx")]
+use std::sync::SgxMutex as Mutex;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use lazy_static::lazy_static;
+use sgx_types::{c_char, c_int, c_void};
+use teaclave_types::{FunctionArguments, FunctionRuntime, TeaclaveExecutor};
+
+const ENTRYPOINT: &[u8] = b"entrypoint\0";
+const STACK_SIZE: u32 = 64 * 1024;
+const HEAP_SIZE: u32 = 64 * 1024;
+const ERROR_BUF_SIZE: usize = 128;
+const FFI_ERROR: c_int = -1;
+
+#[repr(C)]
+struct NativeSymbol {
+    symbol: *const c_char,
+    func_ptr: *const c_void,
+    signature: *const c_char,
+    attachment: *const c_void,
+}
+
+type WasmModule = *mut c_void;
+type WasmModuleInst = *mut c_void;
+type WasmExecEnv = *mut c_void;
+type WasmFunctionInst = *mut c_void;
+
+extern "C" {
+    fn wasm_runtime_init() -> bool;
+    fn wasm_runtime_register_natives(
+        module_name: *const c_char,
+        native_symbols: *mut NativeSymbol,
+        n_native_symbols: u32,
+    ) -> bool;
+    fn wasm_runtime_load(
+        buf: *mut u8,
+        size: u32,
+        error_buf: *mut c_char,
+        error_buf_size: u32,
+    ) -> WasmModule;
+    fn wasm_runtime_unload(module: WasmModule);
+    fn wasm_runtime_instantiate(
+        module: WasmModule,
+        stack_size: u32,
+        heap_size: u32,
+        error_buf: *mut c_char,
+        error_buf_size: u32,
+    ) -> WasmModuleInst;
+    fn wasm_runtime_deinstantiate(module_inst: WasmModuleInst);
+    fn wasm_runtime_lookup_function(
+        module_inst: WasmModuleInst,
+        name: *const c_char,
+        signature: *const c_char,
+    ) -> WasmFunctionInst;
+    fn wasm_runtime_create_exec_env(module_inst: WasmModuleInst, stack_size: u32) -> WasmExecEnv;
+    fn wasm_runtime_destroy_exec_env(exec_env: WasmExecEnv);
+    fn wasm_runtime_call_wasm(
+        exec_env: WasmExecEnv,
+        function: WasmFunctionInst,
+        argc: u32,
+        argv: *mut u32,
+    ) -> bool;
+    fn wasm_runtime_get_exception(module_inst: WasmModuleInst) -> *const c_char;
+    fn wasm_runtime_module_dup_data(
+        module_inst: WasmModuleInst,
+        src: *const c_char,
+        size: u32,
+    ) -> u32;
+}
+
+lazy_static! {
+    // WAMR is initialized, and the natives registered, once per enclave.
+    static ref INITIALIZED: Mutex<bool> = Mutex::new(false);
+}
+
+/// Runs the `entrypoint(argc, argv)` function exported by the module given
+/// as the payload, with the arguments of the task as `key value` pairs. The
+/// return value of the function is the result of the task.
+#[derive(Default)]
+pub struct WAMicroRuntime;
+
+impl TeaclaveExecutor for WAMicroRuntime {
+    fn execute(
+        &self,
+        _name: String,
+        arguments: FunctionArguments,
+        payload: Vec<u8>,
+        runtime: FunctionRuntime,
+    ) -> Result<String> {
+        init_runtime()?;
+        let argv = arguments
+            .into_vec()
+            .into_iter()
+            .map(CString::new)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        set_thread_context(Context::new(runtime))?;
+        let result = run_module(payload, &argv);
+        reset_thread_context()?;
+        result
+    }
+}
+
+fn init_runtime() -> Result<()> {
+    let mut initialized = INITIALIZED
+        .lock()
+        .map_err(|_| anyhow!("Cannot lock WAMR"))?;
+    if *initialized {
+        return Ok(());
+    }
+    ensure!(unsafe { wasm_runtime_init() }, "Cannot initialize WAMR");
+    // WAMR keeps, and sorts, the symbols, so they are never freed.
+    let symbols = Box::leak(native_symbols().into_boxed_slice());
+    let registered = unsafe {
+        wasm_runtime_register_natives(
+            b"env\0".as_ptr() as *const c_char,
+            symbols.as_mut_ptr(),
+            symbols.len() as u32,
+        )
+    };
+    ensure!(registered, "Cannot register the natives of WAMR");
+    *initialized = true;
+    Ok(())
+}
+
+// Signatures of WAMR: `$` is a string, `*` a buffer whose size is the
+// following `~`, validated against the memory of the module.
+fn native_symbols() -> Vec<NativeSymbol> {
+    let symbol =
+        |name: &'static [u8], func_ptr: *const c_void, signature: &'static [u8]| NativeSymbol {
+            symbol: name.as_ptr() as *const c_char,
+            func_ptr,
+            signature: signature.as_ptr() as *const c_char,
+            attachment: std::ptr::null(),
+        };
+    vec![
+        symbol(
+            b"teaclave_open_input\0",
+            wasm_open_input as *const c_void,
+            b"($)i\0",
+        ),
+        symbol(
+            b"teaclave_create_output\0",
+            wasm_create_output as *const c_void,
+            b"($)i\0",
+        ),
+        symbol(
+            b"teaclave_read_file\0",
+            wasm_read_file as *const c_void,
+            b"(i*~)i\0",
+        ),
+        symbol(
+            b"teaclave_write_file\0",
+            wasm_write_file as *const c_void,
+            b"(i*~)i\0",
+        ),
+        symbol(
+            b"teaclave_close_file\0",
+            wasm_close_file as *const c_void,
+            b"(i)i\0",
+        ),
+        symbol(
+            b"teaclave_get_env\0",
+            wasm_get_env as *const c_void,
+            b"($*~)i\0",
+        ),
+        symbol(b"teaclave_log\0", wasm_log as *const c_void, b"($)i\0"),
+        symbol(
+            b"teaclave_emit_result\0",
+            wasm_emit_result as *const c_void,
+            b"(*~)i\0",
+        ),
+    ]
+}
+
+// Handles of WAMR, released when dropped in the reverse order of their
+// creation.
+struct Module(WasmModule);
+struct ModuleInstance(WasmModuleInst);
+struct ExecEnv(WasmExecEnv);
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        unsafe { wasm_runtime_unload(self.0) }
+    }
+}
+
+impl Drop for ModuleInstance {
+    fn drop(&mut self) {
+        unsafe { wasm_runtime_deinstantiate(self.0) }
+    }
+}
+
+impl Drop for ExecEnv {
+    fn drop(&mut self) {
+        unsafe { wasm_runtime_destroy_exec_env(self.0) }
+    }
+}
+
+// The payload is kept until the module is unloaded, as WAMR refers to it.
+fn run_module(mut payload: Vec<u8>, argv: &[CString]) -> Result<String> {
+    let mut error_buf = [0u8; ERROR_BUF_SIZE];
+    let module = unsafe {
+        wasm_runtime_load(
+            payload.as_mut_ptr(),
+            payload.len() as u32,
+            error_buf.as_mut_ptr() as *mut c_char,
+            ERROR_BUF_SIZE as u32,
+        )
+    };
+    if module.is_null() {
+        bail!("Cannot load WebAssembly module: {}", c_message(&error_buf));
+    }
+    let module = Module(module);
+
+    let instance = unsafe {
+        wasm_runtime_instantiate(
+            module.0,
+            STACK_SIZE,
+            HEAP_SIZE,
+            error_buf.as_mut_ptr() as *mut c_char,
+            ERROR_BUF_SIZE as u32,
+        )
+    };
+    if instance.is_null() {
+        bail!(
+            "Cannot instantiate WebAssembly module: {}",
+            c_message(&error_buf)
+        );
+    }
+    let instance = ModuleInstance(instance);
+
+    let function = unsafe {
+        wasm_runtime_lookup_function(
+            instance.0,
+            ENTRYPOINT.as_ptr() as *const c_char,
+            std::ptr::null(),
+        )
+    };
+    ensure!(
+        !function.is_null(),
+        "WebAssembly module exports no entrypoint function"
+    );
+    let exec_env = unsafe { wasm_runtime_create_exec_env(instance.0, STACK_SIZE) };
+    ensure!(
+        !exec_env.is_null(),
+        "Cannot create WAMR execution environment"
+    );
+    let exec_env = ExecEnv(exec_env);
+
+    // The arguments, and the array of their addresses, are copied into the
+    // heap of the module, which is freed with the instance.
+    let mut app_argv = Vec::with_capacity(argv.len() * 4);
+    for arg in argv {
+        let offset = dup_data(&instance, arg.as_bytes_with_nul())?;
+        app_argv.extend_from_slice(&offset.to_le_bytes());
+    }
+    let app_argv_offset = if argv.is_empty() {
+        0
+    } else {
+        dup_data(&instance, &app_argv)?
+    };
+    let mut call_argv = [argv.len() as u32, app_argv_offset];
+    let succeeded =
+        unsafe { wasm_runtime_call_wasm(exec_env.0, function, 2, call_argv.as_mut_ptr()) };
+    if !succeeded {
+        let exception = unsafe { wasm_runtime_get_exception(instance.0) };
+        let exception = if exception.is_null() {
+            "unknown exception".to_string()
+        } else {
+            unsafe { CStr::from_ptr(exception).to_string_lossy().into_owned() }
+        };
+        bail!("WebAssembly function failed: {}", exception);
+    }
+    drop(exec_env);
+    drop(instance);
+    drop(module);
+    drop(payload);
+    Ok((call_argv[0] as i32).to_string())
+}
+
+fn dup_data(instance: &ModuleInstance, data: &[u8]) -> Result<u32> {
+    let offset = unsafe {
+        wasm_runtime_module_dup_data(
+            instance.0,
+            data.as_ptr() as *const c_char,
+            data.len() as u32,
+        )
+    };
+    ensure!(offset != 0, "WebAssembly module heap exhausted");
+    Ok(offset)
+}
+
+fn c_message(buf: &[u8]) -> String {
+    let len = buf
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or_else(|| buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+// Natives imported by the modules. WAMR passes the execution environment
+// first, and strings and buffers as native pointers checked to be in the
+// memory of the module.
+
+extern "C" fn wasm_open_input(_exec_env: WasmExecEnv, fid: *const c_char) -> c_int {
+    let fid = unsafe { CStr::from_ptr(fid).to_string_lossy() };
+    rtc_open_input(&fid).unwrap_or_else(|e| {
+        error!("teaclave_open_input: {:?}", e);
+        FFI_ERROR
+    })
+}
+
+extern "C" fn wasm_create_output(_exec_env: WasmExecEnv, fid: *const c_char) -> c_int {
+    let fid = unsafe { CStr::from_ptr(fid).to_string_lossy() };
+    rtc_create_output(&fid).unwrap_or_else(|e| {
+        error!("teaclave_create_output: {:?}", e);
+        FFI_ERROR
+    })
+}
+
+extern "C" fn wasm_read_file(
+    _exec_env: WasmExecEnv,
+    handle: c_int,
+    buf: *mut u8,
+    size: c_int,
+) -> c_int {
+    let buf = unsafe { slice::from_raw_parts_mut(buf, size as usize) };
+    match rtc_read_handle(handle, buf) {
+        Ok(read) => read as c_int,
+        Err(e) => {
+            error!("teaclave_read_file: {:?}", e);
+            FFI_ERROR
+        }
+    }
+}
+
+extern "C" fn wasm_write_file(
+    _exec_env: WasmExecEnv,
+    handle: c_int,
+    buf: *const u8,
+    size: c_int,
+) -> c_int {
+    let buf = unsafe { slice::from_raw_parts(buf, size as usize) };
+    match rtc_write_handle(handle, buf) {
+        Ok(written) => written as c_int,
+        Err(e) => {
+            error!("teaclave_write_file: {:?}", e);
+            FFI_ERROR
+        }
+    }
+}
+
+extern "C" fn wasm_close_file(_exec_env: WasmExecEnv, handle: c_int) -> c_int {
+    match rtc_close_handle(handle) {
+        Ok(_) => 0,
+        Err(e) => {
+            error!("teaclave_close_file: {:?}", e);
+            FFI_ERROR
+        }
+    }
+}
+
+extern "C" fn wasm_get_env(
+    _exec_env: WasmExecEnv,
+    key: *const c_char,
+    buf: *mut u8,
+    size: c_int,
+) -> c_int {
+    let key = unsafe { CStr::from_ptr(key).to_string_lossy() };
+    let value = match rtc_get_env(&key) {
+        Ok(Some(value)) => value,
+        _ => return FFI_ERROR,
+    };
+    let buf = unsafe { slice::from_raw_parts_mut(buf, size as usize) };
+    if value.len() <= buf.len() {
+        buf[..value.len()].copy_from_slice(value.as_bytes());
+    }
+    value.len() as c_int
+}
+
+extern "C" fn wasm_log(_exec_env: WasmExecEnv, message: *const c_char) -> c_int {
+    let message = unsafe { CStr::from_ptr(message).to_string_lossy() };
+    match rtc_log(&message) {
+        Ok(_) => 0,
+        Err(_) => FFI_ERROR,
+    }
+}
+
+extern "C" fn wasm_emit_result(_exec_env: WasmExecEnv, buf: *const u8, size: c_int) -> c_int {
+    let chunk = unsafe { slice::from_raw_parts(buf, size as usize) };
+    match rtc_emit_result(chunk) {
+        Ok(_) => 0,
+        Err(_) => FFI_ERROR,
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
+    // (module
+    //   (memory 1)
+    //   (func (export "entrypoint") (param i32 i32) (result i32)
+    //     local.get 0))
+    const ARGC_MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic and version
+        0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // (i32, i32) -> i32
+        0x03, 0x02, 0x01, 0x00, // one function of the type
+        0x05, 0x03, 0x01, 0x00, 0x01, // one page of memory
+        0x07, 0x0e, 0x01, 0x0a, b'e', b'n', b't', b'r', b'y', b'p', b'o', b'i', b'n', b't', 0x00,
+        0x00, // export "entrypoint"
+        0x0a, 0x06, 0x01, 0x04, 0x00, 0x20, 0x00, 0x0b, // local.get 0
+    ];
+
+    pub fn run_tests() -> bool {
+        run_tests!(test_wamr_entrypoint, test_wamr_invalid_module)
+    }
+
+    fn runtime() -> FunctionRuntime {
+        Box::new(RawIoRuntime::new(
+            StagedFiles::default(),
+            StagedFiles::default(),
+        ))
+    }
+
+    fn test_wamr_entrypoint() {
+        let arguments = FunctionArguments::from_map(hashmap!(
+            "rounds" => "2",
+            "name" => "teaclave"
+        ));
+        let summary = WAMicroRuntime::default()
+            .execute(String::new(), arguments, ARGC_MODULE.to_vec(), runtime())
+            .unwrap();
+        // The arguments are passed as key value pairs.
+        assert_eq!(summary, "4");
+
+        let summary = WAMicroRuntime::default()
+            .execute(
+                String::new(),
+                FunctionArguments::default(),
+                ARGC_MODULE.to_vec(),
+                runtime(),
+            )
+            .unwrap();
+        assert_eq!(summary, "0");
+    }
+
+    fn test_wamr_invalid_module() {
+        let executor = WAMicroRuntime::default();
+        let result = executor.execute(
+            String::new(),
+            FunctionArguments::default(),
+            b"def entrypoint(argv):\n\treturn".to_vec(),
+            runtime(),
+        );
+        assert!(result.is_err());
+
+        // Exported under another name.
+        let mut module = ARGC_MODULE.to_vec();
+        let name = module.iter().position(|&b| b == b'e').unwrap();
+        module[name] = b'E';
+        let result = executor.execute(
+            String::new(),
+            FunctionArguments::default(),
+            module,
+            runtime(),
+        );
+        assert!(result.is_err());
+    }
+}
@@ -22,7 +22,10 @@ use tokio_util::codec;
 use url::Url;
 
 use std::path::{Component, Path, PathBuf};
-use teaclave_types::{FileAgentRequest, HandleFileCommand, HandleFileInfo};
+use teaclave_types::{
+    FileAgentRequest, HandleFileCommand, HandleFileInfo, TaskBudgetError, FA_ERR_GENERAL,
+    FA_ERR_TIMEOUT, FA_OK,
+};
 
 async fn download_remote_input_to_file(
     presigned_url: Url,
@@ -193,37 +196,46 @@ async fn handle_upload(info: HandleFileInfo, fusion_base: impl AsRef<Path>) -> a
 
 fn handle_file_request(bytes: &[u8]) -> anyhow::Result<()> {
     let req: FileAgentRequest = serde_json::from_slice(bytes)?;
+    let timeout = req.timeout;
     let results = tokio::runtime::Builder::new()
         .threaded_scheduler()
         .enable_all()
         .build()?
         .block_on(async {
             let fusion_base = req.fusion_base.clone();
-            match req.cmd {
-                HandleFileCommand::Download => {
-                    let futures: Vec<_> = req
-                        .info
-                        .into_iter()
-                        .map(|info| {
-                            let fusion_base = fusion_base.clone();
-                            tokio::spawn(async { handle_download(info, fusion_base).await })
-                        })
-                        .collect();
-                    join_all(futures).await
-                }
-                HandleFileCommand::Upload => {
-                    let futures: Vec<_> = req
-                        .info
-                        .into_iter()
-                        .map(|info| {
-                            let fusion_base = fusion_base.clone();
-                            tokio::spawn(async { handle_upload(info, fusion_base).await })
-                        })
-                        .collect();
-                    join_all(futures).await
+            let handle_files = async move {
+                match req.cmd {
+                    HandleFileCommand::Download => {
+                        let futures: Vec<_> = req
+                            .info
+                            .into_iter()
+                            .map(|info| {
+                                let fusion_base = fusion_base.clone();
+                                tokio::spawn(async { handle_download(info, fusion_base).await })
+                            })
+                            .collect();
+                        join_all(futures).await
+                    }
+                    HandleFileCommand::Upload => {
+                        let futures: Vec<_> = req
+                            .info
+                            .into_iter()
+                            .map(|info| {
+                                let fusion_base = fusion_base.clone();
+                                tokio::spawn(async { handle_upload(info, fusion_base).await })
+                            })
+                            .collect();
+                        join_all(futures).await
+                    }
                 }
+            };
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, handle_files)
+                    .await
+                    .map_err(|_| TaskBudgetError::StagingTimeout),
+                None => Ok(handle_files.await),
             }
-        });
+        })?;
 
     let (task_results, errs): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
 
@@ -243,8 +255,9 @@ fn handle_file_request(bytes: &[u8]) -> anyhow::Result<()> {
 pub extern "C" fn ocall_handle_file_request(in_buf: *const u8, in_len: u32) -> u32 {
     let input_buf: &[u8] = unsafe { std::slice::from_raw_parts(in_buf, in_len as usize) };
     match handle_file_request(input_buf) {
-        Ok(_) => 0,
-        Err(_) => 1,
+        Ok(_) => FA_OK,
+        Err(e) if e.is::<TaskBudgetError>() => FA_ERR_TIMEOUT,
+        Err(_) => FA_ERR_GENERAL,
     }
 }
 
@@ -0,0 +1,357 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::convert::TryFrom;
+use std::format;
+use std::io::{self, BufRead, BufReader, Write};
+
+use teaclave_types::platform::rand::random_u64;
+use teaclave_types::{FunctionArguments, FunctionRuntime, PrivacyCost};
+
+use anyhow::{anyhow, bail, ensure, Result};
+
+const IN_DATA: &str = "input_data";
+const OUT_RESULT: &str = "output_data";
+
+const DEFAULT_BINS: usize = 10;
+const MAX_BINS: usize = 4096;
+
+/// Releases a differentially private count, sum, mean or histogram of a
+/// column of CSV rows. The privacy cost is given by the `epsilon` and
+/// `delta` arguments: noise is drawn from the Laplace distribution for
+/// pure epsilon-DP, or from the Gaussian distribution if delta is positive.
+/// Values are clamped to the `lower` and `upper` bounds, which bound the
+/// contribution of a row.
+///
+/// Input files with privacy budgets can only be read by this function, and
+/// the management service spends the costs of its tasks from them.
+#[derive(Default)]
+pub struct DpAggregate;
+
+#[derive(serde::Deserialize)]
+struct DpAggregateArguments {
+    aggregate: Aggregate,
+    #[serde(default)]
+    column: usize,
+    #[serde(default)]
+    has_header: bool,
+    lower: Option<f64>,
+    upper: Option<f64>,
+    bins: Option<usize>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Aggregate {
+    Count,
+    Sum,
+    Mean,
+    Histogram,
+}
+
+impl Aggregate {
+    fn name(self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Mean => "mean",
+            Aggregate::Histogram => "histogram",
+        }
+    }
+}
+
+impl TryFrom<FunctionArguments> for DpAggregateArguments {
+    type Error = anyhow::Error;
+
+    fn try_from(arguments: FunctionArguments) -> Result<Self, Self::Error> {
+        use anyhow::Context;
+        serde_json::from_str(&arguments.into_string()).context("Cannot deserialize arguments")
+    }
+}
+
+impl DpAggregateArguments {
+    fn bounds(&self) -> Result<(f64, f64)> {
+        match (self.lower, self.upper) {
+            (Some(lower), Some(upper)) if lower.is_finite() && upper.is_finite() => {
+                ensure!(lower < upper, "Lower bound must be below the upper bound");
+                Ok((lower, upper))
+            }
+            _ => bail!("The {} needs lower and upper bounds", self.aggregate.name()),
+        }
+    }
+}
+
+impl DpAggregate {
+    pub const NAME: &'static str = "builtin-dp-aggregate";
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn run(
+        &self,
+        arguments: FunctionArguments,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        let cost = PrivacyCost::from_arguments(&arguments)?;
+        let args = DpAggregateArguments::try_from(arguments)?;
+        let values = read_column(runtime.open_input(IN_DATA)?, args.column, args.has_header)?;
+        let noise = Noise::new(cost);
+
+        let mut output = runtime.create_output(OUT_RESULT)?;
+        match args.aggregate {
+            Aggregate::Count => {
+                writeln!(&mut output, "{}", noisy_count(values.len(), &noise))?;
+            }
+            Aggregate::Sum => {
+                let (lower, upper) = args.bounds()?;
+                writeln!(&mut output, "{}", noisy_sum(&values, lower, upper, &noise)?)?;
+            }
+            Aggregate::Mean => {
+                let (lower, upper) = args.bounds()?;
+                // Half of the budget for each of the sum and the count
+                let half = Noise::new(PrivacyCost::new(cost.epsilon / 2.0, cost.delta / 2.0));
+                let sum = noisy_sum(&values, lower, upper, &half)?;
+                let count = noisy_count(values.len(), &half).max(1.0);
+                let mean = (sum / count).max(lower).min(upper);
+                writeln!(&mut output, "{}", mean)?;
+            }
+            Aggregate::Histogram => {
+                let (lower, upper) = args.bounds()?;
+                let bins = args.bins.unwrap_or(DEFAULT_BINS);
+                ensure!(bins > 0 && bins <= MAX_BINS, "Bins not in 1..={}", MAX_BINS);
+                let width = (upper - lower) / bins as f64;
+                for (i, count) in histogram(&values, lower, upper, bins)?.iter().enumerate() {
+                    let count = (*count as f64 + noise.sample(1.0)).round().max(0.0);
+                    let bin_lower = lower + width * i as f64;
+                    writeln!(&mut output, "{},{},{}", bin_lower, bin_lower + width, count)?;
+                }
+            }
+        }
+
+        let summary = format!(
+            "Released a noisy {} with epsilon {} and delta {}.",
+            args.aggregate.name(),
+            cost.epsilon,
+            cost.delta
+        );
+        Ok(summary)
+    }
+}
+
+// Fields of the column, which are parsed by the aggregates of values only,
+// so that any rows can be counted.
+fn read_column(input: impl io::Read, column: usize, has_header: bool) -> Result<Vec<String>> {
+    let mut values = Vec::new();
+    let reader = BufReader::new(input);
+    for line in reader.lines().skip(has_header as usize) {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = line
+            .split(',')
+            .nth(column)
+            .ok_or_else(|| anyhow!("Rows without column {}", column))?;
+        values.push(value.trim().to_string());
+    }
+    Ok(values)
+}
+
+// Values are not shown in errors, which are returned to the task creator.
+fn parse_values(values: &[String], lower: f64, upper: f64) -> Result<Vec<f64>> {
+    values
+        .iter()
+        .map(|value| {
+            let value: f64 = value
+                .parse()
+                .map_err(|_| anyhow!("Invalid number in column"))?;
+            ensure!(!value.is_nan(), "Invalid number in column");
+            Ok(value.max(lower).min(upper))
+        })
+        .collect()
+}
+
+// Counts are rounded and not negative, which is post-processing of the
+// noisy value and spends no budget.
+fn noisy_count(count: usize, noise: &Noise) -> f64 {
+    (count as f64 + noise.sample(1.0)).round().max(0.0)
+}
+
+fn noisy_sum(values: &[String], lower: f64, upper: f64, noise: &Noise) -> Result<f64> {
+    let sum: f64 = parse_values(values, lower, upper)?.iter().sum();
+    // A row changes the sum by its clamped value at most.
+    let sensitivity = lower.abs().max(upper.abs());
+    Ok(sum + noise.sample(sensitivity))
+}
+
+// Counts of the values in bins of equal width, of which the last one
+// includes the upper bound.
+fn histogram(values: &[String], lower: f64, upper: f64, bins: usize) -> Result<Vec<u64>> {
+    let mut counts = vec![0; bins];
+    let width = (upper - lower) / bins as f64;
+    for value in parse_values(values, lower, upper)? {
+        let bin = ((value - lower) / width) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+    Ok(counts)
+}
+
+// Noise of the Laplace mechanism, or of the Gaussian mechanism if delta is
+// positive, scaled to the sensitivity of a query.
+struct Noise {
+    cost: PrivacyCost,
+}
+
+impl Noise {
+    fn new(cost: PrivacyCost) -> Self {
+        Self { cost }
+    }
+
+    fn sample(&self, sensitivity: f64) -> f64 {
+        if self.cost.delta > 0.0 {
+            let sigma =
+                sensitivity * (2.0 * (1.25 / self.cost.delta).ln()).sqrt() / self.cost.epsilon;
+            let (u1, u2) = (uniform(), uniform());
+            sigma * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+        } else {
+            let scale = sensitivity / self.cost.epsilon;
+            let u = uniform() - 0.5;
+            -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+        }
+    }
+}
+
+// Uniform in (0, 1), from 53 random bits.
+fn uniform() -> f64 {
+    ((random_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::untrusted::fs;
+    use teaclave_crypto::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(test_dp_aggregate, test_dp_noise, test_dp_histogram)
+    }
+
+    fn run(arguments: serde_json::Value, plain_output: &str) -> Result<String> {
+        let arguments = FunctionArguments::from_json(arguments)?;
+        let plain_input = "fixtures/functions/dp_aggregate/input.csv";
+
+        let input_files = StagedFiles::new(hashmap!(
+            IN_DATA =>
+            StagedFileInfo::new(plain_input, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let output_files = StagedFiles::new(hashmap!(
+            OUT_RESULT =>
+            StagedFileInfo::new(plain_output, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let runtime = Box::new(RawIoRuntime::new(input_files, output_files));
+        DpAggregate::new().run(arguments, runtime)
+    }
+
+    fn test_dp_aggregate() {
+        let plain_output = "fixtures/functions/dp_aggregate/result.txt.out";
+
+        // The noise of a large epsilon is negligible.
+        let summary = run(
+            json!({"aggregate": "count", "epsilon": 1e9, "has_header": true}),
+            plain_output,
+        )
+        .unwrap();
+        assert_eq!(
+            summary,
+            "Released a noisy count with epsilon 1000000000 and delta 0."
+        );
+        let result = fs::read_to_string(&plain_output).unwrap();
+        assert_eq!(result, "10\n");
+
+        run(
+            json!({"aggregate": "mean", "epsilon": "1e9", "column": 1, "has_header": true,
+                   "lower": 0, "upper": 100}),
+            plain_output,
+        )
+        .unwrap();
+        let result = fs::read_to_string(&plain_output).unwrap();
+        let mean: f64 = result.trim().parse().unwrap();
+        assert!((mean - 41.5).abs() < 1e-3);
+
+        // Values are clamped.
+        run(
+            json!({"aggregate": "sum", "epsilon": 1e9, "delta": 1e-5, "column": 1,
+                   "has_header": true, "lower": 0, "upper": 50}),
+            plain_output,
+        )
+        .unwrap();
+        let result = fs::read_to_string(&plain_output).unwrap();
+        let sum: f64 = result.trim().parse().unwrap();
+        assert!((sum - 395.0).abs() < 1e-3);
+
+        assert!(run(json!({"aggregate": "count"}), plain_output).is_err());
+        assert!(run(json!({"aggregate": "sum", "epsilon": 1}), plain_output).is_err());
+        assert!(run(
+            json!({"aggregate": "median", "epsilon": 1, "lower": 0, "upper": 1}),
+            plain_output
+        )
+        .is_err());
+        // The header is not a number.
+        assert!(run(
+            json!({"aggregate": "sum", "epsilon": 1, "column": 1, "lower": 0, "upper": 1}),
+            plain_output
+        )
+        .is_err());
+    }
+
+    fn test_dp_noise() {
+        let samples = 20000;
+        for cost in [PrivacyCost::new(1.0, 0.0), PrivacyCost::new(1.0, 1e-5)].iter() {
+            let noise = Noise::new(*cost);
+            let draws: Vec<f64> = (0..samples).map(|_| noise.sample(1.0)).collect();
+            let mean = draws.iter().sum::<f64>() / samples as f64;
+            let variance = draws.iter().map(|d| d * d).sum::<f64>() / samples as f64;
+            let expected = if cost.delta > 0.0 {
+                2.0 * (1.25 / cost.delta).ln()
+            } else {
+                2.0
+            };
+            assert!(mean.abs() < 0.2);
+            assert!((variance / expected - 1.0).abs() < 0.1);
+        }
+    }
+
+    fn test_dp_histogram() {
+        let values: Vec<String> = ["-5", "0", "9.9", "10", "55", "100", "120"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        let counts = histogram(&values, 0.0, 100.0, 10).unwrap();
+        assert_eq!(counts, vec![3, 1, 0, 0, 0, 1, 0, 0, 0, 2]);
+        assert!(histogram(&["abc".to_string()], 0.0, 1.0, 1).is_err());
+    }
+}
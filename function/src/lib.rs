@@ -22,6 +22,7 @@ extern crate sgx_tstd as std;
 #[cfg(feature = "mesalock_sgx")]
 use std::prelude::v1::*;
 
+mod dp_aggregate;
 mod echo;
 mod face_detection;
 mod gbdt_predict;
@@ -29,12 +30,18 @@ mod gbdt_train;
 mod logistic_regression_predict;
 mod logistic_regression_train;
 mod online_decrypt;
+mod onnx_inference;
 mod ordered_set_intersect;
 mod password_check;
 mod principal_components_analysis;
 mod private_join_and_compute;
+mod psi_cardinality;
+mod psi_join;
 mod rsa_sign;
+mod secure_aggregation;
+mod sql_query;
 
+pub use dp_aggregate::DpAggregate;
 pub use echo::Echo;
 pub use face_detection::FaceDetection;
 pub use gbdt_predict::GbdtPredict;
@@ -42,11 +49,16 @@ pub use gbdt_train::GbdtTrain;
 pub use logistic_regression_predict::LogisticRegressionPredict;
 pub use logistic_regression_train::LogisticRegressionTrain;
 pub use online_decrypt::OnlineDecrypt;
+pub use onnx_inference::OnnxInference;
 pub use ordered_set_intersect::OrderedSetIntersect;
 pub use password_check::PasswordCheck;
 pub use principal_components_analysis::PrincipalComponentsAnalysis;
 pub use private_join_and_compute::PrivateJoinAndCompute;
+pub use psi_cardinality::PsiCardinality;
+pub use psi_join::PsiJoin;
 pub use rsa_sign::RsaSign;
+pub use secure_aggregation::SecureAggregation;
+pub use sql_query::SqlQuery;
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
@@ -55,6 +67,7 @@ pub mod tests {
 
     pub fn run_tests() -> bool {
         check_all_passed!(
+            dp_aggregate::tests::run_tests(),
             echo::tests::run_tests(),
             face_detection::tests::run_tests(),
             gbdt_predict::tests::run_tests(),
@@ -63,10 +76,15 @@ pub mod tests {
             logistic_regression_train::tests::run_tests(),
             password_check::tests::run_tests(),
             online_decrypt::tests::run_tests(),
+            onnx_inference::tests::run_tests(),
             ordered_set_intersect::tests::run_tests(),
             principal_components_analysis::tests::run_tests(),
             private_join_and_compute::tests::run_tests(),
+            psi_cardinality::tests::run_tests(),
+            psi_join::tests::run_tests(),
             rsa_sign::tests::run_tests(),
+            secure_aggregation::tests::run_tests(),
+            sql_query::tests::run_tests(),
         )
     }
 }
@@ -0,0 +1,756 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::collections::HashMap;
+use std::format;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use teaclave_types::{FunctionArguments, FunctionRuntime};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use prost::Message;
+
+const IN_MODEL: &str = "model_file";
+const IN_DATA: &str = "data_file";
+const OUT_RESULT: &str = "result_file";
+
+// Elements of a tensor, bounding the memory used by a model.
+const MAX_TENSOR_LEN: usize = 16 * 1024 * 1024;
+
+/// Runs an ONNX model on the rows of a CSV dataset, one prediction per row.
+/// The model is interpreted in the enclave, supporting the operators of
+/// common models on tabular data (e.g., `Gemm`, `MatMul`, `Relu`, `Softmax`)
+/// over float tensors.
+#[derive(Default)]
+pub struct OnnxInference;
+
+#[derive(serde::Deserialize)]
+struct OnnxInferenceArguments {
+    /// Output of the graph to write, the first one by default.
+    output_name: Option<String>,
+}
+
+impl std::convert::TryFrom<FunctionArguments> for OnnxInferenceArguments {
+    type Error = anyhow::Error;
+
+    fn try_from(arguments: FunctionArguments) -> Result<Self, Self::Error> {
+        use anyhow::Context;
+        serde_json::from_str(&arguments.into_string()).context("Cannot deserialize arguments")
+    }
+}
+
+impl OnnxInference {
+    pub const NAME: &'static str = "builtin-onnx-inference";
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn run(
+        &self,
+        arguments: FunctionArguments,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        use std::convert::TryFrom;
+        let args = OnnxInferenceArguments::try_from(arguments)?;
+
+        let mut model = Vec::new();
+        runtime.open_input(IN_MODEL)?.read_to_end(&mut model)?;
+        let model = Model::from_bytes(&model)?;
+
+        let data = parse_data(runtime.open_input(IN_DATA)?)?;
+        let output = model.run(data, args.output_name.as_deref())?;
+
+        let mut of_result = runtime.create_output(OUT_RESULT)?;
+        let rows = output.rows();
+        let width = output.len() / rows.max(1);
+        for row in output.data.chunks(width.max(1)) {
+            let values: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            writeln!(&mut of_result, "{}", values.join(","))?;
+        }
+
+        let summary = format!("Inference result has {} lines of data.", rows);
+        Ok(summary)
+    }
+}
+
+fn parse_data(input: impl io::Read) -> Result<Tensor> {
+    let mut data = Vec::new();
+    let mut width = None;
+    let reader = BufReader::new(input);
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let row = line
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        ensure!(
+            *width.get_or_insert(row.len()) == row.len(),
+            "Rows of different lengths"
+        );
+        data.extend(row);
+    }
+    let width = width.ok_or_else(|| anyhow!("Empty dataset"))?;
+    Tensor::new(vec![data.len() / width, width], data)
+}
+
+// Messages of the ONNX format (onnx.proto) read by the interpreter; other
+// fields are skipped.
+
+#[derive(Clone, PartialEq, Message)]
+struct ModelProto {
+    #[prost(message, optional, tag = "7")]
+    graph: Option<GraphProto>,
+    #[prost(message, repeated, tag = "8")]
+    opset_import: Vec<OperatorSetIdProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OperatorSetIdProto {
+    #[prost(string, tag = "1")]
+    domain: String,
+    #[prost(int64, tag = "2")]
+    version: i64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GraphProto {
+    #[prost(message, repeated, tag = "1")]
+    node: Vec<NodeProto>,
+    #[prost(message, repeated, tag = "5")]
+    initializer: Vec<TensorProto>,
+    #[prost(message, repeated, tag = "11")]
+    input: Vec<ValueInfoProto>,
+    #[prost(message, repeated, tag = "12")]
+    output: Vec<ValueInfoProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct NodeProto {
+    #[prost(string, repeated, tag = "1")]
+    input: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    output: Vec<String>,
+    #[prost(string, tag = "4")]
+    op_type: String,
+    #[prost(message, repeated, tag = "5")]
+    attribute: Vec<AttributeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct AttributeProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(float, tag = "2")]
+    f: f32,
+    #[prost(int64, tag = "3")]
+    i: i64,
+    #[prost(message, optional, tag = "5")]
+    t: Option<TensorProto>,
+    #[prost(int64, repeated, tag = "8")]
+    ints: Vec<i64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorProto {
+    #[prost(int64, repeated, tag = "1")]
+    dims: Vec<i64>,
+    #[prost(int32, tag = "2")]
+    data_type: i32,
+    #[prost(float, repeated, tag = "4")]
+    float_data: Vec<f32>,
+    #[prost(int32, repeated, tag = "5")]
+    int32_data: Vec<i32>,
+    #[prost(int64, repeated, tag = "7")]
+    int64_data: Vec<i64>,
+    #[prost(string, tag = "8")]
+    name: String,
+    #[prost(bytes, tag = "9")]
+    raw_data: Vec<u8>,
+    #[prost(double, repeated, tag = "10")]
+    double_data: Vec<f64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ValueInfoProto {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+const DATA_TYPE_FLOAT: i32 = 1;
+const DATA_TYPE_INT32: i32 = 6;
+const DATA_TYPE_INT64: i32 = 7;
+const DATA_TYPE_DOUBLE: i32 = 11;
+
+/// A tensor of floats in row-major order. Integer tensors, e.g., shapes,
+/// are converted.
+#[derive(Debug, Clone, PartialEq)]
+struct Tensor {
+    shape: Vec<usize>,
+    data: Vec<f32>,
+}
+
+impl Tensor {
+    fn new(shape: Vec<usize>, data: Vec<f32>) -> Result<Self> {
+        ensure!(
+            shape_len(&shape)? == data.len(),
+            "Tensor of shape {:?} has {} elements",
+            shape,
+            data.len()
+        );
+        Ok(Self { shape, data })
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    // Rows of the tensor along its first dimension, one for scalars.
+    fn rows(&self) -> usize {
+        self.shape.first().copied().unwrap_or(1)
+    }
+
+    fn from_proto(proto: &TensorProto) -> Result<Self> {
+        let shape = proto
+            .dims
+            .iter()
+            .map(|&d| dim(d))
+            .collect::<Result<Vec<_>>>()?;
+        let raw = &proto.raw_data;
+        let data: Vec<f32> = match proto.data_type {
+            DATA_TYPE_FLOAT if raw.is_empty() => proto.float_data.clone(),
+            DATA_TYPE_FLOAT => raw
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            DATA_TYPE_DOUBLE if raw.is_empty() => {
+                proto.double_data.iter().map(|&v| v as f32).collect()
+            }
+            DATA_TYPE_DOUBLE => raw
+                .chunks_exact(8)
+                .map(|b| {
+                    f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+                })
+                .collect(),
+            DATA_TYPE_INT32 if raw.is_empty() => {
+                proto.int32_data.iter().map(|&v| v as f32).collect()
+            }
+            DATA_TYPE_INT32 => raw
+                .chunks_exact(4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32)
+                .collect(),
+            DATA_TYPE_INT64 if raw.is_empty() => {
+                proto.int64_data.iter().map(|&v| v as f32).collect()
+            }
+            DATA_TYPE_INT64 => raw
+                .chunks_exact(8)
+                .map(|b| {
+                    i64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+                })
+                .collect(),
+            data_type => bail!("Unsupported tensor type: {}", data_type),
+        };
+        Tensor::new(shape, data)
+    }
+}
+
+fn dim(d: i64) -> Result<usize> {
+    ensure!(d >= 0, "Negative dimension: {}", d);
+    Ok(d as usize)
+}
+
+fn shape_len(shape: &[usize]) -> Result<usize> {
+    shape
+        .iter()
+        .try_fold(1usize, |len, &d| len.checked_mul(d))
+        .filter(|&len| len <= MAX_TENSOR_LEN)
+        .ok_or_else(|| anyhow!("Tensor of shape {:?} too large", shape))
+}
+
+struct Model {
+    graph: GraphProto,
+    opset: i64,
+}
+
+impl Model {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let model = ModelProto::decode(bytes).map_err(|e| anyhow!("Invalid ONNX model: {}", e))?;
+        let graph = model
+            .graph
+            .ok_or_else(|| anyhow!("ONNX model has no graph"))?;
+        let opset = model
+            .opset_import
+            .iter()
+            .find(|o| o.domain.is_empty() || o.domain == "ai.onnx")
+            .map(|o| o.version)
+            .unwrap_or(1);
+        Ok(Self { graph, opset })
+    }
+
+    // Nodes of ONNX graphs are sorted topologically, so each runs after the
+    // nodes of its inputs.
+    fn run(&self, data: Tensor, output_name: Option<&str>) -> Result<Tensor> {
+        let mut values = HashMap::new();
+        for initializer in self.graph.initializer.iter() {
+            values.insert(initializer.name.clone(), Tensor::from_proto(initializer)?);
+        }
+        let inputs: Vec<&str> = self
+            .graph
+            .input
+            .iter()
+            .map(|i| i.name.as_str())
+            .filter(|name| !values.contains_key(*name))
+            .collect();
+        ensure!(
+            inputs.len() == 1,
+            "ONNX model must have one input, not {}",
+            inputs.len()
+        );
+        values.insert(inputs[0].to_string(), data);
+
+        for node in self.graph.node.iter() {
+            let outputs = self.run_node(node, &values)?;
+            for (name, output) in node.output.iter().zip(outputs) {
+                values.insert(name.clone(), output);
+            }
+        }
+
+        let output_name = match output_name {
+            Some(name) => name,
+            None => self
+                .graph
+                .output
+                .first()
+                .map(|o| o.name.as_str())
+                .ok_or_else(|| anyhow!("ONNX model has no output"))?,
+        };
+        values
+            .remove(output_name)
+            .ok_or_else(|| anyhow!("Output not found: {}", output_name))
+    }
+
+    fn run_node(&self, node: &NodeProto, values: &HashMap<String, Tensor>) -> Result<Vec<Tensor>> {
+        // Optional inputs are empty names.
+        let input = |index: usize| -> Result<Option<&Tensor>> {
+            match node.input.get(index).map(String::as_str) {
+                None | Some("") => Ok(None),
+                Some(name) => values
+                    .get(name)
+                    .map(Some)
+                    .ok_or_else(|| anyhow!("Value not found: {}", name)),
+            }
+        };
+        let required = |index: usize| -> Result<&Tensor> {
+            input(index)?.ok_or_else(|| anyhow!("{} needs input {}", node.op_type, index))
+        };
+        let attribute = |name: &str| node.attribute.iter().find(|a| a.name == name);
+        let int = |name: &str, default: i64| attribute(name).map(|a| a.i).unwrap_or(default);
+        let float = |name: &str, default: f32| attribute(name).map(|a| a.f).unwrap_or(default);
+
+        let output = match node.op_type.as_str() {
+            "Identity" => required(0)?.clone(),
+            "Constant" => {
+                let value = attribute("value")
+                    .and_then(|a| a.t.as_ref())
+                    .ok_or_else(|| anyhow!("Constant needs a tensor value"))?;
+                Tensor::from_proto(value)?
+            }
+            "Add" => broadcast(required(0)?, required(1)?, |a, b| a + b)?,
+            "Sub" => broadcast(required(0)?, required(1)?, |a, b| a - b)?,
+            "Mul" => broadcast(required(0)?, required(1)?, |a, b| a * b)?,
+            "Div" => broadcast(required(0)?, required(1)?, |a, b| a / b)?,
+            "Relu" => map(required(0)?, |v| v.max(0.0)),
+            "Sigmoid" => map(required(0)?, |v| 1.0 / (1.0 + (-v).exp())),
+            "Tanh" => map(required(0)?, f32::tanh),
+            "Exp" => map(required(0)?, f32::exp),
+            "MatMul" => matmul(required(0)?, required(1)?)?,
+            "Gemm" => gemm(
+                required(0)?,
+                required(1)?,
+                input(2)?,
+                float("alpha", 1.0),
+                float("beta", 1.0),
+                int("transA", 0) != 0,
+                int("transB", 0) != 0,
+            )?,
+            "Softmax" => {
+                let default_axis = if self.opset >= 13 { -1 } else { 1 };
+                softmax(required(0)?, int("axis", default_axis), self.opset >= 13)?
+            }
+            "Flatten" => {
+                let x = required(0)?;
+                let axis = normalize_axis(int("axis", 1), x.shape.len() + 1)?;
+                let shape = vec![shape_len(&x.shape[..axis])?, shape_len(&x.shape[axis..])?];
+                Tensor::new(shape, x.data.clone())?
+            }
+            "Reshape" => reshape(required(0)?, required(1)?)?,
+            "Transpose" => {
+                let perm = attribute("perm").map(|a| a.ints.clone());
+                transpose(required(0)?, perm)?
+            }
+            "ArgMax" => argmax(required(0)?, int("axis", 0), int("keepdims", 1) != 0)?,
+            op_type => bail!("Unsupported ONNX operator: {}", op_type),
+        };
+        Ok(vec![output])
+    }
+}
+
+fn map(x: &Tensor, f: impl Fn(f32) -> f32) -> Tensor {
+    Tensor {
+        shape: x.shape.clone(),
+        data: x.data.iter().map(|&v| f(v)).collect(),
+    }
+}
+
+fn normalize_axis(axis: i64, rank: usize) -> Result<usize> {
+    let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+    ensure!(
+        normalized >= 0 && (normalized as usize) < rank.max(1),
+        "Invalid axis {} of rank {}",
+        axis,
+        rank
+    );
+    Ok(normalized as usize)
+}
+
+// Strides of a shape, zero for the dimensions broadcast to `shape`.
+fn broadcast_strides(from: &[usize], shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![0; shape.len()];
+    let offset = shape.len() - from.len();
+    let mut stride = 1;
+    for (i, &d) in from.iter().enumerate().rev() {
+        if d != 1 {
+            strides[offset + i] = stride;
+        }
+        stride *= d;
+    }
+    strides
+}
+
+// Applies `f` to the elements of `a` and `b` broadcast to the same shape,
+// as in numpy.
+fn broadcast(a: &Tensor, b: &Tensor, f: impl Fn(f32, f32) -> f32) -> Result<Tensor> {
+    let rank = a.shape.len().max(b.shape.len());
+    let dim_of = |shape: &[usize], i: usize| {
+        let offset = rank - shape.len();
+        if i < offset {
+            1
+        } else {
+            shape[i - offset]
+        }
+    };
+    let mut shape = Vec::with_capacity(rank);
+    for i in 0..rank {
+        let (da, db) = (dim_of(&a.shape, i), dim_of(&b.shape, i));
+        ensure!(
+            da == db || da == 1 || db == 1,
+            "Cannot broadcast shapes {:?} and {:?}",
+            a.shape,
+            b.shape
+        );
+        shape.push(da.max(db));
+    }
+    let len = shape_len(&shape)?;
+    let a_strides = broadcast_strides(&a.shape, &shape);
+    let b_strides = broadcast_strides(&b.shape, &shape);
+    let mut data = Vec::with_capacity(len);
+    let mut index = vec![0; rank];
+    for _ in 0..len {
+        let offset =
+            |strides: &[usize]| index.iter().zip(strides).map(|(i, s)| i * s).sum::<usize>();
+        data.push(f(a.data[offset(&a_strides)], b.data[offset(&b_strides)]));
+        // Next index in row-major order.
+        for i in (0..rank).rev() {
+            index[i] += 1;
+            if index[i] < shape[i] {
+                break;
+            }
+            index[i] = 0;
+        }
+    }
+    Tensor::new(shape, data)
+}
+
+// Multiplies the matrices of `a`, e.g., the rows of a batch, by the matrix
+// `b`.
+fn matmul(a: &Tensor, b: &Tensor) -> Result<Tensor> {
+    ensure!(
+        !a.shape.is_empty() && b.shape.len() == 2,
+        "Unsupported MatMul of shapes {:?} and {:?}",
+        a.shape,
+        b.shape
+    );
+    let k = *a.shape.last().unwrap();
+    let (bk, n) = (b.shape[0], b.shape[1]);
+    ensure!(
+        k == bk,
+        "Cannot multiply shapes {:?} and {:?}",
+        a.shape,
+        b.shape
+    );
+    let m = a.len() / k.max(1);
+    let mut shape = a.shape[..a.shape.len() - 1].to_vec();
+    shape.push(n);
+    shape_len(&shape)?;
+    let mut data = vec![0.0; m * n];
+    for i in 0..m {
+        for p in 0..k {
+            let x = a.data[i * k + p];
+            for j in 0..n {
+                data[i * n + j] += x * b.data[p * n + j];
+            }
+        }
+    }
+    Tensor::new(shape, data)
+}
+
+fn gemm(
+    a: &Tensor,
+    b: &Tensor,
+    c: Option<&Tensor>,
+    alpha: f32,
+    beta: f32,
+    trans_a: bool,
+    trans_b: bool,
+) -> Result<Tensor> {
+    ensure!(
+        a.shape.len() == 2 && b.shape.len() == 2,
+        "Gemm needs matrices, not {:?} and {:?}",
+        a.shape,
+        b.shape
+    );
+    let a = if trans_a {
+        transpose(a, None)?
+    } else {
+        a.clone()
+    };
+    let b = if trans_b {
+        transpose(b, None)?
+    } else {
+        b.clone()
+    };
+    let product = map(&matmul(&a, &b)?, |v| alpha * v);
+    match c {
+        Some(c) => broadcast(&product, &map(c, |v| beta * v), |x, y| x + y),
+        None => Ok(product),
+    }
+}
+
+// Before opset 13, the input is coerced to a matrix of the dimensions before
+// and from the axis, and each row is normalized.
+fn softmax(x: &Tensor, axis: i64, single_axis: bool) -> Result<Tensor> {
+    let axis = normalize_axis(axis, x.shape.len())?;
+    let (outer, size, inner) = if single_axis {
+        (
+            shape_len(&x.shape[..axis])?,
+            x.shape.get(axis).copied().unwrap_or(1),
+            shape_len(&x.shape[axis + 1..])?,
+        )
+    } else {
+        (
+            shape_len(&x.shape[..axis])?,
+            shape_len(&x.shape[axis..])?,
+            1,
+        )
+    };
+    let mut data = x.data.clone();
+    for o in 0..outer {
+        for i in 0..inner {
+            let at = |j: usize| o * size * inner + j * inner + i;
+            let max = (0..size)
+                .map(|j| data[at(j)])
+                .fold(f32::NEG_INFINITY, f32::max);
+            let mut sum = 0.0;
+            for j in 0..size {
+                data[at(j)] = (data[at(j)] - max).exp();
+                sum += data[at(j)];
+            }
+            for j in 0..size {
+                data[at(j)] /= sum;
+            }
+        }
+    }
+    Tensor::new(x.shape.clone(), data)
+}
+
+// Zeros copy the dimension of the input, and a -1 takes the elements left.
+fn reshape(x: &Tensor, shape: &Tensor) -> Result<Tensor> {
+    let mut inferred = None;
+    let mut dims = Vec::with_capacity(shape.len());
+    for (i, &d) in shape.data.iter().enumerate() {
+        let d = d as i64;
+        match d {
+            -1 => {
+                ensure!(inferred.is_none(), "Reshape infers one dimension only");
+                inferred = Some(i);
+                dims.push(1);
+            }
+            0 => dims.push(
+                *x.shape
+                    .get(i)
+                    .ok_or_else(|| anyhow!("Reshape copies a missing dimension"))?,
+            ),
+            d => dims.push(dim(d)?),
+        }
+    }
+    if let Some(i) = inferred {
+        let known = shape_len(&dims)?;
+        ensure!(
+            known > 0 && x.len() % known == 0,
+            "Cannot reshape {:?} to {:?}",
+            x.shape,
+            shape.data
+        );
+        dims[i] = x.len() / known;
+    }
+    Tensor::new(dims, x.data.clone())
+}
+
+// Permutes the dimensions, reversing them by default.
+fn transpose(x: &Tensor, perm: Option<Vec<i64>>) -> Result<Tensor> {
+    let rank = x.shape.len();
+    let perm: Vec<usize> = match perm {
+        Some(perm) => perm.iter().map(|&p| dim(p)).collect::<Result<_>>()?,
+        None => (0..rank).rev().collect(),
+    };
+    let mut sorted = perm.clone();
+    sorted.sort();
+    ensure!(
+        sorted == (0..rank).collect::<Vec<_>>(),
+        "Invalid permutation {:?}",
+        perm
+    );
+    let shape: Vec<usize> = perm.iter().map(|&p| x.shape[p]).collect();
+    let in_strides = broadcast_strides(&x.shape, &x.shape);
+    let strides: Vec<usize> = perm.iter().map(|&p| in_strides[p]).collect();
+    let mut data = Vec::with_capacity(x.len());
+    let mut index = vec![0; rank];
+    for _ in 0..x.len() {
+        let offset: usize = index.iter().zip(&strides).map(|(i, s)| i * s).sum();
+        data.push(x.data[offset]);
+        for i in (0..rank).rev() {
+            index[i] += 1;
+            if index[i] < shape[i] {
+                break;
+            }
+            index[i] = 0;
+        }
+    }
+    Tensor::new(shape, data)
+}
+
+// Indices of the first maximum along the axis.
+fn argmax(x: &Tensor, axis: i64, keepdims: bool) -> Result<Tensor> {
+    let axis = normalize_axis(axis, x.shape.len())?;
+    let size = x.shape[axis];
+    ensure!(size > 0, "ArgMax of an empty axis");
+    let outer = shape_len(&x.shape[..axis])?;
+    let inner = shape_len(&x.shape[axis + 1..])?;
+    let mut data = Vec::with_capacity(outer * inner);
+    for o in 0..outer {
+        for i in 0..inner {
+            let at = |j: usize| x.data[o * size * inner + j * inner + i];
+            let best = (1..size).fold(0, |best, j| if at(j) > at(best) { j } else { best });
+            data.push(best as f32);
+        }
+    }
+    let mut shape = x.shape.clone();
+    if keepdims {
+        shape[axis] = 1;
+    } else {
+        shape.remove(axis);
+    }
+    Tensor::new(shape, data)
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::untrusted::fs;
+    use teaclave_crypto::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(test_onnx_inference, test_onnx_operators)
+    }
+
+    fn test_onnx_inference() {
+        let arguments = FunctionArguments::default();
+
+        let plain_model = "fixtures/functions/onnx_inference/model.onnx";
+        let plain_data = "fixtures/functions/onnx_inference/test_data.txt";
+        let plain_output = "fixtures/functions/onnx_inference/result.txt.out";
+        let expected_output = "fixtures/functions/onnx_inference/expected_result.txt";
+
+        let input_files = StagedFiles::new(hashmap!(
+            IN_MODEL =>
+            StagedFileInfo::new(plain_model, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+            IN_DATA =>
+            StagedFileInfo::new(plain_data, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let output_files = StagedFiles::new(hashmap!(
+            OUT_RESULT =>
+            StagedFileInfo::new(plain_output, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let runtime = Box::new(RawIoRuntime::new(input_files, output_files));
+
+        let summary = OnnxInference::new().run(arguments, runtime).unwrap();
+        assert_eq!(summary, "Inference result has 4 lines of data.");
+
+        let result = fs::read_to_string(&plain_output).unwrap();
+        let expected = fs::read_to_string(&expected_output).unwrap();
+        assert_eq!(&result[..], &expected[..]);
+    }
+
+    fn test_onnx_operators() {
+        let x = Tensor::new(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let row = Tensor::new(vec![3], vec![1.0, 0.0, -1.0]).unwrap();
+        let sum = broadcast(&x, &row, |a, b| a + b).unwrap();
+        assert_eq!(sum.data, vec![2.0, 2.0, 2.0, 5.0, 5.0, 5.0]);
+
+        let t = transpose(&x, None).unwrap();
+        assert_eq!(t.shape, vec![3, 2]);
+        assert_eq!(t.data, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        let product = matmul(&x, &t).unwrap();
+        assert_eq!(product.data, vec![14.0, 32.0, 32.0, 77.0]);
+        let product = gemm(&x, &x, Some(&row.clone()), 1.0, 1.0, false, true);
+        assert!(product.is_err());
+
+        let shape = Tensor::new(vec![2], vec![-1.0, 2.0]).unwrap();
+        assert_eq!(reshape(&x, &shape).unwrap().shape, vec![3, 2]);
+        assert_eq!(argmax(&x, 1, false).unwrap().data, vec![2.0, 2.0]);
+
+        let probabilities = softmax(&x, 1, true).unwrap();
+        for row in probabilities.data.chunks(3) {
+            assert!((row.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+            assert!(row[0] < row[1] && row[1] < row[2]);
+        }
+
+        assert!(Model::from_bytes(b"not a model").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Write};
+
+use teaclave_types::{FunctionArguments, FunctionRuntime};
+
+const IN_DATA1: &str = "input_data1";
+const IN_DATA2: &str = "input_data2";
+const OUT_RESULT1: &str = "output_result1";
+const OUT_RESULT2: &str = "output_result2";
+
+/// Computes the size of the intersection of the sets of two parties, one
+/// item (e.g., the hash of an identifier) per line, revealing nothing but
+/// the size to each party. Unlike `builtin-ordered-set-intersect`, the items
+/// need not be sorted.
+#[derive(Default)]
+pub struct PsiCardinality;
+
+impl PsiCardinality {
+    pub const NAME: &'static str = "builtin-psi-cardinality";
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn run(
+        &self,
+        _arguments: FunctionArguments,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        let set1 = read_set(runtime.open_input(IN_DATA1)?)?;
+        let set2 = read_set(runtime.open_input(IN_DATA2)?)?;
+        let cardinality = set1.intersection(&set2).count();
+
+        for output in [OUT_RESULT1, OUT_RESULT2].iter() {
+            let mut output = runtime.create_output(output)?;
+            writeln!(&mut output, "{}", cardinality)?;
+        }
+
+        // The summary is returned to the creator of the task, who may not
+        // be a party.
+        Ok("Intersection size written to the outputs.".to_string())
+    }
+}
+
+fn read_set(input: impl io::Read) -> anyhow::Result<HashSet<String>> {
+    let mut set = HashSet::new();
+    for line in BufReader::new(input).lines() {
+        let line = line?;
+        let item = line.trim();
+        if !item.is_empty() {
+            set.insert(item.to_string());
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::untrusted::fs;
+    use teaclave_crypto::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(test_psi_cardinality)
+    }
+
+    fn test_psi_cardinality() {
+        let arguments = FunctionArguments::default();
+
+        // Reuse the sets of the ordered set intersection
+        let base = Path::new("fixtures/functions/ordered_set_intersect");
+        let user1_input = base.join("psi0.txt");
+        let user2_input = base.join("psi1.txt");
+        let user1_output = base.join("output_psi_cardinality0.txt");
+        let user2_output = base.join("output_psi_cardinality1.txt");
+
+        let input_files = StagedFiles::new(hashmap!(
+            IN_DATA1 =>
+            StagedFileInfo::new(&user1_input, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+            IN_DATA2 =>
+            StagedFileInfo::new(&user2_input, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+        ));
+
+        let output_files = StagedFiles::new(hashmap!(
+            OUT_RESULT1 =>
+            StagedFileInfo::new(&user1_output, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+            OUT_RESULT2 =>
+            StagedFileInfo::new(&user2_output, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+        ));
+
+        let runtime = Box::new(RawIoRuntime::new(input_files, output_files));
+        let summary = PsiCardinality::new().run(arguments, runtime).unwrap();
+        assert_eq!(summary, "Intersection size written to the outputs.");
+
+        assert_eq!(fs::read_to_string(&user1_output).unwrap(), "3\n");
+        assert_eq!(fs::read_to_string(&user2_output).unwrap(), "3\n");
+
+        let items = "b\na\n\nb\nc".as_bytes();
+        let set = read_set(items).unwrap();
+        assert_eq!(set.len(), 3);
+    }
+}
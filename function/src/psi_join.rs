@@ -0,0 +1,213 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, BufRead, BufReader, Write};
+
+use teaclave_types::{FunctionArguments, FunctionRuntime};
+
+use anyhow::{anyhow, ensure, Context, Result};
+
+use crate::sql_query::{join_csv_line, split_csv_line};
+
+const IN_DATA1: &str = "input_data1";
+const IN_DATA2: &str = "input_data2";
+const OUT_RESULT: &str = "output_result";
+const MAX_ROWS: usize = 1 << 22;
+
+/// Joins the records of two parties on a common key column and outputs only
+/// the matched rows. The output should be registered as a fusion output owned
+/// by both parties, so that it is encrypted under a key managed by the
+/// platform and can only be used by tasks approved by all of them.
+#[derive(Default)]
+pub struct PsiJoin;
+
+#[derive(serde::Deserialize)]
+struct PsiJoinArguments {
+    key: String,
+}
+
+impl TryFrom<FunctionArguments> for PsiJoinArguments {
+    type Error = anyhow::Error;
+
+    fn try_from(arguments: FunctionArguments) -> Result<Self, Self::Error> {
+        serde_json::from_str(&arguments.into_string()).context("Cannot deserialize arguments")
+    }
+}
+
+struct Records {
+    key_index: usize,
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Records {
+    // All the columns except for the key
+    fn others<'a>(&self, row: &'a [String]) -> impl Iterator<Item = &'a String> {
+        let key_index = self.key_index;
+        row.iter()
+            .enumerate()
+            .filter(move |(i, _)| *i != key_index)
+            .map(|(_, field)| field)
+    }
+}
+
+impl PsiJoin {
+    pub const NAME: &'static str = "builtin-psi-join";
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn run(
+        &self,
+        arguments: FunctionArguments,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        let args = PsiJoinArguments::try_from(arguments)?;
+        let records1 = read_records(runtime.open_input(IN_DATA1)?, &args.key)
+            .context("Cannot read input_data1")?;
+        let records2 = read_records(runtime.open_input(IN_DATA2)?, &args.key)
+            .context("Cannot read input_data2")?;
+
+        let mut index: HashMap<&str, Vec<&Vec<String>>> = HashMap::new();
+        for row in records2.rows.iter() {
+            index
+                .entry(row[records2.key_index].as_str())
+                .or_default()
+                .push(row);
+        }
+
+        let mut output = runtime.create_output(OUT_RESULT)?;
+        let header = std::iter::once(&args.key)
+            .chain(records1.others(&records1.columns))
+            .chain(records2.others(&records2.columns))
+            .cloned();
+        writeln!(&mut output, "{}", join_csv_line(header))?;
+
+        let mut count = 0;
+        for row1 in records1.rows.iter() {
+            let matches = match index.get(row1[records1.key_index].as_str()) {
+                Some(matches) => matches,
+                None => continue,
+            };
+            for row2 in matches {
+                count += 1;
+                ensure!(count <= MAX_ROWS, "Too many rows in the join result");
+                let fields = std::iter::once(&row1[records1.key_index])
+                    .chain(records1.others(row1))
+                    .chain(records2.others(row2))
+                    .cloned();
+                writeln!(&mut output, "{}", join_csv_line(fields))?;
+            }
+        }
+
+        // The summary is returned to the creator of the task, who may not
+        // be a party.
+        Ok("Joined rows written to the output.".to_string())
+    }
+}
+
+fn read_records(input: impl io::Read, key: &str) -> Result<Records> {
+    let mut lines = BufReader::new(input).lines();
+    let header = lines.next().ok_or_else(|| anyhow!("Missing header"))??;
+    let columns = split_csv_line(&header)?;
+    let key_index = columns
+        .iter()
+        .position(|c| c == key)
+        .ok_or_else(|| anyhow!("Key column {} not found", key))?;
+
+    let mut rows = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(&line)?;
+        ensure!(
+            fields.len() == columns.len(),
+            "Expected {} fields in a row",
+            columns.len()
+        );
+        ensure!(rows.len() < MAX_ROWS, "Too many rows");
+        rows.push(fields);
+    }
+
+    Ok(Records {
+        key_index,
+        columns,
+        rows,
+    })
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::Path;
+    use std::untrusted::fs;
+    use teaclave_crypto::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(test_psi_join, test_psi_join_missing_key)
+    }
+
+    fn test_psi_join() {
+        let arguments = FunctionArguments::from_json(json!({
+            "key": "email"
+        }))
+        .unwrap();
+
+        let base = Path::new("fixtures/functions/psi_join");
+        let input1 = base.join("bank.csv");
+        let input2 = base.join("insurer.csv");
+        let output = base.join("result.csv.out");
+        let expected_output = base.join("expected_result.csv");
+
+        let input_files = StagedFiles::new(hashmap!(
+            IN_DATA1 =>
+            StagedFileInfo::new(&input1, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+            IN_DATA2 =>
+            StagedFileInfo::new(&input2, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+        ));
+
+        let output_files = StagedFiles::new(hashmap!(
+            OUT_RESULT =>
+            StagedFileInfo::new(&output, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let runtime = Box::new(RawIoRuntime::new(input_files, output_files));
+        let summary = PsiJoin::new().run(arguments, runtime).unwrap();
+        assert_eq!(summary, "Joined rows written to the output.");
+
+        let result = fs::read_to_string(&output).unwrap();
+        let expected = fs::read_to_string(&expected_output).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    fn test_psi_join_missing_key() {
+        let input = "id,name\n1,alice\n".as_bytes();
+        assert!(read_records(input, "email").is_err());
+    }
+}
@@ -0,0 +1,265 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::convert::TryFrom;
+use std::format;
+use std::io::{self, Read};
+
+use teaclave_types::{FunctionArguments, FunctionRuntime};
+
+use anyhow::{bail, ensure, Result};
+
+const OUT_AGGREGATE: &str = "aggregate";
+
+// Length of a gradient, which is held once per contribution in the enclave.
+const MAX_DIMENSION: usize = 16 * 1024 * 1024;
+
+/// Aggregates the model gradients of the data owners of a round of federated
+/// learning. Each owner contributes an input, named in the `inputs` argument,
+/// holding `{"weight": <samples>, "gradient": [...]}`; an owner sitting out
+/// the round contributes an empty file. Gradients are clipped to the L2 norm
+/// `clip_norm` if given, and their average weighted by the weights is
+/// written to the output in the same form, only if at least `quorum` owners
+/// have contributed, so that no owner's gradient is released alone.
+#[derive(Default)]
+pub struct SecureAggregation;
+
+#[derive(serde::Deserialize)]
+struct SecureAggregationArguments {
+    inputs: Vec<String>,
+    quorum: usize,
+    clip_norm: Option<f64>,
+}
+
+impl TryFrom<FunctionArguments> for SecureAggregationArguments {
+    type Error = anyhow::Error;
+
+    fn try_from(arguments: FunctionArguments) -> Result<Self, Self::Error> {
+        use anyhow::Context;
+        serde_json::from_str(&arguments.into_string()).context("Cannot deserialize arguments")
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+struct Contribution {
+    #[serde(default = "default_weight")]
+    weight: f64,
+    gradient: Vec<f64>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl SecureAggregation {
+    pub const NAME: &'static str = "builtin-secure-aggregation";
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn run(
+        &self,
+        arguments: FunctionArguments,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        let args = SecureAggregationArguments::try_from(arguments)?;
+        ensure!(args.quorum > 0, "The quorum must be positive");
+        if let Some(clip_norm) = args.clip_norm {
+            ensure!(
+                clip_norm.is_finite() && clip_norm > 0.0,
+                "The clipping norm must be positive"
+            );
+        }
+
+        let mut contributions = Vec::new();
+        for name in &args.inputs {
+            if let Some(contribution) = read_contribution(runtime.open_input(name)?)? {
+                contributions.push(contribution);
+            }
+        }
+        // Nothing is written below the quorum, which fails the task.
+        ensure!(
+            contributions.len() >= args.quorum,
+            "Quorum of {} not reached: {} contributions",
+            args.quorum,
+            contributions.len()
+        );
+
+        let aggregate = aggregate(&contributions, args.clip_norm)?;
+        let output = runtime.create_output(OUT_AGGREGATE)?;
+        serde_json::to_writer(output, &aggregate)?;
+
+        let summary = format!(
+            "Aggregated {} of {} contributions.",
+            contributions.len(),
+            args.inputs.len()
+        );
+        Ok(summary)
+    }
+}
+
+fn read_contribution(mut input: impl io::Read) -> Result<Option<Contribution>> {
+    let mut content = String::new();
+    input.read_to_string(&mut content)?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    let contribution: Contribution = serde_json::from_str(&content)?;
+    ensure!(
+        contribution.weight.is_finite() && contribution.weight > 0.0,
+        "Weights must be positive"
+    );
+    ensure!(
+        contribution.gradient.len() <= MAX_DIMENSION,
+        "Gradient too long"
+    );
+    ensure!(
+        contribution.gradient.iter().all(|value| value.is_finite()),
+        "Gradients must be finite"
+    );
+    Ok(Some(contribution))
+}
+
+fn aggregate(contributions: &[Contribution], clip_norm: Option<f64>) -> Result<Contribution> {
+    let dimension = match contributions.first() {
+        Some(contribution) => contribution.gradient.len(),
+        None => bail!("No contributions"),
+    };
+    let mut sum = vec![0.0; dimension];
+    let mut total_weight = 0.0;
+    for contribution in contributions {
+        ensure!(
+            contribution.gradient.len() == dimension,
+            "Gradients of different lengths"
+        );
+        let norm = contribution
+            .gradient
+            .iter()
+            .map(|value| value * value)
+            .sum::<f64>()
+            .sqrt();
+        let scale = match clip_norm {
+            Some(clip_norm) if norm > clip_norm => clip_norm / norm,
+            _ => 1.0,
+        };
+        for (sum, value) in sum.iter_mut().zip(&contribution.gradient) {
+            *sum += contribution.weight * scale * value;
+        }
+        total_weight += contribution.weight;
+    }
+    Ok(Contribution {
+        weight: total_weight,
+        gradient: sum.into_iter().map(|sum| sum / total_weight).collect(),
+    })
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::Path;
+    use std::untrusted::fs;
+    use teaclave_crypto::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(test_secure_aggregation, test_secure_aggregation_quorum)
+    }
+
+    fn run(arguments: serde_json::Value, plain_output: &Path) -> Result<String> {
+        let arguments = FunctionArguments::from_json(arguments)?;
+        let base = Path::new("fixtures/functions/secure_aggregation");
+        let input = |name: &str| {
+            StagedFileInfo::new(
+                base.join(name),
+                TeaclaveFile128Key::random(),
+                FileAuthTag::mock(),
+            )
+        };
+
+        let input_files = StagedFiles::new(hashmap!(
+            "owner1" => input("owner1.json"),
+            "owner2" => input("owner2.json"),
+            "owner3" => input("owner3.json"),
+        ));
+
+        let output_files = StagedFiles::new(hashmap!(
+            OUT_AGGREGATE =>
+            StagedFileInfo::new(plain_output, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let runtime = Box::new(RawIoRuntime::new(input_files, output_files));
+        SecureAggregation::new().run(arguments, runtime)
+    }
+
+    fn test_secure_aggregation() {
+        let plain_output = Path::new("fixtures/functions/secure_aggregation/aggregate.json.out");
+
+        // The third owner sits out the round with an empty file.
+        let summary = run(
+            json!({"inputs": ["owner1", "owner2", "owner3"], "quorum": 2}),
+            plain_output,
+        )
+        .unwrap();
+        assert_eq!(summary, "Aggregated 2 of 3 contributions.");
+        let aggregate: Contribution =
+            serde_json::from_slice(&fs::read(plain_output).unwrap()).unwrap();
+        assert_eq!(
+            aggregate,
+            Contribution {
+                weight: 4.0,
+                gradient: vec![0.75, 0.5],
+            }
+        );
+
+        // The gradient of the first owner, of norm 2, is halved.
+        run(
+            json!({"inputs": ["owner1", "owner2"], "quorum": 2, "clip_norm": 1.0}),
+            plain_output,
+        )
+        .unwrap();
+        let aggregate: Contribution =
+            serde_json::from_slice(&fs::read(plain_output).unwrap()).unwrap();
+        assert_eq!(
+            aggregate,
+            Contribution {
+                weight: 4.0,
+                gradient: vec![0.75, 0.25],
+            }
+        );
+    }
+
+    fn test_secure_aggregation_quorum() {
+        let plain_output =
+            Path::new("fixtures/functions/secure_aggregation/aggregate_quorum.json.out");
+        let result = run(
+            json!({"inputs": ["owner1", "owner2", "owner3"], "quorum": 3}),
+            plain_output,
+        );
+        assert!(result.is_err());
+        assert!(!plain_output.exists());
+
+        let result = run(json!({"inputs": ["owner1"], "quorum": 0}), plain_output);
+        assert!(result.is_err());
+    }
+}
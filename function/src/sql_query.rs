@@ -0,0 +1,1483 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::format;
+use std::io::{self, BufRead, BufReader, Write};
+
+use teaclave_types::{FunctionArguments, FunctionRuntime};
+
+use anyhow::{anyhow, bail, ensure, Result};
+
+const OUT_RESULT: &str = "result_file";
+
+// Rows of a table, including intermediate results of joins.
+const MAX_ROWS: usize = 4 * 1024 * 1024;
+
+/// Runs a SQL query over CSV files. Tables in the query are the inputs of the
+/// same names, with the column names in the first line, and the result set is
+/// written to the output as CSV.
+///
+/// Queries are `SELECT` statements with `DISTINCT`, inner `JOIN ... ON`,
+/// `WHERE`, `GROUP BY`, `HAVING`, `ORDER BY`, `LIMIT` and `OFFSET`. The
+/// aggregate functions are `COUNT`, `SUM`, `AVG`, `MIN` and `MAX`, and the
+/// scalar functions are `LOWER`, `UPPER`, `LENGTH`, `ABS`, `ROUND` and
+/// `COALESCE`. Values of the CSV files are numbers, strings or `NULL` (empty
+/// fields).
+#[derive(Default)]
+pub struct SqlQuery;
+
+#[derive(serde::Deserialize)]
+struct SqlQueryArguments {
+    query: String,
+}
+
+impl TryFrom<FunctionArguments> for SqlQueryArguments {
+    type Error = anyhow::Error;
+
+    fn try_from(arguments: FunctionArguments) -> Result<Self, Self::Error> {
+        use anyhow::Context;
+        serde_json::from_str(&arguments.into_string()).context("Cannot deserialize arguments")
+    }
+}
+
+impl SqlQuery {
+    pub const NAME: &'static str = "builtin-sql-query";
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn run(
+        &self,
+        arguments: FunctionArguments,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        let args = SqlQueryArguments::try_from(arguments)?;
+        let query = Query::parse(&args.query)?;
+
+        let mut tables = HashMap::new();
+        for name in query.table_names() {
+            if !tables.contains_key(name) {
+                let table = read_csv(runtime.open_input(name)?)?;
+                tables.insert(name.to_string(), table);
+            }
+        }
+
+        let result = query.execute(&tables)?;
+        let mut output = runtime.create_output(OUT_RESULT)?;
+        write_csv(&result, &mut output)?;
+
+        let summary = format!("Query result has {} rows.", result.rows.len());
+        Ok(summary)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+// Values of grouping and joining, where numbers are equal by their bits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Null,
+    Number(u64),
+    Text(String),
+}
+
+impl Value {
+    fn parse(field: &str) -> Self {
+        if field.is_empty() {
+            return Value::Null;
+        }
+        match field.parse::<f64>() {
+            Ok(number) if number.is_finite() => Value::Number(number),
+            _ => Value::Text(field.to_string()),
+        }
+    }
+
+    fn boolean(value: bool) -> Self {
+        Value::Number(if value { 1.0 } else { 0.0 })
+    }
+
+    fn key(&self) -> Key {
+        match self {
+            Value::Null => Key::Null,
+            // Zero and negative zero are the same key.
+            Value::Number(number) => Key::Number((number + 0.0).to_bits()),
+            Value::Text(text) => Key::Text(text.clone()),
+        }
+    }
+
+    fn number(&self) -> Result<Option<f64>> {
+        match self {
+            Value::Null => Ok(None),
+            Value::Number(number) => Ok(Some(*number)),
+            Value::Text(text) => bail!("Not a number: '{}'", text),
+        }
+    }
+
+    fn truth(&self) -> Result<Option<bool>> {
+        Ok(self.number()?.map(|number| number != 0.0))
+    }
+
+    // Total order of values, sorting nulls first and numbers before strings.
+    fn sort_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Number(_), Value::Text(_)) => Ordering::Less,
+            (Value::Text(_), Value::Number(_)) => Ordering::Greater,
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                write!(f, "{}", *number as i64)
+            }
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Table {
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+fn read_csv(input: impl io::Read) -> Result<Table> {
+    let mut lines = BufReader::new(input).lines();
+    let header = lines.next().ok_or_else(|| anyhow!("Empty CSV file"))??;
+    let columns = split_csv_line(&header)?;
+    let mut rows = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(&line)?;
+        ensure!(
+            fields.len() == columns.len(),
+            "Row of {} fields in a table of {} columns",
+            fields.len(),
+            columns.len()
+        );
+        ensure!(rows.len() < MAX_ROWS, "Too many rows");
+        rows.push(fields.iter().map(|f| Value::parse(f)).collect());
+    }
+    Ok(Table { columns, rows })
+}
+
+// Fields are separated by commas and may be quoted, with quotes doubled.
+pub(crate) fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let line = line.trim_end_matches('\r');
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => bail!("Unterminated quoted field"),
+                }
+            }
+            ensure!(
+                matches!(chars.peek(), None | Some(',')),
+                "Unexpected characters after quoted field"
+            );
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ',' {
+                    break;
+                }
+                field.push(c);
+                chars.next();
+            }
+            field = field.trim().to_string();
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+// Quotes the fields with separators, quotes or line breaks.
+pub(crate) fn join_csv_line(fields: impl IntoIterator<Item = String>) -> String {
+    let quote = |field: String| {
+        if field.contains(&[',', '"', '\n', '\r'][..]) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field
+        }
+    };
+    let fields: Vec<String> = fields.into_iter().map(quote).collect();
+    fields.join(",")
+}
+
+fn write_csv(table: &Table, output: &mut impl Write) -> Result<()> {
+    writeln!(output, "{}", join_csv_line(table.columns.iter().cloned()))?;
+    for row in table.rows.iter() {
+        writeln!(
+            output,
+            "{}",
+            join_csv_line(row.iter().map(Value::to_string))
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Concat,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    // Column of an optional table, resolved to the index in a row before
+    // evaluation.
+    Column(Option<String>, String),
+    Index(usize),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    IsNull(Box<Expr>, bool),
+    Like(Box<Expr>, Box<Expr>, bool),
+    InList(Box<Expr>, Vec<Expr>, bool),
+    // Function of an upper-case name; `COUNT(*)` has no arguments.
+    Call {
+        name: String,
+        args: Vec<Expr>,
+        distinct: bool,
+    },
+}
+
+fn is_aggregate(name: &str) -> bool {
+    matches!(name, "COUNT" | "SUM" | "AVG" | "MIN" | "MAX")
+}
+
+impl Expr {
+    fn has_aggregate(&self) -> bool {
+        match self {
+            Expr::Literal(_) | Expr::Column(..) | Expr::Index(_) => false,
+            Expr::Neg(e) | Expr::Not(e) | Expr::IsNull(e, _) => e.has_aggregate(),
+            Expr::Binary(_, a, b) | Expr::Like(a, b, _) => a.has_aggregate() || b.has_aggregate(),
+            Expr::InList(e, list, _) => e.has_aggregate() || list.iter().any(Expr::has_aggregate),
+            Expr::Call { name, args, .. } => {
+                is_aggregate(name) || args.iter().any(Expr::has_aggregate)
+            }
+        }
+    }
+
+    // Resolves the columns to the indices of the schema.
+    fn bind(&self, schema: &Schema) -> Result<Expr> {
+        let bind = |e: &Expr| e.bind(schema).map(Box::new);
+        let expr = match self {
+            Expr::Literal(_) | Expr::Index(_) => self.clone(),
+            Expr::Column(table, name) => Expr::Index(schema.resolve(table.as_deref(), name)?),
+            Expr::Neg(e) => Expr::Neg(bind(e)?),
+            Expr::Not(e) => Expr::Not(bind(e)?),
+            Expr::Binary(op, a, b) => Expr::Binary(*op, bind(a)?, bind(b)?),
+            Expr::IsNull(e, negated) => Expr::IsNull(bind(e)?, *negated),
+            Expr::Like(a, b, negated) => Expr::Like(bind(a)?, bind(b)?, *negated),
+            Expr::InList(e, list, negated) => Expr::InList(
+                bind(e)?,
+                list.iter().map(|e| e.bind(schema)).collect::<Result<_>>()?,
+                *negated,
+            ),
+            Expr::Call {
+                name,
+                args,
+                distinct,
+            } => {
+                if is_aggregate(name) {
+                    ensure!(
+                        !args.iter().any(Expr::has_aggregate),
+                        "Nested aggregate functions"
+                    );
+                }
+                Expr::Call {
+                    name: name.clone(),
+                    args: args.iter().map(|e| e.bind(schema)).collect::<Result<_>>()?,
+                    distinct: *distinct,
+                }
+            }
+        };
+        Ok(expr)
+    }
+
+    // Evaluates the expression on a group of rows, of which columns take
+    // the values in the first row and aggregate functions take all rows.
+    // Expressions without aggregates are evaluated on groups of one row.
+    fn eval(&self, rows: &[&[Value]]) -> Result<Value> {
+        let value = match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Column(_, name) => bail!("Unresolved column: {}", name),
+            Expr::Index(index) => rows
+                .first()
+                .map(|row| row[*index].clone())
+                .unwrap_or(Value::Null),
+            Expr::Neg(e) => match e.eval(rows)?.number()? {
+                Some(number) => Value::Number(-number),
+                None => Value::Null,
+            },
+            Expr::Not(e) => match e.eval(rows)?.truth()? {
+                Some(truth) => Value::boolean(!truth),
+                None => Value::Null,
+            },
+            Expr::Binary(op, a, b) => eval_binary(*op, a.eval(rows)?, b.eval(rows)?)?,
+            Expr::IsNull(e, negated) => Value::boolean((e.eval(rows)? == Value::Null) != *negated),
+            Expr::Like(a, b, negated) => match (a.eval(rows)?, b.eval(rows)?) {
+                (Value::Null, _) | (_, Value::Null) => Value::Null,
+                (value, pattern) => {
+                    let value: Vec<char> = value.to_string().chars().collect();
+                    let pattern: Vec<char> = pattern.to_string().chars().collect();
+                    Value::boolean(like(&value, &pattern) != *negated)
+                }
+            },
+            Expr::InList(e, list, negated) => {
+                let value = e.eval(rows)?;
+                if value == Value::Null {
+                    Value::Null
+                } else {
+                    let mut found = false;
+                    let mut has_null = false;
+                    for item in list.iter() {
+                        match item.eval(rows)? {
+                            Value::Null => has_null = true,
+                            item => found |= item.sort_cmp(&value) == Ordering::Equal,
+                        }
+                    }
+                    if !found && has_null {
+                        Value::Null
+                    } else {
+                        Value::boolean(found != *negated)
+                    }
+                }
+            }
+            Expr::Call {
+                name,
+                args,
+                distinct,
+            } if is_aggregate(name) => eval_aggregate(name, args, *distinct, rows)?,
+            Expr::Call { name, args, .. } => {
+                let args = args
+                    .iter()
+                    .map(|e| e.eval(rows))
+                    .collect::<Result<Vec<_>>>()?;
+                eval_function(name, args)?
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn eval_binary(op: BinaryOp, a: Value, b: Value) -> Result<Value> {
+    use BinaryOp::*;
+    let value = match op {
+        And | Or => {
+            let (a, b) = (a.truth()?, b.truth()?);
+            let short = op == Or;
+            if a == Some(short) || b == Some(short) {
+                Value::boolean(short)
+            } else if a.is_none() || b.is_none() {
+                Value::Null
+            } else {
+                Value::boolean(!short)
+            }
+        }
+        _ if a == Value::Null || b == Value::Null => Value::Null,
+        Eq | NotEq | Lt | LtEq | Gt | GtEq => {
+            let ordering = a.sort_cmp(&b);
+            Value::boolean(match op {
+                Eq => ordering == Ordering::Equal,
+                NotEq => ordering != Ordering::Equal,
+                Lt => ordering == Ordering::Less,
+                LtEq => ordering != Ordering::Greater,
+                Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            })
+        }
+        Concat => Value::Text(format!("{}{}", a, b)),
+        _ => {
+            let (a, b) = (a.number()?.unwrap(), b.number()?.unwrap());
+            match op {
+                Add => Value::Number(a + b),
+                Sub => Value::Number(a - b),
+                Mul => Value::Number(a * b),
+                // Division by zero is null.
+                Div | Rem if b == 0.0 => Value::Null,
+                Div => Value::Number(a / b),
+                _ => Value::Number(a % b),
+            }
+        }
+    };
+    Ok(value)
+}
+
+// Matches `%` to any characters and `_` to one character. On a mismatch, the
+// last `%` takes one more character, which bounds the time by the product of
+// the lengths.
+fn like(value: &[char], pattern: &[char]) -> bool {
+    let (mut v, mut p) = (0, 0);
+    // positions of the last `%` and of the value where it was matched
+    let mut backtrack = None;
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == '%' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == '_' || pattern[p] == value[v]) {
+            v += 1;
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            backtrack = Some((star, matched + 1));
+            p = star + 1;
+            v = matched + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+fn eval_aggregate(name: &str, args: &[Expr], distinct: bool, rows: &[&[Value]]) -> Result<Value> {
+    if name == "COUNT" && args.is_empty() {
+        return Ok(Value::Number(rows.len() as f64));
+    }
+    ensure!(args.len() == 1, "{} takes one argument", name);
+    let mut values = Vec::new();
+    let mut seen = HashSet::new();
+    for row in rows.iter() {
+        let value = args[0].eval(&[row])?;
+        if value != Value::Null && (!distinct || seen.insert(value.key())) {
+            values.push(value);
+        }
+    }
+    let sum = || -> Result<f64> {
+        values
+            .iter()
+            .try_fold(0.0, |sum, v| Ok(sum + v.number()?.unwrap_or(0.0)))
+    };
+    let value = match name {
+        "COUNT" => Value::Number(values.len() as f64),
+        _ if values.is_empty() => Value::Null,
+        "SUM" => Value::Number(sum()?),
+        "AVG" => Value::Number(sum()? / values.len() as f64),
+        "MIN" => values.into_iter().min_by(Value::sort_cmp).unwrap(),
+        _ => values.into_iter().max_by(Value::sort_cmp).unwrap(),
+    };
+    Ok(value)
+}
+
+fn eval_function(name: &str, args: Vec<Value>) -> Result<Value> {
+    if name == "COALESCE" {
+        return Ok(args
+            .into_iter()
+            .find(|v| *v != Value::Null)
+            .unwrap_or(Value::Null));
+    }
+    let (value, digits) = match (name, args.as_slice()) {
+        ("ROUND", [value, digits]) => (value, digits.number()?.unwrap_or(0.0)),
+        (_, [value]) => (value, 0.0),
+        _ => bail!("Unknown function {} of {} arguments", name, args.len()),
+    };
+    if *value == Value::Null {
+        return Ok(Value::Null);
+    }
+    let value = match name {
+        "LOWER" => Value::Text(value.to_string().to_lowercase()),
+        "UPPER" => Value::Text(value.to_string().to_uppercase()),
+        "LENGTH" => Value::Number(value.to_string().chars().count() as f64),
+        "ABS" => Value::Number(value.number()?.unwrap().abs()),
+        "ROUND" => {
+            let scale = 10f64.powi(digits as i32);
+            Value::Number((value.number()?.unwrap() * scale).round() / scale)
+        }
+        _ => bail!("Unknown function: {}", name),
+    };
+    Ok(value)
+}
+
+// Columns of the rows in a query, with the aliases of their tables.
+struct Schema {
+    columns: Vec<(String, String)>,
+}
+
+impl Schema {
+    fn extend(&mut self, table: &str, columns: &[String]) {
+        self.columns
+            .extend(columns.iter().map(|c| (table.to_string(), c.clone())));
+    }
+
+    // Names of tables and columns are case-insensitive.
+    fn resolve(&self, table: Option<&str>, name: &str) -> Result<usize> {
+        let mut found = self.columns.iter().enumerate().filter(|(_, (t, c))| {
+            c.eq_ignore_ascii_case(name)
+                && table.map_or(true, |table| t.eq_ignore_ascii_case(table))
+        });
+        let column = match table {
+            Some(table) => format!("{}.{}", table, name),
+            None => name.to_string(),
+        };
+        match (found.next(), found.next()) {
+            (Some((index, _)), None) => Ok(index),
+            (None, _) => bail!("Unknown column: {}", column),
+            _ => bail!("Ambiguous column: {}", column),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TableRef {
+    name: String,
+    alias: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SelectItem {
+    // All columns, or the columns of a table.
+    Wildcard(Option<String>),
+    Expr { expr: Expr, name: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    distinct: bool,
+    items: Vec<SelectItem>,
+    from: TableRef,
+    joins: Vec<(TableRef, Expr)>,
+    filter: Option<Expr>,
+    group_by: Vec<Expr>,
+    having: Option<Expr>,
+    order_by: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+// Keys of sorting, either a column of the result or an expression.
+enum SortKey {
+    Output(usize),
+    Expr(Expr),
+}
+
+impl Query {
+    fn parse(query: &str) -> Result<Self> {
+        let mut parser = Parser::new(query)?;
+        let query = parser.query()?;
+        parser.symbol(";");
+        ensure!(parser.at_end(), "Unexpected {}", parser.describe());
+        Ok(query)
+    }
+
+    fn table_names(&self) -> Vec<&str> {
+        std::iter::once(&self.from)
+            .chain(self.joins.iter().map(|(table, _)| table))
+            .map(|table| table.name.as_str())
+            .collect()
+    }
+
+    fn execute(&self, tables: &HashMap<String, Table>) -> Result<Table> {
+        let table = |t: &TableRef| {
+            tables
+                .get(&t.name)
+                .ok_or_else(|| anyhow!("Unknown table: {}", t.name))
+        };
+        let not_aggregate = |expr: &Expr, clause: &str| {
+            ensure!(
+                !expr.has_aggregate(),
+                "Aggregate functions are not allowed in {}",
+                clause
+            );
+            Ok(())
+        };
+
+        let from = table(&self.from)?;
+        let mut schema = Schema {
+            columns: Vec::new(),
+        };
+        schema.extend(&self.from.alias, &from.columns);
+        let mut rows = from.rows.clone();
+        for (join, on) in self.joins.iter() {
+            let right = table(join)?;
+            let width = schema.columns.len();
+            schema.extend(&join.alias, &right.columns);
+            not_aggregate(on, "ON")?;
+            rows = join_rows(rows, &right.rows, &on.bind(&schema)?, width)?;
+        }
+
+        if let Some(filter) = &self.filter {
+            not_aggregate(filter, "WHERE")?;
+            let filter = filter.bind(&schema)?;
+            let mut filtered = Vec::new();
+            for row in rows.into_iter() {
+                if filter.eval(&[&row])?.truth()? == Some(true) {
+                    filtered.push(row);
+                }
+            }
+            rows = filtered;
+        }
+
+        let mut columns = Vec::new();
+        let mut items = Vec::new();
+        for item in self.items.iter() {
+            match item {
+                SelectItem::Wildcard(table) => {
+                    let start = items.len();
+                    for (index, (t, c)) in schema.columns.iter().enumerate() {
+                        if table
+                            .as_ref()
+                            .map_or(true, |table| t.eq_ignore_ascii_case(table))
+                        {
+                            columns.push(c.clone());
+                            items.push(Expr::Index(index));
+                        }
+                    }
+                    ensure!(
+                        items.len() > start,
+                        "Unknown table: {}",
+                        table.as_ref().unwrap()
+                    );
+                }
+                SelectItem::Expr { expr, name } => {
+                    columns.push(name.clone());
+                    items.push(expr.bind(&schema)?);
+                }
+            }
+        }
+        let having = self.having.as_ref().map(|e| e.bind(&schema)).transpose()?;
+
+        // Sorting by the names or positions of result columns, or else by
+        // expressions of the rows.
+        let mut sort_keys = Vec::new();
+        for (expr, _) in self.order_by.iter() {
+            let key = match expr {
+                Expr::Column(None, name) if schema.resolve(None, name).is_err() => {
+                    match columns.iter().position(|c| c.eq_ignore_ascii_case(name)) {
+                        Some(index) => SortKey::Output(index),
+                        None => SortKey::Expr(expr.bind(&schema)?),
+                    }
+                }
+                Expr::Literal(Value::Number(position)) => {
+                    let index = *position as usize;
+                    ensure!(
+                        *position >= 1.0 && index <= columns.len(),
+                        "Invalid position in ORDER BY: {}",
+                        position
+                    );
+                    SortKey::Output(index - 1)
+                }
+                _ => SortKey::Expr(expr.bind(&schema)?),
+            };
+            sort_keys.push(key);
+        }
+
+        let aggregate = !self.group_by.is_empty()
+            || having.is_some()
+            || items.iter().any(Expr::has_aggregate)
+            || sort_keys.iter().any(|key| match key {
+                SortKey::Expr(expr) => expr.has_aggregate(),
+                SortKey::Output(_) => false,
+            });
+        let groups: Vec<Vec<&[Value]>> = if aggregate {
+            let group_by = self
+                .group_by
+                .iter()
+                .map(|e| {
+                    not_aggregate(e, "GROUP BY")?;
+                    e.bind(&schema)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let mut groups: Vec<Vec<&[Value]>> = Vec::new();
+            let mut indices = HashMap::new();
+            for row in rows.iter() {
+                let key = group_by
+                    .iter()
+                    .map(|e| Ok(e.eval(&[row])?.key()))
+                    .collect::<Result<Vec<_>>>()?;
+                let index = *indices.entry(key).or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+                groups[index].push(row);
+            }
+            // Aggregates without grouping have one group, even of no rows.
+            if group_by.is_empty() && groups.is_empty() {
+                groups.push(Vec::new());
+            }
+            groups
+        } else {
+            rows.iter().map(|row| vec![row.as_slice()]).collect()
+        };
+
+        let mut results = Vec::new();
+        for group in groups.iter() {
+            if let Some(having) = &having {
+                if having.eval(group)?.truth()? != Some(true) {
+                    continue;
+                }
+            }
+            let values = items
+                .iter()
+                .map(|e| e.eval(group))
+                .collect::<Result<Vec<_>>>()?;
+            let keys = sort_keys
+                .iter()
+                .map(|key| match key {
+                    SortKey::Output(index) => Ok(values[*index].clone()),
+                    SortKey::Expr(expr) => expr.eval(group),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            results.push((values, keys));
+        }
+
+        results.sort_by(|(_, a), (_, b)| {
+            a.iter()
+                .zip(b.iter())
+                .zip(self.order_by.iter())
+                .map(|((a, b), (_, descending))| {
+                    let ordering = a.sort_cmp(b);
+                    if *descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        let mut seen = HashSet::new();
+        let rows = results
+            .into_iter()
+            .map(|(values, _)| values)
+            .filter(|values| {
+                !self.distinct || seen.insert(values.iter().map(Value::key).collect::<Vec<_>>())
+            })
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Ok(Table { columns, rows })
+    }
+}
+
+// Inner join of the rows, hashing the right rows for the equality of a left
+// and a right column.
+fn join_rows(
+    left: Vec<Vec<Value>>,
+    right: &[Vec<Value>],
+    on: &Expr,
+    width: usize,
+) -> Result<Vec<Vec<Value>>> {
+    let mut rows = Vec::new();
+    let mut push = |l: &[Value], r: &[Value]| {
+        ensure!(rows.len() < MAX_ROWS, "Too many rows in join");
+        rows.push(l.iter().chain(r.iter()).cloned().collect::<Vec<_>>());
+        Ok(())
+    };
+    let columns = match on {
+        Expr::Binary(BinaryOp::Eq, a, b) => match (a.as_ref(), b.as_ref()) {
+            (Expr::Index(a), Expr::Index(b)) if *a < width && *b >= width => Some((*a, *b - width)),
+            (Expr::Index(a), Expr::Index(b)) if *b < width && *a >= width => Some((*b, *a - width)),
+            _ => None,
+        },
+        _ => None,
+    };
+    match columns {
+        Some((left_column, right_column)) => {
+            let mut index: HashMap<Key, Vec<&[Value]>> = HashMap::new();
+            for r in right.iter() {
+                if r[right_column] != Value::Null {
+                    index.entry(r[right_column].key()).or_default().push(r);
+                }
+            }
+            for l in left.iter() {
+                if let Some(matched) = index.get(&l[left_column].key()) {
+                    for r in matched.iter() {
+                        push(l, r)?;
+                    }
+                }
+            }
+        }
+        None => {
+            for l in left.iter() {
+                for r in right.iter() {
+                    let row: Vec<Value> = l.iter().chain(r.iter()).cloned().collect();
+                    if on.eval(&[&row])?.truth()? == Some(true) {
+                        push(l, r)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(rows)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    QuotedWord(String),
+    Number(f64),
+    Text(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "<>", "!=", "||", ",", ".", "(", ")", "*", "+", "-", "/", "%", "=", "<", ">", ";",
+];
+
+// Keywords which are not taken as implicit aliases or column names.
+const KEYWORDS: &[&str] = &[
+    "SELECT", "DISTINCT", "FROM", "JOIN", "INNER", "ON", "WHERE", "GROUP", "BY", "HAVING", "ORDER",
+    "ASC", "DESC", "LIMIT", "OFFSET", "AS", "AND", "OR", "NOT", "IS", "NULL", "LIKE", "IN",
+    "BETWEEN", "TRUE", "FALSE",
+];
+
+// Tokens with their byte ranges in the query.
+fn tokenize(query: &str) -> Result<Vec<(Token, usize, usize)>> {
+    let bytes = query.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        let token = if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if query[i..].starts_with("--") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            continue;
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            Token::Word(query[start..i].to_string())
+        } else if c.is_ascii_digit()
+            || (c == b'.' && bytes.get(i + 1).map_or(false, u8::is_ascii_digit))
+        {
+            while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                i += 1;
+            }
+            let number = query[start..i]
+                .parse()
+                .map_err(|_| anyhow!("Invalid number: {}", &query[start..i]))?;
+            Token::Number(number)
+        } else if c == b'\'' || c == b'"' {
+            // Strings in single quotes and names in double quotes, with the
+            // quotes doubled.
+            let mut text = String::new();
+            i += 1;
+            loop {
+                let end = query[i..]
+                    .find(c as char)
+                    .map(|end| i + end)
+                    .ok_or_else(|| anyhow!("Unterminated quote at {}", start))?;
+                text.push_str(&query[i..end]);
+                i = end + 1;
+                if bytes.get(i) == Some(&c) {
+                    text.push(c as char);
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            if c == b'\'' {
+                Token::Text(text)
+            } else {
+                Token::QuotedWord(text)
+            }
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| query[i..].starts_with(*s))
+                .ok_or_else(|| anyhow!("Unexpected character at {}", start))?;
+            i += symbol.len();
+            Token::Symbol(symbol)
+        };
+        tokens.push((token, start, i));
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(query: &'a str) -> Result<Self> {
+        Ok(Self {
+            query,
+            tokens: tokenize(query)?,
+            pos: 0,
+        })
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self, offset: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.pos + offset)
+            .map(|(token, _, _)| token)
+    }
+
+    fn describe(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some((_, start, end)) => format!("'{}' at {}", &self.query[*start..*end], start),
+            None => "end of query".to_string(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(0), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        ensure!(
+            self.keyword(keyword),
+            "Expected {} but found {}",
+            keyword,
+            self.describe()
+        );
+        Ok(())
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(0), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        ensure!(
+            self.symbol(symbol),
+            "Expected '{}' but found {}",
+            symbol,
+            self.describe()
+        );
+        Ok(())
+    }
+
+    // Names are words other than keywords, or quoted.
+    fn name(&mut self) -> Option<String> {
+        let name = match self.peek(0)? {
+            Token::Word(word) if !KEYWORDS.iter().any(|k| word.eq_ignore_ascii_case(k)) => {
+                word.clone()
+            }
+            Token::QuotedWord(word) => word.clone(),
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(name)
+    }
+
+    fn expect_name(&mut self) -> Result<String> {
+        let description = self.describe();
+        self.name()
+            .ok_or_else(|| anyhow!("Expected a name but found {}", description))
+    }
+
+    fn alias(&mut self) -> Result<Option<String>> {
+        if self.keyword("AS") {
+            return self.expect_name().map(Some);
+        }
+        Ok(self.name())
+    }
+
+    fn count(&mut self) -> Result<usize> {
+        match self.peek(0) {
+            Some(Token::Number(number)) if number.fract() == 0.0 => {
+                let count = *number as usize;
+                self.pos += 1;
+                Ok(count)
+            }
+            _ => bail!("Expected a count but found {}", self.describe()),
+        }
+    }
+
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let mut items = vec![item(self)?];
+        while self.symbol(",") {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn query(&mut self) -> Result<Query> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.keyword("DISTINCT");
+        let items = self.list(Self::select_item)?;
+        self.expect_keyword("FROM")?;
+        let from = self.table()?;
+
+        let mut joins = Vec::new();
+        while self.is_keyword("JOIN") || self.is_keyword("INNER") {
+            self.keyword("INNER");
+            self.expect_keyword("JOIN")?;
+            let table = self.table()?;
+            self.expect_keyword("ON")?;
+            joins.push((table, self.expr()?));
+        }
+
+        let filter = if self.keyword("WHERE") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+        let group_by = if self.keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            self.list(Self::expr)?
+        } else {
+            Vec::new()
+        };
+        let having = if self.keyword("HAVING") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+        let order_by = if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            self.list(|parser| {
+                let expr = parser.expr()?;
+                let descending = parser.keyword("DESC");
+                if !descending {
+                    parser.keyword("ASC");
+                }
+                Ok((expr, descending))
+            })?
+        } else {
+            Vec::new()
+        };
+        let limit = if self.keyword("LIMIT") {
+            Some(self.count()?)
+        } else {
+            None
+        };
+        let offset = if self.keyword("OFFSET") {
+            self.count()?
+        } else {
+            0
+        };
+
+        Ok(Query {
+            distinct,
+            items,
+            from,
+            joins,
+            filter,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn table(&mut self) -> Result<TableRef> {
+        let name = self.expect_name()?;
+        let alias = self.alias()?.unwrap_or_else(|| name.clone());
+        Ok(TableRef { name, alias })
+    }
+
+    fn select_item(&mut self) -> Result<SelectItem> {
+        if self.symbol("*") {
+            return Ok(SelectItem::Wildcard(None));
+        }
+        if self.peek(1) == Some(&Token::Symbol(".")) && self.peek(2) == Some(&Token::Symbol("*")) {
+            let table = self.expect_name()?;
+            self.pos += 2;
+            return Ok(SelectItem::Wildcard(Some(table)));
+        }
+        let start = self.tokens.get(self.pos).map_or(0, |(_, start, _)| *start);
+        let expr = self.expr()?;
+        let end = self.tokens[self.pos - 1].2;
+        // Columns are named by aliases, column names or else the text of
+        // their expressions.
+        let name = match (self.alias()?, &expr) {
+            (Some(alias), _) => alias,
+            (None, Expr::Column(_, name)) => name.clone(),
+            (None, _) => self.query[start..end].to_string(),
+        };
+        Ok(SelectItem::Expr { expr, name })
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.keyword("OR") {
+            expr = Expr::Binary(BinaryOp::Or, Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.keyword("AND") {
+            expr = Expr::Binary(BinaryOp::And, Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let expr = self.additive()?;
+        let ops = [
+            ("=", BinaryOp::Eq),
+            ("!=", BinaryOp::NotEq),
+            ("<>", BinaryOp::NotEq),
+            ("<", BinaryOp::Lt),
+            ("<=", BinaryOp::LtEq),
+            (">", BinaryOp::Gt),
+            (">=", BinaryOp::GtEq),
+        ];
+        for (symbol, op) in ops.iter() {
+            if self.symbol(symbol) {
+                return Ok(Expr::Binary(
+                    *op,
+                    Box::new(expr),
+                    Box::new(self.additive()?),
+                ));
+            }
+        }
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull(Box::new(expr), negated));
+        }
+
+        let negated = self.keyword("NOT");
+        if self.keyword("LIKE") {
+            Ok(Expr::Like(
+                Box::new(expr),
+                Box::new(self.additive()?),
+                negated,
+            ))
+        } else if self.keyword("IN") {
+            self.expect_symbol("(")?;
+            let list = self.list(Self::expr)?;
+            self.expect_symbol(")")?;
+            Ok(Expr::InList(Box::new(expr), list, negated))
+        } else if self.keyword("BETWEEN") {
+            let low = self.additive()?;
+            self.expect_keyword("AND")?;
+            let high = self.additive()?;
+            let between = Expr::Binary(
+                BinaryOp::And,
+                Box::new(Expr::Binary(
+                    BinaryOp::GtEq,
+                    Box::new(expr.clone()),
+                    Box::new(low),
+                )),
+                Box::new(Expr::Binary(BinaryOp::LtEq, Box::new(expr), Box::new(high))),
+            );
+            Ok(if negated {
+                Expr::Not(Box::new(between))
+            } else {
+                between
+            })
+        } else if negated {
+            bail!("Expected LIKE, IN or BETWEEN but found {}", self.describe())
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr> {
+        let mut expr = self.multiplicative()?;
+        loop {
+            let op = if self.symbol("+") {
+                BinaryOp::Add
+            } else if self.symbol("-") {
+                BinaryOp::Sub
+            } else if self.symbol("||") {
+                BinaryOp::Concat
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.symbol("*") {
+                BinaryOp::Mul
+            } else if self.symbol("/") {
+                BinaryOp::Div
+            } else if self.symbol("%") {
+                BinaryOp::Rem
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.symbol("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.symbol("+") {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let literal = match self.peek(0) {
+            Some(Token::Number(number)) => Some(Value::Number(*number)),
+            Some(Token::Text(text)) => Some(Value::Text(text.clone())),
+            _ if self.is_keyword("NULL") => Some(Value::Null),
+            _ if self.is_keyword("TRUE") => Some(Value::boolean(true)),
+            _ if self.is_keyword("FALSE") => Some(Value::boolean(false)),
+            _ => None,
+        };
+        if let Some(literal) = literal {
+            self.pos += 1;
+            return Ok(Expr::Literal(literal));
+        }
+        if self.symbol("(") {
+            let expr = self.expr()?;
+            self.expect_symbol(")")?;
+            return Ok(expr);
+        }
+
+        let description = self.describe();
+        let name = self
+            .name()
+            .ok_or_else(|| anyhow!("Unexpected {}", description))?;
+        if self.symbol("(") {
+            let name = name.to_uppercase();
+            if name == "COUNT" && self.symbol("*") {
+                self.expect_symbol(")")?;
+                return Ok(Expr::Call {
+                    name,
+                    args: Vec::new(),
+                    distinct: false,
+                });
+            }
+            let distinct = self.keyword("DISTINCT");
+            let args = if self.symbol(")") {
+                Vec::new()
+            } else {
+                let args = self.list(Self::expr)?;
+                self.expect_symbol(")")?;
+                args
+            };
+            return Ok(Expr::Call {
+                name,
+                args,
+                distinct,
+            });
+        }
+        if self.symbol(".") {
+            let column = self.expect_name()?;
+            return Ok(Expr::Column(Some(name), column));
+        }
+        Ok(Expr::Column(None, name))
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::untrusted::fs;
+    use teaclave_crypto::*;
+    use teaclave_runtime::*;
+    use teaclave_test_utils::*;
+    use teaclave_types::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_sql_query,
+            test_sql_query_expressions,
+            test_sql_query_invalid
+        )
+    }
+
+    fn test_sql_query() {
+        let arguments = FunctionArguments::from_json(json!({
+            "query": "SELECT c.country, COUNT(*) AS orders, SUM(o.amount) AS total \
+                      FROM customers c JOIN orders o ON c.id = o.customer_id \
+                      WHERE o.amount > 5 GROUP BY c.country ORDER BY total DESC"
+        }))
+        .unwrap();
+
+        let plain_customers = "fixtures/functions/sql_query/customers.csv";
+        let plain_orders = "fixtures/functions/sql_query/orders.csv";
+        let plain_output = "fixtures/functions/sql_query/result.csv.out";
+        let expected_output = "fixtures/functions/sql_query/expected_result.csv";
+
+        let input_files = StagedFiles::new(hashmap!(
+            "customers" =>
+            StagedFileInfo::new(plain_customers, TeaclaveFile128Key::random(), FileAuthTag::mock()),
+            "orders" =>
+            StagedFileInfo::new(plain_orders, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let output_files = StagedFiles::new(hashmap!(
+            OUT_RESULT =>
+            StagedFileInfo::new(plain_output, TeaclaveFile128Key::random(), FileAuthTag::mock())
+        ));
+
+        let runtime = Box::new(RawIoRuntime::new(input_files, output_files));
+
+        let summary = SqlQuery::new().run(arguments, runtime).unwrap();
+        assert_eq!(summary, "Query result has 2 rows.");
+
+        let result = fs::read_to_string(&plain_output).unwrap();
+        let expected = fs::read_to_string(&expected_output).unwrap();
+        assert_eq!(&result[..], &expected[..]);
+    }
+
+    fn query(query: &str) -> Result<Table> {
+        let table = read_csv(
+            "name,age,city\nAlice,30,Paris\n\"Smith, Bob\",25,\nCarol,35,paris\nDave,,Rome\n"
+                .as_bytes(),
+        )?;
+        let mut tables = HashMap::new();
+        tables.insert("people".to_string(), table);
+        Query::parse(query)?.execute(&tables)
+    }
+
+    fn column(table: &Table, index: usize) -> Vec<String> {
+        table
+            .rows
+            .iter()
+            .map(|row| row[index].to_string())
+            .collect()
+    }
+
+    fn test_sql_query_expressions() {
+        let result = query("SELECT name, age * 2 FROM people WHERE age >= 30 ORDER BY 2").unwrap();
+        assert_eq!(result.columns, vec!["name", "age * 2"]);
+        assert_eq!(column(&result, 1), vec!["60", "70"]);
+
+        let result = query("SELECT * FROM people WHERE city IS NULL OR name LIKE 'D%'").unwrap();
+        assert_eq!(column(&result, 0), vec!["Smith, Bob", "Dave"]);
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert!(like(&chars("%b a"), &chars("%a")));
+        assert!(like(&chars("abcbc"), &chars("a%bc")));
+        assert!(!like(&chars("abcb"), &chars("a%bc")));
+        assert!(like(&chars(""), &chars("%%")));
+        // Repeated wildcards take no exponential time.
+        let value = chars(&"a".repeat(200));
+        assert!(!like(&value, &chars(&format!("{}b", "%a".repeat(20)))));
+
+        let result = query(
+            "SELECT LOWER(city) AS city, COUNT(*), AVG(age) FROM people \
+             WHERE city IS NOT NULL GROUP BY LOWER(city) HAVING COUNT(*) > 1",
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Text("paris".to_string()),
+                Value::Number(2.0),
+                Value::Number(32.5),
+            ]]
+        );
+
+        let result = query("SELECT COUNT(age), MAX(name) FROM people WHERE age > 100").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Number(0.0), Value::Null]]);
+
+        let result = query(
+            "SELECT DISTINCT a.city FROM people a JOIN people b ON a.age < b.age \
+             WHERE a.city IN ('Paris', 'Rome') ORDER BY a.city DESC LIMIT 1",
+        )
+        .unwrap();
+        assert_eq!(column(&result, 0), vec!["Paris"]);
+
+        let mut output = Vec::new();
+        write_csv(
+            &query("SELECT name FROM people LIMIT 1 OFFSET 1").unwrap(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(output, b"name\n\"Smith, Bob\"\n");
+    }
+
+    fn test_sql_query_invalid() {
+        assert!(query("DELETE FROM people").is_err());
+        assert!(query("SELECT name FROM people WHERE").is_err());
+        assert!(query("SELECT unknown FROM people").is_err());
+        assert!(query("SELECT name FROM people a JOIN people b ON a.age = b.age").is_err());
+        assert!(query("SELECT name FROM people WHERE COUNT(*) > 1").is_err());
+        assert!(query("SELECT name FROM missing").is_err());
+        assert!(query("SELECT name + 1 FROM people").is_err());
+    }
+}
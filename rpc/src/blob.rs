@@ -0,0 +1,164 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Transfer of blobs, e.g., function payloads and return values, in chunks
+//! over request/response RPCs. The receiver paces the transfer by asking for
+//! chunks, and resumes it from the offset received so far after a failure.
+//! Blobs are checked against their SHA-256 hashes once complete.
+
+use anyhow::{ensure, Result};
+use ring::digest;
+use std::prelude::v1::*;
+
+/// Largest chunk of a blob in a request or response.
+pub const MAX_CHUNK_LEN: u64 = 4 * 1024 * 1024;
+
+/// SHA-256 of a blob or chunk.
+pub fn blob_hash(data: &[u8]) -> Vec<u8> {
+    digest::digest(&digest::SHA256, data).as_ref().to_vec()
+}
+
+/// Checks a chunk against its hash.
+pub fn check_chunk(data: &[u8], hash: &[u8]) -> Result<()> {
+    ensure!(blob_hash(data) == hash, "chunk hash mismatched");
+    Ok(())
+}
+
+/// The sending side of a blob.
+pub struct BlobSource<'a> {
+    data: &'a [u8],
+    hash: Vec<u8>,
+}
+
+impl<'a> BlobSource<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            hash: blob_hash(data),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+
+    /// The chunk of at most `len` bytes, capped at `MAX_CHUNK_LEN`, from
+    /// `offset`. A length of 0 asks for the rest of the blob in one chunk,
+    /// for receivers which do not transfer in chunks.
+    pub fn chunk(&self, offset: u64, len: u64) -> Result<&'a [u8]> {
+        ensure!(offset <= self.len(), "invalid offset");
+        let end = match len {
+            0 => self.len(),
+            len => std::cmp::min(
+                offset.saturating_add(std::cmp::min(len, MAX_CHUNK_LEN)),
+                self.len(),
+            ),
+        };
+        Ok(&self.data[offset as usize..end as usize])
+    }
+
+    /// The blob split into numbered chunks of `chunk_len` bytes, for
+    /// transfers in parts which may be sent in any order.
+    pub fn parts(&self, chunk_len: u64) -> Result<impl Iterator<Item = (u32, &'a [u8])>> {
+        ensure!(
+            chunk_len > 0 && chunk_len <= MAX_CHUNK_LEN,
+            "invalid chunk length"
+        );
+        let data: &'a [u8] = self.data;
+        ensure!(
+            (data.len() as u64 + chunk_len - 1) / chunk_len <= u32::MAX as u64,
+            "too many parts"
+        );
+        Ok(data
+            .chunks(chunk_len as usize)
+            .enumerate()
+            .map(|(number, part)| (number as u32, part)))
+    }
+}
+
+/// The receiving side of a blob of known length and hash. Chunks are
+/// appended in order; after a failure, the transfer resumes from `offset`.
+pub struct BlobSink {
+    len: u64,
+    hash: Vec<u8>,
+    data: Vec<u8>,
+    context: digest::Context,
+}
+
+impl BlobSink {
+    pub fn new(len: u64, hash: Vec<u8>) -> Result<Self> {
+        ensure!(hash.len() == digest::SHA256_OUTPUT_LEN, "invalid blob hash");
+        Ok(Self {
+            len,
+            hash,
+            data: Vec::new(),
+            context: digest::Context::new(&digest::SHA256),
+        })
+    }
+
+    pub fn total_len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+
+    /// Bytes received so far, from which the next chunk starts.
+    pub fn offset(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.offset() == self.len
+    }
+
+    /// Appends the chunk starting at `offset`.
+    pub fn write(&mut self, offset: u64, chunk: &[u8]) -> Result<()> {
+        ensure!(offset == self.offset(), "chunk out of order");
+        ensure!(
+            chunk.len() as u64 <= self.len - self.offset(),
+            "chunk beyond the end of the blob"
+        );
+        ensure!(
+            !chunk.is_empty() || self.is_complete(),
+            "empty chunk before the end of the blob"
+        );
+        self.context.update(chunk);
+        self.data.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    /// The blob, once complete and matching the hash.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        ensure!(self.is_complete(), "blob incomplete");
+        let hash = self.context.finish();
+        ensure!(
+            hash.as_ref() == self.hash.as_slice(),
+            "blob hash mismatched"
+        );
+        Ok(self.data)
+    }
+}
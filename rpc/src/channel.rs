@@ -16,51 +16,500 @@
 // under the License.
 
 use crate::config::SgxTrustedTlsClientConfig;
-use crate::transport::{ClientTransport, SgxTrustedTlsTransport};
-use crate::Request;
+use crate::interceptor::{Interceptor, Interceptors};
+use crate::transport::{ClientTransport, SgxTrustedTlsTransport, Socket, UNIX_ADDRESS_PREFIX};
+use crate::{Request, ServiceRequest};
 use anyhow::anyhow;
 use anyhow::Result;
 use http::Uri;
+use log::debug;
 use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
 use std::sync::Arc;
+#[cfg(not(feature = "mesalock_sgx"))]
+use std::sync::Mutex;
+#[cfg(feature = "mesalock_sgx")]
+use std::sync::SgxMutex as Mutex;
+use std::time::{Duration, SystemTime};
+use teaclave_types::{TeaclaveServiceResponseError, TeaclaveServiceResponseResult};
 
-pub struct SgxTrustedTlsChannel<U, V>
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+
+type ClientTlsTransport = SgxTrustedTlsTransport<rustls::ClientSession>;
+
+/// Time after which idle connections are reconnected rather than used, below
+/// the time servers keep them open (`server::DEFAULT_IDLE_TIMEOUT`).
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(20);
+
+fn connect_transport(
+    address: &str,
+    client_config: &SgxTrustedTlsClientConfig,
+) -> Result<ClientTlsTransport> {
+    // Services are authenticated with their attestation reports rather than
+    // hostnames, so any name is fine for Unix domain sockets.
+    let hostname = if address.starts_with(UNIX_ADDRESS_PREFIX) {
+        "localhost".to_string()
+    } else {
+        let uri = address.parse::<Uri>()?;
+        uri.host()
+            .ok_or_else(|| anyhow!("Invalid hostname."))?
+            .to_string()
+    };
+    let stream = Socket::connect(address)?;
+    let hostname = webpki::DNSNameRef::try_from_ascii_str(&hostname)?;
+    let session =
+        rustls::ClientSession::new(&Arc::new(client_config.client_config.clone()), hostname);
+    let tls_stream = rustls::StreamOwned::new(session, stream);
+
+    Ok(SgxTrustedTlsTransport::new(tls_stream))
+}
+
+// Remaining time before `deadline`, fails once the deadline has passed.
+fn remaining(deadline: Option<SystemTime>) -> TeaclaveServiceResponseResult<Option<Duration>> {
+    match deadline {
+        None => Ok(None),
+        Some(deadline) => match deadline.duration_since(SystemTime::now()) {
+            Ok(remaining) if remaining > Duration::from_millis(0) => Ok(Some(remaining)),
+            _ => Err(TeaclaveServiceResponseError::DeadlineExceeded),
+        },
+    }
+}
+
+// Send `input` bounding the socket operations by `timeout`, which is also
+// passed on to the service as the remaining budget of the request.
+fn send_with_timeout<U, V>(
+    transport: &mut ClientTlsTransport,
+    input: &Request<U>,
+    max_message_len: u64,
+    chunk_len: u64,
+    compression: bool,
+    timeout: Option<Duration>,
+) -> TeaclaveServiceResponseResult<V>
 where
     U: Serialize + std::fmt::Debug,
     V: for<'de> Deserialize<'de> + std::fmt::Debug,
 {
-    transport: SgxTrustedTlsTransport<rustls::ClientSession>,
+    transport.set_max_message_len(max_message_len);
+    transport.set_chunk_len(chunk_len);
+    transport.set_compression(compression);
+    transport
+        .set_timeout(timeout)
+        .map_err(|e| TeaclaveServiceResponseError::ConnectionError(e.to_string()))?;
+    let mut request = Request {
+        metadata: input.metadata.clone(),
+        message: &input.message,
+    };
+    if let Some(timeout) = timeout {
+        request.set_timeout(timeout);
+    }
+    transport.send(request)
+}
+
+enum ChannelTransport {
+    Direct(DirectTransport),
+    Pooled(Arc<SgxTrustedTlsChannelPool>),
+}
+
+// The connection of a channel without a pool, re-established before a call if
+// it has been idle long enough for the server to close it.
+struct DirectTransport {
+    address: String,
+    client_config: SgxTrustedTlsClientConfig,
+    // Dropped after a deadline is exceeded, as a late response may still
+    // arrive on the connection.
+    transport: Option<ClientTlsTransport>,
+    last_used: SystemTime,
+}
+
+impl DirectTransport {
+    fn transport(&mut self) -> TeaclaveServiceResponseResult<&mut ClientTlsTransport> {
+        let idle = match self.last_used.elapsed() {
+            Ok(elapsed) => elapsed >= DEFAULT_IDLE_TIMEOUT,
+            Err(_) => false,
+        };
+        if idle && self.transport.is_some() {
+            debug!("Reconnect idle connection to {}", self.address);
+            let transport = connect_transport(&self.address, &self.client_config)
+                .map_err(|e| TeaclaveServiceResponseError::ConnectionError(e.to_string()))?;
+            self.transport = Some(transport);
+            self.last_used = SystemTime::now();
+        }
+        self.transport.as_mut().ok_or_else(|| {
+            TeaclaveServiceResponseError::ConnectionError(
+                "connection closed after deadline exceeded".to_string(),
+            )
+        })
+    }
+}
+
+pub struct SgxTrustedTlsChannel<U, V>
+where
+    U: Serialize + std::fmt::Debug + ServiceRequest,
+    V: for<'de> Deserialize<'de> + std::fmt::Debug,
+{
+    transport: ChannelTransport,
+    max_message_len: u64,
+    chunk_len: u64,
+    compression: bool,
+    timeout: Option<Duration>,
+    interceptors: Interceptors,
     maker: std::marker::PhantomData<(U, V)>,
 }
 
 impl<U, V> SgxTrustedTlsChannel<U, V>
 where
-    U: Serialize + std::fmt::Debug,
+    U: Serialize + std::fmt::Debug + ServiceRequest,
     V: for<'de> Deserialize<'de> + std::fmt::Debug,
 {
     pub fn new(
         address: &str,
         client_config: &SgxTrustedTlsClientConfig,
     ) -> Result<SgxTrustedTlsChannel<U, V>> {
-        let uri = address.parse::<Uri>()?;
-        let hostname = uri.host().ok_or_else(|| anyhow!("Invalid hostname."))?;
-        let stream = std::net::TcpStream::connect(address)?;
-        let hostname = webpki::DNSNameRef::try_from_ascii_str(hostname)?;
-        let session =
-            rustls::ClientSession::new(&Arc::new(client_config.client_config.clone()), hostname);
-        let tls_stream = rustls::StreamOwned::new(session, stream);
-        let transport = SgxTrustedTlsTransport::new(tls_stream);
+        let transport = connect_transport(address, client_config)?;
 
         Ok(Self {
-            transport,
+            transport: ChannelTransport::Direct(DirectTransport {
+                address: address.to_string(),
+                client_config: client_config.clone(),
+                transport: Some(transport),
+                last_used: SystemTime::now(),
+            }),
+            max_message_len: crate::protocol::DEFAULT_MAX_MESSAGE_LEN,
+            chunk_len: crate::protocol::DEFAULT_CHUNK_LEN,
+            compression: false,
+            timeout: None,
+            interceptors: Interceptors::new(),
             maker: std::marker::PhantomData::<(U, V)>,
         })
     }
 
-    pub fn invoke(
-        &mut self,
+    /// Create a channel sending requests over the connections of `pool`.
+    /// A connection is checked out once so that an unreachable endpoint is
+    /// reported here rather than on the first call, as are idempotent
+    /// requests which are not methods of the service.
+    pub fn with_pool(pool: Arc<SgxTrustedTlsChannelPool>) -> Result<SgxTrustedTlsChannel<U, V>> {
+        crate::check_method_names::<U>(pool.config.idempotent_requests.iter().map(String::as_str))?;
+        let transport = pool.checkout()?;
+        pool.checkin(transport);
+
+        Ok(Self {
+            transport: ChannelTransport::Pooled(pool),
+            max_message_len: crate::protocol::DEFAULT_MAX_MESSAGE_LEN,
+            chunk_len: crate::protocol::DEFAULT_CHUNK_LEN,
+            compression: false,
+            timeout: None,
+            interceptors: Interceptors::new(),
+            maker: std::marker::PhantomData::<(U, V)>,
+        })
+    }
+
+    /// Max length of a response, which may be sent in multiple frames.
+    pub fn max_message_len(self, max_message_len: u64) -> Self {
+        Self {
+            max_message_len,
+            ..self
+        }
+    }
+
+    /// Length of the frames large requests are split into, at most 32MB.
+    pub fn chunk_len(self, chunk_len: u64) -> Self {
+        Self {
+            chunk_len: crate::protocol::clamp_chunk_len(chunk_len),
+            ..self
+        }
+    }
+
+    /// Compress large requests once the service announces compression
+    /// support on the connection. Only enable it for services of this
+    /// version, as older ones cannot parse the announcement. Never enable it
+    /// for services whose messages carry key material or other secrets: the
+    /// length of a compressed message leaks its content to a network observer
+    /// when secrets are mixed with data chosen by an attacker.
+    pub fn compression(self, enabled: bool) -> Self {
+        Self {
+            compression: enabled,
+            ..self
+        }
+    }
+
+    /// Max time to wait for each call. A shorter budget already set in the
+    /// request metadata, e.g., forwarded from an upstream call, is kept.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Run `interceptor` on every request before it is sent, after the
+    /// interceptors added before.
+    pub fn interceptor(mut self, interceptor: Arc<dyn Interceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Interceptors run on every request, e.g., added from the endpoint.
+    pub fn interceptors(self, interceptors: Interceptors) -> Self {
+        Self {
+            interceptors,
+            ..self
+        }
+    }
+
+    pub fn invoke(&mut self, input: Request<U>) -> TeaclaveServiceResponseResult<V> {
+        let interceptors = self.interceptors.clone();
+        crate::trace::call(input, |input| {
+            interceptors.intercept(input, |input| self.send(input))
+        })
+    }
+
+    fn send(&mut self, input: Request<U>) -> TeaclaveServiceResponseResult<V> {
+        // The shortest of the budget of the request, of the channel, and of
+        // the request being handled by this thread, if any.
+        let timeout = vec![input.timeout(), self.timeout, crate::deadline::remaining()]
+            .into_iter()
+            .flatten()
+            .min();
+        let deadline = timeout.map(|timeout| SystemTime::now() + timeout);
+        let max_message_len = self.max_message_len;
+        let chunk_len = self.chunk_len;
+        let compression = self.compression;
+        match &mut self.transport {
+            ChannelTransport::Direct(direct) => {
+                let timeout = remaining(deadline)?;
+                let response = send_with_timeout(
+                    direct.transport()?,
+                    &input,
+                    max_message_len,
+                    chunk_len,
+                    compression,
+                    timeout,
+                );
+                if let Err(TeaclaveServiceResponseError::DeadlineExceeded) = response {
+                    direct.transport = None;
+                }
+                direct.last_used = SystemTime::now();
+                response
+            }
+            ChannelTransport::Pooled(pool) => {
+                pool.invoke(input, max_message_len, chunk_len, compression, deadline)
+            }
+        }
+    }
+}
+
+/// Settings of a channel pool: how many idle connections are kept, how long
+/// they stay usable, and how failed calls are retried.
+#[derive(Debug, Clone)]
+pub struct ChannelPoolConfig {
+    pool_size: usize,
+    idle_timeout: Duration,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    idempotent_requests: Vec<String>,
+}
+
+impl Default for ChannelPoolConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: 4,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(3),
+            idempotent_requests: Vec::new(),
+        }
+    }
+}
+
+impl ChannelPoolConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max number of idle connections kept for reuse.
+    pub fn pool_size(self, pool_size: usize) -> Self {
+        Self { pool_size, ..self }
+    }
+
+    /// Idle connections older than this are closed instead of reused. Keep it
+    /// below the idle timeout of the server, which closes connections without
+    /// requests.
+    pub fn idle_timeout(self, idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout,
+            ..self
+        }
+    }
+
+    /// Number of retries after a connection failure, 0 disables retrying.
+    /// Calls are only retried if the connection failed before the request
+    /// was sent, or if the request is idempotent, see `idempotent_requests`.
+    pub fn max_retries(self, max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..self
+        }
+    }
+
+    /// Delay before the first retry, doubled on every further retry up to
+    /// `max_backoff`.
+    pub fn backoff(self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            initial_backoff,
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// Names of the requests (e.g., `Get` of the storage service) which are
+    /// safe to run twice, and therefore retried even when the connection
+    /// failed after they were sent. Other requests, e.g., `CreateTask` or
+    /// `Enqueue`, may have been handled by the service already, so they are
+    /// only retried when the connection failed before they were sent.
+    pub fn idempotent_requests(self, names: &[&str]) -> Self {
+        Self {
+            idempotent_requests: names.iter().map(|name| name.to_string()).collect(),
+            ..self
+        }
+    }
+}
+
+struct IdleTransport {
+    transport: ClientTlsTransport,
+    since: SystemTime,
+}
+
+/// Attested TLS connections to one endpoint, shared by all channels created
+/// from it. Connections are reused across calls, re-established when they
+/// break, and calls failing with a connection error before the request is
+/// sent (or of idempotent requests) are retried with exponential backoff.
+pub struct SgxTrustedTlsChannelPool {
+    address: String,
+    client_config: SgxTrustedTlsClientConfig,
+    config: ChannelPoolConfig,
+    idle: Mutex<Vec<IdleTransport>>,
+}
+
+impl SgxTrustedTlsChannelPool {
+    pub fn new(
+        address: &str,
+        client_config: SgxTrustedTlsClientConfig,
+        config: ChannelPoolConfig,
+    ) -> Self {
+        Self {
+            address: address.to_string(),
+            client_config,
+            config,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ChannelPoolConfig {
+        &self.config
+    }
+
+    fn checkout(&self) -> Result<ClientTlsTransport> {
+        let mut idle = self
+            .idle
+            .lock()
+            .map_err(|_| anyhow!("channel pool lock poisoned"))?;
+        while let Some(entry) = idle.pop() {
+            match entry.since.elapsed() {
+                Ok(elapsed) if elapsed < self.config.idle_timeout => return Ok(entry.transport),
+                _ => debug!("Drop idle connection to {}", self.address),
+            }
+        }
+        drop(idle);
+
+        connect_transport(&self.address, &self.client_config)
+    }
+
+    fn checkin(&self, transport: ClientTlsTransport) {
+        if let Ok(mut idle) = self.idle.lock() {
+            if idle.len() < self.config.pool_size {
+                idle.push(IdleTransport {
+                    transport,
+                    since: SystemTime::now(),
+                });
+            }
+        }
+    }
+
+    fn invoke<U, V>(
+        &self,
         input: Request<U>,
-    ) -> teaclave_types::TeaclaveServiceResponseResult<V> {
-        self.transport.send(input)
+        max_message_len: u64,
+        chunk_len: u64,
+        compression: bool,
+        deadline: Option<SystemTime>,
+    ) -> TeaclaveServiceResponseResult<V>
+    where
+        U: Serialize + std::fmt::Debug + ServiceRequest,
+        V: for<'de> Deserialize<'de> + std::fmt::Debug,
+    {
+        let method = input.message.method_name();
+        let idempotent = self
+            .config
+            .idempotent_requests
+            .iter()
+            .any(|name| name == method);
+        let mut backoff = self.config.initial_backoff;
+        let mut retries = 0;
+        loop {
+            let timeout = remaining(deadline)?;
+            let result = self
+                .checkout()
+                .map_err(|e| TeaclaveServiceResponseError::ConnectionError(e.to_string()));
+            // Once a connection is checked out, the request may reach the
+            // service even if the connection fails afterwards.
+            let sent = result.is_ok();
+            let result = result.and_then(|mut transport| {
+                let response = send_with_timeout(
+                    &mut transport,
+                    &input,
+                    max_message_len,
+                    chunk_len,
+                    compression,
+                    timeout,
+                );
+                // Only reuse connections whose last exchange completed,
+                // either with a response or with an error from the service.
+                // Messages too large are dropped without breaking the
+                // connection.
+                match response {
+                    Ok(_)
+                    | Err(TeaclaveServiceResponseError::RequestError(_))
+                    | Err(TeaclaveServiceResponseError::ResourceExhausted(_))
+                    | Err(TeaclaveServiceResponseError::MessageTooLarge { .. }) => {
+                        self.checkin(transport)
+                    }
+                    _ => debug!("Drop connection to {}", self.address),
+                }
+                response
+            });
+
+            match result {
+                Err(TeaclaveServiceResponseError::ConnectionError(e))
+                    if retries < self.config.max_retries && (!sent || idempotent) =>
+                {
+                    debug!(
+                        "Connection to {} failed: {}, retry {} after {:?}",
+                        self.address, e, retries, backoff
+                    );
+                    if let Some(timeout) = remaining(deadline)? {
+                        if timeout <= backoff {
+                            return Err(TeaclaveServiceResponseError::DeadlineExceeded);
+                        }
+                    }
+                    std::thread::sleep(backoff);
+                    backoff = std::cmp::min(backoff * 2, self.config.max_backoff);
+                    retries += 1;
+                }
+                _ => return result,
+            }
+        }
     }
 }
@@ -31,10 +31,58 @@ use std::time::SystemTime;
 use std::untrusted::time::SystemTimeEx;
 
 use teaclave_attestation::report::AttestationReport;
-use teaclave_attestation::verifier::AttestationReportVerifier;
+use teaclave_attestation::verifier::{AttestationReportVerifier, QuoteStatusPolicy};
 use teaclave_attestation::AttestedTlsConfig;
+use teaclave_config::TlsConfig;
 use teaclave_types::EnclaveAttr;
 
+/// Restrictions on the cipher suites, protocol versions and session
+/// resumption of TLS connections.
+#[derive(Clone, Debug, Default)]
+pub struct TlsPolicy {
+    config: TlsConfig,
+}
+
+impl TlsPolicy {
+    pub fn new(config: TlsConfig) -> Self {
+        Self { config }
+    }
+
+    /// Create TLS policy from Teaclave runtime configuration.
+    pub fn from_teaclave_config(config: &teaclave_config::RuntimeConfig) -> Self {
+        Self::new(config.tls.clone())
+    }
+
+    fn cipher_suites(&self) -> Result<Vec<&'static rustls::SupportedCipherSuite>> {
+        let suites: Vec<&'static rustls::SupportedCipherSuite> =
+            if self.config.cipher_suites.is_empty() {
+                rustls::ALL_CIPHERSUITES.to_vec()
+            } else {
+                self.config
+                    .cipher_suites
+                    .iter()
+                    .map(|name| {
+                        rustls::ALL_CIPHERSUITES
+                            .iter()
+                            .find(|suite| format!("{:?}", suite.suite) == *name)
+                            .copied()
+                            .ok_or_else(|| anyhow!("unsupported cipher suite: {}", name))
+                    })
+                    .collect::<Result<_>>()?
+            };
+
+        if self.config.tls13_only
+            && !suites
+                .iter()
+                .any(|suite| suite.usable_for_version(rustls::ProtocolVersion::TLSv1_3))
+        {
+            bail!("no TLS 1.3 cipher suite is allowed");
+        }
+
+        Ok(suites)
+    }
+}
+
 #[derive(Clone)]
 pub struct SgxTrustedTlsServerConfig {
     server_config: rustls::ServerConfig,
@@ -87,21 +135,65 @@ impl SgxTrustedTlsServerConfig {
     // Disable this function for non-SGX targets.
     #[cfg(feature = "mesalock_sgx")]
     pub fn attestation_report_verifier(
-        mut self,
+        self,
         accepted_enclave_attrs: Vec<EnclaveAttr>,
         root_ca: &[u8],
         verifier: fn(&AttestationReport) -> bool,
     ) -> Result<Self> {
-        let verifier = Arc::new(AttestationReportVerifier::new(
+        self.attestation_report_verifier_with_policy(
             accepted_enclave_attrs,
             root_ca,
             verifier,
-        ));
+            QuoteStatusPolicy::default(),
+        )
+    }
+
+    // Disable this function for non-SGX targets.
+    #[cfg(feature = "mesalock_sgx")]
+    pub fn attestation_report_verifier_with_policy(
+        mut self,
+        accepted_enclave_attrs: Vec<EnclaveAttr>,
+        root_ca: &[u8],
+        verifier: fn(&AttestationReport) -> bool,
+        quote_status_policy: QuoteStatusPolicy,
+    ) -> Result<Self> {
+        let verifier = Arc::new(
+            AttestationReportVerifier::new(accepted_enclave_attrs, root_ca, verifier)
+                .quote_status_policy(quote_status_policy),
+        );
 
         self.server_config.set_client_certificate_verifier(verifier);
         Ok(Self { ..self })
     }
 
+    /// Request the attested TLS certificates of clients without requiring
+    /// them, for the attestation gate of servers whose clients are not
+    /// enclaves, e.g., those of the API endpoints.
+    // Disable this function for non-SGX targets.
+    #[cfg(feature = "mesalock_sgx")]
+    pub fn optional_client_cert(mut self, root_ca: &[u8]) -> Self {
+        self.server_config
+            .set_client_certificate_verifier(crate::gate::OptionalClientAuth::new(root_ca));
+        Self { ..self }
+    }
+
+    pub fn tls_policy(mut self, tls_policy: TlsPolicy) -> Result<Self> {
+        self.server_config.ciphersuites = tls_policy.cipher_suites()?;
+        if tls_policy.config.tls13_only {
+            self.server_config.versions = vec![rustls::ProtocolVersion::TLSv1_3];
+        }
+        self.server_config.session_storage = match tls_policy.config.session_cache_size {
+            0 => Arc::new(rustls::NoServerSessionStorage {}),
+            size => rustls::ServerSessionMemoryCache::new(size),
+        };
+        // Servers issue no tickets by default.
+        if tls_policy.config.session_tickets {
+            self.server_config.ticketer = rustls::Ticketer::new();
+        }
+
+        Ok(Self { ..self })
+    }
+
     pub fn server_config(&self) -> Arc<rustls::ServerConfig> {
         Arc::new(self.server_config.clone())
     }
@@ -139,6 +231,7 @@ impl SgxTrustedTlsServerConfig {
     }
 }
 
+#[derive(Clone)]
 pub struct SgxTrustedTlsClientConfig {
     pub client_config: rustls::ClientConfig,
     pub attested_tls_config: Option<Arc<RwLock<AttestedTlsConfig>>>,
@@ -194,16 +287,30 @@ impl SgxTrustedTlsClientConfig {
     }
 
     pub fn attestation_report_verifier(
-        mut self,
+        self,
         accepted_enclave_attrs: Vec<EnclaveAttr>,
         root_ca: &[u8],
         verifier: fn(&AttestationReport) -> bool,
     ) -> Self {
-        let verifier = Arc::new(AttestationReportVerifier::new(
+        self.attestation_report_verifier_with_policy(
             accepted_enclave_attrs,
             root_ca,
             verifier,
-        ));
+            QuoteStatusPolicy::default(),
+        )
+    }
+
+    pub fn attestation_report_verifier_with_policy(
+        mut self,
+        accepted_enclave_attrs: Vec<EnclaveAttr>,
+        root_ca: &[u8],
+        verifier: fn(&AttestationReport) -> bool,
+        quote_status_policy: QuoteStatusPolicy,
+    ) -> Self {
+        let verifier = Arc::new(
+            AttestationReportVerifier::new(accepted_enclave_attrs, root_ca, verifier)
+                .quote_status_policy(quote_status_policy),
+        );
         self.client_config
             .dangerous()
             .set_certificate_verifier(verifier);
@@ -220,6 +327,20 @@ impl SgxTrustedTlsClientConfig {
         Self { ..self }
     }
 
+    pub fn tls_policy(mut self, tls_policy: TlsPolicy) -> Result<Self> {
+        self.client_config.ciphersuites = tls_policy.cipher_suites()?;
+        if tls_policy.config.tls13_only {
+            self.client_config.versions = vec![rustls::ProtocolVersion::TLSv1_3];
+        }
+        self.client_config.session_persistence = match tls_policy.config.session_cache_size {
+            0 => Arc::new(rustls::NoClientSessionStorage {}),
+            size => rustls::ClientSessionMemoryCache::new(size),
+        };
+        self.client_config.enable_tickets = tls_policy.config.session_tickets;
+
+        Ok(Self { ..self })
+    }
+
     pub fn from_attested_tls_config(
         attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
     ) -> Result<Self> {
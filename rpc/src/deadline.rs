@@ -0,0 +1,78 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Propagation of the time budget of requests. Servers make the deadline of
+//! each request (from its `timeout_ms` metadata) the current one of the
+//! handling thread, so that calls made by the handler on any channel are
+//! bounded by the remaining budget of the caller.
+
+use crate::Request;
+use std::cell::Cell;
+use std::prelude::v1::*;
+use std::time::{Duration, SystemTime};
+use teaclave_types::{TeaclaveServiceResponseError, TeaclaveServiceResponseResult};
+
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+
+thread_local! {
+    static CURRENT: Cell<Option<SystemTime>> = Cell::new(None);
+}
+
+/// Remaining time before the deadline of the request handled by the current
+/// thread, if any. Zero once the deadline has passed.
+pub fn remaining() -> Option<Duration> {
+    CURRENT.with(|current| current.get()).map(|deadline| {
+        deadline
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+    })
+}
+
+/// Makes `deadline` the current one of the thread until the guard is dropped.
+pub fn enter(deadline: Option<SystemTime>) -> DeadlineGuard {
+    let previous = CURRENT.with(|current| current.replace(deadline));
+    DeadlineGuard { previous }
+}
+
+pub struct DeadlineGuard {
+    previous: Option<SystemTime>,
+}
+
+impl Drop for DeadlineGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| current.set(previous));
+    }
+}
+
+// Handles a request within its time budget: requests arriving without any
+// budget left are rejected, and calls made by the handler are bounded by the
+// rest of it.
+pub(crate) fn serve<V, R, F>(request: Request<V>, handle: F) -> TeaclaveServiceResponseResult<R>
+where
+    F: FnOnce(Request<V>) -> TeaclaveServiceResponseResult<R>,
+{
+    let timeout = match request.timeout() {
+        Some(timeout) if timeout == Duration::from_millis(0) => {
+            return Err(TeaclaveServiceResponseError::DeadlineExceeded)
+        }
+        timeout => timeout,
+    };
+    let _deadline = enter(timeout.map(|timeout| SystemTime::now() + timeout));
+    handle(request)
+}
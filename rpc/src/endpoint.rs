@@ -15,15 +15,26 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::channel::SgxTrustedTlsChannel;
+use crate::channel::{ChannelPoolConfig, SgxTrustedTlsChannel, SgxTrustedTlsChannelPool};
 use crate::config::SgxTrustedTlsClientConfig;
+use crate::interceptor::{Interceptor, Interceptors};
+use crate::ServiceRequest;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::Duration;
+use teaclave_config::MessageLimitsConfig;
 
 pub struct Endpoint {
     url: String,
     config: SgxTrustedTlsClientConfig,
+    pool: Option<Arc<SgxTrustedTlsChannelPool>>,
+    timeout: Option<Duration>,
+    max_message_len: u64,
+    chunk_len: u64,
+    compression: bool,
+    interceptors: Interceptors,
 }
 
 impl Endpoint {
@@ -32,21 +43,113 @@ impl Endpoint {
         Self {
             url: url.to_string(),
             config,
+            pool: None,
+            timeout: None,
+            max_message_len: crate::protocol::DEFAULT_MAX_MESSAGE_LEN,
+            chunk_len: crate::protocol::DEFAULT_CHUNK_LEN,
+            compression: false,
+            interceptors: Interceptors::new(),
         }
     }
 
     pub fn connect<U, V>(&self) -> Result<SgxTrustedTlsChannel<U, V>>
     where
-        U: Serialize + std::fmt::Debug,
+        U: Serialize + std::fmt::Debug + ServiceRequest,
         V: for<'de> Deserialize<'de> + std::fmt::Debug,
     {
-        SgxTrustedTlsChannel::<U, V>::new(&self.url, &self.config)
+        let channel = match &self.pool {
+            Some(pool) => SgxTrustedTlsChannel::<U, V>::with_pool(pool.clone())?,
+            None => SgxTrustedTlsChannel::<U, V>::new(&self.url, &self.config)?,
+        };
+        let channel = channel
+            .max_message_len(self.max_message_len)
+            .chunk_len(self.chunk_len)
+            .compression(self.compression)
+            .interceptors(self.interceptors.clone());
+        match self.timeout {
+            Some(timeout) => Ok(channel.timeout(timeout)),
+            None => Ok(channel),
+        }
     }
 
     pub fn config(self, config: SgxTrustedTlsClientConfig) -> Self {
+        let url = &self.url;
+        let pool = self
+            .pool
+            .as_ref()
+            .map(|pool| Self::new_pool(url, &config, pool.config().clone()));
         Self {
-            url: self.url,
             config,
+            pool,
+            ..self
+        }
+    }
+
+    /// Default timeout of calls on channels connected from this endpoint.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Max length of the messages on channels connected from this endpoint.
+    pub fn max_message_len(self, max_message_len: u64) -> Self {
+        Self {
+            max_message_len,
+            ..self
         }
     }
+
+    /// Length of the frames large requests are split into, at most 32MB.
+    pub fn chunk_len(self, chunk_len: u64) -> Self {
+        Self {
+            chunk_len: crate::protocol::clamp_chunk_len(chunk_len),
+            ..self
+        }
+    }
+
+    /// Message limits of the service from the runtime config.
+    pub fn message_limits(self, limits: &MessageLimitsConfig) -> Self {
+        self.max_message_len(limits.max_message_len)
+            .chunk_len(limits.frame_len)
+    }
+
+    /// Compress large requests to the service if it supports compression,
+    /// see `SgxTrustedTlsChannel::compression`.
+    pub fn compression(self, enabled: bool) -> Self {
+        Self {
+            compression: enabled,
+            ..self
+        }
+    }
+
+    /// Run `interceptor` on every request of channels connected from this
+    /// endpoint, e.g., to add the credentials of the user.
+    pub fn interceptor(mut self, interceptor: Arc<dyn Interceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Share a pool of connections among all channels connected from this
+    /// endpoint instead of opening a new connection for each of them.
+    pub fn pool(self, pool_config: ChannelPoolConfig) -> Self {
+        let pool = Self::new_pool(&self.url, &self.config, pool_config);
+        Self {
+            pool: Some(pool),
+            ..self
+        }
+    }
+
+    fn new_pool(
+        url: &str,
+        config: &SgxTrustedTlsClientConfig,
+        pool_config: ChannelPoolConfig,
+    ) -> Arc<SgxTrustedTlsChannelPool> {
+        Arc::new(SgxTrustedTlsChannelPool::new(
+            url,
+            config.clone(),
+            pool_config,
+        ))
+    }
 }
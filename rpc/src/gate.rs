@@ -0,0 +1,228 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Attestation gate of servers: sensitive requests are only handled for
+//! peers whose attested TLS client certificates show one of the accepted
+//! enclaves, e.g., an admin tool. Requests are checked by the transport
+//! before they reach the service, so handlers need no checks of their own.
+
+use anyhow::Result;
+use log::{debug, info};
+use std::collections::HashSet;
+use std::prelude::v1::*;
+use std::sync::Arc;
+use teaclave_attestation::report::AttestationReport;
+use teaclave_attestation::verifier::universal_quote_verifier;
+use teaclave_types::{
+    EnclaveMeasurement, MrEnclave, MrSigner, TeaclaveServiceResponseError,
+    TeaclaveServiceResponseResult,
+};
+
+struct AcceptedEnclave {
+    mr_signer: MrSigner,
+    // Any enclave of the signer if None
+    mr_enclave: Option<MrEnclave>,
+}
+
+impl AcceptedEnclave {
+    fn new(mr_signer: &str, mr_enclave: Option<&str>) -> Result<Self> {
+        let mr_enclave = match mr_enclave {
+            Some(mr_enclave) => Some(mr_enclave.parse()?),
+            None => None,
+        };
+        Ok(Self {
+            mr_signer: mr_signer.parse()?,
+            mr_enclave,
+        })
+    }
+
+    fn accepts(&self, measurement: &EnclaveMeasurement) -> bool {
+        self.mr_signer == measurement.mr_signer
+            && self
+                .mr_enclave
+                .map_or(true, |mr_enclave| mr_enclave == measurement.mr_enclave)
+    }
+}
+
+// Methods gated to the same enclaves.
+struct GateRule {
+    methods: HashSet<String>,
+    enclaves: Vec<AcceptedEnclave>,
+}
+
+impl GateRule {
+    fn accepts(&self, measurement: &EnclaveMeasurement) -> bool {
+        self.enclaves.iter().any(|e| e.accepts(measurement))
+    }
+}
+
+/// Methods and the enclaves given by their hex-encoded MRSIGNER and MRENCLAVE
+/// (any enclave of the signer if None) they are gated to.
+pub type GateRuleSpec<'a> = (&'a [&'a str], Vec<(&'a str, Option<&'a str>)>);
+
+pub struct AttestationGate {
+    rules: Vec<GateRule>,
+    root_ca: Vec<u8>,
+}
+
+impl AttestationGate {
+    /// Gate of `methods`, accepting the enclaves given by their hex-encoded
+    /// MRSIGNER and MRENCLAVE (any enclave of the signer if None). Services
+    /// take both from the build config, as the host must not choose them.
+    /// Certificates of peers are verified with the root CA of the
+    /// attestation service.
+    pub fn new<'a>(
+        methods: &'a [&'a str],
+        enclaves: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+        root_ca: &[u8],
+    ) -> Result<Arc<Self>> {
+        Self::with_rules(&[(methods, enclaves.into_iter().collect())], root_ca)
+    }
+
+    /// Gate of the methods of each rule to its own enclaves. A method of
+    /// several rules is accepted for the enclaves of any of them.
+    pub fn with_rules(rules: &[GateRuleSpec<'_>], root_ca: &[u8]) -> Result<Arc<Self>> {
+        let rules = rules
+            .iter()
+            .map(|(methods, enclaves)| {
+                let enclaves = enclaves
+                    .iter()
+                    .map(|(mr_signer, mr_enclave)| AcceptedEnclave::new(mr_signer, *mr_enclave))
+                    .collect::<Result<_>>()?;
+                Ok(GateRule {
+                    methods: methods.iter().map(|method| method.to_string()).collect(),
+                    enclaves,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Arc::new(Self {
+            rules,
+            root_ca: root_ca.to_vec(),
+        }))
+    }
+
+    /// Whether no request is gated.
+    pub fn is_empty(&self) -> bool {
+        self.rules.iter().all(|rule| rule.methods.is_empty())
+    }
+
+    pub(crate) fn methods(&self) -> impl Iterator<Item = &str> {
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.methods.iter().map(String::as_str))
+    }
+
+    pub(crate) fn is_gated(&self, method: &str) -> bool {
+        self.rules.iter().any(|rule| rule.methods.contains(method))
+    }
+
+    /// Measurement of the enclave of a peer, if it presented a certificate
+    /// with a valid attestation report.
+    pub(crate) fn peer_measurement(
+        &self,
+        certs: Option<Vec<rustls::Certificate>>,
+    ) -> Option<EnclaveMeasurement> {
+        let cert = certs?.into_iter().next()?;
+        let report = match AttestationReport::from_cert(&cert.0, &self.root_ca) {
+            Ok(report) => report,
+            Err(e) => {
+                debug!("Invalid client certificate: {:?}", e);
+                return None;
+            }
+        };
+        if !universal_quote_verifier(&report) {
+            return None;
+        }
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        Some(EnclaveMeasurement::new(
+            enclave_report.mr_enclave,
+            enclave_report.mr_signer,
+        ))
+    }
+
+    /// Rejects a gated request unless the peer is an accepted enclave.
+    pub(crate) fn check(
+        &self,
+        method: &str,
+        peer: Option<&EnclaveMeasurement>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        if !self.is_gated(method) {
+            return Ok(());
+        }
+        let accepted = peer.map_or(false, |peer| {
+            self.rules
+                .iter()
+                .any(|rule| rule.methods.contains(method) && rule.accepts(peer))
+        });
+        match peer {
+            Some(peer) if accepted => {
+                info!(target: "audit", "{} request from enclave {:?}", method, peer);
+                Ok(())
+            }
+            _ => {
+                info!(target: "audit", "{} request denied, peer {:?}", method, peer);
+                Err(TeaclaveServiceResponseError::RequestError(
+                    "permission denied".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Requests client certificates without requiring them, so that the peers
+/// of servers without mutual attestation, e.g., those of the API endpoints,
+/// can present their enclaves to the attestation gate.
+pub(crate) struct OptionalClientAuth {
+    root_ca: Vec<u8>,
+}
+
+impl OptionalClientAuth {
+    pub(crate) fn new(root_ca: &[u8]) -> Arc<Self> {
+        Arc::new(Self {
+            root_ca: root_ca.to_vec(),
+        })
+    }
+}
+
+impl rustls::ClientCertVerifier for OptionalClientAuth {
+    fn offer_client_auth(&self) -> bool {
+        true
+    }
+
+    fn client_auth_mandatory(&self) -> bool {
+        false
+    }
+
+    fn client_auth_root_subjects(&self) -> rustls::DistinguishedNames {
+        rustls::DistinguishedNames::new()
+    }
+
+    fn verify_client_cert(
+        &self,
+        certs: &[rustls::Certificate],
+    ) -> std::result::Result<rustls::ClientCertVerified, rustls::TLSError> {
+        if certs.len() != 1 {
+            return Err(rustls::TLSError::NoCertificatesPresented);
+        }
+        match AttestationReport::from_cert(&certs[0].0, &self.root_ca) {
+            Ok(_) => Ok(rustls::ClientCertVerified::assertion()),
+            Err(_) => Err(rustls::TLSError::WebPKIError(
+                webpki::Error::ExtensionValueInvalid,
+            )),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{Request, ServiceRequest, TeaclaveService};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::prelude::v1::*;
+use std::sync::Arc;
+use teaclave_types::{TeaclaveServiceResponseError, TeaclaveServiceResponseResult};
+
+/// Middleware of servers and channels, e.g., for injecting credentials,
+/// logging, metrics or quota enforcement. Interceptors are run in the order
+/// they are added for requests, and in the reverse order for responses.
+pub trait Interceptor: Send + Sync {
+    /// Called before a request is handled by the service (on servers) or sent
+    /// (on channels), with the name of its method, e.g., `UserLogin`. The
+    /// metadata can be modified; returning an error rejects the request with
+    /// it, and the following interceptors are skipped.
+    fn on_request(
+        &self,
+        _metadata: &mut HashMap<String, String>,
+        _method: &str,
+        _message: &dyn Debug,
+    ) -> TeaclaveServiceResponseResult<()> {
+        Ok(())
+    }
+
+    /// Called with the response of a request which passed `on_request`, along
+    /// with the metadata of that request. Returning an error replaces the
+    /// response with it.
+    fn on_response(
+        &self,
+        _metadata: &HashMap<String, String>,
+        _response: Result<&dyn Debug, &TeaclaveServiceResponseError>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        Ok(())
+    }
+}
+
+/// An ordered list of interceptors, cheap to clone.
+#[derive(Clone, Default)]
+pub struct Interceptors(Vec<Arc<dyn Interceptor>>);
+
+impl Interceptors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.0.push(interceptor);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Runs `call` on the request once all interceptors accepted it.
+    pub(crate) fn intercept<T, R, F>(
+        &self,
+        mut request: Request<T>,
+        call: F,
+    ) -> TeaclaveServiceResponseResult<R>
+    where
+        T: Debug + ServiceRequest,
+        R: Debug,
+        F: FnOnce(Request<T>) -> TeaclaveServiceResponseResult<R>,
+    {
+        if self.is_empty() {
+            return call(request);
+        }
+        let method = request.message.method_name();
+        for interceptor in self.0.iter() {
+            interceptor.on_request(&mut request.metadata, method, &request.message)?;
+        }
+        let metadata = request.metadata.clone();
+        let mut response = call(request);
+        for interceptor in self.0.iter().rev() {
+            let result = match &response {
+                Ok(r) => interceptor.on_response(&metadata, Ok(r)),
+                Err(e) => interceptor.on_response(&metadata, Err(e)),
+            };
+            if let Err(e) = result {
+                response = Err(e);
+            }
+        }
+        response
+    }
+}
+
+// Runs the interceptors of a server around the service.
+#[derive(Clone)]
+pub(crate) struct InterceptedService<X> {
+    service: X,
+    interceptors: Interceptors,
+}
+
+impl<X> InterceptedService<X> {
+    pub(crate) fn new(service: X, interceptors: Interceptors) -> Self {
+        Self {
+            service,
+            interceptors,
+        }
+    }
+}
+
+impl<X, V, U> TeaclaveService<V, U> for InterceptedService<X>
+where
+    X: TeaclaveService<V, U>,
+    U: Serialize + Debug,
+    V: for<'de> Deserialize<'de> + Debug + ServiceRequest,
+{
+    fn handle_request(&self, request: Request<V>) -> TeaclaveServiceResponseResult<U> {
+        self.interceptors
+            .intercept(request, |request| self.service.handle_request(request))
+    }
+}
+
+/// Adds fixed metadata to every request, e.g., the `id` and `token` of the
+/// user on channels of clients. Existing entries are kept.
+pub struct MetadataInterceptor {
+    metadata: HashMap<String, String>,
+}
+
+impl MetadataInterceptor {
+    pub fn new(metadata: HashMap<String, String>) -> Self {
+        Self { metadata }
+    }
+}
+
+impl Interceptor for MetadataInterceptor {
+    fn on_request(
+        &self,
+        metadata: &mut HashMap<String, String>,
+        _method: &str,
+        _message: &dyn Debug,
+    ) -> TeaclaveServiceResponseResult<()> {
+        for (key, value) in self.metadata.iter() {
+            metadata
+                .entry(key.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Logs every request and whether it succeeded. Only the name of the request
+/// and the metadata keys are logged, as the rest may contain credentials.
+pub struct LoggingInterceptor {
+    name: String,
+}
+
+impl LoggingInterceptor {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl Interceptor for LoggingInterceptor {
+    fn on_request(
+        &self,
+        metadata: &mut HashMap<String, String>,
+        method: &str,
+        _message: &dyn Debug,
+    ) -> TeaclaveServiceResponseResult<()> {
+        info!(
+            "{}: request {}, metadata {:?}",
+            self.name,
+            method,
+            metadata.keys().collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    fn on_response(
+        &self,
+        _metadata: &HashMap<String, String>,
+        response: Result<&dyn Debug, &TeaclaveServiceResponseError>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        match response {
+            Ok(_) => info!("{}: ok", self.name),
+            Err(e) => info!("{}: error {:?}", self.name, e),
+        }
+        Ok(())
+    }
+}
@@ -34,13 +34,44 @@ where
     ) -> std::result::Result<U, TeaclaveServiceResponseError>;
 }
 
+/// Requests of a service, an enum with a variant for each method.
+pub trait ServiceRequest {
+    /// Names of the methods of the service, e.g., `UserLogin`, as given to
+    /// attestation gates and to the retries of channel pools.
+    const METHOD_NAMES: &'static [&'static str];
+
+    /// Name of the method of the request.
+    fn method_name(&self) -> &'static str;
+}
+
+/// Fails for names which are not methods of the service of `T`, e.g., in
+/// lists of gated requests, so that typos are found at startup.
+pub fn check_method_names<'a, T: ServiceRequest>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<()> {
+    for name in names {
+        anyhow::ensure!(
+            T::METHOD_NAMES.contains(&name),
+            "unknown method {} of the service",
+            name
+        );
+    }
+    Ok(())
+}
+
+pub mod blob;
 pub mod channel;
 pub mod config;
+pub mod deadline;
 pub mod endpoint;
+pub mod gate;
+pub mod interceptor;
 mod protocol;
+pub mod rate_limit;
 mod request;
-pub use request::{IntoRequest, Request};
+pub use request::{IntoRequest, Request, PEER_ADDR_METADATA_KEY, TIMEOUT_METADATA_KEY};
 pub use teaclave_rpc_proc_macro::into_request;
 pub mod server;
+pub mod trace;
 mod transport;
 mod utils;
@@ -29,6 +29,8 @@ pub enum ProtocolError {
     IoError(#[from] io::Error),
     #[error("SerdeError")]
     SerdeError(#[from] serde_json::error::Error),
+    #[error("Message of {len} bytes exceeds the max length {max_len}")]
+    MessageTooLarge { len: u64, max_len: u64 },
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -36,12 +38,19 @@ pub enum ProtocolError {
 impl From<ProtocolError> for TeaclaveServiceResponseError {
     fn from(error: ProtocolError) -> Self {
         match error {
-            ProtocolError::IoError(e) => {
-                TeaclaveServiceResponseError::ConnectionError(format!("{}", e))
-            }
+            ProtocolError::IoError(e) => match e.kind() {
+                // Reported by sockets with a read/write timeout set.
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                    TeaclaveServiceResponseError::DeadlineExceeded
+                }
+                _ => TeaclaveServiceResponseError::ConnectionError(format!("{}", e)),
+            },
             ProtocolError::SerdeError(_) => {
                 TeaclaveServiceResponseError::InternalError("serde".to_string())
             }
+            ProtocolError::MessageTooLarge { len, max_len } => {
+                TeaclaveServiceResponseError::MessageTooLarge { len, max_len }
+            }
             ProtocolError::Other(_) => {
                 TeaclaveServiceResponseError::InternalError("internal".to_string())
             }
@@ -49,12 +58,128 @@ impl From<ProtocolError> for TeaclaveServiceResponseError {
     }
 }
 
+/// Flag in the frame header indicating that more frames of the same message
+/// follow.
+pub(crate) const CONTINUATION_FLAG: u64 = 1 << 63;
+
+/// Flag in the header of the first frame indicating that the message is
+/// compressed with zlib.
+pub(crate) const COMPRESSED_FLAG: u64 = 1 << 62;
+
+/// Flag in the header of the first frame indicating that the sender accepts
+/// compressed messages on this connection.
+pub(crate) const ACCEPT_COMPRESSION_FLAG: u64 = 1 << 61;
+
+/// Bits of the frame header holding the length of the frame.
+pub(crate) const FRAME_LEN_MASK: u64 = ACCEPT_COMPRESSION_FLAG - 1;
+
+/// Messages shorter than this are not worth compressing.
+pub(crate) const MIN_COMPRESSION_LEN: usize = 1_024;
+
+/// Default length of each frame when a large message is split into frames.
+pub(crate) const DEFAULT_CHUNK_LEN: u64 = 1_024 * 1_024;
+
+/// Max length of a frame accepted from peers, which also bounds the length
+/// messages are split into.
+pub(crate) const MAX_FRAME_LEN: u64 = 32 * 1_024 * 1_024;
+
+/// Min length of the frames messages are split into, so that a message of the
+/// max length does not need more than `MAX_FRAMES` frames.
+pub(crate) const MIN_CHUNK_LEN: u64 = 4 * 1_024;
+
+/// Max number of frames of a message accepted from peers, including the frames
+/// of messages dropped for exceeding the max message length.
+pub(crate) const MAX_FRAMES: u64 = 64 * 1_024;
+
+/// Length of the frames to split messages into, at least `MIN_CHUNK_LEN` and at
+/// most `MAX_FRAME_LEN`.
+pub(crate) fn clamp_chunk_len(chunk_len: u64) -> u64 {
+    std::cmp::min(std::cmp::max(chunk_len, MIN_CHUNK_LEN), MAX_FRAME_LEN)
+}
+
+/// Default max length of a message which may consist of multiple frames. The
+/// whole message is buffered before it is parsed, so this bounds the memory
+/// each connection can take in the enclave heap; it is the max frame length,
+/// i.e., the max message length before messages were split into frames.
+pub(crate) const DEFAULT_MAX_MESSAGE_LEN: u64 = MAX_FRAME_LEN;
+
+/// Compression state of a connection. A message is compressed only if both
+/// sides have compression enabled: each side announces it with
+/// `ACCEPT_COMPRESSION_FLAG` in the messages it sends, so peers without
+/// compression support never receive compressed messages.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Compression {
+    pub enabled: bool,
+    pub peer_enabled: bool,
+}
+
+impl Compression {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            peer_enabled: false,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.enabled && self.peer_enabled
+    }
+}
+
+fn compress(buf: &[u8]) -> Vec<u8> {
+    deflate::deflate_bytes_zlib(buf)
+}
+
+// Decompress `buf`, failing as soon as the output exceeds `max_len`.
+fn decompress(buf: &[u8], max_len: u64) -> std::result::Result<Vec<u8>, ProtocolError> {
+    let mut stream = inflate::InflateStream::from_zlib();
+    let mut output = Vec::new();
+    let mut offset = 0;
+    loop {
+        let (consumed, chunk) = stream
+            .update(&buf[offset..])
+            .map_err(|e| anyhow::anyhow!("Decompression error: {}", e))?;
+        if consumed == 0 && chunk.is_empty() {
+            break;
+        }
+        offset += consumed;
+        output.extend_from_slice(chunk);
+        if output.len() as u64 > max_len {
+            return Err(ProtocolError::MessageTooLarge {
+                len: output.len() as u64,
+                max_len,
+            });
+        }
+    }
+
+    Ok(output)
+}
+
+/// Each message is sent as one or more frames. A frame starts with an 8-byte
+/// big-endian header containing the length of the frame, whose most
+/// significant bit is set if more frames of the message follow. Messages
+/// shorter than the chunk length are sent in a single frame, which is the same
+/// as the original format. This is not streaming: the sender serializes the
+/// whole message before writing its frames, and the receiver buffers all of
+/// them before parsing, so both hold the whole message in memory. Frames only
+/// let the receiver reject a message as soon as it exceeds the max length,
+/// instead of trusting a length announced up front.
+/// Flags of the whole message (`COMPRESSED_FLAG` and
+/// `ACCEPT_COMPRESSION_FLAG`) are set in the header of its first frame.
+/// The frames of a message exceeding the max message length are read and
+/// dropped, so that the connection can be used for the following messages,
+/// unless the message has more than `MAX_FRAMES` frames or is more than twice
+/// the max message length, in which case the read fails and the connection is
+/// dropped. Messages exceeding it are not sent at all.
 pub(crate) struct JsonProtocol<'a, T>
 where
     T: io::Read + io::Write,
 {
     pub transport: &'a mut T,
     max_frame_len: u64,
+    max_message_len: u64,
+    chunk_len: u64,
+    compression: Compression,
 }
 
 impl<'a, T> JsonProtocol<'a, T>
@@ -64,8 +189,37 @@ where
     pub fn new(transport: &'a mut T) -> JsonProtocol<'a, T> {
         Self {
             transport,
-            // Default max frame length is 32MB
-            max_frame_len: 32 * 1_024 * 1_024,
+            max_frame_len: MAX_FRAME_LEN,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            chunk_len: DEFAULT_CHUNK_LEN,
+            compression: Compression::default(),
+        }
+    }
+
+    pub fn chunk_len(self, chunk_len: u64) -> Self {
+        Self {
+            chunk_len: clamp_chunk_len(chunk_len),
+            ..self
+        }
+    }
+
+    pub fn compression(self, compression: Compression) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
+    /// Compression state after the messages exchanged so far, to be kept for
+    /// the following messages on the same connection.
+    pub fn compression_state(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn max_message_len(self, max_message_len: u64) -> Self {
+        Self {
+            max_message_len,
+            ..self
         }
     }
 
@@ -73,18 +227,77 @@ where
     where
         V: for<'de> Deserialize<'de> + std::fmt::Debug,
     {
-        let mut header = [0u8; 8];
+        let mut recv_buf: Vec<u8> = Vec::new();
+        let mut compressed = false;
+        let mut first = true;
+        // Length of the message once it exceeds the max message length.
+        let mut dropped_len: Option<u64> = None;
+        let mut frames: u64 = 0;
+
+        loop {
+            frames += 1;
+            if frames > MAX_FRAMES {
+                return Err(ProtocolError::Other(anyhow::anyhow!(
+                    "Exceed max frame count"
+                )));
+            }
+            let mut header = [0u8; 8];
+            self.transport.read_exact(&mut header)?;
+            let header = u64::from_be_bytes(header);
+            if first {
+                compressed = header & COMPRESSED_FLAG != 0;
+                if header & ACCEPT_COMPRESSION_FLAG != 0 {
+                    self.compression.peer_enabled = true;
+                }
+                first = false;
+            }
+            let has_more = header & CONTINUATION_FLAG != 0;
+            let buf_len = header & FRAME_LEN_MASK;
+            if buf_len > self.max_frame_len {
+                return Err(ProtocolError::Other(anyhow::anyhow!(
+                    "Exceed max frame length"
+                )));
+            }
+            if dropped_len.is_none() && recv_buf.len() as u64 + buf_len > self.max_message_len {
+                dropped_len = Some(recv_buf.len() as u64);
+                recv_buf = Vec::new();
+            }
+
+            match dropped_len.as_mut() {
+                Some(len) => {
+                    if *len + buf_len > self.max_message_len.saturating_mul(2) {
+                        return Err(ProtocolError::Other(anyhow::anyhow!(
+                            "Exceed max length of dropped messages"
+                        )));
+                    }
+                    let mut frame = (&mut *self.transport).take(buf_len);
+                    if io::copy(&mut frame, &mut io::sink())? < buf_len {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+                    *len += buf_len;
+                }
+                None => {
+                    let offset = recv_buf.len();
+                    recv_buf.resize(offset + buf_len as usize, 0u8);
+                    self.transport.read_exact(&mut recv_buf[offset..])?;
+                }
+            }
 
-        self.transport.read_exact(&mut header)?;
-        let buf_len = u64::from_be_bytes(header);
-        if buf_len > self.max_frame_len {
-            return Err(ProtocolError::Other(anyhow::anyhow!(
-                "Exceed max frame length"
-            )));
+            if !has_more {
+                break;
+            }
+        }
+
+        if let Some(len) = dropped_len {
+            return Err(ProtocolError::MessageTooLarge {
+                len,
+                max_len: self.max_message_len,
+            });
         }
 
-        let mut recv_buf: Vec<u8> = vec![0u8; buf_len as usize];
-        self.transport.read_exact(&mut recv_buf)?;
+        if compressed {
+            recv_buf = decompress(&recv_buf, self.max_message_len)?;
+        }
 
         trace!("Recv: {}", std::string::String::from_utf8_lossy(&recv_buf));
         let r: V = serde_json::from_slice(&recv_buf)?;
@@ -100,11 +313,41 @@ where
 
         trace!("Send: {}", std::string::String::from_utf8_lossy(&send_buf));
 
-        let buf_len = send_buf.len() as u64;
-        let header = buf_len.to_be_bytes();
+        if send_buf.len() as u64 > self.max_message_len {
+            return Err(ProtocolError::MessageTooLarge {
+                len: send_buf.len() as u64,
+                max_len: self.max_message_len,
+            });
+        }
+
+        let mut flags: u64 = 0;
+        if self.compression.enabled {
+            flags |= ACCEPT_COMPRESSION_FLAG;
+        }
+        let mut send_buf = send_buf;
+        if self.compression.is_active() && send_buf.len() >= MIN_COMPRESSION_LEN {
+            let compressed = compress(&send_buf);
+            if compressed.len() < send_buf.len() {
+                send_buf = compressed;
+                flags |= COMPRESSED_FLAG;
+            }
+        }
 
-        self.transport.write(&header)?;
-        self.transport.write_all(&send_buf)?;
+        let mut chunks = send_buf.chunks(self.chunk_len as usize).peekable();
+        // An empty message is still sent as one empty frame.
+        if chunks.peek().is_none() {
+            self.transport.write_all(&flags.to_be_bytes())?;
+        }
+        while let Some(chunk) = chunks.next() {
+            let mut header = chunk.len() as u64 | flags;
+            // Message flags are only set in the first frame.
+            flags = 0;
+            if chunks.peek().is_some() {
+                header |= CONTINUATION_FLAG;
+            }
+            self.transport.write_all(&header.to_be_bytes())?;
+            self.transport.write_all(chunk)?;
+        }
         self.transport.flush()?;
 
         Ok(())
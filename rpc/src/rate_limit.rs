@@ -0,0 +1,169 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+//! Rate limiting of servers: token buckets for each user (the `id` in the
+//! metadata) and each client IP address, protecting services from runaway
+//! client loops. Requests over the rate are rejected with a
+//! `ResourceExhausted` error before they reach the service.
+
+use crate::interceptor::Interceptor;
+use crate::PEER_ADDR_METADATA_KEY;
+use log::info;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::prelude::v1::*;
+#[cfg(not(feature = "mesalock_sgx"))]
+use std::sync::Mutex;
+#[cfg(feature = "mesalock_sgx")]
+use std::sync::SgxMutex as Mutex;
+use std::time::Duration;
+use teaclave_config::RateLimitConfig;
+use teaclave_types::{platform, TeaclaveServiceResponseError, TeaclaveServiceResponseResult};
+
+// Full buckets are dropped once there are more, as they are the same as new
+// ones.
+const MAX_BUCKETS: usize = 10_000;
+
+/// A bucket of up to `burst` tokens refilled at `rate` tokens per second,
+/// each request taking one.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    // time of the last refill since the Unix epoch
+    updated: Duration,
+}
+
+impl TokenBucket {
+    pub fn new(burst: u32, now: Duration) -> Self {
+        Self {
+            tokens: burst as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, rate: f64, burst: u32, now: Duration) {
+        // The clock of the host may go backwards.
+        let elapsed = now.checked_sub(self.updated).unwrap_or_default();
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(burst as f64);
+        self.updated = now;
+    }
+
+    /// Takes a token if there is one.
+    pub fn take(&mut self, rate: f64, burst: u32, now: Duration) -> bool {
+        self.refill(rate, burst, now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    fn is_full(&mut self, rate: f64, burst: u32, now: Duration) -> bool {
+        self.refill(rate, burst, now);
+        self.tokens >= burst as f64
+    }
+}
+
+// The buckets of one kind of client, e.g., users.
+struct Buckets {
+    kind: &'static str,
+    rate: f64,
+    burst: u32,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl Buckets {
+    fn new(kind: &'static str, rate: f64, burst: u32) -> Self {
+        Self {
+            kind,
+            rate,
+            burst,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.rate > 0.0 && self.burst > 0
+    }
+
+    fn take(&self, client: &str, now: Duration) -> TeaclaveServiceResponseResult<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let mut buckets = self
+            .buckets
+            .lock()
+            .map_err(|_| TeaclaveServiceResponseError::InternalError("lock error".to_string()))?;
+        let (rate, burst) = (self.rate, self.burst);
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(client) {
+            buckets.retain(|_, bucket| !bucket.is_full(rate, burst, now));
+        }
+        let bucket = buckets
+            .entry(client.to_string())
+            .or_insert_with(|| TokenBucket::new(burst, now));
+        if bucket.take(rate, burst, now) {
+            return Ok(());
+        }
+        info!(target: "audit", "rate limit of {} {} exceeded", self.kind, client);
+        Err(TeaclaveServiceResponseError::ResourceExhausted(format!(
+            "rate limit of {} requests per second per {} exceeded",
+            rate, self.kind
+        )))
+    }
+}
+
+/// Rejects the requests of a user or an IP address over the rate limits of
+/// the runtime config. Requests without a user id, or received over a Unix
+/// domain socket, are only limited by what is known of them. User ids are
+/// those claimed by the requests, as authentication comes later: requests
+/// claiming the id of another user also consume its bucket, which the limit
+/// of their IP address bounds.
+pub struct RateLimitInterceptor {
+    users: Buckets,
+    ips: Buckets,
+}
+
+impl RateLimitInterceptor {
+    pub fn from_config(config: &RateLimitConfig) -> Self {
+        Self {
+            users: Buckets::new("user", config.user_requests_per_sec, config.user_burst),
+            ips: Buckets::new("IP address", config.ip_requests_per_sec, config.ip_burst),
+        }
+    }
+
+    /// Whether no request is limited.
+    pub fn is_disabled(&self) -> bool {
+        !self.users.is_enabled() && !self.ips.is_enabled()
+    }
+}
+
+impl Interceptor for RateLimitInterceptor {
+    fn on_request(
+        &self,
+        metadata: &mut HashMap<String, String>,
+        _method: &str,
+        _message: &dyn Debug,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let now = platform::time::since_epoch();
+        if let Some(ip) = metadata.get(PEER_ADDR_METADATA_KEY) {
+            self.ips.take(ip, now)?;
+        }
+        if let Some(id) = metadata.get("id") {
+            self.users.take(id, now)?;
+        }
+        Ok(())
+    }
+}
@@ -18,6 +18,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::prelude::v1::*;
+use std::time::Duration;
+use teaclave_types::TraceContext;
+
+use crate::trace::{SPAN_ID_METADATA_KEY, TRACE_ID_METADATA_KEY};
+
+/// Metadata key of the remaining time budget of a request in milliseconds.
+pub const TIMEOUT_METADATA_KEY: &str = "timeout_ms";
+
+/// Metadata key of the IP address of the client, set by servers for the
+/// requests received over TCP. Values sent by clients are dropped.
+pub const PEER_ADDR_METADATA_KEY: &str = "peer_addr";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Request<T> {
@@ -55,6 +66,38 @@ impl<T> Request<T> {
     pub fn metadata_mut(&mut self) -> &mut HashMap<String, String> {
         &mut self.metadata
     }
+
+    /// Remaining time budget the caller is willing to wait for a response.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.metadata
+            .get(TIMEOUT_METADATA_KEY)
+            .and_then(|timeout| timeout.parse::<u64>().ok())
+            .map(Duration::from_millis)
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.metadata.insert(
+            TIMEOUT_METADATA_KEY.to_string(),
+            timeout.as_millis().to_string(),
+        );
+    }
+
+    /// Trace context of the caller, see the `trace` module.
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        let trace_id = self.metadata.get(TRACE_ID_METADATA_KEY)?;
+        let span_id = self.metadata.get(SPAN_ID_METADATA_KEY)?;
+        Some(TraceContext {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+        })
+    }
+
+    pub fn set_trace_context(&mut self, context: &TraceContext) {
+        self.metadata
+            .insert(TRACE_ID_METADATA_KEY.to_string(), context.trace_id.clone());
+        self.metadata
+            .insert(SPAN_ID_METADATA_KEY.to_string(), context.span_id.clone());
+    }
 }
 
 pub trait IntoRequest<T> {
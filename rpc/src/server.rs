@@ -16,28 +16,49 @@
 // under the License.
 
 use crate::config::SgxTrustedTlsServerConfig;
-use crate::transport::{ServerTransport, SgxTrustedTlsTransport};
-use crate::TeaclaveService;
+use crate::gate::AttestationGate;
+use crate::interceptor::{InterceptedService, Interceptor, Interceptors};
+use crate::transport::{ServerTransport, SgxTrustedTlsTransport, Socket};
+use crate::{ServiceRequest, TeaclaveService};
 use anyhow::Result;
 use log::{debug, error, warn};
 use serde::{Deserialize, Serialize};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::Duration;
+use teaclave_config::MessageLimitsConfig;
+use teaclave_types::platform;
+
+/// Time after which internal services close connections without requests,
+/// longer than `channel::DEFAULT_IDLE_TIMEOUT` so that clients of this crate
+/// drop them first.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub struct SgxTrustedTlsServer<U, V>
 where
     U: Serialize + std::fmt::Debug,
-    V: for<'de> Deserialize<'de> + std::fmt::Debug,
+    V: for<'de> Deserialize<'de> + std::fmt::Debug + ServiceRequest,
 {
     addr: std::net::SocketAddr,
+    unix_socket: Option<PathBuf>,
     tls_config: SgxTrustedTlsServerConfig,
     tcp_nodelay: bool,
     n_workers: usize,
+    idle_timeout: Option<Duration>,
+    max_message_len: u64,
+    chunk_len: u64,
+    compression: bool,
+    interceptors: Interceptors,
+    gate: Option<Arc<AttestationGate>>,
     maker: std::marker::PhantomData<(U, V)>,
 }
 
 impl<U, V> SgxTrustedTlsServer<U, V>
 where
     U: Serialize + std::fmt::Debug,
-    V: for<'de> Deserialize<'de> + std::fmt::Debug,
+    V: for<'de> Deserialize<'de> + std::fmt::Debug + ServiceRequest,
 {
     pub fn new(
         addr: std::net::SocketAddr,
@@ -45,9 +66,16 @@ where
     ) -> SgxTrustedTlsServer<U, V> {
         Self {
             addr,
+            unix_socket: None,
             tls_config: server_config,
             tcp_nodelay: true,
             n_workers: 8,
+            idle_timeout: None,
+            max_message_len: crate::protocol::DEFAULT_MAX_MESSAGE_LEN,
+            chunk_len: crate::protocol::DEFAULT_CHUNK_LEN,
+            compression: false,
+            interceptors: Interceptors::new(),
+            gate: None,
             maker: std::marker::PhantomData::<(U, V)>,
         }
     }
@@ -59,6 +87,15 @@ where
         }
     }
 
+    /// Also accept connections on a Unix domain socket at `path`, e.g., from
+    /// services on the same host. A stale socket file at `path` is removed.
+    pub fn unix_socket(self, path: Option<PathBuf>) -> Self {
+        Self {
+            unix_socket: path,
+            ..self
+        }
+    }
+
     pub fn n_workers(self, n: usize) -> Self {
         Self {
             n_workers: n,
@@ -66,14 +103,131 @@ where
         }
     }
 
+    /// Close connections on which no request arrives for `idle_timeout`,
+    /// e.g., idle connections kept by channel pools, so that they do not hold
+    /// workers. By default connections are kept open until the client closes
+    /// them, as other clients may not reconnect.
+    pub fn idle_timeout(self, idle_timeout: Option<Duration>) -> Self {
+        Self {
+            idle_timeout,
+            ..self
+        }
+    }
+
+    /// Max length of a request, which may be sent in multiple frames.
+    pub fn max_message_len(self, max_message_len: u64) -> Self {
+        Self {
+            max_message_len,
+            ..self
+        }
+    }
+
+    /// Length of the frames large responses are split into, at most 32MB.
+    pub fn chunk_len(self, chunk_len: u64) -> Self {
+        Self {
+            chunk_len: crate::protocol::clamp_chunk_len(chunk_len),
+            ..self
+        }
+    }
+
+    /// Message limits of the service from the runtime config.
+    pub fn message_limits(self, limits: &MessageLimitsConfig) -> Self {
+        self.max_message_len(limits.max_message_len)
+            .chunk_len(limits.frame_len)
+    }
+
+    /// Compress large responses to clients which announce compression
+    /// support. Disabled by default, and must stay disabled for services
+    /// whose responses carry key material, see `SgxTrustedTlsChannel::compression`.
+    pub fn compression(self, enabled: bool) -> Self {
+        Self {
+            compression: enabled,
+            ..self
+        }
+    }
+
+    /// Run `interceptor` on every request before it is handled by the
+    /// service, after the interceptors added before.
+    pub fn interceptor(mut self, interceptor: Arc<dyn Interceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Handle the requests of the gate only for the accepted enclaves. The
+    /// gate runs before the interceptors. `start` fails if a request of the
+    /// gate is not a method of the service.
+    pub fn attestation_gate(self, gate: Arc<AttestationGate>) -> Self {
+        Self {
+            gate: Some(gate),
+            ..self
+        }
+    }
+
     pub fn start<X>(&mut self, service: X) -> Result<()>
     where
         X: 'static + TeaclaveService<V, U> + Clone + core::marker::Send,
     {
+        if let Some(gate) = &self.gate {
+            crate::check_method_names::<V>(gate.methods())?;
+        }
+        let service = InterceptedService::new(service, self.interceptors.clone());
         let pool = threadpool::ThreadPool::new(self.n_workers);
+        if let Some(path) = &self.unix_socket {
+            match platform::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+            let listener = UnixListener::bind(path)?;
+            let mut acceptor = self.acceptor(pool.clone());
+            let service = service.clone();
+            std::thread::spawn(move || {
+                let incoming = listener.incoming().map(|stream| stream.map(Socket::Unix));
+                if let Err(e) = acceptor.accept(incoming, service) {
+                    error!("Unix socket listener exit, error: {}.", e);
+                }
+            });
+        }
         let listener = std::net::TcpListener::bind(self.addr)?;
+        let incoming = listener.incoming().map(|stream| stream.map(Socket::Tcp));
+        self.acceptor(pool).accept(incoming, service)
+    }
+
+    fn acceptor(&self, pool: threadpool::ThreadPool) -> Acceptor {
+        Acceptor {
+            tls_config: self.tls_config.clone(),
+            tcp_nodelay: self.tcp_nodelay,
+            idle_timeout: self.idle_timeout,
+            max_message_len: self.max_message_len,
+            chunk_len: self.chunk_len,
+            compression: self.compression,
+            gate: self.gate.clone(),
+            pool,
+        }
+    }
+}
+
+// Serves the connections of a listener on the worker pool.
+struct Acceptor {
+    tls_config: SgxTrustedTlsServerConfig,
+    tcp_nodelay: bool,
+    idle_timeout: Option<Duration>,
+    max_message_len: u64,
+    chunk_len: u64,
+    compression: bool,
+    gate: Option<Arc<AttestationGate>>,
+    pool: threadpool::ThreadPool,
+}
+
+impl Acceptor {
+    fn accept<U, V, X, I>(&mut self, incoming: I, service: X) -> Result<()>
+    where
+        U: Serialize + std::fmt::Debug,
+        V: for<'de> Deserialize<'de> + std::fmt::Debug + ServiceRequest,
+        X: 'static + TeaclaveService<V, U> + Clone + core::marker::Send,
+        I: Iterator<Item = std::io::Result<Socket>>,
+    {
         let mut tls_config_ref = self.tls_config.server_config();
-        for stream in listener.incoming() {
+        for stream in incoming {
             match stream {
                 Ok(stream) => {
                     // Before introducing async into enclave, we check
@@ -84,15 +238,25 @@ where
                         tls_config_ref = self.tls_config.server_config();
                     }
 
-                    if let Err(e) = stream.set_nodelay(self.tcp_nodelay) {
-                        warn!("Cannot set_nodelay: {:}", e);
-                        continue;
+                    if let Socket::Tcp(stream) = &stream {
+                        if let Err(e) = stream.set_nodelay(self.tcp_nodelay) {
+                            warn!("Cannot set_nodelay: {:}", e);
+                            continue;
+                        }
                     }
                     let session = rustls::ServerSession::new(&tls_config_ref);
                     let tls_stream = rustls::StreamOwned::new(session, stream);
-                    let mut transport = SgxTrustedTlsTransport::new(tls_stream);
+                    let mut transport = SgxTrustedTlsTransport::new(tls_stream)
+                        .max_message_len(self.max_message_len)
+                        .chunk_len(self.chunk_len)
+                        .compression(self.compression)
+                        .gate(self.gate.clone());
+                    if let Err(e) = transport.set_timeout(self.idle_timeout) {
+                        warn!("Cannot set idle timeout: {:}", e);
+                        continue;
+                    }
                     let service = service.clone();
-                    pool.execute(move || match transport.serve(service) {
+                    self.pool.execute(move || match transport.serve(service) {
                         Ok(_) => (),
                         Err(e) => {
                             debug!("serve error: {:?}", e);
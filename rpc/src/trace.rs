@@ -0,0 +1,101 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Propagation of trace contexts through request metadata. Servers continue
+//! the trace of each request in a new span, which is the current context of
+//! the handling thread, so that calls made by the handler are continued in
+//! the same trace.
+
+use crate::Request;
+use std::cell::RefCell;
+use std::prelude::v1::*;
+use teaclave_types::TraceContext;
+
+/// Metadata key of the trace id of a request.
+pub const TRACE_ID_METADATA_KEY: &str = "trace_id";
+/// Metadata key of the span id of the caller of a request.
+pub const SPAN_ID_METADATA_KEY: &str = "span_id";
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = RefCell::new(None);
+}
+
+/// The trace context of the current thread, if any.
+pub fn current() -> Option<TraceContext> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Makes `context` the current one of the thread until the guard is dropped.
+pub fn enter(context: TraceContext) -> TraceGuard {
+    let previous = CURRENT.with(|current| current.replace(Some(context)));
+    TraceGuard { previous }
+}
+
+pub struct TraceGuard {
+    previous: Option<TraceContext>,
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+// Handles a request in a span continuing the trace of the caller, or starting
+// a new trace.
+pub(crate) fn serve<V, R, F>(request: Request<V>, handle: F) -> R
+where
+    F: FnOnce(Request<V>) -> R,
+{
+    let parent = request.trace_context();
+    let context = match &parent {
+        Some(parent) => parent.child(),
+        None => TraceContext::new(),
+    };
+    log::debug!(
+        "rpc_server: request={} trace_id={} span_id={} parent_span_id={:?}",
+        std::any::type_name::<V>(),
+        context.trace_id,
+        context.span_id,
+        parent.map(|parent| parent.span_id),
+    );
+    let _trace = enter(context);
+    handle(request)
+}
+
+// Sends a request in a span continuing the current trace, or starting a new
+// trace.
+pub(crate) fn call<U, R, F>(mut request: Request<U>, send: F) -> R
+where
+    F: FnOnce(Request<U>) -> R,
+{
+    let parent = current();
+    let context = match &parent {
+        Some(parent) => parent.child(),
+        None => TraceContext::new(),
+    };
+    log::debug!(
+        "rpc_client: request={} trace_id={} span_id={} parent_span_id={:?}",
+        std::any::type_name::<U>(),
+        context.trace_id,
+        context.span_id,
+        parent.map(|parent| parent.span_id),
+    );
+    request.set_trace_context(&context);
+    send(request)
+}
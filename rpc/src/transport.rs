@@ -15,13 +15,91 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::gate::AttestationGate;
 use crate::protocol;
 use crate::Request;
+use crate::ServiceRequest;
 use crate::TeaclaveService;
+use crate::PEER_ADDR_METADATA_KEY;
 use anyhow::Result;
 use log::debug;
 use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream};
+use std::os::unix::net::UnixStream;
 use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Prefix of the addresses of services listening on a Unix domain socket,
+/// e.g., `unix:/var/run/teaclave/storage.sock`.
+pub(crate) const UNIX_ADDRESS_PREFIX: &str = "unix:";
+
+/// Connection the TLS session of a transport runs over. Unix domain sockets
+/// skip the network stack for services on the same host, while the peers are
+/// still authenticated with attested TLS.
+pub(crate) enum Socket {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Socket {
+    /// Connect to `address`, either `unix:<path>` or `<host>:<port>`.
+    pub(crate) fn connect(address: &str) -> std::io::Result<Self> {
+        if address.starts_with(UNIX_ADDRESS_PREFIX) {
+            let path = &address[UNIX_ADDRESS_PREFIX.len()..];
+            UnixStream::connect(path).map(Socket::Unix)
+        } else {
+            TcpStream::connect(address).map(Socket::Tcp)
+        }
+    }
+
+    // IP address of the peer, unknown for Unix domain sockets.
+    fn peer_ip(&self) -> Option<IpAddr> {
+        match self {
+            Socket::Tcp(stream) => stream.peer_addr().ok().map(|addr| addr.ip()),
+            Socket::Unix(_) => None,
+        }
+    }
+
+    fn set_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => {
+                stream.set_read_timeout(timeout)?;
+                stream.set_write_timeout(timeout)
+            }
+            Socket::Unix(stream) => {
+                stream.set_read_timeout(timeout)?;
+                stream.set_write_timeout(timeout)
+            }
+        }
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Socket::Tcp(stream) => stream.read(buf),
+            Socket::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Socket::Tcp(stream) => stream.write(buf),
+            Socket::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.flush(),
+            Socket::Unix(stream) => stream.flush(),
+        }
+    }
+}
 
 pub(crate) trait ClientTransport {
     fn send<U, V>(
@@ -37,22 +115,76 @@ pub(crate) trait ServerTransport {
     fn serve<U, V, X>(&mut self, service: X) -> Result<()>
     where
         U: Serialize + std::fmt::Debug,
-        V: for<'de> Deserialize<'de> + std::fmt::Debug,
+        V: for<'de> Deserialize<'de> + std::fmt::Debug + ServiceRequest,
         X: TeaclaveService<V, U>;
 }
 pub(crate) struct SgxTrustedTlsTransport<S>
 where
     S: rustls::Session,
 {
-    stream: rustls::StreamOwned<S, std::net::TcpStream>,
+    stream: rustls::StreamOwned<S, Socket>,
+    max_message_len: u64,
+    chunk_len: u64,
+    compression: protocol::Compression,
+    gate: Option<Arc<AttestationGate>>,
 }
 
 impl<S> SgxTrustedTlsTransport<S>
 where
     S: rustls::Session,
 {
-    pub fn new(stream: rustls::StreamOwned<S, std::net::TcpStream>) -> SgxTrustedTlsTransport<S> {
-        SgxTrustedTlsTransport::<S> { stream }
+    pub fn new(stream: rustls::StreamOwned<S, Socket>) -> SgxTrustedTlsTransport<S> {
+        SgxTrustedTlsTransport::<S> {
+            stream,
+            max_message_len: protocol::DEFAULT_MAX_MESSAGE_LEN,
+            chunk_len: protocol::DEFAULT_CHUNK_LEN,
+            compression: protocol::Compression::default(),
+            gate: None,
+        }
+    }
+
+    pub fn compression(self, enabled: bool) -> Self {
+        Self {
+            compression: protocol::Compression::new(enabled),
+            ..self
+        }
+    }
+
+    /// Whether compression is wanted for the following messages. Whether the
+    /// peer accepts compression, as learned from previous messages on this
+    /// connection, is kept.
+    pub fn set_compression(&mut self, enabled: bool) {
+        self.compression.enabled = enabled;
+    }
+
+    pub fn max_message_len(self, max_message_len: u64) -> Self {
+        Self {
+            max_message_len,
+            ..self
+        }
+    }
+
+    pub fn set_max_message_len(&mut self, max_message_len: u64) {
+        self.max_message_len = max_message_len;
+    }
+
+    pub fn chunk_len(self, chunk_len: u64) -> Self {
+        Self { chunk_len, ..self }
+    }
+
+    pub fn set_chunk_len(&mut self, chunk_len: u64) {
+        self.chunk_len = chunk_len;
+    }
+
+    /// Check the requests served against the attestation gate, if any.
+    pub fn gate(self, gate: Option<Arc<AttestationGate>>) -> Self {
+        Self { gate, ..self }
+    }
+
+    /// Bound blocking reads and writes on the connection, `None` waits
+    /// indefinitely.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.stream.sock.set_timeout(timeout)
     }
 }
 
@@ -68,13 +200,17 @@ where
         U: Serialize + std::fmt::Debug,
         V: for<'de> Deserialize<'de> + std::fmt::Debug,
     {
-        let mut protocol = protocol::JsonProtocol::new(&mut self.stream);
+        let mut protocol = protocol::JsonProtocol::new(&mut self.stream)
+            .max_message_len(self.max_message_len)
+            .chunk_len(self.chunk_len)
+            .compression(self.compression);
         protocol.write_message(request)?;
-        protocol.read_message::<protocol::JsonProtocolResult<
-                V,
-                teaclave_types::TeaclaveServiceResponseError,
-            >>()?
-            .into()
+        let response = protocol.read_message::<protocol::JsonProtocolResult<
+            V,
+            teaclave_types::TeaclaveServiceResponseError,
+        >>();
+        self.compression = protocol.compression_state();
+        response?.into()
     }
 }
 
@@ -85,21 +221,48 @@ where
     fn serve<U, V, X>(&mut self, service: X) -> Result<()>
     where
         U: Serialize + std::fmt::Debug,
-        V: for<'de> Deserialize<'de> + std::fmt::Debug,
+        V: for<'de> Deserialize<'de> + std::fmt::Debug + ServiceRequest,
         X: TeaclaveService<V, U>,
     {
         use crate::protocol::{JsonProtocol, JsonProtocolResult};
         use teaclave_types::TeaclaveServiceResponseError;
-        let mut protocol = JsonProtocol::new(&mut self.stream);
+        // The handshake is completed first for the certificate of the peer.
+        let gate = self.gate.clone().filter(|gate| !gate.is_empty());
+        let peer = match &gate {
+            Some(gate) => {
+                let stream = &mut self.stream;
+                if let Err(e) = stream.sess.complete_io(&mut stream.sock) {
+                    debug!("Handshake failed: {:?}", e);
+                    return Ok(());
+                }
+                gate.peer_measurement(stream.sess.get_peer_certificates())
+            }
+            None => None,
+        };
+        let peer_ip = self.stream.sock.peer_ip();
+        let mut protocol = JsonProtocol::new(&mut self.stream)
+            .max_message_len(self.max_message_len)
+            .chunk_len(self.chunk_len)
+            .compression(self.compression);
 
         loop {
-            let request: Request<V> = match protocol.read_message::<Request<V>>() {
+            let mut request: Request<V> = match protocol.read_message::<Request<V>>() {
                 Ok(r) => r,
                 Err(e) => match e {
                     protocol::ProtocolError::IoError(_) => {
                         debug!("Connection disconnected.");
                         return Ok(());
                     }
+                    // The frames of the request have been dropped, so the
+                    // connection can still be used.
+                    protocol::ProtocolError::MessageTooLarge { .. } => {
+                        debug!("{:?}", e);
+                        let error: TeaclaveServiceResponseError = e.into();
+                        let response: JsonProtocolResult<U, TeaclaveServiceResponseError> =
+                            Err(error).into();
+                        protocol.write_message(response)?;
+                        continue;
+                    }
                     _ => {
                         debug!("{:?}", e);
                         let response: JsonProtocolResult<U, TeaclaveServiceResponseError> =
@@ -112,9 +275,36 @@ where
                     }
                 },
             };
+            match peer_ip {
+                Some(ip) => request
+                    .metadata
+                    .insert(PEER_ADDR_METADATA_KEY.to_string(), ip.to_string()),
+                None => request.metadata.remove(PEER_ADDR_METADATA_KEY),
+            };
+            if let Some(gate) = &gate {
+                let method = request.message.method_name();
+                if let Err(e) = gate.check(method, peer.as_ref()) {
+                    let response: JsonProtocolResult<U, TeaclaveServiceResponseError> =
+                        Err(e).into();
+                    protocol.write_message(response)?;
+                    continue;
+                }
+            }
             let response: JsonProtocolResult<U, TeaclaveServiceResponseError> =
-                service.handle_request(request).into();
-            protocol.write_message(response)?;
+                crate::trace::serve(request, |request| {
+                    crate::deadline::serve(request, |request| service.handle_request(request))
+                })
+                .into();
+            // A response exceeding the max message length is not sent, the
+            // client gets the error instead.
+            match protocol.write_message(response) {
+                Err(protocol::ProtocolError::MessageTooLarge { len, max_len }) => {
+                    let response: JsonProtocolResult<U, TeaclaveServiceResponseError> =
+                        Err(TeaclaveServiceResponseError::MessageTooLarge { len, max_len }).into();
+                    protocol.write_message(response)?;
+                }
+                result => result?,
+            }
         }
     }
 }
@@ -20,12 +20,18 @@ use std::prelude::v1::*;
 
 use std::io;
 
+use teaclave_types::FunctionEnv;
+use teaclave_types::FunctionLog;
+use teaclave_types::ResultStream;
 use teaclave_types::StagedFiles;
 use teaclave_types::TeaclaveRuntime;
 
 pub struct DefaultRuntime {
     input_files: StagedFiles,
     output_files: StagedFiles,
+    env: FunctionEnv,
+    log: FunctionLog,
+    result_stream: ResultStream,
 }
 
 impl DefaultRuntime {
@@ -33,6 +39,24 @@ impl DefaultRuntime {
         DefaultRuntime {
             input_files,
             output_files,
+            env: FunctionEnv::default(),
+            log: FunctionLog::default(),
+            result_stream: ResultStream::default(),
+        }
+    }
+
+    pub fn with_env(self, env: FunctionEnv) -> DefaultRuntime {
+        DefaultRuntime { env, ..self }
+    }
+
+    pub fn with_log(self, log: FunctionLog) -> DefaultRuntime {
+        DefaultRuntime { log, ..self }
+    }
+
+    pub fn with_result_stream(self, result_stream: ResultStream) -> DefaultRuntime {
+        DefaultRuntime {
+            result_stream,
+            ..self
         }
     }
 }
@@ -59,4 +83,16 @@ impl TeaclaveRuntime for DefaultRuntime {
         let writable = file_info.create_writable_io()?;
         Ok(writable)
     }
+
+    fn env(&self) -> &FunctionEnv {
+        &self.env
+    }
+
+    fn log(&self, message: &str) {
+        self.log.append(message)
+    }
+
+    fn emit_result(&self, chunk: &[u8]) {
+        self.result_stream.emit(chunk)
+    }
 }
@@ -21,12 +21,18 @@ use std::prelude::v1::*;
 use std::io;
 use std::untrusted::fs::File;
 
+use teaclave_types::FunctionEnv;
+use teaclave_types::FunctionLog;
+use teaclave_types::ResultStream;
 use teaclave_types::StagedFiles;
 use teaclave_types::TeaclaveRuntime;
 
 pub struct RawIoRuntime {
     input_files: StagedFiles,
     output_files: StagedFiles,
+    env: FunctionEnv,
+    log: FunctionLog,
+    result_stream: ResultStream,
 }
 
 impl RawIoRuntime {
@@ -34,6 +40,24 @@ impl RawIoRuntime {
         RawIoRuntime {
             input_files,
             output_files,
+            env: FunctionEnv::default(),
+            log: FunctionLog::default(),
+            result_stream: ResultStream::default(),
+        }
+    }
+
+    pub fn with_env(self, env: FunctionEnv) -> RawIoRuntime {
+        RawIoRuntime { env, ..self }
+    }
+
+    pub fn with_log(self, log: FunctionLog) -> RawIoRuntime {
+        RawIoRuntime { log, ..self }
+    }
+
+    pub fn with_result_stream(self, result_stream: ResultStream) -> RawIoRuntime {
+        RawIoRuntime {
+            result_stream,
+            ..self
         }
     }
 }
@@ -58,4 +82,16 @@ impl TeaclaveRuntime for RawIoRuntime {
         let f = File::create(&file_info.path)?;
         Ok(Box::new(f))
     }
+
+    fn env(&self) -> &FunctionEnv {
+        &self.env
+    }
+
+    fn log(&self, message: &str) {
+        self.log.append(message)
+    }
+
+    fn emit_result(&self, chunk: &[u8]) {
+        self.result_stream.emit(chunk)
+    }
 }
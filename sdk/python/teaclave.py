@@ -274,13 +274,33 @@ class RegisterFunctionRequest:
 
 
 class RegisterInputFileRequest:
-    def __init__(self, metadata: Metadata, url: str, cmac: List[int],
-                 crypto_info: CryptoInfo):
+    def __init__(self,
+                 metadata: Metadata,
+                 url: str,
+                 cmac: List[int],
+                 crypto_info: CryptoInfo,
+                 attributes: str = ""):
         self.request = "register_input_file"
         self.metadata = metadata
         self.url = url
         self.cmac = cmac
         self.crypto_info = crypto_info
+        self.attributes = attributes
+
+
+class RegisterInlineInputFileRequest:
+    def __init__(self,
+                 metadata: Metadata,
+                 content: List[int],
+                 cmac: List[int],
+                 crypto_info: CryptoInfo,
+                 attributes: str = ""):
+        self.request = "register_inline_input_file"
+        self.metadata = metadata
+        self.content = content
+        self.cmac = cmac
+        self.crypto_info = crypto_info
+        self.attributes = attributes
 
 
 class RegisterOutputFileRequest:
@@ -311,7 +331,9 @@ class CreateTaskRequest:
     def __init__(self, metadata: Metadata, function_id: str,
                  function_arguments: Dict[str, Any], executor: str,
                  inputs_ownership: List[OwnerList],
-                 outputs_ownership: List[OwnerList]):
+                 outputs_ownership: List[OwnerList],
+                 env: Dict[str, str] = {},
+                 priority: str = "normal"):
         self.request = "create_task"
         self.metadata = metadata
         self.function_id = function_id
@@ -319,6 +341,8 @@ class CreateTaskRequest:
         self.executor = executor
         self.inputs_ownership = inputs_ownership
         self.outputs_ownership = outputs_ownership
+        self.env = env
+        self.priority = priority
 
 
 class AssignDataRequest:
@@ -373,10 +397,32 @@ class FrontendClient:
         response = _read_message(self.channel)
         return response["content"]["function_id"]
 
-    def register_input_file(self, url: str, schema: str, key: List[int],
-                            iv: List[int], cmac: List[int]):
+    def register_input_file(self,
+                            url: str,
+                            schema: str,
+                            key: List[int],
+                            iv: List[int],
+                            cmac: List[int],
+                            attributes: Dict[str, Any] = {}):
+        attributes = json.dumps(attributes) if attributes else ""
         request = RegisterInputFileRequest(self.metadata, url, cmac,
-                                           CryptoInfo(schema, key, iv))
+                                           CryptoInfo(schema, key, iv),
+                                           attributes)
+        _write_message(self.channel, request)
+        response = _read_message(self.channel)
+        return response["content"]["data_id"]
+
+    def register_inline_input_file(self,
+                                   content: List[int],
+                                   schema: str,
+                                   key: List[int],
+                                   iv: List[int],
+                                   cmac: List[int],
+                                   attributes: Dict[str, Any] = {}):
+        attributes = json.dumps(attributes) if attributes else ""
+        request = RegisterInlineInputFileRequest(self.metadata, content, cmac,
+                                                 CryptoInfo(schema, key, iv),
+                                                 attributes)
         _write_message(self.channel, request)
         response = _read_message(self.channel)
         return response["content"]["data_id"]
@@ -394,11 +440,14 @@ class FrontendClient:
                     function_arguments: Dict[str, Any],
                     executor: str,
                     inputs_ownership: List[OwnerList] = [],
-                    outputs_ownership: List[OwnerList] = []):
+                    outputs_ownership: List[OwnerList] = [],
+                    env: Dict[str, str] = {},
+                    priority: str = "normal"):
         function_arguments = json.dumps(function_arguments)
         request = CreateTaskRequest(self.metadata, function_id,
                                     function_arguments, executor,
-                                    inputs_ownership, outputs_ownership)
+                                    inputs_ownership, outputs_ownership, env,
+                                    priority)
         _write_message(self.channel, request)
         response = _read_message(self.channel)
         return response["content"]["task_id"]
@@ -456,13 +505,23 @@ def _write_message(sock: ssl.SSLSocket, message: Any):
 
 
 def _read_message(sock: ssl.SSLSocket):
-    response_len = struct.unpack(">Q", sock.read(8))
+    # Large messages are split into frames, the most significant bit of the
+    # frame length is set if more frames follow.
+    continuation_flag = 1 << 63
     raw = bytearray()
-    total_recv = 0
-    while total_recv < response_len[0]:
-        data = sock.recv()
-        total_recv += len(data)
-        raw += data
+    has_more = True
+    while has_more:
+        header = bytearray()
+        while len(header) < 8:
+            header += sock.read(8 - len(header))
+        frame_len = struct.unpack(">Q", header)[0]
+        has_more = frame_len & continuation_flag != 0
+        frame_len &= ~continuation_flag
+        total_recv = 0
+        while total_recv < frame_len:
+            data = sock.recv(frame_len - total_recv)
+            total_recv += len(data)
+            raw += data
     response = json.loads(raw)
     return response
 
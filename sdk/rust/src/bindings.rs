@@ -387,6 +387,11 @@ generate_function_serialized!(
     teaclave_register_input_file_serialized,
     register_input_file_serialized
 );
+generate_function_serialized!(
+    FrontendClient,
+    teaclave_register_inline_input_file_serialized,
+    register_inline_input_file_serialized
+);
 generate_function_serialized!(
     FrontendClient,
     teaclave_register_output_file_serialized,
@@ -15,32 +15,75 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+use teaclave_attestation::report::AttestationReport;
 use teaclave_attestation::verifier;
+use teaclave_proto::teaclave_attestation_verifier_service::TeaclaveAttestationVerifierClient;
 use teaclave_proto::teaclave_authentication_service::TeaclaveAuthenticationApiClient;
 use teaclave_proto::teaclave_authentication_service_proto as authentication_proto;
 use teaclave_proto::teaclave_frontend_service::TeaclaveFrontendClient;
 use teaclave_proto::teaclave_frontend_service_proto as frontend_proto;
+use teaclave_rpc::blob::{blob_hash, BlobSink, BlobSource};
 use teaclave_rpc::config::SgxTrustedTlsClientConfig;
 use teaclave_rpc::endpoint::Endpoint;
-use teaclave_types::FileAuthTag;
+use teaclave_types::{CoseSign1, ExternalID, FileAuthTag};
 use url::Url;
 
+pub use teaclave_proto::teaclave_access_control_service::AccessControlDecision;
+pub use teaclave_proto::teaclave_attestation_verifier_service::{VerifyRequest, VerifyResponse};
 pub use teaclave_proto::teaclave_authentication_service::{
-    UserLoginRequest, UserLoginResponse, UserRegisterRequest, UserRegisterResponse,
+    AssignRolesRequest, AssignRolesResponse, ConfirmTotpRequest, ConfirmTotpResponse,
+    CreateApiKeyRequest, CreateApiKeyResponse, CreateRoleRequest, CreateRoleResponse,
+    DeleteUserRequest, DeleteUserResponse, DisableUserRequest, DisableUserResponse,
+    EnrollTotpRequest, EnrollTotpResponse, ExportAuditLogRequest, ExportAuditLogResponse,
+    ListUsersRequest, ListUsersResponse, RefreshTokenRequest, RefreshTokenResponse,
+    ResetPasswordRequest, ResetPasswordResponse, RevokeApiKeyRequest, RevokeApiKeyResponse,
+    RevokeTokenRequest, RevokeTokenResponse, UserLoginRequest, UserLoginResponse,
+    UserLoginWithOidcRequest, UserLoginWithOidcResponse, UserRegisterRequest, UserRegisterResponse,
+    UserSummary,
 };
 pub use teaclave_proto::teaclave_frontend_service::GetFunctionResponse as Function;
 pub use teaclave_proto::teaclave_frontend_service::{
     ApproveTaskRequest, ApproveTaskResponse, AssignDataRequest, AssignDataResponse,
-    CreateTaskRequest, CreateTaskResponse, GetFunctionRequest, GetFunctionResponse, GetTaskRequest,
-    GetTaskResponse, InvokeTaskRequest, InvokeTaskResponse, RegisterFunctionRequest,
-    RegisterFunctionResponse, RegisterInputFileRequest, RegisterInputFileResponse,
-    RegisterOutputFileRequest, RegisterOutputFileResponse,
+    BeginPayloadUploadRequest, BeginPayloadUploadResponse, CheckConsistencyRequest,
+    CheckConsistencyResponse, CommitPayloadRequest, CommitPayloadResponse, ConsistencyFinding,
+    CreatePipelineRequest, CreatePipelineResponse, CreateScheduledTaskRequest,
+    CreateScheduledTaskResponse, CreateTaskRequest, CreateTaskResponse, CreateTasksRequest,
+    CreateTasksResponse, DeleteWebhookRequest, DeleteWebhookResponse, EnterReadOnlyModeRequest,
+    EnterReadOnlyModeResponse, ExitReadOnlyModeRequest, ExitReadOnlyModeResponse,
+    ExplainAccessRequest, ExplainAccessResponse, FunctionSummary, GetAccessControlPolicyRequest,
+    GetAccessControlPolicyResponse, GetFunctionRequest, GetFunctionResponse,
+    GetMeasurementInclusionRequest, GetMeasurementInclusionResponse, GetPipelineRequest,
+    GetPipelineResponse, GetPlatformInfoRequest, GetPlatformInfoResponse, GetQuotaUsageRequest,
+    GetQuotaUsageResponse, GetTaskLogRequest, GetTaskLogResponse, GetTaskRequest, GetTaskResponse,
+    GetTaskResultRequest, GetTaskResultResponse, GetTenantStatsRequest, GetTenantStatsResponse,
+    HealthRequest, HealthResponse, InconsistencyKind, InvokeTaskFailure, InvokeTaskRequest,
+    InvokeTaskResponse, InvokeTasksRequest, InvokeTasksResponse, ListExecutorsRequest,
+    ListExecutorsResponse, ListFunctionsRequest, ListFunctionsResponse, ListNodesRequest,
+    ListNodesResponse, ListTasksRequest, ListTasksResponse, ListUpcomingRunsRequest,
+    ListUpcomingRunsResponse, PauseScheduledTaskRequest, PauseScheduledTaskResponse,
+    RegisterExecutorEnclaveRequest, RegisterExecutorEnclaveResponse, RegisterFunctionRequest,
+    RegisterFunctionResponse, RegisterInlineInputFileRequest, RegisterInlineInputFileResponse,
+    RegisterInputFileRequest, RegisterInputFileResponse, RegisterOutputFileRequest,
+    RegisterOutputFileResponse, RegisterWebhookRequest, RegisterWebhookResponse,
+    ResumeScheduledTaskRequest, ResumeScheduledTaskResponse, ReviewOutputRequest,
+    ReviewOutputResponse, RollbackAccessControlPolicyRequest, RollbackAccessControlPolicyResponse,
+    RollbackFunctionRequest, RollbackFunctionResponse, SetUserQuotaRequest, SetUserQuotaResponse,
+    StreamTaskResultRequest, StreamTaskResultResponse, TaskOverrides, TaskSummary,
+    TransferOwnershipRequest, TransferOwnershipResponse, UpdateAccessControlPolicyRequest,
+    UpdateAccessControlPolicyResponse, UploadPartRequest, UploadPartResponse,
 };
 pub use teaclave_types::{
-    EnclaveInfo, Executor, FileCrypto, FunctionInput, FunctionOutput, TaskResult,
+    verify_audit_chain, AttestationSummary, AuditEvent, AuditEventKind, AuditLogEntry, EnclaveInfo,
+    EnclaveMeasurement, ExecutionReceiptClaims, Executor, ExecutorNode, FileCrypto, FunctionInput,
+    FunctionManifest, FunctionOutput, FunctionVersion, HeldOutputsStatus, ListOptions,
+    MeasurementLogEntry, NodeExecutors, ObjectFilter, Permission, PipelineLink, PipelineStatus,
+    QuotaUsage, RetryOn, RetryPolicy, TaskEvent, TaskEventKind, TaskResult, TaskResultClaims,
+    TaskSchedule, TenantStats, UserQuota, WEBHOOK_SIGNATURE_HEADER,
 };
 
 pub mod bindings;
@@ -107,6 +150,176 @@ impl AuthenticationClient {
 
         Ok(response.token)
     }
+
+    /// Log in as a user enrolled in TOTP two-factor authentication, with the
+    /// current code of the authenticator.
+    pub fn user_login_with_totp(
+        &mut self,
+        user_id: &str,
+        user_password: &str,
+        totp_code: &str,
+    ) -> Result<String> {
+        let request = UserLoginRequest::new(user_id, user_password).totp_code(totp_code);
+        let response = self.user_login_with_request(request)?;
+
+        Ok(response.token)
+    }
+
+    /// Log in with an ID token of an OpenID Connect provider, returning the
+    /// id of the user and the token.
+    pub fn user_login_with_oidc(&mut self, id_token: &str) -> Result<(String, String)> {
+        let request = UserLoginWithOidcRequest::new(id_token);
+        let response = self.api_client.user_login_with_oidc(request)?;
+
+        Ok((response.id, response.token))
+    }
+
+    /// Set the id and login token of the user, authenticating the requests
+    /// managing API keys.
+    pub fn set_credential(&mut self, id: &str, token: &str) {
+        let mut metadata = HashMap::new();
+        metadata.insert("id".to_string(), id.to_string());
+        metadata.insert("token".to_string(), token.to_string());
+        self.api_client.set_metadata(metadata);
+    }
+
+    /// Create an API key allowed for the frontend operations in `scopes`
+    /// (e.g., "get_task", or "*" for all), returning the id of the key and
+    /// the key. The key is only returned once, and can be used in place of
+    /// the token in `FrontendClient::set_credential`.
+    pub fn create_api_key(&mut self, name: &str, scopes: &[&str]) -> Result<(String, String)> {
+        let scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        let request = CreateApiKeyRequest::new(name, scopes);
+        let response = self.api_client.create_api_key(request)?;
+
+        Ok((response.key_id, response.api_key))
+    }
+
+    pub fn revoke_api_key(&mut self, key_id: &str) -> Result<()> {
+        let request = RevokeApiKeyRequest::new(key_id);
+        let _response = self.api_client.revoke_api_key(request)?;
+
+        Ok(())
+    }
+
+    /// Enroll the user set with `set_credential` in TOTP two-factor
+    /// authentication, returning the base32 secret and the provisioning URI
+    /// for authenticator apps. Users already enrolled give a current code,
+    /// otherwise `totp_code` is empty. The secret is used once confirmed with
+    /// `confirm_totp`; later logins need `user_login_with_totp`.
+    pub fn enroll_totp(&mut self, totp_code: &str) -> Result<(String, String)> {
+        let request = EnrollTotpRequest::new().totp_code(totp_code);
+        let response = self.api_client.enroll_totp(request)?;
+
+        Ok((response.secret, response.provisioning_uri))
+    }
+
+    /// Confirm the secret of the last `enroll_totp` with a code generated
+    /// from it.
+    pub fn confirm_totp(&mut self, totp_code: &str) -> Result<()> {
+        let request = ConfirmTotpRequest::new(totp_code);
+        let _response = self.api_client.confirm_totp(request)?;
+
+        Ok(())
+    }
+
+    /// Get a new login token, revoking the one set with `set_credential`.
+    pub fn refresh_token(&mut self) -> Result<String> {
+        let response = self.api_client.refresh_token(RefreshTokenRequest)?;
+
+        Ok(response.token)
+    }
+
+    /// Revoke the login token set with `set_credential`, or with `all`, every
+    /// login token of the user.
+    pub fn revoke_token(&mut self, all: bool) -> Result<()> {
+        let mut request = RevokeTokenRequest::new();
+        if all {
+            request = request.all();
+        }
+        let _response = self.api_client.revoke_token(request)?;
+
+        Ok(())
+    }
+
+    /// Create or replace a role granting the permissions. Requires the
+    /// `ManageUsers` permission.
+    pub fn create_role(&mut self, name: &str, permissions: &[Permission]) -> Result<()> {
+        let request = CreateRoleRequest::new(name, permissions.to_vec());
+        let _response = self.api_client.create_role(request)?;
+
+        Ok(())
+    }
+
+    /// Replace the roles of the user. Requires the `ManageUsers` permission.
+    pub fn assign_roles(&mut self, user_id: &str, roles: &[&str]) -> Result<()> {
+        let roles = roles.iter().map(|role| role.to_string()).collect();
+        let request = AssignRolesRequest::new(user_id, roles);
+        let _response = self.api_client.assign_roles(request)?;
+
+        Ok(())
+    }
+
+    /// List a page of users, returning the users and the `start_after` of the
+    /// next page, or `None` after the last page. Requires the `ManageUsers`
+    /// permission.
+    pub fn list_users(
+        &mut self,
+        request: ListUsersRequest,
+    ) -> Result<(Vec<UserSummary>, Option<String>)> {
+        let response = self.api_client.list_users(request)?;
+        let next_start_after = if response.next_start_after.is_empty() {
+            None
+        } else {
+            Some(response.next_start_after)
+        };
+
+        Ok((response.users, next_start_after))
+    }
+
+    /// Disable the user, whose functions and pending tasks can no longer be
+    /// used. Requires the `ManageUsers` permission.
+    pub fn disable_user(&mut self, user_id: &str) -> Result<()> {
+        let _response = self
+            .api_client
+            .disable_user(DisableUserRequest::new(user_id))?;
+
+        Ok(())
+    }
+
+    /// Delete the credentials and roles of the user, disabling it. Requires
+    /// the `ManageUsers` permission.
+    pub fn delete_user(&mut self, user_id: &str) -> Result<()> {
+        let _response = self
+            .api_client
+            .delete_user(DeleteUserRequest::new(user_id))?;
+
+        Ok(())
+    }
+
+    /// Replace the password of the user. Requires the `ManageUsers`
+    /// permission.
+    pub fn reset_password(&mut self, user_id: &str, new_password: &str) -> Result<()> {
+        let request = ResetPasswordRequest::new(user_id, new_password);
+        let _response = self.api_client.reset_password(request)?;
+
+        Ok(())
+    }
+
+    /// Export at most `limit` entries of the audit log from `start_seq`.
+    /// The service verifies their chain and seals; check that `broken_seq`
+    /// of the response is `None`, and link consecutive exports with
+    /// `verify_audit_chain`. Requires the `ManageUsers` permission.
+    pub fn export_audit_log(
+        &mut self,
+        start_seq: u64,
+        limit: u32,
+    ) -> Result<ExportAuditLogResponse> {
+        let request = ExportAuditLogRequest::new(start_seq, limit);
+        let response = self.api_client.export_audit_log(request)?;
+
+        Ok(response)
+    }
 }
 
 impl AuthenticationService {
@@ -130,6 +343,50 @@ impl AuthenticationService {
     }
 }
 
+/// Client of the attestation verifier service, which verifies attested TLS
+/// certificates of enclaves against the policies of its runtime config.
+pub struct AttestationVerifierClient {
+    api_client: TeaclaveAttestationVerifierClient,
+}
+
+pub struct AttestationVerifierService;
+
+impl AttestationVerifierClient {
+    pub fn new(api_client: TeaclaveAttestationVerifierClient) -> Self {
+        Self { api_client }
+    }
+
+    /// Verifies `evidence`, the DER-encoded attested TLS certificate of an
+    /// enclave, with the policy named `policy`.
+    pub fn verify(&mut self, evidence: &[u8], policy: &str) -> Result<VerifyResponse> {
+        let request = VerifyRequest::new(evidence, policy);
+        let response = self.api_client.verify(request)?;
+
+        Ok(response)
+    }
+}
+
+impl AttestationVerifierService {
+    pub fn connect(
+        url: &str,
+        enclave_info: &EnclaveInfo,
+        as_root_ca_cert: &[u8],
+    ) -> Result<AttestationVerifierClient> {
+        let enclave_attr = enclave_info
+            .get_enclave_attr("teaclave_attestation_verifier_service")
+            .expect("enclave attr");
+        let config = SgxTrustedTlsClientConfig::new().attestation_report_verifier(
+            vec![enclave_attr],
+            as_root_ca_cert,
+            verifier::universal_quote_verifier,
+        );
+        let channel = Endpoint::new(url).config(config).connect()?;
+        let client = TeaclaveAttestationVerifierClient::new(channel)?;
+
+        Ok(AttestationVerifierClient::new(client))
+    }
+}
+
 #[repr(C)]
 pub struct FrontendService;
 
@@ -152,6 +409,25 @@ impl FrontendService {
 
         Ok(FrontendClient::new(client))
     }
+
+    /// Connect to the frontend service, and check that the measurement of
+    /// the attested enclave is in the transparency log signed with
+    /// `log_public_key` (a raw Ed25519 public key).
+    pub fn connect_with_measurement_log(
+        url: &str,
+        enclave_info: &EnclaveInfo,
+        as_root_ca_cert: &[u8],
+        log_public_key: &[u8],
+    ) -> Result<FrontendClient> {
+        let mut client = Self::connect(url, enclave_info, as_root_ca_cert)?;
+        client.verify_measurement_in_log(
+            "teaclave_frontend_service",
+            enclave_info,
+            log_public_key,
+        )?;
+
+        Ok(client)
+    }
 }
 
 pub struct FrontendClient {
@@ -206,7 +482,14 @@ impl FrontendClient {
             .description(description)
             .executor_type(executor_type);
         if let Some(payload) = payload {
-            request = request.payload(payload.into());
+            // Large payloads are uploaded in parts beforehand, which is
+            // resumed on failures.
+            if payload.len() as u64 > PAYLOAD_PART_LEN {
+                let upload_id = self.upload_function_payload(payload)?;
+                request = request.payload_upload_id(upload_id.as_str().try_into()?);
+            } else {
+                request = request.payload(payload.into());
+            }
         }
         if let Some(arguments) = arguments {
             request = request.arguments(arguments);
@@ -222,6 +505,82 @@ impl FrontendClient {
         Ok(response.function_id.to_string())
     }
 
+    /// Registers the function packaged with the manifest (`function.toml`)
+    /// at `path`, reading the payload from its entry point and the bundle of
+    /// Python modules, if any.
+    pub fn register_function_from_manifest(&mut self, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+        let manifest = FunctionManifest::from_toml(&fs::read_to_string(path)?)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let payload = match &manifest.entry_point {
+            Some(entry_point) => fs::read(dir.join(entry_point))?,
+            None => Vec::new(),
+        };
+        let bundle = match &manifest.bundle {
+            Some(bundle) => fs::read(dir.join(bundle))?,
+            None => Vec::new(),
+        };
+        let mut request =
+            RegisterFunctionRequest::from_manifest(manifest, Vec::new())?.bundle(bundle);
+        if payload.len() as u64 > PAYLOAD_PART_LEN {
+            let upload_id = self.upload_function_payload(&payload)?;
+            request = request.payload_upload_id(upload_id.as_str().try_into()?);
+        } else {
+            request = request.payload(payload);
+        }
+        let response = self.register_function_with_request(request)?;
+
+        Ok(response.function_id.to_string())
+    }
+
+    pub fn begin_payload_upload_with_request(
+        &mut self,
+        request: BeginPayloadUploadRequest,
+    ) -> Result<BeginPayloadUploadResponse> {
+        let response = self.api_client.begin_payload_upload(request)?;
+
+        Ok(response)
+    }
+
+    pub fn upload_part_with_request(
+        &mut self,
+        request: UploadPartRequest,
+    ) -> Result<UploadPartResponse> {
+        let response = self.api_client.upload_part(request)?;
+
+        Ok(response)
+    }
+
+    pub fn commit_payload_with_request(
+        &mut self,
+        request: CommitPayloadRequest,
+    ) -> Result<CommitPayloadResponse> {
+        let response = self.api_client.commit_payload(request)?;
+
+        Ok(response)
+    }
+
+    /// Upload a function payload in parts, retrying failed uploads up to
+    /// `PAYLOAD_UPLOAD_RETRIES` times in a row. Returns the id of the upload,
+    /// which registers the function in place of the payload.
+    pub fn upload_function_payload(&mut self, payload: &[u8]) -> Result<String> {
+        let mut upload = FunctionPayloadUpload::new(payload);
+        let mut retries = 0;
+        loop {
+            match upload.resume(self) {
+                Ok(upload_id) => return Ok(upload_id.to_string()),
+                Err(e) => {
+                    retries += 1;
+                    if retries > PAYLOAD_UPLOAD_RETRIES {
+                        return Err(e);
+                    }
+                }
+            }
+            let one_second = std::time::Duration::from_secs(1);
+            std::thread::sleep(one_second);
+        }
+    }
+
     pub fn get_function_with_request(
         &mut self,
         request: GetFunctionRequest,
@@ -248,6 +607,30 @@ impl FrontendClient {
         Ok(response)
     }
 
+    /// Make an earlier version of the function the one used by the tasks
+    /// asking for the latest, or the version before the current one if
+    /// `version` is 0. Returns the new default version.
+    pub fn rollback_function(&mut self, function_id: &str, version: u32) -> Result<u32> {
+        let request = RollbackFunctionRequest::new(function_id.try_into()?, version);
+        let response = self.api_client.rollback_function(request)?;
+
+        Ok(response.default_version)
+    }
+
+    /// Registers an executor enclave built by the user with its measurement.
+    /// Functions registered with the returned id run only in the enclave,
+    /// on the workers which can launch it.
+    pub fn register_executor_enclave(
+        &mut self,
+        name: &str,
+        measurement: EnclaveMeasurement,
+    ) -> Result<String> {
+        let request = RegisterExecutorEnclaveRequest::new(name, measurement);
+        let response = self.api_client.register_executor_enclave(request)?;
+
+        Ok(response.executor_enclave_id.to_string())
+    }
+
     pub fn register_input_file_with_request(
         &mut self,
         request: RegisterInputFileRequest,
@@ -282,6 +665,42 @@ impl FrontendClient {
         Ok(response.data_id.to_string())
     }
 
+    pub fn register_inline_input_file_with_request(
+        &mut self,
+        request: RegisterInlineInputFileRequest,
+    ) -> Result<RegisterInlineInputFileResponse> {
+        let response = self.api_client.register_inline_input_file(request)?;
+
+        Ok(response)
+    }
+
+    pub fn register_inline_input_file_serialized(
+        &mut self,
+        serialized_request: &str,
+    ) -> Result<String> {
+        let request: frontend_proto::RegisterInlineInputFileRequest =
+            serde_json::from_str(serialized_request)?;
+        let response: frontend_proto::RegisterInlineInputFileResponse = self
+            .register_inline_input_file_with_request(request.try_into()?)?
+            .into();
+        let serialized_response = serde_json::to_string(&response)?;
+
+        Ok(serialized_response)
+    }
+
+    pub fn register_inline_input_file(
+        &mut self,
+        content: &[u8],
+        cmac: &[u8],
+        file_crypto: FileCrypto,
+    ) -> Result<String> {
+        let cmac = FileAuthTag::from_bytes(cmac)?;
+        let request = RegisterInlineInputFileRequest::new(content.to_vec(), cmac, file_crypto);
+        let response = self.register_inline_input_file_with_request(request)?;
+
+        Ok(response.data_id.to_string())
+    }
+
     pub fn register_output_file_with_request(
         &mut self,
         request: RegisterOutputFileRequest,
@@ -462,6 +881,56 @@ impl FrontendClient {
         Ok(())
     }
 
+    /// Invoke a task retried by the scheduler as allowed by the policy if it
+    /// fails. The attempts and the last error are returned by `get_task`.
+    pub fn invoke_task_with_retry(&mut self, task_id: &str, policy: RetryPolicy) -> Result<()> {
+        let request = InvokeTaskRequest::new(task_id.try_into()?).retry_policy(policy);
+        let _ = self.invoke_task_with_request(request)?;
+
+        Ok(())
+    }
+
+    /// Create a task for each of the overrides of the arguments and
+    /// environment of the template, e.g., for parameter sweeps, returning
+    /// their ids in the same order. No task is created if any is invalid.
+    pub fn create_tasks(
+        &mut self,
+        template: CreateTaskRequest,
+        overrides: Vec<TaskOverrides>,
+    ) -> Result<Vec<String>> {
+        let request = CreateTasksRequest::new(template, overrides);
+        let response = self.api_client.create_tasks(request)?;
+
+        Ok(response
+            .task_ids
+            .iter()
+            .map(ExternalID::to_string)
+            .collect())
+    }
+
+    /// Invoke the tasks, returning those which could not be invoked with the
+    /// reasons. The others are invoked.
+    pub fn invoke_tasks(&mut self, task_ids: &[&str]) -> Result<Vec<InvokeTaskFailure>> {
+        let task_ids = task_ids
+            .iter()
+            .map(|id| (*id).try_into())
+            .collect::<Result<_>>()?;
+        let request = InvokeTasksRequest::new(task_ids);
+        let response = self.api_client.invoke_tasks(request)?;
+
+        Ok(response.failures)
+    }
+
+    /// Approve or reject the outputs of a task held by the content scanners,
+    /// as an owner of its input data. The task uploads them once every owner
+    /// has approved them.
+    pub fn review_output(&mut self, task_id: &str, approve: bool) -> Result<ReviewOutputResponse> {
+        let request = ReviewOutputRequest::new(task_id.try_into()?, approve);
+        let response = self.api_client.review_output(request)?;
+
+        Ok(response)
+    }
+
     pub fn get_task_with_request(&mut self, request: GetTaskRequest) -> Result<GetTaskResponse> {
         let response = self.api_client.get_task(request)?;
 
@@ -477,17 +946,636 @@ impl FrontendClient {
         Ok(serialized_response)
     }
 
+    /// Capabilities of the deployment, e.g. supported executors and crypto
+    /// schemes. No credential is needed.
+    pub fn get_platform_info(&mut self) -> Result<GetPlatformInfoResponse> {
+        let request = GetPlatformInfoRequest::new();
+        let response = self.api_client.get_platform_info(request)?;
+
+        Ok(response)
+    }
+
+    /// Get the health checks of the frontend service and of the services it
+    /// depends on.
+    pub fn health(&mut self) -> Result<HealthResponse> {
+        let response = self.api_client.health(HealthRequest::new())?;
+
+        Ok(response)
+    }
+
+    /// Check that the measurement of the service in the enclave info, which
+    /// attested enclaves of the service must present, is in the transparency
+    /// log signed with `log_public_key` (a raw Ed25519 public key).
+    pub fn verify_measurement_in_log(
+        &mut self,
+        service_name: &str,
+        enclave_info: &EnclaveInfo,
+        log_public_key: &[u8],
+    ) -> Result<()> {
+        let measurement = enclave_info
+            .measurements
+            .get(service_name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown service: {}", service_name))?;
+        let entry = MeasurementLogEntry::new(service_name, *measurement);
+        let request = GetMeasurementInclusionRequest::new(entry.clone());
+        let response = self.api_client.get_measurement_inclusion(request)?;
+        response.signed_tree_head.verify(log_public_key)?;
+        response.proof.verify(&entry, &response.signed_tree_head)?;
+
+        Ok(())
+    }
+
+    /// Rejects mutating requests of every user for `duration_secs`, e.g.
+    /// during incident response. Returns the expiry in seconds since the Unix
+    /// epoch. Only platform admins can enter and exit the read-only mode.
+    pub fn enter_read_only_mode(&mut self, duration_secs: u64, reason: &str) -> Result<u64> {
+        let request = EnterReadOnlyModeRequest::new(duration_secs, reason);
+        let response = self.api_client.enter_read_only_mode(request)?;
+
+        Ok(response.expires_at)
+    }
+
+    pub fn exit_read_only_mode(&mut self) -> Result<()> {
+        let request = ExitReadOnlyModeRequest::new();
+        let _ = self.api_client.exit_read_only_mode(request)?;
+
+        Ok(())
+    }
+
+    /// Swap in the access control model of a policy bundle signed by a key
+    /// pinned in the build config. Returns the version of the bundle. Only
+    /// users with the `manage_users` permission can manage policies.
+    pub fn update_access_control_policy(&mut self, bundle: &[u8], signature: &[u8]) -> Result<u64> {
+        let request = UpdateAccessControlPolicyRequest::new(bundle, signature);
+        let response = self.api_client.update_access_control_policy(request)?;
+
+        Ok(response.version)
+    }
+
+    pub fn rollback_access_control_policy(&mut self, version: u64) -> Result<()> {
+        let request = RollbackAccessControlPolicyRequest::new(version);
+        let _ = self.api_client.rollback_access_control_policy(request)?;
+
+        Ok(())
+    }
+
+    /// Returns the current version of the access control policy, and the
+    /// versions available for rollback.
+    pub fn get_access_control_policy(&mut self) -> Result<(u64, Vec<u64>)> {
+        let request = GetAccessControlPolicyRequest::new();
+        let response = self.api_client.get_access_control_policy(request)?;
+
+        Ok((response.version, response.retained_versions))
+    }
+
+    /// Returns whether the access control model lets the user access the
+    /// object (a file, a function or a task), with the decisions it is based
+    /// on. Only users with the `manage_users` permission and the owner of the
+    /// object can ask.
+    pub fn explain_access(
+        &mut self,
+        user_id: &str,
+        object_id: &str,
+    ) -> Result<(bool, Vec<AccessControlDecision>)> {
+        let object_id = object_id.try_into()?;
+        let request = ExplainAccessRequest::new(user_id, object_id);
+        let response = self.api_client.explain_access(request)?;
+
+        Ok((response.accept, response.explanation))
+    }
+
+    /// Hand the functions, data and tasks of a user selected by the filter
+    /// over to another user, e.g., when the user leaves. Returns the ids of
+    /// the transferred objects, and the other owners and participants of them
+    /// to notify. Only users with the `manage_users` permission can transfer.
+    pub fn transfer_ownership(
+        &mut self,
+        from_user: &str,
+        to_user: &str,
+        object_filter: ObjectFilter,
+    ) -> Result<(Vec<String>, Vec<String>)> {
+        let request = TransferOwnershipRequest::new(from_user, to_user, object_filter);
+        let response = self.api_client.transfer_ownership(request)?;
+        let object_ids = response
+            .object_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        let collaborators = response
+            .collaborators
+            .into_iter()
+            .map(|user_id| user_id.to_string())
+            .collect();
+
+        Ok((object_ids, collaborators))
+    }
+
+    /// Set the limits of a user (zero for unlimited), checked before the
+    /// user invokes tasks or registers inline input data. Only users with the
+    /// `manage_users` permission can set quotas.
+    pub fn set_user_quota(&mut self, user_id: &str, quota: UserQuota) -> Result<()> {
+        let request = SetUserQuotaRequest::new(user_id, quota);
+        let _response = self.api_client.set_user_quota(request)?;
+
+        Ok(())
+    }
+
+    /// Get the quota and the current usage of the user, or of another user
+    /// with the `manage_users` permission.
+    pub fn get_quota_usage(&mut self, user_id: Option<&str>) -> Result<(UserQuota, QuotaUsage)> {
+        let request = match user_id {
+            Some(user_id) => GetQuotaUsageRequest::new().user_id(user_id),
+            None => GetQuotaUsageRequest::new(),
+        };
+        let response = self.api_client.get_quota_usage(request)?;
+
+        Ok((response.quota, response.usage))
+    }
+
+    /// Get the statistics of the tasks of the user finished in the last
+    /// `window_secs` (at most a week), with the quota and its usage. Users
+    /// with the `manage_users` permission get those of another user, or of
+    /// all users with recent tasks if `user_id` is `None`.
+    pub fn get_tenant_stats(
+        &mut self,
+        window_secs: u64,
+        user_id: Option<&str>,
+    ) -> Result<Vec<TenantStats>> {
+        let request = match user_id {
+            Some(user_id) => GetTenantStatsRequest::new(window_secs).user_id(user_id),
+            None => GetTenantStatsRequest::new(window_secs),
+        };
+        let response = self.api_client.get_tenant_stats(request)?;
+
+        Ok(response.stats)
+    }
+
+    /// List the execution nodes last reported by the scheduler, with their
+    /// capacity and running tasks (`ExecutorNode::utilization`). Requires
+    /// the `manage_users` permission.
+    pub fn list_nodes(&mut self) -> Result<Vec<ExecutorNode>> {
+        let request = ListNodesRequest::new();
+        let response = self.api_client.list_nodes(request)?;
+
+        Ok(response.nodes)
+    }
+
+    /// List the executors (e.g., `builtin`, `mesapy`) and builtin functions
+    /// of each execution node last reported by the scheduler.
+    pub fn list_executors(&mut self) -> Result<Vec<NodeExecutors>> {
+        let request = ListExecutorsRequest::new();
+        let response = self.api_client.list_executors(request)?;
+
+        Ok(response.nodes)
+    }
+
+    /// Run the task on a schedule: an interval (`@every 1h`) or a cron
+    /// expression in UTC (`0 2 * * *`). The task must be approved with its
+    /// data assigned and is copied by every run, whose output files have
+    /// `{run}` in their URLs replaced by the number of the run.
+    pub fn create_scheduled_task(&mut self, task_id: &str, schedule: &str) -> Result<String> {
+        let request = CreateScheduledTaskRequest::new(task_id.try_into()?, schedule.try_into()?);
+        let response = self.api_client.create_scheduled_task(request)?;
+
+        Ok(response.scheduled_task_id.to_string())
+    }
+
+    pub fn pause_scheduled_task(&mut self, scheduled_task_id: &str) -> Result<()> {
+        let request = PauseScheduledTaskRequest::new(scheduled_task_id.try_into()?);
+        self.api_client.pause_scheduled_task(request)?;
+
+        Ok(())
+    }
+
+    /// Resume the scheduled task, without the runs missed while paused.
+    pub fn resume_scheduled_task(&mut self, scheduled_task_id: &str) -> Result<()> {
+        let request = ResumeScheduledTaskRequest::new(scheduled_task_id.try_into()?);
+        self.api_client.resume_scheduled_task(request)?;
+
+        Ok(())
+    }
+
+    /// Get the next `count` (at most 100) runs of the scheduled task, in
+    /// seconds since the Unix epoch, with the number of runs so far and the
+    /// task of the last one.
+    pub fn list_upcoming_runs(
+        &mut self,
+        scheduled_task_id: &str,
+        count: u32,
+    ) -> Result<ListUpcomingRunsResponse> {
+        let request = ListUpcomingRunsRequest::new(scheduled_task_id.try_into()?, count);
+        let response = self.api_client.list_upcoming_runs(request)?;
+
+        Ok(response)
+    }
+
+    /// Run the tasks as a pipeline: once a task succeeds, its outputs are
+    /// assigned to the inputs they are linked to, and each task is invoked
+    /// when its upstream tasks have succeeded. The tasks must be created by
+    /// the user and not yet invoked; tasks with other participants still
+    /// need their approvals.
+    pub fn create_pipeline(
+        &mut self,
+        task_ids: &[&str],
+        links: Vec<PipelineLink>,
+    ) -> Result<String> {
+        let task_ids = task_ids
+            .iter()
+            .map(|id| (*id).try_into())
+            .collect::<Result<_>>()?;
+        let request = CreatePipelineRequest::new(task_ids, links);
+        let response = self.api_client.create_pipeline(request)?;
+
+        Ok(response.pipeline_id.to_string())
+    }
+
+    /// Get the tasks of the pipeline in the order of their stages, with its
+    /// status and the task whose failure failed it.
+    pub fn get_pipeline(&mut self, pipeline_id: &str) -> Result<GetPipelineResponse> {
+        let request = GetPipelineRequest::new(pipeline_id.try_into()?);
+        let response = self.api_client.get_pipeline(request)?;
+
+        Ok(response)
+    }
+
+    /// List a page of the functions of the user and the public functions,
+    /// returning the functions and the cursor of the next page, or `None`
+    /// after the last page.
+    pub fn list_functions(
+        &mut self,
+        request: ListFunctionsRequest,
+    ) -> Result<(Vec<FunctionSummary>, Option<String>)> {
+        let response = self.api_client.list_functions(request)?;
+
+        Ok((response.functions, response.next_cursor))
+    }
+
+    /// List a page of the tasks in which the user participates, returning the
+    /// tasks and the cursor of the next page, or `None` after the last page.
+    pub fn list_tasks(
+        &mut self,
+        request: ListTasksRequest,
+    ) -> Result<(Vec<TaskSummary>, Option<String>)> {
+        let response = self.api_client.list_tasks(request)?;
+
+        Ok((response.tasks, response.next_cursor))
+    }
+
+    /// Register the HTTPS endpoint to which the state changes of the tasks of
+    /// the user are posted, replacing the previous one. Returns the secret
+    /// with which the endpoint verifies the events (`TaskEvent::verify`).
+    pub fn register_webhook(&mut self, url: &str) -> Result<Vec<u8>> {
+        let request = RegisterWebhookRequest::new(Url::parse(url)?);
+        let response = self.api_client.register_webhook(request)?;
+
+        Ok(response.secret)
+    }
+
+    pub fn delete_webhook(&mut self) -> Result<()> {
+        let request = DeleteWebhookRequest::new();
+        let _response = self.api_client.delete_webhook(request)?;
+
+        Ok(())
+    }
+
+    /// Check the records of the management service for inconsistencies left
+    /// by crashes, and repair those which can be repaired if `repair` is
+    /// set. Requires the manage_users permission.
+    pub fn check_consistency(&mut self, repair: bool) -> Result<Vec<ConsistencyFinding>> {
+        let request = CheckConsistencyRequest::new(repair);
+        let response = self.api_client.check_consistency(request)?;
+
+        Ok(response.findings)
+    }
+
+    pub fn get_task_result_with_request(
+        &mut self,
+        request: GetTaskResultRequest,
+    ) -> Result<GetTaskResultResponse> {
+        let response = self.api_client.get_task_result(request)?;
+
+        Ok(response)
+    }
+
+    /// Get at most `limit` bytes of the log of a finished task from
+    /// `offset`, or the rest of it if `limit` is 0. The log is kept also
+    /// when the task failed.
+    pub fn get_task_log(
+        &mut self,
+        task_id: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<GetTaskLogResponse> {
+        let request = GetTaskLogRequest::new(task_id.try_into()?).range(offset, limit);
+        let response = self.api_client.get_task_log(request)?;
+
+        Ok(response)
+    }
+
+    /// Get at most `limit` intermediate results of a task from the chunk at
+    /// `offset`, or the rest of them if `limit` is 0. Functions emit them
+    /// while they run, and the task may not have finished yet.
+    pub fn stream_task_result(
+        &mut self,
+        task_id: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<StreamTaskResultResponse> {
+        let request = StreamTaskResultRequest::new(task_id.try_into()?).range(offset, limit);
+        let response = self.api_client.stream_task_result(request)?;
+
+        Ok(response)
+    }
+
+    /// Pass the intermediate results of a task to `on_chunk` as they are
+    /// emitted, until the task finishes. Returns whether chunks were dropped
+    /// as they exceeded the limit of the platform.
+    pub fn follow_task_result(
+        &mut self,
+        task_id: &str,
+        mut on_chunk: impl FnMut(&[u8]),
+    ) -> Result<bool> {
+        let mut offset = 0;
+        loop {
+            let response = self.stream_task_result(task_id, offset, 0)?;
+            for chunk in response.chunks.iter() {
+                on_chunk(chunk);
+            }
+            offset = response.next_offset;
+            if response.finished {
+                return Ok(response.truncated);
+            }
+            let one_second = std::time::Duration::from_secs(1);
+            std::thread::sleep(one_second);
+        }
+    }
+
+    /// Wait for the task to finish and download its return value, retrying
+    /// failed ranges up to `TASK_RESULT_RETRIES` times in a row.
     pub fn get_task_result(&mut self, task_id: &str) -> Result<Vec<u8>> {
+        let mut download = TaskResultDownload::new(task_id)?;
+        let mut retries = 0;
         loop {
-            let request = GetTaskRequest::new(task_id.try_into()?);
-            let response = self.get_task_with_request(request)?;
-            if let TaskResult::Ok(task_outputs) = response.result {
-                return Ok(task_outputs.return_value);
+            let received_len = download.received_len();
+            match download.resume(self) {
+                Ok(Some(return_value)) => return Ok(return_value),
+                Ok(None) => (),
+                Err(e) => {
+                    if download.received_len() > received_len {
+                        retries = 0;
+                    }
+                    retries += 1;
+                    if retries > TASK_RESULT_RETRIES {
+                        return Err(e);
+                    }
+                }
             }
             let one_second = std::time::Duration::from_secs(1);
             std::thread::sleep(one_second);
         }
     }
+
+    /// Export the result of a succeeded task as a CWT in a COSE_Sign1
+    /// envelope, which can be checked with `verify_task_result_cwt` or any
+    /// COSE library.
+    pub fn export_task_result(&mut self, task_id: &str) -> Result<Vec<u8>> {
+        // The return value itself is covered by the digest in the claims.
+        let request = GetTaskResultRequest::new(task_id.try_into()?)
+            .range(0, 1)
+            .export_cwt();
+        let response = self.api_client.get_task_result(request)?;
+        match response.result {
+            TaskResult::Ok(_) => Ok(response.cwt),
+            TaskResult::Err(failure) => bail!("task failed: {}", failure),
+            TaskResult::NotReady => bail!("task not finished"),
+        }
+    }
+
+    /// Get the execution receipt of a succeeded deterministic task, which can
+    /// be checked with `verify_execution_receipt`.
+    pub fn get_execution_receipt(&mut self, task_id: &str) -> Result<Vec<u8>> {
+        let request = GetTaskResultRequest::new(task_id.try_into()?).range(0, 1);
+        let response = self.api_client.get_task_result(request)?;
+        match response.result {
+            TaskResult::Ok(outputs) => match outputs.receipt {
+                Some(receipt) => Ok(receipt),
+                None => bail!("no execution receipt, the task is not deterministic"),
+            },
+            TaskResult::Err(failure) => bail!("task failed: {}", failure),
+            TaskResult::NotReady => bail!("task not finished"),
+        }
+    }
+}
+
+/// Verify a task result exported by `export_task_result`: the signing
+/// certificate must carry a valid attestation report of the management
+/// service in `enclave_info`, and its key must have signed the token.
+pub fn verify_task_result_cwt(
+    cwt: &[u8],
+    enclave_info: &EnclaveInfo,
+    as_root_ca_cert: &[u8],
+) -> Result<TaskResultClaims> {
+    let (payload, measurement) = verify_cose_sign1(
+        cwt,
+        enclave_info,
+        as_root_ca_cert,
+        "teaclave_management_service",
+    )?;
+    let claims = TaskResultClaims::from_slice(&payload)?;
+    ensure!(
+        claims.attestation.measurement == measurement,
+        "attestation claims mismatched"
+    );
+    Ok(claims)
+}
+
+/// Verify an execution receipt got with `get_execution_receipt`: the signing
+/// certificate must carry a valid attestation report of the execution
+/// service in `enclave_info`, and its key must have signed the receipt. The
+/// claims identify the function, arguments and inputs which produced the
+/// outputs.
+pub fn verify_execution_receipt(
+    receipt: &[u8],
+    enclave_info: &EnclaveInfo,
+    as_root_ca_cert: &[u8],
+) -> Result<ExecutionReceiptClaims> {
+    let (payload, measurement) = verify_cose_sign1(
+        receipt,
+        enclave_info,
+        as_root_ca_cert,
+        "teaclave_execution_service",
+    )?;
+    let claims = ExecutionReceiptClaims::from_slice(&payload)?;
+    ensure!(
+        claims.attestation.measurement == measurement,
+        "attestation claims mismatched"
+    );
+    Ok(claims)
+}
+
+// Checks that the token is signed by the key of a certificate attested by
+// the enclave of the service, returning the payload and the measurement.
+fn verify_cose_sign1(
+    token: &[u8],
+    enclave_info: &EnclaveInfo,
+    as_root_ca_cert: &[u8],
+    service: &str,
+) -> Result<(Vec<u8>, EnclaveMeasurement)> {
+    let token = CoseSign1::from_slice(token)?;
+    let report = AttestationReport::from_cert(&token.certificate, as_root_ca_cert)?;
+    let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+    let measurement = match enclave_info.measurements.get(service) {
+        Some(measurement) => *measurement,
+        None => bail!("no measurement of {}", service),
+    };
+    ensure!(
+        enclave_report.mr_enclave == measurement.mr_enclave
+            && enclave_report.mr_signer == measurement.mr_signer,
+        "token not signed by {}",
+        service
+    );
+
+    // The report data is the public key of the certificate without the
+    // leading byte of its uncompressed encoding.
+    let mut public_key = vec![4u8];
+    public_key.extend_from_slice(enclave_report.report_data.as_bytes());
+    token.verify(&public_key)?;
+    Ok((token.payload, measurement))
+}
+
+/// Length of the ranges of return values fetched by `TaskResultDownload`.
+pub const TASK_RESULT_RANGE_LEN: u64 = 1024 * 1024;
+const TASK_RESULT_RETRIES: usize = 3;
+
+/// Download of the return value of a task in ranges. After a failure, e.g.,
+/// of the connection, `resume` continues from the end of the ranges received
+/// so far, possibly with a new client. The value is verified against the hash
+/// reported by the service once complete.
+pub struct TaskResultDownload {
+    task_id: ExternalID,
+    range_len: u64,
+    sink: Option<BlobSink>,
+}
+
+impl TaskResultDownload {
+    pub fn new(task_id: &str) -> Result<Self> {
+        Ok(Self {
+            task_id: task_id.try_into()?,
+            range_len: TASK_RESULT_RANGE_LEN,
+            sink: None,
+        })
+    }
+
+    pub fn range_len(self, range_len: u64) -> Self {
+        Self { range_len, ..self }
+    }
+
+    pub fn received_len(&self) -> u64 {
+        self.sink.as_ref().map_or(0, |sink| sink.offset())
+    }
+
+    /// Fetch the remaining ranges, returning the return value, or `None` if
+    /// the task has not finished. A value not matching the hash is discarded,
+    /// so that the next call starts over.
+    pub fn resume(&mut self, client: &mut FrontendClient) -> Result<Option<Vec<u8>>> {
+        loop {
+            let offset = self.received_len();
+            let request =
+                GetTaskResultRequest::new(self.task_id.clone()).range(offset, self.range_len);
+            let response = client.get_task_result_with_request(request)?;
+            let range = match response.result {
+                TaskResult::Ok(outputs) => outputs.return_value,
+                TaskResult::Err(failure) => bail!("task failed: {}", failure),
+                TaskResult::NotReady => return Ok(None),
+            };
+            if self.sink.is_none() {
+                self.sink = Some(BlobSink::new(
+                    response.return_value_len,
+                    response.return_value_hash.clone(),
+                )?);
+            }
+            let sink = self.sink.as_mut().unwrap();
+            if sink.total_len() != response.return_value_len
+                || sink.hash() != response.return_value_hash.as_slice()
+            {
+                self.sink = None;
+                bail!("return value changed during the download");
+            }
+            sink.write(offset, &range)?;
+            if !sink.is_complete() {
+                continue;
+            }
+            let sink = self.sink.take().unwrap();
+            return sink
+                .finish()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("hash of the return value mismatched"));
+        }
+    }
+}
+
+/// Length of the parts of function payloads uploaded by
+/// `FunctionPayloadUpload`.
+pub const PAYLOAD_PART_LEN: u64 = 1024 * 1024;
+const PAYLOAD_UPLOAD_RETRIES: usize = 3;
+
+/// Upload of a function payload in parts. After a failure, e.g., of the
+/// connection, `resume` asks the service for the parts received so far and
+/// uploads the others, possibly with a new client.
+pub struct FunctionPayloadUpload<'a> {
+    payload: BlobSource<'a>,
+    part_len: u64,
+    upload_id: Option<ExternalID>,
+}
+
+impl<'a> FunctionPayloadUpload<'a> {
+    pub fn new(payload: &'a [u8]) -> Self {
+        Self {
+            payload: BlobSource::new(payload),
+            part_len: PAYLOAD_PART_LEN,
+            upload_id: None,
+        }
+    }
+
+    pub fn part_len(self, part_len: u64) -> Self {
+        Self { part_len, ..self }
+    }
+
+    /// The id of the upload once begun.
+    pub fn upload_id(&self) -> Option<&ExternalID> {
+        self.upload_id.as_ref()
+    }
+
+    /// Upload the parts not received by the service yet and commit the
+    /// payload, returning the id of the upload.
+    pub fn resume(&mut self, client: &mut FrontendClient) -> Result<ExternalID> {
+        let parts = self.payload.parts(self.part_len)?;
+        let mut request = BeginPayloadUploadRequest::new(
+            self.payload.len(),
+            self.part_len,
+            self.payload.hash().to_vec(),
+        );
+        if let Some(upload_id) = &self.upload_id {
+            request = request.resume(upload_id.clone());
+        }
+        let response = client.begin_payload_upload_with_request(request)?;
+        let upload_id = response.upload_id;
+        self.upload_id = Some(upload_id.clone());
+
+        for (part_number, part) in parts {
+            if response.received_parts.contains(&part_number) {
+                continue;
+            }
+            let part_hash = blob_hash(part);
+            let request =
+                UploadPartRequest::new(upload_id.clone(), part_number, part.to_vec(), part_hash);
+            client.upload_part_with_request(request)?;
+        }
+        let request = CommitPayloadRequest::new(upload_id.clone());
+        client.commit_payload_with_request(request)?;
+
+        Ok(upload_id)
+    }
 }
 
 #[cfg(test)]
@@ -530,6 +1618,8 @@ mod tests {
 
         let mut client =
             FrontendService::connect("localhost:7777", &enclave_info, &as_root_ca_cert).unwrap();
+        let platform_info = client.get_platform_info().unwrap();
+        assert!(platform_info.executors.contains(&Executor::Builtin));
         client.set_credential(USER_ID, &token);
         let function_id = client
             .register_function(
@@ -556,7 +1646,11 @@ mod tests {
 
         let _ = client.invoke_task(&task_id).unwrap();
         let result = client.get_task_result(&task_id).unwrap();
-        assert_eq!(result, b"Hello, Teaclave!")
+        assert_eq!(result, b"Hello, Teaclave!");
+
+        let mut download = TaskResultDownload::new(&task_id).unwrap().range_len(4);
+        let result = download.resume(&mut client).unwrap();
+        assert_eq!(result.unwrap(), b"Hello, Teaclave!")
     }
 
     #[test]
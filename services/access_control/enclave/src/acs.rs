@@ -15,22 +15,25 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::policy::PolicyModel;
+use crate::versions::PolicyVersions;
 use anyhow::{anyhow, Result};
 use cfg_if::cfg_if;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::prelude::v1::*;
 use std::sync::Arc;
+use teaclave_config::AccessControlEngine;
 cfg_if! {
     if #[cfg(feature = "mesalock_sgx")]  {
-        use std::sync::SgxMutex as Mutex;
+        use std::sync::{SgxMutex as Mutex, SgxRwLock as RwLock};
     } else {
-        use std::sync::Mutex;
+        use std::sync::{Mutex, RwLock};
     }
 }
 
-const MODEL_TEXT: &str = include_str!("../../model.conf");
+pub(crate) const MODEL_TEXT: &str = include_str!("../../model.conf");
 extern "C" {
     fn acs_setup_model(model_text: *const c_char) -> i32;
     fn acs_enforce_request(request_type: *const c_char, request_content: *const c_char) -> i32;
@@ -47,12 +50,61 @@ pub(crate) enum EnforceRequest {
     UserAccessFunction(String, String),
     // user_access_task= usr, task
     UserAccessTask(String, String),
+    // user_own_function = usr, function
+    UserOwnFunction(String, String),
     // task_access_function = task, function
     TaskAccessFunction(String, String),
     // task_access_data = task, data
     TaskAccessData(String, String),
 }
 
+impl EnforceRequest {
+    // One request of each kind, to check that a model serves all of them.
+    pub(crate) fn samples() -> Vec<EnforceRequest> {
+        let (a, b) = (String::new(), String::new());
+        vec![
+            EnforceRequest::UserAccessData(a.clone(), b.clone()),
+            EnforceRequest::UserAccessFunction(a.clone(), b.clone()),
+            EnforceRequest::UserAccessTask(a.clone(), b.clone()),
+            EnforceRequest::UserOwnFunction(a.clone(), b.clone()),
+            EnforceRequest::TaskAccessFunction(a.clone(), b.clone()),
+            EnforceRequest::TaskAccessData(a, b),
+        ]
+    }
+
+    // Name of the request and its matcher in the model
+    pub(crate) fn rule(&self) -> &'static str {
+        match self {
+            EnforceRequest::UserAccessData(..) => "user_access_data",
+            EnforceRequest::UserAccessFunction(..) => "user_access_function",
+            EnforceRequest::UserAccessTask(..) => "user_access_task",
+            EnforceRequest::UserOwnFunction(..) => "user_own_function",
+            EnforceRequest::TaskAccessFunction(..) => "task_access_function",
+            EnforceRequest::TaskAccessData(..) => "task_access_data",
+        }
+    }
+
+    // Attributes of the request, named as in the model
+    pub(crate) fn attributes(&self) -> HashMap<String, String> {
+        let (names, values) = match self {
+            EnforceRequest::UserAccessData(usr, data) => (("usr", "data"), (usr, data)),
+            EnforceRequest::UserAccessFunction(usr, function)
+            | EnforceRequest::UserOwnFunction(usr, function) => {
+                (("usr", "function"), (usr, function))
+            }
+            EnforceRequest::UserAccessTask(usr, task) => (("usr", "task"), (usr, task)),
+            EnforceRequest::TaskAccessFunction(task, function) => {
+                (("task", "function"), (task, function))
+            }
+            EnforceRequest::TaskAccessData(task, data) => (("task", "data"), (task, data)),
+        };
+        let mut attributes = HashMap::new();
+        attributes.insert(names.0.to_string(), values.0.to_string());
+        attributes.insert(names.1.to_string(), values.1.to_string());
+        attributes
+    }
+}
+
 #[cfg(test_mode)]
 pub(crate) enum AccessControlTerms {
     // data_owner = data, usr
@@ -65,6 +117,33 @@ pub(crate) enum AccessControlTerms {
     TaskParticipant(String, String),
 }
 
+#[cfg(test_mode)]
+impl AccessControlTerms {
+    // Name of the term in the model
+    pub(crate) fn term(&self) -> &'static str {
+        match self {
+            AccessControlTerms::DataOwner(..) => "data_owner",
+            AccessControlTerms::FunctionOwner(..) => "function_owner",
+            AccessControlTerms::IsPublicFunction(..) => "is_public_function",
+            AccessControlTerms::TaskParticipant(..) => "task_participant",
+        }
+    }
+
+    // Values of the fact, in the order of the model
+    pub(crate) fn values(&self) -> Vec<String> {
+        match self {
+            AccessControlTerms::DataOwner(data, usr) => vec![data.to_string(), usr.to_string()],
+            AccessControlTerms::FunctionOwner(function, usr) => {
+                vec![function.to_string(), usr.to_string()]
+            }
+            AccessControlTerms::IsPublicFunction(function) => vec![function.to_string()],
+            AccessControlTerms::TaskParticipant(task, usr) => {
+                vec![task.to_string(), usr.to_string()]
+            }
+        }
+    }
+}
+
 pub trait PyMarshallable {
     fn marshal(&self, buffer: &mut String);
 }
@@ -153,162 +232,257 @@ impl PyMarshallable for &String {
 }
 
 #[cfg(test_mode)]
-pub(crate) fn init_mock_data() -> Result<()> {
+pub(crate) fn mock_terms() -> Vec<AccessControlTerms> {
+    let mut terms = Vec::new();
+
     // mock data for AuthorizeData
     let term = AccessControlTerms::DataOwner("mock_data".to_string(), "mock_user_a".to_string());
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::DataOwner("mock_data".to_string(), "mock_user_b".to_string());
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::DataOwner("mock_data".to_string(), "mock_user_c".to_string());
-    announce_fact(term)?;
+    terms.push(term);
 
     // mock data for AuthorizeFunction
     let term = AccessControlTerms::FunctionOwner(
         "mock_private_function".to_string(),
         "mock_private_function_owner".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::FunctionOwner(
         "mock_public_function".to_string(),
         "mock_public_function_owner".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::IsPublicFunction("mock_public_function".to_string());
-    announce_fact(term)?;
+    terms.push(term);
 
     // mock data for AuthorizeTask
     let term = AccessControlTerms::TaskParticipant(
         "mock_task".to_string(),
         "mock_participant_a".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::TaskParticipant(
         "mock_task".to_string(),
         "mock_participant_b".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
 
     // mock data for AuthorizeStagedTask
     let term = AccessControlTerms::TaskParticipant(
         "mock_staged_task".to_string(),
         "mock_staged_participant_a".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::TaskParticipant(
         "mock_staged_task".to_string(),
         "mock_staged_participant_b".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::FunctionOwner(
         "mock_staged_allowed_private_function".to_string(),
         "mock_staged_participant_a".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::FunctionOwner(
         "mock_staged_disallowed_private_function".to_string(),
         "mock_staged_non_participant".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::IsPublicFunction("mock_staged_public_function".to_string());
-    announce_fact(term)?;
+    terms.push(term);
 
     let term = AccessControlTerms::DataOwner(
         "mock_staged_allowed_data1".to_string(),
         "mock_staged_participant_a".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::DataOwner(
         "mock_staged_allowed_data2".to_string(),
         "mock_staged_participant_b".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::DataOwner(
         "mock_staged_allowed_data3".to_string(),
         "mock_staged_participant_a".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::DataOwner(
         "mock_staged_allowed_data3".to_string(),
         "mock_staged_participant_b".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
 
     let term = AccessControlTerms::DataOwner(
         "mock_staged_disallowed_data1".to_string(),
         "mock_staged_non_participant".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::DataOwner(
         "mock_staged_disallowed_data2".to_string(),
         "mock_staged_participant_a".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
     let term = AccessControlTerms::DataOwner(
         "mock_staged_disallowed_data2".to_string(),
         "mock_staged_non_participant".to_string(),
     );
-    announce_fact(term)?;
+    terms.push(term);
 
-    Ok(())
+    terms
 }
 
+/// Enforces requests with the engine selected in the runtime config.
 #[derive(Clone)]
-pub(crate) struct AccessControlModule {
-    lock: Arc<Mutex<u32>>,
+pub(crate) enum AccessControlModule {
+    // The Python engine has a single global model, see `init_acs`.
+    Python { lock: Arc<Mutex<u32>> },
+    // The native engine can swap in signed policy bundles at runtime.
+    Native(Arc<RwLock<PolicyVersions>>),
 }
 
 impl AccessControlModule {
-    pub(crate) fn new() -> Self {
-        AccessControlModule {
+    pub(crate) fn new(engine: AccessControlEngine, policy_signers: &[&[u8]]) -> Result<Self> {
+        let module = match engine {
+            AccessControlEngine::Python => {
+                init_acs()?;
+                Self::python()
+            }
+            AccessControlEngine::Native => {
+                #[allow(unused_mut)]
+                let mut model = PolicyModel::new(MODEL_TEXT)?;
+                #[cfg(test_mode)]
+                for term in mock_terms() {
+                    model.add_fact(term.term(), term.values())?;
+                }
+                let signers = policy_signers.iter().map(|key| key.to_vec()).collect();
+                let versions = PolicyVersions::new(MODEL_TEXT, model, signers);
+                AccessControlModule::Native(Arc::new(RwLock::new(versions)))
+            }
+        };
+        Ok(module)
+    }
+
+    // The Python engine, set up with `init_acs`.
+    pub(crate) fn python() -> Self {
+        AccessControlModule::Python {
             lock: Arc::new(Mutex::new(0)),
         }
     }
 
     pub(crate) fn enforce_request(&self, request: EnforceRequest) -> Result<bool> {
-        let (request_type, request_content) = match request {
-            EnforceRequest::UserAccessData(usr, data) => {
-                let mut buffer = String::new();
-                (usr, data).marshal(&mut buffer);
-                ("user_access_data", buffer)
+        match self {
+            AccessControlModule::Python { lock } => enforce_python_request(lock, request),
+            AccessControlModule::Native(versions) => {
+                // Requests are enforced with the model current at the time.
+                let model = versions
+                    .read()
+                    .map_err(|_| anyhow!("failed to accquire lock"))?
+                    .model();
+                model.enforce(request.rule(), &request.attributes())
             }
-            EnforceRequest::UserAccessFunction(usr, function) => {
-                let mut buffer = String::new();
-                (usr, function).marshal(&mut buffer);
-                ("user_access_function", buffer)
-            }
-            EnforceRequest::UserAccessTask(usr, task) => {
-                let mut buffer = String::new();
-                (usr, task).marshal(&mut buffer);
-                ("user_access_task", buffer)
-            }
-            EnforceRequest::TaskAccessFunction(task, function) => {
-                let mut buffer = String::new();
-                (task, function).marshal(&mut buffer);
-                ("task_access_function", buffer)
-            }
-            EnforceRequest::TaskAccessData(task, data) => {
-                let mut buffer = String::new();
-                (task, data).marshal(&mut buffer);
-                ("task_access_data", buffer)
+        }
+    }
+
+    /// Checks the label rules of the current model. Models of the Python
+    /// engine cannot have label rules.
+    pub(crate) fn denied_labels(&self, tags: &[String], labels: &[String]) -> Result<Vec<String>> {
+        match self {
+            AccessControlModule::Python { .. } => Ok(Vec::new()),
+            AccessControlModule::Native(versions) => {
+                let model = versions
+                    .read()
+                    .map_err(|_| anyhow!("failed to accquire lock"))?
+                    .model();
+                Ok(model.denied_labels(tags, labels))
             }
-        };
+        }
+    }
+
+    /// Swaps in the model of a signed policy bundle, and returns its version.
+    pub(crate) fn update_policy(&self, bundle: &[u8], bundle_signature: &[u8]) -> Result<u64> {
+        self.versions()?
+            .write()
+            .map_err(|_| anyhow!("failed to accquire lock"))?
+            .update(bundle, bundle_signature)
+    }
+
+    pub(crate) fn rollback_policy(&self, version: u64) -> Result<()> {
+        self.versions()?
+            .write()
+            .map_err(|_| anyhow!("failed to accquire lock"))?
+            .rollback(version)
+    }
 
-        let c_request_type = CString::new(request_type.to_string())?;
-        let c_request_content = CString::new(request_content)?;
-        let _lock = self
-            .lock
-            .lock()
+    /// The current version, and the versions retained for rollback.
+    pub(crate) fn policy_versions(&self) -> Result<(u64, Vec<u64>)> {
+        let versions = self
+            .versions()?
+            .read()
             .map_err(|_| anyhow!("failed to accquire lock"))?;
-        let py_ret =
-            unsafe { acs_enforce_request(c_request_type.as_ptr(), c_request_content.as_ptr()) };
+        Ok((versions.version(), versions.retained_versions()))
+    }
+
+    fn versions(&self) -> Result<&RwLock<PolicyVersions>> {
+        match self {
+            AccessControlModule::Native(versions) => Ok(versions),
+            AccessControlModule::Python { .. } => {
+                Err(anyhow!("policies of the python engine cannot be updated"))
+            }
+        }
+    }
+}
 
-        match py_ret {
-            0 => Ok(false),
-            1 => Ok(true),
-            _ => Err(anyhow!("mesapy error")),
+fn enforce_python_request(lock: &Mutex<u32>, request: EnforceRequest) -> Result<bool> {
+    let (request_type, request_content) = match request {
+        EnforceRequest::UserAccessData(usr, data) => {
+            let mut buffer = String::new();
+            (usr, data).marshal(&mut buffer);
+            ("user_access_data", buffer)
+        }
+        EnforceRequest::UserAccessFunction(usr, function) => {
+            let mut buffer = String::new();
+            (usr, function).marshal(&mut buffer);
+            ("user_access_function", buffer)
+        }
+        EnforceRequest::UserAccessTask(usr, task) => {
+            let mut buffer = String::new();
+            (usr, task).marshal(&mut buffer);
+            ("user_access_task", buffer)
+        }
+        EnforceRequest::UserOwnFunction(usr, function) => {
+            let mut buffer = String::new();
+            (usr, function).marshal(&mut buffer);
+            ("user_own_function", buffer)
         }
+        EnforceRequest::TaskAccessFunction(task, function) => {
+            let mut buffer = String::new();
+            (task, function).marshal(&mut buffer);
+            ("task_access_function", buffer)
+        }
+        EnforceRequest::TaskAccessData(task, data) => {
+            let mut buffer = String::new();
+            (task, data).marshal(&mut buffer);
+            ("task_access_data", buffer)
+        }
+    };
+
+    let c_request_type = CString::new(request_type.to_string())?;
+    let c_request_content = CString::new(request_content)?;
+    let _lock = lock
+        .lock()
+        .map_err(|_| anyhow!("failed to accquire lock"))?;
+    let py_ret =
+        unsafe { acs_enforce_request(c_request_type.as_ptr(), c_request_content.as_ptr()) };
+
+    match py_ret {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(anyhow!("mesapy error")),
     }
 }
+
 pub(crate) fn init_acs() -> Result<()> {
     let ec = unsafe { acs_setup_model(CString::new(MODEL_TEXT).unwrap().as_ptr()) };
 
@@ -316,35 +490,18 @@ pub(crate) fn init_acs() -> Result<()> {
         Err(anyhow!("failed to init mesapy"))
     } else {
         #[cfg(test_mode)]
-        init_mock_data()?;
+        for term in mock_terms() {
+            announce_fact(term)?;
+        }
         Ok(())
     }
 }
 
 #[cfg(test_mode)]
-fn announce_fact(term: AccessControlTerms) -> Result<()> {
-    let (term_type, term_fact) = match term {
-        AccessControlTerms::DataOwner(data, usr) => {
-            let mut buffer = String::new();
-            (data, usr).marshal(&mut buffer);
-            ("data_owner", buffer)
-        }
-        AccessControlTerms::FunctionOwner(function, usr) => {
-            let mut buffer = String::new();
-            (function, usr).marshal(&mut buffer);
-            ("function_owner", buffer)
-        }
-        AccessControlTerms::IsPublicFunction(function) => {
-            let mut buffer = String::new();
-            (function,).marshal(&mut buffer);
-            ("is_public_function", buffer)
-        }
-        AccessControlTerms::TaskParticipant(task, usr) => {
-            let mut buffer = String::new();
-            (task, usr).marshal(&mut buffer);
-            ("task_participant", buffer)
-        }
-    };
+pub(crate) fn announce_fact(term: AccessControlTerms) -> Result<()> {
+    let term_type = term.term();
+    let mut term_fact = String::new();
+    term.values().marshal(&mut term_fact);
     let c_term_type = CString::new(term_type.to_string())?;
     let c_term_fact = CString::new(term_fact)?;
 
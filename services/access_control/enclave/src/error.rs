@@ -24,6 +24,10 @@ use thiserror::Error;
 pub(crate) enum TeaclavAccessControlError {
     #[error("access control error")]
     AccessControlError,
+    #[error("permission denied")]
+    PermissionDenied,
+    #[error("policy error: {0}")]
+    PolicyError(String),
 }
 
 impl From<TeaclavAccessControlError> for TeaclaveServiceResponseError {
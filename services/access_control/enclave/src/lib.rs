@@ -25,6 +25,7 @@ extern crate log;
 use anyhow::{anyhow, Result};
 
 use std::prelude::v1::*;
+use std::sync::Arc;
 use teaclave_attestation::{verifier, AttestationConfig, RemoteAttestation};
 use teaclave_binder::proto::{
     ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
@@ -32,20 +33,24 @@ use teaclave_binder::proto::{
 };
 use teaclave_binder::{handle_ecall, register_ecall_handler};
 use teaclave_config::build::{
-    ACCESS_CONTROL_INBOUND_SERVICES, AS_ROOT_CA_CERT, AUDITOR_PUBLIC_KEYS,
+    ACCESS_CONTROL_INBOUND_SERVICES, ACCESS_CONTROL_POLICY_SIGNERS, AS_ROOT_CA_CERT,
+    AUDITOR_PUBLIC_KEYS,
 };
 use teaclave_config::RuntimeConfig;
 use teaclave_proto::teaclave_access_control_service::{
     TeaclaveAccessControlRequest, TeaclaveAccessControlResponse,
 };
-use teaclave_rpc::config::SgxTrustedTlsServerConfig;
+use teaclave_rpc::config::{SgxTrustedTlsServerConfig, TlsPolicy};
+use teaclave_rpc::interceptor::LoggingInterceptor;
 use teaclave_rpc::server::SgxTrustedTlsServer;
 use teaclave_service_enclave_utils::ServiceEnclave;
 use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
 
 mod acs;
 mod error;
+mod policy;
 mod service;
+mod versions;
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
     let listen_address = config.internal_endpoints.access_control.listen_address;
@@ -67,18 +72,24 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         })
         .collect::<Result<_>>()?;
     let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
-        .attestation_report_verifier(
-        accepted_enclave_attrs,
-        AS_ROOT_CA_CERT,
-        verifier::universal_quote_verifier,
-    )?;
+        .attestation_report_verifier_with_policy(
+            accepted_enclave_attrs,
+            AS_ROOT_CA_CERT,
+            verifier::universal_quote_verifier,
+            verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+        )?
+        .tls_policy(TlsPolicy::from_teaclave_config(&config))?;
 
-    acs::init_acs()?;
+    let access_control_module =
+        acs::AccessControlModule::new(config.access_control.engine, ACCESS_CONTROL_POLICY_SIGNERS)?;
     let mut server = SgxTrustedTlsServer::<
         TeaclaveAccessControlResponse,
         TeaclaveAccessControlRequest,
-    >::new(listen_address, server_config);
-    let service = service::TeaclaveAccessControlService::new();
+    >::new(listen_address, server_config)
+    .message_limits(&config.internal_endpoints.access_control.message_limits)
+    .unix_socket(config.internal_endpoints.access_control.unix_socket.clone())
+    .interceptor(Arc::new(LoggingInterceptor::new("access_control")));
+    let service = service::TeaclaveAccessControlService::new(access_control_module);
     match server.start(service) {
         Ok(_) => (),
         Err(e) => {
@@ -133,6 +144,14 @@ pub mod tests {
             service::tests::user_access_task,
             service::tests::task_access_function,
             service::tests::task_access_data,
+            service::tests::explain_decisions,
+            service::tests::update_policy,
+            service::tests::authorize_data_use,
+            policy::tests::native_engine_equivalence,
+            policy::tests::model_errors,
+            policy::tests::label_rules,
+            versions::tests::policy_updates,
+            versions::tests::invalid_policy_bundles,
         )
     }
 }
@@ -0,0 +1,716 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Native evaluator of the access control model (model.conf), implementing
+// the semantics of the Python engine (python/acs_engine.py) for the subset of
+// Python the matchers use:
+//
+// - `term(request.attr, ..)` is true if the term has the fact;
+// - `term(.., _, ..)` queries the values of the facts at the placeholders
+//   which match the other arguments;
+// - `query <= query` is true if the values of the first query are a subset
+//   of those of the second, e.g., if no fact matches the first query;
+// - `not`, `and`, `or`, parentheses, `true` and `false`.
+//
+// The native engine also reads the optional `[label_rules]` section, lines of
+// `<label> requires <tag>, ..`: functions can only use data with the label if
+// they have all the tags, e.g., `region=eu requires eu-approved`.
+
+use anyhow::{anyhow, bail, ensure, Result};
+use std::collections::{HashMap, HashSet};
+use std::prelude::v1::*;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Dot,
+    Comma,
+    LeftParen,
+    RightParen,
+    SubsetEq,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' | '\r' => {
+                chars.next();
+            }
+            '.' => {
+                chars.next();
+                tokens.push(Token::Dot);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            '<' => {
+                chars.next();
+                ensure!(chars.next() == Some('='), "unsupported operator in matcher");
+                tokens.push(Token::SubsetEq);
+            }
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '_' || c.is_ascii_alphanumeric() {
+                        ident.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c => bail!("unexpected character in matcher: {}", c),
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Arg {
+    // attribute of the request
+    Attribute(String),
+    // `_`
+    Placeholder,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(bool),
+    Term(String, Vec<Arg>),
+    Subset(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+// Value of an expression: a boolean, or the values of the facts matched by
+// a query, one tuple per fact.
+enum Value {
+    Bool(bool),
+    Query(HashSet<Vec<String>>),
+}
+
+// Recursive descent parser of a matcher, following the precedence of Python:
+// `or` < `and` < `not` < `<=`.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    request: &'a str,
+    attributes: &'a [String],
+    terms: &'a HashMap<String, usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            token => bail!("expecting {:?}, found {:?}", expected, token),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) => ident == keyword,
+            _ => false,
+        }
+    }
+
+    fn parse(mut self) -> Result<Expr> {
+        let expr = self.or()?;
+        ensure!(self.peek().is_none(), "trailing tokens in matcher");
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.is_keyword("or") {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.is_keyword("and") {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.is_keyword("not") {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let expr = self.primary()?;
+        if self.peek() == Some(&Token::SubsetEq) {
+            self.next();
+            let superset = self.primary()?;
+            return Ok(Expr::Subset(Box::new(expr), Box::new(superset)));
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LeftParen) => {
+                let expr = self.or()?;
+                self.expect(Token::RightParen)?;
+                Ok(expr)
+            }
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" | "True" => Ok(Expr::Literal(true)),
+                "false" | "False" => Ok(Expr::Literal(false)),
+                _ => self.term(ident),
+            },
+            token => bail!("unexpected token in matcher: {:?}", token),
+        }
+    }
+
+    fn term(&mut self, name: String) -> Result<Expr> {
+        let arity = *self
+            .terms
+            .get(&name)
+            .ok_or_else(|| anyhow!("unknown term: {}", name))?;
+        self.expect(Token::LeftParen)?;
+        let mut args = Vec::new();
+        loop {
+            args.push(self.arg()?);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RightParen) => break,
+                token => bail!("unexpected token in arguments of {}: {:?}", name, token),
+            }
+        }
+        ensure!(args.len() == arity, "wrong number of arguments of {}", name);
+        Ok(Expr::Term(name, args))
+    }
+
+    fn arg(&mut self) -> Result<Arg> {
+        match self.next() {
+            Some(Token::Ident(ref ident)) if ident == "_" => Ok(Arg::Placeholder),
+            Some(Token::Ident(ref ident)) if ident == self.request => {
+                self.expect(Token::Dot)?;
+                match self.next() {
+                    Some(Token::Ident(attribute)) if self.attributes.contains(&attribute) => {
+                        Ok(Arg::Attribute(attribute))
+                    }
+                    token => bail!("unknown attribute of {}: {:?}", self.request, token),
+                }
+            }
+            token => bail!("unexpected argument: {:?}", token),
+        }
+    }
+}
+
+/// The requests, terms and matchers of the model, with the facts of the
+/// terms.
+pub(crate) struct PolicyModel {
+    // request -> names of its attributes
+    requests: HashMap<String, Vec<String>>,
+    // term -> arity
+    terms: HashMap<String, usize>,
+    matchers: HashMap<String, Expr>,
+    facts: HashMap<String, HashSet<Vec<String>>>,
+    // label -> tags required to use data with the label
+    label_rules: HashMap<String, Vec<String>>,
+}
+
+// Names and values of the definitions of a section, e.g., `usr, data` of
+// `user_access_data = usr, data`.
+fn definitions(lines: &[&str]) -> Result<Vec<(String, String)>> {
+    let mut definitions = Vec::new();
+    for line in lines {
+        let (name, value) = match line.find('=') {
+            Some(pos) => (line[..pos].trim(), line[pos + 1..].trim()),
+            None => bail!("invalid definition: {}", line),
+        };
+        ensure!(
+            !name.is_empty() && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric()),
+            "invalid name: {}",
+            name
+        );
+        ensure!(
+            !definitions.iter().any(|(n, _)| n == name),
+            "multiple definitions of {}",
+            name
+        );
+        definitions.push((name.to_string(), value.to_string()));
+    }
+    Ok(definitions)
+}
+
+fn label_rules(lines: &[&str]) -> Result<HashMap<String, Vec<String>>> {
+    let valid = |s: &str| !s.is_empty() && !s.contains(char::is_whitespace);
+    let mut rules = HashMap::new();
+    for line in lines {
+        let (label, tags) = match line.find(" requires ") {
+            Some(pos) => (line[..pos].trim(), &line[pos + " requires ".len()..]),
+            None => bail!("invalid label rule: {}", line),
+        };
+        let tags: Vec<String> = tags.split(',').map(|s| s.trim().to_string()).collect();
+        ensure!(
+            valid(label) && tags.iter().all(|tag| valid(tag)),
+            "invalid label rule: {}",
+            line
+        );
+        ensure!(
+            rules.insert(label.to_string(), tags).is_none(),
+            "multiple rules of label {}",
+            label
+        );
+    }
+    Ok(rules)
+}
+
+impl PolicyModel {
+    pub(crate) fn new(model_text: &str) -> Result<Self> {
+        // Escaped line breaks continue the line; comments start with `#`.
+        let text = model_text.replace("\\\n", "");
+        let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut section = None;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                ensure!(!sections.contains_key(line), "multiple sections {}", line);
+                sections.insert(line, Vec::new());
+                section = Some(line);
+                continue;
+            }
+            match section {
+                Some(section) => sections.get_mut(section).unwrap().push(line),
+                None => bail!("definition out of sections: {}", line),
+            }
+        }
+        let mut section = |name| {
+            sections
+                .remove(name)
+                .ok_or_else(|| anyhow!("missing section {}", name))
+        };
+        let split = |value: &str| -> Vec<String> {
+            value.split(',').map(|s| s.trim().to_string()).collect()
+        };
+
+        let requests: HashMap<String, Vec<String>> = definitions(&section("[requests]")?)?
+            .into_iter()
+            .map(|(name, value)| (name, split(&value)))
+            .collect();
+        let terms: HashMap<String, usize> = definitions(&section("[terms]")?)?
+            .into_iter()
+            .map(|(name, value)| (name, split(&value).len()))
+            .collect();
+        if let Some(name) = requests.keys().find(|name| terms.contains_key(*name)) {
+            bail!("{} defined as both request and term", name);
+        }
+
+        let mut matchers = HashMap::new();
+        for (name, value) in definitions(&section("[matchers]")?)? {
+            let attributes = requests
+                .get(&name)
+                .ok_or_else(|| anyhow!("matcher of unknown request: {}", name))?;
+            let parser = Parser {
+                tokens: tokenize(&value)?,
+                pos: 0,
+                request: &name,
+                attributes,
+                terms: &terms,
+            };
+            let expr = parser
+                .parse()
+                .map_err(|e| anyhow!("invalid matcher of {}: {}", name, e))?;
+            matchers.insert(name, expr);
+        }
+        if let Some(name) = requests.keys().find(|name| !matchers.contains_key(*name)) {
+            bail!("missing matcher of {}", name);
+        }
+
+        let label_rules = match sections.remove("[label_rules]") {
+            Some(lines) => label_rules(&lines)?,
+            None => HashMap::new(),
+        };
+
+        let facts = terms
+            .keys()
+            .map(|t| (t.to_string(), HashSet::new()))
+            .collect();
+        Ok(Self {
+            requests,
+            terms,
+            matchers,
+            facts,
+            label_rules,
+        })
+    }
+
+    pub(crate) fn add_fact(&mut self, term: &str, values: Vec<String>) -> Result<()> {
+        let arity = self
+            .terms
+            .get(term)
+            .ok_or_else(|| anyhow!("unknown term: {}", term))?;
+        ensure!(values.len() == *arity, "wrong number of values of {}", term);
+        self.facts.get_mut(term).unwrap().insert(values);
+        Ok(())
+    }
+
+    /// Adds the facts of another model, e.g., the one it replaces. The terms
+    /// of the facts must be defined with the same arity.
+    pub(crate) fn add_facts_of(&mut self, other: &PolicyModel) -> Result<()> {
+        for (term, facts) in other.facts.iter().filter(|(_, f)| !f.is_empty()) {
+            ensure!(
+                self.terms.get(term) == other.terms.get(term),
+                "term {} with facts is not kept",
+                term
+            );
+            self.facts
+                .get_mut(term)
+                .unwrap()
+                .extend(facts.iter().cloned());
+        }
+        Ok(())
+    }
+
+    /// The labels of the data whose rules the tags of a function do not
+    /// satisfy.
+    pub(crate) fn denied_labels(&self, tags: &[String], labels: &[String]) -> Vec<String> {
+        labels
+            .iter()
+            .filter(|label| match self.label_rules.get(*label) {
+                Some(required) => !required.iter().all(|tag| tags.contains(tag)),
+                None => false,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Evaluates the matcher of the request with its attributes.
+    pub(crate) fn enforce(
+        &self,
+        request: &str,
+        attributes: &HashMap<String, String>,
+    ) -> Result<bool> {
+        let names = self
+            .requests
+            .get(request)
+            .ok_or_else(|| anyhow!("unknown request: {}", request))?;
+        ensure!(
+            names.iter().all(|name| attributes.contains_key(name)),
+            "missing attributes of {}",
+            request
+        );
+        match self.evaluate(&self.matchers[request], attributes)? {
+            Value::Bool(accept) => Ok(accept),
+            Value::Query(_) => bail!("matcher of {} is not a condition", request),
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr, attributes: &HashMap<String, String>) -> Result<Value> {
+        let condition = |expr| match self.evaluate(expr, attributes)? {
+            Value::Bool(value) => Ok(value),
+            Value::Query(_) => bail!("query used as a condition"),
+        };
+        let query = |expr| match self.evaluate(expr, attributes)? {
+            Value::Query(values) => Ok(values),
+            Value::Bool(_) => bail!("condition used as a query"),
+        };
+        let value = match expr {
+            Expr::Literal(value) => Value::Bool(*value),
+            Expr::Term(term, args) => self.query_term(term, args, attributes),
+            Expr::Subset(subset, superset) => {
+                Value::Bool(query(subset)?.is_subset(&query(superset)?))
+            }
+            Expr::Not(expr) => Value::Bool(!condition(expr)?),
+            // Short-circuited as in Python.
+            Expr::And(left, right) => Value::Bool(condition(left)? && condition(right)?),
+            Expr::Or(left, right) => Value::Bool(condition(left)? || condition(right)?),
+        };
+        Ok(value)
+    }
+
+    fn query_term(&self, term: &str, args: &[Arg], attributes: &HashMap<String, String>) -> Value {
+        let facts = &self.facts[term];
+        let matches = |fact: &&Vec<String>| {
+            fact.iter().zip(args).all(|(value, arg)| match arg {
+                Arg::Attribute(attribute) => *value == attributes[attribute],
+                Arg::Placeholder => true,
+            })
+        };
+        if args.iter().all(|arg| matches!(arg, Arg::Attribute(_))) {
+            return Value::Bool(facts.iter().any(|fact| matches(&fact)));
+        }
+        let values = facts
+            .iter()
+            .filter(matches)
+            .map(|fact| {
+                fact.iter()
+                    .zip(args)
+                    .filter(|(_, arg)| matches!(arg, Arg::Placeholder))
+                    .map(|(value, _)| value.to_string())
+                    .collect()
+            })
+            .collect();
+        Value::Query(values)
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::acs::{
+        announce_fact, mock_terms, AccessControlModule, AccessControlTerms, EnforceRequest,
+        MODEL_TEXT,
+    };
+    use std::vec;
+    use teaclave_types::platform::rand::random_u64;
+
+    // xorshift64*, seeded randomly and logged to reproduce failures.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_bool(&mut self) -> bool {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 63 == 1
+        }
+    }
+
+    struct Ids {
+        users: Vec<String>,
+        data: Vec<String>,
+        functions: Vec<String>,
+        tasks: Vec<String>,
+    }
+
+    // Checks the decisions of the native model and the (global) model of the
+    // Python engine for all requests on the ids.
+    fn check_equivalence(python: &AccessControlModule, model: &PolicyModel, ids: &Ids) {
+        let mut requests = Vec::new();
+        for usr in &ids.users {
+            for data in &ids.data {
+                requests.push(EnforceRequest::UserAccessData(usr.clone(), data.clone()));
+            }
+            for function in &ids.functions {
+                requests.push(EnforceRequest::UserAccessFunction(
+                    usr.clone(),
+                    function.clone(),
+                ));
+                requests.push(EnforceRequest::UserOwnFunction(
+                    usr.clone(),
+                    function.clone(),
+                ));
+            }
+            for task in &ids.tasks {
+                requests.push(EnforceRequest::UserAccessTask(usr.clone(), task.clone()));
+            }
+        }
+        for task in &ids.tasks {
+            for function in &ids.functions {
+                requests.push(EnforceRequest::TaskAccessFunction(
+                    task.clone(),
+                    function.clone(),
+                ));
+            }
+            for data in &ids.data {
+                requests.push(EnforceRequest::TaskAccessData(task.clone(), data.clone()));
+            }
+        }
+        for request in requests {
+            let rule = request.rule();
+            let attributes = request.attributes();
+            let native = model.enforce(rule, &attributes).unwrap();
+            let expected = python.enforce_request(request).unwrap();
+            assert_eq!(native, expected, "{} {:?}", rule, attributes);
+        }
+    }
+
+    pub fn native_engine_equivalence() {
+        let python = AccessControlModule::python();
+        let mut model = PolicyModel::new(MODEL_TEXT).unwrap();
+        for term in mock_terms() {
+            model.add_fact(term.term(), term.values()).unwrap();
+        }
+
+        // The mock facts, announced to the Python engine by `init_acs`
+        let mut ids = Ids {
+            users: vec!["mock_user_d".to_string()],
+            data: Vec::new(),
+            functions: Vec::new(),
+            tasks: Vec::new(),
+        };
+        for term in mock_terms() {
+            let (ids, id) = match term {
+                AccessControlTerms::DataOwner(data, usr) => {
+                    ids.users.push(usr);
+                    (&mut ids.data, data)
+                }
+                AccessControlTerms::FunctionOwner(function, usr) => {
+                    ids.users.push(usr);
+                    (&mut ids.functions, function)
+                }
+                AccessControlTerms::IsPublicFunction(function) => (&mut ids.functions, function),
+                AccessControlTerms::TaskParticipant(task, usr) => {
+                    ids.users.push(usr);
+                    (&mut ids.tasks, task)
+                }
+            };
+            ids.push(id);
+        }
+        check_equivalence(&python, &model, &ids);
+
+        // Random facts on disjoint ids in each round, including resources
+        // without owners and tasks without participants.
+        let seed = random_u64() | 1;
+        info!("native_engine_equivalence seed: {}", seed);
+        let mut rng = Rng(seed);
+        for round in 0..50 {
+            let ids_of = |kind: &str| -> Vec<String> {
+                (0..3)
+                    .map(|i| format!("round{}_{}{}", round, kind, i))
+                    .collect()
+            };
+            let ids = Ids {
+                users: ids_of("user"),
+                data: ids_of("data"),
+                functions: ids_of("function"),
+                tasks: ids_of("task"),
+            };
+            let mut terms = Vec::new();
+            for usr in &ids.users {
+                for data in &ids.data {
+                    terms.push(AccessControlTerms::DataOwner(data.clone(), usr.clone()));
+                }
+                for function in &ids.functions {
+                    terms.push(AccessControlTerms::FunctionOwner(
+                        function.clone(),
+                        usr.clone(),
+                    ));
+                }
+                for task in &ids.tasks {
+                    terms.push(AccessControlTerms::TaskParticipant(
+                        task.clone(),
+                        usr.clone(),
+                    ));
+                }
+            }
+            for function in &ids.functions {
+                terms.push(AccessControlTerms::IsPublicFunction(function.clone()));
+            }
+            for term in terms {
+                if rng.next_bool() {
+                    model.add_fact(term.term(), term.values()).unwrap();
+                    announce_fact(term).unwrap();
+                }
+            }
+            check_equivalence(&python, &model, &ids);
+        }
+    }
+
+    pub fn model_errors() {
+        let model = "[requests]\nr = a, b\n[terms]\nt = x, y\n[matchers]\n";
+        assert!(PolicyModel::new(&format!("{}r = t(r.a, r.b)", model)).is_ok());
+        // unknown attribute
+        assert!(PolicyModel::new(&format!("{}r = t(r.a, r.c)", model)).is_err());
+        // wrong number of arguments
+        assert!(PolicyModel::new(&format!("{}r = t(r.a)", model)).is_err());
+        // unknown term
+        assert!(PolicyModel::new(&format!("{}r = u(r.a, r.b)", model)).is_err());
+        // unsupported operator
+        assert!(PolicyModel::new(&format!("{}r = t(r.a, _) < t(r.b, _)", model)).is_err());
+        // missing matcher
+        assert!(PolicyModel::new(model).is_err());
+
+        let mut model = PolicyModel::new(MODEL_TEXT).unwrap();
+        assert!(model
+            .add_fact("data_owner", vec!["data".to_string()])
+            .is_err());
+        assert!(model.add_fact("unknown", vec!["data".to_string()]).is_err());
+        let request = EnforceRequest::UserAccessData("usr".to_string(), "data".to_string());
+        assert!(model.enforce("unknown", &request.attributes()).is_err());
+        assert!(model
+            .enforce("user_access_task", &request.attributes())
+            .is_err());
+        assert_eq!(
+            model.enforce(request.rule(), &request.attributes()).ok(),
+            Some(false)
+        );
+    }
+    pub fn label_rules() {
+        let model_text = format!(
+            "{}\n[label_rules]\nregion=eu requires eu-approved\npii requires pii-approved, audited\n",
+            MODEL_TEXT
+        );
+        let model = PolicyModel::new(&model_text).unwrap();
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let labels = tags(&["region=eu", "pii", "public"]);
+        assert_eq!(
+            model.denied_labels(&tags(&[]), &labels),
+            tags(&["region=eu", "pii"])
+        );
+        assert_eq!(
+            model.denied_labels(&tags(&["eu-approved", "pii-approved"]), &labels),
+            tags(&["pii"])
+        );
+        assert!(model
+            .denied_labels(&tags(&["eu-approved", "pii-approved", "audited"]), &labels)
+            .is_empty());
+
+        // Models without the section have no rules.
+        let model = PolicyModel::new(MODEL_TEXT).unwrap();
+        assert!(model.denied_labels(&tags(&[]), &labels).is_empty());
+
+        for rules in &[
+            "region=eu eu-approved",
+            "region=eu requires ",
+            "region eu requires eu-approved",
+            "pii requires a\npii requires b",
+        ] {
+            let model_text = format!("{}\n[label_rules]\n{}\n", MODEL_TEXT, rules);
+            assert!(PolicyModel::new(&model_text).is_err());
+        }
+    }
+}
@@ -17,15 +17,20 @@
 
 use crate::acs::{AccessControlModule, EnforceRequest};
 use crate::error::TeaclavAccessControlError;
+use std::collections::HashMap;
 use std::prelude::v1::*;
 use teaclave_proto::teaclave_access_control_service::{
-    AuthorizeDataRequest, AuthorizeDataResponse, AuthorizeFunctionRequest,
-    AuthorizeFunctionResponse, AuthorizeStagedTaskRequest, AuthorizeStagedTaskResponse,
-    AuthorizeTaskRequest, AuthorizeTaskResponse, TeaclaveAccessControl,
+    AccessControlDecision, AuthorizeDataRequest, AuthorizeDataResponse, AuthorizeDataUseRequest,
+    AuthorizeDataUseResponse, AuthorizeFunctionRequest, AuthorizeFunctionResponse,
+    AuthorizeStagedTaskRequest, AuthorizeStagedTaskResponse, AuthorizeTaskRequest,
+    AuthorizeTaskResponse, GetAccessControlPolicyRequest, GetAccessControlPolicyResponse,
+    HealthRequest, HealthResponse, RollbackAccessControlPolicyRequest,
+    RollbackAccessControlPolicyResponse, TeaclaveAccessControl, UpdateAccessControlPolicyRequest,
+    UpdateAccessControlPolicyResponse,
 };
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::{bail, teaclave_service};
-use teaclave_types::TeaclaveServiceResponseResult;
+use teaclave_service_enclave_utils::{ensure, health, teaclave_service};
+use teaclave_types::{Permission, TeaclaveServiceResponseResult};
 
 #[teaclave_service(teaclave_access_control_service, TeaclaveAccessControl)]
 #[derive(Clone)]
@@ -34,117 +39,232 @@ pub(crate) struct TeaclaveAccessControlService {
 }
 
 impl TeaclaveAccessControlService {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(access_control_module: AccessControlModule) -> Self {
         TeaclaveAccessControlService {
-            access_control_module: AccessControlModule::new(),
+            access_control_module,
+        }
+    }
+
+    // Enforces the request and records the decision for explanations.
+    fn enforce(
+        &self,
+        request: EnforceRequest,
+        decisions: &mut Vec<AccessControlDecision>,
+    ) -> TeaclaveServiceResponseResult<bool> {
+        let rule = request.rule();
+        let attributes = request.attributes();
+        let accept = self
+            .access_control_module
+            .enforce_request(request)
+            .map_err(|_| TeaclavAccessControlError::AccessControlError)?;
+        decisions.push(AccessControlDecision::new(rule, accept, attributes));
+        Ok(accept)
+    }
+
+    // Explanations reveal the facts a decision is based on, so they are only
+    // given to admins, i.e., users with the manage_users permission, and to
+    // the owner of the resource. `owner_request` checks the ownership of the
+    // requesting user.
+    fn may_explain(
+        &self,
+        metadata: &HashMap<String, String>,
+        owner_request: impl FnOnce(String) -> EnforceRequest,
+    ) -> bool {
+        if is_admin(metadata) {
+            return true;
+        }
+        match metadata.get("id") {
+            Some(id) => self
+                .access_control_module
+                .enforce_request(owner_request(id.to_string()))
+                .unwrap_or(false),
+            None => false,
         }
     }
 }
 
+// Users with the manage_users permission, forwarded by the management service
+fn is_admin(metadata: &HashMap<String, String>) -> bool {
+    let permission = Permission::ManageUsers.to_string();
+    metadata.get("permissions").map_or(false, |permissions| {
+        permissions.split(',').any(|p| p == permission)
+    })
+}
+
 impl TeaclaveAccessControl for TeaclaveAccessControlService {
     fn authorize_data(
         &self,
         request: Request<AuthorizeDataRequest>,
     ) -> TeaclaveServiceResponseResult<AuthorizeDataResponse> {
+        let data_id = &request.message.object_data_id;
+        let explain = request.message.explain
+            && self.may_explain(&request.metadata, |id| {
+                EnforceRequest::UserAccessData(id, data_id.to_string())
+            });
         let request = request.message;
         let request =
             EnforceRequest::UserAccessData(request.subject_user_id, request.object_data_id);
-        match self.access_control_module.enforce_request(request) {
-            Ok(accept) => {
-                let response = AuthorizeDataResponse::new(accept);
-                Ok(response)
-            }
-            Err(_) => Err(TeaclavAccessControlError::AccessControlError.into()),
+        let mut decisions = Vec::new();
+        let accept = self.enforce(request, &mut decisions)?;
+        let response = AuthorizeDataResponse::new(accept);
+        if explain {
+            return Ok(response.explanation(decisions));
         }
+        Ok(response)
     }
 
     fn authorize_function(
         &self,
         request: Request<AuthorizeFunctionRequest>,
     ) -> TeaclaveServiceResponseResult<AuthorizeFunctionResponse> {
+        let function_id = &request.message.object_function_id;
+        let explain = request.message.explain
+            && self.may_explain(&request.metadata, |id| {
+                EnforceRequest::UserOwnFunction(id, function_id.to_string())
+            });
         let request = request.message;
         let request =
             EnforceRequest::UserAccessFunction(request.subject_user_id, request.object_function_id);
-        match self.access_control_module.enforce_request(request) {
-            Ok(accept) => {
-                let response = AuthorizeFunctionResponse::new(accept);
-                Ok(response)
-            }
-            Err(_) => Err(TeaclavAccessControlError::AccessControlError.into()),
+        let mut decisions = Vec::new();
+        let accept = self.enforce(request, &mut decisions)?;
+        let response = AuthorizeFunctionResponse::new(accept);
+        if explain {
+            return Ok(response.explanation(decisions));
         }
+        Ok(response)
     }
 
     fn authorize_task(
         &self,
         request: Request<AuthorizeTaskRequest>,
     ) -> TeaclaveServiceResponseResult<AuthorizeTaskResponse> {
+        let task_id = &request.message.object_task_id;
+        let explain = request.message.explain
+            && self.may_explain(&request.metadata, |id| {
+                EnforceRequest::UserAccessTask(id, task_id.to_string())
+            });
         let request = request.message;
         let request =
             EnforceRequest::UserAccessTask(request.subject_user_id, request.object_task_id);
-        match self.access_control_module.enforce_request(request) {
-            Ok(accept) => {
-                let response = AuthorizeTaskResponse::new(accept);
-                Ok(response)
-            }
-            Err(_) => Err(TeaclavAccessControlError::AccessControlError.into()),
+        let mut decisions = Vec::new();
+        let accept = self.enforce(request, &mut decisions)?;
+        let response = AuthorizeTaskResponse::new(accept);
+        if explain {
+            return Ok(response.explanation(decisions));
         }
+        Ok(response)
     }
 
     fn authorize_staged_task(
         &self,
         request: Request<AuthorizeStagedTaskRequest>,
     ) -> TeaclaveServiceResponseResult<AuthorizeStagedTaskResponse> {
+        let task_id = &request.message.subject_task_id;
+        let explain = request.message.explain
+            && self.may_explain(&request.metadata, |id| {
+                EnforceRequest::UserAccessTask(id, task_id.to_string())
+            });
         let request = request.message;
-        let enforce_access_function_request = EnforceRequest::TaskAccessFunction(
+        let mut enforce_requests = vec![EnforceRequest::TaskAccessFunction(
             request.subject_task_id.clone(),
             request.object_function_id,
-        );
-        match self
-            .access_control_module
-            .enforce_request(enforce_access_function_request)
+        )];
+        for object_data_id in request
+            .object_input_data_id_list
+            .iter()
+            .chain(request.object_output_data_id_list.iter())
         {
-            Ok(accept) => {
-                if !accept {
-                    return Ok(AuthorizeStagedTaskResponse::new(false));
-                }
-            }
-            Err(_) => bail!(TeaclavAccessControlError::AccessControlError),
-        }
-        for object_data_id in request.object_input_data_id_list.iter() {
-            let enforce_access_data_request = EnforceRequest::TaskAccessData(
+            enforce_requests.push(EnforceRequest::TaskAccessData(
                 request.subject_task_id.clone(),
                 object_data_id.to_string(),
-            );
-            match self
-                .access_control_module
-                .enforce_request(enforce_access_data_request)
-            {
-                Ok(accept) => {
-                    if !accept {
-                        return Ok(AuthorizeStagedTaskResponse::new(false));
-                    }
-                }
-                Err(_) => bail!(TeaclavAccessControlError::AccessControlError),
-            }
+            ));
         }
-        for object_data_id in request.object_output_data_id_list.iter() {
-            let enforce_access_data_request = EnforceRequest::TaskAccessData(
-                request.subject_task_id.clone(),
-                object_data_id.to_string(),
-            );
-            match self
-                .access_control_module
-                .enforce_request(enforce_access_data_request)
-            {
-                Ok(accept) => {
-                    if !accept {
-                        return Ok(AuthorizeStagedTaskResponse::new(false));
-                    }
-                }
-                Err(_) => bail!(TeaclavAccessControlError::AccessControlError),
+
+        // Stops at the first denied request, which is the last decision of
+        // the explanation.
+        let mut decisions = Vec::new();
+        let mut accept = true;
+        for enforce_request in enforce_requests {
+            if !self.enforce(enforce_request, &mut decisions)? {
+                accept = false;
+                break;
             }
         }
-        Ok(AuthorizeStagedTaskResponse { accept: true })
+        let response = AuthorizeStagedTaskResponse::new(accept);
+        if explain {
+            return Ok(response.explanation(decisions));
+        }
+        Ok(response)
+    }
+
+    // Labels and tags are not secret to the callers, who have read the data
+    // and the function.
+    fn authorize_data_use(
+        &self,
+        request: Request<AuthorizeDataUseRequest>,
+    ) -> TeaclaveServiceResponseResult<AuthorizeDataUseResponse> {
+        let request = request.message;
+        let denied_labels = self
+            .access_control_module
+            .denied_labels(&request.subject_function_tags, &request.object_data_labels)
+            .map_err(|_| TeaclavAccessControlError::AccessControlError)?;
+        Ok(AuthorizeDataUseResponse::new(denied_labels))
+    }
+
+    // Policies are updated by admins, with bundles signed by the keys pinned
+    // in the build config.
+    fn update_policy(
+        &self,
+        request: Request<UpdateAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<UpdateAccessControlPolicyResponse> {
+        ensure!(
+            is_admin(&request.metadata),
+            TeaclavAccessControlError::PermissionDenied
+        );
+        let version = self
+            .access_control_module
+            .update_policy(&request.message.bundle, &request.message.signature)
+            .map_err(|e| TeaclavAccessControlError::PolicyError(e.to_string()))?;
+        Ok(UpdateAccessControlPolicyResponse::new(version))
+    }
+
+    fn rollback_policy(
+        &self,
+        request: Request<RollbackAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<RollbackAccessControlPolicyResponse> {
+        ensure!(
+            is_admin(&request.metadata),
+            TeaclavAccessControlError::PermissionDenied
+        );
+        self.access_control_module
+            .rollback_policy(request.message.version)
+            .map_err(|e| TeaclavAccessControlError::PolicyError(e.to_string()))?;
+        Ok(RollbackAccessControlPolicyResponse)
+    }
+
+    fn get_policy(
+        &self,
+        request: Request<GetAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<GetAccessControlPolicyResponse> {
+        ensure!(
+            is_admin(&request.metadata),
+            TeaclavAccessControlError::PermissionDenied
+        );
+        let (version, retained_versions) = self
+            .access_control_module
+            .policy_versions()
+            .map_err(|e| TeaclavAccessControlError::PolicyError(e.to_string()))?;
+        Ok(GetAccessControlPolicyResponse::new(
+            version,
+            retained_versions,
+        ))
+    }
+
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        Ok(HealthResponse::new(vec![health::attestation_check()]))
     }
 }
 
@@ -153,8 +273,14 @@ pub mod tests {
     use super::*;
     use teaclave_rpc::IntoRequest;
 
+    // The tests run with the Python engine, whose model is set up by
+    // `init_acs`. The native engine is checked against it in `policy`.
+    fn get_mock_service() -> TeaclaveAccessControlService {
+        TeaclaveAccessControlService::new(AccessControlModule::python())
+    }
+
     pub fn user_access_data() {
-        let service = TeaclaveAccessControlService::new();
+        let service = get_mock_service();
         let request = AuthorizeDataRequest::new("mock_user_a", "mock_data").into_request();
         let response = service.authorize_data(request);
         assert!(response.is_ok());
@@ -182,7 +308,7 @@ pub mod tests {
     }
 
     pub fn user_access_function() {
-        let service = TeaclaveAccessControlService::new();
+        let service = get_mock_service();
         let request =
             AuthorizeFunctionRequest::new("mock_public_function_owner", "mock_public_function")
                 .into_request();
@@ -213,7 +339,7 @@ pub mod tests {
     }
 
     pub fn user_access_task() {
-        let service = TeaclaveAccessControlService::new();
+        let service = get_mock_service();
         let request = AuthorizeTaskRequest::new("mock_participant_a", "mock_task").into_request();
         let response = service.authorize_task(request);
         assert!(response.is_ok());
@@ -231,7 +357,7 @@ pub mod tests {
     }
 
     pub fn task_access_function() {
-        let service = TeaclaveAccessControlService::new();
+        let service = get_mock_service();
         let mut request = get_correct_authorized_stage_task_req();
         request.object_function_id = "mock_staged_allowed_private_function".to_string();
         let response = service.authorize_staged_task(request.into_request());
@@ -265,10 +391,11 @@ pub mod tests {
                 "mock_staged_allowed_data2".to_string(),
                 "mock_staged_allowed_data3".to_string(),
             ],
+            explain: false,
         }
     }
     pub fn task_access_data() {
-        let service = TeaclaveAccessControlService::new();
+        let service = get_mock_service();
         let request = get_correct_authorized_stage_task_req().into_request();
         let response = service.authorize_staged_task(request);
         assert!(response.is_ok());
@@ -306,4 +433,133 @@ pub mod tests {
         assert!(response.is_ok());
         assert!(!response.unwrap().accept);
     }
+
+    pub fn explain_decisions() {
+        let service = get_mock_service();
+        let request = AuthorizeFunctionRequest::new("mock_user_a", "mock_private_function")
+            .explain()
+            .into_request();
+        let response = service.authorize_function(request).unwrap();
+        assert!(!response.accept);
+        assert!(response.explanation.is_empty());
+
+        let mut request = AuthorizeFunctionRequest::new("mock_user_a", "mock_private_function")
+            .explain()
+            .into_request();
+        request
+            .metadata
+            .insert("id".to_string(), "mock_private_function_owner".to_string());
+        let response = service.authorize_function(request).unwrap();
+        assert!(!response.accept);
+        assert_eq!(response.explanation.len(), 1);
+        assert_eq!(response.explanation[0].rule, "user_access_function");
+        assert_eq!(
+            response.explanation[0].attributes["function"],
+            "mock_private_function"
+        );
+
+        let mut request = get_correct_authorized_stage_task_req();
+        request
+            .object_output_data_id_list
+            .push("mock_staged_disallowed_data1".to_string());
+        let mut request = request.explain().into_request();
+        request.metadata.insert(
+            "permissions".to_string(),
+            Permission::ManageUsers.to_string(),
+        );
+        let response = service.authorize_staged_task(request).unwrap();
+        assert!(!response.accept);
+        // The function and six data are allowed before the denied data.
+        assert_eq!(response.explanation.len(), 8);
+        let denied = response.explanation.last().unwrap();
+        assert_eq!(denied.rule, "task_access_data");
+        assert!(!denied.accept);
+        assert_eq!(denied.attributes["data"], "mock_staged_disallowed_data1");
+    }
+
+    fn with_permissions<T>(mut request: Request<T>) -> Request<T> {
+        request.metadata.insert(
+            "permissions".to_string(),
+            Permission::ManageUsers.to_string(),
+        );
+        request
+    }
+
+    pub fn update_policy() {
+        use crate::acs::MODEL_TEXT;
+        use crate::versions::tests::{signed_bundle, signers};
+        use teaclave_config::AccessControlEngine;
+
+        let signers = signers();
+        let signers: Vec<&[u8]> = signers.iter().map(|key| key.as_slice()).collect();
+        let module = AccessControlModule::new(AccessControlEngine::Native, &signers).unwrap();
+        let service = TeaclaveAccessControlService::new(module);
+        let deny_data = MODEL_TEXT.replace(
+            "user_access_data = data_owner(user_access_data.data, user_access_data.usr)",
+            "user_access_data = false",
+        );
+        let (bundle, signature) = signed_bundle(1, &deny_data);
+        let accepts_mock_data = || {
+            let request = AuthorizeDataRequest::new("mock_user_a", "mock_data").into_request();
+            service.authorize_data(request).unwrap().accept
+        };
+
+        let request =
+            UpdateAccessControlPolicyRequest::new(bundle.clone(), signature.clone()).into_request();
+        assert!(service.update_policy(request).is_err());
+        assert!(accepts_mock_data());
+
+        let request = UpdateAccessControlPolicyRequest::new(bundle, signature).into_request();
+        let response = service.update_policy(with_permissions(request)).unwrap();
+        assert_eq!(response.version, 1);
+        assert!(!accepts_mock_data());
+
+        let request = GetAccessControlPolicyRequest::new().into_request();
+        let response = service.get_policy(with_permissions(request)).unwrap();
+        assert_eq!(response.version, 1);
+        assert_eq!(response.retained_versions, vec![0, 1]);
+
+        let request = RollbackAccessControlPolicyRequest::new(0).into_request();
+        assert!(service.rollback_policy(with_permissions(request)).is_ok());
+        assert!(accepts_mock_data());
+
+        // Policies of the Python engine cannot be updated.
+        let request = GetAccessControlPolicyRequest::new().into_request();
+        assert!(get_mock_service()
+            .get_policy(with_permissions(request))
+            .is_err());
+    }
+    pub fn authorize_data_use() {
+        use crate::acs::MODEL_TEXT;
+        use crate::versions::tests::{signed_bundle, signers};
+        use teaclave_config::AccessControlEngine;
+
+        let signers = signers();
+        let signers: Vec<&[u8]> = signers.iter().map(|key| key.as_slice()).collect();
+        let module = AccessControlModule::new(AccessControlEngine::Native, &signers).unwrap();
+        let service = TeaclaveAccessControlService::new(module);
+        let request = |tags: &[&str]| {
+            AuthorizeDataUseRequest::new(
+                tags.iter().map(|t| t.to_string()).collect(),
+                vec!["region=eu".to_string(), "pii".to_string()],
+            )
+            .into_request()
+        };
+        assert!(service.authorize_data_use(request(&[])).unwrap().accept);
+
+        let model = format!(
+            "{}\n[label_rules]\nregion=eu requires eu-approved\n",
+            MODEL_TEXT
+        );
+        let (bundle, signature) = signed_bundle(1, &model);
+        let update = UpdateAccessControlPolicyRequest::new(bundle, signature).into_request();
+        assert!(service.update_policy(with_permissions(update)).is_ok());
+        let response = service.authorize_data_use(request(&[])).unwrap();
+        assert!(!response.accept);
+        assert_eq!(response.denied_labels, vec!["region=eu".to_string()]);
+        let response = service
+            .authorize_data_use(request(&["eu-approved"]))
+            .unwrap();
+        assert!(response.accept);
+    }
 }
@@ -0,0 +1,247 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Versions of the model of the native engine, updated at runtime with
+// policy bundles signed by the keys pinned in the build config. The model
+// built into the enclave (model.conf) is version 0.
+
+use crate::acs::EnforceRequest;
+use crate::policy::PolicyModel;
+use anyhow::{anyhow, bail, ensure, Result};
+use ring::signature;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::prelude::v1::*;
+use std::sync::Arc;
+
+// Versions kept for rollback, including the current one.
+const MAX_RETAINED_VERSIONS: usize = 8;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyBundle {
+    version: u64,
+    // the model in the format of model.conf
+    model: String,
+}
+
+impl PolicyBundle {
+    // The signature is checked before the bundle is parsed.
+    fn verify(bundle: &[u8], bundle_signature: &[u8], signers: &[Vec<u8>]) -> Result<Self> {
+        ensure!(!signers.is_empty(), "no policy signers in the build config");
+        let verified = signers.iter().any(|key| {
+            signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, key)
+                .verify(bundle, bundle_signature)
+                .is_ok()
+        });
+        ensure!(verified, "invalid signature of the policy bundle");
+        Ok(serde_json::from_slice(bundle)?)
+    }
+}
+
+pub(crate) struct PolicyVersions {
+    signers: Vec<Vec<u8>>,
+    version: u64,
+    model: Arc<PolicyModel>,
+    // Updates must exceed the latest accepted version, so that bundles of
+    // rolled back versions cannot be replayed.
+    latest_version: u64,
+    // version -> model text
+    retained: BTreeMap<u64, String>,
+}
+
+impl PolicyVersions {
+    pub(crate) fn new(model_text: &str, model: PolicyModel, signers: Vec<Vec<u8>>) -> Self {
+        let mut retained = BTreeMap::new();
+        retained.insert(0, model_text.to_string());
+        Self {
+            signers,
+            version: 0,
+            model: Arc::new(model),
+            latest_version: 0,
+            retained,
+        }
+    }
+
+    pub(crate) fn model(&self) -> Arc<PolicyModel> {
+        self.model.clone()
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub(crate) fn retained_versions(&self) -> Vec<u64> {
+        self.retained.keys().cloned().collect()
+    }
+
+    /// Verifies the bundle and makes its model current.
+    pub(crate) fn update(&mut self, bundle: &[u8], bundle_signature: &[u8]) -> Result<u64> {
+        let bundle = PolicyBundle::verify(bundle, bundle_signature, &self.signers)?;
+        ensure!(
+            bundle.version > self.latest_version,
+            "version {} is not newer than {}",
+            bundle.version,
+            self.latest_version
+        );
+        self.activate(bundle.version, &bundle.model)?;
+        self.latest_version = bundle.version;
+        self.retained.insert(bundle.version, bundle.model);
+        while self.retained.len() > MAX_RETAINED_VERSIONS {
+            let oldest = *self.retained.keys().next().unwrap();
+            self.retained.remove(&oldest);
+        }
+        Ok(bundle.version)
+    }
+
+    /// Makes the model of a retained version current again.
+    pub(crate) fn rollback(&mut self, version: u64) -> Result<()> {
+        let model_text = match self.retained.get(&version) {
+            Some(text) => text.clone(),
+            None => bail!("version {} is not retained", version),
+        };
+        self.activate(version, &model_text)
+    }
+
+    // Builds the model with the facts of the current one, and checks that it
+    // serves every request of the service before swapping it in.
+    fn activate(&mut self, version: u64, model_text: &str) -> Result<()> {
+        let mut model = PolicyModel::new(model_text)?;
+        model.add_facts_of(&self.model)?;
+        for request in EnforceRequest::samples() {
+            model
+                .enforce(request.rule(), &request.attributes())
+                .map_err(|e| anyhow!("model does not serve {}: {}", request.rule(), e))?;
+        }
+        self.version = version;
+        self.model = Arc::new(model);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::acs::{mock_terms, MODEL_TEXT};
+    use std::vec;
+    use teaclave_types::platform;
+
+    pub(crate) fn signers() -> Vec<Vec<u8>> {
+        vec![platform::fs::read("fixtures/access_control/signer.public.der").unwrap()]
+    }
+
+    pub(crate) fn signed_bundle(version: u64, model: &str) -> (Vec<u8>, Vec<u8>) {
+        let bundle = serde_json::json!({ "version": version, "model": model })
+            .to_string()
+            .into_bytes();
+        let key = platform::fs::read("fixtures/access_control/signer.private.der").unwrap();
+        let key_pair = signature::RsaKeyPair::from_der(&key).unwrap();
+        let mut bundle_signature = vec![0; key_pair.public_modulus_len()];
+        key_pair
+            .sign(
+                &signature::RSA_PKCS1_SHA256,
+                &ring::rand::SystemRandom::new(),
+                &bundle,
+                &mut bundle_signature,
+            )
+            .unwrap();
+        (bundle, bundle_signature)
+    }
+
+    fn mock_versions(signers: Vec<Vec<u8>>) -> PolicyVersions {
+        let mut model = PolicyModel::new(MODEL_TEXT).unwrap();
+        for term in mock_terms() {
+            model.add_fact(term.term(), term.values()).unwrap();
+        }
+        PolicyVersions::new(MODEL_TEXT, model, signers)
+    }
+
+    fn accepts_mock_data(versions: &PolicyVersions) -> bool {
+        let request =
+            EnforceRequest::UserAccessData("mock_user_a".to_string(), "mock_data".to_string());
+        versions
+            .model()
+            .enforce(request.rule(), &request.attributes())
+            .unwrap()
+    }
+
+    pub fn policy_updates() {
+        let deny_data = MODEL_TEXT.replace(
+            "user_access_data = data_owner(user_access_data.data, user_access_data.usr)",
+            "user_access_data = false",
+        );
+        assert_ne!(deny_data, MODEL_TEXT);
+
+        let mut versions = mock_versions(signers());
+        assert!(accepts_mock_data(&versions));
+
+        // The facts of the current model are kept.
+        let (bundle, bundle_signature) = signed_bundle(1, MODEL_TEXT);
+        assert_eq!(versions.update(&bundle, &bundle_signature).ok(), Some(1));
+        assert!(accepts_mock_data(&versions));
+
+        let (bundle, bundle_signature) = signed_bundle(3, &deny_data);
+        assert_eq!(versions.update(&bundle, &bundle_signature).ok(), Some(3));
+        assert!(!accepts_mock_data(&versions));
+        assert_eq!(versions.version(), 3);
+        assert_eq!(versions.retained_versions(), vec![0, 1, 3]);
+
+        assert!(versions.rollback(1).is_ok());
+        assert_eq!(versions.version(), 1);
+        assert!(accepts_mock_data(&versions));
+        assert!(versions.rollback(2).is_err());
+
+        // Replayed and older bundles are rejected, also after a rollback.
+        assert!(versions.update(&bundle, &bundle_signature).is_err());
+        let (bundle, bundle_signature) = signed_bundle(2, &deny_data);
+        assert!(versions.update(&bundle, &bundle_signature).is_err());
+        assert_eq!(versions.version(), 1);
+
+        for version in 4..4 + MAX_RETAINED_VERSIONS as u64 {
+            let (bundle, bundle_signature) = signed_bundle(version, MODEL_TEXT);
+            assert!(versions.update(&bundle, &bundle_signature).is_ok());
+        }
+        assert_eq!(versions.retained_versions().len(), MAX_RETAINED_VERSIONS);
+        assert!(versions.rollback(0).is_err());
+    }
+
+    pub fn invalid_policy_bundles() {
+        let mut versions = mock_versions(signers());
+        let (bundle, mut bundle_signature) = signed_bundle(1, MODEL_TEXT);
+        bundle_signature[0] ^= 1;
+        assert!(versions.update(&bundle, &bundle_signature).is_err());
+
+        // Models must serve every request, and keep the terms with facts.
+        let missing_request = MODEL_TEXT.replace("task_access_data", "task_read_data");
+        let (bundle, bundle_signature) = signed_bundle(1, &missing_request);
+        assert!(versions.update(&bundle, &bundle_signature).is_err());
+        let missing_term = MODEL_TEXT
+            .replace("is_public_function = function", "")
+            .replace("is_public_function(user_access_function.function) or", "")
+            .replace("is_public_function(task_access_function.function) or", "");
+        let (bundle, bundle_signature) = signed_bundle(1, &missing_term);
+        assert!(versions.update(&bundle, &bundle_signature).is_err());
+        let (bundle, bundle_signature) = signed_bundle(1, "[requests]");
+        assert!(versions.update(&bundle, &bundle_signature).is_err());
+        assert_eq!(versions.version(), 0);
+        assert!(accepts_mock_data(&versions));
+
+        let mut versions = mock_versions(vec![]);
+        let (bundle, bundle_signature) = signed_bundle(1, MODEL_TEXT);
+        assert!(versions.update(&bundle, &bundle_signature).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Verifying evidence checks the signature of the attestation service on the
+// report and the certificate chain of its signing key. Reports of enclaves
+// verified again and again, e.g., the services of a deployment, are kept for
+// a while instead, by the SHA-256 digest of their evidence.
+
+use std::collections::{HashMap, VecDeque};
+use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use teaclave_attestation::report::AttestationReport;
+
+type Digest = [u8; 32];
+
+struct CachedReport {
+    report: Arc<AttestationReport>,
+    verified_at: SystemTime,
+}
+
+pub(crate) struct ReportCache {
+    capacity: usize,
+    ttl: Duration,
+    reports: HashMap<Digest, CachedReport>,
+    // Digests in the order of insertion, evicted first in first out
+    order: VecDeque<Digest>,
+}
+
+pub(crate) fn evidence_digest(evidence: &[u8]) -> Digest {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(ring::digest::digest(&ring::digest::SHA256, evidence).as_ref());
+    digest
+}
+
+impl ReportCache {
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            reports: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// The report verified from the evidence, and the time elapsed since
+    /// then, unless it has expired.
+    pub(crate) fn get(
+        &self,
+        digest: &Digest,
+        now: SystemTime,
+    ) -> Option<(Arc<AttestationReport>, Duration)> {
+        let cached = self.reports.get(digest)?;
+        let elapsed = now.duration_since(cached.verified_at).unwrap_or_default();
+        if elapsed > self.ttl {
+            return None;
+        }
+        Some((cached.report.clone(), elapsed))
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        digest: Digest,
+        report: Arc<AttestationReport>,
+        verified_at: SystemTime,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        let cached = CachedReport {
+            report,
+            verified_at,
+        };
+        if self.reports.insert(digest, cached).is_some() {
+            // Replaced an expired report, keeping its position
+            return;
+        }
+        self.order.push_back(digest);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.reports.remove(&oldest);
+            }
+        }
+    }
+
+    #[cfg(feature = "enclave_unit_test")]
+    pub(crate) fn len(&self) -> usize {
+        self.reports.len()
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::policy::tests::attestation_report;
+    use teaclave_types::platform;
+
+    pub fn test_report_cache() {
+        let now = platform::time::now();
+        let mut cache = ReportCache::new(2, Duration::from_secs(60));
+        let report = Arc::new(attestation_report());
+        let (a, b, c) = (
+            evidence_digest(b"a"),
+            evidence_digest(b"b"),
+            evidence_digest(b"c"),
+        );
+        assert!(cache.get(&a, now).is_none());
+
+        cache.insert(a, report.clone(), now);
+        let (_, elapsed) = cache.get(&a, now + Duration::from_secs(10)).unwrap();
+        assert_eq!(elapsed, Duration::from_secs(10));
+        // expired
+        assert!(cache.get(&a, now + Duration::from_secs(61)).is_none());
+
+        // The oldest report is evicted.
+        cache.insert(b, report.clone(), now);
+        cache.insert(c, report.clone(), now);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&a, now).is_none());
+        assert!(cache.get(&b, now).is_some());
+        assert!(cache.get(&c, now).is_some());
+
+        let mut cache = ReportCache::new(0, Duration::from_secs(60));
+        cache.insert(a, report, now);
+        assert!(cache.get(&a, now).is_none());
+    }
+}
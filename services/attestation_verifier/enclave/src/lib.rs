@@ -0,0 +1,138 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#![cfg_attr(feature = "mesalock_sgx", no_std)]
+#[cfg(feature = "mesalock_sgx")]
+#[macro_use]
+extern crate sgx_tstd as std;
+
+#[macro_use]
+extern crate log;
+use anyhow::{anyhow, Result};
+
+use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::Duration;
+use teaclave_attestation::{AttestationConfig, RemoteAttestation};
+use teaclave_binder::proto::{
+    ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
+    StartServiceInput, StartServiceOutput,
+};
+use teaclave_binder::{handle_ecall, register_ecall_handler};
+use teaclave_config::build::AS_ROOT_CA_CERT;
+use teaclave_config::RuntimeConfig;
+use teaclave_proto::teaclave_attestation_verifier_service::{
+    TeaclaveAttestationVerifierRequest, TeaclaveAttestationVerifierResponse,
+};
+use teaclave_rpc::config::{SgxTrustedTlsServerConfig, TlsPolicy};
+use teaclave_rpc::interceptor::LoggingInterceptor;
+use teaclave_rpc::server::SgxTrustedTlsServer;
+use teaclave_service_enclave_utils::ServiceEnclave;
+use teaclave_types::{TeeServiceError, TeeServiceResult};
+
+mod cache;
+mod error;
+mod policy;
+mod service;
+
+fn start_service(config: &RuntimeConfig) -> Result<()> {
+    let endpoint = config
+        .api_endpoints
+        .attestation_verifier
+        .as_ref()
+        .ok_or_else(|| anyhow!("attestation verifier endpoint not configured"))?;
+    let verifier_config = &config.attestation_verifier;
+    let policies = policy::VerificationPolicy::from_configs(&verifier_config.policies)?;
+    let cache = cache::ReportCache::new(
+        verifier_config.cache_size,
+        Duration::from_secs(verifier_config.cache_ttl_secs),
+    );
+
+    let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
+    let attested_tls_config = RemoteAttestation::new(attestation_config)
+        .generate_and_endorse()?
+        .attested_tls_config()
+        .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
+    // Clients are not attested, as for the other API endpoints.
+    let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
+        .tls_policy(TlsPolicy::from_teaclave_config(&config))?;
+
+    let mut server = SgxTrustedTlsServer::<
+        TeaclaveAttestationVerifierResponse,
+        TeaclaveAttestationVerifierRequest,
+    >::new(endpoint.listen_address, server_config)
+    .message_limits(&endpoint.message_limits)
+    .interceptor(Arc::new(LoggingInterceptor::new("attestation_verifier")));
+    let service =
+        service::TeaclaveAttestationVerifierService::new(policies, cache, AS_ROOT_CA_CERT);
+    match server.start(service) {
+        Ok(_) => (),
+        Err(e) => {
+            error!("Service exit, error: {}.", e);
+        }
+    }
+    Ok(())
+}
+
+#[handle_ecall]
+fn handle_start_service(input: &StartServiceInput) -> TeeServiceResult<StartServiceOutput> {
+    match start_service(&input.config) {
+        Ok(_) => Ok(StartServiceOutput),
+        Err(e) => {
+            log::error!("Failed to start the service: {}", e);
+            Err(TeeServiceError::ServiceError)
+        }
+    }
+}
+
+#[handle_ecall]
+fn handle_init_enclave(_: &InitEnclaveInput) -> TeeServiceResult<InitEnclaveOutput> {
+    ServiceEnclave::init(env!("CARGO_PKG_NAME"))?;
+    Ok(InitEnclaveOutput)
+}
+
+#[handle_ecall]
+fn handle_finalize_enclave(_: &FinalizeEnclaveInput) -> TeeServiceResult<FinalizeEnclaveOutput> {
+    ServiceEnclave::finalize()?;
+    Ok(FinalizeEnclaveOutput)
+}
+
+register_ecall_handler!(
+    type ECallCommand,
+    (ECallCommand::StartService, StartServiceInput, StartServiceOutput),
+    (ECallCommand::InitEnclave, InitEnclaveInput, InitEnclaveOutput),
+    (ECallCommand::FinalizeEnclave, FinalizeEnclaveInput, FinalizeEnclaveOutput),
+);
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            policy::tests::test_accepted_enclaves,
+            policy::tests::test_debug_enclave,
+            policy::tests::test_report_age,
+            policy::tests::test_quote_status,
+            policy::tests::test_invalid_policies,
+            cache::tests::test_report_cache,
+            service::tests::test_unknown_policy,
+            service::tests::test_invalid_evidence,
+        )
+    }
+}
@@ -0,0 +1,253 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{anyhow, ensure, Result};
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::time::Duration;
+use teaclave_attestation::report::{AttestationReport, SgxQuoteStatus};
+use teaclave_attestation::verifier::{QuoteStatusPolicy, QuoteVerdict};
+use teaclave_config::{AcceptedEnclaveConfig, VerificationPolicyConfig};
+use teaclave_types::{MrEnclave, MrSigner};
+
+// SGX_FLAGS_DEBUG of the enclave attributes
+const SGX_FLAGS_DEBUG: u8 = 0x02;
+
+struct AcceptedEnclave {
+    mr_signer: MrSigner,
+    // Any enclave of the signer if None
+    mr_enclave: Option<MrEnclave>,
+}
+
+impl AcceptedEnclave {
+    fn from_config(config: &AcceptedEnclaveConfig) -> Result<Self> {
+        let mr_enclave = match &config.mr_enclave {
+            Some(mr_enclave) => Some(mr_enclave.parse()?),
+            None => None,
+        };
+        Ok(Self {
+            mr_signer: config.mr_signer.parse()?,
+            mr_enclave,
+        })
+    }
+}
+
+/// A verification policy of the runtime config.
+pub(crate) struct VerificationPolicy {
+    enclaves: Vec<AcceptedEnclave>,
+    max_report_age: Option<Duration>,
+    quote_status_policy: QuoteStatusPolicy,
+    allow_debug: bool,
+}
+
+impl VerificationPolicy {
+    pub(crate) fn from_config(config: &VerificationPolicyConfig) -> Result<Self> {
+        let enclaves = config
+            .enclaves
+            .iter()
+            .map(AcceptedEnclave::from_config)
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            enclaves,
+            max_report_age: config.max_report_age_secs.map(Duration::from_secs),
+            quote_status_policy: QuoteStatusPolicy::new(config.quote_status.clone()),
+            allow_debug: config.allow_debug,
+        })
+    }
+
+    /// Policies by name, rejecting duplicate names.
+    pub(crate) fn from_configs(
+        configs: &[VerificationPolicyConfig],
+    ) -> Result<HashMap<String, Self>> {
+        let mut policies = HashMap::new();
+        for config in configs {
+            let policy = Self::from_config(config)
+                .map_err(|e| anyhow!("invalid policy {}: {}", config.name, e))?;
+            ensure!(
+                policies.insert(config.name.clone(), policy).is_none(),
+                "duplicate policy {}",
+                config.name
+            );
+        }
+        Ok(policies)
+    }
+
+    /// Checks a verified report of the given age, returning the reasons to
+    /// reject it, if any.
+    pub(crate) fn check(&self, report: &AttestationReport, report_age: Duration) -> Vec<String> {
+        let mut reasons = Vec::new();
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+
+        let accepted = self.enclaves.iter().any(|enclave| {
+            enclave.mr_signer == enclave_report.mr_signer
+                && enclave
+                    .mr_enclave
+                    .map_or(true, |mr_enclave| mr_enclave == enclave_report.mr_enclave)
+        });
+        if !accepted {
+            reasons.push("enclave not accepted".to_string());
+        }
+
+        if !self.allow_debug && enclave_report.attributes[0] & SGX_FLAGS_DEBUG != 0 {
+            reasons.push("debug enclave".to_string());
+        }
+
+        if let Some(max_report_age) = self.max_report_age {
+            if report_age > max_report_age {
+                reasons.push(format!("report is {}s old", report_age.as_secs()));
+            }
+        }
+
+        match self.quote_status_policy.check(report) {
+            QuoteVerdict::Accepted { .. } => (),
+            QuoteVerdict::NotOverridden if report.sgx_quote_status == SgxQuoteStatus::OK => (),
+            QuoteVerdict::NotOverridden => {
+                reasons.push(format!("quote status {:?}", report.sgx_quote_status))
+            }
+            QuoteVerdict::Rejected { advisory_ids } => reasons.push(format!(
+                "quote status {:?} with advisories {}",
+                report.sgx_quote_status,
+                advisory_ids.join(", ")
+            )),
+        }
+
+        reasons
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::vec;
+    use teaclave_attestation::report::SgxQuote;
+    use teaclave_config::QuoteStatusConfig;
+
+    // A report of a production enclave with an OK quote status.
+    pub(crate) fn attestation_report() -> AttestationReport {
+        let quote_encoded = "AgABAC8LAAAKAAkAAAAAAK1zRQOIpndiP4IhlnW2AkwAAAAA\
+                             AAAAAAAAAAAAAAAABQ4CBf+AAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABwAAAAAAAAAHAAAA\
+                             AAAAADMKqRCjd2eA4gAmrj2sB68OWpMfhPH4MH27hZAvWGlT\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACD1xnn\
+                             ferKFHD2uvYqTXdDA8iZ22kCD5xw7h38CMfOngAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+                             AAAAAAAAAADYIY9k0MVmCdIDUuFLf/2bGIHAfPjO9nvC7fgz\
+                             rQedeA3WW4dFeI6oe+RCLdV3XYD1n6lEZjITOzPPLWDxulGz";
+        let quote_raw = base64::decode(quote_encoded.as_bytes()).unwrap();
+        let mut report = AttestationReport {
+            freshness: Duration::from_secs(0),
+            sgx_quote_status: SgxQuoteStatus::OK,
+            advisory_ids: Vec::new(),
+            sgx_quote_body: SgxQuote::parse_from(quote_raw.as_slice()).unwrap(),
+        };
+        report.sgx_quote_body.isv_enclave_report.attributes[0] &= !SGX_FLAGS_DEBUG;
+        report
+    }
+
+    // A policy accepting the enclave of the report.
+    fn policy_config(report: &AttestationReport) -> VerificationPolicyConfig {
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        VerificationPolicyConfig {
+            name: "test".to_string(),
+            enclaves: vec![AcceptedEnclaveConfig {
+                mr_signer: enclave_report.mr_signer.to_hex(),
+                mr_enclave: Some(enclave_report.mr_enclave.to_hex()),
+            }],
+            max_report_age_secs: None,
+            quote_status: QuoteStatusConfig::default(),
+            allow_debug: false,
+        }
+    }
+
+    fn check(config: &VerificationPolicyConfig, report: &AttestationReport) -> Vec<String> {
+        VerificationPolicy::from_config(config)
+            .unwrap()
+            .check(report, report.freshness)
+    }
+
+    pub fn test_accepted_enclaves() {
+        let report = attestation_report();
+        let mut config = policy_config(&report);
+        assert!(check(&config, &report).is_empty());
+
+        // Any enclave of the signer
+        config.enclaves[0].mr_enclave = None;
+        assert!(check(&config, &report).is_empty());
+
+        config.enclaves[0].mr_signer = "00".repeat(32);
+        assert_eq!(check(&config, &report), vec!["enclave not accepted"]);
+
+        config.enclaves.clear();
+        assert_eq!(check(&config, &report), vec!["enclave not accepted"]);
+    }
+
+    pub fn test_debug_enclave() {
+        let mut report = attestation_report();
+        report.sgx_quote_body.isv_enclave_report.attributes[0] |= SGX_FLAGS_DEBUG;
+        let mut config = policy_config(&report);
+        assert_eq!(check(&config, &report), vec!["debug enclave"]);
+
+        config.allow_debug = true;
+        assert!(check(&config, &report).is_empty());
+    }
+
+    pub fn test_report_age() {
+        let mut report = attestation_report();
+        report.freshness = Duration::from_secs(7200);
+        let mut config = policy_config(&report);
+        assert!(check(&config, &report).is_empty());
+
+        config.max_report_age_secs = Some(3600);
+        assert_eq!(check(&config, &report), vec!["report is 7200s old"]);
+
+        config.max_report_age_secs = Some(7200);
+        assert!(check(&config, &report).is_empty());
+    }
+
+    pub fn test_quote_status() {
+        let mut report = attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::SwHardeningNeeded;
+        report.advisory_ids = vec!["INTEL-SA-00334".to_string()];
+        let mut config = policy_config(&report);
+        assert_eq!(check(&config, &report).len(), 1);
+
+        config.quote_status.accept_sw_hardening_needed_if_only =
+            Some(vec!["INTEL-SA-00334".to_string()]);
+        assert!(check(&config, &report).is_empty());
+
+        report.advisory_ids.push("INTEL-SA-00615".to_string());
+        let reasons = check(&config, &report);
+        assert_eq!(reasons.len(), 1);
+        assert!(reasons[0].contains("INTEL-SA-00615"));
+    }
+
+    pub fn test_invalid_policies() {
+        let report = attestation_report();
+        let config = policy_config(&report);
+        assert!(VerificationPolicy::from_configs(&[config.clone()]).is_ok());
+        assert!(VerificationPolicy::from_configs(&[config.clone(), config.clone()]).is_err());
+
+        let mut invalid = config;
+        invalid.enclaves[0].mr_signer = "not hex".to_string();
+        assert!(VerificationPolicy::from_configs(&[invalid.clone()]).is_err());
+        invalid.enclaves[0].mr_signer = "00".repeat(16);
+        assert!(VerificationPolicy::from_configs(&[invalid]).is_err());
+    }
+}
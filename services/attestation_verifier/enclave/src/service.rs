@@ -0,0 +1,171 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::cache::{evidence_digest, ReportCache};
+use crate::error::TeaclaveAttestationVerifierError;
+use crate::policy::VerificationPolicy;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxMutex as Mutex};
+use std::time::Duration;
+use teaclave_attestation::report::AttestationReport;
+use teaclave_proto::teaclave_attestation_verifier_service::{
+    HealthRequest, HealthResponse, TeaclaveAttestationVerifier, VerifyRequest, VerifyResponse,
+};
+use teaclave_rpc::Request;
+use teaclave_service_enclave_utils::{health, teaclave_service};
+use teaclave_types::{platform, TeaclaveServiceResponseResult};
+
+#[teaclave_service(teaclave_attestation_verifier_service, TeaclaveAttestationVerifier)]
+#[derive(Clone)]
+pub(crate) struct TeaclaveAttestationVerifierService {
+    policies: Arc<HashMap<String, VerificationPolicy>>,
+    cache: Arc<Mutex<ReportCache>>,
+    // Root certificate of the attestation service
+    root_ca: Vec<u8>,
+}
+
+impl TeaclaveAttestationVerifierService {
+    pub(crate) fn new(
+        policies: HashMap<String, VerificationPolicy>,
+        cache: ReportCache,
+        root_ca: &[u8],
+    ) -> Self {
+        Self {
+            policies: Arc::new(policies),
+            cache: Arc::new(Mutex::new(cache)),
+            root_ca: root_ca.to_vec(),
+        }
+    }
+
+    // The report endorsed in the evidence and its age, from the cache if it
+    // has been verified recently. The cache is skipped if its lock is
+    // poisoned.
+    fn verify_evidence(&self, evidence: &[u8]) -> Result<(Arc<AttestationReport>, Duration)> {
+        let digest = evidence_digest(evidence);
+        let now = platform::time::now();
+        let cached = match self.cache.lock() {
+            Ok(cache) => cache.get(&digest, now),
+            Err(_) => None,
+        };
+        if let Some((report, elapsed)) = cached {
+            let age = report.freshness + elapsed;
+            return Ok((report, age));
+        }
+
+        let report = Arc::new(AttestationReport::from_cert(evidence, &self.root_ca)?);
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(digest, report.clone(), now);
+        }
+        let age = report.freshness;
+        Ok((report, age))
+    }
+}
+
+impl TeaclaveAttestationVerifier for TeaclaveAttestationVerifierService {
+    fn verify(
+        &self,
+        request: Request<VerifyRequest>,
+    ) -> TeaclaveServiceResponseResult<VerifyResponse> {
+        let request = request.message;
+        let policy = self.policies.get(&request.policy).ok_or_else(|| {
+            TeaclaveAttestationVerifierError::UnknownPolicy(request.policy.clone())
+        })?;
+
+        let (report, report_age) = match self.verify_evidence(&request.evidence) {
+            Ok(verified) => verified,
+            Err(e) => {
+                info!(
+                    target: "audit",
+                    "evidence rejected by policy {}: {}", request.policy, e
+                );
+                return Ok(VerifyResponse {
+                    reasons: vec![format!("invalid evidence: {}", e)],
+                    ..Default::default()
+                });
+            }
+        };
+
+        let reasons = policy.check(&report, report_age);
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        let response = VerifyResponse {
+            accepted: reasons.is_empty(),
+            reasons,
+            mr_enclave: enclave_report.mr_enclave.to_hex(),
+            mr_signer: enclave_report.mr_signer.to_hex(),
+            isv_prod_id: enclave_report.isv_prod_id,
+            isv_svn: enclave_report.isv_svn,
+            report_data: enclave_report.report_data.as_bytes().to_vec(),
+            quote_status: format!("{:?}", report.sgx_quote_status),
+            advisory_ids: report.advisory_ids.clone(),
+            report_age_secs: report_age.as_secs(),
+        };
+        info!(
+            target: "audit",
+            "enclave {} verified with policy {}: accepted {}, {:?}",
+            response.mr_enclave, request.policy, response.accepted, response.reasons
+        );
+        Ok(response)
+    }
+
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        Ok(HealthResponse::new(vec![health::attestation_check()]))
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::vec;
+    use teaclave_config::{AcceptedEnclaveConfig, QuoteStatusConfig, VerificationPolicyConfig};
+    use teaclave_rpc::IntoRequest;
+
+    fn get_mock_service() -> TeaclaveAttestationVerifierService {
+        let config = VerificationPolicyConfig {
+            name: "test".to_string(),
+            enclaves: vec![AcceptedEnclaveConfig {
+                mr_signer: "00".repeat(32),
+                mr_enclave: None,
+            }],
+            max_report_age_secs: None,
+            quote_status: QuoteStatusConfig::default(),
+            allow_debug: false,
+        };
+        let policies = VerificationPolicy::from_configs(&[config]).unwrap();
+        let cache = ReportCache::new(16, Duration::from_secs(60));
+        TeaclaveAttestationVerifierService::new(policies, cache, b"not a certificate")
+    }
+
+    pub fn test_unknown_policy() {
+        let service = get_mock_service();
+        let request = VerifyRequest::new(vec![0u8; 16], "unknown").into_request();
+        assert!(service.verify(request).is_err());
+    }
+
+    pub fn test_invalid_evidence() {
+        let service = get_mock_service();
+        let request = VerifyRequest::new(vec![0u8; 16], "test").into_request();
+        let response = service.verify(request).unwrap();
+        assert!(!response.accepted);
+        assert_eq!(response.reasons.len(), 1);
+        assert!(response.reasons[0].starts_with("invalid evidence"));
+    }
+}
@@ -0,0 +1,292 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::user_info::UserInfo;
+use anyhow::{anyhow, ensure, Result};
+use ring::{constant_time, digest};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "enclave_unit_test")]
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxMutex as Mutex};
+use teaclave_proto::teaclave_common::{HealthCheck, HealthRequest};
+use teaclave_proto::teaclave_storage_service::{BatchRequest, GetRequest, TeaclaveStorageClient};
+use teaclave_rpc::endpoint::Endpoint;
+use teaclave_service_enclave_utils::health;
+use teaclave_types::{
+    platform, tenant_namespace, ExternalID, Storable, TeaclaveServiceResponseError, UserID,
+};
+use uuid::Uuid;
+
+const API_KEY_PREFIX: &str = "teaclave-api-key";
+const SECRET_LEN: usize = 32;
+const MAX_NAME_LEN: usize = 64;
+const MAX_SCOPES: usize = 64;
+
+/// Scope of API keys allowed for every operation.
+pub(crate) const ALL_SCOPES: &str = "*";
+
+// API keys are long-lived credentials of a user, accepted in place of login
+// tokens for the operations in their scopes. Only the hash of the secret is
+// stored; the key is shown to the user once on creation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct ApiKey {
+    pub key_id: Uuid,
+    pub user_id: String,
+    // epoch of the user record the key was created for
+    #[serde(default)]
+    pub user_epoch: Uuid,
+    pub name: String,
+    // names of the frontend operations allowed, or "*"
+    pub scopes: Vec<String>,
+    // The secret is random, so SHA-256 without salt suffices.
+    pub secret_hash: Vec<u8>,
+    pub revoked: bool,
+}
+
+impl Storable for ApiKey {
+    fn key_prefix() -> &'static str {
+        "api-key"
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.key_id
+    }
+}
+
+impl ApiKey {
+    // Returns the key and its string, "teaclave-api-key.<key id>.<secret>".
+    pub(crate) fn new(user: &UserInfo, name: &str, scopes: Vec<String>) -> Result<(Self, String)> {
+        ensure!(
+            !name.is_empty() && name.len() <= MAX_NAME_LEN,
+            "invalid name"
+        );
+        ensure!(
+            !scopes.is_empty() && scopes.len() <= MAX_SCOPES,
+            "invalid scopes"
+        );
+        ensure!(scopes.iter().all(|s| !s.is_empty()), "invalid scopes");
+        let key_id = platform::rand::new_uuid();
+        let mut secret = vec![0; SECRET_LEN];
+        platform::rand::fill_bytes(&mut secret);
+        let api_key = Self {
+            key_id,
+            user_id: user.id.clone(),
+            user_epoch: user.epoch,
+            name: name.to_string(),
+            scopes,
+            secret_hash: digest::digest(&digest::SHA256, &secret).as_ref().to_vec(),
+            revoked: false,
+        };
+        let encoded = format!("{}.{}.{}", API_KEY_PREFIX, key_id, hex::encode(&secret));
+        Ok((api_key, encoded))
+    }
+
+    fn verify_secret(&self, secret: &[u8]) -> bool {
+        let hash = digest::digest(&digest::SHA256, secret);
+        constant_time::verify_slices_are_equal(hash.as_ref(), &self.secret_hash).is_ok()
+    }
+
+    pub(crate) fn allows(&self, operation: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|scope| scope == ALL_SCOPES || scope == operation)
+    }
+}
+
+pub(crate) fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+fn parse_api_key(api_key: &str) -> Result<(Uuid, Vec<u8>)> {
+    let parts: Vec<&str> = api_key.split('.').collect();
+    ensure!(
+        parts.len() == 3 && parts[0] == API_KEY_PREFIX,
+        "invalid API key"
+    );
+    let key_id = Uuid::parse_str(parts[1])?;
+    let secret = hex::decode(parts[2])?;
+    Ok((key_id, secret))
+}
+
+// API keys outlive the in-memory user database, so they are kept in the
+// storage service, in the namespace of their user. Keys stored before outside
+// of namespaces are still read, and moved on the next write.
+#[derive(Clone)]
+pub(crate) enum ApiKeyStore {
+    Storage(Arc<Mutex<TeaclaveStorageClient>>),
+    #[cfg(feature = "enclave_unit_test")]
+    Memory(Arc<Mutex<HashMap<(String, Vec<u8>), Vec<u8>>>>),
+}
+
+impl ApiKeyStore {
+    pub(crate) fn connect(storage_service_endpoint: Endpoint) -> Result<Self> {
+        let mut i = 0;
+        let channel = loop {
+            match storage_service_endpoint.connect() {
+                Ok(channel) => break channel,
+                Err(_) => {
+                    anyhow::ensure!(i < 10, "failed to connect to storage service");
+                    log::debug!("Failed to connect to storage service, retry {}", i);
+                    i += 1;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_secs(3));
+        };
+        let client = TeaclaveStorageClient::new(channel)?;
+        Ok(ApiKeyStore::Storage(Arc::new(Mutex::new(client))))
+    }
+
+    #[cfg(feature = "enclave_unit_test")]
+    pub(crate) fn in_memory() -> Self {
+        ApiKeyStore::Memory(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    // Keys are looked up in the namespace of the user, so that keys of other
+    // users are not found.
+    pub(crate) fn get(&self, user_id: &str, key_id: &Uuid) -> Result<ApiKey> {
+        let key = ExternalID::new(ApiKey::key_prefix(), *key_id).to_bytes();
+        let namespace = tenant_namespace(&UserID::from(user_id));
+        let value = match self {
+            ApiKeyStore::Storage(client) => {
+                let mut client = client
+                    .lock()
+                    .map_err(|_| anyhow!("Cannot lock storage client"))?;
+                match client.get(GetRequest::new(key.as_slice()).namespace(namespace)) {
+                    Ok(response) => response.value,
+                    Err(TeaclaveServiceResponseError::RequestError(_)) => {
+                        client.get(GetRequest::new(key))?.value
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            #[cfg(feature = "enclave_unit_test")]
+            ApiKeyStore::Memory(map) => map
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock API keys"))?
+                .get(&(namespace, key))
+                .cloned()
+                .ok_or_else(|| anyhow!("API key not exist"))?,
+        };
+        ApiKey::from_slice(&value)
+    }
+
+    pub(crate) fn put(&self, api_key: &ApiKey) -> Result<()> {
+        let key = api_key.key();
+        let value = api_key.to_vec()?;
+        let namespace = tenant_namespace(&UserID::from(api_key.user_id.as_str()));
+        match self {
+            ApiKeyStore::Storage(client) => {
+                let batch = BatchRequest::new()
+                    .delete(key.as_slice())
+                    .put_in(namespace, key, value);
+                client
+                    .lock()
+                    .map_err(|_| anyhow!("Cannot lock storage client"))?
+                    .batch(batch)?;
+            }
+            #[cfg(feature = "enclave_unit_test")]
+            ApiKeyStore::Memory(map) => {
+                map.lock()
+                    .map_err(|_| anyhow!("Cannot lock API keys"))?
+                    .insert((namespace, key), value);
+            }
+        }
+        Ok(())
+    }
+
+    // Only the owner of a key can revoke it.
+    pub(crate) fn revoke(&self, user_id: &str, key_id: &str) -> Result<()> {
+        let key_id = Uuid::parse_str(key_id)?;
+        let mut api_key = self.get(user_id, &key_id)?;
+        ensure!(api_key.user_id == user_id, "not the owner of the API key");
+        api_key.revoked = true;
+        self.put(&api_key)
+    }
+
+    // Accepts an API key of the user which is not revoked and allows the
+    // operation. Keys created for an earlier record of the same id, e.g.,
+    // before a restart emptied the user database, are rejected.
+    pub(crate) fn authenticate(&self, user: &UserInfo, api_key: &str, operation: &str) -> bool {
+        let (key_id, secret) = match parse_api_key(api_key) {
+            Ok(parsed) => parsed,
+            Err(_) => return false,
+        };
+        match self.get(&user.id, &key_id) {
+            Ok(stored) => {
+                stored.verify_secret(&secret)
+                    && stored.user_id == user.id
+                    && !stored.user_epoch.is_nil()
+                    && stored.user_epoch == user.epoch
+                    && !stored.revoked
+                    && stored.allows(operation)
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub(crate) fn health_check(&self) -> HealthCheck {
+        match self {
+            ApiKeyStore::Storage(client) => match client.lock() {
+                Ok(mut client) => {
+                    health::dependency_check("storage", client.health(HealthRequest::new()))
+                }
+                Err(_) => HealthCheck::unhealthy("storage", "cannot lock storage client"),
+            },
+            #[cfg(feature = "enclave_unit_test")]
+            ApiKeyStore::Memory(_) => HealthCheck::healthy("storage", "in memory"),
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_api_key() {
+        let store = ApiKeyStore::in_memory();
+        let user = UserInfo::new_external("test_user_id");
+        let another_user = UserInfo::new_external("another_user_id");
+        let scopes = vec!["get_task".to_string(), "invoke_task".to_string()];
+        let (api_key, encoded) = ApiKey::new(&user, "ci", scopes).unwrap();
+        store.put(&api_key).unwrap();
+        assert!(is_api_key(&encoded));
+        assert!(store.authenticate(&user, &encoded, "get_task"));
+
+        // The key is bound to its user record, scopes and secret.
+        assert!(!store.authenticate(&another_user, &encoded, "get_task"));
+        let registered_again = UserInfo::new_external("test_user_id");
+        assert!(!store.authenticate(&registered_again, &encoded, "get_task"));
+        assert!(!store.authenticate(&user, &encoded, "approve_task"));
+        let forged = format!("{}.{}.{}", API_KEY_PREFIX, api_key.key_id, "00".repeat(32));
+        assert!(!store.authenticate(&user, &forged, "get_task"));
+        assert!(!store.authenticate(&user, "teaclave-api-key.x.y", "get_task"));
+
+        // Keys are kept in the namespace of their user.
+        assert!(store.get("another_user_id", &api_key.key_id).is_err());
+        assert!(store
+            .revoke("another_user_id", &api_key.key_id.to_string())
+            .is_err());
+        store
+            .revoke("test_user_id", &api_key.key_id.to_string())
+            .unwrap();
+        assert!(!store.authenticate(&user, &encoded, "get_task"));
+
+        assert!(ApiKey::new(&user, "", vec![ALL_SCOPES.to_string()]).is_err());
+        assert!(ApiKey::new(&user, "ci", vec![]).is_err());
+    }
+}
@@ -15,19 +15,41 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::api_key::{ApiKey, ApiKeyStore};
+use crate::argon2::Argon2Params;
 use crate::error::TeaclaveAuthenticationApiError;
+use crate::impersonation::Impersonation;
+use crate::ldap::Ldap;
+use crate::oidc::Oidc;
+use crate::read_only::ReadOnlyMode;
+use crate::revocation::TokenRevocations;
+use crate::role::Roles;
+use crate::totp;
 use crate::user_db::{DbClient, DbError};
-use crate::user_info::UserInfo;
+use crate::user_info::{Claims, UserInfo, UserStatus};
+use crate::user_resources::UserResources;
 use std::prelude::v1::*;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::untrusted::time::SystemTimeEx;
 use teaclave_proto::teaclave_authentication_service::{
-    TeaclaveAuthenticationApi, UserLoginRequest, UserLoginResponse, UserRegisterRequest,
-    UserRegisterResponse,
+    AssignRolesRequest, AssignRolesResponse, ConfirmTotpRequest, ConfirmTotpResponse,
+    CreateApiKeyRequest, CreateApiKeyResponse, CreateRoleRequest, CreateRoleResponse,
+    DeleteUserRequest, DeleteUserResponse, DisableUserRequest, DisableUserResponse,
+    EnrollTotpRequest, EnrollTotpResponse, ExportAuditLogRequest, ExportAuditLogResponse,
+    GrantImpersonationRequest, GrantImpersonationResponse, HealthRequest, HealthResponse,
+    ListUsersRequest, ListUsersResponse, RefreshTokenRequest, RefreshTokenResponse,
+    ResetPasswordRequest, ResetPasswordResponse, RevokeApiKeyRequest, RevokeApiKeyResponse,
+    RevokeTokenRequest, RevokeTokenResponse, TeaclaveAuthenticationApi, UserLoginRequest,
+    UserLoginResponse, UserLoginWithOidcRequest, UserLoginWithOidcResponse, UserRegisterRequest,
+    UserRegisterResponse, UserSummary,
 };
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::{bail, ensure, teaclave_service};
-use teaclave_types::TeaclaveServiceResponseResult;
+use teaclave_service_enclave_utils::audit::AuditRecorder;
+use teaclave_service_enclave_utils::{bail, ensure, health, teaclave_service};
+use teaclave_types::{AuditEventKind, Permission, TeaclaveServiceResponseResult};
+
+const MAX_LIST_USERS: u32 = 100;
+const MAX_EXPORT_AUDIT_LOG: u32 = 1000;
 
 #[teaclave_service(
     teaclave_authentication_service,
@@ -38,13 +60,257 @@ use teaclave_types::TeaclaveServiceResponseResult;
 pub(crate) struct TeaclaveAuthenticationApiService {
     db_client: DbClient,
     jwt_secret: Vec<u8>,
+    impersonation: Impersonation,
+    oidc: Oidc,
+    ldap: Option<Ldap>,
+    api_keys: ApiKeyStore,
+    revocations: TokenRevocations,
+    roles: Roles,
+    password_hashing: Argon2Params,
+    user_resources: UserResources,
+    read_only: ReadOnlyMode,
+    audit: AuditRecorder,
 }
 
 impl TeaclaveAuthenticationApiService {
-    pub(crate) fn new(db_client: DbClient, jwt_secret: Vec<u8>) -> Self {
+    pub(crate) fn new(
+        db_client: DbClient,
+        jwt_secret: Vec<u8>,
+        impersonation: Impersonation,
+        oidc: Oidc,
+        ldap: Option<Ldap>,
+        api_keys: ApiKeyStore,
+        revocations: TokenRevocations,
+        roles: Roles,
+        password_hashing: Argon2Params,
+        user_resources: UserResources,
+        read_only: ReadOnlyMode,
+        audit: AuditRecorder,
+    ) -> Self {
         Self {
             db_client,
             jwt_secret,
+            impersonation,
+            oidc,
+            ldap,
+            api_keys,
+            revocations,
+            roles,
+            password_hashing,
+            user_resources,
+            read_only,
+            audit,
+        }
+    }
+
+    // Rejects writes in the read-only mode of the platform, or if the mode
+    // cannot be read from the storage.
+    fn ensure_writable(&self) -> TeaclaveServiceResponseResult<()> {
+        let window = self
+            .read_only
+            .window()
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        if let Some(window) = window {
+            bail!(TeaclaveAuthenticationApiError::ReadOnlyMode(
+                window.expires_at,
+                window.reason
+            ));
+        }
+        Ok(())
+    }
+
+    // Authenticates the user with the id and login token in the request
+    // metadata, returning the claims of the token. API keys are not accepted.
+    fn authenticated_user<T>(
+        &self,
+        request: &Request<T>,
+    ) -> TeaclaveServiceResponseResult<(UserInfo, Claims)> {
+        let id = request
+            .metadata
+            .get("id")
+            .ok_or(TeaclaveAuthenticationApiError::PermissionDenied)?;
+        let token = request
+            .metadata
+            .get("token")
+            .ok_or(TeaclaveAuthenticationApiError::PermissionDenied)?;
+        let user = self
+            .db_client
+            .get_user(id)
+            .map_err(|_| TeaclaveAuthenticationApiError::PermissionDenied)?;
+        let claims = self
+            .revocations
+            .validate_user_credential(&user, &self.jwt_secret, token)
+            .map_err(|_| TeaclaveAuthenticationApiError::PermissionDenied)?;
+        Ok((user, claims))
+    }
+
+    // Authenticates a user whose roles grant the permission.
+    fn authorized_user<T>(
+        &self,
+        request: &Request<T>,
+        permission: Permission,
+    ) -> TeaclaveServiceResponseResult<UserInfo> {
+        let (user, _) = self.authenticated_user(request)?;
+        let is_platform_admin = self.impersonation.is_platform_admin(&user.id);
+        ensure!(
+            self.roles
+                .permissions(&user, is_platform_admin)
+                .contains(&permission),
+            TeaclaveAuthenticationApiError::PermissionDenied
+        );
+        Ok(user)
+    }
+
+    // Disabled users are not issued tokens, whichever way they log in.
+    fn issue_token(&self, user: &UserInfo) -> TeaclaveServiceResponseResult<String> {
+        ensure!(
+            user.is_active(),
+            TeaclaveAuthenticationApiError::PermissionDenied
+        );
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        let exp = (now + Duration::from_secs(24 * 60)).as_secs();
+        let generation = self.revocations.generation(&user.id);
+        let token = user
+            .get_token(exp, generation, &self.jwt_secret)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        self.audit
+            .record(AuditEventKind::TokenIssued, &user.id, "login token");
+        Ok(token)
+    }
+
+    // Replaces a password hash of legacy algorithms or parameters with the
+    // configured Argon2id one. Failures are logged but do not fail the login.
+    fn rehash_password(&self, user: &mut UserInfo, password: &str) {
+        user.set_password(password, &self.password_hashing);
+        match self.db_client.update_user(user) {
+            Ok(_) => log::info!("Password hash of user {} upgraded", user.id),
+            Err(e) => log::warn!("Failed to upgrade password hash of {}: {}", user.id, e),
+        }
+    }
+
+    // Users enrolled in TOTP two-factor authentication must include a code of
+    // their authenticator in logins. Each code is accepted once.
+    fn verify_totp(
+        &self,
+        user: &mut UserInfo,
+        totp_code: &str,
+    ) -> TeaclaveServiceResponseResult<()> {
+        if user.totp_secret.is_empty() {
+            return Ok(());
+        }
+        ensure!(
+            !totp_code.is_empty(),
+            TeaclaveAuthenticationApiError::TotpRequired
+        );
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        let step = totp::verify(
+            &user.totp_secret,
+            totp_code,
+            user.totp_last_step,
+            now.as_secs(),
+        )
+        .ok_or(TeaclaveAuthenticationApiError::PermissionDenied)?;
+        user.totp_last_step = step;
+        self.db_client
+            .update_user(user)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        Ok(())
+    }
+
+    // Revokes the tokens of the disabled or deleted user, and stops the
+    // management service from serving its functions and pending tasks. The
+    // request can be retried if the management service fails.
+    fn disable_user_resources(
+        &self,
+        admin: &UserInfo,
+        user: &UserInfo,
+    ) -> TeaclaveServiceResponseResult<()> {
+        self.revocations.revoke_all(&user.id);
+        let deleted = user.status == UserStatus::Deleted;
+        if let Err(e) = self.user_resources.disable(&user.id, deleted) {
+            log::warn!("Failed to disable resources of user {}: {}", user.id, e);
+            bail!(TeaclaveAuthenticationApiError::ServiceUnavailable);
+        }
+        let kind = if deleted {
+            AuditEventKind::UserDeleted
+        } else {
+            AuditEventKind::UserDisabled
+        };
+        self.audit
+            .record(kind, &admin.id, format!("user {}", user.id));
+        Ok(())
+    }
+
+    // Users authenticated by an external provider are created on their first
+    // login.
+    fn get_or_create_external_user(
+        &self,
+        id: &str,
+        provider: &str,
+    ) -> TeaclaveServiceResponseResult<UserInfo> {
+        match self.db_client.get_user(id) {
+            Ok(user) => Ok(user),
+            Err(DbError::UserNotExist) => {
+                let user = UserInfo::new_external(id);
+                match self.db_client.create_user(&user) {
+                    // Created by a concurrent login of the same user.
+                    Ok(_) | Err(DbError::UserExist) => (),
+                    Err(_) => bail!(TeaclaveAuthenticationApiError::ServiceUnavailable),
+                }
+                self.audit.record(
+                    AuditEventKind::ExternalUserCreated,
+                    id,
+                    format!("{} login", provider),
+                );
+                Ok(user)
+            }
+            Err(_) => bail!(TeaclaveAuthenticationApiError::ServiceUnavailable),
+        }
+    }
+
+    // Returns the login token of a user logging in with a password, checked
+    // by LDAP for its reserved ids.
+    fn login_with_password(
+        &self,
+        request: &UserLoginRequest,
+    ) -> TeaclaveServiceResponseResult<String> {
+        ensure!(
+            !request.id.is_empty(),
+            TeaclaveAuthenticationApiError::InvalidUserId
+        );
+        ensure!(
+            !request.password.is_empty(),
+            TeaclaveAuthenticationApiError::InvalidPassword
+        );
+        if Ldap::is_reserved(&request.id) {
+            let ldap = self
+                .ldap
+                .as_ref()
+                .ok_or(TeaclaveAuthenticationApiError::PermissionDenied)?;
+            if let Err(e) = ldap.authenticate(&request.id, &request.password) {
+                log::warn!("LDAP login of {} failed: {}", request.id, e);
+                bail!(TeaclaveAuthenticationApiError::PermissionDenied);
+            }
+            let mut user = self.get_or_create_external_user(&request.id, "LDAP")?;
+            self.verify_totp(&mut user, &request.totp_code)?;
+            return self.issue_token(&user);
+        }
+        let mut user = self
+            .db_client
+            .get_user(&request.id)
+            .map_err(|_| TeaclaveAuthenticationApiError::PermissionDenied)?;
+        if !user.verify_password(&request.password) {
+            bail!(TeaclaveAuthenticationApiError::PermissionDenied)
+        } else {
+            self.verify_totp(&mut user, &request.totp_code)?;
+            if user.needs_rehash(&self.password_hashing) {
+                self.rehash_password(&mut user, &request.password);
+            }
+            self.issue_token(&user)
         }
     }
 }
@@ -54,15 +320,22 @@ impl TeaclaveAuthenticationApi for TeaclaveAuthenticationApiService {
         &self,
         request: Request<UserRegisterRequest>,
     ) -> TeaclaveServiceResponseResult<UserRegisterResponse> {
+        self.ensure_writable()?;
         let request = request.message;
         ensure!(
             !request.id.is_empty(),
             TeaclaveAuthenticationApiError::InvalidUserId
         );
+        ensure!(
+            !self.oidc.is_reserved(&request.id)
+                && !Ldap::is_reserved(&request.id)
+                && !self.impersonation.is_reserved(&request.id),
+            TeaclaveAuthenticationApiError::InvalidUserId
+        );
         if self.db_client.get_user(&request.id).is_ok() {
             bail!(TeaclaveAuthenticationApiError::InvalidUserId);
         }
-        let new_user = UserInfo::new(&request.id, &request.password);
+        let new_user = UserInfo::new(&request.id, &request.password, &self.password_hashing);
         match self.db_client.create_user(&new_user) {
             Ok(_) => Ok(UserRegisterResponse {}),
             Err(DbError::UserExist) => Err(TeaclaveAuthenticationApiError::InvalidUserId.into()),
@@ -74,58 +347,485 @@ impl TeaclaveAuthenticationApi for TeaclaveAuthenticationApiService {
         &self,
         request: Request<UserLoginRequest>,
     ) -> TeaclaveServiceResponseResult<UserLoginResponse> {
+        let request = request.message;
+        let method = if Ldap::is_reserved(&request.id) {
+            "LDAP"
+        } else {
+            "password"
+        };
+        match self.login_with_password(&request) {
+            Ok(token) => {
+                self.audit
+                    .record(AuditEventKind::Login, &request.id, method);
+                Ok(UserLoginResponse { token })
+            }
+            Err(e) => {
+                self.audit
+                    .record(AuditEventKind::LoginFailed, &request.id, method);
+                Err(e)
+            }
+        }
+    }
+
+    fn user_login_with_oidc(
+        &self,
+        request: Request<UserLoginWithOidcRequest>,
+    ) -> TeaclaveServiceResponseResult<UserLoginWithOidcResponse> {
+        let request = request.message;
+        let id = match self.oidc.verify(&request.id_token) {
+            Ok(id) => id,
+            Err(_) => {
+                // The user is unknown if the ID token is rejected.
+                self.audit.record(AuditEventKind::LoginFailed, "", "OIDC");
+                bail!(TeaclaveAuthenticationApiError::PermissionDenied);
+            }
+        };
+        let token = self
+            .get_or_create_external_user(&id, "OIDC")
+            .and_then(|user| self.issue_token(&user));
+        match token {
+            Ok(token) => {
+                self.audit.record(AuditEventKind::Login, &id, "OIDC");
+                Ok(UserLoginWithOidcResponse::new(id, token))
+            }
+            Err(e) => {
+                self.audit.record(AuditEventKind::LoginFailed, &id, "OIDC");
+                Err(e)
+            }
+        }
+    }
+
+    // The user granting the consent is authenticated with the id and token in
+    // the request metadata.
+    fn grant_impersonation(
+        &self,
+        request: Request<GrantImpersonationRequest>,
+    ) -> TeaclaveServiceResponseResult<GrantImpersonationResponse> {
+        let (user, _) = self.authenticated_user(&request)?;
+        let request = request.message;
+        let consent_token = self
+            .impersonation
+            .grant(&user.id, &request.admin_id, request.validity)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidConsent)?;
+        self.audit.record(
+            AuditEventKind::ImpersonationGranted,
+            &user.id,
+            format!(
+                "granted platform admin {} impersonation for {:?}",
+                request.admin_id, request.validity
+            ),
+        );
+        Ok(GrantImpersonationResponse::new(consent_token))
+    }
+
+    // The key is returned only once; the service keeps its hash.
+    fn create_api_key(
+        &self,
+        request: Request<CreateApiKeyRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateApiKeyResponse> {
+        self.ensure_writable()?;
+        let (user, _) = self.authenticated_user(&request)?;
+        let request = request.message;
+        let (api_key, encoded) = ApiKey::new(&user, &request.name, request.scopes)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidApiKey)?;
+        self.api_keys
+            .put(&api_key)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        self.audit.record(
+            AuditEventKind::TokenIssued,
+            &user.id,
+            format!(
+                "API key {} ({}) with scopes {:?}",
+                api_key.key_id, api_key.name, api_key.scopes
+            ),
+        );
+        Ok(CreateApiKeyResponse::new(
+            api_key.key_id.to_string(),
+            encoded,
+        ))
+    }
+
+    fn revoke_api_key(
+        &self,
+        request: Request<RevokeApiKeyRequest>,
+    ) -> TeaclaveServiceResponseResult<RevokeApiKeyResponse> {
+        let (user, _) = self.authenticated_user(&request)?;
+        let key_id = request.message.key_id;
+        self.api_keys
+            .revoke(&user.id, &key_id)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidApiKey)?;
+        self.audit.record(
+            AuditEventKind::ApiKeyRevoked,
+            &user.id,
+            format!("API key {}", key_id),
+        );
+        Ok(RevokeApiKeyResponse)
+    }
+
+    // Issues a new login token and revokes the one of the request.
+    fn refresh_token(
+        &self,
+        request: Request<RefreshTokenRequest>,
+    ) -> TeaclaveServiceResponseResult<RefreshTokenResponse> {
+        let (user, claims) = self.authenticated_user(&request)?;
+        let token = self.issue_token(&user)?;
+        self.revocations.revoke(&claims);
+        Ok(RefreshTokenResponse::new(token))
+    }
+
+    fn revoke_token(
+        &self,
+        request: Request<RevokeTokenRequest>,
+    ) -> TeaclaveServiceResponseResult<RevokeTokenResponse> {
+        let (user, claims) = self.authenticated_user(&request)?;
+        if request.message.all {
+            self.revocations.revoke_all(&user.id);
+            self.audit
+                .record(AuditEventKind::TokenRevoked, &user.id, "all login tokens");
+        } else {
+            self.revocations.revoke(&claims);
+            self.audit.record(
+                AuditEventKind::TokenRevoked,
+                &user.id,
+                format!("login token {}", claims.jti),
+            );
+        }
+        Ok(RevokeTokenResponse)
+    }
+
+    fn create_role(
+        &self,
+        request: Request<CreateRoleRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateRoleResponse> {
+        self.ensure_writable()?;
+        let admin = self.authorized_user(&request, Permission::ManageUsers)?;
+        let request = request.message;
+        self.roles
+            .create(&request.name, &request.permissions)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidRole)?;
+        self.audit.record(
+            AuditEventKind::RoleCreated,
+            &admin.id,
+            format!(
+                "role {} with permissions {:?}",
+                request.name, request.permissions
+            ),
+        );
+        Ok(CreateRoleResponse)
+    }
+
+    fn assign_roles(
+        &self,
+        request: Request<AssignRolesRequest>,
+    ) -> TeaclaveServiceResponseResult<AssignRolesResponse> {
+        self.ensure_writable()?;
+        let admin = self.authorized_user(&request, Permission::ManageUsers)?;
         let request = request.message;
         ensure!(
-            !request.id.is_empty(),
-            TeaclaveAuthenticationApiError::InvalidUserId
+            self.roles.exist(&request.roles),
+            TeaclaveAuthenticationApiError::InvalidRole
+        );
+        let mut user = self
+            .db_client
+            .get_user(&request.user_id)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidUserId)?;
+        user.roles = request.roles;
+        self.db_client
+            .update_user(&user)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        self.audit.record(
+            AuditEventKind::RolesAssigned,
+            &admin.id,
+            format!("roles {:?} to user {}", user.roles, user.id),
         );
+        Ok(AssignRolesResponse)
+    }
+
+    // Users logging in with OpenID Connect use the two-factor authentication
+    // of their provider, so only users with a password or of LDAP can enroll.
+    // Users already enrolled must give a current code, so that a stolen token
+    // cannot replace their second factor. The new secret is kept pending
+    // until confirmed, and the enrolled one stays in use meanwhile.
+    fn enroll_totp(
+        &self,
+        request: Request<EnrollTotpRequest>,
+    ) -> TeaclaveServiceResponseResult<EnrollTotpResponse> {
+        let (mut user, _) = self.authenticated_user(&request)?;
         ensure!(
-            !request.password.is_empty(),
-            TeaclaveAuthenticationApiError::InvalidPassword
+            !user.salted_password_hash.is_empty() || Ldap::is_reserved(&user.id),
+            TeaclaveAuthenticationApiError::PermissionDenied
         );
-        let user = self
+        self.verify_totp(&mut user, &request.message.totp_code)?;
+        let secret = totp::new_secret();
+        user.pending_totp_secret = secret.clone();
+        self.db_client
+            .update_user(&user)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        Ok(EnrollTotpResponse::new(
+            totp::encode_secret(&secret),
+            totp::provisioning_uri(&user.id, &secret),
+        ))
+    }
+
+    // Replaces the enrolled secret with the pending one, proving that the
+    // authenticator of the user was set up with it.
+    fn confirm_totp(
+        &self,
+        request: Request<ConfirmTotpRequest>,
+    ) -> TeaclaveServiceResponseResult<ConfirmTotpResponse> {
+        let (mut user, _) = self.authenticated_user(&request)?;
+        ensure!(
+            !user.pending_totp_secret.is_empty(),
+            TeaclaveAuthenticationApiError::PermissionDenied
+        );
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        let step = totp::verify(
+            &user.pending_totp_secret,
+            &request.message.totp_code,
+            0,
+            now.as_secs(),
+        )
+        .ok_or(TeaclaveAuthenticationApiError::PermissionDenied)?;
+        user.totp_secret = std::mem::take(&mut user.pending_totp_secret);
+        user.totp_last_step = step;
+        self.db_client
+            .update_user(&user)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        self.audit
+            .record(AuditEventKind::TotpEnrolled, &user.id, "");
+        Ok(ConfirmTotpResponse)
+    }
+
+    // Deleted users are never listed.
+    fn list_users(
+        &self,
+        request: Request<ListUsersRequest>,
+    ) -> TeaclaveServiceResponseResult<ListUsersResponse> {
+        self.authorized_user(&request, Permission::ManageUsers)?;
+        let request = request.message;
+        ensure!(
+            request.limit > 0 && request.limit <= MAX_LIST_USERS,
+            TeaclaveAuthenticationApiError::InvalidRequest
+        );
+        let users = self
             .db_client
-            .get_user(&request.id)
-            .map_err(|_| TeaclaveAuthenticationApiError::PermissionDenied)?;
-        if !user.verify_password(&request.password) {
-            bail!(TeaclaveAuthenticationApiError::PermissionDenied)
-        } else {
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
+            .list_users(&request.start_after)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        let mut matched = users.into_iter().filter(|user| {
+            user.status != UserStatus::Deleted
+                && (request.include_disabled || user.is_active())
+                && user.id.starts_with(&request.id_prefix)
+                && (request.role.is_empty() || user.roles.contains(&request.role))
+        });
+        let page: Vec<UserInfo> = matched.by_ref().take(request.limit as usize).collect();
+        let next_start_after = match (matched.next(), page.last()) {
+            (Some(_), Some(last)) => last.id.clone(),
+            _ => String::new(),
+        };
+        let users = page
+            .into_iter()
+            .map(|user| UserSummary {
+                disabled: !user.is_active(),
+                totp_enrolled: !user.totp_secret.is_empty(),
+                id: user.id,
+                roles: user.roles,
+            })
+            .collect();
+        Ok(ListUsersResponse::new(users, next_start_after))
+    }
+
+    // Admins cannot disable or delete themselves, so that the platform is
+    // not left without any.
+    fn disable_user(
+        &self,
+        request: Request<DisableUserRequest>,
+    ) -> TeaclaveServiceResponseResult<DisableUserResponse> {
+        let admin = self.authorized_user(&request, Permission::ManageUsers)?;
+        let user_id = request.message.user_id;
+        ensure!(
+            user_id != admin.id,
+            TeaclaveAuthenticationApiError::InvalidUserId
+        );
+        let mut user = self
+            .db_client
+            .get_user(&user_id)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidUserId)?;
+        if user.is_active() {
+            user.status = UserStatus::Disabled;
+            self.db_client
+                .update_user(&user)
                 .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
-            let exp = (now + Duration::from_secs(24 * 60)).as_secs();
-            match user.get_token(exp, &self.jwt_secret) {
-                Ok(token) => Ok(UserLoginResponse { token }),
-                Err(_) => Err(TeaclaveAuthenticationApiError::ServiceUnavailable.into()),
-            }
         }
+        self.disable_user_resources(&admin, &user)?;
+        Ok(DisableUserResponse)
+    }
+
+    fn delete_user(
+        &self,
+        request: Request<DeleteUserRequest>,
+    ) -> TeaclaveServiceResponseResult<DeleteUserResponse> {
+        let admin = self.authorized_user(&request, Permission::ManageUsers)?;
+        let user_id = request.message.user_id;
+        ensure!(
+            user_id != admin.id,
+            TeaclaveAuthenticationApiError::InvalidUserId
+        );
+        let mut user = self
+            .db_client
+            .get_user(&user_id)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidUserId)?;
+        user.delete();
+        self.db_client
+            .update_user(&user)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        self.disable_user_resources(&admin, &user)?;
+        Ok(DeleteUserResponse)
+    }
+
+    // Users of OpenID Connect and LDAP have no password to reset.
+    fn reset_password(
+        &self,
+        request: Request<ResetPasswordRequest>,
+    ) -> TeaclaveServiceResponseResult<ResetPasswordResponse> {
+        self.ensure_writable()?;
+        let admin = self.authorized_user(&request, Permission::ManageUsers)?;
+        let request = request.message;
+        ensure!(
+            !request.new_password.is_empty(),
+            TeaclaveAuthenticationApiError::InvalidPassword
+        );
+        let mut user = self
+            .db_client
+            .get_user(&request.user_id)
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidUserId)?;
+        ensure!(
+            user.status != UserStatus::Deleted && !user.salted_password_hash.is_empty(),
+            TeaclaveAuthenticationApiError::InvalidUserId
+        );
+        user.set_password(&request.new_password, &self.password_hashing);
+        self.db_client
+            .update_user(&user)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        self.revocations.revoke_all(&user.id);
+        self.audit.record(
+            AuditEventKind::PasswordReset,
+            &admin.id,
+            format!("user {}", user.id),
+        );
+        Ok(ResetPasswordResponse)
+    }
+
+    // The seals of the entries are verified by the storage service, which
+    // holds their key.
+    fn export_audit_log(
+        &self,
+        request: Request<ExportAuditLogRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportAuditLogResponse> {
+        let admin = self.authorized_user(&request, Permission::ManageUsers)?;
+        let request = request.message;
+        ensure!(
+            request.limit > 0 && request.limit <= MAX_EXPORT_AUDIT_LOG,
+            TeaclaveAuthenticationApiError::InvalidRequest
+        );
+        let response = self
+            .audit
+            .export(request.start_seq, request.limit)
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+        self.audit.record(
+            AuditEventKind::AuditLogExported,
+            &admin.id,
+            format!(
+                "{} entries from {}",
+                response.entries.len(),
+                request.start_seq
+            ),
+        );
+        Ok(ExportAuditLogResponse {
+            entries: response.entries,
+            log_len: response.log_len,
+            broken_seq: response.broken_seq,
+        })
+    }
+
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        Ok(HealthResponse::new(vec![
+            health::attestation_check(),
+            self.db_client.health_check(),
+            self.api_keys.health_check(),
+        ]))
     }
 }
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
+    use crate::oidc::OidcProvider;
     use crate::user_db::*;
     use crate::user_info::*;
-    use rand::RngCore;
+    use jsonwebtoken as jwt;
     use std::vec;
+    use teaclave_config::ImpersonationConfig;
     use teaclave_rpc::IntoRequest;
+    use teaclave_types::{platform, ReadOnlyWindow};
+
+    const OIDC_ISSUER: &str = "https://accounts.example.com";
+    const OIDC_AUDIENCE: &str = "teaclave";
 
     fn get_mock_service() -> TeaclaveAuthenticationApiService {
         let database = Database::open().unwrap();
         let mut jwt_secret = vec![0; JWT_SECRET_LEN];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut jwt_secret);
+        platform::rand::fill_bytes(&mut jwt_secret);
+        let mut consent_secret = vec![0; JWT_SECRET_LEN];
+        platform::rand::fill_bytes(&mut consent_secret);
+        let config = ImpersonationConfig {
+            max_consent_secs: 3600,
+        };
         TeaclaveAuthenticationApiService {
             db_client: database.get_client(),
             jwt_secret,
+            impersonation: Impersonation::new(consent_secret, &["test_admin_id"], &config),
+            oidc: Oidc::new(vec![OidcProvider {
+                name: "example".to_string(),
+                issuer: OIDC_ISSUER.to_string(),
+                audience: OIDC_AUDIENCE.to_string(),
+                public_keys: vec![platform::fs::read("fixtures/oidc/test.public.der").unwrap()],
+            }]),
+            ldap: None,
+            api_keys: ApiKeyStore::in_memory(),
+            revocations: TokenRevocations::new(),
+            roles: Roles::new(),
+            password_hashing: Argon2Params::default(),
+            user_resources: UserResources::in_memory(),
+            read_only: ReadOnlyMode::in_memory(),
+            audit: AuditRecorder::log_only("teaclave_authentication_service"),
         }
     }
 
+    fn get_id_token(sub: &str, issuer: &str, audience: &str) -> String {
+        let key = platform::fs::read("fixtures/oidc/test.private.der").unwrap();
+        let exp = platform::time::since_epoch().as_secs() + 600;
+        let claims = serde_json::json!({
+            "sub": sub,
+            "iss": issuer,
+            "aud": audience,
+            "exp": exp,
+        });
+        jwt::encode(&jwt::Header::new(jwt::Algorithm::RS256), &claims, &key).unwrap()
+    }
+
     pub fn test_user_register() {
         let request = UserRegisterRequest::new("test_register_id", "test_password").into_request();
         let service = get_mock_service();
         assert!(service.user_register(request).is_ok());
+
+        // The ids of the platform admins of the build config are reserved.
+        let request = UserRegisterRequest::new("test_admin_id", "test_password").into_request();
+        assert!(service.user_register(request).is_err());
     }
 
     pub fn test_user_login() {
@@ -143,4 +843,467 @@ pub mod tests {
         let request = UserLoginRequest::new("test_login_id", "test_password1").into_request();
         assert!(service.user_login(request).is_err());
     }
+
+    pub fn test_rehash_legacy_password() {
+        let service = get_mock_service();
+        let user = crate::user_info::tests::new_pbkdf2_user("test_rehash_id", "test_password");
+        service.db_client.create_user(&user).unwrap();
+
+        let request = UserLoginRequest::new("test_rehash_id", "test_password1").into_request();
+        assert!(service.user_login(request).is_err());
+        let user = service.db_client.get_user("test_rehash_id").unwrap();
+        assert!(user.needs_rehash(&service.password_hashing));
+
+        let request = UserLoginRequest::new("test_rehash_id", "test_password").into_request();
+        assert!(service.user_login(request).is_ok());
+        let user = service.db_client.get_user("test_rehash_id").unwrap();
+        assert!(!user.needs_rehash(&service.password_hashing));
+        assert!(user.verify_password("test_password"));
+
+        let request = UserLoginRequest::new("test_rehash_id", "test_password").into_request();
+        assert!(service.user_login(request).is_ok());
+    }
+
+    pub fn test_enroll_totp() {
+        let service = get_mock_service();
+        let request = UserRegisterRequest::new("test_totp_id", "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+        let request = UserLoginRequest::new("test_totp_id", "test_password").into_request();
+        let token = service.user_login(request).unwrap().token;
+
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("id".to_string(), "test_totp_id".to_string());
+        metadata.insert("token".to_string(), token);
+        let mut request = EnrollTotpRequest::new().into_request();
+        request.metadata = metadata.clone();
+        let response = service.enroll_totp(request).unwrap();
+        assert!(response.provisioning_uri.starts_with("otpauth://totp/"));
+        let user = service.db_client.get_user("test_totp_id").unwrap();
+        assert_eq!(
+            totp::encode_secret(&user.pending_totp_secret),
+            response.secret
+        );
+
+        // The secret is not used until confirmed.
+        let request = UserLoginRequest::new("test_totp_id", "test_password").into_request();
+        assert!(service.user_login(request).is_ok());
+        let code = totp::tests::current_code(&user.pending_totp_secret);
+        let wrong_code = if code == "000000" { "111111" } else { "000000" };
+        let mut request = ConfirmTotpRequest::new(wrong_code).into_request();
+        request.metadata = metadata.clone();
+        assert!(service.confirm_totp(request).is_err());
+        let mut request = ConfirmTotpRequest::new(&code).into_request();
+        request.metadata = metadata.clone();
+        assert!(service.confirm_totp(request).is_ok());
+        let user = service.db_client.get_user("test_totp_id").unwrap();
+        assert!(user.pending_totp_secret.is_empty());
+
+        let request = UserLoginRequest::new("test_totp_id", "test_password").into_request();
+        assert!(service.user_login(request).is_err());
+        let request = UserLoginRequest::new("test_totp_id", "test_password")
+            .totp_code(wrong_code)
+            .into_request();
+        assert!(service.user_login(request).is_err());
+        // The code confirming the secret cannot be used again.
+        let request = UserLoginRequest::new("test_totp_id", "test_password")
+            .totp_code(&code)
+            .into_request();
+        assert!(service.user_login(request).is_err());
+
+        // Enrolling again needs a current code.
+        let mut request = EnrollTotpRequest::new().into_request();
+        request.metadata = metadata.clone();
+        assert!(service.enroll_totp(request).is_err());
+        let mut request = EnrollTotpRequest::new()
+            .totp_code(wrong_code)
+            .into_request();
+        request.metadata = metadata;
+        assert!(service.enroll_totp(request).is_err());
+        let user = service.db_client.get_user("test_totp_id").unwrap();
+        assert!(user.pending_totp_secret.is_empty());
+    }
+
+    pub fn test_user_login_with_oidc() {
+        let service = get_mock_service();
+        let id_token = get_id_token("alice", OIDC_ISSUER, OIDC_AUDIENCE);
+        let request = UserLoginWithOidcRequest::new(&id_token).into_request();
+        let response = service.user_login_with_oidc(request).unwrap();
+        assert_eq!(response.id, "example:alice");
+        let user = service.db_client.get_user("example:alice").unwrap();
+        assert!(user.validate_token(&service.jwt_secret, &response.token));
+
+        // The user is created once, and has no password.
+        let request = UserLoginWithOidcRequest::new(&id_token).into_request();
+        assert!(service.user_login_with_oidc(request).is_ok());
+        let request = UserLoginRequest::new("example:alice", "test_password").into_request();
+        assert!(service.user_login(request).is_err());
+
+        // Tokens of other issuers or audiences are rejected.
+        for (issuer, audience) in &[
+            ("https://evil.example.com", OIDC_AUDIENCE),
+            (OIDC_ISSUER, "another_client"),
+        ] {
+            let id_token = get_id_token("alice", issuer, audience);
+            let request = UserLoginWithOidcRequest::new(id_token).into_request();
+            assert!(service.user_login_with_oidc(request).is_err());
+        }
+
+        // Ids of the users of the providers cannot be registered.
+        let request = UserRegisterRequest::new("example:bob", "test_password").into_request();
+        assert!(service.user_register(request).is_err());
+    }
+
+    pub fn test_user_login_with_ldap() {
+        let service = get_mock_service();
+        // Ids of the users of the LDAP server cannot be registered, and
+        // cannot log in without an LDAP server.
+        let request = UserRegisterRequest::new("ldap:alice", "test_password").into_request();
+        assert!(service.user_register(request).is_err());
+        let request = UserLoginRequest::new("ldap:alice", "test_password").into_request();
+        assert!(service.user_login(request).is_err());
+        assert!(service.db_client.get_user("ldap:alice").is_err());
+    }
+
+    pub fn test_create_api_key() {
+        let service = get_mock_service();
+        let request = UserRegisterRequest::new("test_api_key_id", "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+        let request = UserLoginRequest::new("test_api_key_id", "test_password").into_request();
+        let token = service.user_login(request).unwrap().token;
+
+        let scopes = vec!["get_task".to_string()];
+        let mut request = CreateApiKeyRequest::new("ci", scopes.clone()).into_request();
+        request
+            .metadata
+            .insert("id".to_string(), "test_api_key_id".to_string());
+        request.metadata.insert("token".to_string(), token.clone());
+        let response = service.create_api_key(request).unwrap();
+        let user = service.db_client.get_user("test_api_key_id").unwrap();
+        assert!(service
+            .api_keys
+            .authenticate(&user, &response.api_key, "get_task"));
+
+        // API keys cannot create API keys.
+        let mut request = CreateApiKeyRequest::new("ci", scopes).into_request();
+        request
+            .metadata
+            .insert("id".to_string(), "test_api_key_id".to_string());
+        request
+            .metadata
+            .insert("token".to_string(), response.api_key.clone());
+        assert!(service.create_api_key(request).is_err());
+
+        let mut request = RevokeApiKeyRequest::new(&response.key_id).into_request();
+        request
+            .metadata
+            .insert("id".to_string(), "test_api_key_id".to_string());
+        request.metadata.insert("token".to_string(), token);
+        assert!(service.revoke_api_key(request).is_ok());
+        assert!(!service
+            .api_keys
+            .authenticate(&user, &response.api_key, "get_task"));
+    }
+
+    pub fn test_refresh_and_revoke_token() {
+        let service = get_mock_service();
+        let request = UserRegisterRequest::new("test_revoke_id", "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+        let login = || {
+            let request = UserLoginRequest::new("test_revoke_id", "test_password").into_request();
+            service.user_login(request).unwrap().token
+        };
+        let with_credential = |token: &str| {
+            let mut metadata = std::collections::HashMap::new();
+            metadata.insert("id".to_string(), "test_revoke_id".to_string());
+            metadata.insert("token".to_string(), token.to_string());
+            metadata
+        };
+
+        // The refreshed token replaces the old one.
+        let token = login();
+        let mut request = RefreshTokenRequest.into_request();
+        request.metadata = with_credential(&token);
+        let refreshed = service.refresh_token(request).unwrap().token;
+        let mut request = RefreshTokenRequest.into_request();
+        request.metadata = with_credential(&token);
+        assert!(service.refresh_token(request).is_err());
+
+        let another_token = login();
+        let mut request = RevokeTokenRequest::new().into_request();
+        request.metadata = with_credential(&refreshed);
+        assert!(service.revoke_token(request).is_ok());
+        let mut request = RevokeTokenRequest::new().into_request();
+        request.metadata = with_credential(&refreshed);
+        assert!(service.revoke_token(request).is_err());
+
+        let mut request = RevokeTokenRequest::new().all().into_request();
+        request.metadata = with_credential(&another_token);
+        assert!(service.revoke_token(request).is_ok());
+        let mut request = RefreshTokenRequest.into_request();
+        request.metadata = with_credential(&another_token);
+        assert!(service.refresh_token(request).is_err());
+
+        // Tokens issued after the revocation are valid.
+        let mut request = RefreshTokenRequest.into_request();
+        request.metadata = with_credential(&login());
+        assert!(service.refresh_token(request).is_ok());
+    }
+
+    pub fn test_manage_roles() {
+        let service = get_mock_service();
+        for id in &["test_admin_id", "test_role_id"] {
+            let request = UserRegisterRequest::new(*id, "test_password").into_request();
+            assert!(service.user_register(request).is_ok());
+        }
+        let login = |id: &str| {
+            let request = UserLoginRequest::new(id, "test_password").into_request();
+            let token = service.user_login(request).unwrap().token;
+            let mut metadata = std::collections::HashMap::new();
+            metadata.insert("id".to_string(), id.to_string());
+            metadata.insert("token".to_string(), token);
+            metadata
+        };
+
+        // Users without the manage_users permission cannot manage roles.
+        let permissions = vec![Permission::ReadAnyOutput];
+        let mut request = CreateRoleRequest::new("auditor", permissions.clone()).into_request();
+        request.metadata = login("test_role_id");
+        assert!(service.create_role(request).is_err());
+
+        let mut request = CreateRoleRequest::new("auditor", permissions).into_request();
+        request.metadata = login("test_admin_id");
+        assert!(service.create_role(request).is_ok());
+        let roles = vec!["auditor".to_string(), "unknown".to_string()];
+        let mut request = AssignRolesRequest::new("test_role_id", roles).into_request();
+        request.metadata = login("test_admin_id");
+        assert!(service.assign_roles(request).is_err());
+        let roles = vec!["auditor".to_string(), "admin".to_string()];
+        let mut request = AssignRolesRequest::new("test_role_id", roles).into_request();
+        request.metadata = login("test_admin_id");
+        assert!(service.assign_roles(request).is_ok());
+
+        // With the admin role, the user can manage roles as well.
+        let mut request = CreateRoleRequest::new("developer", vec![]).into_request();
+        request.metadata = login("test_role_id");
+        assert!(service.create_role(request).is_ok());
+    }
+
+    pub fn test_manage_users() {
+        let service = get_mock_service();
+        for id in &["test_admin_id", "test_user_a", "test_user_b", "test_user_c"] {
+            let request = UserRegisterRequest::new(*id, "test_password").into_request();
+            assert!(service.user_register(request).is_ok());
+        }
+        let login = |id: &str, password: &str| {
+            let request = UserLoginRequest::new(id, password).into_request();
+            let token = service.user_login(request)?.token;
+            let mut metadata = std::collections::HashMap::new();
+            metadata.insert("id".to_string(), id.to_string());
+            metadata.insert("token".to_string(), token);
+            Ok::<_, teaclave_types::TeaclaveServiceResponseError>(metadata)
+        };
+        let admin = login("test_admin_id", "test_password").unwrap();
+
+        // Users without the manage_users permission cannot list users.
+        let mut request = ListUsersRequest::new(10).into_request();
+        request.metadata = login("test_user_a", "test_password").unwrap();
+        assert!(service.list_users(request).is_err());
+
+        let mut request = ListUsersRequest::new(2)
+            .id_prefix("test_user_")
+            .into_request();
+        request.metadata = admin.clone();
+        let response = service.list_users(request).unwrap();
+        let ids: Vec<&str> = response.users.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, vec!["test_user_a", "test_user_b"]);
+        assert_eq!(response.next_start_after, "test_user_b");
+        let mut request = ListUsersRequest::new(2)
+            .id_prefix("test_user_")
+            .start_after("test_user_b")
+            .into_request();
+        request.metadata = admin.clone();
+        let response = service.list_users(request).unwrap();
+        assert_eq!(response.users.len(), 1);
+        assert_eq!(response.users[0].id, "test_user_c");
+        assert!(response.next_start_after.is_empty());
+        let mut request = ListUsersRequest::new(0).into_request();
+        request.metadata = admin.clone();
+        assert!(service.list_users(request).is_err());
+
+        // Disabled users cannot log in, and their tokens are revoked.
+        let user_a = login("test_user_a", "test_password").unwrap();
+        let mut request = DisableUserRequest::new("test_user_a").into_request();
+        request.metadata = admin.clone();
+        assert!(service.disable_user(request).is_ok());
+        assert!(service.user_resources.is_disabled("test_user_a"));
+        assert!(login("test_user_a", "test_password").is_err());
+        let mut request = RefreshTokenRequest::new().into_request();
+        request.metadata = user_a;
+        assert!(service.refresh_token(request).is_err());
+        let mut request = DisableUserRequest::new("test_admin_id").into_request();
+        request.metadata = admin.clone();
+        assert!(service.disable_user(request).is_err());
+
+        let mut request = ListUsersRequest::new(10)
+            .id_prefix("test_user_")
+            .into_request();
+        request.metadata = admin.clone();
+        let response = service.list_users(request).unwrap();
+        assert_eq!(response.users.len(), 2);
+        let mut request = ListUsersRequest::new(10)
+            .id_prefix("test_user_")
+            .include_disabled()
+            .into_request();
+        request.metadata = admin.clone();
+        let response = service.list_users(request).unwrap();
+        assert_eq!(response.users.len(), 3);
+        assert!(response.users[0].disabled);
+
+        // Reset passwords replace the old one.
+        let user_b = login("test_user_b", "test_password").unwrap();
+        let mut request = ResetPasswordRequest::new("test_user_b", "new_password").into_request();
+        request.metadata = admin.clone();
+        assert!(service.reset_password(request).is_ok());
+        assert!(login("test_user_b", "test_password").is_err());
+        assert!(login("test_user_b", "new_password").is_ok());
+        let mut request = RevokeTokenRequest::new().into_request();
+        request.metadata = user_b;
+        assert!(service.revoke_token(request).is_err());
+
+        // Ids of deleted users cannot be registered again.
+        let mut request = DeleteUserRequest::new("test_user_c").into_request();
+        request.metadata = admin.clone();
+        assert!(service.delete_user(request).is_ok());
+        assert!(service.user_resources.is_disabled("test_user_c"));
+        assert!(login("test_user_c", "test_password").is_err());
+        let request = UserRegisterRequest::new("test_user_c", "test_password").into_request();
+        assert!(service.user_register(request).is_err());
+        let mut request = ResetPasswordRequest::new("test_user_c", "new_password").into_request();
+        request.metadata = admin.clone();
+        assert!(service.reset_password(request).is_err());
+        let mut request = ListUsersRequest::new(10)
+            .id_prefix("test_user_")
+            .include_disabled()
+            .into_request();
+        request.metadata = admin;
+        let response = service.list_users(request).unwrap();
+        assert_eq!(response.users.len(), 2);
+    }
+
+    pub fn test_export_audit_log() {
+        let service = get_mock_service();
+        for id in &["test_admin_id", "test_user_a"] {
+            let request = UserRegisterRequest::new(*id, "test_password").into_request();
+            assert!(service.user_register(request).is_ok());
+        }
+        let login = |id: &str| {
+            let request = UserLoginRequest::new(id, "test_password").into_request();
+            let token = service.user_login(request).unwrap().token;
+            let mut metadata = std::collections::HashMap::new();
+            metadata.insert("id".to_string(), id.to_string());
+            metadata.insert("token".to_string(), token);
+            metadata
+        };
+
+        let mut request = ExportAuditLogRequest::new(0, 10).into_request();
+        request.metadata = login("test_user_a");
+        assert!(service.export_audit_log(request).is_err());
+
+        let admin = login("test_admin_id");
+        let mut request = ExportAuditLogRequest::new(0, 0).into_request();
+        request.metadata = admin.clone();
+        assert!(service.export_audit_log(request).is_err());
+        // The mock service only logs the events, so there is no log to export.
+        let mut request = ExportAuditLogRequest::new(0, 10).into_request();
+        request.metadata = admin;
+        assert!(service.export_audit_log(request).is_err());
+    }
+
+    pub fn test_grant_impersonation() {
+        let service = get_mock_service();
+        let request = UserRegisterRequest::new("test_consent_id", "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+        let request = UserLoginRequest::new("test_consent_id", "test_password").into_request();
+        let token = service.user_login(request).unwrap().token;
+
+        let validity = Duration::from_secs(600);
+        let mut request = GrantImpersonationRequest::new("test_admin_id", validity).into_request();
+        request
+            .metadata
+            .insert("id".to_string(), "test_consent_id".to_string());
+        request.metadata.insert("token".to_string(), token.clone());
+        let consent_token = service.grant_impersonation(request).unwrap().consent_token;
+        let user_id = service
+            .impersonation
+            .validate("test_admin_id", &consent_token)
+            .unwrap();
+        assert_eq!(user_id, "test_consent_id");
+
+        // The consent token is neither a login token of the user nor valid for
+        // another platform admin.
+        let user = service.db_client.get_user("test_consent_id").unwrap();
+        assert!(!user.validate_token(&service.jwt_secret, &consent_token));
+        assert!(service
+            .impersonation
+            .validate("test_consent_id", &consent_token)
+            .is_err());
+
+        // Only platform admins can be granted, for at most the maximum validity.
+        for (admin_id, validity) in &[
+            ("test_consent_id", validity),
+            ("test_admin_id", Duration::from_secs(3601)),
+        ] {
+            let mut request = GrantImpersonationRequest::new(*admin_id, *validity).into_request();
+            request
+                .metadata
+                .insert("id".to_string(), "test_consent_id".to_string());
+            request.metadata.insert("token".to_string(), token.clone());
+            assert!(service.grant_impersonation(request).is_err());
+        }
+    }
+
+    pub fn test_read_only_mode() {
+        let service = get_mock_service();
+        let request = UserRegisterRequest::new("test_admin_id", "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+        let request = UserLoginRequest::new("test_admin_id", "test_password").into_request();
+        let token = service.user_login(request).unwrap().token;
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("id".to_string(), "test_admin_id".to_string());
+        metadata.insert("token".to_string(), token);
+
+        let now = platform::time::since_epoch().as_secs();
+        service
+            .read_only
+            .enter(ReadOnlyWindow::new("test_admin_id", "migration", 60, now).unwrap());
+
+        let request = UserRegisterRequest::new("test_read_only_id", "test_password").into_request();
+        assert!(service.user_register(request).is_err());
+        let scopes = vec!["get_task".to_string()];
+        let mut request = CreateApiKeyRequest::new("ci", scopes).into_request();
+        request.metadata = metadata.clone();
+        assert!(service.create_api_key(request).is_err());
+        let permissions = vec![Permission::ReadAnyOutput];
+        let mut request = CreateRoleRequest::new("auditor", permissions).into_request();
+        request.metadata = metadata.clone();
+        assert!(service.create_role(request).is_err());
+        let roles = vec!["admin".to_string()];
+        let mut request = AssignRolesRequest::new("test_admin_id", roles).into_request();
+        request.metadata = metadata.clone();
+        assert!(service.assign_roles(request).is_err());
+        let mut request = ResetPasswordRequest::new("test_admin_id", "new_password").into_request();
+        request.metadata = metadata.clone();
+        assert!(service.reset_password(request).is_err());
+
+        // Logins are still served.
+        let request = UserLoginRequest::new("test_admin_id", "test_password").into_request();
+        assert!(service.user_login(request).is_ok());
+
+        // An expired window no longer rejects writes.
+        service
+            .read_only
+            .enter(ReadOnlyWindow::new("test_admin_id", "migration", 60, now - 60).unwrap());
+        let request = UserRegisterRequest::new("test_read_only_id", "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+    }
 }
@@ -0,0 +1,172 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Argon2id password hashing (RFC 9106, version 0x13) with the rust-argon2
+// crate vendored in common/rust_argon2_sgx. Lanes are filled one after
+// another in the enclave.
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use teaclave_config::PasswordHashingConfig;
+
+// Floor of the parameters of new hashes, as the runtime config is not
+// trusted (OWASP minimum for Argon2id).
+const MIN_MEMORY_KIB: u32 = 19 * 1024;
+const MIN_ITERATIONS: u32 = 2;
+// Memory of the hashes computed at once, bounding their share of the heap of
+// the enclave (HeapMaxSize of Enclave.config.xml).
+const MAX_HASHING_MEMORY_KIB: u32 = 96 * 1024;
+const HASHING_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static HASHING_MEMORY_KIB: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Argon2Params {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Params {
+    // Recommended by OWASP for Argon2id
+    fn default() -> Self {
+        Self {
+            memory_kib: MIN_MEMORY_KIB,
+            iterations: MIN_ITERATIONS,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Argon2Params {
+    pub(crate) fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self> {
+        ensure!(
+            (1..=0xff_ffff).contains(&parallelism),
+            "invalid Argon2 parallelism"
+        );
+        ensure!(
+            iterations >= MIN_ITERATIONS,
+            "Argon2 iterations must be at least {}",
+            MIN_ITERATIONS
+        );
+        ensure!(
+            memory_kib >= MIN_MEMORY_KIB && memory_kib >= 8 * parallelism,
+            "Argon2 memory must be at least {} KiB and 8 KiB per lane",
+            MIN_MEMORY_KIB
+        );
+        ensure!(
+            memory_kib <= MAX_HASHING_MEMORY_KIB,
+            "Argon2 memory must be at most {} KiB",
+            MAX_HASHING_MEMORY_KIB
+        );
+        Ok(Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        })
+    }
+
+    pub(crate) fn from_config(config: &PasswordHashingConfig) -> Result<Self> {
+        Self::new(config.memory_kib, config.iterations, config.parallelism)
+    }
+}
+
+// Reservation of the memory of a hash, waiting while the hashes computed at
+// once would use more than MAX_HASHING_MEMORY_KIB.
+struct HashingMemory(u32);
+
+impl HashingMemory {
+    fn reserve(memory_kib: u32) -> Self {
+        // Parameters of stored hashes are not checked by Argon2Params::new.
+        let memory_kib = std::cmp::min(memory_kib, MAX_HASHING_MEMORY_KIB);
+        loop {
+            let reserved = HASHING_MEMORY_KIB.load(Ordering::SeqCst);
+            if reserved + memory_kib <= MAX_HASHING_MEMORY_KIB
+                && HASHING_MEMORY_KIB
+                    .compare_exchange(
+                        reserved,
+                        reserved + memory_kib,
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    )
+                    .is_ok()
+            {
+                return Self(memory_kib);
+            }
+            std::thread::sleep(HASHING_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for HashingMemory {
+    fn drop(&mut self) {
+        HASHING_MEMORY_KIB.fetch_sub(self.0, Ordering::SeqCst);
+    }
+}
+
+// Returns an empty hash, matching no password, for parameters rejected by
+// Argon2 (only possible for stored hashes).
+pub(crate) fn hash_password(
+    params: &Argon2Params,
+    password: &[u8],
+    salt: &[u8],
+    tag_len: usize,
+) -> Vec<u8> {
+    let config = ::argon2::Config {
+        variant: ::argon2::Variant::Argon2id,
+        version: ::argon2::Version::Version13,
+        mem_cost: params.memory_kib,
+        time_cost: params.iterations,
+        lanes: params.parallelism,
+        thread_mode: ::argon2::ThreadMode::Sequential,
+        secret: &[],
+        ad: &[],
+        hash_length: tag_len as u32,
+    };
+    let _memory = HashingMemory::reserve(params.memory_kib);
+    ::argon2::hash_raw(password, salt, &config).unwrap_or_default()
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_argon2id() {
+        // Test vector of the reference implementation
+        let params = Argon2Params::new(64 * 1024, 2, 1).unwrap();
+        let tag = hash_password(&params, b"password", b"somesalt", 32);
+        assert_eq!(
+            tag,
+            vec![
+                0x09, 0x31, 0x61, 0x15, 0xd5, 0xcf, 0x24, 0xed, 0x5a, 0x15, 0xa3, 0x1a, 0x3b, 0xa3,
+                0x26, 0xe5, 0xcf, 0x32, 0xed, 0xc2, 0x47, 0x02, 0x98, 0x7c, 0x02, 0xb6, 0x56, 0x6f,
+                0x61, 0x91, 0x3c, 0xf7,
+            ]
+        );
+        assert_eq!(HASHING_MEMORY_KIB.load(Ordering::SeqCst), 0);
+
+        // Below the floor
+        assert!(Argon2Params::new(8, 1, 1).is_err());
+        assert!(Argon2Params::new(MIN_MEMORY_KIB, 1, 1).is_err());
+        assert!(Argon2Params::new(MIN_MEMORY_KIB - 1, 2, 1).is_err());
+        assert!(Argon2Params::new(MAX_HASHING_MEMORY_KIB + 1, 2, 1).is_err());
+        assert!(Argon2Params::new(MIN_MEMORY_KIB, 2, 1).is_ok());
+    }
+}
@@ -30,6 +30,18 @@ pub(crate) enum TeaclaveAuthenticationApiError {
     InvalidPassword,
     #[error("service unavailable")]
     ServiceUnavailable,
+    #[error("invalid consent")]
+    InvalidConsent,
+    #[error("invalid API key")]
+    InvalidApiKey,
+    #[error("invalid role")]
+    InvalidRole,
+    #[error("TOTP code required")]
+    TotpRequired,
+    #[error("invalid request")]
+    InvalidRequest,
+    #[error("platform is in read-only mode until {0}: {1}")]
+    ReadOnlyMode(u64, String),
 }
 
 impl From<TeaclaveAuthenticationApiError> for TeaclaveServiceResponseError {
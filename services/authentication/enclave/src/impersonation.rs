@@ -0,0 +1,120 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::ldap::Ldap;
+use crate::user_info::{ISSUER_NAME, JWT_ALG};
+use anyhow::{ensure, Result};
+use jsonwebtoken as jwt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::prelude::v1::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::untrusted::time::SystemTimeEx;
+use teaclave_config::ImpersonationConfig;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ConsentClaims {
+    // id of the user granting the consent
+    pub sub: String,
+    // issuer
+    pub iss: String,
+    // expiration time
+    pub exp: u64,
+    // id of the platform admin acting as the user
+    pub act: String,
+}
+
+// Consent tokens are signed with their own secret, so that they cannot be
+// used as login tokens of the users.
+#[derive(Clone)]
+pub(crate) struct Impersonation {
+    secret: Vec<u8>,
+    platform_admins: HashSet<String>,
+    max_consent: Duration,
+    ldap: Option<Ldap>,
+}
+
+impl Impersonation {
+    // The platform admins come from the build config, as the runtime config
+    // is not trusted.
+    pub(crate) fn new(
+        secret: Vec<u8>,
+        platform_admins: &[&str],
+        config: &ImpersonationConfig,
+    ) -> Self {
+        Self {
+            secret,
+            platform_admins: platform_admins.iter().map(|id| id.to_string()).collect(),
+            max_consent: Duration::from_secs(config.max_consent_secs),
+            ldap: None,
+        }
+    }
+
+    // Members of the admin groups of the LDAP server are also platform admins
+    // while their groups are cached.
+    pub(crate) fn ldap(mut self, ldap: Option<Ldap>) -> Self {
+        self.ldap = ldap;
+        self
+    }
+
+    pub(crate) fn is_platform_admin(&self, id: &str) -> bool {
+        self.platform_admins.contains(id)
+            || self.ldap.as_ref().map_or(false, |ldap| ldap.is_admin(id))
+    }
+
+    // The ids of the platform admins are reserved, so that nobody can
+    // register them with a password in the in-memory user database.
+    pub(crate) fn is_reserved(&self, id: &str) -> bool {
+        self.platform_admins.contains(id)
+    }
+
+    // Issues a token allowing the platform admin to act as the user until the
+    // consent expires.
+    pub(crate) fn grant(
+        &self,
+        user_id: &str,
+        admin_id: &str,
+        validity: Duration,
+    ) -> Result<String> {
+        ensure!(self.is_platform_admin(admin_id), "not a platform admin");
+        ensure!(
+            validity > Duration::default() && validity <= self.max_consent,
+            "invalid consent validity"
+        );
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let claims = ConsentClaims {
+            sub: user_id.to_string(),
+            iss: ISSUER_NAME.to_string(),
+            exp: (now + validity).as_secs(),
+            act: admin_id.to_string(),
+        };
+        let mut header = jwt::Header::default();
+        header.alg = JWT_ALG;
+        let token = jwt::encode(&header, &claims, &self.secret)?;
+        Ok(token)
+    }
+
+    // Returns the id of the user who granted the platform admin the consent.
+    pub(crate) fn validate(&self, admin_id: &str, consent_token: &str) -> Result<String> {
+        ensure!(self.is_platform_admin(admin_id), "not a platform admin");
+        let mut validation = jwt::Validation::new(JWT_ALG);
+        validation.iss = Some(ISSUER_NAME.to_string());
+        let claims = jwt::decode::<ConsentClaims>(consent_token, &self.secret, &validation)?.claims;
+        ensure!(claims.act == admin_id, "consent granted to another user");
+        Ok(claims.sub)
+    }
+}
@@ -15,28 +15,86 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::api_key::{self, ApiKeyStore};
+use crate::impersonation::Impersonation;
+use crate::revocation::TokenRevocations;
+use crate::role::Roles;
 use crate::user_db::DbClient;
 use crate::user_info::UserInfo;
 use std::prelude::v1::*;
 use teaclave_proto::teaclave_authentication_service::{
-    TeaclaveAuthenticationInternal, UserAuthenticateRequest, UserAuthenticateResponse,
+    HealthRequest, HealthResponse, ImpersonationAuthenticateRequest,
+    ImpersonationAuthenticateResponse, TeaclaveAuthenticationInternal, UserAuthenticateRequest,
+    UserAuthenticateResponse,
 };
+use teaclave_proto::teaclave_common::UserCredential;
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::teaclave_service;
-use teaclave_types::TeaclaveServiceResponseResult;
+use teaclave_service_enclave_utils::audit::AuditRecorder;
+use teaclave_service_enclave_utils::{health, teaclave_service};
+use teaclave_types::{AuditEventKind, Permission, TeaclaveServiceResponseResult};
 
 #[teaclave_service(teaclave_authentication_service, TeaclaveAuthenticationInternal)]
 #[derive(Clone)]
 pub(crate) struct TeaclaveAuthenticationInternalService {
     db_client: DbClient,
     jwt_secret: Vec<u8>,
+    impersonation: Impersonation,
+    api_keys: ApiKeyStore,
+    revocations: TokenRevocations,
+    roles: Roles,
+    audit: AuditRecorder,
 }
 
 impl TeaclaveAuthenticationInternalService {
-    pub(crate) fn new(db_client: DbClient, jwt_secret: Vec<u8>) -> Self {
+    pub(crate) fn new(
+        db_client: DbClient,
+        jwt_secret: Vec<u8>,
+        impersonation: Impersonation,
+        api_keys: ApiKeyStore,
+        revocations: TokenRevocations,
+        roles: Roles,
+        audit: AuditRecorder,
+    ) -> Self {
         Self {
             db_client,
             jwt_secret,
+            impersonation,
+            api_keys,
+            revocations,
+            roles,
+            audit,
+        }
+    }
+
+    // The token is either a login token which is not revoked, or an API key
+    // of the user whose scopes allow the operation.
+    fn authenticate(&self, credential: &UserCredential, operation: &str) -> Option<UserInfo> {
+        if credential.id.is_empty() || credential.token.is_empty() {
+            return None;
+        }
+        let user: UserInfo = self.db_client.get_user(&credential.id).ok()?;
+        if !user.is_active() {
+            return None;
+        }
+        let accept = if api_key::is_api_key(&credential.token) {
+            self.api_keys
+                .authenticate(&user, &credential.token, operation)
+        } else {
+            self.revocations
+                .validate_user_credential(&user, &self.jwt_secret, &credential.token)
+                .is_ok()
+        };
+        if accept {
+            Some(user)
+        } else {
+            None
+        }
+    }
+
+    fn is_active_user(&self, id: &str) -> bool {
+        match self.db_client.get_user(id) {
+            Ok(user) => user.is_active(),
+            Err(_) => false,
         }
     }
 }
@@ -47,40 +105,110 @@ impl TeaclaveAuthenticationInternal for TeaclaveAuthenticationInternalService {
         request: Request<UserAuthenticateRequest>,
     ) -> TeaclaveServiceResponseResult<UserAuthenticateResponse> {
         let request = request.message;
-        if request.credential.id.is_empty() || request.credential.token.is_empty() {
-            return Ok(UserAuthenticateResponse::new(false));
-        }
-        let user: UserInfo = match self.db_client.get_user(&request.credential.id) {
-            Ok(value) => value,
-            Err(_) => return Ok(UserAuthenticateResponse::new(false)),
+        let user = match self.authenticate(&request.credential, &request.operation) {
+            Some(user) => user,
+            None => return Ok(UserAuthenticateResponse::new(false)),
         };
-        let accept = user.validate_token(&self.jwt_secret, &request.credential.token);
-        Ok(UserAuthenticateResponse::new(accept))
+        // The roles of the user must also grant the operation.
+        let is_platform_admin = self.impersonation.is_platform_admin(&user.id);
+        let permissions = self.roles.permissions(&user, is_platform_admin);
+        if let Some(required) = Permission::required_for(&request.operation) {
+            if !permissions.contains(&required) {
+                return Ok(UserAuthenticateResponse::new(false));
+            }
+        }
+        Ok(UserAuthenticateResponse::new(true).permissions(permissions))
+    }
+
+    // Accepts a platform admin presenting a valid consent token, and returns
+    // the id of the user who granted the consent.
+    fn impersonation_authenticate(
+        &self,
+        request: Request<ImpersonationAuthenticateRequest>,
+    ) -> TeaclaveServiceResponseResult<ImpersonationAuthenticateResponse> {
+        let request = request.message;
+        let admin_id = &request.credential.id;
+        // Platform admins cannot impersonate with scoped API keys.
+        if self
+            .authenticate(&request.credential, api_key::ALL_SCOPES)
+            .is_none()
+        {
+            return Ok(ImpersonationAuthenticateResponse::new(false, ""));
+        }
+        match self
+            .impersonation
+            .validate(admin_id, &request.consent_token)
+        {
+            // Disabled users cannot be impersonated.
+            Ok(user_id) if self.is_active_user(&user_id) => {
+                self.audit.record(
+                    AuditEventKind::Impersonated,
+                    admin_id,
+                    format!("acting as user {}: {}", user_id, request.operation),
+                );
+                Ok(ImpersonationAuthenticateResponse::new(true, user_id))
+            }
+            _ => {
+                self.audit.record(
+                    AuditEventKind::ImpersonationDenied,
+                    admin_id,
+                    format!("denied impersonation: {}", request.operation),
+                );
+                Ok(ImpersonationAuthenticateResponse::new(false, ""))
+            }
+        }
+    }
+
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        Ok(HealthResponse::new(vec![
+            health::attestation_check(),
+            self.db_client.health_check(),
+            self.api_keys.health_check(),
+        ]))
     }
 }
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
+    use crate::argon2::Argon2Params;
     use crate::user_db::*;
     use crate::user_info::*;
-    use rand::RngCore;
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
     use std::untrusted::time::SystemTimeEx;
     use std::vec;
-    use teaclave_proto::teaclave_common::UserCredential;
+    use teaclave_config::ImpersonationConfig;
     use teaclave_rpc::IntoRequest;
+    use teaclave_types::platform;
 
     fn get_mock_service() -> TeaclaveAuthenticationInternalService {
         let database = Database::open().unwrap();
         let mut jwt_secret = vec![0; JWT_SECRET_LEN];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut jwt_secret);
-        let user = UserInfo::new("test_authenticate_id", "test_authenticate_id");
+        platform::rand::fill_bytes(&mut jwt_secret);
+        let mut consent_secret = vec![0; JWT_SECRET_LEN];
+        platform::rand::fill_bytes(&mut consent_secret);
+        let config = ImpersonationConfig {
+            max_consent_secs: 3600,
+        };
+        let user = UserInfo::new(
+            "test_authenticate_id",
+            "test_authenticate_id",
+            &Argon2Params::default(),
+        );
         database.get_client().create_user(&user).unwrap();
+        let admin = UserInfo::new("test_admin_id", "test_admin_id", &Argon2Params::default());
+        database.get_client().create_user(&admin).unwrap();
         TeaclaveAuthenticationInternalService {
             db_client: database.get_client(),
             jwt_secret,
+            impersonation: Impersonation::new(consent_secret, &["test_admin_id"], &config),
+            api_keys: ApiKeyStore::in_memory(),
+            revocations: TokenRevocations::new(),
+            roles: Roles::new(),
+            audit: AuditRecorder::log_only("teaclave_authentication_service"),
         }
     }
 
@@ -91,7 +219,7 @@ pub mod tests {
 
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
         let exp = (now + Duration::from_secs(24 * 60)).as_secs();
-        let token = user.get_token(exp, &service.jwt_secret).unwrap();
+        let token = user.get_token(exp, 0, &service.jwt_secret).unwrap();
 
         let response = get_authenticate_response(id, &token, &service);
         assert!(response.accept);
@@ -99,6 +227,158 @@ pub mod tests {
         debug!("valid token: {:?}", token.unwrap());
     }
 
+    pub fn test_role_authenticate() {
+        let id = "test_authenticate_id";
+        let service = get_mock_service();
+        let my_claims = get_correct_claim(id);
+        let token = gen_token(my_claims, None, &service.jwt_secret);
+        let credential = UserCredential::new(id, &token);
+        let request = UserAuthenticateRequest::new(credential).operation("invoke_task");
+        let response = service.user_authenticate(request.into_request()).unwrap();
+        assert!(response.accept);
+        assert_eq!(response.permissions, Permission::DEFAULT.to_vec());
+
+        // A role without the permission denies the operation.
+        service
+            .roles
+            .create("auditor", &[Permission::ReadAnyOutput])
+            .unwrap();
+        let mut user = service.db_client.get_user(id).unwrap();
+        user.roles = vec!["auditor".to_string()];
+        service.db_client.update_user(&user).unwrap();
+        let credential = UserCredential::new(id, &token);
+        let request = UserAuthenticateRequest::new(credential).operation("invoke_task");
+        let response = service.user_authenticate(request.into_request()).unwrap();
+        assert!(!response.accept);
+        let credential = UserCredential::new(id, &token);
+        let request = UserAuthenticateRequest::new(credential).operation("get_task");
+        let response = service.user_authenticate(request.into_request()).unwrap();
+        assert!(response.accept);
+        assert_eq!(response.permissions, vec![Permission::ReadAnyOutput]);
+    }
+
+    pub fn test_revoked_token_authenticate() {
+        let id = "test_authenticate_id";
+        let service = get_mock_service();
+        let my_claims = get_correct_claim(id);
+        let token = gen_token(my_claims, None, &service.jwt_secret);
+        let response = get_authenticate_response(id, &token, &service);
+        assert!(response.accept);
+
+        let claims = validate_token(id, &service.jwt_secret, &token)
+            .unwrap()
+            .claims;
+        service.revocations.revoke(&claims);
+        let response = get_authenticate_response(id, &token, &service);
+        assert!(!response.accept);
+    }
+
+    pub fn test_api_key_authenticate() {
+        let id = "test_authenticate_id";
+        let service = get_mock_service();
+        let user = service.db_client.get_user(id).unwrap();
+        let scopes = vec!["get_task".to_string()];
+        let (api_key, encoded) = api_key::ApiKey::new(&user, "test_key", scopes).unwrap();
+        service.api_keys.put(&api_key).unwrap();
+
+        let credential = UserCredential::new(id, &encoded);
+        let request = UserAuthenticateRequest::new(credential).operation("get_task");
+        let response = service.user_authenticate(request.into_request()).unwrap();
+        assert!(response.accept);
+        let credential = UserCredential::new(id, &encoded);
+        let request = UserAuthenticateRequest::new(credential).operation("invoke_task");
+        let response = service.user_authenticate(request.into_request()).unwrap();
+        assert!(!response.accept);
+
+        // The id registered again, e.g., after a restart, gets a new epoch.
+        let user = UserInfo::new(id, "test_password", &Argon2Params::default());
+        service.db_client.update_user(&user).unwrap();
+        let credential = UserCredential::new(id, &encoded);
+        let request = UserAuthenticateRequest::new(credential).operation("get_task");
+        let response = service.user_authenticate(request.into_request()).unwrap();
+        assert!(!response.accept);
+    }
+
+    pub fn test_impersonation_authenticate() {
+        let id = "test_authenticate_id";
+        let admin_id = "test_admin_id";
+        let service = get_mock_service();
+        let admin = service.db_client.get_user(admin_id).unwrap();
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let exp = (now + Duration::from_secs(24 * 60)).as_secs();
+        let admin_token = admin.get_token(exp, 0, &service.jwt_secret).unwrap();
+        let consent_token = service
+            .impersonation
+            .grant(id, admin_id, Duration::from_secs(60))
+            .unwrap();
+
+        let credential = UserCredential::new(admin_id, &admin_token);
+        let request = ImpersonationAuthenticateRequest::new(credential, &consent_token);
+        let response = service
+            .impersonation_authenticate(request.into_request())
+            .unwrap();
+        assert!(response.accept);
+        assert_eq!(response.user_id, id);
+
+        // The consent token alone does not authenticate the platform admin.
+        let credential = UserCredential::new(admin_id, "wrong token");
+        let request = ImpersonationAuthenticateRequest::new(credential, &consent_token);
+        let response = service
+            .impersonation_authenticate(request.into_request())
+            .unwrap();
+        assert!(!response.accept);
+
+        let credential = UserCredential::new(admin_id, &admin_token);
+        let request = ImpersonationAuthenticateRequest::new(credential, "wrong consent");
+        let response = service
+            .impersonation_authenticate(request.into_request())
+            .unwrap();
+        assert!(!response.accept);
+    }
+
+    pub fn test_disabled_user_authenticate() {
+        let id = "test_authenticate_id";
+        let admin_id = "test_admin_id";
+        let service = get_mock_service();
+        let token = gen_token(get_correct_claim(id), None, &service.jwt_secret);
+        let user = service.db_client.get_user(id).unwrap();
+        let (api_key, encoded) =
+            api_key::ApiKey::new(&user, "test_key", vec![api_key::ALL_SCOPES.to_string()]).unwrap();
+        service.api_keys.put(&api_key).unwrap();
+        let admin_token = gen_token(get_correct_claim(admin_id), None, &service.jwt_secret);
+        let consent_token = service
+            .impersonation
+            .grant(id, admin_id, Duration::from_secs(60))
+            .unwrap();
+
+        let mut user = service.db_client.get_user(id).unwrap();
+        user.status = UserStatus::Disabled;
+        service.db_client.update_user(&user).unwrap();
+
+        let response = get_authenticate_response(id, &token, &service);
+        assert!(!response.accept);
+        let response = get_authenticate_response(id, &encoded, &service);
+        assert!(!response.accept);
+        let credential = UserCredential::new(admin_id, &admin_token);
+        let request = ImpersonationAuthenticateRequest::new(credential, &consent_token);
+        let response = service
+            .impersonation_authenticate(request.into_request())
+            .unwrap();
+        assert!(!response.accept);
+    }
+
+    pub fn test_health() {
+        let service = get_mock_service();
+        let response = service.health(HealthRequest::new().into_request()).unwrap();
+        let database = response
+            .checks
+            .iter()
+            .find(|check| check.name == "database")
+            .unwrap();
+        assert!(database.healthy);
+    }
+
     pub fn test_invalid_algorithm() {
         let id = "test_authenticate_id";
         let service = get_mock_service();
@@ -190,6 +470,8 @@ pub mod tests {
             sub: id.to_string(),
             iss: ISSUER_NAME.to_string(),
             exp: now + 24 * 60,
+            jti: platform::rand::new_uuid().to_string(),
+            gen: 0,
         }
     }
 
@@ -0,0 +1,208 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Without the `ldap` feature, LDAP users cannot log in.
+#![cfg_attr(not(feature = "ldap"), allow(dead_code))]
+
+use anyhow::{anyhow, ensure, Result};
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxRwLock as RwLock};
+use std::time::{Duration, SystemTime};
+use teaclave_config::build::{LdapDirectory, LDAP_DIRECTORY};
+use teaclave_config::LdapConfig;
+use teaclave_types::platform;
+
+#[cfg(feature = "ldap")]
+mod protocol;
+
+const USER_ID_PREFIX: &str = "ldap:";
+const MAX_USERNAME_LEN: usize = 256;
+
+// Usernames are inserted into DNs, so characters with a meaning in DNs
+// (RFC 4514) are rejected instead of escaped.
+fn is_valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.len() <= MAX_USERNAME_LEN
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' || c == '@')
+}
+
+struct CachedGroups {
+    groups: Vec<String>,
+    expiry: SystemTime,
+}
+
+// Login of the users of an LDAP or Active Directory server, which validates
+// their passwords with a bind. The users are mapped to Teaclave users with the
+// id "ldap:<username>", so these ids cannot be registered with passwords. The
+// groups of a user are read on login and cached, granting the platform admin
+// role to members of the admin groups until the cache expires. Only the URL
+// of the server is in the runtime config, the rest is pinned in the build
+// config.
+#[derive(Clone)]
+pub(crate) struct Ldap {
+    url: String,
+    directory: &'static LdapDirectory,
+    tls_config: Arc<rustls::ClientConfig>,
+    groups: Arc<RwLock<HashMap<String, CachedGroups>>>,
+}
+
+impl Ldap {
+    pub(crate) fn new(config: &LdapConfig, directory: &'static LdapDirectory) -> Result<Self> {
+        let mut tls_config = rustls::ClientConfig::new();
+        for cert in directory.root_ca_certs {
+            tls_config
+                .root_store
+                .add(&rustls::Certificate(cert.to_vec()))
+                .map_err(|_| anyhow!("invalid LDAP root CA certificate"))?;
+        }
+        Ok(Self {
+            url: config.url.clone(),
+            directory,
+            tls_config: Arc::new(tls_config),
+            groups: Arc::new(RwLock::new(HashMap::new())),
+        })
+    }
+
+    // The server is authenticated with the CA certificates in the build
+    // config, as the runtime config is not trusted.
+    pub(crate) fn from_config(config: Option<&LdapConfig>) -> Result<Option<Self>> {
+        match config {
+            Some(config) => {
+                ensure!(
+                    cfg!(feature = "ldap"),
+                    "LDAP login is not built into the authentication service"
+                );
+                ensure!(
+                    !LDAP_DIRECTORY.root_ca_certs.is_empty(),
+                    "no LDAP root CA certificate in the build config"
+                );
+                Ok(Some(Self::new(config, LDAP_DIRECTORY)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn is_reserved(user_id: &str) -> bool {
+        user_id.starts_with(USER_ID_PREFIX)
+    }
+
+    fn user_dn(&self, user_id: &str) -> Result<String> {
+        ensure!(Self::is_reserved(user_id), "not an LDAP user");
+        let username = &user_id[USER_ID_PREFIX.len()..];
+        ensure!(is_valid_username(username), "invalid username");
+        Ok(self
+            .directory
+            .user_dn_template
+            .replace("{username}", username))
+    }
+
+    // Validates the password of the user, and caches its groups.
+    #[cfg(feature = "ldap")]
+    pub(crate) fn authenticate(&self, user_id: &str, password: &str) -> Result<()> {
+        let dn = self.user_dn(user_id)?;
+        let mut session = protocol::connect(&self.url, &self.tls_config)?;
+        session.bind(&dn, password)?;
+        let groups = session.groups(&dn, self.directory.group_attribute);
+        session.unbind();
+        self.cache_groups(user_id, groups?);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "ldap"))]
+    pub(crate) fn authenticate(&self, _user_id: &str, _password: &str) -> Result<()> {
+        anyhow::bail!("LDAP login is not built into the authentication service")
+    }
+
+    fn cache_groups(&self, user_id: &str, groups: Vec<String>) {
+        let expiry = platform::time::now() + Duration::from_secs(self.directory.group_cache_secs);
+        let mut cache = match self.groups.write() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let now = platform::time::now();
+        cache.retain(|_, cached| cached.expiry > now);
+        cache.insert(user_id.to_string(), CachedGroups { groups, expiry });
+    }
+
+    pub(crate) fn is_admin(&self, user_id: &str) -> bool {
+        let cache = match self.groups.read() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match cache.get(user_id) {
+            Some(cached) if cached.expiry > platform::time::now() => cached
+                .groups
+                .iter()
+                .any(|group| self.directory.admin_groups.contains(&group.as_str())),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub use super::protocol::tests::test_ldap_bind;
+
+    const ADMIN_GROUP: &str = "cn=admins,ou=groups,dc=example,dc=com";
+    const DIRECTORY: LdapDirectory = LdapDirectory {
+        root_ca_certs: &[],
+        user_dn_template: "uid={username},ou=people,dc=example,dc=com",
+        group_attribute: "memberOf",
+        admin_groups: &[ADMIN_GROUP],
+        group_cache_secs: 3600,
+    };
+
+    fn get_mock_ldap() -> Ldap {
+        let config = LdapConfig {
+            url: "ldaps://ldap.example.com".to_string(),
+        };
+        Ldap::new(&config, &DIRECTORY).unwrap()
+    }
+
+    pub fn test_ldap_user_dn() {
+        let ldap = get_mock_ldap();
+        assert_eq!(
+            ldap.user_dn("ldap:alice").unwrap(),
+            "uid=alice,ou=people,dc=example,dc=com"
+        );
+        for user_id in &[
+            "alice",
+            "ldap:",
+            "ldap:alice,ou=admins",
+            "ldap:*",
+            "ldap:a\\2c",
+        ] {
+            assert!(ldap.user_dn(user_id).is_err());
+        }
+    }
+
+    pub fn test_ldap_admin_groups() {
+        let ldap = get_mock_ldap();
+        assert!(!ldap.is_admin("ldap:alice"));
+        ldap.cache_groups("ldap:alice", vec![ADMIN_GROUP.to_string()]);
+        ldap.cache_groups("ldap:bob", vec!["cn=users".to_string()]);
+        assert!(ldap.is_admin("ldap:alice"));
+        assert!(!ldap.is_admin("ldap:bob"));
+        // The cache is shared by the clones in the API and internal services.
+        assert!(ldap.clone().is_admin("ldap:alice"));
+    }
+}
@@ -0,0 +1,354 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The LDAPv3 messages (RFC 4511) used for login, in a minimal BER encoding,
+//! only built with the `ldap` feature.
+
+use anyhow::{anyhow, bail, ensure, Result};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+const LDAPS_PORT: u16 = 636;
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Responses are only a bind result and one entry with its groups.
+const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+mod ber {
+    use anyhow::{ensure, Result};
+    use std::prelude::v1::*;
+
+    pub(super) const BOOLEAN: u8 = 0x01;
+    pub(super) const INTEGER: u8 = 0x02;
+    pub(super) const OCTET_STRING: u8 = 0x04;
+    pub(super) const ENUMERATED: u8 = 0x0a;
+    pub(super) const SEQUENCE: u8 = 0x30;
+    pub(super) const SET: u8 = 0x31;
+
+    pub(super) fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut output = vec![tag];
+        let len = content.len();
+        if len < 0x80 {
+            output.push(len as u8);
+        } else {
+            let bytes = (len as u64).to_be_bytes();
+            let skip = bytes.iter().take_while(|b| **b == 0).count();
+            output.push(0x80 | (bytes.len() - skip) as u8);
+            output.extend_from_slice(&bytes[skip..]);
+        }
+        output.extend_from_slice(content);
+        output
+    }
+
+    pub(super) fn encode_integer(tag: u8, value: u32) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let mut start = 0;
+        while start < bytes.len() - 1 && bytes[start] == 0 && bytes[start + 1] & 0x80 == 0 {
+            start += 1;
+        }
+        encode(tag, &bytes[start..])
+    }
+
+    // Returns the length of the header and of the content of an element,
+    // once the input holds its whole header.
+    pub(super) fn header(input: &[u8]) -> Option<(usize, usize)> {
+        let first = *input.get(1)?;
+        if first & 0x80 == 0 {
+            return Some((2, first as usize));
+        }
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 {
+            // Indefinite or oversized lengths are not used by LDAP.
+            return Some((2, usize::max_value()));
+        }
+        let bytes = input.get(2..2 + count)?;
+        let len = bytes.iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+        Some((2 + count, len))
+    }
+
+    // Splits the first element of the input into its tag and content, and
+    // returns the rest of the input.
+    pub(super) fn decode(input: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+        let (header_len, len) = header(input).ok_or_else(|| anyhow::anyhow!("truncated"))?;
+        ensure!(input.len() - header_len >= len, "truncated");
+        let content = &input[header_len..header_len + len];
+        Ok((input[0], content, &input[header_len + len..]))
+    }
+
+    pub(super) fn decode_expected(tag: u8, input: &[u8]) -> Result<(&[u8], &[u8])> {
+        let (actual, content, rest) = decode(input)?;
+        ensure!(actual == tag, "unexpected tag {:#x}", actual);
+        Ok((content, rest))
+    }
+
+    pub(super) fn decode_integer(content: &[u8]) -> Result<u32> {
+        ensure!(!content.is_empty() && content.len() <= 4, "invalid integer");
+        ensure!(content[0] & 0x80 == 0, "negative integer");
+        Ok(content.iter().fold(0, |value, b| (value << 8) | *b as u32))
+    }
+}
+
+// Tags of the protocol operations.
+const BIND_REQUEST: u8 = 0x60;
+const BIND_RESPONSE: u8 = 0x61;
+const UNBIND_REQUEST: u8 = 0x42;
+const SEARCH_REQUEST: u8 = 0x63;
+const SEARCH_RESULT_ENTRY: u8 = 0x64;
+const SEARCH_RESULT_DONE: u8 = 0x65;
+const SEARCH_RESULT_REFERENCE: u8 = 0x73;
+const SIMPLE_AUTHENTICATION: u8 = 0x80;
+const PRESENT_FILTER: u8 = 0x87;
+
+const SUCCESS: u32 = 0;
+
+pub(super) struct LdapSession<S: Read + Write> {
+    stream: S,
+    message_id: u32,
+}
+
+impl<S: Read + Write> LdapSession<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream,
+            message_id: 0,
+        }
+    }
+
+    fn request(&mut self, operation: Vec<u8>) -> Result<u32> {
+        self.message_id += 1;
+        let mut content = ber::encode_integer(ber::INTEGER, self.message_id);
+        content.extend(operation);
+        self.stream
+            .write_all(&ber::encode(ber::SEQUENCE, &content))?;
+        self.stream.flush()?;
+        Ok(self.message_id)
+    }
+
+    fn read_message(&mut self) -> Result<Vec<u8>> {
+        let mut message = vec![0; 2];
+        self.stream.read_exact(&mut message)?;
+        let (header_len, len) = loop {
+            if let Some(header) = ber::header(&message) {
+                break header;
+            }
+            let mut byte = [0; 1];
+            self.stream.read_exact(&mut byte)?;
+            message.push(byte[0]);
+        };
+        ensure!(len <= MAX_MESSAGE_LEN, "message too large");
+        message.resize(header_len + len, 0);
+        self.stream.read_exact(&mut message[header_len..])?;
+        Ok(message)
+    }
+
+    // Returns the tag and the content of the operation in the response.
+    fn response(&mut self, message_id: u32) -> Result<(u8, Vec<u8>)> {
+        let message = self.read_message()?;
+        let (content, _) = ber::decode_expected(ber::SEQUENCE, &message)?;
+        let (id, operation) = ber::decode_expected(ber::INTEGER, content)?;
+        ensure!(
+            ber::decode_integer(id)? == message_id,
+            "unexpected message id"
+        );
+        let (tag, operation, _) = ber::decode(operation)?;
+        Ok((tag, operation.to_vec()))
+    }
+
+    pub(super) fn bind(&mut self, dn: &str, password: &str) -> Result<()> {
+        // A simple bind without a password is an unauthenticated bind,
+        // which succeeds for any DN.
+        ensure!(!password.is_empty(), "empty password");
+        let mut operation = ber::encode_integer(ber::INTEGER, 3);
+        operation.extend(ber::encode(ber::OCTET_STRING, dn.as_bytes()));
+        operation.extend(ber::encode(SIMPLE_AUTHENTICATION, password.as_bytes()));
+        let id = self.request(ber::encode(BIND_REQUEST, &operation))?;
+        let (tag, result) = self.response(id)?;
+        ensure!(tag == BIND_RESPONSE, "unexpected response {:#x}", tag);
+        ensure!(result_code(&result)? == SUCCESS, "invalid credentials");
+        Ok(())
+    }
+
+    // Reads the values of the group attribute of the entry.
+    pub(super) fn groups(&mut self, dn: &str, attribute: &str) -> Result<Vec<String>> {
+        let mut operation = ber::encode(ber::OCTET_STRING, dn.as_bytes());
+        // base object scope, never dereference aliases, no size limit
+        operation.extend(ber::encode_integer(ber::ENUMERATED, 0));
+        operation.extend(ber::encode_integer(ber::ENUMERATED, 0));
+        operation.extend(ber::encode_integer(ber::INTEGER, 0));
+        operation.extend(ber::encode_integer(ber::INTEGER, TIMEOUT.as_secs() as u32));
+        operation.extend(ber::encode(ber::BOOLEAN, &[0]));
+        operation.extend(ber::encode(PRESENT_FILTER, b"objectClass"));
+        operation.extend(ber::encode(
+            ber::SEQUENCE,
+            &ber::encode(ber::OCTET_STRING, attribute.as_bytes()),
+        ));
+        let id = self.request(ber::encode(SEARCH_REQUEST, &operation))?;
+
+        let mut groups = Vec::new();
+        loop {
+            let (tag, result) = self.response(id)?;
+            match tag {
+                SEARCH_RESULT_ENTRY => groups.extend(entry_values(&result, attribute)?),
+                SEARCH_RESULT_REFERENCE => (),
+                SEARCH_RESULT_DONE => {
+                    ensure!(result_code(&result)? == SUCCESS, "search failed");
+                    return Ok(groups);
+                }
+                _ => bail!("unexpected response {:#x}", tag),
+            }
+        }
+    }
+
+    pub(super) fn unbind(&mut self) {
+        // The server closes the connection without a response.
+        let _ = self.request(ber::encode(UNBIND_REQUEST, &[]));
+    }
+}
+
+pub(super) fn connect(
+    url: &str,
+    tls_config: &Arc<rustls::ClientConfig>,
+) -> Result<LdapSession<rustls::StreamOwned<rustls::ClientSession, TcpStream>>> {
+    let url = url::Url::parse(url)?;
+    let host = url.host_str().ok_or_else(|| anyhow!("invalid LDAP URL"))?;
+    let dns_name =
+        webpki::DNSNameRef::try_from_ascii_str(host).map_err(|_| anyhow!("invalid LDAP host"))?;
+    let client = rustls::ClientSession::new(tls_config, dns_name);
+    let addrs = url.socket_addrs(|| Some(LDAPS_PORT))?;
+    let socket = TcpStream::connect(&*addrs)?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.set_write_timeout(Some(TIMEOUT))?;
+    Ok(LdapSession::new(rustls::StreamOwned::new(client, socket)))
+}
+
+fn result_code(result: &[u8]) -> Result<u32> {
+    let (code, _) = ber::decode_expected(ber::ENUMERATED, result)?;
+    ber::decode_integer(code)
+}
+
+fn entry_values(entry: &[u8], attribute: &str) -> Result<Vec<String>> {
+    let (_object_name, rest) = ber::decode_expected(ber::OCTET_STRING, entry)?;
+    let (mut attributes, _) = ber::decode_expected(ber::SEQUENCE, rest)?;
+    let mut values = Vec::new();
+    while !attributes.is_empty() {
+        let (partial, rest) = ber::decode_expected(ber::SEQUENCE, attributes)?;
+        attributes = rest;
+        let (name, rest) = ber::decode_expected(ber::OCTET_STRING, partial)?;
+        if !String::from_utf8_lossy(name).eq_ignore_ascii_case(attribute) {
+            continue;
+        }
+        let (mut vals, _) = ber::decode_expected(ber::SET, rest)?;
+        while !vals.is_empty() {
+            let (value, rest) = ber::decode_expected(ber::OCTET_STRING, vals)?;
+            vals = rest;
+            values.push(String::from_utf8(value.to_vec())?);
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn message(id: u32, tag: u8, operation: &[u8]) -> Vec<u8> {
+        let mut content = ber::encode_integer(ber::INTEGER, id);
+        content.extend(ber::encode(tag, operation));
+        ber::encode(ber::SEQUENCE, &content)
+    }
+
+    fn result(code: u32) -> Vec<u8> {
+        let mut result = ber::encode_integer(ber::ENUMERATED, code);
+        result.extend(ber::encode(ber::OCTET_STRING, b""));
+        result.extend(ber::encode(ber::OCTET_STRING, b""));
+        result
+    }
+
+    fn entry(dn: &str, attribute: &str, values: &[&str]) -> Vec<u8> {
+        let vals: Vec<u8> = values
+            .iter()
+            .flat_map(|v| ber::encode(ber::OCTET_STRING, v.as_bytes()))
+            .collect();
+        let mut partial = ber::encode(ber::OCTET_STRING, attribute.as_bytes());
+        partial.extend(ber::encode(ber::SET, &vals));
+        let mut entry = ber::encode(ber::OCTET_STRING, dn.as_bytes());
+        entry.extend(ber::encode(
+            ber::SEQUENCE,
+            &ber::encode(ber::SEQUENCE, &partial),
+        ));
+        entry
+    }
+
+    fn get_mock_session(responses: Vec<Vec<u8>>) -> LdapSession<MockStream> {
+        LdapSession::new(MockStream {
+            input: Cursor::new(responses.concat()),
+            output: Vec::new(),
+        })
+    }
+
+    pub fn test_ldap_bind() {
+        let dn = "uid=alice,ou=people,dc=example,dc=com";
+        let groups = vec![
+            "cn=admins,ou=groups,dc=example,dc=com",
+            "cn=users,ou=groups,dc=example,dc=com",
+        ];
+        let mut session = get_mock_session(vec![
+            message(1, BIND_RESPONSE, &result(SUCCESS)),
+            message(2, SEARCH_RESULT_ENTRY, &entry(dn, "memberOf", &groups)),
+            message(2, SEARCH_RESULT_DONE, &result(SUCCESS)),
+        ]);
+        assert!(session.bind(dn, "test_password").is_ok());
+        assert_eq!(session.groups(dn, "memberof").unwrap(), groups);
+        let (request, _) = ber::decode_expected(ber::SEQUENCE, &session.stream.output).unwrap();
+        let (_, operation) = ber::decode_expected(ber::INTEGER, request).unwrap();
+        assert_eq!(operation[0], BIND_REQUEST);
+
+        // invalidCredentials
+        let mut session = get_mock_session(vec![message(1, BIND_RESPONSE, &result(49))]);
+        assert!(session.bind(dn, "test_password").is_err());
+        // Unauthenticated binds are not sent.
+        let mut session = get_mock_session(vec![message(1, BIND_RESPONSE, &result(SUCCESS))]);
+        assert!(session.bind(dn, "").is_err());
+        // Responses to other requests are rejected.
+        let mut session = get_mock_session(vec![message(2, BIND_RESPONSE, &result(SUCCESS))]);
+        assert!(session.bind(dn, "test_password").is_err());
+    }
+}
@@ -24,7 +24,7 @@ extern crate sgx_tstd as std;
 extern crate log;
 use anyhow::{anyhow, Result};
 
-use rand::RngCore;
+use std::path::PathBuf;
 use std::prelude::v1::*;
 use std::sync::{Arc, SgxRwLock as RwLock};
 use std::thread;
@@ -36,45 +36,82 @@ use teaclave_binder::proto::{
 };
 use teaclave_binder::{handle_ecall, register_ecall_handler};
 use teaclave_config::build::{
-    AS_ROOT_CA_CERT, AUDITOR_PUBLIC_KEYS, AUTHENTICATION_INBOUND_SERVICES,
+    AS_ROOT_CA_CERT, ATTESTATION_GATE, AUDITOR_PUBLIC_KEYS, AUTHENTICATION_INBOUND_SERVICES,
+    PLATFORM_ADMINS,
 };
-use teaclave_config::RuntimeConfig;
+use teaclave_config::{MessageLimitsConfig, RuntimeConfig};
 use teaclave_proto::teaclave_authentication_service::{
     TeaclaveAuthenticationApiRequest, TeaclaveAuthenticationApiResponse,
     TeaclaveAuthenticationInternalRequest, TeaclaveAuthenticationInternalResponse,
 };
-use teaclave_rpc::config::SgxTrustedTlsServerConfig;
+use teaclave_rpc::config::{SgxTrustedTlsServerConfig, TlsPolicy};
+use teaclave_rpc::gate::AttestationGate;
+use teaclave_rpc::interceptor::LoggingInterceptor;
 use teaclave_rpc::server::SgxTrustedTlsServer;
-use teaclave_service_enclave_utils::ServiceEnclave;
-use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
+use teaclave_service_enclave_utils::audit::{AuditInterceptor, AuditRecorder};
+use teaclave_service_enclave_utils::{
+    create_trusted_management_endpoint, create_trusted_storage_endpoint, ServiceEnclave,
+};
+use teaclave_types::{platform, EnclaveInfo, TeeServiceError, TeeServiceResult};
 
+mod api_key;
 mod api_service;
+mod argon2;
 mod error;
+mod impersonation;
 mod internal_service;
+mod ldap;
+mod oidc;
+mod read_only;
+mod revocation;
+mod role;
+mod totp;
 mod user_db;
 mod user_info;
+mod user_resources;
 
 fn start_internal_endpoint(
     addr: std::net::SocketAddr,
     db_client: user_db::DbClient,
     jwt_secret: Vec<u8>,
+    impersonation: impersonation::Impersonation,
     attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
     accepted_enclave_attrs: Vec<teaclave_types::EnclaveAttr>,
+    quote_status_policy: verifier::QuoteStatusPolicy,
+    tls_policy: TlsPolicy,
+    message_limits: MessageLimitsConfig,
+    unix_socket: Option<PathBuf>,
+    api_keys: api_key::ApiKeyStore,
+    revocations: revocation::TokenRevocations,
+    roles: role::Roles,
+    audit: AuditRecorder,
 ) -> Result<()> {
     let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
-        .attestation_report_verifier(
-        accepted_enclave_attrs,
-        AS_ROOT_CA_CERT,
-        verifier::universal_quote_verifier,
-    )?;
+        .attestation_report_verifier_with_policy(
+            accepted_enclave_attrs,
+            AS_ROOT_CA_CERT,
+            verifier::universal_quote_verifier,
+            quote_status_policy,
+        )?
+        .tls_policy(tls_policy)?;
 
     let mut server = SgxTrustedTlsServer::<
         TeaclaveAuthenticationInternalResponse,
         TeaclaveAuthenticationInternalRequest,
-    >::new(addr, server_config);
+    >::new(addr, server_config)
+    .message_limits(&message_limits)
+    .unix_socket(unix_socket)
+    .interceptor(Arc::new(LoggingInterceptor::new("authentication_internal")));
 
-    let service =
-        internal_service::TeaclaveAuthenticationInternalService::new(db_client, jwt_secret);
+    let service = internal_service::TeaclaveAuthenticationInternalService::new(
+        db_client,
+        jwt_secret,
+        impersonation,
+        api_keys,
+        revocations,
+        roles,
+        audit,
+    );
 
     match server.start(service) {
         Ok(_) => Ok(()),
@@ -89,16 +126,50 @@ fn start_api_endpoint(
     addr: std::net::SocketAddr,
     db_client: user_db::DbClient,
     jwt_secret: Vec<u8>,
+    impersonation: impersonation::Impersonation,
+    ldap: Option<ldap::Ldap>,
+    api_keys: api_key::ApiKeyStore,
+    revocations: revocation::TokenRevocations,
+    roles: role::Roles,
+    password_hashing: argon2::Argon2Params,
+    user_resources: user_resources::UserResources,
+    read_only: read_only::ReadOnlyMode,
+    audit: AuditRecorder,
     attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+    tls_policy: TlsPolicy,
+    message_limits: MessageLimitsConfig,
+    gate: Arc<AttestationGate>,
 ) -> Result<()> {
-    let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?;
+    let mut server_config =
+        SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
+            .tls_policy(tls_policy)?;
+    if !gate.is_empty() {
+        server_config = server_config.optional_client_cert(AS_ROOT_CA_CERT);
+    }
 
     let mut server = SgxTrustedTlsServer::<
         TeaclaveAuthenticationApiResponse,
         TeaclaveAuthenticationApiRequest,
-    >::new(addr, server_config);
+    >::new(addr, server_config)
+    .message_limits(&message_limits)
+    .attestation_gate(gate)
+    .interceptor(Arc::new(LoggingInterceptor::new("authentication_api")))
+    .interceptor(Arc::new(AuditInterceptor::new(audit.clone())));
 
-    let service = api_service::TeaclaveAuthenticationApiService::new(db_client, jwt_secret);
+    let service = api_service::TeaclaveAuthenticationApiService::new(
+        db_client,
+        jwt_secret,
+        impersonation,
+        oidc::Oidc::from_build_config(),
+        ldap,
+        api_keys,
+        revocations,
+        roles,
+        password_hashing,
+        user_resources,
+        read_only,
+        audit,
+    );
 
     match server.start(service) {
         Ok(_) => Ok(()),
@@ -122,27 +193,95 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
             None => Err(anyhow!("cannot get enclave attribute of {}", service)),
         })
         .collect::<Result<_>>()?;
+    let quote_status_policy = verifier::QuoteStatusPolicy::from_teaclave_config(&config);
+    let tls_policy = TlsPolicy::from_teaclave_config(&config);
     let api_listen_address = config.api_endpoints.authentication.listen_address;
+    let api_message_limits = config.api_endpoints.authentication.message_limits.clone();
+    let api_gate = AttestationGate::new(
+        ATTESTATION_GATE.methods,
+        ATTESTATION_GATE
+            .enclaves
+            .iter()
+            .map(|enclave| (enclave.mr_signer, enclave.mr_enclave)),
+        AS_ROOT_CA_CERT,
+    )?;
     let internal_listen_address = config.internal_endpoints.authentication.listen_address;
+    let internal_message_limits = config
+        .internal_endpoints
+        .authentication
+        .message_limits
+        .clone();
+    let internal_unix_socket = config.internal_endpoints.authentication.unix_socket.clone();
     let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
     let attested_tls_config = RemoteAttestation::new(attestation_config)
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
+    let storage_service_endpoint = create_trusted_storage_endpoint(
+        &config.internal_endpoints.storage.advertised_address,
+        &enclave_info,
+        AS_ROOT_CA_CERT,
+        verifier::universal_quote_verifier,
+        quote_status_policy.clone(),
+        tls_policy.clone(),
+        attested_tls_config.clone(),
+    )?
+    .message_limits(&config.internal_endpoints.storage.message_limits);
+    let audit =
+        AuditRecorder::connect("teaclave_authentication_service", &storage_service_endpoint)?;
+    let read_only = read_only::ReadOnlyMode::connect(&storage_service_endpoint)?;
+    let api_keys = api_key::ApiKeyStore::connect(storage_service_endpoint)?;
+    let management_service_endpoint = create_trusted_management_endpoint(
+        &config.internal_endpoints.management.advertised_address,
+        &enclave_info,
+        AS_ROOT_CA_CERT,
+        verifier::universal_quote_verifier,
+        quote_status_policy.clone(),
+        tls_policy.clone(),
+        attested_tls_config.clone(),
+    )?
+    .message_limits(&config.internal_endpoints.management.message_limits);
+    let user_resources = user_resources::UserResources::connect(management_service_endpoint)?;
+    let internal_api_keys = api_keys.clone();
+    let internal_audit = audit.clone();
     let database = user_db::Database::open()?;
     let mut api_jwt_secret = vec![0; user_info::JWT_SECRET_LEN];
-    let mut rng = rand::thread_rng();
-    rng.fill_bytes(&mut api_jwt_secret);
+    platform::rand::fill_bytes(&mut api_jwt_secret);
     let internal_jwt_secret = api_jwt_secret.to_owned();
+    let api_revocations = revocation::TokenRevocations::new();
+    let internal_revocations = api_revocations.clone();
+    let api_roles = role::Roles::new();
+    let internal_roles = api_roles.clone();
+    let mut consent_secret = vec![0; user_info::JWT_SECRET_LEN];
+    platform::rand::fill_bytes(&mut consent_secret);
+    let ldap_client = ldap::Ldap::from_config(config.ldap.as_ref())?;
+    let internal_impersonation =
+        impersonation::Impersonation::new(consent_secret, PLATFORM_ADMINS, &config.impersonation)
+            .ldap(ldap_client.clone());
+    let api_impersonation = internal_impersonation.clone();
+    let password_hashing = argon2::Argon2Params::from_config(&config.password_hashing)?;
 
     let attested_tls_config_ref = attested_tls_config.clone();
+    let api_tls_policy = tls_policy.clone();
     let client = database.get_client();
     let api_endpoint_thread_handler = thread::spawn(move || {
         let _ = start_api_endpoint(
             api_listen_address,
             client,
             api_jwt_secret,
+            api_impersonation,
+            ldap_client,
+            api_keys,
+            api_revocations,
+            api_roles,
+            password_hashing,
+            user_resources,
+            read_only,
+            audit,
             attested_tls_config_ref,
+            api_tls_policy,
+            api_message_limits,
+            api_gate,
         );
     });
 
@@ -152,8 +291,17 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
             internal_listen_address,
             client,
             internal_jwt_secret,
+            internal_impersonation,
             attested_tls_config,
             accepted_enclave_attrs,
+            quote_status_policy,
+            tls_policy,
+            internal_message_limits,
+            internal_unix_socket,
+            internal_api_keys,
+            internal_revocations,
+            internal_roles,
+            internal_audit,
         );
     });
 
@@ -204,14 +352,41 @@ pub mod tests {
 
     pub fn run_tests() -> bool {
         run_tests!(
+            api_key::tests::test_api_key,
+            revocation::tests::test_token_revocation,
+            role::tests::test_roles,
+            totp::tests::test_totp,
+            argon2::tests::test_argon2id,
+            user_info::tests::test_password_hash,
+            user_info::tests::test_delete_user,
             api_service::tests::test_user_login,
+            api_service::tests::test_rehash_legacy_password,
+            api_service::tests::test_enroll_totp,
+            api_service::tests::test_user_login_with_oidc,
+            api_service::tests::test_user_login_with_ldap,
             api_service::tests::test_user_register,
+            api_service::tests::test_create_api_key,
+            api_service::tests::test_refresh_and_revoke_token,
+            api_service::tests::test_manage_roles,
+            api_service::tests::test_manage_users,
+            api_service::tests::test_export_audit_log,
+            api_service::tests::test_grant_impersonation,
+            api_service::tests::test_read_only_mode,
             internal_service::tests::test_user_authenticate,
+            internal_service::tests::test_api_key_authenticate,
+            internal_service::tests::test_revoked_token_authenticate,
+            internal_service::tests::test_role_authenticate,
+            internal_service::tests::test_impersonation_authenticate,
+            internal_service::tests::test_disabled_user_authenticate,
+            internal_service::tests::test_health,
             internal_service::tests::test_invalid_algorithm,
             internal_service::tests::test_invalid_issuer,
             internal_service::tests::test_expired_token,
             internal_service::tests::test_invalid_user,
             internal_service::tests::test_wrong_secret,
+            ldap::tests::test_ldap_bind,
+            ldap::tests::test_ldap_user_dn,
+            ldap::tests::test_ldap_admin_groups,
         )
     }
 }
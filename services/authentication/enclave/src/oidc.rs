@@ -0,0 +1,99 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{bail, ensure, Result};
+use jsonwebtoken as jwt;
+use serde::Deserialize;
+use std::prelude::v1::*;
+use teaclave_config::build::{OidcProvider as OidcProviderConfig, OIDC_PROVIDERS};
+
+static ID_TOKEN_ALG: jwt::Algorithm = jwt::Algorithm::RS256;
+
+// The issuer, audience (which may be a list) and expiration time are checked
+// by the validation.
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    // subject, the id of the user at the provider
+    sub: String,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct OidcProvider {
+    pub name: String,
+    pub issuer: String,
+    pub audience: String,
+    pub public_keys: Vec<Vec<u8>>,
+}
+
+impl From<&OidcProviderConfig> for OidcProvider {
+    fn from(config: &OidcProviderConfig) -> Self {
+        Self {
+            name: config.name.to_string(),
+            issuer: config.issuer.to_string(),
+            audience: config.audience.to_string(),
+            public_keys: config.public_keys.iter().map(|k| k.to_vec()).collect(),
+        }
+    }
+}
+
+impl OidcProvider {
+    fn user_id(&self, subject: &str) -> String {
+        format!("{}:{}", self.name, subject)
+    }
+}
+
+// Federated login with the ID tokens of OpenID Connect providers. The users
+// of a provider are mapped to Teaclave users with the id "<name>:<sub>", so
+// these ids cannot be registered with passwords.
+#[derive(Clone, Default)]
+pub(crate) struct Oidc {
+    providers: Vec<OidcProvider>,
+}
+
+impl Oidc {
+    pub(crate) fn new(providers: Vec<OidcProvider>) -> Self {
+        Self { providers }
+    }
+
+    // The providers are pinned in the build config, as the runtime config is
+    // not trusted.
+    pub(crate) fn from_build_config() -> Self {
+        Self::new(OIDC_PROVIDERS.iter().map(OidcProvider::from).collect())
+    }
+
+    // Returns the id of the Teaclave user the ID token is issued for.
+    pub(crate) fn verify(&self, id_token: &str) -> Result<String> {
+        for provider in self.providers.iter() {
+            let mut validation = jwt::Validation::new(ID_TOKEN_ALG);
+            validation.iss = Some(provider.issuer.to_string());
+            validation.set_audience(&provider.audience);
+            for key in provider.public_keys.iter() {
+                if let Ok(data) = jwt::decode::<IdTokenClaims>(id_token, key, &validation) {
+                    ensure!(!data.claims.sub.is_empty(), "empty subject");
+                    return Ok(provider.user_id(&data.claims.sub));
+                }
+            }
+        }
+        bail!("ID token is not accepted by any provider")
+    }
+
+    pub(crate) fn is_reserved(&self, user_id: &str) -> bool {
+        self.providers
+            .iter()
+            .any(|provider| user_id.starts_with(&provider.user_id("")))
+    }
+}
@@ -0,0 +1,87 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{anyhow, Result};
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxMutex as Mutex};
+use teaclave_proto::teaclave_storage_service::{GetRequest, TeaclaveStorageClient};
+use teaclave_rpc::endpoint::Endpoint;
+use teaclave_types::{platform, ReadOnlyWindow, TeaclaveServiceResponseError, READ_ONLY_MODE_KEY};
+
+// The read-only mode of the platform is entered and exited through the
+// management service, which keeps its window in the storage.
+#[derive(Clone)]
+pub(crate) enum ReadOnlyMode {
+    Storage(Arc<Mutex<TeaclaveStorageClient>>),
+    #[cfg(feature = "enclave_unit_test")]
+    Memory(Arc<Mutex<Option<ReadOnlyWindow>>>),
+}
+
+impl ReadOnlyMode {
+    pub(crate) fn connect(storage_service_endpoint: &Endpoint) -> Result<Self> {
+        let mut i = 0;
+        let channel = loop {
+            match storage_service_endpoint.connect() {
+                Ok(channel) => break channel,
+                Err(_) => {
+                    anyhow::ensure!(i < 10, "failed to connect to storage service");
+                    log::debug!("Failed to connect to storage service, retry {}", i);
+                    i += 1;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_secs(3));
+        };
+        let client = TeaclaveStorageClient::new(channel)?;
+        Ok(ReadOnlyMode::Storage(Arc::new(Mutex::new(client))))
+    }
+
+    #[cfg(feature = "enclave_unit_test")]
+    pub(crate) fn in_memory() -> Self {
+        ReadOnlyMode::Memory(Arc::new(Mutex::new(None)))
+    }
+
+    #[cfg(feature = "enclave_unit_test")]
+    pub(crate) fn enter(&self, window: ReadOnlyWindow) {
+        if let ReadOnlyMode::Memory(stored) = self {
+            *stored.lock().unwrap() = Some(window);
+        }
+    }
+
+    // The window of the read-only mode, if active.
+    pub(crate) fn window(&self) -> Result<Option<ReadOnlyWindow>> {
+        let window = match self {
+            ReadOnlyMode::Storage(client) => {
+                let response = client
+                    .lock()
+                    .map_err(|_| anyhow!("Cannot lock storage client"))?
+                    .get(GetRequest::new(READ_ONLY_MODE_KEY.as_bytes()));
+                match response {
+                    Ok(response) => Some(ReadOnlyWindow::from_slice(&response.value)?),
+                    Err(TeaclaveServiceResponseError::RequestError(_)) => None,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            #[cfg(feature = "enclave_unit_test")]
+            ReadOnlyMode::Memory(stored) => stored
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock read-only mode"))?
+                .clone(),
+        };
+        let now = platform::time::since_epoch().as_secs();
+        Ok(window.filter(|window| window.is_active(now)))
+    }
+}
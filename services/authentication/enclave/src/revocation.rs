@@ -0,0 +1,149 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::user_info::{Claims, UserInfo};
+use anyhow::{ensure, Result};
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxRwLock as RwLock};
+use teaclave_types::platform;
+
+#[derive(Default)]
+struct Revoked {
+    // ids of the revoked tokens, and when they expire
+    tokens: HashMap<String, u64>,
+    // Tokens issued to a user before the latest generation are revoked.
+    generations: HashMap<String, u64>,
+}
+
+// Login tokens revoked before they expire. Tokens are signed with a secret
+// generated on startup, so the list is kept in memory as well. It is shared
+// by the API and internal endpoints.
+#[derive(Clone, Default)]
+pub(crate) struct TokenRevocations {
+    revoked: Arc<RwLock<Revoked>>,
+}
+
+impl TokenRevocations {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    // Generation of the tokens issued to the user now.
+    pub(crate) fn generation(&self, user_id: &str) -> u64 {
+        let revoked = match self.revoked.read() {
+            Ok(revoked) => revoked,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        revoked
+            .generations
+            .get(user_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn revoke(&self, claims: &Claims) {
+        let mut revoked = match self.revoked.write() {
+            Ok(revoked) => revoked,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // Expired tokens are rejected anyway.
+        let now = platform::time::since_epoch().as_secs();
+        revoked.tokens.retain(|_, exp| *exp >= now);
+        revoked.tokens.insert(claims.jti.clone(), claims.exp);
+    }
+
+    pub(crate) fn revoke_all(&self, user_id: &str) {
+        let mut revoked = match self.revoked.write() {
+            Ok(revoked) => revoked,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *revoked.generations.entry(user_id.to_string()).or_default() += 1;
+    }
+
+    pub(crate) fn is_revoked(&self, claims: &Claims) -> bool {
+        let revoked = match self.revoked.read() {
+            Ok(revoked) => revoked,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let generation = revoked
+            .generations
+            .get(&claims.sub)
+            .copied()
+            .unwrap_or_default();
+        claims.gen < generation || revoked.tokens.contains_key(&claims.jti)
+    }
+
+    // Returns the claims of a token of the user which is neither invalid nor
+    // revoked. Tokens of disabled users are rejected.
+    pub(crate) fn validate_user_credential(
+        &self,
+        user: &UserInfo,
+        secret: &[u8],
+        token: &str,
+    ) -> Result<Claims> {
+        ensure!(user.is_active(), "user disabled");
+        let claims = user.decode_token(secret, token)?;
+        ensure!(!self.is_revoked(&claims), "token revoked");
+        Ok(claims)
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::argon2::Argon2Params;
+    use std::vec;
+
+    pub fn test_token_revocation() {
+        let revocations = TokenRevocations::new();
+        let user = UserInfo::new(
+            "test_revocation_id",
+            "test_password",
+            &Argon2Params::default(),
+        );
+        let secret = vec![1; 64];
+        let exp = platform::time::since_epoch().as_secs() + 60;
+        let issue = || {
+            let generation = revocations.generation(&user.id);
+            user.get_token(exp, generation, &secret).unwrap()
+        };
+
+        let token = issue();
+        let another_token = issue();
+        let claims = revocations
+            .validate_user_credential(&user, &secret, &token)
+            .unwrap();
+        revocations.revoke(&claims);
+        assert!(revocations
+            .validate_user_credential(&user, &secret, &token)
+            .is_err());
+        assert!(revocations
+            .validate_user_credential(&user, &secret, &another_token)
+            .is_ok());
+
+        revocations.revoke_all(&user.id);
+        assert!(revocations
+            .validate_user_credential(&user, &secret, &another_token)
+            .is_err());
+        // Tokens issued afterwards are valid.
+        let token = issue();
+        assert!(revocations
+            .validate_user_credential(&user, &secret, &token)
+            .is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::user_info::UserInfo;
+use anyhow::{ensure, Result};
+use std::collections::{HashMap, HashSet};
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxRwLock as RwLock};
+use teaclave_types::Permission;
+
+/// Role of every user unless assigned others, granting the default
+/// permissions.
+pub(crate) const USER_ROLE: &str = "user";
+/// Role granting every permission, like the platform admins in the runtime
+/// config.
+pub(crate) const ADMIN_ROLE: &str = "admin";
+const MAX_ROLE_NAME_LEN: usize = 64;
+
+// Roles and the permissions they grant, shared by the API and internal
+// endpoints. Like the users they are assigned to, they are kept in memory.
+#[derive(Clone)]
+pub(crate) struct Roles {
+    roles: Arc<RwLock<HashMap<String, HashSet<Permission>>>>,
+}
+
+impl Roles {
+    pub(crate) fn new() -> Self {
+        let mut roles = HashMap::new();
+        roles.insert(
+            USER_ROLE.to_string(),
+            Permission::DEFAULT.iter().copied().collect(),
+        );
+        roles.insert(
+            ADMIN_ROLE.to_string(),
+            Permission::ALL.iter().copied().collect(),
+        );
+        Self {
+            roles: Arc::new(RwLock::new(roles)),
+        }
+    }
+
+    // Creates or replaces a role. The built-in roles cannot be changed.
+    pub(crate) fn create(&self, name: &str, permissions: &[Permission]) -> Result<()> {
+        ensure!(
+            !name.is_empty() && name.len() <= MAX_ROLE_NAME_LEN,
+            "invalid role name"
+        );
+        ensure!(name != USER_ROLE && name != ADMIN_ROLE, "built-in role");
+        let mut roles = match self.roles.write() {
+            Ok(roles) => roles,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        roles.insert(name.to_string(), permissions.iter().copied().collect());
+        Ok(())
+    }
+
+    pub(crate) fn exist(&self, names: &[String]) -> bool {
+        let roles = match self.roles.read() {
+            Ok(roles) => roles,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        names.iter().all(|name| roles.contains_key(name))
+    }
+
+    // Permissions granted by the roles of the user. Platform admins have every
+    // permission.
+    pub(crate) fn permissions(&self, user: &UserInfo, is_platform_admin: bool) -> Vec<Permission> {
+        if is_platform_admin {
+            return Permission::ALL.to_vec();
+        }
+        let roles = match self.roles.read() {
+            Ok(roles) => roles,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let granted: HashSet<Permission> = user
+            .roles
+            .iter()
+            .filter_map(|name| roles.get(name))
+            .flatten()
+            .copied()
+            .collect();
+        // in a stable order
+        Permission::ALL
+            .iter()
+            .filter(|permission| granted.contains(permission))
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::argon2::Argon2Params;
+
+    pub fn test_roles() {
+        let roles = Roles::new();
+        let mut user = UserInfo::new("test_role_id", "test_password", &Argon2Params::default());
+        assert_eq!(
+            roles.permissions(&user, false),
+            Permission::DEFAULT.to_vec()
+        );
+        assert_eq!(roles.permissions(&user, true), Permission::ALL.to_vec());
+
+        roles
+            .create("auditor", &[Permission::ReadAnyOutput])
+            .unwrap();
+        assert!(roles.exist(&["auditor".to_string(), USER_ROLE.to_string()]));
+        assert!(!roles.exist(&["unknown".to_string()]));
+        user.roles = vec!["auditor".to_string()];
+        assert_eq!(
+            roles.permissions(&user, false),
+            vec![Permission::ReadAnyOutput]
+        );
+
+        assert!(roles.create(ADMIN_ROLE, &[]).is_err());
+        assert!(roles.create("", &[]).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Time-based one-time passwords (RFC 6238) with the parameters supported by
+// common authenticator apps: HMAC-SHA1, 30 second steps and 6 digits.
+
+use crate::user_info::ISSUER_NAME;
+use ring::hmac;
+use std::prelude::v1::*;
+use std::vec;
+use teaclave_types::platform;
+
+const SECRET_LEN: usize = 20;
+const STEP_SECS: u64 = 30;
+const DIGITS: u32 = 6;
+// Codes of the previous and next steps are accepted for clock drift.
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub(crate) fn new_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_LEN];
+    platform::rand::fill_bytes(&mut secret);
+    secret
+}
+
+// The secret is shown to users in base32, without padding.
+pub(crate) fn encode_secret(secret: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in secret.chunks(5) {
+        let mut buffer = [0u8; 5];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let bits = buffer.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        let chars = (chunk.len() * 8 + 4) / 5;
+        for i in 0..chars {
+            let index = (bits >> (35 - i * 5)) & 0x1f;
+            encoded.push(BASE32_ALPHABET[index as usize] as char);
+        }
+    }
+    encoded
+}
+
+// URI to enroll the secret in authenticator apps, usually shown as a QR code.
+pub(crate) fn provisioning_uri(user_id: &str, secret: &[u8]) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{user}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = ISSUER_NAME,
+        user = user_id,
+        secret = encode_secret(secret),
+        digits = DIGITS,
+        period = STEP_SECS,
+    )
+}
+
+fn code(secret: &[u8], step: u64) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &step.to_be_bytes());
+    let hash = tag.as_ref();
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let binary = (u32::from(hash[offset]) & 0x7f) << 24
+        | u32::from(hash[offset + 1]) << 16
+        | u32::from(hash[offset + 2]) << 8
+        | u32::from(hash[offset + 3]);
+    binary % 10u32.pow(DIGITS)
+}
+
+// Returns the time step of a valid code, which must be later than the step
+// of the last accepted code of the user to prevent replays.
+pub(crate) fn verify(secret: &[u8], code_str: &str, last_step: u64, now_secs: u64) -> Option<u64> {
+    if code_str.len() != DIGITS as usize || !code_str.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let expected: u32 = code_str.parse().ok()?;
+    let current = now_secs / STEP_SECS;
+    let first = current.saturating_sub(ALLOWED_DRIFT_STEPS);
+    (first..=current + ALLOWED_DRIFT_STEPS)
+        .filter(|step| *step > last_step)
+        .find(|step| code(secret, *step) == expected)
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub(crate) fn current_code(secret: &[u8]) -> String {
+        let now = platform::time::since_epoch().as_secs();
+        format!("{:06}", code(secret, now / STEP_SECS))
+    }
+
+    pub fn test_totp() {
+        // Test vectors of RFC 6238, truncated to 6 digits
+        let secret = b"12345678901234567890";
+        assert_eq!(code(secret, 59 / STEP_SECS), 287_082);
+        assert_eq!(code(secret, 1_111_111_109 / STEP_SECS), 81_804);
+        assert_eq!(code(secret, 1_234_567_890 / STEP_SECS), 5_924);
+        assert_eq!(encode_secret(secret), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+
+        let now = 1_234_567_890;
+        assert_eq!(verify(secret, "005924", 0, now), Some(now / STEP_SECS));
+        assert_eq!(
+            verify(secret, "005924", 0, now + STEP_SECS),
+            Some(now / STEP_SECS)
+        );
+        assert_eq!(verify(secret, "005924", 0, now + 2 * STEP_SECS), None);
+        // replayed code
+        assert_eq!(verify(secret, "005924", now / STEP_SECS, now), None);
+        assert_eq!(verify(secret, "5924", 0, now), None);
+        assert_eq!(verify(secret, "00592a", 0, now), None);
+    }
+}
@@ -16,9 +16,11 @@
 // under the License.
 
 use crate::user_info::UserInfo;
+use rusty_leveldb::LdbIterator;
 use std::prelude::v1::*;
 use std::sync::mpsc::{channel, Sender};
 use std::thread;
+use teaclave_proto::teaclave_common::HealthCheck;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -64,10 +66,28 @@ struct CreateRequest {
     value: Vec<u8>,
 }
 
+#[derive(Clone)]
+struct UpdateRequest {
+    key: Vec<u8>,
+    value: Vec<u8>,
+}
+
+#[derive(Clone)]
+struct ListRequest {
+    start_after: Vec<u8>,
+}
+
+#[derive(Clone)]
+struct ListResponse {
+    values: Vec<Vec<u8>>,
+}
+
 #[derive(Clone)]
 enum DbRequest {
     Get(GetRequest),
     Create(CreateRequest),
+    Update(UpdateRequest),
+    List(ListRequest),
     Ping,
 }
 
@@ -75,6 +95,8 @@ enum DbRequest {
 enum DbResponse {
     Get(GetResponse),
     Create,
+    Update,
+    List(ListResponse),
     Ping,
 }
 
@@ -115,6 +137,27 @@ impl Database {
                             Err(_) => Err(DbError::LevelDbInternalError),
                         },
                     },
+                    DbRequest::Update(request) => match database.get(&request.key) {
+                        Some(_) => match database.put(&request.key, &request.value) {
+                            Ok(_) => Ok(DbResponse::Update),
+                            Err(_) => Err(DbError::LevelDbInternalError),
+                        },
+                        None => Err(DbError::UserNotExist),
+                    },
+                    // Keys are iterated in order, so the users are sorted
+                    // by id.
+                    DbRequest::List(request) => match database.new_iter() {
+                        Ok(mut iter) => {
+                            let mut values = Vec::new();
+                            while let Some((key, value)) = iter.next() {
+                                if key > request.start_after {
+                                    values.push(value);
+                                }
+                            }
+                            Ok(DbResponse::List(ListResponse { values }))
+                        }
+                        Err(_) => Err(DbError::LevelDbInternalError),
+                    },
                     DbRequest::Ping => Ok(DbResponse::Ping),
                 };
                 match sender.send(response) {
@@ -146,6 +189,14 @@ pub(crate) struct DbClient {
 }
 
 impl DbClient {
+    /// Checks that the database thread is serving requests.
+    pub(crate) fn health_check(&self) -> HealthCheck {
+        match self.get_user("") {
+            Ok(_) | Err(DbError::UserNotExist) => HealthCheck::healthy("database", "ok"),
+            Err(e) => HealthCheck::unhealthy("database", e.to_string()),
+        }
+    }
+
     pub(crate) fn get_user(&self, id: &str) -> Result<UserInfo, DbError> {
         let (sender, receiver) = channel();
         let request = DbRequest::Get(GetRequest {
@@ -182,6 +233,43 @@ impl DbClient {
         }
     }
 
+    pub(crate) fn update_user(&self, user: &UserInfo) -> Result<(), DbError> {
+        let (sender, receiver) = channel();
+        let user_bytes = serde_json::to_vec(&user).map_err(|_| DbError::InvalidRequest)?;
+        let request = DbRequest::Update(UpdateRequest {
+            key: user.id.as_bytes().to_vec(),
+            value: user_bytes,
+        });
+        let call = DBCall { sender, request };
+        self.sender.send(call)?;
+        let result = receiver.recv()?;
+        let db_response = result?;
+        match db_response {
+            DbResponse::Update => Ok(()),
+            _ => Err(DbError::InvalidResponse),
+        }
+    }
+
+    // Returns the users whose id is after start_after, sorted by id.
+    pub(crate) fn list_users(&self, start_after: &str) -> Result<Vec<UserInfo>, DbError> {
+        let (sender, receiver) = channel();
+        let request = DbRequest::List(ListRequest {
+            start_after: start_after.as_bytes().to_vec(),
+        });
+        let call = DBCall { sender, request };
+        self.sender.send(call)?;
+        let result = receiver.recv()?;
+        let db_response = result?;
+        match db_response {
+            DbResponse::List(response) => response
+                .values
+                .iter()
+                .map(|value| serde_json::from_slice(value).map_err(|_| DbError::InvalidResponse))
+                .collect(),
+            _ => Err(DbError::InvalidResponse),
+        }
+    }
+
     // Check whether the database is opened successfully.
     fn ping(&self) -> Result<(), DbError> {
         let (sender, receiver) = channel();
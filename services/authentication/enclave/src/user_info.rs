@@ -15,29 +15,87 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::argon2::{self, Argon2Params};
+use crate::role;
 use anyhow::Result;
 use jsonwebtoken as jwt;
-use rand::prelude::RngCore;
-use ring::{digest, pbkdf2};
+use ring::{constant_time, pbkdf2};
 use serde::{Deserialize, Serialize};
 use std::num;
 use std::prelude::v1::*;
 use std::vec;
+use teaclave_types::platform;
+use uuid::Uuid;
 
 const SALT_LEN: usize = 16;
-const PASSWORD_DIGEST_LEN: usize = digest::SHA512_OUTPUT_LEN;
 const PBKDF2_ITERATIONS: u32 = 100_000;
 static PBKDF2_ALG: pbkdf2::Algorithm = pbkdf2::PBKDF2_HMAC_SHA512;
+const ARGON2_HASH_LEN: usize = 32;
 
 pub(crate) const ISSUER_NAME: &str = "Teaclave";
 pub(crate) static JWT_ALG: jwt::Algorithm = jwt::Algorithm::HS512;
 pub(crate) const JWT_SECRET_LEN: usize = 512;
 
+// Users registered before Argon2id was introduced have PBKDF2 hashes, which
+// are replaced on their next login.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) enum PasswordHashAlgorithm {
+    Pbkdf2Sha512,
+    Argon2id(Argon2Params),
+}
+
+impl Default for PasswordHashAlgorithm {
+    fn default() -> Self {
+        PasswordHashAlgorithm::Pbkdf2Sha512
+    }
+}
+
+// Disabled and deleted users cannot authenticate. Deleted users keep their
+// id, so that it cannot be registered again to take over the functions and
+// tasks of the user.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) enum UserStatus {
+    Active,
+    Disabled,
+    Deleted,
+}
+
+impl Default for UserStatus {
+    fn default() -> Self {
+        UserStatus::Active
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub(crate) struct UserInfo {
     pub id: String,
     pub salt: Vec<u8>,
     pub salted_password_hash: Vec<u8>,
+    #[serde(default)]
+    pub password_hash_algorithm: PasswordHashAlgorithm,
+    #[serde(default = "default_roles")]
+    pub roles: Vec<String>,
+    // Empty unless the user enrolled in TOTP two-factor authentication
+    #[serde(default)]
+    pub totp_secret: Vec<u8>,
+    // Time step of the last accepted TOTP code, which cannot be used again
+    #[serde(default)]
+    pub totp_last_step: u64,
+    // Secret of the last enrollment, used in place of totp_secret once
+    // confirmed with a code generated from it
+    #[serde(default)]
+    pub pending_totp_secret: Vec<u8>,
+    #[serde(default)]
+    pub status: UserStatus,
+    // Random on registration. API keys outlive the in-memory user database,
+    // so they are bound to it rather than to the id, which can be registered
+    // again after a restart.
+    #[serde(default)]
+    pub epoch: Uuid,
+}
+
+fn default_roles() -> Vec<String> {
+    vec![role::USER_ROLE.to_string()]
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,47 +106,103 @@ pub(crate) struct Claims {
     pub iss: String,
     // expiration time
     pub exp: u64,
+    // token id, for revocation
+    pub jti: String,
+    // revocation generation of the user when the token was issued
+    pub gen: u64,
 }
 
 impl UserInfo {
-    pub(crate) fn new(id: &str, password: &str) -> Self {
-        let mut rng = rand::thread_rng();
-        let mut salt = vec![0u8; SALT_LEN];
-        rng.fill_bytes(&mut salt);
-        let mut salted_password_hash = vec![0u8; PASSWORD_DIGEST_LEN];
-        let pbkdf2_iterations = num::NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
-        pbkdf2::derive(
-            PBKDF2_ALG,
-            pbkdf2_iterations,
-            &salt,
-            password.as_bytes(),
-            &mut salted_password_hash,
-        );
+    pub(crate) fn new(id: &str, password: &str, params: &Argon2Params) -> Self {
+        let mut user = Self::new_external(id);
+        user.set_password(password, params);
+        user
+    }
+
+    // Users of OpenID Connect providers have no password.
+    pub(crate) fn new_external(id: &str) -> Self {
         Self {
             id: id.to_string(),
-            salt,
-            salted_password_hash,
+            salt: Vec::new(),
+            salted_password_hash: Vec::new(),
+            password_hash_algorithm: PasswordHashAlgorithm::default(),
+            roles: default_roles(),
+            totp_secret: Vec::new(),
+            totp_last_step: 0,
+            pending_totp_secret: Vec::new(),
+            status: UserStatus::Active,
+            epoch: platform::rand::new_uuid(),
         }
     }
 
+    pub(crate) fn is_active(&self) -> bool {
+        self.status == UserStatus::Active
+    }
+
+    // Removes the credentials and roles of the user, keeping the id.
+    pub(crate) fn delete(&mut self) {
+        self.salt.clear();
+        self.salted_password_hash.clear();
+        self.password_hash_algorithm = PasswordHashAlgorithm::default();
+        self.roles.clear();
+        self.totp_secret.clear();
+        self.totp_last_step = 0;
+        self.pending_totp_secret.clear();
+        self.status = UserStatus::Deleted;
+    }
+
+    pub(crate) fn set_password(&mut self, password: &str, params: &Argon2Params) {
+        let mut salt = vec![0u8; SALT_LEN];
+        platform::rand::fill_bytes(&mut salt);
+        self.salted_password_hash =
+            argon2::hash_password(params, password.as_bytes(), &salt, ARGON2_HASH_LEN);
+        self.salt = salt;
+        self.password_hash_algorithm = PasswordHashAlgorithm::Argon2id(params.clone());
+    }
+
     pub(crate) fn verify_password(&self, password: &str) -> bool {
-        let pbkdf2_iterations = num::NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
-        pbkdf2::verify(
-            PBKDF2_ALG,
-            pbkdf2_iterations,
-            &self.salt,
-            password.as_bytes(),
-            &self.salted_password_hash,
-        )
-        .is_ok()
+        if self.salted_password_hash.is_empty() {
+            return false;
+        }
+        match &self.password_hash_algorithm {
+            PasswordHashAlgorithm::Pbkdf2Sha512 => {
+                let pbkdf2_iterations = num::NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
+                pbkdf2::verify(
+                    PBKDF2_ALG,
+                    pbkdf2_iterations,
+                    &self.salt,
+                    password.as_bytes(),
+                    &self.salted_password_hash,
+                )
+                .is_ok()
+            }
+            PasswordHashAlgorithm::Argon2id(params) => {
+                let hash = argon2::hash_password(
+                    params,
+                    password.as_bytes(),
+                    &self.salt,
+                    self.salted_password_hash.len(),
+                );
+                constant_time::verify_slices_are_equal(&hash, &self.salted_password_hash).is_ok()
+            }
+        }
+    }
+
+    // Whether the password hash should be replaced with one of the
+    // configured parameters after a successful login.
+    pub(crate) fn needs_rehash(&self, params: &Argon2Params) -> bool {
+        !self.salted_password_hash.is_empty()
+            && self.password_hash_algorithm != PasswordHashAlgorithm::Argon2id(params.clone())
     }
 
-    pub(crate) fn get_token(&self, exp: u64, secret: &[u8]) -> Result<String> {
+    pub(crate) fn get_token(&self, exp: u64, generation: u64, secret: &[u8]) -> Result<String> {
         let iss = ISSUER_NAME.to_string();
         let claims = Claims {
             sub: self.id.to_string(),
             iss,
             exp,
+            jti: platform::rand::new_uuid().to_string(),
+            gen: generation,
         };
         let mut header = jwt::Header::default();
         header.alg = JWT_ALG;
@@ -97,10 +211,82 @@ impl UserInfo {
     }
 
     pub(crate) fn validate_token(&self, secret: &[u8], token: &str) -> bool {
+        self.decode_token(secret, token).is_ok()
+    }
+
+    // Returns the claims of a valid token of the user, which may still be
+    // revoked.
+    pub(crate) fn decode_token(&self, secret: &[u8], token: &str) -> Result<Claims> {
         let iss = ISSUER_NAME.to_string();
         let mut validation = jwt::Validation::new(JWT_ALG);
         validation.iss = Some(iss);
         validation.sub = Some(self.id.to_string());
-        jwt::decode::<Claims>(token, secret, &validation).is_ok()
+        let claims = jwt::decode::<Claims>(token, secret, &validation)?.claims;
+        Ok(claims)
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use ring::digest;
+
+    // Users registered before the migration to Argon2id
+    pub(crate) fn new_pbkdf2_user(id: &str, password: &str) -> UserInfo {
+        let mut user = UserInfo::new_external(id);
+        let mut salt = vec![0u8; SALT_LEN];
+        platform::rand::fill_bytes(&mut salt);
+        let mut hash = vec![0u8; digest::SHA512_OUTPUT_LEN];
+        let pbkdf2_iterations = num::NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
+        pbkdf2::derive(
+            PBKDF2_ALG,
+            pbkdf2_iterations,
+            &salt,
+            password.as_bytes(),
+            &mut hash,
+        );
+        user.salt = salt;
+        user.salted_password_hash = hash;
+        user
+    }
+
+    pub fn test_password_hash() {
+        let params = Argon2Params::default();
+        let user = UserInfo::new("test_hash_id", "test_password", &params);
+        assert!(user.verify_password("test_password"));
+        assert!(!user.verify_password("test_password1"));
+        assert!(!user.needs_rehash(&params));
+        assert!(user.needs_rehash(&Argon2Params::new(params.memory_kib, 3, 1).unwrap()));
+
+        let mut user = new_pbkdf2_user("test_hash_id", "test_password");
+        assert_eq!(
+            user.password_hash_algorithm,
+            PasswordHashAlgorithm::Pbkdf2Sha512
+        );
+        assert!(user.verify_password("test_password"));
+        assert!(!user.verify_password("test_password1"));
+        assert!(user.needs_rehash(&params));
+        user.set_password("test_password", &params);
+        assert!(user.verify_password("test_password"));
+        assert!(!user.needs_rehash(&params));
+
+        assert!(!UserInfo::new_external("test_hash_id").needs_rehash(&params));
+    }
+
+    pub fn test_delete_user() {
+        let params = Argon2Params::default();
+        let mut user = UserInfo::new("test_delete_id", "test_password", &params);
+        assert!(user.is_active());
+        user.delete();
+        assert!(!user.is_active());
+        assert_eq!(user.status, UserStatus::Deleted);
+        assert!(!user.verify_password("test_password"));
+        assert!(user.roles.is_empty());
+
+        // Users stored before the status was introduced are active.
+        let mut value = serde_json::to_value(&UserInfo::new_external("test_delete_id")).unwrap();
+        value.as_object_mut().unwrap().remove("status");
+        let user: UserInfo = serde_json::from_value(value).unwrap();
+        assert!(user.is_active());
     }
 }
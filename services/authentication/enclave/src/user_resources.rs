@@ -0,0 +1,87 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{anyhow, Result};
+#[cfg(feature = "enclave_unit_test")]
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxMutex as Mutex};
+use teaclave_proto::teaclave_management_service::{
+    DisableUserResourcesRequest, TeaclaveManagementClient,
+};
+use teaclave_rpc::endpoint::Endpoint;
+
+// Functions and tasks of users are kept by the management service, which
+// stops serving those of disabled and deleted users.
+#[derive(Clone)]
+pub(crate) enum UserResources {
+    Management(Arc<Mutex<TeaclaveManagementClient>>),
+    // ids of the disabled users, and whether they are deleted
+    #[cfg(feature = "enclave_unit_test")]
+    Memory(Arc<Mutex<HashMap<String, bool>>>),
+}
+
+impl UserResources {
+    pub(crate) fn connect(management_service_endpoint: Endpoint) -> Result<Self> {
+        let mut i = 0;
+        let channel = loop {
+            match management_service_endpoint.connect() {
+                Ok(channel) => break channel,
+                Err(_) => {
+                    anyhow::ensure!(i < 10, "failed to connect to management service");
+                    log::debug!("Failed to connect to management service, retry {}", i);
+                    i += 1;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_secs(3));
+        };
+        let client = TeaclaveManagementClient::new(channel)?;
+        Ok(UserResources::Management(Arc::new(Mutex::new(client))))
+    }
+
+    #[cfg(feature = "enclave_unit_test")]
+    pub(crate) fn in_memory() -> Self {
+        UserResources::Memory(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    pub(crate) fn disable(&self, user_id: &str, deleted: bool) -> Result<()> {
+        match self {
+            UserResources::Management(client) => {
+                client
+                    .lock()
+                    .map_err(|_| anyhow!("Cannot lock management client"))?
+                    .disable_user_resources(DisableUserResourcesRequest::new(user_id, deleted))?;
+            }
+            #[cfg(feature = "enclave_unit_test")]
+            UserResources::Memory(disabled) => {
+                disabled
+                    .lock()
+                    .map_err(|_| anyhow!("Cannot lock disabled users"))?
+                    .insert(user_id.to_string(), deleted);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "enclave_unit_test")]
+    pub(crate) fn is_disabled(&self, user_id: &str) -> bool {
+        match self {
+            UserResources::Memory(disabled) => disabled.lock().unwrap().contains_key(user_id),
+            UserResources::Management(_) => false,
+        }
+    }
+}
@@ -37,6 +37,26 @@ fn main() -> Result<()> {
         PACKAGE_NAME,
         "runtime.config.toml",
     )?);
+
+    // Executor enclaves of function providers are launched with the same
+    // config, and serve the execution service once attested by it.
+    let executor_enclaves = launcher
+        .config()
+        .executor_enclaves
+        .iter()
+        .map(|enclave| {
+            TeaclaveServiceLauncher::new(&enclave.name, "runtime.config.toml").map(Arc::new)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for enclave in executor_enclaves.iter() {
+        let enclave = enclave.clone();
+        thread::spawn(move || {
+            if let Err(e) = enclave.start() {
+                log::error!("Executor enclave exited: {:?}", e);
+            }
+        });
+    }
+
     let launcher_ref = launcher.clone();
     thread::spawn(move || {
         let _ = launcher_ref.start();
@@ -50,6 +70,12 @@ fn main() -> Result<()> {
         thread::park();
     }
 
+    for enclave in executor_enclaves.iter() {
+        enclave.finalize();
+        unsafe {
+            enclave.destroy();
+        }
+    }
     launcher.finalize();
     unsafe {
         launcher.destroy(); // force to destroy the enclave
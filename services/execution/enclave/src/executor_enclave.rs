@@ -0,0 +1,169 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+//! Executor enclaves built by function providers run the functions
+//! registered with them in place of the executors of the worker. They are
+//! launched by the host of the execution service, which hands a task over
+//! only after the enclave has attested to the measurement registered with
+//! the management service.
+
+use std::prelude::v1::*;
+use std::str::FromStr;
+use std::sync::{Arc, SgxRwLock as RwLock};
+
+use teaclave_attestation::verifier::{self, QuoteStatusPolicy};
+use teaclave_attestation::AttestedTlsConfig;
+use teaclave_config::build::AS_ROOT_CA_CERT;
+use teaclave_config::RuntimeConfig;
+use teaclave_proto::teaclave_executor_enclave_service::*;
+use teaclave_rpc::config::{SgxTrustedTlsClientConfig, TlsPolicy};
+use teaclave_rpc::endpoint::Endpoint;
+use teaclave_types::{EnclaveAttr, EnclaveMeasurement, MrEnclave, StagedFunction};
+
+use anyhow::{anyhow, Result};
+use uuid::Uuid;
+
+/// The executor enclaves launched on the node, with their addresses.
+pub(crate) struct ExecutorEnclaves {
+    enclaves: Vec<(MrEnclave, String)>,
+    attested_tls_config: Option<Arc<RwLock<AttestedTlsConfig>>>,
+    quote_status_policy: QuoteStatusPolicy,
+    tls_policy: TlsPolicy,
+}
+
+impl ExecutorEnclaves {
+    pub(crate) fn from_config(
+        config: &RuntimeConfig,
+        attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+    ) -> Result<Self> {
+        let enclaves = config
+            .executor_enclaves
+            .iter()
+            .map(|enclave| {
+                Ok((
+                    MrEnclave::from_str(&enclave.mr_enclave)?,
+                    enclave.address.clone(),
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            enclaves,
+            attested_tls_config: Some(attested_tls_config),
+            quote_status_policy: QuoteStatusPolicy::from_teaclave_config(config),
+            tls_policy: TlsPolicy::from_teaclave_config(config),
+        })
+    }
+
+    /// MRENCLAVE of the enclaves, advertised to the scheduler service.
+    pub(crate) fn mr_enclaves(&self) -> Vec<MrEnclave> {
+        self.enclaves
+            .iter()
+            .map(|(mr_enclave, _)| *mr_enclave)
+            .collect()
+    }
+
+    /// Invokes the function in the enclave with the registered measurement,
+    /// with the files staged for it. The log and intermediate results of the
+    /// function are returned with its result.
+    pub(crate) fn invoke(
+        &self,
+        measurement: &EnclaveMeasurement,
+        task_id: Uuid,
+        function: StagedFunction,
+    ) -> Result<String> {
+        let address = self
+            .enclaves
+            .iter()
+            .find(|(mr_enclave, _)| *mr_enclave == measurement.mr_enclave)
+            .map(|(_, address)| address)
+            .ok_or_else(|| anyhow!("Executor enclave not launched: {}", measurement.mr_enclave))?;
+        let attested_tls_config = self
+            .attested_tls_config
+            .clone()
+            .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
+
+        // The enclave must attest to the registered measurement, so that the
+        // keys of the staged files are handed over only to it.
+        let client_config =
+            SgxTrustedTlsClientConfig::from_attested_tls_config(attested_tls_config)?
+                .attestation_report_verifier_with_policy(
+                    vec![EnclaveAttr {
+                        measurement: *measurement,
+                    }],
+                    AS_ROOT_CA_CERT,
+                    verifier::universal_quote_verifier,
+                    self.quote_status_policy.clone(),
+                )
+                .tls_policy(self.tls_policy.clone())?;
+        let channel = Endpoint::new(address).config(client_config).connect()?;
+        let mut client = TeaclaveExecutorEnclaveClient::new(channel)?;
+
+        // Bundles are rejected when functions are registered.
+        anyhow::ensure!(
+            function.bundle.is_empty(),
+            "Executor enclaves do not run bundles"
+        );
+        let log = function.log.clone();
+        let result_stream = function.result_stream.clone();
+        let request = InvokeFunctionRequest {
+            task_id,
+            function_name: function.name,
+            function_payload: function.payload,
+            function_arguments: function.arguments,
+            input_files: function.input_files,
+            output_files: function.output_files,
+            env: function.env,
+            time_limit: function.time_limit,
+            output_size_limit: function.output_size_limit,
+        };
+        let response = client.invoke_function(request)?;
+
+        for message in response.log.iter() {
+            log.append(message);
+        }
+        for chunk in response.result_chunks.iter() {
+            result_stream.emit(chunk);
+        }
+        Ok(response.summary)
+    }
+}
+
+impl Default for ExecutorEnclaves {
+    fn default() -> Self {
+        Self {
+            enclaves: Vec::new(),
+            attested_tls_config: None,
+            quote_status_policy: QuoteStatusPolicy::default(),
+            tls_policy: TlsPolicy::default(),
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_types::{platform, MrSigner};
+
+    pub fn test_invoke_not_launched() {
+        let enclaves = ExecutorEnclaves::default();
+        assert!(enclaves.mr_enclaves().is_empty());
+
+        let measurement = EnclaveMeasurement::new(MrEnclave::new([1; 32]), MrSigner::default());
+        let function = StagedFunction::new().name("echo");
+        let result = enclaves.invoke(&measurement, platform::rand::new_uuid(), function);
+        assert!(result.is_err());
+    }
+}
@@ -34,14 +34,25 @@ use teaclave_binder::proto::{
 use teaclave_binder::{handle_ecall, register_ecall_handler};
 use teaclave_config::build::{AS_ROOT_CA_CERT, AUDITOR_PUBLIC_KEYS};
 use teaclave_config::RuntimeConfig;
+use teaclave_rpc::channel::ChannelPoolConfig;
+use teaclave_rpc::config::TlsPolicy;
 use teaclave_service_enclave_utils::create_trusted_scheduler_endpoint;
 use teaclave_service_enclave_utils::ServiceEnclave;
-use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
+use teaclave_types::{EnclaveInfo, NodeCapacity, TeeServiceError, TeeServiceResult};
+use teaclave_worker::{BuiltinFunctionExecutor, Worker};
 
+mod executor_enclave;
 mod ocall;
+mod output_scan;
+mod result_forwarder;
 mod service;
+mod slot_pool;
 mod task_file_manager;
 
+// Requests to the scheduler service which can run twice, and are retried even
+// if the connection failed after they were sent.
+const SCHEDULER_IDEMPOTENT_REQUESTS: &[&str] = &["RegisterNode", "Heartbeat", "Health"];
+
 fn start_service(config: &RuntimeConfig) -> Result<()> {
     let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
     let attested_tls_config = RemoteAttestation::new(attestation_config)
@@ -59,8 +70,12 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         &enclave_info,
         AS_ROOT_CA_CERT,
         verifier::universal_quote_verifier,
-        attested_tls_config,
-    )?;
+        verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+        TlsPolicy::from_teaclave_config(&config),
+        attested_tls_config.clone(),
+    )?
+    .pool(ChannelPoolConfig::default().idempotent_requests(SCHEDULER_IDEMPOTENT_REQUESTS))
+    .message_limits(&config.internal_endpoints.scheduler.message_limits);
 
     let fusion_base = config.mount.fusion_base_dir.clone();
 
@@ -75,11 +90,38 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         fusion_base.display()
     );
 
-    let mut service =
-        service::TeaclaveExecutionService::new(scheduler_service_endpoint, fusion_base)?;
-    let _ = service.start();
-
-    Ok(())
+    let scanners = output_scan::OutputScanners::from_config(&config.output_scan)?;
+    let executor_enclaves =
+        executor_enclave::ExecutorEnclaves::from_config(&config, attested_tls_config.clone())?;
+    let node_config = &config.execution_node;
+    let slots = slot_pool::SlotPool::new(
+        node_config.slots,
+        node_config.slot_memory_mb,
+        node_config.memory_mb,
+    )?;
+    let capacity = NodeCapacity {
+        cpus: node_config.cpus,
+        memory_mb: node_config.memory_mb,
+        slots: slots.capacity(),
+    };
+    let mut builtin_functions = BuiltinFunctionExecutor::default();
+    if !node_config.builtin_functions.is_empty() {
+        builtin_functions.retain(&node_config.builtin_functions)?;
+    }
+    let mut worker = Worker::default();
+    worker.register_builtin_functions(builtin_functions);
+    let mut service = service::TeaclaveExecutionService::new(
+        scheduler_service_endpoint,
+        worker,
+        fusion_base,
+        scanners,
+        executor_enclaves,
+        attested_tls_config,
+        capacity,
+        node_config.labels.clone(),
+        slots,
+    )?;
+    service.start()
 }
 
 #[handle_ecall]
@@ -119,10 +161,19 @@ pub mod tests {
 
     pub fn run_tests() -> bool {
         run_tests!(
+            executor_enclave::tests::test_invoke_not_launched,
             ocall::tests::test_handle_file_request,
+            output_scan::tests::test_pii_scanner,
+            output_scan::tests::test_min_aggregation_scanner,
+            output_scan::tests::test_output_scanners,
+            service::tests::test_check_output_policies,
             service::tests::test_invoke_echo,
             service::tests::test_invoke_gbdt_train,
+            service::tests::test_invoke_output_too_large,
+            service::tests::test_scan_outputs,
+            slot_pool::tests::test_slot_pool,
             task_file_manager::tests::test_input,
+            task_file_manager::tests::test_staging_time_limit,
         )
     }
 }
@@ -19,7 +19,7 @@ use anyhow::ensure;
 use anyhow::Result;
 use sgx_types::sgx_status_t;
 use std::prelude::v1::*;
-use teaclave_types::FileAgentRequest;
+use teaclave_types::{FileAgentRequest, TaskBudgetError, FA_ERR_TIMEOUT, FA_OK};
 
 extern "C" {
     fn ocall_handle_file_request(
@@ -42,7 +42,10 @@ pub(crate) fn handle_file_request(request: FileAgentRequest) -> Result<()> {
         "ocall sgx_error = {:?}",
         res
     );
-    ensure!(rt == 0, "ocall error = {:?}", rt);
+    if rt == FA_ERR_TIMEOUT {
+        return Err(TaskBudgetError::StagingTimeout.into());
+    }
+    ensure!(rt == FA_OK, "ocall error = {:?}", rt);
     Ok(())
 }
 
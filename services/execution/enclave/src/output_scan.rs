@@ -0,0 +1,273 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Content scanners run on the plaintext of the outputs of a task before
+//! they are uploaded. A scanner flags content which should not leave the
+//! enclave without the review of the data owners.
+
+use std::prelude::v1::*;
+
+use anyhow::{bail, Result};
+use teaclave_config::OutputScanConfig;
+use teaclave_types::ScanFinding;
+
+pub(crate) trait OutputScanner: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Returns why the content cannot be released, if it cannot.
+    fn scan(&self, content: &[u8]) -> Option<String>;
+}
+
+/// Looks for email addresses, card numbers passing the Luhn check and US
+/// social security numbers in text outputs.
+pub(crate) struct PiiScanner;
+
+impl OutputScanner for PiiScanner {
+    fn name(&self) -> &'static str {
+        "pii"
+    }
+
+    fn scan(&self, content: &[u8]) -> Option<String> {
+        let text = String::from_utf8_lossy(content);
+        for (i, line) in text.lines().enumerate() {
+            let kind = if line.split_whitespace().any(is_email_address) {
+                "email address"
+            } else if has_card_number(line) {
+                "card number"
+            } else if has_ssn(line) {
+                "social security number"
+            } else {
+                continue;
+            };
+            return Some(format!("{} in line {}", kind, i + 1));
+        }
+        None
+    }
+}
+
+fn is_email_address(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+    let mut parts = word.splitn(2, '@');
+    let (local, domain) = match (parts.next(), parts.next()) {
+        (Some(local), Some(domain)) => (local, domain),
+        _ => return false,
+    };
+    let is_local_char = |c: char| c.is_ascii_alphanumeric() || "._%+-".contains(c);
+    let labels: Vec<&str> = domain.split('.').collect();
+    !local.is_empty()
+        && local.chars().all(is_local_char)
+        && labels.len() > 1
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+// Card numbers are 13 to 19 digits, possibly grouped with spaces or dashes.
+fn has_card_number(line: &str) -> bool {
+    let mut digits = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(digit) = c.to_digit(10) {
+            digits.push(digit);
+            continue;
+        }
+        let grouped = (c == ' ' || c == '-')
+            && !digits.is_empty()
+            && chars.peek().map_or(false, |next| next.is_ascii_digit());
+        if !grouped {
+            if is_card_number(&digits) {
+                return true;
+            }
+            digits.clear();
+        }
+    }
+    is_card_number(&digits)
+}
+
+fn is_card_number(digits: &[u32]) -> bool {
+    if digits.len() < 13 || digits.len() > 19 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+// Social security numbers are written as "ddd-dd-dddd".
+fn has_ssn(line: &str) -> bool {
+    line.split(|c: char| !c.is_ascii_digit() && c != '-')
+        .any(|word| {
+            let groups: Vec<&str> = word.split('-').collect();
+            groups.len() == 3
+                && groups
+                    .iter()
+                    .zip(&[3, 2, 4])
+                    .all(|(group, &len)| group.len() == len)
+        })
+}
+
+/// Checks that every row of a CSV output aggregates at least `min_count`
+/// records, so that no row describes a small group of individuals. Outputs
+/// without the count column in their header are not checked.
+pub(crate) struct MinAggregationScanner {
+    min_count: u64,
+    count_column: String,
+}
+
+impl MinAggregationScanner {
+    pub(crate) fn new(min_count: u64, count_column: impl ToString) -> Self {
+        Self {
+            min_count,
+            count_column: count_column.to_string(),
+        }
+    }
+}
+
+impl OutputScanner for MinAggregationScanner {
+    fn name(&self) -> &'static str {
+        "min_aggregation"
+    }
+
+    fn scan(&self, content: &[u8]) -> Option<String> {
+        let text = String::from_utf8_lossy(content);
+        let mut lines = text.lines();
+        let column = lines
+            .next()?
+            .split(',')
+            .position(|name| name.trim() == self.count_column)?;
+        for (i, line) in lines.enumerate() {
+            let field = match line.split(',').nth(column) {
+                Some(field) => field.trim(),
+                None => continue,
+            };
+            match field.parse::<u64>() {
+                Ok(count) if count >= self.min_count => (),
+                _ => {
+                    return Some(format!(
+                        "{} of {:?} below {} in line {}",
+                        self.count_column,
+                        field,
+                        self.min_count,
+                        i + 2
+                    ))
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The scanners enabled in the runtime config.
+#[derive(Default)]
+pub(crate) struct OutputScanners {
+    scanners: Vec<Box<dyn OutputScanner>>,
+}
+
+impl OutputScanners {
+    pub(crate) fn from_config(config: &OutputScanConfig) -> Result<Self> {
+        let mut scanners: Vec<Box<dyn OutputScanner>> = Vec::new();
+        for name in config.scanners.iter() {
+            match name.as_str() {
+                "pii" => scanners.push(Box::new(PiiScanner)),
+                "min_aggregation" => scanners.push(Box::new(MinAggregationScanner::new(
+                    config.min_count,
+                    &config.count_column,
+                ))),
+                _ => bail!("Unknown output scanner: {}", name),
+            }
+        }
+        Ok(Self { scanners })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.scanners.is_empty()
+    }
+
+    /// Runs every scanner on the output, returning the findings of those
+    /// which flag it.
+    pub(crate) fn scan(&self, output: &str, content: &[u8]) -> Vec<ScanFinding> {
+        self.scanners
+            .iter()
+            .filter_map(|scanner| {
+                scanner.scan(content).map(|reason| ScanFinding {
+                    output: output.to_string(),
+                    scanner: scanner.name().to_string(),
+                    reason,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_pii_scanner() {
+        let scanner = PiiScanner;
+        assert!(scanner.scan(b"accuracy,0.92\nloss,0.13").is_none());
+        assert!(scanner.scan(b"id,name\n1,2019-10-12").is_none());
+
+        let reason = scanner.scan(b"name,email\nalice,<alice@example.com>");
+        assert_eq!(reason.unwrap(), "email address in line 2");
+        let reason = scanner.scan(b"card: 4111 1111 1111 1111");
+        assert_eq!(reason.unwrap(), "card number in line 1");
+        // Fails the Luhn check.
+        assert!(scanner.scan(b"4111 1111 1111 1112").is_none());
+        let reason = scanner.scan(b"ssn=078-05-1120");
+        assert_eq!(reason.unwrap(), "social security number in line 1");
+    }
+
+    pub fn test_min_aggregation_scanner() {
+        let scanner = MinAggregationScanner::new(10, "count");
+        assert!(scanner.scan(b"zip,count\n94105,12\n94107,30").is_none());
+        // Outputs without the count column are not checked.
+        assert!(scanner.scan(b"zip,total\n94105,1").is_none());
+
+        let reason = scanner.scan(b"zip, count\n94105,12\n94107,3");
+        assert_eq!(reason.unwrap(), "count of \"3\" below 10 in line 3");
+    }
+
+    pub fn test_output_scanners() {
+        let config = OutputScanConfig {
+            scanners: vec!["pii".to_string(), "min_aggregation".to_string()],
+            ..OutputScanConfig::default()
+        };
+        let scanners = OutputScanners::from_config(&config).unwrap();
+        assert!(!scanners.is_empty());
+
+        let findings = scanners.scan("report", b"email,count\nbob@example.com,1");
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].scanner, "pii");
+        assert_eq!(findings[1].scanner, "min_aggregation");
+        assert_eq!(findings[0].output, "report");
+
+        let config = OutputScanConfig {
+            scanners: vec!["wasm".to_string()],
+            ..OutputScanConfig::default()
+        };
+        assert!(OutputScanners::from_config(&config).is_err());
+    }
+}
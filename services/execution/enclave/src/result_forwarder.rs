@@ -0,0 +1,101 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::prelude::v1::*;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, SgxMutex as Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use teaclave_proto::teaclave_scheduler_service::*;
+use teaclave_types::{FunctionLog, ResultStream};
+
+use anyhow::Result;
+use uuid::Uuid;
+
+// Interval at which the intermediate results and log messages of a running
+// function are sent to the scheduler service.
+const FORWARD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Sends the intermediate results and log messages emitted by a running
+/// function to the scheduler service from a background thread, so that
+/// clients can fetch them before the task finishes.
+pub(crate) struct ResultForwarder {
+    stop: Sender<()>,
+    handle: JoinHandle<usize>,
+}
+
+impl ResultForwarder {
+    pub(crate) fn spawn(
+        task_id: Uuid,
+        stream: ResultStream,
+        log: FunctionLog,
+        scheduler_client: Arc<Mutex<TeaclaveSchedulerClient>>,
+    ) -> Self {
+        let (stop, stopped) = channel();
+        let handle = std::thread::spawn(move || {
+            // Bytes of the log sent so far.
+            let mut log_offset = 0;
+            loop {
+                let stopping = match stopped.recv_timeout(FORWARD_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => false,
+                    _ => true,
+                };
+                match forward(&task_id, &stream, &log, log_offset, &scheduler_client) {
+                    Ok(offset) => log_offset = offset,
+                    Err(e) => log::warn!("Cannot forward results of task {}: {:?}", task_id, e),
+                }
+                if stopping {
+                    break log_offset;
+                }
+            }
+        });
+        Self { stop, handle }
+    }
+
+    /// Sends the chunks left and waits for the thread to exit. Called before
+    /// the result of the task is sent, after which chunks are dropped.
+    /// Returns the bytes of the log sent, the rest is sent with the result.
+    pub(crate) fn finish(self) -> usize {
+        let _ = self.stop.send(());
+        self.handle.join().unwrap_or(0)
+    }
+}
+
+fn forward(
+    task_id: &Uuid,
+    stream: &ResultStream,
+    log: &FunctionLog,
+    log_offset: usize,
+    scheduler_client: &Arc<Mutex<TeaclaveSchedulerClient>>,
+) -> Result<usize> {
+    let (chunks, truncated) = stream.take();
+    let (log_content, log_truncated) = log.since(log_offset);
+    if chunks.is_empty() && !truncated && log_content.is_empty() {
+        return Ok(log_offset);
+    }
+    // The offset advances once the log is sent, so that it is sent again
+    // after errors.
+    let sent = log_offset + log_content.len();
+    let request = AppendTaskResultChunksRequest::new(*task_id, chunks, truncated)
+        .log(log_content, log_truncated);
+    let _response = scheduler_client
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Cannot lock scheduler client"))?
+        .append_task_result_chunks(request)?;
+    Ok(sent)
+}
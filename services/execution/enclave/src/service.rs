@@ -18,9 +18,14 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::prelude::v1::*;
-use std::sync::{Arc, SgxMutex as Mutex};
+use std::sync::{Arc, SgxMutex as Mutex, SgxRwLock as RwLock};
 
+use crate::executor_enclave::ExecutorEnclaves;
+use crate::output_scan::OutputScanners;
+use crate::result_forwarder::ResultForwarder;
+use crate::slot_pool::{Slot, SlotPool};
 use crate::task_file_manager::TaskFileManager;
+use teaclave_attestation::AttestedTlsConfig;
 use teaclave_proto::teaclave_scheduler_service::*;
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_types::*;
@@ -30,18 +35,46 @@ use anyhow::Result;
 use uuid::Uuid;
 
 static WORKER_BASE_DIR: &str = "/tmp/teaclave_agent/";
+// Outputs of functions which wrote too much to them, kept for operators
+// instead of being uploaded.
+static QUARANTINE_DIR: &str = "/tmp/teaclave_agent/quarantine/";
 
 #[derive(Clone)]
 pub(crate) struct TeaclaveExecutionService {
     worker: Arc<Worker>,
     scheduler_client: Arc<Mutex<TeaclaveSchedulerClient>>,
     fusion_base: PathBuf,
+    worker_id: String,
+    // offset of the scheduler clock estimated from the last heartbeat
+    clock_offset: ClockOffset,
+    scanners: Arc<OutputScanners>,
+    executor_enclaves: Arc<ExecutorEnclaves>,
+    // Key and attestation report signing the receipts of deterministic tasks
+    attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+    capacity: NodeCapacity,
+    labels: Vec<String>,
+    registered: bool,
+    slots: SlotPool,
+    // MesaPy functions run one at a time, as the interpreter is not
+    // reentrant.
+    mesapy_lock: Arc<Mutex<()>>,
+    // Tokens of the running tasks, canceled when the scheduler preempts them
+    cancel_tokens: Arc<Mutex<HashMap<Uuid, CancelToken>>>,
+    // Held by the threads of the running tasks
+    running_tasks: Arc<()>,
 }
 
 impl TeaclaveExecutionService {
     pub(crate) fn new(
         scheduler_service_endpoint: Endpoint,
+        worker: Worker,
         fusion_base: impl AsRef<Path>,
+        scanners: OutputScanners,
+        executor_enclaves: ExecutorEnclaves,
+        attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+        capacity: NodeCapacity,
+        labels: Vec<String>,
+        slots: SlotPool,
     ) -> Result<Self> {
         let mut i = 0;
         let channel = loop {
@@ -58,15 +91,54 @@ impl TeaclaveExecutionService {
         let scheduler_client = Arc::new(Mutex::new(TeaclaveSchedulerClient::new(channel)?));
 
         Ok(TeaclaveExecutionService {
-            worker: Arc::new(Worker::default()),
+            worker: Arc::new(worker),
             scheduler_client,
             fusion_base: fusion_base.as_ref().to_owned(),
+            worker_id: platform::rand::new_uuid().to_string(),
+            clock_offset: ClockOffset::default(),
+            scanners: Arc::new(scanners),
+            executor_enclaves: Arc::new(executor_enclaves),
+            attested_tls_config,
+            capacity,
+            labels,
+            registered: false,
+            slots,
+            mesapy_lock: Arc::new(Mutex::new(())),
+            cancel_tokens: Arc::new(Mutex::new(HashMap::new())),
+            running_tasks: Arc::new(()),
         })
     }
 
+    // Pulls a task whenever a slot is free, and runs it in a thread of its
+    // own, which frees the slot once the result is sent and the function has
+    // returned. Returns once too
+    // many functions are left running, so that the enclave is restarted.
     pub(crate) fn start(&mut self) -> Result<()> {
         loop {
             std::thread::sleep(std::time::Duration::from_secs(3));
+            if !self.registered {
+                match self.register_node() {
+                    Ok(_) => self.registered = true,
+                    Err(e) => log::warn!("RegisterNode Error: {:?}", e),
+                }
+            }
+            if let Err(e) = self.heartbeat() {
+                log::warn!("Heartbeat Error: {:?}", e);
+            }
+            if self.worker.is_exhausted() {
+                // Only a restart frees the threads of the functions left
+                // running, after the results of the running tasks are sent.
+                anyhow::ensure!(
+                    Arc::strong_count(&self.running_tasks) > 1,
+                    "Too many functions left running ({}), restarting",
+                    self.worker.abandoned_threads()
+                );
+                continue;
+            }
+            let slot = match self.slots.try_acquire() {
+                Some(slot) => slot,
+                None => continue,
+            };
             let staged_task = match self.pull_task() {
                 Ok(staged_task) => staged_task,
                 Err(e) => {
@@ -74,23 +146,115 @@ impl TeaclaveExecutionService {
                     continue;
                 }
             };
+            let service = self.clone();
+            std::thread::spawn(move || service.run_task(staged_task, slot));
+        }
+    }
 
-            log::debug!("InvokeTask: {:?}", staged_task);
-            let result = self.invoke_task(&staged_task);
-            log::debug!("InvokeTask result: {:?}", result);
+    fn run_task(&self, staged_task: StagedTask, slot: Slot) {
+        // Continue the trace of the request invoking the task, so that the
+        // calls to the scheduler below are part of it.
+        let context = match &staged_task.trace_context {
+            Some(parent) => parent.child(),
+            None => TraceContext::new(),
+        };
+        log::debug!(
+            "invoke_task: task_id={} trace_id={} span_id={} parent_span_id={:?}",
+            staged_task.task_id,
+            context.trace_id,
+            context.span_id,
+            staged_task
+                .trace_context
+                .as_ref()
+                .map(|parent| &parent.span_id),
+        );
+        let _trace = teaclave_rpc::trace::enter(context);
+
+        log::debug!("InvokeTask: {:?}", staged_task);
+        let function_log = FunctionLog::new();
+        let result_stream = ResultStream::new();
+        let forwarder = ResultForwarder::spawn(
+            staged_task.task_id,
+            result_stream.clone(),
+            function_log.clone(),
+            self.scheduler_client.clone(),
+        );
+        let cancel_token = CancelToken::new();
+        self.set_cancel_token(staged_task.task_id, Some(cancel_token.clone()));
+        // Shared with the thread of the function, which may be left running
+        // after the task, e.g., once preempted.
+        let slot = Arc::new(slot);
+        let result = self.invoke_task(
+            &staged_task,
+            &function_log,
+            &result_stream,
+            cancel_token,
+            slot.clone(),
+        );
+        self.set_cancel_token(staged_task.task_id, None);
+        let log_offset = forwarder.finish();
+        log::debug!("InvokeTask result: {:?}", result);
+
+        if let Err(e) =
+            self.update_task_result(&staged_task.task_id, result, &function_log, log_offset)
+        {
+            log::error!("UpdateResult Error: {:?}", e);
+        }
+        drop(slot);
+    }
 
-            match self.update_task_result(&staged_task.task_id, result) {
-                Ok(_) => (),
-                Err(e) => {
-                    log::error!("UpdateResult Error: {:?}", e);
-                    continue;
-                }
+    fn register_node(&mut self) -> Result<()> {
+        let request = RegisterNodeRequest::new(&self.worker_id, self.capacity)
+            .labels(self.labels.clone())
+            .executors(self.worker.executors())
+            .builtin_functions(self.worker.builtin_functions());
+        self.scheduler_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Cannot lock scheduler client"))?
+            .register_node(request)?;
+        Ok(())
+    }
+
+    fn heartbeat(&mut self) -> Result<()> {
+        let request = HeartbeatRequest::new(&self.worker_id, self.clock_offset);
+        let sent_ms = platform::time::since_epoch().as_millis() as u64;
+        let response = self
+            .scheduler_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Cannot lock scheduler client"))?
+            .heartbeat(request)?;
+        let received_ms = platform::time::since_epoch().as_millis() as u64;
+        self.clock_offset = ClockOffset::estimate(sent_ms, response.scheduler_time_ms, received_ms);
+
+        log::debug!("Scheduler clock offset: {:?}", self.clock_offset);
+        let cancel_tokens = self
+            .cancel_tokens
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Cannot lock cancel tokens"))?;
+        for task_id in response.preempted.iter() {
+            if let Some(token) = cancel_tokens.get(task_id) {
+                log::info!("Task {} preempted", task_id);
+                token.cancel();
             }
         }
+        Ok(())
+    }
+
+    fn set_cancel_token(&self, task_id: Uuid, token: Option<CancelToken>) {
+        if let Ok(mut cancel_tokens) = self.cancel_tokens.lock() {
+            match token {
+                Some(token) => cancel_tokens.insert(task_id, token),
+                None => cancel_tokens.remove(&task_id),
+            };
+        }
     }
 
     fn pull_task(&mut self) -> Result<StagedTask> {
-        let request = PullTaskRequest {};
+        let request = PullTaskRequest::new(&self.worker_id)
+            .executor_enclaves(self.executor_enclaves.mr_enclaves())
+            .labels(self.labels.clone());
         let response = self
             .scheduler_client
             .clone()
@@ -102,7 +266,14 @@ impl TeaclaveExecutionService {
         Ok(response.staged_task)
     }
 
-    fn invoke_task(&mut self, task: &StagedTask) -> Result<TaskOutputs> {
+    fn invoke_task(
+        &self,
+        task: &StagedTask,
+        log: &FunctionLog,
+        result_stream: &ResultStream,
+        cancel_token: CancelToken,
+        slot: Arc<Slot>,
+    ) -> Result<TaskOutputs> {
         self.update_task_status(&task.task_id, TaskStatus::Running)?;
 
         let file_mgr = TaskFileManager::new(
@@ -111,24 +282,98 @@ impl TeaclaveExecutionService {
             &task.task_id,
             &task.input_data,
             &task.output_data,
-        )?;
-        let invocation = prepare_task(&task, &file_mgr)?;
+        )?
+        .staging_time(task.budget.staging_time);
+
+        // The outputs were held for review and have been released since.
+        if let Some(release) = &task.release {
+            let outputs_tag = file_mgr.release_outputs(release)?;
+            return Ok(TaskOutputs::new(release.return_value.clone(), outputs_tag));
+        }
+
+        let invocation = prepare_task(&task, &file_mgr)?
+            .log(log.clone())
+            .result_stream(result_stream.clone())
+            .cancel_token(cancel_token);
 
         log::debug!("Invoke function: {:?}", invocation);
-        let worker = Worker::default();
-        let summary = worker.invoke_function(invocation)?;
+        let result = match &task.executor_enclave {
+            Some(measurement) => {
+                self.executor_enclaves
+                    .invoke(measurement, task.task_id, invocation)
+            }
+            None if task.executor == Executor::MesaPy => {
+                let _mesapy = self
+                    .mesapy_lock
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("Cannot lock MesaPy"))?;
+                self.worker.invoke_function_holding(invocation, slot)
+            }
+            None => self.worker.invoke_function_holding(invocation, slot),
+        };
+        let summary = match result {
+            Ok(summary) => summary,
+            Err(e) => {
+                if let Some(TaskBudgetError::OutputTooLarge(_)) = e.downcast_ref() {
+                    let quarantine = Path::new(QUARANTINE_DIR).join(task.task_id.to_string());
+                    if let Err(e) = file_mgr.quarantine_outputs(&quarantine) {
+                        log::error!("Cannot quarantine outputs: {:?}", e);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        if !task.output_policies.is_empty() {
+            check_output_policies(&task.output_policies, &file_mgr)?;
+        }
+        let findings = scan_outputs(&self.scanners, &file_mgr, &summary)?;
+        if !findings.is_empty() {
+            let files = file_mgr.hold_outputs(&task.task_id)?;
+            let held = HeldOutputs::new(task.task_id, files, summary.into_bytes(), findings);
+            return Err(OutputsHeld(held).into());
+        }
 
         let outputs_tag = finalize_task(&file_mgr)?;
         let task_outputs = TaskOutputs::new(summary.as_bytes(), outputs_tag);
+        if task.deterministic {
+            let receipt = self.execution_receipt(task, &task_outputs)?;
+            return Ok(task_outputs.receipt(receipt));
+        }
         Ok(task_outputs)
     }
 
+    // Signs what the task ran and produced with the key of the attested TLS
+    // certificate, whose attestation report lets result consumers check
+    // that this enclave produced the outputs.
+    fn execution_receipt(&self, task: &StagedTask, outputs: &TaskOutputs) -> Result<Vec<u8>> {
+        let config = self
+            .attested_tls_config
+            .read()
+            .map_err(|_| anyhow::anyhow!("Cannot lock attested TLS config"))?;
+        let claims = ExecutionReceiptClaims::new(
+            "teaclave_execution_service",
+            ExternalID::new(TaskState::key_prefix(), task.task_id).to_string(),
+            task,
+            outputs,
+            platform::time::since_epoch().as_secs(),
+            config.attestation_summary()?,
+        );
+        let token = CoseSign1::sign(claims.to_vec(), &config.cert, &config.private_key)?;
+        Ok(token.to_vec())
+    }
+
     fn update_task_result(
-        &mut self,
+        &self,
         task_id: &Uuid,
         task_result: Result<TaskOutputs>,
+        log: &FunctionLog,
+        log_offset: usize,
     ) -> Result<()> {
-        let request = UpdateTaskResultRequest::new(*task_id, task_result);
+        // The log not yet forwarded is sent also for failed tasks, to help
+        // debugging them.
+        let (content, truncated) = log.since(log_offset);
+        let request = UpdateTaskResultRequest::new(*task_id, task_result).log(content, truncated);
 
         let _response = self
             .scheduler_client
@@ -140,7 +385,7 @@ impl TeaclaveExecutionService {
         Ok(())
     }
 
-    fn update_task_status(&mut self, task_id: &Uuid, task_status: TaskStatus) -> Result<()> {
+    fn update_task_status(&self, task_id: &Uuid, task_status: TaskStatus) -> Result<()> {
         let request = UpdateTaskStatusRequest::new(task_id.to_owned(), task_status);
         let _response = self
             .scheduler_client
@@ -156,20 +401,54 @@ impl TeaclaveExecutionService {
 fn prepare_task(task: &StagedTask, file_mgr: &TaskFileManager) -> Result<StagedFunction> {
     let input_files = file_mgr.prepare_staged_inputs()?;
     let output_files = file_mgr.prepare_staged_outputs()?;
-    let function_payload = String::from_utf8_lossy(&task.function_payload).to_string();
 
     let staged_function = StagedFunction::new()
         .executor_type(task.executor_type)
         .executor(task.executor)
         .name(&task.function_name)
         .arguments(task.function_arguments.clone())
-        .payload(function_payload)
+        .payload(task.function_payload.clone())
+        .bundle(task.function_bundle.clone())
         .input_files(input_files)
         .output_files(output_files)
-        .runtime_name("default");
+        .runtime_name("default")
+        .time_limit(task.budget.execution_time)
+        .output_size_limit(task.budget.output_size)
+        .env(task.env.clone());
     Ok(staged_function)
 }
 
+// Outputs are checked against the policies of the function before any of
+// them is uploaded, and the task fails if one is violated.
+fn check_output_policies(
+    policies: &HashMap<String, OutputPolicy>,
+    file_mgr: &TaskFileManager,
+) -> Result<()> {
+    for (output, content) in file_mgr.read_staged_outputs()? {
+        if let Some(policy) = policies.get(&output) {
+            policy
+                .check(&content)
+                .map_err(|e| anyhow::anyhow!("Output {} violates its policy: {}", output, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn scan_outputs(
+    scanners: &OutputScanners,
+    file_mgr: &TaskFileManager,
+    summary: &str,
+) -> Result<Vec<ScanFinding>> {
+    if scanners.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut findings = scanners.scan("return_value", summary.as_bytes());
+    for (output, content) in file_mgr.read_staged_outputs()? {
+        findings.extend(scanners.scan(&output, &content));
+    }
+    Ok(findings)
+}
+
 fn finalize_task(file_mgr: &TaskFileManager) -> Result<HashMap<String, FileAuthTag>> {
     file_mgr.upload_outputs()
 }
@@ -179,12 +458,12 @@ pub mod tests {
     use super::*;
     use serde_json::json;
     use std::format;
+    use std::untrusted::path::PathEx;
     use teaclave_crypto::*;
     use url::Url;
-    use uuid::Uuid;
 
     pub fn test_invoke_echo() {
-        let task_id = Uuid::new_v4();
+        let task_id = platform::rand::new_uuid();
         let function_arguments =
             FunctionArguments::from_json(json!({"message": "Hello, Teaclave!"})).unwrap();
         let staged_task = StagedTask::new()
@@ -211,8 +490,41 @@ pub mod tests {
         assert_eq!(result.unwrap(), "Hello, Teaclave!");
     }
 
-    pub fn test_invoke_gbdt_train() {
-        let task_id = Uuid::new_v4();
+    pub fn test_scan_outputs() {
+        let function_arguments =
+            FunctionArguments::from_json(json!({"message": "Mail alice@example.com"})).unwrap();
+        let staged_task = StagedTask::new()
+            .task_id(platform::rand::new_uuid())
+            .executor(Executor::Builtin)
+            .function_name("builtin-echo")
+            .function_arguments(function_arguments);
+
+        let file_mgr = TaskFileManager::new(
+            WORKER_BASE_DIR,
+            "/tmp/fusion_base",
+            &staged_task.task_id,
+            &staged_task.input_data,
+            &staged_task.output_data,
+        )
+        .unwrap();
+        let invocation = prepare_task(&staged_task, &file_mgr).unwrap();
+        let summary = Worker::default().invoke_function(invocation).unwrap();
+
+        let scanners = OutputScanners::default();
+        let findings = scan_outputs(&scanners, &file_mgr, &summary).unwrap();
+        assert!(findings.is_empty());
+
+        let config = teaclave_config::OutputScanConfig {
+            scanners: vec!["pii".to_string()],
+            ..Default::default()
+        };
+        let scanners = OutputScanners::from_config(&config).unwrap();
+        let findings = scan_outputs(&scanners, &file_mgr, &summary).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].output, "return_value");
+    }
+
+    fn gbdt_train_task(task_id: Uuid) -> StagedTask {
         let function_arguments = FunctionArguments::from_json(json!({
             "feature_size": 4,
             "max_depth": 4,
@@ -244,13 +556,17 @@ pub mod tests {
         let input_data = hashmap!("training_data" => training_input_data);
         let output_data = hashmap!("trained_model" => model_output_data);
 
-        let staged_task = StagedTask::new()
+        StagedTask::new()
             .task_id(task_id)
             .executor(Executor::Builtin)
             .function_name("builtin-gbdt-train")
             .function_arguments(function_arguments)
             .input_data(input_data)
-            .output_data(output_data);
+            .output_data(output_data)
+    }
+
+    pub fn test_invoke_gbdt_train() {
+        let staged_task = gbdt_train_task(platform::rand::new_uuid());
 
         let file_mgr = TaskFileManager::new(
             WORKER_BASE_DIR,
@@ -270,4 +586,56 @@ pub mod tests {
         log::debug!("summary: {:?}", result);
         assert!(result.is_ok());
     }
+
+    pub fn test_check_output_policies() {
+        let json = OutputPolicy::new().format(OutputFormat::Json);
+        let staged_task = gbdt_train_task(platform::rand::new_uuid())
+            .output_policy("trained_model", json.clone().max_bytes(16));
+
+        let file_mgr = TaskFileManager::new(
+            WORKER_BASE_DIR,
+            "/tmp/fusion_base",
+            &staged_task.task_id,
+            &staged_task.input_data,
+            &staged_task.output_data,
+        )
+        .unwrap();
+        let invocation = prepare_task(&staged_task, &file_mgr).unwrap();
+        Worker::default().invoke_function(invocation).unwrap();
+
+        // The model is in JSON, but longer than 16 bytes.
+        let error = check_output_policies(&staged_task.output_policies, &file_mgr).unwrap_err();
+        assert!(error.to_string().contains("trained_model"));
+        let policies = hashmap!("trained_model" => json);
+        assert!(check_output_policies(&policies, &file_mgr).is_ok());
+        let csv = OutputPolicy::new()
+            .format(OutputFormat::Csv)
+            .columns(vec!["count".to_string()]);
+        let policies = hashmap!("trained_model" => csv);
+        assert!(check_output_policies(&policies, &file_mgr).is_err());
+    }
+
+    pub fn test_invoke_output_too_large() {
+        let staged_task =
+            gbdt_train_task(platform::rand::new_uuid()).budget(TaskBudget::new().output_size(16));
+
+        let file_mgr = TaskFileManager::new(
+            WORKER_BASE_DIR,
+            "/tmp/fusion_base",
+            &staged_task.task_id,
+            &staged_task.input_data,
+            &staged_task.output_data,
+        )
+        .unwrap();
+        let invocation = prepare_task(&staged_task, &file_mgr).unwrap();
+
+        let worker = Worker::default();
+        let error = worker.invoke_function(invocation).unwrap_err();
+        let failure = TaskFailure::from(error);
+        assert_eq!(failure.kind, TaskFailureKind::OutputTooLarge);
+
+        let quarantine = Path::new(QUARANTINE_DIR).join(staged_task.task_id.to_string());
+        file_mgr.quarantine_outputs(&quarantine).unwrap();
+        assert!(quarantine.join("trained_model").exists());
+    }
 }
@@ -0,0 +1,135 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Slots of the execution service, each running a task in a thread of its
+//! own. A slot reserves a share of the memory of the node for the function
+//! it runs, and a task is only pulled once a slot and its memory are free.
+
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxMutex as Mutex};
+
+use anyhow::{ensure, Result};
+
+// Every slot may use three threads of the enclave: the task, the function
+// with a time or output limit, and the forwarder of its results. Bounded by
+// TCSNum of Enclave.config.xml, less the thread of the service. A function
+// left running in the background holds its slot until it returns.
+const MAX_SLOTS: u32 = 6;
+
+#[derive(Debug, Default)]
+struct SlotUsage {
+    running: u32,
+    reserved_mb: u64,
+}
+
+#[derive(Clone)]
+pub(crate) struct SlotPool {
+    slots: u32,
+    slot_memory_mb: u64,
+    // Zero for no memory accounting
+    memory_mb: u64,
+    usage: Arc<Mutex<SlotUsage>>,
+}
+
+impl SlotPool {
+    pub(crate) fn new(slots: u32, slot_memory_mb: u64, memory_mb: u64) -> Result<Self> {
+        ensure!(
+            slots >= 1 && slots <= MAX_SLOTS,
+            "Slots of the execution service not in 1..={}",
+            MAX_SLOTS
+        );
+        ensure!(
+            memory_mb == 0 || slot_memory_mb <= memory_mb,
+            "Memory of a slot ({} MB) over the memory of the node ({} MB)",
+            slot_memory_mb,
+            memory_mb
+        );
+        Ok(Self {
+            slots,
+            slot_memory_mb,
+            memory_mb,
+            usage: Arc::new(Mutex::new(SlotUsage::default())),
+        })
+    }
+
+    /// Tasks run at once, reported to the scheduler service: the slots
+    /// whose memory fits in the node.
+    pub(crate) fn capacity(&self) -> u32 {
+        match self.memory_mb.checked_div(self.slot_memory_mb) {
+            Some(fitting) if self.memory_mb > 0 => std::cmp::min(self.slots as u64, fitting) as u32,
+            _ => self.slots,
+        }
+    }
+
+    /// Reserves a slot and its memory, released when the slot is dropped.
+    pub(crate) fn try_acquire(&self) -> Option<Slot> {
+        let mut usage = self.usage.lock().ok()?;
+        let reserved_mb = usage.reserved_mb + self.slot_memory_mb;
+        if usage.running >= self.slots || (self.memory_mb > 0 && reserved_mb > self.memory_mb) {
+            return None;
+        }
+        usage.running += 1;
+        usage.reserved_mb = reserved_mb;
+        Some(Slot {
+            memory_mb: self.slot_memory_mb,
+            usage: self.usage.clone(),
+        })
+    }
+}
+
+/// A slot running a task.
+pub(crate) struct Slot {
+    memory_mb: u64,
+    usage: Arc<Mutex<SlotUsage>>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Ok(mut usage) = self.usage.lock() {
+            usage.running -= 1;
+            usage.reserved_mb -= self.memory_mb;
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_slot_pool() {
+        let pool = SlotPool::new(2, 0, 0).unwrap();
+        assert_eq!(pool.capacity(), 2);
+        let first = pool.try_acquire().unwrap();
+        let second = pool.try_acquire().unwrap();
+        assert!(pool.try_acquire().is_none());
+        drop(first);
+        assert!(pool.try_acquire().is_some());
+        drop(second);
+
+        // Only two slots of 1 GB fit in the memory of the node.
+        let pool = SlotPool::new(4, 1024, 2560).unwrap();
+        assert_eq!(pool.capacity(), 2);
+        let slots: Vec<Slot> = (0..2).map(|_| pool.try_acquire().unwrap()).collect();
+        assert!(pool.try_acquire().is_none());
+        drop(slots);
+        assert!(pool.try_acquire().is_some());
+
+        assert!(SlotPool::new(0, 0, 0).is_err());
+        assert!(SlotPool::new(MAX_SLOTS + 1, 0, 0).is_err());
+        assert!(SlotPool::new(1, 4096, 2048).is_err());
+    }
+}
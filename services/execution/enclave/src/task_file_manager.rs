@@ -17,11 +17,14 @@
 
 use crate::ocall::handle_file_request;
 use anyhow::Result;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
 use std::prelude::v1::*;
+use std::time::{Duration, SystemTime};
 use std::untrusted::path::PathEx;
+use std::untrusted::time::SystemTimeEx;
 use teaclave_crypto::TeaclaveFile128Key;
 use teaclave_types::*;
 use url::Url;
@@ -31,6 +34,9 @@ pub(crate) struct TaskFileManager {
     inter_inputs: InterInputs,
     inter_outputs: InterOutputs,
     fusion_base: PathBuf,
+    // Time limit shared by downloading inputs and uploading outputs.
+    staging_time: Option<Duration>,
+    staging_elapsed: Cell<Duration>,
 }
 
 struct InterInputs {
@@ -74,13 +80,22 @@ impl TaskFileManager {
             inter_inputs,
             inter_outputs,
             fusion_base: fusion_base.as_ref().to_owned(),
+            staging_time: None,
+            staging_elapsed: Cell::new(Duration::default()),
         };
 
         Ok(tfmgr)
     }
 
+    pub(crate) fn staging_time(self, staging_time: Option<Duration>) -> Self {
+        Self {
+            staging_time,
+            ..self
+        }
+    }
+
     pub(crate) fn prepare_staged_inputs(&self) -> Result<StagedFiles> {
-        self.inter_inputs.download(&self.fusion_base)?;
+        self.with_staging_time(|timeout| self.inter_inputs.download(&self.fusion_base, timeout))?;
         self.inter_inputs.convert_to_staged_files()
     }
 
@@ -91,9 +106,115 @@ impl TaskFileManager {
 
     pub(crate) fn upload_outputs(&self) -> Result<HashMap<String, FileAuthTag>> {
         let auth_tags = self.inter_outputs.convert_staged_files_for_upload()?;
-        self.inter_outputs.upload(&self.fusion_base)?;
+        self.with_staging_time(|timeout| self.inter_outputs.upload(&self.fusion_base, timeout))?;
         Ok(auth_tags)
     }
+
+    /// Reads the content of the staged outputs written by the function, to
+    /// scan them before they are uploaded.
+    pub(crate) fn read_staged_outputs(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        self.inter_outputs
+            .inner
+            .iter()
+            .filter(|inter_output| inter_output.staged_info.path.exists())
+            .map(|inter_output| {
+                let content = inter_output.staged_info.get_plaintext()?;
+                Ok((inter_output.funiq_key.clone(), content))
+            })
+            .collect()
+    }
+
+    /// Encrypts the staged outputs for their owners like `upload_outputs`,
+    /// but uploads them to the fusion storage instead of their URLs, where
+    /// they wait for their review.
+    pub(crate) fn hold_outputs(&self, task_id: &Uuid) -> Result<HashMap<String, HeldFile>> {
+        let mut auth_tags = self.inter_outputs.convert_staged_files_for_upload()?;
+        let held_files = self
+            .inter_outputs
+            .inner
+            .iter()
+            .map(|inter_output| {
+                let key = &inter_output.funiq_key;
+                let url = Url::parse(&format!(
+                    "fusion:///TEACLAVE_FUSION_BASE/{}-{}.held",
+                    task_id, key
+                ))?;
+                let cmac = auth_tags
+                    .remove(key)
+                    .ok_or_else(|| anyhow::anyhow!("Missing tag of output: {}", key))?;
+                Ok((key.clone(), HeldFile { url, cmac }))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        self.with_staging_time(|timeout| {
+            self.inter_outputs
+                .upload_to(&self.fusion_base, timeout, |inter_output| {
+                    held_files[&inter_output.funiq_key].url.clone()
+                })
+        })?;
+        Ok(held_files)
+    }
+
+    /// Uploads the outputs held for review to their URLs after they have
+    /// been released.
+    pub(crate) fn release_outputs(
+        &self,
+        release: &OutputRelease,
+    ) -> Result<HashMap<String, FileAuthTag>> {
+        for inter_output in self.inter_outputs.inner.iter() {
+            anyhow::ensure!(
+                release.files.contains_key(&inter_output.funiq_key),
+                "Output not held: {}",
+                inter_output.funiq_key
+            );
+        }
+        self.with_staging_time(|timeout| {
+            self.inter_outputs
+                .download_held(&release.files, &self.fusion_base, timeout)
+        })?;
+        self.with_staging_time(|timeout| self.inter_outputs.upload(&self.fusion_base, timeout))?;
+        let auth_tags = release
+            .files
+            .iter()
+            .map(|(key, held_file)| (key.clone(), held_file.cmac))
+            .collect();
+        Ok(auth_tags)
+    }
+
+    /// Moves the staged outputs written by the function to `quarantine`,
+    /// named after their keys, without uploading them.
+    pub(crate) fn quarantine_outputs(&self, quarantine: impl AsRef<Path>) -> Result<()> {
+        std::untrusted::fs::create_dir_all(quarantine.as_ref())?;
+        for inter_output in self.inter_outputs.inner.iter() {
+            let staged_path = &inter_output.staged_info.path;
+            if !staged_path.exists() {
+                continue;
+            }
+            let dest = quarantine.as_ref().join(&inter_output.funiq_key);
+            std::untrusted::fs::rename(staged_path, dest)?;
+        }
+        Ok(())
+    }
+
+    // Runs a file agent request bounded by the remaining staging time.
+    fn with_staging_time(
+        &self,
+        request: impl FnOnce(Option<Duration>) -> Result<()>,
+    ) -> Result<()> {
+        let timeout = match self.staging_time {
+            Some(staging_time) => match staging_time.checked_sub(self.staging_elapsed.get()) {
+                Some(remaining) if remaining > Duration::default() => Some(remaining),
+                _ => return Err(TaskBudgetError::StagingTimeout.into()),
+            },
+            None => None,
+        };
+
+        let start = SystemTime::now();
+        let result = request(timeout);
+        let elapsed = SystemTime::now().duration_since(start).unwrap_or_default();
+        self.staging_elapsed
+            .set(self.staging_elapsed.get() + elapsed);
+        result
+    }
 }
 
 impl InterInput {
@@ -153,6 +274,38 @@ impl InterInput {
                 crypto.decrypt(&mut bytes)?;
                 StagedFileInfo::create_with_bytes(dst, &bytes)?
             }
+            FileCrypto::AesGcmSiv256(crypto) => {
+                let mut bytes = read_all_bytes(src)?;
+                let n = bytes.len();
+                anyhow::ensure!(
+                    n > FILE_AUTH_TAG_LENGTH,
+                    "AesGcmSiv256 File, invalid length: {:?}",
+                    src
+                );
+                anyhow::ensure!(
+                    self.file.cmac == bytes[n - FILE_AUTH_TAG_LENGTH..],
+                    "AesGcmSiv256 File, invalid tag: {:?}",
+                    src
+                );
+                crypto.decrypt(&mut bytes)?;
+                StagedFileInfo::create_with_bytes(dst, &bytes)?
+            }
+            FileCrypto::XChaCha20Poly1305(crypto) => {
+                let mut bytes = read_all_bytes(src)?;
+                let n = bytes.len();
+                anyhow::ensure!(
+                    n > FILE_AUTH_TAG_LENGTH,
+                    "XChaCha20Poly1305 File, invalid length: {:?}",
+                    src
+                );
+                anyhow::ensure!(
+                    self.file.cmac == bytes[n - FILE_AUTH_TAG_LENGTH..],
+                    "XChaCha20Poly1305 File, invalid tag: {:?}",
+                    src
+                );
+                crypto.decrypt(&mut bytes)?;
+                StagedFileInfo::create_with_bytes(dst, &bytes)?
+            }
             FileCrypto::Raw => {
                 let bytes = read_all_bytes(src)?;
                 StagedFileInfo::create_with_bytes(dst, &bytes)?
@@ -178,12 +331,29 @@ impl InterInputs {
             .collect()
     }
 
-    pub(crate) fn download(&self, fusion_base: impl AsRef<Path>) -> Result<()> {
-        let req_info = self.inner.iter().map(|inter_input| {
-            HandleFileInfo::new(&inter_input.download_path, &inter_input.file.url)
-        });
+    pub(crate) fn download(
+        &self,
+        fusion_base: impl AsRef<Path>,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        // Inline inputs are shipped with the task, write them out directly
+        // instead of asking the file agent to fetch them.
+        for inter_input in self.inner.iter() {
+            if let Some(content) = &inter_input.file.content {
+                std::untrusted::fs::write(&inter_input.download_path, content)?;
+            }
+        }
+
+        let req_info = self
+            .inner
+            .iter()
+            .filter(|inter_input| inter_input.file.content.is_none())
+            .map(|inter_input| {
+                HandleFileInfo::new(&inter_input.download_path, &inter_input.file.url)
+            });
         let request =
-            FileAgentRequest::new(HandleFileCommand::Download, req_info, fusion_base.as_ref());
+            FileAgentRequest::new(HandleFileCommand::Download, req_info, fusion_base.as_ref())
+                .timeout(timeout);
         log::debug!("Ocall file download request: {:?}", request);
         handle_file_request(request)?;
         Ok(())
@@ -237,6 +407,12 @@ impl InterOutput {
             FileCrypto::AesGcm256(_) => {
                 anyhow::bail!("OutputFile: unsupported type");
             }
+            FileCrypto::AesGcmSiv256(_) => {
+                anyhow::bail!("OutputFile: unsupported type");
+            }
+            FileCrypto::XChaCha20Poly1305(_) => {
+                anyhow::bail!("OutputFile: unsupported type");
+            }
             FileCrypto::Raw => {
                 anyhow::bail!("OutputFile: unsupported type");
             }
@@ -279,16 +455,55 @@ impl InterOutputs {
             .collect()
     }
 
-    pub(crate) fn upload(&self, fusion_base: impl AsRef<Path>) -> Result<()> {
+    pub(crate) fn upload(
+        &self,
+        fusion_base: impl AsRef<Path>,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        self.upload_to(fusion_base, timeout, |inter_output| {
+            inter_output.file.url.clone()
+        })
+    }
+
+    // Uploads the converted outputs to the URLs given by `url_of`.
+    fn upload_to(
+        &self,
+        fusion_base: impl AsRef<Path>,
+        timeout: Option<Duration>,
+        url_of: impl Fn(&InterOutput) -> Url,
+    ) -> Result<()> {
         let req_info = self.inner.iter().map(|inter_output| {
-            HandleFileInfo::new(&inter_output.upload_path, &inter_output.file.url)
+            HandleFileInfo::new(&inter_output.upload_path, &url_of(inter_output))
         });
         let request =
-            FileAgentRequest::new(HandleFileCommand::Upload, req_info, fusion_base.as_ref());
+            FileAgentRequest::new(HandleFileCommand::Upload, req_info, fusion_base.as_ref())
+                .timeout(timeout);
         log::debug!("Ocall file upload request: {:?}", request);
         handle_file_request(request)?;
         Ok(())
     }
+
+    // Downloads the held outputs from the fusion storage in place of the
+    // converted outputs.
+    fn download_held(
+        &self,
+        held_files: &HashMap<String, HeldFile>,
+        fusion_base: impl AsRef<Path>,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        let req_info = self.inner.iter().map(|inter_output| {
+            HandleFileInfo::new(
+                &inter_output.upload_path,
+                &held_files[&inter_output.funiq_key].url,
+            )
+        });
+        let request =
+            FileAgentRequest::new(HandleFileCommand::Download, req_info, fusion_base.as_ref())
+                .timeout(timeout);
+        log::debug!("Ocall file download request: {:?}", request);
+        handle_file_request(request)?;
+        Ok(())
+    }
 }
 
 // Staged file is put in $base_dir/${funiq_key}-staged/$original_name
@@ -339,7 +554,7 @@ pub mod tests {
         let input_file = FunctionInputFile::new(input_url, tag, crypto);
         let inputs = hashmap!("training_data" => input_file);
         let outputs = hashmap!();
-        let task_id = Uuid::new_v4();
+        let task_id = platform::rand::new_uuid();
 
         let file_mgr = TaskFileManager::new(
             "/tmp",
@@ -352,4 +567,28 @@ pub mod tests {
         file_mgr.prepare_staged_inputs().unwrap();
         file_mgr.prepare_staged_outputs().unwrap();
     }
+
+    pub fn test_staging_time_limit() {
+        let input_url =
+            Url::parse("http://localhost:6789/fixtures/functions/gbdt_training/train.enc").unwrap();
+        let tag = FileAuthTag::from_hex("881adca6b0524472da0a9d0bb02b9af9").unwrap();
+        let crypto = TeaclaveFile128Key::new(&[0; 16]).unwrap();
+        let input_file = FunctionInputFile::new(input_url, tag, crypto);
+        let inputs = hashmap!("training_data" => input_file);
+        let outputs = hashmap!();
+        let task_id = platform::rand::new_uuid();
+
+        let file_mgr = TaskFileManager::new(
+            "/tmp",
+            "/tmp/fusion_base",
+            &task_id,
+            &inputs.into(),
+            &outputs.into(),
+        )
+        .unwrap()
+        .staging_time(Some(Duration::from_secs(0)));
+        let error = file_mgr.prepare_staged_inputs().unwrap_err();
+        let failure = TaskFailure::from(error);
+        assert_eq!(failure.kind, TaskFailureKind::StagingTimeout);
+    }
 }
@@ -24,8 +24,12 @@ use thiserror::Error;
 pub(crate) enum TeaclaveFrontendError {
     #[error("authentication error")]
     AuthenticationError,
+    #[error("impersonation error")]
+    ImpersonationError,
     #[error("lock error")]
     LockError,
+    #[error("quota exceeded: {0}")]
+    QuotaExceeded(String),
 }
 
 impl From<TeaclaveFrontendError> for TeaclaveServiceResponseError {
@@ -0,0 +1,66 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Health checks of the dependencies, served without authentication from the
+// last result. At most one request refreshes them once they are older than
+// MAX_AGE_SECS; other requests meanwhile get the last result, so that clients
+// cannot make the frontend lock its clients and call other services at will.
+
+use std::prelude::v1::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, SgxRwLock as RwLock};
+use teaclave_proto::teaclave_common::HealthCheck;
+use teaclave_types::platform;
+
+const MAX_AGE_SECS: u64 = 10;
+
+#[derive(Clone, Default)]
+pub(crate) struct HealthCache {
+    // seconds since the Unix epoch of the checks, and the checks
+    checks: Arc<RwLock<Option<(u64, Vec<HealthCheck>)>>>,
+    refreshing: Arc<AtomicBool>,
+}
+
+impl HealthCache {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the last checks, refreshed with `check` first if they are stale
+    // and no other request is refreshing them.
+    pub(crate) fn get(&self, check: impl FnOnce() -> Vec<HealthCheck>) -> Vec<HealthCheck> {
+        let now = platform::time::since_epoch().as_secs();
+        let last = self.checks.read().ok().and_then(|checks| checks.clone());
+        if let Some((checked_at, checks)) = &last {
+            if now.saturating_sub(*checked_at) < MAX_AGE_SECS {
+                return checks.clone();
+            }
+        }
+        if self.refreshing.swap(true, Ordering::AcqRel) {
+            return match last {
+                Some((_, checks)) => checks,
+                None => vec![HealthCheck::unhealthy("dependencies", "not checked yet")],
+            };
+        }
+        let checks = check();
+        if let Ok(mut last) = self.checks.write() {
+            *last = Some((now, checks.clone()));
+        }
+        self.refreshing.store(false, Ordering::Release);
+        checks
+    }
+}
@@ -25,6 +25,7 @@ extern crate log;
 use anyhow::{anyhow, Result};
 
 use std::prelude::v1::*;
+use std::sync::Arc;
 use teaclave_attestation::verifier;
 use teaclave_attestation::{AttestationConfig, RemoteAttestation};
 use teaclave_binder::proto::{
@@ -32,21 +33,73 @@ use teaclave_binder::proto::{
     StartServiceInput, StartServiceOutput,
 };
 use teaclave_binder::{handle_ecall, register_ecall_handler};
-use teaclave_config::build::AS_ROOT_CA_CERT;
+use teaclave_config::build::{AS_ROOT_CA_CERT, ATTESTATION_GATE};
 use teaclave_config::RuntimeConfig;
 use teaclave_proto::teaclave_frontend_service::{
-    TeaclaveFrontendRequest, TeaclaveFrontendResponse,
+    GetPlatformInfoResponse, TeaclaveFrontendRequest, TeaclaveFrontendResponse,
 };
-use teaclave_rpc::config::SgxTrustedTlsServerConfig;
+use teaclave_rpc::channel::ChannelPoolConfig;
+use teaclave_rpc::config::{SgxTrustedTlsServerConfig, TlsPolicy};
+use teaclave_rpc::gate::AttestationGate;
+use teaclave_rpc::interceptor::LoggingInterceptor;
+use teaclave_rpc::rate_limit::RateLimitInterceptor;
 use teaclave_rpc::server::SgxTrustedTlsServer;
 use teaclave_service_enclave_utils::{
     create_trusted_authentication_endpoint, create_trusted_management_endpoint, ServiceEnclave,
 };
-use teaclave_types::{TeeServiceError, TeeServiceResult};
+use teaclave_types::{
+    AesGcm128Key, AesGcm256Key, AesGcmSiv256Key, Executor, TeaclaveFile128Key, TeeServiceError,
+    TeeServiceResult, XChaCha20Poly1305Key,
+};
 
 mod error;
+mod health_cache;
 mod service;
 
+// Reads of the management service, which are retried even if the connection
+// failed after they were sent.
+const MANAGEMENT_IDEMPOTENT_REQUESTS: &[&str] = &[
+    "GetOutputFile",
+    "GetInputFile",
+    "GetFunction",
+    "GetTask",
+    "GetTaskResult",
+    "GetTaskLog",
+    "GetMeasurementInclusion",
+    "GetAccessControlPolicy",
+    "ExplainAccess",
+    "GetQuotaUsage",
+    "GetTenantStats",
+    "ListNodes",
+    "ListExecutors",
+    "ListUpcomingRuns",
+    "GetPipeline",
+    "ListFunctions",
+    "ListTasks",
+    "Health",
+];
+
+fn platform_info(config: &RuntimeConfig) -> GetPlatformInfoResponse {
+    let crypto_schemes = vec![
+        AesGcm128Key::SCHEMA.to_string(),
+        AesGcm256Key::SCHEMA.to_string(),
+        AesGcmSiv256Key::SCHEMA.to_string(),
+        XChaCha20Poly1305Key::SCHEMA.to_string(),
+        TeaclaveFile128Key::SCHEMA.to_string(),
+    ];
+    GetPlatformInfoResponse::new(env!("CARGO_PKG_VERSION"), &config.attestation.algorithm)
+        .executors(vec![
+            Executor::MesaPy,
+            Executor::Builtin,
+            Executor::WAMicroRuntime,
+        ])
+        .crypto_schemes(crypto_schemes)
+        .limits(
+            config.api_endpoints.frontend.message_limits.max_message_len,
+            config.limits.inline_data_max_size as u64,
+        )
+}
+
 fn start_service(config: &RuntimeConfig) -> Result<()> {
     let listen_address = config.api_endpoints.frontend.listen_address;
     let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
@@ -54,13 +107,32 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
-    let server_config =
-        SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config.clone())?;
+    let gate = AttestationGate::new(
+        ATTESTATION_GATE.methods,
+        ATTESTATION_GATE
+            .enclaves
+            .iter()
+            .map(|enclave| (enclave.mr_signer, enclave.mr_enclave)),
+        AS_ROOT_CA_CERT,
+    )?;
+    let mut server_config =
+        SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config.clone())?
+            .tls_policy(TlsPolicy::from_teaclave_config(&config))?;
+    if !gate.is_empty() {
+        server_config = server_config.optional_client_cert(AS_ROOT_CA_CERT);
+    }
 
     let mut server = SgxTrustedTlsServer::<TeaclaveFrontendResponse, TeaclaveFrontendRequest>::new(
         listen_address,
         server_config,
-    );
+    )
+    .message_limits(&config.api_endpoints.frontend.message_limits)
+    .attestation_gate(gate)
+    .interceptor(Arc::new(LoggingInterceptor::new("frontend")));
+    let rate_limit = RateLimitInterceptor::from_config(&config.rate_limit);
+    if !rate_limit.is_disabled() {
+        server = server.interceptor(Arc::new(rate_limit));
+    }
 
     let enclave_info = teaclave_types::EnclaveInfo::from_bytes(&config.audit.enclave_info_bytes);
     let authentication_service_endpoint = create_trusted_authentication_endpoint(
@@ -68,20 +140,28 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         &enclave_info,
         AS_ROOT_CA_CERT,
         verifier::universal_quote_verifier,
+        verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+        TlsPolicy::from_teaclave_config(&config),
         attested_tls_config.clone(),
-    )?;
+    )?
+    .message_limits(&config.internal_endpoints.authentication.message_limits);
 
     let management_service_endpoint = create_trusted_management_endpoint(
         &config.internal_endpoints.management.advertised_address,
         &enclave_info,
         AS_ROOT_CA_CERT,
         verifier::universal_quote_verifier,
+        verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+        TlsPolicy::from_teaclave_config(&config),
         attested_tls_config,
-    )?;
+    )?
+    .pool(ChannelPoolConfig::default().idempotent_requests(MANAGEMENT_IDEMPOTENT_REQUESTS))
+    .message_limits(&config.internal_endpoints.management.message_limits);
 
     let service = service::TeaclaveFrontendService::new(
         authentication_service_endpoint,
         management_service_endpoint,
+        platform_info(config),
     )?;
     match server.start(service) {
         Ok(_) => (),
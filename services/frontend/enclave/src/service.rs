@@ -16,46 +16,123 @@
 // under the License.
 
 use crate::error::TeaclaveFrontendError;
+use crate::health_cache::HealthCache;
 
 use anyhow::Result;
+use std::collections::HashMap;
 use std::prelude::v1::*;
 use std::sync::{Arc, SgxMutex as Mutex};
 
 use teaclave_proto::teaclave_authentication_service::{
-    TeaclaveAuthenticationInternalClient, UserAuthenticateRequest,
+    ImpersonationAuthenticateRequest, TeaclaveAuthenticationInternalClient, UserAuthenticateRequest,
 };
-use teaclave_proto::teaclave_common::UserCredential;
+use teaclave_proto::teaclave_common::{HealthCheck, UserCredential};
 use teaclave_proto::teaclave_frontend_service::{
     ApproveTaskRequest, ApproveTaskResponse, AssignDataRequest, AssignDataResponse,
-    CreateTaskRequest, CreateTaskResponse, GetFunctionRequest, GetFunctionResponse,
-    GetInputFileRequest, GetInputFileResponse, GetOutputFileRequest, GetOutputFileResponse,
-    GetTaskRequest, GetTaskResponse, InvokeTaskRequest, InvokeTaskResponse,
+    BeginPayloadUploadRequest, BeginPayloadUploadResponse, CheckConsistencyRequest,
+    CheckConsistencyResponse, CommitPayloadRequest, CommitPayloadResponse, CreatePipelineRequest,
+    CreatePipelineResponse, CreateScheduledTaskRequest, CreateScheduledTaskResponse,
+    CreateTaskRequest, CreateTaskResponse, CreateTasksRequest, CreateTasksResponse,
+    DeleteWebhookRequest, DeleteWebhookResponse, EnterReadOnlyModeRequest,
+    EnterReadOnlyModeResponse, ExitReadOnlyModeRequest, ExitReadOnlyModeResponse,
+    ExplainAccessRequest, ExplainAccessResponse, GetAccessControlPolicyRequest,
+    GetAccessControlPolicyResponse, GetFunctionRequest, GetFunctionResponse, GetInputFileRequest,
+    GetInputFileResponse, GetMeasurementInclusionRequest, GetMeasurementInclusionResponse,
+    GetOutputFileRequest, GetOutputFileResponse, GetPipelineRequest, GetPipelineResponse,
+    GetPlatformInfoRequest, GetPlatformInfoResponse, GetQuotaUsageRequest, GetQuotaUsageResponse,
+    GetTaskLogRequest, GetTaskLogResponse, GetTaskRequest, GetTaskResponse, GetTaskResultRequest,
+    GetTaskResultResponse, GetTenantStatsRequest, GetTenantStatsResponse, HealthRequest,
+    HealthResponse, InvokeTaskRequest, InvokeTaskResponse, InvokeTasksRequest, InvokeTasksResponse,
+    ListExecutorsRequest, ListExecutorsResponse, ListFunctionsRequest, ListFunctionsResponse,
+    ListNodesRequest, ListNodesResponse, ListTasksRequest, ListTasksResponse,
+    ListUpcomingRunsRequest, ListUpcomingRunsResponse, PauseScheduledTaskRequest,
+    PauseScheduledTaskResponse, RegisterExecutorEnclaveRequest, RegisterExecutorEnclaveResponse,
     RegisterFunctionRequest, RegisterFunctionResponse, RegisterFusionOutputRequest,
-    RegisterFusionOutputResponse, RegisterInputFileRequest, RegisterInputFileResponse,
-    RegisterInputFromOutputRequest, RegisterInputFromOutputResponse, RegisterOutputFileRequest,
-    RegisterOutputFileResponse, TeaclaveFrontend, UpdateInputFileRequest, UpdateInputFileResponse,
-    UpdateOutputFileRequest, UpdateOutputFileResponse,
+    RegisterFusionOutputResponse, RegisterInlineInputFileRequest, RegisterInlineInputFileResponse,
+    RegisterInputFileRequest, RegisterInputFileResponse, RegisterInputFromOutputRequest,
+    RegisterInputFromOutputResponse, RegisterOutputFileRequest, RegisterOutputFileResponse,
+    RegisterWebhookRequest, RegisterWebhookResponse, ResumeScheduledTaskRequest,
+    ResumeScheduledTaskResponse, ReviewOutputRequest, ReviewOutputResponse,
+    RollbackAccessControlPolicyRequest, RollbackAccessControlPolicyResponse,
+    RollbackFunctionRequest, RollbackFunctionResponse, SetUserQuotaRequest, SetUserQuotaResponse,
+    StreamTaskResultRequest, StreamTaskResultResponse, TeaclaveFrontend, TransferOwnershipRequest,
+    TransferOwnershipResponse, UpdateAccessControlPolicyRequest, UpdateAccessControlPolicyResponse,
+    UpdateInputFileRequest, UpdateInputFileResponse, UpdateOutputFileRequest,
+    UpdateOutputFileResponse, UploadPartRequest, UploadPartResponse,
 };
 use teaclave_proto::teaclave_management_service::TeaclaveManagementClient;
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::{bail, teaclave_service};
-use teaclave_types::TeaclaveServiceResponseResult;
+use teaclave_service_enclave_utils::{bail, health, teaclave_service};
+use teaclave_types::{QuotaUsage, TeaclaveServiceResponseResult, UserQuota};
+
+const IMPERSONATION_TOKEN: &str = "impersonation_token";
+// Permissions granted by the roles of the user, set by the frontend service
+// only.
+const PERMISSIONS: &str = "permissions";
 
 #[teaclave_service(teaclave_frontend_service, TeaclaveFrontend, TeaclaveFrontendError)]
 #[derive(Clone)]
 pub(crate) struct TeaclaveFrontendService {
     authentication_client: Arc<Mutex<TeaclaveAuthenticationInternalClient>>,
     management_client: Arc<Mutex<TeaclaveManagementClient>>,
+    platform_info: GetPlatformInfoResponse,
+    health_cache: HealthCache,
 }
 
+// Requests carrying an "impersonation_token" are sent by a platform admin
+// acting as the user who granted the consent, and are only allowed for
+// read-only operations. The management service rejects the others in
+// read-only mode.
+// Requests consuming the quota of the user are checked with the usage
+// reported by the management service.
 macro_rules! authentication_and_forward_to_management {
     ($service: ident, $request: ident, $func: ident) => {{
-        match $service.authenticate(&$request) {
-            Ok(true) => (),
-            _ => bail!(TeaclaveFrontendError::AuthenticationError),
+        authentication_and_forward_to_management!(@check_mutating $service, $request);
+        authentication_and_forward_to_management!(@forward $service, $request, $func)
+    }};
+    ($service: ident, $request: ident, $func: ident, quota: $check: expr) => {{
+        authentication_and_forward_to_management!(@check_mutating $service, $request);
+        let metadata = $service
+            .authenticate(&$request, stringify!($func))
+            .map_err(|_| TeaclaveFrontendError::AuthenticationError)?;
+        $service.ensure_within_quota(&metadata, $check)?;
+        let $request = Request {
+            metadata,
+            message: $request.message,
+        };
+        authentication_and_forward_to_management!(@send $service, $request, $func)
+    }};
+    ($service: ident, $request: ident, $func: ident, read_only) => {{
+        if $request.metadata.contains_key(IMPERSONATION_TOKEN) {
+            let metadata = $service
+                .impersonate(&$request, stringify!($func))
+                .map_err(|_| TeaclaveFrontendError::ImpersonationError)?;
+            let $request = Request {
+                metadata,
+                message: $request.message,
+            };
+            authentication_and_forward_to_management!(@send $service, $request, $func)
+        } else {
+            authentication_and_forward_to_management!(@forward $service, $request, $func)
         }
-
+    }};
+    (@check_mutating $service: ident, $request: ident) => {{
+        if $request.metadata.contains_key(IMPERSONATION_TOKEN) {
+            bail!(TeaclaveFrontendError::ImpersonationError);
+        }
+    }};
+    (@forward $service: ident, $request: ident, $func: ident) => {{
+        let metadata = $service
+            .authenticate(&$request, stringify!($func))
+            .map_err(|_| TeaclaveFrontendError::AuthenticationError)?;
+        let $request = Request {
+            metadata,
+            message: $request.message,
+        };
+        authentication_and_forward_to_management!(@send $service, $request, $func)
+    }};
+    (@send $service: ident, $request: ident, $func: ident) => {{
         let client = $service.management_client.clone();
         let mut client = client
             .lock()
@@ -75,6 +152,7 @@ impl TeaclaveFrontendService {
     pub(crate) fn new(
         authentication_service_endpoint: Endpoint,
         management_service_endpoint: Endpoint,
+        platform_info: GetPlatformInfoResponse,
     ) -> Result<Self> {
         let mut i = 0;
         let authentication_channel = loop {
@@ -111,8 +189,26 @@ impl TeaclaveFrontendService {
         Ok(Self {
             authentication_client,
             management_client,
+            platform_info,
+            health_cache: HealthCache::new(),
         })
     }
+
+    fn check_dependencies(&self) -> Vec<HealthCheck> {
+        let authentication_check = match self.authentication_client.lock() {
+            Ok(mut client) => {
+                health::dependency_check("authentication", client.health(HealthRequest::new()))
+            }
+            Err(_) => HealthCheck::unhealthy("authentication", "cannot lock client"),
+        };
+        let management_check = match self.management_client.lock() {
+            Ok(mut client) => {
+                health::dependency_check("management", client.health(HealthRequest::new()))
+            }
+            Err(_) => HealthCheck::unhealthy("management", "cannot lock client"),
+        };
+        vec![authentication_check, management_check]
+    }
 }
 
 impl TeaclaveFrontend for TeaclaveFrontendService {
@@ -123,6 +219,19 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
         authentication_and_forward_to_management!(self, request, register_input_file)
     }
 
+    fn register_inline_input_file(
+        &self,
+        request: Request<RegisterInlineInputFileRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterInlineInputFileResponse> {
+        let len = request.message.content.len() as u64;
+        authentication_and_forward_to_management!(
+            self,
+            request,
+            register_inline_input_file,
+            quota: |quota, usage| quota.check_input(usage, len)
+        )
+    }
+
     fn update_input_file(
         &self,
         request: Request<UpdateInputFileRequest>,
@@ -161,14 +270,14 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
         &self,
         request: Request<GetOutputFileRequest>,
     ) -> TeaclaveServiceResponseResult<GetOutputFileResponse> {
-        authentication_and_forward_to_management!(self, request, get_output_file)
+        authentication_and_forward_to_management!(self, request, get_output_file, read_only)
     }
 
     fn get_input_file(
         &self,
         request: Request<GetInputFileRequest>,
     ) -> TeaclaveServiceResponseResult<GetInputFileResponse> {
-        authentication_and_forward_to_management!(self, request, get_input_file)
+        authentication_and_forward_to_management!(self, request, get_input_file, read_only)
     }
 
     fn register_function(
@@ -178,11 +287,46 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
         authentication_and_forward_to_management!(self, request, register_function)
     }
 
+    fn begin_payload_upload(
+        &self,
+        request: Request<BeginPayloadUploadRequest>,
+    ) -> TeaclaveServiceResponseResult<BeginPayloadUploadResponse> {
+        authentication_and_forward_to_management!(self, request, begin_payload_upload)
+    }
+
+    fn upload_part(
+        &self,
+        request: Request<UploadPartRequest>,
+    ) -> TeaclaveServiceResponseResult<UploadPartResponse> {
+        authentication_and_forward_to_management!(self, request, upload_part)
+    }
+
+    fn commit_payload(
+        &self,
+        request: Request<CommitPayloadRequest>,
+    ) -> TeaclaveServiceResponseResult<CommitPayloadResponse> {
+        authentication_and_forward_to_management!(self, request, commit_payload)
+    }
+
     fn get_function(
         &self,
         request: Request<GetFunctionRequest>,
     ) -> TeaclaveServiceResponseResult<GetFunctionResponse> {
-        authentication_and_forward_to_management!(self, request, get_function)
+        authentication_and_forward_to_management!(self, request, get_function, read_only)
+    }
+
+    fn rollback_function(
+        &self,
+        request: Request<RollbackFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<RollbackFunctionResponse> {
+        authentication_and_forward_to_management!(self, request, rollback_function)
+    }
+
+    fn register_executor_enclave(
+        &self,
+        request: Request<RegisterExecutorEnclaveRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterExecutorEnclaveResponse> {
+        authentication_and_forward_to_management!(self, request, register_executor_enclave)
     }
 
     fn create_task(
@@ -196,7 +340,28 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
         &self,
         request: Request<GetTaskRequest>,
     ) -> TeaclaveServiceResponseResult<GetTaskResponse> {
-        authentication_and_forward_to_management!(self, request, get_task)
+        authentication_and_forward_to_management!(self, request, get_task, read_only)
+    }
+
+    fn get_task_result(
+        &self,
+        request: Request<GetTaskResultRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTaskResultResponse> {
+        authentication_and_forward_to_management!(self, request, get_task_result, read_only)
+    }
+
+    fn get_task_log(
+        &self,
+        request: Request<GetTaskLogRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTaskLogResponse> {
+        authentication_and_forward_to_management!(self, request, get_task_log, read_only)
+    }
+
+    fn stream_task_result(
+        &self,
+        request: Request<StreamTaskResultRequest>,
+    ) -> TeaclaveServiceResponseResult<StreamTaskResultResponse> {
+        authentication_and_forward_to_management!(self, request, stream_task_result, read_only)
     }
 
     fn assign_data(
@@ -217,13 +382,277 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
         &self,
         request: Request<InvokeTaskRequest>,
     ) -> TeaclaveServiceResponseResult<InvokeTaskResponse> {
-        authentication_and_forward_to_management!(self, request, invoke_task)
+        authentication_and_forward_to_management!(
+            self,
+            request,
+            invoke_task,
+            quota: |quota, usage| quota.check_invocation(usage)
+        )
+    }
+
+    fn create_tasks(
+        &self,
+        request: Request<CreateTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateTasksResponse> {
+        authentication_and_forward_to_management!(self, request, create_tasks)
+    }
+
+    // The management service checks the quota for each of the tasks.
+    fn invoke_tasks(
+        &self,
+        request: Request<InvokeTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<InvokeTasksResponse> {
+        authentication_and_forward_to_management!(
+            self,
+            request,
+            invoke_tasks,
+            quota: |quota, usage| quota.check_invocation(usage)
+        )
+    }
+
+    fn review_output(
+        &self,
+        request: Request<ReviewOutputRequest>,
+    ) -> TeaclaveServiceResponseResult<ReviewOutputResponse> {
+        authentication_and_forward_to_management!(self, request, review_output)
+    }
+
+    // Served without authentication for probes of load balancers, with the
+    // checks of the dependencies cached.
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        let mut checks = vec![health::attestation_check()];
+        checks.extend(self.health_cache.get(|| self.check_dependencies()));
+        Ok(HealthResponse::new(checks))
+    }
+
+    // Capabilities of the deployment, available without authentication so
+    // that clients can adapt before logging in.
+    fn get_platform_info(
+        &self,
+        _request: Request<GetPlatformInfoRequest>,
+    ) -> TeaclaveServiceResponseResult<GetPlatformInfoResponse> {
+        Ok(self.platform_info.clone())
+    }
+
+    // Served without authentication, so that clients can check the
+    // measurement of the attested enclave before sending credentials.
+    fn get_measurement_inclusion(
+        &self,
+        request: Request<GetMeasurementInclusionRequest>,
+    ) -> TeaclaveServiceResponseResult<GetMeasurementInclusionResponse> {
+        let client = self.management_client.clone();
+        let mut client = client
+            .lock()
+            .map_err(|_| TeaclaveFrontendError::LockError)?;
+        client.metadata_mut().clear();
+        let response = client.get_measurement_inclusion(request.message)?;
+        Ok(response)
+    }
+
+    // Forwarded to the access control service, which checks the manage_users
+    // permission of the user.
+    fn update_access_control_policy(
+        &self,
+        request: Request<UpdateAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<UpdateAccessControlPolicyResponse> {
+        authentication_and_forward_to_management!(self, request, update_access_control_policy)
+    }
+
+    fn rollback_access_control_policy(
+        &self,
+        request: Request<RollbackAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<RollbackAccessControlPolicyResponse> {
+        authentication_and_forward_to_management!(self, request, rollback_access_control_policy)
+    }
+
+    fn get_access_control_policy(
+        &self,
+        request: Request<GetAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<GetAccessControlPolicyResponse> {
+        authentication_and_forward_to_management!(
+            self,
+            request,
+            get_access_control_policy,
+            read_only
+        )
+    }
+
+    fn explain_access(
+        &self,
+        request: Request<ExplainAccessRequest>,
+    ) -> TeaclaveServiceResponseResult<ExplainAccessResponse> {
+        authentication_and_forward_to_management!(self, request, explain_access, read_only)
+    }
+
+    fn transfer_ownership(
+        &self,
+        request: Request<TransferOwnershipRequest>,
+    ) -> TeaclaveServiceResponseResult<TransferOwnershipResponse> {
+        authentication_and_forward_to_management!(self, request, transfer_ownership)
+    }
+
+    fn set_user_quota(
+        &self,
+        request: Request<SetUserQuotaRequest>,
+    ) -> TeaclaveServiceResponseResult<SetUserQuotaResponse> {
+        authentication_and_forward_to_management!(self, request, set_user_quota)
+    }
+
+    fn get_quota_usage(
+        &self,
+        request: Request<GetQuotaUsageRequest>,
+    ) -> TeaclaveServiceResponseResult<GetQuotaUsageResponse> {
+        authentication_and_forward_to_management!(self, request, get_quota_usage, read_only)
+    }
+
+    fn get_tenant_stats(
+        &self,
+        request: Request<GetTenantStatsRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTenantStatsResponse> {
+        authentication_and_forward_to_management!(self, request, get_tenant_stats, read_only)
+    }
+
+    fn list_nodes(
+        &self,
+        request: Request<ListNodesRequest>,
+    ) -> TeaclaveServiceResponseResult<ListNodesResponse> {
+        authentication_and_forward_to_management!(self, request, list_nodes, read_only)
+    }
+
+    fn list_executors(
+        &self,
+        request: Request<ListExecutorsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListExecutorsResponse> {
+        authentication_and_forward_to_management!(self, request, list_executors, read_only)
+    }
+
+    fn create_scheduled_task(
+        &self,
+        request: Request<CreateScheduledTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateScheduledTaskResponse> {
+        authentication_and_forward_to_management!(self, request, create_scheduled_task)
+    }
+
+    fn pause_scheduled_task(
+        &self,
+        request: Request<PauseScheduledTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<PauseScheduledTaskResponse> {
+        authentication_and_forward_to_management!(self, request, pause_scheduled_task)
+    }
+
+    fn resume_scheduled_task(
+        &self,
+        request: Request<ResumeScheduledTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ResumeScheduledTaskResponse> {
+        authentication_and_forward_to_management!(self, request, resume_scheduled_task)
+    }
+
+    fn list_upcoming_runs(
+        &self,
+        request: Request<ListUpcomingRunsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListUpcomingRunsResponse> {
+        authentication_and_forward_to_management!(self, request, list_upcoming_runs, read_only)
+    }
+
+    fn create_pipeline(
+        &self,
+        request: Request<CreatePipelineRequest>,
+    ) -> TeaclaveServiceResponseResult<CreatePipelineResponse> {
+        authentication_and_forward_to_management!(self, request, create_pipeline)
+    }
+
+    fn get_pipeline(
+        &self,
+        request: Request<GetPipelineRequest>,
+    ) -> TeaclaveServiceResponseResult<GetPipelineResponse> {
+        authentication_and_forward_to_management!(self, request, get_pipeline, read_only)
+    }
+
+    fn list_functions(
+        &self,
+        request: Request<ListFunctionsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListFunctionsResponse> {
+        authentication_and_forward_to_management!(self, request, list_functions, read_only)
+    }
+
+    fn list_tasks(
+        &self,
+        request: Request<ListTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<ListTasksResponse> {
+        authentication_and_forward_to_management!(self, request, list_tasks, read_only)
+    }
+
+    fn register_webhook(
+        &self,
+        request: Request<RegisterWebhookRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterWebhookResponse> {
+        authentication_and_forward_to_management!(self, request, register_webhook)
+    }
+
+    fn delete_webhook(
+        &self,
+        request: Request<DeleteWebhookRequest>,
+    ) -> TeaclaveServiceResponseResult<DeleteWebhookResponse> {
+        authentication_and_forward_to_management!(self, request, delete_webhook)
+    }
+
+    fn check_consistency(
+        &self,
+        request: Request<CheckConsistencyRequest>,
+    ) -> TeaclaveServiceResponseResult<CheckConsistencyResponse> {
+        authentication_and_forward_to_management!(self, request, check_consistency)
+    }
+
+    fn enter_read_only_mode(
+        &self,
+        request: Request<EnterReadOnlyModeRequest>,
+    ) -> TeaclaveServiceResponseResult<EnterReadOnlyModeResponse> {
+        authentication_and_forward_to_management!(self, request, enter_read_only_mode)
+    }
+
+    fn exit_read_only_mode(
+        &self,
+        request: Request<ExitReadOnlyModeRequest>,
+    ) -> TeaclaveServiceResponseResult<ExitReadOnlyModeResponse> {
+        authentication_and_forward_to_management!(self, request, exit_read_only_mode)
     }
 }
 
 impl TeaclaveFrontendService {
-    fn authenticate<T>(&self, request: &Request<T>) -> anyhow::Result<bool> {
-        use anyhow::anyhow;
+    // Checks the quota of the authenticated user against its usage, which the
+    // management service updates once the request is served. Concurrent
+    // requests may all pass the check before the usage is updated.
+    fn ensure_within_quota(
+        &self,
+        metadata: &HashMap<String, String>,
+        check: impl FnOnce(&UserQuota, &QuotaUsage) -> anyhow::Result<()>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let client = self.management_client.clone();
+        let mut client = client
+            .lock()
+            .map_err(|_| TeaclaveFrontendError::LockError)?;
+        client.metadata_mut().clear();
+        client.metadata_mut().extend(metadata.clone());
+        let response = client.get_quota_usage(GetQuotaUsageRequest::new());
+        client.metadata_mut().clear();
+        let response = response?;
+        check(&response.quota, &response.usage)
+            .map_err(|e| TeaclaveFrontendError::QuotaExceeded(e.to_string()))?;
+        Ok(())
+    }
+
+    // The operation is checked against the scopes of API keys.
+    // Authenticates the user, and returns the metadata to forward with the
+    // permissions granted by its roles.
+    fn authenticate<T>(
+        &self,
+        request: &Request<T>,
+        operation: &str,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        use anyhow::{anyhow, ensure};
         let id = request
             .metadata
             .get("id")
@@ -233,13 +662,61 @@ impl TeaclaveFrontendService {
             .get("token")
             .ok_or_else(|| anyhow!("Missing credential"))?;
         let credential = UserCredential::new(id, token);
-        let auth_request = UserAuthenticateRequest { credential };
+        let auth_request = UserAuthenticateRequest::new(credential).operation(operation);
+        let auth_response = self
+            .authentication_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock authentication client"))?
+            .user_authenticate(auth_request)?;
+        ensure!(auth_response.accept, "Authentication failed");
+
+        let permissions: Vec<String> = auth_response
+            .permissions
+            .iter()
+            .map(|permission| permission.to_string())
+            .collect();
+        let mut forwarded = request.metadata.clone();
+        forwarded.insert(PERMISSIONS.to_string(), permissions.join(","));
+        Ok(forwarded)
+    }
+
+    // Authenticates the platform admin with the consent token, and returns the
+    // metadata to forward on behalf of the user. Every attempt is audited.
+    fn impersonate<T>(
+        &self,
+        request: &Request<T>,
+        operation: &str,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        use anyhow::{anyhow, ensure};
+        let metadata = &request.metadata;
+        let id = metadata
+            .get("id")
+            .ok_or_else(|| anyhow!("Missing credential"))?;
+        let token = metadata
+            .get("token")
+            .ok_or_else(|| anyhow!("Missing credential"))?;
+        let consent_token = metadata
+            .get(IMPERSONATION_TOKEN)
+            .ok_or_else(|| anyhow!("Missing consent"))?;
+        let credential = UserCredential::new(id, token);
+        let auth_request =
+            ImpersonationAuthenticateRequest::new(credential, consent_token).operation(operation);
         let auth_response = self
             .authentication_client
             .clone()
             .lock()
             .map_err(|_| anyhow!("Cannot lock authentication client"))?
-            .user_authenticate(auth_request);
-        Ok(auth_response?.accept)
+            .impersonation_authenticate(auth_request)?;
+        // Recorded in the audit log by the authentication service.
+        ensure!(auth_response.accept, "Impersonation denied");
+
+        let mut forwarded = metadata.clone();
+        forwarded.remove("token");
+        forwarded.remove(IMPERSONATION_TOKEN);
+        forwarded.remove(PERMISSIONS);
+        forwarded.insert("id".to_string(), auth_response.user_id);
+        forwarded.insert("impersonator".to_string(), id.to_string());
+        Ok(forwarded)
     }
 }
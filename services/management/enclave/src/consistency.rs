@@ -0,0 +1,371 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_frontend_service::{ConsistencyFinding, InconsistencyKind};
+use teaclave_types::{
+    ExternalID, StagedTask, Storable, TaskFailure, TaskPriority, TaskResult, TaskState, TaskStatus,
+    TeaclaveInputFile, TeaclaveOutputFile,
+};
+
+// Scheme of the URLs of the data shared by the tasks of several owners
+const FUSION_SCHEME: &str = "fusion";
+
+/// A write of a record repairing a finding, which deletes the record if
+/// `value` is none.
+pub(crate) struct Repair {
+    pub(crate) key: Vec<u8>,
+    pub(crate) value: Option<Vec<u8>>,
+    /// The record as checked, restored if the repairs fail.
+    pub(crate) original: Option<Vec<u8>>,
+}
+
+/// Checks a snapshot of the storage for records left inconsistent, e.g., by
+/// crashes between the writes of a request, returning the findings with
+/// their repairs, if any.
+pub(crate) fn check_records(
+    records: &[(Vec<u8>, Vec<u8>)],
+) -> Vec<(ConsistencyFinding, Option<Repair>)> {
+    let records: HashMap<&[u8], &[u8]> = records
+        .iter()
+        .map(|(key, value)| (key.as_slice(), value.as_slice()))
+        .collect();
+    let mut tasks = Vec::new();
+    let mut outputs = Vec::new();
+    let mut referred_urls = HashSet::new();
+    for (key, value) in &records {
+        let id = match std::str::from_utf8(key).map(ExternalID::try_from) {
+            Ok(Ok(id)) => id,
+            _ => continue,
+        };
+        if id.prefix == TaskState::key_prefix() {
+            if let Ok(ts) = TaskState::from_slice(value) {
+                for name in ts.assigned_inputs.keys() {
+                    if let Some(file) = ts.assigned_inputs.get(name) {
+                        referred_urls.insert(file.url.to_string());
+                    }
+                }
+                for name in ts.assigned_outputs.keys() {
+                    if let Some(file) = ts.assigned_outputs.get(name) {
+                        referred_urls.insert(file.url.to_string());
+                    }
+                }
+                tasks.push((ts, *value));
+            }
+        } else if id.prefix == TeaclaveOutputFile::key_prefix() {
+            if let Ok(file) = TeaclaveOutputFile::from_slice(value) {
+                outputs.push((file, *value));
+            }
+        } else if id.prefix == TeaclaveInputFile::key_prefix() {
+            // Inputs registered from fusion data keep it in use.
+            if let Ok(file) = TeaclaveInputFile::from_slice(value) {
+                referred_urls.insert(file.url.to_string());
+            }
+        }
+    }
+
+    let mut findings = Vec::new();
+    for (ts, value) in tasks {
+        findings.extend(check_task(ts, value, &records));
+    }
+    for (file, value) in outputs {
+        findings.extend(check_fusion_data(file, value, &referred_urls));
+    }
+    for priority in TaskPriority::ALL.iter() {
+        let queue_key = StagedTask::get_priority_queue_key(*priority);
+        findings.extend(check_queue(queue_key, &records));
+    }
+    findings
+}
+
+// Tasks not finished whose function has been deleted can never run; they
+// are finished with a failure.
+fn check_task(
+    mut ts: TaskState,
+    value: &[u8],
+    records: &HashMap<&[u8], &[u8]>,
+) -> Option<(ConsistencyFinding, Option<Repair>)> {
+    if ts.status == TaskStatus::Finished
+        || records.contains_key(ts.function_id.to_bytes().as_slice())
+    {
+        return None;
+    }
+    let finding = ConsistencyFinding::new(
+        InconsistencyKind::TaskWithoutFunction,
+        ts.external_id(),
+        format!("function {} not found", ts.function_id.to_string()),
+    );
+    ts.status = TaskStatus::Finished;
+    ts.result = TaskResult::Err(TaskFailure::new("function deleted"));
+    let repair = ts.to_vec().ok().map(|repaired| Repair {
+        key: ts.key(),
+        value: Some(repaired),
+        original: Some(value.to_vec()),
+    });
+    Some((finding, repair))
+}
+
+// Fusion data written by a task which no longer refers to it cannot be
+// read by anyone; its record is deleted. Fusion data not written yet may
+// still be assigned to a task.
+fn check_fusion_data(
+    file: TeaclaveOutputFile,
+    value: &[u8],
+    referred_urls: &HashSet<String>,
+) -> Option<(ConsistencyFinding, Option<Repair>)> {
+    if file.url.scheme() != FUSION_SCHEME
+        || file.cmac.is_none()
+        || referred_urls.contains(file.url.as_str())
+    {
+        return None;
+    }
+    let finding = ConsistencyFinding::new(
+        InconsistencyKind::OrphanedFusionData,
+        file.external_id(),
+        format!("{} not referred to by any task", file.url),
+    );
+    let repair = Repair {
+        key: file.key(),
+        value: None,
+        original: Some(value.to_vec()),
+    };
+    Some((finding, Some(repair)))
+}
+
+// The storage keeps a queue in the records "queue-<key>-head" and
+// "queue-<key>-tail", and its entries in "queue-<key>-<index>" with the
+// index in little endian.
+fn check_queue(
+    queue_key: &str,
+    records: &HashMap<&[u8], &[u8]>,
+) -> Vec<(ConsistencyFinding, Option<Repair>)> {
+    let prefix = format!("queue-{}-", queue_key).into_bytes();
+    let head_key = [prefix.as_slice(), b"head"].concat();
+    let tail_key = [prefix.as_slice(), b"tail"].concat();
+    let head = read_index(records.get(head_key.as_slice()));
+    let tail = read_index(records.get(tail_key.as_slice()));
+    let mut entries = BTreeMap::new();
+    for (key, value) in records {
+        if key.len() == prefix.len() + 4
+            && key.starts_with(&prefix)
+            && *key != head_key.as_slice()
+            && *key != tail_key.as_slice()
+        {
+            let mut index = [0u8; 4];
+            index.copy_from_slice(&key[prefix.len()..]);
+            entries.insert(u32::from_le_bytes(index), (*key, *value));
+        }
+    }
+
+    let mut findings = Vec::new();
+    // Dequeuing fails at a missing entry, which stalls the queue; those at
+    // the head are skipped.
+    let first = if head < tail {
+        entries
+            .range(head..tail)
+            .next()
+            .map(|(index, _)| *index)
+            .unwrap_or(tail)
+    } else {
+        head
+    };
+    if first > head {
+        let finding = ConsistencyFinding::new(
+            InconsistencyKind::DanglingQueueEntry,
+            queue_key,
+            format!("entries {} to {} missing at the head", head, first - 1),
+        );
+        let repair = Repair {
+            key: head_key.clone(),
+            value: Some(first.to_le_bytes().to_vec()),
+            original: records.get(head_key.as_slice()).map(|value| value.to_vec()),
+        };
+        findings.push((finding, Some(repair)));
+    }
+    if first < tail {
+        let missing = tail - first - entries.range(first..tail).count() as u32;
+        if missing > 0 {
+            let finding = ConsistencyFinding::new(
+                InconsistencyKind::DanglingQueueEntry,
+                queue_key,
+                format!("{} entries missing after the head", missing),
+            );
+            findings.push((finding, None));
+        }
+    }
+
+    for (index, (key, value)) in entries {
+        // Entries past the tail are overwritten by the next enqueue.
+        if index >= tail {
+            continue;
+        }
+        // Left by a dequeue interrupted after it moved the head.
+        if index < head {
+            let finding = ConsistencyFinding::new(
+                InconsistencyKind::DanglingQueueEntry,
+                queue_key,
+                format!("entry {} left behind the head", index),
+            );
+            let repair = Repair {
+                key: key.to_vec(),
+                value: None,
+                original: Some(value.to_vec()),
+            };
+            findings.push((finding, Some(repair)));
+            continue;
+        }
+        // Entries in the middle of the queue cannot be removed; the scheduler
+        // dispatches them, and fails to update their tasks.
+        let detail = match StagedTask::from_slice(value) {
+            Ok(staged_task) => {
+                let task_key =
+                    ExternalID::new(TaskState::key_prefix(), staged_task.task_id).to_bytes();
+                match records
+                    .get(task_key.as_slice())
+                    .map(|value| TaskState::from_slice(value))
+                {
+                    None => format!("entry {} refers to a missing task", index),
+                    // Tasks are queued just before they are staged.
+                    Some(Ok(ts))
+                        if ts.status == TaskStatus::Approved || ts.status == TaskStatus::Staged =>
+                    {
+                        continue
+                    }
+                    Some(Ok(ts)) => format!(
+                        "entry {} refers to task {} already {:?}",
+                        index,
+                        ts.external_id().to_string(),
+                        ts.status
+                    ),
+                    Some(Err(_)) => format!("entry {} refers to an unreadable task", index),
+                }
+            }
+            Err(_) => format!("entry {} unreadable", index),
+        };
+        let finding =
+            ConsistencyFinding::new(InconsistencyKind::DanglingQueueEntry, queue_key, detail);
+        findings.push((finding, None));
+    }
+    findings
+}
+
+fn read_index(value: Option<&&[u8]>) -> u32 {
+    match value {
+        Some(value) if value.len() == 4 => {
+            let mut index = [0u8; 4];
+            index.copy_from_slice(value);
+            u32::from_le_bytes(index)
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_types::{platform, FileAuthTag, FileCrypto, Function, StagedTask};
+    use url::Url;
+
+    fn queue_key(index: &[u8]) -> Vec<u8> {
+        [b"queue-staged-task-".as_ref(), index].concat()
+    }
+
+    pub fn check_inconsistent_records() {
+        let function = Function::new().id(platform::rand::new_uuid());
+        let ts = TaskState {
+            task_id: platform::rand::new_uuid(),
+            function_id: function.external_id(),
+            status: TaskStatus::Staged,
+            ..Default::default()
+        };
+        let orphaned_task = TaskState {
+            task_id: platform::rand::new_uuid(),
+            function_id: ExternalID::new(Function::key_prefix(), platform::rand::new_uuid()),
+            status: TaskStatus::Approved,
+            ..Default::default()
+        };
+        let url = Url::parse("fusion:///TEACLAVE_FUSION_BASE/a.fusion").unwrap();
+        let mut fusion_data =
+            TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["user1", "user2"]);
+        let pending_fusion_data = fusion_data.clone();
+        fusion_data.cmac = Some(FileAuthTag::default());
+        fusion_data.uuid = platform::rand::new_uuid();
+        let staged_task = StagedTask::new().task_id(ts.task_id);
+        let missing_task = StagedTask::new().task_id(platform::rand::new_uuid());
+
+        let records = vec![
+            (function.key(), function.to_vec().unwrap()),
+            (ts.key(), ts.to_vec().unwrap()),
+            (orphaned_task.key(), orphaned_task.to_vec().unwrap()),
+            (fusion_data.key(), fusion_data.to_vec().unwrap()),
+            (
+                pending_fusion_data.key(),
+                pending_fusion_data.to_vec().unwrap(),
+            ),
+            // Entry 1 left by a dequeue, entry 2 missing at the head.
+            (queue_key(b"head"), 2u32.to_le_bytes().to_vec()),
+            (queue_key(b"tail"), 5u32.to_le_bytes().to_vec()),
+            (
+                queue_key(&1u32.to_le_bytes()),
+                staged_task.to_vec().unwrap(),
+            ),
+            (
+                queue_key(&3u32.to_le_bytes()),
+                staged_task.to_vec().unwrap(),
+            ),
+            (
+                queue_key(&4u32.to_le_bytes()),
+                missing_task.to_vec().unwrap(),
+            ),
+        ];
+        let findings = check_records(&records);
+        assert_eq!(findings.len(), 5);
+
+        let (finding, repair) = findings
+            .iter()
+            .find(|(finding, _)| finding.kind == InconsistencyKind::TaskWithoutFunction)
+            .unwrap();
+        assert_eq!(finding.key, orphaned_task.external_id().to_string());
+        let repaired = TaskState::from_slice(repair.as_ref().unwrap().value.as_ref().unwrap());
+        assert_eq!(repaired.unwrap().status, TaskStatus::Finished);
+
+        let (finding, repair) = findings
+            .iter()
+            .find(|(finding, _)| finding.kind == InconsistencyKind::OrphanedFusionData)
+            .unwrap();
+        assert_eq!(finding.key, fusion_data.external_id().to_string());
+        assert!(repair.as_ref().unwrap().value.is_none());
+
+        let queue_findings: Vec<_> = findings
+            .iter()
+            .filter(|(finding, _)| finding.kind == InconsistencyKind::DanglingQueueEntry)
+            .collect();
+        assert_eq!(queue_findings.len(), 3);
+        let repairs: Vec<&Repair> = queue_findings
+            .iter()
+            .filter_map(|(_, repair)| repair.as_ref())
+            .collect();
+        assert_eq!(repairs.len(), 2);
+        assert!(repairs.iter().any(|repair| repair.key == queue_key(b"head")
+            && repair.value == Some(3u32.to_le_bytes().to_vec())));
+        assert!(repairs
+            .iter()
+            .any(|repair| repair.key == queue_key(&1u32.to_le_bytes()) && repair.value.is_none()));
+    }
+}
@@ -31,6 +31,28 @@ pub(crate) enum TeaclaveManagementServiceError {
     PermissionDenied,
     #[error("bad task")]
     BadTask,
+    #[error("function environment not allowed")]
+    EnvNotAllowed,
+    #[error("measurement log unavailable")]
+    MeasurementLogUnavailable,
+    #[error("measurement not in the log")]
+    MeasurementNotFound,
+    #[error("failed to export task result")]
+    ExportError,
+    #[error("access control service unavailable")]
+    AccessControlUnavailable,
+    #[error("invalid payload part")]
+    InvalidPart,
+    #[error("payload upload incomplete")]
+    UploadIncomplete,
+    #[error("quota exceeded")]
+    QuotaExceeded,
+    #[error("privacy budget exhausted")]
+    PrivacyBudgetExhausted,
+    #[error("invalid read-only mode: {0}")]
+    InvalidReadOnlyMode(String),
+    #[error("platform is in read-only mode until {0}: {1}")]
+    ReadOnlyMode(u64, String),
 }
 
 impl From<TeaclaveManagementServiceError> for TeaclaveServiceResponseError {
@@ -25,6 +25,9 @@ extern crate log;
 use anyhow::{anyhow, Result};
 
 use std::prelude::v1::*;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use teaclave_attestation::{verifier, AttestationConfig, RemoteAttestation};
 use teaclave_binder::proto::{
@@ -37,13 +40,33 @@ use teaclave_config::RuntimeConfig;
 use teaclave_proto::teaclave_management_service::{
     TeaclaveManagementRequest, TeaclaveManagementResponse,
 };
-use teaclave_rpc::config::SgxTrustedTlsServerConfig;
-use teaclave_rpc::server::SgxTrustedTlsServer;
-use teaclave_service_enclave_utils::{create_trusted_storage_endpoint, ServiceEnclave};
-use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
+use teaclave_rpc::channel::ChannelPoolConfig;
+use teaclave_rpc::config::{SgxTrustedTlsServerConfig, TlsPolicy};
+use teaclave_rpc::interceptor::LoggingInterceptor;
+use teaclave_rpc::server::{SgxTrustedTlsServer, DEFAULT_IDLE_TIMEOUT};
+use teaclave_service_enclave_utils::audit::AuditInterceptor;
+use teaclave_service_enclave_utils::{
+    create_trusted_access_control_endpoint, create_trusted_storage_endpoint, ServiceEnclave,
+};
+use teaclave_types::{platform, EnclaveInfo, MeasurementLog, TeeServiceError, TeeServiceResult};
 
+mod consistency;
 mod error;
 mod service;
+mod stats;
+mod webhook;
+
+// Interval of the updates of the task statistics from the storage
+const TASK_STATS_INTERVAL: Duration = Duration::from_secs(1);
+// Interval of the checks of the scheduled tasks due
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(1);
+// Interval of the checks of the tasks of the running pipelines
+const PIPELINE_INTERVAL: Duration = Duration::from_secs(1);
+// Interval of the posts of task events to webhooks
+const WEBHOOK_INTERVAL: Duration = Duration::from_secs(1);
+// Reads of the storage service, which are retried even if the connection
+// failed after they were sent.
+const STORAGE_IDEMPOTENT_REQUESTS: &[&str] = &["Get", "ScanPrefix", "Range", "Health"];
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
     let listen_address = config.internal_endpoints.management.listen_address;
@@ -66,26 +89,120 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         .collect::<Result<_>>()?;
     let server_config =
         SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config.clone())?
-            .attestation_report_verifier(
+            .attestation_report_verifier_with_policy(
                 accepted_enclave_attrs,
                 AS_ROOT_CA_CERT,
                 verifier::universal_quote_verifier,
-            )?;
-    let mut server =
-        SgxTrustedTlsServer::<TeaclaveManagementResponse, TeaclaveManagementRequest>::new(
-            listen_address,
-            server_config,
-        );
+                verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+            )?
+            .tls_policy(TlsPolicy::from_teaclave_config(&config))?;
+    let server = SgxTrustedTlsServer::<TeaclaveManagementResponse, TeaclaveManagementRequest>::new(
+        listen_address,
+        server_config,
+    )
+    .message_limits(&config.internal_endpoints.management.message_limits)
+    .unix_socket(config.internal_endpoints.management.unix_socket.clone())
+    .idle_timeout(Some(DEFAULT_IDLE_TIMEOUT))
+    .interceptor(Arc::new(LoggingInterceptor::new("management")));
 
     let storage_service_endpoint = create_trusted_storage_endpoint(
         &config.internal_endpoints.storage.advertised_address,
         &enclave_info,
         AS_ROOT_CA_CERT,
         verifier::universal_quote_verifier,
+        verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+        TlsPolicy::from_teaclave_config(&config),
+        attested_tls_config.clone(),
+    )?
+    .pool(ChannelPoolConfig::default().idempotent_requests(STORAGE_IDEMPOTENT_REQUESTS))
+    .message_limits(&config.internal_endpoints.storage.message_limits);
+
+    let replication_config = &config.storage_replication;
+    let storage_replica_endpoints = replication_config
+        .replicas
+        .iter()
+        .map(|address| {
+            create_trusted_storage_endpoint(
+                address,
+                &enclave_info,
+                AS_ROOT_CA_CERT,
+                verifier::universal_quote_verifier,
+                verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+                TlsPolicy::from_teaclave_config(&config),
+                attested_tls_config.clone(),
+            )
+            .map(|endpoint| {
+                endpoint
+                    .pool(
+                        ChannelPoolConfig::default()
+                            .idempotent_requests(STORAGE_IDEMPOTENT_REQUESTS),
+                    )
+                    .message_limits(&config.internal_endpoints.storage.message_limits)
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let access_control_service_endpoint = create_trusted_access_control_endpoint(
+        &config.internal_endpoints.access_control.advertised_address,
+        &enclave_info,
+        AS_ROOT_CA_CERT,
+        verifier::universal_quote_verifier,
+        verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+        TlsPolicy::from_teaclave_config(&config),
+        attested_tls_config.clone(),
+    )?
+    .message_limits(&config.internal_endpoints.access_control.message_limits);
+
+    // The log is read from the host, and verified by clients with its public
+    // key.
+    let measurement_log = match &config.measurement_log {
+        Some(log_config) => {
+            let bytes = platform::fs::read(&log_config.path)?;
+            Some(MeasurementLog::from_bytes(&bytes)?)
+        }
+        None => None,
+    };
+
+    let service = service::TeaclaveManagementService::new(
+        storage_service_endpoint,
+        storage_replica_endpoints,
+        Duration::from_millis(replication_config.max_staleness_ms),
+        config.limits.inline_data_max_size,
+        config.limits.payload_max_size,
+        measurement_log,
         attested_tls_config,
+        access_control_service_endpoint,
+        config.function_env.clone(),
     )?;
-
-    let service = service::TeaclaveManagementService::new(storage_service_endpoint)?;
+    let stats_service = service.clone();
+    thread::spawn(move || loop {
+        thread::sleep(TASK_STATS_INTERVAL);
+        if let Err(e) = stats_service.update_task_stats() {
+            log::debug!("Failed to update task stats: {:?}", e);
+        }
+    });
+    let schedule_service = service.clone();
+    thread::spawn(move || loop {
+        thread::sleep(SCHEDULE_INTERVAL);
+        if let Err(e) = schedule_service.run_scheduled_tasks() {
+            log::warn!("Failed to run scheduled tasks: {:?}", e);
+        }
+    });
+    let pipeline_service = service.clone();
+    thread::spawn(move || loop {
+        thread::sleep(PIPELINE_INTERVAL);
+        if let Err(e) = pipeline_service.run_pipelines() {
+            log::warn!("Failed to run pipelines: {:?}", e);
+        }
+    });
+    let webhook_service = service.clone();
+    thread::spawn(move || loop {
+        thread::sleep(WEBHOOK_INTERVAL);
+        if let Err(e) = webhook_service.deliver_webhooks() {
+            log::debug!("Failed to deliver webhooks: {:?}", e);
+        }
+    });
+    let mut server = server.interceptor(Arc::new(AuditInterceptor::new(service.audit().clone())));
     match server.start(service) {
         Ok(_) => (),
         Err(e) => {
@@ -137,6 +254,13 @@ pub mod tests {
             service::tests::handle_function,
             service::tests::handle_task,
             service::tests::handle_staged_task,
+            service::tests::handle_task_result_range,
+            service::tests::handle_function_env,
+            service::tests::handle_read_any_output,
+            service::tests::handle_ownership_transfer,
+            consistency::tests::check_inconsistent_records,
+            stats::tests::aggregate_task_stats,
+            webhook::tests::dispatch_task_events,
         )
     }
 }
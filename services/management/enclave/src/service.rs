@@ -15,34 +15,105 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::consistency;
 use crate::error::TeaclaveManagementServiceError;
+use crate::stats::{TaskStatsAggregator, MAX_STATS_WINDOW_SECS};
+use crate::webhook::{self, WebhookDispatcher};
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryInto;
 use std::prelude::v1::*;
-use std::sync::{Arc, SgxMutex as Mutex};
+use std::sync::{Arc, SgxMutex as Mutex, SgxRwLock as RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use teaclave_attestation::AttestedTlsConfig;
+use teaclave_config::FunctionEnvConfig;
+use teaclave_proto::teaclave_access_control_service::{
+    AuthorizeDataRequest, AuthorizeDataUseRequest, AuthorizeFunctionRequest, AuthorizeTaskRequest,
+    TeaclaveAccessControlClient,
+};
 use teaclave_proto::teaclave_frontend_service::{
     ApproveTaskRequest, ApproveTaskResponse, AssignDataRequest, AssignDataResponse,
-    CreateTaskRequest, CreateTaskResponse, GetFunctionRequest, GetFunctionResponse,
-    GetInputFileRequest, GetInputFileResponse, GetOutputFileRequest, GetOutputFileResponse,
-    GetTaskRequest, GetTaskResponse, InvokeTaskRequest, InvokeTaskResponse,
-    RegisterFunctionRequest, RegisterFunctionResponse, RegisterFusionOutputRequest,
-    RegisterFusionOutputResponse, RegisterInputFileRequest, RegisterInputFileResponse,
-    RegisterInputFromOutputRequest, RegisterInputFromOutputResponse, RegisterOutputFileRequest,
-    RegisterOutputFileResponse, UpdateInputFileRequest, UpdateInputFileResponse,
-    UpdateOutputFileRequest, UpdateOutputFileResponse,
+    BeginPayloadUploadRequest, BeginPayloadUploadResponse, CheckConsistencyRequest,
+    CheckConsistencyResponse, CommitPayloadRequest, CommitPayloadResponse, CreatePipelineRequest,
+    CreatePipelineResponse, CreateScheduledTaskRequest, CreateScheduledTaskResponse,
+    CreateTaskRequest, CreateTaskResponse, CreateTasksRequest, CreateTasksResponse,
+    DeleteWebhookRequest, DeleteWebhookResponse, EnterReadOnlyModeRequest,
+    EnterReadOnlyModeResponse, ExitReadOnlyModeRequest, ExitReadOnlyModeResponse,
+    ExplainAccessRequest, ExplainAccessResponse, FunctionSummary, GetAccessControlPolicyRequest,
+    GetAccessControlPolicyResponse, GetFunctionRequest, GetFunctionResponse, GetInputFileRequest,
+    GetInputFileResponse, GetMeasurementInclusionRequest, GetMeasurementInclusionResponse,
+    GetOutputFileRequest, GetOutputFileResponse, GetPipelineRequest, GetPipelineResponse,
+    GetQuotaUsageRequest, GetQuotaUsageResponse, GetTaskLogRequest, GetTaskLogResponse,
+    GetTaskRequest, GetTaskResponse, GetTaskResultRequest, GetTaskResultResponse,
+    GetTenantStatsRequest, GetTenantStatsResponse, InvokeTaskFailure, InvokeTaskRequest,
+    InvokeTaskResponse, InvokeTasksRequest, InvokeTasksResponse, ListExecutorsRequest,
+    ListExecutorsResponse, ListFunctionsRequest, ListFunctionsResponse, ListNodesRequest,
+    ListNodesResponse, ListTasksRequest, ListTasksResponse, ListUpcomingRunsRequest,
+    ListUpcomingRunsResponse, PauseScheduledTaskRequest, PauseScheduledTaskResponse,
+    RegisterExecutorEnclaveRequest, RegisterExecutorEnclaveResponse, RegisterFunctionRequest,
+    RegisterFunctionResponse, RegisterFusionOutputRequest, RegisterFusionOutputResponse,
+    RegisterInlineInputFileRequest, RegisterInlineInputFileResponse, RegisterInputFileRequest,
+    RegisterInputFileResponse, RegisterInputFromOutputRequest, RegisterInputFromOutputResponse,
+    RegisterOutputFileRequest, RegisterOutputFileResponse, RegisterWebhookRequest,
+    RegisterWebhookResponse, ResumeScheduledTaskRequest, ResumeScheduledTaskResponse,
+    ReviewOutputRequest, ReviewOutputResponse, RollbackAccessControlPolicyRequest,
+    RollbackAccessControlPolicyResponse, RollbackFunctionRequest, RollbackFunctionResponse,
+    SetUserQuotaRequest, SetUserQuotaResponse, StreamTaskResultRequest, StreamTaskResultResponse,
+    TaskSummary, TransferOwnershipRequest, TransferOwnershipResponse,
+    UpdateAccessControlPolicyRequest, UpdateAccessControlPolicyResponse, UpdateInputFileRequest,
+    UpdateInputFileResponse, UpdateOutputFileRequest, UpdateOutputFileResponse, UploadPartRequest,
+    UploadPartResponse,
+};
+use teaclave_proto::teaclave_management_service::{
+    DisableUserResourcesRequest, DisableUserResourcesResponse, HealthRequest, HealthResponse,
+    TeaclaveManagement,
 };
-use teaclave_proto::teaclave_management_service::TeaclaveManagement;
 use teaclave_proto::teaclave_storage_service::{
-    EnqueueRequest, GetRequest, PutRequest, TeaclaveStorageClient,
+    BatchRequest, ChangeCursor, DeleteRequest, GetRequest, PutRequest, ScanPrefixRequest,
+    StorageChange, TeaclaveStorageClient,
 };
+use teaclave_rpc::blob::{self, BlobSink, BlobSource};
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::{ensure, teaclave_service};
+use teaclave_service_enclave_utils::audit::AuditRecorder;
+use teaclave_service_enclave_utils::{bail, ensure, health, teaclave_service};
 use teaclave_types::*;
 use url::Url;
 use uuid::Uuid;
 
+// Key prefix of the users disabled or deleted by the authentication service
+const DISABLED_USER_PREFIX: &str = "disabled-user";
+// Key prefixes of the quotas of users and their usage
+const QUOTA_PREFIX: &str = "user-quota";
+const USAGE_PREFIX: &str = "user-usage";
+// Key prefix of the webhooks of users
+const WEBHOOK_PREFIX: &str = "user-webhook";
+// Kinds of the objects of TransferOwnership, the prefixes of their ids
+const TRANSFERABLE_KINDS: &[&str] = &["function", "input", "output", "task"];
+// Key prefix of the versions of the functions of an owner with a name
+const FUNCTION_VERSIONS_PREFIX: &str = "function-versions";
+// Key of the ids of the running pipelines
+const PIPELINE_INDEX_KEY: &str = "pipeline-index";
+// Most upcoming runs of a scheduled task listed at once
+const MAX_UPCOMING_RUNS: u32 = 100;
+// Time after which the storage deletes an upload and its parts if they are
+// not written again, e.g., abandoned uploads
+const UPLOAD_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+// Key of the open uploads of a user, in the namespace of the user
+const OPEN_UPLOADS_KEY: &str = "upload-index";
+// Most uploads a user may have open at once, and most bytes they may stage,
+// or the maximum size of a payload if larger
+const MAX_OPEN_UPLOADS: usize = 8;
+const MAX_STAGED_UPLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+// A record with the namespace it is kept in, if any, and its key there
+type NamespacedRecord = (Option<String>, Vec<u8>, Vec<u8>);
+
+// Maximum length in bytes of a value of the environment set by a task
+const MAX_ENV_VALUE_LEN: usize = 1024;
+// Max number of tasks created or invoked by a batch request
+const MAX_BATCH_TASKS: usize = 4096;
+
 #[teaclave_service(
     teaclave_management_service,
     TeaclaveManagement,
@@ -51,6 +122,40 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub(crate) struct TeaclaveManagementService {
     storage_client: Arc<Mutex<TeaclaveStorageClient>>,
+    // Read-only replicas of the storage, queried before the primary by
+    // requests which do not modify the records they read.
+    storage_replica_clients: Vec<Arc<Mutex<TeaclaveStorageClient>>>,
+    max_replica_staleness: Duration,
+    inline_data_max_size: usize,
+    payload_max_size: usize,
+    measurement_log: Option<Arc<MeasurementLog>>,
+    // Its key signs the task results exported as CWTs.
+    attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+    // Connected for policy administration, and to check the label rules of
+    // the data assigned to tasks.
+    access_control_endpoint: Arc<Endpoint>,
+    // Keys of the environment which tasks may set, and the defaults of the
+    // deployment given to every function.
+    function_env: Arc<FunctionEnvConfig>,
+    // Serializes the updates of the usage records of users.
+    usage_lock: Arc<Mutex<()>>,
+    // Serializes the updates of the versions of functions.
+    versions_lock: Arc<Mutex<()>>,
+    // Serializes the updates of the scheduled tasks and their index.
+    schedule_lock: Arc<Mutex<()>>,
+    // Serializes the updates of the pipelines and their index.
+    pipeline_lock: Arc<Mutex<()>>,
+    // Serializes the spending of the privacy budgets of input files.
+    privacy_lock: Arc<Mutex<()>>,
+    // Serializes the updates of the open uploads of users.
+    uploads_lock: Arc<Mutex<()>>,
+    // Statistics of the tasks of tenants, updated from the change stream of
+    // the storage.
+    task_stats: Arc<Mutex<TaskStatsAggregator>>,
+    // Events of the state changes of tasks, followed from the change stream
+    // of the storage, and their posts to the webhooks of users.
+    webhooks: Arc<Mutex<WebhookDispatcher>>,
+    audit: AuditRecorder,
 }
 
 impl TeaclaveManagement for TeaclaveManagementService {
@@ -59,14 +164,19 @@ impl TeaclaveManagement for TeaclaveManagementService {
         &self,
         request: Request<RegisterInputFileRequest>,
     ) -> TeaclaveServiceResponseResult<RegisterInputFileResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
         let request = request.message;
+        let privacy_budget = new_privacy_budget(request.privacy_budget)?;
         let input_file = TeaclaveInputFile::new(
             request.url,
             request.cmac,
             request.crypto_info,
             vec![user_id],
-        );
+        )
+        .attributes(request.attributes)
+        .labels(request.labels)
+        .privacy_budget(privacy_budget);
 
         self.write_to_db(&input_file)
             .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
@@ -75,13 +185,52 @@ impl TeaclaveManagement for TeaclaveManagementService {
         Ok(response)
     }
 
+    // access control: none
+    fn register_inline_input_file(
+        &self,
+        request: Request<RegisterInlineInputFileRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterInlineInputFileResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        ensure!(
+            request.content.len() <= self.inline_data_max_size,
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        let privacy_budget = new_privacy_budget(request.privacy_budget)?;
+
+        let input_file = TeaclaveInputFile::from_bytes(
+            request.content,
+            request.cmac,
+            request.crypto_info,
+            vec![user_id],
+        )
+        .map_err(|_| TeaclaveManagementServiceError::DataError)?
+        .attributes(request.attributes)
+        .labels(request.labels)
+        .privacy_budget(privacy_budget);
+
+        self.write_to_db(&input_file)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let len = input_file.content.as_ref().map_or(0, Vec::len) as u64;
+        self.update_usage(&user_id, |record| record.record_input(len))?;
+
+        let response = RegisterInlineInputFileResponse::new(input_file.external_id());
+        Ok(response)
+    }
+
     // access control:
     // 1) exisiting_file.owner_list.len() == 1
     // 2) user_id in existing_file.owner_list
+    // 3) existing_file is not registered inline
+    // 4) existing_file has no privacy budget, which must not be renewed by a
+    //    new record of the file
     fn update_input_file(
         &self,
         request: Request<UpdateInputFileRequest>,
     ) -> TeaclaveServiceResponseResult<UpdateInputFileResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
         let request = request.message;
 
@@ -94,12 +243,19 @@ impl TeaclaveManagement for TeaclaveManagementService {
             TeaclaveManagementServiceError::PermissionDenied
         );
 
+        ensure!(
+            !old_input_file.is_inline() && old_input_file.privacy_budget.is_none(),
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+
         let input_file = TeaclaveInputFile::new(
             request.url,
             old_input_file.cmac,
             old_input_file.crypto_info,
             old_input_file.owner,
-        );
+        )
+        .attributes(old_input_file.attributes)
+        .labels(old_input_file.labels);
 
         self.write_to_db(&input_file)
             .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
@@ -113,6 +269,7 @@ impl TeaclaveManagement for TeaclaveManagementService {
         &self,
         request: Request<RegisterOutputFileRequest>,
     ) -> TeaclaveServiceResponseResult<RegisterOutputFileResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
         let request = request.message;
         let output_file = TeaclaveOutputFile::new(request.url, request.crypto_info, vec![user_id]);
@@ -131,6 +288,7 @@ impl TeaclaveManagement for TeaclaveManagementService {
         &self,
         request: Request<UpdateOutputFileRequest>,
     ) -> TeaclaveServiceResponseResult<UpdateOutputFileResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
         let request = request.message;
 
@@ -161,6 +319,7 @@ impl TeaclaveManagement for TeaclaveManagementService {
         &self,
         request: Request<RegisterFusionOutputRequest>,
     ) -> TeaclaveServiceResponseResult<RegisterFusionOutputResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let owner_list = request.message.owner_list;
@@ -187,6 +346,7 @@ impl TeaclaveManagement for TeaclaveManagementService {
         &self,
         request: Request<RegisterInputFromOutputRequest>,
     ) -> TeaclaveServiceResponseResult<RegisterInputFromOutputResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let output: TeaclaveOutputFile = self
@@ -208,7 +368,9 @@ impl TeaclaveManagement for TeaclaveManagementService {
         Ok(response)
     }
 
-    // access control: output_file.owner contains user_id
+    // access control:
+    // 1) output_file.owner contains user_id, or
+    // 2) the user has the read_any_output permission
     fn get_output_file(
         &self,
         request: Request<GetOutputFileRequest>,
@@ -216,11 +378,11 @@ impl TeaclaveManagement for TeaclaveManagementService {
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let output_file: TeaclaveOutputFile = self
-            .read_from_db(&request.message.data_id)
+            .query_from_db(&request.message.data_id)
             .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
 
         ensure!(
-            output_file.owner.contains(&user_id),
+            output_file.owner.contains(&user_id) || can_read_any_output(request.metadata()),
             TeaclaveManagementServiceError::PermissionDenied
         );
 
@@ -236,7 +398,7 @@ impl TeaclaveManagement for TeaclaveManagementService {
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let input_file: TeaclaveInputFile = self
-            .read_from_db(&request.message.data_id)
+            .query_from_db(&request.message.data_id)
             .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
 
         ensure!(
@@ -244,29 +406,235 @@ impl TeaclaveManagement for TeaclaveManagementService {
             TeaclaveManagementServiceError::PermissionDenied
         );
 
-        let response = GetInputFileResponse::new(input_file.owner, input_file.cmac);
+        let response = GetInputFileResponse::new(input_file.owner, input_file.cmac)
+            .privacy_budget(input_file.privacy_budget);
         Ok(response)
     }
 
     // access_control: none
+    // The function is the next version of the functions of the user with its
+    // name, and becomes their default.
     fn register_function(
         &self,
         request: Request<RegisterFunctionRequest>,
     ) -> TeaclaveServiceResponseResult<RegisterFunctionResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
+        let mut request = request.message;
+
+        // The upload is consumed by the function.
+        let upload = match request.payload_upload_id.take() {
+            Some(upload_id) => {
+                let upload = self.read_upload(&upload_id, &user_id)?;
+                ensure!(
+                    request.payload.is_empty(),
+                    TeaclaveManagementServiceError::InvalidRequest
+                );
+                Some(upload)
+            }
+            None => None,
+        };
+        if let Some(upload) = &upload {
+            request.payload = upload
+                .payload
+                .clone()
+                .ok_or(TeaclaveManagementServiceError::UploadIncomplete)?;
+        }
+        if let Some(manifest) = &request.manifest {
+            check_manifest(manifest, &request)?;
+        }
+        if !request.bundle.is_empty() {
+            check_bundle(&request)?;
+        }
+        check_output_policies(&request)?;
+        // Functions run only in the executor enclaves of their owner.
+        if let Some(enclave_id) = &request.executor_enclave {
+            let enclave: ExecutorEnclave = self
+                .read_from_db(enclave_id)
+                .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+            ensure!(
+                enclave.owner == user_id,
+                TeaclaveManagementServiceError::PermissionDenied
+            );
+        }
 
-        let function = Function::from(request.message)
-            .id(Uuid::new_v4())
-            .owner(user_id);
+        let function = Function::from(request)
+            .id(platform::rand::new_uuid())
+            .owner(user_id.clone())
+            .created_at(now_secs());
 
-        self.write_to_db(&function)
+        let _guard = self
+            .versions_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let mut versions = self.read_function_versions(&user_id, &function.name)?;
+        let version = versions.push(function.id);
+        let function = function.version(version);
+        let value =
+            serde_json::to_vec(&versions).map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        let mut batch = put_record(BatchRequest::new(), &function)?.put(
+            function_versions_key(&versions.owner, &versions.name),
+            value,
+        );
+        let _uploads_guard = self
+            .uploads_lock
+            .lock()
             .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        if let Some(upload) = upload {
+            let namespace = tenant_namespace(&upload.owner);
+            let mut open_uploads = self.read_open_uploads(&upload.owner)?;
+            open_uploads.close(&upload.id);
+            let value = serde_json::to_vec(&open_uploads)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+            batch = batch.delete_in(namespace.as_str(), upload.key()).put_in(
+                namespace,
+                OPEN_UPLOADS_KEY,
+                value,
+            );
+        }
+        self.write_batch(batch)?;
 
-        let response = RegisterFunctionResponse::new(function.external_id());
+        self.audit.record(
+            AuditEventKind::FunctionRegistered,
+            &user_id.to_string(),
+            function.external_id().to_string(),
+        );
+        let response = RegisterFunctionResponse::new(function.external_id(), function.version);
         Ok(response)
     }
 
-    // access control: function.public || function.owner == user_id
+    // access control: upload.owner == user_id
+    fn begin_payload_upload(
+        &self,
+        request: Request<BeginPayloadUploadRequest>,
+    ) -> TeaclaveServiceResponseResult<BeginPayloadUploadResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let upload = match request.upload_id {
+            Some(upload_id) => {
+                let upload = self.read_upload(&upload_id, &user_id)?;
+                ensure!(
+                    upload.total_len == request.total_len
+                        && upload.part_size == request.part_size
+                        && upload.payload_hash == request.payload_hash,
+                    TeaclaveManagementServiceError::InvalidRequest
+                );
+                upload
+            }
+            None => {
+                ensure!(
+                    request.total_len <= self.payload_max_size as u64
+                        && request.part_size <= blob::MAX_CHUNK_LEN,
+                    TeaclaveManagementServiceError::InvalidRequest
+                );
+                let upload = PayloadUpload::new(
+                    platform::rand::new_uuid(),
+                    user_id,
+                    request.total_len,
+                    request.part_size,
+                    request.payload_hash,
+                    now_secs(),
+                )
+                .map_err(|_| TeaclaveManagementServiceError::InvalidRequest)?;
+                self.write_upload(&upload)?;
+                upload
+            }
+        };
+
+        // A committed upload has all parts, so that clients retrying after a
+        // lost response of the commit go on to commit again.
+        let namespace = tenant_namespace(&upload.owner);
+        let mut received_parts = Vec::new();
+        for part_number in 0..upload.part_count() {
+            if upload.payload.is_some()
+                || self
+                    .get_optional_from_db_in(Some(&namespace), &upload.part_key(part_number))?
+                    .is_some()
+            {
+                received_parts.push(part_number);
+            }
+        }
+        Ok(BeginPayloadUploadResponse {
+            upload_id: upload.external_id(),
+            part_count: upload.part_count(),
+            received_parts,
+        })
+    }
+
+    // access control: upload.owner == user_id
+    fn upload_part(
+        &self,
+        request: Request<UploadPartRequest>,
+    ) -> TeaclaveServiceResponseResult<UploadPartResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let upload = self.read_upload(&request.upload_id, &user_id)?;
+        ensure!(
+            upload.payload.is_none(),
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        upload
+            .check_part(request.part_number, &request.data, &request.part_hash)
+            .map_err(|_| TeaclaveManagementServiceError::InvalidPart)?;
+        // Parts are written on their own, so that they can be uploaded in
+        // parallel.
+        self.put_to_db_with_ttl(
+            Some(&tenant_namespace(&upload.owner)),
+            &upload.part_key(request.part_number),
+            &request.data,
+            UPLOAD_TTL,
+        )
+        .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        Ok(UploadPartResponse)
+    }
+
+    // access control: upload.owner == user_id
+    fn commit_payload(
+        &self,
+        request: Request<CommitPayloadRequest>,
+    ) -> TeaclaveServiceResponseResult<CommitPayloadResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let mut upload = self.read_upload(&request.message.upload_id, &user_id)?;
+        // Committing again is a no-op, e.g., after a lost response.
+        if let Some(payload) = &upload.payload {
+            return Ok(CommitPayloadResponse {
+                payload_len: payload.len() as u64,
+            });
+        }
+
+        let namespace = tenant_namespace(&upload.owner);
+        let mut sink = BlobSink::new(upload.total_len, upload.payload_hash.clone())
+            .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        for part_number in 0..upload.part_count() {
+            match self.get_optional_from_db_in(Some(&namespace), &upload.part_key(part_number))? {
+                Some(part) => sink
+                    .write(sink.offset(), &part)
+                    .map_err(|_| TeaclaveManagementServiceError::DataError)?,
+                None => bail!(TeaclaveManagementServiceError::UploadIncomplete),
+            }
+        }
+        let payload = sink
+            .finish()
+            .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        let payload_len = payload.len() as u64;
+        upload.payload = Some(payload);
+        self.write_upload(&upload)?;
+        for part_number in 0..upload.part_count() {
+            self.delete_from_db(Some(&namespace), &upload.part_key(part_number));
+        }
+
+        Ok(CommitPayloadResponse { payload_len })
+    }
+
+    // access control:
+    // 1) function.public || function.owner == user_id
+    // 2) function.owner is not disabled
     fn get_function(
         &self,
         request: Request<GetFunctionRequest>,
@@ -274,13 +642,24 @@ impl TeaclaveManagement for TeaclaveManagementService {
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let function: Function = self
-            .read_from_db(&request.message.function_id)
+            .query_from_db(&request.message.function_id)
             .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
 
         ensure!(
             (function.public || function.owner == user_id),
             TeaclaveManagementServiceError::PermissionDenied
         );
+        ensure!(
+            !self.is_user_disabled(&function.owner)?,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        let default_version = match function.version {
+            0 => 0,
+            _ => {
+                self.read_function_versions(&function.owner, &function.name)?
+                    .default_version
+            }
+        };
 
         let response = GetFunctionResponse {
             name: function.name,
@@ -292,11 +671,84 @@ impl TeaclaveManagement for TeaclaveManagementService {
             arguments: function.arguments,
             inputs: function.inputs,
             outputs: function.outputs,
+            tags: function.tags,
+            manifest: function.manifest,
+            version: function.version,
+            default_version,
+            executor_enclave: function.executor_enclave,
         };
         Ok(response)
     }
 
-    // access control: none
+    // access control: user_id == function.owner
+    fn rollback_function(
+        &self,
+        request: Request<RollbackFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<RollbackFunctionResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let function: Function = self
+            .read_from_db(&request.function_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        ensure!(
+            function.owner == user_id,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        let _guard = self
+            .versions_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let mut versions = self.read_function_versions(&user_id, &function.name)?;
+        let default_version = versions.rollback(request.version).map_err(|e| {
+            log::warn!("Rollback of function error: {:?}", e);
+            TeaclaveManagementServiceError::InvalidRequest
+        })?;
+        self.write_function_versions(&versions)?;
+
+        self.audit.record(
+            AuditEventKind::FunctionRolledBack,
+            &user_id.to_string(),
+            format!("{} to version {}", function.name, default_version),
+        );
+        Ok(RollbackFunctionResponse { default_version })
+    }
+
+    // access control: none, the enclave is owned by the user registering it
+    fn register_executor_enclave(
+        &self,
+        request: Request<RegisterExecutorEnclaveRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterExecutorEnclaveResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let enclave = ExecutorEnclave::new(
+            platform::rand::new_uuid(),
+            user_id.clone(),
+            request.name,
+            request.measurement,
+            now_secs(),
+        )
+        .map_err(|_| TeaclaveManagementServiceError::InvalidRequest)?;
+        self.write_to_db(&enclave)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+
+        self.audit.record(
+            AuditEventKind::ExecutorEnclaveRegistered,
+            &user_id.to_string(),
+            format!(
+                "{} with MRENCLAVE {}",
+                enclave.external_id(),
+                enclave.measurement.mr_enclave
+            ),
+        );
+        Ok(RegisterExecutorEnclaveResponse::new(enclave.external_id()))
+    }
+
+    // access control: function.owner is not disabled
     // when a task is created, following rules will be verified:
     // 1) arugments match function definition
     // 2) input match function definition
@@ -305,27 +757,15 @@ impl TeaclaveManagement for TeaclaveManagementService {
         &self,
         request: Request<CreateTaskRequest>,
     ) -> TeaclaveServiceResponseResult<CreateTaskResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let request = request.message;
 
-        let function: Function = self
-            .read_from_db(&request.function_id)
-            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
-
-        let task = Task::<Create>::new(
-            user_id,
-            request.executor,
-            request.function_arguments,
-            request.inputs_ownership,
-            request.outputs_ownership,
-            function,
-        )
-        .map_err(|_| TeaclaveManagementServiceError::BadTask)?;
-
-        log::debug!("CreateTask: {:?}", task);
+        let function = self.read_task_function(&request.function_id, request.function_version)?;
+        validate_env(&request.env, &self.function_env.allowed_keys)?;
 
-        let ts: TaskState = task.into();
+        let ts = new_task_state(user_id, request, function)?;
         self.write_to_db(&ts)
             .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
 
@@ -333,7 +773,45 @@ impl TeaclaveManagement for TeaclaveManagementService {
         Ok(response)
     }
 
-    // access control: task.participants.contains(&user_id)
+    // access control: the same as CreateTask for each task
+    // The tasks are created only if all of them are valid.
+    fn create_tasks(
+        &self,
+        request: Request<CreateTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateTasksResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let request = request.message;
+        ensure!(
+            !request.overrides.is_empty() && request.overrides.len() <= MAX_BATCH_TASKS,
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+
+        let template = request.template;
+        let function = self.read_task_function(&template.function_id, template.function_version)?;
+        let states = request
+            .overrides
+            .into_iter()
+            .map(|overrides| {
+                let request = template.clone().apply(overrides);
+                validate_env(&request.env, &self.function_env.allowed_keys)?;
+                new_task_state(user_id.clone(), request, function.clone())
+            })
+            .collect::<TeaclaveServiceResponseResult<Vec<_>>>()?;
+
+        let mut task_ids = Vec::with_capacity(states.len());
+        for ts in states {
+            self.write_to_db(&ts)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+            task_ids.push(ts.external_id());
+        }
+        Ok(CreateTasksResponse::new(task_ids))
+    }
+
+    // access control:
+    // 1) task.participants.contains(&user_id), or
+    // 2) the user has the read_any_output permission
     fn get_task(
         &self,
         request: Request<GetTaskRequest>,
@@ -341,11 +819,11 @@ impl TeaclaveManagement for TeaclaveManagementService {
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let ts: TaskState = self
-            .read_from_db(&request.message.task_id)
+            .query_from_db(&request.message.task_id)
             .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
 
         ensure!(
-            ts.has_participant(&user_id),
+            ts.has_participant(&user_id) || can_read_any_output(request.metadata()),
             TeaclaveManagementServiceError::PermissionDenied
         );
 
@@ -363,9 +841,118 @@ impl TeaclaveManagement for TeaclaveManagementService {
             approved_users: ts.approved_users,
             assigned_inputs: ts.assigned_inputs.external_ids(),
             assigned_outputs: ts.assigned_outputs.external_ids(),
+            env: ts.env,
+            priority: ts.priority,
             result: ts.result,
             status: ts.status,
+            attempts: ts.attempts,
+            last_error: ts.last_error,
+            preemptions: ts.preemptions,
+        };
+        Ok(response)
+    }
+
+    // access control:
+    // 1) task.participants.contains(user_id), or
+    // 2) the user has the read_any_output permission
+    fn get_task_result(
+        &self,
+        request: Request<GetTaskResultRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTaskResultResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let read_any_output = can_read_any_output(request.metadata());
+        let request = request.message;
+
+        let ts: TaskState = self
+            .query_from_db(&request.task_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+
+        ensure!(
+            ts.has_participant(&user_id) || read_any_output,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        let cwt = match &ts.result {
+            TaskResult::Ok(outputs) if request.export_cwt => {
+                self.task_result_cwt(&ts, outputs).map_err(|e| {
+                    log::error!("Failed to export task result: {:?}", e);
+                    TeaclaveManagementServiceError::ExportError
+                })?
+            }
+            _ => Vec::new(),
+        };
+        let response = task_result_range(ts.result, request.offset, request.length)
+            .map_err(|_| TeaclaveManagementServiceError::InvalidRequest)?;
+        Ok(response.cwt(cwt))
+    }
+
+    // access control:
+    // 1) task.participants.contains(user_id), or
+    // 2) the user has the read_any_output permission
+    fn get_task_log(
+        &self,
+        request: Request<GetTaskLogRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTaskLogResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let read_any_output = can_read_any_output(request.metadata());
+        let request = request.message;
+
+        let ts: TaskState = self
+            .query_from_db(&request.task_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+
+        ensure!(
+            ts.has_participant(&user_id) || read_any_output,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        // Tasks which have not logged yet have no log.
+        let key = ExternalID::new(TaskLog::key_prefix(), ts.uuid());
+        let task_log = match self.get_optional_from_db(&key.to_bytes())? {
+            Some(value) => TaskLog::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?,
+            None => TaskLog::default(),
+        };
+        let log = task_log.range(request.offset, request.limit).to_vec();
+        let response =
+            GetTaskLogResponse::new(log, task_log.content.len() as u64, task_log.truncated);
+        Ok(response)
+    }
+
+    // access control:
+    // 1) task.participants.contains(user_id), or
+    // 2) the user has the read_any_output permission
+    fn stream_task_result(
+        &self,
+        request: Request<StreamTaskResultRequest>,
+    ) -> TeaclaveServiceResponseResult<StreamTaskResultResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let read_any_output = can_read_any_output(request.metadata());
+        let request = request.message;
+
+        let ts: TaskState = self
+            .query_from_db(&request.task_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+
+        ensure!(
+            ts.has_participant(&user_id) || read_any_output,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        // The execution service sends the chunks before the result, so the
+        // stream of a task read as finished above is complete.
+        let finished = ts.status == TaskStatus::Finished;
+        let key = ExternalID::new(TaskResultStream::key_prefix(), ts.uuid());
+        let stream = match self.get_optional_from_db(&key.to_bytes())? {
+            Some(value) => TaskResultStream::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?,
+            None => TaskResultStream::default(),
         };
+        let chunks = stream.range(request.offset, request.limit).to_vec();
+        let next_offset = request.offset.saturating_add(chunks.len() as u64);
+        let response = StreamTaskResultResponse::new(chunks, next_offset)
+            .finished(finished)
+            .truncated(stream.truncated);
         Ok(response)
     }
 
@@ -379,10 +966,15 @@ impl TeaclaveManagement for TeaclaveManagementService {
     //    * inputs_ownership or outputs_ownership contains the data name
     //    * input file: OwnerList match input_file.owner
     //    * output file: OwnerList match output_file.owner
+    // 5) neither task.creator nor task.function_owner is disabled
+    // 6) the tags of the function satisfy the label rules of the input files
+    // 7) input files with privacy budgets are read by differentially private
+    //    functions only
     fn assign_data(
         &self,
         request: Request<AssignDataRequest>,
     ) -> TeaclaveServiceResponseResult<AssignDataResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let request = request.message;
@@ -395,19 +987,35 @@ impl TeaclaveManagement for TeaclaveManagementService {
             ts.has_participant(&user_id),
             TeaclaveManagementServiceError::PermissionDenied
         );
+        self.ensure_task_enabled(&ts)?;
+        let function_id = ts.function_id.clone();
 
         let mut task: Task<Assign> = ts.try_into().map_err(|e| {
             log::warn!("Assign state error: {:?}", e);
             TeaclaveManagementServiceError::PermissionDenied
         })?;
 
+        let mut labels = Vec::new();
+        let mut has_privacy_budget = false;
         for (data_name, data_id) in request.inputs.iter() {
             let file: TeaclaveInputFile = self
                 .read_from_db(&data_id)
                 .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+            labels.extend(file.labels.iter().cloned());
+            has_privacy_budget |= file.privacy_budget.is_some();
             task.assign_input(&user_id, data_name, file)
                 .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
         }
+        self.ensure_data_use_allowed(&function_id, labels)?;
+        if has_privacy_budget {
+            let function: Function = self
+                .read_from_db(&function_id)
+                .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+            ensure!(
+                is_differential_privacy_function(function.executor_type, &function.name),
+                TeaclaveManagementServiceError::PermissionDenied
+            );
+        }
 
         for (data_name, data_id) in request.outputs.iter() {
             let file: TeaclaveOutputFile = self
@@ -429,16 +1037,19 @@ impl TeaclaveManagement for TeaclaveManagementService {
     // access_control:
     // 1) task status == Ready
     // 2) user_id in task.participants
+    // 3) neither task.creator nor task.function_owner is disabled
     fn approve_task(
         &self,
         request: Request<ApproveTaskRequest>,
     ) -> TeaclaveServiceResponseResult<ApproveTaskResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
 
         let request = request.message;
         let ts: TaskState = self
             .read_from_db(&request.task_id)
             .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        self.ensure_task_enabled(&ts)?;
 
         let mut task: Task<Approve> = ts.try_into().map_err(|e| {
             log::warn!("Approve state error: {:?}", e);
@@ -460,129 +1071,2048 @@ impl TeaclaveManagement for TeaclaveManagementService {
     // access_control:
     // 1) task status == Approved
     // 2) user_id == task.creator
+    // 3) neither task.creator nor task.function_owner is disabled
     fn invoke_task(
         &self,
         request: Request<InvokeTaskRequest>,
     ) -> TeaclaveServiceResponseResult<InvokeTaskResponse> {
+        self.ensure_writable()?;
         let user_id = self.get_request_user_id(request.metadata())?;
         let request = request.message;
 
-        let ts: TaskState = self
-            .read_from_db(&request.task_id)
-            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        self.invoke_created_task(&user_id, &request.task_id, request.retry_policy)?;
+        Ok(InvokeTaskResponse)
+    }
 
-        // Early validation
+    // access control: the same as InvokeTask for each task
+    // Each task is checked against the quota of the user, and the failure of a
+    // task does not stop the invocation of the others.
+    fn invoke_tasks(
+        &self,
+        request: Request<InvokeTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<InvokeTasksResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
         ensure!(
-            ts.has_creator(&user_id),
-            TeaclaveManagementServiceError::PermissionDenied
+            !request.task_ids.is_empty() && request.task_ids.len() <= MAX_BATCH_TASKS,
+            TeaclaveManagementServiceError::InvalidRequest
         );
 
-        let function: Function = self
-            .read_from_db(&ts.function_id)
-            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        let mut failures = Vec::new();
+        for task_id in request.task_ids {
+            let result = self
+                .ensure_invocation_quota(&user_id, now_secs())
+                .and_then(|_| self.invoke_created_task(&user_id, &task_id, None));
+            if let Err(e) = result {
+                log::debug!("InvokeTasks: cannot invoke {:?}: {:?}", task_id, e);
+                failures.push(InvokeTaskFailure {
+                    task_id,
+                    reason: e.to_string(),
+                });
+            }
+        }
+        Ok(InvokeTasksResponse::new(failures))
+    }
 
-        log::debug!("InvokeTask: get function: {:?}", function);
+    // access control: user_id is a reviewer of the outputs of the task held
+    // by the content scanners, i.e., an owner of its input data
+    // Once every reviewer has approved them, the task is staged again for
+    // the execution service to upload them.
+    fn review_output(
+        &self,
+        request: Request<ReviewOutputRequest>,
+    ) -> TeaclaveServiceResponseResult<ReviewOutputResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
 
-        let mut task: Task<Stage> = ts.try_into().map_err(|e| {
-            log::warn!("Stage state error: {:?}", e);
+        let mut ts: TaskState = self
+            .read_from_db(&request.task_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        let held_id = ExternalID::new(HeldOutputs::key_prefix(), ts.uuid());
+        let mut held: HeldOutputs = self
+            .read_from_db(&held_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+
+        let status = held.review(&user_id, request.approve).map_err(|e| {
+            log::debug!("ReviewOutput: {:?}", e);
             TeaclaveManagementServiceError::PermissionDenied
         })?;
+        // The task is staged with the review, or not at all.
+        let mut batch = BatchRequest::new();
+        if status == HeldOutputsStatus::Released {
+            let staged_task = ts
+                .stage_release(held.release())
+                .map_err(|_| TeaclaveManagementServiceError::BadTask)?;
+            let queue_key = StagedTask::get_priority_queue_key(staged_task.priority);
+            batch = put_record(batch, &ts)?.enqueue(queue_key.as_bytes(), to_value(&staged_task)?);
+        }
+        self.write_batch(put_record(batch, &held)?)?;
 
-        log::debug!("InvokeTask: get task: {:?}", task);
-
-        let staged_task = task.stage_for_running(&user_id, function)?;
+        self.audit.record(
+            AuditEventKind::OutputReviewed,
+            &user_id.to_string(),
+            format!("{} {}", request.task_id, status.as_str()),
+        );
+        let findings = held.findings.iter().map(ToString::to_string).collect();
+        Ok(ReviewOutputResponse::new(status, findings))
+    }
 
-        log::debug!("InvokeTask: staged task: {:?}", staged_task);
+    // access control:
+    // 1) user_id == task.creator
+    // 2) the task is approved with its data assigned, and the URLs of its
+    //    outputs have the run placeholder
+    fn create_scheduled_task(
+        &self,
+        request: Request<CreateScheduledTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateScheduledTaskResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
 
-        self.enqueue_to_db(StagedTask::get_queue_key().as_bytes(), &staged_task)?;
+        let template: TaskState = self
+            .read_from_db(&request.task_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        ensure!(
+            template.has_creator(&user_id),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        self.ensure_task_enabled(&template)?;
+        if let Err(e) = ScheduledTask::check_template(&template) {
+            log::warn!("Invalid template of scheduled task: {:?}", e);
+            return Err(TeaclaveManagementServiceError::InvalidRequest.into());
+        }
 
-        let ts: TaskState = task.into();
-        self.write_to_db(&ts)
+        let scheduled = ScheduledTask::new(user_id, request.task_id, request.schedule, now_secs());
+        ensure!(
+            scheduled.next_run.is_some(),
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        let _guard = self
+            .schedule_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        self.write_to_db(&scheduled)
             .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
 
-        Ok(InvokeTaskResponse)
+        Ok(CreateScheduledTaskResponse::new(scheduled.external_id()))
     }
-}
 
-impl TeaclaveManagementService {
-    pub(crate) fn new(storage_service_endpoint: Endpoint) -> Result<Self> {
-        let mut i = 0;
-        let channel = loop {
-            match storage_service_endpoint.connect() {
-                Ok(channel) => break channel,
-                Err(_) => {
-                    anyhow::ensure!(i < 10, "failed to connect to storage service");
-                    log::debug!("Failed to connect to storage service, retry {}", i);
-                    i += 1;
-                }
-            }
-            std::thread::sleep(std::time::Duration::from_secs(3));
-        };
-        let storage_client = Arc::new(Mutex::new(TeaclaveStorageClient::new(channel)?));
-        let service = Self { storage_client };
+    // access control: user_id == scheduled_task.creator
+    fn pause_scheduled_task(
+        &self,
+        request: Request<PauseScheduledTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<PauseScheduledTaskResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        self.update_scheduled_task(&request.message.scheduled_task_id, &user_id, |scheduled| {
+            scheduled.pause()
+        })?;
+        Ok(PauseScheduledTaskResponse)
+    }
 
-        #[cfg(test_mode)]
-        service.add_mock_data()?;
+    // access control: user_id == scheduled_task.creator
+    fn resume_scheduled_task(
+        &self,
+        request: Request<ResumeScheduledTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ResumeScheduledTaskResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        self.update_scheduled_task(&request.message.scheduled_task_id, &user_id, |scheduled| {
+            scheduled.resume(now_secs())
+        })?;
+        Ok(ResumeScheduledTaskResponse)
+    }
+
+    // access control: user_id == scheduled_task.creator
+    fn list_upcoming_runs(
+        &self,
+        request: Request<ListUpcomingRunsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListUpcomingRunsResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+        ensure!(
+            request.count > 0 && request.count <= MAX_UPCOMING_RUNS,
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+
+        let scheduled: ScheduledTask = self
+            .query_from_db(&request.scheduled_task_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        ensure!(
+            scheduled.creator == user_id,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        Ok(ListUpcomingRunsResponse {
+            run_times: scheduled.upcoming(request.count as usize),
+            schedule: scheduled.schedule,
+            paused: scheduled.paused,
+            runs: scheduled.runs,
+            last_task_id: scheduled.last_task_id,
+        })
+    }
+
+    // access control:
+    // 1) user_id == task.creator of every task
+    // 2) the tasks are not invoked, and the linked outputs and inputs are
+    //    declared with the same owners, including user_id
+    // 3) neither task.creator nor task.function_owner is disabled
+    fn create_pipeline(
+        &self,
+        request: Request<CreatePipelineRequest>,
+    ) -> TeaclaveServiceResponseResult<CreatePipelineResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let pipeline = Pipeline::new(user_id, request.task_ids, request.links).map_err(|e| {
+            log::warn!("Invalid pipeline: {:?}", e);
+            TeaclaveManagementServiceError::InvalidRequest
+        })?;
+        let mut states = Vec::with_capacity(pipeline.tasks.len());
+        for task_id in pipeline.tasks.iter() {
+            let ts: TaskState = self
+                .read_from_db(task_id)
+                .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+            ensure!(
+                ts.has_creator(&pipeline.creator),
+                TeaclaveManagementServiceError::PermissionDenied
+            );
+            self.ensure_task_enabled(&ts)?;
+            states.push(ts);
+        }
+        if let Err(e) = pipeline.check_tasks(&states) {
+            log::warn!("Invalid tasks of pipeline: {:?}", e);
+            return Err(TeaclaveManagementServiceError::InvalidRequest.into());
+        }
+
+        let _guard = self
+            .pipeline_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        self.write_to_db(&pipeline)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let mut index = self.read_pipeline_index()?;
+        index.push(pipeline.id);
+        self.write_pipeline_index(&index)?;
+
+        Ok(CreatePipelineResponse::new(pipeline.external_id()))
+    }
+
+    // access control:
+    // 1) user_id == pipeline.creator, or
+    // 2) the user has the read_any_output permission
+    fn get_pipeline(
+        &self,
+        request: Request<GetPipelineRequest>,
+    ) -> TeaclaveServiceResponseResult<GetPipelineResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let read_any_output = can_read_any_output(request.metadata());
+
+        let pipeline: Pipeline = self
+            .query_from_db(&request.message.pipeline_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        ensure!(
+            pipeline.creator == user_id || read_any_output,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        Ok(GetPipelineResponse {
+            creator: pipeline.creator,
+            task_ids: pipeline.tasks,
+            links: pipeline.links,
+            status: pipeline.status,
+            failed_task_id: pipeline.failed_task,
+        })
+    }
+
+    // access control:
+    // 1) function.public || function.owner == user_id
+    // 2) function.owner is not disabled
+    fn list_functions(
+        &self,
+        request: Request<ListFunctionsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListFunctionsResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let mut disabled_owners = HashMap::new();
+        let mut functions = Vec::new();
+        for function in self.read_all_of::<Function>()? {
+            if !(function.public || function.owner == user_id)
+                || request
+                    .owner
+                    .as_ref()
+                    .map_or(false, |owner| owner != &function.owner)
+            {
+                continue;
+            }
+            let disabled = match disabled_owners.get(&function.owner) {
+                Some(disabled) => *disabled,
+                None => {
+                    let disabled = self.is_user_disabled(&function.owner)?;
+                    disabled_owners.insert(function.owner.clone(), disabled);
+                    disabled
+                }
+            };
+            if !disabled {
+                functions.push(function);
+            }
+        }
+        let (functions, next_cursor) = request
+            .options
+            .page(functions, |function| (function.created_at, function.id))
+            .map_err(|_| TeaclaveManagementServiceError::InvalidRequest)?;
+
+        let functions = functions
+            .into_iter()
+            .map(|function| FunctionSummary {
+                function_id: function.external_id(),
+                name: function.name,
+                owner: function.owner,
+                version: function.version,
+                public: function.public,
+                created_at: function.created_at,
+            })
+            .collect();
+        Ok(ListFunctionsResponse {
+            functions,
+            next_cursor,
+        })
+    }
+
+    // access control:
+    // 1) task.participants.contains(&user_id), or
+    // 2) the user has the read_any_output permission
+    fn list_tasks(
+        &self,
+        request: Request<ListTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<ListTasksResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let read_any_output = can_read_any_output(request.metadata());
+        let request = request.message;
+
+        let tasks: Vec<TaskState> = self
+            .read_all_of::<TaskState>()?
+            .into_iter()
+            .filter(|ts| {
+                (ts.has_participant(&user_id) || read_any_output)
+                    && request
+                        .creator
+                        .as_ref()
+                        .map_or(true, |creator| creator == &ts.creator)
+                    && (request.statuses.is_empty() || request.statuses.contains(&ts.status))
+            })
+            .collect();
+        let (tasks, next_cursor) = request
+            .options
+            .page(tasks, |ts| (ts.created_at, ts.task_id))
+            .map_err(|_| TeaclaveManagementServiceError::InvalidRequest)?;
+
+        let tasks = tasks
+            .into_iter()
+            .map(|ts| TaskSummary {
+                task_id: ts.external_id(),
+                creator: ts.creator,
+                function_id: ts.function_id,
+                status: ts.status,
+                created_at: ts.created_at,
+            })
+            .collect();
+        Ok(ListTasksResponse { tasks, next_cursor })
+    }
+
+    // access control: the webhook of the user
+    // A new secret is returned each time, so that a leaked secret is replaced
+    // by registering again.
+    fn register_webhook(
+        &self,
+        request: Request<RegisterWebhookRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterWebhookResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let mut secret = vec![0; WEBHOOK_SECRET_LEN];
+        platform::rand::fill_bytes(&mut secret);
+        let webhook = Webhook::new(
+            user_id.clone(),
+            request.message.url,
+            secret.clone(),
+            now_secs(),
+        )
+        .map_err(|_| TeaclaveManagementServiceError::InvalidRequest)?;
+        let value =
+            serde_json::to_vec(&webhook).map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        self.put_to_db(&user_key(WEBHOOK_PREFIX, &user_id), &value)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+
+        Ok(RegisterWebhookResponse { secret })
+    }
+
+    // access control: the webhook of the user
+    fn delete_webhook(
+        &self,
+        request: Request<DeleteWebhookRequest>,
+    ) -> TeaclaveServiceResponseResult<DeleteWebhookResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        self.delete_from_db(None, &user_key(WEBHOOK_PREFIX, &user_id));
+        Ok(DeleteWebhookResponse)
+    }
+
+    // access control: the user has the manage_users permission
+    fn check_consistency(
+        &self,
+        request: Request<CheckConsistencyRequest>,
+    ) -> TeaclaveServiceResponseResult<CheckConsistencyResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        ensure!(
+            has_permission(request.metadata(), Permission::ManageUsers),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        let repair_requested = request.message.repair;
+        if repair_requested {
+            self.ensure_writable()?;
+        }
+        let mut findings = Vec::new();
+        let mut repairs = Vec::new();
+        let records: Vec<(Vec<u8>, Vec<u8>)> = self
+            .read_all_from_db()?
+            .into_iter()
+            .map(|(_, key, value)| (key, value))
+            .collect();
+        for (finding, repair) in consistency::check_records(&records) {
+            if let Some(repair) = repair.filter(|_| repair_requested) {
+                repairs.push((findings.len(), repair));
+            }
+            findings.push(finding);
+        }
+
+        // The repairs are applied all or none; those applied are reverted
+        // if one fails.
+        for (i, (_, repair)) in repairs.iter().enumerate() {
+            if self.apply_repair(&repair.key, &repair.value).is_err() {
+                for (_, repair) in repairs[..i].iter().rev() {
+                    if let Err(e) = self.apply_repair(&repair.key, &repair.original) {
+                        log::error!("Failed to restore {:?}: {:?}", repair.key, e);
+                    }
+                }
+                return Err(TeaclaveManagementServiceError::StorageError.into());
+            }
+        }
+        for (index, _) in repairs {
+            let finding = &mut findings[index];
+            finding.repaired = true;
+            self.audit.record(
+                AuditEventKind::ConsistencyRepaired,
+                &user_id.to_string(),
+                format!(
+                    "{} {}: {}",
+                    finding.kind.as_str(),
+                    finding.key,
+                    finding.detail
+                ),
+            );
+        }
+        Ok(CheckConsistencyResponse::new(findings))
+    }
+
+    // access control: the user has the manage_users permission
+    // Entering the mode again replaces its window.
+    fn enter_read_only_mode(
+        &self,
+        request: Request<EnterReadOnlyModeRequest>,
+    ) -> TeaclaveServiceResponseResult<EnterReadOnlyModeResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        ensure!(
+            has_permission(request.metadata(), Permission::ManageUsers),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        let request = request.message;
+        let window = ReadOnlyWindow::new(
+            &user_id.to_string(),
+            &request.reason,
+            request.duration_secs,
+            now_secs(),
+        )
+        .map_err(|e| TeaclaveManagementServiceError::InvalidReadOnlyMode(e.to_string()))?;
+        self.put_to_db(READ_ONLY_MODE_KEY.as_bytes(), &window.to_vec())
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        self.audit.record(
+            AuditEventKind::ReadOnlyModeEntered,
+            &user_id.to_string(),
+            format!("until {}: {}", window.expires_at, window.reason),
+        );
+        Ok(EnterReadOnlyModeResponse::new(window.expires_at))
+    }
+
+    // access control: the user has the manage_users permission
+    fn exit_read_only_mode(
+        &self,
+        request: Request<ExitReadOnlyModeRequest>,
+    ) -> TeaclaveServiceResponseResult<ExitReadOnlyModeResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        ensure!(
+            has_permission(request.metadata(), Permission::ManageUsers),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        if self.read_only_window()?.is_none() {
+            return Ok(ExitReadOnlyModeResponse);
+        }
+        self.storage_client
+            .clone()
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?
+            .delete(DeleteRequest::new(READ_ONLY_MODE_KEY.as_bytes()))
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        self.audit
+            .record(AuditEventKind::ReadOnlyModeExited, &user_id.to_string(), "");
+        Ok(ExitReadOnlyModeResponse)
+    }
+
+    // access control: none, the log is public
+    fn get_measurement_inclusion(
+        &self,
+        request: Request<GetMeasurementInclusionRequest>,
+    ) -> TeaclaveServiceResponseResult<GetMeasurementInclusionResponse> {
+        let log = self
+            .measurement_log
+            .as_ref()
+            .ok_or(TeaclaveManagementServiceError::MeasurementLogUnavailable)?;
+        let signed_tree_head = log
+            .signed_tree_head
+            .clone()
+            .ok_or(TeaclaveManagementServiceError::MeasurementLogUnavailable)?;
+        let proof = log
+            .inclusion_proof(&request.message.entry)
+            .map_err(|_| TeaclaveManagementServiceError::MeasurementNotFound)?;
+        Ok(GetMeasurementInclusionResponse::new(
+            proof,
+            signed_tree_head,
+        ))
+    }
+
+    // access control: manage_users, checked by the access control service
+    fn update_access_control_policy(
+        &self,
+        request: Request<UpdateAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<UpdateAccessControlPolicyResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let response = self
+            .access_control_client(request.metadata)?
+            .update_policy(request.message)?;
+        self.audit.record(
+            AuditEventKind::PolicyUpdated,
+            &user_id.to_string(),
+            format!("access control policy version {}", response.version),
+        );
+        Ok(response)
+    }
+
+    // access control: manage_users, checked by the access control service
+    fn rollback_access_control_policy(
+        &self,
+        request: Request<RollbackAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<RollbackAccessControlPolicyResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let version = request.message.version;
+        let response = self
+            .access_control_client(request.metadata)?
+            .rollback_policy(request.message)?;
+        self.audit.record(
+            AuditEventKind::PolicyUpdated,
+            &user_id.to_string(),
+            format!("access control policy rolled back to version {}", version),
+        );
+        Ok(response)
+    }
+
+    // access control: manage_users, checked by the access control service
+    fn get_access_control_policy(
+        &self,
+        request: Request<GetAccessControlPolicyRequest>,
+    ) -> TeaclaveServiceResponseResult<GetAccessControlPolicyResponse> {
+        let response = self
+            .access_control_client(request.metadata)?
+            .get_policy(request.message)?;
+        Ok(response)
+    }
+
+    // access control: manage_users or the owner of the object, checked by the
+    // access control service
+    fn explain_access(
+        &self,
+        request: Request<ExplainAccessRequest>,
+    ) -> TeaclaveServiceResponseResult<ExplainAccessResponse> {
+        let mut client = self.access_control_client(request.metadata)?;
+        let request = request.message;
+        let prefix = request.object_id.prefix.as_str();
+        let user_id = request.user_id.to_string();
+        let object_id = request.object_id.to_string();
+        let (accept, explanation) = if prefix == TeaclaveInputFile::key_prefix()
+            || prefix == TeaclaveOutputFile::key_prefix()
+        {
+            let response =
+                client.authorize_data(AuthorizeDataRequest::new(user_id, object_id).explain())?;
+            (response.accept, response.explanation)
+        } else if prefix == Function::key_prefix() {
+            let response = client
+                .authorize_function(AuthorizeFunctionRequest::new(user_id, object_id).explain())?;
+            (response.accept, response.explanation)
+        } else if prefix == TaskState::key_prefix() {
+            let response =
+                client.authorize_task(AuthorizeTaskRequest::new(user_id, object_id).explain())?;
+            (response.accept, response.explanation)
+        } else {
+            bail!(TeaclaveManagementServiceError::InvalidRequest);
+        };
+        // Other users get the decision without its explanation.
+        ensure!(
+            !explanation.is_empty(),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        Ok(ExplainAccessResponse::new(accept, explanation))
+    }
+
+    // access control: manage_users
+    // The objects are updated in place, keeping their ids. Either all selected
+    // objects are transferred, or none: a failed write restores the objects
+    // written before it.
+    fn transfer_ownership(
+        &self,
+        request: Request<TransferOwnershipRequest>,
+    ) -> TeaclaveServiceResponseResult<TransferOwnershipResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        ensure!(
+            has_permission(request.metadata(), Permission::ManageUsers),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        let request = request.message;
+        let from_user = request.from_user;
+        let to_user = request.to_user;
+        let filter = request.object_filter;
+        ensure!(
+            !from_user.to_string().is_empty()
+                && !to_user.to_string().is_empty()
+                && from_user != to_user,
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        ensure!(
+            filter
+                .kinds
+                .iter()
+                .all(|kind| TRANSFERABLE_KINDS.contains(&kind.as_str())),
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        ensure!(
+            !self.is_user_disabled(&to_user)?,
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+
+        let mut transfers = Vec::new();
+        let mut collaborators = HashSet::new();
+        for (namespace, key, value) in self.read_all_from_db()? {
+            let id: ExternalID = match std::str::from_utf8(&key).map(TryInto::try_into) {
+                Ok(Ok(id)) => id,
+                _ => continue,
+            };
+            if !filter.matches(&id) {
+                continue;
+            }
+            let transferred =
+                transfer_record(&id, &value, &from_user, &to_user, &mut collaborators)
+                    .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+            if let Some((transferred, tenant)) = transferred {
+                transfers.push((id, namespace, key, transferred, tenant));
+            }
+        }
+        // Objects selected by id must all belong to the user.
+        ensure!(
+            filter
+                .ids
+                .iter()
+                .all(|id| transfers.iter().any(|(transferred, ..)| transferred == id)),
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+
+        // Records move to the namespace of their new tenant.
+        let batch = transfers.iter().fold(
+            BatchRequest::new(),
+            |batch, (_, namespace, key, transferred, tenant)| {
+                let batch = match namespace {
+                    Some(namespace) => batch.delete_in(namespace.as_str(), key.as_slice()),
+                    None => batch,
+                };
+                put_value(batch, key, transferred.clone(), tenant.as_ref())
+            },
+        );
+        self.write_batch(batch)?;
+
+        let object_ids: Vec<ExternalID> = transfers.into_iter().map(|(id, ..)| id).collect();
+        for id in &object_ids {
+            self.audit.record(
+                AuditEventKind::OwnershipTransferred,
+                &user_id.to_string(),
+                format!("{} from {} to {}", id.to_string(), from_user, to_user),
+            );
+        }
+        collaborators.remove(&from_user);
+        collaborators.remove(&to_user);
+        let collaborators: Vec<UserID> = collaborators.into_iter().collect();
+        if !collaborators.is_empty() {
+            let names: Vec<String> = collaborators.iter().map(UserID::to_string).collect();
+            self.audit.record(
+                AuditEventKind::OwnershipTransferred,
+                &user_id.to_string(),
+                format!(
+                    "objects of {} shared with {} transferred to {}",
+                    from_user,
+                    names.join(","),
+                    to_user
+                ),
+            );
+        }
+        Ok(TransferOwnershipResponse::new(object_ids, collaborators))
+    }
+
+    // access control: the user has the manage_users permission
+    fn set_user_quota(
+        &self,
+        request: Request<SetUserQuotaRequest>,
+    ) -> TeaclaveServiceResponseResult<SetUserQuotaResponse> {
+        self.ensure_writable()?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+        ensure!(
+            has_permission(request.metadata(), Permission::ManageUsers),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        let request = request.message;
+        ensure!(
+            !request.user_id.to_string().is_empty(),
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        let value = serde_json::to_vec(&request.quota)
+            .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        self.put_to_db(&user_key(QUOTA_PREFIX, &request.user_id), &value)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+
+        self.audit.record(
+            AuditEventKind::QuotaSet,
+            &user_id.to_string(),
+            format!("quota of {}: {:?}", request.user_id, request.quota),
+        );
+        Ok(SetUserQuotaResponse)
+    }
+
+    // access control:
+    // 1) the usage is of the user, or
+    // 2) the user has the manage_users permission
+    fn get_quota_usage(
+        &self,
+        request: Request<GetQuotaUsageRequest>,
+    ) -> TeaclaveServiceResponseResult<GetQuotaUsageResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let can_manage_users = has_permission(request.metadata(), Permission::ManageUsers);
+        let target = match request.message.user_id {
+            Some(target) => target,
+            None => user_id.clone(),
+        };
+        ensure!(
+            target == user_id || can_manage_users,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+
+        let quota = self.read_quota(&target)?;
+        let mut record = self.read_usage(&target)?;
+        record.retain_active(|task_id| self.is_task_active(task_id));
+        Ok(GetQuotaUsageResponse::new(quota, record.usage(now_secs())))
+    }
+
+    // access control:
+    // 1) the stats are of the user, or
+    // 2) the user has the manage_users permission, who gets the stats of all
+    //    users with tasks finished in the last week by default
+    fn get_tenant_stats(
+        &self,
+        request: Request<GetTenantStatsRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTenantStatsResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let can_manage_users = has_permission(request.metadata(), Permission::ManageUsers);
+        let request = request.message;
+        ensure!(
+            request.window_secs > 0 && request.window_secs <= MAX_STATS_WINDOW_SECS,
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        let tenants = match request.user_id {
+            Some(target) => {
+                ensure!(
+                    target == user_id || can_manage_users,
+                    TeaclaveManagementServiceError::PermissionDenied
+                );
+                vec![target]
+            }
+            None if can_manage_users => self
+                .task_stats
+                .lock()
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?
+                .tenants(),
+            None => vec![user_id],
+        };
+
+        let now_ms = now_millis();
+        let mut stats = Vec::with_capacity(tenants.len());
+        for tenant in tenants {
+            let mut tenant_stats = self
+                .task_stats
+                .lock()
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?
+                .stats(&tenant, request.window_secs, now_ms);
+            tenant_stats.quota = self.read_quota(&tenant)?;
+            let mut record = self.read_usage(&tenant)?;
+            record.retain_active(|task_id| self.is_task_active(task_id));
+            tenant_stats.usage = record.usage(now_ms / 1000);
+            stats.push(tenant_stats);
+        }
+        Ok(GetTenantStatsResponse::new(stats))
+    }
+
+    // access control: the user has the manage_users permission
+    fn list_nodes(
+        &self,
+        request: Request<ListNodesRequest>,
+    ) -> TeaclaveServiceResponseResult<ListNodesResponse> {
+        ensure!(
+            has_permission(request.metadata(), Permission::ManageUsers),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        let snapshot = self.executor_nodes()?;
+        Ok(ListNodesResponse::new(snapshot.nodes, snapshot.updated_at))
+    }
+
+    // access control: none
+    // Users choose the executor of their functions from those of the nodes.
+    fn list_executors(
+        &self,
+        _request: Request<ListExecutorsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListExecutorsResponse> {
+        let snapshot = self.executor_nodes()?;
+        let nodes = snapshot.nodes.into_iter().map(Into::into).collect();
+        Ok(ListExecutorsResponse::new(nodes, snapshot.updated_at))
+    }
+
+    // access control: none, only the authentication service sends the request
+    // Records are kept, so that the functions and tasks of the user can still
+    // be audited.
+    // It is served in read-only mode too, so that users deleted meanwhile
+    // cannot run their functions once the mode is exited.
+    fn disable_user_resources(
+        &self,
+        request: Request<DisableUserResourcesRequest>,
+    ) -> TeaclaveServiceResponseResult<DisableUserResourcesResponse> {
+        let request = request.message;
+        ensure!(
+            !request.user_id.is_empty(),
+            TeaclaveManagementServiceError::InvalidRequest
+        );
+        let user_id = UserID::from(request.user_id);
+        let value = serde_json::json!({ "deleted": request.deleted }).to_string();
+        let put_request = PutRequest::new(disabled_user_key(&user_id).as_slice(), value.as_bytes());
+        self.storage_client
+            .clone()
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?
+            .put(put_request)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        log::info!(
+            target: "audit",
+            "Disabled functions and pending tasks of user {}",
+            user_id
+        );
+        Ok(DisableUserResourcesResponse)
+    }
+
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        let storage_response = self
+            .storage_client
+            .clone()
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?
+            .health(HealthRequest::new());
+        Ok(HealthResponse::new(vec![
+            health::attestation_check(),
+            health::dependency_check("storage", storage_response),
+        ]))
+    }
+}
+
+impl TeaclaveManagementService {
+    pub(crate) fn new(
+        storage_service_endpoint: Endpoint,
+        storage_replica_endpoints: Vec<Endpoint>,
+        max_replica_staleness: Duration,
+        inline_data_max_size: usize,
+        payload_max_size: usize,
+        measurement_log: Option<MeasurementLog>,
+        attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+        access_control_endpoint: Endpoint,
+        function_env: FunctionEnvConfig,
+    ) -> Result<Self> {
+        let mut i = 0;
+        let channel = loop {
+            match storage_service_endpoint.connect() {
+                Ok(channel) => break channel,
+                Err(_) => {
+                    anyhow::ensure!(i < 10, "failed to connect to storage service");
+                    log::debug!("Failed to connect to storage service, retry {}", i);
+                    i += 1;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_secs(3));
+        };
+        let storage_client = Arc::new(Mutex::new(TeaclaveStorageClient::new(channel)?));
+
+        // Replicas are optional: an unreachable replica is skipped and its
+        // queries are served by the primary.
+        let mut storage_replica_clients = Vec::new();
+        for endpoint in storage_replica_endpoints {
+            match endpoint.connect().and_then(TeaclaveStorageClient::new) {
+                Ok(client) => storage_replica_clients.push(Arc::new(Mutex::new(client))),
+                Err(e) => log::warn!("Failed to connect to storage replica: {:?}", e),
+            }
+        }
+
+        let audit = AuditRecorder::new("teaclave_management_service", storage_client.clone());
+        let service = Self {
+            storage_client,
+            storage_replica_clients,
+            max_replica_staleness,
+            inline_data_max_size,
+            payload_max_size,
+            measurement_log: measurement_log.map(Arc::new),
+            attested_tls_config,
+            access_control_endpoint: Arc::new(access_control_endpoint),
+            function_env: Arc::new(function_env),
+            usage_lock: Arc::new(Mutex::new(())),
+            versions_lock: Arc::new(Mutex::new(())),
+            schedule_lock: Arc::new(Mutex::new(())),
+            pipeline_lock: Arc::new(Mutex::new(())),
+            privacy_lock: Arc::new(Mutex::new(())),
+            uploads_lock: Arc::new(Mutex::new(())),
+            task_stats: Arc::new(Mutex::new(TaskStatsAggregator::new())),
+            webhooks: Arc::new(Mutex::new(WebhookDispatcher::new())),
+            audit,
+        };
+
+        #[cfg(test_mode)]
+        service.add_mock_data()?;
+
+        Ok(service)
+    }
+
+    pub(crate) fn audit(&self) -> &AuditRecorder {
+        &self.audit
+    }
+
+    // Applies the changes of the storage since the last update to the task
+    // statistics of tenants.
+    pub(crate) fn update_task_stats(&self) -> Result<()> {
+        let mut task_stats = self
+            .task_stats
+            .lock()
+            .map_err(|_| anyhow!("cannot lock task stats"))?;
+        let request = task_stats.cursor().request();
+        let response = self
+            .storage_client
+            .lock()
+            .map_err(|_| anyhow!("cannot lock storage client"))?
+            .get_changes(request)?;
+        task_stats.apply(response, now_millis());
+        Ok(())
+    }
+
+    // Posts the state changes of tasks since the last delivery to the webhooks
+    // of their participants, and retries the failed posts. Users without a
+    // webhook, or disabled, are skipped. The posts are sent without holding
+    // the dispatcher.
+    pub(crate) fn deliver_webhooks(&self) -> Result<()> {
+        let mut webhooks = self
+            .webhooks
+            .lock()
+            .map_err(|_| anyhow!("cannot lock webhooks"))?;
+        let request = webhooks.cursor().request();
+        let response = self
+            .storage_client
+            .lock()
+            .map_err(|_| anyhow!("cannot lock storage client"))?
+            .get_changes(request)?;
+        let now = now_secs();
+        for (event, users) in webhooks.apply(response, now) {
+            for user_id in users {
+                let webhook = match self.read_webhook(&user_id) {
+                    Ok(Some(webhook)) => webhook,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::warn!("Failed to read webhook of {}: {:?}", user_id, e);
+                        continue;
+                    }
+                };
+                if let Err(e) = webhooks.enqueue(&webhook, &event) {
+                    log::warn!("Dropped webhook event of {}: {:?}", event.task_id, e);
+                }
+            }
+        }
+        let posts = webhooks.take_due(now);
+        drop(webhooks);
+
+        let results = webhook::send(posts, webhook::post_event);
+        self.webhooks
+            .lock()
+            .map_err(|_| anyhow!("cannot lock webhooks"))?
+            .finish(results, now_secs());
+        Ok(())
+    }
+
+    fn read_webhook(&self, user_id: &UserID) -> TeaclaveServiceResponseResult<Option<Webhook>> {
+        if self.is_user_disabled(user_id)? {
+            return Ok(None);
+        }
+        match self.get_optional_from_db(&user_key(WEBHOOK_PREFIX, user_id))? {
+            Some(value) => Ok(Some(
+                serde_json::from_slice(&value)
+                    .map_err(|_| TeaclaveManagementServiceError::DataError)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // The metadata carries the id and permissions of the user.
+    fn access_control_client(
+        &self,
+        metadata: HashMap<String, String>,
+    ) -> Result<TeaclaveAccessControlClient, TeaclaveManagementServiceError> {
+        let mut client = self
+            .access_control_endpoint
+            .connect()
+            .and_then(TeaclaveAccessControlClient::new)
+            .map_err(|_| TeaclaveManagementServiceError::AccessControlUnavailable)?;
+        client.metadata_mut().extend(metadata);
+        Ok(client)
+    }
+
+    // Checks the label rules of the access control model for the data with
+    // the labels, before it is assigned to a task of the function.
+    fn ensure_data_use_allowed(
+        &self,
+        function_id: &ExternalID,
+        labels: Vec<String>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        if labels.is_empty() {
+            return Ok(());
+        }
+        let function: Function = self
+            .read_from_db(function_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        let request = AuthorizeDataUseRequest::new(function.tags, labels);
+        let response = self
+            .access_control_client(HashMap::new())?
+            .authorize_data_use(request)?;
+        if !response.accept {
+            log::warn!(
+                "Function {} denied data with labels {:?}",
+                function_id.to_string(),
+                response.denied_labels
+            );
+        }
+        ensure!(
+            response.accept,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        Ok(())
+    }
+
+    pub fn create_fusion_data(&self, owners: impl Into<OwnerList>) -> Result<TeaclaveOutputFile> {
+        let uuid = platform::rand::new_uuid();
+        let url = format!("fusion:///TEACLAVE_FUSION_BASE/{}.fusion", uuid.to_string());
+        let url = Url::parse(&url).map_err(|_| anyhow!("invalid url"))?;
+        let crypto_info = FileCrypto::default();
+
+        Ok(TeaclaveOutputFile::new(url, crypto_info, owners))
+    }
+
+    // Signs the claims of the whole result with the key of the current
+    // attested TLS certificate, which carries the attestation report of this
+    // enclave for verifiers.
+    fn task_result_cwt(&self, ts: &TaskState, outputs: &TaskOutputs) -> Result<Vec<u8>> {
+        let config = self
+            .attested_tls_config
+            .read()
+            .map_err(|_| anyhow!("Cannot lock attested TLS config"))?;
+        let attestation = config.attestation_summary()?;
+        let claims = TaskResultClaims::new(
+            "teaclave_management_service",
+            ts.external_id().to_string(),
+            ts.function_id.to_string(),
+            outputs,
+            platform::time::since_epoch().as_secs(),
+            attestation,
+        );
+        let token = CoseSign1::sign(claims.to_vec(), &config.cert, &config.private_key)?;
+        Ok(token.to_vec())
+    }
+
+    fn get_request_user_id(
+        &self,
+        meta: &HashMap<String, String>,
+    ) -> TeaclaveServiceResponseResult<UserID> {
+        let user_id = meta
+            .get("id")
+            .ok_or_else(|| TeaclaveManagementServiceError::InvalidRequest)?;
+        Ok(user_id.to_string().into())
+    }
+
+    fn write_to_db(&self, item: &impl Storable) -> Result<()> {
+        if item.tenant().is_none() {
+            return self.put_to_db(&item.key(), &item.to_vec()?);
+        }
+        let batch = put_value(
+            BatchRequest::new(),
+            &item.key(),
+            item.to_vec()?,
+            item.tenant(),
+        );
+        self.storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .batch(batch)?;
+        Ok(())
+    }
+
+    fn put_to_db(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let put_request = PutRequest::new(key, value);
+        let _put_response = self
+            .storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .put(put_request)?;
+        Ok(())
+    }
+
+    // The key is deleted by the storage once `ttl` has passed.
+    fn put_to_db_with_ttl(
+        &self,
+        namespace: Option<&str>,
+        key: &[u8],
+        value: &[u8],
+        ttl: Duration,
+    ) -> Result<()> {
+        let mut put_request = PutRequest::new(key, value).ttl(ttl);
+        if let Some(namespace) = namespace {
+            put_request = put_request.namespace(namespace);
+        }
+        let _put_response = self
+            .storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .put(put_request)?;
+        Ok(())
+    }
+
+    fn read_from_db<T: Storable>(&self, key: &ExternalID) -> Result<T> {
+        anyhow::ensure!(T::match_prefix(&key.prefix), "Key prefix doesn't match.");
+
+        let mut client = self
+            .storage_client
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?;
+        let value = get_record(&mut client, &key.to_bytes(), None)?;
+        T::from_slice(value.as_slice())
+    }
+
+    // Reads from the first replica which is not staler than
+    // max_replica_staleness, falling back to the primary. Only for read-only
+    // queries: a record read here may miss the latest writes.
+    fn query_from_db<T: Storable>(&self, key: &ExternalID) -> Result<T> {
+        anyhow::ensure!(T::match_prefix(&key.prefix), "Key prefix doesn't match.");
+
+        for client in &self.storage_replica_clients {
+            let staleness = Some(self.max_replica_staleness);
+            let response = match client.lock() {
+                Ok(mut client) => get_record(&mut client, &key.to_bytes(), staleness),
+                Err(_) => continue,
+            };
+            match response {
+                Ok(value) => return T::from_slice(value.as_slice()),
+                Err(e) => log::debug!("Failed to query storage replica: {:?}", e),
+            }
+        }
+        self.read_from_db(key)
+    }
+
+    // A consistent snapshot of all records, as served by the storage to its
+    // replicas a page at a time, with their namespaces. The records changed
+    // while the pages are read are caught up with afterwards.
+    fn read_all_from_db(&self) -> TeaclaveServiceResponseResult<Vec<NamespacedRecord>> {
+        let client = self.storage_client.clone();
+        let mut client = client
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let mut records = BTreeMap::new();
+        let mut cursor = ChangeCursor::default();
+        loop {
+            let response = client
+                .get_changes(cursor.request())
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+            if cursor.starts_snapshot(&response) {
+                records.clear();
+            }
+            cursor.advance(&response);
+            let snapshot = response.snapshot;
+            for change in response.changes {
+                match change {
+                    StorageChange::Put { key, value } => records.insert(key, value),
+                    StorageChange::Delete { key } => records.remove(&key),
+                };
+            }
+            // Done once the changes after the snapshot are applied.
+            if !snapshot {
+                break;
+            }
+        }
+        let records = records
+            .into_iter()
+            .map(|(key, value)| {
+                let (namespace, record_key) = split_namespaced_key(&key);
+                (namespace.map(str::to_string), record_key.to_vec(), value)
+            })
+            .collect();
+        Ok(records)
+    }
+
+    // Records with keys under `prefix`, in `namespace` if any, read a page at a
+    // time. Records written between the pages may be missed.
+    fn scan_prefix_from_db(
+        &self,
+        namespace: Option<&str>,
+        prefix: &[u8],
+    ) -> TeaclaveServiceResponseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut records = Vec::new();
+        let mut start = None;
+        loop {
+            let mut request = ScanPrefixRequest::new(prefix);
+            if let Some(namespace) = namespace {
+                request = request.namespace(namespace);
+            }
+            if let Some(start) = start {
+                request = request.start(start);
+            }
+            let response = self
+                .storage_client
+                .clone()
+                .lock()
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?
+                .scan_prefix(request)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+            records.extend(response.entries);
+            match response.next_key {
+                Some(next_key) => start = Some(next_key),
+                None => return Ok(records),
+            }
+        }
+    }
+
+    // Records of a kind, scanned by the prefix of their keys, also in the
+    // namespaces of their tenants found by their locators. Records which cannot
+    // be read are skipped.
+    fn read_all_of<T: Storable>(&self) -> TeaclaveServiceResponseResult<Vec<T>> {
+        let prefix = format!("{}-", T::key_prefix());
+        let mut namespaces: Vec<Vec<u8>> = self
+            .scan_prefix_from_db(None, &locator_key(prefix.as_bytes()))?
+            .into_iter()
+            .map(|(_, namespace)| namespace)
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+        let mut records = self.scan_prefix_from_db(None, prefix.as_bytes())?;
+        for namespace in namespaces {
+            let namespace = String::from_utf8(namespace)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+            records.extend(self.scan_prefix_from_db(Some(&namespace), prefix.as_bytes())?);
+        }
+        let records = records
+            .into_iter()
+            .filter(|(key, _)| {
+                match std::str::from_utf8(key).map(TryInto::<ExternalID>::try_into) {
+                    Ok(Ok(id)) => id.prefix == T::key_prefix(),
+                    _ => false,
+                }
+            })
+            .filter_map(|(_, value)| T::from_slice(&value).ok())
+            .collect();
+        Ok(records)
+    }
+
+    // The primary storage fails reads of missing keys with request errors;
+    // other errors fail the read.
+    // Deletes records no longer needed, which only wastes space if it fails.
+    fn delete_from_db(&self, namespace: Option<&str>, key: &[u8]) {
+        let mut request = DeleteRequest::new(key);
+        if let Some(namespace) = namespace {
+            request = request.namespace(namespace);
+        }
+        let response = match self.storage_client.lock() {
+            Ok(mut client) => client.delete(request),
+            Err(_) => return,
+        };
+        if let Err(e) = response {
+            log::warn!("Failed to delete record from storage: {:?}", e);
+        }
+    }
+
+    // Writes the record of a repair, or deletes it if the value is none, where
+    // its locator points to.
+    fn apply_repair(&self, key: &[u8], value: &Option<Vec<u8>>) -> Result<()> {
+        let mut client = self
+            .storage_client
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?;
+        let namespace = locate_record(&mut client, key, None)?;
+        match value {
+            Some(value) => {
+                let mut request = PutRequest::new(key, value.as_slice());
+                if let Some(namespace) = namespace {
+                    request = request.namespace(namespace);
+                }
+                client.put(request)?;
+            }
+            None => {
+                let mut request = DeleteRequest::new(key);
+                if let Some(namespace) = namespace {
+                    request = request.namespace(namespace);
+                }
+                client.delete(request)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Uploads are kept in the namespace of their owner, with their parts.
+    fn read_upload(
+        &self,
+        upload_id: &ExternalID,
+        user_id: &UserID,
+    ) -> TeaclaveServiceResponseResult<PayloadUpload> {
+        ensure!(
+            PayloadUpload::match_prefix(&upload_id.prefix),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        let namespace = tenant_namespace(user_id);
+        let upload: PayloadUpload = self
+            .get_optional_from_db_in(Some(&namespace), &upload_id.to_bytes())?
+            .and_then(|value| PayloadUpload::from_slice(&value).ok())
+            .ok_or(TeaclaveManagementServiceError::PermissionDenied)?;
+        ensure!(
+            &upload.owner == user_id,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        Ok(upload)
+    }
+
+    // Uploads expire with their parts, unless committed and consumed by a
+    // function before. Users may only open a few uploads at once, staging a
+    // limited number of bytes, counted until the uploads expire.
+    fn write_upload(&self, upload: &PayloadUpload) -> TeaclaveServiceResponseResult<()> {
+        let value = upload
+            .to_vec()
+            .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        let namespace = tenant_namespace(&upload.owner);
+
+        let _guard = self
+            .uploads_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let now = now_secs();
+        let mut open_uploads = self.read_open_uploads(&upload.owner)?;
+        open_uploads.prune(now);
+        if !open_uploads.contains(&upload.id) {
+            let max_staged_bytes = MAX_STAGED_UPLOAD_BYTES.max(self.payload_max_size as u64);
+            ensure!(
+                open_uploads.len() < MAX_OPEN_UPLOADS
+                    && open_uploads.staged_bytes() + upload.total_len <= max_staged_bytes,
+                TeaclaveManagementServiceError::QuotaExceeded
+            );
+        }
+        open_uploads.open(upload, now + UPLOAD_TTL.as_secs());
+        let index = serde_json::to_vec(&open_uploads)
+            .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        self.put_to_db_with_ttl(
+            Some(&namespace),
+            OPEN_UPLOADS_KEY.as_bytes(),
+            &index,
+            UPLOAD_TTL,
+        )
+        .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        self.put_to_db_with_ttl(Some(&namespace), &upload.key(), &value, UPLOAD_TTL)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        Ok(())
+    }
+
+    fn read_open_uploads(&self, user_id: &UserID) -> TeaclaveServiceResponseResult<OpenUploads> {
+        let namespace = tenant_namespace(user_id);
+        match self.get_optional_from_db_in(Some(&namespace), OPEN_UPLOADS_KEY.as_bytes())? {
+            Some(value) => Ok(serde_json::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?),
+            None => Ok(OpenUploads::default()),
+        }
+    }
+
+    fn get_optional_from_db(&self, key: &[u8]) -> TeaclaveServiceResponseResult<Option<Vec<u8>>> {
+        self.get_optional_from_db_in(None, key)
+    }
+
+    fn get_optional_from_db_in(
+        &self,
+        namespace: Option<&str>,
+        key: &[u8],
+    ) -> TeaclaveServiceResponseResult<Option<Vec<u8>>> {
+        let mut request = GetRequest::new(key);
+        if let Some(namespace) = namespace {
+            request = request.namespace(namespace);
+        }
+        let response = self
+            .storage_client
+            .clone()
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?
+            .get(request);
+        match response {
+            Ok(response) => Ok(Some(response.value)),
+            Err(TeaclaveServiceResponseError::RequestError(_)) => Ok(None),
+            Err(_) => Err(TeaclaveManagementServiceError::StorageError.into()),
+        }
+    }
+
+    // The window of the read-only mode, if active.
+    fn read_only_window(&self) -> TeaclaveServiceResponseResult<Option<ReadOnlyWindow>> {
+        let value = match self.get_optional_from_db(READ_ONLY_MODE_KEY.as_bytes())? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let window = ReadOnlyWindow::from_slice(&value)
+            .map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        Ok(Some(window).filter(|window| window.is_active(now_secs())))
+    }
+
+    // Rejects mutating requests in read-only mode, or if the mode cannot be
+    // read from the storage.
+    fn ensure_writable(&self) -> TeaclaveServiceResponseResult<()> {
+        if let Some(window) = self.read_only_window()? {
+            bail!(TeaclaveManagementServiceError::ReadOnlyMode(
+                window.expires_at,
+                window.reason
+            ));
+        }
+        Ok(())
+    }
+
+    // The nodes last reported by the scheduler service.
+    fn executor_nodes(&self) -> TeaclaveServiceResponseResult<ExecutorNodes> {
+        match self.get_optional_from_db(EXECUTOR_NODES_KEY.as_bytes())? {
+            Some(value) => Ok(serde_json::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?),
+            None => Ok(ExecutorNodes::default()),
+        }
+    }
+
+    // Whether the authentication service disabled or deleted the user.
+    fn is_user_disabled(&self, user_id: &UserID) -> TeaclaveServiceResponseResult<bool> {
+        Ok(self
+            .get_optional_from_db(&disabled_user_key(user_id))?
+            .is_some())
+    }
+
+    fn read_quota(&self, user_id: &UserID) -> TeaclaveServiceResponseResult<UserQuota> {
+        match self.get_optional_from_db(&user_key(QUOTA_PREFIX, user_id))? {
+            Some(value) => Ok(serde_json::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?),
+            None => Ok(UserQuota::default()),
+        }
+    }
+
+    fn read_usage(&self, user_id: &UserID) -> TeaclaveServiceResponseResult<UsageRecord> {
+        match self.get_optional_from_db(&user_key(USAGE_PREFIX, user_id))? {
+            Some(value) => Ok(serde_json::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?),
+            None => Ok(UsageRecord::default()),
+        }
+    }
+
+    // Tasks count against the quota of the user who invoked them until they
+    // finish. Tasks which cannot be read no longer count.
+    fn is_task_active(&self, task_id: &Uuid) -> bool {
+        let id = ExternalID::new(TaskState::key_prefix(), *task_id);
+        match self.query_from_db::<TaskState>(&id) {
+            Ok(ts) => ts.status != TaskStatus::Finished,
+            Err(_) => false,
+        }
+    }
+
+    // The usage is checked by the frontend service before forwarding the
+    // request, and updated here once the request is served.
+    fn update_usage(
+        &self,
+        user_id: &UserID,
+        update: impl FnOnce(&mut UsageRecord),
+    ) -> TeaclaveServiceResponseResult<()> {
+        let _guard = self
+            .usage_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let mut record = self.read_usage(user_id)?;
+        record.retain_active(|task_id| self.is_task_active(task_id));
+        update(&mut record);
+        let value =
+            serde_json::to_vec(&record).map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        self.put_to_db(&user_key(USAGE_PREFIX, user_id), &value)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        Ok(())
+    }
+
+    // The function of a new task, whose owner must not be disabled.
+    fn read_task_function(
+        &self,
+        function_id: &ExternalID,
+        version: FunctionVersion,
+    ) -> TeaclaveServiceResponseResult<Function> {
+        let function: Function = self
+            .read_from_db(function_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        let function = self.resolve_function_version(function, version)?;
+        ensure!(
+            !self.is_user_disabled(&function.owner)?,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        Ok(function)
+    }
+
+    // Invokes the task on behalf of the user, who must be its creator.
+    fn invoke_created_task(
+        &self,
+        user_id: &UserID,
+        task_id: &ExternalID,
+        retry_policy: Option<RetryPolicy>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let mut ts: TaskState = self
+            .read_from_db(task_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
 
-        Ok(service)
+        // Early validation
+        ensure!(
+            ts.has_creator(user_id),
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        self.ensure_task_enabled(&ts)?;
+        if let Some(retry_policy) = retry_policy {
+            retry_policy
+                .check()
+                .map_err(|_| TeaclaveManagementServiceError::InvalidRequest)?;
+            ts.retry_policy = retry_policy;
+        }
+        self.stage_task(user_id, ts)?;
+
+        self.audit.record(
+            AuditEventKind::TaskInvoked,
+            &user_id.to_string(),
+            task_id.to_string(),
+        );
+        Ok(())
     }
 
-    pub fn create_fusion_data(&self, owners: impl Into<OwnerList>) -> Result<TeaclaveOutputFile> {
-        let uuid = Uuid::new_v4();
-        let url = format!("fusion:///TEACLAVE_FUSION_BASE/{}.fusion", uuid.to_string());
-        let url = Url::parse(&url).map_err(|_| anyhow!("invalid url"))?;
-        let crypto_info = FileCrypto::default();
+    // Tasks created by disabled users, or running their functions, cannot
+    // make progress.
+    // Stages the task for the scheduler on behalf of the user, who is its
+    // creator, and counts it against the quota of the user.
+    fn stage_task(&self, user_id: &UserID, ts: TaskState) -> TeaclaveServiceResponseResult<()> {
+        let function: Function = self
+            .read_from_db(&ts.function_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
 
-        Ok(TeaclaveOutputFile::new(url, crypto_info, owners))
+        log::debug!("InvokeTask: get function: {:?}", function);
+
+        let budgeted_inputs: Vec<Uuid> = ts
+            .assigned_inputs
+            .values()
+            .filter(|file| file.privacy_budget.is_some())
+            .map(|file| file.uuid)
+            .collect();
+
+        let mut task: Task<Stage> = ts.try_into().map_err(|e| {
+            log::warn!("Stage state error: {:?}", e);
+            TeaclaveManagementServiceError::PermissionDenied
+        })?;
+
+        log::debug!("InvokeTask: get task: {:?}", task);
+
+        let manifest = function.manifest.clone();
+        let executor_enclave = match &function.executor_enclave {
+            Some(enclave_id) => {
+                let enclave: ExecutorEnclave = self
+                    .read_from_db(enclave_id)
+                    .map_err(|_| TeaclaveManagementServiceError::BadTask)?;
+                Some(enclave.measurement)
+            }
+            None => None,
+        };
+        let staged_task = task
+            .stage_for_running(user_id, function)?
+            .executor_enclave(executor_enclave);
+
+        // Arguments are checked once their templates have been rendered.
+        if let Some(manifest) = manifest {
+            manifest
+                .check_arguments(&staged_task.function_arguments)
+                .map_err(|_| TeaclaveManagementServiceError::BadTask)?;
+        }
+
+        // The environment of the task overrides the defaults of the
+        // deployment.
+        let mut env = self.function_env.defaults.clone();
+        env.extend(staged_task.env.clone());
+
+        // The scheduler and the execution service continue the trace of the
+        // request with the staged task.
+        let staged_task = staged_task
+            .env(env)
+            .trace_context(teaclave_rpc::trace::current());
+
+        log::debug!("InvokeTask: staged task: {:?}", staged_task);
+
+        // The budgets are spent, the task staged and its state written all
+        // together, or not at all.
+        let privacy_guard = self
+            .privacy_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let files =
+            self.spend_privacy_budgets(&budgeted_inputs, &staged_task.function_arguments)?;
+        let queue_key = StagedTask::get_priority_queue_key(staged_task.priority);
+        let mut batch = BatchRequest::new().enqueue(queue_key.as_bytes(), to_value(&staged_task)?);
+        for file in &files {
+            batch = put_record(batch, file)?;
+        }
+        let ts: TaskState = task.into();
+        self.write_batch(put_record(batch, &ts)?)?;
+        drop(privacy_guard);
+
+        self.update_usage(user_id, |record| {
+            record.record_invocation(ts.task_id, now_secs())
+        })?;
+        Ok(())
     }
 
-    fn get_request_user_id(
+    // Spends the privacy cost of a task, given by its arguments, from the
+    // budgets of its input files, and returns the files to write. Nothing is
+    // spent unless every budget covers the cost. The caller holds the privacy
+    // lock until the files are written.
+    fn spend_privacy_budgets(
         &self,
-        meta: &HashMap<String, String>,
-    ) -> TeaclaveServiceResponseResult<UserID> {
-        let user_id = meta
-            .get("id")
-            .ok_or_else(|| TeaclaveManagementServiceError::InvalidRequest)?;
-        Ok(user_id.to_string().into())
+        inputs: &[Uuid],
+        arguments: &FunctionArguments,
+    ) -> TeaclaveServiceResponseResult<Vec<TeaclaveInputFile>> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let cost = PrivacyCost::from_arguments(arguments).map_err(|e| {
+            log::warn!("Invalid privacy cost: {:?}", e);
+            TeaclaveManagementServiceError::BadTask
+        })?;
+
+        let mut files = Vec::with_capacity(inputs.len());
+        for uuid in inputs {
+            let id = ExternalID::new(TeaclaveInputFile::key_prefix(), *uuid);
+            let mut file: TeaclaveInputFile = self
+                .read_from_db(&id)
+                .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+            if let Some(budget) = file.privacy_budget.as_mut() {
+                budget
+                    .spend(&cost)
+                    .map_err(|_| TeaclaveManagementServiceError::PrivacyBudgetExhausted)?;
+            }
+            files.push(file);
+        }
+        Ok(files)
     }
 
-    fn write_to_db(&self, item: &impl Storable) -> Result<()> {
-        let k = item.key();
-        let v = item.to_vec()?;
-        let put_request = PutRequest::new(k.as_slice(), v.as_slice());
-        let _put_response = self
-            .storage_client
-            .clone()
+    fn update_scheduled_task(
+        &self,
+        id: &ExternalID,
+        user_id: &UserID,
+        update: impl FnOnce(&mut ScheduledTask),
+    ) -> TeaclaveServiceResponseResult<()> {
+        let _guard = self
+            .schedule_lock
             .lock()
-            .map_err(|_| anyhow!("Cannot lock storage client"))?
-            .put(put_request)?;
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let mut scheduled: ScheduledTask = self
+            .read_from_db(id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        ensure!(
+            &scheduled.creator == user_id,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        update(&mut scheduled);
+        self.write_to_db(&scheduled)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
         Ok(())
     }
 
-    fn read_from_db<T: Storable>(&self, key: &ExternalID) -> Result<T> {
-        anyhow::ensure!(T::match_prefix(&key.prefix), "Key prefix doesn't match.");
+    /// Invokes the runs of the scheduled tasks which are due. A run which
+    /// cannot be invoked, e.g., over the quota of the creator, is skipped.
+    /// No runs are invoked in read-only mode; those due run once it is over.
+    pub(crate) fn run_scheduled_tasks(&self) -> TeaclaveServiceResponseResult<()> {
+        if self.read_only_window()?.is_some() {
+            return Ok(());
+        }
+        let _guard = self
+            .schedule_lock
+            .lock()
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let now = now_secs();
+        for mut scheduled in self.read_all_of::<ScheduledTask>()? {
+            if !scheduled.is_due(now) {
+                continue;
+            }
+            if let Err(e) = self.run_scheduled_task(&mut scheduled, now) {
+                log::warn!(
+                    "Failed to run scheduled task {:?}: {:?}",
+                    scheduled.external_id(),
+                    e
+                );
+            }
+            self.write_to_db(&scheduled)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        }
+        Ok(())
+    }
 
-        let request = GetRequest::new(key.to_bytes());
-        let response = self
-            .storage_client
+    fn run_scheduled_task(
+        &self,
+        scheduled: &mut ScheduledTask,
+        now: u64,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let template: TaskState = self
+            .read_from_db(&scheduled.template_id)
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+        let ts = scheduled
+            .next_task(&template, now)
+            .map_err(|_| TeaclaveManagementServiceError::BadTask)?;
+        let creator = scheduled.creator.clone();
+        self.ensure_task_enabled(&ts)?;
+
+        self.ensure_invocation_quota(&creator, now)?;
+
+        for file in ts
+            .assigned_outputs
             .clone()
+            .into_iter()
+            .map(|(_, file)| file)
+        {
+            self.write_to_db(&file)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        }
+        let task_id = ts.external_id();
+        self.stage_task(&creator, ts)?;
+
+        self.audit.record(
+            AuditEventKind::TaskInvoked,
+            &creator.to_string(),
+            format!(
+                "{} of {}",
+                task_id.to_string(),
+                scheduled.external_id().to_string()
+            ),
+        );
+        Ok(())
+    }
+
+    // Unlike invocations, the runs of scheduled tasks and the tasks of
+    // pipelines do not go through the frontend service, which checks the
+    // quota, and it checks batch invocations only once.
+    fn ensure_invocation_quota(
+        &self,
+        user_id: &UserID,
+        now: u64,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let quota = self.read_quota(user_id)?;
+        let mut record = self.read_usage(user_id)?;
+        record.retain_active(|task_id| self.is_task_active(task_id));
+        ensure!(
+            quota.check_invocation(&record.usage(now)).is_ok(),
+            TeaclaveManagementServiceError::QuotaExceeded
+        );
+        Ok(())
+    }
+
+    // Versions of the functions of the owner with the name, none if the
+    // owner registered none since versioning.
+    fn read_function_versions(
+        &self,
+        owner: &UserID,
+        name: &str,
+    ) -> TeaclaveServiceResponseResult<FunctionVersions> {
+        match self.get_optional_from_db(&function_versions_key(owner, name))? {
+            Some(value) => Ok(serde_json::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?),
+            None => Ok(FunctionVersions::new(owner.clone(), name)),
+        }
+    }
+
+    fn write_function_versions(
+        &self,
+        versions: &FunctionVersions,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let value =
+            serde_json::to_vec(versions).map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        self.put_to_db(
+            &function_versions_key(&versions.owner, &versions.name),
+            &value,
+        )
+        .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        Ok(())
+    }
+
+    // The function of the version asked for by a task, among the functions
+    // of the owner with the name of the given one.
+    fn resolve_function_version(
+        &self,
+        function: Function,
+        version: FunctionVersion,
+    ) -> TeaclaveServiceResponseResult<Function> {
+        if version == FunctionVersion::Given {
+            return Ok(function);
+        }
+        let versions = self.read_function_versions(&function.owner, &function.name)?;
+        let id = versions
+            .get(version)
+            .ok_or(TeaclaveManagementServiceError::InvalidRequest)?;
+        if id == function.id {
+            return Ok(function);
+        }
+        self.read_from_db(&ExternalID::new(Function::key_prefix(), id))
+            .map_err(|_| TeaclaveManagementServiceError::PermissionDenied.into())
+    }
+
+    // Ids of the running pipelines, so that finished pipelines are not read
+    // again. The caller holds the pipeline lock.
+    fn read_pipeline_index(&self) -> TeaclaveServiceResponseResult<Vec<Uuid>> {
+        match self.get_optional_from_db(PIPELINE_INDEX_KEY.as_bytes())? {
+            Some(value) => Ok(serde_json::from_slice(&value)
+                .map_err(|_| TeaclaveManagementServiceError::DataError)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn write_pipeline_index(&self, index: &[Uuid]) -> TeaclaveServiceResponseResult<()> {
+        let value =
+            serde_json::to_vec(index).map_err(|_| TeaclaveManagementServiceError::DataError)?;
+        self.put_to_db(PIPELINE_INDEX_KEY.as_bytes(), &value)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        Ok(())
+    }
+
+    /// Advances the running pipelines: the outputs of the tasks which have
+    /// succeeded are assigned to the inputs linked to them, and the tasks
+    /// whose upstream tasks have all succeeded are invoked. Pipelines which
+    /// succeeded or failed are dropped from the index. Pipelines are not
+    /// advanced in read-only mode.
+    pub(crate) fn run_pipelines(&self) -> TeaclaveServiceResponseResult<()> {
+        if self.read_only_window()?.is_some() {
+            return Ok(());
+        }
+        let _guard = self
+            .pipeline_lock
             .lock()
-            .map_err(|_| anyhow!("Cannot lock storage client"))?
-            .get(request)?;
-        T::from_slice(response.value.as_slice())
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        let index = self.read_pipeline_index()?;
+        let mut running = Vec::with_capacity(index.len());
+        for id in index.iter() {
+            let external_id = ExternalID::new(Pipeline::key_prefix(), *id);
+            let mut pipeline: Pipeline = match self.read_from_db(&external_id) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    log::warn!("Failed to read pipeline {:?}: {:?}", external_id, e);
+                    running.push(*id);
+                    continue;
+                }
+            };
+            if let Err(e) = self.advance_pipeline(&mut pipeline) {
+                log::warn!("Failed to advance pipeline {:?}: {:?}", external_id, e);
+            }
+            if pipeline.status == PipelineStatus::Running {
+                running.push(*id);
+                continue;
+            }
+            self.write_to_db(&pipeline)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+            log::info!("Pipeline {:?} {}", external_id, pipeline.status);
+        }
+        if running.len() != index.len() {
+            self.write_pipeline_index(&running)?;
+        }
+        Ok(())
     }
 
-    fn enqueue_to_db(&self, key: &[u8], item: &impl Storable) -> TeaclaveServiceResponseResult<()> {
-        let value = item
-            .to_vec()
-            .map_err(|_| TeaclaveManagementServiceError::DataError)?;
-        let enqueue_request = EnqueueRequest::new(key, value);
-        let _enqueue_response = self
+    fn advance_pipeline(&self, pipeline: &mut Pipeline) -> TeaclaveServiceResponseResult<()> {
+        let mut states = Vec::with_capacity(pipeline.tasks.len());
+        for task_id in pipeline.tasks.iter() {
+            let ts: TaskState = self
+                .read_from_db(task_id)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+            states.push(ts);
+        }
+        if let Some(ts) = states
+            .iter()
+            .find(|ts| matches!(ts.result, TaskResult::Err(_)))
+        {
+            pipeline.status = PipelineStatus::Failed;
+            pipeline.failed_task = Some(ts.external_id());
+            return Ok(());
+        }
+        if states
+            .iter()
+            .all(|ts| matches!(ts.result, TaskResult::Ok(_)))
+        {
+            pipeline.status = PipelineStatus::Succeeded;
+            return Ok(());
+        }
+
+        let succeeded = |task_id: &ExternalID| {
+            pipeline
+                .tasks
+                .iter()
+                .position(|id| id == task_id)
+                .map_or(false, |i| matches!(states[i].result, TaskResult::Ok(_)))
+        };
+        let mut failed_task = None;
+        for ts in states.iter() {
+            let task_id = ts.external_id();
+            let ready = matches!(
+                ts.status,
+                TaskStatus::Created | TaskStatus::DataAssigned | TaskStatus::Approved
+            ) && pipeline
+                .upstream_links(&task_id)
+                .all(|link| succeeded(&link.from_task));
+            if !ready {
+                continue;
+            }
+            if let Err(e) = self.start_pipeline_task(pipeline, &states, ts.clone()) {
+                log::warn!("Failed to start task {:?} of pipeline: {:?}", task_id, e);
+                failed_task = Some(task_id);
+                break;
+            }
+        }
+        if failed_task.is_some() {
+            pipeline.status = PipelineStatus::Failed;
+            pipeline.failed_task = failed_task;
+        }
+        Ok(())
+    }
+
+    // Assigns the outputs of the upstream tasks to the linked inputs of the
+    // task on behalf of the creator, who owns them, and invokes the task
+    // once its participants have approved it. A task over the quota of the
+    // creator waits.
+    fn start_pipeline_task(
+        &self,
+        pipeline: &Pipeline,
+        states: &[TaskState],
+        mut ts: TaskState,
+    ) -> TeaclaveServiceResponseResult<()> {
+        self.ensure_task_enabled(&ts)?;
+        let task_id = ts.external_id();
+        let unassigned: Vec<PipelineLink> = pipeline
+            .upstream_links(&task_id)
+            .filter(|link| ts.assigned_inputs.get(&link.input).is_none())
+            .cloned()
+            .collect();
+        if !unassigned.is_empty() {
+            let mut task: Task<Assign> = ts.try_into().map_err(|e| {
+                log::warn!("Assign state error: {:?}", e);
+                TeaclaveManagementServiceError::BadTask
+            })?;
+            for link in unassigned.iter() {
+                let output = pipeline
+                    .tasks
+                    .iter()
+                    .position(|id| id == &link.from_task)
+                    .and_then(|i| states[i].assigned_outputs.get(&link.output))
+                    .ok_or(TeaclaveManagementServiceError::BadTask)?;
+                // The assigned output has its tag once read from the storage.
+                let output: TeaclaveOutputFile = self
+                    .read_from_db(&output.external_id())
+                    .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+                let input = TeaclaveInputFile::from_output(output)
+                    .map_err(|_| TeaclaveManagementServiceError::BadTask)?;
+                self.write_to_db(&input)
+                    .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+                task.assign_input(&pipeline.creator, &link.input, input)
+                    .map_err(|_| TeaclaveManagementServiceError::PermissionDenied)?;
+            }
+            ts = task.into();
+            self.write_to_db(&ts)
+                .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
+        }
+
+        // Multi-party tasks wait for the approvals of their participants.
+        if !ts.all_data_assigned() || !ts.everyone_approved() {
+            return Ok(());
+        }
+        if let Err(e) = self.ensure_invocation_quota(&pipeline.creator, now_secs()) {
+            log::debug!("Task {:?} of pipeline waits: {:?}", task_id, e);
+            return Ok(());
+        }
+        self.stage_task(&pipeline.creator, ts)?;
+
+        self.audit.record(
+            AuditEventKind::TaskInvoked,
+            &pipeline.creator.to_string(),
+            format!(
+                "{} of {}",
+                task_id.to_string(),
+                pipeline.external_id().to_string()
+            ),
+        );
+        Ok(())
+    }
+
+    fn ensure_task_enabled(&self, ts: &TaskState) -> TeaclaveServiceResponseResult<()> {
+        ensure!(
+            !self.is_user_disabled(&ts.creator)? && !self.is_user_disabled(&ts.function_owner)?,
+            TeaclaveManagementServiceError::PermissionDenied
+        );
+        Ok(())
+    }
+
+    // Applies the writes of the batch all together, or none of them.
+    fn write_batch(&self, batch: BatchRequest) -> TeaclaveServiceResponseResult<()> {
+        let _batch_response = self
             .storage_client
             .clone()
             .lock()
             .map_err(|_| TeaclaveManagementServiceError::StorageError)?
-            .enqueue(enqueue_request)?;
+            .batch(batch)
+            .map_err(|_| TeaclaveManagementServiceError::StorageError)?;
         Ok(())
     }
 
@@ -636,6 +3166,312 @@ impl TeaclaveManagementService {
     }
 }
 
+fn disabled_user_key(user_id: &UserID) -> Vec<u8> {
+    user_key(DISABLED_USER_PREFIX, user_id)
+}
+
+fn to_value(item: &impl Storable) -> TeaclaveServiceResponseResult<Vec<u8>> {
+    Ok(item
+        .to_vec()
+        .map_err(|_| TeaclaveManagementServiceError::DataError)?)
+}
+
+// Adds the write of a record to a batch.
+fn put_record(
+    batch: BatchRequest,
+    item: &impl Storable,
+) -> TeaclaveServiceResponseResult<BatchRequest> {
+    Ok(put_value(
+        batch,
+        &item.key(),
+        to_value(item)?,
+        item.tenant(),
+    ))
+}
+
+// Records of a tenant are put in its namespace, so that they are deleted with
+// the namespace, with a locator outside of namespaces to find them by key. A
+// copy outside of namespaces, written before, is deleted.
+fn put_value(
+    batch: BatchRequest,
+    key: &[u8],
+    value: Vec<u8>,
+    tenant: Option<&UserID>,
+) -> BatchRequest {
+    match tenant {
+        Some(tenant) => {
+            let namespace = tenant_namespace(tenant);
+            batch
+                .put(locator_key(key), namespace.as_bytes())
+                .delete(key)
+                .put_in(namespace, key, value)
+        }
+        None => batch.put(key, value),
+    }
+}
+
+// The namespace of the record with the key if it is kept in the namespace of
+// its tenant, read from its locator.
+fn locate_record(
+    client: &mut TeaclaveStorageClient,
+    key: &[u8],
+    max_staleness: Option<Duration>,
+) -> Result<Option<String>> {
+    let mut request = GetRequest::new(locator_key(key));
+    if let Some(max_staleness) = max_staleness {
+        request = request.max_staleness(max_staleness);
+    }
+    match client.get(request) {
+        Ok(response) => Ok(Some(String::from_utf8(response.value)?)),
+        Err(TeaclaveServiceResponseError::RequestError(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn get_record(
+    client: &mut TeaclaveStorageClient,
+    key: &[u8],
+    max_staleness: Option<Duration>,
+) -> Result<Vec<u8>> {
+    let mut request = GetRequest::new(key);
+    if let Some(namespace) = locate_record(client, key, max_staleness)? {
+        request = request.namespace(namespace);
+    }
+    if let Some(max_staleness) = max_staleness {
+        request = request.max_staleness(max_staleness);
+    }
+    Ok(client.get(request)?.value)
+}
+
+fn user_key(prefix: &str, user_id: &UserID) -> Vec<u8> {
+    format!("{}-{}", prefix, user_id).into_bytes()
+}
+
+// The length of the owner keeps the keys of the owners and names apart.
+fn function_versions_key(owner: &UserID, name: &str) -> Vec<u8> {
+    let owner = owner.to_string();
+    format!(
+        "{}-{}-{}-{}",
+        FUNCTION_VERSIONS_PREFIX,
+        owner.len(),
+        owner,
+        name
+    )
+    .into_bytes()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// The permissions granted by the roles of the user are set by the frontend
+// service.
+fn has_permission(meta: &HashMap<String, String>, permission: Permission) -> bool {
+    let permission = permission.to_string();
+    meta.get("permissions").map_or(false, |permissions| {
+        permissions.split(',').any(|p| p == permission)
+    })
+}
+
+// Users whose roles grant the read_any_output permission can read the tasks
+// and outputs of other users.
+fn can_read_any_output(meta: &HashMap<String, String>) -> bool {
+    has_permission(meta, Permission::ReadAnyOutput)
+}
+
+// Tasks may only set the keys allowed by the deployment, with short values.
+fn new_task_state(
+    user_id: UserID,
+    request: CreateTaskRequest,
+    function: Function,
+) -> TeaclaveServiceResponseResult<TaskState> {
+    if let Err(e) = request.affinity.check() {
+        log::warn!("Invalid task affinity: {:?}", e);
+        return Err(TeaclaveManagementServiceError::InvalidRequest.into());
+    }
+    let task = Task::<Create>::new(
+        user_id,
+        request.executor,
+        request.function_arguments,
+        request.inputs_ownership,
+        request.outputs_ownership,
+        function,
+    )
+    .map_err(|_| TeaclaveManagementServiceError::BadTask)?
+    .budget(request.budget)
+    .env(request.env)
+    .priority(request.priority)
+    .affinity(request.affinity)
+    .deterministic(request.deterministic);
+
+    log::debug!("CreateTask: {:?}", task);
+
+    Ok(task.into())
+}
+
+fn validate_env(
+    env: &FunctionEnv,
+    allowed_keys: &[String],
+) -> Result<(), TeaclaveManagementServiceError> {
+    for (key, value) in env {
+        if !allowed_keys.contains(key) || value.len() > MAX_ENV_VALUE_LEN {
+            log::warn!("Environment key {} not allowed", key);
+            return Err(TeaclaveManagementServiceError::EnvNotAllowed);
+        }
+    }
+    Ok(())
+}
+
+// The manifest must be valid and declare the function being registered.
+fn check_manifest(
+    manifest: &FunctionManifest,
+    request: &RegisterFunctionRequest,
+) -> Result<(), TeaclaveManagementServiceError> {
+    if let Err(e) = manifest.validate() {
+        log::warn!("Invalid function manifest: {:?}", e);
+        return Err(TeaclaveManagementServiceError::InvalidRequest);
+    }
+    let declared =
+        |data: &[ManifestData]| -> Vec<String> { data.iter().map(|d| d.name.clone()).collect() };
+    let inputs: Vec<String> = request.inputs.iter().map(|i| i.name.clone()).collect();
+    let outputs: Vec<String> = request.outputs.iter().map(|o| o.name.clone()).collect();
+    ensure!(
+        manifest.name == request.name
+            && manifest.executor_type().ok() == Some(request.executor_type)
+            && manifest.argument_names() == request.arguments
+            && declared(&manifest.inputs) == inputs
+            && declared(&manifest.outputs) == outputs
+            && manifest.bundle.is_some() == !request.bundle.is_empty(),
+        TeaclaveManagementServiceError::InvalidRequest
+    );
+    Ok(())
+}
+
+fn check_output_policies(
+    request: &RegisterFunctionRequest,
+) -> Result<(), TeaclaveManagementServiceError> {
+    for output in &request.outputs {
+        if let Err(e) = output.policy.validate() {
+            log::warn!("Invalid policy of output {}: {:?}", output.name, e);
+            return Err(TeaclaveManagementServiceError::InvalidRequest);
+        }
+    }
+    Ok(())
+}
+
+// Bundles are unpacked by the MesaPy executor of the platform.
+// Budgets of new input files, of which nothing is spent.
+fn new_privacy_budget(
+    budget: Option<PrivacyBudget>,
+) -> Result<Option<PrivacyBudget>, TeaclaveManagementServiceError> {
+    let budget = budget.map(|budget| PrivacyBudget::new(budget.epsilon, budget.delta));
+    if let Some(budget) = &budget {
+        budget.check().map_err(|e| {
+            log::warn!("Invalid privacy budget: {:?}", e);
+            TeaclaveManagementServiceError::InvalidRequest
+        })?;
+    }
+    Ok(budget)
+}
+
+fn check_bundle(request: &RegisterFunctionRequest) -> Result<(), TeaclaveManagementServiceError> {
+    ensure!(
+        request.executor_type == ExecutorType::Python && request.executor_enclave.is_none(),
+        TeaclaveManagementServiceError::InvalidRequest
+    );
+    if let Err(e) = PythonBundle::from_zip(&request.bundle) {
+        log::warn!("Invalid function bundle: {:?}", e);
+        return Err(TeaclaveManagementServiceError::InvalidRequest);
+    }
+    Ok(())
+}
+
+// Hands the object over to `to` if `from` owns it or, for tasks, takes part
+// in it, and adds the other owners and participants to `collaborators`.
+// Returns the updated record, or None if the object is not of `from`.
+fn transfer_record(
+    id: &ExternalID,
+    value: &[u8],
+    from: &UserID,
+    to: &UserID,
+    collaborators: &mut HashSet<UserID>,
+) -> Result<Option<(Vec<u8>, Option<UserID>)>> {
+    let prefix = id.prefix.as_str();
+    if prefix == Function::key_prefix() {
+        let mut function = Function::from_slice(value)?;
+        if &function.owner != from {
+            return Ok(None);
+        }
+        function.owner = to.clone();
+        return transferred(&function);
+    }
+    if prefix == TeaclaveInputFile::key_prefix() {
+        let mut file = TeaclaveInputFile::from_slice(value)?;
+        if !file.owner.replace(from, to) {
+            return Ok(None);
+        }
+        collaborators.extend(file.owner.uids.iter().cloned());
+        return transferred(&file);
+    }
+    if prefix == TeaclaveOutputFile::key_prefix() {
+        let mut file = TeaclaveOutputFile::from_slice(value)?;
+        if !file.owner.replace(from, to) {
+            return Ok(None);
+        }
+        collaborators.extend(file.owner.uids.iter().cloned());
+        return transferred(&file);
+    }
+    if prefix == TaskState::key_prefix() {
+        let mut ts = TaskState::from_slice(value)?;
+        if !ts.transfer_ownership(from, to) {
+            return Ok(None);
+        }
+        collaborators.extend(ts.participants.uids.iter().cloned());
+        return transferred(&ts);
+    }
+    Ok(None)
+}
+
+// A transferred record with its tenant.
+fn transferred(item: &impl Storable) -> Result<Option<(Vec<u8>, Option<UserID>)>> {
+    Ok(Some((item.to_vec()?, item.tenant().cloned())))
+}
+
+// Cuts the range of the return value of a succeeded task, so that large
+// values can be fetched in pieces and resumed after a failure. The hash of
+// the whole value lets clients verify the pieces they put together.
+fn task_result_range(
+    result: TaskResult,
+    offset: u64,
+    length: u64,
+) -> Result<GetTaskResultResponse> {
+    let outputs = match result {
+        TaskResult::Ok(outputs) => outputs,
+        result => return Ok(GetTaskResultResponse::new(result, 0, Vec::new())),
+    };
+    let source = BlobSource::new(&outputs.return_value);
+    let range = TaskOutputs {
+        return_value: source.chunk(offset, length)?.to_vec(),
+        tags_map: outputs.tags_map,
+        receipt: outputs.receipt,
+    };
+    Ok(GetTaskResultResponse::new(
+        TaskResult::Ok(range),
+        source.len(),
+        source.hash().to_vec(),
+    ))
+}
+
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
@@ -671,7 +3507,7 @@ pub mod tests {
         let function_input = FunctionInput::new("input", "input_desc");
         let function_output = FunctionOutput::new("output", "output_desc");
         let function = Function::new()
-            .id(Uuid::new_v4())
+            .id(platform::rand::new_uuid())
             .name("mock_function")
             .description("mock function")
             .payload(b"python script".to_vec())
@@ -688,7 +3524,7 @@ pub mod tests {
 
     pub fn handle_task() {
         let function = Function::new()
-            .id(Uuid::new_v4())
+            .id(platform::rand::new_uuid())
             .name("mock_function")
             .description("mock function")
             .payload(b"python script".to_vec())
@@ -715,7 +3551,7 @@ pub mod tests {
 
     pub fn handle_staged_task() {
         let function = Function::new()
-            .id(Uuid::new_v4())
+            .id(platform::rand::new_uuid())
             .name("mock_function")
             .description("mock function")
             .payload(b"python script".to_vec())
@@ -728,7 +3564,7 @@ pub mod tests {
         let output_data = FunctionOutputFile::new(url, FileCrypto::default());
 
         let staged_task = StagedTask::new()
-            .task_id(Uuid::new_v4())
+            .task_id(platform::rand::new_uuid())
             .executor(Executor::MesaPy)
             .function_payload(function.payload)
             .function_arguments(hashmap!("arg" => "data"))
@@ -739,4 +3575,125 @@ pub mod tests {
         let deserialized_data = StagedTask::from_slice(&value).unwrap();
         debug!("staged task: {:?}", deserialized_data);
     }
+
+    pub fn handle_task_result_range() {
+        let outputs = TaskOutputs::new(b"Hello, Teaclave!".to_vec(), HashMap::new());
+        let result = TaskResult::Ok(outputs);
+        let response = task_result_range(result.clone(), 7, 8).unwrap();
+        assert_eq!(response.return_value_len, 16);
+        assert_eq!(
+            response.return_value_hash,
+            blob::blob_hash(b"Hello, Teaclave!")
+        );
+        match response.result {
+            TaskResult::Ok(range) => assert_eq!(range.return_value, b"Teaclave"),
+            _ => panic!("unexpected result"),
+        }
+
+        // The range is clipped to the end; an empty length means the rest.
+        let response = task_result_range(result.clone(), 15, 100).unwrap();
+        assert_eq!(response.result.unwrap().return_value, b"!");
+        let response = task_result_range(result.clone(), 16, 0).unwrap();
+        assert!(response.result.unwrap().return_value.is_empty());
+        assert!(task_result_range(result, 17, 0).is_err());
+
+        let response = task_result_range(TaskResult::NotReady, 0, 0).unwrap();
+        assert_eq!(response.return_value_len, 0);
+    }
+
+    pub fn handle_function_env() {
+        let allowed_keys = vec!["batch_size".to_string()];
+        assert!(validate_env(&HashMap::new(), &[]).is_ok());
+        assert!(validate_env(&hashmap!("batch_size" => "64"), &allowed_keys).is_ok());
+        assert!(validate_env(&hashmap!("batch_size" => "64"), &[]).is_err());
+        assert!(validate_env(&hashmap!("epochs" => "10"), &allowed_keys).is_err());
+        let long_value = "1".repeat(MAX_ENV_VALUE_LEN + 1);
+        assert!(validate_env(&hashmap!("batch_size" => long_value), &allowed_keys).is_err());
+    }
+
+    pub fn handle_read_any_output() {
+        let mut meta = HashMap::new();
+        assert!(!can_read_any_output(&meta));
+        meta.insert(
+            "permissions".to_string(),
+            "register_function,invoke_task".to_string(),
+        );
+        assert!(!can_read_any_output(&meta));
+        meta.insert(
+            "permissions".to_string(),
+            "invoke_task,read_any_output".to_string(),
+        );
+        assert!(can_read_any_output(&meta));
+    }
+
+    pub fn handle_ownership_transfer() {
+        let from = UserID::from("mock_user");
+        let to = UserID::from("new_user");
+        let mut collaborators = HashSet::new();
+
+        let function = Function::new()
+            .id(platform::rand::new_uuid())
+            .name("mock_function")
+            .payload(b"python script".to_vec())
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .owner("mock_user2");
+        let value = function.to_vec().unwrap();
+        let function_id = function.external_id();
+        let id = function_id.clone();
+        let transferred = transfer_record(&id, &value, &from, &to, &mut collaborators).unwrap();
+        assert!(transferred.is_none());
+        let function = function.owner("mock_user");
+        let value = function.to_vec().unwrap();
+        let transferred = transfer_record(&id, &value, &from, &to, &mut collaborators).unwrap();
+        let (value, tenant) = transferred.unwrap();
+        let function = Function::from_slice(&value).unwrap();
+        assert_eq!(function.owner, to);
+        assert_eq!(tenant, Some(to.clone()));
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output_file =
+            TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user", "mock_user2"]);
+        let value = output_file.to_vec().unwrap();
+        let id = output_file.external_id();
+        let transferred = transfer_record(&id, &value, &from, &to, &mut collaborators).unwrap();
+        let (value, tenant) = transferred.unwrap();
+        let output_file = TeaclaveOutputFile::from_slice(&value).unwrap();
+        assert!(tenant.is_none());
+        assert_eq!(
+            output_file.owner,
+            OwnerList::from(vec!["new_user", "mock_user2"])
+        );
+        assert!(collaborators.contains(&UserID::from("mock_user2")));
+
+        let output_owners: HashMap<String, OwnerList> =
+            hashmap!("output" => vec!["mock_user", "mock_user3"]);
+        let task = Task::<Create>::new(
+            from.clone(),
+            Executor::MesaPy,
+            FunctionArguments::default(),
+            HashMap::<String, OwnerList>::new(),
+            output_owners,
+            function.owner("mock_user2"),
+        )
+        .unwrap();
+        let ts: TaskState = task.try_into().unwrap();
+        let value = ts.to_vec().unwrap();
+        let id = ts.external_id();
+        let transferred = transfer_record(&id, &value, &from, &to, &mut collaborators).unwrap();
+        let ts = TaskState::from_slice(&transferred.unwrap().0).unwrap();
+        assert!(ts.has_creator(&to));
+        assert!(ts.has_participant(&to) && !ts.has_participant(&from));
+        assert_eq!(
+            ts.outputs_ownership.get("output"),
+            Some(&OwnerList::from(vec!["new_user", "mock_user3"]))
+        );
+        assert!(collaborators.contains(&UserID::from("mock_user3")));
+
+        let filter = ObjectFilter::new().kinds(vec!["task".to_string()]);
+        assert!(filter.matches(&id));
+        assert!(!filter.matches(&function_id));
+        let filter = ObjectFilter::new().ids(vec![function_id.clone()]);
+        assert!(!filter.matches(&id));
+        assert!(filter.matches(&function_id));
+    }
 }
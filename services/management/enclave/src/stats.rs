@@ -0,0 +1,269 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_storage_service::{ChangeCursor, GetChangesResponse, StorageChange};
+use teaclave_types::{
+    split_namespaced_key, ExternalID, Storable, TaskResult, TaskState, TaskStatus, TenantStats,
+    UserID,
+};
+use uuid::Uuid;
+
+// Width of the time buckets of the counters, in milliseconds
+const BUCKET_MS: u64 = 60 * 1000;
+// Longest window of the statistics, for which the counters are kept
+pub(crate) const MAX_STATS_WINDOW_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counters {
+    tasks_run: u64,
+    tasks_succeeded: u64,
+    latency_ms_sum: u64,
+    latency_samples: u64,
+    data_processed_bytes: u64,
+}
+
+// A task not finished yet, with the time it was first seen invoked.
+struct PendingTask {
+    creator: UserID,
+    invoked_at_ms: Option<u64>,
+}
+
+/// Statistics of the tasks of tenants, updated incrementally from the changes
+/// of the task records in the storage service. Tasks are counted when they
+/// are seen finished, in buckets of a minute.
+pub(crate) struct TaskStatsAggregator {
+    cursor: ChangeCursor,
+    pending: HashMap<Uuid, PendingTask>,
+    counters: HashMap<UserID, BTreeMap<u64, Counters>>,
+}
+
+impl TaskStatsAggregator {
+    pub(crate) fn new() -> Self {
+        Self {
+            // Of no epoch of the change log, so that the storage sends a
+            // snapshot first, whose finished tasks are not counted.
+            cursor: ChangeCursor::default(),
+            pending: HashMap::new(),
+            counters: HashMap::new(),
+        }
+    }
+
+    /// Position in the change log after the last change applied.
+    pub(crate) fn cursor(&self) -> &ChangeCursor {
+        &self.cursor
+    }
+
+    /// Applies the changes fetched from the storage at `now_ms` (milliseconds
+    /// since the Unix epoch). Only tasks seen before they finished are
+    /// counted, so rewrites of finished tasks are not counted again.
+    pub(crate) fn apply(&mut self, response: GetChangesResponse, now_ms: u64) {
+        // Tasks finished in the gap before a snapshot are missed.
+        if self.cursor.starts_snapshot(&response) {
+            self.pending.clear();
+        }
+        self.cursor.advance(&response);
+        for change in response.changes {
+            match change {
+                StorageChange::Put { key, value } => {
+                    if task_uuid(&key).is_none() {
+                        continue;
+                    }
+                    match TaskState::from_slice(&value) {
+                        Ok(ts) => self.apply_task(ts, now_ms),
+                        Err(e) => log::debug!("Failed to read task record: {:?}", e),
+                    }
+                }
+                StorageChange::Delete { key } => {
+                    if let Some(uuid) = task_uuid(&key) {
+                        self.pending.remove(&uuid);
+                    }
+                }
+            }
+        }
+        self.prune(now_ms);
+    }
+
+    fn apply_task(&mut self, ts: TaskState, now_ms: u64) {
+        let invoked = match ts.status {
+            TaskStatus::Staged | TaskStatus::Running => true,
+            TaskStatus::Finished => {
+                let task = match self.pending.remove(&ts.task_id) {
+                    Some(task) => task,
+                    None => return,
+                };
+                let mut data_processed_bytes: u64 = ts
+                    .assigned_inputs
+                    .into_iter()
+                    .filter_map(|(_, file)| file.content)
+                    .map(|content| content.len() as u64)
+                    .sum();
+                let succeeded = match &ts.result {
+                    TaskResult::Ok(outputs) => {
+                        data_processed_bytes += outputs.return_value.len() as u64;
+                        true
+                    }
+                    _ => false,
+                };
+                let counters = self
+                    .counters
+                    .entry(task.creator)
+                    .or_default()
+                    .entry(now_ms / BUCKET_MS)
+                    .or_default();
+                counters.tasks_run += 1;
+                if succeeded {
+                    counters.tasks_succeeded += 1;
+                }
+                if let Some(invoked_at_ms) = task.invoked_at_ms {
+                    counters.latency_ms_sum += now_ms.saturating_sub(invoked_at_ms);
+                    counters.latency_samples += 1;
+                }
+                counters.data_processed_bytes += data_processed_bytes;
+                return;
+            }
+            _ => false,
+        };
+        let task = self.pending.entry(ts.task_id).or_insert(PendingTask {
+            creator: ts.creator.clone(),
+            invoked_at_ms: None,
+        });
+        task.creator = ts.creator;
+        if invoked && task.invoked_at_ms.is_none() {
+            task.invoked_at_ms = Some(now_ms);
+        }
+    }
+
+    // Drops the buckets older than the longest window.
+    fn prune(&mut self, now_ms: u64) {
+        let oldest = now_ms.saturating_sub(MAX_STATS_WINDOW_SECS * 1000) / BUCKET_MS;
+        self.counters.retain(|_, buckets| {
+            *buckets = buckets.split_off(&oldest);
+            !buckets.is_empty()
+        });
+    }
+
+    /// Tenants with tasks finished in the longest window.
+    pub(crate) fn tenants(&self) -> Vec<UserID> {
+        self.counters.keys().cloned().collect()
+    }
+
+    /// Statistics of the tasks of the tenant finished in the last
+    /// `window_secs`, rounded up to whole buckets, without the quota.
+    pub(crate) fn stats(&self, user_id: &UserID, window_secs: u64, now_ms: u64) -> TenantStats {
+        let oldest = now_ms.saturating_sub(window_secs.saturating_mul(1000)) / BUCKET_MS;
+        let mut total = Counters::default();
+        if let Some(buckets) = self.counters.get(user_id) {
+            for counters in buckets.range(oldest..).map(|(_, counters)| counters) {
+                total.tasks_run += counters.tasks_run;
+                total.tasks_succeeded += counters.tasks_succeeded;
+                total.latency_ms_sum += counters.latency_ms_sum;
+                total.latency_samples += counters.latency_samples;
+                total.data_processed_bytes += counters.data_processed_bytes;
+            }
+        }
+        TenantStats {
+            user_id: user_id.clone(),
+            tasks_run: total.tasks_run,
+            tasks_succeeded: total.tasks_succeeded,
+            average_latency_ms: total
+                .latency_ms_sum
+                .checked_div(total.latency_samples)
+                .unwrap_or_default(),
+            data_processed_bytes: total.data_processed_bytes,
+            ..Default::default()
+        }
+    }
+}
+
+// The uuid of the task if the key is of a task record, also in the namespace
+// of its creator.
+pub(crate) fn task_uuid(key: &[u8]) -> Option<Uuid> {
+    let (_, key) = split_namespaced_key(key);
+    let key = std::str::from_utf8(key).ok()?;
+    let id = ExternalID::try_from(key).ok()?;
+    if id.prefix == TaskState::key_prefix() {
+        Some(id.uuid)
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_types::{locator_key, platform, TaskOutputs};
+
+    fn put(ts: &TaskState) -> StorageChange {
+        StorageChange::Put {
+            key: ts.key(),
+            value: ts.to_vec().unwrap(),
+        }
+    }
+
+    pub fn aggregate_task_stats() {
+        let user_id = UserID::from("tenant");
+        let mut ts = TaskState {
+            task_id: platform::rand::new_uuid(),
+            creator: user_id.clone(),
+            ..Default::default()
+        };
+        let mut finished = ts.clone();
+        finished.task_id = platform::rand::new_uuid();
+        finished.status = TaskStatus::Finished;
+        finished.result = TaskResult::Ok(TaskOutputs::new(vec![0u8; 10], HashMap::new()));
+
+        // Tasks finished before the snapshot are not counted.
+        let mut aggregator = TaskStatsAggregator::new();
+        let snapshot = GetChangesResponse::snapshot(1, 1, vec![put(&ts), put(&finished)], None);
+        aggregator.apply(snapshot, 0);
+        assert!(aggregator.tenants().is_empty());
+
+        ts.status = TaskStatus::Staged;
+        let changes = GetChangesResponse::new(1, 2, vec![put(&ts)]);
+        aggregator.apply(changes, 1000);
+        ts.status = TaskStatus::Finished;
+        ts.result = TaskResult::Ok(TaskOutputs::new(vec![0u8; 4], HashMap::new()));
+        // Rewrites of the finished task are ignored.
+        let changes = GetChangesResponse::new(1, 4, vec![put(&ts), put(&ts)]);
+        aggregator.apply(changes, 4000);
+        assert_eq!(aggregator.cursor().sequence(), 4);
+
+        let stats = aggregator.stats(&user_id, 60, 4000);
+        assert_eq!(stats.tasks_run, 1);
+        assert_eq!(stats.tasks_succeeded, 1);
+        assert_eq!(stats.average_latency_ms, 3000);
+        assert_eq!(stats.data_processed_bytes, 4);
+        assert_eq!(stats.success_rate(), 1.0);
+
+        // Windows count whole buckets of a minute.
+        let now_ms = 4000 + 5 * BUCKET_MS;
+        assert_eq!(aggregator.stats(&user_id, 60, now_ms).tasks_run, 0);
+        assert_eq!(aggregator.stats(&user_id, 6 * 60, now_ms).tasks_run, 1);
+
+        let later = (MAX_STATS_WINDOW_SECS + 60) * 1000;
+        aggregator.apply(GetChangesResponse::new(1, 4, Vec::new()), later);
+        assert!(aggregator.tenants().is_empty());
+
+        // Tasks are kept in the namespaces of their creators.
+        let key = [b"ns-tenant-0/".as_ref(), &ts.key()].concat();
+        assert_eq!(task_uuid(&key), Some(ts.task_id));
+        assert_eq!(task_uuid(&locator_key(&ts.key())), None);
+    }
+}
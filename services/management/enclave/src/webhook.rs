@@ -0,0 +1,438 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::stats::task_uuid;
+use anyhow::{anyhow, ensure, Result};
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use teaclave_proto::teaclave_storage_service::{ChangeCursor, GetChangesResponse, StorageChange};
+use teaclave_types::{
+    platform, Storable, TaskEvent, TaskEventKind, TaskState, UserID, Webhook,
+    WEBHOOK_SIGNATURE_HEADER,
+};
+use url::Url;
+use uuid::Uuid;
+
+// Posts of an event to a webhook before it is dropped
+const MAX_ATTEMPTS: u32 = 5;
+// Posts waiting to be sent or retried; further events are dropped
+const MAX_PENDING_POSTS: usize = 10_000;
+// Posts to the webhook of one user waiting to be sent or retried, so that a
+// single webhook cannot fill the queue
+const MAX_PENDING_POSTS_PER_WEBHOOK: usize = 100;
+// Posts sent in one delivery round
+const MAX_POSTS_PER_ROUND: usize = 64;
+// Time of one delivery round, after which the remaining posts wait for the
+// next round
+const ROUND_TIMEOUT: Duration = Duration::from_secs(30);
+// Timeout of connecting to a webhook
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+// Timeout of the whole exchange of a post, after connecting
+const POST_TIMEOUT: Duration = Duration::from_secs(5);
+// Longest response header read from a webhook
+const MAX_RESPONSE_LEN: usize = 16 * 1024;
+
+pub(crate) struct Post {
+    owner: UserID,
+    url: Url,
+    body: Vec<u8>,
+    signature: String,
+    attempts: u32,
+    // Seconds since the Unix epoch
+    next_attempt_secs: u64,
+}
+
+/// Events of the state changes of tasks, followed from the changes of the
+/// task records in the storage service, and their posts to webhooks.
+pub(crate) struct WebhookDispatcher {
+    cursor: ChangeCursor,
+    // Last event of the tasks not finished yet
+    pending: HashMap<Uuid, TaskEventKind>,
+    posts: VecDeque<Post>,
+    // Number of the posts of each webhook, queued or being sent
+    posts_per_webhook: HashMap<UserID, usize>,
+}
+
+impl WebhookDispatcher {
+    pub(crate) fn new() -> Self {
+        Self {
+            // Of no epoch of the change log, so that the storage sends a
+            // snapshot first, whose tasks are not posted.
+            cursor: ChangeCursor::default(),
+            pending: HashMap::new(),
+            posts: VecDeque::new(),
+            posts_per_webhook: HashMap::new(),
+        }
+    }
+
+    /// Position in the change log after the last change applied.
+    pub(crate) fn cursor(&self) -> &ChangeCursor {
+        &self.cursor
+    }
+
+    /// Applies the changes fetched from the storage at `now_secs`, returning
+    /// the events and the users whose webhooks they are posted to. Tasks in a
+    /// snapshot are recorded without events, so changes in the gap before it
+    /// are missed; tasks first seen finished are not posted either, so
+    /// rewrites of finished tasks are not posted again.
+    pub(crate) fn apply(
+        &mut self,
+        response: GetChangesResponse,
+        now_secs: u64,
+    ) -> Vec<(TaskEvent, Vec<UserID>)> {
+        if self.cursor.starts_snapshot(&response) {
+            self.pending.clear();
+        }
+        self.cursor.advance(&response);
+        let mut events = Vec::new();
+        for change in response.changes {
+            match change {
+                StorageChange::Put { key, value } => {
+                    if task_uuid(&key).is_none() {
+                        continue;
+                    }
+                    match TaskState::from_slice(&value) {
+                        Ok(ts) => {
+                            if let Some(event) = self.apply_task(ts, response.snapshot, now_secs) {
+                                events.push(event);
+                            }
+                        }
+                        Err(e) => log::debug!("Failed to read task record: {:?}", e),
+                    }
+                }
+                StorageChange::Delete { key } => {
+                    if let Some(uuid) = task_uuid(&key) {
+                        self.pending.remove(&uuid);
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    fn apply_task(
+        &mut self,
+        ts: TaskState,
+        snapshot: bool,
+        now_secs: u64,
+    ) -> Option<(TaskEvent, Vec<UserID>)> {
+        let kind = TaskEventKind::from_task(&ts.status, &ts.result)?;
+        if kind.is_final() {
+            self.pending.remove(&ts.task_id)?;
+        } else if self.pending.insert(ts.task_id, kind) == Some(kind) {
+            return None;
+        }
+        if snapshot {
+            return None;
+        }
+
+        let event = TaskEvent::new(platform::rand::new_uuid(), ts.external_id(), kind, now_secs);
+        let mut users: Vec<UserID> = ts.participants.into_iter().collect();
+        if !users.contains(&ts.creator) {
+            users.push(ts.creator);
+        }
+        Some((event, users))
+    }
+
+    /// Queues the post of the event to the webhook.
+    pub(crate) fn enqueue(&mut self, webhook: &Webhook, event: &TaskEvent) -> Result<()> {
+        ensure!(
+            self.posts.len() < MAX_PENDING_POSTS,
+            "too many pending webhook posts"
+        );
+        let count = self
+            .posts_per_webhook
+            .entry(webhook.owner.clone())
+            .or_insert(0);
+        ensure!(
+            *count < MAX_PENDING_POSTS_PER_WEBHOOK,
+            "too many pending posts to the webhook of {}",
+            webhook.owner
+        );
+        let (body, signature) = event.sign(&webhook.secret)?;
+        *count += 1;
+        self.posts.push_back(Post {
+            owner: webhook.owner.clone(),
+            url: webhook.url.clone(),
+            body,
+            signature,
+            attempts: 0,
+            next_attempt_secs: 0,
+        });
+        Ok(())
+    }
+
+    /// Takes the posts due at `now_secs` for a delivery round, at most
+    /// `MAX_POSTS_PER_ROUND`, to be sent with `send` without holding the
+    /// dispatcher.
+    pub(crate) fn take_due(&mut self, now_secs: u64) -> Vec<Post> {
+        let mut due = Vec::new();
+        let mut waiting = VecDeque::new();
+        while let Some(p) = self.posts.pop_front() {
+            if p.next_attempt_secs > now_secs || due.len() >= MAX_POSTS_PER_ROUND {
+                waiting.push_back(p);
+            } else {
+                due.push(p);
+            }
+        }
+        self.posts = waiting;
+        due
+    }
+
+    /// Queues the posts of a delivery round again, unless they were sent.
+    /// Failed posts are retried after 2, 4, 8 and 16 seconds, then dropped;
+    /// posts not attempted in the round are retried in the next one.
+    pub(crate) fn finish(&mut self, results: Vec<(Post, Option<Result<()>>)>, now_secs: u64) {
+        for (mut p, result) in results {
+            match result {
+                Some(Ok(())) => self.release(&p.owner),
+                Some(Err(e)) => {
+                    p.attempts += 1;
+                    if p.attempts >= MAX_ATTEMPTS {
+                        log::warn!("Dropped webhook post to {}: {:?}", p.url, e);
+                        self.release(&p.owner);
+                        continue;
+                    }
+                    p.next_attempt_secs = now_secs + (1 << p.attempts);
+                    self.posts.push_back(p);
+                }
+                None => self.posts.push_back(p),
+            }
+        }
+    }
+
+    fn release(&mut self, owner: &UserID) {
+        if let Some(count) = self.posts_per_webhook.get_mut(owner) {
+            *count -= 1;
+            if *count == 0 {
+                self.posts_per_webhook.remove(owner);
+            }
+        }
+    }
+}
+
+/// Sends the posts of a delivery round with `post`, returning the result of
+/// each post, or `None` if it was not attempted. The round stops after
+/// `ROUND_TIMEOUT`, and once a post to a webhook fails, the other posts to it
+/// wait for the next round, so that an unresponsive webhook does not hold up
+/// the posts to the others for long.
+pub(crate) fn send(
+    posts: Vec<Post>,
+    post: impl Fn(&Url, &[u8], &str) -> Result<()>,
+) -> Vec<(Post, Option<Result<()>>)> {
+    let started = platform::time::now();
+    let mut failed: Vec<UserID> = Vec::new();
+    posts
+        .into_iter()
+        .map(|p| {
+            let elapsed = platform::time::now()
+                .duration_since(started)
+                .unwrap_or_default();
+            if elapsed >= ROUND_TIMEOUT || failed.contains(&p.owner) {
+                return (p, None);
+            }
+            let result = post(&p.url, &p.body, &p.signature);
+            if result.is_err() {
+                failed.push(p.owner.clone());
+            }
+            (p, Some(result))
+        })
+        .collect()
+}
+
+// Connects to any of the addresses of the webhook within `CONNECT_TIMEOUT`.
+fn connect(url: &Url) -> Result<TcpStream> {
+    let addrs = url.socket_addrs(|| Some(443))?;
+    let mut error = anyhow!("webhook URL without address");
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(socket) => return Ok(socket),
+            Err(e) => error = e.into(),
+        }
+    }
+    Err(error)
+}
+
+// Time left before `deadline`, failing once it has passed.
+fn remaining(deadline: SystemTime) -> Result<Duration> {
+    match deadline.duration_since(platform::time::now()) {
+        Ok(remaining) if remaining > Duration::from_millis(0) => Ok(remaining),
+        _ => Err(anyhow!("webhook post timed out")),
+    }
+}
+
+/// Posts the body to the webhook over HTTPS, with the server authenticated
+/// by the web PKI. Fails unless the response is a success (2xx) within
+/// `POST_TIMEOUT` after connecting.
+pub(crate) fn post_event(url: &Url, body: &[u8], signature: &str) -> Result<()> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("webhook URL without host"))?;
+    let dns_name = webpki::DNSNameRef::try_from_ascii_str(host)?;
+    let mut config = rustls::ClientConfig::new();
+    config
+        .root_store
+        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+    let session = rustls::ClientSession::new(&Arc::new(config), dns_name);
+    let socket = connect(url)?;
+    let deadline = platform::time::now() + POST_TIMEOUT;
+    socket.set_read_timeout(Some(POST_TIMEOUT))?;
+    socket.set_write_timeout(Some(POST_TIMEOUT))?;
+    let mut stream = rustls::StreamOwned::new(session, socket);
+
+    let host_header = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    let header = format!(
+        "POST {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         {}: {}\r\n\
+         Connection: Close\r\n\r\n",
+        &url[url::Position::BeforePath..url::Position::AfterQuery],
+        host_header,
+        body.len(),
+        WEBHOOK_SIGNATURE_HEADER,
+        signature
+    );
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        // Bounds the whole response rather than each read, as a webhook may
+        // send it one byte at a time.
+        stream.sock.set_read_timeout(Some(remaining(deadline)?))?;
+        let len = stream.read(&mut buf)?;
+        ensure!(len > 0, "webhook closed the connection");
+        response.extend_from_slice(&buf[..len]);
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut http_response = httparse::Response::new(&mut headers);
+        let status = http_response
+            .parse(&response)
+            .map_err(|e| anyhow!("invalid webhook response: {:?}", e))?;
+        if status.is_complete() {
+            let code = http_response.code.unwrap_or_default();
+            ensure!((200..300).contains(&code), "webhook responded {}", code);
+            return Ok(());
+        }
+        ensure!(
+            response.len() < MAX_RESPONSE_LEN,
+            "webhook response too long"
+        );
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use teaclave_types::{TaskFailure, TaskResult, TaskStatus, UserList, WEBHOOK_SECRET_LEN};
+
+    fn put(ts: &TaskState) -> StorageChange {
+        StorageChange::Put {
+            key: ts.key(),
+            value: ts.to_vec().unwrap(),
+        }
+    }
+
+    pub fn dispatch_task_events() {
+        let mut ts = TaskState {
+            task_id: platform::rand::new_uuid(),
+            creator: UserID::from("creator"),
+            participants: UserList::new(vec!["participant"]),
+            status: TaskStatus::Staged,
+            ..Default::default()
+        };
+
+        // Tasks in the snapshot are not posted.
+        let mut dispatcher = WebhookDispatcher::new();
+        let snapshot = GetChangesResponse::snapshot(1, 1, vec![put(&ts)], None);
+        assert!(dispatcher.apply(snapshot, 0).is_empty());
+
+        ts.status = TaskStatus::Running;
+        let changes = GetChangesResponse::new(1, 3, vec![put(&ts), put(&ts)]);
+        let events = dispatcher.apply(changes, 10);
+        assert_eq!(events.len(), 1);
+        let (event, users) = &events[0];
+        assert_eq!(event.kind, TaskEventKind::Running);
+        assert_eq!(event.task_id, ts.external_id().to_string());
+        assert_eq!(event.timestamp, 10);
+        assert_eq!(users.len(), 2);
+        assert!(users.contains(&UserID::from("creator")));
+
+        // Rewrites of the finished task are not posted again.
+        ts.status = TaskStatus::Finished;
+        ts.result = TaskResult::Err(TaskFailure::new("error"));
+        let changes = GetChangesResponse::new(1, 5, vec![put(&ts), put(&ts)]);
+        let events = dispatcher.apply(changes, 20);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0.kind, TaskEventKind::Failed);
+        assert_eq!(dispatcher.cursor().sequence(), 5);
+
+        // Failed posts are retried with backoff, then dropped.
+        let url = Url::parse("https://example.com/hook").unwrap();
+        let secret = vec![1; WEBHOOK_SECRET_LEN];
+        let webhook = Webhook::new("creator", url, secret.clone(), 0).unwrap();
+        dispatcher.enqueue(&webhook, &events[0].0).unwrap();
+        dispatcher.enqueue(&webhook, &events[0].0).unwrap();
+        let attempts = RefCell::new(0);
+        let failing = |_: &Url, body: &[u8], signature: &str| {
+            assert!(TaskEvent::verify(body, signature, &secret).is_ok());
+            *attempts.borrow_mut() += 1;
+            Err(anyhow!("unreachable"))
+        };
+        let deliver = |dispatcher: &mut WebhookDispatcher,
+                       now,
+                       post: &dyn Fn(&Url, &[u8], &str) -> Result<()>| {
+            let results = send(dispatcher.take_due(now), post);
+            dispatcher.finish(results, now);
+        };
+        // Once a post to the webhook fails, the other one waits.
+        deliver(&mut dispatcher, 100, &failing);
+        assert_eq!(*attempts.borrow(), 1);
+        deliver(&mut dispatcher, 101, &failing);
+        assert_eq!(*attempts.borrow(), 2);
+        for now in 102..200 {
+            deliver(&mut dispatcher, now, &failing);
+        }
+        assert_eq!(*attempts.borrow(), 2 * MAX_ATTEMPTS);
+        assert!(dispatcher.posts.is_empty());
+        assert!(dispatcher.posts_per_webhook.is_empty());
+
+        dispatcher.enqueue(&webhook, &events[0].0).unwrap();
+        deliver(&mut dispatcher, 200, &|_: &Url, _: &[u8], _: &str| Ok(()));
+        assert!(dispatcher.posts.is_empty());
+
+        // The posts to a single webhook are limited.
+        for _ in 0..MAX_PENDING_POSTS_PER_WEBHOOK {
+            dispatcher.enqueue(&webhook, &events[0].0).unwrap();
+        }
+        assert!(dispatcher.enqueue(&webhook, &events[0].0).is_err());
+        let other = Webhook::new("participant", webhook.url.clone(), secret.clone(), 0).unwrap();
+        assert!(dispatcher.enqueue(&other, &events[0].0).is_ok());
+        assert_eq!(dispatcher.take_due(300).len(), MAX_POSTS_PER_ROUND);
+    }
+}
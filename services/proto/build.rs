@@ -24,8 +24,10 @@ use std::str;
 fn main() {
     let proto_files = [
         "services/proto/src/proto/teaclave_access_control_service.proto",
+        "services/proto/src/proto/teaclave_attestation_verifier_service.proto",
         "services/proto/src/proto/teaclave_authentication_service.proto",
         "services/proto/src/proto/teaclave_common.proto",
+        "services/proto/src/proto/teaclave_executor_enclave_service.proto",
         "services/proto/src/proto/teaclave_storage_service.proto",
         "services/proto/src/proto/teaclave_frontend_service.proto",
         "services/proto/src/proto/teaclave_management_service.proto",
@@ -94,6 +94,24 @@ pub fn get_default_config() -> prost_build::Config {
     let mut config = prost_build::Config::new();
     config.service_generator(Box::new(MesaTEEServiceGenerator));
     config.type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
+    // Optional fields which clients may omit in JSON requests.
+    for field in &[
+        ".teaclave_frontend_service_proto.RegisterInputFileRequest.attributes",
+        ".teaclave_frontend_service_proto.RegisterInlineInputFileRequest.attributes",
+        ".teaclave_frontend_service_proto.RegisterInputFileRequest.labels",
+        ".teaclave_frontend_service_proto.RegisterInlineInputFileRequest.labels",
+        ".teaclave_frontend_service_proto.RegisterFunctionRequest.tags",
+        ".teaclave_frontend_service_proto.RegisterFunctionRequest.payload_upload_id",
+        ".teaclave_frontend_service_proto.RegisterFunctionRequest.manifest",
+        ".teaclave_frontend_service_proto.GetFunctionResponse.tags",
+        ".teaclave_frontend_service_proto.GetFunctionResponse.manifest",
+        ".teaclave_frontend_service_proto.CreateTaskRequest.env",
+        ".teaclave_frontend_service_proto.GetTaskResponse.env",
+        ".teaclave_frontend_service_proto.CreateTaskRequest.priority",
+        ".teaclave_frontend_service_proto.GetTaskResponse.priority",
+    ] {
+        config.field_attribute(field, "#[serde(default)]");
+    }
     config
 }
 
@@ -20,8 +20,10 @@
 extern crate sgx_tstd as std;
 
 pub mod teaclave_access_control_service;
+pub mod teaclave_attestation_verifier_service;
 pub mod teaclave_authentication_service;
 pub mod teaclave_common;
+pub mod teaclave_executor_enclave_service;
 pub mod teaclave_frontend_service;
 pub mod teaclave_management_service;
 pub mod teaclave_scheduler_service;
@@ -41,6 +43,10 @@ pub mod teaclave_common_proto {
     include_proto!("teaclave_common_proto");
 }
 
+pub mod teaclave_executor_enclave_service_proto {
+    include_proto!("teaclave_executor_enclave_service_proto");
+}
+
 pub mod teaclave_storage_service_proto {
     include_proto!("teaclave_storage_service_proto");
 }
@@ -57,6 +63,10 @@ pub mod teaclave_access_control_service_proto {
     include_proto!("teaclave_access_control_service_proto");
 }
 
+pub mod teaclave_attestation_verifier_service_proto {
+    include_proto!("teaclave_attestation_verifier_service_proto");
+}
+
 pub mod teaclave_scheduler_service_proto {
     include_proto!("teaclave_scheduler_service_proto");
 }
@@ -16,20 +16,57 @@
 // under the License.
 
 use crate::teaclave_access_control_service_proto as proto;
+use crate::teaclave_frontend_service_proto as frontend_proto;
 use anyhow::{Error, Result};
+use std::collections::HashMap;
 use std::prelude::v1::*;
 use teaclave_rpc::into_request;
 
+pub use crate::teaclave_common::{HealthRequest, HealthResponse};
 pub use proto::TeaclaveAccessControl;
 pub use proto::TeaclaveAccessControlClient;
 pub use proto::TeaclaveAccessControlRequest;
 pub use proto::TeaclaveAccessControlResponse;
 
+pub type UpdateAccessControlPolicyRequest =
+    crate::teaclave_frontend_service::UpdateAccessControlPolicyRequest;
+pub type UpdateAccessControlPolicyResponse =
+    crate::teaclave_frontend_service::UpdateAccessControlPolicyResponse;
+pub type RollbackAccessControlPolicyRequest =
+    crate::teaclave_frontend_service::RollbackAccessControlPolicyRequest;
+pub type RollbackAccessControlPolicyResponse =
+    crate::teaclave_frontend_service::RollbackAccessControlPolicyResponse;
+pub type GetAccessControlPolicyRequest =
+    crate::teaclave_frontend_service::GetAccessControlPolicyRequest;
+pub type GetAccessControlPolicyResponse =
+    crate::teaclave_frontend_service::GetAccessControlPolicyResponse;
+
+/// A request of the access control model evaluated for a decision, with the
+/// attributes it was evaluated on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessControlDecision {
+    // Name of the request and its matcher in the model, e.g. "user_access_data"
+    pub rule: String,
+    pub accept: bool,
+    pub attributes: HashMap<String, String>,
+}
+
+impl AccessControlDecision {
+    pub fn new(rule: impl Into<String>, accept: bool, attributes: HashMap<String, String>) -> Self {
+        Self {
+            rule: rule.into(),
+            accept,
+            attributes,
+        }
+    }
+}
+
 #[into_request(TeaclaveAccessControlRequest::AuthorizeData)]
 #[derive(Debug)]
 pub struct AuthorizeDataRequest {
     pub subject_user_id: String,
     pub object_data_id: String,
+    pub explain: bool,
 }
 
 impl AuthorizeDataRequest {
@@ -37,6 +74,15 @@ impl AuthorizeDataRequest {
         Self {
             subject_user_id: subject_user_id.into(),
             object_data_id: object_data_id.into(),
+            explain: false,
+        }
+    }
+
+    // Asks for the decisions the response is based on.
+    pub fn explain(self) -> Self {
+        Self {
+            explain: true,
+            ..self
         }
     }
 }
@@ -45,11 +91,23 @@ impl AuthorizeDataRequest {
 #[derive(Debug)]
 pub struct AuthorizeDataResponse {
     pub accept: bool,
+    // Only set for explained requests of admins and the resource owner
+    pub explanation: Vec<AccessControlDecision>,
 }
 
 impl AuthorizeDataResponse {
     pub fn new(accept: bool) -> Self {
-        Self { accept }
+        Self {
+            accept,
+            explanation: Vec::new(),
+        }
+    }
+
+    pub fn explanation(self, explanation: Vec<AccessControlDecision>) -> Self {
+        Self {
+            explanation,
+            ..self
+        }
     }
 }
 
@@ -58,6 +116,7 @@ impl AuthorizeDataResponse {
 pub struct AuthorizeFunctionRequest {
     pub subject_user_id: String,
     pub object_function_id: String,
+    pub explain: bool,
 }
 
 impl AuthorizeFunctionRequest {
@@ -65,6 +124,15 @@ impl AuthorizeFunctionRequest {
         Self {
             subject_user_id: subject_user_id.into(),
             object_function_id: object_function_id.into(),
+            explain: false,
+        }
+    }
+
+    // Asks for the decisions the response is based on.
+    pub fn explain(self) -> Self {
+        Self {
+            explain: true,
+            ..self
         }
     }
 }
@@ -73,11 +141,23 @@ impl AuthorizeFunctionRequest {
 #[derive(Debug)]
 pub struct AuthorizeFunctionResponse {
     pub accept: bool,
+    // Only set for explained requests of admins and the resource owner
+    pub explanation: Vec<AccessControlDecision>,
 }
 
 impl AuthorizeFunctionResponse {
     pub fn new(accept: bool) -> Self {
-        Self { accept }
+        Self {
+            accept,
+            explanation: Vec::new(),
+        }
+    }
+
+    pub fn explanation(self, explanation: Vec<AccessControlDecision>) -> Self {
+        Self {
+            explanation,
+            ..self
+        }
     }
 }
 
@@ -86,6 +166,7 @@ impl AuthorizeFunctionResponse {
 pub struct AuthorizeTaskRequest {
     pub subject_user_id: String,
     pub object_task_id: String,
+    pub explain: bool,
 }
 
 impl AuthorizeTaskRequest {
@@ -93,6 +174,15 @@ impl AuthorizeTaskRequest {
         Self {
             subject_user_id: subject_user_id.into(),
             object_task_id: object_task_id.into(),
+            explain: false,
+        }
+    }
+
+    // Asks for the decisions the response is based on.
+    pub fn explain(self) -> Self {
+        Self {
+            explain: true,
+            ..self
         }
     }
 }
@@ -101,11 +191,23 @@ impl AuthorizeTaskRequest {
 #[derive(Debug)]
 pub struct AuthorizeTaskResponse {
     pub accept: bool,
+    // Only set for explained requests of admins and the resource owner
+    pub explanation: Vec<AccessControlDecision>,
 }
 
 impl AuthorizeTaskResponse {
     pub fn new(accept: bool) -> Self {
-        Self { accept }
+        Self {
+            accept,
+            explanation: Vec::new(),
+        }
+    }
+
+    pub fn explanation(self, explanation: Vec<AccessControlDecision>) -> Self {
+        Self {
+            explanation,
+            ..self
+        }
     }
 }
 
@@ -116,20 +218,98 @@ pub struct AuthorizeStagedTaskRequest {
     pub object_function_id: String,
     pub object_input_data_id_list: Vec<String>,
     pub object_output_data_id_list: Vec<String>,
+    pub explain: bool,
+}
+
+impl AuthorizeStagedTaskRequest {
+    // Asks for the decisions the response is based on.
+    pub fn explain(self) -> Self {
+        Self {
+            explain: true,
+            ..self
+        }
+    }
 }
 
 #[into_request(TeaclaveAccessControlResponse::AuthorizeStagedTask)]
 #[derive(Debug)]
 pub struct AuthorizeStagedTaskResponse {
     pub accept: bool,
+    // Only set for explained requests of admins and the resource owner
+    pub explanation: Vec<AccessControlDecision>,
 }
 
 impl AuthorizeStagedTaskResponse {
     pub fn new(accept: bool) -> Self {
-        Self { accept }
+        Self {
+            accept,
+            explanation: Vec::new(),
+        }
+    }
+
+    pub fn explanation(self, explanation: Vec<AccessControlDecision>) -> Self {
+        Self {
+            explanation,
+            ..self
+        }
     }
 }
 
+#[into_request(TeaclaveAccessControlRequest::AuthorizeDataUse)]
+#[derive(Debug)]
+pub struct AuthorizeDataUseRequest {
+    pub subject_function_tags: Vec<String>,
+    pub object_data_labels: Vec<String>,
+}
+
+impl AuthorizeDataUseRequest {
+    pub fn new(subject_function_tags: Vec<String>, object_data_labels: Vec<String>) -> Self {
+        Self {
+            subject_function_tags,
+            object_data_labels,
+        }
+    }
+}
+
+#[into_request(TeaclaveAccessControlResponse::AuthorizeDataUse)]
+#[derive(Debug)]
+pub struct AuthorizeDataUseResponse {
+    pub accept: bool,
+    // Labels whose rules the function does not satisfy
+    pub denied_labels: Vec<String>,
+}
+
+impl AuthorizeDataUseResponse {
+    pub fn new(denied_labels: Vec<String>) -> Self {
+        Self {
+            accept: denied_labels.is_empty(),
+            denied_labels,
+        }
+    }
+}
+
+pub(crate) fn from_proto_decisions(
+    decisions: Vec<frontend_proto::AccessControlDecision>,
+) -> Vec<AccessControlDecision> {
+    decisions
+        .into_iter()
+        .map(|d| AccessControlDecision::new(d.rule, d.accept, d.attributes))
+        .collect()
+}
+
+pub(crate) fn to_proto_decisions(
+    decisions: Vec<AccessControlDecision>,
+) -> Vec<frontend_proto::AccessControlDecision> {
+    decisions
+        .into_iter()
+        .map(|d| frontend_proto::AccessControlDecision {
+            rule: d.rule,
+            accept: d.accept,
+            attributes: d.attributes,
+        })
+        .collect()
+}
+
 impl std::convert::TryFrom<proto::AuthorizeDataRequest> for AuthorizeDataRequest {
     type Error = Error;
 
@@ -137,6 +317,7 @@ impl std::convert::TryFrom<proto::AuthorizeDataRequest> for AuthorizeDataRequest
         let ret = Self {
             subject_user_id: proto.subject_user_id,
             object_data_id: proto.object_data_id,
+            explain: proto.explain,
         };
 
         Ok(ret)
@@ -148,6 +329,7 @@ impl From<AuthorizeDataRequest> for proto::AuthorizeDataRequest {
         Self {
             subject_user_id: request.subject_user_id,
             object_data_id: request.object_data_id,
+            explain: request.explain,
         }
     }
 }
@@ -158,6 +340,7 @@ impl std::convert::TryFrom<proto::AuthorizeDataResponse> for AuthorizeDataRespon
     fn try_from(proto: proto::AuthorizeDataResponse) -> Result<Self> {
         Ok(Self {
             accept: proto.accept,
+            explanation: from_proto_decisions(proto.explanation),
         })
     }
 }
@@ -166,6 +349,7 @@ impl From<AuthorizeDataResponse> for proto::AuthorizeDataResponse {
     fn from(response: AuthorizeDataResponse) -> Self {
         Self {
             accept: response.accept,
+            explanation: to_proto_decisions(response.explanation),
         }
     }
 }
@@ -177,6 +361,7 @@ impl std::convert::TryFrom<proto::AuthorizeFunctionRequest> for AuthorizeFunctio
         let ret = Self {
             subject_user_id: proto.subject_user_id,
             object_function_id: proto.object_function_id,
+            explain: proto.explain,
         };
 
         Ok(ret)
@@ -188,6 +373,7 @@ impl From<AuthorizeFunctionRequest> for proto::AuthorizeFunctionRequest {
         Self {
             subject_user_id: request.subject_user_id,
             object_function_id: request.object_function_id,
+            explain: request.explain,
         }
     }
 }
@@ -198,6 +384,7 @@ impl std::convert::TryFrom<proto::AuthorizeFunctionResponse> for AuthorizeFuncti
     fn try_from(proto: proto::AuthorizeFunctionResponse) -> Result<Self> {
         Ok(Self {
             accept: proto.accept,
+            explanation: from_proto_decisions(proto.explanation),
         })
     }
 }
@@ -206,6 +393,7 @@ impl From<AuthorizeFunctionResponse> for proto::AuthorizeFunctionResponse {
     fn from(response: AuthorizeFunctionResponse) -> Self {
         Self {
             accept: response.accept,
+            explanation: to_proto_decisions(response.explanation),
         }
     }
 }
@@ -217,6 +405,7 @@ impl std::convert::TryFrom<proto::AuthorizeTaskRequest> for AuthorizeTaskRequest
         let ret = Self {
             subject_user_id: proto.subject_user_id,
             object_task_id: proto.object_task_id,
+            explain: proto.explain,
         };
 
         Ok(ret)
@@ -228,6 +417,7 @@ impl From<AuthorizeTaskRequest> for proto::AuthorizeTaskRequest {
         Self {
             subject_user_id: request.subject_user_id,
             object_task_id: request.object_task_id,
+            explain: request.explain,
         }
     }
 }
@@ -238,6 +428,7 @@ impl std::convert::TryFrom<proto::AuthorizeTaskResponse> for AuthorizeTaskRespon
     fn try_from(proto: proto::AuthorizeTaskResponse) -> Result<Self> {
         Ok(Self {
             accept: proto.accept,
+            explanation: from_proto_decisions(proto.explanation),
         })
     }
 }
@@ -246,6 +437,7 @@ impl From<AuthorizeTaskResponse> for proto::AuthorizeTaskResponse {
     fn from(response: AuthorizeTaskResponse) -> Self {
         Self {
             accept: response.accept,
+            explanation: to_proto_decisions(response.explanation),
         }
     }
 }
@@ -259,6 +451,7 @@ impl std::convert::TryFrom<proto::AuthorizeStagedTaskRequest> for AuthorizeStage
             object_function_id: proto.object_function_id,
             object_input_data_id_list: proto.object_input_data_id_list,
             object_output_data_id_list: proto.object_output_data_id_list,
+            explain: proto.explain,
         };
 
         Ok(ret)
@@ -272,6 +465,7 @@ impl From<AuthorizeStagedTaskRequest> for proto::AuthorizeStagedTaskRequest {
             object_function_id: request.object_function_id,
             object_input_data_id_list: request.object_input_data_id_list,
             object_output_data_id_list: request.object_output_data_id_list,
+            explain: request.explain,
         }
     }
 }
@@ -282,6 +476,7 @@ impl std::convert::TryFrom<proto::AuthorizeStagedTaskResponse> for AuthorizeStag
     fn try_from(proto: proto::AuthorizeStagedTaskResponse) -> Result<Self> {
         Ok(Self {
             accept: proto.accept,
+            explanation: from_proto_decisions(proto.explanation),
         })
     }
 }
@@ -290,6 +485,47 @@ impl From<AuthorizeStagedTaskResponse> for proto::AuthorizeStagedTaskResponse {
     fn from(response: AuthorizeStagedTaskResponse) -> Self {
         Self {
             accept: response.accept,
+            explanation: to_proto_decisions(response.explanation),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AuthorizeDataUseRequest> for AuthorizeDataUseRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::AuthorizeDataUseRequest) -> Result<Self> {
+        Ok(Self {
+            subject_function_tags: proto.subject_function_tags,
+            object_data_labels: proto.object_data_labels,
+        })
+    }
+}
+
+impl From<AuthorizeDataUseRequest> for proto::AuthorizeDataUseRequest {
+    fn from(request: AuthorizeDataUseRequest) -> Self {
+        Self {
+            subject_function_tags: request.subject_function_tags,
+            object_data_labels: request.object_data_labels,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AuthorizeDataUseResponse> for AuthorizeDataUseResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::AuthorizeDataUseResponse) -> Result<Self> {
+        Ok(Self {
+            accept: proto.accept,
+            denied_labels: proto.denied_labels,
+        })
+    }
+}
+
+impl From<AuthorizeDataUseResponse> for proto::AuthorizeDataUseResponse {
+    fn from(response: AuthorizeDataUseResponse) -> Self {
+        Self {
+            accept: response.accept,
+            denied_labels: response.denied_labels,
         }
     }
 }
@@ -0,0 +1,121 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::teaclave_attestation_verifier_service_proto as proto;
+use anyhow::{Error, Result};
+use std::convert::TryInto;
+use std::prelude::v1::*;
+use teaclave_rpc::into_request;
+
+pub use crate::teaclave_common::{HealthRequest, HealthResponse};
+pub use proto::TeaclaveAttestationVerifier;
+pub use proto::TeaclaveAttestationVerifierClient;
+pub use proto::TeaclaveAttestationVerifierRequest;
+pub use proto::TeaclaveAttestationVerifierResponse;
+
+#[into_request(TeaclaveAttestationVerifierRequest::Verify)]
+#[derive(Debug)]
+pub struct VerifyRequest {
+    // DER-encoded attested TLS certificate of the enclave
+    pub evidence: Vec<u8>,
+    // Name of a policy in the runtime config of the verifier
+    pub policy: String,
+}
+
+impl VerifyRequest {
+    pub fn new(evidence: impl Into<Vec<u8>>, policy: impl Into<String>) -> Self {
+        Self {
+            evidence: evidence.into(),
+            policy: policy.into(),
+        }
+    }
+}
+
+/// Verdict on the evidence, with the attested contents of the report it is
+/// based on.
+#[into_request(TeaclaveAttestationVerifierResponse::Verify)]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VerifyResponse {
+    pub accepted: bool,
+    // Policy checks the report failed, empty if accepted
+    pub reasons: Vec<String>,
+    // Hex-encoded measurements of the enclave
+    pub mr_enclave: String,
+    pub mr_signer: String,
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub report_data: Vec<u8>,
+    pub quote_status: String,
+    pub advisory_ids: Vec<String>,
+    pub report_age_secs: u64,
+}
+
+impl std::convert::TryFrom<proto::VerifyRequest> for VerifyRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::VerifyRequest) -> Result<Self> {
+        Ok(Self {
+            evidence: proto.evidence,
+            policy: proto.policy,
+        })
+    }
+}
+
+impl From<VerifyRequest> for proto::VerifyRequest {
+    fn from(request: VerifyRequest) -> Self {
+        Self {
+            evidence: request.evidence,
+            policy: request.policy,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::VerifyResponse> for VerifyResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::VerifyResponse) -> Result<Self> {
+        Ok(Self {
+            accepted: proto.accepted,
+            reasons: proto.reasons,
+            mr_enclave: proto.mr_enclave,
+            mr_signer: proto.mr_signer,
+            isv_prod_id: proto.isv_prod_id.try_into()?,
+            isv_svn: proto.isv_svn.try_into()?,
+            report_data: proto.report_data,
+            quote_status: proto.quote_status,
+            advisory_ids: proto.advisory_ids,
+            report_age_secs: proto.report_age_secs,
+        })
+    }
+}
+
+impl From<VerifyResponse> for proto::VerifyResponse {
+    fn from(response: VerifyResponse) -> Self {
+        Self {
+            accepted: response.accepted,
+            reasons: response.reasons,
+            mr_enclave: response.mr_enclave,
+            mr_signer: response.mr_signer,
+            isv_prod_id: response.isv_prod_id as u32,
+            isv_svn: response.isv_svn as u32,
+            report_data: response.report_data,
+            quote_status: response.quote_status,
+            advisory_ids: response.advisory_ids,
+            report_age_secs: response.report_age_secs,
+        }
+    }
+}
@@ -18,11 +18,14 @@
 use anyhow::anyhow;
 use anyhow::{Error, Result};
 use core::convert::TryInto;
+use std::convert::TryFrom;
 use std::prelude::v1::*;
 use teaclave_rpc::into_request;
+use teaclave_types::{AuditLogEntry, Permission};
 
 use crate::teaclave_authentication_service_proto as proto;
 use crate::teaclave_common;
+pub use crate::teaclave_common::{HealthRequest, HealthResponse};
 pub use proto::TeaclaveAuthenticationApi;
 pub use proto::TeaclaveAuthenticationApiClient;
 pub use proto::TeaclaveAuthenticationApiRequest;
@@ -57,6 +60,8 @@ pub struct UserRegisterResponse;
 pub struct UserLoginRequest {
     pub id: std::string::String,
     pub password: std::string::String,
+    // Empty if the user is not enrolled in TOTP two-factor authentication
+    pub totp_code: std::string::String,
 }
 
 impl UserLoginRequest {
@@ -64,6 +69,14 @@ impl UserLoginRequest {
         Self {
             id: id.into(),
             password: password.into(),
+            totp_code: String::new(),
+        }
+    }
+
+    pub fn totp_code(self, totp_code: impl Into<String>) -> Self {
+        Self {
+            totp_code: totp_code.into(),
+            ..self
         }
     }
 }
@@ -82,15 +95,58 @@ impl UserLoginResponse {
     }
 }
 
+#[into_request(TeaclaveAuthenticationApiRequest::UserLoginWithOidc)]
+#[derive(Debug)]
+pub struct UserLoginWithOidcRequest {
+    pub id_token: std::string::String,
+}
+
+impl UserLoginWithOidcRequest {
+    pub fn new(id_token: impl Into<String>) -> Self {
+        Self {
+            id_token: id_token.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::UserLoginWithOidc)]
+#[derive(Debug)]
+pub struct UserLoginWithOidcResponse {
+    pub id: std::string::String,
+    pub token: std::string::String,
+}
+
+impl UserLoginWithOidcResponse {
+    pub fn new(id: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            token: token.into(),
+        }
+    }
+}
+
 #[into_request(TeaclaveAuthenticationInternalRequest::UserAuthenticate)]
 #[derive(Debug)]
 pub struct UserAuthenticateRequest {
     pub credential: teaclave_common::UserCredential,
+    pub operation: std::string::String,
 }
 
 impl UserAuthenticateRequest {
     pub fn new(credential: teaclave_common::UserCredential) -> Self {
-        Self { credential }
+        Self {
+            credential,
+            operation: String::new(),
+        }
+    }
+
+    /// The operation the credential is used for, checked against the scopes
+    /// of API keys.
+    pub fn operation(self, operation: impl Into<String>) -> Self {
+        Self {
+            operation: operation.into(),
+            ..self
+        }
     }
 }
 
@@ -98,11 +154,428 @@ impl UserAuthenticateRequest {
 #[derive(Debug)]
 pub struct UserAuthenticateResponse {
     pub accept: bool,
+    pub permissions: std::vec::Vec<Permission>,
 }
 
 impl UserAuthenticateResponse {
     pub fn new(accept: bool) -> Self {
-        Self { accept }
+        Self {
+            accept,
+            permissions: Vec::new(),
+        }
+    }
+
+    pub fn permissions(self, permissions: Vec<Permission>) -> Self {
+        Self {
+            permissions,
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::GrantImpersonation)]
+#[derive(Debug)]
+pub struct GrantImpersonationRequest {
+    pub admin_id: std::string::String,
+    pub validity: std::time::Duration,
+}
+
+impl GrantImpersonationRequest {
+    pub fn new(admin_id: impl Into<String>, validity: std::time::Duration) -> Self {
+        Self {
+            admin_id: admin_id.into(),
+            validity,
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::GrantImpersonation)]
+#[derive(Debug)]
+pub struct GrantImpersonationResponse {
+    pub consent_token: std::string::String,
+}
+
+impl GrantImpersonationResponse {
+    pub fn new(consent_token: impl Into<String>) -> Self {
+        Self {
+            consent_token: consent_token.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::CreateApiKey)]
+#[derive(Debug)]
+pub struct CreateApiKeyRequest {
+    pub name: std::string::String,
+    pub scopes: std::vec::Vec<std::string::String>,
+}
+
+impl CreateApiKeyRequest {
+    pub fn new(name: impl Into<String>, scopes: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            scopes,
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::CreateApiKey)]
+#[derive(Debug)]
+pub struct CreateApiKeyResponse {
+    pub key_id: std::string::String,
+    pub api_key: std::string::String,
+}
+
+impl CreateApiKeyResponse {
+    pub fn new(key_id: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            key_id: key_id.into(),
+            api_key: api_key.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::RevokeApiKey)]
+#[derive(Debug)]
+pub struct RevokeApiKeyRequest {
+    pub key_id: std::string::String,
+}
+
+impl RevokeApiKeyRequest {
+    pub fn new(key_id: impl Into<String>) -> Self {
+        Self {
+            key_id: key_id.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::RevokeApiKey)]
+#[derive(Debug, Default)]
+pub struct RevokeApiKeyResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::RefreshToken)]
+#[derive(Debug, Default)]
+pub struct RefreshTokenRequest;
+
+#[into_request(TeaclaveAuthenticationApiResponse::RefreshToken)]
+#[derive(Debug)]
+pub struct RefreshTokenResponse {
+    pub token: std::string::String,
+}
+
+impl RefreshTokenResponse {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::RevokeToken)]
+#[derive(Debug, Default)]
+pub struct RevokeTokenRequest {
+    pub all: bool,
+}
+
+impl RevokeTokenRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Revokes every login token of the user instead of the one of the request.
+    pub fn all(self) -> Self {
+        Self { all: true }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::RevokeToken)]
+#[derive(Debug, Default)]
+pub struct RevokeTokenResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::CreateRole)]
+#[derive(Debug)]
+pub struct CreateRoleRequest {
+    pub name: std::string::String,
+    pub permissions: std::vec::Vec<Permission>,
+}
+
+impl CreateRoleRequest {
+    pub fn new(name: impl Into<String>, permissions: Vec<Permission>) -> Self {
+        Self {
+            name: name.into(),
+            permissions,
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::CreateRole)]
+#[derive(Debug, Default)]
+pub struct CreateRoleResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::AssignRoles)]
+#[derive(Debug)]
+pub struct AssignRolesRequest {
+    pub user_id: std::string::String,
+    pub roles: std::vec::Vec<std::string::String>,
+}
+
+impl AssignRolesRequest {
+    pub fn new(user_id: impl Into<String>, roles: Vec<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            roles,
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::AssignRoles)]
+#[derive(Debug, Default)]
+pub struct AssignRolesResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::EnrollTotp)]
+#[derive(Debug, Default)]
+pub struct EnrollTotpRequest {
+    // Empty if the user is not enrolled in TOTP two-factor authentication
+    pub totp_code: std::string::String,
+}
+
+impl EnrollTotpRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn totp_code(self, totp_code: impl Into<String>) -> Self {
+        Self {
+            totp_code: totp_code.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::EnrollTotp)]
+#[derive(Debug)]
+pub struct EnrollTotpResponse {
+    pub secret: std::string::String,
+    pub provisioning_uri: std::string::String,
+}
+
+impl EnrollTotpResponse {
+    pub fn new(secret: impl Into<String>, provisioning_uri: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+            provisioning_uri: provisioning_uri.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::ConfirmTotp)]
+#[derive(Debug)]
+pub struct ConfirmTotpRequest {
+    pub totp_code: std::string::String,
+}
+
+impl ConfirmTotpRequest {
+    pub fn new(totp_code: impl Into<String>) -> Self {
+        Self {
+            totp_code: totp_code.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::ConfirmTotp)]
+#[derive(Debug, Default)]
+pub struct ConfirmTotpResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::ListUsers)]
+#[derive(Debug, Default)]
+pub struct ListUsersRequest {
+    pub start_after: std::string::String,
+    pub limit: u32,
+    pub id_prefix: std::string::String,
+    pub role: std::string::String,
+    pub include_disabled: bool,
+}
+
+impl ListUsersRequest {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    pub fn start_after(self, start_after: impl Into<String>) -> Self {
+        Self {
+            start_after: start_after.into(),
+            ..self
+        }
+    }
+
+    pub fn id_prefix(self, id_prefix: impl Into<String>) -> Self {
+        Self {
+            id_prefix: id_prefix.into(),
+            ..self
+        }
+    }
+
+    pub fn role(self, role: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            ..self
+        }
+    }
+
+    pub fn include_disabled(self) -> Self {
+        Self {
+            include_disabled: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UserSummary {
+    pub id: std::string::String,
+    pub roles: std::vec::Vec<std::string::String>,
+    pub disabled: bool,
+    pub totp_enrolled: bool,
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::ListUsers)]
+#[derive(Debug)]
+pub struct ListUsersResponse {
+    pub users: std::vec::Vec<UserSummary>,
+    pub next_start_after: std::string::String,
+}
+
+impl ListUsersResponse {
+    pub fn new(users: Vec<UserSummary>, next_start_after: impl Into<String>) -> Self {
+        Self {
+            users,
+            next_start_after: next_start_after.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::DisableUser)]
+#[derive(Debug)]
+pub struct DisableUserRequest {
+    pub user_id: std::string::String,
+}
+
+impl DisableUserRequest {
+    pub fn new(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::DisableUser)]
+#[derive(Debug, Default)]
+pub struct DisableUserResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::DeleteUser)]
+#[derive(Debug)]
+pub struct DeleteUserRequest {
+    pub user_id: std::string::String,
+}
+
+impl DeleteUserRequest {
+    pub fn new(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::DeleteUser)]
+#[derive(Debug, Default)]
+pub struct DeleteUserResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::ResetPassword)]
+#[derive(Debug)]
+pub struct ResetPasswordRequest {
+    pub user_id: std::string::String,
+    pub new_password: std::string::String,
+}
+
+impl ResetPasswordRequest {
+    pub fn new(user_id: impl Into<String>, new_password: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            new_password: new_password.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::ResetPassword)]
+#[derive(Debug, Default)]
+pub struct ResetPasswordResponse;
+
+#[into_request(TeaclaveAuthenticationApiRequest::ExportAuditLog)]
+#[derive(Debug)]
+pub struct ExportAuditLogRequest {
+    pub start_seq: u64,
+    pub limit: u32,
+}
+
+impl ExportAuditLogRequest {
+    pub fn new(start_seq: u64, limit: u32) -> Self {
+        Self { start_seq, limit }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::ExportAuditLog)]
+#[derive(Debug)]
+pub struct ExportAuditLogResponse {
+    pub entries: std::vec::Vec<AuditLogEntry>,
+    pub log_len: u64,
+    pub broken_seq: Option<u64>,
+}
+
+#[into_request(TeaclaveAuthenticationInternalRequest::ImpersonationAuthenticate)]
+#[derive(Debug)]
+pub struct ImpersonationAuthenticateRequest {
+    pub credential: teaclave_common::UserCredential,
+    pub consent_token: std::string::String,
+    pub operation: std::string::String,
+}
+
+impl ImpersonationAuthenticateRequest {
+    pub fn new(
+        credential: teaclave_common::UserCredential,
+        consent_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            credential,
+            consent_token: consent_token.into(),
+            operation: String::new(),
+        }
+    }
+
+    /// The operation the platform admin performs as the user, recorded in
+    /// the audit log.
+    pub fn operation(self, operation: impl Into<String>) -> Self {
+        Self {
+            operation: operation.into(),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationInternalResponse::ImpersonationAuthenticate)]
+#[derive(Debug)]
+pub struct ImpersonationAuthenticateResponse {
+    pub accept: bool,
+    pub user_id: std::string::String,
+}
+
+impl ImpersonationAuthenticateResponse {
+    pub fn new(accept: bool, user_id: impl Into<String>) -> Self {
+        Self {
+            accept,
+            user_id: user_id.into(),
+        }
     }
 }
 
@@ -149,6 +622,7 @@ impl std::convert::TryFrom<proto::UserLoginRequest> for UserLoginRequest {
         let ret = Self {
             id: proto.id,
             password: proto.password,
+            totp_code: proto.totp_code,
         };
 
         Ok(ret)
@@ -160,6 +634,7 @@ impl From<UserLoginRequest> for proto::UserLoginRequest {
         Self {
             id: request.id,
             password: request.password,
+            totp_code: request.totp_code,
         }
     }
 }
@@ -182,35 +657,81 @@ impl From<UserLoginResponse> for proto::UserLoginResponse {
     }
 }
 
-impl std::convert::TryFrom<proto::UserAuthenticateRequest> for UserAuthenticateRequest {
+impl std::convert::TryFrom<proto::UserLoginWithOidcRequest> for UserLoginWithOidcRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::UserAuthenticateRequest) -> Result<Self> {
+    fn try_from(proto: proto::UserLoginWithOidcRequest) -> Result<Self> {
         let ret = Self {
-            credential: proto
-                .credential
-                .ok_or_else(|| anyhow!("Missing credential"))?
-                .try_into()?,
+            id_token: proto.id_token,
         };
 
         Ok(ret)
     }
 }
 
-impl From<UserAuthenticateRequest> for proto::UserAuthenticateRequest {
-    fn from(request: UserAuthenticateRequest) -> Self {
+impl From<UserLoginWithOidcRequest> for proto::UserLoginWithOidcRequest {
+    fn from(request: UserLoginWithOidcRequest) -> Self {
         Self {
-            credential: Some(request.credential.into()),
+            id_token: request.id_token,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::UserAuthenticateResponse> for UserAuthenticateResponse {
+impl std::convert::TryFrom<proto::UserLoginWithOidcResponse> for UserLoginWithOidcResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::UserAuthenticateResponse) -> Result<Self> {
+    fn try_from(proto: proto::UserLoginWithOidcResponse) -> Result<Self> {
+        let ret = Self {
+            id: proto.id,
+            token: proto.token,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<UserLoginWithOidcResponse> for proto::UserLoginWithOidcResponse {
+    fn from(response: UserLoginWithOidcResponse) -> Self {
+        Self {
+            id: response.id,
+            token: response.token,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UserAuthenticateRequest> for UserAuthenticateRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::UserAuthenticateRequest) -> Result<Self> {
+        let ret = Self {
+            credential: proto
+                .credential
+                .ok_or_else(|| anyhow!("Missing credential"))?
+                .try_into()?,
+            operation: proto.operation,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<UserAuthenticateRequest> for proto::UserAuthenticateRequest {
+    fn from(request: UserAuthenticateRequest) -> Self {
+        Self {
+            credential: Some(request.credential.into()),
+            operation: request.operation,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UserAuthenticateResponse> for UserAuthenticateResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::UserAuthenticateResponse) -> Result<Self> {
+        let permissions = from_proto_permissions(proto.permissions)?;
         let ret = Self {
             accept: proto.accept,
+            permissions,
         };
 
         Ok(ret)
@@ -221,6 +742,643 @@ impl From<UserAuthenticateResponse> for proto::UserAuthenticateResponse {
     fn from(response: UserAuthenticateResponse) -> Self {
         Self {
             accept: response.accept,
+            permissions: to_proto_permissions(response.permissions),
+        }
+    }
+}
+
+fn from_proto_permissions(permissions: Vec<String>) -> Result<Vec<Permission>> {
+    permissions
+        .iter()
+        .map(|permission| Permission::try_from(permission.as_str()))
+        .collect()
+}
+
+fn to_proto_permissions(permissions: Vec<Permission>) -> Vec<String> {
+    permissions
+        .iter()
+        .map(|permission| permission.to_string())
+        .collect()
+}
+
+impl std::convert::TryFrom<proto::GrantImpersonationRequest> for GrantImpersonationRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GrantImpersonationRequest) -> Result<Self> {
+        let ret = Self {
+            admin_id: proto.admin_id,
+            validity: std::time::Duration::from_secs(proto.validity_secs),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GrantImpersonationRequest> for proto::GrantImpersonationRequest {
+    fn from(request: GrantImpersonationRequest) -> Self {
+        Self {
+            admin_id: request.admin_id,
+            validity_secs: request.validity.as_secs(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GrantImpersonationResponse> for GrantImpersonationResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GrantImpersonationResponse) -> Result<Self> {
+        let ret = Self {
+            consent_token: proto.consent_token,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GrantImpersonationResponse> for proto::GrantImpersonationResponse {
+    fn from(response: GrantImpersonationResponse) -> Self {
+        Self {
+            consent_token: response.consent_token,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateApiKeyRequest> for CreateApiKeyRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateApiKeyRequest) -> Result<Self> {
+        let ret = Self {
+            name: proto.name,
+            scopes: proto.scopes,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<CreateApiKeyRequest> for proto::CreateApiKeyRequest {
+    fn from(request: CreateApiKeyRequest) -> Self {
+        Self {
+            name: request.name,
+            scopes: request.scopes,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateApiKeyResponse> for CreateApiKeyResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateApiKeyResponse) -> Result<Self> {
+        let ret = Self {
+            key_id: proto.key_id,
+            api_key: proto.api_key,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<CreateApiKeyResponse> for proto::CreateApiKeyResponse {
+    fn from(response: CreateApiKeyResponse) -> Self {
+        Self {
+            key_id: response.key_id,
+            api_key: response.api_key,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RevokeApiKeyRequest> for RevokeApiKeyRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RevokeApiKeyRequest) -> Result<Self> {
+        let ret = Self {
+            key_id: proto.key_id,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RevokeApiKeyRequest> for proto::RevokeApiKeyRequest {
+    fn from(request: RevokeApiKeyRequest) -> Self {
+        Self {
+            key_id: request.key_id,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RevokeApiKeyResponse> for RevokeApiKeyResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::RevokeApiKeyResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<RevokeApiKeyResponse> for proto::RevokeApiKeyResponse {
+    fn from(_response: RevokeApiKeyResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::RefreshTokenRequest> for RefreshTokenRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::RefreshTokenRequest) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<RefreshTokenRequest> for proto::RefreshTokenRequest {
+    fn from(_request: RefreshTokenRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::RefreshTokenResponse> for RefreshTokenResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RefreshTokenResponse) -> Result<Self> {
+        let ret = Self { token: proto.token };
+
+        Ok(ret)
+    }
+}
+
+impl From<RefreshTokenResponse> for proto::RefreshTokenResponse {
+    fn from(response: RefreshTokenResponse) -> Self {
+        Self {
+            token: response.token,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RevokeTokenRequest> for RevokeTokenRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RevokeTokenRequest) -> Result<Self> {
+        let ret = Self { all: proto.all };
+
+        Ok(ret)
+    }
+}
+
+impl From<RevokeTokenRequest> for proto::RevokeTokenRequest {
+    fn from(request: RevokeTokenRequest) -> Self {
+        Self { all: request.all }
+    }
+}
+
+impl std::convert::TryFrom<proto::RevokeTokenResponse> for RevokeTokenResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::RevokeTokenResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<RevokeTokenResponse> for proto::RevokeTokenResponse {
+    fn from(_response: RevokeTokenResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateRoleRequest> for CreateRoleRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateRoleRequest) -> Result<Self> {
+        let permissions = from_proto_permissions(proto.permissions)?;
+        let ret = Self {
+            name: proto.name,
+            permissions,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<CreateRoleRequest> for proto::CreateRoleRequest {
+    fn from(request: CreateRoleRequest) -> Self {
+        Self {
+            name: request.name,
+            permissions: to_proto_permissions(request.permissions),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateRoleResponse> for CreateRoleResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::CreateRoleResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<CreateRoleResponse> for proto::CreateRoleResponse {
+    fn from(_response: CreateRoleResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::AssignRolesRequest> for AssignRolesRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::AssignRolesRequest) -> Result<Self> {
+        let ret = Self {
+            user_id: proto.user_id,
+            roles: proto.roles,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<AssignRolesRequest> for proto::AssignRolesRequest {
+    fn from(request: AssignRolesRequest) -> Self {
+        Self {
+            user_id: request.user_id,
+            roles: request.roles,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AssignRolesResponse> for AssignRolesResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::AssignRolesResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<AssignRolesResponse> for proto::AssignRolesResponse {
+    fn from(_response: AssignRolesResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::EnrollTotpRequest> for EnrollTotpRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::EnrollTotpRequest) -> Result<Self> {
+        let ret = Self {
+            totp_code: proto.totp_code,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<EnrollTotpRequest> for proto::EnrollTotpRequest {
+    fn from(request: EnrollTotpRequest) -> Self {
+        Self {
+            totp_code: request.totp_code,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::EnrollTotpResponse> for EnrollTotpResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::EnrollTotpResponse) -> Result<Self> {
+        let ret = Self {
+            secret: proto.secret,
+            provisioning_uri: proto.provisioning_uri,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<EnrollTotpResponse> for proto::EnrollTotpResponse {
+    fn from(response: EnrollTotpResponse) -> Self {
+        Self {
+            secret: response.secret,
+            provisioning_uri: response.provisioning_uri,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ConfirmTotpRequest> for ConfirmTotpRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ConfirmTotpRequest) -> Result<Self> {
+        let ret = Self {
+            totp_code: proto.totp_code,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ConfirmTotpRequest> for proto::ConfirmTotpRequest {
+    fn from(request: ConfirmTotpRequest) -> Self {
+        Self {
+            totp_code: request.totp_code,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ConfirmTotpResponse> for ConfirmTotpResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::ConfirmTotpResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<ConfirmTotpResponse> for proto::ConfirmTotpResponse {
+    fn from(_response: ConfirmTotpResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::ListUsersRequest> for ListUsersRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListUsersRequest) -> Result<Self> {
+        let ret = Self {
+            start_after: proto.start_after,
+            limit: proto.limit,
+            id_prefix: proto.id_prefix,
+            role: proto.role,
+            include_disabled: proto.include_disabled,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListUsersRequest> for proto::ListUsersRequest {
+    fn from(request: ListUsersRequest) -> Self {
+        Self {
+            start_after: request.start_after,
+            limit: request.limit,
+            id_prefix: request.id_prefix,
+            role: request.role,
+            include_disabled: request.include_disabled,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UserSummary> for UserSummary {
+    type Error = Error;
+
+    fn try_from(proto: proto::UserSummary) -> Result<Self> {
+        let ret = Self {
+            id: proto.id,
+            roles: proto.roles,
+            disabled: proto.disabled,
+            totp_enrolled: proto.totp_enrolled,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<UserSummary> for proto::UserSummary {
+    fn from(summary: UserSummary) -> Self {
+        Self {
+            id: summary.id,
+            roles: summary.roles,
+            disabled: summary.disabled,
+            totp_enrolled: summary.totp_enrolled,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListUsersResponse> for ListUsersResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListUsersResponse) -> Result<Self> {
+        let users = proto
+            .users
+            .into_iter()
+            .map(|summary| summary.try_into())
+            .collect::<Result<Vec<UserSummary>>>()?;
+        let ret = Self {
+            users,
+            next_start_after: proto.next_start_after,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListUsersResponse> for proto::ListUsersResponse {
+    fn from(response: ListUsersResponse) -> Self {
+        Self {
+            users: response.users.into_iter().map(|s| s.into()).collect(),
+            next_start_after: response.next_start_after,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DisableUserRequest> for DisableUserRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::DisableUserRequest) -> Result<Self> {
+        Ok(Self {
+            user_id: proto.user_id,
+        })
+    }
+}
+
+impl From<DisableUserRequest> for proto::DisableUserRequest {
+    fn from(request: DisableUserRequest) -> Self {
+        Self {
+            user_id: request.user_id,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DisableUserResponse> for DisableUserResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::DisableUserResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<DisableUserResponse> for proto::DisableUserResponse {
+    fn from(_response: DisableUserResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::DeleteUserRequest> for DeleteUserRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::DeleteUserRequest) -> Result<Self> {
+        Ok(Self {
+            user_id: proto.user_id,
+        })
+    }
+}
+
+impl From<DeleteUserRequest> for proto::DeleteUserRequest {
+    fn from(request: DeleteUserRequest) -> Self {
+        Self {
+            user_id: request.user_id,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DeleteUserResponse> for DeleteUserResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::DeleteUserResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<DeleteUserResponse> for proto::DeleteUserResponse {
+    fn from(_response: DeleteUserResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::ResetPasswordRequest> for ResetPasswordRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ResetPasswordRequest) -> Result<Self> {
+        let ret = Self {
+            user_id: proto.user_id,
+            new_password: proto.new_password,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ResetPasswordRequest> for proto::ResetPasswordRequest {
+    fn from(request: ResetPasswordRequest) -> Self {
+        Self {
+            user_id: request.user_id,
+            new_password: request.new_password,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ResetPasswordResponse> for ResetPasswordResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::ResetPasswordResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<ResetPasswordResponse> for proto::ResetPasswordResponse {
+    fn from(_response: ResetPasswordResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportAuditLogRequest> for ExportAuditLogRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportAuditLogRequest) -> Result<Self> {
+        Ok(Self {
+            start_seq: proto.start_seq,
+            limit: proto.limit,
+        })
+    }
+}
+
+impl From<ExportAuditLogRequest> for proto::ExportAuditLogRequest {
+    fn from(request: ExportAuditLogRequest) -> Self {
+        Self {
+            start_seq: request.start_seq,
+            limit: request.limit,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportAuditLogResponse> for ExportAuditLogResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportAuditLogResponse) -> Result<Self> {
+        let entries = proto
+            .entries
+            .iter()
+            .map(|entry| AuditLogEntry::from_slice(entry))
+            .collect::<Result<_>>()?;
+        let broken_seq = if proto.broken {
+            Some(proto.broken_seq)
+        } else {
+            None
+        };
+        Ok(Self {
+            entries,
+            log_len: proto.log_len,
+            broken_seq,
+        })
+    }
+}
+
+impl From<ExportAuditLogResponse> for proto::ExportAuditLogResponse {
+    fn from(response: ExportAuditLogResponse) -> Self {
+        Self {
+            entries: response
+                .entries
+                .iter()
+                .map(|entry| entry.to_vec().unwrap())
+                .collect(),
+            log_len: response.log_len,
+            broken: response.broken_seq.is_some(),
+            broken_seq: response.broken_seq.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ImpersonationAuthenticateRequest>
+    for ImpersonationAuthenticateRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::ImpersonationAuthenticateRequest) -> Result<Self> {
+        let ret = Self {
+            credential: proto
+                .credential
+                .ok_or_else(|| anyhow!("Missing credential"))?
+                .try_into()?,
+            consent_token: proto.consent_token,
+            operation: proto.operation,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ImpersonationAuthenticateRequest> for proto::ImpersonationAuthenticateRequest {
+    fn from(request: ImpersonationAuthenticateRequest) -> Self {
+        Self {
+            credential: Some(request.credential.into()),
+            consent_token: request.consent_token,
+            operation: request.operation,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ImpersonationAuthenticateResponse>
+    for ImpersonationAuthenticateResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::ImpersonationAuthenticateResponse) -> Result<Self> {
+        let ret = Self {
+            accept: proto.accept,
+            user_id: proto.user_id,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ImpersonationAuthenticateResponse> for proto::ImpersonationAuthenticateResponse {
+    fn from(response: ImpersonationAuthenticateResponse) -> Self {
+        Self {
+            accept: response.accept,
+            user_id: response.user_id,
         }
     }
 }
@@ -18,11 +18,23 @@
 #[cfg(feature = "mesalock_sgx")]
 use std::prelude::v1::*;
 
+use crate::teaclave_access_control_service::TeaclaveAccessControlRequest;
+use crate::teaclave_attestation_verifier_service::TeaclaveAttestationVerifierRequest;
+use crate::teaclave_authentication_service::{
+    TeaclaveAuthenticationApiRequest, TeaclaveAuthenticationInternalRequest,
+};
 use crate::teaclave_common_proto as proto;
+use crate::teaclave_frontend_service::TeaclaveFrontendRequest;
+use crate::teaclave_management_service::TeaclaveManagementRequest;
+use crate::teaclave_scheduler_service::TeaclaveSchedulerRequest;
+use crate::teaclave_storage_service::TeaclaveStorageRequest;
 use anyhow::{bail, Error, Result};
 use std::convert::TryInto;
 use teaclave_crypto::TeaclaveFile128Key;
-use teaclave_types::{FileCrypto, TaskFailure, TaskOutputs, TaskResult, TaskStatus};
+use teaclave_rpc::into_request;
+use teaclave_types::{
+    FileCrypto, TaskFailure, TaskFailureKind, TaskOutputs, TaskResult, TaskStatus,
+};
 
 #[derive(Debug)]
 pub struct UserCredential {
@@ -127,12 +139,39 @@ pub fn i32_from_task_status(status: TaskStatus) -> i32 {
     }
 }
 
+pub fn i32_to_task_failure_kind(kind: i32) -> Result<TaskFailureKind> {
+    let ret = match proto::TaskFailureKind::from_i32(kind) {
+        Some(proto::TaskFailureKind::Error) => TaskFailureKind::Error,
+        Some(proto::TaskFailureKind::StagingTimeout) => TaskFailureKind::StagingTimeout,
+        Some(proto::TaskFailureKind::ExecutionTimeout) => TaskFailureKind::ExecutionTimeout,
+        Some(proto::TaskFailureKind::OutputTooLarge) => TaskFailureKind::OutputTooLarge,
+        Some(proto::TaskFailureKind::OutputHeld) => TaskFailureKind::OutputHeld,
+        Some(proto::TaskFailureKind::WorkerLost) => TaskFailureKind::WorkerLost,
+        Some(proto::TaskFailureKind::Preempted) => TaskFailureKind::Preempted,
+        None => bail!("invalid task failure kind"),
+    };
+    Ok(ret)
+}
+
+pub fn i32_from_task_failure_kind(kind: TaskFailureKind) -> i32 {
+    match kind {
+        TaskFailureKind::Error => proto::TaskFailureKind::Error as i32,
+        TaskFailureKind::StagingTimeout => proto::TaskFailureKind::StagingTimeout as i32,
+        TaskFailureKind::ExecutionTimeout => proto::TaskFailureKind::ExecutionTimeout as i32,
+        TaskFailureKind::OutputTooLarge => proto::TaskFailureKind::OutputTooLarge as i32,
+        TaskFailureKind::OutputHeld => proto::TaskFailureKind::OutputHeld as i32,
+        TaskFailureKind::WorkerLost => proto::TaskFailureKind::WorkerLost as i32,
+        TaskFailureKind::Preempted => proto::TaskFailureKind::Preempted as i32,
+    }
+}
+
 impl std::convert::TryFrom<proto::TaskOutputs> for TaskOutputs {
     type Error = Error;
     fn try_from(proto: proto::TaskOutputs) -> Result<Self> {
         let ret = TaskOutputs {
             return_value: proto.return_value,
             tags_map: proto.tags_map.try_into()?,
+            receipt: Some(proto.receipt).filter(|receipt| !receipt.is_empty()),
         };
         Ok(ret)
     }
@@ -142,6 +181,7 @@ impl std::convert::From<TaskOutputs> for proto::TaskOutputs {
         proto::TaskOutputs {
             return_value: outputs.return_value,
             tags_map: outputs.tags_map.into(),
+            receipt: outputs.receipt.unwrap_or_default(),
         }
     }
 }
@@ -151,6 +191,7 @@ impl std::convert::TryFrom<proto::TaskFailure> for TaskFailure {
     fn try_from(proto: proto::TaskFailure) -> Result<Self> {
         let ret = TaskFailure {
             reason: proto.reason,
+            kind: i32_to_task_failure_kind(proto.kind)?,
         };
         Ok(ret)
     }
@@ -159,6 +200,7 @@ impl std::convert::From<TaskFailure> for proto::TaskFailure {
     fn from(outputs: TaskFailure) -> Self {
         proto::TaskFailure {
             reason: outputs.reason,
+            kind: i32_from_task_failure_kind(outputs.kind),
         }
     }
 }
@@ -194,3 +236,150 @@ impl std::convert::From<TaskResult> for proto::TaskResult {
         proto::TaskResult { result: opt_result }
     }
 }
+
+/// Request of the `Health` RPC, which is served by every service.
+#[into_request(TeaclaveAccessControlRequest::Health)]
+#[into_request(TeaclaveAttestationVerifierRequest::Health)]
+#[into_request(TeaclaveAuthenticationApiRequest::Health)]
+#[into_request(TeaclaveAuthenticationInternalRequest::Health)]
+#[into_request(TeaclaveFrontendRequest::Health)]
+#[into_request(TeaclaveManagementRequest::Health)]
+#[into_request(TeaclaveSchedulerRequest::Health)]
+#[into_request(TeaclaveStorageRequest::Health)]
+#[derive(Debug, Default)]
+pub struct HealthRequest;
+
+impl HealthRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Outcome of one check of a service, e.g., of its attestation or of a
+/// service it depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthCheck {
+    pub name: String,
+    pub healthy: bool,
+    pub detail: String,
+}
+
+impl HealthCheck {
+    pub fn healthy(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            healthy: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn unhealthy(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            healthy: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// A service answering the request is alive; it is ready to serve requests
+/// if all of its checks passed.
+#[derive(Debug, Default)]
+pub struct HealthResponse {
+    pub checks: Vec<HealthCheck>,
+    /// Number of queued tasks, for services with a task queue.
+    pub queue_depth: u64,
+    /// Number of writes not compacted yet, for the storage service.
+    pub pending_compaction_writes: u64,
+}
+
+impl HealthResponse {
+    pub fn new(checks: Vec<HealthCheck>) -> Self {
+        Self {
+            checks,
+            queue_depth: 0,
+            pending_compaction_writes: 0,
+        }
+    }
+
+    pub fn queue_depth(self, queue_depth: u64) -> Self {
+        Self {
+            queue_depth,
+            ..self
+        }
+    }
+
+    pub fn pending_compaction_writes(self, pending_compaction_writes: u64) -> Self {
+        Self {
+            pending_compaction_writes,
+            ..self
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.checks.iter().all(|check| check.healthy)
+    }
+}
+
+impl std::convert::TryFrom<proto::HealthRequest> for HealthRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::HealthRequest) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<HealthRequest> for proto::HealthRequest {
+    fn from(_request: HealthRequest) -> Self {
+        Self {}
+    }
+}
+
+impl From<proto::HealthCheck> for HealthCheck {
+    fn from(proto: proto::HealthCheck) -> Self {
+        Self {
+            name: proto.name,
+            healthy: proto.healthy,
+            detail: proto.detail,
+        }
+    }
+}
+
+impl From<HealthCheck> for proto::HealthCheck {
+    fn from(check: HealthCheck) -> Self {
+        Self {
+            name: check.name,
+            healthy: check.healthy,
+            detail: check.detail,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::HealthResponse> for HealthResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::HealthResponse) -> Result<Self> {
+        let ret = Self {
+            checks: proto.checks.into_iter().map(HealthCheck::from).collect(),
+            queue_depth: proto.queue_depth,
+            pending_compaction_writes: proto.pending_compaction_writes,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<HealthResponse> for proto::HealthResponse {
+    fn from(response: HealthResponse) -> Self {
+        Self {
+            ready: response.is_ready(),
+            checks: response
+                .checks
+                .into_iter()
+                .map(proto::HealthCheck::from)
+                .collect(),
+            queue_depth: response.queue_depth,
+            pending_compaction_writes: response.pending_compaction_writes,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::prelude::v1::*;
+use std::time::Duration;
+
+use crate::teaclave_executor_enclave_service_proto as proto;
+use anyhow::{anyhow, Error, Result};
+use core::convert::TryInto;
+pub use proto::TeaclaveExecutorEnclave;
+pub use proto::TeaclaveExecutorEnclaveClient;
+pub use proto::TeaclaveExecutorEnclaveRequest;
+pub use proto::TeaclaveExecutorEnclaveResponse;
+use teaclave_rpc::into_request;
+use teaclave_types::{FileAuthTag, FunctionArguments, FunctionEnv, StagedFileInfo, StagedFiles};
+use uuid::Uuid;
+
+#[into_request(TeaclaveExecutorEnclaveRequest::InvokeFunction)]
+#[derive(Debug, Default)]
+pub struct InvokeFunctionRequest {
+    pub task_id: Uuid,
+    pub function_name: String,
+    pub function_payload: Vec<u8>,
+    pub function_arguments: FunctionArguments,
+    pub input_files: StagedFiles,
+    pub output_files: StagedFiles,
+    pub env: FunctionEnv,
+    pub time_limit: Option<Duration>,
+    /// Bytes the function may write to each output.
+    pub output_size_limit: Option<u64>,
+}
+
+#[into_request(TeaclaveExecutorEnclaveResponse::InvokeFunction)]
+#[derive(Debug, Default)]
+pub struct InvokeFunctionResponse {
+    pub summary: String,
+    pub log: Vec<String>,
+    pub result_chunks: Vec<Vec<u8>>,
+}
+
+impl InvokeFunctionResponse {
+    pub fn new(summary: impl ToString) -> Self {
+        Self {
+            summary: summary.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn log(self, log: Vec<String>) -> Self {
+        Self { log, ..self }
+    }
+
+    pub fn result_chunks(self, result_chunks: Vec<Vec<u8>>) -> Self {
+        Self {
+            result_chunks,
+            ..self
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::StagedFileInfo> for StagedFileInfo {
+    type Error = Error;
+
+    fn try_from(proto: proto::StagedFileInfo) -> Result<Self> {
+        let crypto_info = proto
+            .crypto_info
+            .ok_or_else(|| anyhow!("missing crypto_info"))?
+            .try_into()?;
+        let cmac = FileAuthTag::from_bytes(&proto.cmac)?;
+        Ok(StagedFileInfo::new(
+            PathBuf::from(proto.path),
+            crypto_info,
+            cmac,
+        ))
+    }
+}
+
+impl From<&StagedFileInfo> for proto::StagedFileInfo {
+    fn from(info: &StagedFileInfo) -> Self {
+        Self {
+            path: info.path.to_string_lossy().to_string(),
+            crypto_info: Some(info.crypto_info.into()),
+            cmac: info.cmac.to_bytes(),
+        }
+    }
+}
+
+fn staged_files_from_proto(proto: HashMap<String, proto::StagedFileInfo>) -> Result<StagedFiles> {
+    proto
+        .into_iter()
+        .map(|(name, info)| Ok((name, info.try_into()?)))
+        .collect()
+}
+
+fn staged_files_to_proto(files: &StagedFiles) -> HashMap<String, proto::StagedFileInfo> {
+    files
+        .iter()
+        .map(|(name, info)| (name.to_string(), info.into()))
+        .collect()
+}
+
+impl std::convert::TryFrom<proto::InvokeFunctionRequest> for InvokeFunctionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::InvokeFunctionRequest) -> Result<Self> {
+        let ret = Self {
+            task_id: Uuid::parse_str(&proto.task_id)?,
+            function_name: proto.function_name,
+            function_payload: proto.function_payload,
+            function_arguments: proto.function_arguments.try_into()?,
+            input_files: staged_files_from_proto(proto.input_files)?,
+            output_files: staged_files_from_proto(proto.output_files)?,
+            env: proto.env,
+            time_limit: match proto.time_limit_ms {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            },
+            output_size_limit: match proto.output_size_limit {
+                0 => None,
+                limit => Some(limit),
+            },
+        };
+        Ok(ret)
+    }
+}
+
+impl From<InvokeFunctionRequest> for proto::InvokeFunctionRequest {
+    fn from(request: InvokeFunctionRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            function_name: request.function_name,
+            function_payload: request.function_payload,
+            function_arguments: request.function_arguments.into_string(),
+            input_files: staged_files_to_proto(&request.input_files),
+            output_files: staged_files_to_proto(&request.output_files),
+            env: request.env,
+            time_limit_ms: request
+                .time_limit
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            output_size_limit: request.output_size_limit.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InvokeFunctionResponse> for InvokeFunctionResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::InvokeFunctionResponse) -> Result<Self> {
+        let ret = Self {
+            summary: proto.summary,
+            log: proto.log,
+            result_chunks: proto.result_chunks,
+        };
+        Ok(ret)
+    }
+}
+
+impl From<InvokeFunctionResponse> for proto::InvokeFunctionResponse {
+    fn from(response: InvokeFunctionResponse) -> Self {
+        Self {
+            summary: response.summary,
+            log: response.log,
+            result_chunks: response.result_chunks,
+        }
+    }
+}
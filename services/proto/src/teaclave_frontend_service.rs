@@ -15,6 +15,10 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::teaclave_access_control_service::{
+    from_proto_decisions, to_proto_decisions, AccessControlDecision, TeaclaveAccessControlRequest,
+    TeaclaveAccessControlResponse,
+};
 use crate::teaclave_common::{i32_from_task_status, i32_to_task_status};
 use crate::teaclave_frontend_service_proto as proto;
 use crate::teaclave_management_service::TeaclaveManagementRequest;
@@ -24,15 +28,21 @@ use anyhow::{Error, Result};
 use core::convert::TryInto;
 use std::collections::HashMap;
 use std::prelude::v1::*;
+use std::time::Duration;
 use teaclave_rpc::into_request;
 use teaclave_types::{
-    Executor, ExecutorType, ExternalID, FileAuthTag, FileCrypto, Function, FunctionArguments,
-    FunctionInput, FunctionOutput, OwnerList, TaskFileOwners, TaskResult, TaskStatus, UserID,
-    UserList,
+    EnclaveMeasurement, Executor, ExecutorNode, ExecutorType, ExternalID, FileAttributes,
+    FileAuthTag, FileCrypto, Function, FunctionArguments, FunctionEnv, FunctionInput,
+    FunctionManifest, FunctionOutput, FunctionVersion, HeldOutputsStatus, InclusionProof,
+    ListOptions, LogHash, MeasurementLogEntry, MrEnclave, MrSigner, NodeCapacity, NodeExecutors,
+    ObjectFilter, OutputPolicy, OwnerList, PipelineLink, PipelineStatus, PrivacyBudget, QuotaUsage,
+    RetryOn, RetryPolicy, SignedTreeHead, TaskBudget, TaskFileOwners, TaskPriority, TaskResult,
+    TaskSchedule, TaskStatus, TenantStats, UserID, UserList, UserQuota,
 };
 use url::Url;
 use uuid::Uuid;
 
+pub use crate::teaclave_common::{HealthRequest, HealthResponse};
 pub use proto::TeaclaveFrontend;
 pub use proto::TeaclaveFrontendClient;
 pub use proto::TeaclaveFrontendRequest;
@@ -45,6 +55,9 @@ pub struct RegisterInputFileRequest {
     pub url: Url,
     pub cmac: FileAuthTag,
     pub crypto_info: FileCrypto,
+    pub attributes: FileAttributes,
+    pub labels: Vec<String>,
+    pub privacy_budget: Option<PrivacyBudget>,
 }
 
 impl RegisterInputFileRequest {
@@ -53,6 +66,76 @@ impl RegisterInputFileRequest {
             url,
             cmac,
             crypto_info: crypto.into(),
+            attributes: FileAttributes::default(),
+            labels: Vec::new(),
+            privacy_budget: None,
+        }
+    }
+
+    pub fn attributes(self, attributes: FileAttributes) -> Self {
+        Self { attributes, ..self }
+    }
+
+    pub fn labels<T: IntoIterator>(self, labels: T) -> Self
+    where
+        <T as IntoIterator>::Item: ToString,
+    {
+        Self {
+            labels: labels.into_iter().map(|x| x.to_string()).collect(),
+            ..self
+        }
+    }
+
+    pub fn privacy_budget(self, privacy_budget: PrivacyBudget) -> Self {
+        Self {
+            privacy_budget: Some(privacy_budget),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::RegisterInlineInputFile)]
+#[into_request(TeaclaveManagementRequest::RegisterInlineInputFile)]
+#[derive(Debug, PartialEq)]
+pub struct RegisterInlineInputFileRequest {
+    pub content: Vec<u8>,
+    pub cmac: FileAuthTag,
+    pub crypto_info: FileCrypto,
+    pub attributes: FileAttributes,
+    pub labels: Vec<String>,
+    pub privacy_budget: Option<PrivacyBudget>,
+}
+
+impl RegisterInlineInputFileRequest {
+    pub fn new(content: Vec<u8>, cmac: FileAuthTag, crypto: impl Into<FileCrypto>) -> Self {
+        Self {
+            content,
+            cmac,
+            crypto_info: crypto.into(),
+            attributes: FileAttributes::default(),
+            labels: Vec::new(),
+            privacy_budget: None,
+        }
+    }
+
+    pub fn attributes(self, attributes: FileAttributes) -> Self {
+        Self { attributes, ..self }
+    }
+
+    pub fn labels<T: IntoIterator>(self, labels: T) -> Self
+    where
+        <T as IntoIterator>::Item: ToString,
+    {
+        Self {
+            labels: labels.into_iter().map(|x| x.to_string()).collect(),
+            ..self
+        }
+    }
+
+    pub fn privacy_budget(self, privacy_budget: PrivacyBudget) -> Self {
+        Self {
+            privacy_budget: Some(privacy_budget),
+            ..self
         }
     }
 }
@@ -84,6 +167,19 @@ impl RegisterInputFileResponse {
     }
 }
 
+#[into_request(TeaclaveFrontendResponse::RegisterInlineInputFile)]
+#[into_request(TeaclaveManagementResponse::RegisterInlineInputFile)]
+#[derive(Debug, PartialEq)]
+pub struct RegisterInlineInputFileResponse {
+    pub data_id: ExternalID,
+}
+
+impl RegisterInlineInputFileResponse {
+    pub fn new(data_id: ExternalID) -> Self {
+        Self { data_id }
+    }
+}
+
 #[into_request(TeaclaveFrontendResponse::UpdateInputFile)]
 #[into_request(TeaclaveManagementResponse::UpdateInputFile)]
 #[derive(Debug, PartialEq)]
@@ -227,11 +323,23 @@ impl GetInputFileRequest {
 pub struct GetInputFileResponse {
     pub owner: OwnerList,
     pub cmac: FileAuthTag,
+    pub privacy_budget: Option<PrivacyBudget>,
 }
 
 impl GetInputFileResponse {
     pub fn new(owner: OwnerList, cmac: FileAuthTag) -> Self {
-        Self { owner, cmac }
+        Self {
+            owner,
+            cmac,
+            privacy_budget: None,
+        }
+    }
+
+    pub fn privacy_budget(self, privacy_budget: Option<PrivacyBudget>) -> Self {
+        Self {
+            privacy_budget,
+            ..self
+        }
     }
 }
 
@@ -274,6 +382,16 @@ pub struct RegisterFunctionRequest {
     pub arguments: Vec<String>,
     pub inputs: Vec<FunctionInput>,
     pub outputs: Vec<FunctionOutput>,
+    pub tags: Vec<String>,
+    /// A committed payload upload used in place of `payload`.
+    pub payload_upload_id: Option<ExternalID>,
+    /// The package manifest, which the request must match.
+    pub manifest: Option<FunctionManifest>,
+    /// An executor enclave of the user running the function in place of
+    /// the executor of `executor_type`.
+    pub executor_enclave: Option<ExternalID>,
+    /// A zip of pure-Python modules which Python functions can import.
+    pub bundle: Vec<u8>,
 }
 
 impl RegisterFunctionRequest {
@@ -285,6 +403,36 @@ impl RegisterFunctionRequest {
         }
     }
 
+    /// Builds the request of a package manifest, with the payload of its
+    /// entry point (empty for builtin functions).
+    pub fn from_manifest(manifest: FunctionManifest, payload: Vec<u8>) -> Result<Self> {
+        manifest.validate()?;
+        let ret = Self {
+            name: manifest.name.clone(),
+            description: manifest.description.clone(),
+            executor_type: manifest.executor_type()?,
+            payload,
+            public: manifest.public,
+            arguments: manifest.argument_names(),
+            inputs: manifest
+                .inputs
+                .iter()
+                .map(|d| FunctionInput::new(d.name.as_str(), d.description.as_str()))
+                .collect(),
+            outputs: manifest
+                .outputs
+                .iter()
+                .map(|d| FunctionOutput::new(d.name.as_str(), d.description.as_str()))
+                .collect(),
+            tags: manifest.tags.clone(),
+            payload_upload_id: None,
+            manifest: Some(manifest),
+            executor_enclave: None,
+            bundle: Vec::new(),
+        };
+        Ok(ret)
+    }
+
     pub fn name(self, name: impl ToString) -> Self {
         Self {
             name: name.to_string(),
@@ -310,6 +458,17 @@ impl RegisterFunctionRequest {
         Self { payload, ..self }
     }
 
+    pub fn bundle(self, bundle: Vec<u8>) -> Self {
+        Self { bundle, ..self }
+    }
+
+    pub fn payload_upload_id(self, upload_id: ExternalID) -> Self {
+        Self {
+            payload_upload_id: Some(upload_id),
+            ..self
+        }
+    }
+
     pub fn public(self, public: bool) -> Self {
         Self { public, ..self }
     }
@@ -331,6 +490,23 @@ impl RegisterFunctionRequest {
     pub fn outputs(self, outputs: Vec<FunctionOutput>) -> Self {
         Self { outputs, ..self }
     }
+
+    pub fn tags<T: IntoIterator>(self, tags: T) -> Self
+    where
+        <T as IntoIterator>::Item: ToString,
+    {
+        Self {
+            tags: tags.into_iter().map(|x| x.to_string()).collect(),
+            ..self
+        }
+    }
+
+    pub fn executor_enclave(self, executor_enclave_id: ExternalID) -> Self {
+        Self {
+            executor_enclave: Some(executor_enclave_id),
+            ..self
+        }
+    }
 }
 
 // We explicitly construct Function here in case of missing any field
@@ -347,6 +523,12 @@ impl From<RegisterFunctionRequest> for Function {
             arguments: request.arguments,
             inputs: request.inputs,
             outputs: request.outputs,
+            tags: request.tags,
+            manifest: request.manifest,
+            version: 0,
+            created_at: 0,
+            executor_enclave: request.executor_enclave,
+            bundle: request.bundle,
         }
     }
 }
@@ -355,14 +537,106 @@ impl From<RegisterFunctionRequest> for Function {
 #[derive(Debug)]
 pub struct RegisterFunctionResponse {
     pub function_id: ExternalID,
+    /// Version among the functions of the user with the same name.
+    pub version: u32,
 }
 
 impl RegisterFunctionResponse {
-    pub fn new(function_id: ExternalID) -> Self {
-        Self { function_id }
+    pub fn new(function_id: ExternalID, version: u32) -> Self {
+        Self {
+            function_id,
+            version,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::BeginPayloadUpload)]
+#[into_request(TeaclaveFrontendRequest::BeginPayloadUpload)]
+#[derive(Debug)]
+pub struct BeginPayloadUploadRequest {
+    /// Set to resume an upload, with the same fields as it began.
+    pub upload_id: Option<ExternalID>,
+    pub total_len: u64,
+    pub part_size: u64,
+    /// SHA-256 of the whole payload.
+    pub payload_hash: Vec<u8>,
+}
+
+impl BeginPayloadUploadRequest {
+    pub fn new(total_len: u64, part_size: u64, payload_hash: Vec<u8>) -> Self {
+        Self {
+            upload_id: None,
+            total_len,
+            part_size,
+            payload_hash,
+        }
+    }
+
+    pub fn resume(self, upload_id: ExternalID) -> Self {
+        Self {
+            upload_id: Some(upload_id),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::BeginPayloadUpload)]
+#[into_request(TeaclaveFrontendResponse::BeginPayloadUpload)]
+#[derive(Debug)]
+pub struct BeginPayloadUploadResponse {
+    pub upload_id: ExternalID,
+    pub part_count: u32,
+    /// Parts already uploaded, numbered from 0.
+    pub received_parts: Vec<u32>,
+}
+
+#[into_request(TeaclaveManagementRequest::UploadPart)]
+#[into_request(TeaclaveFrontendRequest::UploadPart)]
+#[derive(Debug)]
+pub struct UploadPartRequest {
+    pub upload_id: ExternalID,
+    pub part_number: u32,
+    pub data: Vec<u8>,
+    /// SHA-256 of `data`.
+    pub part_hash: Vec<u8>,
+}
+
+impl UploadPartRequest {
+    pub fn new(upload_id: ExternalID, part_number: u32, data: Vec<u8>, part_hash: Vec<u8>) -> Self {
+        Self {
+            upload_id,
+            part_number,
+            data,
+            part_hash,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::UploadPart)]
+#[into_request(TeaclaveFrontendResponse::UploadPart)]
+#[derive(Debug)]
+pub struct UploadPartResponse;
+
+#[into_request(TeaclaveManagementRequest::CommitPayload)]
+#[into_request(TeaclaveFrontendRequest::CommitPayload)]
+#[derive(Debug)]
+pub struct CommitPayloadRequest {
+    pub upload_id: ExternalID,
+}
+
+impl CommitPayloadRequest {
+    pub fn new(upload_id: ExternalID) -> Self {
+        Self { upload_id }
     }
 }
 
+#[into_request(TeaclaveManagementResponse::CommitPayload)]
+#[into_request(TeaclaveFrontendResponse::CommitPayload)]
+#[derive(Debug)]
+pub struct CommitPayloadResponse {
+    pub payload_len: u64,
+}
+
 #[into_request(TeaclaveManagementRequest::GetFunction)]
 #[into_request(TeaclaveFrontendRequest::GetFunction)]
 #[derive(Debug)]
@@ -388,17 +662,87 @@ pub struct GetFunctionResponse {
     pub arguments: Vec<String>,
     pub inputs: Vec<FunctionInput>,
     pub outputs: Vec<FunctionOutput>,
+    pub tags: Vec<String>,
+    pub manifest: Option<FunctionManifest>,
+    /// Zero for the functions registered before versioning.
+    pub version: u32,
+    /// Version used by the tasks asking for the latest.
+    pub default_version: u32,
+    pub executor_enclave: Option<ExternalID>,
+}
+
+#[into_request(TeaclaveFrontendRequest::RollbackFunction)]
+#[into_request(TeaclaveManagementRequest::RollbackFunction)]
+#[derive(Debug)]
+pub struct RollbackFunctionRequest {
+    /// Any version of the function.
+    pub function_id: ExternalID,
+    /// An earlier version than the default, 0 for the one before it.
+    pub version: u32,
+}
+
+impl RollbackFunctionRequest {
+    pub fn new(function_id: ExternalID, version: u32) -> Self {
+        Self {
+            function_id,
+            version,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::RollbackFunction)]
+#[into_request(TeaclaveManagementResponse::RollbackFunction)]
+#[derive(Debug)]
+pub struct RollbackFunctionResponse {
+    pub default_version: u32,
+}
+
+#[into_request(TeaclaveManagementRequest::RegisterExecutorEnclave)]
+#[into_request(TeaclaveFrontendRequest::RegisterExecutorEnclave)]
+#[derive(Debug)]
+pub struct RegisterExecutorEnclaveRequest {
+    pub name: String,
+    pub measurement: EnclaveMeasurement,
+}
+
+impl RegisterExecutorEnclaveRequest {
+    pub fn new(name: impl ToString, measurement: EnclaveMeasurement) -> Self {
+        Self {
+            name: name.to_string(),
+            measurement,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::RegisterExecutorEnclave)]
+#[derive(Debug)]
+pub struct RegisterExecutorEnclaveResponse {
+    pub executor_enclave_id: ExternalID,
+}
+
+impl RegisterExecutorEnclaveResponse {
+    pub fn new(executor_enclave_id: ExternalID) -> Self {
+        Self {
+            executor_enclave_id,
+        }
+    }
 }
 
 #[into_request(TeaclaveManagementRequest::CreateTask)]
 #[into_request(TeaclaveFrontendRequest::CreateTask)]
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct CreateTaskRequest {
     pub function_id: ExternalID,
+    pub function_version: FunctionVersion,
     pub function_arguments: FunctionArguments,
     pub executor: Executor,
     pub inputs_ownership: TaskFileOwners,
     pub outputs_ownership: TaskFileOwners,
+    pub budget: TaskBudget,
+    pub env: FunctionEnv,
+    pub priority: TaskPriority,
+    pub affinity: TaskAffinity,
+    pub deterministic: bool,
 }
 
 impl CreateTaskRequest {
@@ -413,6 +757,13 @@ impl CreateTaskRequest {
         }
     }
 
+    pub fn function_version(self, function_version: FunctionVersion) -> Self {
+        Self {
+            function_version,
+            ..self
+        }
+    }
+
     pub fn function_arguments(self, function_arguments: impl Into<FunctionArguments>) -> Self {
         Self {
             function_arguments: function_arguments.into(),
@@ -437,6 +788,41 @@ impl CreateTaskRequest {
             ..self
         }
     }
+
+    pub fn budget(self, budget: TaskBudget) -> Self {
+        Self { budget, ..self }
+    }
+
+    pub fn env(self, env: FunctionEnv) -> Self {
+        Self { env, ..self }
+    }
+
+    pub fn priority(self, priority: TaskPriority) -> Self {
+        Self { priority, ..self }
+    }
+
+    pub fn affinity(self, affinity: TaskAffinity) -> Self {
+        Self { affinity, ..self }
+    }
+
+    pub fn deterministic(self, deterministic: bool) -> Self {
+        Self {
+            deterministic,
+            ..self
+        }
+    }
+
+    /// The request with the arguments and environment of the overrides in
+    /// place of those with the same keys.
+    pub fn apply(self, overrides: TaskOverrides) -> Self {
+        let mut env = self.env;
+        env.extend(overrides.env);
+        Self {
+            function_arguments: self.function_arguments.merge(overrides.function_arguments),
+            env,
+            ..self
+        }
+    }
 }
 
 #[into_request(TeaclaveManagementResponse::CreateTask)]
@@ -478,869 +864,4119 @@ pub struct GetTaskResponse {
     pub approved_users: UserList,
     pub assigned_inputs: HashMap<String, ExternalID>,
     pub assigned_outputs: HashMap<String, ExternalID>,
+    pub env: FunctionEnv,
+    pub priority: TaskPriority,
     pub status: TaskStatus,
     pub result: TaskResult,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub preemptions: u32,
 }
 
-#[into_request(TeaclaveManagementRequest::AssignData)]
-#[into_request(TeaclaveFrontendRequest::AssignData)]
+#[into_request(TeaclaveManagementRequest::GetTaskResult)]
+#[into_request(TeaclaveFrontendRequest::GetTaskResult)]
 #[derive(Debug)]
-pub struct AssignDataRequest {
+pub struct GetTaskResultRequest {
     pub task_id: ExternalID,
-    pub inputs: HashMap<String, ExternalID>,
-    pub outputs: HashMap<String, ExternalID>,
+    pub offset: u64,
+    // 0 for the rest of the return value
+    pub length: u64,
+    pub export_cwt: bool,
 }
 
-impl AssignDataRequest {
-    pub fn new(
-        task_id: ExternalID,
-        inputs: HashMap<String, ExternalID>,
-        outputs: HashMap<String, ExternalID>,
-    ) -> Self {
+impl GetTaskResultRequest {
+    pub fn new(task_id: ExternalID) -> Self {
         Self {
             task_id,
-            inputs,
-            outputs,
+            offset: 0,
+            length: 0,
+            export_cwt: false,
+        }
+    }
+
+    pub fn range(self, offset: u64, length: u64) -> Self {
+        Self {
+            offset,
+            length,
+            ..self
+        }
+    }
+
+    pub fn export_cwt(self) -> Self {
+        Self {
+            export_cwt: true,
+            ..self
         }
     }
 }
 
+#[into_request(TeaclaveManagementResponse::GetTaskResult)]
 #[derive(Debug)]
-pub struct AssignDataResponse;
+pub struct GetTaskResultResponse {
+    // The return value in `TaskResult::Ok` is the requested range.
+    pub result: TaskResult,
+    pub return_value_len: u64,
+    pub return_value_hash: Vec<u8>,
+    // Empty unless requested and the task succeeded.
+    pub cwt: Vec<u8>,
+}
 
-#[into_request(TeaclaveManagementRequest::ApproveTask)]
-#[into_request(TeaclaveFrontendRequest::ApproveTask)]
+impl GetTaskResultResponse {
+    pub fn new(result: TaskResult, return_value_len: u64, return_value_hash: Vec<u8>) -> Self {
+        Self {
+            result,
+            return_value_len,
+            return_value_hash,
+            cwt: Vec::new(),
+        }
+    }
+
+    pub fn cwt(self, cwt: Vec<u8>) -> Self {
+        Self { cwt, ..self }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::GetTaskLog)]
+#[into_request(TeaclaveFrontendRequest::GetTaskLog)]
 #[derive(Debug)]
-pub struct ApproveTaskRequest {
+pub struct GetTaskLogRequest {
     pub task_id: ExternalID,
+    pub offset: u64,
+    // 0 for the rest of the log
+    pub limit: u64,
 }
 
-impl ApproveTaskRequest {
+impl GetTaskLogRequest {
     pub fn new(task_id: ExternalID) -> Self {
-        Self { task_id }
+        Self {
+            task_id,
+            offset: 0,
+            limit: 0,
+        }
+    }
+
+    pub fn range(self, offset: u64, limit: u64) -> Self {
+        Self {
+            offset,
+            limit,
+            ..self
+        }
     }
 }
 
+#[into_request(TeaclaveManagementResponse::GetTaskLog)]
 #[derive(Debug)]
-pub struct ApproveTaskResponse;
+pub struct GetTaskLogResponse {
+    pub log: Vec<u8>,
+    pub total_len: u64,
+    pub truncated: bool,
+}
 
-#[into_request(TeaclaveManagementRequest::InvokeTask)]
-#[into_request(TeaclaveFrontendRequest::InvokeTask)]
+impl GetTaskLogResponse {
+    pub fn new(log: Vec<u8>, total_len: u64, truncated: bool) -> Self {
+        Self {
+            log,
+            total_len,
+            truncated,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::StreamTaskResult)]
+#[into_request(TeaclaveFrontendRequest::StreamTaskResult)]
 #[derive(Debug)]
-pub struct InvokeTaskRequest {
+pub struct StreamTaskResultRequest {
     pub task_id: ExternalID,
+    // index of the first chunk
+    pub offset: u64,
+    // 0 for the rest of the chunks
+    pub limit: u64,
 }
 
-impl InvokeTaskRequest {
+impl StreamTaskResultRequest {
     pub fn new(task_id: ExternalID) -> Self {
-        Self { task_id }
+        Self {
+            task_id,
+            offset: 0,
+            limit: 0,
+        }
+    }
+
+    pub fn range(self, offset: u64, limit: u64) -> Self {
+        Self {
+            offset,
+            limit,
+            ..self
+        }
     }
 }
 
+#[into_request(TeaclaveManagementResponse::StreamTaskResult)]
 #[derive(Debug)]
-pub struct InvokeTaskResponse;
+pub struct StreamTaskResultResponse {
+    pub chunks: Vec<Vec<u8>>,
+    pub next_offset: u64,
+    pub finished: bool,
+    pub truncated: bool,
+}
 
-impl std::convert::TryFrom<proto::RegisterInputFileRequest> for RegisterInputFileRequest {
-    type Error = Error;
+impl StreamTaskResultResponse {
+    pub fn new(chunks: Vec<Vec<u8>>, next_offset: u64) -> Self {
+        Self {
+            chunks,
+            next_offset,
+            finished: false,
+            truncated: false,
+        }
+    }
 
-    fn try_from(proto: proto::RegisterInputFileRequest) -> Result<Self> {
+    pub fn finished(self, finished: bool) -> Self {
+        Self { finished, ..self }
+    }
+
+    pub fn truncated(self, truncated: bool) -> Self {
+        Self { truncated, ..self }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::AssignData)]
+#[into_request(TeaclaveFrontendRequest::AssignData)]
+#[derive(Debug)]
+pub struct AssignDataRequest {
+    pub task_id: ExternalID,
+    pub inputs: HashMap<String, ExternalID>,
+    pub outputs: HashMap<String, ExternalID>,
+}
+
+impl AssignDataRequest {
+    pub fn new(
+        task_id: ExternalID,
+        inputs: HashMap<String, ExternalID>,
+        outputs: HashMap<String, ExternalID>,
+    ) -> Self {
+        Self {
+            task_id,
+            inputs,
+            outputs,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AssignDataResponse;
+
+#[into_request(TeaclaveManagementRequest::ApproveTask)]
+#[into_request(TeaclaveFrontendRequest::ApproveTask)]
+#[derive(Debug)]
+pub struct ApproveTaskRequest {
+    pub task_id: ExternalID,
+}
+
+impl ApproveTaskRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self { task_id }
+    }
+}
+
+#[derive(Debug)]
+pub struct ApproveTaskResponse;
+
+#[into_request(TeaclaveManagementRequest::InvokeTask)]
+#[into_request(TeaclaveFrontendRequest::InvokeTask)]
+#[derive(Debug)]
+pub struct InvokeTaskRequest {
+    pub task_id: ExternalID,
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+impl InvokeTaskRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self {
+            task_id,
+            retry_policy: None,
+        }
+    }
+
+    pub fn retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy: Some(retry_policy),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InvokeTaskResponse;
+
+/// Arguments and environment of a task of a batch replacing those of the
+/// template with the same keys.
+#[derive(Debug, Default)]
+pub struct TaskOverrides {
+    pub function_arguments: FunctionArguments,
+    pub env: FunctionEnv,
+}
+
+impl TaskOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn function_arguments(self, function_arguments: impl Into<FunctionArguments>) -> Self {
+        Self {
+            function_arguments: function_arguments.into(),
+            ..self
+        }
+    }
+
+    pub fn env(self, env: FunctionEnv) -> Self {
+        Self { env, ..self }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::CreateTasks)]
+#[into_request(TeaclaveFrontendRequest::CreateTasks)]
+pub struct CreateTasksRequest {
+    pub template: CreateTaskRequest,
+    /// One task is created for each.
+    pub overrides: Vec<TaskOverrides>,
+}
+
+impl CreateTasksRequest {
+    pub fn new(template: CreateTaskRequest, overrides: Vec<TaskOverrides>) -> Self {
+        Self {
+            template,
+            overrides,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::CreateTasks)]
+#[derive(Debug)]
+pub struct CreateTasksResponse {
+    /// In the order of the overrides.
+    pub task_ids: Vec<ExternalID>,
+}
+
+impl CreateTasksResponse {
+    pub fn new(task_ids: Vec<ExternalID>) -> Self {
+        Self { task_ids }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::InvokeTasks)]
+#[into_request(TeaclaveFrontendRequest::InvokeTasks)]
+#[derive(Debug)]
+pub struct InvokeTasksRequest {
+    pub task_ids: Vec<ExternalID>,
+}
+
+impl InvokeTasksRequest {
+    pub fn new(task_ids: Vec<ExternalID>) -> Self {
+        Self { task_ids }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvokeTaskFailure {
+    pub task_id: ExternalID,
+    pub reason: String,
+}
+
+#[into_request(TeaclaveManagementResponse::InvokeTasks)]
+#[derive(Debug, Default)]
+pub struct InvokeTasksResponse {
+    /// Tasks which could not be invoked, the others are invoked.
+    pub failures: Vec<InvokeTaskFailure>,
+}
+
+impl InvokeTasksResponse {
+    pub fn new(failures: Vec<InvokeTaskFailure>) -> Self {
+        Self { failures }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ReviewOutput)]
+#[into_request(TeaclaveFrontendRequest::ReviewOutput)]
+#[derive(Debug)]
+pub struct ReviewOutputRequest {
+    pub task_id: ExternalID,
+    pub approve: bool,
+}
+
+impl ReviewOutputRequest {
+    pub fn new(task_id: ExternalID, approve: bool) -> Self {
+        Self { task_id, approve }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ReviewOutput)]
+#[derive(Debug)]
+pub struct ReviewOutputResponse {
+    pub status: HeldOutputsStatus,
+    pub findings: Vec<String>,
+}
+
+impl ReviewOutputResponse {
+    pub fn new(status: HeldOutputsStatus, findings: Vec<String>) -> Self {
+        Self { status, findings }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::GetPlatformInfo)]
+#[derive(Debug, Default)]
+pub struct GetPlatformInfoRequest;
+
+impl GetPlatformInfoRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::GetPlatformInfo)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetPlatformInfoResponse {
+    pub api_version: String,
+    pub executors: Vec<Executor>,
+    // Crypto schemas accepted for registered files, e.g. "teaclave-file-128"
+    pub crypto_schemes: Vec<String>,
+    pub max_message_len: u64,
+    pub inline_data_max_size: u64,
+    // Remote attestation algorithm of the deployment, e.g. "sgx_epid"
+    pub attestation_type: String,
+}
+
+impl GetPlatformInfoResponse {
+    pub fn new(api_version: impl Into<String>, attestation_type: impl Into<String>) -> Self {
+        Self {
+            api_version: api_version.into(),
+            executors: Vec::new(),
+            crypto_schemes: Vec::new(),
+            max_message_len: 0,
+            inline_data_max_size: 0,
+            attestation_type: attestation_type.into(),
+        }
+    }
+
+    pub fn executors(self, executors: impl Into<Vec<Executor>>) -> Self {
+        Self {
+            executors: executors.into(),
+            ..self
+        }
+    }
+
+    pub fn crypto_schemes(self, crypto_schemes: Vec<String>) -> Self {
+        Self {
+            crypto_schemes,
+            ..self
+        }
+    }
+
+    pub fn limits(self, max_message_len: u64, inline_data_max_size: u64) -> Self {
+        Self {
+            max_message_len,
+            inline_data_max_size,
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::EnterReadOnlyMode)]
+#[into_request(TeaclaveManagementRequest::EnterReadOnlyMode)]
+#[derive(Debug)]
+pub struct EnterReadOnlyModeRequest {
+    pub duration_secs: u64,
+    pub reason: String,
+}
+
+impl EnterReadOnlyModeRequest {
+    pub fn new(duration_secs: u64, reason: impl Into<String>) -> Self {
+        Self {
+            duration_secs,
+            reason: reason.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::EnterReadOnlyMode)]
+#[into_request(TeaclaveManagementResponse::EnterReadOnlyMode)]
+#[derive(Debug)]
+pub struct EnterReadOnlyModeResponse {
+    // seconds since the Unix epoch
+    pub expires_at: u64,
+}
+
+impl EnterReadOnlyModeResponse {
+    pub fn new(expires_at: u64) -> Self {
+        Self { expires_at }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::ExitReadOnlyMode)]
+#[into_request(TeaclaveManagementRequest::ExitReadOnlyMode)]
+#[derive(Debug, Default)]
+pub struct ExitReadOnlyModeRequest;
+
+impl ExitReadOnlyModeRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::ExitReadOnlyMode)]
+#[into_request(TeaclaveManagementResponse::ExitReadOnlyMode)]
+#[derive(Debug)]
+pub struct ExitReadOnlyModeResponse;
+
+#[into_request(TeaclaveFrontendRequest::GetMeasurementInclusion)]
+#[into_request(TeaclaveManagementRequest::GetMeasurementInclusion)]
+#[derive(Debug)]
+pub struct GetMeasurementInclusionRequest {
+    pub entry: MeasurementLogEntry,
+}
+
+impl GetMeasurementInclusionRequest {
+    pub fn new(entry: MeasurementLogEntry) -> Self {
+        Self { entry }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::GetMeasurementInclusion)]
+#[into_request(TeaclaveManagementResponse::GetMeasurementInclusion)]
+#[derive(Debug)]
+pub struct GetMeasurementInclusionResponse {
+    pub proof: InclusionProof,
+    pub signed_tree_head: SignedTreeHead,
+}
+
+impl GetMeasurementInclusionResponse {
+    pub fn new(proof: InclusionProof, signed_tree_head: SignedTreeHead) -> Self {
+        Self {
+            proof,
+            signed_tree_head,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::UpdateAccessControlPolicy)]
+#[into_request(TeaclaveManagementRequest::UpdateAccessControlPolicy)]
+#[into_request(TeaclaveAccessControlRequest::UpdatePolicy)]
+#[derive(Debug)]
+pub struct UpdateAccessControlPolicyRequest {
+    // JSON of the version and the model
+    pub bundle: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl UpdateAccessControlPolicyRequest {
+    pub fn new(bundle: impl Into<Vec<u8>>, signature: impl Into<Vec<u8>>) -> Self {
+        Self {
+            bundle: bundle.into(),
+            signature: signature.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::UpdateAccessControlPolicy)]
+#[into_request(TeaclaveManagementResponse::UpdateAccessControlPolicy)]
+#[into_request(TeaclaveAccessControlResponse::UpdatePolicy)]
+#[derive(Debug)]
+pub struct UpdateAccessControlPolicyResponse {
+    pub version: u64,
+}
+
+impl UpdateAccessControlPolicyResponse {
+    pub fn new(version: u64) -> Self {
+        Self { version }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::RollbackAccessControlPolicy)]
+#[into_request(TeaclaveManagementRequest::RollbackAccessControlPolicy)]
+#[into_request(TeaclaveAccessControlRequest::RollbackPolicy)]
+#[derive(Debug)]
+pub struct RollbackAccessControlPolicyRequest {
+    pub version: u64,
+}
+
+impl RollbackAccessControlPolicyRequest {
+    pub fn new(version: u64) -> Self {
+        Self { version }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::RollbackAccessControlPolicy)]
+#[into_request(TeaclaveManagementResponse::RollbackAccessControlPolicy)]
+#[into_request(TeaclaveAccessControlResponse::RollbackPolicy)]
+#[derive(Debug)]
+pub struct RollbackAccessControlPolicyResponse;
+
+#[into_request(TeaclaveFrontendRequest::GetAccessControlPolicy)]
+#[into_request(TeaclaveManagementRequest::GetAccessControlPolicy)]
+#[into_request(TeaclaveAccessControlRequest::GetPolicy)]
+#[derive(Debug, Default)]
+pub struct GetAccessControlPolicyRequest;
+
+impl GetAccessControlPolicyRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::GetAccessControlPolicy)]
+#[into_request(TeaclaveManagementResponse::GetAccessControlPolicy)]
+#[into_request(TeaclaveAccessControlResponse::GetPolicy)]
+#[derive(Debug)]
+pub struct GetAccessControlPolicyResponse {
+    pub version: u64,
+    // Versions available for rollback, in ascending order
+    pub retained_versions: Vec<u64>,
+}
+
+impl GetAccessControlPolicyResponse {
+    pub fn new(version: u64, retained_versions: Vec<u64>) -> Self {
+        Self {
+            version,
+            retained_versions,
+        }
+    }
+}
+
+/// Asks for the decisions of the access control model on the access of a
+/// user to a file, a function or a task. Only platform admins and the owner
+/// of the object get them.
+#[into_request(TeaclaveFrontendRequest::ExplainAccess)]
+#[into_request(TeaclaveManagementRequest::ExplainAccess)]
+#[derive(Debug)]
+pub struct ExplainAccessRequest {
+    pub user_id: UserID,
+    pub object_id: ExternalID,
+}
+
+impl ExplainAccessRequest {
+    pub fn new(user_id: impl Into<UserID>, object_id: ExternalID) -> Self {
+        Self {
+            user_id: user_id.into(),
+            object_id,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::ExplainAccess)]
+#[into_request(TeaclaveManagementResponse::ExplainAccess)]
+#[derive(Debug)]
+pub struct ExplainAccessResponse {
+    pub accept: bool,
+    pub explanation: Vec<AccessControlDecision>,
+}
+
+impl ExplainAccessResponse {
+    pub fn new(accept: bool, explanation: Vec<AccessControlDecision>) -> Self {
+        Self {
+            accept,
+            explanation,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::TransferOwnership)]
+#[into_request(TeaclaveManagementRequest::TransferOwnership)]
+#[derive(Debug)]
+pub struct TransferOwnershipRequest {
+    pub from_user: UserID,
+    pub to_user: UserID,
+    pub object_filter: ObjectFilter,
+}
+
+impl TransferOwnershipRequest {
+    pub fn new(
+        from_user: impl Into<UserID>,
+        to_user: impl Into<UserID>,
+        object_filter: ObjectFilter,
+    ) -> Self {
+        Self {
+            from_user: from_user.into(),
+            to_user: to_user.into(),
+            object_filter,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::TransferOwnership)]
+#[into_request(TeaclaveManagementResponse::TransferOwnership)]
+#[derive(Debug)]
+pub struct TransferOwnershipResponse {
+    pub object_ids: Vec<ExternalID>,
+    // Other owners and participants of the transferred objects
+    pub collaborators: Vec<UserID>,
+}
+
+impl TransferOwnershipResponse {
+    pub fn new(object_ids: Vec<ExternalID>, collaborators: Vec<UserID>) -> Self {
+        Self {
+            object_ids,
+            collaborators,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::SetUserQuota)]
+#[into_request(TeaclaveManagementRequest::SetUserQuota)]
+#[derive(Debug)]
+pub struct SetUserQuotaRequest {
+    pub user_id: UserID,
+    pub quota: UserQuota,
+}
+
+impl SetUserQuotaRequest {
+    pub fn new(user_id: impl Into<UserID>, quota: UserQuota) -> Self {
+        Self {
+            user_id: user_id.into(),
+            quota,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::SetUserQuota)]
+#[into_request(TeaclaveManagementResponse::SetUserQuota)]
+#[derive(Debug)]
+pub struct SetUserQuotaResponse;
+
+#[into_request(TeaclaveFrontendRequest::GetQuotaUsage)]
+#[into_request(TeaclaveManagementRequest::GetQuotaUsage)]
+#[derive(Debug, Default)]
+pub struct GetQuotaUsageRequest {
+    // None for the requesting user
+    pub user_id: Option<UserID>,
+}
+
+impl GetQuotaUsageRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn user_id(self, user_id: impl Into<UserID>) -> Self {
+        Self {
+            user_id: Some(user_id.into()),
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::GetQuotaUsage)]
+#[into_request(TeaclaveManagementResponse::GetQuotaUsage)]
+#[derive(Debug)]
+pub struct GetQuotaUsageResponse {
+    pub quota: UserQuota,
+    pub usage: QuotaUsage,
+}
+
+impl GetQuotaUsageResponse {
+    pub fn new(quota: UserQuota, usage: QuotaUsage) -> Self {
+        Self { quota, usage }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::GetTenantStats)]
+#[into_request(TeaclaveManagementRequest::GetTenantStats)]
+#[derive(Debug)]
+pub struct GetTenantStatsRequest {
+    pub window_secs: u64,
+    // None for the requesting user, or all users with manage_users
+    pub user_id: Option<UserID>,
+}
+
+impl GetTenantStatsRequest {
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs,
+            user_id: None,
+        }
+    }
+
+    pub fn user_id(self, user_id: impl Into<UserID>) -> Self {
+        Self {
+            user_id: Some(user_id.into()),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::GetTenantStats)]
+#[into_request(TeaclaveManagementResponse::GetTenantStats)]
+#[derive(Debug)]
+pub struct GetTenantStatsResponse {
+    pub stats: Vec<TenantStats>,
+}
+
+impl GetTenantStatsResponse {
+    pub fn new(stats: Vec<TenantStats>) -> Self {
+        Self { stats }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::ListNodes)]
+#[into_request(TeaclaveManagementRequest::ListNodes)]
+#[derive(Debug, Default)]
+pub struct ListNodesRequest {}
+
+impl ListNodesRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::ListNodes)]
+#[into_request(TeaclaveManagementResponse::ListNodes)]
+#[derive(Debug)]
+pub struct ListNodesResponse {
+    pub nodes: Vec<ExecutorNode>,
+    /// When the scheduler last reported the nodes, zero if it never did.
+    pub updated_at: u64,
+}
+
+impl ListNodesResponse {
+    pub fn new(nodes: Vec<ExecutorNode>, updated_at: u64) -> Self {
+        Self { nodes, updated_at }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::ListExecutors)]
+#[into_request(TeaclaveManagementRequest::ListExecutors)]
+#[derive(Debug, Default)]
+pub struct ListExecutorsRequest {}
+
+impl ListExecutorsRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::ListExecutors)]
+#[into_request(TeaclaveManagementResponse::ListExecutors)]
+#[derive(Debug)]
+pub struct ListExecutorsResponse {
+    pub nodes: Vec<NodeExecutors>,
+    /// When the scheduler last reported the nodes, zero if it never did.
+    pub updated_at: u64,
+}
+
+impl ListExecutorsResponse {
+    pub fn new(nodes: Vec<NodeExecutors>, updated_at: u64) -> Self {
+        Self { nodes, updated_at }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::CreateScheduledTask)]
+#[into_request(TeaclaveManagementRequest::CreateScheduledTask)]
+#[derive(Debug)]
+pub struct CreateScheduledTaskRequest {
+    /// An approved task with its data assigned, copied by every run.
+    pub task_id: ExternalID,
+    pub schedule: TaskSchedule,
+}
+
+impl CreateScheduledTaskRequest {
+    pub fn new(task_id: ExternalID, schedule: TaskSchedule) -> Self {
+        Self { task_id, schedule }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::CreateScheduledTask)]
+#[into_request(TeaclaveManagementResponse::CreateScheduledTask)]
+#[derive(Debug)]
+pub struct CreateScheduledTaskResponse {
+    pub scheduled_task_id: ExternalID,
+}
+
+impl CreateScheduledTaskResponse {
+    pub fn new(scheduled_task_id: ExternalID) -> Self {
+        Self { scheduled_task_id }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::PauseScheduledTask)]
+#[into_request(TeaclaveManagementRequest::PauseScheduledTask)]
+#[derive(Debug)]
+pub struct PauseScheduledTaskRequest {
+    pub scheduled_task_id: ExternalID,
+}
+
+impl PauseScheduledTaskRequest {
+    pub fn new(scheduled_task_id: ExternalID) -> Self {
+        Self { scheduled_task_id }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::PauseScheduledTask)]
+#[into_request(TeaclaveManagementResponse::PauseScheduledTask)]
+#[derive(Debug)]
+pub struct PauseScheduledTaskResponse;
+
+#[into_request(TeaclaveFrontendRequest::ResumeScheduledTask)]
+#[into_request(TeaclaveManagementRequest::ResumeScheduledTask)]
+#[derive(Debug)]
+pub struct ResumeScheduledTaskRequest {
+    pub scheduled_task_id: ExternalID,
+}
+
+impl ResumeScheduledTaskRequest {
+    pub fn new(scheduled_task_id: ExternalID) -> Self {
+        Self { scheduled_task_id }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::ResumeScheduledTask)]
+#[into_request(TeaclaveManagementResponse::ResumeScheduledTask)]
+#[derive(Debug)]
+pub struct ResumeScheduledTaskResponse;
+
+#[into_request(TeaclaveFrontendRequest::ListUpcomingRuns)]
+#[into_request(TeaclaveManagementRequest::ListUpcomingRuns)]
+#[derive(Debug)]
+pub struct ListUpcomingRunsRequest {
+    pub scheduled_task_id: ExternalID,
+    pub count: u32,
+}
+
+impl ListUpcomingRunsRequest {
+    pub fn new(scheduled_task_id: ExternalID, count: u32) -> Self {
+        Self {
+            scheduled_task_id,
+            count,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::ListUpcomingRuns)]
+#[into_request(TeaclaveManagementResponse::ListUpcomingRuns)]
+#[derive(Debug)]
+pub struct ListUpcomingRunsResponse {
+    pub schedule: TaskSchedule,
+    pub paused: bool,
+    /// Times of the runs in seconds since the Unix epoch, none if paused.
+    pub run_times: Vec<u64>,
+    pub runs: u64,
+    pub last_task_id: Option<ExternalID>,
+}
+
+#[into_request(TeaclaveFrontendRequest::CreatePipeline)]
+#[into_request(TeaclaveManagementRequest::CreatePipeline)]
+#[derive(Debug)]
+pub struct CreatePipelineRequest {
+    /// Tasks of the requesting user which are not yet invoked.
+    pub task_ids: Vec<ExternalID>,
+    pub links: Vec<PipelineLink>,
+}
+
+impl CreatePipelineRequest {
+    pub fn new(task_ids: Vec<ExternalID>, links: Vec<PipelineLink>) -> Self {
+        Self { task_ids, links }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::CreatePipeline)]
+#[into_request(TeaclaveManagementResponse::CreatePipeline)]
+#[derive(Debug)]
+pub struct CreatePipelineResponse {
+    pub pipeline_id: ExternalID,
+}
+
+impl CreatePipelineResponse {
+    pub fn new(pipeline_id: ExternalID) -> Self {
+        Self { pipeline_id }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::GetPipeline)]
+#[into_request(TeaclaveManagementRequest::GetPipeline)]
+#[derive(Debug)]
+pub struct GetPipelineRequest {
+    pub pipeline_id: ExternalID,
+}
+
+impl GetPipelineRequest {
+    pub fn new(pipeline_id: ExternalID) -> Self {
+        Self { pipeline_id }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::GetPipeline)]
+#[into_request(TeaclaveManagementResponse::GetPipeline)]
+#[derive(Debug)]
+pub struct GetPipelineResponse {
+    pub creator: UserID,
+    /// Tasks in the order of their stages.
+    pub task_ids: Vec<ExternalID>,
+    pub links: Vec<PipelineLink>,
+    pub status: PipelineStatus,
+    pub failed_task_id: Option<ExternalID>,
+}
+
+#[into_request(TeaclaveFrontendRequest::ListFunctions)]
+#[into_request(TeaclaveManagementRequest::ListFunctions)]
+#[derive(Debug, Default)]
+pub struct ListFunctionsRequest {
+    /// Functions of any owner visible to the user if none.
+    pub owner: Option<UserID>,
+    pub options: ListOptions,
+}
+
+impl ListFunctionsRequest {
+    pub fn new(options: ListOptions) -> Self {
+        Self {
+            owner: None,
+            options,
+        }
+    }
+
+    pub fn owner(self, owner: impl Into<UserID>) -> Self {
+        Self {
+            owner: Some(owner.into()),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSummary {
+    pub function_id: ExternalID,
+    pub name: String,
+    pub owner: UserID,
+    pub version: u32,
+    pub public: bool,
+    pub created_at: u64,
+}
+
+#[into_request(TeaclaveFrontendResponse::ListFunctions)]
+#[into_request(TeaclaveManagementResponse::ListFunctions)]
+#[derive(Debug)]
+pub struct ListFunctionsResponse {
+    pub functions: Vec<FunctionSummary>,
+    /// Cursor of the next page, none for the last page.
+    pub next_cursor: Option<String>,
+}
+
+#[into_request(TeaclaveFrontendRequest::ListTasks)]
+#[into_request(TeaclaveManagementRequest::ListTasks)]
+#[derive(Debug, Default)]
+pub struct ListTasksRequest {
+    /// Tasks of any creator visible to the user if none.
+    pub creator: Option<UserID>,
+    /// Tasks of any status if empty.
+    pub statuses: Vec<TaskStatus>,
+    pub options: ListOptions,
+}
+
+impl ListTasksRequest {
+    pub fn new(options: ListOptions) -> Self {
+        Self {
+            creator: None,
+            statuses: Vec::new(),
+            options,
+        }
+    }
+
+    pub fn creator(self, creator: impl Into<UserID>) -> Self {
+        Self {
+            creator: Some(creator.into()),
+            ..self
+        }
+    }
+
+    pub fn statuses(self, statuses: Vec<TaskStatus>) -> Self {
+        Self { statuses, ..self }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskSummary {
+    pub task_id: ExternalID,
+    pub creator: UserID,
+    pub function_id: ExternalID,
+    pub status: TaskStatus,
+    pub created_at: u64,
+}
+
+#[into_request(TeaclaveFrontendResponse::ListTasks)]
+#[into_request(TeaclaveManagementResponse::ListTasks)]
+#[derive(Debug)]
+pub struct ListTasksResponse {
+    pub tasks: Vec<TaskSummary>,
+    /// Cursor of the next page, none for the last page.
+    pub next_cursor: Option<String>,
+}
+
+#[into_request(TeaclaveFrontendRequest::RegisterWebhook)]
+#[into_request(TeaclaveManagementRequest::RegisterWebhook)]
+#[derive(Debug)]
+pub struct RegisterWebhookRequest {
+    /// HTTPS endpoint to which the events of the tasks of the user are
+    /// posted, replacing the previous one.
+    pub url: Url,
+}
+
+impl RegisterWebhookRequest {
+    pub fn new(url: Url) -> Self {
+        Self { url }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::RegisterWebhook)]
+#[into_request(TeaclaveManagementResponse::RegisterWebhook)]
+#[derive(Debug)]
+pub struct RegisterWebhookResponse {
+    /// Key of the signatures of the events, see `TaskEvent::verify`.
+    pub secret: Vec<u8>,
+}
+
+#[into_request(TeaclaveFrontendRequest::DeleteWebhook)]
+#[into_request(TeaclaveManagementRequest::DeleteWebhook)]
+#[derive(Debug, Default)]
+pub struct DeleteWebhookRequest;
+
+impl DeleteWebhookRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::DeleteWebhook)]
+#[into_request(TeaclaveManagementResponse::DeleteWebhook)]
+#[derive(Debug)]
+pub struct DeleteWebhookResponse;
+
+#[into_request(TeaclaveFrontendRequest::CheckConsistency)]
+#[into_request(TeaclaveManagementRequest::CheckConsistency)]
+#[derive(Debug, Default)]
+pub struct CheckConsistencyRequest {
+    /// Whether to repair the findings which can be repaired, all or none of
+    /// them.
+    pub repair: bool,
+}
+
+impl CheckConsistencyRequest {
+    pub fn new(repair: bool) -> Self {
+        Self { repair }
+    }
+}
+
+/// Kinds of the records left inconsistent, e.g., by crashes in the middle of
+/// requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InconsistencyKind {
+    /// A task not finished whose function no longer exists.
+    TaskWithoutFunction,
+    /// Fusion data written by a task which no longer refers to it.
+    OrphanedFusionData,
+    /// An entry of a queue of staged tasks which is missing, left behind, or
+    /// refers to a task not staged.
+    DanglingQueueEntry,
+}
+
+impl InconsistencyKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InconsistencyKind::TaskWithoutFunction => "task_without_function",
+            InconsistencyKind::OrphanedFusionData => "orphaned_fusion_data",
+            InconsistencyKind::DanglingQueueEntry => "dangling_queue_entry",
+        }
+    }
+}
+
+impl std::str::FromStr for InconsistencyKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let kind = match s {
+            "task_without_function" => InconsistencyKind::TaskWithoutFunction,
+            "orphaned_fusion_data" => InconsistencyKind::OrphanedFusionData,
+            "dangling_queue_entry" => InconsistencyKind::DanglingQueueEntry,
+            _ => return Err(anyhow!("invalid inconsistency kind")),
+        };
+        Ok(kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsistencyFinding {
+    pub kind: InconsistencyKind,
+    /// Id of the record, or key of the queue.
+    pub key: String,
+    pub detail: String,
+    pub repaired: bool,
+}
+
+impl ConsistencyFinding {
+    pub fn new(kind: InconsistencyKind, key: impl ToString, detail: impl ToString) -> Self {
+        Self {
+            kind,
+            key: key.to_string(),
+            detail: detail.to_string(),
+            repaired: false,
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendResponse::CheckConsistency)]
+#[into_request(TeaclaveManagementResponse::CheckConsistency)]
+#[derive(Debug)]
+pub struct CheckConsistencyResponse {
+    pub findings: Vec<ConsistencyFinding>,
+}
+
+impl CheckConsistencyResponse {
+    pub fn new(findings: Vec<ConsistencyFinding>) -> Self {
+        Self { findings }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFileRequest> for RegisterInputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFileRequest) -> Result<Self> {
         let url = Url::parse(&proto.url)?;
         let cmac = FileAuthTag::from_bytes(&proto.cmac)?;
         let crypto_info = proto
             .crypto_info
             .ok_or_else(|| anyhow!("missing crypto_info"))?
             .try_into()?;
-        Ok(RegisterInputFileRequest {
-            url,
-            cmac,
-            crypto_info,
+        let attributes = proto.attributes.try_into()?;
+        Ok(RegisterInputFileRequest {
+            url,
+            cmac,
+            crypto_info,
+            attributes,
+            labels: proto.labels,
+            privacy_budget: proto.privacy_budget.map(PrivacyBudget::from),
+        })
+    }
+}
+
+impl From<RegisterInputFileRequest> for proto::RegisterInputFileRequest {
+    fn from(request: RegisterInputFileRequest) -> Self {
+        Self {
+            url: request.url.into_string(),
+            cmac: request.cmac.to_bytes(),
+            crypto_info: Some(request.crypto_info.into()),
+            attributes: request.attributes.into_string(),
+            labels: request.labels,
+            privacy_budget: request.privacy_budget.map(proto::PrivacyBudget::from),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInlineInputFileRequest>
+    for RegisterInlineInputFileRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInlineInputFileRequest) -> Result<Self> {
+        let cmac = FileAuthTag::from_bytes(&proto.cmac)?;
+        let crypto_info = proto
+            .crypto_info
+            .ok_or_else(|| anyhow!("missing crypto_info"))?
+            .try_into()?;
+        let attributes = proto.attributes.try_into()?;
+        Ok(RegisterInlineInputFileRequest {
+            content: proto.content,
+            cmac,
+            crypto_info,
+            attributes,
+            labels: proto.labels,
+            privacy_budget: proto.privacy_budget.map(PrivacyBudget::from),
+        })
+    }
+}
+
+impl From<RegisterInlineInputFileRequest> for proto::RegisterInlineInputFileRequest {
+    fn from(request: RegisterInlineInputFileRequest) -> Self {
+        Self {
+            content: request.content,
+            cmac: request.cmac.to_bytes(),
+            crypto_info: Some(request.crypto_info.into()),
+            attributes: request.attributes.into_string(),
+            labels: request.labels,
+            privacy_budget: request.privacy_budget.map(proto::PrivacyBudget::from),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UpdateInputFileRequest> for UpdateInputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::UpdateInputFileRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let url = Url::parse(&proto.url)?;
+
+        Ok(UpdateInputFileRequest { data_id, url })
+    }
+}
+
+impl From<UpdateInputFileRequest> for proto::UpdateInputFileRequest {
+    fn from(request: UpdateInputFileRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+            url: request.url.into_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFileResponse> for RegisterInputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFileResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterInputFileResponse> for proto::RegisterInputFileResponse {
+    fn from(request: RegisterInputFileResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInlineInputFileResponse>
+    for RegisterInlineInputFileResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInlineInputFileResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterInlineInputFileResponse> for proto::RegisterInlineInputFileResponse {
+    fn from(response: RegisterInlineInputFileResponse) -> Self {
+        Self {
+            data_id: response.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UpdateInputFileResponse> for UpdateInputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::UpdateInputFileResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<UpdateInputFileResponse> for proto::UpdateInputFileResponse {
+    fn from(request: UpdateInputFileResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterOutputFileRequest> for RegisterOutputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterOutputFileRequest) -> Result<Self> {
+        let ret = Self {
+            url: Url::parse(&proto.url)?,
+            crypto_info: proto
+                .crypto_info
+                .ok_or_else(|| anyhow!("missing crypto_info"))?
+                .try_into()?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterOutputFileRequest> for proto::RegisterOutputFileRequest {
+    fn from(request: RegisterOutputFileRequest) -> Self {
+        Self {
+            url: request.url.into_string(),
+            crypto_info: Some(request.crypto_info.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UpdateOutputFileRequest> for UpdateOutputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::UpdateOutputFileRequest) -> Result<Self> {
+        let ret = Self {
+            data_id: proto.data_id.try_into()?,
+            url: Url::parse(&proto.url)?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<UpdateOutputFileRequest> for proto::UpdateOutputFileRequest {
+    fn from(request: UpdateOutputFileRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+            url: request.url.into_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterOutputFileResponse> for RegisterOutputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterOutputFileResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterOutputFileResponse> for proto::RegisterOutputFileResponse {
+    fn from(request: RegisterOutputFileResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UpdateOutputFileResponse> for UpdateOutputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::UpdateOutputFileResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<UpdateOutputFileResponse> for proto::UpdateOutputFileResponse {
+    fn from(request: UpdateOutputFileResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFusionOutputRequest> for RegisterFusionOutputRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFusionOutputRequest) -> Result<Self> {
+        let ret = Self {
+            owner_list: OwnerList::new(proto.owner_list),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterFusionOutputRequest> for proto::RegisterFusionOutputRequest {
+    fn from(request: RegisterFusionOutputRequest) -> Self {
+        Self {
+            owner_list: request.owner_list.into(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFusionOutputResponse> for RegisterFusionOutputResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFusionOutputResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterFusionOutputResponse> for proto::RegisterFusionOutputResponse {
+    fn from(request: RegisterFusionOutputResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFromOutputRequest>
+    for RegisterInputFromOutputRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFromOutputRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let ret = Self { data_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterInputFromOutputRequest> for proto::RegisterInputFromOutputRequest {
+    fn from(request: RegisterInputFromOutputRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFromOutputResponse>
+    for RegisterInputFromOutputResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFromOutputResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterInputFromOutputResponse> for proto::RegisterInputFromOutputResponse {
+    fn from(request: RegisterInputFromOutputResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetInputFileRequest> for GetInputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetInputFileRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let ret = Self { data_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetInputFileRequest> for proto::GetInputFileRequest {
+    fn from(request: GetInputFileRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetInputFileResponse> for GetInputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetInputFileResponse) -> Result<Self> {
+        Ok(Self {
+            owner: OwnerList::new(proto.owner),
+            cmac: FileAuthTag::from_bytes(&proto.cmac)?,
+            privacy_budget: proto.privacy_budget.map(PrivacyBudget::from),
+        })
+    }
+}
+
+impl From<GetInputFileResponse> for proto::GetInputFileResponse {
+    fn from(request: GetInputFileResponse) -> Self {
+        Self {
+            owner: request.owner.into(),
+            cmac: request.cmac.to_bytes(),
+            privacy_budget: request.privacy_budget.map(proto::PrivacyBudget::from),
+        }
+    }
+}
+
+impl From<proto::PrivacyBudget> for PrivacyBudget {
+    fn from(proto: proto::PrivacyBudget) -> Self {
+        Self {
+            epsilon: proto.epsilon,
+            delta: proto.delta,
+            spent_epsilon: proto.spent_epsilon,
+            spent_delta: proto.spent_delta,
+        }
+    }
+}
+
+impl From<PrivacyBudget> for proto::PrivacyBudget {
+    fn from(budget: PrivacyBudget) -> Self {
+        Self {
+            epsilon: budget.epsilon,
+            delta: budget.delta,
+            spent_epsilon: budget.spent_epsilon,
+            spent_delta: budget.spent_delta,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetOutputFileRequest> for GetOutputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetOutputFileRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let ret = Self { data_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetOutputFileRequest> for proto::GetOutputFileRequest {
+    fn from(request: GetOutputFileRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetOutputFileResponse> for GetOutputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetOutputFileResponse) -> Result<Self> {
+        let cmac = {
+            if proto.cmac.is_empty() {
+                None
+            } else {
+                Some(FileAuthTag::from_bytes(&proto.cmac)?)
+            }
+        };
+
+        Ok(Self {
+            owner: OwnerList::new(proto.owner),
+            cmac,
+        })
+    }
+}
+
+impl From<GetOutputFileResponse> for proto::GetOutputFileResponse {
+    fn from(request: GetOutputFileResponse) -> Self {
+        Self {
+            owner: request.owner.into(),
+            cmac: request.cmac.map_or_else(Vec::new, |cmac| cmac.to_bytes()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::FunctionInput> for FunctionInput {
+    type Error = Error;
+
+    fn try_from(proto: proto::FunctionInput) -> Result<Self> {
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<FunctionInput> for proto::FunctionInput {
+    fn from(input: FunctionInput) -> Self {
+        Self {
+            name: input.name,
+            description: input.description,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::FunctionOutput> for FunctionOutput {
+    type Error = Error;
+
+    fn try_from(proto: proto::FunctionOutput) -> Result<Self> {
+        let policy = match proto.policy {
+            Some(policy) => policy.try_into()?,
+            None => OutputPolicy::default(),
+        };
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+            policy,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<FunctionOutput> for proto::FunctionOutput {
+    fn from(output: FunctionOutput) -> Self {
+        let policy = if output.policy.is_empty() {
+            None
+        } else {
+            Some(output.policy.into())
+        };
+        Self {
+            name: output.name,
+            description: output.description,
+            policy,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::OutputPolicy> for OutputPolicy {
+    type Error = Error;
+
+    fn try_from(proto: proto::OutputPolicy) -> Result<Self> {
+        let ret = Self {
+            max_bytes: Some(proto.max_bytes).filter(|limit| *limit > 0),
+            format: proto.format.try_into()?,
+            columns: proto.columns,
+            count_column: Some(proto.count_column).filter(|column| !column.is_empty()),
+            min_count: proto.min_count,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<OutputPolicy> for proto::OutputPolicy {
+    fn from(policy: OutputPolicy) -> Self {
+        Self {
+            max_bytes: policy.max_bytes.unwrap_or_default(),
+            format: policy.format.to_string(),
+            columns: policy.columns,
+            count_column: policy.count_column.unwrap_or_default(),
+            min_count: policy.min_count,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFunctionRequest> for RegisterFunctionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFunctionRequest) -> Result<Self> {
+        let inputs: Result<Vec<FunctionInput>> = proto
+            .inputs
+            .into_iter()
+            .map(FunctionInput::try_from)
+            .collect();
+        let outputs: Result<Vec<FunctionOutput>> = proto
+            .outputs
+            .into_iter()
+            .map(FunctionOutput::try_from)
+            .collect();
+        let executor_type = proto.executor_type.try_into()?;
+
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+            executor_type,
+            payload: proto.payload,
+            public: proto.public,
+            arguments: proto.arguments,
+            inputs: inputs?,
+            outputs: outputs?,
+            tags: proto.tags,
+            payload_upload_id: match proto.payload_upload_id.as_str() {
+                "" => None,
+                id => Some(id.try_into()?),
+            },
+            manifest: manifest_from_proto(&proto.manifest)?,
+            executor_enclave: match proto.executor_enclave_id.as_str() {
+                "" => None,
+                id => Some(id.try_into()?),
+            },
+            bundle: proto.bundle,
+        };
+        Ok(ret)
+    }
+}
+
+impl From<RegisterFunctionRequest> for proto::RegisterFunctionRequest {
+    fn from(request: RegisterFunctionRequest) -> Self {
+        let inputs: Vec<proto::FunctionInput> = request
+            .inputs
+            .into_iter()
+            .map(proto::FunctionInput::from)
+            .collect();
+        let outputs: Vec<proto::FunctionOutput> = request
+            .outputs
+            .into_iter()
+            .map(proto::FunctionOutput::from)
+            .collect();
+
+        Self {
+            name: request.name,
+            description: request.description,
+            executor_type: request.executor_type.into(),
+            payload: request.payload,
+            public: request.public,
+            arguments: request.arguments,
+            inputs,
+            outputs,
+            tags: request.tags,
+            payload_upload_id: request
+                .payload_upload_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            manifest: manifest_to_proto(request.manifest),
+            executor_enclave_id: request
+                .executor_enclave
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            bundle: request.bundle,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFunctionResponse> for RegisterFunctionResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFunctionResponse) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let ret = Self::new(function_id, proto.version);
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterFunctionResponse> for proto::RegisterFunctionResponse {
+    fn from(response: RegisterFunctionResponse) -> Self {
+        Self {
+            function_id: response.function_id.to_string(),
+            version: response.version,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetFunctionRequest> for GetFunctionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetFunctionRequest) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let ret = Self { function_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetFunctionRequest> for proto::GetFunctionRequest {
+    fn from(request: GetFunctionRequest) -> Self {
+        Self {
+            function_id: request.function_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetFunctionResponse> for GetFunctionResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetFunctionResponse) -> Result<Self> {
+        let inputs: Result<Vec<FunctionInput>> = proto
+            .inputs
+            .into_iter()
+            .map(FunctionInput::try_from)
+            .collect();
+        let outputs: Result<Vec<FunctionOutput>> = proto
+            .outputs
+            .into_iter()
+            .map(FunctionOutput::try_from)
+            .collect();
+        let executor_type = proto.executor_type.try_into()?;
+
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+            owner: proto.owner.into(),
+            executor_type,
+            payload: proto.payload,
+            public: proto.public,
+            arguments: proto.arguments,
+            inputs: inputs?,
+            outputs: outputs?,
+            tags: proto.tags,
+            manifest: manifest_from_proto(&proto.manifest)?,
+            version: proto.version,
+            default_version: proto.default_version,
+            executor_enclave: match proto.executor_enclave_id.as_str() {
+                "" => None,
+                id => Some(id.try_into()?),
+            },
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetFunctionResponse> for proto::GetFunctionResponse {
+    fn from(response: GetFunctionResponse) -> Self {
+        let inputs: Vec<proto::FunctionInput> = response
+            .inputs
+            .into_iter()
+            .map(proto::FunctionInput::from)
+            .collect();
+        let outputs: Vec<proto::FunctionOutput> = response
+            .outputs
+            .into_iter()
+            .map(proto::FunctionOutput::from)
+            .collect();
+
+        Self {
+            name: response.name,
+            description: response.description,
+            owner: response.owner.into(),
+            executor_type: response.executor_type.into(),
+            payload: response.payload,
+            public: response.public,
+            arguments: response.arguments,
+            inputs,
+            outputs,
+            tags: response.tags,
+            manifest: manifest_to_proto(response.manifest),
+            version: response.version,
+            default_version: response.default_version,
+            executor_enclave_id: response
+                .executor_enclave
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RollbackFunctionRequest> for RollbackFunctionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RollbackFunctionRequest) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        Ok(Self::new(function_id, proto.version))
+    }
+}
+
+impl From<RollbackFunctionRequest> for proto::RollbackFunctionRequest {
+    fn from(request: RollbackFunctionRequest) -> Self {
+        Self {
+            function_id: request.function_id.to_string(),
+            version: request.version,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RollbackFunctionResponse> for RollbackFunctionResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RollbackFunctionResponse) -> Result<Self> {
+        Ok(Self {
+            default_version: proto.default_version,
+        })
+    }
+}
+
+impl From<RollbackFunctionResponse> for proto::RollbackFunctionResponse {
+    fn from(response: RollbackFunctionResponse) -> Self {
+        Self {
+            default_version: response.default_version,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterExecutorEnclaveRequest>
+    for RegisterExecutorEnclaveRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterExecutorEnclaveRequest) -> Result<Self> {
+        let mr_enclave: MrEnclave = proto.mr_enclave.as_slice().try_into()?;
+        let mr_signer: MrSigner = proto.mr_signer.as_slice().try_into()?;
+        let measurement = EnclaveMeasurement::new(mr_enclave, mr_signer);
+        Ok(Self::new(proto.name, measurement))
+    }
+}
+
+impl From<RegisterExecutorEnclaveRequest> for proto::RegisterExecutorEnclaveRequest {
+    fn from(request: RegisterExecutorEnclaveRequest) -> Self {
+        Self {
+            name: request.name,
+            mr_enclave: request.measurement.mr_enclave.as_bytes().to_vec(),
+            mr_signer: request.measurement.mr_signer.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterExecutorEnclaveResponse>
+    for RegisterExecutorEnclaveResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterExecutorEnclaveResponse) -> Result<Self> {
+        let executor_enclave_id = proto.executor_enclave_id.try_into()?;
+        Ok(Self::new(executor_enclave_id))
+    }
+}
+
+impl From<RegisterExecutorEnclaveResponse> for proto::RegisterExecutorEnclaveResponse {
+    fn from(response: RegisterExecutorEnclaveResponse) -> Self {
+        Self {
+            executor_enclave_id: response.executor_enclave_id.to_string(),
+        }
+    }
+}
+
+// Manifests are carried as TOML, empty if there is none.
+fn manifest_from_proto(manifest: &str) -> Result<Option<FunctionManifest>> {
+    match manifest {
+        "" => Ok(None),
+        manifest => Ok(Some(FunctionManifest::from_toml(manifest)?)),
+    }
+}
+
+fn manifest_to_proto(manifest: Option<FunctionManifest>) -> String {
+    manifest
+        .and_then(|manifest| manifest.to_toml().ok())
+        .unwrap_or_default()
+}
+
+fn from_proto_ownership(proto: Vec<proto::OwnerList>) -> TaskFileOwners {
+    proto
+        .into_iter()
+        .map(|ol| (ol.data_name, ol.uids))
+        .collect()
+}
+
+fn to_proto_ownership(ownership: TaskFileOwners) -> Vec<proto::OwnerList> {
+    ownership
+        .into_iter()
+        .map(|(name, ol)| proto::OwnerList {
+            data_name: name,
+            uids: ol.into(),
+        })
+        .collect()
+}
+
+impl std::convert::TryFrom<proto::CreateTaskRequest> for CreateTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateTaskRequest) -> Result<Self> {
+        let function_arguments: FunctionArguments = proto.function_arguments.try_into()?;
+        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
+        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
+        let function_id = proto.function_id.try_into()?;
+        let executor = proto.executor.try_into()?;
+        let budget = TaskBudget {
+            staging_time: duration_from_ms(proto.staging_time_limit_ms),
+            execution_time: duration_from_ms(proto.execution_time_limit_ms),
+            output_size: Some(proto.output_size_limit).filter(|limit| *limit > 0),
+        };
+
+        let ret = Self {
+            function_id,
+            function_version: proto.function_version.try_into()?,
+            function_arguments,
+            executor,
+            inputs_ownership,
+            outputs_ownership,
+            budget,
+            env: proto.env,
+            priority: proto.priority.try_into()?,
+            affinity: TaskAffinity {
+                affinity: proto.affinity,
+                anti_affinity: proto.anti_affinity,
+            },
+            deterministic: proto.deterministic,
+        };
+        Ok(ret)
+    }
+}
+
+impl From<CreateTaskRequest> for proto::CreateTaskRequest {
+    fn from(request: CreateTaskRequest) -> Self {
+        let function_arguments = request.function_arguments.into_string();
+        let inputs_ownership = to_proto_ownership(request.inputs_ownership);
+        let outputs_ownership = to_proto_ownership(request.outputs_ownership);
+
+        Self {
+            function_id: request.function_id.to_string(),
+            function_arguments,
+            executor: request.executor.to_string(),
+            inputs_ownership,
+            outputs_ownership,
+            staging_time_limit_ms: duration_to_ms(request.budget.staging_time),
+            execution_time_limit_ms: duration_to_ms(request.budget.execution_time),
+            env: request.env,
+            priority: request.priority.to_string(),
+            function_version: request.function_version.to_string(),
+            output_size_limit: request.budget.output_size.unwrap_or_default(),
+            affinity: request.affinity.affinity,
+            anti_affinity: request.affinity.anti_affinity,
+            deterministic: request.deterministic,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateTaskResponse> for CreateTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateTaskResponse) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<CreateTaskResponse> for proto::CreateTaskResponse {
+    fn from(response: CreateTaskResponse) -> Self {
+        Self {
+            task_id: response.task_id.to_string(),
+        }
+    }
+}
+
+// Zero stands for no time limit in the proto messages.
+fn duration_from_ms(ms: u64) -> Option<Duration> {
+    match ms {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+fn duration_to_ms(duration: Option<Duration>) -> u64 {
+    duration.map(|d| d.as_millis() as u64).unwrap_or_default()
+}
+
+fn to_proto_file_ids(map: HashMap<String, ExternalID>) -> Vec<proto::DataMap> {
+    map.into_iter()
+        .map(|(name, ext_id)| proto::DataMap {
+            data_name: name,
+            data_id: ext_id.to_string(),
+        })
+        .collect()
+}
+
+fn from_proto_file_ids(vector: Vec<proto::DataMap>) -> Result<HashMap<String, ExternalID>> {
+    vector
+        .into_iter()
+        .map(|item| {
+            item.data_id
+                .clone()
+                .try_into()
+                .map(|ext_id| (item.data_name, ext_id))
+        })
+        .collect()
+}
+
+impl std::convert::TryFrom<proto::GetTaskRequest> for GetTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskRequest> for proto::GetTaskRequest {
+    fn from(request: GetTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTaskResponse> for GetTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskResponse) -> Result<Self> {
+        let function_arguments: FunctionArguments = proto.function_arguments.try_into()?;
+        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
+        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
+        let assigned_inputs = from_proto_file_ids(proto.assigned_inputs)?;
+        let assigned_outputs = from_proto_file_ids(proto.assigned_outputs)?;
+        let status = i32_to_task_status(proto.status)?;
+        let function_id = proto.function_id.try_into()?;
+        let task_id = proto.task_id.try_into()?;
+        let result = proto.result.try_into()?;
+
+        let ret = Self {
+            task_id,
+            creator: proto.creator.into(),
+            function_id,
+            function_owner: proto.function_owner.into(),
+            function_arguments,
+            inputs_ownership,
+            outputs_ownership,
+            participants: UserList::new(proto.participants),
+            approved_users: UserList::new(proto.approved_users),
+            assigned_inputs,
+            assigned_outputs,
+            env: proto.env,
+            priority: proto.priority.try_into()?,
+            status,
+            result,
+            attempts: proto.attempts,
+            last_error: Some(proto.last_error).filter(|error| !error.is_empty()),
+            preemptions: proto.preemptions,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskResponse> for proto::GetTaskResponse {
+    fn from(response: GetTaskResponse) -> Self {
+        let function_arguments = response.function_arguments.into_string();
+        let inputs_ownership = to_proto_ownership(response.inputs_ownership);
+        let outputs_ownership = to_proto_ownership(response.outputs_ownership);
+        let assigned_inputs = to_proto_file_ids(response.assigned_inputs);
+        let assigned_outputs = to_proto_file_ids(response.assigned_outputs);
+        let status = i32_from_task_status(response.status);
+        Self {
+            task_id: response.task_id.to_string(),
+            creator: response.creator.to_string(),
+            function_id: response.function_id.to_string(),
+            function_owner: response.function_owner.to_string(),
+            function_arguments,
+            inputs_ownership,
+            outputs_ownership,
+            participants: response.participants.into(),
+            approved_users: response.approved_users.into(),
+            assigned_inputs,
+            assigned_outputs,
+            env: response.env,
+            priority: response.priority.to_string(),
+            status,
+            result: Some(response.result.into()),
+            attempts: response.attempts,
+            last_error: response.last_error.unwrap_or_default(),
+            preemptions: response.preemptions,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTaskResultRequest> for GetTaskResultRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskResultRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self {
+            task_id,
+            offset: proto.offset,
+            length: proto.length,
+            export_cwt: proto.export_cwt,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskResultRequest> for proto::GetTaskResultRequest {
+    fn from(request: GetTaskResultRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            offset: request.offset,
+            length: request.length,
+            export_cwt: request.export_cwt,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTaskResultResponse> for GetTaskResultResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskResultResponse) -> Result<Self> {
+        let result = proto.result.try_into()?;
+        let ret = Self {
+            result,
+            return_value_len: proto.return_value_len,
+            return_value_hash: proto.return_value_hash,
+            cwt: proto.cwt,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskResultResponse> for proto::GetTaskResultResponse {
+    fn from(response: GetTaskResultResponse) -> Self {
+        Self {
+            result: Some(response.result.into()),
+            return_value_len: response.return_value_len,
+            return_value_hash: response.return_value_hash,
+            cwt: response.cwt,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTaskLogRequest> for GetTaskLogRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskLogRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self {
+            task_id,
+            offset: proto.offset,
+            limit: proto.limit,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskLogRequest> for proto::GetTaskLogRequest {
+    fn from(request: GetTaskLogRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            offset: request.offset,
+            limit: request.limit,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTaskLogResponse> for GetTaskLogResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskLogResponse) -> Result<Self> {
+        let ret = Self {
+            log: proto.log,
+            total_len: proto.total_len,
+            truncated: proto.truncated,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskLogResponse> for proto::GetTaskLogResponse {
+    fn from(response: GetTaskLogResponse) -> Self {
+        Self {
+            log: response.log,
+            total_len: response.total_len,
+            truncated: response.truncated,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::StreamTaskResultRequest> for StreamTaskResultRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::StreamTaskResultRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self {
+            task_id,
+            offset: proto.offset,
+            limit: proto.limit,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<StreamTaskResultRequest> for proto::StreamTaskResultRequest {
+    fn from(request: StreamTaskResultRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            offset: request.offset,
+            limit: request.limit,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::StreamTaskResultResponse> for StreamTaskResultResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::StreamTaskResultResponse) -> Result<Self> {
+        let ret = Self {
+            chunks: proto.chunks,
+            next_offset: proto.next_offset,
+            finished: proto.finished,
+            truncated: proto.truncated,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<StreamTaskResultResponse> for proto::StreamTaskResultResponse {
+    fn from(response: StreamTaskResultResponse) -> Self {
+        Self {
+            chunks: response.chunks,
+            next_offset: response.next_offset,
+            finished: response.finished,
+            truncated: response.truncated,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AssignDataRequest> for AssignDataRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::AssignDataRequest) -> Result<Self> {
+        let inputs = from_proto_file_ids(proto.inputs)?;
+        let outputs = from_proto_file_ids(proto.outputs)?;
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self {
+            task_id,
+            inputs,
+            outputs,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<AssignDataRequest> for proto::AssignDataRequest {
+    fn from(request: AssignDataRequest) -> Self {
+        let inputs = to_proto_file_ids(request.inputs);
+        let outputs = to_proto_file_ids(request.outputs);
+        Self {
+            task_id: request.task_id.to_string(),
+            inputs,
+            outputs,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AssignDataResponse> for AssignDataResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::AssignDataResponse) -> Result<Self> {
+        Ok(AssignDataResponse)
+    }
+}
+
+impl From<AssignDataResponse> for proto::AssignDataResponse {
+    fn from(_response: AssignDataResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::ApproveTaskRequest> for ApproveTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ApproveTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<ApproveTaskRequest> for proto::ApproveTaskRequest {
+    fn from(request: ApproveTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ApproveTaskResponse> for ApproveTaskResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::ApproveTaskResponse) -> Result<Self> {
+        Ok(ApproveTaskResponse)
+    }
+}
+
+impl From<ApproveTaskResponse> for proto::ApproveTaskResponse {
+    fn from(_response: ApproveTaskResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::InvokeTaskRequest> for InvokeTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::InvokeTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let retry_policy = proto.retry_policy.map(RetryPolicy::from);
+        let ret = Self {
+            task_id,
+            retry_policy,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<InvokeTaskRequest> for proto::InvokeTaskRequest {
+    fn from(request: InvokeTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            retry_policy: request.retry_policy.map(proto::RetryPolicy::from),
+        }
+    }
+}
+
+impl From<proto::RetryPolicy> for RetryPolicy {
+    fn from(proto: proto::RetryPolicy) -> Self {
+        let retry_on = if proto.any_failure {
+            RetryOn::AnyFailure
+        } else {
+            RetryOn::InfrastructureFailure
+        };
+        RetryPolicy::new(proto.max_attempts)
+            .backoff_secs(proto.backoff_secs)
+            .retry_on(retry_on)
+    }
+}
+
+impl From<RetryPolicy> for proto::RetryPolicy {
+    fn from(policy: RetryPolicy) -> Self {
+        Self {
+            max_attempts: policy.max_attempts,
+            backoff_secs: policy.backoff_secs,
+            any_failure: policy.retry_on == RetryOn::AnyFailure,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InvokeTaskResponse> for InvokeTaskResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::InvokeTaskResponse) -> Result<Self> {
+        Ok(InvokeTaskResponse)
+    }
+}
+
+impl From<InvokeTaskResponse> for proto::InvokeTaskResponse {
+    fn from(_response: InvokeTaskResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::TaskOverrides> for TaskOverrides {
+    type Error = Error;
+
+    fn try_from(proto: proto::TaskOverrides) -> Result<Self> {
+        let function_arguments = if proto.function_arguments.is_empty() {
+            FunctionArguments::default()
+        } else {
+            proto.function_arguments.try_into()?
+        };
+        Ok(Self {
+            function_arguments,
+            env: proto.env,
+        })
+    }
+}
+
+impl From<TaskOverrides> for proto::TaskOverrides {
+    fn from(overrides: TaskOverrides) -> Self {
+        Self {
+            function_arguments: overrides.function_arguments.into_string(),
+            env: overrides.env,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateTasksRequest> for CreateTasksRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateTasksRequest) -> Result<Self> {
+        let template = proto
+            .template
+            .ok_or_else(|| anyhow!("Missing template"))?
+            .try_into()?;
+        let overrides = proto
+            .overrides
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        Ok(Self::new(template, overrides))
+    }
+}
+
+impl From<CreateTasksRequest> for proto::CreateTasksRequest {
+    fn from(request: CreateTasksRequest) -> Self {
+        Self {
+            template: Some(request.template.into()),
+            overrides: request.overrides.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateTasksResponse> for CreateTasksResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateTasksResponse) -> Result<Self> {
+        let task_ids = proto
+            .task_ids
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        Ok(Self::new(task_ids))
+    }
+}
+
+impl From<CreateTasksResponse> for proto::CreateTasksResponse {
+    fn from(response: CreateTasksResponse) -> Self {
+        Self {
+            task_ids: response
+                .task_ids
+                .iter()
+                .map(ExternalID::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InvokeTasksRequest> for InvokeTasksRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::InvokeTasksRequest) -> Result<Self> {
+        let task_ids = proto
+            .task_ids
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        Ok(Self::new(task_ids))
+    }
+}
+
+impl From<InvokeTasksRequest> for proto::InvokeTasksRequest {
+    fn from(request: InvokeTasksRequest) -> Self {
+        Self {
+            task_ids: request.task_ids.iter().map(ExternalID::to_string).collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InvokeTasksResponse> for InvokeTasksResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::InvokeTasksResponse) -> Result<Self> {
+        let failures = proto
+            .failures
+            .into_iter()
+            .map(|failure| {
+                Ok(InvokeTaskFailure {
+                    task_id: failure.task_id.try_into()?,
+                    reason: failure.reason,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self::new(failures))
+    }
+}
+
+impl From<InvokeTasksResponse> for proto::InvokeTasksResponse {
+    fn from(response: InvokeTasksResponse) -> Self {
+        let failures = response
+            .failures
+            .into_iter()
+            .map(|failure| proto::InvokeTaskFailure {
+                task_id: failure.task_id.to_string(),
+                reason: failure.reason,
+            })
+            .collect();
+        Self { failures }
+    }
+}
+
+impl std::convert::TryFrom<proto::ReviewOutputRequest> for ReviewOutputRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ReviewOutputRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        Ok(Self::new(task_id, proto.approve))
+    }
+}
+
+impl From<ReviewOutputRequest> for proto::ReviewOutputRequest {
+    fn from(request: ReviewOutputRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            approve: request.approve,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ReviewOutputResponse> for ReviewOutputResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ReviewOutputResponse) -> Result<Self> {
+        let status = proto.status.as_str().try_into()?;
+        Ok(Self::new(status, proto.findings))
+    }
+}
+
+impl From<ReviewOutputResponse> for proto::ReviewOutputResponse {
+    fn from(response: ReviewOutputResponse) -> Self {
+        Self {
+            status: response.status.as_str().to_string(),
+            findings: response.findings,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetPlatformInfoRequest> for GetPlatformInfoRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::GetPlatformInfoRequest) -> Result<Self> {
+        Ok(GetPlatformInfoRequest)
+    }
+}
+
+impl From<GetPlatformInfoRequest> for proto::GetPlatformInfoRequest {
+    fn from(_request: GetPlatformInfoRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetPlatformInfoResponse> for GetPlatformInfoResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetPlatformInfoResponse) -> Result<Self> {
+        let executors = proto
+            .executors
+            .into_iter()
+            .map(|executor| executor.try_into())
+            .collect::<Result<_>>()?;
+        let ret = Self {
+            api_version: proto.api_version,
+            executors,
+            crypto_schemes: proto.crypto_schemes,
+            max_message_len: proto.max_message_len,
+            inline_data_max_size: proto.inline_data_max_size,
+            attestation_type: proto.attestation_type,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetPlatformInfoResponse> for proto::GetPlatformInfoResponse {
+    fn from(response: GetPlatformInfoResponse) -> Self {
+        Self {
+            api_version: response.api_version,
+            executors: response.executors.iter().map(|e| e.to_string()).collect(),
+            crypto_schemes: response.crypto_schemes,
+            max_message_len: response.max_message_len,
+            inline_data_max_size: response.inline_data_max_size,
+            attestation_type: response.attestation_type,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::EnterReadOnlyModeRequest> for EnterReadOnlyModeRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::EnterReadOnlyModeRequest) -> Result<Self> {
+        Ok(Self {
+            duration_secs: proto.duration_secs,
+            reason: proto.reason,
+        })
+    }
+}
+
+impl From<EnterReadOnlyModeRequest> for proto::EnterReadOnlyModeRequest {
+    fn from(request: EnterReadOnlyModeRequest) -> Self {
+        Self {
+            duration_secs: request.duration_secs,
+            reason: request.reason,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::EnterReadOnlyModeResponse> for EnterReadOnlyModeResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::EnterReadOnlyModeResponse) -> Result<Self> {
+        Ok(Self {
+            expires_at: proto.expires_at,
+        })
+    }
+}
+
+impl From<EnterReadOnlyModeResponse> for proto::EnterReadOnlyModeResponse {
+    fn from(response: EnterReadOnlyModeResponse) -> Self {
+        Self {
+            expires_at: response.expires_at,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExitReadOnlyModeRequest> for ExitReadOnlyModeRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::ExitReadOnlyModeRequest) -> Result<Self> {
+        Ok(ExitReadOnlyModeRequest)
+    }
+}
+
+impl From<ExitReadOnlyModeRequest> for proto::ExitReadOnlyModeRequest {
+    fn from(_request: ExitReadOnlyModeRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::ExitReadOnlyModeResponse> for ExitReadOnlyModeResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::ExitReadOnlyModeResponse) -> Result<Self> {
+        Ok(ExitReadOnlyModeResponse)
+    }
+}
+
+impl From<ExitReadOnlyModeResponse> for proto::ExitReadOnlyModeResponse {
+    fn from(_response: ExitReadOnlyModeResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::MeasurementLogEntry> for MeasurementLogEntry {
+    type Error = Error;
+
+    fn try_from(proto: proto::MeasurementLogEntry) -> Result<Self> {
+        let mr_enclave: MrEnclave = proto.mr_enclave.as_slice().try_into()?;
+        let mr_signer: MrSigner = proto.mr_signer.as_slice().try_into()?;
+        let measurement = EnclaveMeasurement::new(mr_enclave, mr_signer);
+        Ok(MeasurementLogEntry::new(proto.service_name, measurement))
+    }
+}
+
+impl From<MeasurementLogEntry> for proto::MeasurementLogEntry {
+    fn from(entry: MeasurementLogEntry) -> Self {
+        Self {
+            service_name: entry.service_name,
+            mr_enclave: entry.measurement.mr_enclave.as_bytes().to_vec(),
+            mr_signer: entry.measurement.mr_signer.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::SignedTreeHead> for SignedTreeHead {
+    type Error = Error;
+
+    fn try_from(proto: proto::SignedTreeHead) -> Result<Self> {
+        Ok(SignedTreeHead {
+            tree_size: proto.tree_size,
+            root_hash: proto.root_hash.as_slice().try_into()?,
+            signature: proto.signature.as_slice().try_into()?,
+        })
+    }
+}
+
+impl From<SignedTreeHead> for proto::SignedTreeHead {
+    fn from(tree_head: SignedTreeHead) -> Self {
+        Self {
+            tree_size: tree_head.tree_size,
+            root_hash: tree_head.root_hash.as_bytes().to_vec(),
+            signature: tree_head.signature.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetMeasurementInclusionRequest>
+    for GetMeasurementInclusionRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::GetMeasurementInclusionRequest) -> Result<Self> {
+        let entry = proto
+            .entry
+            .ok_or_else(|| anyhow!("missing entry"))?
+            .try_into()?;
+        Ok(GetMeasurementInclusionRequest { entry })
+    }
+}
+
+impl From<GetMeasurementInclusionRequest> for proto::GetMeasurementInclusionRequest {
+    fn from(request: GetMeasurementInclusionRequest) -> Self {
+        Self {
+            entry: Some(request.entry.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetMeasurementInclusionResponse>
+    for GetMeasurementInclusionResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::GetMeasurementInclusionResponse) -> Result<Self> {
+        let audit_path: Vec<LogHash> = proto
+            .audit_path
+            .iter()
+            .map(|hash| hash.as_slice().try_into())
+            .collect::<Result<_>>()?;
+        let signed_tree_head = proto
+            .signed_tree_head
+            .ok_or_else(|| anyhow!("missing signed_tree_head"))?
+            .try_into()?;
+        Ok(GetMeasurementInclusionResponse {
+            proof: InclusionProof {
+                leaf_index: proto.leaf_index,
+                audit_path,
+            },
+            signed_tree_head,
+        })
+    }
+}
+
+impl From<GetMeasurementInclusionResponse> for proto::GetMeasurementInclusionResponse {
+    fn from(response: GetMeasurementInclusionResponse) -> Self {
+        Self {
+            leaf_index: response.proof.leaf_index,
+            audit_path: response
+                .proof
+                .audit_path
+                .iter()
+                .map(|hash| hash.as_bytes().to_vec())
+                .collect(),
+            signed_tree_head: Some(response.signed_tree_head.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UpdateAccessControlPolicyRequest>
+    for UpdateAccessControlPolicyRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::UpdateAccessControlPolicyRequest) -> Result<Self> {
+        Ok(Self {
+            bundle: proto.bundle,
+            signature: proto.signature,
         })
     }
 }
 
-impl From<RegisterInputFileRequest> for proto::RegisterInputFileRequest {
-    fn from(request: RegisterInputFileRequest) -> Self {
+impl From<UpdateAccessControlPolicyRequest> for proto::UpdateAccessControlPolicyRequest {
+    fn from(request: UpdateAccessControlPolicyRequest) -> Self {
         Self {
-            url: request.url.into_string(),
-            cmac: request.cmac.to_bytes(),
-            crypto_info: Some(request.crypto_info.into()),
+            bundle: request.bundle,
+            signature: request.signature,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::UpdateInputFileRequest> for UpdateInputFileRequest {
+impl std::convert::TryFrom<proto::UpdateAccessControlPolicyResponse>
+    for UpdateAccessControlPolicyResponse
+{
     type Error = Error;
 
-    fn try_from(proto: proto::UpdateInputFileRequest) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        let url = Url::parse(&proto.url)?;
+    fn try_from(proto: proto::UpdateAccessControlPolicyResponse) -> Result<Self> {
+        Ok(Self {
+            version: proto.version,
+        })
+    }
+}
 
-        Ok(UpdateInputFileRequest { data_id, url })
+impl From<UpdateAccessControlPolicyResponse> for proto::UpdateAccessControlPolicyResponse {
+    fn from(response: UpdateAccessControlPolicyResponse) -> Self {
+        Self {
+            version: response.version,
+        }
     }
 }
 
-impl From<UpdateInputFileRequest> for proto::UpdateInputFileRequest {
-    fn from(request: UpdateInputFileRequest) -> Self {
+impl std::convert::TryFrom<proto::RollbackAccessControlPolicyRequest>
+    for RollbackAccessControlPolicyRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RollbackAccessControlPolicyRequest) -> Result<Self> {
+        Ok(Self {
+            version: proto.version,
+        })
+    }
+}
+
+impl From<RollbackAccessControlPolicyRequest> for proto::RollbackAccessControlPolicyRequest {
+    fn from(request: RollbackAccessControlPolicyRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
-            url: request.url.into_string(),
+            version: request.version,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterInputFileResponse> for RegisterInputFileResponse {
+impl std::convert::TryFrom<proto::RollbackAccessControlPolicyResponse>
+    for RollbackAccessControlPolicyResponse
+{
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterInputFileResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(_proto: proto::RollbackAccessControlPolicyResponse) -> Result<Self> {
+        Ok(RollbackAccessControlPolicyResponse)
     }
 }
 
-impl From<RegisterInputFileResponse> for proto::RegisterInputFileResponse {
-    fn from(request: RegisterInputFileResponse) -> Self {
+impl From<RollbackAccessControlPolicyResponse> for proto::RollbackAccessControlPolicyResponse {
+    fn from(_response: RollbackAccessControlPolicyResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetAccessControlPolicyRequest> for GetAccessControlPolicyRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::GetAccessControlPolicyRequest) -> Result<Self> {
+        Ok(GetAccessControlPolicyRequest)
+    }
+}
+
+impl From<GetAccessControlPolicyRequest> for proto::GetAccessControlPolicyRequest {
+    fn from(_request: GetAccessControlPolicyRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetAccessControlPolicyResponse>
+    for GetAccessControlPolicyResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::GetAccessControlPolicyResponse) -> Result<Self> {
+        Ok(Self {
+            version: proto.version,
+            retained_versions: proto.retained_versions,
+        })
+    }
+}
+
+impl From<GetAccessControlPolicyResponse> for proto::GetAccessControlPolicyResponse {
+    fn from(response: GetAccessControlPolicyResponse) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            version: response.version,
+            retained_versions: response.retained_versions,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::UpdateInputFileResponse> for UpdateInputFileResponse {
+impl std::convert::TryFrom<proto::ExplainAccessRequest> for ExplainAccessRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::UpdateInputFileResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(proto: proto::ExplainAccessRequest) -> Result<Self> {
+        Ok(Self {
+            user_id: proto.user_id.into(),
+            object_id: proto.object_id.try_into()?,
+        })
     }
 }
 
-impl From<UpdateInputFileResponse> for proto::UpdateInputFileResponse {
-    fn from(request: UpdateInputFileResponse) -> Self {
+impl From<ExplainAccessRequest> for proto::ExplainAccessRequest {
+    fn from(request: ExplainAccessRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            user_id: request.user_id.into(),
+            object_id: request.object_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterOutputFileRequest> for RegisterOutputFileRequest {
+impl std::convert::TryFrom<proto::ExplainAccessResponse> for ExplainAccessResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterOutputFileRequest) -> Result<Self> {
-        let ret = Self {
-            url: Url::parse(&proto.url)?,
-            crypto_info: proto
-                .crypto_info
-                .ok_or_else(|| anyhow!("missing crypto_info"))?
-                .try_into()?,
+    fn try_from(proto: proto::ExplainAccessResponse) -> Result<Self> {
+        Ok(Self {
+            accept: proto.accept,
+            explanation: from_proto_decisions(proto.explanation),
+        })
+    }
+}
+
+impl From<ExplainAccessResponse> for proto::ExplainAccessResponse {
+    fn from(response: ExplainAccessResponse) -> Self {
+        Self {
+            accept: response.accept,
+            explanation: to_proto_decisions(response.explanation),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::TransferOwnershipRequest> for TransferOwnershipRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::TransferOwnershipRequest) -> Result<Self> {
+        let filter = proto
+            .object_filter
+            .ok_or_else(|| anyhow!("missing object_filter"))?;
+        let ids = filter
+            .ids
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<ExternalID>>>()?;
+        Ok(Self {
+            from_user: proto.from_user.into(),
+            to_user: proto.to_user.into(),
+            object_filter: ObjectFilter::new().kinds(filter.kinds).ids(ids),
+        })
+    }
+}
+
+impl From<TransferOwnershipRequest> for proto::TransferOwnershipRequest {
+    fn from(request: TransferOwnershipRequest) -> Self {
+        let filter = request.object_filter;
+        Self {
+            from_user: request.from_user.into(),
+            to_user: request.to_user.into(),
+            object_filter: Some(proto::ObjectFilter {
+                kinds: filter.kinds,
+                ids: filter.ids.iter().map(ExternalID::to_string).collect(),
+            }),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::TransferOwnershipResponse> for TransferOwnershipResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::TransferOwnershipResponse) -> Result<Self> {
+        let object_ids = proto
+            .object_ids
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<ExternalID>>>()?;
+        Ok(Self {
+            object_ids,
+            collaborators: proto.collaborators.into_iter().map(UserID::from).collect(),
+        })
+    }
+}
+
+impl From<TransferOwnershipResponse> for proto::TransferOwnershipResponse {
+    fn from(response: TransferOwnershipResponse) -> Self {
+        Self {
+            object_ids: response
+                .object_ids
+                .iter()
+                .map(ExternalID::to_string)
+                .collect(),
+            collaborators: response
+                .collaborators
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<proto::UserQuota> for UserQuota {
+    fn from(proto: proto::UserQuota) -> Self {
+        UserQuota::new(
+            proto.max_concurrent_tasks,
+            proto.max_tasks_per_day,
+            proto.max_input_bytes,
+        )
+    }
+}
+
+impl From<UserQuota> for proto::UserQuota {
+    fn from(quota: UserQuota) -> Self {
+        Self {
+            max_concurrent_tasks: quota.max_concurrent_tasks,
+            max_tasks_per_day: quota.max_tasks_per_day,
+            max_input_bytes: quota.max_input_bytes,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::SetUserQuotaRequest> for SetUserQuotaRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::SetUserQuotaRequest) -> Result<Self> {
+        let quota = proto.quota.ok_or_else(|| anyhow!("missing quota"))?;
+        Ok(Self::new(proto.user_id, quota.into()))
+    }
+}
+
+impl From<SetUserQuotaRequest> for proto::SetUserQuotaRequest {
+    fn from(request: SetUserQuotaRequest) -> Self {
+        Self {
+            user_id: request.user_id.into(),
+            quota: Some(request.quota.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::SetUserQuotaResponse> for SetUserQuotaResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::SetUserQuotaResponse) -> Result<Self> {
+        Ok(SetUserQuotaResponse)
+    }
+}
+
+impl From<SetUserQuotaResponse> for proto::SetUserQuotaResponse {
+    fn from(_response: SetUserQuotaResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetQuotaUsageRequest> for GetQuotaUsageRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetQuotaUsageRequest) -> Result<Self> {
+        let user_id = if proto.user_id.is_empty() {
+            None
+        } else {
+            Some(proto.user_id.into())
         };
+        Ok(Self { user_id })
+    }
+}
 
-        Ok(ret)
+impl From<GetQuotaUsageRequest> for proto::GetQuotaUsageRequest {
+    fn from(request: GetQuotaUsageRequest) -> Self {
+        Self {
+            user_id: request.user_id.map(String::from).unwrap_or_default(),
+        }
     }
 }
 
-impl From<RegisterOutputFileRequest> for proto::RegisterOutputFileRequest {
-    fn from(request: RegisterOutputFileRequest) -> Self {
+impl std::convert::TryFrom<proto::GetQuotaUsageResponse> for GetQuotaUsageResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetQuotaUsageResponse) -> Result<Self> {
+        let quota = proto.quota.ok_or_else(|| anyhow!("missing quota"))?;
+        let usage = proto.usage.ok_or_else(|| anyhow!("missing usage"))?;
+        let usage = QuotaUsage {
+            concurrent_tasks: usage.concurrent_tasks,
+            tasks_today: usage.tasks_today,
+            input_bytes: usage.input_bytes,
+        };
+        Ok(Self::new(quota.into(), usage))
+    }
+}
+
+impl From<GetQuotaUsageResponse> for proto::GetQuotaUsageResponse {
+    fn from(response: GetQuotaUsageResponse) -> Self {
+        let usage = response.usage;
         Self {
-            url: request.url.into_string(),
-            crypto_info: Some(request.crypto_info.into()),
+            quota: Some(response.quota.into()),
+            usage: Some(proto::QuotaUsage {
+                concurrent_tasks: usage.concurrent_tasks,
+                tasks_today: usage.tasks_today,
+                input_bytes: usage.input_bytes,
+            }),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::TenantStats> for TenantStats {
+    type Error = Error;
+
+    fn try_from(proto: proto::TenantStats) -> Result<Self> {
+        let quota = proto.quota.ok_or_else(|| anyhow!("missing quota"))?;
+        let usage = proto.usage.ok_or_else(|| anyhow!("missing usage"))?;
+        Ok(Self {
+            user_id: proto.user_id.into(),
+            tasks_run: proto.tasks_run,
+            tasks_succeeded: proto.tasks_succeeded,
+            average_latency_ms: proto.average_latency_ms,
+            data_processed_bytes: proto.data_processed_bytes,
+            quota: quota.into(),
+            usage: QuotaUsage {
+                concurrent_tasks: usage.concurrent_tasks,
+                tasks_today: usage.tasks_today,
+                input_bytes: usage.input_bytes,
+            },
+        })
+    }
+}
+
+impl From<TenantStats> for proto::TenantStats {
+    fn from(stats: TenantStats) -> Self {
+        let usage = stats.usage;
+        Self {
+            user_id: stats.user_id.into(),
+            tasks_run: stats.tasks_run,
+            tasks_succeeded: stats.tasks_succeeded,
+            average_latency_ms: stats.average_latency_ms,
+            data_processed_bytes: stats.data_processed_bytes,
+            quota: Some(stats.quota.into()),
+            usage: Some(proto::QuotaUsage {
+                concurrent_tasks: usage.concurrent_tasks,
+                tasks_today: usage.tasks_today,
+                input_bytes: usage.input_bytes,
+            }),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTenantStatsRequest> for GetTenantStatsRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTenantStatsRequest) -> Result<Self> {
+        let user_id = if proto.user_id.is_empty() {
+            None
+        } else {
+            Some(proto.user_id.into())
+        };
+        Ok(Self {
+            window_secs: proto.window_secs,
+            user_id,
+        })
+    }
+}
+
+impl From<GetTenantStatsRequest> for proto::GetTenantStatsRequest {
+    fn from(request: GetTenantStatsRequest) -> Self {
+        Self {
+            window_secs: request.window_secs,
+            user_id: request.user_id.map(String::from).unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTenantStatsResponse> for GetTenantStatsResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTenantStatsResponse) -> Result<Self> {
+        let stats = proto
+            .stats
+            .into_iter()
+            .map(TenantStats::try_from)
+            .collect::<Result<_>>()?;
+        Ok(Self::new(stats))
+    }
+}
+
+impl From<GetTenantStatsResponse> for proto::GetTenantStatsResponse {
+    fn from(response: GetTenantStatsResponse) -> Self {
+        Self {
+            stats: response.stats.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExecutorNode> for ExecutorNode {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExecutorNode) -> Result<Self> {
+        let capacity = NodeCapacity {
+            cpus: proto.cpus,
+            memory_mb: proto.memory_mb,
+            slots: proto.slots,
+        };
+        Ok(Self {
+            worker_id: proto.worker_id,
+            capacity,
+            running: proto.running,
+            registered_at: proto.registered_at,
+            last_seen: proto.last_seen,
+            labels: proto.labels,
+            executors: proto.executors,
+            builtin_functions: proto.builtin_functions,
+        })
+    }
+}
+
+impl From<ExecutorNode> for proto::ExecutorNode {
+    fn from(node: ExecutorNode) -> Self {
+        Self {
+            worker_id: node.worker_id,
+            cpus: node.capacity.cpus,
+            memory_mb: node.capacity.memory_mb,
+            slots: node.capacity.slots,
+            running: node.running,
+            registered_at: node.registered_at,
+            last_seen: node.last_seen,
+            labels: node.labels,
+            executors: node.executors,
+            builtin_functions: node.builtin_functions,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListNodesRequest> for ListNodesRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::ListNodesRequest) -> Result<Self> {
+        Ok(Self::new())
+    }
+}
+
+impl From<ListNodesRequest> for proto::ListNodesRequest {
+    fn from(_request: ListNodesRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::ListNodesResponse> for ListNodesResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListNodesResponse) -> Result<Self> {
+        let nodes = proto
+            .nodes
+            .into_iter()
+            .map(ExecutorNode::try_from)
+            .collect::<Result<_>>()?;
+        Ok(Self::new(nodes, proto.updated_at))
+    }
+}
+
+impl From<ListNodesResponse> for proto::ListNodesResponse {
+    fn from(response: ListNodesResponse) -> Self {
+        Self {
+            nodes: response.nodes.into_iter().map(Into::into).collect(),
+            updated_at: response.updated_at,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::NodeExecutors> for NodeExecutors {
+    type Error = Error;
+
+    fn try_from(proto: proto::NodeExecutors) -> Result<Self> {
+        Ok(Self {
+            worker_id: proto.worker_id,
+            labels: proto.labels,
+            executors: proto.executors,
+            builtin_functions: proto.builtin_functions,
+        })
+    }
+}
+
+impl From<NodeExecutors> for proto::NodeExecutors {
+    fn from(node: NodeExecutors) -> Self {
+        Self {
+            worker_id: node.worker_id,
+            labels: node.labels,
+            executors: node.executors,
+            builtin_functions: node.builtin_functions,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::UpdateOutputFileRequest> for UpdateOutputFileRequest {
+impl std::convert::TryFrom<proto::ListExecutorsRequest> for ListExecutorsRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::ListExecutorsRequest) -> Result<Self> {
+        Ok(Self::new())
+    }
+}
+
+impl From<ListExecutorsRequest> for proto::ListExecutorsRequest {
+    fn from(_request: ListExecutorsRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::ListExecutorsResponse> for ListExecutorsResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::UpdateOutputFileRequest) -> Result<Self> {
-        let ret = Self {
-            data_id: proto.data_id.try_into()?,
-            url: Url::parse(&proto.url)?,
-        };
-
-        Ok(ret)
+    fn try_from(proto: proto::ListExecutorsResponse) -> Result<Self> {
+        let nodes = proto
+            .nodes
+            .into_iter()
+            .map(NodeExecutors::try_from)
+            .collect::<Result<_>>()?;
+        Ok(Self::new(nodes, proto.updated_at))
     }
 }
 
-impl From<UpdateOutputFileRequest> for proto::UpdateOutputFileRequest {
-    fn from(request: UpdateOutputFileRequest) -> Self {
+impl From<ListExecutorsResponse> for proto::ListExecutorsResponse {
+    fn from(response: ListExecutorsResponse) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
-            url: request.url.into_string(),
+            nodes: response.nodes.into_iter().map(Into::into).collect(),
+            updated_at: response.updated_at,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterOutputFileResponse> for RegisterOutputFileResponse {
+impl std::convert::TryFrom<proto::CreateScheduledTaskRequest> for CreateScheduledTaskRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterOutputFileResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(proto: proto::CreateScheduledTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let schedule = TaskSchedule::parse(&proto.schedule)?;
+        Ok(Self::new(task_id, schedule))
     }
 }
 
-impl From<RegisterOutputFileResponse> for proto::RegisterOutputFileResponse {
-    fn from(request: RegisterOutputFileResponse) -> Self {
+impl From<CreateScheduledTaskRequest> for proto::CreateScheduledTaskRequest {
+    fn from(request: CreateScheduledTaskRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            task_id: request.task_id.to_string(),
+            schedule: request.schedule.into(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::UpdateOutputFileResponse> for UpdateOutputFileResponse {
+impl std::convert::TryFrom<proto::CreateScheduledTaskResponse> for CreateScheduledTaskResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::UpdateOutputFileResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(proto: proto::CreateScheduledTaskResponse) -> Result<Self> {
+        let scheduled_task_id = proto.scheduled_task_id.try_into()?;
+        Ok(Self::new(scheduled_task_id))
     }
 }
 
-impl From<UpdateOutputFileResponse> for proto::UpdateOutputFileResponse {
-    fn from(request: UpdateOutputFileResponse) -> Self {
+impl From<CreateScheduledTaskResponse> for proto::CreateScheduledTaskResponse {
+    fn from(response: CreateScheduledTaskResponse) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            scheduled_task_id: response.scheduled_task_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFusionOutputRequest> for RegisterFusionOutputRequest {
+impl std::convert::TryFrom<proto::PauseScheduledTaskRequest> for PauseScheduledTaskRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterFusionOutputRequest) -> Result<Self> {
-        let ret = Self {
-            owner_list: OwnerList::new(proto.owner_list),
-        };
-
-        Ok(ret)
+    fn try_from(proto: proto::PauseScheduledTaskRequest) -> Result<Self> {
+        let scheduled_task_id = proto.scheduled_task_id.try_into()?;
+        Ok(Self::new(scheduled_task_id))
     }
 }
 
-impl From<RegisterFusionOutputRequest> for proto::RegisterFusionOutputRequest {
-    fn from(request: RegisterFusionOutputRequest) -> Self {
+impl From<PauseScheduledTaskRequest> for proto::PauseScheduledTaskRequest {
+    fn from(request: PauseScheduledTaskRequest) -> Self {
         Self {
-            owner_list: request.owner_list.into(),
+            scheduled_task_id: request.scheduled_task_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFusionOutputResponse> for RegisterFusionOutputResponse {
+impl std::convert::TryFrom<proto::PauseScheduledTaskResponse> for PauseScheduledTaskResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterFusionOutputResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(_proto: proto::PauseScheduledTaskResponse) -> Result<Self> {
+        Ok(PauseScheduledTaskResponse)
     }
 }
 
-impl From<RegisterFusionOutputResponse> for proto::RegisterFusionOutputResponse {
-    fn from(request: RegisterFusionOutputResponse) -> Self {
-        Self {
-            data_id: request.data_id.to_string(),
-        }
+impl From<PauseScheduledTaskResponse> for proto::PauseScheduledTaskResponse {
+    fn from(_response: PauseScheduledTaskResponse) -> Self {
+        Self {}
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterInputFromOutputRequest>
-    for RegisterInputFromOutputRequest
-{
+impl std::convert::TryFrom<proto::ResumeScheduledTaskRequest> for ResumeScheduledTaskRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterInputFromOutputRequest) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        let ret = Self { data_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::ResumeScheduledTaskRequest) -> Result<Self> {
+        let scheduled_task_id = proto.scheduled_task_id.try_into()?;
+        Ok(Self::new(scheduled_task_id))
     }
 }
 
-impl From<RegisterInputFromOutputRequest> for proto::RegisterInputFromOutputRequest {
-    fn from(request: RegisterInputFromOutputRequest) -> Self {
+impl From<ResumeScheduledTaskRequest> for proto::ResumeScheduledTaskRequest {
+    fn from(request: ResumeScheduledTaskRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            scheduled_task_id: request.scheduled_task_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterInputFromOutputResponse>
-    for RegisterInputFromOutputResponse
-{
+impl std::convert::TryFrom<proto::ResumeScheduledTaskResponse> for ResumeScheduledTaskResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterInputFromOutputResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(_proto: proto::ResumeScheduledTaskResponse) -> Result<Self> {
+        Ok(ResumeScheduledTaskResponse)
     }
 }
 
-impl From<RegisterInputFromOutputResponse> for proto::RegisterInputFromOutputResponse {
-    fn from(request: RegisterInputFromOutputResponse) -> Self {
-        Self {
-            data_id: request.data_id.to_string(),
-        }
+impl From<ResumeScheduledTaskResponse> for proto::ResumeScheduledTaskResponse {
+    fn from(_response: ResumeScheduledTaskResponse) -> Self {
+        Self {}
     }
 }
 
-impl std::convert::TryFrom<proto::GetInputFileRequest> for GetInputFileRequest {
+impl std::convert::TryFrom<proto::ListUpcomingRunsRequest> for ListUpcomingRunsRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::GetInputFileRequest) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        let ret = Self { data_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::ListUpcomingRunsRequest) -> Result<Self> {
+        let scheduled_task_id = proto.scheduled_task_id.try_into()?;
+        Ok(Self::new(scheduled_task_id, proto.count))
     }
 }
 
-impl From<GetInputFileRequest> for proto::GetInputFileRequest {
-    fn from(request: GetInputFileRequest) -> Self {
+impl From<ListUpcomingRunsRequest> for proto::ListUpcomingRunsRequest {
+    fn from(request: ListUpcomingRunsRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            scheduled_task_id: request.scheduled_task_id.to_string(),
+            count: request.count,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetInputFileResponse> for GetInputFileResponse {
+impl std::convert::TryFrom<proto::ListUpcomingRunsResponse> for ListUpcomingRunsResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::GetInputFileResponse) -> Result<Self> {
+    fn try_from(proto: proto::ListUpcomingRunsResponse) -> Result<Self> {
+        let last_task_id = match proto.last_task_id.as_str() {
+            "" => None,
+            id => Some(id.try_into()?),
+        };
         Ok(Self {
-            owner: OwnerList::new(proto.owner),
-            cmac: FileAuthTag::from_bytes(&proto.cmac)?,
+            schedule: TaskSchedule::parse(&proto.schedule)?,
+            paused: proto.paused,
+            run_times: proto.run_times,
+            runs: proto.runs,
+            last_task_id,
         })
     }
 }
 
-impl From<GetInputFileResponse> for proto::GetInputFileResponse {
-    fn from(request: GetInputFileResponse) -> Self {
+impl From<ListUpcomingRunsResponse> for proto::ListUpcomingRunsResponse {
+    fn from(response: ListUpcomingRunsResponse) -> Self {
         Self {
-            owner: request.owner.into(),
-            cmac: request.cmac.to_bytes(),
+            schedule: response.schedule.into(),
+            paused: response.paused,
+            run_times: response.run_times,
+            runs: response.runs,
+            last_task_id: response
+                .last_task_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetOutputFileRequest> for GetOutputFileRequest {
+impl std::convert::TryFrom<proto::PipelineLink> for PipelineLink {
     type Error = Error;
 
-    fn try_from(proto: proto::GetOutputFileRequest) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        let ret = Self { data_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::PipelineLink) -> Result<Self> {
+        Ok(Self::new(
+            proto.from_task_id.try_into()?,
+            proto.output,
+            proto.to_task_id.try_into()?,
+            proto.input,
+        ))
     }
 }
 
-impl From<GetOutputFileRequest> for proto::GetOutputFileRequest {
-    fn from(request: GetOutputFileRequest) -> Self {
+impl From<PipelineLink> for proto::PipelineLink {
+    fn from(link: PipelineLink) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            from_task_id: link.from_task.to_string(),
+            output: link.output,
+            to_task_id: link.to_task.to_string(),
+            input: link.input,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetOutputFileResponse> for GetOutputFileResponse {
+impl std::convert::TryFrom<proto::CreatePipelineRequest> for CreatePipelineRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::GetOutputFileResponse) -> Result<Self> {
-        let cmac = {
-            if proto.cmac.is_empty() {
-                None
-            } else {
-                Some(FileAuthTag::from_bytes(&proto.cmac)?)
-            }
-        };
-
-        Ok(Self {
-            owner: OwnerList::new(proto.owner),
-            cmac,
-        })
+    fn try_from(proto: proto::CreatePipelineRequest) -> Result<Self> {
+        let task_ids = proto
+            .task_ids
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<ExternalID>>>()?;
+        let links = proto
+            .links
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        Ok(Self::new(task_ids, links))
     }
 }
 
-impl From<GetOutputFileResponse> for proto::GetOutputFileResponse {
-    fn from(request: GetOutputFileResponse) -> Self {
+impl From<CreatePipelineRequest> for proto::CreatePipelineRequest {
+    fn from(request: CreatePipelineRequest) -> Self {
         Self {
-            owner: request.owner.into(),
-            cmac: request.cmac.map_or_else(Vec::new, |cmac| cmac.to_bytes()),
+            task_ids: request.task_ids.iter().map(ExternalID::to_string).collect(),
+            links: request.links.into_iter().map(Into::into).collect(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::FunctionInput> for FunctionInput {
+impl std::convert::TryFrom<proto::CreatePipelineResponse> for CreatePipelineResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::FunctionInput) -> Result<Self> {
-        let ret = Self {
-            name: proto.name,
-            description: proto.description,
-        };
-
-        Ok(ret)
+    fn try_from(proto: proto::CreatePipelineResponse) -> Result<Self> {
+        let pipeline_id = proto.pipeline_id.try_into()?;
+        Ok(Self::new(pipeline_id))
     }
 }
 
-impl From<FunctionInput> for proto::FunctionInput {
-    fn from(input: FunctionInput) -> Self {
+impl From<CreatePipelineResponse> for proto::CreatePipelineResponse {
+    fn from(response: CreatePipelineResponse) -> Self {
         Self {
-            name: input.name,
-            description: input.description,
+            pipeline_id: response.pipeline_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::FunctionOutput> for FunctionOutput {
+impl std::convert::TryFrom<proto::GetPipelineRequest> for GetPipelineRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::FunctionOutput) -> Result<Self> {
-        let ret = Self {
-            name: proto.name,
-            description: proto.description,
-        };
-
-        Ok(ret)
+    fn try_from(proto: proto::GetPipelineRequest) -> Result<Self> {
+        let pipeline_id = proto.pipeline_id.try_into()?;
+        Ok(Self::new(pipeline_id))
     }
 }
 
-impl From<FunctionOutput> for proto::FunctionOutput {
-    fn from(output: FunctionOutput) -> Self {
+impl From<GetPipelineRequest> for proto::GetPipelineRequest {
+    fn from(request: GetPipelineRequest) -> Self {
         Self {
-            name: output.name,
-            description: output.description,
+            pipeline_id: request.pipeline_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFunctionRequest> for RegisterFunctionRequest {
+impl std::convert::TryFrom<proto::GetPipelineResponse> for GetPipelineResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterFunctionRequest) -> Result<Self> {
-        let inputs: Result<Vec<FunctionInput>> = proto
-            .inputs
+    fn try_from(proto: proto::GetPipelineResponse) -> Result<Self> {
+        let task_ids = proto
+            .task_ids
             .into_iter()
-            .map(FunctionInput::try_from)
-            .collect();
-        let outputs: Result<Vec<FunctionOutput>> = proto
-            .outputs
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<ExternalID>>>()?;
+        let links = proto
+            .links
             .into_iter()
-            .map(FunctionOutput::try_from)
-            .collect();
-        let executor_type = proto.executor_type.try_into()?;
-
-        let ret = Self {
-            name: proto.name,
-            description: proto.description,
-            executor_type,
-            payload: proto.payload,
-            public: proto.public,
-            arguments: proto.arguments,
-            inputs: inputs?,
-            outputs: outputs?,
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        let failed_task_id = match proto.failed_task_id.as_str() {
+            "" => None,
+            id => Some(id.try_into()?),
         };
-        Ok(ret)
+        Ok(Self {
+            creator: proto.creator.into(),
+            task_ids,
+            links,
+            status: proto.status.as_str().try_into()?,
+            failed_task_id,
+        })
     }
 }
 
-impl From<RegisterFunctionRequest> for proto::RegisterFunctionRequest {
-    fn from(request: RegisterFunctionRequest) -> Self {
-        let inputs: Vec<proto::FunctionInput> = request
-            .inputs
-            .into_iter()
-            .map(proto::FunctionInput::from)
-            .collect();
-        let outputs: Vec<proto::FunctionOutput> = request
-            .outputs
-            .into_iter()
-            .map(proto::FunctionOutput::from)
-            .collect();
+impl From<GetPipelineResponse> for proto::GetPipelineResponse {
+    fn from(response: GetPipelineResponse) -> Self {
+        Self {
+            creator: response.creator.into(),
+            task_ids: response
+                .task_ids
+                .iter()
+                .map(ExternalID::to_string)
+                .collect(),
+            links: response.links.into_iter().map(Into::into).collect(),
+            status: response.status.to_string(),
+            failed_task_id: response
+                .failed_task_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
 
+impl From<proto::ListOptions> for ListOptions {
+    fn from(proto: proto::ListOptions) -> Self {
+        let non_zero = |secs| match secs {
+            0 => None,
+            secs => Some(secs),
+        };
         Self {
-            name: request.name,
-            description: request.description,
-            executor_type: request.executor_type.into(),
-            payload: request.payload,
-            public: request.public,
-            arguments: request.arguments,
-            inputs,
-            outputs,
+            created_after: non_zero(proto.created_after),
+            created_before: non_zero(proto.created_before),
+            descending: proto.descending,
+            limit: proto.limit,
+            cursor: match proto.cursor.as_str() {
+                "" => None,
+                _ => Some(proto.cursor),
+            },
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFunctionResponse> for RegisterFunctionResponse {
-    type Error = Error;
+impl From<ListOptions> for proto::ListOptions {
+    fn from(options: ListOptions) -> Self {
+        Self {
+            created_after: options.created_after.unwrap_or_default(),
+            created_before: options.created_before.unwrap_or_default(),
+            descending: options.descending,
+            limit: options.limit,
+            cursor: options.cursor.unwrap_or_default(),
+        }
+    }
+}
 
-    fn try_from(proto: proto::RegisterFunctionResponse) -> Result<Self> {
-        let function_id = proto.function_id.try_into()?;
-        let ret = Self { function_id };
+impl std::convert::TryFrom<proto::ListFunctionsRequest> for ListFunctionsRequest {
+    type Error = Error;
 
-        Ok(ret)
+    fn try_from(proto: proto::ListFunctionsRequest) -> Result<Self> {
+        let owner = match proto.owner.as_str() {
+            "" => None,
+            _ => Some(proto.owner.into()),
+        };
+        Ok(Self {
+            owner,
+            options: proto.options.map(Into::into).unwrap_or_default(),
+        })
     }
 }
 
-impl From<RegisterFunctionResponse> for proto::RegisterFunctionResponse {
-    fn from(response: RegisterFunctionResponse) -> Self {
+impl From<ListFunctionsRequest> for proto::ListFunctionsRequest {
+    fn from(request: ListFunctionsRequest) -> Self {
         Self {
-            function_id: response.function_id.to_string(),
+            owner: request.owner.map(Into::into).unwrap_or_default(),
+            options: Some(request.options.into()),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetFunctionRequest> for GetFunctionRequest {
+impl std::convert::TryFrom<proto::FunctionSummary> for FunctionSummary {
     type Error = Error;
 
-    fn try_from(proto: proto::GetFunctionRequest) -> Result<Self> {
-        let function_id = proto.function_id.try_into()?;
-        let ret = Self { function_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::FunctionSummary) -> Result<Self> {
+        Ok(Self {
+            function_id: proto.function_id.try_into()?,
+            name: proto.name,
+            owner: proto.owner.into(),
+            version: proto.version,
+            public: proto.public,
+            created_at: proto.created_at,
+        })
     }
 }
 
-impl From<GetFunctionRequest> for proto::GetFunctionRequest {
-    fn from(request: GetFunctionRequest) -> Self {
+impl From<FunctionSummary> for proto::FunctionSummary {
+    fn from(summary: FunctionSummary) -> Self {
         Self {
-            function_id: request.function_id.to_string(),
+            function_id: summary.function_id.to_string(),
+            name: summary.name,
+            owner: summary.owner.into(),
+            version: summary.version,
+            public: summary.public,
+            created_at: summary.created_at,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetFunctionResponse> for GetFunctionResponse {
+impl std::convert::TryFrom<proto::ListFunctionsResponse> for ListFunctionsResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::GetFunctionResponse) -> Result<Self> {
-        let inputs: Result<Vec<FunctionInput>> = proto
-            .inputs
-            .into_iter()
-            .map(FunctionInput::try_from)
-            .collect();
-        let outputs: Result<Vec<FunctionOutput>> = proto
-            .outputs
+    fn try_from(proto: proto::ListFunctionsResponse) -> Result<Self> {
+        let functions = proto
+            .functions
             .into_iter()
-            .map(FunctionOutput::try_from)
-            .collect();
-        let executor_type = proto.executor_type.try_into()?;
-
-        let ret = Self {
-            name: proto.name,
-            description: proto.description,
-            owner: proto.owner.into(),
-            executor_type,
-            payload: proto.payload,
-            public: proto.public,
-            arguments: proto.arguments,
-            inputs: inputs?,
-            outputs: outputs?,
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        let next_cursor = match proto.next_cursor.as_str() {
+            "" => None,
+            _ => Some(proto.next_cursor),
         };
+        Ok(Self {
+            functions,
+            next_cursor,
+        })
+    }
+}
 
-        Ok(ret)
+impl From<ListFunctionsResponse> for proto::ListFunctionsResponse {
+    fn from(response: ListFunctionsResponse) -> Self {
+        Self {
+            functions: response.functions.into_iter().map(Into::into).collect(),
+            next_cursor: response.next_cursor.unwrap_or_default(),
+        }
     }
 }
 
-impl From<GetFunctionResponse> for proto::GetFunctionResponse {
-    fn from(response: GetFunctionResponse) -> Self {
-        let inputs: Vec<proto::FunctionInput> = response
-            .inputs
-            .into_iter()
-            .map(proto::FunctionInput::from)
-            .collect();
-        let outputs: Vec<proto::FunctionOutput> = response
-            .outputs
+impl std::convert::TryFrom<proto::ListTasksRequest> for ListTasksRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListTasksRequest) -> Result<Self> {
+        let creator = match proto.creator.as_str() {
+            "" => None,
+            _ => Some(proto.creator.into()),
+        };
+        let statuses = proto
+            .statuses
             .into_iter()
-            .map(proto::FunctionOutput::from)
-            .collect();
+            .map(i32_to_task_status)
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            creator,
+            statuses,
+            options: proto.options.map(Into::into).unwrap_or_default(),
+        })
+    }
+}
 
+impl From<ListTasksRequest> for proto::ListTasksRequest {
+    fn from(request: ListTasksRequest) -> Self {
         Self {
-            name: response.name,
-            description: response.description,
-            owner: response.owner.into(),
-            executor_type: response.executor_type.into(),
-            payload: response.payload,
-            public: response.public,
-            arguments: response.arguments,
-            inputs,
-            outputs,
+            creator: request.creator.map(Into::into).unwrap_or_default(),
+            statuses: request
+                .statuses
+                .into_iter()
+                .map(i32_from_task_status)
+                .collect(),
+            options: Some(request.options.into()),
         }
     }
 }
 
-fn from_proto_ownership(proto: Vec<proto::OwnerList>) -> TaskFileOwners {
-    proto
-        .into_iter()
-        .map(|ol| (ol.data_name, ol.uids))
-        .collect()
-}
+impl std::convert::TryFrom<proto::TaskSummary> for TaskSummary {
+    type Error = Error;
 
-fn to_proto_ownership(ownership: TaskFileOwners) -> Vec<proto::OwnerList> {
-    ownership
-        .into_iter()
-        .map(|(name, ol)| proto::OwnerList {
-            data_name: name,
-            uids: ol.into(),
+    fn try_from(proto: proto::TaskSummary) -> Result<Self> {
+        Ok(Self {
+            task_id: proto.task_id.try_into()?,
+            creator: proto.creator.into(),
+            function_id: proto.function_id.try_into()?,
+            status: i32_to_task_status(proto.status)?,
+            created_at: proto.created_at,
         })
-        .collect()
+    }
 }
 
-impl std::convert::TryFrom<proto::CreateTaskRequest> for CreateTaskRequest {
-    type Error = Error;
+impl From<TaskSummary> for proto::TaskSummary {
+    fn from(summary: TaskSummary) -> Self {
+        Self {
+            task_id: summary.task_id.to_string(),
+            creator: summary.creator.into(),
+            function_id: summary.function_id.to_string(),
+            status: i32_from_task_status(summary.status),
+            created_at: summary.created_at,
+        }
+    }
+}
 
-    fn try_from(proto: proto::CreateTaskRequest) -> Result<Self> {
-        let function_arguments: FunctionArguments = proto.function_arguments.try_into()?;
-        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
-        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
-        let function_id = proto.function_id.try_into()?;
-        let executor = proto.executor.try_into()?;
+impl std::convert::TryFrom<proto::ListTasksResponse> for ListTasksResponse {
+    type Error = Error;
 
-        let ret = Self {
-            function_id,
-            function_arguments,
-            executor,
-            inputs_ownership,
-            outputs_ownership,
+    fn try_from(proto: proto::ListTasksResponse) -> Result<Self> {
+        let tasks = proto
+            .tasks
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        let next_cursor = match proto.next_cursor.as_str() {
+            "" => None,
+            _ => Some(proto.next_cursor),
         };
-        Ok(ret)
+        Ok(Self { tasks, next_cursor })
     }
 }
 
-impl From<CreateTaskRequest> for proto::CreateTaskRequest {
-    fn from(request: CreateTaskRequest) -> Self {
-        let function_arguments = request.function_arguments.into_string();
-        let inputs_ownership = to_proto_ownership(request.inputs_ownership);
-        let outputs_ownership = to_proto_ownership(request.outputs_ownership);
-
+impl From<ListTasksResponse> for proto::ListTasksResponse {
+    fn from(response: ListTasksResponse) -> Self {
         Self {
-            function_id: request.function_id.to_string(),
-            function_arguments,
-            executor: request.executor.to_string(),
-            inputs_ownership,
-            outputs_ownership,
+            tasks: response.tasks.into_iter().map(Into::into).collect(),
+            next_cursor: response.next_cursor.unwrap_or_default(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::CreateTaskResponse> for CreateTaskResponse {
+impl std::convert::TryFrom<proto::RegisterWebhookRequest> for RegisterWebhookRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::CreateTaskResponse) -> Result<Self> {
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::RegisterWebhookRequest) -> Result<Self> {
+        let url = Url::parse(&proto.url)?;
+        Ok(Self::new(url))
     }
 }
 
-impl From<CreateTaskResponse> for proto::CreateTaskResponse {
-    fn from(response: CreateTaskResponse) -> Self {
+impl From<RegisterWebhookRequest> for proto::RegisterWebhookRequest {
+    fn from(request: RegisterWebhookRequest) -> Self {
         Self {
-            task_id: response.task_id.to_string(),
+            url: request.url.into_string(),
         }
     }
 }
 
-fn to_proto_file_ids(map: HashMap<String, ExternalID>) -> Vec<proto::DataMap> {
-    map.into_iter()
-        .map(|(name, ext_id)| proto::DataMap {
-            data_name: name,
-            data_id: ext_id.to_string(),
+impl std::convert::TryFrom<proto::RegisterWebhookResponse> for RegisterWebhookResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterWebhookResponse) -> Result<Self> {
+        Ok(Self {
+            secret: hex::decode(&proto.secret)?,
         })
-        .collect()
+    }
 }
 
-fn from_proto_file_ids(vector: Vec<proto::DataMap>) -> Result<HashMap<String, ExternalID>> {
-    vector
-        .into_iter()
-        .map(|item| {
-            item.data_id
-                .clone()
-                .try_into()
-                .map(|ext_id| (item.data_name, ext_id))
-        })
-        .collect()
+impl From<RegisterWebhookResponse> for proto::RegisterWebhookResponse {
+    fn from(response: RegisterWebhookResponse) -> Self {
+        Self {
+            secret: hex::encode(&response.secret),
+        }
+    }
 }
 
-impl std::convert::TryFrom<proto::GetTaskRequest> for GetTaskRequest {
+impl std::convert::TryFrom<proto::DeleteWebhookRequest> for DeleteWebhookRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::GetTaskRequest) -> Result<Self> {
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
+    fn try_from(_proto: proto::DeleteWebhookRequest) -> Result<Self> {
+        Ok(DeleteWebhookRequest)
+    }
+}
 
-        Ok(ret)
+impl From<DeleteWebhookRequest> for proto::DeleteWebhookRequest {
+    fn from(_request: DeleteWebhookRequest) -> Self {
+        Self {}
     }
 }
 
-impl From<GetTaskRequest> for proto::GetTaskRequest {
-    fn from(request: GetTaskRequest) -> Self {
+impl std::convert::TryFrom<proto::DeleteWebhookResponse> for DeleteWebhookResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::DeleteWebhookResponse) -> Result<Self> {
+        Ok(DeleteWebhookResponse)
+    }
+}
+
+impl From<DeleteWebhookResponse> for proto::DeleteWebhookResponse {
+    fn from(_response: DeleteWebhookResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::CheckConsistencyRequest> for CheckConsistencyRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CheckConsistencyRequest) -> Result<Self> {
+        Ok(Self::new(proto.repair))
+    }
+}
+
+impl From<CheckConsistencyRequest> for proto::CheckConsistencyRequest {
+    fn from(request: CheckConsistencyRequest) -> Self {
         Self {
-            task_id: request.task_id.to_string(),
+            repair: request.repair,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetTaskResponse> for GetTaskResponse {
+impl std::convert::TryFrom<proto::ConsistencyFinding> for ConsistencyFinding {
     type Error = Error;
 
-    fn try_from(proto: proto::GetTaskResponse) -> Result<Self> {
-        let function_arguments: FunctionArguments = proto.function_arguments.try_into()?;
-        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
-        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
-        let assigned_inputs = from_proto_file_ids(proto.assigned_inputs)?;
-        let assigned_outputs = from_proto_file_ids(proto.assigned_outputs)?;
-        let status = i32_to_task_status(proto.status)?;
-        let function_id = proto.function_id.try_into()?;
-        let task_id = proto.task_id.try_into()?;
-        let result = proto.result.try_into()?;
+    fn try_from(proto: proto::ConsistencyFinding) -> Result<Self> {
+        Ok(Self {
+            kind: proto.kind.parse()?,
+            key: proto.key,
+            detail: proto.detail,
+            repaired: proto.repaired,
+        })
+    }
+}
 
-        let ret = Self {
-            task_id,
-            creator: proto.creator.into(),
-            function_id,
-            function_owner: proto.function_owner.into(),
-            function_arguments,
-            inputs_ownership,
-            outputs_ownership,
-            participants: UserList::new(proto.participants),
-            approved_users: UserList::new(proto.approved_users),
-            assigned_inputs,
-            assigned_outputs,
-            status,
-            result,
-        };
+impl From<ConsistencyFinding> for proto::ConsistencyFinding {
+    fn from(finding: ConsistencyFinding) -> Self {
+        Self {
+            kind: finding.kind.as_str().to_string(),
+            key: finding.key,
+            detail: finding.detail,
+            repaired: finding.repaired,
+        }
+    }
+}
 
-        Ok(ret)
+impl std::convert::TryFrom<proto::CheckConsistencyResponse> for CheckConsistencyResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CheckConsistencyResponse) -> Result<Self> {
+        let findings = proto
+            .findings
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+        Ok(Self { findings })
     }
 }
 
-impl From<GetTaskResponse> for proto::GetTaskResponse {
-    fn from(response: GetTaskResponse) -> Self {
-        let function_arguments = response.function_arguments.into_string();
-        let inputs_ownership = to_proto_ownership(response.inputs_ownership);
-        let outputs_ownership = to_proto_ownership(response.outputs_ownership);
-        let assigned_inputs = to_proto_file_ids(response.assigned_inputs);
-        let assigned_outputs = to_proto_file_ids(response.assigned_outputs);
-        let status = i32_from_task_status(response.status);
+impl From<CheckConsistencyResponse> for proto::CheckConsistencyResponse {
+    fn from(response: CheckConsistencyResponse) -> Self {
         Self {
-            task_id: response.task_id.to_string(),
-            creator: response.creator.to_string(),
-            function_id: response.function_id.to_string(),
-            function_owner: response.function_owner.to_string(),
-            function_arguments,
-            inputs_ownership,
-            outputs_ownership,
-            participants: response.participants.into(),
-            approved_users: response.approved_users.into(),
-            assigned_inputs,
-            assigned_outputs,
-            status,
-            result: Some(response.result.into()),
+            findings: response.findings.into_iter().map(Into::into).collect(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::AssignDataRequest> for AssignDataRequest {
+impl std::convert::TryFrom<proto::BeginPayloadUploadRequest> for BeginPayloadUploadRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::AssignDataRequest) -> Result<Self> {
-        let inputs = from_proto_file_ids(proto.inputs)?;
-        let outputs = from_proto_file_ids(proto.outputs)?;
-        let task_id = proto.task_id.try_into()?;
+    fn try_from(proto: proto::BeginPayloadUploadRequest) -> Result<Self> {
+        let upload_id = match proto.upload_id.as_str() {
+            "" => None,
+            id => Some(id.try_into()?),
+        };
         let ret = Self {
-            task_id,
-            inputs,
-            outputs,
+            upload_id,
+            total_len: proto.total_len,
+            part_size: proto.part_size,
+            payload_hash: proto.payload_hash,
         };
-
         Ok(ret)
     }
 }
 
-impl From<AssignDataRequest> for proto::AssignDataRequest {
-    fn from(request: AssignDataRequest) -> Self {
-        let inputs = to_proto_file_ids(request.inputs);
-        let outputs = to_proto_file_ids(request.outputs);
+impl From<BeginPayloadUploadRequest> for proto::BeginPayloadUploadRequest {
+    fn from(request: BeginPayloadUploadRequest) -> Self {
         Self {
-            task_id: request.task_id.to_string(),
-            inputs,
-            outputs,
+            upload_id: request
+                .upload_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            total_len: request.total_len,
+            part_size: request.part_size,
+            payload_hash: request.payload_hash,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::AssignDataResponse> for AssignDataResponse {
+impl std::convert::TryFrom<proto::BeginPayloadUploadResponse> for BeginPayloadUploadResponse {
     type Error = Error;
 
-    fn try_from(_proto: proto::AssignDataResponse) -> Result<Self> {
-        Ok(AssignDataResponse)
+    fn try_from(proto: proto::BeginPayloadUploadResponse) -> Result<Self> {
+        let ret = Self {
+            upload_id: proto.upload_id.try_into()?,
+            part_count: proto.part_count,
+            received_parts: proto.received_parts,
+        };
+        Ok(ret)
     }
 }
 
-impl From<AssignDataResponse> for proto::AssignDataResponse {
-    fn from(_response: AssignDataResponse) -> Self {
-        Self {}
+impl From<BeginPayloadUploadResponse> for proto::BeginPayloadUploadResponse {
+    fn from(response: BeginPayloadUploadResponse) -> Self {
+        Self {
+            upload_id: response.upload_id.to_string(),
+            part_count: response.part_count,
+            received_parts: response.received_parts,
+        }
     }
 }
 
-impl std::convert::TryFrom<proto::ApproveTaskRequest> for ApproveTaskRequest {
+impl std::convert::TryFrom<proto::UploadPartRequest> for UploadPartRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::ApproveTaskRequest) -> Result<Self> {
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
-
+    fn try_from(proto: proto::UploadPartRequest) -> Result<Self> {
+        let ret = Self {
+            upload_id: proto.upload_id.try_into()?,
+            part_number: proto.part_number,
+            data: proto.data,
+            part_hash: proto.part_hash,
+        };
         Ok(ret)
     }
 }
 
-impl From<ApproveTaskRequest> for proto::ApproveTaskRequest {
-    fn from(request: ApproveTaskRequest) -> Self {
+impl From<UploadPartRequest> for proto::UploadPartRequest {
+    fn from(request: UploadPartRequest) -> Self {
         Self {
-            task_id: request.task_id.to_string(),
+            upload_id: request.upload_id.to_string(),
+            part_number: request.part_number,
+            data: request.data,
+            part_hash: request.part_hash,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::ApproveTaskResponse> for ApproveTaskResponse {
+impl std::convert::TryFrom<proto::UploadPartResponse> for UploadPartResponse {
     type Error = Error;
 
-    fn try_from(_proto: proto::ApproveTaskResponse) -> Result<Self> {
-        Ok(ApproveTaskResponse)
+    fn try_from(_proto: proto::UploadPartResponse) -> Result<Self> {
+        Ok(Self)
     }
 }
 
-impl From<ApproveTaskResponse> for proto::ApproveTaskResponse {
-    fn from(_response: ApproveTaskResponse) -> Self {
+impl From<UploadPartResponse> for proto::UploadPartResponse {
+    fn from(_response: UploadPartResponse) -> Self {
         Self {}
     }
 }
 
-impl std::convert::TryFrom<proto::InvokeTaskRequest> for InvokeTaskRequest {
+impl std::convert::TryFrom<proto::CommitPayloadRequest> for CommitPayloadRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::InvokeTaskRequest) -> Result<Self> {
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::CommitPayloadRequest) -> Result<Self> {
+        Ok(Self {
+            upload_id: proto.upload_id.try_into()?,
+        })
     }
 }
 
-impl From<InvokeTaskRequest> for proto::InvokeTaskRequest {
-    fn from(request: InvokeTaskRequest) -> Self {
+impl From<CommitPayloadRequest> for proto::CommitPayloadRequest {
+    fn from(request: CommitPayloadRequest) -> Self {
         Self {
-            task_id: request.task_id.to_string(),
+            upload_id: request.upload_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::InvokeTaskResponse> for InvokeTaskResponse {
+impl std::convert::TryFrom<proto::CommitPayloadResponse> for CommitPayloadResponse {
     type Error = Error;
 
-    fn try_from(_proto: proto::InvokeTaskResponse) -> Result<Self> {
-        Ok(InvokeTaskResponse)
+    fn try_from(proto: proto::CommitPayloadResponse) -> Result<Self> {
+        Ok(Self {
+            payload_len: proto.payload_len,
+        })
     }
 }
 
-impl From<InvokeTaskResponse> for proto::InvokeTaskResponse {
-    fn from(_response: InvokeTaskResponse) -> Self {
-        Self {}
+impl From<CommitPayloadResponse> for proto::CommitPayloadResponse {
+    fn from(response: CommitPayloadResponse) -> Self {
+        Self {
+            payload_len: response.payload_len,
+        }
     }
 }
@@ -16,13 +16,21 @@
 // under the License.
 
 use crate::teaclave_management_service_proto as proto;
+use anyhow::{Error, Result};
+use std::prelude::v1::*;
+use teaclave_rpc::into_request;
 
+pub use crate::teaclave_common::{HealthRequest, HealthResponse};
 pub use proto::TeaclaveManagement;
 pub use proto::TeaclaveManagementClient;
 pub use proto::TeaclaveManagementRequest;
 pub use proto::TeaclaveManagementResponse;
 
 pub type RegisterInputFileRequest = crate::teaclave_frontend_service::RegisterInputFileRequest;
+pub type RegisterInlineInputFileRequest =
+    crate::teaclave_frontend_service::RegisterInlineInputFileRequest;
+pub type RegisterInlineInputFileResponse =
+    crate::teaclave_frontend_service::RegisterInlineInputFileResponse;
 pub type UpdateInputFileRequest = crate::teaclave_frontend_service::UpdateInputFileRequest;
 pub type RegisterInputFileResponse = crate::teaclave_frontend_service::RegisterInputFileResponse;
 pub type UpdateInputFileResponse = crate::teaclave_frontend_service::UpdateInputFileResponse;
@@ -44,15 +52,153 @@ pub type GetOutputFileRequest = crate::teaclave_frontend_service::GetOutputFileR
 pub type GetOutputFileResponse = crate::teaclave_frontend_service::GetOutputFileResponse;
 pub type RegisterFunctionRequest = crate::teaclave_frontend_service::RegisterFunctionRequest;
 pub type RegisterFunctionResponse = crate::teaclave_frontend_service::RegisterFunctionResponse;
+pub type BeginPayloadUploadRequest = crate::teaclave_frontend_service::BeginPayloadUploadRequest;
+pub type BeginPayloadUploadResponse = crate::teaclave_frontend_service::BeginPayloadUploadResponse;
+pub type UploadPartRequest = crate::teaclave_frontend_service::UploadPartRequest;
+pub type UploadPartResponse = crate::teaclave_frontend_service::UploadPartResponse;
+pub type CommitPayloadRequest = crate::teaclave_frontend_service::CommitPayloadRequest;
+pub type CommitPayloadResponse = crate::teaclave_frontend_service::CommitPayloadResponse;
 pub type GetFunctionRequest = crate::teaclave_frontend_service::GetFunctionRequest;
 pub type GetFunctionResponse = crate::teaclave_frontend_service::GetFunctionResponse;
+pub type RollbackFunctionRequest = crate::teaclave_frontend_service::RollbackFunctionRequest;
+pub type RollbackFunctionResponse = crate::teaclave_frontend_service::RollbackFunctionResponse;
+pub type RegisterExecutorEnclaveRequest =
+    crate::teaclave_frontend_service::RegisterExecutorEnclaveRequest;
+pub type RegisterExecutorEnclaveResponse =
+    crate::teaclave_frontend_service::RegisterExecutorEnclaveResponse;
+pub type GetMeasurementInclusionRequest =
+    crate::teaclave_frontend_service::GetMeasurementInclusionRequest;
+pub type GetMeasurementInclusionResponse =
+    crate::teaclave_frontend_service::GetMeasurementInclusionResponse;
 pub type CreateTaskRequest = crate::teaclave_frontend_service::CreateTaskRequest;
 pub type CreateTaskResponse = crate::teaclave_frontend_service::CreateTaskResponse;
 pub type GetTaskRequest = crate::teaclave_frontend_service::GetTaskRequest;
 pub type GetTaskResponse = crate::teaclave_frontend_service::GetTaskResponse;
+pub type GetTaskResultRequest = crate::teaclave_frontend_service::GetTaskResultRequest;
+pub type GetTaskResultResponse = crate::teaclave_frontend_service::GetTaskResultResponse;
+pub type GetTaskLogRequest = crate::teaclave_frontend_service::GetTaskLogRequest;
+pub type GetTaskLogResponse = crate::teaclave_frontend_service::GetTaskLogResponse;
+pub type StreamTaskResultRequest = crate::teaclave_frontend_service::StreamTaskResultRequest;
+pub type StreamTaskResultResponse = crate::teaclave_frontend_service::StreamTaskResultResponse;
 pub type AssignDataRequest = crate::teaclave_frontend_service::AssignDataRequest;
 pub type AssignDataResponse = crate::teaclave_frontend_service::AssignDataResponse;
 pub type ApproveTaskRequest = crate::teaclave_frontend_service::ApproveTaskRequest;
 pub type ApproveTaskResponse = crate::teaclave_frontend_service::ApproveTaskResponse;
 pub type InvokeTaskRequest = crate::teaclave_frontend_service::InvokeTaskRequest;
 pub type InvokeTaskResponse = crate::teaclave_frontend_service::InvokeTaskResponse;
+pub type CreateTasksRequest = crate::teaclave_frontend_service::CreateTasksRequest;
+pub type CreateTasksResponse = crate::teaclave_frontend_service::CreateTasksResponse;
+pub type InvokeTasksRequest = crate::teaclave_frontend_service::InvokeTasksRequest;
+pub type InvokeTasksResponse = crate::teaclave_frontend_service::InvokeTasksResponse;
+pub type ReviewOutputRequest = crate::teaclave_frontend_service::ReviewOutputRequest;
+pub type ReviewOutputResponse = crate::teaclave_frontend_service::ReviewOutputResponse;
+pub type UpdateAccessControlPolicyRequest =
+    crate::teaclave_frontend_service::UpdateAccessControlPolicyRequest;
+pub type UpdateAccessControlPolicyResponse =
+    crate::teaclave_frontend_service::UpdateAccessControlPolicyResponse;
+pub type RollbackAccessControlPolicyRequest =
+    crate::teaclave_frontend_service::RollbackAccessControlPolicyRequest;
+pub type RollbackAccessControlPolicyResponse =
+    crate::teaclave_frontend_service::RollbackAccessControlPolicyResponse;
+pub type GetAccessControlPolicyRequest =
+    crate::teaclave_frontend_service::GetAccessControlPolicyRequest;
+pub type GetAccessControlPolicyResponse =
+    crate::teaclave_frontend_service::GetAccessControlPolicyResponse;
+pub type ExplainAccessRequest = crate::teaclave_frontend_service::ExplainAccessRequest;
+pub type ExplainAccessResponse = crate::teaclave_frontend_service::ExplainAccessResponse;
+pub type TransferOwnershipRequest = crate::teaclave_frontend_service::TransferOwnershipRequest;
+pub type TransferOwnershipResponse = crate::teaclave_frontend_service::TransferOwnershipResponse;
+pub type SetUserQuotaRequest = crate::teaclave_frontend_service::SetUserQuotaRequest;
+pub type SetUserQuotaResponse = crate::teaclave_frontend_service::SetUserQuotaResponse;
+pub type GetQuotaUsageRequest = crate::teaclave_frontend_service::GetQuotaUsageRequest;
+pub type GetQuotaUsageResponse = crate::teaclave_frontend_service::GetQuotaUsageResponse;
+pub type GetTenantStatsRequest = crate::teaclave_frontend_service::GetTenantStatsRequest;
+pub type GetTenantStatsResponse = crate::teaclave_frontend_service::GetTenantStatsResponse;
+pub type ListNodesRequest = crate::teaclave_frontend_service::ListNodesRequest;
+pub type ListNodesResponse = crate::teaclave_frontend_service::ListNodesResponse;
+pub type ListExecutorsRequest = crate::teaclave_frontend_service::ListExecutorsRequest;
+pub type ListExecutorsResponse = crate::teaclave_frontend_service::ListExecutorsResponse;
+pub type CreateScheduledTaskRequest = crate::teaclave_frontend_service::CreateScheduledTaskRequest;
+pub type CreateScheduledTaskResponse =
+    crate::teaclave_frontend_service::CreateScheduledTaskResponse;
+pub type PauseScheduledTaskRequest = crate::teaclave_frontend_service::PauseScheduledTaskRequest;
+pub type PauseScheduledTaskResponse = crate::teaclave_frontend_service::PauseScheduledTaskResponse;
+pub type ResumeScheduledTaskRequest = crate::teaclave_frontend_service::ResumeScheduledTaskRequest;
+pub type ResumeScheduledTaskResponse =
+    crate::teaclave_frontend_service::ResumeScheduledTaskResponse;
+pub type ListUpcomingRunsRequest = crate::teaclave_frontend_service::ListUpcomingRunsRequest;
+pub type ListUpcomingRunsResponse = crate::teaclave_frontend_service::ListUpcomingRunsResponse;
+pub type CreatePipelineRequest = crate::teaclave_frontend_service::CreatePipelineRequest;
+pub type CreatePipelineResponse = crate::teaclave_frontend_service::CreatePipelineResponse;
+pub type GetPipelineRequest = crate::teaclave_frontend_service::GetPipelineRequest;
+pub type GetPipelineResponse = crate::teaclave_frontend_service::GetPipelineResponse;
+pub type ListFunctionsRequest = crate::teaclave_frontend_service::ListFunctionsRequest;
+pub type ListFunctionsResponse = crate::teaclave_frontend_service::ListFunctionsResponse;
+pub type ListTasksRequest = crate::teaclave_frontend_service::ListTasksRequest;
+pub type ListTasksResponse = crate::teaclave_frontend_service::ListTasksResponse;
+pub type RegisterWebhookRequest = crate::teaclave_frontend_service::RegisterWebhookRequest;
+pub type RegisterWebhookResponse = crate::teaclave_frontend_service::RegisterWebhookResponse;
+pub type DeleteWebhookRequest = crate::teaclave_frontend_service::DeleteWebhookRequest;
+pub type DeleteWebhookResponse = crate::teaclave_frontend_service::DeleteWebhookResponse;
+pub type CheckConsistencyRequest = crate::teaclave_frontend_service::CheckConsistencyRequest;
+pub type CheckConsistencyResponse = crate::teaclave_frontend_service::CheckConsistencyResponse;
+pub type EnterReadOnlyModeRequest = crate::teaclave_frontend_service::EnterReadOnlyModeRequest;
+pub type EnterReadOnlyModeResponse = crate::teaclave_frontend_service::EnterReadOnlyModeResponse;
+pub type ExitReadOnlyModeRequest = crate::teaclave_frontend_service::ExitReadOnlyModeRequest;
+pub type ExitReadOnlyModeResponse = crate::teaclave_frontend_service::ExitReadOnlyModeResponse;
+
+#[into_request(TeaclaveManagementRequest::DisableUserResources)]
+#[derive(Debug)]
+pub struct DisableUserResourcesRequest {
+    pub user_id: std::string::String,
+    pub deleted: bool,
+}
+
+impl DisableUserResourcesRequest {
+    pub fn new(user_id: impl Into<String>, deleted: bool) -> Self {
+        Self {
+            user_id: user_id.into(),
+            deleted,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::DisableUserResources)]
+#[derive(Debug, Default)]
+pub struct DisableUserResourcesResponse;
+
+impl std::convert::TryFrom<proto::DisableUserResourcesRequest> for DisableUserResourcesRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::DisableUserResourcesRequest) -> Result<Self> {
+        let ret = Self {
+            user_id: proto.user_id,
+            deleted: proto.deleted,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<DisableUserResourcesRequest> for proto::DisableUserResourcesRequest {
+    fn from(request: DisableUserResourcesRequest) -> Self {
+        Self {
+            user_id: request.user_id,
+            deleted: request.deleted,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DisableUserResourcesResponse> for DisableUserResourcesResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::DisableUserResourcesResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<DisableUserResourcesResponse> for proto::DisableUserResourcesResponse {
+    fn from(_response: DisableUserResourcesResponse) -> Self {
+        Self {}
+    }
+}
@@ -22,6 +22,7 @@ use std::collections::HashMap;
 use std::prelude::v1::*;
 
 use crate::teaclave_common::{i32_from_task_status, i32_to_task_status};
+pub use crate::teaclave_common::{HealthRequest, HealthResponse};
 use crate::teaclave_scheduler_service_proto as proto;
 use anyhow::{Error, Result};
 use core::convert::TryInto;
@@ -30,7 +31,10 @@ pub use proto::TeaclaveSchedulerClient;
 pub use proto::TeaclaveSchedulerRequest;
 pub use proto::TeaclaveSchedulerResponse;
 use teaclave_rpc::into_request;
-use teaclave_types::{StagedTask, TaskFailure, TaskOutputs, TaskResult, TaskStatus};
+use teaclave_types::{
+    ClockOffset, HeldOutputs, MrEnclave, NodeCapacity, OutputsHeld, StagedTask, Storable,
+    TaskFailure, TaskOutputs, TaskResult, TaskStatus,
+};
 use uuid::Uuid;
 
 #[into_request(TeaclaveSchedulerRequest::Subscribe)]
@@ -41,8 +45,77 @@ pub struct SubscribeResponse {
     pub success: bool,
 }
 
+#[into_request(TeaclaveSchedulerRequest::RegisterNode)]
+#[derive(Debug)]
+pub struct RegisterNodeRequest {
+    pub worker_id: String,
+    pub capacity: NodeCapacity,
+    pub labels: Vec<String>,
+    pub executors: Vec<String>,
+    pub builtin_functions: Vec<String>,
+}
+
+impl RegisterNodeRequest {
+    pub fn new(worker_id: impl Into<String>, capacity: NodeCapacity) -> Self {
+        Self {
+            worker_id: worker_id.into(),
+            capacity,
+            labels: Vec::new(),
+            executors: Vec::new(),
+            builtin_functions: Vec::new(),
+        }
+    }
+
+    pub fn labels(self, labels: Vec<String>) -> Self {
+        Self { labels, ..self }
+    }
+
+    pub fn executors(self, executors: Vec<String>) -> Self {
+        Self { executors, ..self }
+    }
+
+    pub fn builtin_functions(self, builtin_functions: Vec<String>) -> Self {
+        Self {
+            builtin_functions,
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveSchedulerResponse::RegisterNode)]
+#[derive(Debug)]
+pub struct RegisterNodeResponse {}
+
 #[into_request(TeaclaveSchedulerRequest::PullTask)]
-pub struct PullTaskRequest {}
+pub struct PullTaskRequest {
+    pub worker_id: String,
+    /// Executor enclaves the worker can launch.
+    pub executor_enclaves: Vec<MrEnclave>,
+    /// Labels of the node, sent with every pull as the scheduler forgets
+    /// the registered nodes when it restarts.
+    pub labels: Vec<String>,
+}
+
+impl PullTaskRequest {
+    pub fn new(worker_id: impl Into<String>) -> Self {
+        Self {
+            worker_id: worker_id.into(),
+            executor_enclaves: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn labels(self, labels: Vec<String>) -> Self {
+        Self { labels, ..self }
+    }
+
+    pub fn executor_enclaves(self, executor_enclaves: Vec<MrEnclave>) -> Self {
+        Self {
+            executor_enclaves,
+            ..self
+        }
+    }
+}
 
 #[into_request(TeaclaveSchedulerResponse::PullTask)]
 #[derive(Debug)]
@@ -56,23 +129,80 @@ impl PullTaskResponse {
     }
 }
 
+#[into_request(TeaclaveSchedulerRequest::Heartbeat)]
+#[derive(Debug)]
+pub struct HeartbeatRequest {
+    pub worker_id: String,
+    pub clock_offset: ClockOffset,
+}
+
+impl HeartbeatRequest {
+    pub fn new(worker_id: impl Into<String>, clock_offset: ClockOffset) -> Self {
+        Self {
+            worker_id: worker_id.into(),
+            clock_offset,
+        }
+    }
+}
+
+#[into_request(TeaclaveSchedulerResponse::Heartbeat)]
+#[derive(Debug)]
+pub struct HeartbeatResponse {
+    pub scheduler_time_ms: u64,
+    /// Tasks of the worker to cancel, which the scheduler stages again.
+    pub preempted: Vec<Uuid>,
+}
+
+impl HeartbeatResponse {
+    pub fn new(scheduler_time_ms: u64) -> Self {
+        Self {
+            scheduler_time_ms,
+            preempted: Vec::new(),
+        }
+    }
+
+    pub fn preempted(self, preempted: Vec<Uuid>) -> Self {
+        Self { preempted, ..self }
+    }
+}
+
 #[into_request(TeaclaveSchedulerRequest::UpdateTaskResult)]
 pub struct UpdateTaskResultRequest {
     pub task_id: Uuid,
     pub task_result: TaskResult,
+    /// Log messages of the function.
+    pub log: Vec<u8>,
+    pub log_truncated: bool,
+    /// Outputs held by the content scanners of the execution service.
+    pub held_outputs: Option<HeldOutputs>,
 }
 
 impl UpdateTaskResultRequest {
     pub fn new(task_id: Uuid, task_result: Result<TaskOutputs>) -> Self {
+        let mut held_outputs = None;
         let result = match task_result {
             Ok(task_output) => TaskResult::Ok(task_output),
-            Err(e) => TaskResult::Err(TaskFailure {
-                reason: e.to_string(),
-            }),
+            Err(e) => {
+                if let Some(OutputsHeld(held)) = e.downcast_ref() {
+                    held_outputs = Some(held.clone());
+                }
+                TaskResult::Err(TaskFailure::from(e))
+            }
         };
         Self {
             task_id,
             task_result: result,
+            log: Vec::new(),
+            log_truncated: false,
+            held_outputs,
+        }
+    }
+
+    pub fn log(self, log: Vec<u8>, log_truncated: bool) -> Self {
+        Self {
+            log,
+            log_truncated,
+            ..self
         }
     }
 }
@@ -80,6 +210,39 @@ impl UpdateTaskResultRequest {
 #[into_request(TeaclaveSchedulerResponse::UpdateTaskResult)]
 pub struct UpdateTaskResultResponse {}
 
+#[into_request(TeaclaveSchedulerRequest::AppendTaskResultChunks)]
+pub struct AppendTaskResultChunksRequest {
+    pub task_id: Uuid,
+    pub chunks: Vec<Vec<u8>>,
+    pub truncated: bool,
+    /// Log messages of the function since the last request.
+    pub log: Vec<u8>,
+    pub log_truncated: bool,
+}
+
+impl AppendTaskResultChunksRequest {
+    pub fn new(task_id: Uuid, chunks: Vec<Vec<u8>>, truncated: bool) -> Self {
+        Self {
+            task_id,
+            chunks,
+            truncated,
+            log: Vec::new(),
+            log_truncated: false,
+        }
+    }
+
+    pub fn log(self, log: Vec<u8>, log_truncated: bool) -> Self {
+        Self {
+            log,
+            log_truncated,
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveSchedulerResponse::AppendTaskResultChunks)]
+pub struct AppendTaskResultChunksResponse {}
+
 #[into_request(TeaclaveSchedulerRequest::UpdateTaskStatus)]
 pub struct UpdateTaskStatusRequest {
     pub task_id: Uuid,
@@ -137,17 +300,81 @@ impl std::convert::From<SubscribeResponse> for proto::SubscribeResponse {
     }
 }
 
+impl std::convert::TryFrom<proto::RegisterNodeRequest> for RegisterNodeRequest {
+    type Error = Error;
+    fn try_from(proto: proto::RegisterNodeRequest) -> Result<Self> {
+        let capacity = NodeCapacity {
+            cpus: proto.cpus,
+            memory_mb: proto.memory_mb,
+            slots: proto.slots,
+        };
+        let ret = Self {
+            worker_id: proto.worker_id,
+            capacity,
+            labels: proto.labels,
+            executors: proto.executors,
+            builtin_functions: proto.builtin_functions,
+        };
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<RegisterNodeRequest> for proto::RegisterNodeRequest {
+    fn from(req: RegisterNodeRequest) -> Self {
+        proto::RegisterNodeRequest {
+            worker_id: req.worker_id,
+            cpus: req.capacity.cpus,
+            memory_mb: req.capacity.memory_mb,
+            slots: req.capacity.slots,
+            labels: req.labels,
+            executors: req.executors,
+            builtin_functions: req.builtin_functions,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterNodeResponse> for RegisterNodeResponse {
+    type Error = Error;
+    fn try_from(proto: proto::RegisterNodeResponse) -> Result<Self> {
+        let ret = Self {};
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<RegisterNodeResponse> for proto::RegisterNodeResponse {
+    fn from(req: RegisterNodeResponse) -> Self {
+        proto::RegisterNodeResponse {}
+    }
+}
+
 impl std::convert::TryFrom<proto::PullTaskRequest> for PullTaskRequest {
     type Error = Error;
     fn try_from(proto: proto::PullTaskRequest) -> Result<Self> {
-        let ret = Self {};
+        let executor_enclaves = proto
+            .executor_enclaves
+            .iter()
+            .map(|mr_enclave| mr_enclave.as_slice().try_into())
+            .collect::<Result<Vec<MrEnclave>>>()?;
+        let ret = Self {
+            worker_id: proto.worker_id,
+            executor_enclaves,
+            labels: proto.labels,
+        };
         Ok(ret)
     }
 }
 
 impl std::convert::From<PullTaskRequest> for proto::PullTaskRequest {
     fn from(req: PullTaskRequest) -> Self {
-        proto::PullTaskRequest {}
+        proto::PullTaskRequest {
+            worker_id: req.worker_id,
+            executor_enclaves: req
+                .executor_enclaves
+                .iter()
+                .map(|mr_enclave| mr_enclave.as_bytes().to_vec())
+                .collect(),
+            labels: req.labels,
+        }
     }
 }
 
@@ -168,12 +395,69 @@ impl std::convert::From<PullTaskResponse> for proto::PullTaskResponse {
     }
 }
 
+impl std::convert::TryFrom<proto::HeartbeatRequest> for HeartbeatRequest {
+    type Error = Error;
+    fn try_from(proto: proto::HeartbeatRequest) -> Result<Self> {
+        let clock_offset = ClockOffset {
+            offset_ms: proto.clock_offset_ms,
+            round_trip_ms: proto.round_trip_ms,
+        };
+        let ret = Self {
+            worker_id: proto.worker_id,
+            clock_offset,
+        };
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<HeartbeatRequest> for proto::HeartbeatRequest {
+    fn from(req: HeartbeatRequest) -> Self {
+        proto::HeartbeatRequest {
+            worker_id: req.worker_id,
+            clock_offset_ms: req.clock_offset.offset_ms,
+            round_trip_ms: req.clock_offset.round_trip_ms,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::HeartbeatResponse> for HeartbeatResponse {
+    type Error = Error;
+    fn try_from(proto: proto::HeartbeatResponse) -> Result<Self> {
+        let preempted = proto
+            .preempted
+            .iter()
+            .map(|task_id| Uuid::parse_str(task_id).map_err(Into::into))
+            .collect::<Result<_>>()?;
+        let ret = Self {
+            scheduler_time_ms: proto.scheduler_time_ms,
+            preempted,
+        };
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<HeartbeatResponse> for proto::HeartbeatResponse {
+    fn from(req: HeartbeatResponse) -> Self {
+        proto::HeartbeatResponse {
+            scheduler_time_ms: req.scheduler_time_ms,
+            preempted: req.preempted.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+}
+
 impl std::convert::TryFrom<proto::UpdateTaskResultRequest> for UpdateTaskResultRequest {
     type Error = Error;
     fn try_from(proto: proto::UpdateTaskResultRequest) -> Result<Self> {
         let ret = Self {
             task_id: Uuid::parse_str(&proto.task_id)?,
             task_result: proto.result.try_into()?,
+            log: proto.log,
+            log_truncated: proto.log_truncated,
+            held_outputs: if proto.held_outputs.is_empty() {
+                None
+            } else {
+                Some(HeldOutputs::from_slice(&proto.held_outputs)?)
+            },
         };
         Ok(ret)
     }
@@ -184,6 +468,12 @@ impl std::convert::From<UpdateTaskResultRequest> for proto::UpdateTaskResultRequ
         proto::UpdateTaskResultRequest {
             task_id: req.task_id.to_string(),
             result: Some(req.task_result.into()),
+            log: req.log,
+            log_truncated: req.log_truncated,
+            held_outputs: req
+                .held_outputs
+                .and_then(|held| held.to_vec().ok())
+                .unwrap_or_default(),
         }
     }
 }
@@ -202,6 +492,48 @@ impl std::convert::From<UpdateTaskResultResponse> for proto::UpdateTaskResultRes
     }
 }
 
+impl std::convert::TryFrom<proto::AppendTaskResultChunksRequest> for AppendTaskResultChunksRequest {
+    type Error = Error;
+    fn try_from(proto: proto::AppendTaskResultChunksRequest) -> Result<Self> {
+        let ret = Self {
+            task_id: Uuid::parse_str(&proto.task_id)?,
+            chunks: proto.chunks,
+            truncated: proto.truncated,
+            log: proto.log,
+            log_truncated: proto.log_truncated,
+        };
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<AppendTaskResultChunksRequest> for proto::AppendTaskResultChunksRequest {
+    fn from(req: AppendTaskResultChunksRequest) -> Self {
+        proto::AppendTaskResultChunksRequest {
+            task_id: req.task_id.to_string(),
+            chunks: req.chunks,
+            truncated: req.truncated,
+            log: req.log,
+            log_truncated: req.log_truncated,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AppendTaskResultChunksResponse>
+    for AppendTaskResultChunksResponse
+{
+    type Error = Error;
+    fn try_from(proto: proto::AppendTaskResultChunksResponse) -> Result<Self> {
+        let ret = Self {};
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<AppendTaskResultChunksResponse> for proto::AppendTaskResultChunksResponse {
+    fn from(req: AppendTaskResultChunksResponse) -> Self {
+        proto::AppendTaskResultChunksResponse {}
+    }
+}
+
 impl std::convert::TryFrom<proto::UpdateTaskStatusRequest> for UpdateTaskStatusRequest {
     type Error = Error;
     fn try_from(proto: proto::UpdateTaskStatusRequest) -> Result<Self> {
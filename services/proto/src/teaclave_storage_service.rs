@@ -15,25 +15,54 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use anyhow::{Error, Result};
+use anyhow::{bail, Error, Result};
+use std::convert::TryInto;
 use std::prelude::v1::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+pub use crate::teaclave_common::{HealthRequest, HealthResponse};
 use crate::teaclave_storage_service_proto as proto;
 pub use proto::TeaclaveStorage;
 pub use proto::TeaclaveStorageClient;
 pub use proto::TeaclaveStorageRequest;
 pub use proto::TeaclaveStorageResponse;
 use teaclave_rpc::into_request;
+use teaclave_types::{AuditEvent, AuditLogEntry};
+use url::Url;
 
 #[into_request(TeaclaveStorageRequest::Get)]
 #[derive(Debug)]
 pub struct GetRequest {
     pub key: Vec<u8>,
+    /// Maximum staleness a replica may serve this read with. `None` accepts
+    /// any staleness. Ignored by the primary.
+    pub max_staleness: Option<Duration>,
+    /// Namespace of the key, e.g., of a tenant. Keys in a namespace are
+    /// apart from the keys outside of it, and from those of other namespaces.
+    pub namespace: Option<String>,
 }
 
 impl GetRequest {
     pub fn new(key: impl Into<Vec<u8>>) -> Self {
-        Self { key: key.into() }
+        Self {
+            key: key.into(),
+            max_staleness: None,
+            namespace: None,
+        }
+    }
+
+    pub fn max_staleness(self, max_staleness: Duration) -> Self {
+        Self {
+            max_staleness: Some(max_staleness),
+            ..self
+        }
+    }
+
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
+        }
     }
 }
 
@@ -41,14 +70,22 @@ impl GetRequest {
 #[derive(Debug)]
 pub struct GetResponse {
     pub value: Vec<u8>,
+    /// Time since the serving replica was last in sync with the primary.
+    /// Always zero when served by the primary.
+    pub staleness: Duration,
 }
 
 impl GetResponse {
     pub fn new(value: impl Into<Vec<u8>>) -> Self {
         Self {
             value: value.into(),
+            staleness: Duration::default(),
         }
     }
+
+    pub fn staleness(self, staleness: Duration) -> Self {
+        Self { staleness, ..self }
+    }
 }
 
 #[into_request(TeaclaveStorageRequest::Put)]
@@ -56,6 +93,10 @@ impl GetResponse {
 pub struct PutRequest {
     pub key: Vec<u8>,
     pub value: Vec<u8>,
+    /// The key is deleted once this time has passed, in whole seconds. `None`
+    /// keeps the key until it is deleted, and clears an earlier TTL.
+    pub ttl: Option<Duration>,
+    pub namespace: Option<String>,
 }
 
 impl PutRequest {
@@ -63,6 +104,22 @@ impl PutRequest {
         Self {
             key: key.into(),
             value: value.into(),
+            ttl: None,
+            namespace: None,
+        }
+    }
+
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
         }
     }
 }
@@ -75,11 +132,22 @@ pub struct PutResponse;
 #[derive(Debug)]
 pub struct DeleteRequest {
     pub key: Vec<u8>,
+    pub namespace: Option<String>,
 }
 
 impl DeleteRequest {
     pub fn new(key: impl Into<Vec<u8>>) -> Self {
-        Self { key: key.into() }
+        Self {
+            key: key.into(),
+            namespace: None,
+        }
+    }
+
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
+        }
     }
 }
 
@@ -92,6 +160,7 @@ pub struct DeleteResponse;
 pub struct EnqueueRequest {
     pub key: Vec<u8>,
     pub value: Vec<u8>,
+    pub namespace: Option<String>,
 }
 
 impl EnqueueRequest {
@@ -99,6 +168,14 @@ impl EnqueueRequest {
         Self {
             key: key.into(),
             value: value.into(),
+            namespace: None,
+        }
+    }
+
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
         }
     }
 }
@@ -111,11 +188,22 @@ pub struct EnqueueResponse;
 #[derive(Debug)]
 pub struct DequeueRequest {
     pub key: Vec<u8>,
+    pub namespace: Option<String>,
 }
 
 impl DequeueRequest {
     pub fn new(key: impl Into<Vec<u8>>) -> Self {
-        Self { key: key.into() }
+        Self {
+            key: key.into(),
+            namespace: None,
+        }
+    }
+
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
+        }
     }
 }
 
@@ -133,170 +221,1580 @@ impl DequeueResponse {
     }
 }
 
-impl std::convert::TryFrom<proto::GetRequest> for GetRequest {
-    type Error = Error;
+/// A write of a batch.
+/// A write of a batch, to a key in `namespace`, or else in the namespace of
+/// the batch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchOperation {
+    Put {
+        key: Vec<u8>,
+        value: Vec<u8>,
+        namespace: Option<String>,
+    },
+    Delete {
+        key: Vec<u8>,
+        namespace: Option<String>,
+    },
+    Enqueue {
+        key: Vec<u8>,
+        value: Vec<u8>,
+        namespace: Option<String>,
+    },
+}
 
-    fn try_from(proto: proto::GetRequest) -> Result<Self> {
-        let ret = Self { key: proto.key };
+impl BatchOperation {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            BatchOperation::Put { key, .. } => key,
+            BatchOperation::Delete { key, .. } => key,
+            BatchOperation::Enqueue { key, .. } => key,
+        }
+    }
 
-        Ok(ret)
+    pub fn namespace(&self) -> Option<&str> {
+        match self {
+            BatchOperation::Put { namespace, .. } => namespace.as_deref(),
+            BatchOperation::Delete { namespace, .. } => namespace.as_deref(),
+            BatchOperation::Enqueue { namespace, .. } => namespace.as_deref(),
+        }
     }
 }
 
-impl From<GetRequest> for proto::GetRequest {
-    fn from(request: GetRequest) -> Self {
-        Self { key: request.key }
-    }
+/// Writes applied in order, all of them or none.
+#[into_request(TeaclaveStorageRequest::Batch)]
+#[derive(Debug, Default)]
+pub struct BatchRequest {
+    pub operations: Vec<BatchOperation>,
+    pub namespace: Option<String>,
 }
 
-impl std::convert::TryFrom<proto::GetResponse> for GetResponse {
-    type Error = Error;
+impl BatchRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    fn try_from(proto: proto::GetResponse) -> Result<Self> {
-        let ret = Self { value: proto.value };
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
+        }
+    }
 
-        Ok(ret)
+    pub fn put(mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Self {
+        self.operations.push(BatchOperation::Put {
+            key: key.into(),
+            value: value.into(),
+            namespace: None,
+        });
+        self
+    }
+
+    /// Puts a key of `namespace` rather than of the namespace of the batch.
+    pub fn put_in(
+        mut self,
+        namespace: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.operations.push(BatchOperation::Put {
+            key: key.into(),
+            value: value.into(),
+            namespace: Some(namespace.into()),
+        });
+        self
+    }
+
+    pub fn delete(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.operations.push(BatchOperation::Delete {
+            key: key.into(),
+            namespace: None,
+        });
+        self
+    }
+
+    /// Deletes a key of `namespace` rather than of the namespace of the batch.
+    pub fn delete_in(mut self, namespace: impl Into<String>, key: impl Into<Vec<u8>>) -> Self {
+        self.operations.push(BatchOperation::Delete {
+            key: key.into(),
+            namespace: Some(namespace.into()),
+        });
+        self
+    }
+
+    pub fn enqueue(mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Self {
+        self.operations.push(BatchOperation::Enqueue {
+            key: key.into(),
+            value: value.into(),
+            namespace: None,
+        });
+        self
     }
 }
 
-impl From<GetResponse> for proto::GetResponse {
-    fn from(response: GetResponse) -> Self {
+#[into_request(TeaclaveStorageResponse::Batch)]
+#[derive(Debug, Default)]
+pub struct BatchResponse;
+
+/// Limits the keys of a namespace: writes growing its usage beyond a limit
+/// fail. `None` is unlimited.
+#[into_request(TeaclaveStorageRequest::SetNamespaceQuota)]
+#[derive(Debug)]
+pub struct SetNamespaceQuotaRequest {
+    pub namespace: String,
+    pub max_keys: Option<u64>,
+    /// Most bytes of the keys and values.
+    pub max_bytes: Option<u64>,
+}
+
+impl SetNamespaceQuotaRequest {
+    pub fn new(namespace: impl Into<String>) -> Self {
         Self {
-            value: response.value,
+            namespace: namespace.into(),
+            max_keys: None,
+            max_bytes: None,
+        }
+    }
+
+    pub fn max_keys(self, max_keys: u64) -> Self {
+        Self {
+            max_keys: Some(max_keys),
+            ..self
+        }
+    }
+
+    pub fn max_bytes(self, max_bytes: u64) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            ..self
         }
     }
 }
 
-impl std::convert::TryFrom<proto::PutRequest> for PutRequest {
-    type Error = Error;
+#[into_request(TeaclaveStorageResponse::SetNamespaceQuota)]
+#[derive(Debug, Default)]
+pub struct SetNamespaceQuotaResponse;
 
-    fn try_from(proto: proto::PutRequest) -> Result<Self> {
-        let ret = Self {
-            key: proto.key,
-            value: proto.value,
-        };
+#[into_request(TeaclaveStorageRequest::GetNamespaceUsage)]
+#[derive(Debug)]
+pub struct GetNamespaceUsageRequest {
+    pub namespace: String,
+}
 
-        Ok(ret)
+impl GetNamespaceUsageRequest {
+    pub fn new(namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+        }
     }
 }
 
-impl From<PutRequest> for proto::PutRequest {
-    fn from(request: PutRequest) -> Self {
+#[into_request(TeaclaveStorageResponse::GetNamespaceUsage)]
+#[derive(Debug)]
+pub struct GetNamespaceUsageResponse {
+    pub key_count: u64,
+    pub byte_count: u64,
+    pub max_keys: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+/// Deletes all keys of a namespace, with its usage and quota.
+#[into_request(TeaclaveStorageRequest::WipeNamespace)]
+#[derive(Debug)]
+pub struct WipeNamespaceRequest {
+    pub namespace: String,
+}
+
+impl WipeNamespaceRequest {
+    pub fn new(namespace: impl Into<String>) -> Self {
         Self {
-            key: request.key,
-            value: request.value,
+            namespace: namespace.into(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::PutResponse> for PutResponse {
-    type Error = Error;
+#[into_request(TeaclaveStorageResponse::WipeNamespace)]
+#[derive(Debug)]
+pub struct WipeNamespaceResponse {
+    pub deleted_keys: u64,
+}
 
-    fn try_from(_proto: proto::PutResponse) -> Result<Self> {
-        Ok(Self {})
-    }
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageChange {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
 }
 
-impl From<PutResponse> for proto::PutResponse {
-    fn from(_response: PutResponse) -> Self {
-        Self {}
+#[into_request(TeaclaveStorageRequest::GetChanges)]
+#[derive(Debug)]
+pub struct GetChangesRequest {
+    /// Epoch of the change log of the last change already applied by the
+    /// caller, or zero if none.
+    pub epoch: u64,
+    /// Sequence number of the last change already applied by the caller.
+    pub since: u64,
+    /// Key to continue the snapshot of `epoch` from, the `next_key` of its
+    /// previous page.
+    pub snapshot_start: Option<Vec<u8>>,
+}
+
+impl GetChangesRequest {
+    pub fn new(epoch: u64, since: u64) -> Self {
+        Self {
+            epoch,
+            since,
+            snapshot_start: None,
+        }
+    }
+
+    pub fn snapshot_start(self, snapshot_start: impl Into<Vec<u8>>) -> Self {
+        Self {
+            snapshot_start: Some(snapshot_start.into()),
+            ..self
+        }
     }
 }
 
-impl std::convert::TryFrom<proto::DeleteRequest> for DeleteRequest {
-    type Error = Error;
+#[into_request(TeaclaveStorageResponse::GetChanges)]
+#[derive(Debug)]
+pub struct GetChangesResponse {
+    /// Epoch of the change log, which changes when the primary restarts and
+    /// its sequence numbers start over.
+    pub epoch: u64,
+    /// Sequence number of the last change included in the response. For
+    /// snapshots, the sequence number when the snapshot started, after which
+    /// the caller catches up with the changes made while it was paged.
+    pub sequence: u64,
+    /// If true, `changes` is a page of a snapshot of the database and the
+    /// caller should discard its local state once all pages are applied.
+    pub snapshot: bool,
+    pub changes: Vec<StorageChange>,
+    /// First key of the next page of the snapshot, if any.
+    pub next_key: Option<Vec<u8>>,
+}
 
-    fn try_from(proto: proto::DeleteRequest) -> Result<Self> {
-        let ret = Self { key: proto.key };
+impl GetChangesResponse {
+    pub fn new(epoch: u64, sequence: u64, changes: Vec<StorageChange>) -> Self {
+        Self {
+            epoch,
+            sequence,
+            snapshot: false,
+            changes,
+            next_key: None,
+        }
+    }
 
-        Ok(ret)
+    pub fn snapshot(
+        epoch: u64,
+        sequence: u64,
+        changes: Vec<StorageChange>,
+        next_key: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            epoch,
+            sequence,
+            snapshot: true,
+            changes,
+            next_key,
+        }
     }
 }
 
-impl From<DeleteRequest> for proto::DeleteRequest {
-    fn from(request: DeleteRequest) -> Self {
-        Self { key: request.key }
-    }
+/// Position of a follower of the change log of the storage, e.g., a replica.
+/// Snapshots are fetched a page at a time, after which the follower catches up
+/// with the changes made while they were paged.
+#[derive(Debug, Default, Clone)]
+pub struct ChangeCursor {
+    epoch: u64,
+    sequence: u64,
+    snapshot_start: Option<Vec<u8>>,
 }
 
-impl std::convert::TryFrom<proto::DeleteResponse> for DeleteResponse {
-    type Error = Error;
+impl ChangeCursor {
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
 
-    fn try_from(_proto: proto::DeleteResponse) -> Result<Self> {
-        Ok(Self {})
+    pub fn sequence(&self) -> u64 {
+        self.sequence
     }
-}
 
-impl From<DeleteResponse> for proto::DeleteResponse {
-    fn from(_response: DeleteResponse) -> Self {
-        Self {}
+    /// Whether the pages of a snapshot are being fetched.
+    pub fn in_snapshot(&self) -> bool {
+        self.snapshot_start.is_some()
+    }
+
+    /// Request of the changes, or the snapshot page, after the cursor.
+    pub fn request(&self) -> GetChangesRequest {
+        let request = GetChangesRequest::new(self.epoch, self.sequence);
+        match &self.snapshot_start {
+            Some(start) => request.snapshot_start(start.clone()),
+            None => request,
+        }
+    }
+
+    /// Whether `response` to the request of the cursor is the first page of a
+    /// snapshot, instead of a page of the snapshot being fetched or changes.
+    pub fn starts_snapshot(&self, response: &GetChangesResponse) -> bool {
+        response.snapshot && (self.snapshot_start.is_none() || response.epoch != self.epoch)
+    }
+
+    /// Moves past `response` to the request of the cursor, once applied.
+    pub fn advance(&mut self, response: &GetChangesResponse) {
+        self.epoch = response.epoch;
+        self.sequence = response.sequence;
+        self.snapshot_start = response.next_key.clone();
     }
 }
 
-impl std::convert::TryFrom<proto::EnqueueRequest> for EnqueueRequest {
-    type Error = Error;
+/// Entries under a key prefix in the order of their keys, a page at a time.
+#[into_request(TeaclaveStorageRequest::ScanPrefix)]
+#[derive(Debug)]
+pub struct ScanPrefixRequest {
+    pub prefix: Vec<u8>,
+    /// Key to continue from, the `next_key` of the previous page.
+    pub start: Option<Vec<u8>>,
+    /// Most entries returned, capped by the storage. Zero for the cap.
+    pub limit: u32,
+    pub namespace: Option<String>,
+}
 
-    fn try_from(proto: proto::EnqueueRequest) -> Result<Self> {
-        let ret = Self {
-            key: proto.key,
-            value: proto.value,
-        };
+impl ScanPrefixRequest {
+    pub fn new(prefix: impl Into<Vec<u8>>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            start: None,
+            limit: 0,
+            namespace: None,
+        }
+    }
 
-        Ok(ret)
+    pub fn start(self, start: impl Into<Vec<u8>>) -> Self {
+        Self {
+            start: Some(start.into()),
+            ..self
+        }
+    }
+
+    pub fn limit(self, limit: u32) -> Self {
+        Self { limit, ..self }
+    }
+
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
+        }
     }
 }
 
-impl From<EnqueueRequest> for proto::EnqueueRequest {
-    fn from(request: EnqueueRequest) -> Self {
+#[into_request(TeaclaveStorageResponse::ScanPrefix)]
+#[derive(Debug)]
+pub struct ScanPrefixResponse {
+    pub entries: Vec<(Vec<u8>, Vec<u8>)>,
+    /// First key of the next page, if any.
+    pub next_key: Option<Vec<u8>>,
+}
+
+/// Entries with keys from `start` up to `end` (exclusive) in their order, a
+/// page at a time.
+#[into_request(TeaclaveStorageRequest::Range)]
+#[derive(Debug)]
+pub struct RangeRequest {
+    pub start: Vec<u8>,
+    /// Unbounded if none.
+    pub end: Option<Vec<u8>>,
+    /// Most entries returned, capped by the storage. Zero for the cap.
+    pub limit: u32,
+    pub namespace: Option<String>,
+}
+
+impl RangeRequest {
+    pub fn new(start: impl Into<Vec<u8>>) -> Self {
         Self {
-            key: request.key,
-            value: request.value,
+            start: start.into(),
+            end: None,
+            limit: 0,
+            namespace: None,
+        }
+    }
+
+    pub fn end(self, end: impl Into<Vec<u8>>) -> Self {
+        Self {
+            end: Some(end.into()),
+            ..self
+        }
+    }
+
+    pub fn limit(self, limit: u32) -> Self {
+        Self { limit, ..self }
+    }
+
+    pub fn namespace(self, namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            ..self
         }
     }
 }
 
-impl std::convert::TryFrom<proto::EnqueueResponse> for EnqueueResponse {
-    type Error = Error;
+#[into_request(TeaclaveStorageResponse::Range)]
+#[derive(Debug)]
+pub struct RangeResponse {
+    pub entries: Vec<(Vec<u8>, Vec<u8>)>,
+    /// First key of the next page, if any.
+    pub next_key: Option<Vec<u8>>,
+}
 
-    fn try_from(_proto: proto::EnqueueResponse) -> Result<Self> {
-        Ok(Self {})
+#[into_request(TeaclaveStorageRequest::GetUsage)]
+#[derive(Debug, Default)]
+pub struct GetUsageRequest;
+
+impl GetUsageRequest {
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
-impl From<EnqueueResponse> for proto::EnqueueResponse {
-    fn from(_response: EnqueueResponse) -> Self {
-        Self {}
+/// Number of keys and bytes (of keys and values) stored under a key prefix,
+/// i.e., the part of the key before the first `-`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefixUsage {
+    pub prefix: Vec<u8>,
+    pub key_count: u64,
+    pub byte_count: u64,
+}
+
+#[into_request(TeaclaveStorageResponse::GetUsage)]
+#[derive(Debug)]
+pub struct GetUsageResponse {
+    /// Usages of the last count of the database, which is counted a batch of
+    /// keys at a time.
+    pub usages: Vec<PrefixUsage>,
+    pub usage_counted_at: Option<SystemTime>,
+    /// Writes since the last compaction.
+    pub pending_writes: u64,
+    pub last_compaction: Option<SystemTime>,
+    /// True if the pending writes exceed the configured lag threshold.
+    pub compaction_behind: bool,
+    /// Compactions completed since the service started.
+    pub compactions: u64,
+    pub compaction: Option<CompactionProgress>,
+}
+
+/// Progress of a compaction of the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionProgress {
+    pub compacted_keys: u64,
+    /// Keys of the database when the compaction started.
+    pub total_keys: u64,
+    pub started_at: SystemTime,
+}
+
+/// Starts compacting the database, a batch of keys at a time, whether
+/// compaction is due or not.
+#[into_request(TeaclaveStorageRequest::Compact)]
+#[derive(Debug, Default)]
+pub struct CompactRequest;
+
+impl CompactRequest {
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
-impl std::convert::TryFrom<proto::DequeueRequest> for DequeueRequest {
-    type Error = Error;
+#[into_request(TeaclaveStorageResponse::Compact)]
+#[derive(Debug)]
+pub struct CompactResponse {
+    /// False if a compaction was in progress already.
+    pub started: bool,
+}
 
-    fn try_from(proto: proto::DequeueRequest) -> Result<Self> {
-        let ret = Self { key: proto.key };
+#[into_request(TeaclaveStorageRequest::RotateKey)]
+#[derive(Debug, Default)]
+pub struct RotateKeyRequest;
 
-        Ok(ret)
+impl RotateKeyRequest {
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
-impl From<DequeueRequest> for proto::DequeueRequest {
-    fn from(request: DequeueRequest) -> Self {
-        Self { key: request.key }
+#[into_request(TeaclaveStorageResponse::RotateKey)]
+#[derive(Debug)]
+pub struct RotateKeyResponse {
+    /// Version of the key the database is re-encrypted with.
+    pub target_version: u32,
+}
+
+#[into_request(TeaclaveStorageRequest::GetKeyRotation)]
+#[derive(Debug, Default)]
+pub struct GetKeyRotationRequest;
+
+impl GetKeyRotationRequest {
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
-impl std::convert::TryFrom<proto::DequeueResponse> for DequeueResponse {
-    type Error = Error;
+/// Progress of an unfinished rotation of the key of the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyRotationProgress {
+    pub target_version: u32,
+    /// Keys re-encrypted with the new key so far.
+    pub copied_keys: u64,
+    /// Keys of the database when the rotation started (or resumed).
+    pub total_keys: u64,
+    pub started_at: SystemTime,
+}
 
-    fn try_from(proto: proto::DequeueResponse) -> Result<Self> {
-        Ok(Self { value: proto.value })
+#[into_request(TeaclaveStorageResponse::GetKeyRotation)]
+#[derive(Debug)]
+pub struct GetKeyRotationResponse {
+    /// Version of the key of the database in use.
+    pub key_version: u32,
+    pub rotation: Option<KeyRotationProgress>,
+}
+
+#[into_request(TeaclaveStorageRequest::CreateSnapshot)]
+#[derive(Debug)]
+pub struct CreateSnapshotRequest {
+    /// Where the snapshot is exported to, through the file agent of the
+    /// storage service.
+    pub url: Url,
+}
+
+impl CreateSnapshotRequest {
+    pub fn new(url: Url) -> Self {
+        Self { url }
     }
 }
 
-impl From<DequeueResponse> for proto::DequeueResponse {
-    fn from(response: DequeueResponse) -> Self {
-        Self {
-            value: response.value,
+#[into_request(TeaclaveStorageResponse::CreateSnapshot)]
+#[derive(Debug)]
+pub struct CreateSnapshotResponse {
+    /// Number of keys in the snapshot.
+    pub key_count: u64,
+    /// Size of the encrypted snapshot.
+    pub byte_count: u64,
+}
+
+#[into_request(TeaclaveStorageRequest::AppendAuditEvent)]
+#[derive(Debug)]
+pub struct AppendAuditEventRequest {
+    pub event: AuditEvent,
+}
+
+impl AppendAuditEventRequest {
+    pub fn new(event: AuditEvent) -> Self {
+        Self { event }
+    }
+}
+
+#[into_request(TeaclaveStorageResponse::AppendAuditEvent)]
+#[derive(Debug)]
+pub struct AppendAuditEventResponse {
+    pub seq: u64,
+}
+
+#[into_request(TeaclaveStorageRequest::ExportAuditLog)]
+#[derive(Debug)]
+pub struct ExportAuditLogRequest {
+    pub start_seq: u64,
+    pub limit: u32,
+}
+
+impl ExportAuditLogRequest {
+    pub fn new(start_seq: u64, limit: u32) -> Self {
+        Self { start_seq, limit }
+    }
+}
+
+#[into_request(TeaclaveStorageResponse::ExportAuditLog)]
+#[derive(Debug)]
+pub struct ExportAuditLogResponse {
+    pub entries: Vec<AuditLogEntry>,
+    /// Number of entries in the log.
+    pub log_len: u64,
+    /// The first exported entry whose link or seal does not verify.
+    pub broken_seq: Option<u64>,
+}
+
+// Keys outside of namespaces have an empty namespace on the wire.
+fn from_namespace(namespace: String) -> Option<String> {
+    Some(namespace).filter(|namespace| !namespace.is_empty())
+}
+
+impl std::convert::TryFrom<proto::GetRequest> for GetRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetRequest) -> Result<Self> {
+        let max_staleness = match proto.max_staleness_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        };
+        let ret = Self {
+            key: proto.key,
+            max_staleness,
+            namespace: from_namespace(proto.namespace),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetRequest> for proto::GetRequest {
+    fn from(request: GetRequest) -> Self {
+        Self {
+            key: request.key,
+            max_staleness_ms: request
+                .max_staleness
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetResponse> for GetResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetResponse) -> Result<Self> {
+        let ret = Self {
+            value: proto.value,
+            staleness: Duration::from_millis(proto.staleness_ms),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetResponse> for proto::GetResponse {
+    fn from(response: GetResponse) -> Self {
+        Self {
+            value: response.value,
+            staleness_ms: response.staleness.as_millis() as u64,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::PutRequest> for PutRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::PutRequest) -> Result<Self> {
+        let ttl = match proto.ttl_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        let ret = Self {
+            key: proto.key,
+            value: proto.value,
+            ttl,
+            namespace: from_namespace(proto.namespace),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<PutRequest> for proto::PutRequest {
+    fn from(request: PutRequest) -> Self {
+        Self {
+            key: request.key,
+            value: request.value,
+            // Shorter TTLs are rounded up to a second, as 0 means no TTL.
+            ttl_secs: request
+                .ttl
+                .map(|ttl| std::cmp::max(ttl.as_secs(), 1))
+                .unwrap_or_default(),
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::PutResponse> for PutResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::PutResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<PutResponse> for proto::PutResponse {
+    fn from(_response: PutResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::DeleteRequest> for DeleteRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::DeleteRequest) -> Result<Self> {
+        let ret = Self {
+            key: proto.key,
+            namespace: from_namespace(proto.namespace),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<DeleteRequest> for proto::DeleteRequest {
+    fn from(request: DeleteRequest) -> Self {
+        Self {
+            key: request.key,
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DeleteResponse> for DeleteResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::DeleteResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<DeleteResponse> for proto::DeleteResponse {
+    fn from(_response: DeleteResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::EnqueueRequest> for EnqueueRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::EnqueueRequest) -> Result<Self> {
+        let ret = Self {
+            key: proto.key,
+            value: proto.value,
+            namespace: from_namespace(proto.namespace),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<EnqueueRequest> for proto::EnqueueRequest {
+    fn from(request: EnqueueRequest) -> Self {
+        Self {
+            key: request.key,
+            value: request.value,
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::EnqueueResponse> for EnqueueResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::EnqueueResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<EnqueueResponse> for proto::EnqueueResponse {
+    fn from(_response: EnqueueResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::DequeueRequest> for DequeueRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::DequeueRequest) -> Result<Self> {
+        let ret = Self {
+            key: proto.key,
+            namespace: from_namespace(proto.namespace),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<DequeueRequest> for proto::DequeueRequest {
+    fn from(request: DequeueRequest) -> Self {
+        Self {
+            key: request.key,
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DequeueResponse> for DequeueResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::DequeueResponse) -> Result<Self> {
+        Ok(Self { value: proto.value })
+    }
+}
+
+impl From<DequeueResponse> for proto::DequeueResponse {
+    fn from(response: DequeueResponse) -> Self {
+        Self {
+            value: response.value,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::BatchOperation> for BatchOperation {
+    type Error = Error;
+
+    fn try_from(proto: proto::BatchOperation) -> Result<Self> {
+        let namespace = from_namespace(proto.namespace);
+        let ret = match proto::BatchOperationKind::from_i32(proto.kind) {
+            Some(proto::BatchOperationKind::Put) => BatchOperation::Put {
+                key: proto.key,
+                value: proto.value,
+                namespace,
+            },
+            Some(proto::BatchOperationKind::Delete) => BatchOperation::Delete {
+                key: proto.key,
+                namespace,
+            },
+            Some(proto::BatchOperationKind::Enqueue) => BatchOperation::Enqueue {
+                key: proto.key,
+                value: proto.value,
+                namespace,
+            },
+            None => bail!("invalid batch operation"),
+        };
+        Ok(ret)
+    }
+}
+
+impl From<BatchOperation> for proto::BatchOperation {
+    fn from(operation: BatchOperation) -> Self {
+        let (kind, key, value, namespace) = match operation {
+            BatchOperation::Put {
+                key,
+                value,
+                namespace,
+            } => (proto::BatchOperationKind::Put, key, value, namespace),
+            BatchOperation::Delete { key, namespace } => (
+                proto::BatchOperationKind::Delete,
+                key,
+                Vec::new(),
+                namespace,
+            ),
+            BatchOperation::Enqueue {
+                key,
+                value,
+                namespace,
+            } => (proto::BatchOperationKind::Enqueue, key, value, namespace),
+        };
+        Self {
+            kind: kind as i32,
+            key,
+            value,
+            namespace: namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::BatchRequest> for BatchRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::BatchRequest) -> Result<Self> {
+        let operations = proto
+            .operations
+            .into_iter()
+            .map(|operation| operation.try_into())
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            operations,
+            namespace: from_namespace(proto.namespace),
+        })
+    }
+}
+
+impl From<BatchRequest> for proto::BatchRequest {
+    fn from(request: BatchRequest) -> Self {
+        Self {
+            operations: request
+                .operations
+                .into_iter()
+                .map(proto::BatchOperation::from)
+                .collect(),
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::BatchResponse> for BatchResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::BatchResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<BatchResponse> for proto::BatchResponse {
+    fn from(_response: BatchResponse) -> Self {
+        Self {}
+    }
+}
+
+// Limits are unlimited if zero on the wire.
+fn from_limit(limit: u64) -> Option<u64> {
+    Some(limit).filter(|limit| *limit != 0)
+}
+
+impl std::convert::TryFrom<proto::SetNamespaceQuotaRequest> for SetNamespaceQuotaRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::SetNamespaceQuotaRequest) -> Result<Self> {
+        Ok(Self {
+            namespace: proto.namespace,
+            max_keys: from_limit(proto.max_keys),
+            max_bytes: from_limit(proto.max_bytes),
+        })
+    }
+}
+
+impl From<SetNamespaceQuotaRequest> for proto::SetNamespaceQuotaRequest {
+    fn from(request: SetNamespaceQuotaRequest) -> Self {
+        Self {
+            namespace: request.namespace,
+            max_keys: request.max_keys.unwrap_or_default(),
+            max_bytes: request.max_bytes.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::SetNamespaceQuotaResponse> for SetNamespaceQuotaResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::SetNamespaceQuotaResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<SetNamespaceQuotaResponse> for proto::SetNamespaceQuotaResponse {
+    fn from(_response: SetNamespaceQuotaResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetNamespaceUsageRequest> for GetNamespaceUsageRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetNamespaceUsageRequest) -> Result<Self> {
+        Ok(Self {
+            namespace: proto.namespace,
+        })
+    }
+}
+
+impl From<GetNamespaceUsageRequest> for proto::GetNamespaceUsageRequest {
+    fn from(request: GetNamespaceUsageRequest) -> Self {
+        Self {
+            namespace: request.namespace,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetNamespaceUsageResponse> for GetNamespaceUsageResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetNamespaceUsageResponse) -> Result<Self> {
+        Ok(Self {
+            key_count: proto.key_count,
+            byte_count: proto.byte_count,
+            max_keys: from_limit(proto.max_keys),
+            max_bytes: from_limit(proto.max_bytes),
+        })
+    }
+}
+
+impl From<GetNamespaceUsageResponse> for proto::GetNamespaceUsageResponse {
+    fn from(response: GetNamespaceUsageResponse) -> Self {
+        Self {
+            key_count: response.key_count,
+            byte_count: response.byte_count,
+            max_keys: response.max_keys.unwrap_or_default(),
+            max_bytes: response.max_bytes.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::WipeNamespaceRequest> for WipeNamespaceRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::WipeNamespaceRequest) -> Result<Self> {
+        Ok(Self {
+            namespace: proto.namespace,
+        })
+    }
+}
+
+impl From<WipeNamespaceRequest> for proto::WipeNamespaceRequest {
+    fn from(request: WipeNamespaceRequest) -> Self {
+        Self {
+            namespace: request.namespace,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::WipeNamespaceResponse> for WipeNamespaceResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::WipeNamespaceResponse) -> Result<Self> {
+        Ok(Self {
+            deleted_keys: proto.deleted_keys,
+        })
+    }
+}
+
+impl From<WipeNamespaceResponse> for proto::WipeNamespaceResponse {
+    fn from(response: WipeNamespaceResponse) -> Self {
+        Self {
+            deleted_keys: response.deleted_keys,
+        }
+    }
+}
+
+impl From<proto::StorageChange> for StorageChange {
+    fn from(proto: proto::StorageChange) -> Self {
+        if proto.deleted {
+            StorageChange::Delete { key: proto.key }
+        } else {
+            StorageChange::Put {
+                key: proto.key,
+                value: proto.value,
+            }
+        }
+    }
+}
+
+impl From<StorageChange> for proto::StorageChange {
+    fn from(change: StorageChange) -> Self {
+        match change {
+            StorageChange::Put { key, value } => Self {
+                key,
+                value,
+                deleted: false,
+            },
+            StorageChange::Delete { key } => Self {
+                key,
+                value: Vec::new(),
+                deleted: true,
+            },
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetChangesRequest> for GetChangesRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetChangesRequest) -> Result<Self> {
+        Ok(Self {
+            epoch: proto.epoch,
+            since: proto.since,
+            snapshot_start: Some(proto.snapshot_start).filter(|start| !start.is_empty()),
+        })
+    }
+}
+
+impl From<GetChangesRequest> for proto::GetChangesRequest {
+    fn from(request: GetChangesRequest) -> Self {
+        Self {
+            since: request.since,
+            epoch: request.epoch,
+            snapshot_start: request.snapshot_start.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetChangesResponse> for GetChangesResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetChangesResponse) -> Result<Self> {
+        let ret = Self {
+            epoch: proto.epoch,
+            sequence: proto.sequence,
+            snapshot: proto.snapshot,
+            changes: proto.changes.into_iter().map(StorageChange::from).collect(),
+            next_key: Some(proto.next_key).filter(|_| proto.more),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetChangesResponse> for proto::GetChangesResponse {
+    fn from(response: GetChangesResponse) -> Self {
+        Self {
+            sequence: response.sequence,
+            snapshot: response.snapshot,
+            changes: response
+                .changes
+                .into_iter()
+                .map(proto::StorageChange::from)
+                .collect(),
+            epoch: response.epoch,
+            more: response.next_key.is_some(),
+            next_key: response.next_key.unwrap_or_default(),
+        }
+    }
+}
+
+fn entries_from_proto(entries: Vec<proto::StorageEntry>) -> Vec<(Vec<u8>, Vec<u8>)> {
+    entries
+        .into_iter()
+        .map(|entry| (entry.key, entry.value))
+        .collect()
+}
+
+fn entries_to_proto(entries: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<proto::StorageEntry> {
+    entries
+        .into_iter()
+        .map(|(key, value)| proto::StorageEntry { key, value })
+        .collect()
+}
+
+impl std::convert::TryFrom<proto::ScanPrefixRequest> for ScanPrefixRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ScanPrefixRequest) -> Result<Self> {
+        Ok(Self {
+            prefix: proto.prefix,
+            start: Some(proto.start).filter(|start| !start.is_empty()),
+            limit: proto.limit,
+            namespace: from_namespace(proto.namespace),
+        })
+    }
+}
+
+impl From<ScanPrefixRequest> for proto::ScanPrefixRequest {
+    fn from(request: ScanPrefixRequest) -> Self {
+        Self {
+            prefix: request.prefix,
+            start: request.start.unwrap_or_default(),
+            limit: request.limit,
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ScanPrefixResponse> for ScanPrefixResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ScanPrefixResponse) -> Result<Self> {
+        Ok(Self {
+            entries: entries_from_proto(proto.entries),
+            next_key: Some(proto.next_key).filter(|_| proto.more),
+        })
+    }
+}
+
+impl From<ScanPrefixResponse> for proto::ScanPrefixResponse {
+    fn from(response: ScanPrefixResponse) -> Self {
+        Self {
+            entries: entries_to_proto(response.entries),
+            more: response.next_key.is_some(),
+            next_key: response.next_key.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RangeRequest> for RangeRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RangeRequest) -> Result<Self> {
+        Ok(Self {
+            start: proto.start,
+            end: Some(proto.end).filter(|end| !end.is_empty()),
+            limit: proto.limit,
+            namespace: from_namespace(proto.namespace),
+        })
+    }
+}
+
+impl From<RangeRequest> for proto::RangeRequest {
+    fn from(request: RangeRequest) -> Self {
+        Self {
+            start: request.start,
+            end: request.end.unwrap_or_default(),
+            limit: request.limit,
+            namespace: request.namespace.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RangeResponse> for RangeResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RangeResponse) -> Result<Self> {
+        Ok(Self {
+            entries: entries_from_proto(proto.entries),
+            next_key: Some(proto.next_key).filter(|_| proto.more),
+        })
+    }
+}
+
+impl From<RangeResponse> for proto::RangeResponse {
+    fn from(response: RangeResponse) -> Self {
+        Self {
+            entries: entries_to_proto(response.entries),
+            more: response.next_key.is_some(),
+            next_key: response.next_key.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetUsageRequest> for GetUsageRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::GetUsageRequest) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<GetUsageRequest> for proto::GetUsageRequest {
+    fn from(_request: GetUsageRequest) -> Self {
+        Self {}
+    }
+}
+
+impl From<proto::PrefixUsage> for PrefixUsage {
+    fn from(proto: proto::PrefixUsage) -> Self {
+        Self {
+            prefix: proto.prefix,
+            key_count: proto.key_count,
+            byte_count: proto.byte_count,
+        }
+    }
+}
+
+impl From<PrefixUsage> for proto::PrefixUsage {
+    fn from(usage: PrefixUsage) -> Self {
+        Self {
+            prefix: usage.prefix,
+            key_count: usage.key_count,
+            byte_count: usage.byte_count,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetUsageResponse> for GetUsageResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetUsageResponse) -> Result<Self> {
+        let last_compaction = match proto.last_compaction_timestamp {
+            0 => None,
+            secs => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+        };
+        let compaction = if proto.compacting {
+            Some(CompactionProgress {
+                compacted_keys: proto.compacted_keys,
+                total_keys: proto.total_keys,
+                started_at: UNIX_EPOCH + Duration::from_secs(proto.compaction_started_timestamp),
+            })
+        } else {
+            None
+        };
+        let usage_counted_at = match proto.usage_timestamp {
+            0 => None,
+            secs => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+        };
+        let ret = Self {
+            usages: proto.usages.into_iter().map(PrefixUsage::from).collect(),
+            usage_counted_at,
+            pending_writes: proto.pending_writes,
+            last_compaction,
+            compaction_behind: proto.compaction_behind,
+            compactions: proto.compactions,
+            compaction,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetUsageResponse> for proto::GetUsageResponse {
+    fn from(response: GetUsageResponse) -> Self {
+        let last_compaction_timestamp = response
+            .last_compaction
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let usage_timestamp = response
+            .usage_counted_at
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut ret = Self {
+            usages: response
+                .usages
+                .into_iter()
+                .map(proto::PrefixUsage::from)
+                .collect(),
+            usage_timestamp,
+            pending_writes: response.pending_writes,
+            last_compaction_timestamp,
+            compaction_behind: response.compaction_behind,
+            compactions: response.compactions,
+            ..Default::default()
+        };
+        if let Some(compaction) = response.compaction {
+            ret.compacting = true;
+            ret.compacted_keys = compaction.compacted_keys;
+            ret.total_keys = compaction.total_keys;
+            ret.compaction_started_timestamp = compaction
+                .started_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+        }
+        ret
+    }
+}
+
+impl std::convert::TryFrom<proto::CompactRequest> for CompactRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::CompactRequest) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<CompactRequest> for proto::CompactRequest {
+    fn from(_request: CompactRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::CompactResponse> for CompactResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CompactResponse) -> Result<Self> {
+        Ok(Self {
+            started: proto.started,
+        })
+    }
+}
+
+impl From<CompactResponse> for proto::CompactResponse {
+    fn from(response: CompactResponse) -> Self {
+        Self {
+            started: response.started,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RotateKeyRequest> for RotateKeyRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::RotateKeyRequest) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<RotateKeyRequest> for proto::RotateKeyRequest {
+    fn from(_request: RotateKeyRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::RotateKeyResponse> for RotateKeyResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RotateKeyResponse) -> Result<Self> {
+        Ok(Self {
+            target_version: proto.target_version,
+        })
+    }
+}
+
+impl From<RotateKeyResponse> for proto::RotateKeyResponse {
+    fn from(response: RotateKeyResponse) -> Self {
+        Self {
+            target_version: response.target_version,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetKeyRotationRequest> for GetKeyRotationRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::GetKeyRotationRequest) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<GetKeyRotationRequest> for proto::GetKeyRotationRequest {
+    fn from(_request: GetKeyRotationRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetKeyRotationResponse> for GetKeyRotationResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetKeyRotationResponse) -> Result<Self> {
+        let rotation = if proto.rotating {
+            Some(KeyRotationProgress {
+                target_version: proto.target_version,
+                copied_keys: proto.copied_keys,
+                total_keys: proto.total_keys,
+                started_at: UNIX_EPOCH + Duration::from_secs(proto.started_timestamp),
+            })
+        } else {
+            None
+        };
+        let ret = Self {
+            key_version: proto.key_version,
+            rotation,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetKeyRotationResponse> for proto::GetKeyRotationResponse {
+    fn from(response: GetKeyRotationResponse) -> Self {
+        let mut ret = Self {
+            key_version: response.key_version,
+            ..Default::default()
+        };
+        if let Some(rotation) = response.rotation {
+            ret.rotating = true;
+            ret.target_version = rotation.target_version;
+            ret.copied_keys = rotation.copied_keys;
+            ret.total_keys = rotation.total_keys;
+            ret.started_timestamp = rotation
+                .started_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+        }
+        ret
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateSnapshotRequest> for CreateSnapshotRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateSnapshotRequest) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(&proto.url)?,
+        })
+    }
+}
+
+impl From<CreateSnapshotRequest> for proto::CreateSnapshotRequest {
+    fn from(request: CreateSnapshotRequest) -> Self {
+        Self {
+            url: request.url.into_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateSnapshotResponse> for CreateSnapshotResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateSnapshotResponse) -> Result<Self> {
+        Ok(Self {
+            key_count: proto.key_count,
+            byte_count: proto.byte_count,
+        })
+    }
+}
+
+impl From<CreateSnapshotResponse> for proto::CreateSnapshotResponse {
+    fn from(response: CreateSnapshotResponse) -> Self {
+        Self {
+            key_count: response.key_count,
+            byte_count: response.byte_count,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AppendAuditEventRequest> for AppendAuditEventRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::AppendAuditEventRequest) -> Result<Self> {
+        let event = serde_json::from_slice(&proto.event)?;
+        Ok(Self { event })
+    }
+}
+
+impl From<AppendAuditEventRequest> for proto::AppendAuditEventRequest {
+    fn from(request: AppendAuditEventRequest) -> Self {
+        Self {
+            event: serde_json::to_vec(&request.event).unwrap(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AppendAuditEventResponse> for AppendAuditEventResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::AppendAuditEventResponse) -> Result<Self> {
+        Ok(Self { seq: proto.seq })
+    }
+}
+
+impl From<AppendAuditEventResponse> for proto::AppendAuditEventResponse {
+    fn from(response: AppendAuditEventResponse) -> Self {
+        Self { seq: response.seq }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportAuditLogRequest> for ExportAuditLogRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportAuditLogRequest) -> Result<Self> {
+        Ok(Self {
+            start_seq: proto.start_seq,
+            limit: proto.limit,
+        })
+    }
+}
+
+impl From<ExportAuditLogRequest> for proto::ExportAuditLogRequest {
+    fn from(request: ExportAuditLogRequest) -> Self {
+        Self {
+            start_seq: request.start_seq,
+            limit: request.limit,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportAuditLogResponse> for ExportAuditLogResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportAuditLogResponse) -> Result<Self> {
+        let entries = proto
+            .entries
+            .iter()
+            .map(|entry| AuditLogEntry::from_slice(entry))
+            .collect::<Result<_>>()?;
+        let broken_seq = if proto.broken {
+            Some(proto.broken_seq)
+        } else {
+            None
+        };
+        Ok(Self {
+            entries,
+            log_len: proto.log_len,
+            broken_seq,
+        })
+    }
+}
+
+impl From<ExportAuditLogResponse> for proto::ExportAuditLogResponse {
+    fn from(response: ExportAuditLogResponse) -> Self {
+        Self {
+            entries: response
+                .entries
+                .iter()
+                .map(|entry| entry.to_vec().unwrap())
+                .collect(),
+            log_len: response.log_len,
+            broken: response.broken_seq.is_some(),
+            broken_seq: response.broken_seq.unwrap_or_default(),
         }
     }
 }
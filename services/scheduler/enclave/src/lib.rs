@@ -22,6 +22,7 @@ extern crate sgx_tstd as std;
 
 #[cfg(feature = "mesalock_sgx")]
 use std::prelude::v1::*;
+use std::sync::Arc;
 
 #[macro_use]
 extern crate log;
@@ -38,14 +39,18 @@ use teaclave_config::RuntimeConfig;
 use teaclave_proto::teaclave_scheduler_service::{
     TeaclaveSchedulerRequest, TeaclaveSchedulerResponse,
 };
-use teaclave_rpc::config::SgxTrustedTlsServerConfig;
-use teaclave_rpc::server::SgxTrustedTlsServer;
+use teaclave_rpc::config::{SgxTrustedTlsServerConfig, TlsPolicy};
+use teaclave_rpc::interceptor::LoggingInterceptor;
+use teaclave_rpc::server::{SgxTrustedTlsServer, DEFAULT_IDLE_TIMEOUT};
 use teaclave_service_enclave_utils::create_trusted_storage_endpoint;
 use teaclave_service_enclave_utils::ServiceEnclave;
 use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
 
 mod error;
+mod policy;
+mod preemption;
 mod publisher;
+mod queues;
 mod service;
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
@@ -69,17 +74,23 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         .collect::<Result<_>>()?;
     let server_config =
         SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config.clone())?
-            .attestation_report_verifier(
+            .attestation_report_verifier_with_policy(
                 accepted_enclave_attrs,
                 AS_ROOT_CA_CERT,
                 verifier::universal_quote_verifier,
-            )?;
+                verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+            )?
+            .tls_policy(TlsPolicy::from_teaclave_config(&config))?;
 
     let mut server =
         SgxTrustedTlsServer::<TeaclaveSchedulerResponse, TeaclaveSchedulerRequest>::new(
             listen_address,
             server_config,
-        );
+        )
+        .message_limits(&config.internal_endpoints.scheduler.message_limits)
+        .unix_socket(config.internal_endpoints.scheduler.unix_socket.clone())
+        .idle_timeout(Some(DEFAULT_IDLE_TIMEOUT))
+        .interceptor(Arc::new(LoggingInterceptor::new("scheduler")));
 
     let storage_service_address = &config.internal_endpoints.storage.advertised_address;
     let storage_service_endpoint = create_trusted_storage_endpoint(
@@ -87,10 +98,14 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         &enclave_info,
         AS_ROOT_CA_CERT,
         verifier::universal_quote_verifier,
+        verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+        TlsPolicy::from_teaclave_config(&config),
         attested_tls_config,
-    )?;
+    )?
+    .message_limits(&config.internal_endpoints.storage.message_limits);
 
-    let service = service::TeaclaveSchedulerService::new(storage_service_endpoint)?;
+    let service =
+        service::TeaclaveSchedulerService::new(storage_service_endpoint, &config.scheduling)?;
     match server.start(service) {
         Ok(_) => (),
         Err(e) => {
@@ -132,8 +147,17 @@ register_ecall_handler!(
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
+    use super::*;
+    use teaclave_test_utils::*;
 
     pub fn run_tests() -> bool {
-        true
+        run_tests!(
+            policy::tests::test_can_launch,
+            policy::tests::test_cost_based_placement,
+            policy::tests::test_least_loaded,
+            policy::tests::test_placement_stats_workers,
+            preemption::tests::test_preemption_candidates,
+            queues::tests::test_priority_order,
+        )
     }
 }
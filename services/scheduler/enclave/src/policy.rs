@@ -0,0 +1,300 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::sync::Arc;
+use teaclave_config::{SchedulingConfig, SchedulingPolicyKind};
+use teaclave_types::{platform, ExecutorNode, MrEnclave, StagedTask, Storable};
+use uuid::Uuid;
+
+const PLACEMENT_STATS_PREFIX: &str = "placement"; // placement-function-uuid
+
+// Runs of tasks of a function on a worker before its mean runtime is used.
+const MIN_RUNS: u64 = 3;
+// Weight of the last run in the mean runtime of a worker.
+const RUNTIME_WEIGHT: f64 = 0.2;
+// Workers kept in the statistics of a function, the most recently run first.
+// The ids of workers change when they restart.
+const MAX_WORKERS: usize = 32;
+
+/// Runtimes of the tasks of a worker.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub(crate) struct WorkerRuntime {
+    pub(crate) runs: u64,
+    /// Moving average of the runtimes, in milliseconds.
+    pub(crate) mean_ms: f64,
+    /// Time of the last run, in seconds since the Unix epoch.
+    pub(crate) last_run_secs: u64,
+}
+
+/// Runtimes of the tasks of a function on each worker, from the dispatch of
+/// the tasks to their results. Stored in the storage service, so that they
+/// outlive the scheduler.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct PlacementStats {
+    pub(crate) function_id: Uuid,
+    pub(crate) workers: HashMap<String, WorkerRuntime>,
+}
+
+impl Storable for PlacementStats {
+    fn key_prefix() -> &'static str {
+        PLACEMENT_STATS_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.function_id
+    }
+}
+
+impl PlacementStats {
+    pub(crate) fn new(function_id: Uuid) -> Self {
+        Self {
+            function_id,
+            workers: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, worker_id: &str, runtime_ms: u64, now_secs: u64) {
+        let runtime = self.workers.entry(worker_id.to_string()).or_default();
+        runtime.mean_ms = if runtime.runs == 0 {
+            runtime_ms as f64
+        } else {
+            runtime.mean_ms * (1.0 - RUNTIME_WEIGHT) + runtime_ms as f64 * RUNTIME_WEIGHT
+        };
+        runtime.runs += 1;
+        runtime.last_run_secs = now_secs;
+
+        if self.workers.len() > MAX_WORKERS {
+            let oldest = self
+                .workers
+                .iter()
+                .min_by_key(|(_, runtime)| runtime.last_run_secs)
+                .map(|(worker_id, _)| worker_id.clone());
+            if let Some(worker_id) = oldest {
+                self.workers.remove(&worker_id);
+            }
+        }
+    }
+
+    // Mean runtime of the worker, if known well enough.
+    fn mean_ms(&self, worker_id: &str) -> Option<f64> {
+        self.workers
+            .get(worker_id)
+            .filter(|runtime| runtime.runs >= MIN_RUNS)
+            .map(|runtime| runtime.mean_ms)
+    }
+}
+
+/// Decides whether a staged task is dispatched to the worker pulling it, or
+/// left in its queue for other workers.
+pub(crate) trait SchedulingPolicy: Send + Sync {
+    /// Whether the policy uses the runtimes of the function of the task.
+    fn uses_stats(&self) -> bool;
+
+    /// Whether to dispatch a task to `worker_id`, given the runtimes of its
+    /// function and the workers seen recently.
+    fn dispatch(&self, stats: &PlacementStats, worker_id: &str, workers: &[&str]) -> bool;
+}
+
+/// Dispatches tasks to any worker, in the order of the priority queues.
+pub(crate) struct PriorityPolicy;
+
+impl SchedulingPolicy for PriorityPolicy {
+    fn uses_stats(&self) -> bool {
+        false
+    }
+
+    fn dispatch(&self, _stats: &PlacementStats, _worker_id: &str, _workers: &[&str]) -> bool {
+        true
+    }
+}
+
+/// Leaves tasks to the workers on which tasks of the same function ran
+/// fastest, e.g., with warm caches or faster CPUs. Workers without enough
+/// runs, and a fraction of the tasks, are dispatched tasks regardless, so
+/// that the runtimes of all workers are known.
+pub(crate) struct CostBasedPolicy {
+    exploration: f64,
+    tolerance: f64,
+}
+
+impl CostBasedPolicy {
+    pub(crate) fn new(exploration: f64, tolerance: f64) -> Self {
+        Self {
+            exploration,
+            tolerance,
+        }
+    }
+
+    // Decides with a random number in [0, 1) drawn for the exploration.
+    fn dispatch_with(
+        &self,
+        stats: &PlacementStats,
+        worker_id: &str,
+        workers: &[&str],
+        roll: f64,
+    ) -> bool {
+        let mean_ms = match stats.mean_ms(worker_id) {
+            Some(mean_ms) => mean_ms,
+            None => return true,
+        };
+        if roll < self.exploration {
+            return true;
+        }
+        let best_ms = workers
+            .iter()
+            .filter_map(|worker| stats.mean_ms(worker))
+            .fold(mean_ms, f64::min);
+        mean_ms <= best_ms * (1.0 + self.tolerance)
+    }
+}
+
+impl SchedulingPolicy for CostBasedPolicy {
+    fn uses_stats(&self) -> bool {
+        true
+    }
+
+    fn dispatch(&self, stats: &PlacementStats, worker_id: &str, workers: &[&str]) -> bool {
+        let roll = platform::rand::random_u64() as f64 / u64::MAX as f64;
+        self.dispatch_with(stats, worker_id, workers, roll)
+    }
+}
+
+/// Whether the worker can run the task, i.e., launch the executor enclave of
+/// its function if it has one, with the labels of its node satisfying the
+/// affinity of the task. Checked before the scheduling policy.
+pub(crate) fn can_launch(
+    staged_task: &StagedTask,
+    executor_enclaves: &[MrEnclave],
+    labels: &[String],
+) -> bool {
+    let can_launch_enclave = match &staged_task.executor_enclave {
+        Some(measurement) => executor_enclaves.contains(&measurement.mr_enclave),
+        None => true,
+    };
+    can_launch_enclave && staged_task.affinity.matches(labels)
+}
+
+/// Whether no other node with a free slot runs a smaller fraction of its
+/// slots than the node of the worker. Workers which did not register their
+/// node are not balanced.
+pub(crate) fn is_least_loaded(worker_id: &str, nodes: &[ExecutorNode]) -> bool {
+    let utilization = match nodes.iter().find(|node| node.worker_id == worker_id) {
+        Some(node) => node.utilization(),
+        None => return true,
+    };
+    nodes
+        .iter()
+        .filter(|node| node.has_free_slot())
+        .all(|node| node.utilization() >= utilization)
+}
+
+pub(crate) fn from_config(config: &SchedulingConfig) -> Arc<dyn SchedulingPolicy> {
+    match config.policy {
+        SchedulingPolicyKind::Priority => Arc::new(PriorityPolicy),
+        SchedulingPolicyKind::CostBased => {
+            Arc::new(CostBasedPolicy::new(config.exploration, config.tolerance))
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_types::{EnclaveMeasurement, MrSigner, NodeCapacity, TaskAffinity};
+
+    pub fn test_cost_based_placement() {
+        let policy = CostBasedPolicy::new(0.1, 0.2);
+        let mut stats = PlacementStats::new(Uuid::new_v4());
+        let workers = ["fast", "slow"];
+
+        // Workers are dispatched tasks until their runtimes are known.
+        assert!(policy.dispatch_with(&stats, "slow", &workers, 0.5));
+        for _ in 0..MIN_RUNS {
+            stats.record("fast", 100, 0);
+            stats.record("slow", 1000, 0);
+        }
+        assert!(policy.dispatch_with(&stats, "fast", &workers, 0.5));
+        assert!(!policy.dispatch_with(&stats, "slow", &workers, 0.5));
+
+        // Except for the exploration fraction.
+        assert!(policy.dispatch_with(&stats, "slow", &workers, 0.05));
+
+        // Tasks are dispatched to slower workers if faster ones are gone, or
+        // are within the tolerance.
+        assert!(policy.dispatch_with(&stats, "slow", &["slow"], 0.5));
+        for _ in 0..MIN_RUNS {
+            stats.record("close", 110, 0);
+        }
+        assert!(policy.dispatch_with(&stats, "close", &["fast", "close"], 0.5));
+    }
+
+    pub fn test_can_launch() {
+        let mr_enclave = MrEnclave::new([1; 32]);
+        let measurement = EnclaveMeasurement::new(mr_enclave, MrSigner::default());
+        let task = StagedTask::new();
+        assert!(can_launch(&task, &[], &[]));
+
+        let task = task.executor_enclave(measurement);
+        assert!(!can_launch(&task, &[], &[]));
+        assert!(!can_launch(&task, &[MrEnclave::new([2; 32])], &[]));
+        assert!(can_launch(
+            &task,
+            &[MrEnclave::new([2; 32]), mr_enclave],
+            &[]
+        ));
+
+        let labels = vec!["icelake".to_string()];
+        let task = StagedTask::new().affinity(TaskAffinity::new().affinity("icelake"));
+        assert!(can_launch(&task, &[], &labels));
+        assert!(!can_launch(&task, &[], &[]));
+        let task = StagedTask::new().affinity(TaskAffinity::new().anti_affinity("icelake"));
+        assert!(!can_launch(&task, &[], &labels));
+    }
+
+    pub fn test_least_loaded() {
+        let node = |worker_id, slots, running| {
+            let capacity = NodeCapacity {
+                slots,
+                ..NodeCapacity::default()
+            };
+            ExecutorNode {
+                running,
+                ..ExecutorNode::new(worker_id, capacity, 0)
+            }
+        };
+        let nodes = [node("small", 2, 1), node("large", 8, 2), node("full", 1, 1)];
+        assert!(is_least_loaded("large", &nodes));
+        assert!(!is_least_loaded("small", &nodes));
+        assert!(is_least_loaded("unregistered", &nodes));
+
+        // Nodes without slots are not waited for.
+        let nodes = [node("small", 2, 1), node("drained", 0, 0)];
+        assert!(is_least_loaded("small", &nodes));
+    }
+
+    pub fn test_placement_stats_workers() {
+        let mut stats = PlacementStats::new(Uuid::new_v4());
+        for i in 0..MAX_WORKERS as u64 + 1 {
+            stats.record(&format!("worker-{}", i), 100, i);
+        }
+        assert_eq!(stats.workers.len(), MAX_WORKERS);
+        assert!(!stats.workers.contains_key("worker-0"));
+    }
+}
@@ -0,0 +1,204 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Preemption of running tasks for tasks of higher priority waiting while no
+//! node has a free slot. The worker cancels the preempted task, which the
+//! scheduler stages again once its result arrives.
+
+use std::prelude::v1::*;
+use std::time::Duration;
+
+use teaclave_config::{PreemptionPolicyKind, SchedulingConfig};
+use teaclave_types::{ExecutorNode, StagedTask, TaskPriority};
+use uuid::Uuid;
+
+/// A task running on a worker, which may be preempted.
+pub(crate) struct RunningTask<'a> {
+    pub(crate) staged_task: &'a StagedTask,
+    pub(crate) worker_id: &'a str,
+    pub(crate) runtime: Duration,
+}
+
+pub(crate) struct PreemptionPolicy {
+    kind: PreemptionPolicyKind,
+    min_runtime: Duration,
+    max_preemptions: u32,
+}
+
+impl PreemptionPolicy {
+    pub(crate) fn from_config(config: &SchedulingConfig) -> Self {
+        Self {
+            kind: config.preemption,
+            min_runtime: Duration::from_secs(config.min_runtime_before_preemption_secs),
+            max_preemptions: config.max_preemptions,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.kind != PreemptionPolicyKind::None
+    }
+
+    pub(crate) fn max_preemptions(&self) -> u32 {
+        self.max_preemptions
+    }
+
+    /// Whether a task of the running priority may be preempted for one of
+    /// the waiting priority.
+    pub(crate) fn preempts(&self, waiting: TaskPriority, running: TaskPriority) -> bool {
+        match self.kind {
+            PreemptionPolicyKind::None => false,
+            PreemptionPolicyKind::BatchForInteractive => {
+                waiting == TaskPriority::Interactive && running == TaskPriority::Batch
+            }
+            PreemptionPolicyKind::LowerPriority => rank(waiting) < rank(running),
+        }
+    }
+
+    /// Returns the running tasks which may be preempted for the waiting
+    /// task, the best first: of the lowest priority, then running for the
+    /// shortest time so that the least work is lost. Only tasks on nodes
+    /// whose labels satisfy the affinity of the waiting task are preempted.
+    /// Tasks in executor enclaves are not, as the call to the enclave cannot
+    /// be canceled, nor are tasks for waiting tasks of executor enclaves,
+    /// which the scheduler cannot tell the workers of.
+    pub(crate) fn candidates(
+        &self,
+        waiting: &StagedTask,
+        running: &[RunningTask],
+        nodes: &[ExecutorNode],
+    ) -> Vec<Uuid> {
+        if waiting.executor_enclave.is_some() {
+            return Vec::new();
+        }
+        let mut candidates: Vec<&RunningTask> = running
+            .iter()
+            .filter(|task| {
+                task.staged_task.executor_enclave.is_none()
+                    && task.runtime >= self.min_runtime
+                    && self.preempts(waiting.priority, task.staged_task.priority)
+                    && nodes.iter().any(|node| {
+                        node.worker_id == task.worker_id && waiting.affinity.matches(&node.labels)
+                    })
+            })
+            .collect();
+        candidates.sort_by_key(|task| {
+            (
+                std::cmp::Reverse(rank(task.staged_task.priority)),
+                task.runtime,
+            )
+        });
+        candidates
+            .iter()
+            .map(|task| task.staged_task.task_id)
+            .collect()
+    }
+}
+
+// Zero for the highest priority.
+fn rank(priority: TaskPriority) -> usize {
+    TaskPriority::ALL
+        .iter()
+        .position(|p| *p == priority)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_types::{NodeCapacity, TaskAffinity};
+
+    pub fn test_preemption_candidates() {
+        let config = SchedulingConfig {
+            preemption: PreemptionPolicyKind::LowerPriority,
+            min_runtime_before_preemption_secs: 30,
+            ..SchedulingConfig::default()
+        };
+        let policy = PreemptionPolicy::from_config(&config);
+        assert!(policy.preempts(TaskPriority::Interactive, TaskPriority::Normal));
+        assert!(!policy.preempts(TaskPriority::Normal, TaskPriority::Normal));
+        assert!(!policy.preempts(TaskPriority::Batch, TaskPriority::Normal));
+
+        let nodes = [
+            ExecutorNode::new("a", NodeCapacity::default(), 0),
+            ExecutorNode::new("b", NodeCapacity::default(), 0).labels(vec!["gpu".to_string()]),
+        ];
+        let task = |priority| StagedTask::new().task_id(Uuid::new_v4()).priority(priority);
+        let normal = task(TaskPriority::Normal);
+        let batch = task(TaskPriority::Batch);
+        let short_batch = task(TaskPriority::Batch);
+        let just_started = task(TaskPriority::Batch);
+        let running = [
+            RunningTask {
+                staged_task: &normal,
+                worker_id: "a",
+                runtime: Duration::from_secs(60),
+            },
+            RunningTask {
+                staged_task: &batch,
+                worker_id: "a",
+                runtime: Duration::from_secs(600),
+            },
+            RunningTask {
+                staged_task: &short_batch,
+                worker_id: "b",
+                runtime: Duration::from_secs(60),
+            },
+            RunningTask {
+                staged_task: &just_started,
+                worker_id: "b",
+                runtime: Duration::from_secs(10),
+            },
+        ];
+
+        // Batch tasks first, the most recently started first.
+        let waiting = StagedTask::new().priority(TaskPriority::Interactive);
+        assert_eq!(
+            policy.candidates(&waiting, &running, &nodes),
+            vec![short_batch.task_id, batch.task_id, normal.task_id]
+        );
+        let waiting = StagedTask::new().priority(TaskPriority::Normal);
+        assert_eq!(
+            policy.candidates(&waiting, &running, &nodes),
+            vec![short_batch.task_id, batch.task_id]
+        );
+
+        // Only tasks on nodes satisfying the affinity of the waiting task.
+        let waiting = StagedTask::new()
+            .priority(TaskPriority::Interactive)
+            .affinity(TaskAffinity::new().affinity("gpu"));
+        assert_eq!(
+            policy.candidates(&waiting, &running, &nodes),
+            vec![short_batch.task_id]
+        );
+
+        let config = SchedulingConfig {
+            preemption: PreemptionPolicyKind::BatchForInteractive,
+            ..config
+        };
+        let policy = PreemptionPolicy::from_config(&config);
+        let waiting = StagedTask::new().priority(TaskPriority::Interactive);
+        assert_eq!(
+            policy.candidates(&waiting, &running, &nodes),
+            vec![short_batch.task_id, batch.task_id]
+        );
+        let waiting = StagedTask::new().priority(TaskPriority::Normal);
+        assert!(policy.candidates(&waiting, &running, &nodes).is_empty());
+
+        let policy = PreemptionPolicy::from_config(&SchedulingConfig::default());
+        assert!(!policy.is_enabled());
+    }
+}
@@ -0,0 +1,101 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::prelude::v1::*;
+use teaclave_types::TaskPriority;
+
+// Dispatches of tasks of higher priority after which a queue is polled first,
+// so that tasks of lower priority are not starved.
+const STARVATION_LIMIT: u32 = 4;
+
+/// Order in which the queues of staged tasks are polled: from the highest
+/// priority, except for queues skipped `STARVATION_LIMIT` times in a row.
+/// The storage cannot tell the length of queues, so a queue counts as skipped
+/// until it is found empty.
+#[derive(Default)]
+pub(crate) struct PriorityQueues {
+    // Dispatches of tasks of higher priority since the queue was last
+    // polled, indexed like TaskPriority::ALL.
+    skipped: [u32; 3],
+}
+
+impl PriorityQueues {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The priorities of the queues to poll, in order.
+    pub(crate) fn order(&self) -> Vec<TaskPriority> {
+        let (mut starved, rest): (Vec<_>, Vec<_>) = TaskPriority::ALL
+            .iter()
+            .enumerate()
+            .partition(|(index, _)| self.skipped[*index] >= STARVATION_LIMIT);
+        starved.extend(rest);
+        starved.into_iter().map(|(_, priority)| *priority).collect()
+    }
+
+    /// Records the dispatch of a task of the priority.
+    pub(crate) fn dispatched(&mut self, priority: TaskPriority) {
+        let index = position(priority);
+        self.skipped[index] = 0;
+        for skipped in self.skipped.iter_mut().skip(index + 1) {
+            *skipped += 1;
+        }
+    }
+
+    /// Records that the queue of the priority had no task.
+    pub(crate) fn empty(&mut self, priority: TaskPriority) {
+        self.skipped[position(priority)] = 0;
+    }
+}
+
+fn position(priority: TaskPriority) -> usize {
+    TaskPriority::ALL
+        .iter()
+        .position(|p| *p == priority)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_priority_order() {
+        let mut queues = PriorityQueues::new();
+        assert_eq!(queues.order(), TaskPriority::ALL.to_vec());
+
+        // Batch jobs go first after STARVATION_LIMIT other dispatches.
+        for _ in 0..STARVATION_LIMIT {
+            assert_eq!(queues.order()[0], TaskPriority::Interactive);
+            queues.dispatched(TaskPriority::Interactive);
+        }
+        assert_eq!(
+            queues.order(),
+            vec![
+                TaskPriority::Normal,
+                TaskPriority::Batch,
+                TaskPriority::Interactive
+            ]
+        );
+        queues.dispatched(TaskPriority::Normal);
+        assert_eq!(queues.order()[0], TaskPriority::Batch);
+
+        // Queues found empty are no longer starved.
+        queues.empty(TaskPriority::Batch);
+        assert_eq!(queues.order(), TaskPriority::ALL.to_vec());
+    }
+}
@@ -16,32 +16,98 @@
 // under the License.
 
 use crate::error::TeaclaveSchedulerError;
+use crate::policy::{self, PlacementStats, SchedulingPolicy};
+use crate::preemption::{PreemptionPolicy, RunningTask};
+use crate::queues::PriorityQueues;
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
 use std::prelude::v1::*;
 use std::sync::{Arc, SgxMutex as Mutex};
+use std::time::{Duration, SystemTime};
 
+use teaclave_config::SchedulingConfig;
+use teaclave_proto::teaclave_common::HealthCheck;
 use teaclave_proto::teaclave_scheduler_service::*;
 use teaclave_proto::teaclave_storage_service::*;
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::teaclave_service;
+use teaclave_service_enclave_utils::{health, teaclave_service};
 use teaclave_types::*;
 use uuid::Uuid;
 
 use anyhow::anyhow;
 use anyhow::Result;
 
+// Skews of the clocks of workers above which they are logged, and the
+// scheduler is reported as unhealthy.
+const CLOCK_SKEW_WARNING: Duration = Duration::from_secs(1);
+const CLOCK_SKEW_UNHEALTHY: Duration = Duration::from_secs(30);
+// Tasks left by the scheduling policy for other workers are dispatched to any
+// worker after this long.
+const MAX_DEFERRAL: Duration = Duration::from_secs(10);
+// Dispatches without results after this long are forgotten, e.g., of workers
+// which failed.
+const DISPATCH_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+// Interval of writing the snapshot of the execution nodes on heartbeats.
+const NODES_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+// Interval of looking for waiting tasks to preempt running tasks for on
+// heartbeats, each of which queues the first tasks again.
+const PREEMPTION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+struct ClockSample {
+    offset: ClockOffset,
+    received: SystemTime,
+}
+
+impl ClockSample {
+    fn is_expired(&self, now: SystemTime, node_timeout: Duration) -> bool {
+        now.duration_since(self.received).unwrap_or_default() > node_timeout
+    }
+}
+
+// A task dispatched to a worker, whose runtime is recorded with its result,
+// and which is queued again if the worker is lost.
+struct Dispatch {
+    staged_task: StagedTask,
+    worker_id: String,
+    dispatched: SystemTime,
+}
+
 #[teaclave_service(teaclave_scheduler_service, TeaclaveScheduler, TeaclaveSchedulerError)]
 #[derive(Clone)]
 pub(crate) struct TeaclaveSchedulerService {
     storage_client: Arc<Mutex<TeaclaveStorageClient>>,
     task_queue: Arc<Mutex<VecDeque<StagedTask>>>,
+    // Order of polling the queues of staged tasks of each priority
+    priority_queues: Arc<Mutex<PriorityQueues>>,
+    clock_samples: Arc<Mutex<HashMap<String, ClockSample>>>,
+    policy: Arc<dyn SchedulingPolicy>,
+    // Runtimes of the functions of the tasks, read from the storage once
+    placement_stats: Arc<Mutex<HashMap<Uuid, PlacementStats>>>,
+    // Tasks left for other workers, with the time they were first left
+    deferred: Arc<Mutex<HashMap<Uuid, SystemTime>>>,
+    dispatches: Arc<Mutex<HashMap<Uuid, Dispatch>>>,
+    // Registered execution nodes, whose running tasks are counted from the
+    // dispatches
+    nodes: Arc<Mutex<HashMap<String, ExecutorNode>>>,
+    nodes_snapshot_at: Arc<Mutex<SystemTime>>,
+    // Workers are forgotten, and their tasks queued again, after this long
+    // without a heartbeat.
+    node_timeout: Duration,
+    max_requeues: u32,
+    preemption: Arc<PreemptionPolicy>,
+    // Tasks preempted and not staged again yet, with their workers. One task
+    // is preempted at a time.
+    preempted: Arc<Mutex<HashMap<Uuid, String>>>,
+    preemption_checked_at: Arc<Mutex<SystemTime>>,
 }
 
 impl TeaclaveSchedulerService {
-    pub(crate) fn new(storage_service_endpoint: Endpoint) -> Result<Self> {
+    pub(crate) fn new(
+        storage_service_endpoint: Endpoint,
+        scheduling_config: &SchedulingConfig,
+    ) -> Result<Self> {
         let mut i = 0;
         let channel = loop {
             match storage_service_endpoint.connect() {
@@ -59,6 +125,19 @@ impl TeaclaveSchedulerService {
         let service = Self {
             storage_client,
             task_queue,
+            priority_queues: Arc::new(Mutex::new(PriorityQueues::new())),
+            clock_samples: Arc::new(Mutex::new(HashMap::new())),
+            policy: crate::policy::from_config(scheduling_config),
+            placement_stats: Arc::new(Mutex::new(HashMap::new())),
+            deferred: Arc::new(Mutex::new(HashMap::new())),
+            dispatches: Arc::new(Mutex::new(HashMap::new())),
+            nodes: Arc::new(Mutex::new(HashMap::new())),
+            nodes_snapshot_at: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
+            node_timeout: Duration::from_secs(scheduling_config.node_timeout_secs),
+            max_requeues: scheduling_config.max_requeues,
+            preemption: Arc::new(PreemptionPolicy::from_config(scheduling_config)),
+            preempted: Arc::new(Mutex::new(HashMap::new())),
+            preemption_checked_at: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
         };
 
         Ok(service)
@@ -76,35 +155,467 @@ impl TeaclaveSchedulerService {
             .map_err(|_| TeaclaveSchedulerError::DataError.into())
     }
 
+    fn push_staged_task(&self, key: &[u8], staged_task: &StagedTask) -> Result<()> {
+        let enqueue_request = EnqueueRequest::new(key, staged_task.to_vec()?);
+        self.storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .enqueue(enqueue_request)?;
+        Ok(())
+    }
+
+    // Whether the task is dispatched to the worker, or left for less loaded
+    // nodes and the workers preferred by the scheduling policy for up to
+    // MAX_DEFERRAL.
+    fn place(&self, staged_task: &StagedTask, worker_id: &str) -> Result<bool> {
+        if worker_id.is_empty() {
+            return Ok(true);
+        }
+        let least_loaded = policy::is_least_loaded(worker_id, &self.executor_nodes()?);
+        if least_loaded && !self.policy.uses_stats() {
+            return Ok(true);
+        }
+        let now = platform::time::now();
+        let mut deferred = self
+            .deferred
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock deferred tasks"))?;
+        let since = *deferred.entry(staged_task.task_id).or_insert(now);
+        let dispatch = (least_loaded && self.dispatch_by_policy(staged_task, worker_id, now)?)
+            || now.duration_since(since).unwrap_or_default() > MAX_DEFERRAL;
+        if dispatch {
+            deferred.remove(&staged_task.task_id);
+        }
+        Ok(dispatch)
+    }
+
+    fn dispatch_by_policy(
+        &self,
+        staged_task: &StagedTask,
+        worker_id: &str,
+        now: SystemTime,
+    ) -> Result<bool> {
+        if !self.policy.uses_stats() {
+            return Ok(true);
+        }
+        let stats = self.placement_stats(&staged_task.function_id)?;
+        let samples = self
+            .clock_samples
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock clock samples"))?;
+        let workers: Vec<&str> = samples
+            .iter()
+            .filter(|(_, sample)| !sample.is_expired(now, self.node_timeout))
+            .map(|(worker_id, _)| worker_id.as_str())
+            .collect();
+        Ok(self.policy.dispatch(&stats, worker_id, &workers))
+    }
+
+    // The nodes seen recently, with the tasks dispatched to them.
+    fn executor_nodes(&self) -> Result<Vec<ExecutorNode>> {
+        let now = platform::time::now();
+        let now_secs = platform::time::since_epoch().as_secs();
+        let mut nodes: Vec<ExecutorNode> = self
+            .nodes
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock nodes"))?
+            .values()
+            .filter(|node| now_secs.saturating_sub(node.last_seen) <= self.node_timeout.as_secs())
+            .cloned()
+            .collect();
+        let dispatches = self
+            .dispatches
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock dispatches"))?;
+        for dispatch in dispatches.values() {
+            if now.duration_since(dispatch.dispatched).unwrap_or_default() >= DISPATCH_EXPIRY {
+                continue;
+            }
+            if let Some(node) = nodes
+                .iter_mut()
+                .find(|node| node.worker_id == dispatch.worker_id)
+            {
+                node.running += 1;
+            }
+        }
+        Ok(nodes)
+    }
+
+    // Writes the nodes to the storage for the management service, at most
+    // every NODES_SNAPSHOT_INTERVAL unless forced.
+    fn save_nodes_snapshot(&self, force: bool) -> Result<()> {
+        let now = platform::time::now();
+        {
+            let mut snapshot_at = self
+                .nodes_snapshot_at
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock nodes snapshot time"))?;
+            if !force
+                && now.duration_since(*snapshot_at).unwrap_or_default() < NODES_SNAPSHOT_INTERVAL
+            {
+                return Ok(());
+            }
+            *snapshot_at = now;
+        }
+        let snapshot = ExecutorNodes {
+            nodes: self.executor_nodes()?,
+            updated_at: platform::time::since_epoch().as_secs(),
+        };
+        let value = serde_json::to_vec(&snapshot)?;
+        let put_request = PutRequest::new(EXECUTOR_NODES_KEY.as_bytes(), value.as_slice());
+        self.storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .put(put_request)?;
+        Ok(())
+    }
+
+    // Queues again the tasks dispatched to workers without a heartbeat for
+    // node_timeout, e.g., of crashed execution services. Results of the
+    // tasks sent later by the workers are rejected.
+    fn requeue_lost_tasks(&self) -> Result<()> {
+        let now = platform::time::now();
+        let lost: Vec<Dispatch> = {
+            let samples = self
+                .clock_samples
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock clock samples"))?;
+            let mut dispatches = self
+                .dispatches
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock dispatches"))?;
+            let lost_ids: Vec<Uuid> = dispatches
+                .iter()
+                .filter(|(_, dispatch)| {
+                    let last_seen = samples
+                        .get(&dispatch.worker_id)
+                        .map_or(dispatch.dispatched, |sample| {
+                            std::cmp::max(sample.received, dispatch.dispatched)
+                        });
+                    now.duration_since(last_seen).unwrap_or_default() > self.node_timeout
+                })
+                .map(|(task_id, _)| *task_id)
+                .collect();
+            lost_ids
+                .iter()
+                .filter_map(|task_id| dispatches.remove(task_id))
+                .collect()
+        };
+        for dispatch in lost {
+            let task_id = dispatch.staged_task.task_id;
+            if let Err(e) = self.requeue(dispatch) {
+                log::warn!("Cannot queue lost task {} again: {:?}", task_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn requeue(&self, dispatch: Dispatch) -> Result<()> {
+        let staged_task = dispatch.staged_task;
+        let mut ts = self.get_task_state(&staged_task.task_id)?;
+        if ts.requeues >= self.max_requeues {
+            log::warn!(
+                "Task {} failed: worker {} lost, queued {} times",
+                staged_task.task_id,
+                dispatch.worker_id,
+                ts.requeues
+            );
+            let reason = format!("worker lost, task queued again {} times", ts.requeues);
+            let failure = TaskFailure {
+                reason: reason.clone(),
+                kind: TaskFailureKind::WorkerLost,
+            };
+            if self.retry(&mut ts, staged_task, &failure)? {
+                return Ok(());
+            }
+            ts.fail_lost(reason)?;
+            return self.put_into_db(&ts);
+        }
+        log::warn!(
+            "Task {} queued again: worker {} lost",
+            staged_task.task_id,
+            dispatch.worker_id
+        );
+        ts.requeue()?;
+        // Staged before it is queued, for the next worker to run it.
+        self.put_into_db(&ts)?;
+        let key = StagedTask::get_priority_queue_key(staged_task.priority);
+        self.push_staged_task(key.as_bytes(), &staged_task)
+    }
+
+    // Stages the failed task again after the backoff if its retry policy
+    // allows, returning whether it did.
+    fn retry(
+        &self,
+        ts: &mut TaskState,
+        staged_task: StagedTask,
+        failure: &TaskFailure,
+    ) -> Result<bool> {
+        let delay = match ts.retry_policy.retry_delay(ts.counted_attempts(), failure) {
+            Some(delay) => delay,
+            None => return Ok(false),
+        };
+        log::info!(
+            "Task {} retried in {}s after attempt {}: {}",
+            staged_task.task_id,
+            delay.as_secs(),
+            ts.attempts,
+            failure.reason
+        );
+        ts.retry(failure)?;
+        self.put_into_db(ts)?;
+        let not_before = platform::time::since_epoch().as_secs() + delay.as_secs();
+        let staged_task = staged_task.not_before(not_before);
+        let key = StagedTask::get_priority_queue_key(staged_task.priority);
+        self.push_staged_task(key.as_bytes(), &staged_task)?;
+        Ok(true)
+    }
+
+    // Stages the task canceled by its worker for a task of higher priority
+    // again, at the end of its queue.
+    fn stage_preempted(&self, ts: &mut TaskState, staged_task: StagedTask) -> Result<()> {
+        ts.preempt()?;
+        log::info!(
+            "Task {} staged again after {} preemptions",
+            staged_task.task_id,
+            ts.preemptions
+        );
+        self.put_into_db(ts)?;
+        let key = StagedTask::get_priority_queue_key(staged_task.priority);
+        self.push_staged_task(key.as_bytes(), &staged_task)
+    }
+
+    // Preempts a running task when a task of higher priority waits and no
+    // node has a free slot, and returns the preempted tasks of the worker,
+    // until their results arrive.
+    fn preempted_tasks(&self, worker_id: &str) -> Result<Vec<Uuid>> {
+        if !self.preemption.is_enabled() {
+            return Ok(Vec::new());
+        }
+        let mut preempted = self
+            .preempted
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock preempted tasks"))?;
+        {
+            let dispatches = self
+                .dispatches
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock dispatches"))?;
+            preempted.retain(|task_id, _| dispatches.contains_key(task_id));
+        }
+        if preempted.is_empty() && self.is_preemption_check_due()? {
+            if let Some((task_id, worker_id)) = self.select_preempted_task()? {
+                preempted.insert(task_id, worker_id);
+            }
+        }
+        Ok(preempted
+            .iter()
+            .filter(|(_, preempted_worker_id)| preempted_worker_id.as_str() == worker_id)
+            .map(|(task_id, _)| *task_id)
+            .collect())
+    }
+
+    fn is_preemption_check_due(&self) -> Result<bool> {
+        let now = platform::time::now();
+        let mut checked_at = self
+            .preemption_checked_at
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock preemption check time"))?;
+        if now.duration_since(*checked_at).unwrap_or_default() < PREEMPTION_CHECK_INTERVAL {
+            return Ok(false);
+        }
+        *checked_at = now;
+        Ok(true)
+    }
+
+    // The running task to preempt for the waiting task of the highest
+    // priority, with its worker.
+    fn select_preempted_task(&self) -> Result<Option<(Uuid, String)>> {
+        let nodes = self.executor_nodes()?;
+        if nodes.is_empty() || nodes.iter().any(ExecutorNode::has_free_slot) {
+            return Ok(None);
+        }
+        let waiting = match self.waiting_task()? {
+            Some(waiting) => waiting,
+            None => return Ok(None),
+        };
+        let now = platform::time::now();
+        let dispatches = self
+            .dispatches
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock dispatches"))?;
+        let running: Vec<RunningTask> = dispatches
+            .values()
+            .map(|dispatch| RunningTask {
+                staged_task: &dispatch.staged_task,
+                worker_id: &dispatch.worker_id,
+                runtime: now.duration_since(dispatch.dispatched).unwrap_or_default(),
+            })
+            .collect();
+        for task_id in self.preemption.candidates(&waiting, &running, &nodes) {
+            let ts = self.get_task_state(&task_id)?;
+            if ts.status != TaskStatus::Running
+                || ts.preemptions >= self.preemption.max_preemptions()
+            {
+                continue;
+            }
+            let worker_id = &dispatches[&task_id].worker_id;
+            log::info!(
+                "Task {} on worker {} preempted for task {} of {} priority",
+                task_id,
+                worker_id,
+                waiting.task_id,
+                waiting.priority
+            );
+            return Ok(Some((task_id, worker_id.clone())));
+        }
+        Ok(None)
+    }
+
+    // The first task of the queue of the highest priority above the lowest
+    // one. The storage cannot peek at queues, so the task is queued again at
+    // the end.
+    fn waiting_task(&self) -> Result<Option<StagedTask>> {
+        let _queues = self
+            .priority_queues
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock priority queues"))?;
+        let now_secs = platform::time::since_epoch().as_secs();
+        let higher_priorities = &TaskPriority::ALL[..TaskPriority::ALL.len() - 1];
+        for priority in higher_priorities {
+            let key = StagedTask::get_priority_queue_key(*priority).as_bytes();
+            let staged_task = match self.pull_staged_task::<StagedTask>(key) {
+                Ok(staged_task) => staged_task,
+                Err(_) => continue,
+            };
+            self.push_staged_task(key, &staged_task)?;
+            if staged_task.not_before <= now_secs {
+                return Ok(Some(staged_task));
+            }
+        }
+        Ok(None)
+    }
+
+    fn placement_stats(&self, function_id: &Uuid) -> Result<PlacementStats> {
+        let mut cache = self
+            .placement_stats
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock placement stats"))?;
+        if let Some(stats) = cache.get(function_id) {
+            return Ok(stats.clone());
+        }
+        let key = ExternalID::new(PlacementStats::key_prefix(), function_id.to_owned());
+        let stats = self
+            .get_from_db(&key)
+            .unwrap_or_else(|_| PlacementStats::new(*function_id));
+        cache.insert(*function_id, stats.clone());
+        Ok(stats)
+    }
+
+    // Records the runtime of the succeeded task from its dispatch for the
+    // scheduling policy.
+    fn record_runtime(&self, dispatch: Dispatch) -> Result<()> {
+        let now = platform::time::now();
+        let runtime = now.duration_since(dispatch.dispatched).unwrap_or_default();
+        let function_id = dispatch.staged_task.function_id;
+        let mut stats = self.placement_stats(&function_id)?;
+        stats.record(
+            &dispatch.worker_id,
+            runtime.as_millis() as u64,
+            platform::time::since_epoch().as_secs(),
+        );
+        self.put_into_db(&stats)?;
+        self.placement_stats
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock placement stats"))?
+            .insert(function_id, stats);
+        Ok(())
+    }
+
+    // Reports the largest skew of the workers seen recently.
+    fn clock_skew_check(&self) -> HealthCheck {
+        let now = platform::time::now();
+        let max_skew = match self.clock_samples.lock() {
+            Ok(samples) => samples
+                .values()
+                .filter(|sample| !sample.is_expired(now, self.node_timeout))
+                .map(|sample| sample.offset.min_skew())
+                .max()
+                .unwrap_or_default(),
+            Err(_) => return HealthCheck::unhealthy("clock_skew", "cannot lock clock samples"),
+        };
+        let detail = format!("max worker skew {}ms", max_skew.as_millis());
+        if max_skew > CLOCK_SKEW_UNHEALTHY {
+            HealthCheck::unhealthy("clock_skew", detail)
+        } else {
+            HealthCheck::healthy("clock_skew", detail)
+        }
+    }
+
     fn get_task_state(&self, task_id: &Uuid) -> Result<TaskState> {
         let key = ExternalID::new(TaskState::key_prefix(), task_id.to_owned());
         self.get_from_db(&key)
     }
 
+    // Records of tenants are kept in their namespaces, found by the locators
+    // of the records; others are kept outside of namespaces.
     fn get_from_db<T: Storable>(&self, key: &ExternalID) -> Result<T> {
         anyhow::ensure!(T::match_prefix(&key.prefix), "Key prefix doesn't match.");
-        let get_request = GetRequest::new(key.to_bytes());
-        let response = self
+        let mut client = self
             .storage_client
-            .clone()
             .lock()
-            .map_err(|_| anyhow!("Cannot lock storage client"))?
-            .get(get_request)?;
+            .map_err(|_| anyhow!("Cannot lock storage client"))?;
+        let mut get_request = GetRequest::new(key.to_bytes());
+        match client.get(GetRequest::new(locator_key(&key.to_bytes()))) {
+            Ok(response) => {
+                get_request = get_request.namespace(String::from_utf8(response.value)?);
+            }
+            Err(TeaclaveServiceResponseError::RequestError(_)) => (),
+            Err(e) => return Err(e.into()),
+        }
+        let response = client.get(get_request)?;
         T::from_slice(response.value.as_slice())
     }
 
     fn put_into_db(&self, item: &impl Storable) -> Result<()> {
         let k = item.key();
         let v = item.to_vec()?;
-        let put_request = PutRequest::new(k.as_slice(), v.as_slice());
-        let _put_response = self
+        let mut client = self
             .storage_client
-            .clone()
             .lock()
-            .map_err(|_| anyhow!("Cannot lock storage client"))?
-            .put(put_request)?;
+            .map_err(|_| anyhow!("Cannot lock storage client"))?;
+        match item.tenant() {
+            Some(tenant) => {
+                let namespace = tenant_namespace(tenant);
+                let batch_request = BatchRequest::new()
+                    .put(locator_key(&k), namespace.as_bytes())
+                    .delete(k.as_slice())
+                    .put_in(namespace, k.as_slice(), v);
+                client.batch(batch_request)?;
+            }
+            None => {
+                let put_request = PutRequest::new(k.as_slice(), v.as_slice());
+                client.put(put_request)?;
+            }
+        }
         Ok(())
     }
+
+    // Log messages are forwarded while the function runs and with its result,
+    // and appended to the log of the task, which keeps those of earlier runs.
+    fn append_task_log(&self, task_id: &Uuid, content: &[u8], truncated: bool) -> Result<()> {
+        if content.is_empty() && !truncated {
+            return Ok(());
+        }
+        let key = ExternalID::new(TaskLog::key_prefix(), *task_id);
+        let mut task_log = self
+            .get_from_db(&key)
+            .unwrap_or_else(|_| TaskLog::new(*task_id, Vec::new(), false));
+        task_log.append(content, truncated);
+        self.put_into_db(&task_log)
+    }
 }
 
 impl TeaclaveScheduler for TeaclaveSchedulerService {
@@ -132,16 +643,188 @@ impl TeaclaveScheduler for TeaclaveSchedulerService {
         unimplemented!()
     }
 
+    // Registers the node of a worker, replacing the node registered with the
+    // same worker id.
+    fn register_node(
+        &self,
+        request: Request<RegisterNodeRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterNodeResponse> {
+        let request = request.message;
+        if request.worker_id.is_empty() {
+            return Err(anyhow!("Empty worker id").into());
+        }
+        log::info!(
+            "Worker {} registered with {:?}, labels {:?}, executors {:?}",
+            request.worker_id,
+            request.capacity,
+            request.labels,
+            request.executors
+        );
+        let now_secs = platform::time::since_epoch().as_secs();
+        let node = ExecutorNode::new(&request.worker_id, request.capacity, now_secs)
+            .labels(request.labels)
+            .executors(request.executors)
+            .builtin_functions(request.builtin_functions);
+        self.nodes
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock nodes"))?
+            .insert(request.worker_id, node);
+        self.save_nodes_snapshot(true)?;
+        Ok(RegisterNodeResponse {})
+    }
+
+    // Dispatches the first task of the queues which the scheduling policy
+    // places on the worker. Tasks left for other workers, or waiting for the
+    // backoff of a retry, are queued again. Nothing is dispatched to workers
+    // without a free slot.
     fn pull_task(
         &self,
-        _request: Request<PullTaskRequest>,
+        request: Request<PullTaskRequest>,
     ) -> TeaclaveServiceResponseResult<PullTaskResponse> {
-        let key = StagedTask::get_queue_key().as_bytes();
-        let staged_task = self.pull_staged_task(key)?;
+        let PullTaskRequest {
+            worker_id,
+            executor_enclaves,
+            labels,
+        } = request.message;
+        if let Err(e) = self.requeue_lost_tasks() {
+            log::warn!("Cannot queue lost tasks again: {:?}", e);
+        }
+        let node = self
+            .executor_nodes()?
+            .into_iter()
+            .find(|node| node.worker_id == worker_id);
+        if let Some(node) = node {
+            if !node.has_free_slot() {
+                return Err(anyhow!("No free slot on worker {}", worker_id).into());
+            }
+        }
+        let mut queues = self
+            .priority_queues
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock priority queues"))?;
+        let mut result = Err(TeaclaveSchedulerError::StorageError.into());
+        let mut left = HashSet::new();
+        let now_secs = platform::time::since_epoch().as_secs();
+        'queues: for priority in queues.order() {
+            let key = StagedTask::get_priority_queue_key(priority).as_bytes();
+            loop {
+                let staged_task = match self.pull_staged_task::<StagedTask>(key) {
+                    Ok(staged_task) => staged_task,
+                    Err(e) => {
+                        result = Err(e);
+                        queues.empty(priority);
+                        break;
+                    }
+                };
+                // The queue has only tasks left for other workers.
+                if left.contains(&staged_task.task_id) {
+                    self.push_staged_task(key, &staged_task)?;
+                    break;
+                }
+                if staged_task.not_before > now_secs {
+                    self.push_staged_task(key, &staged_task)?;
+                    left.insert(staged_task.task_id);
+                    continue;
+                }
+                if policy::can_launch(&staged_task, &executor_enclaves, &labels)
+                    && self.place(&staged_task, &worker_id)?
+                {
+                    queues.dispatched(priority);
+                    result = Ok(staged_task);
+                    break 'queues;
+                }
+                self.push_staged_task(key, &staged_task)?;
+                left.insert(staged_task.task_id);
+            }
+        }
+        let staged_task = result?;
+        if !worker_id.is_empty() {
+            let now = platform::time::now();
+            let mut dispatches = self
+                .dispatches
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock dispatches"))?;
+            dispatches.retain(|_, dispatch| {
+                now.duration_since(dispatch.dispatched).unwrap_or_default() < DISPATCH_EXPIRY
+            });
+            dispatches.insert(
+                staged_task.task_id,
+                Dispatch {
+                    staged_task: staged_task.clone(),
+                    worker_id,
+                    dispatched: now,
+                },
+            );
+        }
+        if let Some(context) = &staged_task.trace_context {
+            log::info!(
+                "task dispatched: task_id={} trace_id={} parent_span_id={}",
+                staged_task.task_id,
+                context.trace_id,
+                context.span_id,
+            );
+        }
         let response = PullTaskResponse::new(staged_task);
         Ok(response)
     }
 
+    // Records the offset of the clock of the worker estimated from its
+    // previous heartbeat, and returns the scheduler time for the next
+    // estimate.
+    fn heartbeat(
+        &self,
+        request: Request<HeartbeatRequest>,
+    ) -> TeaclaveServiceResponseResult<HeartbeatResponse> {
+        let request = request.message;
+        let now = platform::time::now();
+        let offset = request.clock_offset;
+        if offset.min_skew() > CLOCK_SKEW_WARNING {
+            log::warn!(
+                "Clock of worker {} is {}ms ahead of the scheduler (skewed by at least {}ms)",
+                request.worker_id,
+                -offset.offset_ms,
+                offset.min_skew().as_millis()
+            );
+        }
+        let mut samples = self
+            .clock_samples
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock clock samples"))?;
+        samples.retain(|_, sample| !sample.is_expired(now, self.node_timeout));
+        samples.insert(
+            request.worker_id.clone(),
+            ClockSample {
+                offset,
+                received: now,
+            },
+        );
+        drop(samples);
+        let now_secs = platform::time::since_epoch().as_secs();
+        {
+            let mut nodes = self
+                .nodes
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock nodes"))?;
+            nodes.retain(|_, node| {
+                now_secs.saturating_sub(node.last_seen) <= self.node_timeout.as_secs()
+            });
+            if let Some(node) = nodes.get_mut(&request.worker_id) {
+                node.last_seen = now_secs;
+            }
+        }
+        if let Err(e) = self.save_nodes_snapshot(false) {
+            log::warn!("Cannot save snapshot of nodes: {:?}", e);
+        }
+        let preempted = self
+            .preempted_tasks(&request.worker_id)
+            .unwrap_or_else(|e| {
+                log::warn!("Cannot preempt tasks: {:?}", e);
+                Vec::new()
+            });
+        let scheduler_time_ms = platform::time::since_epoch().as_millis() as u64;
+        Ok(HeartbeatResponse::new(scheduler_time_ms).preempted(preempted))
+    }
+
     fn update_task_status(
         &self,
         request: Request<UpdateTaskStatusRequest>,
@@ -153,17 +836,58 @@ impl TeaclaveScheduler for TeaclaveSchedulerService {
         log::debug!("UpdateTaskStatus: Task {:?}", task);
         // Only TaskStatus::Running is implicitly allowed here.
 
-        let ts = TaskState::from(task);
+        let mut ts = TaskState::from(task);
+        ts.attempts += 1;
         self.put_into_db(&ts)?;
         Ok(UpdateTaskStatusResponse {})
     }
 
+    // Failed tasks are staged again as allowed by their retry policies, and
+    // preempted tasks regardless, unless their dispatches are unknown, e.g.,
+    // after a restart of the scheduler.
     fn update_task_result(
         &self,
         request: Request<UpdateTaskResultRequest>,
     ) -> TeaclaveServiceResponseResult<UpdateTaskResultResponse> {
         let request = request.message;
-        let ts = self.get_task_state(&request.task_id)?;
+        let mut ts = self.get_task_state(&request.task_id)?;
+        // Results of tasks no longer running, e.g., retried, are dropped.
+        if ts.status != TaskStatus::Running {
+            return Err(anyhow!("Task not running: {:?}", ts.status).into());
+        }
+        // The owners of the input data, or the creator of a task without
+        // inputs, review the outputs held by the content scanners.
+        if let Some(held) = request.held_outputs {
+            let mut reviewers =
+                UserList::unions(ts.assigned_inputs.values().map(|file| file.owner.clone()));
+            if reviewers.is_empty() {
+                reviewers.insert(ts.creator.clone());
+            }
+            self.put_into_db(&held.reviewers(reviewers))?;
+        }
+        let dispatch = self
+            .dispatches
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock dispatches"))?
+            .remove(&request.task_id);
+
+        // Written before the result, so that the log of a finished task is
+        // available.
+        if let Err(e) = self.append_task_log(&request.task_id, &request.log, request.log_truncated)
+        {
+            log::warn!("Cannot save log of task {}: {:?}", request.task_id, e);
+        }
+
+        if let (TaskResult::Err(failure), Some(dispatch)) = (&request.task_result, &dispatch) {
+            if failure.kind == TaskFailureKind::Preempted {
+                self.stage_preempted(&mut ts, dispatch.staged_task.clone())?;
+                return Ok(UpdateTaskResultResponse {});
+            }
+            if self.retry(&mut ts, dispatch.staged_task.clone(), failure)? {
+                return Ok(UpdateTaskResultResponse {});
+            }
+        }
+
         let mut task: Task<Finish> = ts.try_into()?;
 
         if let TaskResult::Ok(outputs) = &request.task_result {
@@ -173,6 +897,12 @@ impl TeaclaveScheduler for TeaclaveSchedulerService {
             }
         };
 
+        if let Some(dispatch) = dispatch.filter(|_| request.task_result.is_ok()) {
+            if let Err(e) = self.record_runtime(dispatch) {
+                log::warn!("Cannot record runtime of task {}: {:?}", request.task_id, e);
+            }
+        }
+
         // Updating task result means we have finished execution
         task.update_result(request.task_result)?;
         log::debug!("UpdateTaskResult: Task {:?}", task);
@@ -181,6 +911,53 @@ impl TeaclaveScheduler for TeaclaveSchedulerService {
         self.put_into_db(&ts)?;
         Ok(UpdateTaskResultResponse {})
     }
+
+    fn append_task_result_chunks(
+        &self,
+        request: Request<AppendTaskResultChunksRequest>,
+    ) -> TeaclaveServiceResponseResult<AppendTaskResultChunksResponse> {
+        let request = request.message;
+        let ts = self.get_task_state(&request.task_id)?;
+        // Chunks arriving after the result are dropped.
+        if ts.status != TaskStatus::Running {
+            return Err(anyhow!("Task not running: {:?}", ts.status).into());
+        }
+
+        self.append_task_log(&request.task_id, &request.log, request.log_truncated)?;
+        if request.chunks.is_empty() && !request.truncated {
+            return Ok(AppendTaskResultChunksResponse {});
+        }
+        let key = ExternalID::new(TaskResultStream::key_prefix(), request.task_id);
+        let mut stream = self
+            .get_from_db(&key)
+            .unwrap_or_else(|_| TaskResultStream::new(request.task_id));
+        stream.append(request.chunks, request.truncated);
+        self.put_into_db(&stream)?;
+        Ok(AppendTaskResultChunksResponse {})
+    }
+
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        let storage_response = self
+            .storage_client
+            .clone()
+            .lock()
+            .map_err(|_| TeaclaveSchedulerError::StorageError)?
+            .health(HealthRequest::new());
+        let queue_depth = self
+            .task_queue
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock task queue"))?
+            .len();
+        let checks = vec![
+            health::attestation_check(),
+            health::dependency_check("storage", storage_response),
+            self.clock_skew_check(),
+        ];
+        Ok(HealthResponse::new(checks).queue_depth(queue_depth as u64))
+    }
 }
 
 #[cfg(test_mode)]
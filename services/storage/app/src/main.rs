@@ -21,6 +21,9 @@ use std::sync::Arc;
 use std::thread;
 use teaclave_service_app_utils::{register_signals, TeaclaveServiceLauncher};
 
+// Use to import ocall
+pub use teaclave_file_agent::ocall_handle_file_request;
+
 const PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
 
 fn main() -> Result<()> {
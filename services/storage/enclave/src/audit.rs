@@ -0,0 +1,142 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// The audit log is kept in the database under the `audit-` prefix, which
+// other requests cannot write: entries at `audit-entry-<seq>` (big endian, so
+// that they sort in order) and the last entry at `audit-head`.
+
+use crate::backend::StorageBackend;
+use crate::error::TeaclaveStorageError;
+use crate::replication::ChangeLog;
+use crate::sealing;
+use anyhow::Result;
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_storage_service::{ExportAuditLogResponse, StorageChange};
+use teaclave_service_enclave_utils::ensure;
+use teaclave_types::{
+    verify_audit_chain, AuditEvent, AuditLogEntry, TeaclaveServiceResponseResult,
+};
+
+const AUDIT_PREFIX: &[u8] = b"audit-";
+const AUDIT_HEAD_KEY: &[u8] = b"audit-head";
+const AUDIT_ENTRY_PREFIX: &[u8] = b"audit-entry-";
+// Identifies the seal key of the audit log among the keys of the enclave.
+const SEAL_KEY_ID: &[u8] = b"teaclave-audit-log-seal-key";
+
+pub(crate) const MAX_EXPORT_ENTRIES: u32 = 1000;
+
+pub(crate) fn is_audit_key(key: &[u8]) -> bool {
+    key.starts_with(AUDIT_PREFIX)
+}
+
+fn entry_key(seq: u64) -> Vec<u8> {
+    let mut key = AUDIT_ENTRY_PREFIX.to_vec();
+    key.extend_from_slice(&seq.to_be_bytes());
+    key
+}
+
+/// The seal key of the audit log, see `sealing::derive_key`.
+pub(crate) fn seal_key() -> Result<Vec<u8>> {
+    Ok(sealing::derive_key(SEAL_KEY_ID)?.to_vec())
+}
+
+fn read_entry(
+    database: &mut dyn StorageBackend,
+    key: &[u8],
+) -> TeaclaveServiceResponseResult<Option<AuditLogEntry>> {
+    let value = database.get(key).map_err(TeaclaveStorageError::Backend)?;
+    Ok(value.and_then(|value| AuditLogEntry::from_slice(&value).ok()))
+}
+
+// Sealed entries are only appended by the primary, and replicated as they
+// are.
+pub(crate) fn append(
+    database: &mut dyn StorageBackend,
+    change_log: &mut ChangeLog,
+    seal_key: &[u8],
+    event: AuditEvent,
+) -> TeaclaveServiceResponseResult<u64> {
+    let head = read_entry(database, AUDIT_HEAD_KEY)?;
+    if let Some(head) = &head {
+        // Never extend a chain which has been tampered with.
+        ensure!(
+            head.verify_seal(seal_key).is_ok(),
+            TeaclaveStorageError::AuditLogBroken
+        );
+    }
+    let entry = AuditLogEntry::new(head.as_ref(), event, seal_key)
+        .map_err(|_| TeaclaveStorageError::AuditLogBroken)?;
+    let value = entry
+        .to_vec()
+        .map_err(|_| TeaclaveStorageError::AuditLogBroken)?;
+    for key in [entry_key(entry.seq), AUDIT_HEAD_KEY.to_vec()].iter() {
+        database
+            .put(key, &value)
+            .map_err(TeaclaveStorageError::Backend)?;
+        change_log.record(StorageChange::Put {
+            key: key.to_vec(),
+            value: value.clone(),
+        });
+    }
+    Ok(entry.seq)
+}
+
+/// Exports up to `limit` entries from `start_seq`, verifying their links,
+/// starting from the entry before, and seals. Entries missing before the
+/// head are reported as broken.
+pub(crate) fn export(
+    database: &mut dyn StorageBackend,
+    seal_key: &[u8],
+    start_seq: u64,
+    limit: u32,
+) -> TeaclaveServiceResponseResult<ExportAuditLogResponse> {
+    let log_len = match read_entry(database, AUDIT_HEAD_KEY)? {
+        Some(head) => head.seq + 1,
+        None => 0,
+    };
+    let end = std::cmp::min(start_seq.saturating_add(limit as u64), log_len);
+    let prev = match start_seq {
+        0 => None,
+        seq => read_entry(database, &entry_key(seq - 1))?,
+    };
+
+    let mut entries = Vec::new();
+    let mut broken_seq = None;
+    for seq in start_seq..end {
+        match read_entry(database, &entry_key(seq))? {
+            Some(entry) => entries.push(entry),
+            None => {
+                broken_seq = Some(seq);
+                break;
+            }
+        }
+    }
+    if start_seq > 0 && start_seq < log_len && prev.is_none() {
+        broken_seq = Some(start_seq);
+    }
+    let verified = verify_audit_chain(prev.as_ref(), &entries, Some(seal_key));
+    let broken_seq = match (verified, broken_seq) {
+        (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+        (a, b) => a.or(b),
+    };
+
+    Ok(ExportAuditLogResponse {
+        entries,
+        log_len,
+        broken_seq,
+    })
+}
@@ -0,0 +1,208 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Key-value engines under the storage service, selected with the `backend`
+// of `[storage_backend]` in the runtime config:
+//
+// - `leveldb`: LevelDB in memory, or in protected files under the data
+//   directory of `[storage_encryption]` (see rotation.rs)
+// - `remote`: a key-value server outside of the enclave, holding entries
+//   encrypted in the enclave with versioned keys, rotated in place (see
+//   remote.rs)
+
+use anyhow::{anyhow, Result};
+use rusty_leveldb::{DBIterator, LdbIterator, Options, WriteBatch, DB};
+use std::path::Path;
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_storage_service::{KeyRotationProgress, StorageChange};
+
+pub(crate) type Entries<'a> = Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
+
+pub(crate) trait StorageBackend {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()>;
+
+    fn delete(&mut self, key: &[u8]) -> Result<()>;
+
+    /// Applies all of `changes`, or none of them if it fails, even if the
+    /// enclave stops halfway.
+    fn write_batch(&mut self, changes: &[StorageChange]) -> Result<()>;
+
+    /// Entries with keys from `start` on, in the order of their keys.
+    fn scan(&mut self, start: &[u8]) -> Result<Entries<'_>>;
+
+    /// Drops overwritten and deleted entries with keys from `first` to
+    /// `last`.
+    fn compact_range(&mut self, first: &[u8], last: &[u8]) -> Result<()>;
+
+    /// False if compaction is left to a server.
+    fn compacts_locally(&self) -> bool {
+        true
+    }
+
+    /// Persists the writes accepted so far.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Keys of backends which encrypt each entry themselves. The keys of
+    /// other backends are those of their files (see rotation.rs).
+    fn entry_keys(&mut self) -> Option<&mut dyn EntryKeys> {
+        None
+    }
+}
+
+/// Versioned keys of the entries of a backend, rotated by re-encrypting the
+/// entries in place.
+pub(crate) trait EntryKeys {
+    /// Version of the key encrypting new entries.
+    fn key_version(&self) -> u32;
+
+    fn progress(&self) -> Option<KeyRotationProgress>;
+
+    /// Starts encrypting entries with the next key, and returns its version.
+    fn begin_rotation(&mut self) -> Result<u32>;
+
+    /// Re-encrypts the next batch of entries under older keys.
+    fn step(&mut self, batch_size: u64) -> Result<()>;
+}
+
+pub(crate) struct LevelDb {
+    database: DB,
+}
+
+impl LevelDb {
+    pub(crate) fn in_memory(name: &str) -> Result<Self> {
+        let database = DB::open(name, rusty_leveldb::in_memory())
+            .map_err(|e| anyhow!("cannot open {}: {:?}", name, e))?;
+        Ok(Self { database })
+    }
+
+    /// Opens the database in protected files under `path`, encrypted with
+    /// `key`.
+    pub(crate) fn on_disk(path: impl AsRef<Path>, key: [u8; 16]) -> Result<Self> {
+        let path = path.as_ref();
+        let database = DB::open(path, Options::new_disk_db_with(key))
+            .map_err(|e| anyhow!("cannot open {}: {:?}", path.display(), e))?;
+        Ok(Self { database })
+    }
+}
+
+impl StorageBackend for LevelDb {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.database.get(key))
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        Ok(self.database.put(key, value)?)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<()> {
+        Ok(self.database.delete(key)?)
+    }
+
+    // A batch is a single record of the log of LevelDB.
+    fn write_batch(&mut self, changes: &[StorageChange]) -> Result<()> {
+        let mut batch = WriteBatch::new();
+        for change in changes {
+            match change {
+                StorageChange::Put { key, value } => batch.put(key, value),
+                StorageChange::Delete { key } => batch.delete(key),
+            }
+        }
+        Ok(self.database.write(batch, false)?)
+    }
+
+    fn scan(&mut self, start: &[u8]) -> Result<Entries<'_>> {
+        let mut iter = self.database.new_iter()?;
+        if start.is_empty() {
+            iter.seek_to_first();
+        } else {
+            iter.seek(start);
+        }
+        Ok(Box::new(LevelDbEntries {
+            iter,
+            started: false,
+        }))
+    }
+
+    fn compact_range(&mut self, first: &[u8], last: &[u8]) -> Result<()> {
+        Ok(self.database.compact_range(first, last)?)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.database.flush()?)
+    }
+}
+
+// The iterator of LevelDB is positioned at the first entry by the seek, and
+// only advanced for the entries after it.
+struct LevelDbEntries {
+    iter: DBIterator,
+    started: bool,
+}
+
+impl Iterator for LevelDbEntries {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.started {
+            self.iter.advance();
+        }
+        self.started = true;
+        let (mut key, mut value) = (Vec::new(), Vec::new());
+        if self.iter.current(&mut key, &mut value) {
+            Some((key, value))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_leveldb_scan() {
+        let mut backend = LevelDb::in_memory("test_scan_db").unwrap();
+        for key in &["b", "a", "d", "c"] {
+            backend.put(key.as_bytes(), b"value").unwrap();
+        }
+        backend.delete(b"c").unwrap();
+
+        let keys: Vec<Vec<u8>> = backend.scan(b"").unwrap().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec(), b"d".to_vec()]);
+        let keys: Vec<Vec<u8>> = backend.scan(b"c").unwrap().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![b"d".to_vec()]);
+        assert!(backend.scan(b"e").unwrap().next().is_none());
+
+        backend
+            .write_batch(&[
+                StorageChange::Put {
+                    key: b"c".to_vec(),
+                    value: b"value".to_vec(),
+                },
+                StorageChange::Delete { key: b"d".to_vec() },
+            ])
+            .unwrap();
+        let keys: Vec<Vec<u8>> = backend.scan(b"b").unwrap().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![b"b".to_vec(), b"c".to_vec()]);
+
+        backend.compact_range(b"a", b"d").unwrap();
+        assert_eq!(backend.get(b"a").unwrap(), Some(b"value".to_vec()));
+        assert_eq!(backend.get(b"d").unwrap(), None);
+    }
+}
@@ -0,0 +1,102 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Writes of a batch (`Batch`) are staged in memory, over the database, and
+// written with `write_batch` once all of them succeeded. Operations of the
+// batch read the writes staged before them, e.g., the tail of a queue which
+// is enqueued to twice.
+
+use crate::backend::{Entries, StorageBackend};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_storage_service::StorageChange;
+
+pub(crate) struct StagedWrites<'a> {
+    database: &'a mut dyn StorageBackend,
+    // The value of each written key, or None if deleted.
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<'a> StagedWrites<'a> {
+    pub(crate) fn new(database: &'a mut dyn StorageBackend) -> Self {
+        Self {
+            database,
+            writes: BTreeMap::new(),
+        }
+    }
+
+    /// The last write of each key, in the order of the keys.
+    pub(crate) fn into_changes(self) -> Vec<StorageChange> {
+        self.writes
+            .into_iter()
+            .map(|(key, value)| match value {
+                Some(value) => StorageChange::Put { key, value },
+                None => StorageChange::Delete { key },
+            })
+            .collect()
+    }
+}
+
+impl StorageBackend for StagedWrites<'_> {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.writes.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.database.get(key),
+        }
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.writes.insert(key.to_vec(), Some(value.to_vec()));
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<()> {
+        self.writes.insert(key.to_vec(), None);
+        Ok(())
+    }
+
+    fn write_batch(&mut self, changes: &[StorageChange]) -> Result<()> {
+        for change in changes {
+            match change {
+                StorageChange::Put { key, value } => self.put(key, value)?,
+                StorageChange::Delete { key } => self.delete(key)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self, start: &[u8]) -> Result<Entries<'_>> {
+        let mut entries: BTreeMap<Vec<u8>, Vec<u8>> = self.database.scan(start)?.collect();
+        for (key, value) in self.writes.range(start.to_vec()..) {
+            match value {
+                Some(value) => entries.insert(key.clone(), value.clone()),
+                None => entries.remove(key),
+            };
+        }
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    // Staged writes are compacted when written.
+    fn compact_range(&mut self, _first: &[u8], _last: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::backend::StorageBackend;
+use crate::proxy::ProxyRequest;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::prelude::v1::*;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+use teaclave_proto::teaclave_storage_service::{CompactionProgress, PrefixUsage};
+
+// Compaction of the database, a batch of keys at a time so that requests are
+// served in between. A compaction starts once enough writes are pending, or
+// when requested (`Compact`). Overwritten and deleted entries are only dropped
+// from the database when it is compacted.
+pub(crate) struct CompactionState {
+    write_threshold: u64,
+    lag_threshold: u64,
+    batch_size: u64,
+    check_interval: Duration,
+    last_check: Option<SystemTime>,
+    pending_writes: u64,
+    last_compaction: Option<SystemTime>,
+    compactions: u64,
+    run: Option<CompactionRun>,
+    usage: UsageCount,
+}
+
+// Number of keys and bytes under each key prefix, i.e., the part of the key
+// before the first '-' (e.g., "task" or "queue"). The database is counted a
+// batch of keys at a time, again once every check interval, and `GetUsage`
+// answers with the last complete count.
+#[derive(Default)]
+struct UsageCount {
+    // First key of the next batch, if counting.
+    cursor: Option<Vec<u8>>,
+    counting: BTreeMap<Vec<u8>, PrefixUsage>,
+    usages: Vec<PrefixUsage>,
+    counted_at: Option<SystemTime>,
+}
+
+// A compaction in progress.
+struct CompactionRun {
+    // First key of the next batch.
+    cursor: Vec<u8>,
+    compacted_keys: u64,
+    total_keys: u64,
+    // Writes pending when it started, which it drops.
+    pending_writes: u64,
+    started_at: SystemTime,
+}
+
+impl CompactionState {
+    pub(crate) fn new(
+        write_threshold: u64,
+        lag_threshold: u64,
+        batch_size: u64,
+        check_interval: Duration,
+    ) -> Self {
+        Self {
+            write_threshold,
+            lag_threshold,
+            batch_size: std::cmp::max(batch_size, 1),
+            check_interval,
+            last_check: None,
+            pending_writes: 0,
+            last_compaction: None,
+            compactions: 0,
+            run: None,
+            usage: UsageCount::default(),
+        }
+    }
+
+    pub(crate) fn record_writes(&mut self, n: u64) {
+        self.pending_writes += n;
+    }
+
+    pub(crate) fn pending_writes(&self) -> u64 {
+        self.pending_writes
+    }
+
+    pub(crate) fn last_compaction(&self) -> Option<SystemTime> {
+        self.last_compaction
+    }
+
+    /// Compactions completed since the service started.
+    pub(crate) fn compactions(&self) -> u64 {
+        self.compactions
+    }
+
+    pub(crate) fn is_due(&self) -> bool {
+        self.pending_writes >= self.write_threshold
+    }
+
+    pub(crate) fn is_behind(&self) -> bool {
+        self.pending_writes >= self.lag_threshold
+    }
+
+    /// Whether the pending writes are to be checked again, once every check
+    /// interval.
+    pub(crate) fn check(&mut self) -> bool {
+        let now = SystemTime::now();
+        match self.last_check {
+            Some(last_check) if now < last_check + self.check_interval => false,
+            _ => {
+                self.last_check = Some(now);
+                true
+            }
+        }
+    }
+
+    /// Usages of the last complete count of the database.
+    pub(crate) fn usages(&self) -> &[PrefixUsage] {
+        &self.usage.usages
+    }
+
+    pub(crate) fn usage_counted_at(&self) -> Option<SystemTime> {
+        self.usage.counted_at
+    }
+
+    pub(crate) fn progress(&self) -> Option<CompactionProgress> {
+        self.run.as_ref().map(|run| CompactionProgress {
+            compacted_keys: run.compacted_keys,
+            total_keys: run.total_keys,
+            started_at: run.started_at,
+        })
+    }
+
+    /// Starts compacting `database`, and returns false if a compaction is in
+    /// progress already. Its total keys are those of the last usage count.
+    pub(crate) fn start(&mut self, database: &mut dyn StorageBackend) -> Result<bool> {
+        if self.run.is_some() {
+            return Ok(false);
+        }
+        let total_keys = if database.compacts_locally() {
+            self.usage.usages.iter().map(|usage| usage.key_count).sum()
+        } else {
+            0
+        };
+        self.run = Some(CompactionRun {
+            cursor: Vec::new(),
+            compacted_keys: 0,
+            total_keys,
+            pending_writes: self.pending_writes,
+            started_at: SystemTime::now(),
+        });
+        Ok(true)
+    }
+
+    /// Compacts the next batch of keys of the compaction in progress.
+    pub(crate) fn step(&mut self, database: &mut dyn StorageBackend) -> Result<()> {
+        let run = match &mut self.run {
+            Some(run) => run,
+            None => return Ok(()),
+        };
+        // Left to the server otherwise.
+        if database.compacts_locally() {
+            let mut keys: Vec<Vec<u8>> = database
+                .scan(&run.cursor)?
+                .map(|(key, _)| key)
+                .take(self.batch_size as usize + 1)
+                .collect();
+            let next = if keys.len() > self.batch_size as usize {
+                keys.pop()
+            } else {
+                None
+            };
+            if let (Some(first), Some(last)) = (keys.first(), keys.last()) {
+                database.compact_range(first, last)?;
+                run.compacted_keys += keys.len() as u64;
+            }
+            if let Some(next) = next {
+                run.cursor = next;
+                return Ok(());
+            }
+        }
+        let run = self.run.take().unwrap();
+        self.pending_writes = self.pending_writes.saturating_sub(run.pending_writes);
+        self.last_compaction = Some(SystemTime::now());
+        self.compactions += 1;
+        info!("Compacted {} storage keys", run.compacted_keys);
+        Ok(())
+    }
+
+    /// Counts the usage of the next batch of keys, starting a new count once
+    /// every check interval. Backends leaving compaction to a server are not
+    /// counted, as each of their scans fetches all entries.
+    pub(crate) fn count_usage(&mut self, database: &mut dyn StorageBackend) -> Result<()> {
+        if !database.compacts_locally() {
+            return Ok(());
+        }
+        let now = SystemTime::now();
+        let cursor = match self.usage.cursor.take() {
+            Some(cursor) => cursor,
+            None => match self.usage.counted_at {
+                Some(counted_at) if now < counted_at + self.check_interval => return Ok(()),
+                _ => {
+                    self.usage.counting.clear();
+                    Vec::new()
+                }
+            },
+        };
+        let mut entries: Vec<(Vec<u8>, u64)> = database
+            .scan(&cursor)?
+            .map(|(key, value)| {
+                let len = (key.len() + value.len()) as u64;
+                (key, len)
+            })
+            .take(self.batch_size as usize + 1)
+            .collect();
+        let next = if entries.len() > self.batch_size as usize {
+            entries.pop().map(|(key, _)| key)
+        } else {
+            None
+        };
+        for (key, len) in entries {
+            let prefix = key.split(|b| *b == b'-').next().unwrap_or_default();
+            let usage = self
+                .usage
+                .counting
+                .entry(prefix.to_vec())
+                .or_insert_with(|| PrefixUsage {
+                    prefix: prefix.to_vec(),
+                    key_count: 0,
+                    byte_count: 0,
+                });
+            usage.key_count += 1;
+            usage.byte_count += len;
+        }
+        match next {
+            Some(next) => self.usage.cursor = Some(next),
+            None => {
+                let counting = std::mem::replace(&mut self.usage.counting, BTreeMap::new());
+                self.usage.usages = counting.into_iter().map(|(_, usage)| usage).collect();
+                self.usage.counted_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Asks the storage thread every `interval` to continue the compaction in
+// progress, or to start one if due. Runs until the storage thread exits.
+pub(crate) fn schedule_compaction(sender: Sender<ProxyRequest>, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        if sender.send(ProxyRequest::Compact).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::backend::LevelDb;
+
+    pub fn test_compaction_batches() {
+        let mut database = LevelDb::in_memory("test_compaction_db").unwrap();
+        for key in &["a", "b", "c", "d", "e"] {
+            database.put(key.as_bytes(), b"value").unwrap();
+        }
+        let mut compaction = CompactionState::new(5, 10, 2, Duration::from_secs(0));
+        compaction.record_writes(5);
+        // The keys are counted two at a time.
+        compaction.count_usage(&mut database).unwrap();
+        compaction.count_usage(&mut database).unwrap();
+        assert!(compaction.usage_counted_at().is_none());
+        compaction.count_usage(&mut database).unwrap();
+        assert!(compaction.usage_counted_at().is_some());
+        let usages = compaction.usages();
+        assert_eq!(usages.len(), 5);
+        assert_eq!(usages[0].prefix, b"a");
+        assert_eq!(usages[0].byte_count, 6);
+
+        assert!(compaction.start(&mut database).unwrap());
+        assert!(!compaction.start(&mut database).unwrap());
+        assert_eq!(compaction.progress().unwrap().total_keys, 5);
+
+        compaction.step(&mut database).unwrap();
+        assert_eq!(compaction.progress().unwrap().compacted_keys, 2);
+        compaction.record_writes(1);
+        compaction.step(&mut database).unwrap();
+        assert_eq!(compaction.progress().unwrap().compacted_keys, 4);
+        compaction.step(&mut database).unwrap();
+        assert!(compaction.progress().is_none());
+        assert_eq!(compaction.compactions(), 1);
+        assert_eq!(compaction.pending_writes(), 1);
+        assert!(compaction.last_compaction().is_some());
+        assert_eq!(database.get(b"e").unwrap(), Some(b"value".to_vec()));
+    }
+}
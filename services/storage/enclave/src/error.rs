@@ -24,10 +24,28 @@ use thiserror::Error;
 pub(crate) enum TeaclaveStorageError {
     #[error("connection error")]
     Connection,
-    #[error("leveldb error")]
-    LevelDb(#[from] rusty_leveldb::Status),
+    #[error("storage backend error: {0}")]
+    Backend(anyhow::Error),
     #[error("none error")]
     None,
+    #[error("not the primary storage")]
+    NotPrimary,
+    #[error("not a storage replica")]
+    NotReplica,
+    #[error("replica too stale")]
+    Stale,
+    #[error("reserved key")]
+    ReservedKey,
+    #[error("audit log broken")]
+    AuditLogBroken,
+    #[error("key rotation error: {0}")]
+    KeyRotation(String),
+    #[error("snapshot error: {0}")]
+    Snapshot(String),
+    #[error("invalid namespace")]
+    InvalidNamespace,
+    #[error("namespace quota exceeded")]
+    QuotaExceeded,
 }
 
 impl From<TeaclaveStorageError> for TeaclaveServiceResponseError {
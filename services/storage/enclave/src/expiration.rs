@@ -0,0 +1,230 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+// Keys put with a TTL expire at a deadline, in seconds since the epoch. The
+// deadlines are kept in the database under the `expiry-` prefix, which other
+// requests cannot write: the deadline of a key at `expiry-key-<key>`, and an
+// empty entry at `expiry-at-<deadline><key>` (big endian, so that they sort
+// by deadline) for the sweep to find the expired keys in order. Expired keys
+// are not read, and are deleted by the sweep on the primary.
+
+use crate::backend::StorageBackend;
+use crate::error::TeaclaveStorageError;
+use crate::namespace;
+use crate::proxy::ProxyRequest;
+use crate::replication::ChangeLog;
+use std::prelude::v1::*;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+use teaclave_proto::teaclave_storage_service::StorageChange;
+use teaclave_types::TeaclaveServiceResponseResult;
+
+const EXPIRY_PREFIX: &[u8] = b"expiry-";
+const DEADLINE_PREFIX: &[u8] = b"expiry-key-";
+const SCHEDULE_PREFIX: &[u8] = b"expiry-at-";
+
+// Most keys deleted by a sweep, so that requests do not wait for long.
+pub(crate) const MAX_SWEEP_KEYS: usize = 1000;
+
+pub(crate) fn is_expiry_key(key: &[u8]) -> bool {
+    key.starts_with(EXPIRY_PREFIX)
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Deadline of a key put now with `ttl`.
+pub(crate) fn deadline_after(ttl: Duration) -> u64 {
+    now_secs().saturating_add(ttl.as_secs())
+}
+
+fn deadline_key(key: &[u8]) -> Vec<u8> {
+    let mut deadline_key = DEADLINE_PREFIX.to_vec();
+    deadline_key.extend_from_slice(key);
+    deadline_key
+}
+
+fn schedule_key(deadline: u64, key: &[u8]) -> Vec<u8> {
+    let mut schedule_key = SCHEDULE_PREFIX.to_vec();
+    schedule_key.extend_from_slice(&deadline.to_be_bytes());
+    schedule_key.extend_from_slice(key);
+    schedule_key
+}
+
+// The deadline and the key of an entry of the schedule.
+fn parse_schedule_key(schedule_key: &[u8]) -> Option<(u64, Vec<u8>)> {
+    if schedule_key.len() < SCHEDULE_PREFIX.len() + 8 {
+        return None;
+    }
+    let (deadline, key) = schedule_key[SCHEDULE_PREFIX.len()..].split_at(8);
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(deadline);
+    Some((u64::from_be_bytes(bytes), key.to_vec()))
+}
+
+fn apply(
+    database: &mut dyn StorageBackend,
+    change_log: &mut ChangeLog,
+    change: StorageChange,
+) -> TeaclaveServiceResponseResult<()> {
+    match &change {
+        StorageChange::Put { key, value } => database.put(key, value),
+        StorageChange::Delete { key } => database.delete(key),
+    }
+    .map_err(TeaclaveStorageError::Backend)?;
+    change_log.record(change);
+    Ok(())
+}
+
+pub(crate) fn deadline(
+    database: &mut dyn StorageBackend,
+    key: &[u8],
+) -> TeaclaveServiceResponseResult<Option<u64>> {
+    let value = database
+        .get(&deadline_key(key))
+        .map_err(TeaclaveStorageError::Backend)?;
+    Ok(value.filter(|value| value.len() == 8).map(|value| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&value);
+        u64::from_be_bytes(bytes)
+    }))
+}
+
+pub(crate) fn is_expired(
+    database: &mut dyn StorageBackend,
+    key: &[u8],
+    now: u64,
+) -> TeaclaveServiceResponseResult<bool> {
+    Ok(deadline(database, key)?.map_or(false, |deadline| deadline <= now))
+}
+
+/// Sets the deadline of `key`, or clears it if `None`.
+pub(crate) fn set_deadline(
+    database: &mut dyn StorageBackend,
+    change_log: &mut ChangeLog,
+    key: &[u8],
+    deadline: Option<u64>,
+) -> TeaclaveServiceResponseResult<()> {
+    let previous = self::deadline(database, key)?;
+    if previous == deadline {
+        return Ok(());
+    }
+    if let Some(previous) = previous {
+        let change = StorageChange::Delete {
+            key: schedule_key(previous, key),
+        };
+        apply(database, change_log, change)?;
+    }
+    let changes = match deadline {
+        Some(deadline) => vec![
+            StorageChange::Put {
+                key: schedule_key(deadline, key),
+                value: Vec::new(),
+            },
+            StorageChange::Put {
+                key: deadline_key(key),
+                value: deadline.to_be_bytes().to_vec(),
+            },
+        ],
+        None => vec![StorageChange::Delete {
+            key: deadline_key(key),
+        }],
+    };
+    for change in changes {
+        apply(database, change_log, change)?;
+    }
+    Ok(())
+}
+
+/// Deletes up to `limit` keys expired at `now` with their deadlines, and
+/// returns how many.
+pub(crate) fn sweep(
+    database: &mut dyn StorageBackend,
+    change_log: &mut ChangeLog,
+    now: u64,
+    limit: usize,
+) -> TeaclaveServiceResponseResult<usize> {
+    let expired: Vec<(u64, Vec<u8>)> = database
+        .scan(SCHEDULE_PREFIX)
+        .map_err(TeaclaveStorageError::Backend)?
+        .take_while(|(schedule_key, _)| schedule_key.starts_with(SCHEDULE_PREFIX))
+        .filter_map(|(schedule_key, _)| parse_schedule_key(&schedule_key))
+        .take_while(|(deadline, _)| *deadline <= now)
+        .take(limit)
+        .collect();
+    for (deadline, key) in &expired {
+        namespace::release(database, change_log, key)?;
+        let keys = vec![
+            key.to_vec(),
+            deadline_key(key),
+            schedule_key(*deadline, key),
+        ];
+        for key in keys {
+            apply(database, change_log, StorageChange::Delete { key })?;
+        }
+    }
+    Ok(expired.len())
+}
+
+// Asks the storage thread every `interval` to delete the expired keys. Runs
+// until the storage thread exits.
+pub(crate) fn schedule_sweep(sender: Sender<ProxyRequest>, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        if sender.send(ProxyRequest::Expire).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::backend::LevelDb;
+
+    pub fn test_sweep() {
+        let mut database = LevelDb::in_memory("test_expiration_db").unwrap();
+        let mut change_log = ChangeLog::new(64);
+        for (key, deadline) in &[("a", Some(20)), ("b", Some(10)), ("c", None)] {
+            database.put(key.as_bytes(), b"value").unwrap();
+            set_deadline(&mut database, &mut change_log, key.as_bytes(), *deadline).unwrap();
+        }
+        assert_eq!(deadline(&mut database, b"a").unwrap(), Some(20));
+        assert!(is_expired(&mut database, b"b", 10).unwrap());
+        assert!(!is_expired(&mut database, b"a", 10).unwrap());
+        assert!(!is_expired(&mut database, b"c", u64::MAX).unwrap());
+
+        // Putting "a" again with another TTL moves its deadline.
+        set_deadline(&mut database, &mut change_log, b"a", Some(30)).unwrap();
+        assert_eq!(sweep(&mut database, &mut change_log, 25, 10).unwrap(), 1);
+        assert_eq!(database.get(b"b").unwrap(), None);
+        assert_eq!(deadline(&mut database, b"b").unwrap(), None);
+        assert!(database.get(b"a").unwrap().is_some());
+
+        set_deadline(&mut database, &mut change_log, b"a", None).unwrap();
+        assert_eq!(sweep(&mut database, &mut change_log, 100, 10).unwrap(), 0);
+        assert!(database.get(b"a").unwrap().is_some());
+        let keys: Vec<Vec<u8>> = database.scan(b"").unwrap().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![b"a".to_vec(), b"c".to_vec()]);
+        assert!(change_log.sequence() > 0);
+    }
+}
@@ -22,14 +22,14 @@ extern crate sgx_tstd as std;
 #[macro_use]
 extern crate log;
 
-use std::cell::RefCell;
 use std::format;
 use std::prelude::v1::*;
 use std::sync::mpsc::channel;
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
-use anyhow::{anyhow, Result};
-use rusty_leveldb::DB;
+use anyhow::{anyhow, ensure, Result};
 
 use teaclave_attestation::{verifier, AttestationConfig, RemoteAttestation};
 use teaclave_binder::proto::{
@@ -38,16 +38,73 @@ use teaclave_binder::proto::{
 };
 use teaclave_binder::{handle_ecall, register_ecall_handler};
 use teaclave_config::build::{AS_ROOT_CA_CERT, AUDITOR_PUBLIC_KEYS, STORAGE_INBOUND_SERVICES};
-use teaclave_config::RuntimeConfig;
+use teaclave_config::{RuntimeConfig, StorageBackendConfig, StorageBackendKind};
 use teaclave_proto::teaclave_storage_service::{TeaclaveStorageRequest, TeaclaveStorageResponse};
-use teaclave_rpc::config::SgxTrustedTlsServerConfig;
-use teaclave_rpc::server::SgxTrustedTlsServer;
-use teaclave_service_enclave_utils::ServiceEnclave;
+use teaclave_rpc::config::{SgxTrustedTlsServerConfig, TlsPolicy};
+use teaclave_rpc::gate::AttestationGate;
+use teaclave_rpc::interceptor::LoggingInterceptor;
+use teaclave_rpc::server::{SgxTrustedTlsServer, DEFAULT_IDLE_TIMEOUT};
+use teaclave_service_enclave_utils::{create_trusted_storage_endpoint, ServiceEnclave};
 use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
 
+mod audit;
+mod backend;
+mod batch;
+mod compaction;
 mod error;
+mod expiration;
+mod namespace;
+mod ocall;
 mod proxy;
+mod remote;
+mod replication;
+mod rotation;
+mod sealing;
 mod service;
+mod snapshot;
+
+// Requests reporting on, compacting, re-encrypting or exporting the whole
+// database, and limiting or wiping namespaces, which are only served for the
+// management service.
+const MANAGEMENT_ONLY_REQUESTS: &[&str] = &[
+    "GetUsage",
+    "Compact",
+    "SetNamespaceQuota",
+    "WipeNamespace",
+    "CreateSnapshot",
+    "RotateKey",
+];
+
+// Requests returning every namespace and the audit log, which are only served
+// for storage replicas and the management service following the changes for
+// task statistics, webhooks and consistency checks.
+const CHANGES_REQUESTS: &[&str] = &["GetChanges"];
+
+// Opens the database in use, and the state of its key rotation.
+fn open_storage(
+    config: &StorageBackendConfig,
+    data_dir: Option<rotation::DataDir>,
+    rotation_batch_size: u64,
+) -> Result<(Box<dyn backend::StorageBackend>, rotation::EncryptionState)> {
+    match config.backend {
+        StorageBackendKind::LevelDb => match data_dir {
+            Some(data_dir) => data_dir.open_all(rotation_batch_size),
+            None => Ok((
+                Box::new(backend::LevelDb::in_memory("teaclave_db")?),
+                rotation::EncryptionState::in_memory(),
+            )),
+        },
+        StorageBackendKind::Remote => {
+            let address = config
+                .remote_address
+                .as_ref()
+                .ok_or_else(|| anyhow!("remote_address of the remote backend not set"))?;
+            let database = remote::RemoteKv::open_sealed(address, &config.remote_namespace)?;
+            let encryption = rotation::EncryptionState::new(None, 0, None, rotation_batch_size);
+            Ok((Box::new(database), encryption))
+        }
+    }
+}
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
     let listen_address = config.internal_endpoints.storage.listen_address;
@@ -68,26 +125,140 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
             None => Err(anyhow!("cannot get enclave attribute of {}", service)),
         })
         .collect::<Result<_>>()?;
-    let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
-        .attestation_report_verifier(
-        accepted_enclave_attrs,
+    let measurement = |service: &str| match enclave_info.get_enclave_attr(service) {
+        Some(attr) => Ok((
+            attr.measurement.mr_signer.to_hex(),
+            attr.measurement.mr_enclave.to_hex(),
+        )),
+        None => Err(anyhow!("cannot get enclave attribute of {}", service)),
+    };
+    let (management_signer, management_enclave) = measurement("teaclave_management_service")?;
+    let (storage_signer, storage_enclave) = measurement("teaclave_storage_service")?;
+    let management = (
+        management_signer.as_str(),
+        Some(management_enclave.as_str()),
+    );
+    let storage = (storage_signer.as_str(), Some(storage_enclave.as_str()));
+    let gate = AttestationGate::with_rules(
+        &[
+            (MANAGEMENT_ONLY_REQUESTS, vec![management]),
+            (CHANGES_REQUESTS, vec![storage, management]),
+        ],
         AS_ROOT_CA_CERT,
-        verifier::universal_quote_verifier,
     )?;
+    let server_config =
+        SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config.clone())?
+            .attestation_report_verifier_with_policy(
+                accepted_enclave_attrs,
+                AS_ROOT_CA_CERT,
+                verifier::universal_quote_verifier,
+                verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+            )?
+            .tls_policy(TlsPolicy::from_teaclave_config(&config))?;
 
     let (sender, receiver) = channel();
+    let replication_config = &config.storage_replication;
+    let replication = match &replication_config.primary_address {
+        Some(primary_address) => {
+            let primary_endpoint = create_trusted_storage_endpoint(
+                primary_address,
+                &enclave_info,
+                AS_ROOT_CA_CERT,
+                verifier::universal_quote_verifier,
+                verifier::QuoteStatusPolicy::from_teaclave_config(&config),
+                TlsPolicy::from_teaclave_config(&config),
+                attested_tls_config,
+            )?
+            .message_limits(&config.internal_endpoints.storage.message_limits);
+            let sender = sender.clone();
+            let interval = Duration::from_millis(replication_config.sync_interval_ms);
+            thread::spawn(move || {
+                replication::follow_primary(primary_endpoint, sender, interval);
+            });
+            replication::ReplicationState::replica()
+        }
+        None => replication::ReplicationState::primary(replication_config.change_log_capacity)?,
+    };
+    let audit_seal_key = audit::seal_key()?;
+    let compaction_config = &config.storage_compaction;
+    let compaction = compaction::CompactionState::new(
+        compaction_config.write_threshold,
+        compaction_config.lag_threshold,
+        compaction_config.batch_size,
+        Duration::from_secs(compaction_config.check_interval_secs),
+    );
+    let compaction_sender = sender.clone();
+    let compaction_interval = Duration::from_millis(compaction_config.step_interval_ms);
     thread::spawn(move || {
-        let opt = rusty_leveldb::in_memory();
-        let storage = DB::open("teaclave_db", opt).expect("cannot open teaclave_db");
-        let mut storage_service =
-            service::TeaclaveStorageService::new(RefCell::new(storage), receiver);
+        compaction::schedule_compaction(compaction_sender, compaction_interval);
+    });
+    let expiration_sender = sender.clone();
+    let sweep_interval = Duration::from_secs(config.storage_expiration.sweep_interval_secs);
+    thread::spawn(move || {
+        expiration::schedule_sweep(expiration_sender, sweep_interval);
+    });
+
+    let encryption_config = &config.storage_encryption;
+    // Replicas are synced with the primary after restarts, in memory.
+    let (backend_config, data_dir) = match replication_config.primary_address {
+        Some(_) => (StorageBackendConfig::default(), None),
+        None => (
+            config.storage_backend.clone(),
+            encryption_config
+                .data_dir
+                .as_ref()
+                .map(rotation::DataDir::new),
+        ),
+    };
+    ensure!(
+        data_dir.is_none() || backend_config.backend == StorageBackendKind::LevelDb,
+        "data_dir is only used by the leveldb storage backend"
+    );
+    // Keys of the remote backend are rotated in place.
+    if data_dir.is_some() || backend_config.backend == StorageBackendKind::Remote {
+        let rotation_sender = sender.clone();
+        let rotation_interval = Duration::from_millis(encryption_config.rotation_interval_ms);
+        thread::spawn(move || {
+            rotation::schedule_rotation(rotation_sender, rotation_interval);
+        });
+    }
+    let rotation_batch_size = encryption_config.rotation_batch_size;
+    // Replicas restore the snapshot of the primary instead.
+    let restore_snapshot = replication_config.primary_address.is_none();
+    let snapshot_config = config.storage_snapshot.clone();
+
+    thread::spawn(move || {
+        let (mut storage, encryption) =
+            open_storage(&backend_config, data_dir, rotation_batch_size)
+                .expect("cannot open teaclave_db");
+        if restore_snapshot {
+            let restored = snapshot::restore(&mut *storage, &snapshot_config)
+                .expect("cannot restore the storage snapshot");
+            if restored > 0 {
+                info!("Restored {} keys from the storage snapshot", restored);
+            }
+        }
+        let mut storage_service = service::TeaclaveStorageService::new(
+            storage,
+            receiver,
+            replication,
+            compaction,
+            encryption,
+            audit_seal_key,
+            snapshot_config.staging_dir,
+        );
         storage_service.start();
     });
 
     let mut server = SgxTrustedTlsServer::<TeaclaveStorageResponse, TeaclaveStorageRequest>::new(
         listen_address,
         server_config,
-    );
+    )
+    .message_limits(&config.internal_endpoints.storage.message_limits)
+    .unix_socket(config.internal_endpoints.storage.unix_socket.clone())
+    .idle_timeout(Some(DEFAULT_IDLE_TIMEOUT))
+    .attestation_gate(gate)
+    .interceptor(Arc::new(LoggingInterceptor::new("storage")));
 
     let service = proxy::ProxyService::new(sender);
 
@@ -137,11 +308,32 @@ pub mod tests {
 
     pub fn run_tests() -> bool {
         run_tests!(
+            backend::tests::test_leveldb_scan,
+            remote::tests::test_entry_cipher,
+            remote::tests::test_redis_protocol,
             service::tests::test_get_key,
             service::tests::test_put_key,
             service::tests::test_delete_key,
             service::tests::test_enqueue,
             service::tests::test_dequeue,
+            service::tests::test_batch,
+            service::tests::test_get_changes,
+            service::tests::test_replica,
+            service::tests::test_get_usage,
+            service::tests::test_compaction,
+            service::tests::test_compact,
+            service::tests::test_health,
+            service::tests::test_audit_log,
+            service::tests::test_key_rotation,
+            service::tests::test_create_snapshot,
+            service::tests::test_scan_prefix,
+            service::tests::test_range,
+            service::tests::test_put_ttl,
+            service::tests::test_namespace,
+            expiration::tests::test_sweep,
+            namespace::tests::test_namespace_usage,
+            snapshot::tests::test_snapshot,
+            compaction::tests::test_compaction_batches,
         )
     }
 }
@@ -0,0 +1,342 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Namespaces keep the keys of tenants apart. Key K of namespace N is kept at
+// `ns-N/K`, which requests outside of N cannot read or write, and the usage
+// and quota of N at `ns-N#usage` and `ns-N#quota`. Names cannot contain '/'
+// or '#', so the keys of a namespace are never under the prefix of another.
+//
+// The usage of a namespace is its number of keys and their bytes (keys
+// within the namespace and values). Writes to a namespace are staged (see
+// batch.rs) and accounted before they are written, and fail if they grow the
+// usage beyond the quota. Deletes are always accepted, even if the quota was
+// lowered below the usage.
+
+use crate::backend::StorageBackend;
+use crate::error::TeaclaveStorageError;
+use crate::replication::ChangeLog;
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_storage_service::StorageChange;
+use teaclave_types::TeaclaveServiceResponseResult;
+
+const NAMESPACE_PREFIX: &[u8] = b"ns-";
+const USAGE_SUFFIX: &[u8] = b"#usage";
+const QUOTA_SUFFIX: &[u8] = b"#quota";
+const MAX_NAME_LEN: usize = 256;
+
+pub(crate) fn is_namespace_key(key: &[u8]) -> bool {
+    key.starts_with(NAMESPACE_PREFIX)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct Usage {
+    pub(crate) key_count: u64,
+    pub(crate) byte_count: u64,
+}
+
+// Limits are unlimited if none.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct Quota {
+    pub(crate) max_keys: Option<u64>,
+    pub(crate) max_bytes: Option<u64>,
+}
+
+// Two u64 (big endian), with zero for no limit in quotas.
+fn encode(first: u64, second: u64) -> Vec<u8> {
+    let mut bytes = first.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&second.to_be_bytes());
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Option<(u64, u64)> {
+    if bytes.len() != 16 {
+        return None;
+    }
+    let mut first = [0u8; 8];
+    let mut second = [0u8; 8];
+    first.copy_from_slice(&bytes[..8]);
+    second.copy_from_slice(&bytes[8..]);
+    Some((u64::from_be_bytes(first), u64::from_be_bytes(second)))
+}
+
+pub(crate) struct Namespace {
+    // `ns-N`, followed by '/' for the keys or the suffix of the metadata.
+    name_key: Vec<u8>,
+    prefix: Vec<u8>,
+}
+
+impl Namespace {
+    pub(crate) fn new(name: &str) -> Result<Self, TeaclaveStorageError> {
+        if name.is_empty() || name.len() > MAX_NAME_LEN || name.contains(|c| c == '/' || c == '#') {
+            return Err(TeaclaveStorageError::InvalidNamespace);
+        }
+        let mut name_key = NAMESPACE_PREFIX.to_vec();
+        name_key.extend_from_slice(name.as_bytes());
+        let mut prefix = name_key.clone();
+        prefix.push(b'/');
+        Ok(Self { name_key, prefix })
+    }
+
+    /// The namespace of a key of the database, if any.
+    fn of(key: &[u8]) -> Option<Self> {
+        if !is_namespace_key(key) {
+            return None;
+        }
+        let rest = &key[NAMESPACE_PREFIX.len()..];
+        let end = rest.iter().position(|b| *b == b'/')?;
+        let name = std::str::from_utf8(&rest[..end]).ok()?;
+        Self::new(name).ok()
+    }
+
+    /// Prefix of the keys of the namespace in the database.
+    pub(crate) fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub(crate) fn key(&self, key: &[u8]) -> Vec<u8> {
+        let mut namespaced_key = self.prefix.clone();
+        namespaced_key.extend_from_slice(key);
+        namespaced_key
+    }
+
+    fn meta_key(&self, suffix: &[u8]) -> Vec<u8> {
+        let mut meta_key = self.name_key.clone();
+        meta_key.extend_from_slice(suffix);
+        meta_key
+    }
+
+    pub(crate) fn usage(
+        &self,
+        database: &mut dyn StorageBackend,
+    ) -> TeaclaveServiceResponseResult<Usage> {
+        let value = database
+            .get(&self.meta_key(USAGE_SUFFIX))
+            .map_err(TeaclaveStorageError::Backend)?;
+        Ok(value
+            .and_then(|value| decode(&value))
+            .map(|(key_count, byte_count)| Usage {
+                key_count,
+                byte_count,
+            })
+            .unwrap_or_default())
+    }
+
+    pub(crate) fn quota(
+        &self,
+        database: &mut dyn StorageBackend,
+    ) -> TeaclaveServiceResponseResult<Quota> {
+        let value = database
+            .get(&self.meta_key(QUOTA_SUFFIX))
+            .map_err(TeaclaveStorageError::Backend)?;
+        let limit = |limit: u64| Some(limit).filter(|limit| *limit != 0);
+        Ok(value
+            .and_then(|value| decode(&value))
+            .map(|(max_keys, max_bytes)| Quota {
+                max_keys: limit(max_keys),
+                max_bytes: limit(max_bytes),
+            })
+            .unwrap_or_default())
+    }
+
+    pub(crate) fn set_quota(
+        &self,
+        database: &mut dyn StorageBackend,
+        change_log: &mut ChangeLog,
+        quota: Quota,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let key = self.meta_key(QUOTA_SUFFIX);
+        let value = encode(
+            quota.max_keys.unwrap_or_default(),
+            quota.max_bytes.unwrap_or_default(),
+        );
+        database
+            .put(&key, &value)
+            .map_err(TeaclaveStorageError::Backend)?;
+        change_log.record(StorageChange::Put { key, value });
+        Ok(())
+    }
+
+    /// Accounts `changes`, the last write of each key, to the usage of the
+    /// namespace, and adds the change of the usage. Changes of keys outside of
+    /// the namespace are not accounted.
+    pub(crate) fn account(
+        &self,
+        database: &mut dyn StorageBackend,
+        changes: &mut Vec<StorageChange>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let before = self.usage(database)?;
+        let mut usage = before;
+        for change in changes.iter() {
+            let (key, value) = match change {
+                StorageChange::Put { key, value } => (key, Some(value)),
+                StorageChange::Delete { key } => (key, None),
+            };
+            if !key.starts_with(&self.prefix) {
+                continue;
+            }
+            let key_len = (key.len() - self.prefix.len()) as u64;
+            let previous = database.get(key).map_err(TeaclaveStorageError::Backend)?;
+            if let Some(previous) = previous {
+                usage.key_count = usage.key_count.saturating_sub(1);
+                usage.byte_count = usage
+                    .byte_count
+                    .saturating_sub(key_len + previous.len() as u64);
+            }
+            if let Some(value) = value {
+                usage.key_count += 1;
+                usage.byte_count += key_len + value.len() as u64;
+            }
+        }
+        if usage == before {
+            return Ok(());
+        }
+        let quota = self.quota(database)?;
+        let exceeds = |count: u64, previous: u64, limit: Option<u64>| {
+            count > previous && limit.map_or(false, |limit| count > limit)
+        };
+        if exceeds(usage.key_count, before.key_count, quota.max_keys)
+            || exceeds(usage.byte_count, before.byte_count, quota.max_bytes)
+        {
+            return Err(TeaclaveStorageError::QuotaExceeded.into());
+        }
+        changes.push(StorageChange::Put {
+            key: self.meta_key(USAGE_SUFFIX),
+            value: encode(usage.key_count, usage.byte_count),
+        });
+        Ok(())
+    }
+
+    /// Deletes the keys of the namespace with its usage and quota, and
+    /// returns the deleted keys.
+    pub(crate) fn wipe(
+        &self,
+        database: &mut dyn StorageBackend,
+        change_log: &mut ChangeLog,
+    ) -> TeaclaveServiceResponseResult<Vec<Vec<u8>>> {
+        let keys: Vec<Vec<u8>> = database
+            .scan(&self.prefix)
+            .map_err(TeaclaveStorageError::Backend)?
+            .take_while(|(key, _)| key.starts_with(&self.prefix))
+            .map(|(key, _)| key)
+            .collect();
+        let meta_keys = vec![self.meta_key(USAGE_SUFFIX), self.meta_key(QUOTA_SUFFIX)];
+        for key in keys.iter().chain(meta_keys.iter()) {
+            database
+                .delete(key)
+                .map_err(TeaclaveStorageError::Backend)?;
+            change_log.record(StorageChange::Delete { key: key.to_vec() });
+        }
+        Ok(keys)
+    }
+}
+
+/// The key of `key` in the database, within `namespace` if any.
+pub(crate) fn key_in(namespace: Option<&Namespace>, key: &[u8]) -> Vec<u8> {
+    match namespace {
+        Some(namespace) => namespace.key(key),
+        None => key.to_vec(),
+    }
+}
+
+/// Accounts the delete of `key` to the usage of its namespace, if any, for
+/// deletes which are not staged.
+pub(crate) fn release(
+    database: &mut dyn StorageBackend,
+    change_log: &mut ChangeLog,
+    key: &[u8],
+) -> TeaclaveServiceResponseResult<()> {
+    let namespace = match Namespace::of(key) {
+        Some(namespace) => namespace,
+        None => return Ok(()),
+    };
+    let mut changes = vec![StorageChange::Delete { key: key.to_vec() }];
+    namespace.account(database, &mut changes)?;
+    for change in changes.drain(1..) {
+        if let StorageChange::Put { key, value } = &change {
+            database
+                .put(key, value)
+                .map_err(TeaclaveStorageError::Backend)?;
+        }
+        change_log.record(change);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::backend::LevelDb;
+
+    pub fn test_namespace_usage() {
+        let mut database = LevelDb::in_memory("test_namespace_db").unwrap();
+        let mut change_log = ChangeLog::new(64);
+        assert!(Namespace::new("a/b").is_err());
+        assert!(Namespace::new("").is_err());
+        let namespace = Namespace::new("tenant").unwrap();
+        assert_eq!(namespace.key(b"k"), b"ns-tenant/k".to_vec());
+        assert!(Namespace::of(b"ns-tenant/k").is_some());
+        assert!(Namespace::of(b"ns-tenant#usage").is_none());
+
+        let quota = Quota {
+            max_keys: Some(2),
+            max_bytes: None,
+        };
+        namespace
+            .set_quota(&mut database, &mut change_log, quota)
+            .unwrap();
+        assert_eq!(namespace.quota(&mut database).unwrap(), quota);
+
+        let mut changes = vec![
+            StorageChange::Put {
+                key: namespace.key(b"a"),
+                value: b"12".to_vec(),
+            },
+            StorageChange::Put {
+                key: b"outside".to_vec(),
+                value: b"12".to_vec(),
+            },
+        ];
+        namespace.account(&mut database, &mut changes).unwrap();
+        assert_eq!(changes.len(), 3);
+        database.write_batch(&changes).unwrap();
+        let usage = namespace.usage(&mut database).unwrap();
+        assert_eq!(usage.key_count, 1);
+        assert_eq!(usage.byte_count, 3);
+
+        let mut changes = vec![
+            StorageChange::Put {
+                key: namespace.key(b"b"),
+                value: Vec::new(),
+            },
+            StorageChange::Put {
+                key: namespace.key(b"c"),
+                value: Vec::new(),
+            },
+        ];
+        assert!(namespace.account(&mut database, &mut changes).is_err());
+
+        release(&mut database, &mut change_log, &namespace.key(b"a")).unwrap();
+        database.delete(&namespace.key(b"a")).unwrap();
+        assert_eq!(namespace.usage(&mut database).unwrap(), Usage::default());
+
+        database.put(&namespace.key(b"d"), b"").unwrap();
+        let keys = namespace.wipe(&mut database, &mut change_log).unwrap();
+        assert_eq!(keys, vec![namespace.key(b"d")]);
+        assert_eq!(namespace.quota(&mut database).unwrap(), Quota::default());
+        assert!(database.get(b"outside").unwrap().is_some());
+    }
+}
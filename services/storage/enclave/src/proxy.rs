@@ -16,6 +16,7 @@
 // under the License.
 
 use crate::error::TeaclaveStorageError;
+use crate::replication::ReplicatedChanges;
 use std::prelude::v1::*;
 use std::sync::mpsc::{channel, Sender};
 use teaclave_proto::teaclave_storage_service::{TeaclaveStorageRequest, TeaclaveStorageResponse};
@@ -42,7 +43,7 @@ impl teaclave_rpc::TeaclaveService<TeaclaveStorageRequest, TeaclaveStorageRespon
     ) -> TeaclaveServiceResponseResult<TeaclaveStorageResponse> {
         let (sender, receiver) = channel();
         self.sender
-            .send(ProxyRequest { sender, request })
+            .send(ProxyRequest::Service { sender, request })
             .map_err(|_| TeaclaveStorageError::Connection)?;
         receiver
             .recv()
@@ -50,8 +51,16 @@ impl teaclave_rpc::TeaclaveService<TeaclaveStorageRequest, TeaclaveStorageRespon
     }
 }
 
-#[derive(Clone)]
-pub(crate) struct ProxyRequest {
-    pub sender: Sender<TeaclaveServiceResponseResult<TeaclaveStorageResponse>>,
-    pub request: Request<TeaclaveStorageRequest>,
+pub(crate) enum ProxyRequest {
+    Service {
+        sender: Sender<TeaclaveServiceResponseResult<TeaclaveStorageResponse>>,
+        request: Request<TeaclaveStorageRequest>,
+    },
+    Replicate {
+        sender: Sender<TeaclaveServiceResponseResult<()>>,
+        changes: ReplicatedChanges,
+    },
+    Compact,
+    RotateKey,
+    Expire,
 }
@@ -0,0 +1,690 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// The remote backend keeps the entries on a key-value server outside of the
+// enclave, speaking the Redis protocol (e.g., Redis, or a proxy in front of
+// another store). The server never sees the keys and values:
+//
+// - an entry is stored under `<namespace>:` followed by the hex HMAC-SHA256
+//   of its key
+// - its value is the version of its value key (u32, big endian) and a random
+//   nonce, followed by the key and value encrypted with AES-128-GCM, bound to
+//   the key on the server
+//
+// The keys are sealed to the storage enclave. The value key is rotated online
+// (`RotateKey`): new entries are encrypted with the next version, and the
+// entries under older versions are re-encrypted in place, a batch at a time,
+// while each entry is read with the version it names. The version in use is
+// kept on the server at `<namespace>#key-version`, and the last version all
+// entries were re-encrypted with at `<namespace>#rotated-version`, so that
+// rotations resume after restarts.
+//
+// The server can still drop entries or serve older versions of them. Scans
+// fetch all entries of the namespace and sort them in the enclave, so the
+// backend suits databases which are small or rarely scanned.
+
+use crate::backend::{Entries, EntryKeys, StorageBackend};
+use crate::sealing;
+use anyhow::{anyhow, bail, ensure, Result};
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::format;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::prelude::v1::*;
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+use teaclave_proto::teaclave_storage_service::{KeyRotationProgress, StorageChange};
+
+// Number of keys asked for in each SCAN and MGET command.
+const BATCH_SIZE: usize = 1000;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const KEY_VERSION_SUFFIX: &str = "#key-version";
+const ROTATED_VERSION_SUFFIX: &str = "#rotated-version";
+const VERSION_LEN: usize = 4;
+
+// The sealed value key of a version.
+fn sealed_value_key(version: u32) -> Result<[u8; 16]> {
+    match version {
+        0 => sealing::derive_key(b"teaclave-storage-remote-value"),
+        version => {
+            sealing::derive_key(format!("teaclave-storage-remote-value-{}", version).as_bytes())
+        }
+    }
+}
+
+pub(crate) struct EntryCipher {
+    // Value keys by version, the last one encrypting new entries.
+    value_keys: Vec<LessSafeKey>,
+    index_key: hmac::Key,
+}
+
+impl EntryCipher {
+    /// Cipher with `value_key` as version 0.
+    pub(crate) fn new(value_key: &[u8], index_key: &[u8]) -> Result<Self> {
+        let mut cipher = Self {
+            value_keys: Vec::new(),
+            index_key: hmac::Key::new(hmac::HMAC_SHA256, index_key),
+        };
+        cipher.add_value_key(value_key)?;
+        Ok(cipher)
+    }
+
+    /// Keys sealed to the storage enclave, with the value key of version 0.
+    pub(crate) fn sealed() -> Result<Self> {
+        Self::new(
+            &sealed_value_key(0)?,
+            &sealing::derive_key(b"teaclave-storage-remote-index")?,
+        )
+    }
+
+    /// Adds the value key of the next version, which encrypts new entries.
+    pub(crate) fn add_value_key(&mut self, value_key: &[u8]) -> Result<u32> {
+        let value_key = UnboundKey::new(&aead::AES_128_GCM, value_key)
+            .map_err(|_| anyhow!("invalid value key"))?;
+        self.value_keys.push(LessSafeKey::new(value_key));
+        Ok(self.key_version())
+    }
+
+    fn key_version(&self) -> u32 {
+        self.value_keys.len() as u32 - 1
+    }
+
+    fn entry_version(sealed: &[u8]) -> Result<u32> {
+        ensure!(sealed.len() >= VERSION_LEN, "entry too short");
+        let mut version = [0u8; VERSION_LEN];
+        version.copy_from_slice(&sealed[..VERSION_LEN]);
+        Ok(u32::from_be_bytes(version))
+    }
+
+    fn remote_key(&self, namespace: &str, key: &[u8]) -> Vec<u8> {
+        let tag = hmac::sign(&self.index_key, key);
+        let mut remote_key = format!("{}:", namespace).into_bytes();
+        for b in tag.as_ref() {
+            remote_key.extend_from_slice(format!("{:02x}", b).as_bytes());
+        }
+        remote_key
+    }
+
+    fn seal(&self, remote_key: &[u8], key: &[u8], value: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("cannot generate nonce"))?;
+        let mut in_out = (key.len() as u32).to_be_bytes().to_vec();
+        in_out.extend_from_slice(key);
+        in_out.extend_from_slice(value);
+        let version = self.key_version();
+        self.value_keys[version as usize]
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(remote_key),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow!("cannot encrypt entry"))?;
+        let mut sealed = version.to_be_bytes().to_vec();
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&in_out);
+        Ok(sealed)
+    }
+
+    fn open(&self, remote_key: &[u8], sealed: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let version = Self::entry_version(sealed)?;
+        let value_key = self
+            .value_keys
+            .get(version as usize)
+            .ok_or_else(|| anyhow!("entry of unknown key version {}", version))?;
+        let sealed = &sealed[VERSION_LEN..];
+        ensure!(sealed.len() >= NONCE_LEN, "entry too short");
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&sealed[..NONCE_LEN]);
+        let mut in_out = sealed[NONCE_LEN..].to_vec();
+        let plain = value_key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(remote_key),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow!("cannot decrypt entry"))?;
+        ensure!(plain.len() >= 4, "entry too short");
+        let mut key_len = [0u8; 4];
+        key_len.copy_from_slice(&plain[..4]);
+        let key_end = 4 + u32::from_be_bytes(key_len) as usize;
+        ensure!(plain.len() >= key_end, "entry too short");
+        Ok((plain[4..key_end].to_vec(), plain[key_end..].to_vec()))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+}
+
+fn encode_command(args: &[&[u8]]) -> Vec<u8> {
+    let mut command = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        command.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        command.extend_from_slice(arg);
+        command.extend_from_slice(b"\r\n");
+    }
+    command
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    ensure!(line.ends_with("\r\n"), "connection closed");
+    line.truncate(line.len() - 2);
+    Ok(line)
+}
+
+fn read_reply(reader: &mut impl BufRead) -> Result<Reply> {
+    let line = read_line(reader)?;
+    let (kind, rest) = line.split_at(std::cmp::min(1, line.len()));
+    let reply = match kind {
+        "+" => Reply::Status(rest.to_string()),
+        "-" => Reply::Error(rest.to_string()),
+        ":" => Reply::Integer(rest.parse()?),
+        "$" => match rest.parse::<i64>()? {
+            len if len < 0 => Reply::Bulk(None),
+            len => {
+                let mut data = vec![0u8; len as usize + 2];
+                reader.read_exact(&mut data)?;
+                ensure!(data.ends_with(b"\r\n"), "invalid bulk string");
+                data.truncate(len as usize);
+                Reply::Bulk(Some(data))
+            }
+        },
+        "*" => match rest.parse::<i64>()? {
+            len if len < 0 => Reply::Bulk(None),
+            len => Reply::Array(
+                (0..len)
+                    .map(|_| read_reply(reader))
+                    .collect::<Result<_>>()?,
+            ),
+        },
+        _ => bail!("invalid reply: {}", line),
+    };
+    Ok(reply)
+}
+
+// Glob characters in the namespace are matched literally by SCAN.
+fn escape_pattern(namespace: &str) -> String {
+    let mut pattern = String::new();
+    for c in namespace.chars() {
+        if "*?[]\\".contains(c) {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn open(address: &str) -> Result<Self> {
+        let writer = TcpStream::connect(address)?;
+        writer.set_read_timeout(Some(TIMEOUT))?;
+        writer.set_write_timeout(Some(TIMEOUT))?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+
+    fn call(&mut self, args: &[&[u8]]) -> Result<Reply> {
+        self.writer.write_all(&encode_command(args))?;
+        read_reply(&mut self.reader)
+    }
+}
+
+// Entries left to re-encrypt with the value key of the target version.
+struct EntryRotation {
+    target_version: u32,
+    // Keys on the server when the rotation started (or resumed). Entries
+    // written since are encrypted with the target version already.
+    pending: Vec<Vec<u8>>,
+    total_keys: u64,
+    started_at: SystemTime,
+}
+
+impl EntryRotation {
+    fn new(target_version: u32, pending: Vec<Vec<u8>>) -> Self {
+        Self {
+            target_version,
+            total_keys: pending.len() as u64,
+            pending,
+            started_at: SystemTime::now(),
+        }
+    }
+}
+
+pub(crate) struct RemoteKv {
+    address: String,
+    namespace: String,
+    cipher: EntryCipher,
+    // Opened on the first command, and again after a failed one.
+    connection: Option<Connection>,
+    rotation: Option<EntryRotation>,
+}
+
+impl RemoteKv {
+    pub(crate) fn new(
+        address: impl Into<String>,
+        namespace: impl Into<String>,
+        cipher: EntryCipher,
+    ) -> Self {
+        Self {
+            address: address.into(),
+            namespace: namespace.into(),
+            cipher,
+            connection: None,
+            rotation: None,
+        }
+    }
+
+    /// Connects with the keys sealed to the storage enclave up to the version
+    /// in use, and resumes an unfinished rotation.
+    pub(crate) fn open_sealed(
+        address: impl Into<String>,
+        namespace: impl Into<String>,
+    ) -> Result<Self> {
+        let mut remote = Self::new(address, namespace, EntryCipher::sealed()?);
+        let key_version = remote.read_version(KEY_VERSION_SUFFIX)?;
+        for version in 1..=key_version {
+            remote.cipher.add_value_key(&sealed_value_key(version)?)?;
+        }
+        if remote.read_version(ROTATED_VERSION_SUFFIX)? < key_version {
+            let pending = remote.remote_keys()?;
+            remote.rotation = Some(EntryRotation::new(key_version, pending));
+        }
+        Ok(remote)
+    }
+
+    fn meta_key(&self, suffix: &str) -> Vec<u8> {
+        format!("{}{}", self.namespace, suffix).into_bytes()
+    }
+
+    fn read_version(&mut self, suffix: &str) -> Result<u32> {
+        let key = self.meta_key(suffix);
+        match self.call(&[b"GET", &key])? {
+            Reply::Bulk(None) => Ok(0),
+            Reply::Bulk(Some(version)) => Ok(std::str::from_utf8(&version)?.parse()?),
+            reply => bail!("unexpected reply to GET: {:?}", reply),
+        }
+    }
+
+    fn write_version(&mut self, suffix: &str, version: u32) -> Result<()> {
+        let key = self.meta_key(suffix);
+        match self.call(&[b"SET", &key, version.to_string().as_bytes()])? {
+            Reply::Status(_) => Ok(()),
+            reply => bail!("unexpected reply to SET: {:?}", reply),
+        }
+    }
+
+    // Re-encrypts the entries of `remote_keys` under versions before
+    // `target_version`. Entries deleted since are skipped.
+    fn reencrypt(&mut self, remote_keys: &[Vec<u8>], target_version: u32) -> Result<()> {
+        let mut args: Vec<&[u8]> = vec![b"MGET"];
+        args.extend(remote_keys.iter().map(|key| key.as_slice()));
+        let values = match self.call(&args)? {
+            Reply::Array(values) if values.len() == remote_keys.len() => values,
+            reply => bail!("unexpected reply to MGET: {:?}", reply),
+        };
+        for (remote_key, value) in remote_keys.iter().zip(values) {
+            let sealed = match value {
+                Reply::Bulk(Some(sealed)) => sealed,
+                Reply::Bulk(None) => continue,
+                value => bail!("unexpected value from MGET: {:?}", value),
+            };
+            if EntryCipher::entry_version(&sealed)? >= target_version {
+                continue;
+            }
+            let (key, value) = self.cipher.open(remote_key, &sealed)?;
+            let sealed = self.cipher.seal(remote_key, &key, &value)?;
+            match self.call(&[b"SET", remote_key, &sealed])? {
+                Reply::Status(_) => (),
+                reply => bail!("unexpected reply to SET: {:?}", reply),
+            }
+        }
+        Ok(())
+    }
+
+    fn connect(&mut self) -> Result<Connection> {
+        match self.connection.take() {
+            Some(connection) => Ok(connection),
+            None => Connection::open(&self.address)
+                .map_err(|e| anyhow!("cannot connect to {}: {}", self.address, e)),
+        }
+    }
+
+    fn call(&mut self, args: &[&[u8]]) -> Result<Reply> {
+        let mut connection = self.connect()?;
+        let reply = connection.call(args)?;
+        self.connection = Some(connection);
+        match reply {
+            Reply::Error(message) => bail!("remote storage error: {}", message),
+            reply => Ok(reply),
+        }
+    }
+
+    // Runs `commands` in a MULTI/EXEC transaction, which the server applies
+    // all together. The connection is dropped on errors, which discards the
+    // transaction if not executed yet.
+    fn transaction(&mut self, commands: &[Vec<Vec<u8>>]) -> Result<()> {
+        let mut connection = self.connect()?;
+        match connection.call(&[b"MULTI"])? {
+            Reply::Status(_) => (),
+            reply => bail!("unexpected reply to MULTI: {:?}", reply),
+        }
+        for command in commands {
+            let args: Vec<&[u8]> = command.iter().map(|arg| arg.as_slice()).collect();
+            match connection.call(&args)? {
+                Reply::Status(_) => (),
+                reply => bail!("command not queued: {:?}", reply),
+            }
+        }
+        let replies = match connection.call(&[b"EXEC"])? {
+            Reply::Array(replies) if replies.len() == commands.len() => replies,
+            reply => bail!("unexpected reply to EXEC: {:?}", reply),
+        };
+        self.connection = Some(connection);
+        for reply in replies {
+            if let Reply::Error(message) = reply {
+                bail!("remote storage error: {}", message);
+            }
+        }
+        Ok(())
+    }
+
+    // Keys of all entries of the namespace on the server.
+    fn remote_keys(&mut self) -> Result<Vec<Vec<u8>>> {
+        let pattern = format!("{}:*", escape_pattern(&self.namespace));
+        let count = BATCH_SIZE.to_string();
+        let mut cursor = b"0".to_vec();
+        let mut keys = Vec::new();
+        loop {
+            let reply = self.call(&[
+                b"SCAN",
+                &cursor,
+                b"MATCH",
+                pattern.as_bytes(),
+                b"COUNT",
+                count.as_bytes(),
+            ])?;
+            let mut reply = match reply {
+                Reply::Array(reply) if reply.len() == 2 => reply,
+                reply => bail!("unexpected reply to SCAN: {:?}", reply),
+            };
+            match reply.pop() {
+                Some(Reply::Array(batch)) => {
+                    for key in batch {
+                        match key {
+                            Reply::Bulk(Some(key)) => keys.push(key),
+                            key => bail!("unexpected key from SCAN: {:?}", key),
+                        }
+                    }
+                }
+                batch => bail!("unexpected keys from SCAN: {:?}", batch),
+            }
+            cursor = match reply.pop() {
+                Some(Reply::Bulk(Some(cursor))) => cursor,
+                next => bail!("unexpected cursor from SCAN: {:?}", next),
+            };
+            if cursor == b"0" {
+                break;
+            }
+        }
+        // Keys may be returned more than once while the server rehashes.
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+}
+
+impl EntryKeys for RemoteKv {
+    fn key_version(&self) -> u32 {
+        self.cipher.key_version()
+    }
+
+    fn progress(&self) -> Option<KeyRotationProgress> {
+        self.rotation.as_ref().map(|rotation| KeyRotationProgress {
+            target_version: rotation.target_version,
+            copied_keys: rotation.total_keys - rotation.pending.len() as u64,
+            total_keys: rotation.total_keys,
+            started_at: rotation.started_at,
+        })
+    }
+
+    fn begin_rotation(&mut self) -> Result<u32> {
+        ensure!(self.rotation.is_none(), "key rotation in progress");
+        let version = self.cipher.key_version() + 1;
+        let value_key = sealed_value_key(version)?;
+        let pending = self.remote_keys()?;
+        // Recorded before any entry is encrypted with the new version, so that
+        // its key is derived again after restarts.
+        self.write_version(KEY_VERSION_SUFFIX, version)?;
+        self.cipher.add_value_key(&value_key)?;
+        self.rotation = Some(EntryRotation::new(version, pending));
+        Ok(version)
+    }
+
+    fn step(&mut self, batch_size: u64) -> Result<()> {
+        let mut rotation = match self.rotation.take() {
+            Some(rotation) => rotation,
+            None => return Ok(()),
+        };
+        let split = rotation.pending.len().saturating_sub(batch_size as usize);
+        let batch = rotation.pending.split_off(split);
+        if let Err(e) = self.reencrypt(&batch, rotation.target_version) {
+            rotation.pending.extend(batch);
+            self.rotation = Some(rotation);
+            return Err(e);
+        }
+        if !rotation.pending.is_empty() {
+            self.rotation = Some(rotation);
+            return Ok(());
+        }
+        if let Err(e) = self.write_version(ROTATED_VERSION_SUFFIX, rotation.target_version) {
+            self.rotation = Some(rotation);
+            return Err(e);
+        }
+        info!(
+            "Rotated the remote storage key to version {}",
+            rotation.target_version
+        );
+        Ok(())
+    }
+}
+
+impl StorageBackend for RemoteKv {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let remote_key = self.cipher.remote_key(&self.namespace, key);
+        match self.call(&[b"GET", &remote_key])? {
+            Reply::Bulk(None) => Ok(None),
+            Reply::Bulk(Some(sealed)) => {
+                let (entry_key, value) = self.cipher.open(&remote_key, &sealed)?;
+                ensure!(entry_key == key, "entry of another key");
+                Ok(Some(value))
+            }
+            reply => bail!("unexpected reply to GET: {:?}", reply),
+        }
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let remote_key = self.cipher.remote_key(&self.namespace, key);
+        let sealed = self.cipher.seal(&remote_key, key, value)?;
+        match self.call(&[b"SET", &remote_key, &sealed])? {
+            Reply::Status(_) => Ok(()),
+            reply => bail!("unexpected reply to SET: {:?}", reply),
+        }
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<()> {
+        let remote_key = self.cipher.remote_key(&self.namespace, key);
+        match self.call(&[b"DEL", &remote_key])? {
+            Reply::Integer(_) => Ok(()),
+            reply => bail!("unexpected reply to DEL: {:?}", reply),
+        }
+    }
+
+    fn write_batch(&mut self, changes: &[StorageChange]) -> Result<()> {
+        let mut commands = Vec::with_capacity(changes.len());
+        for change in changes {
+            let command = match change {
+                StorageChange::Put { key, value } => {
+                    let remote_key = self.cipher.remote_key(&self.namespace, key);
+                    let sealed = self.cipher.seal(&remote_key, key, value)?;
+                    vec![b"SET".to_vec(), remote_key, sealed]
+                }
+                StorageChange::Delete { key } => {
+                    vec![
+                        b"DEL".to_vec(),
+                        self.cipher.remote_key(&self.namespace, key),
+                    ]
+                }
+            };
+            commands.push(command);
+        }
+        self.transaction(&commands)
+    }
+
+    fn scan(&mut self, start: &[u8]) -> Result<Entries<'_>> {
+        let remote_keys = self.remote_keys()?;
+        let mut entries = Vec::new();
+        for batch in remote_keys.chunks(BATCH_SIZE) {
+            let mut args: Vec<&[u8]> = vec![b"MGET"];
+            args.extend(batch.iter().map(|key| key.as_slice()));
+            let values = match self.call(&args)? {
+                Reply::Array(values) if values.len() == batch.len() => values,
+                reply => bail!("unexpected reply to MGET: {:?}", reply),
+            };
+            for (remote_key, value) in batch.iter().zip(values) {
+                match value {
+                    Reply::Bulk(Some(sealed)) => {
+                        let (key, value) = self.cipher.open(remote_key, &sealed)?;
+                        if key.as_slice() >= start {
+                            entries.push((key, value));
+                        }
+                    }
+                    // Deleted since the scan.
+                    Reply::Bulk(None) => (),
+                    value => bail!("unexpected value from MGET: {:?}", value),
+                }
+            }
+        }
+        entries.sort();
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    // Left to the server.
+    fn compact_range(&mut self, _first: &[u8], _last: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn compacts_locally(&self) -> bool {
+        false
+    }
+
+    // Writes are acknowledged once accepted by the server, and persisted as
+    // configured there.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn entry_keys(&mut self) -> Option<&mut dyn EntryKeys> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_entry_cipher() {
+        let cipher = EntryCipher::new(&[1u8; 16], &[2u8; 16]).unwrap();
+        let remote_key = cipher.remote_key("teaclave", b"task-1");
+        assert!(remote_key.starts_with(b"teaclave:"));
+        assert_eq!(remote_key.len(), "teaclave:".len() + 64);
+        assert_eq!(remote_key, cipher.remote_key("teaclave", b"task-1"));
+        assert_ne!(remote_key, cipher.remote_key("teaclave", b"task-2"));
+
+        let sealed = cipher.seal(&remote_key, b"task-1", b"value").unwrap();
+        let (key, value) = cipher.open(&remote_key, &sealed).unwrap();
+        assert_eq!(key, b"task-1");
+        assert_eq!(value, b"value");
+
+        // Entries cannot be moved to other keys or modified.
+        let other_key = cipher.remote_key("teaclave", b"task-2");
+        assert!(cipher.open(&other_key, &sealed).is_err());
+        let mut modified = sealed.clone();
+        let last = modified.len() - 1;
+        modified[last] ^= 1;
+        assert!(cipher.open(&remote_key, &modified).is_err());
+
+        // Entries are read with the key of their version.
+        let mut cipher = cipher;
+        assert_eq!(cipher.add_value_key(&[3u8; 16]).unwrap(), 1);
+        let resealed = cipher.seal(&remote_key, b"task-1", b"value").unwrap();
+        assert_eq!(EntryCipher::entry_version(&sealed).unwrap(), 0);
+        assert_eq!(EntryCipher::entry_version(&resealed).unwrap(), 1);
+        for sealed in [&sealed, &resealed].iter() {
+            let (_, value) = cipher.open(&remote_key, sealed).unwrap();
+            assert_eq!(value, b"value");
+        }
+        let mut unknown = sealed;
+        unknown[..VERSION_LEN].copy_from_slice(&2u32.to_be_bytes());
+        assert!(cipher.open(&remote_key, &unknown).is_err());
+    }
+
+    pub fn test_redis_protocol() {
+        assert_eq!(
+            encode_command(&[b"GET", b"key"]),
+            b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n".to_vec()
+        );
+        assert_eq!(escape_pattern("a*b"), "a\\*b");
+
+        let mut reader: &[u8] = b"*2\r\n$1\r\n0\r\n*2\r\n$2\r\nk1\r\n$-1\r\n";
+        assert_eq!(
+            read_reply(&mut reader).unwrap(),
+            Reply::Array(vec![
+                Reply::Bulk(Some(b"0".to_vec())),
+                Reply::Array(vec![Reply::Bulk(Some(b"k1".to_vec())), Reply::Bulk(None)]),
+            ])
+        );
+        let mut reader: &[u8] = b"+OK\r\n:1\r\n-ERR wrong type\r\n";
+        assert_eq!(
+            read_reply(&mut reader).unwrap(),
+            Reply::Status("OK".to_string())
+        );
+        assert_eq!(read_reply(&mut reader).unwrap(), Reply::Integer(1));
+        assert_eq!(
+            read_reply(&mut reader).unwrap(),
+            Reply::Error("ERR wrong type".to_string())
+        );
+        assert!(read_reply(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::backend::StorageBackend;
+use crate::proxy::ProxyRequest;
+use anyhow::{anyhow, Result};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::VecDeque;
+use std::prelude::v1::*;
+use std::sync::mpsc::{channel, Sender};
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+use teaclave_proto::teaclave_storage_service::{
+    ChangeCursor, GetChangesResponse, StorageChange, TeaclaveStorageClient,
+};
+use teaclave_rpc::endpoint::Endpoint;
+
+// Recent changes applied to the primary, numbered by a sequence which grows
+// by one for each change. Only the last `capacity` changes are kept; replicas
+// lagging further behind are synced with a full snapshot. The sequence starts
+// over when the primary restarts, so each run of the primary draws a random
+// epoch, and replicas of another epoch are synced with a snapshot too.
+pub(crate) struct ChangeLog {
+    epoch: u64,
+    sequence: u64,
+    changes: VecDeque<StorageChange>,
+    capacity: usize,
+}
+
+impl ChangeLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            epoch: 0,
+            sequence: 0,
+            changes: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub(crate) fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub(crate) fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub(crate) fn record(&mut self, change: StorageChange) {
+        self.sequence += 1;
+        self.changes.push_back(change);
+        if self.changes.len() > self.capacity {
+            self.changes.pop_front();
+        }
+    }
+
+    // Returns the changes after `since` in `epoch`, or None if some of them
+    // are no longer in the log (or `since` is of another epoch, e.g., before
+    // the primary restarted).
+    pub(crate) fn since(&self, epoch: u64, since: u64) -> Option<Vec<StorageChange>> {
+        let first = self.sequence - self.changes.len() as u64;
+        if epoch != self.epoch || since < first || since > self.sequence {
+            return None;
+        }
+        let skip = (since - first) as usize;
+        Some(self.changes.iter().skip(skip).cloned().collect())
+    }
+}
+
+pub(crate) enum ReplicationState {
+    Primary(ChangeLog),
+    Replica(ReplicaState),
+}
+
+impl ReplicationState {
+    pub(crate) fn primary(change_log_capacity: usize) -> Result<Self> {
+        let mut epoch = [0u8; 8];
+        SystemRandom::new()
+            .fill(&mut epoch)
+            .map_err(|_| anyhow!("cannot generate change log epoch"))?;
+        let mut change_log = ChangeLog::new(change_log_capacity);
+        // Zero is left to callers which have applied no changes yet.
+        change_log.epoch = u64::from_le_bytes(epoch).max(1);
+        Ok(ReplicationState::Primary(change_log))
+    }
+
+    pub(crate) fn replica() -> Self {
+        ReplicationState::Replica(ReplicaState::default())
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ReplicaState {
+    // Time at which the last applied changes were fetched from the primary.
+    synced_at: Option<SystemTime>,
+    // Snapshot being fetched from the primary a page at a time.
+    pub(crate) staged: Option<Box<dyn StorageBackend>>,
+}
+
+impl ReplicaState {
+    pub(crate) fn synced(&mut self, fetched_at: SystemTime) {
+        self.synced_at = Some(fetched_at);
+    }
+
+    // None if the replica has never been in sync with the primary.
+    pub(crate) fn staleness(&self) -> Option<Duration> {
+        self.synced_at
+            .map(|t| SystemTime::now().duration_since(t).unwrap_or_default())
+    }
+}
+
+// Changes fetched from the primary, to be applied by the storage thread.
+pub(crate) struct ReplicatedChanges {
+    pub fetched_at: SystemTime,
+    // Whether the changes are the first page of a snapshot.
+    pub starts_snapshot: bool,
+    pub changes: GetChangesResponse,
+}
+
+// Polls the primary for changes every `interval` and forwards them to the
+// storage thread. The pages of a snapshot, and the changes made while they
+// were paged, are fetched without waiting. The position is only advanced
+// once the storage thread has applied the changes; if they fail to apply, the
+// replica starts over with a snapshot. Runs until the storage thread exits.
+pub(crate) fn follow_primary(primary: Endpoint, sender: Sender<ProxyRequest>, interval: Duration) {
+    let mut client: Option<TeaclaveStorageClient> = None;
+    let mut cursor = ChangeCursor::default();
+    let mut catching_up = false;
+    loop {
+        if !catching_up {
+            std::thread::sleep(interval);
+        }
+        catching_up = false;
+
+        if client.is_none() {
+            client = match primary.connect().and_then(TeaclaveStorageClient::new) {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    log::debug!("Failed to connect to primary storage: {:?}", e);
+                    continue;
+                }
+            };
+        }
+
+        let fetched_at = SystemTime::now();
+        let changes = match client.as_mut().unwrap().get_changes(cursor.request()) {
+            Ok(changes) => changes,
+            Err(e) => {
+                log::debug!("Failed to get changes from primary storage: {:?}", e);
+                client = None;
+                continue;
+            }
+        };
+        let mut next = cursor.clone();
+        next.advance(&changes);
+        catching_up = changes.snapshot;
+
+        let (result_sender, result_receiver) = channel();
+        let request = ProxyRequest::Replicate {
+            sender: result_sender,
+            changes: ReplicatedChanges {
+                fetched_at,
+                starts_snapshot: cursor.starts_snapshot(&changes),
+                changes,
+            },
+        };
+        if sender.send(request).is_err() {
+            break;
+        }
+        match result_receiver.recv() {
+            Ok(Ok(())) => cursor = next,
+            Ok(Err(e)) => {
+                log::warn!("Failed to apply changes of primary storage: {:?}", e);
+                cursor = ChangeCursor::default();
+                catching_up = false;
+            }
+            Err(_) => break,
+        }
+    }
+}
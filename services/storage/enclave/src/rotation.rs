@@ -0,0 +1,376 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// The database of the primary can be persisted in protected files under a
+// data directory, encrypted with a seal key of the storage enclave. The key is
+// rotated online by copying the database, a batch of keys at a time, into a
+// new database encrypted with the next key, while writes keep going to the
+// database in use. The directory holds:
+//
+// - `v<N>`: the database encrypted with the key of version N
+// - `CURRENT_KEY`: the version of the database in use (0 if missing)
+// - `ROTATING_KEY`: the target version of an unfinished rotation, resumed
+//   after restarts
+//
+// Backends which encrypt each entry themselves rotate their keys in place
+// instead (see `EntryKeys`).
+
+use crate::backend::{LevelDb, StorageBackend};
+use crate::proxy::ProxyRequest;
+use crate::sealing;
+use anyhow::{anyhow, bail, Result};
+use std::format;
+#[cfg(not(feature = "mesalock_sgx"))]
+use std::fs;
+use std::path::PathBuf;
+use std::prelude::v1::*;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::fs;
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::path::PathEx;
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+use teaclave_proto::teaclave_storage_service::{KeyRotationProgress, StorageChange};
+
+const CURRENT_KEY_FILE: &str = "CURRENT_KEY";
+const ROTATING_KEY_FILE: &str = "ROTATING_KEY";
+
+// The seal key of the database of a version.
+fn database_key(version: u32) -> Result<[u8; 16]> {
+    sealing::derive_key(format!("teaclave-storage-key-{}", version).as_bytes())
+}
+
+pub(crate) struct DataDir {
+    path: PathBuf,
+}
+
+impl DataDir {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn database_path(&self, version: u32) -> PathBuf {
+        self.path.join(format!("v{}", version))
+    }
+
+    fn read_version(&self, name: &str) -> Result<Option<u32>> {
+        let path = self.path.join(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(&path)?.trim().parse()?))
+    }
+
+    // Written to a temporary file first, so that the file is replaced as a
+    // whole.
+    fn write_version(&self, name: &str, version: u32) -> Result<()> {
+        let tmp_path = self.path.join(format!("{}.tmp", name));
+        fs::write(&tmp_path, version.to_string())?;
+        fs::rename(&tmp_path, self.path.join(name))?;
+        Ok(())
+    }
+
+    fn open(&self, version: u32) -> Result<Box<dyn StorageBackend>> {
+        let database = LevelDb::on_disk(self.database_path(version), database_key(version)?)?;
+        Ok(Box::new(database))
+    }
+
+    /// Opens the database in use and resumes an unfinished rotation.
+    pub(crate) fn open_all(
+        self,
+        batch_size: u64,
+    ) -> Result<(Box<dyn StorageBackend>, EncryptionState)> {
+        fs::create_dir_all(&self.path)?;
+        let key_version = self.read_version(CURRENT_KEY_FILE)?.unwrap_or(0);
+        let mut database = self.open(key_version)?;
+        let rotation = match self.read_version(ROTATING_KEY_FILE)? {
+            Some(target_version) if target_version > key_version => {
+                let target = self.open(target_version)?;
+                Some(KeyRotation::resume(target_version, target, &mut *database)?)
+            }
+            // The rotation was committed, but the file was not removed.
+            Some(_) => {
+                fs::remove_file(self.path.join(ROTATING_KEY_FILE))?;
+                None
+            }
+            None => None,
+        };
+        // Left over if the previous rotation failed to remove it.
+        if key_version > 0 && self.database_path(key_version - 1).exists() {
+            self.remove(key_version - 1);
+        }
+        let encryption = EncryptionState::new(Some(self), key_version, rotation, batch_size);
+        Ok((database, encryption))
+    }
+
+    fn begin_rotation(&self, version: u32) -> Result<Box<dyn StorageBackend>> {
+        let target_path = self.database_path(version);
+        if target_path.exists() {
+            fs::remove_dir_all(&target_path)?;
+        }
+        self.write_version(ROTATING_KEY_FILE, version)?;
+        self.open(version)
+    }
+
+    fn commit_rotation(&self, version: u32) -> Result<()> {
+        self.write_version(CURRENT_KEY_FILE, version)?;
+        fs::remove_file(self.path.join(ROTATING_KEY_FILE))?;
+        Ok(())
+    }
+
+    fn abort_rotation(&self, version: u32) -> Result<()> {
+        fs::remove_file(self.path.join(ROTATING_KEY_FILE))?;
+        fs::remove_dir_all(self.database_path(version))?;
+        Ok(())
+    }
+
+    // Removes the files of an old database, which is only logged on failure
+    // since the database is no longer used.
+    fn remove(&self, version: u32) {
+        if let Err(e) = fs::remove_dir_all(self.database_path(version)) {
+            warn!("Failed to remove the storage database v{}: {}", version, e);
+        }
+    }
+}
+
+// Copy of the database in use into the database of the next key.
+pub(crate) struct KeyRotation {
+    target_version: u32,
+    target: Box<dyn StorageBackend>,
+    // Keys up to the cursor have been copied, and writes to them are applied
+    // to the target as well.
+    cursor: Option<Vec<u8>>,
+    copied_keys: u64,
+    total_keys: u64,
+    started_at: SystemTime,
+}
+
+impl KeyRotation {
+    /// Continues copying after the last key of `target`, which is empty for
+    /// a new rotation.
+    pub(crate) fn resume(
+        target_version: u32,
+        mut target: Box<dyn StorageBackend>,
+        source: &mut dyn StorageBackend,
+    ) -> Result<Self> {
+        let mut cursor = None;
+        let mut copied_keys = 0;
+        for (key, _) in target.scan(b"")? {
+            cursor = Some(key);
+            copied_keys += 1;
+        }
+        let total_keys = source.scan(b"")?.count() as u64;
+        Ok(Self {
+            target_version,
+            target,
+            cursor,
+            copied_keys,
+            total_keys,
+            started_at: SystemTime::now(),
+        })
+    }
+
+    fn is_copied(&self, key: &[u8]) -> bool {
+        match &self.cursor {
+            Some(cursor) => key <= cursor.as_slice(),
+            None => false,
+        }
+    }
+
+    // Copies up to `batch_size` keys after the cursor, and returns true if
+    // all keys have been copied.
+    fn step(&mut self, source: &mut dyn StorageBackend, batch_size: u64) -> Result<bool> {
+        let start = self.cursor.clone().unwrap_or_default();
+        let mut entries = source.scan(&start)?;
+        let mut copied = 0;
+        while copied < batch_size {
+            let (key, value) = match entries.next() {
+                Some(entry) => entry,
+                None => return Ok(true),
+            };
+            // The scan starts at the cursor if it has not been deleted.
+            if !self.is_copied(&key) {
+                self.target.put(&key, &value)?;
+                self.cursor = Some(key);
+                self.copied_keys += 1;
+                copied += 1;
+            }
+        }
+        Ok(entries.next().is_none())
+    }
+
+    fn mirror(&mut self, changes: &[StorageChange]) -> Result<()> {
+        for change in changes {
+            match change {
+                StorageChange::Put { key, value } if self.is_copied(key) => {
+                    self.target.put(key, value)?
+                }
+                StorageChange::Delete { key } if self.is_copied(key) => self.target.delete(key)?,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn progress(&self) -> KeyRotationProgress {
+        KeyRotationProgress {
+            target_version: self.target_version,
+            copied_keys: self.copied_keys,
+            total_keys: self.total_keys,
+            started_at: self.started_at,
+        }
+    }
+}
+
+// Version of the key of the database in use and its unfinished rotation.
+pub(crate) struct EncryptionState {
+    // None if the database is kept in memory.
+    data_dir: Option<DataDir>,
+    key_version: u32,
+    rotation: Option<KeyRotation>,
+    batch_size: u64,
+}
+
+impl EncryptionState {
+    pub(crate) fn new(
+        data_dir: Option<DataDir>,
+        key_version: u32,
+        rotation: Option<KeyRotation>,
+        batch_size: u64,
+    ) -> Self {
+        Self {
+            data_dir,
+            key_version,
+            rotation,
+            batch_size,
+        }
+    }
+
+    pub(crate) fn in_memory() -> Self {
+        Self::new(None, 0, None, 0)
+    }
+
+    pub(crate) fn key_version(&self, database: &mut dyn StorageBackend) -> u32 {
+        match database.entry_keys() {
+            Some(entry_keys) => entry_keys.key_version(),
+            None => self.key_version,
+        }
+    }
+
+    pub(crate) fn progress(
+        &self,
+        database: &mut dyn StorageBackend,
+    ) -> Option<KeyRotationProgress> {
+        match database.entry_keys() {
+            Some(entry_keys) => entry_keys.progress(),
+            None => self.rotation.as_ref().map(KeyRotation::progress),
+        }
+    }
+
+    /// Starts re-encrypting `database` with the next key, and returns its
+    /// version.
+    pub(crate) fn begin_rotation(&mut self, database: &mut dyn StorageBackend) -> Result<u32> {
+        if let Some(entry_keys) = database.entry_keys() {
+            return entry_keys.begin_rotation();
+        }
+        if self.rotation.is_some() {
+            bail!("key rotation in progress");
+        }
+        let data_dir = match &self.data_dir {
+            Some(data_dir) => data_dir,
+            None => bail!("database not persisted"),
+        };
+        let version = self.key_version + 1;
+        let target = data_dir.begin_rotation(version)?;
+        self.rotation = Some(KeyRotation::resume(version, target, database)?);
+        Ok(version)
+    }
+
+    /// Applies the changes of a write to the keys already copied. Changes
+    /// which cannot be applied, e.g., changes dropped from the change log,
+    /// abort the rotation.
+    pub(crate) fn mirror(&mut self, changes: Option<Vec<StorageChange>>) {
+        let rotation = match &mut self.rotation {
+            Some(rotation) => rotation,
+            None => return,
+        };
+        let result = match changes {
+            Some(changes) => rotation.mirror(&changes),
+            None => Err(anyhow!("changes dropped from the change log")),
+        };
+        if let Err(e) = result {
+            error!("Aborting the storage key rotation: {:?}", e);
+            self.abort_rotation();
+        }
+    }
+
+    fn abort_rotation(&mut self) {
+        let rotation = match self.rotation.take() {
+            Some(rotation) => rotation,
+            None => return,
+        };
+        let target_version = rotation.target_version;
+        drop(rotation);
+        if let Some(data_dir) = &self.data_dir {
+            if let Err(e) = data_dir.abort_rotation(target_version) {
+                error!("Failed to remove the aborted storage key rotation: {}", e);
+            }
+        }
+    }
+
+    /// Copies the next batch of keys, and switches `database` to the target
+    /// of the rotation once all keys are copied.
+    pub(crate) fn step(&mut self, database: &mut Box<dyn StorageBackend>) -> Result<()> {
+        if let Some(entry_keys) = database.entry_keys() {
+            return entry_keys.step(self.batch_size);
+        }
+        let rotation = match &mut self.rotation {
+            Some(rotation) => rotation,
+            None => return Ok(()),
+        };
+        if !rotation.step(&mut **database, self.batch_size)? {
+            return Ok(());
+        }
+        rotation.target.flush()?;
+        let target_version = rotation.target_version;
+        if let Some(data_dir) = &self.data_dir {
+            data_dir.commit_rotation(target_version)?;
+        }
+        let rotation = self.rotation.take().unwrap();
+        // Closes the database of the previous key before removing its files.
+        drop(std::mem::replace(database, rotation.target));
+        let previous_version = std::mem::replace(&mut self.key_version, target_version);
+        if let Some(data_dir) = &self.data_dir {
+            data_dir.remove(previous_version);
+        }
+        info!("Rotated the storage key to version {}", target_version);
+        Ok(())
+    }
+}
+
+// Asks the storage thread every `interval` to continue the key rotation in
+// progress. Runs until the storage thread exits.
+pub(crate) fn schedule_rotation(sender: Sender<ProxyRequest>, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        if sender.send(ProxyRequest::RotateKey).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{anyhow, ensure, Result};
+use sgx_types::{
+    sgx_attributes_t, sgx_cpu_svn_t, sgx_isv_svn_t, sgx_key_128bit_t, sgx_key_id_t,
+    sgx_key_policy_t, sgx_key_request_t,
+};
+
+/// Derives the seal key identified by `key_id` from the enclave measurement
+/// and the CPU, so it is the same after restarts of the enclave on the
+/// platform, but never leaves it.
+pub(crate) fn derive_key(key_id: &[u8]) -> Result<sgx_key_128bit_t> {
+    let report = sgx_tse::rsgx_self_report();
+    get_key(
+        sgx_types::SGX_KEYPOLICY_MRENCLAVE,
+        key_id,
+        report.body.isv_svn,
+        report.body.cpu_svn,
+    )
+}
+
+/// Security versions of the enclave and the CPU, for `derive_signer_key`.
+pub(crate) fn security_versions() -> (sgx_isv_svn_t, sgx_cpu_svn_t) {
+    let report = sgx_tse::rsgx_self_report();
+    (report.body.isv_svn, report.body.cpu_svn)
+}
+
+/// Derives the seal key identified by `key_id` from the signer and the
+/// product id of the enclave (MRSIGNER) and the CPU, at the given security
+/// versions. Later versions of the storage enclave on the platform can
+/// therefore derive it too, but not enclaves of other signers or products.
+pub(crate) fn derive_signer_key(
+    key_id: &[u8],
+    isv_svn: sgx_isv_svn_t,
+    cpu_svn: sgx_cpu_svn_t,
+) -> Result<sgx_key_128bit_t> {
+    get_key(sgx_types::SGX_KEYPOLICY_MRSIGNER, key_id, isv_svn, cpu_svn)
+}
+
+fn get_key(
+    key_policy: sgx_key_policy_t,
+    key_id: &[u8],
+    isv_svn: sgx_isv_svn_t,
+    cpu_svn: sgx_cpu_svn_t,
+) -> Result<sgx_key_128bit_t> {
+    let mut id = sgx_key_id_t::default();
+    ensure!(key_id.len() <= id.id.len(), "key id too long");
+    id.id[..key_id.len()].copy_from_slice(key_id);
+    let key_request = sgx_key_request_t {
+        key_name: sgx_types::SGX_KEYSELECT_SEAL,
+        key_policy,
+        isv_svn,
+        cpu_svn,
+        attribute_mask: sgx_attributes_t {
+            flags: sgx_types::TSEAL_DEFAULT_FLAGSMASK,
+            xfrm: 0,
+        },
+        key_id: id,
+        misc_mask: sgx_types::TSEAL_DEFAULT_MISCMASK,
+        ..Default::default()
+    };
+    sgx_tse::rsgx_get_key(&key_request).map_err(|e| anyhow!("cannot get seal key: {}", e))
+}
@@ -15,32 +15,344 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::audit;
+use crate::backend::{LevelDb, StorageBackend};
+use crate::batch::StagedWrites;
+use crate::compaction::CompactionState;
 use crate::error::TeaclaveStorageError;
+use crate::expiration;
+use crate::namespace::{self, key_in, Namespace, Quota};
 use crate::proxy::ProxyRequest;
-use rusty_leveldb::DB;
+use crate::replication::{ChangeLog, ReplicatedChanges, ReplicationState};
+use crate::rotation::EncryptionState;
+use crate::snapshot;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::prelude::v1::*;
 use std::sync::mpsc::Receiver;
+use std::time::Duration;
+use teaclave_proto::teaclave_common::HealthCheck;
 use teaclave_proto::teaclave_storage_service::{
-    DeleteRequest, DeleteResponse, DequeueRequest, DequeueResponse, EnqueueRequest,
-    EnqueueResponse, GetRequest, GetResponse, PutRequest, PutResponse, TeaclaveStorage,
+    AppendAuditEventRequest, AppendAuditEventResponse, BatchOperation, BatchRequest, BatchResponse,
+    CompactRequest, CompactResponse, CreateSnapshotRequest, CreateSnapshotResponse, DeleteRequest,
+    DeleteResponse, DequeueRequest, DequeueResponse, EnqueueRequest, EnqueueResponse,
+    ExportAuditLogRequest, ExportAuditLogResponse, GetChangesRequest, GetChangesResponse,
+    GetKeyRotationRequest, GetKeyRotationResponse, GetNamespaceUsageRequest,
+    GetNamespaceUsageResponse, GetRequest, GetResponse, GetUsageRequest, GetUsageResponse,
+    HealthRequest, HealthResponse, PutRequest, PutResponse, RangeRequest, RangeResponse,
+    RotateKeyRequest, RotateKeyResponse, ScanPrefixRequest, ScanPrefixResponse,
+    SetNamespaceQuotaRequest, SetNamespaceQuotaResponse, StorageChange, TeaclaveStorage,
+    WipeNamespaceRequest, WipeNamespaceResponse,
 };
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::{bail, teaclave_service};
+use teaclave_service_enclave_utils::{bail, ensure, health, teaclave_service};
 use teaclave_types::TeaclaveServiceResponseResult;
 
+// Most entries returned by a page of ScanPrefix or Range.
+const MAX_SCAN_ENTRIES: u32 = 1000;
+
+// Most bytes of keys and values returned by GetChanges, a snapshot page or the
+// changes after a sequence. Bytes take up to four times as many in JSON, so
+// the responses stay well below the default max message length.
+const MAX_CHANGES_LEN: usize = 4 * 1024 * 1024;
+
+type Page = (Vec<(Vec<u8>, Vec<u8>)>, Option<Vec<u8>>);
+
+// Keys of the audit log, of the deadlines of keys and of namespaces, which
+// are only written by the storage service or within their namespace.
+fn is_reserved_key(key: &[u8]) -> bool {
+    audit::is_audit_key(key) || expiration::is_expiry_key(key) || namespace::is_namespace_key(key)
+}
+
+fn apply_to(
+    database: &mut dyn StorageBackend,
+    changes: Vec<StorageChange>,
+) -> TeaclaveServiceResponseResult<()> {
+    for change in changes {
+        match change {
+            StorageChange::Put { key, value } => database.put(&key, &value),
+            StorageChange::Delete { key } => database.delete(&key),
+        }
+        .map_err(TeaclaveStorageError::Backend)?;
+    }
+    Ok(())
+}
+
+fn open_namespace(namespace: Option<&str>) -> TeaclaveServiceResponseResult<Option<Namespace>> {
+    match namespace {
+        Some(namespace) => Ok(Some(Namespace::new(namespace)?)),
+        None => Ok(None),
+    }
+}
+
 #[teaclave_service(teaclave_storage_service, TeaclaveStorage, TeaclaveStorageError)]
 pub(crate) struct TeaclaveStorageService {
-    // Current LevelDB implementation is not concurrent, so we need to wrap the
-    // DB with RefCell. This service is running in a single thread, it's safe to
-    // use RefCell.
-    database: RefCell<DB>,
+    // The backends are not concurrent, so we need to wrap the database with
+    // RefCell. This service is running in a single thread, it's safe to use
+    // RefCell.
+    database: RefCell<Box<dyn StorageBackend>>,
     receiver: Receiver<ProxyRequest>,
+    // Writes are only accepted by the primary, which records them in a change
+    // log for replicas to follow.
+    replication: RefCell<ReplicationState>,
+    compaction: RefCell<CompactionState>,
+    // Key of the database files and its rotation.
+    encryption: RefCell<EncryptionState>,
+    // Seals the entries of the audit log.
+    audit_seal_key: Vec<u8>,
+    // Where snapshots are staged for the file agent.
+    snapshot_dir: PathBuf,
 }
 
 impl TeaclaveStorageService {
-    pub(crate) fn new(database: RefCell<DB>, receiver: Receiver<ProxyRequest>) -> Self {
-        Self { database, receiver }
+    pub(crate) fn new(
+        database: Box<dyn StorageBackend>,
+        receiver: Receiver<ProxyRequest>,
+        replication: ReplicationState,
+        compaction: CompactionState,
+        encryption: EncryptionState,
+        audit_seal_key: Vec<u8>,
+        snapshot_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            database: RefCell::new(database),
+            receiver,
+            replication: RefCell::new(replication),
+            compaction: RefCell::new(compaction),
+            encryption: RefCell::new(encryption),
+            audit_seal_key,
+            snapshot_dir: snapshot_dir.into(),
+        }
+    }
+
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&mut dyn StorageBackend, &mut ChangeLog) -> TeaclaveServiceResponseResult<T>,
+    ) -> TeaclaveServiceResponseResult<T> {
+        let mut replication = self.replication.borrow_mut();
+        let change_log = match &mut *replication {
+            ReplicationState::Primary(change_log) => change_log,
+            ReplicationState::Replica(_) => bail!(TeaclaveStorageError::NotPrimary),
+        };
+        let sequence = change_log.sequence();
+        let result = f(&mut **self.database.borrow_mut(), change_log);
+        // Also applied to keys already copied by a key rotation, including
+        // the changes of a write which failed halfway.
+        self.encryption
+            .borrow_mut()
+            .mirror(change_log.since(change_log.epoch(), sequence));
+        let result = result?;
+        self.compaction.borrow_mut().record_writes(1);
+        Ok(result)
+    }
+
+    // Stages the writes of `f`, and writes all of them at once if it succeeds.
+    // Writes to `namespaces` are accounted to their usage first.
+    fn write_staged<T>(
+        &self,
+        namespaces: &[&Namespace],
+        f: impl FnOnce(&mut dyn StorageBackend, &mut ChangeLog) -> TeaclaveServiceResponseResult<T>,
+    ) -> TeaclaveServiceResponseResult<T> {
+        self.write(|database, change_log| {
+            let mut staged = StagedWrites::new(database);
+            // Changes are recorded once written.
+            let mut staged_log = ChangeLog::new(0);
+            let result = f(&mut staged, &mut staged_log)?;
+            let mut changes = staged.into_changes();
+            for namespace in namespaces {
+                namespace.account(database, &mut changes)?;
+            }
+            database
+                .write_batch(&changes)
+                .map_err(TeaclaveStorageError::Backend)?;
+            for change in changes {
+                change_log.record(change);
+            }
+            Ok(result)
+        })
+    }
+
+    // Writes to namespaces are staged to check their quota.
+    fn write_in<T>(
+        &self,
+        namespace: Option<&Namespace>,
+        f: impl FnOnce(&mut dyn StorageBackend, &mut ChangeLog) -> TeaclaveServiceResponseResult<T>,
+    ) -> TeaclaveServiceResponseResult<T> {
+        match namespace {
+            Some(namespace) => self.write_staged(&[namespace], f),
+            None => self.write(f),
+        }
+    }
+
+    // A page of the snapshot of the whole database from `start`, of at most
+    // `MAX_CHANGES_LEN` bytes (or a single entry), and the key of the entry
+    // after it if any.
+    fn snapshot_page(
+        &self,
+        start: &[u8],
+    ) -> TeaclaveServiceResponseResult<(Vec<StorageChange>, Option<Vec<u8>>)> {
+        let mut database = self.database.borrow_mut();
+        let entries = database
+            .scan(start)
+            .map_err(TeaclaveStorageError::Backend)?;
+        let mut page = Vec::new();
+        let mut page_len = 0;
+        for (key, value) in entries {
+            let len = key.len() + value.len();
+            if !page.is_empty() && page_len + len > MAX_CHANGES_LEN {
+                return Ok((page, Some(key)));
+            }
+            page_len += len;
+            page.push(StorageChange::Put { key, value });
+        }
+        Ok((page, None))
+    }
+
+    // Applies changes fetched from the primary. Snapshots are staged page by
+    // page, and replace the database once the replica has caught up with the
+    // changes made while they were paged, so reads are served from the
+    // previous state until then.
+    fn apply_changes(&self, replicated: ReplicatedChanges) -> TeaclaveServiceResponseResult<()> {
+        let mut replication = self.replication.borrow_mut();
+        let replica = match &mut *replication {
+            ReplicationState::Replica(replica) => replica,
+            ReplicationState::Primary(_) => bail!(TeaclaveStorageError::NotReplica),
+        };
+        let changes = replicated.changes;
+        if changes.snapshot {
+            if replicated.starts_snapshot {
+                let staged =
+                    LevelDb::in_memory("teaclave_db").map_err(TeaclaveStorageError::Backend)?;
+                replica.staged = Some(Box::new(staged));
+            }
+            let staged = replica.staged.as_mut().ok_or_else(|| {
+                TeaclaveStorageError::Snapshot("no snapshot in progress".to_string())
+            })?;
+            return apply_to(&mut **staged, changes.changes);
+        }
+
+        let mut database = self.database.borrow_mut();
+        self.compaction
+            .borrow_mut()
+            .record_writes(changes.changes.len() as u64);
+        match replica.staged.take() {
+            Some(mut staged) => {
+                apply_to(&mut *staged, changes.changes)?;
+                *database = staged;
+            }
+            None => apply_to(&mut **database, changes.changes)?,
+        }
+        replica.synced(replicated.fetched_at);
+        Ok(())
+    }
+
+    // Replicas only answer reads once they have caught up with the primary,
+    // and returns how far behind they are.
+    fn check_staleness(
+        &self,
+        max_staleness: Option<Duration>,
+    ) -> TeaclaveServiceResponseResult<Duration> {
+        match &*self.replication.borrow() {
+            ReplicationState::Primary(_) => Ok(Duration::default()),
+            ReplicationState::Replica(replica) => {
+                let staleness = replica.staleness().ok_or(TeaclaveStorageError::Stale)?;
+                if let Some(max_staleness) = max_staleness {
+                    ensure!(staleness <= max_staleness, TeaclaveStorageError::Stale);
+                }
+                Ok(staleness)
+            }
+        }
+    }
+
+    // Reads the entries of `namespace` from `start` on while `in_range` holds
+    // for their keys, up to `limit` of them, and the key of the entry after
+    // them if any. Keys are those within the namespace. Reserved and expired
+    // entries are skipped, so pages may be shorter.
+    fn scan_page(
+        &self,
+        namespace: Option<&Namespace>,
+        start: &[u8],
+        limit: u32,
+        in_range: impl Fn(&[u8]) -> bool,
+    ) -> TeaclaveServiceResponseResult<Page> {
+        self.check_staleness(None)?;
+        let limit = match limit {
+            0 => MAX_SCAN_ENTRIES,
+            limit => std::cmp::min(limit, MAX_SCAN_ENTRIES),
+        } as usize;
+        let prefix = namespace.map_or(&[][..], |namespace| namespace.prefix());
+        let mut database = self.database.borrow_mut();
+        let mut entries: Vec<(Vec<u8>, Vec<u8>)> = database
+            .scan(&key_in(namespace, start))
+            .map_err(TeaclaveStorageError::Backend)?
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key[prefix.len()..].to_vec(), value))
+            .take_while(|(key, _)| in_range(key))
+            .filter(|(key, _)| !is_reserved_key(key))
+            .take(limit + 1)
+            .collect();
+        let next_key = if entries.len() > limit {
+            entries.pop().map(|(key, _)| key)
+        } else {
+            None
+        };
+        let now = expiration::now_secs();
+        let mut page = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            let database_key = key_in(namespace, &key);
+            if !expiration::is_expired(&mut **database, &database_key, now)? {
+                page.push((key, value));
+            }
+        }
+        Ok((page, next_key))
+    }
+
+    // Deletes the keys which have expired, on the primary.
+    fn expire(&self) {
+        if let ReplicationState::Replica(_) = &*self.replication.borrow() {
+            return;
+        }
+        let now = expiration::now_secs();
+        let result = self.write(|database, change_log| {
+            expiration::sweep(database, change_log, now, expiration::MAX_SWEEP_KEYS)
+        });
+        match result {
+            Ok(0) => (),
+            Ok(count) => info!("Deleted {} expired storage keys", count),
+            Err(e) => error!("Failed to delete expired storage keys: {:?}", e),
+        }
+    }
+
+    fn compact_step(&self) {
+        let mut compaction = self.compaction.borrow_mut();
+        let mut database = self.database.borrow_mut();
+        if compaction.check() {
+            if compaction.is_behind() {
+                warn!(
+                    "Storage compaction is behind: {} writes pending",
+                    compaction.pending_writes()
+                );
+            }
+            if compaction.is_due() {
+                if let Err(e) = compaction.start(&mut **database) {
+                    error!("Failed to start compacting storage: {:?}", e);
+                }
+            }
+        }
+        if let Err(e) = compaction.step(&mut **database) {
+            error!("Failed to compact storage: {:?}", e);
+        }
+        if let Err(e) = compaction.count_usage(&mut **database) {
+            error!("Failed to count storage usage: {:?}", e);
+        }
+    }
+
+    fn rotate_key_step(&self) {
+        let mut database = self.database.borrow_mut();
+        if let Err(e) = self.encryption.borrow_mut().step(&mut database) {
+            error!("Failed to rotate the storage key: {:?}", e);
+        }
     }
 }
 
@@ -49,7 +361,9 @@ impl TeaclaveStorageService {
 // queue-key-index: Vec<u8>; elements
 // Todo: what if there are errors when doing get_tail and get_head
 struct DBQueue<'a> {
-    database: &'a mut DB,
+    database: &'a mut dyn StorageBackend,
+    change_log: &'a mut ChangeLog,
+    namespace: Option<&'a Namespace>,
     key: &'a [u8],
 }
 
@@ -58,89 +372,123 @@ impl<'a> DBQueue<'a> {
         let mut head_key = b"queue-".to_vec();
         head_key.extend_from_slice(self.key);
         head_key.extend_from_slice(b"-tail");
-        head_key
+        key_in(self.namespace, &head_key)
     }
     fn get_head_key(&self) -> Vec<u8> {
         let mut head_key = b"queue-".to_vec();
         head_key.extend_from_slice(self.key);
         head_key.extend_from_slice(b"-head");
-        head_key
+        key_in(self.namespace, &head_key)
     }
     fn get_element_key(&self, index: u32) -> Vec<u8> {
         let mut element_key = b"queue-".to_vec();
         element_key.extend_from_slice(self.key);
         element_key.extend_from_slice(b"-");
         element_key.extend_from_slice(&index.to_le_bytes());
-        element_key
+        key_in(self.namespace, &element_key)
     }
 
-    fn get_head(&mut self) -> u32 {
+    fn get_head(&mut self) -> TeaclaveServiceResponseResult<u32> {
         let head_key = self.get_head_key();
-        self.read_u32(&head_key).unwrap_or(0)
+        Ok(self.read_u32(&head_key)?.unwrap_or(0))
     }
 
-    fn get_tail(&mut self) -> u32 {
+    fn get_tail(&mut self) -> TeaclaveServiceResponseResult<u32> {
         let tail_key = self.get_tail_key();
-        self.read_u32(&tail_key).unwrap_or(0)
+        Ok(self.read_u32(&tail_key)?.unwrap_or(0))
     }
 
-    fn read_u32(&mut self, key: &[u8]) -> Option<u32> {
-        let element_bytes: Vec<u8> = match self.database.get(key) {
+    // Errors of the backend are returned rather than read as an empty queue,
+    // which would be overwritten.
+    fn read_u32(&mut self, key: &[u8]) -> TeaclaveServiceResponseResult<Option<u32>> {
+        let element_bytes: Vec<u8> = match self
+            .database
+            .get(key)
+            .map_err(TeaclaveStorageError::Backend)?
+        {
             Some(bytes) => bytes,
-            None => return None,
+            None => return Ok(None),
         };
         if element_bytes.len() != 4 {
-            return None;
+            return Ok(None);
         }
         let mut bytes: [u8; 4] = [0; 4];
         bytes.copy_from_slice(&element_bytes);
-        Some(u32::from_le_bytes(bytes))
+        Ok(Some(u32::from_le_bytes(bytes)))
     }
 
-    pub fn open(database: &'a mut DB, key: &'a [u8]) -> Self {
-        DBQueue { database, key }
+    fn put(&mut self, key: &[u8], value: &[u8]) -> TeaclaveServiceResponseResult<()> {
+        self.database
+            .put(key, value)
+            .map_err(TeaclaveStorageError::Backend)?;
+        self.change_log.record(StorageChange::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> TeaclaveServiceResponseResult<()> {
+        self.database
+            .delete(key)
+            .map_err(TeaclaveStorageError::Backend)?;
+        self.change_log
+            .record(StorageChange::Delete { key: key.to_vec() });
+        Ok(())
+    }
+
+    pub fn open(
+        database: &'a mut dyn StorageBackend,
+        change_log: &'a mut ChangeLog,
+        namespace: Option<&'a Namespace>,
+        key: &'a [u8],
+    ) -> Self {
+        DBQueue {
+            database,
+            change_log,
+            namespace,
+            key,
+        }
     }
 
     pub fn enqueue(&mut self, value: &[u8]) -> TeaclaveServiceResponseResult<()> {
-        let mut tail_index = self.get_tail();
+        let mut tail_index = self.get_tail()?;
         // put element
-        self.database
-            .put(&self.get_element_key(tail_index), value)
-            .map_err(TeaclaveStorageError::LevelDb)?;
+        self.put(&self.get_element_key(tail_index), value)?;
         // tail + 1
         tail_index += 1;
-        self.database
-            .put(&self.get_tail_key(), &tail_index.to_le_bytes())
-            .map_err(TeaclaveStorageError::LevelDb)?;
+        self.put(&self.get_tail_key(), &tail_index.to_le_bytes())?;
         Ok(())
     }
 
     pub fn dequeue(&mut self) -> TeaclaveServiceResponseResult<Vec<u8>> {
-        let mut head_index = self.get_head();
-        let tail_index = self.get_tail();
+        let mut head_index = self.get_head()?;
+        let tail_index = self.get_tail()?;
         // check whether the queue is empty
         if head_index >= tail_index {
             Err(TeaclaveStorageError::None.into())
         } else {
             let element_key = self.get_element_key(head_index);
-            let result = match self.database.get(&element_key) {
+            let result = match self
+                .database
+                .get(&element_key)
+                .map_err(TeaclaveStorageError::Backend)?
+            {
                 Some(value) => value,
                 None => bail!(TeaclaveStorageError::None),
             };
             // update head
             head_index += 1;
-            self.database
-                .put(&self.get_head_key(), &head_index.to_le_bytes())
-                .map_err(TeaclaveStorageError::LevelDb)?;
+            self.put(&self.get_head_key(), &head_index.to_le_bytes())?;
             // delete element; it's ok to ignore the error
-            let _ = self.database.delete(&element_key);
+            let _ = self.delete(&element_key);
             Ok(result)
         }
     }
 
     #[allow(unused)]
-    pub fn len(&mut self) -> u32 {
-        self.get_tail() - self.get_head()
+    pub fn len(&mut self) -> TeaclaveServiceResponseResult<u32> {
+        Ok(self.get_tail()? - self.get_head()?)
     }
 }
 
@@ -157,13 +505,26 @@ impl TeaclaveStorageService {
                     break;
                 }
             };
-            let database_request = request.request;
-            let sender = request.sender;
-            let response = self.dispatch(database_request);
-
-            match sender.send(response) {
-                Ok(_) => (),
-                Err(e) => error!("mpsc send error: {}", e),
+            match request {
+                ProxyRequest::Service { sender, request } => {
+                    let response = self.dispatch(request);
+                    match sender.send(response) {
+                        Ok(_) => (),
+                        Err(e) => error!("mpsc send error: {}", e),
+                    }
+                }
+                ProxyRequest::Replicate { sender, changes } => {
+                    let result = self.apply_changes(changes);
+                    if let Err(e) = &result {
+                        error!("Failed to apply replicated changes: {:?}", e);
+                    }
+                    if let Err(e) = sender.send(result) {
+                        error!("mpsc send error: {}", e);
+                    }
+                }
+                ProxyRequest::Compact => self.compact_step(),
+                ProxyRequest::RotateKey => self.rotate_key_step(),
+                ProxyRequest::Expire => self.expire(),
             }
         }
     }
@@ -171,19 +532,43 @@ impl TeaclaveStorageService {
 impl TeaclaveStorage for TeaclaveStorageService {
     fn get(&self, request: Request<GetRequest>) -> TeaclaveServiceResponseResult<GetResponse> {
         let request = request.message;
-        match self.database.borrow_mut().get(&request.key) {
-            Some(value) => Ok(GetResponse { value }),
+        let staleness = self.check_staleness(request.max_staleness)?;
+        let namespace = open_namespace(request.namespace.as_deref())?;
+        let key = key_in(namespace.as_ref(), &request.key);
+        let mut database = self.database.borrow_mut();
+        let value = database.get(&key).map_err(TeaclaveStorageError::Backend)?;
+        // Expired keys may not have been deleted yet.
+        let now = expiration::now_secs();
+        let value = match value {
+            Some(_) if expiration::is_expired(&mut **database, &key, now)? => None,
+            value => value,
+        };
+        match value {
+            Some(value) => Ok(GetResponse::new(value).staleness(staleness)),
             None => Err(TeaclaveStorageError::None.into()),
         }
     }
 
     fn put(&self, request: Request<PutRequest>) -> TeaclaveServiceResponseResult<PutResponse> {
         let request = request.message;
-        self.database
-            .borrow_mut()
-            .put(&request.key, &request.value)
-            .map_err(TeaclaveStorageError::LevelDb)?;
-        Ok(PutResponse)
+        ensure!(
+            !is_reserved_key(&request.key),
+            TeaclaveStorageError::ReservedKey
+        );
+        let deadline = request.ttl.map(expiration::deadline_after);
+        let namespace = open_namespace(request.namespace.as_deref())?;
+        let key = key_in(namespace.as_ref(), &request.key);
+        self.write_in(namespace.as_ref(), |database, change_log| {
+            database
+                .put(&key, &request.value)
+                .map_err(TeaclaveStorageError::Backend)?;
+            expiration::set_deadline(database, change_log, &key, deadline)?;
+            change_log.record(StorageChange::Put {
+                key,
+                value: request.value,
+            });
+            Ok(PutResponse)
+        })
     }
 
     fn delete(
@@ -191,11 +576,20 @@ impl TeaclaveStorage for TeaclaveStorageService {
         request: Request<DeleteRequest>,
     ) -> TeaclaveServiceResponseResult<DeleteResponse> {
         let request = request.message;
-        self.database
-            .borrow_mut()
-            .delete(&request.key)
-            .map_err(TeaclaveStorageError::LevelDb)?;
-        Ok(DeleteResponse)
+        ensure!(
+            !is_reserved_key(&request.key),
+            TeaclaveStorageError::ReservedKey
+        );
+        let namespace = open_namespace(request.namespace.as_deref())?;
+        let key = key_in(namespace.as_ref(), &request.key);
+        self.write_in(namespace.as_ref(), |database, change_log| {
+            database
+                .delete(&key)
+                .map_err(TeaclaveStorageError::Backend)?;
+            expiration::set_deadline(database, change_log, &key, None)?;
+            change_log.record(StorageChange::Delete { key });
+            Ok(DeleteResponse)
+        })
     }
 
     fn enqueue(
@@ -203,9 +597,15 @@ impl TeaclaveStorage for TeaclaveStorageService {
         request: Request<EnqueueRequest>,
     ) -> TeaclaveServiceResponseResult<EnqueueResponse> {
         let request = request.message;
-        let mut db = self.database.borrow_mut();
-        let mut queue = DBQueue::open(&mut db, &request.key);
-        queue.enqueue(&request.value).map(|_| EnqueueResponse)
+        ensure!(
+            !is_reserved_key(&request.key),
+            TeaclaveStorageError::ReservedKey
+        );
+        let namespace = open_namespace(request.namespace.as_deref())?;
+        self.write_in(namespace.as_ref(), |database, change_log| {
+            let mut queue = DBQueue::open(database, change_log, namespace.as_ref(), &request.key);
+            queue.enqueue(&request.value).map(|_| EnqueueResponse)
+        })
     }
 
     fn dequeue(
@@ -213,9 +613,351 @@ impl TeaclaveStorage for TeaclaveStorageService {
         request: Request<DequeueRequest>,
     ) -> TeaclaveServiceResponseResult<DequeueResponse> {
         let request = request.message;
-        let mut db = self.database.borrow_mut();
-        let mut queue = DBQueue::open(&mut db, &request.key);
-        queue.dequeue().map(|value| DequeueResponse { value })
+        ensure!(
+            !is_reserved_key(&request.key),
+            TeaclaveStorageError::ReservedKey
+        );
+        let namespace = open_namespace(request.namespace.as_deref())?;
+        self.write_in(namespace.as_ref(), |database, change_log| {
+            let mut queue = DBQueue::open(database, change_log, namespace.as_ref(), &request.key);
+            queue.dequeue().map(|value| DequeueResponse { value })
+        })
+    }
+
+    fn batch(
+        &self,
+        request: Request<BatchRequest>,
+    ) -> TeaclaveServiceResponseResult<BatchResponse> {
+        let request = request.message;
+        for operation in &request.operations {
+            ensure!(
+                !is_reserved_key(operation.key()),
+                TeaclaveStorageError::ReservedKey
+            );
+        }
+        // Operations are in the namespace of the batch unless they have their
+        // own; each namespace is accounted once.
+        let mut names: Vec<&str> = request
+            .operations
+            .iter()
+            .filter_map(|operation| operation.namespace())
+            .chain(request.namespace.as_deref())
+            .collect();
+        names.sort();
+        names.dedup();
+        let namespaces = names
+            .into_iter()
+            .map(|name| Ok((name.to_string(), Namespace::new(name)?)))
+            .collect::<TeaclaveServiceResponseResult<HashMap<_, _>>>()?;
+        let batch_namespace = request.namespace;
+        let namespace_of = |namespace: Option<String>| {
+            namespace
+                .or_else(|| batch_namespace.clone())
+                .and_then(|name| namespaces.get(&name))
+        };
+        let accounted: Vec<&Namespace> = namespaces.values().collect();
+        self.write_staged(&accounted, |database, change_log| {
+            for operation in request.operations {
+                match operation {
+                    BatchOperation::Put {
+                        key,
+                        value,
+                        namespace,
+                    } => {
+                        let key = key_in(namespace_of(namespace), &key);
+                        database
+                            .put(&key, &value)
+                            .map_err(TeaclaveStorageError::Backend)?;
+                        expiration::set_deadline(database, change_log, &key, None)?;
+                    }
+                    BatchOperation::Delete { key, namespace } => {
+                        let key = key_in(namespace_of(namespace), &key);
+                        database
+                            .delete(&key)
+                            .map_err(TeaclaveStorageError::Backend)?;
+                        expiration::set_deadline(database, change_log, &key, None)?;
+                    }
+                    BatchOperation::Enqueue {
+                        key,
+                        value,
+                        namespace,
+                    } => {
+                        let namespace = namespace_of(namespace);
+                        DBQueue::open(database, change_log, namespace, &key).enqueue(&value)?;
+                    }
+                }
+            }
+            Ok(BatchResponse)
+        })
+    }
+
+    fn append_audit_event(
+        &self,
+        request: Request<AppendAuditEventRequest>,
+    ) -> TeaclaveServiceResponseResult<AppendAuditEventResponse> {
+        let event = request.message.event;
+        self.write(|database, change_log| {
+            audit::append(database, change_log, &self.audit_seal_key, event)
+                .map(|seq| AppendAuditEventResponse { seq })
+        })
+    }
+
+    fn export_audit_log(
+        &self,
+        request: Request<ExportAuditLogRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportAuditLogResponse> {
+        let request = request.message;
+        // Seals can only be verified with the key of the primary.
+        if let ReplicationState::Replica(_) = &*self.replication.borrow() {
+            bail!(TeaclaveStorageError::NotPrimary);
+        }
+        let limit = std::cmp::min(request.limit, audit::MAX_EXPORT_ENTRIES);
+        audit::export(
+            &mut **self.database.borrow_mut(),
+            &self.audit_seal_key,
+            request.start_seq,
+            limit,
+        )
+    }
+
+    fn get_changes(
+        &self,
+        request: Request<GetChangesRequest>,
+    ) -> TeaclaveServiceResponseResult<GetChangesResponse> {
+        let request = request.message;
+        let replication = self.replication.borrow();
+        let change_log = match &*replication {
+            ReplicationState::Primary(change_log) => change_log,
+            ReplicationState::Replica(_) => bail!(TeaclaveStorageError::NotPrimary),
+        };
+        let epoch = change_log.epoch();
+        match request.snapshot_start {
+            // Later pages of a snapshot keep the sequence at its start. A
+            // snapshot of another epoch starts over.
+            Some(start) if request.epoch == epoch => {
+                let (changes, next_key) = self.snapshot_page(&start)?;
+                return Ok(GetChangesResponse::snapshot(
+                    epoch,
+                    request.since,
+                    changes,
+                    next_key,
+                ));
+            }
+            Some(_) => (),
+            None => {
+                if let Some(mut changes) = change_log.since(request.epoch, request.since) {
+                    let mut len = 0;
+                    let count = changes
+                        .iter()
+                        .take_while(|change| {
+                            len += match change {
+                                StorageChange::Put { key, value } => key.len() + value.len(),
+                                StorageChange::Delete { key } => key.len(),
+                            };
+                            len <= MAX_CHANGES_LEN
+                        })
+                        .count();
+                    // At least one change, so that callers make progress.
+                    changes.truncate(std::cmp::max(count, 1));
+                    let sequence = request.since + changes.len() as u64;
+                    return Ok(GetChangesResponse::new(epoch, sequence, changes));
+                }
+            }
+        }
+        let (changes, next_key) = self.snapshot_page(b"")?;
+        Ok(GetChangesResponse::snapshot(
+            epoch,
+            change_log.sequence(),
+            changes,
+            next_key,
+        ))
+    }
+
+    fn scan_prefix(
+        &self,
+        request: Request<ScanPrefixRequest>,
+    ) -> TeaclaveServiceResponseResult<ScanPrefixResponse> {
+        let request = request.message;
+        let start = match request.start {
+            Some(start) if start > request.prefix => start,
+            _ => request.prefix.clone(),
+        };
+        let namespace = open_namespace(request.namespace.as_deref())?;
+        let (entries, next_key) =
+            self.scan_page(namespace.as_ref(), &start, request.limit, |key| {
+                key.starts_with(&request.prefix)
+            })?;
+        Ok(ScanPrefixResponse { entries, next_key })
+    }
+
+    fn range(
+        &self,
+        request: Request<RangeRequest>,
+    ) -> TeaclaveServiceResponseResult<RangeResponse> {
+        let request = request.message;
+        let end = request.end;
+        let namespace = open_namespace(request.namespace.as_deref())?;
+        let (entries, next_key) =
+            self.scan_page(namespace.as_ref(), &request.start, request.limit, |key| {
+                end.as_ref().map_or(true, |end| key < end.as_slice())
+            })?;
+        Ok(RangeResponse { entries, next_key })
+    }
+
+    fn set_namespace_quota(
+        &self,
+        request: Request<SetNamespaceQuotaRequest>,
+    ) -> TeaclaveServiceResponseResult<SetNamespaceQuotaResponse> {
+        let request = request.message;
+        let namespace = Namespace::new(&request.namespace)?;
+        let quota = Quota {
+            max_keys: request.max_keys,
+            max_bytes: request.max_bytes,
+        };
+        self.write(|database, change_log| {
+            namespace.set_quota(database, change_log, quota)?;
+            Ok(SetNamespaceQuotaResponse)
+        })
+    }
+
+    fn get_namespace_usage(
+        &self,
+        request: Request<GetNamespaceUsageRequest>,
+    ) -> TeaclaveServiceResponseResult<GetNamespaceUsageResponse> {
+        let request = request.message;
+        self.check_staleness(None)?;
+        let namespace = Namespace::new(&request.namespace)?;
+        let mut database = self.database.borrow_mut();
+        let usage = namespace.usage(&mut **database)?;
+        let quota = namespace.quota(&mut **database)?;
+        Ok(GetNamespaceUsageResponse {
+            key_count: usage.key_count,
+            byte_count: usage.byte_count,
+            max_keys: quota.max_keys,
+            max_bytes: quota.max_bytes,
+        })
+    }
+
+    // Wipes which failed halfway can be retried.
+    fn wipe_namespace(
+        &self,
+        request: Request<WipeNamespaceRequest>,
+    ) -> TeaclaveServiceResponseResult<WipeNamespaceResponse> {
+        let request = request.message;
+        let namespace = Namespace::new(&request.namespace)?;
+        let deleted_keys = self.write(|database, change_log| {
+            let keys = namespace.wipe(database, change_log)?;
+            for key in &keys {
+                expiration::set_deadline(database, change_log, key, None)?;
+            }
+            Ok(keys.len() as u64)
+        })?;
+        info!(
+            "Wiped {} keys of storage namespace {}",
+            deleted_keys, request.namespace
+        );
+        Ok(WipeNamespaceResponse { deleted_keys })
+    }
+
+    fn get_usage(
+        &self,
+        _request: Request<GetUsageRequest>,
+    ) -> TeaclaveServiceResponseResult<GetUsageResponse> {
+        let compaction = self.compaction.borrow();
+        Ok(GetUsageResponse {
+            usages: compaction.usages().to_vec(),
+            usage_counted_at: compaction.usage_counted_at(),
+            pending_writes: compaction.pending_writes(),
+            last_compaction: compaction.last_compaction(),
+            compaction_behind: compaction.is_behind(),
+            compactions: compaction.compactions(),
+            compaction: compaction.progress(),
+        })
+    }
+
+    fn compact(
+        &self,
+        _request: Request<CompactRequest>,
+    ) -> TeaclaveServiceResponseResult<CompactResponse> {
+        let started = self
+            .compaction
+            .borrow_mut()
+            .start(&mut **self.database.borrow_mut())
+            .map_err(TeaclaveStorageError::Backend)?;
+        if started {
+            info!("Started compacting storage");
+        }
+        Ok(CompactResponse { started })
+    }
+
+    fn rotate_key(
+        &self,
+        _request: Request<RotateKeyRequest>,
+    ) -> TeaclaveServiceResponseResult<RotateKeyResponse> {
+        if let ReplicationState::Replica(_) = &*self.replication.borrow() {
+            bail!(TeaclaveStorageError::NotPrimary);
+        }
+        let target_version = self
+            .encryption
+            .borrow_mut()
+            .begin_rotation(&mut **self.database.borrow_mut())
+            .map_err(|e| TeaclaveStorageError::KeyRotation(e.to_string()))?;
+        info!(
+            "Started rotating the storage key to version {}",
+            target_version
+        );
+        Ok(RotateKeyResponse { target_version })
+    }
+
+    fn get_key_rotation(
+        &self,
+        _request: Request<GetKeyRotationRequest>,
+    ) -> TeaclaveServiceResponseResult<GetKeyRotationResponse> {
+        let encryption = self.encryption.borrow();
+        let mut database = self.database.borrow_mut();
+        Ok(GetKeyRotationResponse {
+            key_version: encryption.key_version(&mut **database),
+            rotation: encryption.progress(&mut **database),
+        })
+    }
+
+    // The storage thread is blocked until the snapshot is uploaded, so that
+    // no write is missed or half applied.
+    fn create_snapshot(
+        &self,
+        request: Request<CreateSnapshotRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateSnapshotResponse> {
+        let request = request.message;
+        let snapshot = snapshot::create(&mut **self.database.borrow_mut())
+            .map_err(|e| TeaclaveStorageError::Snapshot(e.to_string()))?;
+        snapshot::export(&snapshot, &request.url, &self.snapshot_dir)
+            .map_err(|e| TeaclaveStorageError::Snapshot(e.to_string()))?;
+        info!("Exported a storage snapshot of {} keys", snapshot.key_count);
+        Ok(CreateSnapshotResponse {
+            key_count: snapshot.key_count,
+            byte_count: snapshot.data.len() as u64,
+        })
+    }
+
+    fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> TeaclaveServiceResponseResult<HealthResponse> {
+        let replication = match &*self.replication.borrow() {
+            ReplicationState::Primary(_) => HealthCheck::healthy("replication", "primary"),
+            ReplicationState::Replica(replica) => match replica.staleness() {
+                Some(staleness) => HealthCheck::healthy(
+                    "replication",
+                    format!("synced {}ms ago", staleness.as_millis()),
+                ),
+                None => HealthCheck::unhealthy("replication", "not synced"),
+            },
+        };
+        let pending_writes = self.compaction.borrow().pending_writes();
+        Ok(
+            HealthResponse::new(vec![health::attestation_check(), replication])
+                .pending_compaction_writes(pending_writes),
+        )
     }
 }
 
@@ -223,34 +965,56 @@ impl TeaclaveStorage for TeaclaveStorageService {
 mod test_mode {
     use super::*;
     pub(crate) fn repalce_with_mock_database(service: &mut TeaclaveStorageService) {
-        let opt = rusty_leveldb::in_memory();
-        let mut database = DB::open("mock_db", opt).unwrap();
+        let mut database = LevelDb::in_memory("mock_db").unwrap();
         database.put(b"test_get_key", b"test_get_value").unwrap();
         database
             .put(b"test_delete_key", b"test_delete_value")
             .unwrap();
-        service.database.replace(database);
+        service.database.replace(Box::new(database));
     }
 }
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
+    use crate::rotation::KeyRotation;
     use std::sync::mpsc::channel;
+    use std::untrusted::fs;
+    use teaclave_proto::teaclave_storage_service::ChangeCursor;
     use teaclave_rpc::IntoRequest;
+    use teaclave_types::{AuditEvent, AuditEventKind};
+    use url::Url;
 
     fn get_mock_service() -> TeaclaveStorageService {
         let (_sender, receiver) = channel();
-        let opt = rusty_leveldb::in_memory();
-        let mut database = DB::open("mock_db", opt).unwrap();
+        let mut database = LevelDb::in_memory("mock_db").unwrap();
         database.put(b"test_get_key", b"test_get_value").unwrap();
         database
             .put(b"test_delete_key", b"test_delete_value")
             .unwrap();
-        TeaclaveStorageService {
-            database: RefCell::new(database),
+        TeaclaveStorageService::new(
+            Box::new(database),
             receiver,
-        }
+            ReplicationState::primary(2).unwrap(),
+            CompactionState::new(2, 4, 1000, Duration::from_secs(0)),
+            EncryptionState::in_memory(),
+            vec![1u8; 16],
+            "/tmp/teaclave_storage_snapshots",
+        )
+    }
+
+    fn get_mock_replica() -> TeaclaveStorageService {
+        let (_sender, receiver) = channel();
+        let database = LevelDb::in_memory("mock_db").unwrap();
+        TeaclaveStorageService::new(
+            Box::new(database),
+            receiver,
+            ReplicationState::replica(),
+            CompactionState::new(2, 4, 1000, Duration::from_secs(0)),
+            EncryptionState::in_memory(),
+            vec![1u8; 16],
+            "/tmp/teaclave_storage_snapshots",
+        )
     }
 
     pub fn test_get_key() {
@@ -296,4 +1060,529 @@ pub mod tests {
         let request = DequeueRequest::new("test_dequeue_key").into_request();
         assert_eq!(service.dequeue(request).unwrap().value, b"2");
     }
+
+    pub fn test_get_changes() {
+        let service = get_mock_service();
+        let request = PutRequest::new("test_put_key", "test_put_value").into_request();
+        assert!(service.put(request).is_ok());
+        let request = DeleteRequest::new("test_delete_key").into_request();
+        assert!(service.delete(request).is_ok());
+
+        // Callers without an epoch are sent a snapshot first.
+        let request = GetChangesRequest::new(0, 1).into_request();
+        let response = service.get_changes(request).unwrap();
+        assert!(response.snapshot);
+        let epoch = response.epoch;
+        assert_ne!(epoch, 0);
+
+        let request = GetChangesRequest::new(epoch, 1).into_request();
+        let response = service.get_changes(request).unwrap();
+        assert_eq!(response.sequence, 2);
+        assert!(!response.snapshot);
+        assert_eq!(
+            response.changes,
+            vec![StorageChange::Delete {
+                key: b"test_delete_key".to_vec()
+            }]
+        );
+
+        // The change log only keeps two changes, so changes since 0 are
+        // truncated after a third write and a snapshot is returned.
+        let request = EnqueueRequest::new("test_enqueue_key", "1").into_request();
+        assert!(service.enqueue(request).is_ok());
+        let request = GetChangesRequest::new(epoch, 0).into_request();
+        let response = service.get_changes(request).unwrap();
+        assert_eq!(response.sequence, 4);
+        assert!(response.snapshot);
+        assert!(response.next_key.is_none());
+        assert!(response.changes.contains(&StorageChange::Put {
+            key: b"test_put_key".to_vec(),
+            value: b"test_put_value".to_vec(),
+        }));
+    }
+
+    pub fn test_replica() {
+        let primary = get_mock_service();
+        let replica = get_mock_replica();
+
+        let request = PutRequest::new("test_put_key", "test_put_value").into_request();
+        assert!(replica.put(request).is_err());
+        let request = GetRequest::new("test_get_key").into_request();
+        assert!(replica.get(request).is_err());
+
+        // The snapshot is paged, and replaces the database of the replica
+        // once it has caught up with the changes made while paging.
+        let value = vec![1u8; MAX_CHANGES_LEN - 1024];
+        for key in &["test_large_key_1", "test_large_key_2"] {
+            let request = PutRequest::new(*key, value.clone()).into_request();
+            assert!(primary.put(request).is_ok());
+        }
+        let mut cursor = ChangeCursor::default();
+        let mut pages = 0;
+        loop {
+            let changes = primary
+                .get_changes(cursor.request().into_request())
+                .unwrap();
+            let mut next = cursor.clone();
+            next.advance(&changes);
+            let snapshot = changes.snapshot;
+            let replicated = ReplicatedChanges {
+                fetched_at: std::time::SystemTime::UNIX_EPOCH,
+                starts_snapshot: cursor.starts_snapshot(&changes),
+                changes,
+            };
+            assert!(replica.apply_changes(replicated).is_ok());
+            cursor = next;
+            if !snapshot {
+                break;
+            }
+            pages += 1;
+            let request = GetRequest::new("test_get_key").into_request();
+            assert!(replica.get(request).is_err());
+        }
+        assert_eq!(pages, 2);
+
+        let request = GetRequest::new("test_large_key_2").into_request();
+        assert_eq!(replica.get(request).unwrap().value, value);
+        let request = GetRequest::new("test_get_key").into_request();
+        let response = replica.get(request).unwrap();
+        assert_eq!(response.value, b"test_get_value");
+        assert!(response.staleness > Duration::from_secs(1));
+        let request = GetRequest::new("test_get_key")
+            .max_staleness(Duration::from_secs(1))
+            .into_request();
+        assert!(replica.get(request).is_err());
+    }
+
+    pub fn test_get_usage() {
+        let service = get_mock_service();
+        let request = EnqueueRequest::new("test_enqueue_key", "1").into_request();
+        assert!(service.enqueue(request).is_ok());
+
+        let response = service
+            .get_usage(GetUsageRequest::new().into_request())
+            .unwrap();
+        assert!(response.usages.is_empty());
+        assert!(response.usage_counted_at.is_none());
+
+        // The usage is counted alongside the compaction.
+        service.compact_step();
+        let response = service
+            .get_usage(GetUsageRequest::new().into_request())
+            .unwrap();
+        assert!(response.usage_counted_at.is_some());
+        assert_eq!(response.pending_writes, 1);
+        assert!(!response.compaction_behind);
+        let queue_usage = response
+            .usages
+            .iter()
+            .find(|usage| usage.prefix == b"queue")
+            .unwrap();
+        // One element and the tail index of the queue
+        assert_eq!(queue_usage.key_count, 2);
+        let test_usage = response
+            .usages
+            .iter()
+            .find(|usage| usage.prefix == b"test_get_key")
+            .unwrap();
+        assert_eq!(test_usage.key_count, 1);
+        assert_eq!(test_usage.byte_count, 26);
+    }
+
+    pub fn test_compaction() {
+        let service = get_mock_service();
+        for _ in 0..4 {
+            let request = PutRequest::new("test_put_key", "test_put_value").into_request();
+            assert!(service.put(request).is_ok());
+        }
+        let response = service
+            .get_usage(GetUsageRequest::new().into_request())
+            .unwrap();
+        assert_eq!(response.pending_writes, 4);
+        assert!(response.compaction_behind);
+
+        service.compact_step();
+        let response = service
+            .get_usage(GetUsageRequest::new().into_request())
+            .unwrap();
+        assert_eq!(response.pending_writes, 0);
+        assert!(!response.compaction_behind);
+        assert!(response.last_compaction.is_some());
+        assert_eq!(response.compactions, 1);
+        assert!(response.compaction.is_none());
+        let request = GetRequest::new("test_put_key").into_request();
+        assert_eq!(service.get(request).unwrap().value, b"test_put_value");
+    }
+
+    pub fn test_compact() {
+        let service = get_mock_service();
+        service.compact_step();
+        let response = service
+            .compact(CompactRequest::new().into_request())
+            .unwrap();
+        assert!(response.started);
+        let response = service
+            .compact(CompactRequest::new().into_request())
+            .unwrap();
+        assert!(!response.started);
+        let response = service
+            .get_usage(GetUsageRequest::new().into_request())
+            .unwrap();
+        let progress = response.compaction.unwrap();
+        assert_eq!(progress.compacted_keys, 0);
+        assert_eq!(progress.total_keys, 2);
+
+        service.compact_step();
+        let response = service
+            .get_usage(GetUsageRequest::new().into_request())
+            .unwrap();
+        assert!(response.compaction.is_none());
+        assert_eq!(response.compactions, 1);
+    }
+    pub fn test_health() {
+        let service = get_mock_service();
+        let response = service.health(HealthRequest::new().into_request()).unwrap();
+        let replication = response
+            .checks
+            .iter()
+            .find(|check| check.name == "replication")
+            .unwrap();
+        assert!(replication.healthy);
+        assert_eq!(response.pending_compaction_writes, 0);
+
+        // A replica is not ready until it synced with the primary.
+        let replica = get_mock_replica();
+        let response = replica.health(HealthRequest::new().into_request()).unwrap();
+        assert!(!response.is_ready());
+    }
+
+    pub fn test_audit_log() {
+        let service = get_mock_service();
+        for user_id in &["user_0", "user_1", "user_2"] {
+            let event = AuditEvent::new(AuditEventKind::Login, "test", *user_id, "password");
+            let request = AppendAuditEventRequest::new(event).into_request();
+            assert!(service.append_audit_event(request).is_ok());
+        }
+
+        let request = ExportAuditLogRequest::new(0, 10).into_request();
+        let response = service.export_audit_log(request).unwrap();
+        assert_eq!(response.log_len, 3);
+        assert_eq!(response.entries.len(), 3);
+        assert_eq!(response.broken_seq, None);
+        let request = ExportAuditLogRequest::new(1, 1).into_request();
+        let response = service.export_audit_log(request).unwrap();
+        assert_eq!(response.entries[0].event.user_id, "user_1");
+        assert_eq!(response.broken_seq, None);
+
+        // The log cannot be written with other requests.
+        let request = PutRequest::new("audit-head", "").into_request();
+        assert!(service.put(request).is_err());
+        let request = DeleteRequest::new("audit-head").into_request();
+        assert!(service.delete(request).is_err());
+
+        // A modified entry breaks the chain from there.
+        let mut entry = response.entries[0].clone();
+        entry.event.user_id = "mallory".to_string();
+        let mut key = b"audit-entry-".to_vec();
+        key.extend_from_slice(&1u64.to_be_bytes());
+        service
+            .database
+            .borrow_mut()
+            .put(&key, &entry.to_vec().unwrap())
+            .unwrap();
+        let request = ExportAuditLogRequest::new(0, 10).into_request();
+        let response = service.export_audit_log(request).unwrap();
+        assert_eq!(response.broken_seq, Some(1));
+    }
+
+    pub fn test_key_rotation() {
+        let service = get_mock_service();
+        // The mock database is kept in memory.
+        let request = RotateKeyRequest::new().into_request();
+        assert!(service.rotate_key(request).is_err());
+
+        let request = PutRequest::new("test_a_key", "test_a_value").into_request();
+        assert!(service.put(request).is_ok());
+        let target = LevelDb::in_memory("mock_target_db").unwrap();
+        let rotation =
+            KeyRotation::resume(1, Box::new(target), &mut **service.database.borrow_mut()).unwrap();
+        service
+            .encryption
+            .replace(EncryptionState::new(None, 0, Some(rotation), 2));
+
+        // Copies test_a_key and test_delete_key. Writes to the copied keys are
+        // applied to both databases, and the other keys are copied later.
+        service.rotate_key_step();
+        let request = DeleteRequest::new("test_delete_key").into_request();
+        assert!(service.delete(request).is_ok());
+        let request = PutRequest::new("test_z_key", "test_z_value").into_request();
+        assert!(service.put(request).is_ok());
+        let response = service
+            .get_key_rotation(GetKeyRotationRequest::new().into_request())
+            .unwrap();
+        assert_eq!(response.key_version, 0);
+        let progress = response.rotation.unwrap();
+        assert_eq!(progress.target_version, 1);
+        assert_eq!(progress.copied_keys, 2);
+        assert_eq!(progress.total_keys, 3);
+
+        // Copies the rest and switches to the new database.
+        service.rotate_key_step();
+        let response = service
+            .get_key_rotation(GetKeyRotationRequest::new().into_request())
+            .unwrap();
+        assert_eq!(response.key_version, 1);
+        assert!(response.rotation.is_none());
+        let request = GetRequest::new("test_delete_key").into_request();
+        assert!(service.get(request).is_err());
+        for (key, value) in &[
+            ("test_a_key", "test_a_value"),
+            ("test_get_key", "test_get_value"),
+            ("test_z_key", "test_z_value"),
+        ] {
+            let request = GetRequest::new(*key).into_request();
+            assert_eq!(service.get(request).unwrap().value, value.as_bytes());
+        }
+    }
+
+    pub fn test_create_snapshot() {
+        let service = get_mock_service();
+        let path = "/tmp/teaclave_storage_snapshot_test";
+        let _ = fs::remove_file(path);
+        let url = Url::parse(&format!("file://{}", path)).unwrap();
+        let request = CreateSnapshotRequest::new(url).into_request();
+        let response = service.create_snapshot(request).unwrap();
+        assert_eq!(response.key_count, 2);
+
+        let data = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(data.len() as u64, response.byte_count);
+        let entries = snapshot::open(&data).unwrap();
+        assert!(entries.contains(&(b"test_get_key".to_vec(), b"test_get_value".to_vec())));
+
+        // Snapshots are only moved by the file agent.
+        let url = Url::parse("fusion:///TEACLAVE_FUSION_BASE/snapshot").unwrap();
+        let request = CreateSnapshotRequest::new(url).into_request();
+        assert!(service.create_snapshot(request).is_err());
+    }
+
+    pub fn test_put_ttl() {
+        let service = get_mock_service();
+        let request = PutRequest::new("test_ttl_key", "test_ttl_value")
+            .ttl(Duration::from_secs(3600))
+            .into_request();
+        assert!(service.put(request).is_ok());
+        let request = GetRequest::new("test_ttl_key").into_request();
+        assert!(service.get(request).is_ok());
+        let request = PutRequest::new("expiry-key-test_ttl_key", "").into_request();
+        assert!(service.put(request).is_err());
+
+        // Move the deadline to the past, as if the TTL had passed.
+        service
+            .write(|database, change_log| {
+                expiration::set_deadline(database, change_log, b"test_ttl_key", Some(1))
+            })
+            .unwrap();
+        let request = GetRequest::new("test_ttl_key").into_request();
+        assert!(service.get(request).is_err());
+        let request = ScanPrefixRequest::new("test_ttl_").into_request();
+        assert!(service.scan_prefix(request).unwrap().entries.is_empty());
+        service.expire();
+        let mut database = service.database.borrow_mut();
+        assert_eq!(database.get(b"test_ttl_key").unwrap(), None);
+        let deadline = expiration::deadline(&mut **database, b"test_ttl_key");
+        assert_eq!(deadline.unwrap(), None);
+
+        // Putting a key without a TTL clears its deadline.
+        let mut change_log = ChangeLog::new(16);
+        let key = b"test_get_key";
+        expiration::set_deadline(&mut **database, &mut change_log, key, Some(1)).unwrap();
+        drop(database);
+        let request = PutRequest::new("test_get_key", "test_get_value").into_request();
+        assert!(service.put(request).is_ok());
+        let request = GetRequest::new("test_get_key").into_request();
+        assert!(service.get(request).is_ok());
+    }
+
+    pub fn test_batch() {
+        let service = get_mock_service();
+        let request = BatchRequest::new()
+            .put("test_batch_key", "test_batch_value")
+            .delete("test_delete_key")
+            .enqueue("test_batch_queue", "1")
+            .enqueue("test_batch_queue", "2")
+            .into_request();
+        assert!(service.batch(request).is_ok());
+        // The put, the delete, the two elements and the tail of the queue.
+        let response = service
+            .get_changes(GetChangesRequest::new(0, 0).into_request())
+            .unwrap();
+        assert_eq!(response.sequence, 5);
+        let request = GetRequest::new("test_batch_key").into_request();
+        assert_eq!(service.get(request).unwrap().value, b"test_batch_value");
+        let request = GetRequest::new("test_delete_key").into_request();
+        assert!(service.get(request).is_err());
+        for value in &[b"1", b"2"] {
+            let request = DequeueRequest::new("test_batch_queue").into_request();
+            assert_eq!(&service.dequeue(request).unwrap().value, value);
+        }
+
+        // Batches writing reserved keys are rejected as a whole.
+        let request = BatchRequest::new()
+            .put("test_batch_key", "test_batch_value_2")
+            .put("expiry-key-test_batch_key", "")
+            .into_request();
+        assert!(service.batch(request).is_err());
+        let request = GetRequest::new("test_batch_key").into_request();
+        assert_eq!(service.get(request).unwrap().value, b"test_batch_value");
+    }
+
+    pub fn test_namespace() {
+        let service = get_mock_service();
+        let request = PutRequest::new("test_ns_key", "tenant_value")
+            .namespace("tenant")
+            .into_request();
+        assert!(service.put(request).is_ok());
+        let request = GetRequest::new("test_ns_key").into_request();
+        assert!(service.get(request).is_err());
+        let request = GetRequest::new("test_get_key")
+            .namespace("tenant")
+            .into_request();
+        assert!(service.get(request).is_err());
+        let request = GetRequest::new("test_ns_key")
+            .namespace("tenant")
+            .into_request();
+        assert_eq!(service.get(request).unwrap().value, b"tenant_value");
+        let request = GetRequest::new("ns-tenant/test_ns_key").into_request();
+        assert!(service.get(request).is_err());
+        let request = PutRequest::new("test_ns_key", "")
+            .namespace("a/b")
+            .into_request();
+        assert!(service.put(request).is_err());
+
+        let request = SetNamespaceQuotaRequest::new("tenant")
+            .max_keys(2)
+            .into_request();
+        assert!(service.set_namespace_quota(request).is_ok());
+        let request = EnqueueRequest::new("test_ns_queue", "1")
+            .namespace("tenant")
+            .into_request();
+        assert!(service.enqueue(request).is_err());
+        let request = BatchRequest::new()
+            .put("test_ns_key", "tenant_value_2")
+            .put("test_ns_key_2", "value")
+            .namespace("tenant")
+            .into_request();
+        assert!(service.batch(request).is_ok());
+        let request = GetNamespaceUsageRequest::new("tenant").into_request();
+        let response = service.get_namespace_usage(request).unwrap();
+        assert_eq!(response.key_count, 2);
+        assert_eq!(response.byte_count, 43);
+        assert_eq!(response.max_keys, Some(2));
+        assert_eq!(response.max_bytes, None);
+
+        let request = ScanPrefixRequest::new("test_ns_")
+            .namespace("tenant")
+            .into_request();
+        let response = service.scan_prefix(request).unwrap();
+        assert_eq!(
+            keys(&response.entries),
+            vec![&b"test_ns_key"[..], &b"test_ns_key_2"[..]]
+        );
+        let request = RangeRequest::new("").into_request();
+        let response = service.range(request).unwrap();
+        assert_eq!(
+            keys(&response.entries),
+            vec![&b"test_delete_key"[..], &b"test_get_key"[..]]
+        );
+
+        let request = WipeNamespaceRequest::new("tenant").into_request();
+        assert_eq!(service.wipe_namespace(request).unwrap().deleted_keys, 2);
+        let request = GetNamespaceUsageRequest::new("tenant").into_request();
+        let response = service.get_namespace_usage(request).unwrap();
+        assert_eq!(response.key_count, 0);
+        assert_eq!(response.max_keys, None);
+        let request = GetRequest::new("test_get_key").into_request();
+        assert!(service.get(request).is_ok());
+
+        // Operations of a batch may be in other namespaces, each accounted.
+        let request = BatchRequest::new()
+            .put("test_ns_locator", "tenant")
+            .put_in("tenant", "test_ns_key", "value")
+            .delete_in("other", "test_ns_key")
+            .into_request();
+        assert!(service.batch(request).is_ok());
+        let request = GetRequest::new("test_ns_key")
+            .namespace("tenant")
+            .into_request();
+        assert_eq!(service.get(request).unwrap().value, b"value");
+        let request = GetRequest::new("test_ns_locator").into_request();
+        assert_eq!(service.get(request).unwrap().value, b"tenant");
+        let request = GetNamespaceUsageRequest::new("tenant").into_request();
+        assert_eq!(service.get_namespace_usage(request).unwrap().key_count, 1);
+        let request = BatchRequest::new()
+            .put_in("a/b", "test_ns_key", "value")
+            .into_request();
+        assert!(service.batch(request).is_err());
+    }
+
+    fn keys(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<&[u8]> {
+        entries.iter().map(|(key, _)| key.as_slice()).collect()
+    }
+
+    pub fn test_scan_prefix() {
+        let service = get_mock_service();
+        for key in &["task-3", "task-1", "task-2", "tasks", "user-1"] {
+            let request = PutRequest::new(*key, "value").into_request();
+            assert!(service.put(request).is_ok());
+        }
+        let event = AuditEvent::new(AuditEventKind::Login, "test", "user-1", "password");
+        let request = AppendAuditEventRequest::new(event).into_request();
+        assert!(service.append_audit_event(request).is_ok());
+
+        let request = ScanPrefixRequest::new("task-").limit(2).into_request();
+        let response = service.scan_prefix(request).unwrap();
+        assert_eq!(keys(&response.entries), vec![b"task-1", b"task-2"]);
+        assert_eq!(response.entries[0].1, b"value");
+        assert_eq!(response.next_key, Some(b"task-3".to_vec()));
+
+        let request = ScanPrefixRequest::new("task-")
+            .start(response.next_key.unwrap())
+            .limit(2)
+            .into_request();
+        let response = service.scan_prefix(request).unwrap();
+        assert_eq!(keys(&response.entries), vec![b"task-3"]);
+        assert_eq!(response.next_key, None);
+
+        let request = ScanPrefixRequest::new("audit-").into_request();
+        let response = service.scan_prefix(request).unwrap();
+        assert!(response.entries.is_empty());
+
+        let replica = get_mock_replica();
+        let request = ScanPrefixRequest::new("task-").into_request();
+        assert!(replica.scan_prefix(request).is_err());
+    }
+
+    pub fn test_range() {
+        let service = get_mock_service();
+        for key in &["b", "a", "d", "c"] {
+            let request = PutRequest::new(*key, "value").into_request();
+            assert!(service.put(request).is_ok());
+        }
+
+        let request = RangeRequest::new("b").end("d").into_request();
+        let response = service.range(request).unwrap();
+        assert_eq!(keys(&response.entries), vec![b"b", b"c"]);
+        assert_eq!(response.next_key, None);
+
+        let request = RangeRequest::new("").limit(1).into_request();
+        let response = service.range(request).unwrap();
+        assert_eq!(keys(&response.entries), vec![b"a"]);
+        assert_eq!(response.next_key, Some(b"b".to_vec()));
+
+        let request = RangeRequest::new("d").into_request();
+        let response = service.range(request).unwrap();
+        assert_eq!(keys(&response.entries)[0], b"d");
+        assert_eq!(response.entries.len(), 3);
+    }
 }
@@ -0,0 +1,241 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Snapshots of the database, for disaster recovery. A snapshot holds all the
+// entries of the database, read by the storage thread between two requests,
+// encrypted with AES-128-GCM under a new seal key of the signer of the storage
+// enclave (see `sealing::derive_signer_key`). Unlike the sealed database
+// files, it can therefore be restored by later versions of the enclave, but
+// only by storage enclaves: the snapshot to restore is named by the untrusted
+// runtime config, and one the enclave cannot decrypt is rejected, so the host
+// cannot make the primary start from records of its own. Snapshots are staged
+// in a directory outside of the enclave and moved by the file agent of the
+// storage app, like the files of tasks (e.g., to `file://` paths or presigned
+// S3 URLs).
+//
+// A snapshot is a header, i.e., the magic below, the security versions (the
+// ISV SVN as u16, big endian, and the CPU SVN) and the id of the seal key,
+// followed by the entries encrypted with the header as additional data, each
+// a key and a value prefixed with their lengths (u32, big endian).
+
+use crate::backend::StorageBackend;
+use crate::ocall;
+use crate::sealing;
+use anyhow::{anyhow, bail, ensure, Result};
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use sgx_types::sgx_cpu_svn_t;
+use std::format;
+#[cfg(not(feature = "mesalock_sgx"))]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::prelude::v1::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::fs;
+#[cfg(feature = "mesalock_sgx")]
+use std::untrusted::time::SystemTimeEx;
+use teaclave_config::StorageSnapshotConfig;
+use teaclave_types::{FileAgentRequest, HandleFileCommand, HandleFileInfo};
+use url::Url;
+
+const MAGIC: &[u8] = b"TEACLAVE-STORAGE-SNAPSHOT-2";
+const SVN_LEN: usize = 2;
+const CPU_SVN_LEN: usize = 16;
+const KEY_ID_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + SVN_LEN + CPU_SVN_LEN + KEY_ID_LEN;
+
+pub(crate) struct Snapshot {
+    pub(crate) data: Vec<u8>,
+    pub(crate) key_count: u64,
+}
+
+// The seal key of a snapshot, derived at the security versions of the
+// enclave and the CPU in its header.
+fn cipher_key(header: &[u8]) -> Result<LessSafeKey> {
+    ensure!(
+        header.len() == HEADER_LEN && header.starts_with(MAGIC),
+        "not a storage snapshot"
+    );
+    let mut svn = [0u8; SVN_LEN];
+    svn.copy_from_slice(&header[MAGIC.len()..MAGIC.len() + SVN_LEN]);
+    let mut cpu_svn = sgx_cpu_svn_t::default();
+    cpu_svn
+        .svn
+        .copy_from_slice(&header[MAGIC.len() + SVN_LEN..HEADER_LEN - KEY_ID_LEN]);
+    let key_id = &header[HEADER_LEN - KEY_ID_LEN..];
+    let key = sealing::derive_signer_key(key_id, u16::from_be_bytes(svn), cpu_svn)?;
+    let key = UnboundKey::new(&aead::AES_128_GCM, &key).map_err(|_| anyhow!("invalid key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+// Each key id encrypts a single snapshot, so the nonce is fixed.
+fn nonce() -> Nonce {
+    Nonce::assume_unique_for_key([0u8; aead::NONCE_LEN])
+}
+
+fn read_bytes<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    ensure!(data.len() >= 4, "truncated snapshot");
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&data[..4]);
+    let end = 4 + u32::from_be_bytes(len) as usize;
+    ensure!(data.len() >= end, "truncated snapshot");
+    let bytes = &data[4..end];
+    *data = &data[end..];
+    Ok(bytes)
+}
+
+/// Reads and encrypts all the entries of `database` under a new seal key.
+pub(crate) fn create(database: &mut dyn StorageBackend) -> Result<Snapshot> {
+    let mut entries = Vec::new();
+    let mut key_count = 0;
+    for (key, value) in database.scan(b"")? {
+        for bytes in [key, value].iter() {
+            entries.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            entries.extend_from_slice(bytes);
+        }
+        key_count += 1;
+    }
+    let (isv_svn, cpu_svn) = sealing::security_versions();
+    let mut key_id = [0u8; KEY_ID_LEN];
+    SystemRandom::new()
+        .fill(&mut key_id)
+        .map_err(|_| anyhow!("cannot generate snapshot key id"))?;
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&isv_svn.to_be_bytes());
+    data.extend_from_slice(&cpu_svn.svn);
+    data.extend_from_slice(&key_id);
+    cipher_key(&data)?
+        .seal_in_place_append_tag(nonce(), Aad::from(&data), &mut entries)
+        .map_err(|_| anyhow!("cannot encrypt snapshot"))?;
+    data.extend_from_slice(&entries);
+    Ok(Snapshot { data, key_count })
+}
+
+/// Decrypts the entries of a snapshot, which fails unless a storage enclave
+/// created it.
+pub(crate) fn open(data: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    ensure!(data.len() >= HEADER_LEN, "not a storage snapshot");
+    let (header, encrypted) = data.split_at(HEADER_LEN);
+    let mut in_out = encrypted.to_vec();
+    let mut plain: &[u8] = cipher_key(header)?
+        .open_in_place(nonce(), Aad::from(header), &mut in_out)
+        .map_err(|_| anyhow!("cannot decrypt snapshot"))?;
+    let mut entries = Vec::new();
+    while !plain.is_empty() {
+        let key = read_bytes(&mut plain)?.to_vec();
+        let value = read_bytes(&mut plain)?.to_vec();
+        entries.push((key, value));
+    }
+    Ok(entries)
+}
+
+fn staging_path(staging_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(staging_dir)?;
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    Ok(staging_dir.join(format!("snapshot-{}", nanos)))
+}
+
+fn check_url(url: &Url) -> Result<()> {
+    match url.scheme() {
+        "file" | "http" | "https" => Ok(()),
+        scheme => bail!("snapshots cannot be moved with {} URLs", scheme),
+    }
+}
+
+/// Uploads `snapshot` to `url` with the file agent.
+pub(crate) fn export(snapshot: &Snapshot, url: &Url, staging_dir: &Path) -> Result<()> {
+    check_url(url)?;
+    let path = staging_path(staging_dir)?;
+    fs::write(&path, &snapshot.data)?;
+    let info = HandleFileInfo::new(&path, url);
+    let request = FileAgentRequest::new(HandleFileCommand::Upload, vec![info], staging_dir);
+    let result = ocall::handle_file_request(request);
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Restores the snapshot of `config` into `database` if it is empty, and
+/// returns the number of restored keys.
+pub(crate) fn restore(
+    database: &mut dyn StorageBackend,
+    config: &StorageSnapshotConfig,
+) -> Result<u64> {
+    let url = match &config.restore_url {
+        Some(url) => Url::parse(url)?,
+        None => return Ok(0),
+    };
+    if database.scan(b"")?.next().is_some() {
+        warn!("Storage database not empty, snapshot not restored");
+        return Ok(0);
+    }
+    check_url(&url)?;
+    let staging_dir = Path::new(&config.staging_dir);
+    let path = staging_path(staging_dir)?;
+    let info = HandleFileInfo::new(&path, &url);
+    let request = FileAgentRequest::new(HandleFileCommand::Download, vec![info], staging_dir);
+    let data = ocall::handle_file_request(request).and_then(|_| Ok(fs::read(&path)?));
+    let _ = fs::remove_file(&path);
+    let entries = open(&data?)?;
+    for (key, value) in &entries {
+        database.put(key, value)?;
+    }
+    database.flush()?;
+    Ok(entries.len() as u64)
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::backend::LevelDb;
+
+    pub fn test_snapshot() {
+        let mut database = LevelDb::in_memory("test_snapshot_db").unwrap();
+        database.put(b"task-1", b"value-1").unwrap();
+        database.put(b"user-1", b"").unwrap();
+        let snapshot = create(&mut database).unwrap();
+        assert_eq!(snapshot.key_count, 2);
+        assert!(snapshot.data.starts_with(MAGIC));
+
+        let entries = open(&snapshot.data).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                (b"task-1".to_vec(), b"value-1".to_vec()),
+                (b"user-1".to_vec(), Vec::new()),
+            ]
+        );
+        // Snapshots are authenticated by the seal key, which the host can
+        // neither choose nor derive.
+        let mut modified = snapshot.data.clone();
+        let last = modified.len() - 1;
+        modified[last] ^= 1;
+        assert!(open(&modified).is_err());
+        let mut modified = snapshot.data.clone();
+        modified[HEADER_LEN - 1] ^= 1;
+        assert!(open(&modified).is_err());
+        let mut forged = snapshot.data[..HEADER_LEN].to_vec();
+        let key = UnboundKey::new(&aead::AES_128_GCM, &[0u8; 16]).unwrap();
+        let mut entries = vec![0, 0, 0, 1, b'k', 0, 0, 0, 1, b'v'];
+        LessSafeKey::new(key)
+            .seal_in_place_append_tag(nonce(), Aad::from(&forged), &mut entries)
+            .unwrap();
+        forged.extend_from_slice(&entries);
+        assert!(open(&forged).is_err());
+        assert!(check_url(&Url::parse("fusion:///TEACLAVE_FUSION_BASE/a").unwrap()).is_err());
+    }
+}
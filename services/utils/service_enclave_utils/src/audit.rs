@@ -0,0 +1,152 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Recording of security events into the audit log kept by the storage
+//! service. Events are also logged with the `audit` target, so they are not
+//! lost if the storage is unreachable; recording never fails the request.
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::prelude::v1::*;
+use std::sync::{Arc, SgxMutex as Mutex};
+use teaclave_proto::teaclave_storage_service::{
+    AppendAuditEventRequest, ExportAuditLogRequest, ExportAuditLogResponse, TeaclaveStorageClient,
+};
+use teaclave_rpc::endpoint::Endpoint;
+use teaclave_rpc::interceptor::Interceptor;
+use teaclave_types::{
+    AuditEvent, AuditEventKind, TeaclaveServiceResponseError, TeaclaveServiceResponseResult,
+};
+
+// Metadata key of the request name, kept for the response.
+const AUDIT_REQUEST_METADATA_KEY: &str = "audit_request";
+
+#[derive(Clone)]
+pub struct AuditRecorder {
+    service: String,
+    // None if events are only logged, e.g., in tests.
+    storage_client: Option<Arc<Mutex<TeaclaveStorageClient>>>,
+}
+
+impl AuditRecorder {
+    pub fn new(service: &str, storage_client: Arc<Mutex<TeaclaveStorageClient>>) -> Self {
+        Self {
+            service: service.to_string(),
+            storage_client: Some(storage_client),
+        }
+    }
+
+    pub fn connect(service: &str, storage_service_endpoint: &Endpoint) -> Result<Self> {
+        let mut i = 0;
+        let channel = loop {
+            match storage_service_endpoint.connect() {
+                Ok(channel) => break channel,
+                Err(_) => {
+                    anyhow::ensure!(i < 10, "failed to connect to storage service");
+                    log::debug!("Failed to connect to storage service, retry {}", i);
+                    i += 1;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_secs(3));
+        };
+        let client = TeaclaveStorageClient::new(channel)?;
+        Ok(Self::new(service, Arc::new(Mutex::new(client))))
+    }
+
+    pub fn log_only(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            storage_client: None,
+        }
+    }
+
+    pub fn record(&self, kind: AuditEventKind, user_id: &str, detail: impl Into<String>) {
+        let event = AuditEvent::new(kind, self.service.as_str(), user_id, detail);
+        info!(target: "audit", "{}", event);
+        if let Err(e) = self.append(event) {
+            error!("Failed to record audit event: {:?}", e);
+        }
+    }
+
+    fn append(&self, event: AuditEvent) -> Result<()> {
+        if let Some(client) = &self.storage_client {
+            client
+                .lock()
+                .map_err(|_| anyhow!("Cannot lock storage client"))?
+                .append_audit_event(AppendAuditEventRequest::new(event))?;
+        }
+        Ok(())
+    }
+
+    /// Exports entries of the audit log, verified by the storage service.
+    pub fn export(&self, start_seq: u64, limit: u32) -> Result<ExportAuditLogResponse> {
+        let client = self
+            .storage_client
+            .as_ref()
+            .ok_or_else(|| anyhow!("Audit log not kept"))?;
+        let response = client
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .export_audit_log(ExportAuditLogRequest::new(start_seq, limit))?;
+        Ok(response)
+    }
+}
+
+/// Records the requests of authenticated users denied by the service.
+pub struct AuditInterceptor {
+    recorder: AuditRecorder,
+}
+
+impl AuditInterceptor {
+    pub fn new(recorder: AuditRecorder) -> Self {
+        Self { recorder }
+    }
+}
+
+impl Interceptor for AuditInterceptor {
+    fn on_request(
+        &self,
+        metadata: &mut HashMap<String, String>,
+        method: &str,
+        _message: &dyn Debug,
+    ) -> TeaclaveServiceResponseResult<()> {
+        metadata.insert(AUDIT_REQUEST_METADATA_KEY.to_string(), method.to_string());
+        Ok(())
+    }
+
+    fn on_response(
+        &self,
+        metadata: &HashMap<String, String>,
+        response: Result<&dyn Debug, &TeaclaveServiceResponseError>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        if let Err(TeaclaveServiceResponseError::RequestError(e)) = response {
+            if e == "permission denied" {
+                if let Some(user_id) = metadata.get("id") {
+                    let request = metadata
+                        .get(AUDIT_REQUEST_METADATA_KEY)
+                        .map(String::as_str)
+                        .unwrap_or_default();
+                    self.recorder
+                        .record(AuditEventKind::PolicyDenied, user_id, request);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Checks shared by the `Health` RPC of services.
+
+use std::prelude::v1::*;
+use std::time::{Duration, SystemTime};
+use std::untrusted::time::SystemTimeEx;
+use teaclave_config::build::ATTESTATION_VALIDITY_SECS;
+use teaclave_proto::teaclave_common::{HealthCheck, HealthResponse};
+use teaclave_types::TeaclaveServiceResponseResult;
+
+const ATTESTATION_CHECK: &str = "attestation";
+
+/// Checks that the attestation report of the enclave is endorsed and has
+/// been refreshed in time. Reports are refreshed once per validity period,
+/// so a report older than two periods means that refreshing failed, and
+/// peers will soon reject the service.
+pub fn attestation_check() -> HealthCheck {
+    let validity = Duration::from_secs(ATTESTATION_VALIDITY_SECS);
+    let endorsed = match teaclave_attestation::last_endorsement() {
+        Some(endorsed) => endorsed,
+        None => return HealthCheck::unhealthy(ATTESTATION_CHECK, "not endorsed"),
+    };
+    let age = SystemTime::now()
+        .duration_since(endorsed)
+        .unwrap_or_default();
+    let detail = format!("endorsed {}s ago", age.as_secs());
+    if age > validity * 2 {
+        HealthCheck::unhealthy(ATTESTATION_CHECK, detail)
+    } else {
+        HealthCheck::healthy(ATTESTATION_CHECK, detail)
+    }
+}
+
+/// Checks a service depended on from the response of its `Health` RPC.
+pub fn dependency_check(
+    name: &str,
+    response: TeaclaveServiceResponseResult<HealthResponse>,
+) -> HealthCheck {
+    match response {
+        Ok(response) if response.is_ready() => HealthCheck::healthy(name, "ready"),
+        Ok(response) => {
+            let failed: Vec<_> = response
+                .checks
+                .iter()
+                .filter(|check| !check.healthy)
+                .map(|check| check.name.as_str())
+                .collect();
+            HealthCheck::unhealthy(name, format!("not ready: {}", failed.join(", ")))
+        }
+        Err(e) => HealthCheck::unhealthy(name, e.to_string()),
+    }
+}
@@ -24,12 +24,14 @@ use log::debug;
 use log::error;
 use std::backtrace;
 use std::sync::{Arc, SgxRwLock as RwLock};
-use teaclave_attestation::verifier::AttestationReportVerificationFn;
+use teaclave_attestation::verifier::{AttestationReportVerificationFn, QuoteStatusPolicy};
 use teaclave_attestation::AttestedTlsConfig;
-use teaclave_rpc::config::SgxTrustedTlsClientConfig;
+use teaclave_rpc::config::{SgxTrustedTlsClientConfig, TlsPolicy};
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_types::EnclaveInfo;
 
+pub mod audit;
+pub mod health;
 mod macros;
 
 #[cfg(feature = "cov")]
@@ -83,6 +85,8 @@ macro_rules! impl_create_trusted_endpoint_fn {
             enclave_info: &EnclaveInfo,
             as_root_ca_cert: &[u8],
             verifier: AttestationReportVerificationFn,
+            quote_status_policy: QuoteStatusPolicy,
+            tls_policy: TlsPolicy,
             attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
         ) -> anyhow::Result<Endpoint> {
             let service_enclave_attrs = enclave_info
@@ -90,11 +94,13 @@ macro_rules! impl_create_trusted_endpoint_fn {
                 .expect("enclave_info");
             let service_client_config =
                 SgxTrustedTlsClientConfig::from_attested_tls_config(attested_tls_config)?
-                    .attestation_report_verifier(
+                    .attestation_report_verifier_with_policy(
                         vec![service_enclave_attrs],
                         as_root_ca_cert,
                         verifier,
-                    );
+                        quote_status_policy,
+                    )
+                    .tls_policy(tls_policy)?;
             let service_address = &advertised_address;
 
             Ok(Endpoint::new(service_address).config(service_client_config))
@@ -115,3 +121,7 @@ impl_create_trusted_endpoint_fn!(
     create_trusted_scheduler_endpoint,
     "teaclave_scheduler_service"
 );
+impl_create_trusted_endpoint_fn!(
+    create_trusted_access_control_endpoint,
+    "teaclave_access_control_service"
+);
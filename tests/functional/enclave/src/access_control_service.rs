@@ -117,6 +117,7 @@ fn test_authorize_staged_task_success() {
             "mock_staged_allowed_data2".to_string(),
             "mock_staged_allowed_data3".to_string(),
         ],
+        explain: false,
     };
     let response_result = client.authorize_staged_task(request);
     assert!(response_result.is_ok());
@@ -131,6 +132,7 @@ fn test_authorize_staged_task_fail() {
         object_function_id: "mock_staged_disallowed_private_function".to_string(),
         object_input_data_id_list: vec![],
         object_output_data_id_list: vec![],
+        explain: false,
     };
     let response_result = client.authorize_staged_task(request);
     assert!(response_result.is_ok());
@@ -141,6 +143,7 @@ fn test_authorize_staged_task_fail() {
         object_function_id: "mock_staged_allowed_private_function".to_string(),
         object_input_data_id_list: vec!["mock_staged_disallowed_data1".to_string()],
         object_output_data_id_list: vec![],
+        explain: false,
     };
     let response_result = client.authorize_staged_task(request);
     assert!(response_result.is_ok());
@@ -151,12 +154,46 @@ fn test_authorize_staged_task_fail() {
         object_function_id: "mock_staged_allowed_private_function".to_string(),
         object_input_data_id_list: vec![],
         object_output_data_id_list: vec!["mock_staged_disallowed_data2".to_string()],
+        explain: false,
     };
     let response_result = client.authorize_staged_task(request);
     assert!(response_result.is_ok());
     assert!(!response_result.unwrap().accept);
 }
 
+#[test_case]
+fn test_authorize_data_explanation() {
+    // Only admins and the owner of the data get explanations.
+    let mut client = get_access_control_client();
+    let request = AuthorizeDataRequest::new("mock_user_d", "mock_data").explain();
+    let response = client.authorize_data(request).unwrap();
+    assert!(!response.accept);
+    assert!(response.explanation.is_empty());
+
+    let mut client = get_access_control_client_internal("mock_user_a");
+    let request = AuthorizeDataRequest::new("mock_user_d", "mock_data").explain();
+    let response = client.authorize_data(request).unwrap();
+    assert!(!response.accept);
+    assert_eq!(response.explanation.len(), 1);
+    let decision = &response.explanation[0];
+    assert_eq!(decision.rule, "user_access_data");
+    assert!(!decision.accept);
+    assert_eq!(decision.attributes["usr"], "mock_user_d");
+    assert_eq!(decision.attributes["data"], "mock_data");
+}
+
+#[test_case]
+fn test_update_policy_without_permission() {
+    let mut client = get_access_control_client();
+
+    let request = UpdateAccessControlPolicyRequest::new(b"{}".to_vec(), vec![]);
+    assert!(client.update_policy(request).is_err());
+    let request = RollbackAccessControlPolicyRequest::new(0);
+    assert!(client.rollback_policy(request).is_err());
+    let request = GetAccessControlPolicyRequest::new();
+    assert!(client.get_policy(request).is_err());
+}
+
 #[test_case]
 fn test_concurrency() {
     let mut thread_pool = Vec::new();
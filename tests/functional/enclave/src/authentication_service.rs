@@ -129,6 +129,52 @@ fn test_authenticate_fail() {
     assert!(!response_result.unwrap().accept);
 }
 
+#[test_case]
+fn test_revoke_token() {
+    let mut api_client = get_api_client();
+    let mut internal_client = get_internal_client();
+    let request = UserRegisterRequest::new("test_revoke_id1", "test_password");
+    let response_result = api_client.user_register(request);
+    assert!(response_result.is_ok());
+
+    let request = UserLoginRequest::new("test_revoke_id1", "test_password");
+    let token = api_client.user_login(request).unwrap().token;
+    let mut metadata = std::collections::HashMap::new();
+    metadata.insert("id".to_string(), "test_revoke_id1".to_string());
+    metadata.insert("token".to_string(), token.clone());
+    api_client.set_metadata(metadata);
+    let response_result = api_client.revoke_token(RevokeTokenRequest::new());
+    assert!(response_result.is_ok());
+
+    let credential = UserCredential::new("test_revoke_id1", token);
+    let request = UserAuthenticateRequest::new(credential);
+    let response_result = internal_client.user_authenticate(request);
+    debug!("{:?}", response_result);
+    assert!(!response_result.unwrap().accept);
+}
+
+#[test_case]
+fn test_enroll_totp() {
+    let mut api_client = get_api_client();
+    let request = UserRegisterRequest::new("test_totp_id1", "test_password");
+    assert!(api_client.user_register(request).is_ok());
+
+    let request = UserLoginRequest::new("test_totp_id1", "test_password");
+    let token = api_client.user_login(request).unwrap().token;
+    let mut metadata = std::collections::HashMap::new();
+    metadata.insert("id".to_string(), "test_totp_id1".to_string());
+    metadata.insert("token".to_string(), token);
+    api_client.set_metadata(metadata);
+    let response = api_client.enroll_totp(EnrollTotpRequest::new()).unwrap();
+    assert_eq!(response.secret.len(), 32);
+
+    // The secret is only used once confirmed with a code generated from it.
+    let request = UserLoginRequest::new("test_totp_id1", "test_password");
+    assert!(api_client.user_login(request).is_ok());
+    let request = ConfirmTotpRequest::new("12345");
+    assert!(api_client.confirm_totp(request).is_err());
+}
+
 #[test_case]
 fn test_register_success() {
     let mut client = get_api_client();
@@ -149,3 +195,28 @@ fn test_register_fail() {
     debug!("{:?}", response_result);
     assert!(response_result.is_err());
 }
+
+#[test_case]
+fn test_manage_users_denied() {
+    let mut api_client = get_api_client();
+    let request = UserRegisterRequest::new("test_manage_users_id1", "test_password");
+    assert!(api_client.user_register(request).is_ok());
+
+    let request = UserLoginRequest::new("test_manage_users_id1", "test_password");
+    let token = api_client.user_login(request).unwrap().token;
+    let mut metadata = std::collections::HashMap::new();
+    metadata.insert("id".to_string(), "test_manage_users_id1".to_string());
+    metadata.insert("token".to_string(), token);
+    api_client.set_metadata(metadata);
+
+    // Users without the manage_users permission cannot manage users.
+    assert!(api_client.list_users(ListUsersRequest::new(10)).is_err());
+    let request = DisableUserRequest::new("test_register_id1");
+    assert!(api_client.disable_user(request).is_err());
+    let request = DeleteUserRequest::new("test_register_id1");
+    assert!(api_client.delete_user(request).is_err());
+    let request = ResetPasswordRequest::new("test_register_id1", "new_password");
+    assert!(api_client.reset_password(request).is_err());
+    let request = ExportAuditLogRequest::new(0, 10);
+    assert!(api_client.export_audit_log(request).is_err());
+}
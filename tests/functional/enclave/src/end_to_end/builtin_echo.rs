@@ -16,6 +16,8 @@
 // under the License.
 
 use super::*;
+use teaclave_attestation::report::AttestationReport;
+use teaclave_config::build::AS_ROOT_CA_CERT;
 use teaclave_test_utils::test_case;
 
 #[test_case]
@@ -63,4 +65,17 @@ pub fn test_echo_task_success() {
     // Get Task
     let ret_val = get_task_until(&mut client, &task_id, TaskStatus::Finished);
     assert_eq!(&ret_val, "Hello From Teaclave!");
+
+    // Export Task Result
+    let request = GetTaskResultRequest::new(task_id.clone()).export_cwt();
+    let response = client.get_task_result(request).unwrap();
+    let token = CoseSign1::from_slice(&response.cwt).unwrap();
+    let report = AttestationReport::from_cert(&token.certificate, AS_ROOT_CA_CERT).unwrap();
+    let report_data = report.sgx_quote_body.isv_enclave_report.report_data;
+    let mut public_key = vec![4u8];
+    public_key.extend_from_slice(report_data.as_bytes());
+    assert!(token.verify(&public_key).is_ok());
+    let claims = TaskResultClaims::from_slice(&token.payload).unwrap();
+    assert_eq!(claims.task_id, task_id.to_string());
+    assert!(claims.matches_return_value(b"Hello From Teaclave!"));
 }
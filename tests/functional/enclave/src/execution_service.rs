@@ -26,22 +26,48 @@ use uuid::Uuid;
 #[test_case]
 fn test_execute_function() {
     let task_id = Uuid::new_v4();
-    let ts = TaskState {
-        task_id,
-        status: TaskStatus::Staged,
-        ..Default::default()
-    };
+    let staged_task = echo_task(task_id);
 
-    let function_id = Uuid::new_v4();
+    let updated_task = run_staged_task(task_id, staged_task);
+    let outputs = updated_task.result.unwrap();
+    assert_eq!(outputs.return_value, b"Hello, Teaclave Tests!");
+    assert!(outputs.receipt.is_none());
+}
 
-    let staged_task = StagedTask::new()
+#[test_case]
+fn test_execute_deterministic_function() {
+    let task_id = Uuid::new_v4();
+    let staged_task = echo_task(task_id).deterministic(true);
+
+    let updated_task = run_staged_task(task_id, staged_task);
+    let outputs = updated_task.result.unwrap();
+    let token = CoseSign1::from_slice(&outputs.receipt.unwrap()).unwrap();
+    let claims = ExecutionReceiptClaims::from_slice(&token.payload).unwrap();
+    assert_eq!(claims.issuer, "teaclave_execution_service");
+    assert_eq!(claims.task_id, format!("task-{}", task_id));
+    assert_eq!(claims.function_name, "builtin-echo");
+    assert!(claims.matches_return_value(b"Hello, Teaclave Tests!"));
+}
+
+fn echo_task(task_id: Uuid) -> StagedTask {
+    StagedTask::new()
         .task_id(task_id)
-        .function_id(function_id.clone())
+        .function_id(Uuid::new_v4())
         .function_name("builtin-echo")
         .executor(Executor::Builtin)
         .function_arguments(hashmap!(
             "message" => "Hello, Teaclave Tests!"
-        ));
+        ))
+}
+
+// Queues the task for the execution service and reads its state back once
+// it has had the time to run.
+fn run_staged_task(task_id: Uuid, staged_task: StagedTask) -> TaskState {
+    let ts = TaskState {
+        task_id,
+        status: TaskStatus::Staged,
+        ..Default::default()
+    };
 
     let mut storage_client = get_storage_client();
     let enqueue_request = EnqueueRequest::new(
@@ -54,11 +80,11 @@ fn test_execute_function() {
 
     std::thread::sleep(std::time::Duration::from_secs(5));
 
-    let get_request = GetRequest::new(ts.key().as_slice());
+    // The scheduler moves the task to the namespace of its creator.
+    let get_request = GetRequest::new(locator_key(&ts.key()));
+    let namespace = storage_client.get(get_request).unwrap().value;
+    let get_request =
+        GetRequest::new(ts.key().as_slice()).namespace(String::from_utf8(namespace).unwrap());
     let get_response = storage_client.get(get_request).unwrap();
-    let updated_task = TaskState::from_slice(get_response.value.as_slice()).unwrap();
-    assert_eq!(
-        updated_task.result.unwrap().return_value,
-        b"Hello, Teaclave Tests!"
-    );
+    TaskState::from_slice(get_response.value.as_slice()).unwrap()
 }
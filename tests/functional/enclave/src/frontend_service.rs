@@ -16,6 +16,7 @@
 // under the License.
 
 use crate::utils::*;
+use ring::digest;
 use std::convert::TryFrom;
 use std::prelude::v1::*;
 use teaclave_proto::teaclave_common::*;
@@ -52,6 +53,48 @@ fn test_register_input_file() {
     assert!(response.is_err());
 }
 
+#[test_case]
+fn test_register_input_file_with_aead_schemes() {
+    let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
+    let cmac = FileAuthTag::mock();
+    let crypto_infos: Vec<FileCrypto> = vec![
+        AesGcmSiv256Key::random().into(),
+        XChaCha20Poly1305Key::random().into(),
+    ];
+    for crypto_info in crypto_infos {
+        let request = RegisterInputFileRequest::new(url.clone(), cmac, crypto_info);
+        let response = authorized_client().register_input_file(request);
+        assert!(response.is_ok());
+    }
+}
+
+#[test_case]
+fn test_register_inline_input_file() {
+    let content = b"inline input data".to_vec();
+    let cmac = FileAuthTag::mock();
+    let crypto_info = FileCrypto::default();
+
+    let request = RegisterInlineInputFileRequest::new(content.clone(), cmac, crypto_info);
+    let response = authorized_client().register_inline_input_file(request);
+    assert!(response.is_ok());
+
+    // inline files have no url to update
+    let data_id = response.unwrap().data_id;
+    let new_url = Url::parse("https://external-storage.com/filepath-new?presigned_token").unwrap();
+    let update_request = UpdateInputFileRequest::new(data_id, new_url);
+    let update_response = authorized_client().update_input_file(update_request);
+    assert!(update_response.is_err());
+
+    // exceeds the inline data size limit
+    let request = RegisterInlineInputFileRequest::new(vec![0u8; 1024 * 1024], cmac, crypto_info);
+    let response = authorized_client().register_inline_input_file(request);
+    assert!(response.is_err());
+
+    let request = RegisterInlineInputFileRequest::new(content, cmac, crypto_info);
+    let response = unauthorized_client().register_inline_input_file(request);
+    assert!(response.is_err());
+}
+
 #[test_case]
 fn test_update_input_file() {
     let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
@@ -234,6 +277,86 @@ fn test_get_task() {
     assert!(response.is_err());
 }
 
+#[test_case]
+fn test_get_task_result() {
+    let mut client = authorized_client();
+    let function_id =
+        ExternalID::try_from("function-00000000-0000-0000-0000-000000000002").unwrap();
+
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("arg1" => "arg1_value"))
+        .executor(Executor::MesaPy)
+        .outputs_ownership(hashmap!("output" => vec!["frontend_user", "mock_user"]));
+    let response = client.create_task(request).unwrap();
+    let task_id = response.task_id;
+
+    let request = GetTaskResultRequest::new(task_id.clone()).range(0, 1024);
+    let response = client.get_task_result(request).unwrap();
+    assert!(matches!(response.result, TaskResult::NotReady));
+    assert_eq!(response.return_value_len, 0);
+
+    // No token for a task not finished
+    let request = GetTaskResultRequest::new(task_id.clone()).export_cwt();
+    let response = client.get_task_result(request).unwrap();
+    assert!(response.cwt.is_empty());
+
+    let request = GetTaskResultRequest::new(task_id);
+    let response = unauthorized_client().get_task_result(request);
+    assert!(response.is_err());
+}
+
+#[test_case]
+fn test_get_task_log() {
+    let mut client = authorized_client();
+    let function_id =
+        ExternalID::try_from("function-00000000-0000-0000-0000-000000000002").unwrap();
+
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("arg1" => "arg1_value"))
+        .executor(Executor::MesaPy)
+        .outputs_ownership(hashmap!("output" => vec!["frontend_user", "mock_user"]));
+    let response = client.create_task(request).unwrap();
+    let task_id = response.task_id;
+
+    // No log for a task not finished
+    let request = GetTaskLogRequest::new(task_id.clone()).range(0, 1024);
+    let response = client.get_task_log(request).unwrap();
+    assert!(response.log.is_empty());
+    assert_eq!(response.total_len, 0);
+
+    let request = GetTaskLogRequest::new(task_id);
+    let response = unauthorized_client().get_task_log(request);
+    assert!(response.is_err());
+}
+
+#[test_case]
+fn test_stream_task_result() {
+    let mut client = authorized_client();
+    let function_id =
+        ExternalID::try_from("function-00000000-0000-0000-0000-000000000002").unwrap();
+
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("arg1" => "arg1_value"))
+        .executor(Executor::MesaPy)
+        .outputs_ownership(hashmap!("output" => vec!["frontend_user", "mock_user"]));
+    let response = client.create_task(request).unwrap();
+    let task_id = response.task_id;
+
+    // No results for a task not running
+    let request = StreamTaskResultRequest::new(task_id.clone()).range(0, 16);
+    let response = client.stream_task_result(request).unwrap();
+    assert!(response.chunks.is_empty());
+    assert_eq!(response.next_offset, 0);
+    assert!(!response.finished);
+
+    let request = StreamTaskResultRequest::new(task_id);
+    let response = unauthorized_client().stream_task_result(request);
+    assert!(response.is_err());
+}
+
 #[test_case]
 fn test_assign_data() {
     let mut client = authorized_client();
@@ -343,8 +466,170 @@ fn test_invoke_task() {
     let response = client.get_task(request).unwrap();
     assert_eq!(response.status, TaskStatus::Staged);
 
-    let request = PullTaskRequest {};
+    let request = PullTaskRequest::new("test_worker");
     let mut scheduler_client = get_scheduler_client();
     let response = scheduler_client.pull_task(request);
     assert!(response.is_ok());
 }
+
+#[test_case]
+fn test_health() {
+    // Probes do not need to authenticate.
+    let response = unauthorized_client().health(HealthRequest::new()).unwrap();
+    assert!(response.is_ready());
+    let checks: Vec<&str> = response
+        .checks
+        .iter()
+        .map(|check| check.name.as_str())
+        .collect();
+    assert!(checks.contains(&"authentication"));
+    assert!(checks.contains(&"management"));
+}
+
+#[test_case]
+fn test_get_platform_info() {
+    let response = unauthorized_client()
+        .get_platform_info(GetPlatformInfoRequest::new())
+        .unwrap();
+    assert!(response.executors.contains(&Executor::MesaPy));
+    assert!(response.executors.contains(&Executor::Builtin));
+    assert!(response
+        .crypto_schemes
+        .contains(&TeaclaveFile128Key::SCHEMA.to_string()));
+    assert!(response
+        .crypto_schemes
+        .contains(&XChaCha20Poly1305Key::SCHEMA.to_string()));
+    assert!(response.max_message_len > 0);
+    assert!(response.inline_data_max_size > 0);
+}
+
+#[test_case]
+fn test_read_only_mode() {
+    // Only platform admins can enter the read-only mode.
+    let request = EnterReadOnlyModeRequest::new(60, "test");
+    let response = authorized_client().enter_read_only_mode(request);
+    assert!(response.is_err());
+    let request = EnterReadOnlyModeRequest::new(60, "test");
+    let response = unauthorized_client().enter_read_only_mode(request);
+    assert!(response.is_err());
+    let response = authorized_client().exit_read_only_mode(ExitReadOnlyModeRequest::new());
+    assert!(response.is_err());
+
+    // Mutating requests are still accepted.
+    let request = RegisterFunctionRequest::default();
+    let response = authorized_client().register_function(request);
+    assert!(response.is_ok());
+}
+
+#[test_case]
+fn test_get_measurement_inclusion() {
+    // No measurement log is configured in the tests, and the request needs no
+    // credential.
+    let measurement = shared_enclave_info().measurements["teaclave_frontend_service"];
+    let entry = MeasurementLogEntry::new("teaclave_frontend_service", measurement);
+    let request = GetMeasurementInclusionRequest::new(entry);
+    let response = unauthorized_client().get_measurement_inclusion(request);
+    assert!(response.is_err());
+}
+
+#[test_case]
+fn test_quota() {
+    // Users have no quota until one is set, by users with manage_users only.
+    let response = authorized_client()
+        .get_quota_usage(GetQuotaUsageRequest::new())
+        .unwrap();
+    assert_eq!(response.quota, UserQuota::default());
+
+    let request = SetUserQuotaRequest::new(USERNAME, UserQuota::new(1, 1, 1));
+    let response = authorized_client().set_user_quota(request);
+    assert!(response.is_err());
+
+    let request = GetQuotaUsageRequest::new().user_id("another_user");
+    let response = authorized_client().get_quota_usage(request);
+    assert!(response.is_err());
+    let response = unauthorized_client().get_quota_usage(GetQuotaUsageRequest::new());
+    assert!(response.is_err());
+}
+
+#[test_case]
+fn test_get_tenant_stats() {
+    let response = authorized_client()
+        .get_tenant_stats(GetTenantStatsRequest::new(60))
+        .unwrap();
+    assert_eq!(response.stats.len(), 1);
+    assert_eq!(response.stats[0].user_id, UserID::from(USERNAME));
+
+    // Windows are limited to a week, and users only see their own stats
+    // without manage_users.
+    let request = GetTenantStatsRequest::new(0);
+    assert!(authorized_client().get_tenant_stats(request).is_err());
+    let request = GetTenantStatsRequest::new(8 * 24 * 60 * 60);
+    assert!(authorized_client().get_tenant_stats(request).is_err());
+    let request = GetTenantStatsRequest::new(60).user_id("another_user");
+    assert!(authorized_client().get_tenant_stats(request).is_err());
+    let request = GetTenantStatsRequest::new(60);
+    assert!(unauthorized_client().get_tenant_stats(request).is_err());
+}
+
+#[test_case]
+fn test_payload_upload() {
+    let hash = |data: &[u8]| digest::digest(&digest::SHA256, data).as_ref().to_vec();
+    let payload = b"a payload uploaded in parts".to_vec();
+    let parts: Vec<&[u8]> = payload.chunks(8).collect();
+
+    let mut client = authorized_client();
+    let request = BeginPayloadUploadRequest::new(payload.len() as u64, 8, hash(&payload));
+    let response = client.begin_payload_upload(request).unwrap();
+    assert_eq!(response.part_count, 4);
+    assert!(response.received_parts.is_empty());
+    let upload_id = response.upload_id;
+
+    // Parts not matching their hash are rejected.
+    let request = UploadPartRequest::new(upload_id.clone(), 0, parts[1].to_vec(), hash(parts[0]));
+    assert!(client.upload_part(request).is_err());
+    for (part_number, part) in parts.iter().enumerate().take(2) {
+        let request = UploadPartRequest::new(
+            upload_id.clone(),
+            part_number as u32,
+            part.to_vec(),
+            hash(part),
+        );
+        assert!(client.upload_part(request).is_ok());
+    }
+    let request = CommitPayloadRequest::new(upload_id.clone());
+    assert!(client.commit_payload(request).is_err());
+
+    // Resuming reports the received parts.
+    let request = BeginPayloadUploadRequest::new(payload.len() as u64, 8, hash(&payload))
+        .resume(upload_id.clone());
+    let response = client.begin_payload_upload(request).unwrap();
+    assert_eq!(response.received_parts, vec![0, 1]);
+    for (part_number, part) in parts.iter().enumerate().skip(2) {
+        let request = UploadPartRequest::new(
+            upload_id.clone(),
+            part_number as u32,
+            part.to_vec(),
+            hash(part),
+        );
+        assert!(client.upload_part(request).is_ok());
+    }
+
+    // Uploads need a valid credential like other requests.
+    let request = CommitPayloadRequest::new(upload_id.clone());
+    assert!(unauthorized_client().commit_payload(request).is_err());
+    let request = CommitPayloadRequest::new(upload_id.clone());
+    let response = client.commit_payload(request).unwrap();
+    assert_eq!(response.payload_len, payload.len() as u64);
+
+    let request = RegisterFunctionRequest::new()
+        .name("payload_upload")
+        .payload_upload_id(upload_id.clone());
+    let function_id = client.register_function(request).unwrap().function_id;
+    let request = GetFunctionRequest::new(function_id);
+    let response = client.get_function(request).unwrap();
+    assert_eq!(response.payload, payload);
+
+    // The upload is consumed by the registration.
+    let request = RegisterFunctionRequest::new().payload_upload_id(upload_id);
+    assert!(client.register_function(request).is_err());
+}
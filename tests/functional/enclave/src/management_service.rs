@@ -18,6 +18,7 @@
 use crate::utils::*;
 use std::convert::TryFrom;
 use std::prelude::v1::*;
+use teaclave_proto::teaclave_frontend_service::TaskOverrides;
 use teaclave_proto::teaclave_management_service::*;
 use teaclave_proto::teaclave_scheduler_service::*;
 use teaclave_test_utils::test_case;
@@ -123,6 +124,81 @@ fn test_get_input_file() {
     assert!(response.is_err());
 }
 
+#[test_case]
+fn test_privacy_budget() {
+    let mut client = authorized_client("mock_user");
+    let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
+
+    let request =
+        RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), FileCrypto::default())
+            .privacy_budget(PrivacyBudget::new(0.0, 0.0));
+    assert!(client.register_input_file(request).is_err());
+    let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::default())
+        .privacy_budget(PrivacyBudget::new(1.0, 0.0));
+    let input_id = client.register_input_file(request).unwrap().data_id;
+
+    let request = RegisterFunctionRequest::new()
+        .name("builtin-dp-aggregate")
+        .executor_type(ExecutorType::Builtin)
+        .arguments(vec!["aggregate", "epsilon"])
+        .inputs(vec![FunctionInput::new(
+            "input_data",
+            "Data of the statistics",
+        )])
+        .outputs(vec![FunctionOutput::new("output_data", "Noisy statistics")]);
+    let function_id = client.register_function(request).unwrap().function_id;
+
+    let mut run_task = |epsilon: &str| {
+        let request = CreateTaskRequest::new()
+            .function_id(function_id.clone())
+            .function_arguments(hashmap!("aggregate" => "count", "epsilon" => epsilon))
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("input_data" => vec!["mock_user"]))
+            .outputs_ownership(hashmap!("output_data" => vec!["mock_user"]));
+        let task_id = client.create_task(request).unwrap().task_id;
+        let url = Url::parse("https://output_file_path").unwrap();
+        let request = RegisterOutputFileRequest::new(url, FileCrypto::default());
+        let output_id = client.register_output_file(request).unwrap().data_id;
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input_data" => input_id.clone()),
+            hashmap!("output_data" => output_id),
+        );
+        client.assign_data(request).unwrap();
+        client
+            .approve_task(ApproveTaskRequest::new(task_id.clone()))
+            .unwrap();
+        client.invoke_task(InvokeTaskRequest::new(task_id))
+    };
+
+    // The second task would exceed the budget.
+    assert!(run_task("0.6").is_ok());
+    assert!(run_task("0.6").is_err());
+    assert!(run_task("0.4").is_ok());
+
+    let request = GetInputFileRequest::new(input_id.clone());
+    let budget = client
+        .get_input_file(request)
+        .unwrap()
+        .privacy_budget
+        .unwrap();
+    assert!((budget.spent_epsilon - 1.0).abs() < 1e-9);
+
+    // Other functions cannot read the file.
+    let request = create_valid_task_request().inputs_ownership(hashmap!(
+        "input" => vec!["mock_user"],
+        "input2" => vec!["mock_user2", "mock_user3"]
+    ));
+    let task_id = client.create_task(request).unwrap().task_id;
+    let request = AssignDataRequest::new(task_id, hashmap!("input" => input_id), hashmap!());
+    assert!(client.assign_data(request).is_err());
+
+    // The budget cannot be renewed by updating the file.
+    let url = Url::parse("https://external-storage.com/filepath2").unwrap();
+    let request = UpdateInputFileRequest::new(input_id, url);
+    assert!(client.update_input_file(request).is_err());
+}
+
 #[test_case]
 fn test_register_function() {
     let function_input = FunctionInput::new("input", "input_desc");
@@ -142,6 +218,37 @@ fn test_register_function() {
     assert!(response.is_ok());
 }
 
+#[test_case]
+fn test_register_function_with_output_policy() {
+    let policy = OutputPolicy::new()
+        .format(OutputFormat::Csv)
+        .aggregation_threshold("count", 10);
+    let function_output = FunctionOutput::new("output", "output_desc").policy(policy);
+    let request = RegisterFunctionRequest::new()
+        .name("mock_function")
+        .executor_type(ExecutorType::Python)
+        .payload(b"def entrypoint:\n\treturn".to_vec())
+        .outputs(vec![function_output]);
+
+    let mut client = authorized_client("mock_user");
+    let response = client.register_function(request).unwrap();
+
+    let request = GetFunctionRequest::new(response.function_id);
+    let response = client.get_function(request).unwrap();
+    assert_eq!(response.outputs[0].policy.min_count, 10);
+
+    // Thresholds apply to CSV outputs only.
+    let policy = OutputPolicy::new().aggregation_threshold("count", 10);
+    let function_output = FunctionOutput::new("output", "output_desc").policy(policy);
+    let request = RegisterFunctionRequest::new()
+        .name("mock_function")
+        .executor_type(ExecutorType::Python)
+        .payload(b"def entrypoint:\n\treturn".to_vec())
+        .outputs(vec![function_output]);
+    let response = client.register_function(request);
+    assert!(response.is_err());
+}
+
 #[test_case]
 fn test_get_function() {
     let function_input = FunctionInput::new("input", "input_desc");
@@ -175,6 +282,395 @@ fn test_get_function() {
     assert!(response.is_ok());
 }
 
+#[test_case]
+fn test_register_function_with_manifest() {
+    let manifest = r#"
+        name = "manifest_function"
+        executor_type = "python"
+        entry_point = "main.py"
+        abi_version = 1
+
+        [[arguments]]
+        name = "top"
+        type = "integer"
+        required = true
+    "#;
+    let manifest = FunctionManifest::from_toml(manifest).unwrap();
+    let payload = b"def entrypoint(argv):\n\treturn".to_vec();
+    let request = RegisterFunctionRequest::from_manifest(manifest.clone(), payload).unwrap();
+
+    let mut client = authorized_client("mock_user");
+    let response = client.register_function(request).unwrap();
+    let function_id = response.function_id;
+    let request = GetFunctionRequest::new(function_id.clone());
+    let response = client.get_function(request).unwrap();
+    assert_eq!(response.arguments, vec!["top"]);
+    assert_eq!(response.manifest, Some(manifest.clone()));
+
+    // The manifest must declare the registered function.
+    let request = RegisterFunctionRequest::from_manifest(manifest, vec![])
+        .unwrap()
+        .arguments(vec!["top", "other"]);
+    assert!(client.register_function(request).is_err());
+
+    // Arguments are checked when the task is invoked.
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("top" => "ten"))
+        .executor(Executor::MesaPy);
+    let task_id = client.create_task(request).unwrap().task_id;
+    let request = InvokeTaskRequest::new(task_id);
+    assert!(client.invoke_task(request).is_err());
+}
+
+#[test_case]
+fn test_register_function_with_bundle() {
+    let bundle = include_bytes!("../../../fixtures/functions/mesapy/bundle.zip").to_vec();
+    let request = |executor_type, bundle: &[u8]| {
+        RegisterFunctionRequest::new()
+            .name("bundled_function")
+            .executor_type(executor_type)
+            .payload(b"import greeting\ndef entrypoint(argv):\n\treturn".to_vec())
+            .bundle(bundle.to_vec())
+    };
+
+    let mut client = authorized_client("mock_user");
+    let response = client.register_function(request(ExecutorType::Python, &bundle));
+    assert!(response.is_ok());
+
+    // Bundles are zip archives of Python modules, for Python functions.
+    let response = client.register_function(request(ExecutorType::Python, b"import greeting"));
+    assert!(response.is_err());
+    let response = client.register_function(request(ExecutorType::Wasm, &bundle));
+    assert!(response.is_err());
+}
+
+#[test_case]
+fn test_function_versions() {
+    let mut client = authorized_client("mock_user");
+    let mut register = |payload: &[u8]| {
+        let request = RegisterFunctionRequest::new()
+            .name("versioned_function")
+            .executor_type(ExecutorType::Python)
+            .payload(payload.to_vec());
+        client.register_function(request).unwrap()
+    };
+    let first = register(b"def entrypoint(argv):\n\treturn 1");
+    let second = register(b"def entrypoint(argv):\n\treturn 2");
+    assert_eq!(second.version, first.version + 1);
+
+    let request = GetFunctionRequest::new(first.function_id.clone());
+    let response = client.get_function(request).unwrap();
+    assert_eq!(response.version, first.version);
+    assert_eq!(response.default_version, second.version);
+
+    // Tasks use the given function, the latest or a pinned version.
+    fn task_function(
+        client: &mut TeaclaveManagementClient,
+        function_id: &ExternalID,
+        version: FunctionVersion,
+    ) -> anyhow::Result<ExternalID> {
+        let request = CreateTaskRequest::new()
+            .function_id(function_id.clone())
+            .function_version(version)
+            .executor(Executor::MesaPy);
+        let task_id = client.create_task(request)?.task_id;
+        let request = GetTaskRequest::new(task_id);
+        Ok(client.get_task(request)?.function_id)
+    }
+    let id = &first.function_id;
+    let given = task_function(&mut client, id, FunctionVersion::Given).unwrap();
+    assert_eq!(given, first.function_id);
+    let latest = task_function(&mut client, id, FunctionVersion::Latest).unwrap();
+    assert_eq!(latest, second.function_id);
+    let pinned = FunctionVersion::Pinned(first.version);
+    assert_eq!(task_function(&mut client, id, pinned).unwrap(), *id);
+    let missing = FunctionVersion::Pinned(second.version + 1);
+    assert!(task_function(&mut client, id, missing).is_err());
+
+    // Rolled back, tasks asking for the latest use the previous version.
+    let request = RollbackFunctionRequest::new(second.function_id.clone(), 0);
+    let response = client.rollback_function(request).unwrap();
+    assert_eq!(response.default_version, first.version);
+    let latest = task_function(&mut client, id, FunctionVersion::Latest).unwrap();
+    assert_eq!(latest, first.function_id);
+
+    // Only earlier versions than the default, by the owner
+    let request = RollbackFunctionRequest::new(second.function_id.clone(), second.version);
+    assert!(client.rollback_function(request).is_err());
+    let mut other_client = authorized_client("mock_another_user");
+    let request = RollbackFunctionRequest::new(second.function_id, 0);
+    assert!(other_client.rollback_function(request).is_err());
+}
+
+#[test_case]
+fn test_executor_enclave() {
+    let mut client = authorized_client("mock_user");
+    let measurement = EnclaveMeasurement::new(MrEnclave::new([1; 32]), MrSigner::new([2; 32]));
+    let request = RegisterExecutorEnclaveRequest::new("wasm_executor", measurement);
+    let enclave_id = client
+        .register_executor_enclave(request)
+        .unwrap()
+        .executor_enclave_id;
+
+    let request = RegisterFunctionRequest::new()
+        .name("wasm_function")
+        .executor_enclave(enclave_id.clone());
+    let function_id = client.register_function(request).unwrap().function_id;
+    let request = GetFunctionRequest::new(function_id.clone());
+    let response = client.get_function(request).unwrap();
+    assert_eq!(response.executor_enclave, Some(enclave_id.clone()));
+
+    // Functions run only in the executor enclaves of their owner.
+    let request = RegisterFunctionRequest::new()
+        .name("wasm_function")
+        .executor_enclave(enclave_id);
+    let mut other_client = authorized_client("mock_another_user");
+    assert!(other_client.register_function(request).is_err());
+
+    // The tasks of the function are staged for the workers launching it.
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .executor(Executor::MesaPy);
+    let task_id = client.create_task(request).unwrap().task_id;
+    let request = ApproveTaskRequest::new(task_id.clone());
+    client.approve_task(request).unwrap();
+    let request = InvokeTaskRequest::new(task_id.clone());
+    client.invoke_task(request).unwrap();
+    let request = GetTaskRequest::new(task_id);
+    let response = client.get_task(request).unwrap();
+    assert_eq!(response.status, TaskStatus::Staged);
+
+    let request = RegisterExecutorEnclaveRequest::new("", measurement);
+    assert!(client.register_executor_enclave(request).is_err());
+}
+
+#[test_case]
+fn test_scheduled_task() {
+    let request = RegisterFunctionRequest::new()
+        .name("scheduled_function")
+        .executor_type(ExecutorType::Python)
+        .payload(b"def entrypoint(argv):\n\treturn".to_vec())
+        .arguments(vec!["arg"]);
+    let mut client = authorized_client("mock_user");
+    let function_id = client.register_function(request).unwrap().function_id;
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("arg" => "data"))
+        .executor(Executor::MesaPy);
+    let task_id = client.create_task(request).unwrap().task_id;
+
+    let hourly = TaskSchedule::parse("@every 1h").unwrap();
+    let request = CreateScheduledTaskRequest::new(task_id.clone(), hourly.clone());
+    let scheduled_task_id = client
+        .create_scheduled_task(request)
+        .unwrap()
+        .scheduled_task_id;
+    let request = ListUpcomingRunsRequest::new(scheduled_task_id.clone(), 3);
+    let response = client.list_upcoming_runs(request).unwrap();
+    assert_eq!(response.schedule, hourly);
+    assert_eq!(response.runs, 0);
+    assert_eq!(response.run_times.len(), 3);
+    assert_eq!(response.run_times[2] - response.run_times[0], 2 * 3600);
+
+    let request = PauseScheduledTaskRequest::new(scheduled_task_id.clone());
+    assert!(client.pause_scheduled_task(request).is_ok());
+    let request = ListUpcomingRunsRequest::new(scheduled_task_id.clone(), 3);
+    let response = client.list_upcoming_runs(request).unwrap();
+    assert!(response.paused);
+    assert!(response.run_times.is_empty());
+    let request = ResumeScheduledTaskRequest::new(scheduled_task_id.clone());
+    assert!(client.resume_scheduled_task(request).is_ok());
+
+    // Only the creator manages the scheduled task.
+    let mut other_client = authorized_client("mock_another_user");
+    let request = PauseScheduledTaskRequest::new(scheduled_task_id.clone());
+    assert!(other_client.pause_scheduled_task(request).is_err());
+    let request = ListUpcomingRunsRequest::new(scheduled_task_id, 3);
+    assert!(other_client.list_upcoming_runs(request).is_err());
+
+    // Schedules without runs, and templates without their data assigned
+    let never = TaskSchedule::parse("0 0 31 2 *").unwrap();
+    let request = CreateScheduledTaskRequest::new(task_id, never);
+    assert!(client.create_scheduled_task(request).is_err());
+    let task_id = client
+        .create_task(create_valid_task_request())
+        .unwrap()
+        .task_id;
+    let request = CreateScheduledTaskRequest::new(task_id, hourly);
+    assert!(client.create_scheduled_task(request).is_err());
+}
+
+#[test_case]
+fn test_list_functions_and_tasks() {
+    let mut client = authorized_client("mock_user_list");
+    let mut function_ids = Vec::new();
+    for _ in 0..3 {
+        let request = RegisterFunctionRequest::new()
+            .name("listed_function")
+            .executor_type(ExecutorType::Python)
+            .payload(b"def entrypoint(argv):\n\treturn 0".to_vec());
+        function_ids.push(client.register_function(request).unwrap().function_id);
+    }
+
+    // Pages of the functions of the owner, oldest first.
+    let mut listed = Vec::new();
+    let mut options = ListOptions::new().limit(2);
+    loop {
+        let request = ListFunctionsRequest::new(options.clone()).owner("mock_user_list");
+        let response = client.list_functions(request).unwrap();
+        assert!(response.functions.len() <= 2);
+        listed.extend(response.functions.into_iter().map(|f| f.function_id));
+        match response.next_cursor {
+            Some(cursor) => options = options.cursor(cursor),
+            None => break,
+        }
+    }
+    let mut expected = function_ids.clone();
+    expected.sort_by_key(|id| id.to_string());
+    listed.sort_by_key(|id| id.to_string());
+    assert_eq!(listed, expected);
+
+    // Private functions are not listed for other users.
+    let request = ListFunctionsRequest::new(ListOptions::new()).owner("mock_user_list");
+    let response = authorized_client("mock_user_c")
+        .list_functions(request)
+        .unwrap();
+    assert!(response.functions.is_empty());
+
+    let request = CreateTaskRequest::new()
+        .function_id(function_ids[0].clone())
+        .executor(Executor::MesaPy);
+    let task_id = client.create_task(request).unwrap().task_id;
+    let request = ListTasksRequest::new(ListOptions::new().descending(true))
+        .creator("mock_user_list")
+        .statuses(vec![TaskStatus::Created]);
+    let response = client.list_tasks(request).unwrap();
+    assert_eq!(response.tasks.len(), 1);
+    assert_eq!(response.tasks[0].task_id, task_id);
+
+    let request = ListTasksRequest::new(ListOptions::new())
+        .creator("mock_user_list")
+        .statuses(vec![TaskStatus::Running]);
+    assert!(client.list_tasks(request).unwrap().tasks.is_empty());
+
+    let request = ListTasksRequest::new(ListOptions::new().cursor("invalid"));
+    assert!(client.list_tasks(request).is_err());
+}
+
+#[test_case]
+fn test_register_webhook() {
+    let mut client = authorized_client("mock_user_webhook");
+    let url = Url::parse("http://localhost/hook").unwrap();
+    assert!(client
+        .register_webhook(RegisterWebhookRequest::new(url))
+        .is_err());
+
+    // Registering again replaces the webhook and its secret.
+    let url = Url::parse("https://localhost/hook").unwrap();
+    let first = client
+        .register_webhook(RegisterWebhookRequest::new(url.clone()))
+        .unwrap();
+    assert_eq!(first.secret.len(), WEBHOOK_SECRET_LEN);
+    let second = client
+        .register_webhook(RegisterWebhookRequest::new(url))
+        .unwrap();
+    assert_ne!(first.secret, second.secret);
+
+    assert!(client.delete_webhook(DeleteWebhookRequest::new()).is_ok());
+}
+
+#[test_case]
+fn test_check_consistency() {
+    // Only users with the manage_users permission check the records.
+    let mut client = authorized_client("mock_user");
+    assert!(client
+        .check_consistency(CheckConsistencyRequest::new(true))
+        .is_err());
+}
+
+#[test_case]
+fn test_list_nodes() {
+    // Only users with the manage_users permission list the nodes.
+    let mut client = authorized_client("mock_user");
+    assert!(client.list_nodes(ListNodesRequest::new()).is_err());
+}
+
+#[test_case]
+fn test_list_executors() {
+    // Any user lists the executors of the nodes.
+    let mut client = authorized_client("mock_user");
+    let response = client.list_executors(ListExecutorsRequest::new()).unwrap();
+    for node in response.nodes.iter() {
+        assert!(!node.worker_id.is_empty());
+    }
+}
+
+#[test_case]
+fn test_pipeline() {
+    let request = RegisterFunctionRequest::new()
+        .name("pipeline_function")
+        .executor_type(ExecutorType::Python)
+        .payload(b"def entrypoint(argv):\n\treturn".to_vec())
+        .inputs(vec![FunctionInput::new("input", "input_desc")])
+        .outputs(vec![FunctionOutput::new("output", "output_desc")]);
+    let mut client = authorized_client("mock_user");
+    let function_id = client.register_function(request).unwrap().function_id;
+    let mut create_task = |input_owners: Vec<&str>| {
+        let request = CreateTaskRequest::new()
+            .function_id(function_id.clone())
+            .executor(Executor::MesaPy)
+            .inputs_ownership(hashmap!("input" => input_owners))
+            .outputs_ownership(hashmap!("output" => vec!["mock_user"]));
+        client.create_task(request).unwrap().task_id
+    };
+    let upstream = create_task(vec!["mock_user"]);
+    let downstream = create_task(vec!["mock_user"]);
+    let shared = create_task(vec!["mock_user", "mock_another_user"]);
+    let link = |from: &ExternalID, to: &ExternalID| {
+        PipelineLink::new(from.clone(), "output", to.clone(), "input")
+    };
+
+    let request = CreatePipelineRequest::new(
+        vec![downstream.clone(), upstream.clone()],
+        vec![link(&upstream, &downstream)],
+    );
+    let pipeline_id = client.create_pipeline(request).unwrap().pipeline_id;
+    let request = GetPipelineRequest::new(pipeline_id.clone());
+    let response = client.get_pipeline(request).unwrap();
+    assert_eq!(response.creator, UserID::from("mock_user"));
+    assert_eq!(
+        response.task_ids,
+        vec![upstream.clone(), downstream.clone()]
+    );
+    assert_eq!(response.links, vec![link(&upstream, &downstream)]);
+    assert_eq!(response.status, PipelineStatus::Running);
+    assert!(response.failed_task_id.is_none());
+
+    // Only the creator gets the pipeline.
+    let mut other_client = authorized_client("mock_another_user");
+    let request = GetPipelineRequest::new(pipeline_id);
+    assert!(other_client.get_pipeline(request).is_err());
+
+    // Cycles, inputs with other owners than the output, and tasks of other
+    // users
+    let request = CreatePipelineRequest::new(
+        vec![upstream.clone(), downstream.clone()],
+        vec![link(&upstream, &downstream), link(&downstream, &upstream)],
+    );
+    assert!(client.create_pipeline(request).is_err());
+    let request = CreatePipelineRequest::new(
+        vec![upstream.clone(), shared.clone()],
+        vec![link(&upstream, &shared)],
+    );
+    assert!(client.create_pipeline(request).is_err());
+    let request = CreatePipelineRequest::new(
+        vec![upstream.clone(), downstream.clone()],
+        vec![link(&upstream, &downstream)],
+    );
+    assert!(other_client.create_pipeline(request).is_err());
+}
+
 fn create_valid_task_request() -> CreateTaskRequest {
     let function_id =
         ExternalID::try_from("function-00000000-0000-0000-0000-000000000001").unwrap();
@@ -225,6 +721,83 @@ fn test_create_task() {
     ));
     let response = client.create_task(request);
     assert!(response.is_err());
+
+    // No environment keys are allowed in the runtime config of the tests.
+    let request = create_valid_task_request().env(hashmap!("batch_size" => "64"));
+    let response = client.create_task(request);
+    assert!(response.is_err());
+}
+
+#[test_case]
+fn test_create_tasks() {
+    let mut client = authorized_client("mock_user");
+
+    let overrides = vec![
+        TaskOverrides::new(),
+        TaskOverrides::new().function_arguments(hashmap!("arg2" => "data3")),
+    ];
+    let request = CreateTasksRequest::new(create_valid_task_request(), overrides);
+    let response = client.create_tasks(request).unwrap();
+    assert_eq!(response.task_ids.len(), 2);
+
+    let request = GetTaskRequest::new(response.task_ids[1].clone());
+    let response = client.get_task(request).unwrap();
+    assert_eq!(
+        response.function_arguments.get("arg1").unwrap(),
+        &serde_json::json!("data1")
+    );
+    assert_eq!(
+        response.function_arguments.get("arg2").unwrap(),
+        &serde_json::json!("data3")
+    );
+
+    // No task is created if one of them is invalid.
+    let overrides = vec![
+        TaskOverrides::new(),
+        TaskOverrides::new().env(hashmap!("batch_size" => "64")),
+    ];
+    let request = CreateTasksRequest::new(create_valid_task_request(), overrides);
+    assert!(client.create_tasks(request).is_err());
+
+    let request = CreateTasksRequest::new(create_valid_task_request(), vec![]);
+    assert!(client.create_tasks(request).is_err());
+}
+
+#[test_case]
+fn test_invoke_tasks() {
+    let mut client = authorized_client("mock_user");
+    let mut client1 = authorized_client("mock_user1");
+
+    let overrides = vec![TaskOverrides::new(), TaskOverrides::new()];
+    let request = CreateTasksRequest::new(create_valid_task_request(), overrides);
+    let mut task_ids = client.create_tasks(request).unwrap().task_ids;
+    let request = create_valid_task_request();
+    task_ids.push(client1.create_task(request).unwrap().task_id);
+
+    // Tasks which are not approved, or created by other users, are reported
+    // as failures.
+    let request = InvokeTasksRequest::new(task_ids.clone());
+    let response = client.invoke_tasks(request).unwrap();
+    assert_eq!(response.failures.len(), 3);
+    for (failure, task_id) in response.failures.iter().zip(task_ids.iter()) {
+        assert_eq!(&failure.task_id, task_id);
+        assert!(!failure.reason.is_empty());
+    }
+
+    let request = InvokeTasksRequest::new(vec![]);
+    assert!(client.invoke_tasks(request).is_err());
+}
+
+#[test_case]
+fn test_review_output() {
+    let mut client = authorized_client("mock_user");
+
+    let request = create_valid_task_request();
+    let task_id = client.create_task(request).unwrap().task_id;
+
+    // The outputs of the task are not held for review.
+    let request = ReviewOutputRequest::new(task_id, true);
+    assert!(client.review_output(request).is_err());
 }
 
 #[test_case]
@@ -579,16 +1152,69 @@ fn test_invoke_task() {
     let response = client2.invoke_task(request);
     assert!(response.is_err());
 
+    // invalid retry policy
+    let request = InvokeTaskRequest::new(task_id.clone()).retry_policy(RetryPolicy::new(0));
+    let response = client.invoke_task(request);
+    assert!(response.is_err());
+
     // invoke task
-    let request = InvokeTaskRequest::new(task_id.clone());
+    let request = InvokeTaskRequest::new(task_id.clone()).retry_policy(RetryPolicy::new(3));
     client.invoke_task(request).unwrap();
 
     let request = GetTaskRequest::new(task_id);
     let response = client2.get_task(request).unwrap();
     assert_eq!(response.status, TaskStatus::Staged);
+    assert_eq!(response.attempts, 0);
+    assert!(response.last_error.is_none());
+    assert_eq!(response.preemptions, 0);
 
-    let request = PullTaskRequest {};
+    let request = PullTaskRequest::new("test_worker");
     let mut scheduler_client = get_scheduler_client();
     let response = scheduler_client.pull_task(request);
     assert!(response.is_ok());
 }
+
+#[test_case]
+fn test_disable_user_resources() {
+    let mut disabled_client = authorized_client("mock_disabled_user");
+    let request = create_valid_task_request();
+    let task_id = disabled_client.create_task(request).unwrap().task_id;
+    let request = RegisterFunctionRequest::new()
+        .name("mock_function")
+        .executor_type(ExecutorType::Python)
+        .payload(b"def entrypoint:\n\treturn".to_vec())
+        .public(true);
+    let function_id = disabled_client
+        .register_function(request)
+        .unwrap()
+        .function_id;
+
+    let mut client = authorized_client("mock_user");
+    let request = GetFunctionRequest::new(function_id.clone());
+    assert!(client.get_function(request).is_ok());
+
+    let request = DisableUserResourcesRequest::new("mock_disabled_user", false);
+    assert!(client.disable_user_resources(request).is_ok());
+
+    // Functions of the user can no longer be used.
+    let request = GetFunctionRequest::new(function_id.clone());
+    assert!(client.get_function(request).is_err());
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .executor(Executor::MesaPy);
+    assert!(client.create_task(request).is_err());
+
+    // Tasks created by the user cannot make progress, but can be read.
+    let mut client1 = authorized_client("mock_user1");
+    let url = Url::parse("input://path").unwrap();
+    let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::default());
+    let input_file_id = client1.register_input_file(request).unwrap().data_id;
+    let request = AssignDataRequest::new(
+        task_id.clone(),
+        hashmap!("input" => input_file_id),
+        hashmap!(),
+    );
+    assert!(client1.assign_data(request).is_err());
+    let request = GetTaskRequest::new(task_id);
+    assert!(client1.get_task(request).is_ok());
+}
@@ -41,13 +41,66 @@ fn test_pull_task() {
     let _enqueue_response = storage_client.enqueue(enqueue_request).unwrap();
 
     let mut client = get_scheduler_client();
-    let request = PullTaskRequest {};
+    let request = PullTaskRequest::new("test_worker");
     let response = client.pull_task(request);
     log::debug!("response: {:?}", response);
     assert!(response.is_ok());
     assert_eq!(response.unwrap().staged_task.function_id, function_id);
 }
 
+#[test_case]
+fn test_pull_task_by_priority() {
+    let mut storage_client = get_storage_client();
+    let mut task_ids = Vec::new();
+    for priority in &[TaskPriority::Batch, TaskPriority::Interactive] {
+        let staged_task = StagedTask::new()
+            .task_id(Uuid::new_v4())
+            .function_name("builtin-echo")
+            .executor(Executor::Builtin)
+            .priority(*priority);
+        task_ids.push(staged_task.task_id);
+        let enqueue_request = EnqueueRequest::new(
+            StagedTask::get_priority_queue_key(*priority).as_bytes(),
+            staged_task.to_vec().unwrap(),
+        );
+        let _enqueue_response = storage_client.enqueue(enqueue_request).unwrap();
+    }
+
+    // The interactive task is dispatched before the batch job queued earlier.
+    let mut client = get_scheduler_client();
+    let response = client
+        .pull_task(PullTaskRequest::new("test_worker"))
+        .unwrap();
+    assert_eq!(response.staged_task.task_id, task_ids[1]);
+    let response = client
+        .pull_task(PullTaskRequest::new("test_worker"))
+        .unwrap();
+    assert_eq!(response.staged_task.task_id, task_ids[0]);
+}
+
+#[test_case]
+fn test_pull_task_by_affinity() {
+    let staged_task = StagedTask::new()
+        .task_id(Uuid::new_v4())
+        .function_name("builtin-echo")
+        .executor(Executor::Builtin)
+        .affinity(TaskAffinity::new().affinity("zone=a"));
+    let mut storage_client = get_storage_client();
+    let enqueue_request = EnqueueRequest::new(
+        StagedTask::get_queue_key().as_bytes(),
+        staged_task.to_vec().unwrap(),
+    );
+    storage_client.enqueue(enqueue_request).unwrap();
+
+    // The task is left for a node with the label.
+    let mut client = get_scheduler_client();
+    let request = PullTaskRequest::new("test_worker").labels(vec!["zone=b".to_string()]);
+    assert!(client.pull_task(request).is_err());
+    let request = PullTaskRequest::new("test_worker").labels(vec!["zone=a".to_string()]);
+    let response = client.pull_task(request).unwrap();
+    assert_eq!(response.staged_task.task_id, staged_task.task_id);
+}
+
 #[test_case]
 fn test_update_task_status_result() {
     let task_id = Uuid::new_v4();
@@ -75,7 +128,7 @@ fn test_update_task_status_result() {
     let _put_response = storage_client.put(put_request).unwrap();
 
     let mut client = get_scheduler_client();
-    let request = PullTaskRequest {};
+    let request = PullTaskRequest::new("test_worker");
     let response = client.pull_task(request).unwrap();
     log::debug!("response: {:?}", response);
     let task_id = response.staged_task.task_id;
@@ -90,3 +143,75 @@ fn test_update_task_status_result() {
 
     assert!(response.is_ok());
 }
+
+#[test_case]
+fn test_heartbeat() {
+    let mut client = get_scheduler_client();
+    let sent_ms = platform::time::since_epoch().as_millis() as u64;
+    let request = HeartbeatRequest::new("test_worker", ClockOffset::default());
+    let response = client.heartbeat(request).unwrap();
+    let received_ms = platform::time::since_epoch().as_millis() as u64;
+
+    // The services run on the same host.
+    let offset = ClockOffset::estimate(sent_ms, response.scheduler_time_ms, received_ms);
+    assert_eq!(offset.min_skew().as_secs(), 0);
+    let request = HeartbeatRequest::new("test_worker", offset);
+    assert!(client.heartbeat(request).is_ok());
+
+    let response = client.health(HealthRequest::new()).unwrap();
+    let check = response
+        .checks
+        .iter()
+        .find(|check| check.name == "clock_skew")
+        .unwrap();
+    assert!(check.healthy);
+}
+
+#[test_case]
+fn test_register_node() {
+    let mut client = get_scheduler_client();
+    let capacity = NodeCapacity {
+        cpus: 4,
+        memory_mb: 8192,
+        slots: 1,
+    };
+    let request = RegisterNodeRequest::new("test_node_worker", capacity)
+        .labels(vec!["icelake".to_string()])
+        .executors(vec!["builtin".to_string()])
+        .builtin_functions(vec!["builtin-echo".to_string()]);
+    assert!(client.register_node(request).is_ok());
+
+    let mut storage_client = get_storage_client();
+    let response = storage_client
+        .get(GetRequest::new(EXECUTOR_NODES_KEY))
+        .unwrap();
+    let snapshot: ExecutorNodes = serde_json::from_slice(&response.value).unwrap();
+    let node = snapshot
+        .nodes
+        .iter()
+        .find(|node| node.worker_id == "test_node_worker")
+        .unwrap();
+    assert_eq!(node.capacity, capacity);
+    assert_eq!(node.running, 0);
+    assert_eq!(node.labels, vec!["icelake".to_string()]);
+    assert_eq!(node.executors, vec!["builtin".to_string()]);
+    assert_eq!(node.builtin_functions, vec!["builtin-echo".to_string()]);
+
+    // Nothing is dispatched to the node once its slot runs a task.
+    let staged_task = StagedTask::new()
+        .task_id(Uuid::new_v4())
+        .function_name("builtin-echo")
+        .executor(Executor::Builtin);
+    let enqueue_request = EnqueueRequest::new(
+        StagedTask::get_queue_key().as_bytes(),
+        staged_task.to_vec().unwrap(),
+    );
+    storage_client.enqueue(enqueue_request).unwrap();
+    let request = PullTaskRequest::new("test_node_worker");
+    assert!(client.pull_task(request).is_ok());
+    let request = PullTaskRequest::new("test_node_worker");
+    assert!(client.pull_task(request).is_err());
+
+    let request = RegisterNodeRequest::new("", capacity);
+    assert!(client.register_node(request).is_err());
+}
@@ -16,10 +16,12 @@
 // under the License.
 
 use std::prelude::v1::*;
+use std::time::Duration;
 use teaclave_config::RuntimeConfig;
 use teaclave_proto::teaclave_storage_service::*;
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_test_utils::test_case;
+use teaclave_types::{AuditEvent, AuditEventKind};
 
 fn get_client() -> TeaclaveStorageClient {
     let runtime_config = RuntimeConfig::from_toml("runtime.config.toml").expect("runtime");
@@ -123,3 +125,103 @@ fn test_dequeue_fail() {
     let response_result = client.dequeue(request);
     assert!(response_result.is_err());
 }
+
+#[test_case]
+fn test_audit_log() {
+    let mut client = get_client();
+    let event = AuditEvent::new(AuditEventKind::Login, "test", "test_audit_user", "password");
+    let request = AppendAuditEventRequest::new(event.clone());
+    let seq = client.append_audit_event(request).unwrap().seq;
+
+    let request = ExportAuditLogRequest::new(seq, 1);
+    let response = client.export_audit_log(request).unwrap();
+    assert!(response.log_len > seq);
+    assert_eq!(response.broken_seq, None);
+    assert_eq!(response.entries[0].event, event);
+
+    let request = PutRequest::new("audit-head", "");
+    assert!(client.put(request).is_err());
+}
+
+#[test_case]
+fn test_scan_prefix() {
+    let mut client = get_client();
+    for key in &["test_scan_key_1", "test_scan_key_2", "test_scan_key_3"] {
+        let request = PutRequest::new(*key, "test_scan_value");
+        assert!(client.put(request).is_ok());
+    }
+
+    let request = ScanPrefixRequest::new("test_scan_key_").limit(2);
+    let response = client.scan_prefix(request).unwrap();
+    assert_eq!(response.entries.len(), 2);
+    assert_eq!(response.entries[0].0, b"test_scan_key_1");
+    assert_eq!(response.entries[0].1, b"test_scan_value");
+
+    let request = ScanPrefixRequest::new("test_scan_key_").start(response.next_key.unwrap());
+    let response = client.scan_prefix(request).unwrap();
+    assert_eq!(response.entries.len(), 1);
+    assert_eq!(response.entries[0].0, b"test_scan_key_3");
+    assert_eq!(response.next_key, None);
+
+    let request = RangeRequest::new("test_scan_key_2").end("test_scan_key_3");
+    let response = client.range(request).unwrap();
+    assert_eq!(response.entries.len(), 1);
+    assert_eq!(response.entries[0].0, b"test_scan_key_2");
+}
+
+#[test_case]
+fn test_batch() {
+    let mut client = get_client();
+    let request = BatchRequest::new()
+        .put("test_batch_key", "test_batch_value")
+        .enqueue("test_batch_queue", "1");
+    assert!(client.batch(request).is_ok());
+    let request = GetRequest::new("test_batch_key");
+    assert_eq!(client.get(request).unwrap().value, b"test_batch_value");
+    let request = DequeueRequest::new("test_batch_queue");
+    assert_eq!(client.dequeue(request).unwrap().value, b"1");
+
+    let request = BatchRequest::new()
+        .delete("test_batch_key")
+        .put("expiry-key-test_batch_key", "");
+    assert!(client.batch(request).is_err());
+    let request = GetRequest::new("test_batch_key");
+    assert!(client.get(request).is_ok());
+}
+
+#[test_case]
+fn test_put_ttl() {
+    let mut client = get_client();
+    let request = PutRequest::new("test_ttl_key", "test_ttl_value").ttl(Duration::from_secs(60));
+    assert!(client.put(request).is_ok());
+    let request = GetRequest::new("test_ttl_key");
+    assert_eq!(client.get(request).unwrap().value, b"test_ttl_value");
+
+    let request = PutRequest::new("expiry-key-test_ttl_key", "");
+    assert!(client.put(request).is_err());
+}
+
+#[test_case]
+fn test_namespace() {
+    let mut client = get_client();
+    let request = PutRequest::new("test_ns_key", "test_ns_value").namespace("test_tenant");
+    assert!(client.put(request).is_ok());
+    let request = GetRequest::new("test_ns_key");
+    assert!(client.get(request).is_err());
+    let request = GetRequest::new("test_ns_key").namespace("test_tenant");
+    assert_eq!(client.get(request).unwrap().value, b"test_ns_value");
+
+    // Quotas and wipes of namespaces are only served for the management
+    // service.
+    let request = SetNamespaceQuotaRequest::new("test_tenant").max_keys(1);
+    assert!(client.set_namespace_quota(request).is_err());
+    let request = GetNamespaceUsageRequest::new("test_tenant");
+    let response = client.get_namespace_usage(request).unwrap();
+    assert_eq!(response.key_count, 1);
+    assert_eq!(response.max_keys, None);
+
+    let request = WipeNamespaceRequest::new("test_tenant");
+    assert!(client.wipe_namespace(request).is_err());
+    let request = GetRequest::new("test_ns_key").namespace("test_tenant");
+    assert!(client.get(request).is_ok());
+}
@@ -21,9 +21,14 @@ use serde::{Deserialize, Serialize};
 use std::io;
 use std::prelude::v1::*;
 use std::untrusted::fs;
+use teaclave_config::{RateLimitConfig, TlsConfig};
+use teaclave_rpc::blob::*;
 use teaclave_rpc::channel::*;
 use teaclave_rpc::config::*;
 use teaclave_rpc::endpoint::*;
+use teaclave_rpc::gate::*;
+use teaclave_rpc::interceptor::*;
+use teaclave_rpc::rate_limit::*;
 use teaclave_rpc::server::*;
 use teaclave_rpc::*;
 use teaclave_types::TeaclaveServiceResponseError;
@@ -38,6 +43,16 @@ enum EchoRequest {
     Say(SayRequest),
 }
 
+impl ServiceRequest for EchoRequest {
+    const METHOD_NAMES: &'static [&'static str] = &["Say"];
+
+    fn method_name(&self) -> &'static str {
+        match self {
+            EchoRequest::Say(_) => "Say",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct SayRequest {
     message: String,
@@ -70,6 +85,55 @@ impl TeaclaveService<EchoRequest, EchoResponse> for EchoService {
     }
 }
 
+// Rejects requests without the expected token, and counts the responses.
+struct TokenInterceptor {
+    responses: std::sync::atomic::AtomicUsize,
+}
+
+impl Interceptor for TokenInterceptor {
+    fn on_request(
+        &self,
+        metadata: &mut std::collections::HashMap<String, String>,
+        _method: &str,
+        _message: &dyn std::fmt::Debug,
+    ) -> TeaclaveServiceResponseResult<()> {
+        match metadata.get("token") {
+            Some(token) if token == "test_token" => Ok(()),
+            _ => Err(TeaclaveServiceResponseError::RequestError(
+                "invalid token".to_string(),
+            )),
+        }
+    }
+
+    fn on_response(
+        &self,
+        _metadata: &std::collections::HashMap<String, String>,
+        _response: Result<&dyn std::fmt::Debug, &TeaclaveServiceResponseError>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        self.responses
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+// Records the metadata of the last request.
+#[derive(Default)]
+struct MetadataRecorder {
+    metadata: std::sync::SgxMutex<std::collections::HashMap<String, String>>,
+}
+
+impl Interceptor for MetadataRecorder {
+    fn on_request(
+        &self,
+        metadata: &mut std::collections::HashMap<String, String>,
+        _method: &str,
+        _message: &dyn std::fmt::Debug,
+    ) -> TeaclaveServiceResponseResult<()> {
+        *self.metadata.lock().unwrap() = metadata.clone();
+        Ok(())
+    }
+}
+
 struct EchoClient {
     channel: SgxTrustedTlsChannel<EchoRequest, EchoResponse>,
 }
@@ -104,7 +168,21 @@ pub fn run_tests() -> bool {
 
     start_echo_service();
 
-    run_tests!(echo_success)
+    run_tests!(
+        echo_success,
+        echo_pooled,
+        echo_timeout,
+        echo_compressed,
+        echo_tls13_only,
+        echo_intercepted,
+        echo_traced,
+        echo_message_too_large,
+        echo_gated,
+        echo_unknown_methods,
+        echo_rate_limited,
+        blob_chunks,
+        blob_resumed,
+    )
 }
 
 fn start_echo_service() {
@@ -123,7 +201,71 @@ fn start_echo_service() {
         let config = SgxTrustedTlsServerConfig::new()
             .server_cert(&cert[0].as_ref(), &private_key.0)
             .unwrap();
-        let mut server = SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, config);
+        let mut server =
+            SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, config).compression(true);
+        server.start(EchoService).unwrap();
+    });
+    thread::spawn(move || {
+        let cert = pemfile::certs(&mut io::BufReader::new(
+            fs::File::open(END_FULLCHAIN).unwrap(),
+        ))
+        .unwrap();
+        let private_key =
+            &pemfile::pkcs8_private_keys(&mut io::BufReader::new(fs::File::open(END_KEY).unwrap()))
+                .unwrap()[0];
+        let addr = "127.0.0.1:12346".parse().unwrap();
+        let config = SgxTrustedTlsServerConfig::new()
+            .server_cert(&cert[0].as_ref(), &private_key.0)
+            .unwrap();
+        let token_interceptor = TokenInterceptor {
+            responses: std::sync::atomic::AtomicUsize::new(0),
+        };
+        let mut server = SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, config)
+            .max_message_len(64 * 1024)
+            .chunk_len(1024)
+            .interceptor(std::sync::Arc::new(LoggingInterceptor::new("echo")))
+            .interceptor(std::sync::Arc::new(token_interceptor));
+        server.start(EchoService).unwrap();
+    });
+    thread::spawn(move || {
+        let cert = pemfile::certs(&mut io::BufReader::new(
+            fs::File::open(END_FULLCHAIN).unwrap(),
+        ))
+        .unwrap();
+        let private_key =
+            &pemfile::pkcs8_private_keys(&mut io::BufReader::new(fs::File::open(END_KEY).unwrap()))
+                .unwrap()[0];
+        let addr = "127.0.0.1:12347".parse().unwrap();
+        let config = SgxTrustedTlsServerConfig::new()
+            .server_cert(&cert[0].as_ref(), &private_key.0)
+            .unwrap();
+        let enclaves: Vec<(&str, Option<&str>)> = vec![];
+        let gate = AttestationGate::new(&["Say"], enclaves, &[]).unwrap();
+        let mut server = SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, config)
+            .attestation_gate(gate);
+        server.start(EchoService).unwrap();
+    });
+    thread::spawn(move || {
+        let cert = pemfile::certs(&mut io::BufReader::new(
+            fs::File::open(END_FULLCHAIN).unwrap(),
+        ))
+        .unwrap();
+        let private_key =
+            &pemfile::pkcs8_private_keys(&mut io::BufReader::new(fs::File::open(END_KEY).unwrap()))
+                .unwrap()[0];
+        let addr = "127.0.0.1:12348".parse().unwrap();
+        let config = SgxTrustedTlsServerConfig::new()
+            .server_cert(&cert[0].as_ref(), &private_key.0)
+            .unwrap();
+        let rate_limit_config = RateLimitConfig {
+            ip_requests_per_sec: 0.001,
+            ip_burst: 2,
+            ..Default::default()
+        };
+        let mut server = SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, config)
+            .interceptor(std::sync::Arc::new(RateLimitInterceptor::from_config(
+                &rate_limit_config,
+            )));
         server.start(EchoService).unwrap();
     });
     thread::sleep(Duration::from_secs(3));
@@ -143,3 +285,351 @@ fn echo_success() {
     assert!(response_result.is_ok());
     assert!(response_result.unwrap().message == "Hello, World!");
 }
+
+fn echo_pooled() {
+    use super::*;
+
+    let endpoint = Endpoint::new("localhost:12345").pool(ChannelPoolConfig::new().pool_size(1));
+    let mut clients = (0..2)
+        .map(|_| EchoClient::new(endpoint.connect().unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    for client in clients.iter_mut() {
+        let request = SayRequest {
+            message: "Hello, World!".to_string(),
+        };
+        let response_result = client.say(request);
+        debug!("{:?}", response_result);
+
+        assert!(response_result.is_ok());
+        assert!(response_result.unwrap().message == "Hello, World!");
+    }
+}
+
+fn echo_timeout() {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use std::untrusted::time::SystemTimeEx;
+
+    let channel = Endpoint::new("localhost:12345")
+        .timeout(Duration::from_secs(10))
+        .connect()
+        .unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    let response_result = client.say(request);
+    debug!("{:?}", response_result);
+
+    assert!(response_result.is_ok());
+    assert!(response_result.unwrap().message == "Hello, World!");
+
+    // Calls made while handling a request whose budget has run out fail
+    // without being sent.
+    let _deadline = teaclave_rpc::deadline::enter(Some(SystemTime::now()));
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    match client.say(request) {
+        Err(TeaclaveServiceResponseError::DeadlineExceeded) => (),
+        _ => panic!("wrong error type"),
+    }
+}
+
+fn echo_compressed() {
+    use super::*;
+
+    let channel = Endpoint::new("localhost:12345")
+        .compression(true)
+        .connect()
+        .unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    // The first request announces compression support, the response and the
+    // following requests on the connection are compressed.
+    let message = "Hello, World!".repeat(10_000);
+    for _ in 0..2 {
+        let request = SayRequest {
+            message: message.clone(),
+        };
+        let response_result = client.say(request);
+
+        assert!(response_result.is_ok());
+        assert!(response_result.unwrap().message == message);
+    }
+}
+
+fn echo_tls13_only() {
+    use super::*;
+
+    let tls_config = TlsConfig {
+        cipher_suites: vec!["TLS13_AES_256_GCM_SHA384".to_string()],
+        tls13_only: true,
+        ..TlsConfig::default()
+    };
+    let config = SgxTrustedTlsClientConfig::new()
+        .tls_policy(TlsPolicy::new(tls_config))
+        .unwrap();
+    let channel = Endpoint::new("localhost:12345")
+        .config(config)
+        .connect()
+        .unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    let response_result = client.say(request);
+    debug!("{:?}", response_result);
+
+    assert!(response_result.is_ok());
+    assert!(response_result.unwrap().message == "Hello, World!");
+
+    // TLS 1.3 cannot be negotiated with TLS 1.2 cipher suites only.
+    let tls_config = TlsConfig {
+        cipher_suites: vec!["TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384".to_string()],
+        tls13_only: true,
+        ..TlsConfig::default()
+    };
+    let config = SgxTrustedTlsClientConfig::new().tls_policy(TlsPolicy::new(tls_config));
+    assert!(config.is_err());
+}
+
+fn echo_intercepted() {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    // Requests without the token are rejected by the server.
+    let channel = Endpoint::new("localhost:12346").connect().unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    assert!(client.say(request).is_err());
+
+    let mut metadata = HashMap::new();
+    metadata.insert("token".to_string(), "test_token".to_string());
+    let channel = Endpoint::new("localhost:12346")
+        .interceptor(Arc::new(MetadataInterceptor::new(metadata)))
+        .connect()
+        .unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    let response_result = client.say(request);
+    debug!("{:?}", response_result);
+
+    assert!(response_result.is_ok());
+    assert!(response_result.unwrap().message == "Hello, World!");
+
+    // Interceptors of channels run before the request is sent.
+    let interceptor = Arc::new(TokenInterceptor {
+        responses: std::sync::atomic::AtomicUsize::new(0),
+    });
+    let channel = Endpoint::new("localhost:12345")
+        .connect()
+        .unwrap()
+        .interceptor(interceptor.clone());
+    let mut client = EchoClient::new(channel).unwrap();
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    assert!(client.say(request).is_err());
+    assert_eq!(
+        interceptor
+            .responses
+            .load(std::sync::atomic::Ordering::SeqCst),
+        0
+    );
+}
+
+fn echo_traced() {
+    use super::*;
+    use std::sync::Arc;
+    use teaclave_rpc::trace::{SPAN_ID_METADATA_KEY, TRACE_ID_METADATA_KEY};
+    use teaclave_types::TraceContext;
+
+    let recorder = Arc::new(MetadataRecorder::default());
+    let channel = Endpoint::new("localhost:12345")
+        .interceptor(recorder.clone())
+        .connect()
+        .unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+
+    // Calls made in a trace continue it in a new span.
+    let context = TraceContext::new();
+    {
+        let _trace = teaclave_rpc::trace::enter(context.clone());
+        let request = SayRequest {
+            message: "Hello, World!".to_string(),
+        };
+        assert!(client.say(request).is_ok());
+    }
+    let metadata = recorder.metadata.lock().unwrap().clone();
+    assert_eq!(metadata[TRACE_ID_METADATA_KEY], context.trace_id);
+    assert_ne!(metadata[SPAN_ID_METADATA_KEY], context.span_id);
+
+    // Other calls start a new trace.
+    assert!(teaclave_rpc::trace::current().is_none());
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    assert!(client.say(request).is_ok());
+    let metadata = recorder.metadata.lock().unwrap().clone();
+    assert_ne!(metadata[TRACE_ID_METADATA_KEY], context.trace_id);
+}
+
+fn echo_message_too_large() {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    let mut metadata = HashMap::new();
+    metadata.insert("token".to_string(), "test_token".to_string());
+    let mut channel: SgxTrustedTlsChannel<EchoRequest, EchoResponse> =
+        Endpoint::new("localhost:12346")
+            .interceptor(Arc::new(MetadataInterceptor::new(metadata)))
+            .connect()
+            .unwrap();
+
+    // The server drops the frames of the request and replies with the error,
+    // and the following requests on the connection are still served.
+    let request = EchoRequest::Say(SayRequest {
+        message: "Hello, World!".repeat(10_000),
+    });
+    let response = channel.invoke(Request::new(request));
+    match response {
+        Err(TeaclaveServiceResponseError::MessageTooLarge { max_len, .. }) => {
+            assert_eq!(max_len, 64 * 1024)
+        }
+        _ => panic!("wrong error type"),
+    }
+    let request = EchoRequest::Say(SayRequest {
+        message: "Hello, World!".to_string(),
+    });
+    assert!(channel.invoke(Request::new(request)).is_ok());
+
+    // Requests exceeding the max length of the channel are not sent.
+    let mut channel: SgxTrustedTlsChannel<EchoRequest, EchoResponse> =
+        Endpoint::new("localhost:12345")
+            .max_message_len(1024)
+            .connect()
+            .unwrap();
+    let request = EchoRequest::Say(SayRequest {
+        message: "Hello, World!".repeat(100),
+    });
+    let response = channel.invoke(Request::new(request));
+    match response {
+        Err(TeaclaveServiceResponseError::MessageTooLarge { max_len, .. }) => {
+            assert_eq!(max_len, 1024)
+        }
+        _ => panic!("wrong error type"),
+    }
+}
+
+fn echo_gated() {
+    use super::*;
+
+    // Gated requests of peers without an attested client certificate are
+    // rejected before reaching the service, and the connection is kept.
+    let channel = Endpoint::new("localhost:12347").connect().unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    for _ in 0..2 {
+        let request = SayRequest {
+            message: "Hello, World!".to_string(),
+        };
+        match client.say(request) {
+            Err(TeaclaveServiceResponseError::RequestError(e)) => {
+                assert_eq!(e, "permission denied")
+            }
+            _ => panic!("wrong error type"),
+        }
+    }
+}
+
+fn echo_unknown_methods() {
+    use super::*;
+
+    assert!(check_method_names::<EchoRequest>(vec!["Say"]).is_ok());
+    assert!(check_method_names::<EchoRequest>(vec!["Say", "Shout"]).is_err());
+    // Pooled channels fail for idempotent requests the service does not have.
+    let endpoint = Endpoint::new("localhost:12345")
+        .pool(ChannelPoolConfig::new().idempotent_requests(&["Shout"]));
+    assert!(endpoint.connect::<EchoRequest, EchoResponse>().is_err());
+}
+
+fn echo_rate_limited() {
+    use super::*;
+    use std::time::Duration;
+
+    // Requests over the burst are rejected, also on new connections from the
+    // same address.
+    let channel = Endpoint::new("localhost:12348").connect().unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    for _ in 0..2 {
+        let request = SayRequest {
+            message: "Hello, World!".to_string(),
+        };
+        assert!(client.say(request).is_ok());
+    }
+    let channel = Endpoint::new("localhost:12348").connect().unwrap();
+    let mut client = EchoClient::new(channel).unwrap();
+    let request = SayRequest {
+        message: "Hello, World!".to_string(),
+    };
+    match client.say(request) {
+        Err(TeaclaveServiceResponseError::ResourceExhausted(_)) => (),
+        _ => panic!("wrong error type"),
+    }
+
+    let mut bucket = TokenBucket::new(1, Duration::from_secs(10));
+    assert!(bucket.take(2.0, 1, Duration::from_secs(10)));
+    assert!(!bucket.take(2.0, 1, Duration::from_millis(10_100)));
+    assert!(bucket.take(2.0, 1, Duration::from_millis(10_600)));
+    // A clock going backwards does not refill the bucket.
+    assert!(!bucket.take(2.0, 1, Duration::from_secs(5)));
+}
+
+fn blob_chunks() {
+    let data = b"Hello, Teaclave!";
+    let source = BlobSource::new(data);
+    assert_eq!(source.len(), 16);
+    assert_eq!(source.hash(), blob_hash(data).as_slice());
+    assert_eq!(source.chunk(7, 8).unwrap(), b"Teaclave");
+    assert_eq!(source.chunk(15, 100).unwrap(), b"!");
+    assert_eq!(source.chunk(0, 0).unwrap(), data);
+    assert!(source.chunk(16, 0).unwrap().is_empty());
+    assert!(source.chunk(17, 0).is_err());
+
+    let parts: Vec<(u32, &[u8])> = source.parts(6).unwrap().collect();
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts[2], (2, &b"ave!"[..]));
+    for (_, part) in &parts {
+        assert!(check_chunk(part, &blob_hash(part)).is_ok());
+    }
+    assert!(check_chunk(parts[0].1, &blob_hash(parts[1].1)).is_err());
+    assert!(source.parts(0).is_err());
+    assert!(source.parts(MAX_CHUNK_LEN + 1).is_err());
+}
+
+fn blob_resumed() {
+    let data = b"Hello, Teaclave!";
+    let source = BlobSource::new(data);
+    let mut sink = BlobSink::new(source.len(), source.hash().to_vec()).unwrap();
+    sink.write(0, source.chunk(0, 6).unwrap()).unwrap();
+    // Chunks must continue from the offset received so far.
+    assert!(sink.write(0, source.chunk(0, 6).unwrap()).is_err());
+    assert!(sink.write(12, source.chunk(12, 6).unwrap()).is_err());
+    assert!(sink.write(sink.offset(), b"").is_err());
+    while !sink.is_complete() {
+        let chunk = source.chunk(sink.offset(), 6).unwrap();
+        sink.write(sink.offset(), chunk).unwrap();
+    }
+    assert!(sink.write(sink.offset(), b"!").is_err());
+    assert_eq!(sink.finish().unwrap(), data);
+
+    let mut sink = BlobSink::new(source.len(), blob_hash(b"Hello, World!!!!")).unwrap();
+    sink.write(0, data).unwrap();
+    assert!(sink.finish().is_err());
+    assert!(BlobSink::new(16, Vec::new()).is_err());
+}
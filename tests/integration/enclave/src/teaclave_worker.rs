@@ -16,6 +16,8 @@
 // under the License.
 
 use std::prelude::v1::*;
+use std::sync::Arc;
+use std::time::Duration;
 
 use serde_json::json;
 use teaclave_crypto::TeaclaveFile128Key;
@@ -23,7 +25,7 @@ use teaclave_types::{
     hashmap, read_all_bytes, Executor, ExecutorType, FileAuthTag, FunctionArguments,
     StagedFileInfo, StagedFiles, StagedFunction,
 };
-use teaclave_worker::Worker;
+use teaclave_worker::{BuiltinFunctionExecutor, Worker};
 
 fn test_start_worker() {
     let arguments = FunctionArguments::from_json(json!({
@@ -75,8 +77,36 @@ fn test_start_worker() {
     assert_eq!(&result[..], &expected[..]);
 }
 
+fn test_abandoned_threads() {
+    let mut functions = BuiltinFunctionExecutor::new();
+    functions.register("sleep", |_, _| {
+        std::thread::sleep(Duration::from_millis(500));
+        Ok(String::new())
+    });
+    let mut worker = Worker::default();
+    worker.register_builtin_functions(functions);
+
+    let staged_function = StagedFunction::new()
+        .executor_type(ExecutorType::Builtin)
+        .executor(Executor::Builtin)
+        .name("sleep")
+        .runtime_name("default")
+        .time_limit(Some(Duration::from_millis(10)));
+    let guard = Arc::new(());
+    let result = worker.invoke_function_holding(staged_function, guard.clone());
+    assert!(result.is_err());
+    assert_eq!(worker.abandoned_threads(), 1);
+    assert_eq!(Arc::strong_count(&guard), 2);
+
+    // Uncounted and the guard released once the function returns
+    std::thread::sleep(Duration::from_secs(1));
+    assert_eq!(worker.abandoned_threads(), 0);
+    assert_eq!(Arc::strong_count(&guard), 1);
+    assert!(!worker.is_exhausted());
+}
+
 pub fn run_tests() -> bool {
     use teaclave_test_utils::*;
 
-    run_tests!(test_start_worker)
+    run_tests!(test_start_worker, test_abandoned_threads)
 }
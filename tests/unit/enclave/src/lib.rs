@@ -36,6 +36,7 @@ fn handle_run_test(_: &RunTestInput) -> TeeServiceResult<RunTestOutput> {
         teaclave_management_service_enclave::tests::run_tests(),
         teaclave_storage_service_enclave::tests::run_tests(),
         teaclave_access_control_service_enclave::tests::run_tests(),
+        teaclave_attestation_verifier_service_enclave::tests::run_tests(),
         teaclave_execution_service_enclave::tests::run_tests(),
         teaclave_authentication_service_enclave::tests::run_tests(),
         teaclave_worker::tests::run_tests(),
@@ -15,26 +15,168 @@
 // specific language governing permissions and limitations
 // under the License.
 
-#[cfg(feature = "mesalock_sgx")]
+#[cfg(feature = "sgx")]
 use std::prelude::v1::*;
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 
-use anyhow::{bail, ensure, Result};
-use serde::{Deserialize, Deserializer};
+use anyhow::{bail, ensure, Context, Error, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-pub type SgxMeasurement = [u8; sgx_types::SGX_HASH_SIZE];
+/// Defines a newtype of a fixed-size byte array, which is displayed, parsed and
+/// serialized as a hex string, and compared in constant time.
+macro_rules! impl_byte_array_newtype {
+    ($(#[$attr:meta])* $name:ident, $len:expr) => {
+        $(#[$attr])*
+        #[derive(Copy, Clone)]
+        pub struct $name([u8; $len]);
 
-#[derive(Debug, Deserialize, Copy, Clone, Eq, PartialEq)]
+        impl $name {
+            pub const LENGTH: usize = $len;
+
+            pub fn new(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+
+            pub fn to_hex(&self) -> String {
+                hex::encode(&self.0[..])
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self([0u8; $len])
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = Error;
+
+            fn try_from(bytes: &[u8]) -> Result<Self> {
+                ensure!(
+                    bytes.len() == $len,
+                    "Invalid length of {}: {}",
+                    stringify!($name),
+                    bytes.len()
+                );
+                let mut array = [0u8; $len];
+                array.copy_from_slice(bytes);
+                Ok(Self(array))
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                let bytes = hex::decode(s)
+                    .with_context(|| format!("Illegal {} provided", stringify!($name)))?;
+                Self::try_from(bytes.as_slice())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.to_hex())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.to_hex())
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                ring::constant_time::verify_slices_are_equal(&self.0, &other.0).is_ok()
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialEq<[u8]> for $name {
+            fn eq(&self, other: &[u8]) -> bool {
+                ring::constant_time::verify_slices_are_equal(&self.0, other).is_ok()
+            }
+        }
+
+        impl PartialEq<[u8; $len]> for $name {
+            fn eq(&self, other: &[u8; $len]) -> bool {
+                ring::constant_time::verify_slices_are_equal(&self.0, &other[..]).is_ok()
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(&self.to_hex())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                use serde::de::Error;
+                let s = String::deserialize(deserializer)?;
+                Self::from_str(&s).map_err(|e| D::Error::custom(format!("{:?}", e)))
+            }
+        }
+    };
+}
+
+impl_byte_array_newtype!(
+    /// Measurement of the enclave (`MRENCLAVE`).
+    MrEnclave,
+    sgx_types::SGX_HASH_SIZE
+);
+impl_byte_array_newtype!(
+    /// Measurement of the public key that signed the enclave (`MRSIGNER`).
+    MrSigner,
+    sgx_types::SGX_HASH_SIZE
+);
+impl_byte_array_newtype!(
+    /// Security version number of the CPU.
+    CpuSvn,
+    sgx_types::SGX_CPUSVN_SIZE
+);
+impl_byte_array_newtype!(
+    /// User data bound to the enclave report.
+    ReportData,
+    sgx_types::SGX_REPORT_DATA_SIZE
+);
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq)]
 pub struct EnclaveMeasurement {
-    #[serde(deserialize_with = "from_hex")]
-    pub mr_signer: SgxMeasurement,
-    #[serde(deserialize_with = "from_hex")]
-    pub mr_enclave: SgxMeasurement,
+    pub mr_signer: MrSigner,
+    pub mr_enclave: MrEnclave,
 }
 
 impl EnclaveMeasurement {
-    pub fn new(mr_enclave: SgxMeasurement, mr_signer: SgxMeasurement) -> Self {
+    pub fn new(mr_enclave: MrEnclave, mr_signer: MrSigner) -> Self {
         Self {
             mr_enclave,
             mr_signer,
@@ -42,21 +184,6 @@ impl EnclaveMeasurement {
     }
 }
 
-/// Deserializes a hex string to a `SgxMeasurement` (i.e., [0; 32]).
-pub fn from_hex<'de, D>(deserializer: D) -> Result<SgxMeasurement, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    use serde::de::Error;
-    String::deserialize(deserializer).and_then(|string| {
-        let v = hex::decode(&string).map_err(|_| Error::custom("ParseError"))?;
-        let mut array = [0; sgx_types::SGX_HASH_SIZE];
-        let bytes = &v[..array.len()]; // panics if not enough data
-        array.copy_from_slice(bytes);
-        Ok(array)
-    })
-}
-
 #[derive(Clone)]
 pub struct EnclaveAttr {
     pub measurement: EnclaveMeasurement,
@@ -145,3 +272,46 @@ impl EnclaveInfo {
         }
     }
 }
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_byte_array_newtype_hex,
+            test_enclave_measurement_from_toml
+        )
+    }
+
+    fn test_byte_array_newtype_hex() {
+        let hex = "83d719e77deaca1470f6baf62a4d774303c899db69020f9c70ee1dfc08c7ce9e";
+        let mr_signer = MrSigner::from_str(hex).unwrap();
+        assert_eq!(mr_signer.to_string(), hex);
+        assert_eq!(mr_signer, MrSigner::try_from(mr_signer.as_bytes()).unwrap());
+        assert!(MrSigner::from_str(&hex[..62]).is_err());
+        assert!(CpuSvn::from_str(hex).is_err());
+
+        let json = serde_json::to_string(&mr_signer).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex));
+        let deserialized: MrSigner = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, mr_signer);
+    }
+
+    fn test_enclave_measurement_from_toml() {
+        let enclave_info = r#"
+            [teaclave_frontend_service]
+            mr_enclave = "eadeb5537962d2451a8619fb6a4b10b72f56479e0b7db0bb9c3f5edc143ca6eb"
+            mr_signer  = "83d719e77deaca1470f6baf62a4d774303c899db69020f9c70ee1dfc08c7ce9e"
+        "#;
+        let enclave_info = EnclaveInfo::from_bytes(enclave_info.as_bytes());
+        let attr = enclave_info
+            .get_enclave_attr("teaclave_frontend_service")
+            .unwrap();
+        assert_eq!(
+            attr.measurement.mr_enclave.to_hex(),
+            "eadeb5537962d2451a8619fb6a4b10b72f56479e0b7db0bb9c3f5edc143ca6eb"
+        );
+    }
+}
@@ -0,0 +1,314 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Append-only audit log of security events. Each entry commits to the hash
+//! of the previous one, and its own hash is sealed with an HMAC keyed by the
+//! storage service enclave, so entries cannot be modified, dropped or
+//! reordered outside the enclave without breaking the chain. The links can be
+//! checked by anyone; the seals only by the enclave holding the key.
+
+#[cfg(feature = "sgx")]
+use std::prelude::v1::*;
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::platform;
+use anyhow::{ensure, Context, Error, Result};
+use ring::{digest, hmac};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Domain separation of the hashes of entries.
+const ENTRY_CONTEXT: &[u8] = b"teaclave-audit-log-v1";
+
+impl_byte_array_newtype!(
+    /// SHA-256 hash, or HMAC-SHA256 seal, of an audit log entry.
+    AuditHash,
+    digest::SHA256_OUTPUT_LEN
+);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    Login,
+    LoginFailed,
+    TokenIssued,
+    PolicyDenied,
+    FunctionRegistered,
+    FunctionRolledBack,
+    TaskInvoked,
+    PolicyUpdated,
+    OwnershipTransferred,
+    QuotaSet,
+    ConsistencyRepaired,
+    OutputReviewed,
+    ExecutorEnclaveRegistered,
+    ImpersonationGranted,
+    Impersonated,
+    ImpersonationDenied,
+    ReadOnlyModeEntered,
+    ReadOnlyModeExited,
+    ExternalUserCreated,
+    UserDisabled,
+    UserDeleted,
+    PasswordReset,
+    RoleCreated,
+    RolesAssigned,
+    ApiKeyRevoked,
+    TokenRevoked,
+    TotpEnrolled,
+    AuditLogExported,
+}
+
+impl fmt::Display for AuditEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            AuditEventKind::Login => "login",
+            AuditEventKind::LoginFailed => "login_failed",
+            AuditEventKind::TokenIssued => "token_issued",
+            AuditEventKind::PolicyDenied => "policy_denied",
+            AuditEventKind::FunctionRegistered => "function_registered",
+            AuditEventKind::FunctionRolledBack => "function_rolled_back",
+            AuditEventKind::TaskInvoked => "task_invoked",
+            AuditEventKind::PolicyUpdated => "policy_updated",
+            AuditEventKind::OwnershipTransferred => "ownership_transferred",
+            AuditEventKind::QuotaSet => "quota_set",
+            AuditEventKind::ConsistencyRepaired => "consistency_repaired",
+            AuditEventKind::OutputReviewed => "output_reviewed",
+            AuditEventKind::ExecutorEnclaveRegistered => "executor_enclave_registered",
+            AuditEventKind::ImpersonationGranted => "impersonation_granted",
+            AuditEventKind::Impersonated => "impersonated",
+            AuditEventKind::ImpersonationDenied => "impersonation_denied",
+            AuditEventKind::ReadOnlyModeEntered => "read_only_mode_entered",
+            AuditEventKind::ReadOnlyModeExited => "read_only_mode_exited",
+            AuditEventKind::ExternalUserCreated => "external_user_created",
+            AuditEventKind::UserDisabled => "user_disabled",
+            AuditEventKind::UserDeleted => "user_deleted",
+            AuditEventKind::PasswordReset => "password_reset",
+            AuditEventKind::RoleCreated => "role_created",
+            AuditEventKind::RolesAssigned => "roles_assigned",
+            AuditEventKind::ApiKeyRevoked => "api_key_revoked",
+            AuditEventKind::TokenRevoked => "token_revoked",
+            AuditEventKind::TotpEnrolled => "totp_enrolled",
+            AuditEventKind::AuditLogExported => "audit_log_exported",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// A security event recorded by a service. The detail must not contain
+/// credentials.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    pub service: String,
+    // Empty if the user is unknown, e.g., of a rejected ID token.
+    pub user_id: String,
+    pub detail: String,
+    // Seconds since the Unix epoch, from the untrusted clock of the host.
+    pub timestamp: u64,
+}
+
+impl AuditEvent {
+    pub fn new(
+        kind: AuditEventKind,
+        service: impl Into<String>,
+        user_id: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            service: service.into(),
+            user_id: user_id.into(),
+            detail: detail.into(),
+            timestamp: platform::time::since_epoch().as_secs(),
+        }
+    }
+}
+
+impl fmt::Display for AuditEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} of user {}: {}",
+            self.service, self.kind, self.user_id, self.detail
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditLogEntry {
+    pub seq: u64,
+    pub event: AuditEvent,
+    // Zero for the first entry.
+    pub prev_hash: AuditHash,
+    pub hash: AuditHash,
+    pub seal: AuditHash,
+}
+
+impl AuditLogEntry {
+    /// Appends the event to the chain after `prev`, or starts the chain.
+    pub fn new(prev: Option<&AuditLogEntry>, event: AuditEvent, seal_key: &[u8]) -> Result<Self> {
+        let (seq, prev_hash) = match prev {
+            Some(prev) => (prev.seq + 1, prev.hash),
+            None => (0, AuditHash::default()),
+        };
+        let hash = Self::chain_hash(seq, &prev_hash, &event)?;
+        let seal = Self::seal_of(&hash, seal_key);
+        Ok(Self {
+            seq,
+            event,
+            prev_hash,
+            hash,
+            seal,
+        })
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).context("Invalid audit log entry")
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Checks the hash of the entry and its link to the previous entry, or
+    /// that it starts the chain.
+    pub fn verify_link(&self, prev: Option<&AuditLogEntry>) -> Result<()> {
+        match prev {
+            Some(prev) => ensure!(
+                self.seq == prev.seq + 1 && self.prev_hash == prev.hash,
+                "Audit log entry {} not linked to the previous entry",
+                self.seq
+            ),
+            None => ensure!(
+                self.seq == 0 && self.prev_hash == AuditHash::default(),
+                "Audit log entry {} does not start the chain",
+                self.seq
+            ),
+        }
+        ensure!(
+            Self::chain_hash(self.seq, &self.prev_hash, &self.event)? == self.hash,
+            "Hash of audit log entry {} mismatched",
+            self.seq
+        );
+        Ok(())
+    }
+
+    pub fn verify_seal(&self, seal_key: &[u8]) -> Result<()> {
+        ensure!(
+            Self::seal_of(&self.hash, seal_key) == self.seal,
+            "Seal of audit log entry {} mismatched",
+            self.seq
+        );
+        Ok(())
+    }
+
+    fn chain_hash(seq: u64, prev_hash: &AuditHash, event: &AuditEvent) -> Result<AuditHash> {
+        let mut context = digest::Context::new(&digest::SHA256);
+        context.update(ENTRY_CONTEXT);
+        context.update(&seq.to_be_bytes());
+        context.update(prev_hash.as_bytes());
+        context.update(&serde_json::to_vec(event)?);
+        AuditHash::try_from(context.finish().as_ref())
+    }
+
+    fn seal_of(hash: &AuditHash, seal_key: &[u8]) -> AuditHash {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, seal_key);
+        let mut seal = [0u8; AuditHash::LENGTH];
+        seal.copy_from_slice(hmac::sign(&key, hash.as_bytes()).as_ref());
+        AuditHash::new(seal)
+    }
+}
+
+/// Verifies consecutive entries following `prev` (`None` if they start the
+/// chain), and their seals if the key is given. Returns the sequence number
+/// of the first entry which does not verify.
+pub fn verify_audit_chain(
+    prev: Option<&AuditLogEntry>,
+    entries: &[AuditLogEntry],
+    seal_key: Option<&[u8]>,
+) -> Option<u64> {
+    let mut prev = prev;
+    for entry in entries {
+        let verified = entry.verify_link(prev).and_then(|_| match seal_key {
+            Some(key) => entry.verify_seal(key),
+            None => Ok(()),
+        });
+        if verified.is_err() {
+            return Some(entry.seq);
+        }
+        prev = Some(entry);
+    }
+    None
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    fn chain(seal_key: &[u8], len: usize) -> Vec<AuditLogEntry> {
+        let mut entries: Vec<AuditLogEntry> = Vec::new();
+        for i in 0..len {
+            let event = AuditEvent::new(
+                AuditEventKind::Login,
+                "teaclave_authentication_service",
+                format!("user_{}", i),
+                "password",
+            );
+            let entry = AuditLogEntry::new(entries.last(), event, seal_key).unwrap();
+            entries.push(entry);
+        }
+        entries
+    }
+
+    pub fn run_tests() -> bool {
+        let seal_key: &[u8] = &[1u8; 16];
+        let entries = chain(seal_key, 4);
+        assert_eq!(entries[3].seq, 3);
+        assert_eq!(verify_audit_chain(None, &entries, Some(seal_key)), None);
+        assert_eq!(
+            verify_audit_chain(Some(&entries[0]), &entries[1..], None),
+            None
+        );
+        assert_eq!(
+            verify_audit_chain(None, &entries, Some(&[2u8; 16][..])),
+            Some(0)
+        );
+
+        let entry = AuditLogEntry::from_slice(&entries[1].to_vec().unwrap()).unwrap();
+        assert_eq!(entry, entries[1]);
+
+        // Modified, dropped and reordered entries break the chain.
+        let mut tampered = entries.clone();
+        tampered[2].event.user_id = "mallory".to_string();
+        assert_eq!(verify_audit_chain(None, &tampered, None), Some(2));
+        let mut tampered = entries.clone();
+        tampered.remove(1);
+        assert_eq!(verify_audit_chain(None, &tampered, None), Some(2));
+        let mut tampered = entries.clone();
+        tampered.swap(1, 2);
+        assert_eq!(verify_audit_chain(None, &tampered, None), Some(2));
+
+        // A rebuilt chain is only caught by the seals.
+        let forged = chain(&[3u8; 16], 4);
+        assert_eq!(verify_audit_chain(None, &forged, None), None);
+        assert_eq!(verify_audit_chain(None, &forged, Some(seal_key)), Some(0));
+        true
+    }
+}
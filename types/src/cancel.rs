@@ -0,0 +1,70 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::fmt;
+use std::prelude::v1::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cooperative cancellation of a running function, shared between the
+/// execution service which cancels it and the worker which stops waiting
+/// for the function and fails its further I/O.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    canceled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.canceled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::SeqCst)
+    }
+}
+
+/// Error of a task canceled to run a task of higher priority in its slot.
+/// The scheduler service stages the task again.
+#[derive(Debug)]
+pub struct TaskPreempted;
+
+impl fmt::Display for TaskPreempted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Task preempted by a task of higher priority")
+    }
+}
+
+impl std::error::Error for TaskPreempted {}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn run_tests() -> bool {
+        let token = CancelToken::new();
+        let worker_token = token.clone();
+        assert!(!worker_token.is_canceled());
+        token.cancel();
+        assert!(worker_token.is_canceled());
+        true
+    }
+}
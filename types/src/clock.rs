@@ -0,0 +1,73 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::time::Duration;
+
+/// Offset of the clock of a peer from the local clock, estimated from one
+/// request-response exchange as in NTP: the peer is assumed to have read its
+/// clock halfway through the round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ClockOffset {
+    /// Peer time minus local time, in milliseconds.
+    pub offset_ms: i64,
+    /// Duration of the exchange in milliseconds, bounding the error of the
+    /// estimate.
+    pub round_trip_ms: u64,
+}
+
+impl ClockOffset {
+    /// Estimates the offset from the local times the request was sent and
+    /// the response was received, and the time of the peer in the response,
+    /// all in milliseconds since the Unix epoch.
+    pub fn estimate(sent_ms: u64, peer_ms: u64, received_ms: u64) -> Self {
+        let round_trip_ms = received_ms.saturating_sub(sent_ms);
+        let midpoint_ms = sent_ms + round_trip_ms / 2;
+        Self {
+            offset_ms: peer_ms as i64 - midpoint_ms as i64,
+            round_trip_ms,
+        }
+    }
+
+    /// Lower bound of the skew between the clocks, i.e., the offset less the
+    /// error of the estimate.
+    pub fn min_skew(&self) -> Duration {
+        let skew_ms = self.offset_ms.abs() as u64;
+        Duration::from_millis(skew_ms.saturating_sub(self.round_trip_ms / 2))
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn run_tests() -> bool {
+        // The peer is 5s ahead and answers after 100ms of a 200ms round trip.
+        let offset = ClockOffset::estimate(1_000_000, 1_005_100, 1_000_200);
+        assert_eq!(offset.offset_ms, 5_000);
+        assert_eq!(offset.round_trip_ms, 200);
+        assert_eq!(offset.min_skew(), Duration::from_millis(4_900));
+
+        let offset = ClockOffset::estimate(1_000_000, 999_950, 1_000_200);
+        assert_eq!(offset.offset_ms, -150);
+        assert_eq!(offset.min_skew(), Duration::from_millis(50));
+
+        // A skew within the error of the estimate is not detected.
+        let offset = ClockOffset::estimate(1_000_000, 1_000_050, 1_000_200);
+        assert_eq!(offset.min_skew(), Duration::default());
+        true
+    }
+}
@@ -0,0 +1,756 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Task results exported as CBOR Web Tokens (RFC 8392) in a COSE_Sign1
+//! envelope (RFC 8152), so that systems outside Teaclave can check them with
+//! standard COSE libraries. The token is signed with the ES256 key of the
+//! attested TLS certificate of the management service, which is carried in
+//! the x5chain header (RFC 9360). Execution receipts of deterministic tasks
+//! are tokens of the same form signed by the execution service. Only the
+//! subset of CBOR used by the tokens is implemented, and it is always encoded
+//! in the canonical form of RFC 7049.
+
+#[cfg(feature = "sgx")]
+use std::prelude::v1::*;
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+use crate::{EnclaveMeasurement, FileAuthTag, MrEnclave, MrSigner, StagedTask, TaskOutputs};
+use anyhow::{anyhow, bail, ensure, Result};
+use ring::{digest, rand, signature};
+
+pub const COSE_SIGN1_TAG: u64 = 18;
+
+const HEADER_ALG: i64 = 1;
+const HEADER_X5CHAIN: i64 = 33;
+const ALG_ES256: i64 = -7;
+
+const CLAIM_ISS: i64 = 1;
+const CLAIM_SUB: i64 = 2;
+const CLAIM_IAT: i64 = 6;
+const CLAIM_FUNCTION_ID: &str = "teaclave_function_id";
+const CLAIM_RETURN_VALUE_SHA256: &str = "teaclave_return_value_sha256";
+const CLAIM_OUTPUT_TAGS: &str = "teaclave_output_tags";
+const CLAIM_ATTESTATION: &str = "teaclave_attestation";
+const CLAIM_FUNCTION_NAME: &str = "teaclave_function_name";
+const CLAIM_PAYLOAD_SHA256: &str = "teaclave_payload_sha256";
+const CLAIM_BUNDLE_SHA256: &str = "teaclave_bundle_sha256";
+const CLAIM_ARGUMENTS_SHA256: &str = "teaclave_arguments_sha256";
+const CLAIM_ENV_SHA256: &str = "teaclave_env_sha256";
+const CLAIM_INPUT_TAGS: &str = "teaclave_input_tags";
+
+// Nesting of arrays, maps and tags accepted by the decoder.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CborValue {
+    Integer(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<CborValue>),
+    Map(Vec<(CborValue, CborValue)>),
+    Tag(u64, Box<CborValue>),
+}
+
+impl CborValue {
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf);
+        buf
+    }
+
+    /// Decodes a single value, rejecting trailing bytes and the items which
+    /// are not supported, e.g., floats and indefinite lengths.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let mut decoder = Decoder { bytes, pos: 0 };
+        let value = decoder.value(0)?;
+        ensure!(
+            decoder.pos == bytes.len(),
+            "Trailing bytes after CBOR value"
+        );
+        Ok(value)
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            CborValue::Integer(i) if *i >= 0 => write_head(buf, 0, *i as u64),
+            CborValue::Integer(i) => write_head(buf, 1, !*i as u64),
+            CborValue::Bytes(b) => {
+                write_head(buf, 2, b.len() as u64);
+                buf.extend_from_slice(b);
+            }
+            CborValue::Text(t) => {
+                write_head(buf, 3, t.len() as u64);
+                buf.extend_from_slice(t.as_bytes());
+            }
+            CborValue::Array(items) => {
+                write_head(buf, 4, items.len() as u64);
+                items.iter().for_each(|item| item.encode(buf));
+            }
+            CborValue::Map(entries) => {
+                // Canonical order: shorter encoded keys first, then bytewise.
+                let mut encoded: Vec<(Vec<u8>, Vec<u8>)> = entries
+                    .iter()
+                    .map(|(k, v)| (k.to_vec(), v.to_vec()))
+                    .collect();
+                encoded.sort_by(|a, b| (a.0.len(), &a.0).cmp(&(b.0.len(), &b.0)));
+                write_head(buf, 5, encoded.len() as u64);
+                for (k, v) in encoded {
+                    buf.extend_from_slice(&k);
+                    buf.extend_from_slice(&v);
+                }
+            }
+            CborValue::Tag(tag, value) => {
+                write_head(buf, 6, *tag);
+                value.encode(buf);
+            }
+        }
+    }
+
+    fn get(&self, key: &CborValue) -> Option<&CborValue> {
+        match self {
+            CborValue::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_integer(&self) -> Result<i64> {
+        match self {
+            CborValue::Integer(i) => Ok(*i),
+            _ => bail!("Expect a CBOR integer"),
+        }
+    }
+
+    fn as_bytes(&self) -> Result<&[u8]> {
+        match self {
+            CborValue::Bytes(b) => Ok(b),
+            _ => bail!("Expect a CBOR byte string"),
+        }
+    }
+
+    fn as_text(&self) -> Result<&str> {
+        match self {
+            CborValue::Text(t) => Ok(t),
+            _ => bail!("Expect a CBOR text string"),
+        }
+    }
+
+    fn as_array(&self) -> Result<&[CborValue]> {
+        match self {
+            CborValue::Array(items) => Ok(items),
+            _ => bail!("Expect a CBOR array"),
+        }
+    }
+
+    fn as_map(&self) -> Result<&[(CborValue, CborValue)]> {
+        match self {
+            CborValue::Map(entries) => Ok(entries),
+            _ => bail!("Expect a CBOR map"),
+        }
+    }
+}
+
+fn write_head(buf: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    if arg < 24 {
+        buf.push(major | arg as u8);
+    } else if arg <= u64::from(std::u8::MAX) {
+        buf.push(major | 24);
+        buf.push(arg as u8);
+    } else if arg <= u64::from(std::u16::MAX) {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(arg as u16).to_be_bytes());
+    } else if arg <= u64::from(std::u32::MAX) {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(arg as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(
+            len <= self.bytes.len() - self.pos,
+            "Unexpected end of CBOR value"
+        );
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn head(&mut self) -> Result<(u8, u64)> {
+        let initial = self.take(1)?[0];
+        let arg = match initial & 0x1f {
+            n @ 0..=23 => u64::from(n),
+            24 => u64::from(self.take(1)?[0]),
+            25 => self.take(2)?.iter().fold(0, |a, b| a << 8 | u64::from(*b)),
+            26 => self.take(4)?.iter().fold(0, |a, b| a << 8 | u64::from(*b)),
+            27 => self.take(8)?.iter().fold(0, |a, b| a << 8 | u64::from(*b)),
+            _ => bail!("Unsupported CBOR item"),
+        };
+        Ok((initial >> 5, arg))
+    }
+
+    // Lengths are checked against the remaining input before allocating.
+    fn length(&self, arg: u64) -> Result<usize> {
+        ensure!(
+            arg <= (self.bytes.len() - self.pos) as u64,
+            "Invalid CBOR length"
+        );
+        Ok(arg as usize)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<CborValue> {
+        ensure!(depth <= MAX_DEPTH, "CBOR value nested too deeply");
+        let (major, arg) = self.head()?;
+        let value = match major {
+            0 => CborValue::Integer(i64::try_from(arg)?),
+            1 => CborValue::Integer(!i64::try_from(arg)?),
+            2 => {
+                let len = self.length(arg)?;
+                CborValue::Bytes(self.take(len)?.to_vec())
+            }
+            3 => {
+                let len = self.length(arg)?;
+                CborValue::Text(String::from_utf8(self.take(len)?.to_vec())?)
+            }
+            4 => {
+                let len = self.length(arg)?;
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(self.value(depth + 1)?);
+                }
+                CborValue::Array(items)
+            }
+            5 => {
+                let len = self.length(arg)?;
+                let mut entries = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let key = self.value(depth + 1)?;
+                    ensure!(
+                        entries.iter().all(|(k, _)| *k != key),
+                        "Duplicate key in CBOR map"
+                    );
+                    entries.push((key, self.value(depth + 1)?));
+                }
+                CborValue::Map(entries)
+            }
+            6 => CborValue::Tag(arg, Box::new(self.value(depth + 1)?)),
+            _ => bail!("Unsupported CBOR item"),
+        };
+        Ok(value)
+    }
+}
+
+/// A COSE_Sign1 message signed with ES256. The signer's certificate is in the
+/// unprotected x5chain header, and the signature is the fixed-length r || s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoseSign1 {
+    protected: Vec<u8>,
+    pub certificate: Vec<u8>,
+    pub payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    /// Signs the payload with the PKCS#8 encoded NIST P-256 private key of the
+    /// certificate.
+    pub fn sign(payload: Vec<u8>, certificate: &[u8], private_key: &[u8]) -> Result<Self> {
+        let key_pair = signature::EcdsaKeyPair::from_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            private_key,
+        )
+        .map_err(|_| anyhow!("Invalid ECDSA private key"))?;
+        let protected = CborValue::Map(vec![(
+            CborValue::Integer(HEADER_ALG),
+            CborValue::Integer(ALG_ES256),
+        )])
+        .to_vec();
+        let message = Self::sig_structure(&protected, &payload);
+        let signature = key_pair
+            .sign(&rand::SystemRandom::new(), &message)
+            .map_err(|_| anyhow!("Failed to sign COSE message"))?;
+        Ok(Self {
+            protected,
+            certificate: certificate.to_vec(),
+            payload,
+            signature: signature.as_ref().to_vec(),
+        })
+    }
+
+    /// Verifies the signature with an uncompressed SEC1 public key, which the
+    /// caller takes from the verified certificate.
+    pub fn verify(&self, public_key: &[u8]) -> Result<()> {
+        let public_key =
+            signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, public_key);
+        let message = Self::sig_structure(&self.protected, &self.payload);
+        public_key
+            .verify(&message, &self.signature)
+            .map_err(|_| anyhow!("Invalid COSE signature"))
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let unprotected = CborValue::Map(vec![(
+            CborValue::Integer(HEADER_X5CHAIN),
+            CborValue::Bytes(self.certificate.clone()),
+        )]);
+        CborValue::Tag(
+            COSE_SIGN1_TAG,
+            Box::new(CborValue::Array(vec![
+                CborValue::Bytes(self.protected.clone()),
+                unprotected,
+                CborValue::Bytes(self.payload.clone()),
+                CborValue::Bytes(self.signature.clone()),
+            ])),
+        )
+        .to_vec()
+    }
+
+    /// Parses the message, which must be tagged and signed with ES256. The
+    /// signature is not verified.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let items = match CborValue::from_slice(bytes)? {
+            CborValue::Tag(COSE_SIGN1_TAG, value) => value,
+            _ => bail!("Not a tagged COSE_Sign1 message"),
+        };
+        let items = items.as_array()?;
+        ensure!(items.len() == 4, "Invalid COSE_Sign1 message");
+        let protected = items[0].as_bytes()?.to_vec();
+        let alg = CborValue::from_slice(&protected)?
+            .get(&CborValue::Integer(HEADER_ALG))
+            .ok_or_else(|| anyhow!("Missing COSE algorithm"))?
+            .as_integer()?;
+        ensure!(alg == ALG_ES256, "Unsupported COSE algorithm");
+        let certificate = items[1]
+            .get(&CborValue::Integer(HEADER_X5CHAIN))
+            .ok_or_else(|| anyhow!("Missing COSE x5chain header"))?
+            .as_bytes()?
+            .to_vec();
+        Ok(Self {
+            protected,
+            certificate,
+            payload: items[2].as_bytes()?.to_vec(),
+            signature: items[3].as_bytes()?.to_vec(),
+        })
+    }
+
+    fn sig_structure(protected: &[u8], payload: &[u8]) -> Vec<u8> {
+        CborValue::Array(vec![
+            CborValue::Text("Signature1".to_string()),
+            CborValue::Bytes(protected.to_vec()),
+            CborValue::Bytes(Vec::new()),
+            CborValue::Bytes(payload.to_vec()),
+        ])
+        .to_vec()
+    }
+}
+
+/// Attestation of the enclave which issued a token, taken from its report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttestationSummary {
+    pub quote_status: String,
+    pub advisory_ids: Vec<String>,
+    pub measurement: EnclaveMeasurement,
+}
+
+impl AttestationSummary {
+    fn to_cbor(&self) -> CborValue {
+        let text = |s: &str| CborValue::Text(s.to_string());
+        CborValue::Map(vec![
+            (text("quote_status"), text(&self.quote_status)),
+            (
+                text("advisory_ids"),
+                CborValue::Array(self.advisory_ids.iter().map(|id| text(id)).collect()),
+            ),
+            (
+                text("mr_enclave"),
+                CborValue::Bytes(self.measurement.mr_enclave.as_bytes().to_vec()),
+            ),
+            (
+                text("mr_signer"),
+                CborValue::Bytes(self.measurement.mr_signer.as_bytes().to_vec()),
+            ),
+        ])
+    }
+
+    fn from_cbor(value: &CborValue) -> Result<Self> {
+        let field = |name: &str| {
+            value
+                .get(&CborValue::Text(name.to_string()))
+                .ok_or_else(|| anyhow!("Missing attestation field {}", name))
+        };
+        let advisory_ids = field("advisory_ids")?
+            .as_array()?
+            .iter()
+            .map(|id| id.as_text().map(|id| id.to_string()))
+            .collect::<Result<_>>()?;
+        let mr_enclave = MrEnclave::try_from(field("mr_enclave")?.as_bytes()?)?;
+        let mr_signer = MrSigner::try_from(field("mr_signer")?.as_bytes()?)?;
+        Ok(Self {
+            quote_status: field("quote_status")?.as_text()?.to_string(),
+            advisory_ids,
+            measurement: EnclaveMeasurement::new(mr_enclave, mr_signer),
+        })
+    }
+}
+
+/// Claims of the token of a finished task. Output files are identified by
+/// their authentication tags, and the return value by its SHA-256 digest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskResultClaims {
+    pub issuer: String,
+    pub task_id: String,
+    pub issued_at: u64,
+    pub function_id: String,
+    pub return_value_sha256: Vec<u8>,
+    pub output_tags: HashMap<String, FileAuthTag>,
+    pub attestation: AttestationSummary,
+}
+
+impl TaskResultClaims {
+    pub fn new(
+        issuer: impl Into<String>,
+        task_id: impl Into<String>,
+        function_id: impl Into<String>,
+        outputs: &TaskOutputs,
+        issued_at: u64,
+        attestation: AttestationSummary,
+    ) -> Self {
+        Self {
+            issuer: issuer.into(),
+            task_id: task_id.into(),
+            issued_at,
+            function_id: function_id.into(),
+            return_value_sha256: sha256(&outputs.return_value),
+            output_tags: output_tags(outputs),
+            attestation,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let text = |s: &str| CborValue::Text(s.to_string());
+        CborValue::Map(vec![
+            (CborValue::Integer(CLAIM_ISS), text(&self.issuer)),
+            (CborValue::Integer(CLAIM_SUB), text(&self.task_id)),
+            (
+                CborValue::Integer(CLAIM_IAT),
+                CborValue::Integer(i64::try_from(self.issued_at).unwrap_or(std::i64::MAX)),
+            ),
+            (text(CLAIM_FUNCTION_ID), text(&self.function_id)),
+            (
+                text(CLAIM_RETURN_VALUE_SHA256),
+                CborValue::Bytes(self.return_value_sha256.clone()),
+            ),
+            (text(CLAIM_OUTPUT_TAGS), tags_to_cbor(&self.output_tags)),
+            (text(CLAIM_ATTESTATION), self.attestation.to_cbor()),
+        ])
+        .to_vec()
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let claims = CborValue::from_slice(bytes)?;
+        let claim = |key: CborValue| {
+            claims
+                .get(&key)
+                .ok_or_else(|| anyhow!("Missing claim {:?}", key))
+        };
+        let text = |s: &str| CborValue::Text(s.to_string());
+        Ok(Self {
+            issuer: claim(CborValue::Integer(CLAIM_ISS))?.as_text()?.to_string(),
+            task_id: claim(CborValue::Integer(CLAIM_SUB))?.as_text()?.to_string(),
+            issued_at: u64::try_from(claim(CborValue::Integer(CLAIM_IAT))?.as_integer()?)?,
+            function_id: claim(text(CLAIM_FUNCTION_ID))?.as_text()?.to_string(),
+            return_value_sha256: claim(text(CLAIM_RETURN_VALUE_SHA256))?.as_bytes()?.to_vec(),
+            output_tags: tags_from_cbor(claim(text(CLAIM_OUTPUT_TAGS))?)?,
+            attestation: AttestationSummary::from_cbor(claim(text(CLAIM_ATTESTATION))?)?,
+        })
+    }
+
+    /// Checks the return value against the digest in the claims.
+    pub fn matches_return_value(&self, return_value: &[u8]) -> bool {
+        sha256(return_value) == self.return_value_sha256
+    }
+}
+
+/// Claims of the execution receipt of a deterministic task: what the
+/// execution service ran, i.e., the function, its arguments and environment
+/// and the input files, and what it produced. Inputs and outputs are
+/// identified by the authentication tags of their files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReceiptClaims {
+    pub issuer: String,
+    pub task_id: String,
+    pub issued_at: u64,
+    pub function_id: String,
+    pub function_name: String,
+    pub payload_sha256: Vec<u8>,
+    pub bundle_sha256: Vec<u8>,
+    /// SHA-256 of the arguments in JSON, with sorted keys.
+    pub arguments_sha256: Vec<u8>,
+    /// SHA-256 of the environment in JSON, with sorted keys.
+    pub env_sha256: Vec<u8>,
+    pub input_tags: HashMap<String, FileAuthTag>,
+    pub return_value_sha256: Vec<u8>,
+    pub output_tags: HashMap<String, FileAuthTag>,
+    pub attestation: AttestationSummary,
+}
+
+impl ExecutionReceiptClaims {
+    pub fn new(
+        issuer: impl Into<String>,
+        task_id: impl Into<String>,
+        task: &StagedTask,
+        outputs: &TaskOutputs,
+        issued_at: u64,
+        attestation: AttestationSummary,
+    ) -> Self {
+        let env: BTreeMap<&String, &String> = task.env.iter().collect();
+        let input_tags = task
+            .input_data
+            .iter()
+            .map(|(name, file)| (name.to_string(), file.cmac.clone()))
+            .collect();
+        Self {
+            issuer: issuer.into(),
+            task_id: task_id.into(),
+            issued_at,
+            function_id: task.function_id.to_string(),
+            function_name: task.function_name.clone(),
+            payload_sha256: sha256(&task.function_payload),
+            bundle_sha256: sha256(&task.function_bundle),
+            arguments_sha256: sha256(task.function_arguments.clone().into_string().as_bytes()),
+            env_sha256: sha256(&serde_json::to_vec(&env).unwrap_or_default()),
+            input_tags,
+            return_value_sha256: sha256(&outputs.return_value),
+            output_tags: output_tags(outputs),
+            attestation,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let text = |s: &str| CborValue::Text(s.to_string());
+        let bytes = |b: &[u8]| CborValue::Bytes(b.to_vec());
+        CborValue::Map(vec![
+            (CborValue::Integer(CLAIM_ISS), text(&self.issuer)),
+            (CborValue::Integer(CLAIM_SUB), text(&self.task_id)),
+            (
+                CborValue::Integer(CLAIM_IAT),
+                CborValue::Integer(i64::try_from(self.issued_at).unwrap_or(std::i64::MAX)),
+            ),
+            (text(CLAIM_FUNCTION_ID), text(&self.function_id)),
+            (text(CLAIM_FUNCTION_NAME), text(&self.function_name)),
+            (text(CLAIM_PAYLOAD_SHA256), bytes(&self.payload_sha256)),
+            (text(CLAIM_BUNDLE_SHA256), bytes(&self.bundle_sha256)),
+            (text(CLAIM_ARGUMENTS_SHA256), bytes(&self.arguments_sha256)),
+            (text(CLAIM_ENV_SHA256), bytes(&self.env_sha256)),
+            (text(CLAIM_INPUT_TAGS), tags_to_cbor(&self.input_tags)),
+            (
+                text(CLAIM_RETURN_VALUE_SHA256),
+                bytes(&self.return_value_sha256),
+            ),
+            (text(CLAIM_OUTPUT_TAGS), tags_to_cbor(&self.output_tags)),
+            (text(CLAIM_ATTESTATION), self.attestation.to_cbor()),
+        ])
+        .to_vec()
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let claims = CborValue::from_slice(bytes)?;
+        let claim = |key: CborValue| {
+            claims
+                .get(&key)
+                .ok_or_else(|| anyhow!("Missing claim {:?}", key))
+        };
+        let text = |s: &str| CborValue::Text(s.to_string());
+        let bytes = |name: &str| -> Result<Vec<u8>> { Ok(claim(text(name))?.as_bytes()?.to_vec()) };
+        Ok(Self {
+            issuer: claim(CborValue::Integer(CLAIM_ISS))?.as_text()?.to_string(),
+            task_id: claim(CborValue::Integer(CLAIM_SUB))?.as_text()?.to_string(),
+            issued_at: u64::try_from(claim(CborValue::Integer(CLAIM_IAT))?.as_integer()?)?,
+            function_id: claim(text(CLAIM_FUNCTION_ID))?.as_text()?.to_string(),
+            function_name: claim(text(CLAIM_FUNCTION_NAME))?.as_text()?.to_string(),
+            payload_sha256: bytes(CLAIM_PAYLOAD_SHA256)?,
+            bundle_sha256: bytes(CLAIM_BUNDLE_SHA256)?,
+            arguments_sha256: bytes(CLAIM_ARGUMENTS_SHA256)?,
+            env_sha256: bytes(CLAIM_ENV_SHA256)?,
+            input_tags: tags_from_cbor(claim(text(CLAIM_INPUT_TAGS))?)?,
+            return_value_sha256: bytes(CLAIM_RETURN_VALUE_SHA256)?,
+            output_tags: tags_from_cbor(claim(text(CLAIM_OUTPUT_TAGS))?)?,
+            attestation: AttestationSummary::from_cbor(claim(text(CLAIM_ATTESTATION))?)?,
+        })
+    }
+
+    /// Checks the function payload against the digest in the claims.
+    pub fn matches_payload(&self, payload: &[u8]) -> bool {
+        sha256(payload) == self.payload_sha256
+    }
+
+    /// Checks the return value against the digest in the claims.
+    pub fn matches_return_value(&self, return_value: &[u8]) -> bool {
+        sha256(return_value) == self.return_value_sha256
+    }
+}
+
+fn sha256(bytes: &[u8]) -> Vec<u8> {
+    digest::digest(&digest::SHA256, bytes).as_ref().to_vec()
+}
+
+fn output_tags(outputs: &TaskOutputs) -> HashMap<String, FileAuthTag> {
+    outputs
+        .tags_map
+        .iter()
+        .map(|(name, tag)| (name.to_string(), tag.clone()))
+        .collect()
+}
+
+fn tags_to_cbor(tags: &HashMap<String, FileAuthTag>) -> CborValue {
+    CborValue::Map(
+        tags.iter()
+            .map(|(name, tag)| {
+                (
+                    CborValue::Text(name.to_string()),
+                    CborValue::Bytes(tag.to_bytes()),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn tags_from_cbor(value: &CborValue) -> Result<HashMap<String, FileAuthTag>> {
+    value
+        .as_map()?
+        .iter()
+        .map(|(name, tag)| {
+            Ok((
+                name.as_text()?.to_string(),
+                FileAuthTag::from_bytes(tag.as_bytes()?)?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::vec;
+
+    fn outputs() -> TaskOutputs {
+        let mut tags = HashMap::new();
+        tags.insert("output".to_string(), FileAuthTag::mock());
+        TaskOutputs::new(b"42".to_vec(), tags)
+    }
+
+    fn attestation() -> AttestationSummary {
+        AttestationSummary {
+            quote_status: "OK".to_string(),
+            advisory_ids: vec!["INTEL-SA-00334".to_string()],
+            measurement: EnclaveMeasurement::new(MrEnclave::new([1; 32]), MrSigner::new([2; 32])),
+        }
+    }
+
+    fn claims() -> TaskResultClaims {
+        TaskResultClaims::new(
+            "teaclave_management_service",
+            "task-00000000-0000-0000-0000-000000000001",
+            "function-00000000-0000-0000-0000-000000000002",
+            &outputs(),
+            1_600_000_000,
+            attestation(),
+        )
+    }
+
+    fn receipt_claims(arguments: HashMap<String, String>) -> ExecutionReceiptClaims {
+        let task = StagedTask::new()
+            .function_name("echo")
+            .function_payload(b"payload".to_vec())
+            .function_arguments(arguments);
+        ExecutionReceiptClaims::new(
+            "teaclave_execution_service",
+            "task-00000000-0000-0000-0000-000000000001",
+            &task,
+            &outputs(),
+            1_600_000_000,
+            attestation(),
+        )
+    }
+
+    pub fn run_tests() -> bool {
+        // Canonical encoding of RFC 7049, Appendix A.
+        assert_eq!(CborValue::Integer(1000).to_vec(), vec![0x19, 0x03, 0xe8]);
+        assert_eq!(CborValue::Integer(-1000).to_vec(), vec![0x39, 0x03, 0xe7]);
+        let map = CborValue::Map(vec![
+            (CborValue::Text("b".to_string()), CborValue::Integer(2)),
+            (CborValue::Integer(10), CborValue::Integer(1)),
+        ]);
+        assert_eq!(map.to_vec(), vec![0xa2, 0x0a, 0x01, 0x61, 0x62, 0x02]);
+        assert!(CborValue::from_slice(&[0xa2, 0x0a, 0x01, 0x0a, 0x02]).is_err());
+        assert!(CborValue::from_slice(&[0x5a, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(CborValue::from_slice(&[0x9f, 0xff]).is_err());
+        assert!(CborValue::from_slice(&[0x01, 0x01]).is_err());
+
+        let claims = claims();
+        let decoded = TaskResultClaims::from_slice(&claims.to_vec()).unwrap();
+        assert_eq!(decoded, claims);
+        assert!(decoded.matches_return_value(b"42"));
+        assert!(!decoded.matches_return_value(b"43"));
+
+        let mut arguments = HashMap::new();
+        arguments.insert("message".to_string(), "Hello".to_string());
+        let receipt = receipt_claims(arguments.clone());
+        let decoded = ExecutionReceiptClaims::from_slice(&receipt.to_vec()).unwrap();
+        assert_eq!(decoded, receipt);
+        assert!(decoded.matches_payload(b"payload"));
+        assert!(decoded.matches_return_value(b"42"));
+        assert_eq!(decoded.output_tags, claims.output_tags);
+        arguments.insert("message".to_string(), "Hi".to_string());
+        assert_ne!(
+            receipt_claims(arguments).arguments_sha256,
+            receipt.arguments_sha256
+        );
+
+        let rng = rand::SystemRandom::new();
+        let pkcs8 = signature::EcdsaKeyPair::generate_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &rng,
+        )
+        .unwrap();
+        let key_pair = signature::EcdsaKeyPair::from_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            pkcs8.as_ref(),
+        )
+        .unwrap();
+        let public_key = signature::KeyPair::public_key(&key_pair).as_ref().to_vec();
+        let token = CoseSign1::sign(claims.to_vec(), b"certificate", pkcs8.as_ref()).unwrap();
+        let parsed = CoseSign1::from_slice(&token.to_vec()).unwrap();
+        assert_eq!(parsed, token);
+        assert_eq!(parsed.certificate, b"certificate".to_vec());
+        assert!(parsed.verify(&public_key).is_ok());
+
+        let mut tampered = parsed;
+        tampered.payload[0] ^= 1;
+        assert!(tampered.verify(&public_key).is_err());
+        true
+    }
+}
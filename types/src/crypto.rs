@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-#[cfg(feature = "mesalock_sgx")]
+#[cfg(feature = "sgx")]
 use std::prelude::v1::*;
 
 use anyhow::{bail, ensure, Context, Result};
@@ -87,6 +87,8 @@ impl std::cmp::PartialEq<[u8; FILE_AUTH_TAG_LENGTH]> for FileAuthTag {
 pub enum FileCrypto {
     AesGcm128(AesGcm128Key),
     AesGcm256(AesGcm256Key),
+    AesGcmSiv256(AesGcmSiv256Key),
+    XChaCha20Poly1305(XChaCha20Poly1305Key),
     TeaclaveFile128(TeaclaveFile128Key),
     Raw,
 }
@@ -102,6 +104,14 @@ impl FileCrypto {
                 let crypto = AesGcm256Key::new(key, iv)?;
                 FileCrypto::AesGcm256(crypto)
             }
+            AesGcmSiv256Key::SCHEMA => {
+                let crypto = AesGcmSiv256Key::new(key, iv)?;
+                FileCrypto::AesGcmSiv256(crypto)
+            }
+            XChaCha20Poly1305Key::SCHEMA => {
+                let crypto = XChaCha20Poly1305Key::new(key, iv)?;
+                FileCrypto::XChaCha20Poly1305(crypto)
+            }
             TeaclaveFile128Key::SCHEMA => {
                 ensure!(iv.is_empty(), "IV is not empty for teaclave_file_128");
                 let crypto = TeaclaveFile128Key::new(key)?;
@@ -118,6 +128,8 @@ impl FileCrypto {
         match self {
             FileCrypto::AesGcm128(_) => AesGcm128Key::SCHEMA,
             FileCrypto::AesGcm256(_) => AesGcm256Key::SCHEMA,
+            FileCrypto::AesGcmSiv256(_) => AesGcmSiv256Key::SCHEMA,
+            FileCrypto::XChaCha20Poly1305(_) => XChaCha20Poly1305Key::SCHEMA,
             FileCrypto::TeaclaveFile128(_) => TeaclaveFile128Key::SCHEMA,
             FileCrypto::Raw => "raw",
         }
@@ -127,6 +139,8 @@ impl FileCrypto {
         match self {
             FileCrypto::AesGcm128(crypto) => (crypto.key.to_vec(), crypto.iv.to_vec()),
             FileCrypto::AesGcm256(crypto) => (crypto.key.to_vec(), crypto.iv.to_vec()),
+            FileCrypto::AesGcmSiv256(crypto) => (crypto.key.to_vec(), crypto.iv.to_vec()),
+            FileCrypto::XChaCha20Poly1305(crypto) => (crypto.key.to_vec(), crypto.iv.to_vec()),
             FileCrypto::TeaclaveFile128(crypto) => (crypto.key.to_vec(), Vec::new()),
             FileCrypto::Raw => (vec![], vec![]),
         }
@@ -145,6 +159,18 @@ impl std::convert::From<AesGcm256Key> for FileCrypto {
     }
 }
 
+impl std::convert::From<AesGcmSiv256Key> for FileCrypto {
+    fn from(crypto: AesGcmSiv256Key) -> Self {
+        FileCrypto::AesGcmSiv256(crypto)
+    }
+}
+
+impl std::convert::From<XChaCha20Poly1305Key> for FileCrypto {
+    fn from(crypto: XChaCha20Poly1305Key) -> Self {
+        FileCrypto::XChaCha20Poly1305(crypto)
+    }
+}
+
 impl std::convert::From<TeaclaveFile128Key> for FileCrypto {
     fn from(crypto: TeaclaveFile128Key) -> Self {
         FileCrypto::TeaclaveFile128(crypto)
@@ -16,7 +16,7 @@
 // under the License.
 
 use std::fmt;
-#[cfg(feature = "mesalock_sgx")]
+#[cfg(feature = "sgx")]
 use std::prelude::v1::*;
 
 use serde::{Deserialize, Serialize};
@@ -75,6 +75,12 @@ pub enum TeaclaveServiceResponseError {
     ConnectionError(String),
     #[error("Internal error: {0}")]
     InternalError(String),
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+    #[error("Message too large: {len} bytes exceeds the max length {max_len}")]
+    MessageTooLarge { len: u64, max_len: u64 },
+    #[error("Resource exhausted: {0}")]
+    ResourceExhausted(String),
 }
 
 impl From<anyhow::Error> for TeaclaveServiceResponseError {
@@ -0,0 +1,96 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::{EnclaveMeasurement, Storable, UserID};
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use uuid::Uuid;
+
+const EXECUTOR_ENCLAVE_PREFIX: &str = "executor-enclave";
+
+/// An executor enclave built by a function provider, running the functions
+/// registered with it instead of the executors shipped with the platform.
+/// Execution services hand tasks over to the enclave only after attesting
+/// that it has the registered measurement.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExecutorEnclave {
+    pub id: Uuid,
+    pub owner: UserID,
+    pub name: String,
+    pub measurement: EnclaveMeasurement,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+impl ExecutorEnclave {
+    pub fn new(
+        id: Uuid,
+        owner: impl Into<UserID>,
+        name: impl ToString,
+        measurement: EnclaveMeasurement,
+        created_at: u64,
+    ) -> Result<Self> {
+        let name = name.to_string();
+        ensure!(!name.is_empty(), "executor enclave without name");
+        Ok(Self {
+            id,
+            owner: owner.into(),
+            name,
+            measurement,
+            created_at,
+        })
+    }
+}
+
+impl Storable for ExecutorEnclave {
+    fn key_prefix() -> &'static str {
+        EXECUTOR_ENCLAVE_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.id
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::{platform, MrEnclave, MrSigner};
+    use std::str::FromStr;
+
+    pub fn run_tests() -> bool {
+        let measurement = EnclaveMeasurement::new(
+            MrEnclave::from_str(&"ab".repeat(32)).unwrap(),
+            MrSigner::from_str(&"cd".repeat(32)).unwrap(),
+        );
+        let id = platform::rand::new_uuid();
+        let enclave =
+            ExecutorEnclave::new(id, "provider", "wasm-executor", measurement, 0).unwrap();
+        assert_eq!(
+            enclave.external_id().to_string(),
+            format!("executor-enclave-{}", id)
+        );
+
+        let bytes = enclave.to_vec().unwrap();
+        let enclave = ExecutorEnclave::from_slice(&bytes).unwrap();
+        assert_eq!(enclave.measurement, measurement);
+        assert_eq!(enclave.owner, UserID::from("provider"));
+
+        assert!(ExecutorEnclave::new(id, "provider", "", measurement, 0).is_err());
+        true
+    }
+}
@@ -16,7 +16,7 @@
 // under the License.
 
 use crate::storage::Storable;
-use crate::{FileAuthTag, FileCrypto, OwnerList};
+use crate::{FileAuthTag, FileCrypto, OwnerList, PrivacyBudget};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::prelude::v1::*;
@@ -25,9 +25,57 @@ use uuid::Uuid;
 
 const INPUT_FILE_PREFIX: &str = "input";
 const OUTPUT_FILE_PREFIX: &str = "output";
+const INLINE_FILE_URL_SCHEME: &str = "inline";
 
 fn create_uuid() -> Uuid {
-    Uuid::new_v4()
+    crate::platform::rand::new_uuid()
+}
+
+/// Attributes of the content of an input file provided by its owner, e.g.,
+/// the number of rows or the schema. Task arguments can refer to them with
+/// `{{input.<name>.<attribute>}}` templates.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct FileAttributes {
+    #[serde(flatten)]
+    inner: serde_json::Map<String, serde_json::Value>,
+}
+
+impl FileAttributes {
+    pub fn new(inner: serde_json::Map<String, serde_json::Value>) -> Self {
+        Self { inner }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Look up a (nested) attribute, e.g., `["schema", "columns"]`.
+    pub fn get(&self, path: &[&str]) -> Option<&serde_json::Value> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.inner.get(*first)?, |value, key| value.get(*key))
+    }
+
+    pub fn into_string(self) -> String {
+        if self.inner.is_empty() {
+            return String::new();
+        }
+        serde_json::Value::Object(self.inner).to_string()
+    }
+}
+
+impl std::convert::TryFrom<String> for FileAttributes {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        match serde_json::from_str(&s)? {
+            serde_json::Value::Object(inner) => Ok(Self { inner }),
+            _ => anyhow::bail!("Cannot convert to file attributes"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -37,6 +85,16 @@ pub struct TeaclaveInputFile {
     pub crypto_info: FileCrypto,
     pub owner: OwnerList,
     pub uuid: Uuid,
+    #[serde(default)]
+    pub content: Option<Vec<u8>>,
+    #[serde(default)]
+    pub attributes: FileAttributes,
+    // Classification labels of the data, e.g., "pii" or "region=eu"
+    #[serde(default)]
+    pub labels: Vec<String>,
+    // Differential privacy budget spent by the tasks reading the file
+    #[serde(default)]
+    pub privacy_budget: Option<PrivacyBudget>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -61,9 +119,57 @@ impl TeaclaveInputFile {
             crypto_info,
             owner: owner.into(),
             uuid: create_uuid(),
+            content: None,
+            attributes: FileAttributes::default(),
+            labels: Vec::new(),
+            privacy_budget: None,
         }
     }
 
+    // Inline files carry their (encrypted) content in the record itself and
+    // are addressed by an "inline:///<uuid>" url instead of an external one.
+    pub fn from_bytes(
+        content: Vec<u8>,
+        cmac: FileAuthTag,
+        crypto_info: FileCrypto,
+        owner: impl Into<OwnerList>,
+    ) -> Result<TeaclaveInputFile> {
+        let uuid = create_uuid();
+        let url = format!("{}:///{}", INLINE_FILE_URL_SCHEME, uuid.to_string());
+        let url = Url::parse(&url).map_err(|_| anyhow!("invalid url"))?;
+        let input = TeaclaveInputFile {
+            url,
+            cmac,
+            crypto_info,
+            owner: owner.into(),
+            uuid,
+            content: Some(content),
+            attributes: FileAttributes::default(),
+            labels: Vec::new(),
+            privacy_budget: None,
+        };
+        Ok(input)
+    }
+
+    pub fn attributes(self, attributes: FileAttributes) -> Self {
+        Self { attributes, ..self }
+    }
+
+    pub fn labels(self, labels: Vec<String>) -> Self {
+        Self { labels, ..self }
+    }
+
+    pub fn privacy_budget(self, privacy_budget: Option<PrivacyBudget>) -> Self {
+        Self {
+            privacy_budget,
+            ..self
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.content.is_some()
+    }
+
     pub fn from_output(output: TeaclaveOutputFile) -> Result<TeaclaveInputFile> {
         let input = TeaclaveInputFile {
             url: output.url,
@@ -73,6 +179,10 @@ impl TeaclaveInputFile {
             crypto_info: output.crypto_info,
             owner: output.owner,
             uuid: output.uuid,
+            content: None,
+            attributes: FileAttributes::default(),
+            labels: Vec::new(),
+            privacy_budget: None,
         };
         Ok(input)
     }
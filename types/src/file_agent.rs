@@ -18,6 +18,12 @@
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::prelude::v1::*;
+use std::time::Duration;
+
+/// Return values of the file agent ocall.
+pub const FA_OK: u32 = 0;
+pub const FA_ERR_GENERAL: u32 = 1;
+pub const FA_ERR_TIMEOUT: u32 = 3;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum HandleFileCommand {
@@ -30,6 +36,9 @@ pub struct FileAgentRequest {
     pub cmd: HandleFileCommand,
     pub info: Vec<HandleFileInfo>,
     pub fusion_base: PathBuf,
+    /// Time limit of handling all the files of the request.
+    #[serde(default)]
+    pub timeout: Option<Duration>,
 }
 
 impl FileAgentRequest {
@@ -45,8 +54,13 @@ impl FileAgentRequest {
             cmd,
             info: info.into_iter().map(|x| x.into()).collect(),
             fusion_base: fusion_base.as_ref().to_owned(),
+            timeout: None,
         }
     }
+
+    pub fn timeout(self, timeout: Option<Duration>) -> Self {
+        Self { timeout, ..self }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,8 +15,10 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::{ExecutorType, Storable, UserID};
+use crate::{ExecutorType, ExternalID, FunctionManifest, OutputPolicy, Storable, UserID};
+use anyhow::{bail, ensure, Result};
 use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
 use std::prelude::v1::*;
 use uuid::Uuid;
 
@@ -39,6 +41,8 @@ impl FunctionInput {
 pub struct FunctionOutput {
     pub name: String,
     pub description: String,
+    #[serde(default)]
+    pub policy: OutputPolicy,
 }
 
 impl FunctionOutput {
@@ -46,8 +50,13 @@ impl FunctionOutput {
         Self {
             name: name.into(),
             description: description.into(),
+            policy: OutputPolicy::default(),
         }
     }
+
+    pub fn policy(self, policy: OutputPolicy) -> Self {
+        Self { policy, ..self }
+    }
 }
 
 const FUNCION_PREFIX: &str = "function";
@@ -64,6 +73,28 @@ pub struct Function {
     pub inputs: Vec<FunctionInput>,
     pub outputs: Vec<FunctionOutput>,
     pub owner: UserID,
+    // Tags checked against the label rules of the access control model,
+    // e.g., "eu-approved"
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Package manifest the function was registered with, if any
+    #[serde(default)]
+    pub manifest: Option<FunctionManifest>,
+    // Version among the functions of the owner with the same name, from 1;
+    // 0 for the functions registered before versioning
+    #[serde(default)]
+    pub version: u32,
+    // Seconds since the Unix epoch; 0 for the functions registered before
+    // it was recorded
+    #[serde(default)]
+    pub created_at: u64,
+    // Executor enclave registered by the provider which runs the function in
+    // place of the executors of the platform
+    #[serde(default)]
+    pub executor_enclave: Option<ExternalID>,
+    // Zip of pure-Python modules importable by a Python function
+    #[serde(default)]
+    pub bundle: Vec<u8>,
 }
 
 impl Function {
@@ -100,6 +131,10 @@ impl Function {
         Self { payload, ..self }
     }
 
+    pub fn bundle(self, bundle: Vec<u8>) -> Self {
+        Self { bundle, ..self }
+    }
+
     pub fn public(self, public: bool) -> Self {
         Self { public, ..self }
     }
@@ -116,12 +151,38 @@ impl Function {
         Self { outputs, ..self }
     }
 
+    pub fn tags(self, tags: Vec<String>) -> Self {
+        Self { tags, ..self }
+    }
+
+    pub fn manifest(self, manifest: impl Into<Option<FunctionManifest>>) -> Self {
+        Self {
+            manifest: manifest.into(),
+            ..self
+        }
+    }
+
     pub fn owner(self, owner: impl Into<UserID>) -> Self {
         Self {
             owner: owner.into(),
             ..self
         }
     }
+
+    pub fn version(self, version: u32) -> Self {
+        Self { version, ..self }
+    }
+
+    pub fn created_at(self, created_at: u64) -> Self {
+        Self { created_at, ..self }
+    }
+
+    pub fn executor_enclave(self, executor_enclave: impl Into<Option<ExternalID>>) -> Self {
+        Self {
+            executor_enclave: executor_enclave.into(),
+            ..self
+        }
+    }
 }
 
 impl Storable for Function {
@@ -132,4 +193,159 @@ impl Storable for Function {
     fn uuid(&self) -> Uuid {
         self.id
     }
+
+    fn tenant(&self) -> Option<&UserID> {
+        Some(&self.owner)
+    }
+}
+
+/// Version of a function used by a task, resolved when the task is created
+/// among the functions of the owner with the name of the given function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionVersion {
+    /// The given function itself.
+    Given,
+    /// The default version, the latest unless rolled back.
+    Latest,
+    Pinned(u32),
+}
+
+impl Default for FunctionVersion {
+    fn default() -> Self {
+        FunctionVersion::Given
+    }
+}
+
+impl std::convert::TryFrom<&str> for FunctionVersion {
+    type Error = anyhow::Error;
+
+    fn try_from(version: &str) -> Result<Self> {
+        let version = match version {
+            "" => FunctionVersion::Given,
+            "latest" => FunctionVersion::Latest,
+            version => match version.parse() {
+                Ok(number) if number > 0 => FunctionVersion::Pinned(number),
+                _ => bail!("Unsupported function version: {}", version),
+            },
+        };
+        Ok(version)
+    }
+}
+
+impl std::convert::TryFrom<String> for FunctionVersion {
+    type Error = anyhow::Error;
+
+    fn try_from(version: String) -> Result<Self> {
+        version.as_str().try_into()
+    }
+}
+
+impl std::fmt::Display for FunctionVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FunctionVersion::Given => Ok(()),
+            FunctionVersion::Latest => write!(f, "latest"),
+            FunctionVersion::Pinned(number) => write!(f, "{}", number),
+        }
+    }
+}
+
+/// Versions of the functions of an owner with the same name, in the order
+/// of their registration, and the one used by tasks asking for the latest.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct FunctionVersions {
+    pub owner: UserID,
+    pub name: String,
+    pub versions: Vec<Uuid>,
+    pub default_version: u32,
+}
+
+impl FunctionVersions {
+    pub fn new(owner: UserID, name: impl ToString) -> Self {
+        Self {
+            owner,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds the function as the next version, which becomes the default.
+    pub fn push(&mut self, id: Uuid) -> u32 {
+        self.versions.push(id);
+        self.default_version = self.versions.len() as u32;
+        self.default_version
+    }
+
+    pub fn get(&self, version: FunctionVersion) -> Option<Uuid> {
+        let number = match version {
+            FunctionVersion::Given => return None,
+            FunctionVersion::Latest => self.default_version,
+            FunctionVersion::Pinned(number) => number,
+        };
+        number
+            .checked_sub(1)
+            .and_then(|i| self.versions.get(i as usize))
+            .copied()
+    }
+
+    /// Makes an earlier version the default, or the one before the default
+    /// if `version` is 0.
+    pub fn rollback(&mut self, version: u32) -> Result<u32> {
+        let version = match version {
+            0 => self.default_version.saturating_sub(1),
+            version => version,
+        };
+        ensure!(
+            version > 0 && version < self.default_version,
+            "no version {} before the default {}",
+            version,
+            self.default_version
+        );
+        self.default_version = version;
+        Ok(version)
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    pub fn run_tests() -> bool {
+        let mut versions = FunctionVersions::new(UserID::from("owner"), "function");
+        let ids: Vec<Uuid> = (1..=3).map(Uuid::from_u128).collect();
+        for id in ids.iter() {
+            versions.push(*id);
+        }
+        assert_eq!(versions.default_version, 3);
+        assert_eq!(versions.get(FunctionVersion::Latest), Some(ids[2]));
+        assert_eq!(versions.get(FunctionVersion::Pinned(1)), Some(ids[0]));
+        assert_eq!(versions.get(FunctionVersion::Pinned(4)), None);
+
+        assert_eq!(versions.rollback(0).unwrap(), 2);
+        assert_eq!(versions.get(FunctionVersion::Latest), Some(ids[1]));
+        assert!(versions.rollback(3).is_err());
+        assert_eq!(versions.rollback(1).unwrap(), 1);
+        assert!(versions.rollback(0).is_err());
+
+        // A new version becomes the default.
+        assert_eq!(versions.push(Uuid::from_u128(4)), 4);
+        assert_eq!(versions.default_version, 4);
+
+        assert_eq!(
+            FunctionVersion::try_from("latest").unwrap(),
+            FunctionVersion::Latest
+        );
+        assert_eq!(
+            FunctionVersion::try_from("2").unwrap(),
+            FunctionVersion::Pinned(2)
+        );
+        assert_eq!(
+            FunctionVersion::try_from("").unwrap(),
+            FunctionVersion::Given
+        );
+        assert!(FunctionVersion::try_from("0").is_err());
+        assert!(FunctionVersion::try_from("v2").is_err());
+        true
+    }
 }
@@ -0,0 +1,264 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{ExecutorType, FunctionArguments};
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::prelude::v1::*;
+
+/// Versions of the interface between functions and their executors which
+/// the platform supports.
+pub const SUPPORTED_FUNCTION_ABI_VERSIONS: &[u32] = &[1];
+
+/// Package manifest of a function (`function.toml`), built into the
+/// registration request by the SDKs and the CLI, and validated by the
+/// management service, which keeps it with the function for review.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunctionManifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Path of the payload relative to the manifest, e.g., "main.py".
+    /// Builtin functions have none.
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// Path of a zip of pure-Python modules relative to the manifest, which
+    /// Python functions can import.
+    #[serde(default)]
+    pub bundle: Option<String>,
+    /// "python", "wasm" or "builtin"
+    pub executor_type: String,
+    pub abi_version: u32,
+    #[serde(default)]
+    pub public: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Skipped if empty, since TOML has no values after tables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<ManifestData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<ManifestData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<ManifestArgument>,
+    #[serde(default)]
+    pub resources: ResourceHints,
+}
+
+/// An input or output file of the function.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestData {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// An argument of the function and the type of its values.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestArgument {
+    pub name: String,
+    /// "string" (default), "integer", "number", "boolean" or "any"
+    #[serde(default = "default_argument_type", rename = "type")]
+    pub argument_type: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub description: String,
+}
+
+fn default_argument_type() -> String {
+    "string".to_string()
+}
+
+/// Resources the function is expected to need, for operators and
+/// schedulers. They do not limit the function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceHints {
+    pub memory_mb: Option<u64>,
+    pub execution_time_secs: Option<u64>,
+}
+
+impl FunctionManifest {
+    pub fn from_toml(manifest: &str) -> Result<Self> {
+        let manifest: Self = toml::from_str(manifest).context("invalid function manifest")?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn executor_type(&self) -> Result<ExecutorType> {
+        ExecutorType::try_from(self.executor_type.as_str())
+    }
+
+    /// Names of the arguments, as registered with the function.
+    pub fn argument_names(&self) -> Vec<String> {
+        self.arguments.iter().map(|a| a.name.clone()).collect()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        ensure!(!self.name.is_empty(), "function name is empty");
+        ensure!(
+            SUPPORTED_FUNCTION_ABI_VERSIONS.contains(&self.abi_version),
+            "unsupported ABI version: {}",
+            self.abi_version
+        );
+        match self.executor_type()? {
+            ExecutorType::Python | ExecutorType::Wasm => ensure!(
+                self.entry_point.is_some(),
+                "{} functions need an entry point",
+                self.executor_type
+            ),
+            ExecutorType::Builtin => ensure!(
+                self.entry_point.is_none(),
+                "builtin functions have no entry point"
+            ),
+        }
+        ensure!(
+            self.bundle.is_none() || self.executor_type()? == ExecutorType::Python,
+            "only python functions have a bundle"
+        );
+        ensure_unique("input", self.inputs.iter().map(|d| d.name.as_str()))?;
+        ensure_unique("output", self.outputs.iter().map(|d| d.name.as_str()))?;
+        ensure_unique("argument", self.arguments.iter().map(|a| a.name.as_str()))?;
+        for argument in &self.arguments {
+            ensure!(
+                ARGUMENT_TYPES.contains(&argument.argument_type.as_str()),
+                "unknown type of argument {}: {}",
+                argument.name,
+                argument.argument_type
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the arguments of a task against the declared ones: required
+    /// arguments must be set, and values must be of their types, or strings
+    /// parsing as them.
+    pub fn check_arguments(&self, arguments: &FunctionArguments) -> Result<()> {
+        for argument in &self.arguments {
+            let value = match arguments.inner().get(&argument.name) {
+                Some(value) => value,
+                None if argument.required => bail!("missing argument: {}", argument.name),
+                None => continue,
+            };
+            ensure!(
+                is_of_type(value, &argument.argument_type),
+                "argument {} is not of type {}",
+                argument.name,
+                argument.argument_type
+            );
+        }
+        Ok(())
+    }
+}
+
+const ARGUMENT_TYPES: &[&str] = &["string", "integer", "number", "boolean", "any"];
+
+fn ensure_unique<'a>(kind: &str, names: impl Iterator<Item = &'a str>) -> Result<()> {
+    let mut seen = HashSet::new();
+    for name in names {
+        ensure!(!name.is_empty(), "{} name is empty", kind);
+        ensure!(seen.insert(name), "duplicated {}: {}", kind, name);
+    }
+    Ok(())
+}
+
+fn is_of_type(value: &serde_json::Value, argument_type: &str) -> bool {
+    use serde_json::Value;
+    match (argument_type, value) {
+        ("any", _) | ("string", Value::String(_)) | ("boolean", Value::Bool(_)) => true,
+        ("integer", Value::Number(n)) => n.is_i64() || n.is_u64(),
+        ("number", Value::Number(_)) => true,
+        ("integer", Value::String(s)) => s.parse::<i64>().is_ok(),
+        ("number", Value::String(s)) => s.parse::<f64>().is_ok(),
+        ("boolean", Value::String(s)) => s.parse::<bool>().is_ok(),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn run_tests() -> bool {
+        let manifest = r#"
+            name = "word_count"
+            entry_point = "main.py"
+            executor_type = "python"
+            abi_version = 1
+
+            [[inputs]]
+            name = "text"
+
+            [[arguments]]
+            name = "top"
+            type = "integer"
+            required = true
+
+            [[arguments]]
+            name = "case_sensitive"
+            type = "boolean"
+
+            [resources]
+            memory_mb = 64
+        "#;
+        let manifest = FunctionManifest::from_toml(manifest).unwrap();
+        assert_eq!(manifest.executor_type().unwrap(), ExecutorType::Python);
+        assert_eq!(manifest.argument_names(), vec!["top", "case_sensitive"]);
+        assert_eq!(manifest.resources.memory_mb, Some(64));
+        let toml = manifest.to_toml().unwrap();
+        assert_eq!(FunctionManifest::from_toml(&toml).unwrap(), manifest);
+
+        let arguments = |json| FunctionArguments::from_json(json).unwrap();
+        let valid = arguments(serde_json::json!({"top": "10"}));
+        assert!(manifest.check_arguments(&valid).is_ok());
+        let valid = arguments(serde_json::json!({"top": 10, "case_sensitive": true}));
+        assert!(manifest.check_arguments(&valid).is_ok());
+        let missing = arguments(serde_json::json!({"case_sensitive": "true"}));
+        assert!(manifest.check_arguments(&missing).is_err());
+        let mistyped = arguments(serde_json::json!({"top": "ten"}));
+        assert!(manifest.check_arguments(&mistyped).is_err());
+
+        let mut invalid = manifest.clone();
+        invalid.abi_version = 0;
+        assert!(invalid.validate().is_err());
+        let mut invalid = manifest.clone();
+        invalid.entry_point = None;
+        assert!(invalid.validate().is_err());
+        let mut bundled = manifest.clone();
+        bundled.bundle = Some("deps.zip".to_string());
+        assert!(bundled.validate().is_ok());
+        bundled.executor_type = "wasm".to_string();
+        assert!(bundled.validate().is_err());
+        let mut invalid = manifest.clone();
+        invalid.arguments[1].name = "top".to_string();
+        assert!(invalid.validate().is_err());
+        let mut invalid = manifest;
+        invalid.arguments[0].argument_type = "date".to_string();
+        assert!(invalid.validate().is_err());
+        assert!(FunctionManifest::from_toml("name = \"f\"\nunknown = 1").is_err());
+        true
+    }
+}
@@ -0,0 +1,240 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::{FileAuthTag, Storable, UserID, UserList};
+use anyhow::{bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use url::Url;
+use uuid::Uuid;
+
+const HELD_OUTPUTS_PREFIX: &str = "held-outputs";
+
+/// Why a content scanner held an output of a task.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ScanFinding {
+    /// Name of the output in the function, or "return_value".
+    pub output: String,
+    pub scanner: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for ScanFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.output, self.scanner, self.reason)
+    }
+}
+
+/// An output in quarantine, written to the fusion storage and encrypted with
+/// the key of its owners like an uploaded output.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HeldFile {
+    pub url: Url,
+    pub cmac: FileAuthTag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum HeldOutputsStatus {
+    Pending,
+    Released,
+    Rejected,
+}
+
+impl HeldOutputsStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HeldOutputsStatus::Pending => "pending",
+            HeldOutputsStatus::Released => "released",
+            HeldOutputsStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl std::convert::TryFrom<&str> for HeldOutputsStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(status: &str) -> Result<Self> {
+        let status = match status {
+            "pending" => HeldOutputsStatus::Pending,
+            "released" => HeldOutputsStatus::Released,
+            "rejected" => HeldOutputsStatus::Rejected,
+            _ => bail!("Invalid held outputs status: {}", status),
+        };
+        Ok(status)
+    }
+}
+
+/// The outputs of a task held by the content scanners until the owners of
+/// its input data review them. Any reviewer can reject them, and they are
+/// released once every reviewer has approved them.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HeldOutputs {
+    pub task_id: Uuid,
+    pub files: HashMap<String, HeldFile>,
+    pub return_value: Vec<u8>,
+    pub findings: Vec<ScanFinding>,
+    pub reviewers: UserList,
+    pub approvals: UserList,
+    pub status: HeldOutputsStatus,
+}
+
+impl HeldOutputs {
+    pub fn new(
+        task_id: Uuid,
+        files: HashMap<String, HeldFile>,
+        return_value: Vec<u8>,
+        findings: Vec<ScanFinding>,
+    ) -> Self {
+        Self {
+            task_id,
+            files,
+            return_value,
+            findings,
+            reviewers: UserList::default(),
+            approvals: UserList::default(),
+            status: HeldOutputsStatus::Pending,
+        }
+    }
+
+    pub fn reviewers(self, reviewers: UserList) -> Self {
+        Self { reviewers, ..self }
+    }
+
+    /// Records the decision of a reviewer, returning the status after it.
+    pub fn review(&mut self, reviewer: &UserID, approve: bool) -> Result<HeldOutputsStatus> {
+        ensure!(
+            self.reviewers.contains(reviewer),
+            "Not a reviewer of the outputs: {:?}",
+            reviewer
+        );
+        ensure!(
+            self.status == HeldOutputsStatus::Pending,
+            "Outputs already reviewed: {:?}",
+            self.status
+        );
+        if !approve {
+            self.status = HeldOutputsStatus::Rejected;
+        } else {
+            self.approvals.insert(reviewer.clone());
+            if self.approvals == self.reviewers {
+                self.status = HeldOutputsStatus::Released;
+            }
+        }
+        Ok(self.status)
+    }
+
+    /// The reason of the failure of the task while its outputs are held.
+    pub fn describe(&self) -> String {
+        let findings: Vec<String> = self.findings.iter().map(ToString::to_string).collect();
+        format!("outputs held for review: {}", findings.join("; "))
+    }
+
+    pub fn release(&self) -> OutputRelease {
+        OutputRelease {
+            files: self.files.clone(),
+            return_value: self.return_value.clone(),
+        }
+    }
+}
+
+impl Storable for HeldOutputs {
+    fn key_prefix() -> &'static str {
+        HELD_OUTPUTS_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.task_id
+    }
+}
+
+/// Error of a task whose outputs are held, carrying them to the scheduler.
+#[derive(Debug)]
+pub struct OutputsHeld(pub HeldOutputs);
+
+impl std::fmt::Display for OutputsHeld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.describe())
+    }
+}
+
+impl std::error::Error for OutputsHeld {}
+
+/// Outputs released after their review, which the execution service uploads
+/// to the outputs of the task in place of running its function again.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct OutputRelease {
+    pub files: HashMap<String, HeldFile>,
+    pub return_value: Vec<u8>,
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::platform;
+    use std::convert::TryFrom;
+
+    pub fn run_tests() -> bool {
+        for status in [
+            HeldOutputsStatus::Pending,
+            HeldOutputsStatus::Released,
+            HeldOutputsStatus::Rejected,
+        ]
+        .iter()
+        {
+            assert_eq!(
+                HeldOutputsStatus::try_from(status.as_str()).unwrap(),
+                *status
+            );
+        }
+
+        let finding = ScanFinding {
+            output: "report".to_string(),
+            scanner: "pii".to_string(),
+            reason: "email address in line 3".to_string(),
+        };
+        let mut held = HeldOutputs::new(
+            platform::rand::new_uuid(),
+            HashMap::new(),
+            Vec::new(),
+            vec![finding],
+        )
+        .reviewers(UserList::from(vec!["alice", "bob"]));
+        assert_eq!(
+            held.describe(),
+            "outputs held for review: report (pii): email address in line 3"
+        );
+
+        assert!(held.review(&UserID::from("mallory"), true).is_err());
+        assert_eq!(
+            held.review(&UserID::from("alice"), true).unwrap(),
+            HeldOutputsStatus::Pending
+        );
+        assert_eq!(
+            held.review(&UserID::from("bob"), true).unwrap(),
+            HeldOutputsStatus::Released
+        );
+        assert!(held.review(&UserID::from("bob"), false).is_err());
+
+        held.status = HeldOutputsStatus::Pending;
+        held.approvals = UserList::default();
+        assert_eq!(
+            held.review(&UserID::from("bob"), false).unwrap(),
+            HeldOutputsStatus::Rejected
+        );
+        true
+    }
+}
@@ -15,49 +15,135 @@
 // specific language governing permissions and limitations
 // under the License.
 
-#![cfg_attr(feature = "mesalock_sgx", no_std)]
-#[cfg(feature = "mesalock_sgx")]
+#![cfg_attr(feature = "sgx", no_std)]
+#[cfg(feature = "sgx")]
 #[macro_use]
 extern crate sgx_tstd as std;
 
-#[cfg(feature = "mesalock_sgx")]
+#[cfg(all(feature = "sgx", feature = "libos"))]
+compile_error!("feature \"sgx\" and feature \"libos\" cannot be enabled at the same time");
+
+#[cfg(feature = "sgx")]
 use std::prelude::v1::*;
 
+#[macro_use]
 mod attestation;
+mod audit;
+mod cancel;
+mod clock;
+mod cose;
 mod crypto;
 mod error;
+mod executor_enclave;
 mod file;
 mod file_agent;
 mod function;
+mod function_manifest;
+mod held_output;
+mod list;
 mod macros;
+mod node;
+mod output_policy;
+mod payload_upload;
+mod permission;
+mod pipeline;
+pub mod platform;
+mod privacy_budget;
+mod python_bundle;
+mod quota;
+mod read_only;
+mod result_stream;
+mod retry;
 mod staged_file;
 mod staged_function;
 mod staged_task;
 mod storage;
 mod task;
+mod task_log;
+mod task_schedule;
 mod task_state;
+mod tenant_stats;
+mod trace;
+mod transparency;
+mod webhook;
 mod worker;
 
 pub use attestation::*;
+pub use audit::*;
+pub use cancel::*;
+pub use clock::*;
+pub use cose::*;
 pub use crypto::*;
 pub use error::*;
+pub use executor_enclave::*;
 pub use file::*;
 pub use file_agent::*;
 pub use function::*;
+pub use function_manifest::*;
+pub use held_output::*;
+pub use list::*;
 pub use macros::*;
+pub use node::*;
+pub use output_policy::*;
+pub use payload_upload::*;
+pub use permission::*;
+pub use pipeline::*;
+pub use privacy_budget::*;
+pub use python_bundle::*;
+pub use quota::*;
+pub use read_only::*;
+pub use result_stream::*;
+pub use retry::*;
 pub use staged_file::*;
 pub use staged_function::*;
 pub use staged_task::*;
 pub use storage::*;
 pub use task::*;
+pub use task_log::*;
+pub use task_schedule::*;
 pub use task_state::*;
+pub use tenant_stats::*;
+pub use trace::*;
+pub use transparency::*;
+pub use webhook::*;
 pub use worker::*;
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
 
+    use teaclave_test_utils::*;
+
     pub fn run_tests() -> bool {
-        worker::tests::run_tests()
+        run_tests!(
+            attestation::tests::run_tests,
+            audit::tests::run_tests,
+            cancel::tests::run_tests,
+            clock::tests::run_tests,
+            cose::tests::run_tests,
+            executor_enclave::tests::run_tests,
+            function::tests::run_tests,
+            function_manifest::tests::run_tests,
+            held_output::tests::run_tests,
+            list::tests::run_tests,
+            node::tests::run_tests,
+            output_policy::tests::run_tests,
+            payload_upload::tests::run_tests,
+            permission::tests::run_tests,
+            pipeline::tests::run_tests,
+            privacy_budget::tests::run_tests,
+            python_bundle::tests::run_tests,
+            quota::tests::run_tests,
+            read_only::tests::run_tests,
+            result_stream::tests::run_tests,
+            retry::tests::run_tests,
+            staged_function::tests::run_tests,
+            storage::tests::run_tests,
+            task_log::tests::run_tests,
+            task_schedule::tests::run_tests,
+            transparency::tests::run_tests,
+            webhook::tests::run_tests,
+            worker::tests::run_tests
+        )
     }
 }
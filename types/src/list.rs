@@ -0,0 +1,187 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{anyhow, Result};
+use std::prelude::v1::*;
+use uuid::Uuid;
+
+/// Items of a page when the list request does not set a limit.
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+/// Largest page, to which limits are capped.
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Filters, order and page of a list request. Items are sorted by their
+/// creation time, then by their ids, so that pages do not overlap.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ListOptions {
+    /// Only items created at or after this time, in seconds since the Unix
+    /// epoch.
+    pub created_after: Option<u64>,
+    /// Only items created before this time.
+    pub created_before: Option<u64>,
+    /// Newest items first.
+    pub descending: bool,
+    /// Items of the page, DEFAULT_PAGE_SIZE if 0.
+    pub limit: u32,
+    /// `next_cursor` of the previous page, none for the first page.
+    pub cursor: Option<String>,
+}
+
+impl ListOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn created_after(self, created_after: u64) -> Self {
+        Self {
+            created_after: Some(created_after),
+            ..self
+        }
+    }
+
+    pub fn created_before(self, created_before: u64) -> Self {
+        Self {
+            created_before: Some(created_before),
+            ..self
+        }
+    }
+
+    pub fn descending(self, descending: bool) -> Self {
+        Self { descending, ..self }
+    }
+
+    pub fn limit(self, limit: u32) -> Self {
+        Self { limit, ..self }
+    }
+
+    pub fn cursor(self, cursor: impl ToString) -> Self {
+        Self {
+            cursor: Some(cursor.to_string()),
+            ..self
+        }
+    }
+
+    /// The page of the items, given their creation times and ids, and the
+    /// cursor of the next page if there are more items.
+    pub fn page<T>(
+        &self,
+        items: Vec<T>,
+        key: impl Fn(&T) -> (u64, Uuid),
+    ) -> Result<(Vec<T>, Option<String>)> {
+        let cursor = match &self.cursor {
+            Some(cursor) => Some(parse_cursor(cursor)?),
+            None => None,
+        };
+        let mut items: Vec<((u64, Uuid), T)> = items
+            .into_iter()
+            .map(|item| (key(&item), item))
+            .filter(|((created_at, _), _)| {
+                self.created_after
+                    .map_or(true, |after| *created_at >= after)
+                    && self
+                        .created_before
+                        .map_or(true, |before| *created_at < before)
+            })
+            .filter(|(key, _)| match cursor {
+                Some(cursor) if self.descending => *key < cursor,
+                Some(cursor) => *key > cursor,
+                None => true,
+            })
+            .collect();
+        items.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if self.descending {
+            items.reverse();
+        }
+
+        let limit = match self.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        } as usize;
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items
+                .last()
+                .map(|((created_at, id), _)| format!("{}:{}", created_at, id))
+        } else {
+            None
+        };
+        Ok((
+            items.into_iter().map(|(_, item)| item).collect(),
+            next_cursor,
+        ))
+    }
+}
+
+fn parse_cursor(cursor: &str) -> Result<(u64, Uuid)> {
+    let mut parts = cursor.splitn(2, ':');
+    let created_at = parts.next().and_then(|s| s.parse().ok());
+    let id = parts.next().and_then(|s| Uuid::parse_str(s).ok());
+    match (created_at, id) {
+        (Some(created_at), Some(id)) => Ok((created_at, id)),
+        _ => Err(anyhow!("Invalid cursor: {}", cursor)),
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(test_pages, test_filters)
+    }
+
+    fn items() -> Vec<(u64, Uuid)> {
+        (0..5u64)
+            .map(|i| (10 * (i / 2), Uuid::from_u128(i as u128)))
+            .collect()
+    }
+
+    fn test_pages() {
+        let options = ListOptions::new().limit(2);
+        let (page, cursor) = options.page(items(), |item| *item).unwrap();
+        assert_eq!(page, items()[..2].to_vec());
+
+        let options = options.cursor(cursor.unwrap());
+        let (page, cursor) = options.page(items(), |item| *item).unwrap();
+        assert_eq!(page, items()[2..4].to_vec());
+
+        let options = options.cursor(cursor.unwrap());
+        let (page, cursor) = options.page(items(), |item| *item).unwrap();
+        assert_eq!(page, items()[4..].to_vec());
+        assert!(cursor.is_none());
+
+        let options = ListOptions::new().descending(true).limit(3);
+        let (page, cursor) = options.page(items(), |item| *item).unwrap();
+        let mut expected = items();
+        expected.reverse();
+        assert_eq!(page, expected[..3].to_vec());
+        let options = options.cursor(cursor.unwrap());
+        let (page, _) = options.page(items(), |item| *item).unwrap();
+        assert_eq!(page, expected[3..].to_vec());
+
+        let options = ListOptions::new().cursor("not a cursor");
+        assert!(options.page(items(), |item| *item).is_err());
+    }
+
+    fn test_filters() {
+        let options = ListOptions::new().created_after(10).created_before(20);
+        let (page, cursor) = options.page(items(), |item| *item).unwrap();
+        assert_eq!(page, items()[2..4].to_vec());
+        assert!(cursor.is_none());
+    }
+}
@@ -0,0 +1,245 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+const MAX_AFFINITY_LABELS: usize = 16;
+const MAX_LABEL_LEN: usize = 64;
+
+/// Key of the snapshot of the execution nodes written by the scheduler
+/// service, read by the management service.
+pub const EXECUTOR_NODES_KEY: &str = "executor-nodes";
+
+/// Resources of an execution node, reported when it registers with the
+/// scheduler service. Zero stands for unknown CPUs and memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NodeCapacity {
+    pub cpus: u32,
+    pub memory_mb: u64,
+    /// Tasks the node runs at once.
+    pub slots: u32,
+}
+
+impl Default for NodeCapacity {
+    fn default() -> Self {
+        Self {
+            cpus: 0,
+            memory_mb: 0,
+            slots: 1,
+        }
+    }
+}
+
+/// An execution node registered with the scheduler service, with the tasks
+/// dispatched to it and not finished yet.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ExecutorNode {
+    pub worker_id: String,
+    pub capacity: NodeCapacity,
+    pub running: u32,
+    /// Seconds since the Unix epoch.
+    pub registered_at: u64,
+    /// Seconds since the Unix epoch of the last heartbeat.
+    pub last_seen: u64,
+    /// Labels of the node in the runtime config, e.g., `icelake` or
+    /// `zone=a`.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Executors of the node, e.g., `builtin` or `mesapy`.
+    #[serde(default)]
+    pub executors: Vec<String>,
+    /// Builtin functions enabled on the node.
+    #[serde(default)]
+    pub builtin_functions: Vec<String>,
+}
+
+impl ExecutorNode {
+    pub fn new(worker_id: impl ToString, capacity: NodeCapacity, registered_at: u64) -> Self {
+        Self {
+            worker_id: worker_id.to_string(),
+            capacity,
+            running: 0,
+            registered_at,
+            last_seen: registered_at,
+            labels: Vec::new(),
+            executors: Vec::new(),
+            builtin_functions: Vec::new(),
+        }
+    }
+
+    pub fn labels(self, labels: Vec<String>) -> Self {
+        Self { labels, ..self }
+    }
+
+    pub fn executors(self, executors: Vec<String>) -> Self {
+        Self { executors, ..self }
+    }
+
+    pub fn builtin_functions(self, builtin_functions: Vec<String>) -> Self {
+        Self {
+            builtin_functions,
+            ..self
+        }
+    }
+
+    /// Fraction of the slots of the node running tasks.
+    pub fn utilization(&self) -> f64 {
+        self.running as f64 / std::cmp::max(self.capacity.slots, 1) as f64
+    }
+
+    pub fn has_free_slot(&self) -> bool {
+        self.running < self.capacity.slots
+    }
+}
+
+/// The executors and builtin functions of an execution node, listed to the
+/// users choosing the executor of their functions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NodeExecutors {
+    pub worker_id: String,
+    pub labels: Vec<String>,
+    pub executors: Vec<String>,
+    pub builtin_functions: Vec<String>,
+}
+
+impl From<ExecutorNode> for NodeExecutors {
+    fn from(node: ExecutorNode) -> Self {
+        Self {
+            worker_id: node.worker_id,
+            labels: node.labels,
+            executors: node.executors,
+            builtin_functions: node.builtin_functions,
+        }
+    }
+}
+
+/// Constraints of a task on the labels of the nodes running it: a node has
+/// every label of the affinity and none of the anti-affinity. Labels are
+/// matched as a whole, e.g., `zone=a` does not match `zone=b`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TaskAffinity {
+    pub affinity: Vec<String>,
+    pub anti_affinity: Vec<String>,
+}
+
+impl TaskAffinity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn affinity(mut self, label: impl ToString) -> Self {
+        self.affinity.push(label.to_string());
+        self
+    }
+
+    pub fn anti_affinity(mut self, label: impl ToString) -> Self {
+        self.anti_affinity.push(label.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.affinity.is_empty() && self.anti_affinity.is_empty()
+    }
+
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.affinity.len() + self.anti_affinity.len() <= MAX_AFFINITY_LABELS,
+            "Over {} affinity labels",
+            MAX_AFFINITY_LABELS
+        );
+        for label in self.affinity.iter().chain(self.anti_affinity.iter()) {
+            ensure!(
+                !label.is_empty() && label.len() <= MAX_LABEL_LEN,
+                "Invalid label: {:?}",
+                label
+            );
+        }
+        ensure!(
+            !self
+                .affinity
+                .iter()
+                .any(|label| self.anti_affinity.contains(label)),
+            "Labels in both affinity and anti-affinity"
+        );
+        Ok(())
+    }
+
+    /// Whether a node of the labels can run the task.
+    pub fn matches(&self, labels: &[String]) -> bool {
+        self.affinity.iter().all(|label| labels.contains(label))
+            && !self
+                .anti_affinity
+                .iter()
+                .any(|label| labels.contains(label))
+    }
+}
+
+/// Snapshot of the execution nodes of the scheduler service.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ExecutorNodes {
+    pub nodes: Vec<ExecutorNode>,
+    /// Seconds since the Unix epoch.
+    pub updated_at: u64,
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn run_tests() -> bool {
+        let capacity = NodeCapacity {
+            cpus: 8,
+            memory_mb: 16384,
+            slots: 4,
+        };
+        let mut node = ExecutorNode::new("worker", capacity, 0);
+        assert!(node.has_free_slot());
+        assert_eq!(node.utilization(), 0.0);
+
+        node.running = 4;
+        assert!(!node.has_free_slot());
+        assert_eq!(node.utilization(), 1.0);
+
+        let capacity = NodeCapacity {
+            slots: 0,
+            ..NodeCapacity::default()
+        };
+        let node = ExecutorNode::new("worker", capacity, 0);
+        assert!(!node.has_free_slot());
+        assert_eq!(node.utilization(), 0.0);
+
+        let labels = vec!["icelake".to_string(), "zone=a".to_string()];
+        assert!(TaskAffinity::new().matches(&[]));
+        let affinity = TaskAffinity::new().affinity("icelake");
+        assert!(affinity.matches(&labels));
+        assert!(!affinity.matches(&[]));
+        let affinity = affinity.anti_affinity("zone=b");
+        assert!(affinity.check().is_ok());
+        assert!(affinity.matches(&labels));
+        let affinity = TaskAffinity::new().anti_affinity("zone=a");
+        assert!(!affinity.matches(&labels));
+        assert!(affinity.matches(&[]));
+
+        let affinity = TaskAffinity::new()
+            .affinity("zone=a")
+            .anti_affinity("zone=a");
+        assert!(affinity.check().is_err());
+        assert!(TaskAffinity::new().affinity("").check().is_err());
+        true
+    }
+}
@@ -0,0 +1,297 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::prelude::v1::*;
+
+// Columns of a CSV output with a header policy.
+const MAX_COLUMNS: usize = 1024;
+
+/// Format of an output, checked before the output is encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OutputFormat {
+    Any,
+    /// UTF-8 text.
+    Text,
+    /// UTF-8 rows of comma-separated fields, with the column names in the
+    /// first row.
+    Csv,
+    Json,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Any
+    }
+}
+
+impl std::convert::TryFrom<&str> for OutputFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(format: &str) -> Result<Self> {
+        let format = match format {
+            "any" | "" => OutputFormat::Any,
+            "text" => OutputFormat::Text,
+            "csv" => OutputFormat::Csv,
+            "json" => OutputFormat::Json,
+            _ => bail!("Unsupported output format: {}", format),
+        };
+        Ok(format)
+    }
+}
+
+impl std::convert::TryFrom<String> for OutputFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(format: String) -> Result<Self> {
+        format.as_str().try_into()
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OutputFormat::Any => write!(f, "any"),
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Policy of an output declared by the owner of the function when it is
+/// registered, and enforced by the execution service before the output is
+/// encrypted and uploaded, so that task arguments cannot turn the function
+/// into a channel for the inputs. A task violating the policy fails without
+/// releasing any output.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct OutputPolicy {
+    /// Bytes the function may write to the output.
+    pub max_bytes: Option<u64>,
+    pub format: OutputFormat,
+    /// Column names of CSV outputs in order, any if empty.
+    pub columns: Vec<String>,
+    /// Column of CSV outputs with the number of records aggregated into each
+    /// row, which must be at least `min_count`.
+    pub count_column: Option<String>,
+    pub min_count: u64,
+}
+
+impl OutputPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_bytes(self, max_bytes: u64) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            ..self
+        }
+    }
+
+    pub fn format(self, format: OutputFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    pub fn columns(self, columns: Vec<String>) -> Self {
+        Self { columns, ..self }
+    }
+
+    pub fn aggregation_threshold(self, count_column: impl ToString, min_count: u64) -> Self {
+        Self {
+            count_column: Some(count_column.to_string()),
+            min_count,
+            ..self
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Checks the policy itself when the function is registered.
+    pub fn validate(&self) -> Result<()> {
+        let is_csv = self.format == OutputFormat::Csv;
+        ensure!(
+            self.columns.is_empty() || is_csv,
+            "Columns need the csv format"
+        );
+        ensure!(self.columns.len() <= MAX_COLUMNS, "Too many columns");
+        match &self.count_column {
+            Some(column) => {
+                ensure!(is_csv, "Aggregation thresholds need the csv format");
+                ensure!(self.min_count > 0, "Aggregation threshold must be positive");
+                ensure!(
+                    self.columns.is_empty() || self.columns.contains(column),
+                    "Count column not among the columns"
+                );
+            }
+            None => ensure!(self.min_count == 0, "Aggregation threshold needs a column"),
+        }
+        Ok(())
+    }
+
+    /// Checks the plaintext of an output against the policy.
+    pub fn check(&self, content: &[u8]) -> Result<()> {
+        if let Some(max_bytes) = self.max_bytes {
+            ensure!(
+                content.len() as u64 <= max_bytes,
+                "{} bytes over the limit of {}",
+                content.len(),
+                max_bytes
+            );
+        }
+        match self.format {
+            OutputFormat::Any => Ok(()),
+            OutputFormat::Text => std::str::from_utf8(content)
+                .map(|_| ())
+                .map_err(|_| anyhow!("Not UTF-8 text")),
+            OutputFormat::Json => serde_json::from_slice::<serde_json::Value>(content)
+                .map(|_| ())
+                .map_err(|e| anyhow!("Not JSON: {}", e)),
+            OutputFormat::Csv => self.check_csv(content),
+        }
+    }
+
+    fn check_csv(&self, content: &[u8]) -> Result<()> {
+        let content = std::str::from_utf8(content).map_err(|_| anyhow!("Not UTF-8 text"))?;
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let header = match lines.next() {
+            Some(header) => split_csv_line(header)?,
+            // Nothing to check in an empty output.
+            None => return Ok(()),
+        };
+        if !self.columns.is_empty() {
+            ensure!(header == self.columns, "Columns {:?} not allowed", header);
+        }
+        let count_index = match &self.count_column {
+            Some(column) => Some(
+                header
+                    .iter()
+                    .position(|name| name == column)
+                    .ok_or_else(|| anyhow!("Missing count column {}", column))?,
+            ),
+            None => None,
+        };
+        for (i, line) in lines.enumerate() {
+            let fields = split_csv_line(line)?;
+            ensure!(
+                fields.len() == header.len(),
+                "Row {} has {} fields, expected {}",
+                i + 1,
+                fields.len(),
+                header.len()
+            );
+            if let Some(index) = count_index {
+                let count: u64 = fields[index]
+                    .parse()
+                    .map_err(|_| anyhow!("Row {} has no count", i + 1))?;
+                ensure!(
+                    count >= self.min_count,
+                    "Row {} aggregates {} records, fewer than {}",
+                    i + 1,
+                    count,
+                    self.min_count
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// Fields of a CSV line, trimmed, where double quotes enclose fields with
+// commas and a doubled quote is a quote.
+fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    ensure!(!quoted, "Unterminated quote");
+    fields.push(field.trim().to_string());
+    Ok(fields)
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    pub fn run_tests() -> bool {
+        let policy = OutputPolicy::new().max_bytes(4);
+        assert!(policy.validate().is_ok());
+        assert!(policy.check(b"1234").is_ok());
+        assert!(policy.check(b"12345").is_err());
+
+        let policy = OutputPolicy::new().format(OutputFormat::Json);
+        assert!(policy.check(br#"{"mean": 4.2}"#).is_ok());
+        assert!(policy.check(b"4.2,").is_err());
+        assert!(OutputPolicy::new()
+            .format(OutputFormat::Text)
+            .check(&[0xff])
+            .is_err());
+
+        let columns = vec!["zip".to_string(), "count".to_string()];
+        let policy = OutputPolicy::new()
+            .format(OutputFormat::Csv)
+            .columns(columns.clone())
+            .aggregation_threshold("count", 10);
+        assert!(policy.validate().is_ok());
+        assert!(policy.check(b"zip,count\n\"10001\",12\n10002,10\n").is_ok());
+        assert!(policy.check(b"zip,count\n10001,12\n10002,9\n").is_err());
+        assert!(policy.check(b"zip,count,name\n10001,12,Alice\n").is_err());
+        assert!(policy.check(b"zip,count\n10001\n").is_err());
+        assert!(policy.check(b"").is_ok());
+
+        // Thresholds and columns apply to CSV outputs only.
+        assert!(OutputPolicy::new()
+            .aggregation_threshold("count", 10)
+            .validate()
+            .is_err());
+        assert!(OutputPolicy::new()
+            .format(OutputFormat::Csv)
+            .columns(columns)
+            .aggregation_threshold("total", 10)
+            .validate()
+            .is_err());
+        assert!(OutputPolicy::new()
+            .format(OutputFormat::Csv)
+            .aggregation_threshold("count", 0)
+            .validate()
+            .is_err());
+
+        assert_eq!(
+            split_csv_line(r#"a, "b, ""c""" ,d"#).unwrap(),
+            vec!["a", r#"b, "c""#, "d"]
+        );
+        assert_eq!(OutputFormat::try_from("").unwrap(), OutputFormat::Any);
+        assert_eq!(OutputFormat::try_from("csv").unwrap(), OutputFormat::Csv);
+        assert!(OutputFormat::try_from("xml").is_err());
+        true
+    }
+}
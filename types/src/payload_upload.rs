@@ -0,0 +1,201 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{Storable, UserID};
+use anyhow::{ensure, Result};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use uuid::Uuid;
+
+const UPLOAD_PREFIX: &str = "upload";
+
+/// A function payload uploaded in parts, kept by the management service
+/// until it is registered with a function.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PayloadUpload {
+    pub id: Uuid,
+    pub owner: UserID,
+    pub total_len: u64,
+    pub part_size: u64,
+    /// SHA-256 of the whole payload.
+    pub payload_hash: Vec<u8>,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    /// The assembled payload, once committed.
+    pub payload: Option<Vec<u8>>,
+}
+
+impl PayloadUpload {
+    pub fn new(
+        id: Uuid,
+        owner: impl Into<UserID>,
+        total_len: u64,
+        part_size: u64,
+        payload_hash: Vec<u8>,
+        created_at: u64,
+    ) -> Result<Self> {
+        ensure!(part_size > 0, "invalid part size");
+        ensure!(
+            payload_hash.len() == digest::SHA256_OUTPUT_LEN,
+            "invalid payload hash"
+        );
+        let upload = Self {
+            id,
+            owner: owner.into(),
+            total_len,
+            part_size,
+            payload_hash,
+            created_at,
+            payload: None,
+        };
+        ensure!(upload.part_count_u64() <= u32::MAX as u64, "too many parts");
+        Ok(upload)
+    }
+
+    fn part_count_u64(&self) -> u64 {
+        (self.total_len + self.part_size - 1) / self.part_size
+    }
+
+    pub fn part_count(&self) -> u32 {
+        self.part_count_u64() as u32
+    }
+
+    /// Checks the length and hash of a part.
+    pub fn check_part(&self, part_number: u32, data: &[u8], part_hash: &[u8]) -> Result<()> {
+        ensure!(part_number < self.part_count(), "invalid part number");
+        let offset = part_number as u64 * self.part_size;
+        let len = std::cmp::min(self.part_size, self.total_len - offset);
+        ensure!(data.len() as u64 == len, "invalid part length");
+        ensure!(
+            digest::digest(&digest::SHA256, data).as_ref() == part_hash,
+            "part hash mismatched"
+        );
+        Ok(())
+    }
+
+    /// Key of a part in the storage service.
+    pub fn part_key(&self, part_number: u32) -> Vec<u8> {
+        format!("{}-part-{}", self.key_string(), part_number).into_bytes()
+    }
+}
+
+impl Storable for PayloadUpload {
+    fn key_prefix() -> &'static str {
+        UPLOAD_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Uploads of a user which have not been consumed by a function or expired,
+/// kept to limit the uploads and the bytes they stage.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OpenUploads {
+    uploads: Vec<OpenUpload>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OpenUpload {
+    id: Uuid,
+    total_len: u64,
+    /// Seconds since the Unix epoch.
+    expires_at: u64,
+}
+
+impl OpenUploads {
+    /// Forgets the uploads expired at `now`.
+    pub fn prune(&mut self, now: u64) {
+        self.uploads.retain(|upload| upload.expires_at > now);
+    }
+
+    pub fn len(&self) -> usize {
+        self.uploads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uploads.is_empty()
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.uploads.iter().any(|upload| &upload.id == id)
+    }
+
+    /// Bytes of the payloads of the uploads, staged in parts or committed.
+    pub fn staged_bytes(&self) -> u64 {
+        self.uploads.iter().map(|upload| upload.total_len).sum()
+    }
+
+    /// Adds the upload, or extends its expiry if it is open.
+    pub fn open(&mut self, upload: &PayloadUpload, expires_at: u64) {
+        self.close(&upload.id);
+        self.uploads.push(OpenUpload {
+            id: upload.id,
+            total_len: upload.total_len,
+            expires_at,
+        });
+    }
+
+    pub fn close(&mut self, id: &Uuid) {
+        self.uploads.retain(|upload| &upload.id != id);
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::platform;
+
+    pub fn run_tests() -> bool {
+        let payload = b"Hello, Teaclave!".to_vec();
+        let hash = |data: &[u8]| digest::digest(&digest::SHA256, data).as_ref().to_vec();
+        let upload = PayloadUpload::new(
+            platform::rand::new_uuid(),
+            "user",
+            payload.len() as u64,
+            6,
+            hash(&payload),
+            0,
+        )
+        .unwrap();
+        assert_eq!(upload.part_count(), 3);
+
+        let parts: Vec<Vec<u8>> = payload.chunks(6).map(|part| part.to_vec()).collect();
+        for (i, part) in parts.iter().enumerate() {
+            assert!(upload.check_part(i as u32, part, &hash(part)).is_ok());
+        }
+        assert!(upload.check_part(0, &parts[0], &hash(&parts[1])).is_err());
+        assert!(upload.check_part(1, &parts[2], &hash(&parts[2])).is_err());
+        assert!(upload.check_part(3, b"", &hash(b"")).is_err());
+
+        let mut open_uploads = OpenUploads::default();
+        open_uploads.open(&upload, 10);
+        open_uploads.open(&upload, 20);
+        assert_eq!(open_uploads.len(), 1);
+        assert_eq!(open_uploads.staged_bytes(), payload.len() as u64);
+        open_uploads.prune(15);
+        assert!(open_uploads.contains(&upload.id));
+        open_uploads.prune(20);
+        assert!(open_uploads.is_empty());
+        open_uploads.open(&upload, 10);
+        open_uploads.close(&upload.id);
+        assert!(open_uploads.is_empty());
+        true
+    }
+}
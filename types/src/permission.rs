@@ -0,0 +1,141 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+/// Permissions granted to users through the roles of the authentication
+/// service.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    RegisterFunction,
+    InvokeTask,
+    /// Creating roles and assigning them to users, and other platform
+    /// administration like the read-only mode.
+    ManageUsers,
+    /// Reading tasks and outputs of other users.
+    ReadAnyOutput,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 4] = [
+        Permission::RegisterFunction,
+        Permission::InvokeTask,
+        Permission::ManageUsers,
+        Permission::ReadAnyOutput,
+    ];
+
+    /// Permissions of the users without other roles, as registered users had
+    /// before roles were introduced.
+    pub const DEFAULT: [Permission; 2] = [Permission::RegisterFunction, Permission::InvokeTask];
+
+    /// The permission required for the frontend operation, if any.
+    pub fn required_for(operation: &str) -> Option<Self> {
+        match operation {
+            "register_function"
+            | "begin_payload_upload"
+            | "upload_part"
+            | "commit_payload"
+            | "rollback_function" => Some(Permission::RegisterFunction),
+            "invoke_task"
+            | "invoke_tasks"
+            | "create_scheduled_task"
+            | "resume_scheduled_task"
+            | "create_pipeline" => Some(Permission::InvokeTask),
+            "enter_read_only_mode"
+            | "exit_read_only_mode"
+            | "transfer_ownership"
+            | "set_user_quota"
+            | "check_consistency" => Some(Permission::ManageUsers),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::RegisterFunction => "register_function",
+            Permission::InvokeTask => "invoke_task",
+            Permission::ManageUsers => "manage_users",
+            Permission::ReadAnyOutput => "read_any_output",
+        }
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::convert::TryFrom<&str> for Permission {
+    type Error = anyhow::Error;
+
+    fn try_from(permission: &str) -> Result<Self> {
+        match Permission::ALL.iter().find(|p| p.as_str() == permission) {
+            Some(p) => Ok(*p),
+            None => bail!("Invalid permission: {}", permission),
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    pub fn run_tests() -> bool {
+        for permission in Permission::ALL.iter() {
+            assert_eq!(
+                Permission::try_from(permission.as_str()).unwrap(),
+                *permission
+            );
+        }
+        assert!(Permission::try_from("admin").is_err());
+        assert_eq!(
+            Permission::required_for("invoke_task"),
+            Some(Permission::InvokeTask)
+        );
+        assert_eq!(
+            Permission::required_for("invoke_tasks"),
+            Some(Permission::InvokeTask)
+        );
+        assert_eq!(
+            Permission::required_for("enter_read_only_mode"),
+            Some(Permission::ManageUsers)
+        );
+        assert_eq!(
+            Permission::required_for("transfer_ownership"),
+            Some(Permission::ManageUsers)
+        );
+        assert_eq!(
+            Permission::required_for("set_user_quota"),
+            Some(Permission::ManageUsers)
+        );
+        assert_eq!(
+            Permission::required_for("check_consistency"),
+            Some(Permission::ManageUsers)
+        );
+        assert_eq!(
+            Permission::required_for("upload_part"),
+            Some(Permission::RegisterFunction)
+        );
+        assert_eq!(Permission::required_for("get_task"), None);
+        true
+    }
+}
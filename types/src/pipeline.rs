@@ -0,0 +1,287 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{platform, ExternalID, Storable, TaskState, TaskStatus, UserID};
+use anyhow::{bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use uuid::Uuid;
+
+const PIPELINE_PREFIX: &str = "pipeline";
+
+/// Wiring of an output file of a task to an input file of a downstream task:
+/// once the upstream task succeeds, its output is assigned as the input.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PipelineLink {
+    pub from_task: ExternalID,
+    pub output: String,
+    pub to_task: ExternalID,
+    pub input: String,
+}
+
+impl PipelineLink {
+    pub fn new(
+        from_task: ExternalID,
+        output: impl ToString,
+        to_task: ExternalID,
+        input: impl ToString,
+    ) -> Self {
+        Self {
+            from_task,
+            output: output.to_string(),
+            to_task,
+            input: input.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PipelineStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl std::convert::TryFrom<&str> for PipelineStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(status: &str) -> Result<Self> {
+        let status = match status {
+            "running" => PipelineStatus::Running,
+            "succeeded" => PipelineStatus::Succeeded,
+            "failed" => PipelineStatus::Failed,
+            _ => bail!("Unsupported pipeline status: {}", status),
+        };
+        Ok(status)
+    }
+}
+
+impl std::fmt::Display for PipelineStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PipelineStatus::Running => write!(f, "running"),
+            PipelineStatus::Succeeded => write!(f, "succeeded"),
+            PipelineStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A DAG of tasks of the same creator, whose outputs are wired to the inputs
+/// of downstream tasks. The management service invokes each task once its
+/// upstream tasks have succeeded and its linked inputs are assigned.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Pipeline {
+    pub id: Uuid,
+    pub creator: UserID,
+    /// Tasks in the order of their stages: each comes after its upstream
+    /// tasks.
+    pub tasks: Vec<ExternalID>,
+    pub links: Vec<PipelineLink>,
+    pub status: PipelineStatus,
+    /// Task whose failure failed the pipeline, if any.
+    pub failed_task: Option<ExternalID>,
+}
+
+impl Storable for Pipeline {
+    fn key_prefix() -> &'static str {
+        PIPELINE_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.id
+    }
+}
+
+impl Pipeline {
+    /// Checks that the links wire distinct inputs of the tasks without
+    /// cycles, and sorts the tasks by stage.
+    pub fn new(creator: UserID, tasks: Vec<ExternalID>, links: Vec<PipelineLink>) -> Result<Self> {
+        ensure!(!tasks.is_empty(), "pipeline has no task");
+        for (i, task) in tasks.iter().enumerate() {
+            ensure!(
+                !tasks[..i].contains(task),
+                "duplicate task {}",
+                task.to_string()
+            );
+        }
+        for (i, link) in links.iter().enumerate() {
+            ensure!(
+                tasks.contains(&link.from_task) && tasks.contains(&link.to_task),
+                "link of a task not in the pipeline"
+            );
+            ensure!(link.from_task != link.to_task, "link of a task to itself");
+            ensure!(
+                !links[..i]
+                    .iter()
+                    .any(|other| other.to_task == link.to_task && other.input == link.input),
+                "input {} of task {} is linked twice",
+                link.input,
+                link.to_task.to_string()
+            );
+        }
+
+        // Kahn's algorithm, keeping the order of the tasks within a stage
+        let mut pending = tasks;
+        let mut sorted: Vec<ExternalID> = Vec::new();
+        while !pending.is_empty() {
+            let (ready, blocked): (Vec<ExternalID>, Vec<ExternalID>) =
+                pending.into_iter().partition(|task| {
+                    links
+                        .iter()
+                        .filter(|link| &link.to_task == task)
+                        .all(|link| sorted.contains(&link.from_task))
+                });
+            ensure!(!ready.is_empty(), "links of the tasks form a cycle");
+            sorted.extend(ready);
+            pending = blocked;
+        }
+
+        Ok(Self {
+            id: platform::rand::new_uuid(),
+            creator,
+            tasks: sorted,
+            links,
+            status: PipelineStatus::Running,
+            failed_task: None,
+        })
+    }
+
+    /// Links to the inputs of `task`.
+    pub fn upstream_links<'a>(
+        &'a self,
+        task: &'a ExternalID,
+    ) -> impl Iterator<Item = &'a PipelineLink> + 'a {
+        self.links.iter().filter(move |link| &link.to_task == task)
+    }
+
+    /// Checks the tasks, given in the order of `self.tasks`: they are created
+    /// by the creator of the pipeline and not yet staged, the linked outputs
+    /// and inputs are declared with the same owners, including the creator
+    /// on whose behalf they are handed over, and the inputs are not assigned.
+    pub fn check_tasks(&self, states: &[TaskState]) -> Result<()> {
+        ensure!(states.len() == self.tasks.len(), "missing tasks");
+        let state_of = |id: &ExternalID| {
+            self.tasks
+                .iter()
+                .position(|task| task == id)
+                .map(|i| &states[i])
+        };
+        for ts in states {
+            ensure!(
+                ts.has_creator(&self.creator),
+                "task {} has another creator",
+                ts.external_id().to_string()
+            );
+            ensure!(
+                matches!(
+                    ts.status,
+                    TaskStatus::Created | TaskStatus::DataAssigned | TaskStatus::Approved
+                ),
+                "task {} is already invoked",
+                ts.external_id().to_string()
+            );
+        }
+        for link in self.links.iter() {
+            let (from, to) = match (state_of(&link.from_task), state_of(&link.to_task)) {
+                (Some(from), Some(to)) => (from, to),
+                _ => bail!("link of a task not in the pipeline"),
+            };
+            let owners = match from.outputs_ownership.get(&link.output) {
+                Some(owners) => owners,
+                None => bail!("no output {} to link", link.output),
+            };
+            ensure!(
+                owners.contains(&self.creator),
+                "output {} is not owned by the creator",
+                link.output
+            );
+            to.inputs_ownership.check(&link.input, owners)?;
+            ensure!(
+                to.assigned_inputs.get(&link.input).is_none(),
+                "input {} is already assigned",
+                link.input
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    pub fn run_tests() -> bool {
+        let task = |n: u128| ExternalID::new("task", Uuid::from_u128(n));
+        let link =
+            |from: u128, to: u128| PipelineLink::new(task(from), "output", task(to), "input");
+        let creator = UserID::from("user");
+
+        // Inputs are linked once.
+        let links = vec![link(1, 3), link(2, 3)];
+        let pipeline = Pipeline::new(creator.clone(), vec![task(1), task(2), task(3)], links);
+        assert!(pipeline.is_err());
+
+        // 3 depends on 1 and 2, which depends on 1.
+        let links = vec![
+            PipelineLink::new(task(1), "output", task(3), "input"),
+            PipelineLink::new(task(2), "output", task(3), "input2"),
+            link(1, 2),
+        ];
+        let pipeline =
+            Pipeline::new(creator.clone(), vec![task(3), task(2), task(1)], links).unwrap();
+        assert_eq!(pipeline.tasks, vec![task(1), task(2), task(3)]);
+        assert_eq!(pipeline.upstream_links(&task(3)).count(), 2);
+        assert_eq!(pipeline.status, PipelineStatus::Running);
+
+        let cycle = vec![link(1, 2), link(2, 1)];
+        assert!(Pipeline::new(creator.clone(), vec![task(1), task(2)], cycle).is_err());
+        assert!(Pipeline::new(creator.clone(), vec![task(1), task(1)], vec![]).is_err());
+        assert!(Pipeline::new(creator.clone(), vec![task(1)], vec![link(1, 2)]).is_err());
+
+        let upstream = TaskState {
+            task_id: Uuid::from_u128(1),
+            creator: creator.clone(),
+            outputs_ownership: vec![("output".to_string(), vec!["user"])]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let mut downstream = TaskState {
+            task_id: Uuid::from_u128(2),
+            creator: creator.clone(),
+            inputs_ownership: vec![("input".to_string(), vec!["user"])]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let pipeline = Pipeline::new(creator, vec![task(1), task(2)], vec![link(1, 2)]).unwrap();
+        let states = vec![upstream.clone(), downstream.clone()];
+        assert!(pipeline.check_tasks(&states).is_ok());
+        downstream.inputs_ownership = vec![("input".to_string(), vec!["user", "other"])]
+            .into_iter()
+            .collect();
+        assert!(pipeline.check_tasks(&[upstream, downstream]).is_err());
+
+        assert_eq!(
+            PipelineStatus::try_from("failed").unwrap(),
+            PipelineStatus::Failed
+        );
+        assert_eq!(PipelineStatus::Succeeded.to_string(), "succeeded");
+        true
+    }
+}
@@ -0,0 +1,68 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Shims of the APIs which differ between targets, so that code shared by
+//! the `std`, `sgx` and `libos` builds needs no cfg of its own. `libos` builds
+//! run on a library OS which provides the std APIs, so they share the `std`
+//! shims.
+
+/// Filesystem of the host. In enclaves, files are untrusted and must be
+/// protected (e.g., with `protected_fs`) or verified before use.
+pub mod fs {
+    #[cfg(not(feature = "sgx"))]
+    pub use std::fs::{copy, create_dir_all, read, remove_file, write, File, OpenOptions};
+    #[cfg(feature = "sgx")]
+    pub use std::untrusted::fs::{
+        copy, create_dir_all, read, remove_file, write, File, OpenOptions,
+    };
+}
+
+/// Wall-clock time. In enclaves, the time is provided by the host and is not
+/// trusted.
+pub mod time {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    #[cfg(feature = "sgx")]
+    use std::untrusted::time::SystemTimeEx;
+
+    pub fn now() -> SystemTime {
+        SystemTime::now()
+    }
+
+    /// Duration since the Unix epoch, or zero if the clock of the host is set
+    /// before it.
+    pub fn since_epoch() -> Duration {
+        now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+}
+
+/// Random numbers for keys and identifiers, drawn from `teaclave_crypto::rng`.
+/// Enclaves use the RDRAND instruction instead of the OS source, which is
+/// untrusted.
+pub mod rand {
+    pub use teaclave_crypto::rng::{fill_bytes, random_u64};
+    use uuid::{Builder, Uuid, Variant, Version};
+
+    /// A random (version 4) UUID.
+    pub fn new_uuid() -> Uuid {
+        let mut bytes = [0; 16];
+        fill_bytes(&mut bytes);
+        Builder::from_bytes(bytes)
+            .set_variant(Variant::RFC4122)
+            .set_version(Version::Random)
+            .build()
+    }
+}
@@ -0,0 +1,183 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{ExecutorType, FunctionArguments};
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+/// Builtin functions releasing differentially private statistics, the only
+/// functions which may read input files with privacy budgets.
+pub const DIFFERENTIAL_PRIVACY_FUNCTIONS: &[&str] = &["builtin-dp-aggregate"];
+
+// Slack of the comparisons of budgets, which are sums of many costs
+const BUDGET_TOLERANCE: f64 = 1e-9;
+
+pub fn is_differential_privacy_function(executor_type: ExecutorType, name: &str) -> bool {
+    executor_type == ExecutorType::Builtin && DIFFERENTIAL_PRIVACY_FUNCTIONS.contains(&name)
+}
+
+/// Privacy loss of a run of a differentially private function, given by
+/// the `epsilon` and `delta` arguments of its task.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrivacyCost {
+    pub epsilon: f64,
+    pub delta: f64,
+}
+
+impl PrivacyCost {
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        Self { epsilon, delta }
+    }
+
+    /// Numbers may be given as strings, as the arguments of the SDKs are.
+    /// Delta is zero if not given.
+    pub fn from_arguments(arguments: &FunctionArguments) -> Result<Self> {
+        let number = |key: &str| -> Result<Option<f64>> {
+            let value = match arguments.inner().get(key) {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+            let number = match value {
+                serde_json::Value::Number(number) => number.as_f64(),
+                serde_json::Value::String(string) => string.parse().ok(),
+                _ => None,
+            };
+            number
+                .map(Some)
+                .ok_or_else(|| anyhow!("Invalid argument {}: {}", key, value))
+        };
+        let epsilon = number("epsilon")?.ok_or_else(|| anyhow!("Missing argument epsilon"))?;
+        let cost = Self::new(epsilon, number("delta")?.unwrap_or(0.0));
+        cost.check()?;
+        Ok(cost)
+    }
+
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.epsilon.is_finite() && self.epsilon > 0.0,
+            "Epsilon must be positive"
+        );
+        ensure!(
+            self.delta >= 0.0 && self.delta < 1.0,
+            "Delta must be in [0, 1)"
+        );
+        Ok(())
+    }
+}
+
+/// Differential privacy budget of an input file, set by its owner when it is
+/// registered. The costs of the tasks reading the file are spent when they
+/// are invoked, adding up by sequential composition, and tasks whose cost
+/// exceeds the rest of the budget are rejected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct PrivacyBudget {
+    pub epsilon: f64,
+    pub delta: f64,
+    #[serde(default)]
+    pub spent_epsilon: f64,
+    #[serde(default)]
+    pub spent_delta: f64,
+}
+
+impl PrivacyBudget {
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        Self {
+            epsilon,
+            delta,
+            ..Self::default()
+        }
+    }
+
+    pub fn check(&self) -> Result<()> {
+        PrivacyCost::new(self.epsilon, self.delta).check()
+    }
+
+    pub fn remaining(&self) -> PrivacyCost {
+        PrivacyCost::new(
+            (self.epsilon - self.spent_epsilon).max(0.0),
+            (self.delta - self.spent_delta).max(0.0),
+        )
+    }
+
+    pub fn can_spend(&self, cost: &PrivacyCost) -> bool {
+        let remaining = self.remaining();
+        cost.epsilon <= remaining.epsilon + BUDGET_TOLERANCE
+            && cost.delta <= remaining.delta + BUDGET_TOLERANCE
+    }
+
+    pub fn spend(&mut self, cost: &PrivacyCost) -> Result<()> {
+        ensure!(self.can_spend(cost), "Privacy budget exhausted");
+        self.spent_epsilon += cost.epsilon;
+        self.spent_delta += cost.delta;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+
+    pub fn run_tests() -> bool {
+        let arguments = FunctionArguments::from_json(json!({
+            "aggregate": "count",
+            "epsilon": 0.5,
+        }))
+        .unwrap();
+        let cost = PrivacyCost::from_arguments(&arguments).unwrap();
+        assert_eq!(cost, PrivacyCost::new(0.5, 0.0));
+        let arguments = FunctionArguments::from_json(json!({
+            "epsilon": "0.25",
+            "delta": "1e-6",
+        }))
+        .unwrap();
+        let cost = PrivacyCost::from_arguments(&arguments).unwrap();
+        assert_eq!(cost, PrivacyCost::new(0.25, 1e-6));
+        for arguments in [
+            json!({}),
+            json!({"epsilon": 0}),
+            json!({"epsilon": "many"}),
+            json!({"epsilon": 1, "delta": 1}),
+        ]
+        .iter()
+        {
+            let arguments = FunctionArguments::from_json(arguments.clone()).unwrap();
+            assert!(PrivacyCost::from_arguments(&arguments).is_err());
+        }
+
+        let mut budget = PrivacyBudget::new(1.0, 1e-5);
+        assert!(budget.check().is_ok());
+        for _ in 0..10 {
+            budget.spend(&PrivacyCost::new(0.1, 1e-6)).unwrap();
+        }
+        assert!(budget.remaining().epsilon < BUDGET_TOLERANCE);
+        assert!(!budget.can_spend(&PrivacyCost::new(0.01, 0.0)));
+        assert!(budget.spend(&PrivacyCost::new(0.01, 0.0)).is_err());
+        assert!(PrivacyBudget::new(0.0, 0.0).check().is_err());
+
+        assert!(is_differential_privacy_function(
+            ExecutorType::Builtin,
+            "builtin-dp-aggregate"
+        ));
+        assert!(!is_differential_privacy_function(
+            ExecutorType::Python,
+            "builtin-dp-aggregate"
+        ));
+        true
+    }
+}
@@ -0,0 +1,397 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{anyhow, bail, ensure, Result};
+use std::collections::HashSet;
+use std::prelude::v1::*;
+
+/// Modules in a bundle of a Python function.
+pub const MAX_PYTHON_BUNDLE_MODULES: usize = 1024;
+/// Bytes of the sources in a bundle of a Python function, once unpacked.
+pub const MAX_PYTHON_BUNDLE_LEN: usize = 16 * 1024 * 1024;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+const CENTRAL_HEADER_LEN: usize = 46;
+const LOCAL_HEADER_LEN: usize = 30;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+const FLAG_ENCRYPTED: u16 = 1;
+
+/// A module of a bundle, e.g., `pkg/util.py` as `pkg.util`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PythonModule {
+    pub name: String,
+    pub path: String,
+    /// Whether the module is the `__init__.py` of a package.
+    pub is_package: bool,
+    pub source: Vec<u8>,
+}
+
+/// Pure-Python modules registered with a function in a zip archive, which
+/// the MesaPy executor makes importable before running the function.
+#[derive(Debug, Default)]
+pub struct PythonBundle {
+    pub modules: Vec<PythonModule>,
+}
+
+impl PythonBundle {
+    /// Unpacks a zip archive of `.py` files, stored or deflated, whose
+    /// directories are packages with an `__init__.py`.
+    pub fn from_zip(zip: &[u8]) -> Result<Self> {
+        let end = find_end_of_central_directory(zip)?;
+        let entries = read_u16(zip, end + 10)? as usize;
+        let mut offset = read_u32(zip, end + 16)? as usize;
+        ensure!(
+            entries <= MAX_PYTHON_BUNDLE_MODULES,
+            "Too many files in the bundle: {}",
+            entries
+        );
+
+        let mut modules = Vec::new();
+        let mut unpacked_len = 0;
+        for _ in 0..entries {
+            ensure!(
+                read_u32(zip, offset)? == CENTRAL_HEADER_SIGNATURE,
+                "Invalid central directory of the bundle"
+            );
+            let flags = read_u16(zip, offset + 8)?;
+            let method = read_u16(zip, offset + 10)?;
+            let crc = read_u32(zip, offset + 16)?;
+            let compressed_len = read_u32(zip, offset + 20)? as usize;
+            let len = read_u32(zip, offset + 24)? as usize;
+            let name_len = read_u16(zip, offset + 28)? as usize;
+            let extra_len = read_u16(zip, offset + 30)? as usize;
+            let comment_len = read_u16(zip, offset + 32)? as usize;
+            let local_offset = read_u32(zip, offset + 42)? as usize;
+            let path = std::str::from_utf8(slice(zip, offset + CENTRAL_HEADER_LEN, name_len)?)
+                .map_err(|_| anyhow!("Invalid file name in the bundle"))?
+                .to_string();
+            offset += CENTRAL_HEADER_LEN + name_len + extra_len + comment_len;
+
+            // Directories are implied by the paths of the modules.
+            if path.ends_with('/') {
+                continue;
+            }
+            ensure!(
+                flags & FLAG_ENCRYPTED == 0,
+                "Encrypted file in the bundle: {}",
+                path
+            );
+            unpacked_len += len;
+            ensure!(
+                unpacked_len <= MAX_PYTHON_BUNDLE_LEN,
+                "Bundle larger than {} bytes once unpacked",
+                MAX_PYTHON_BUNDLE_LEN
+            );
+            let (name, is_package) = module_name(&path)?;
+
+            ensure!(
+                read_u32(zip, local_offset)? == LOCAL_HEADER_SIGNATURE,
+                "Invalid local header of {} in the bundle",
+                path
+            );
+            let data_offset = local_offset
+                + LOCAL_HEADER_LEN
+                + read_u16(zip, local_offset + 26)? as usize
+                + read_u16(zip, local_offset + 28)? as usize;
+            let data = slice(zip, data_offset, compressed_len)?;
+            let source = match method {
+                METHOD_STORED => data.to_vec(),
+                METHOD_DEFLATED => inflate(data, len)?,
+                _ => bail!("Unsupported compression of {} in the bundle", path),
+            };
+            ensure!(
+                source.len() == len && crc32(&source) == crc,
+                "Corrupted file in the bundle: {}",
+                path
+            );
+            modules.push(PythonModule {
+                name,
+                path,
+                is_package,
+                source,
+            });
+        }
+
+        let bundle = Self { modules };
+        bundle.check_packages()?;
+        Ok(bundle)
+    }
+
+    // Python 2 imports modules of packages only, so every directory of a
+    // module needs an `__init__.py`.
+    fn check_packages(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for module in self.modules.iter() {
+            ensure!(
+                names.insert(module.name.as_str()),
+                "Module {} in the bundle twice",
+                module.name
+            );
+        }
+        for module in self.modules.iter() {
+            let mut parent = module.name.as_str();
+            while let Some(index) = parent.rfind('.') {
+                parent = &parent[..index];
+                ensure!(
+                    self.modules
+                        .iter()
+                        .any(|m| m.is_package && m.name == parent),
+                    "Package {} of {} has no __init__.py in the bundle",
+                    parent,
+                    module.path
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_end_of_central_directory(zip: &[u8]) -> Result<usize> {
+    ensure!(
+        zip.len() >= END_OF_CENTRAL_DIRECTORY_LEN,
+        "The bundle is not a zip archive"
+    );
+    // The record is followed by a comment of at most 64 KiB.
+    let last = zip.len() - END_OF_CENTRAL_DIRECTORY_LEN;
+    let first = last.saturating_sub(u16::max_value() as usize);
+    (first..=last)
+        .rev()
+        .find(|&offset| read_u32(zip, offset).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or_else(|| anyhow!("The bundle is not a zip archive"))
+}
+
+// Maps `pkg/util.py` to `pkg.util` and `pkg/__init__.py` to the package
+// `pkg`, whose parts must be identifiers.
+fn module_name(path: &str) -> Result<(String, bool)> {
+    ensure!(
+        path.ends_with(".py"),
+        "Only Python modules are allowed in the bundle: {}",
+        path
+    );
+    let mut parts: Vec<&str> = path[..path.len() - 3].split('/').collect();
+    let is_package = parts.last() == Some(&"__init__");
+    if is_package {
+        parts.pop();
+    }
+    ensure!(
+        !parts.is_empty() && parts.iter().all(|part| is_identifier(part)),
+        "Invalid module path in the bundle: {}",
+        path
+    );
+    Ok((parts.join("."), is_package))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+// Inflates raw deflate data, failing as soon as the output exceeds the size
+// recorded in the archive.
+fn inflate(data: &[u8], len: usize) -> Result<Vec<u8>> {
+    let mut stream = inflate::InflateStream::new();
+    let mut output = Vec::new();
+    let mut offset = 0;
+    loop {
+        let (consumed, chunk) = stream
+            .update(&data[offset..])
+            .map_err(|e| anyhow!("Decompression error: {}", e))?;
+        if consumed == 0 && chunk.is_empty() {
+            break;
+        }
+        offset += consumed;
+        output.extend_from_slice(chunk);
+        ensure!(output.len() <= len, "Corrupted file in the bundle");
+    }
+    Ok(output)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn slice(zip: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| zip.get(offset..end))
+        .ok_or_else(|| anyhow!("Truncated bundle"))
+}
+
+fn read_u16(zip: &[u8], offset: usize) -> Result<u16> {
+    let bytes = slice(zip, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(zip: &[u8], offset: usize) -> Result<u32> {
+    let bytes = slice(zip, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_test_utils::*;
+
+    // "def double(x):\n    return 2 * x\n", deflated
+    const DEFLATED_UTIL: &[u8] = &[
+        0x4b, 0x49, 0x4d, 0x53, 0x48, 0xc9, 0x2f, 0x4d, 0xca, 0x49, 0xd5, 0xa8, 0xd0, 0xb4, 0xe2,
+        0x52, 0x00, 0x82, 0xa2, 0xd4, 0x92, 0xd2, 0xa2, 0x3c, 0x05, 0x23, 0x05, 0x2d, 0x85, 0x0a,
+        0x2e, 0x00,
+    ];
+    const UTIL: &[u8] = b"def double(x):\n    return 2 * x\n";
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_crc32,
+            test_from_zip,
+            test_invalid_zip,
+            test_invalid_modules
+        )
+    }
+
+    struct Entry<'a> {
+        path: &'a str,
+        method: u16,
+        data: &'a [u8],
+        source: &'a [u8],
+    }
+
+    fn stored<'a>(path: &'a str, source: &'a [u8]) -> Entry<'a> {
+        Entry {
+            path,
+            method: METHOD_STORED,
+            data: source,
+            source,
+        }
+    }
+
+    fn zip(entries: &[Entry]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut central = Vec::new();
+        for entry in entries {
+            let header = |signature: u32| {
+                let mut header = signature.to_le_bytes().to_vec();
+                header.extend_from_slice(&[20, 0, 0, 0]);
+                header.extend_from_slice(&entry.method.to_le_bytes());
+                header.extend_from_slice(&[0; 4]);
+                header.extend_from_slice(&crc32(entry.source).to_le_bytes());
+                header.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+                header.extend_from_slice(&(entry.source.len() as u32).to_le_bytes());
+                header.extend_from_slice(&(entry.path.len() as u16).to_le_bytes());
+                header.extend_from_slice(&[0; 2]);
+                header
+            };
+            let mut central_header = header(CENTRAL_HEADER_SIGNATURE);
+            // Version made by, then comment length, disks and attributes.
+            central_header.splice(4..4, vec![20, 0]);
+            central_header.extend_from_slice(&[0; 10]);
+            central_header.extend_from_slice(&(zip.len() as u32).to_le_bytes());
+            central_header.extend_from_slice(entry.path.as_bytes());
+            central.extend_from_slice(&central_header);
+
+            zip.extend_from_slice(&header(LOCAL_HEADER_SIGNATURE));
+            zip.extend_from_slice(entry.path.as_bytes());
+            zip.extend_from_slice(entry.data);
+        }
+        let central_offset = zip.len() as u32;
+        zip.extend_from_slice(&central);
+        zip.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&central_offset.to_le_bytes());
+        zip.extend_from_slice(&[0; 2]);
+        zip
+    }
+
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(UTIL), 0xc7bb_f5a3);
+    }
+
+    fn test_from_zip() {
+        let archive = zip(&[
+            stored("pkg/", b""),
+            stored("pkg/__init__.py", b""),
+            Entry {
+                path: "pkg/util.py",
+                method: METHOD_DEFLATED,
+                data: DEFLATED_UTIL,
+                source: UTIL,
+            },
+            stored("main_helper.py", b"import pkg.util\n"),
+        ]);
+        let bundle = PythonBundle::from_zip(&archive).unwrap();
+        let names: Vec<_> = bundle
+            .modules
+            .iter()
+            .map(|m| (m.name.as_str(), m.is_package))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("pkg", true), ("pkg.util", false), ("main_helper", false)]
+        );
+        assert_eq!(bundle.modules[1].path, "pkg/util.py");
+        assert_eq!(bundle.modules[1].source, UTIL);
+
+        let empty = PythonBundle::from_zip(&zip(&[])).unwrap();
+        assert!(empty.modules.is_empty());
+    }
+
+    fn test_invalid_zip() {
+        assert!(PythonBundle::from_zip(b"def entrypoint(argv): pass").is_err());
+
+        let mut archive = zip(&[stored("util.py", UTIL)]);
+        // Corrupt the source.
+        archive[LOCAL_HEADER_LEN + "util.py".len()] ^= 1;
+        assert!(PythonBundle::from_zip(&archive).is_err());
+
+        let archive = zip(&[stored("util.py", UTIL)]);
+        assert!(PythonBundle::from_zip(&archive[..archive.len() - 30]).is_err());
+    }
+
+    fn test_invalid_modules() {
+        let invalid = vec![
+            zip(&[stored("data.csv", b"1,2\n")]),
+            zip(&[stored("pkg/util.py", UTIL)]),
+            zip(&[stored("my-module.py", UTIL)]),
+            zip(&[stored("../util.py", UTIL)]),
+            zip(&[stored("util.py", UTIL), stored("util.py", UTIL)]),
+        ];
+        for archive in invalid {
+            assert!(PythonBundle::from_zip(&archive).is_err());
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use uuid::Uuid;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Limits of the usage of a user, set by users with the `manage_users`
+/// permission. Zero means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UserQuota {
+    /// Invoked tasks which have not finished yet.
+    pub max_concurrent_tasks: u64,
+    /// Tasks invoked since midnight (UTC).
+    pub max_tasks_per_day: u64,
+    /// Bytes of the input data registered inline, i.e., kept by the
+    /// platform.
+    pub max_input_bytes: u64,
+}
+
+impl UserQuota {
+    pub fn new(max_concurrent_tasks: u64, max_tasks_per_day: u64, max_input_bytes: u64) -> Self {
+        Self {
+            max_concurrent_tasks,
+            max_tasks_per_day,
+            max_input_bytes,
+        }
+    }
+
+    /// Checks that the user can invoke another task.
+    pub fn check_invocation(&self, usage: &QuotaUsage) -> Result<()> {
+        ensure!(
+            below(usage.concurrent_tasks, self.max_concurrent_tasks),
+            "limit of {} concurrent tasks reached",
+            self.max_concurrent_tasks
+        );
+        ensure!(
+            below(usage.tasks_today, self.max_tasks_per_day),
+            "limit of {} tasks per day reached",
+            self.max_tasks_per_day
+        );
+        Ok(())
+    }
+
+    /// Checks that the user can register another `len` bytes of input data.
+    pub fn check_input(&self, usage: &QuotaUsage, len: u64) -> Result<()> {
+        ensure!(
+            self.max_input_bytes == 0
+                || usage.input_bytes.saturating_add(len) <= self.max_input_bytes,
+            "limit of {} input bytes reached",
+            self.max_input_bytes
+        );
+        Ok(())
+    }
+}
+
+// Whether one more is allowed under the limit.
+fn below(count: u64, limit: u64) -> bool {
+    limit == 0 || count < limit
+}
+
+/// Usage of a user counted against the quota.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QuotaUsage {
+    pub concurrent_tasks: u64,
+    pub tasks_today: u64,
+    pub input_bytes: u64,
+}
+
+/// Usage of a user kept by the management service.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UsageRecord {
+    /// Days since the Unix epoch (UTC) when tasks_today were invoked.
+    pub day: u64,
+    pub tasks_today: u64,
+    /// Invoked tasks not seen finished yet.
+    pub active_tasks: Vec<Uuid>,
+    pub input_bytes: u64,
+}
+
+impl UsageRecord {
+    pub fn record_invocation(&mut self, task_id: Uuid, now_secs: u64) {
+        let day = now_secs / SECS_PER_DAY;
+        if day != self.day {
+            self.day = day;
+            self.tasks_today = 0;
+        }
+        self.tasks_today += 1;
+        self.active_tasks.push(task_id);
+    }
+
+    pub fn record_input(&mut self, len: u64) {
+        self.input_bytes = self.input_bytes.saturating_add(len);
+    }
+
+    /// Forgets the tasks for which `is_active` returns false.
+    pub fn retain_active(&mut self, is_active: impl FnMut(&Uuid) -> bool) {
+        self.active_tasks.retain(is_active);
+    }
+
+    pub fn usage(&self, now_secs: u64) -> QuotaUsage {
+        let tasks_today = if now_secs / SECS_PER_DAY == self.day {
+            self.tasks_today
+        } else {
+            0
+        };
+        QuotaUsage {
+            concurrent_tasks: self.active_tasks.len() as u64,
+            tasks_today,
+            input_bytes: self.input_bytes,
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::platform;
+
+    pub fn run_tests() -> bool {
+        let quota = UserQuota::new(2, 3, 100);
+        let mut record = UsageRecord::default();
+        let now = 10 * SECS_PER_DAY + 60;
+        let first = platform::rand::new_uuid();
+        record.record_invocation(first, now);
+        record.record_invocation(platform::rand::new_uuid(), now);
+        assert!(quota.check_invocation(&record.usage(now)).is_err());
+
+        // Finished tasks no longer count, unlike the tasks of the day.
+        record.retain_active(|task_id| task_id != &first);
+        assert!(quota.check_invocation(&record.usage(now)).is_ok());
+        record.record_invocation(platform::rand::new_uuid(), now);
+        record.retain_active(|_| false);
+        assert_eq!(record.usage(now).tasks_today, 3);
+        assert!(quota.check_invocation(&record.usage(now)).is_err());
+        let tomorrow = now + SECS_PER_DAY;
+        assert_eq!(record.usage(tomorrow).tasks_today, 0);
+        assert!(quota.check_invocation(&record.usage(tomorrow)).is_ok());
+
+        record.record_input(60);
+        assert!(quota.check_input(&record.usage(now), 40).is_ok());
+        assert!(quota.check_input(&record.usage(now), 41).is_err());
+
+        let unlimited = UserQuota::default();
+        assert!(unlimited.check_invocation(&record.usage(now)).is_ok());
+        assert!(unlimited.check_input(&record.usage(now), u64::MAX).is_ok());
+        true
+    }
+}
@@ -0,0 +1,106 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Emergency read-only mode, entered by platform admins during incident
+// response or storage migrations. The window is kept in the storage, so that
+// every instance of the management and authentication services rejects
+// mutating requests, and the scheduled runs and pipelines stop, until the
+// admin exits the mode or it expires. Expiry follows the clock of the host, which can only keep the
+// platform read-only, as it could by dropping requests anyway.
+
+use anyhow::{anyhow, ensure, Result};
+use std::prelude::v1::*;
+
+/// Key of the window of the read-only mode in the storage.
+pub const READ_ONLY_MODE_KEY: &str = "read-only-mode";
+/// The mode cannot be entered for more than a day at once.
+pub const MAX_READ_ONLY_SECS: u64 = 24 * 3600;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadOnlyWindow {
+    pub admin: String,
+    pub reason: String,
+    /// Seconds since the Unix epoch.
+    pub expires_at: u64,
+}
+
+impl ReadOnlyWindow {
+    pub fn new(admin: &str, reason: &str, duration_secs: u64, now: u64) -> Result<Self> {
+        ensure!(
+            duration_secs > 0 && duration_secs <= MAX_READ_ONLY_SECS,
+            "read-only mode lasts 1 to {} seconds",
+            MAX_READ_ONLY_SECS
+        );
+        ensure!(!reason.is_empty(), "missing reason of read-only mode");
+        Ok(Self {
+            admin: admin.to_string(),
+            reason: reason.to_string(),
+            expires_at: now + duration_secs,
+        })
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        self.expires_at > now
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        serde_json::json!({
+            "admin": self.admin,
+            "reason": self.reason,
+            "expires_at": self.expires_at,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_slice(bytes)?;
+        let field = |name: &str| value.get(name).ok_or_else(|| anyhow!("missing {}", name));
+        Ok(Self {
+            admin: field("admin")?
+                .as_str()
+                .ok_or_else(|| anyhow!("invalid admin"))?
+                .to_string(),
+            reason: field("reason")?
+                .as_str()
+                .ok_or_else(|| anyhow!("invalid reason"))?
+                .to_string(),
+            expires_at: field("expires_at")?
+                .as_u64()
+                .ok_or_else(|| anyhow!("invalid expiry"))?,
+        })
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn run_tests() -> bool {
+        assert!(ReadOnlyWindow::new("admin", "migration", 0, 100).is_err());
+        assert!(ReadOnlyWindow::new("admin", "migration", MAX_READ_ONLY_SECS + 1, 100).is_err());
+        assert!(ReadOnlyWindow::new("admin", "", 60, 100).is_err());
+
+        let window = ReadOnlyWindow::new("admin", "migration", 60, 100).unwrap();
+        assert!(window.is_active(159));
+        assert!(!window.is_active(160));
+        let decoded = ReadOnlyWindow::from_slice(&window.to_vec()).unwrap();
+        assert_eq!(decoded, window);
+        assert!(ReadOnlyWindow::from_slice(b"{\"admin\":\"admin\"}").is_err());
+        true
+    }
+}
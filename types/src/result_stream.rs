@@ -0,0 +1,160 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::Storable;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use std::sync::Arc;
+#[cfg(not(feature = "sgx"))]
+use std::sync::Mutex;
+#[cfg(feature = "sgx")]
+use std::sync::SgxMutex as Mutex;
+use uuid::Uuid;
+
+const TASK_RESULT_STREAM_PREFIX: &str = "resultstream";
+
+/// Bytes of intermediate results kept for a task; later chunks are dropped.
+pub const MAX_RESULT_STREAM_LEN: usize = 1024 * 1024;
+
+/// Intermediate result chunks emitted by a running function, shared between
+/// the runtime and the execution service which forwards them while the
+/// function runs.
+#[derive(Debug, Clone, Default)]
+pub struct ResultStream {
+    inner: Arc<Mutex<ResultStreamContent>>,
+}
+
+#[derive(Debug, Default)]
+struct ResultStreamContent {
+    // emitted since the last take
+    pending: Vec<Vec<u8>>,
+    len: usize,
+    dropped: bool,
+}
+
+impl ResultStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a chunk, unless the stream is full.
+    pub fn emit(&self, chunk: &[u8]) {
+        let mut stream = match self.inner.lock() {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        if stream.len + chunk.len() > MAX_RESULT_STREAM_LEN {
+            stream.dropped = true;
+            return;
+        }
+        stream.len += chunk.len();
+        stream.pending.push(chunk.to_vec());
+    }
+
+    /// Takes the chunks emitted since the last call and whether chunks were
+    /// dropped since then.
+    pub fn take(&self) -> (Vec<Vec<u8>>, bool) {
+        match self.inner.lock() {
+            Ok(mut stream) => {
+                let dropped = stream.dropped;
+                stream.dropped = false;
+                (std::mem::replace(&mut stream.pending, Vec::new()), dropped)
+            }
+            Err(_) => (Vec::new(), true),
+        }
+    }
+}
+
+/// The intermediate results of a task, appended by the scheduler service
+/// as the execution service forwards them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TaskResultStream {
+    pub task_id: Uuid,
+    pub chunks: Vec<Vec<u8>>,
+    /// Whether chunks were dropped as the stream exceeded
+    /// `MAX_RESULT_STREAM_LEN`.
+    pub truncated: bool,
+}
+
+impl TaskResultStream {
+    pub fn new(task_id: Uuid) -> Self {
+        Self {
+            task_id,
+            ..Self::default()
+        }
+    }
+
+    pub fn append(&mut self, chunks: Vec<Vec<u8>>, truncated: bool) {
+        self.chunks.extend(chunks);
+        self.truncated |= truncated;
+    }
+
+    /// Returns at most `limit` chunks from the chunk at `offset`; zero means
+    /// up to the end.
+    pub fn range(&self, offset: u64, limit: u64) -> &[Vec<u8>] {
+        let len = self.chunks.len() as u64;
+        let start = std::cmp::min(offset, len);
+        let end = if limit == 0 {
+            len
+        } else {
+            std::cmp::min(start.saturating_add(limit), len)
+        };
+        &self.chunks[start as usize..end as usize]
+    }
+}
+
+impl Storable for TaskResultStream {
+    fn key_prefix() -> &'static str {
+        TASK_RESULT_STREAM_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.task_id
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::platform;
+
+    pub fn run_tests() -> bool {
+        let stream = ResultStream::new();
+        let runtime_stream = stream.clone();
+        runtime_stream.emit(b"epoch 1");
+        runtime_stream.emit(b"epoch 2");
+        assert_eq!(
+            stream.take(),
+            (vec![b"epoch 1".to_vec(), b"epoch 2".to_vec()], false)
+        );
+        assert_eq!(stream.take(), (vec![], false));
+
+        let large = vec![0; MAX_RESULT_STREAM_LEN];
+        runtime_stream.emit(&large);
+        let (chunks, dropped) = stream.take();
+        assert!(chunks.is_empty());
+        assert!(dropped);
+
+        let mut task_stream = TaskResultStream::new(platform::rand::new_uuid());
+        task_stream.append(vec![b"epoch 1".to_vec(), b"epoch 2".to_vec()], false);
+        task_stream.append(vec![b"epoch 3".to_vec()], true);
+        assert!(task_stream.truncated);
+        assert_eq!(task_stream.range(1, 1), &[b"epoch 2".to_vec()]);
+        assert_eq!(task_stream.range(1, 0).len(), 2);
+        assert!(task_stream.range(5, 1).is_empty());
+        true
+    }
+}
@@ -0,0 +1,162 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::{TaskFailure, TaskFailureKind};
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use std::time::Duration;
+
+const MAX_ATTEMPTS: u32 = 10;
+const MAX_BACKOFF_SECS: u64 = 60 * 60;
+
+/// Failures of a task which are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RetryOn {
+    /// Failures of the platform rather than of the function, i.e., staging
+    /// timeouts and lost workers.
+    InfrastructureFailure,
+    AnyFailure,
+}
+
+impl Default for RetryOn {
+    fn default() -> Self {
+        RetryOn::InfrastructureFailure
+    }
+}
+
+/// Retries of a failed task, given when it is invoked and enforced by the
+/// scheduler service. The backoff is doubled after every retry. Tasks are
+/// run once by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RetryPolicy {
+    /// Runs of the task at most, including the first one.
+    pub max_attempts: u32,
+    pub backoff_secs: u64,
+    pub retry_on: RetryOn,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_secs: 0,
+            retry_on: RetryOn::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    pub fn backoff_secs(self, backoff_secs: u64) -> Self {
+        Self {
+            backoff_secs,
+            ..self
+        }
+    }
+
+    pub fn retry_on(self, retry_on: RetryOn) -> Self {
+        Self { retry_on, ..self }
+    }
+
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.max_attempts >= 1 && self.max_attempts <= MAX_ATTEMPTS,
+            "Attempts of a task not in 1..={}",
+            MAX_ATTEMPTS
+        );
+        ensure!(
+            self.backoff_secs <= MAX_BACKOFF_SECS,
+            "Backoff of a task over {}s",
+            MAX_BACKOFF_SECS
+        );
+        Ok(())
+    }
+
+    /// Returns how long to wait before retrying the task which failed after
+    /// `attempts` runs, or None if it is not retried. Held outputs are never
+    /// retried, as they are waiting for review.
+    pub fn retry_delay(&self, attempts: u32, failure: &TaskFailure) -> Option<Duration> {
+        if attempts >= self.max_attempts || failure.kind == TaskFailureKind::OutputHeld {
+            return None;
+        }
+        if self.retry_on == RetryOn::InfrastructureFailure && !failure.kind.is_infrastructure() {
+            return None;
+        }
+        let exponent = std::cmp::min(attempts.saturating_sub(1), 16);
+        let backoff = self.backoff_secs.saturating_mul(1 << exponent);
+        let backoff = std::cmp::min(backoff, MAX_BACKOFF_SECS);
+        Some(Duration::from_secs(backoff))
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn run_tests() -> bool {
+        let staging_timeout = TaskFailure {
+            reason: "timeout".to_string(),
+            kind: TaskFailureKind::StagingTimeout,
+        };
+        let error = TaskFailure::new("division by zero");
+
+        // Run once by default.
+        let policy = RetryPolicy::default();
+        assert!(policy.check().is_ok());
+        assert_eq!(policy.retry_delay(1, &staging_timeout), None);
+
+        let policy = RetryPolicy::new(3).backoff_secs(10);
+        assert_eq!(
+            policy.retry_delay(1, &staging_timeout),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            policy.retry_delay(2, &staging_timeout),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(policy.retry_delay(3, &staging_timeout), None);
+        assert_eq!(policy.retry_delay(1, &error), None);
+
+        let policy = policy.retry_on(RetryOn::AnyFailure);
+        assert_eq!(policy.retry_delay(1, &error), Some(Duration::from_secs(10)));
+        let held = TaskFailure {
+            reason: "held".to_string(),
+            kind: TaskFailureKind::OutputHeld,
+        };
+        assert_eq!(policy.retry_delay(1, &held), None);
+
+        let policy = RetryPolicy::new(10).backoff_secs(MAX_BACKOFF_SECS);
+        assert_eq!(
+            policy.retry_delay(9, &staging_timeout),
+            Some(Duration::from_secs(MAX_BACKOFF_SECS))
+        );
+
+        assert!(RetryPolicy::new(0).check().is_err());
+        assert!(RetryPolicy::new(MAX_ATTEMPTS + 1).check().is_err());
+        assert!(RetryPolicy::new(2)
+            .backoff_secs(MAX_BACKOFF_SECS + 1)
+            .check()
+            .is_err());
+        true
+    }
+}
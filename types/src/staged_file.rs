@@ -18,14 +18,11 @@
 use teaclave_crypto::TeaclaveFile128Key;
 
 use std::collections::HashMap;
-#[cfg(not(feature = "mesalock_sgx"))]
-use std::fs::File;
 use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 use std::prelude::v1::*;
-#[cfg(feature = "mesalock_sgx")]
-use std::untrusted::fs::File;
 
+use crate::platform::fs::File;
 use crate::FileAuthTag;
 use anyhow::Context;
 use protected_fs::ProtectedFile;
@@ -106,7 +103,8 @@ impl StagedFileInfo {
         Self::create_with_bytes(dst, &bytes)
     }
 
-    #[cfg(test_mode)]
+    /// Reads the content of the file, e.g., to scan an output written by a
+    /// function before it is uploaded.
     pub fn get_plaintext(&self) -> anyhow::Result<Vec<u8>> {
         let mut content = Vec::new();
         let mut f = ProtectedFile::open_ex(&self.path, &self.crypto_info.key)?;
@@ -148,6 +146,10 @@ impl StagedFiles {
         self.entries.get(key)
     }
 
+    pub fn iter(&self) -> std::collections::hash_map::Iter<String, StagedFileInfo> {
+        self.entries.iter()
+    }
+
     pub fn len(&self) -> usize {
         self.entries.len()
     }
@@ -15,15 +15,22 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::{Executor, ExecutorType, StagedFiles, TeaclaveRuntime};
+use crate::{
+    CancelToken, Executor, ExecutorType, FileAttributes, FunctionLog, ResultStream, StagedFiles,
+    TeaclaveRuntime,
+};
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::prelude::v1::*;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 
 pub type FunctionRuntime = Box<dyn TeaclaveRuntime + Send + Sync>;
+/// Non-secret key/values of the deployment and the task given to a function,
+/// e.g., to tune its batch size without registering it again.
+pub type FunctionEnv = HashMap<String, String>;
 type ArgumentValue = serde_json::Value;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
@@ -102,18 +109,130 @@ impl FunctionArguments {
     pub fn into_string(self) -> String {
         ArgumentValue::Object(self.inner).to_string()
     }
+
+    /// Replace the arguments with the overrides of the same keys, and add the
+    /// other overrides.
+    pub fn merge(mut self, overrides: FunctionArguments) -> Self {
+        self.inner.extend(overrides.inner);
+        self
+    }
+
+    /// Resolve `{{input.<name>.<attribute path>}}` templates in string
+    /// arguments with attributes of the input files. An argument consisting
+    /// of a single template takes the attribute value as is, e.g., a list of
+    /// columns, otherwise the value is formatted into the string. Only `{{`
+    /// followed by `input.` or `output.` starts a template, other braces are
+    /// kept, and `\{{` is a literal `{{`.
+    pub fn render(&self, inputs: &HashMap<String, FileAttributes>) -> Result<Self> {
+        let inner = self
+            .inner
+            .iter()
+            .map(|(k, v)| {
+                let value = match v {
+                    ArgumentValue::String(s) => render_template(s, inputs)
+                        .with_context(|| format!("cannot render argument: {}", k))?,
+                    _ => v.clone(),
+                };
+                Ok((k.to_owned(), value))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { inner })
+    }
+}
+
+const TEMPLATE_START: &str = "{{";
+const TEMPLATE_END: &str = "}}";
+const TEMPLATE_ESCAPE: &str = "\\";
+const TEMPLATE_SCOPES: &[&str] = &["input.", "output."];
+
+// Whether the text following `{{` is a template expression.
+fn is_template(expression: &str) -> bool {
+    let expression = expression.trim_start();
+    TEMPLATE_SCOPES
+        .iter()
+        .any(|scope| expression.starts_with(scope))
+}
+
+fn render_template(
+    template: &str,
+    inputs: &HashMap<String, FileAttributes>,
+) -> Result<ArgumentValue> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(TEMPLATE_START) {
+        let expression_start = start + TEMPLATE_START.len();
+        if rest[..start].ends_with(TEMPLATE_ESCAPE) {
+            rendered.push_str(&rest[..start - TEMPLATE_ESCAPE.len()]);
+            rendered.push_str(TEMPLATE_START);
+            rest = &rest[expression_start..];
+            continue;
+        }
+        if !is_template(&rest[expression_start..]) {
+            rendered.push_str(&rest[..expression_start]);
+            rest = &rest[expression_start..];
+            continue;
+        }
+        let end = rest[start..]
+            .find(TEMPLATE_END)
+            .ok_or_else(|| anyhow!("unclosed template"))?
+            + start;
+        let value = lookup_attribute(&rest[expression_start..end], inputs)?;
+        let tail = &rest[end + TEMPLATE_END.len()..];
+        if start == 0 && tail.is_empty() && rest.len() == template.len() {
+            return Ok(value.clone());
+        }
+
+        rendered.push_str(&rest[..start]);
+        match value {
+            ArgumentValue::String(s) => rendered.push_str(s),
+            _ => rendered.push_str(&value.to_string()),
+        }
+        rest = tail;
+    }
+    rendered.push_str(rest);
+
+    Ok(ArgumentValue::String(rendered))
+}
+
+fn lookup_attribute<'a>(
+    expression: &str,
+    inputs: &'a HashMap<String, FileAttributes>,
+) -> Result<&'a ArgumentValue> {
+    let expression = expression.trim();
+    let path: Vec<&str> = expression.split('.').collect();
+    match path.as_slice() {
+        ["input", fname, attribute @ ..] if !attribute.is_empty() => inputs
+            .get(*fname)
+            .ok_or_else(|| anyhow!("input not found: {}", fname))?
+            .get(attribute)
+            .ok_or_else(|| anyhow!("attribute not found: {}", expression)),
+        _ => bail!("invalid template: {}", expression),
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct StagedFunction {
     pub name: String,
     pub arguments: FunctionArguments,
-    pub payload: String,
+    pub payload: Vec<u8>,
+    /// Zip of pure-Python modules the function can import.
+    pub bundle: Vec<u8>,
     pub input_files: StagedFiles,
     pub output_files: StagedFiles,
     pub executor_type: ExecutorType,
     pub executor: Executor,
     pub runtime_name: String,
+    pub time_limit: Option<Duration>,
+    /// Bytes the function may write to each output.
+    pub output_size_limit: Option<u64>,
+    pub env: FunctionEnv,
+    /// Receives the log messages of the function.
+    pub log: FunctionLog,
+    /// Receives the intermediate results of the function.
+    pub result_stream: ResultStream,
+    /// Cancels the function, e.g., when its task is preempted.
+    pub cancel_token: Option<CancelToken>,
 }
 
 impl StagedFunction {
@@ -132,11 +251,12 @@ impl StagedFunction {
         Self { executor, ..self }
     }
 
-    pub fn payload(self, payload: impl ToString) -> Self {
-        Self {
-            payload: payload.to_string(),
-            ..self
-        }
+    pub fn payload(self, payload: Vec<u8>) -> Self {
+        Self { payload, ..self }
+    }
+
+    pub fn bundle(self, bundle: Vec<u8>) -> Self {
+        Self { bundle, ..self }
     }
 
     pub fn arguments(self, arguments: FunctionArguments) -> Self {
@@ -170,4 +290,132 @@ impl StagedFunction {
             ..self
         }
     }
+
+    pub fn time_limit(self, time_limit: Option<Duration>) -> Self {
+        Self { time_limit, ..self }
+    }
+
+    pub fn output_size_limit(self, output_size_limit: Option<u64>) -> Self {
+        Self {
+            output_size_limit,
+            ..self
+        }
+    }
+
+    pub fn env(self, env: FunctionEnv) -> Self {
+        Self { env, ..self }
+    }
+
+    pub fn log(self, log: FunctionLog) -> Self {
+        Self { log, ..self }
+    }
+
+    pub fn result_stream(self, result_stream: ResultStream) -> Self {
+        Self {
+            result_stream,
+            ..self
+        }
+    }
+
+    pub fn cancel_token(self, cancel_token: CancelToken) -> Self {
+        Self {
+            cancel_token: Some(cancel_token),
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use teaclave_test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_render_arguments,
+            test_render_arguments_error,
+            test_merge_arguments
+        )
+    }
+
+    fn train_data_inputs() -> HashMap<String, FileAttributes> {
+        let attributes = FileAttributes::try_from(
+            r#"{"rows": 1024, "schema": {"columns": ["age", "income"]}, "sep": ","}"#.to_string(),
+        )
+        .unwrap();
+        let mut inputs = HashMap::new();
+        inputs.insert("train_data".to_string(), attributes);
+        inputs
+    }
+
+    fn test_render_arguments() {
+        let arguments = FunctionArguments::try_from(
+            r#"{
+                "rows": "{{input.train_data.rows}}",
+                "columns": "{{ input.train_data.schema.columns }}",
+                "options": "sep={{input.train_data.sep}};rows={{input.train_data.rows}}",
+                "max_depth": 4,
+                "plain": "no template",
+                "braces": "{{not a template}} {{input",
+                "escaped": "\\{{input.train_data.rows}} is {{input.train_data.rows}}"
+            }"#
+            .to_string(),
+        )
+        .unwrap();
+        let rendered = arguments.render(&train_data_inputs()).unwrap();
+
+        assert_eq!(rendered.get("rows").unwrap(), &serde_json::json!(1024));
+        assert_eq!(
+            rendered.get("columns").unwrap(),
+            &serde_json::json!(["age", "income"])
+        );
+        assert_eq!(
+            rendered.get("options").unwrap(),
+            &serde_json::json!("sep=,;rows=1024")
+        );
+        assert_eq!(rendered.get("max_depth").unwrap(), &serde_json::json!(4));
+        assert_eq!(
+            rendered.get("plain").unwrap(),
+            &serde_json::json!("no template")
+        );
+        assert_eq!(
+            rendered.get("braces").unwrap(),
+            &serde_json::json!("{{not a template}} {{input")
+        );
+        assert_eq!(
+            rendered.get("escaped").unwrap(),
+            &serde_json::json!("{{input.train_data.rows}} is 1024")
+        );
+    }
+
+    fn test_render_arguments_error() {
+        let inputs = train_data_inputs();
+        for template in &[
+            "{{input.test_data.rows}}",
+            "{{input.train_data.cols}}",
+            "{{input.train_data}}",
+            "{{output.train_data.rows}}",
+            "{{input.train_data.rows",
+            "{{ input.train_data.rows",
+        ] {
+            let mut map = HashMap::new();
+            map.insert("arg".to_string(), template.to_string());
+            let arguments = FunctionArguments::from_map(map);
+            assert!(arguments.render(&inputs).is_err());
+        }
+    }
+
+    fn test_merge_arguments() {
+        let template =
+            FunctionArguments::try_from(r#"{"max_depth": 4, "lr": 0.1}"#.to_string()).unwrap();
+        let overrides =
+            FunctionArguments::try_from(r#"{"lr": 0.5, "seed": "42"}"#.to_string()).unwrap();
+        let merged = template.merge(overrides);
+
+        assert_eq!(merged.inner().len(), 3);
+        assert_eq!(merged.get("max_depth").unwrap(), &serde_json::json!(4));
+        assert_eq!(merged.get("lr").unwrap(), &serde_json::json!(0.5));
+        assert_eq!(merged.get("seed").unwrap(), &serde_json::json!("42"));
+    }
 }
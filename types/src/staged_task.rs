@@ -24,12 +24,15 @@ use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    Executor, ExecutorType, FileAuthTag, FileCrypto, FunctionArguments, Storable,
-    TeaclaveInputFile, TeaclaveOutputFile,
+    EnclaveMeasurement, Executor, ExecutorType, FileAuthTag, FileCrypto, FunctionArguments,
+    FunctionEnv, OutputPolicy, OutputRelease, Storable, TaskAffinity, TaskBudget, TaskPriority,
+    TeaclaveInputFile, TeaclaveOutputFile, TraceContext,
 };
 
 const STAGED_TASK_PREFIX: &str = "staged-"; // staged-task-uuid
 pub const QUEUE_KEY: &str = "staged-task";
+const INTERACTIVE_QUEUE_KEY: &str = "staged-task-interactive";
+const BATCH_QUEUE_KEY: &str = "staged-task-batch";
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct FunctionInputFiles {
@@ -129,6 +132,8 @@ pub struct FunctionInputFile {
     pub url: Url,
     pub cmac: FileAuthTag,
     pub crypto_info: FileCrypto,
+    #[serde(default)]
+    pub content: Option<Vec<u8>>,
 }
 
 impl FunctionInputFile {
@@ -137,6 +142,7 @@ impl FunctionInputFile {
             url,
             cmac,
             crypto_info: crypto.into(),
+            content: None,
         }
     }
 }
@@ -147,6 +153,7 @@ impl From<TeaclaveInputFile> for FunctionInputFile {
             url: file.url,
             cmac: file.cmac,
             crypto_info: file.crypto_info,
+            content: file.content,
         }
     }
 }
@@ -175,7 +182,7 @@ impl From<TeaclaveOutputFile> for FunctionOutputFile {
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct StagedTask {
     pub task_id: Uuid,
     pub function_id: Uuid,
@@ -184,8 +191,43 @@ pub struct StagedTask {
     pub function_name: String,
     pub function_arguments: FunctionArguments,
     pub function_payload: Vec<u8>,
+    // Zip of pure-Python modules of the function
+    #[serde(default)]
+    pub function_bundle: Vec<u8>,
     pub input_data: FunctionInputFiles,
     pub output_data: FunctionOutputFiles,
+    // Policies of the outputs declared by the owner of the function, checked
+    // before the outputs are uploaded
+    #[serde(default)]
+    pub output_policies: HashMap<String, OutputPolicy>,
+    #[serde(default)]
+    pub budget: TaskBudget,
+    // Environment of the task over the defaults of the deployment
+    #[serde(default)]
+    pub env: FunctionEnv,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    // Labels of the nodes which can run the task
+    #[serde(default)]
+    pub affinity: TaskAffinity,
+    // Sign an execution receipt with the outputs
+    #[serde(default)]
+    pub deterministic: bool,
+    // Trace of the request invoking the task, continued by the scheduler and
+    // the execution service.
+    #[serde(default)]
+    pub trace_context: Option<TraceContext>,
+    // Outputs released after review, uploaded without running the function
+    #[serde(default)]
+    pub release: Option<OutputRelease>,
+    // Measurement of the executor enclave registered for the function, run
+    // in place of the executor only by workers which can launch it
+    #[serde(default)]
+    pub executor_enclave: Option<EnclaveMeasurement>,
+    // Seconds since the Unix epoch before which the task is not dispatched,
+    // e.g., the backoff of a retry
+    #[serde(default)]
+    pub not_before: u64,
 }
 
 impl Storable for StagedTask {
@@ -239,6 +281,13 @@ impl StagedTask {
         }
     }
 
+    pub fn function_bundle(self, function_bundle: Vec<u8>) -> Self {
+        Self {
+            function_bundle,
+            ..self
+        }
+    }
+
     pub fn input_data(self, input_data: impl Into<FunctionInputFiles>) -> Self {
         Self {
             input_data: input_data.into(),
@@ -253,6 +302,11 @@ impl StagedTask {
         }
     }
 
+    pub fn output_policy(mut self, output: impl ToString, policy: OutputPolicy) -> Self {
+        self.output_policies.insert(output.to_string(), policy);
+        self
+    }
+
     pub fn executor_type(self, executor_type: ExecutorType) -> Self {
         Self {
             executor_type,
@@ -260,7 +314,65 @@ impl StagedTask {
         }
     }
 
+    pub fn budget(self, budget: TaskBudget) -> Self {
+        Self { budget, ..self }
+    }
+
+    pub fn env(self, env: FunctionEnv) -> Self {
+        Self { env, ..self }
+    }
+
+    pub fn priority(self, priority: TaskPriority) -> Self {
+        Self { priority, ..self }
+    }
+
+    pub fn affinity(self, affinity: TaskAffinity) -> Self {
+        Self { affinity, ..self }
+    }
+
+    pub fn deterministic(self, deterministic: bool) -> Self {
+        Self {
+            deterministic,
+            ..self
+        }
+    }
+
+    pub fn trace_context(self, trace_context: Option<TraceContext>) -> Self {
+        Self {
+            trace_context,
+            ..self
+        }
+    }
+
+    pub fn executor_enclave(self, executor_enclave: impl Into<Option<EnclaveMeasurement>>) -> Self {
+        Self {
+            executor_enclave: executor_enclave.into(),
+            ..self
+        }
+    }
+
+    pub fn release(self, release: OutputRelease) -> Self {
+        Self {
+            release: Some(release),
+            ..self
+        }
+    }
+
+    pub fn not_before(self, not_before: u64) -> Self {
+        Self { not_before, ..self }
+    }
+
+    /// Key of the queue of the staged tasks of normal priority.
     pub fn get_queue_key() -> &'static str {
         QUEUE_KEY
     }
+
+    /// Key of the queue of the staged tasks of the priority.
+    pub fn get_priority_queue_key(priority: TaskPriority) -> &'static str {
+        match priority {
+            TaskPriority::Interactive => INTERACTIVE_QUEUE_KEY,
+            TaskPriority::Normal => QUEUE_KEY,
+            TaskPriority::Batch => BATCH_QUEUE_KEY,
+        }
+    }
 }
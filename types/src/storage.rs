@@ -15,11 +15,18 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::ExternalID;
+use crate::{ExternalID, UserID};
+use ring::digest;
 use serde::{Deserialize, Serialize};
 use std::prelude::v1::*;
 use uuid::Uuid;
 
+// Keys of namespace N are kept at `ns-N/K` in the database of the storage
+// service, as seen in its change log and snapshots.
+const NAMESPACE_KEY_PREFIX: &str = "ns-";
+const TENANT_NAMESPACE_PREFIX: &str = "tenant-";
+const LOCATOR_PREFIX: &str = "locator-";
+
 pub trait Storable: Serialize + for<'de> Deserialize<'de> {
     fn key_prefix() -> &'static str;
 
@@ -33,6 +40,13 @@ pub trait Storable: Serialize + for<'de> Deserialize<'de> {
         self.key_string().into_bytes()
     }
 
+    /// The user whose namespace of the storage service keeps the record, so
+    /// that it is deleted with the namespace. Records of no tenant are kept
+    /// outside of namespaces.
+    fn tenant(&self) -> Option<&UserID> {
+        None
+    }
+
     fn match_prefix(key: &str) -> bool {
         key.starts_with(Self::key_prefix())
     }
@@ -51,3 +65,63 @@ pub trait Storable: Serialize + for<'de> Deserialize<'de> {
         ExternalID::new(Self::key_prefix(), self.uuid())
     }
 }
+
+/// Namespace of the storage service keeping the records of a user, named by
+/// the hash of the user id, so that the id is not left in the database once
+/// the namespace is wiped.
+pub fn tenant_namespace(user_id: &UserID) -> String {
+    let hash = digest::digest(&digest::SHA256, user_id.to_string().as_bytes());
+    format!(
+        "{}{}",
+        TENANT_NAMESPACE_PREFIX,
+        hex::encode(&hash.as_ref()[..16])
+    )
+}
+
+/// Key of the locator of a record kept in the namespace of its tenant, outside
+/// of namespaces, whose value is the name of the namespace. Records without a
+/// locator are kept outside of namespaces.
+pub fn locator_key(key: &[u8]) -> Vec<u8> {
+    let mut locator_key = LOCATOR_PREFIX.as_bytes().to_vec();
+    locator_key.extend_from_slice(key);
+    locator_key
+}
+
+/// Splits a key of the database of the storage service into its namespace,
+/// if any, and the key within the namespace.
+pub fn split_namespaced_key(key: &[u8]) -> (Option<&str>, &[u8]) {
+    let prefix = NAMESPACE_KEY_PREFIX.as_bytes();
+    if !key.starts_with(prefix) {
+        return (None, key);
+    }
+    let rest = &key[prefix.len()..];
+    match rest.iter().position(|b| *b == b'/') {
+        Some(end) => match std::str::from_utf8(&rest[..end]) {
+            Ok(namespace) => (Some(namespace), &rest[end + 1..]),
+            Err(_) => (None, key),
+        },
+        None => (None, key),
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn run_tests() -> bool {
+        let namespace = tenant_namespace(&UserID::from("user"));
+        assert!(namespace.starts_with(TENANT_NAMESPACE_PREFIX));
+        assert!(!namespace.contains("user"));
+        assert_ne!(namespace, tenant_namespace(&UserID::from("another")));
+
+        let key = format!("ns-{}/task-1", namespace);
+        let (split, record_key) = split_namespaced_key(key.as_bytes());
+        assert_eq!(split, Some(namespace.as_str()));
+        assert_eq!(record_key, b"task-1");
+        // Metadata of namespaces are not in a namespace.
+        let key = format!("ns-{}#usage", namespace);
+        assert_eq!(split_namespaced_key(key.as_bytes()).0, None);
+        assert_eq!(split_namespaced_key(b"task-1"), (None, &b"task-1"[..]));
+        true
+    }
+}
@@ -21,6 +21,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::hash_map::Iter;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[derive(Debug, Default, Clone, Deserialize, PartialEq, Eq, Hash, Serialize)]
@@ -83,6 +84,15 @@ impl OwnerList {
         self.uids.insert(value)
     }
 
+    /// Replaces `from` with `to`, returning whether `from` was in the list.
+    pub fn replace(&mut self, from: &UserID, to: &UserID) -> bool {
+        if !self.uids.remove(from) {
+            return false;
+        }
+        self.uids.insert(to.clone());
+        true
+    }
+
     pub fn union(mut self, other: Self) -> Self {
         for value in other.uids {
             self.uids.insert(value);
@@ -199,6 +209,10 @@ impl std::iter::FromIterator<(String, FileAuthTag)> for OutputsTags {
 pub struct TaskOutputs {
     pub return_value: Vec<u8>,
     pub tags_map: OutputsTags,
+    // Execution receipt signed by the execution service, only for
+    // deterministic tasks
+    #[serde(default)]
+    pub receipt: Option<Vec<u8>>,
 }
 
 impl TaskOutputs {
@@ -206,19 +220,184 @@ impl TaskOutputs {
         TaskOutputs {
             return_value: value.into(),
             tags_map: OutputsTags::new(tags_map),
+            receipt: None,
+        }
+    }
+
+    pub fn receipt(self, receipt: Vec<u8>) -> Self {
+        Self {
+            receipt: Some(receipt),
+            ..self
+        }
+    }
+}
+
+/// Limits of a task, unlimited if not set. The staging time covers
+/// fetching the inputs and uploading the outputs through the file agent, the
+/// execution time covers running the function in the worker. The output size
+/// limits the bytes written by the function to each of its outputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct TaskBudget {
+    pub staging_time: Option<Duration>,
+    pub execution_time: Option<Duration>,
+    #[serde(default)]
+    pub output_size: Option<u64>,
+}
+
+impl TaskBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn staging_time(self, staging_time: Duration) -> Self {
+        Self {
+            staging_time: Some(staging_time),
+            ..self
+        }
+    }
+
+    pub fn execution_time(self, execution_time: Duration) -> Self {
+        Self {
+            execution_time: Some(execution_time),
+            ..self
+        }
+    }
+
+    pub fn output_size(self, output_size: u64) -> Self {
+        Self {
+            output_size: Some(output_size),
+            ..self
+        }
+    }
+}
+
+/// Priority of a task in the scheduling queues: latency-sensitive
+/// interactive tasks are dispatched before normal ones, and those before
+/// long batch jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum TaskPriority {
+    Interactive,
+    Normal,
+    Batch,
+}
+
+impl TaskPriority {
+    /// All priorities, from the highest.
+    pub const ALL: [TaskPriority; 3] = [
+        TaskPriority::Interactive,
+        TaskPriority::Normal,
+        TaskPriority::Batch,
+    ];
+}
+
+impl Default for TaskPriority {
+    fn default() -> Self {
+        TaskPriority::Normal
+    }
+}
+
+impl std::convert::TryFrom<&str> for TaskPriority {
+    type Error = anyhow::Error;
+
+    fn try_from(priority: &str) -> Result<Self> {
+        let priority = match priority {
+            "interactive" => TaskPriority::Interactive,
+            "normal" | "" => TaskPriority::Normal,
+            "batch" => TaskPriority::Batch,
+            _ => bail!("Unsupported task priority: {}", priority),
+        };
+        Ok(priority)
+    }
+}
+
+impl std::convert::TryFrom<String> for TaskPriority {
+    type Error = anyhow::Error;
+
+    fn try_from(priority: String) -> Result<Self> {
+        priority.as_str().try_into()
+    }
+}
+
+impl std::fmt::Display for TaskPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TaskPriority::Interactive => write!(f, "interactive"),
+            TaskPriority::Normal => write!(f, "normal"),
+            TaskPriority::Batch => write!(f, "batch"),
         }
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum TaskBudgetError {
+    #[error("Staging time limit exceeded")]
+    StagingTimeout,
+    #[error("Execution time limit exceeded")]
+    ExecutionTimeout,
+    #[error("Output size limit exceeded: {0}")]
+    OutputTooLarge(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum TaskFailureKind {
+    Error,
+    StagingTimeout,
+    ExecutionTimeout,
+    OutputTooLarge,
+    /// The outputs are held by the content scanners until reviewed.
+    OutputHeld,
+    /// The workers running the task were lost too many times.
+    WorkerLost,
+    /// The task was canceled for a task of higher priority, and is staged
+    /// again rather than finished.
+    Preempted,
+}
+
+impl TaskFailureKind {
+    /// Whether the failure is of the platform rather than of the function.
+    pub fn is_infrastructure(self) -> bool {
+        matches!(
+            self,
+            TaskFailureKind::StagingTimeout | TaskFailureKind::WorkerLost
+        )
+    }
+}
+
+impl Default for TaskFailureKind {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TaskFailure {
     pub reason: String,
+    #[serde(default)]
+    pub kind: TaskFailureKind,
 }
 
 impl TaskFailure {
     pub fn new(reason: impl ToString) -> Self {
         TaskFailure {
             reason: reason.to_string(),
+            kind: TaskFailureKind::Error,
+        }
+    }
+}
+
+impl std::convert::From<Error> for TaskFailure {
+    fn from(error: Error) -> Self {
+        let kind = match error.downcast_ref::<TaskBudgetError>() {
+            Some(TaskBudgetError::StagingTimeout) => TaskFailureKind::StagingTimeout,
+            Some(TaskBudgetError::ExecutionTimeout) => TaskFailureKind::ExecutionTimeout,
+            Some(TaskBudgetError::OutputTooLarge(_)) => TaskFailureKind::OutputTooLarge,
+            None if error.downcast_ref::<OutputsHeld>().is_some() => TaskFailureKind::OutputHeld,
+            None if error.downcast_ref::<TaskPreempted>().is_some() => TaskFailureKind::Preempted,
+            None => TaskFailureKind::Error,
+        };
+        TaskFailure {
+            reason: error.to_string(),
+            kind,
         }
     }
 }
@@ -283,6 +462,33 @@ impl std::convert::TryFrom<String> for ExternalID {
     }
 }
 
+/// Selects objects by the prefixes of their ids (`kinds`, e.g., "function")
+/// and by ids. Empty lists select all objects.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectFilter {
+    pub kinds: Vec<String>,
+    pub ids: Vec<ExternalID>,
+}
+
+impl ObjectFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn kinds(self, kinds: Vec<String>) -> Self {
+        Self { kinds, ..self }
+    }
+
+    pub fn ids(self, ids: Vec<ExternalID>) -> Self {
+        Self { ids, ..self }
+    }
+
+    pub fn matches(&self, id: &ExternalID) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&id.prefix))
+            && (self.ids.is_empty() || self.ids.contains(id))
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum TaskResult {
     NotReady,
@@ -370,6 +576,12 @@ impl TaskFileOwners {
         self.inner.get(key)
     }
 
+    pub fn replace_owner(&mut self, from: &UserID, to: &UserID) -> bool {
+        self.inner.values_mut().fold(false, |replaced, owners| {
+            owners.replace(from, to) || replaced
+        })
+    }
+
     pub fn check(&self, fkey: &str, fowners: &OwnerList) -> Result<()> {
         match self.inner.get(fkey) {
             Some(owner_list) => {
@@ -445,6 +657,18 @@ where
         self.inner.keys()
     }
 
+    pub fn get(&self, fname: &str) -> Option<&T> {
+        self.inner.get(fname)
+    }
+
+    pub fn values(&self) -> std::collections::hash_map::Values<String, T> {
+        self.inner.values()
+    }
+
+    pub fn values_mut(&mut self) -> std::collections::hash_map::ValuesMut<String, T> {
+        self.inner.values_mut()
+    }
+
     pub fn external_ids(&self) -> HashMap<String, ExternalID> {
         self.inner
             .iter()
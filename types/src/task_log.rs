@@ -0,0 +1,181 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::Storable;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use std::sync::Arc;
+#[cfg(not(feature = "sgx"))]
+use std::sync::Mutex;
+#[cfg(feature = "sgx")]
+use std::sync::SgxMutex as Mutex;
+use uuid::Uuid;
+
+const TASK_LOG_PREFIX: &str = "tasklog";
+
+/// Bytes of the log kept for a task; later messages are dropped.
+pub const MAX_TASK_LOG_LEN: usize = 1024 * 1024;
+
+/// Log messages of a running function, shared between the runtime and the
+/// execution service which collects them when the function returns.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionLog {
+    inner: Arc<Mutex<TaskLogContent>>,
+}
+
+#[derive(Debug, Default)]
+struct TaskLogContent {
+    content: Vec<u8>,
+    truncated: bool,
+}
+
+impl FunctionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a message as a line, unless the log is full.
+    pub fn append(&self, message: &str) {
+        let mut log = match self.inner.lock() {
+            Ok(log) => log,
+            Err(_) => return,
+        };
+        let line_len = message.len() + 1;
+        if log.truncated || log.content.len() + line_len > MAX_TASK_LOG_LEN {
+            log.truncated = true;
+            return;
+        }
+        log.content.extend_from_slice(message.as_bytes());
+        log.content.push(b'\n');
+    }
+
+    /// Returns the content so far and whether messages were dropped.
+    pub fn contents(&self) -> (Vec<u8>, bool) {
+        match self.inner.lock() {
+            Ok(log) => (log.content.clone(), log.truncated),
+            Err(_) => (Vec::new(), true),
+        }
+    }
+
+    /// Returns the content from `offset`, e.g., the bytes not yet forwarded,
+    /// and whether messages were dropped.
+    pub fn since(&self, offset: usize) -> (Vec<u8>, bool) {
+        match self.inner.lock() {
+            Ok(log) => {
+                let start = std::cmp::min(offset, log.content.len());
+                (log.content[start..].to_vec(), log.truncated)
+            }
+            Err(_) => (Vec::new(), true),
+        }
+    }
+}
+
+/// The log of a task, written by the scheduler service with the result of
+/// the task.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TaskLog {
+    pub task_id: Uuid,
+    pub content: Vec<u8>,
+    /// Whether messages were dropped as the log exceeded `MAX_TASK_LOG_LEN`.
+    pub truncated: bool,
+}
+
+impl TaskLog {
+    pub fn new(task_id: Uuid, content: Vec<u8>, truncated: bool) -> Self {
+        Self {
+            task_id,
+            content,
+            truncated,
+        }
+    }
+
+    /// Appends content forwarded while the function runs. The log of a task
+    /// retried or preempted keeps the content of the earlier runs.
+    pub fn append(&mut self, content: &[u8], truncated: bool) {
+        let room = MAX_TASK_LOG_LEN.saturating_sub(self.content.len());
+        if content.len() > room {
+            self.content.extend_from_slice(&content[..room]);
+            self.truncated = true;
+        } else {
+            self.content.extend_from_slice(content);
+        }
+        self.truncated |= truncated;
+    }
+
+    /// Returns at most `limit` bytes from `offset`; zero means up to the end.
+    pub fn range(&self, offset: u64, limit: u64) -> &[u8] {
+        let len = self.content.len() as u64;
+        let start = std::cmp::min(offset, len);
+        let end = if limit == 0 {
+            len
+        } else {
+            std::cmp::min(start.saturating_add(limit), len)
+        };
+        &self.content[start as usize..end as usize]
+    }
+}
+
+impl Storable for TaskLog {
+    fn key_prefix() -> &'static str {
+        TASK_LOG_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.task_id
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::platform;
+
+    pub fn run_tests() -> bool {
+        let log = FunctionLog::new();
+        let runtime_log = log.clone();
+        runtime_log.append("loading");
+        runtime_log.append("done");
+        let (content, truncated) = log.contents();
+        assert_eq!(content, b"loading\ndone\n".to_vec());
+        assert!(!truncated);
+
+        let large = "x".repeat(MAX_TASK_LOG_LEN);
+        log.append(&large);
+        log.append("a");
+        let (content, truncated) = log.contents();
+        assert_eq!(content.len(), 13);
+        assert!(truncated);
+
+        assert_eq!(log.since(8), (b"done\n".to_vec(), true));
+        assert_eq!(log.since(100), (Vec::new(), true));
+
+        let mut task_log = TaskLog::new(platform::rand::new_uuid(), Vec::new(), false);
+        task_log.append(b"loading\n", false);
+        task_log.append(b"done\n", false);
+        assert_eq!(task_log.content, content);
+        assert!(!task_log.truncated);
+        task_log.append(large.as_bytes(), false);
+        assert_eq!(task_log.content.len(), MAX_TASK_LOG_LEN);
+        assert!(task_log.truncated);
+
+        let task_log = TaskLog::new(platform::rand::new_uuid(), content, truncated);
+        assert_eq!(task_log.range(0, 7), b"loading");
+        assert_eq!(task_log.range(8, 0), b"done\n");
+        assert_eq!(task_log.range(8, 100), b"done\n");
+        assert!(task_log.range(100, 10).is_empty());
+        true
+    }
+}
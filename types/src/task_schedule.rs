@@ -0,0 +1,572 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{
+    platform, ExternalID, Storable, TaskResult, TaskState, TaskStatus, TeaclaveOutputFile, UserID,
+};
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::prelude::v1::*;
+use url::Url;
+use uuid::Uuid;
+
+const SCHEDULED_TASK_PREFIX: &str = "scheduled-task";
+
+/// Placeholder in the URLs of the output files of a scheduled task, replaced
+/// by the sequence number of each run, so that runs write distinct files.
+pub const RUN_PLACEHOLDER: &str = "{run}";
+// The placeholder in URL paths, where braces are percent-encoded
+const ENCODED_RUN_PLACEHOLDER: &str = "%7Brun%7D";
+
+const SECS_PER_MINUTE: u64 = 60;
+const SECS_PER_HOUR: u64 = 60 * SECS_PER_MINUTE;
+const SECS_PER_DAY: u64 = 24 * SECS_PER_HOUR;
+// Runs further away are considered never to happen, e.g., for "0 0 31 2 *".
+const MAX_LOOKAHEAD_SECS: u64 = 5 * 366 * SECS_PER_DAY;
+const EVERY: &str = "@every";
+
+/// When a scheduled task runs: an interval (`@every 90s`, with the units
+/// `s`, `m`, `h` or `d`) or a cron expression of five fields (minute, hour,
+/// day of month, month and day of week, with lists, ranges and steps, e.g.,
+/// `*/15 9-17 * * 1-5`), evaluated in UTC. `@hourly`, `@daily`, `@weekly`,
+/// `@monthly` and `@yearly` are shorthands of cron expressions.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TaskSchedule {
+    expression: String,
+    kind: ScheduleKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScheduleKind {
+    Interval(u64),
+    Cron(CronFields),
+}
+
+// Allowed values of each field, as bit sets
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CronFields {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // Whether the day fields are restricted, i.e., not "*". If both are, a
+    // day matching either of them matches, as in cron.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl TaskSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let kind = match expression {
+            "@hourly" => parse_cron("0 * * * *")?,
+            "@daily" => parse_cron("0 0 * * *")?,
+            "@weekly" => parse_cron("0 0 * * 0")?,
+            "@monthly" => parse_cron("0 0 1 * *")?,
+            "@yearly" => parse_cron("0 0 1 1 *")?,
+            _ if expression.starts_with(EVERY) => {
+                ScheduleKind::Interval(parse_interval(expression[EVERY.len()..].trim())?)
+            }
+            _ => parse_cron(expression)?,
+        };
+        Ok(Self {
+            expression: expression.to_string(),
+            kind,
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The first run strictly after `secs` (seconds since the Unix epoch),
+    /// if any.
+    pub fn next_after(&self, secs: u64) -> Option<u64> {
+        match &self.kind {
+            ScheduleKind::Interval(interval) => secs.checked_add(*interval),
+            ScheduleKind::Cron(fields) => fields.next_after(secs),
+        }
+    }
+
+    /// The next `count` runs after `secs`.
+    pub fn upcoming(&self, secs: u64, count: usize) -> Vec<u64> {
+        let mut runs = Vec::new();
+        let mut last = secs;
+        while runs.len() < count {
+            match self.next_after(last) {
+                Some(next) => {
+                    runs.push(next);
+                    last = next;
+                }
+                None => break,
+            }
+        }
+        runs
+    }
+}
+
+impl TryFrom<String> for TaskSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(expression: String) -> Result<Self> {
+        Self::parse(&expression)
+    }
+}
+
+impl TryFrom<&str> for TaskSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(expression: &str) -> Result<Self> {
+        Self::parse(expression)
+    }
+}
+
+impl From<TaskSchedule> for String {
+    fn from(schedule: TaskSchedule) -> String {
+        schedule.expression
+    }
+}
+
+impl std::fmt::Display for TaskSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+fn parse_interval(interval: &str) -> Result<u64> {
+    ensure!(
+        !interval.is_empty() && interval.is_ascii(),
+        "invalid interval: {}",
+        interval
+    );
+    let (value, unit) = interval.split_at(interval.len() - 1);
+    let unit = match unit {
+        "s" => 1,
+        "m" => SECS_PER_MINUTE,
+        "h" => SECS_PER_HOUR,
+        "d" => SECS_PER_DAY,
+        _ => bail!("unknown unit of interval: {}", interval),
+    };
+    let value: u64 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid interval: {}", interval))?;
+    let secs = value
+        .checked_mul(unit)
+        .ok_or_else(|| anyhow!("interval too long: {}", interval))?;
+    ensure!(secs > 0, "interval is zero");
+    Ok(secs)
+}
+
+fn parse_cron(expression: &str) -> Result<ScheduleKind> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    ensure!(
+        fields.len() == 5,
+        "cron expressions have five fields: {}",
+        expression
+    );
+    // Sunday is either 0 or 7.
+    let mut weekdays = parse_field(fields[4], 0, 7)?;
+    if weekdays & (1 << 7) != 0 {
+        weekdays = (weekdays | 1) & !(1 << 7);
+    }
+    Ok(ScheduleKind::Cron(CronFields {
+        minutes: parse_field(fields[0], 0, 59)?,
+        hours: parse_field(fields[1], 0, 23)?,
+        days: parse_field(fields[2], 1, 31)?,
+        months: parse_field(fields[3], 1, 12)?,
+        weekdays,
+        days_restricted: fields[2] != "*",
+        weekdays_restricted: fields[4] != "*",
+    }))
+}
+
+// Parses a comma separated list of values, ranges (`1-5`) and steps (`*/15`,
+// `0-30/10`) into the set of the allowed values.
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64> {
+    let mut set = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.find('/') {
+            Some(i) => (&item[..i], Some(&item[i + 1..])),
+            None => (item, None),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            let (start, end) = match range.find('-') {
+                Some(i) => (&range[..i], Some(&range[i + 1..])),
+                None => (range, None),
+            };
+            let start = parse_value(start, min, max)?;
+            match end {
+                Some(end) => (start, parse_value(end, min, max)?),
+                // A value with a step starts a range up to the maximum.
+                None if step.is_some() => (start, max),
+                None => (start, start),
+            }
+        };
+        ensure!(start <= end, "invalid range: {}", item);
+        let step = match step {
+            Some(step) => step
+                .parse::<u64>()
+                .ok()
+                .filter(|step| *step > 0)
+                .ok_or_else(|| anyhow!("invalid step: {}", item))?,
+            None => 1,
+        };
+        let mut value = start;
+        while value <= end {
+            set |= 1 << value;
+            value += step;
+        }
+    }
+    Ok(set)
+}
+
+fn parse_value(value: &str, min: u64, max: u64) -> Result<u64> {
+    let value: u64 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid value: {}", value))?;
+    ensure!(
+        (min..=max).contains(&value),
+        "value {} out of range {}-{}",
+        value,
+        min,
+        max
+    );
+    Ok(value)
+}
+
+impl CronFields {
+    fn next_after(&self, secs: u64) -> Option<u64> {
+        let start = (secs / SECS_PER_MINUTE + 1) * SECS_PER_MINUTE;
+        let limit = start.saturating_add(MAX_LOOKAHEAD_SECS);
+        let mut time = start;
+        // Each step skips at least to the next minute, hour, day or month
+        // which does not match.
+        while time < limit {
+            let days = time / SECS_PER_DAY;
+            let (year, month, day) = civil_from_days(days);
+            if !contains(self.months, month) {
+                let (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                time = days_from_civil(year, month, 1) * SECS_PER_DAY;
+                continue;
+            }
+            if !self.day_matches(day, (days + 4) % 7) {
+                time = (days + 1) * SECS_PER_DAY;
+                continue;
+            }
+            if !contains(self.hours, time % SECS_PER_DAY / SECS_PER_HOUR) {
+                time = (time / SECS_PER_HOUR + 1) * SECS_PER_HOUR;
+                continue;
+            }
+            if !contains(self.minutes, time % SECS_PER_HOUR / SECS_PER_MINUTE) {
+                time += SECS_PER_MINUTE;
+                continue;
+            }
+            return Some(time);
+        }
+        None
+    }
+
+    fn day_matches(&self, day: u64, weekday: u64) -> bool {
+        let day_matches = contains(self.days, day);
+        let weekday_matches = contains(self.weekdays, weekday);
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day_matches || weekday_matches,
+            (true, false) => day_matches,
+            (false, true) => weekday_matches,
+            (false, false) => true,
+        }
+    }
+}
+
+fn contains(set: u64, value: u64) -> bool {
+    set & (1 << value) != 0
+}
+
+// Year, month (1-12) and day (1-31) of the days since the Unix epoch, after
+// http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// A task invoked on a schedule. Each run is a copy of the template task,
+/// created by the same user, with its data assigned and approved by every
+/// participant, and is invoked by the management service on behalf of the
+/// creator.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScheduledTask {
+    pub id: Uuid,
+    pub creator: UserID,
+    pub template_id: ExternalID,
+    pub schedule: TaskSchedule,
+    pub paused: bool,
+    /// Time of the next run, in seconds since the Unix epoch, or None if the
+    /// schedule has no more runs.
+    pub next_run: Option<u64>,
+    /// Number of the runs so far.
+    pub runs: u64,
+    pub last_task_id: Option<ExternalID>,
+}
+
+impl Storable for ScheduledTask {
+    fn key_prefix() -> &'static str {
+        SCHEDULED_TASK_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.id
+    }
+}
+
+impl ScheduledTask {
+    pub fn new(creator: UserID, template_id: ExternalID, schedule: TaskSchedule, now: u64) -> Self {
+        Self {
+            id: platform::rand::new_uuid(),
+            creator,
+            template_id,
+            next_run: schedule.next_after(now),
+            schedule,
+            paused: false,
+            runs: 0,
+            last_task_id: None,
+        }
+    }
+
+    /// Checks that runs can be copied from the template and invoked without
+    /// the participants: its data are assigned, everyone approved it, and
+    /// the URLs of its outputs have the run placeholder.
+    pub fn check_template(template: &TaskState) -> Result<()> {
+        ensure!(
+            template.all_data_assigned(),
+            "data of the template are not assigned"
+        );
+        ensure!(
+            template.everyone_approved(),
+            "template is not approved by every participant"
+        );
+        for (fname, file) in template.assigned_outputs.clone().into_iter() {
+            ensure!(
+                has_run_placeholder(&file.url),
+                "URL of output {} has no run placeholder",
+                fname
+            );
+        }
+        Ok(())
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        !self.paused && self.next_run.map_or(false, |next_run| next_run <= now)
+    }
+
+    /// Creates the task of the next run from the template, with new output
+    /// files, and moves on to the following run. Runs missed, e.g., while
+    /// the service was down, are skipped.
+    pub fn next_task(&mut self, template: &TaskState, now: u64) -> Result<TaskState> {
+        Self::check_template(template)?;
+        let mut ts = template.clone();
+        ts.task_id = platform::rand::new_uuid();
+        ts.created_at = now;
+        for file in ts.assigned_outputs.values_mut() {
+            *file = output_file_for_run(file, self.runs + 1)?;
+        }
+        ts.result = TaskResult::default();
+        ts.status = TaskStatus::Approved;
+
+        self.runs += 1;
+        self.last_task_id = Some(ts.external_id());
+        self.advance(now);
+        Ok(ts)
+    }
+
+    // Anchors the next run on the previous one, unless it is already missed.
+    fn advance(&mut self, now: u64) {
+        let next_run = self
+            .next_run
+            .and_then(|next_run| self.schedule.next_after(next_run));
+        self.next_run = match next_run {
+            Some(next_run) if next_run > now => Some(next_run),
+            _ => self.schedule.next_after(now),
+        };
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes the schedule from `now`, without the runs missed while paused.
+    pub fn resume(&mut self, now: u64) {
+        self.paused = false;
+        self.next_run = self.schedule.next_after(now);
+    }
+
+    /// The next `count` runs, none if paused.
+    pub fn upcoming(&self, count: usize) -> Vec<u64> {
+        match self.next_run {
+            Some(next_run) if !self.paused && count > 0 => {
+                let mut runs = vec![next_run];
+                runs.extend(self.schedule.upcoming(next_run, count - 1));
+                runs
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn has_run_placeholder(url: &Url) -> bool {
+    url.as_str().contains(RUN_PLACEHOLDER) || url.as_str().contains(ENCODED_RUN_PLACEHOLDER)
+}
+
+// A new output file of the run, with the run placeholder of the URL
+// replaced, which has not been written yet.
+fn output_file_for_run(file: &TeaclaveOutputFile, run: u64) -> Result<TeaclaveOutputFile> {
+    let run = run.to_string();
+    let url = file
+        .url
+        .as_str()
+        .replace(RUN_PLACEHOLDER, &run)
+        .replace(ENCODED_RUN_PLACEHOLDER, &run);
+    Ok(TeaclaveOutputFile::new(
+        Url::parse(&url)?,
+        file.crypto_info,
+        file.owner.clone(),
+    ))
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::FileCrypto;
+
+    // 2021-03-01 00:00:00 UTC, a Monday
+    const MONDAY: u64 = 1_614_556_800;
+
+    pub fn run_tests() -> bool {
+        assert_eq!(civil_from_days(MONDAY / SECS_PER_DAY), (2021, 3, 1));
+        assert_eq!(days_from_civil(2021, 3, 1), MONDAY / SECS_PER_DAY);
+        assert_eq!(civil_from_days(days_from_civil(2020, 2, 29)), (2020, 2, 29));
+
+        let schedule = TaskSchedule::parse("@every 90s").unwrap();
+        assert_eq!(schedule.upcoming(100, 2), vec![190, 280]);
+        let schedule = TaskSchedule::parse("@daily").unwrap();
+        assert_eq!(schedule.next_after(MONDAY), Some(MONDAY + SECS_PER_DAY));
+        assert_eq!(schedule.next_after(MONDAY - 1), Some(MONDAY));
+
+        // Every 15 minutes from 9:00 to 9:59 on weekdays
+        let schedule = TaskSchedule::parse("*/15 9 * * 1-5").unwrap();
+        let nine = MONDAY + 9 * SECS_PER_HOUR;
+        assert_eq!(
+            schedule.upcoming(MONDAY, 5),
+            vec![
+                nine,
+                nine + 900,
+                nine + 1800,
+                nine + 2700,
+                nine + SECS_PER_DAY
+            ]
+        );
+        let saturday = MONDAY + 5 * SECS_PER_DAY;
+        let next_monday = MONDAY + 7 * SECS_PER_DAY + 9 * SECS_PER_HOUR;
+        assert_eq!(schedule.next_after(saturday), Some(next_monday));
+
+        // The 1st of the month or Sundays
+        let schedule = TaskSchedule::parse("0 0 1 * 7").unwrap();
+        assert_eq!(schedule.next_after(MONDAY), Some(MONDAY + 6 * SECS_PER_DAY));
+        assert_eq!(
+            TaskSchedule::parse("0 0 31 2 *").unwrap().next_after(0),
+            None
+        );
+
+        for invalid in &[
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@every 0s",
+        ] {
+            assert!(TaskSchedule::parse(invalid).is_err());
+        }
+        let json = serde_json::to_string(&schedule).unwrap();
+        assert_eq!(json, "\"0 0 1 * 7\"");
+        assert!(serde_json::from_str::<TaskSchedule>("\"@every 1w\"").is_err());
+
+        let url = Url::parse("https://storage.example.com/out-{run}.enc?token=1").unwrap();
+        let output = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["user"]);
+        let mut template = TaskState {
+            creator: UserID::from("user"),
+            participants: vec!["user"].into(),
+            outputs_ownership: vec![("output".to_string(), vec!["user"])]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        template.assigned_outputs.assign("output", output).unwrap();
+        assert!(ScheduledTask::check_template(&template).is_ok());
+
+        let schedule = TaskSchedule::parse("@every 60s").unwrap();
+        let mut scheduled = ScheduledTask::new(
+            template.creator.clone(),
+            template.external_id(),
+            schedule,
+            1000,
+        );
+        assert!(!scheduled.is_due(1059));
+        assert!(scheduled.is_due(1060));
+        let ts = scheduled.next_task(&template, 1060).unwrap();
+        assert_ne!(ts.task_id, template.task_id);
+        assert_eq!(ts.status, TaskStatus::Approved);
+        let file = ts.assigned_outputs.clone().into_iter().next().unwrap().1;
+        assert!(file.url.as_str().ends_with("/out-1.enc?token=1"));
+        assert_eq!(scheduled.next_run, Some(1120));
+
+        // Missed runs are skipped.
+        scheduled.next_task(&template, 1500).unwrap();
+        assert_eq!(scheduled.next_run, Some(1560));
+        scheduled.pause();
+        assert!(!scheduled.is_due(2000));
+        assert!(scheduled.upcoming(3).is_empty());
+        scheduled.resume(2000);
+        assert_eq!(scheduled.upcoming(3), vec![2060, 2120, 2180]);
+        true
+    }
+}
@@ -18,7 +18,7 @@
 use crate::*;
 use anyhow::{bail, ensure, Error, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use uuid::Uuid;
 
@@ -38,8 +38,38 @@ pub struct TaskState {
     pub approved_users: UserList,
     pub assigned_inputs: TaskFiles<TeaclaveInputFile>,
     pub assigned_outputs: TaskFiles<TeaclaveOutputFile>,
+    #[serde(default)]
+    pub budget: TaskBudget,
+    #[serde(default)]
+    pub env: FunctionEnv,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    #[serde(default)]
+    pub affinity: TaskAffinity,
     pub result: TaskResult,
     pub status: TaskStatus,
+    // Seconds since the Unix epoch; 0 for the tasks created before it was
+    // recorded
+    #[serde(default)]
+    pub created_at: u64,
+    // Times the task was queued again as the worker running it was lost
+    #[serde(default)]
+    pub requeues: u32,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    // Runs of the task started by workers, including the retries
+    #[serde(default)]
+    pub attempts: u32,
+    // Reason of the last failed run, kept when the task is retried
+    #[serde(default)]
+    pub last_error: Option<String>,
+    // Times the task was canceled for a task of higher priority, whose runs
+    // do not count against its retry policy
+    #[serde(default)]
+    pub preemptions: u32,
+    // Sign an execution receipt with the outputs of the task
+    #[serde(default)]
+    pub deterministic: bool,
 }
 
 impl Storable for TaskState {
@@ -50,6 +80,10 @@ impl Storable for TaskState {
     fn uuid(&self) -> Uuid {
         self.task_id
     }
+
+    fn tenant(&self) -> Option<&UserID> {
+        Some(&self.creator)
+    }
 }
 
 impl TaskState {
@@ -81,6 +115,108 @@ impl TaskState {
     pub fn has_creator(&self, user_id: &UserID) -> bool {
         &self.creator == user_id
     }
+
+    /// Hands the roles of `from` in the task over to `to`, including the
+    /// ownership of the files assigned to it. Returns whether `from` had any.
+    pub fn transfer_ownership(&mut self, from: &UserID, to: &UserID) -> bool {
+        let mut transferred = false;
+        if &self.creator == from {
+            self.creator = to.clone();
+            transferred = true;
+        }
+        if &self.function_owner == from {
+            self.function_owner = to.clone();
+            transferred = true;
+        }
+        transferred |= self.participants.replace(from, to);
+        transferred |= self.approved_users.replace(from, to);
+        transferred |= self.inputs_ownership.replace_owner(from, to);
+        transferred |= self.outputs_ownership.replace_owner(from, to);
+        for file in self.assigned_inputs.values_mut() {
+            transferred |= file.owner.replace(from, to);
+        }
+        for file in self.assigned_outputs.values_mut() {
+            transferred |= file.owner.replace(from, to);
+        }
+        transferred
+    }
+
+    /// Stages the task dispatched to a lost worker again.
+    pub fn requeue(&mut self) -> Result<()> {
+        ensure!(
+            matches!(self.status, TaskStatus::Staged | TaskStatus::Running),
+            "Task not staged or running: {:?}",
+            self.status
+        );
+        self.status = TaskStatus::Staged;
+        self.requeues += 1;
+        Ok(())
+    }
+
+    /// Fails the task dispatched to a lost worker instead of staging it
+    /// again.
+    pub fn fail_lost(&mut self, reason: impl ToString) -> Result<()> {
+        ensure!(
+            matches!(self.status, TaskStatus::Staged | TaskStatus::Running),
+            "Task not staged or running: {:?}",
+            self.status
+        );
+        self.status = TaskStatus::Finished;
+        self.last_error = Some(reason.to_string());
+        self.result = TaskResult::Err(TaskFailure {
+            reason: reason.to_string(),
+            kind: TaskFailureKind::WorkerLost,
+        });
+        Ok(())
+    }
+
+    /// Stages the task canceled for a task of higher priority again.
+    pub fn preempt(&mut self) -> Result<()> {
+        ensure!(
+            self.status == TaskStatus::Running,
+            "Task not running: {:?}",
+            self.status
+        );
+        self.status = TaskStatus::Staged;
+        self.preemptions += 1;
+        Ok(())
+    }
+
+    /// Runs of the task counted against its retry policy.
+    pub fn counted_attempts(&self) -> u32 {
+        self.attempts.saturating_sub(self.preemptions)
+    }
+
+    /// Stages the task which failed again, as allowed by its retry policy.
+    pub fn retry(&mut self, failure: &TaskFailure) -> Result<()> {
+        ensure!(
+            matches!(self.status, TaskStatus::Staged | TaskStatus::Running),
+            "Task not staged or running: {:?}",
+            self.status
+        );
+        self.status = TaskStatus::Staged;
+        self.last_error = Some(failure.reason.clone());
+        Ok(())
+    }
+
+    /// Stages the task which failed as its outputs were held again, to upload
+    /// them once they have been released instead of running its function.
+    pub fn stage_release(&mut self, release: OutputRelease) -> Result<StagedTask> {
+        match &self.result {
+            TaskResult::Err(failure) if failure.kind == TaskFailureKind::OutputHeld => (),
+            _ => bail!("Outputs of the task not held"),
+        }
+        self.status = TaskStatus::Staged;
+        self.result = TaskResult::NotReady;
+        let staged_task = StagedTask::new()
+            .task_id(self.task_id)
+            .output_data(self.assigned_outputs.clone())
+            .budget(self.budget)
+            .priority(self.priority)
+            .affinity(self.affinity.clone())
+            .release(release);
+        Ok(staged_task)
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -135,7 +271,7 @@ impl Task<Create> {
         ensure!(outputs_spec == req_output_fkeys, "output keys mismatch");
 
         let ts = TaskState {
-            task_id: Uuid::new_v4(),
+            task_id: platform::rand::new_uuid(),
             creator: requester,
             executor: req_executor,
             function_id: function.external_id(),
@@ -144,6 +280,7 @@ impl Task<Create> {
             inputs_ownership: req_input_owners,
             outputs_ownership: req_output_owners,
             participants,
+            created_at: platform::time::since_epoch().as_secs(),
             ..Default::default()
         };
 
@@ -152,6 +289,31 @@ impl Task<Create> {
             extra: Create,
         })
     }
+
+    pub fn budget(mut self, budget: TaskBudget) -> Self {
+        self.state.budget = budget;
+        self
+    }
+
+    pub fn env(mut self, env: FunctionEnv) -> Self {
+        self.state.env = env;
+        self
+    }
+
+    pub fn priority(mut self, priority: TaskPriority) -> Self {
+        self.state.priority = priority;
+        self
+    }
+
+    pub fn affinity(mut self, affinity: TaskAffinity) -> Self {
+        self.state.affinity = affinity;
+        self
+    }
+
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.state.deterministic = deterministic;
+        self
+    }
 }
 
 impl Task<Assign> {
@@ -238,7 +400,20 @@ impl Task<Stage> {
             "Requestor is not the task creater"
         );
 
-        let function_arguments = self.state.function_arguments.clone();
+        let input_attributes: HashMap<String, FileAttributes> = self
+            .state
+            .assigned_inputs
+            .clone()
+            .into_iter()
+            .map(|(fname, file)| (fname, file.attributes))
+            .collect();
+        let function_arguments = self.state.function_arguments.render(&input_attributes)?;
+        let output_policies = function
+            .outputs
+            .into_iter()
+            .filter(|output| !output.policy.is_empty())
+            .map(|output| (output.name, output.policy))
+            .collect();
         let staged_task = StagedTask {
             task_id: self.state.task_id,
             executor: self.state.executor,
@@ -246,9 +421,20 @@ impl Task<Stage> {
             function_id: function.id,
             function_name: function.name,
             function_payload: function.payload,
+            function_bundle: function.bundle,
             function_arguments,
             input_data: self.state.assigned_inputs.clone().into(),
             output_data: self.state.assigned_outputs.clone().into(),
+            output_policies,
+            budget: self.state.budget,
+            env: self.state.env.clone(),
+            priority: self.state.priority,
+            affinity: self.state.affinity.clone(),
+            deterministic: self.state.deterministic,
+            trace_context: None,
+            release: None,
+            executor_enclave: None,
+            not_before: 0,
         };
         Ok(staged_task)
     }
@@ -282,6 +468,9 @@ impl Task<Finish> {
     }
 
     pub fn update_result(&mut self, result: TaskResult) -> Result<()> {
+        if let TaskResult::Err(failure) = &result {
+            self.state.last_error = Some(failure.reason.clone());
+        }
         self.state.result = result;
         Ok(())
     }
@@ -0,0 +1,48 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{QuotaUsage, UserID, UserQuota};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+/// Statistics of the tasks created by a tenant, i.e., a user, over a window
+/// of time, with the consumption of the quota of the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TenantStats {
+    pub user_id: UserID,
+    /// Tasks finished in the window, succeeded or failed.
+    pub tasks_run: u64,
+    pub tasks_succeeded: u64,
+    /// Average time from the invocation of the tasks to their results, in
+    /// milliseconds.
+    pub average_latency_ms: u64,
+    /// Bytes of the inline input data and return values of the tasks.
+    pub data_processed_bytes: u64,
+    pub quota: UserQuota,
+    pub usage: QuotaUsage,
+}
+
+impl TenantStats {
+    /// Share of the tasks run which succeeded, 1 if no task was run.
+    pub fn success_rate(&self) -> f64 {
+        if self.tasks_run == 0 {
+            1.0
+        } else {
+            self.tasks_succeeded as f64 / self.tasks_run as f64
+        }
+    }
+}
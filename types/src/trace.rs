@@ -0,0 +1,56 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::prelude::v1::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a span of work, e.g., handling an RPC request, within a trace
+/// which follows one user request across services.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+}
+
+impl TraceContext {
+    /// Starts a new trace.
+    pub fn new() -> Self {
+        Self {
+            trace_id: crate::platform::rand::new_uuid().to_simple().to_string(),
+            span_id: new_span_id(),
+        }
+    }
+
+    /// A new span in the same trace, whose parent is this span.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+        }
+    }
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn new_span_id() -> String {
+    format!("{:016x}", crate::platform::rand::random_u64())
+}
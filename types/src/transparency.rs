@@ -0,0 +1,340 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Append-only transparency log of released enclave measurements, a Merkle
+//! tree as in certificate transparency (RFC 9162) whose head is signed with
+//! the Ed25519 key of the release process. Clients check that the measurement
+//! of an attested enclave is in the log, so that builds which were never
+//! published cannot be targeted at them.
+
+#[cfg(feature = "sgx")]
+use std::prelude::v1::*;
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{EnclaveMeasurement, MrEnclave, MrSigner};
+use anyhow::{bail, ensure, Context, Error, Result};
+use ring::{digest, signature};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+// Domain separation of the signatures of tree heads.
+const TREE_HEAD_CONTEXT: &[u8] = b"teaclave-measurement-log-v1";
+
+impl_byte_array_newtype!(
+    /// SHA-256 hash of a leaf or a node of the measurement log.
+    LogHash,
+    digest::SHA256_OUTPUT_LEN
+);
+impl_byte_array_newtype!(
+    /// Ed25519 signature of a tree head.
+    LogSignature,
+    64
+);
+
+impl LogHash {
+    fn leaf(data: &[u8]) -> Self {
+        let mut context = digest::Context::new(&digest::SHA256);
+        context.update(&[LEAF_PREFIX]);
+        context.update(data);
+        Self::digest(context)
+    }
+
+    fn node(left: &LogHash, right: &LogHash) -> Self {
+        let mut context = digest::Context::new(&digest::SHA256);
+        context.update(&[NODE_PREFIX]);
+        context.update(left.as_bytes());
+        context.update(right.as_bytes());
+        Self::digest(context)
+    }
+
+    fn digest(context: digest::Context) -> Self {
+        let mut hash = [0u8; digest::SHA256_OUTPUT_LEN];
+        hash.copy_from_slice(context.finish().as_ref());
+        Self(hash)
+    }
+}
+
+/// Measurement of a released enclave of a service.
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct MeasurementLogEntry {
+    pub service_name: String,
+    pub measurement: EnclaveMeasurement,
+}
+
+impl MeasurementLogEntry {
+    pub fn new(service_name: impl Into<String>, measurement: EnclaveMeasurement) -> Self {
+        Self {
+            service_name: service_name.into(),
+            measurement,
+        }
+    }
+
+    pub fn leaf_hash(&self) -> LogHash {
+        let name = self.service_name.as_bytes();
+        let mut data = Vec::with_capacity(4 + name.len() + MrEnclave::LENGTH + MrSigner::LENGTH);
+        data.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        data.extend_from_slice(name);
+        data.extend_from_slice(self.measurement.mr_enclave.as_bytes());
+        data.extend_from_slice(self.measurement.mr_signer.as_bytes());
+        LogHash::leaf(&data)
+    }
+}
+
+/// Root hash of the first `tree_size` entries of the log, signed by the
+/// release process.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SignedTreeHead {
+    pub tree_size: u64,
+    pub root_hash: LogHash,
+    pub signature: LogSignature,
+}
+
+impl SignedTreeHead {
+    fn message(tree_size: u64, root_hash: &LogHash) -> Vec<u8> {
+        let mut message = TREE_HEAD_CONTEXT.to_vec();
+        message.extend_from_slice(&tree_size.to_be_bytes());
+        message.extend_from_slice(root_hash.as_bytes());
+        message
+    }
+
+    pub fn sign(tree_size: u64, root_hash: LogHash, key_pair: &signature::Ed25519KeyPair) -> Self {
+        let message = Self::message(tree_size, &root_hash);
+        let mut signature = [0u8; LogSignature::LENGTH];
+        signature.copy_from_slice(key_pair.sign(&message).as_ref());
+        Self {
+            tree_size,
+            root_hash,
+            signature: LogSignature::new(signature),
+        }
+    }
+
+    /// Verifies the signature with the raw 32-byte Ed25519 public key of the
+    /// log.
+    pub fn verify(&self, public_key: &[u8]) -> Result<()> {
+        let message = Self::message(self.tree_size, &self.root_hash);
+        signature::UnparsedPublicKey::new(&signature::ED25519, public_key)
+            .verify(&message, self.signature.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid signature of the measurement log"))
+    }
+}
+
+/// Hashes from a leaf to the root of the tree, proving that the entry is in
+/// the log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub audit_path: Vec<LogHash>,
+}
+
+impl InclusionProof {
+    /// Verifies that the entry is in the tree of the signed tree head, whose
+    /// signature must be verified separately.
+    pub fn verify(&self, entry: &MeasurementLogEntry, tree_head: &SignedTreeHead) -> Result<()> {
+        ensure!(
+            self.leaf_index < tree_head.tree_size,
+            "Leaf index out of the measurement log"
+        );
+        // RFC 9162, section 2.1.3.2
+        let mut fnode = self.leaf_index;
+        let mut snode = tree_head.tree_size - 1;
+        let mut hash = entry.leaf_hash();
+        for sibling in &self.audit_path {
+            ensure!(snode > 0, "Invalid inclusion proof");
+            if fnode & 1 == 1 || fnode == snode {
+                hash = LogHash::node(sibling, &hash);
+                while fnode & 1 == 0 && fnode != 0 {
+                    fnode >>= 1;
+                    snode >>= 1;
+                }
+            } else {
+                hash = LogHash::node(&hash, sibling);
+            }
+            fnode >>= 1;
+            snode >>= 1;
+        }
+        ensure!(
+            snode == 0 && hash == tree_head.root_hash,
+            "Measurement not in the log"
+        );
+        Ok(())
+    }
+}
+
+/// The measurement log, published as JSON and served by the management
+/// service.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MeasurementLog {
+    pub entries: Vec<MeasurementLogEntry>,
+    pub signed_tree_head: Option<SignedTreeHead>,
+}
+
+impl MeasurementLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the log, checking that the signed tree head matches the
+    /// entries. The signature is not verified.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let log: Self = serde_json::from_slice(bytes).context("Invalid measurement log")?;
+        if let Some(tree_head) = &log.signed_tree_head {
+            ensure!(
+                tree_head.tree_size as usize <= log.entries.len(),
+                "Tree size larger than the measurement log"
+            );
+            ensure!(
+                log.root_hash(tree_head.tree_size as usize) == tree_head.root_hash,
+                "Root hash does not match the measurement log"
+            );
+        }
+        Ok(log)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Appends the entry and signs the new tree head. Entries are never
+    /// removed.
+    pub fn append(
+        &mut self,
+        entry: MeasurementLogEntry,
+        key_pair: &signature::Ed25519KeyPair,
+    ) -> Result<()> {
+        ensure!(!self.contains(&entry), "Measurement already in the log");
+        self.entries.push(entry);
+        let tree_size = self.entries.len();
+        let root_hash = self.root_hash(tree_size);
+        self.signed_tree_head = Some(SignedTreeHead::sign(tree_size as u64, root_hash, key_pair));
+        Ok(())
+    }
+
+    pub fn contains(&self, entry: &MeasurementLogEntry) -> bool {
+        self.entries.iter().any(|e| e == entry)
+    }
+
+    /// Proves that the entry is in the tree of the signed tree head.
+    pub fn inclusion_proof(&self, entry: &MeasurementLogEntry) -> Result<InclusionProof> {
+        let tree_size = match &self.signed_tree_head {
+            Some(tree_head) => tree_head.tree_size as usize,
+            None => bail!("Measurement log not signed"),
+        };
+        let leaves = self.leaf_hashes(tree_size);
+        let leaf_hash = entry.leaf_hash();
+        let leaf_index = match leaves.iter().position(|leaf| *leaf == leaf_hash) {
+            Some(index) => index,
+            None => bail!("Measurement not in the log"),
+        };
+        Ok(InclusionProof {
+            leaf_index: leaf_index as u64,
+            audit_path: audit_path(leaf_index, &leaves),
+        })
+    }
+
+    fn leaf_hashes(&self, tree_size: usize) -> Vec<LogHash> {
+        self.entries[..tree_size]
+            .iter()
+            .map(MeasurementLogEntry::leaf_hash)
+            .collect()
+    }
+
+    fn root_hash(&self, tree_size: usize) -> LogHash {
+        tree_hash(&self.leaf_hashes(tree_size))
+    }
+}
+
+// The largest power of two smaller than n, for n > 1.
+fn split(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+fn tree_hash(leaves: &[LogHash]) -> LogHash {
+    match leaves.len() {
+        0 => LogHash::digest(digest::Context::new(&digest::SHA256)),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            LogHash::node(&tree_hash(&leaves[..k]), &tree_hash(&leaves[k..]))
+        }
+    }
+}
+
+fn audit_path(index: usize, leaves: &[LogHash]) -> Vec<LogHash> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split(n);
+    let (mut path, sibling) = if index < k {
+        (audit_path(index, &leaves[..k]), tree_hash(&leaves[k..]))
+    } else {
+        (audit_path(index - k, &leaves[k..]), tree_hash(&leaves[..k]))
+    };
+    path.push(sibling);
+    path
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    fn entry(i: u8) -> MeasurementLogEntry {
+        let measurement = EnclaveMeasurement::new(MrEnclave::new([i; 32]), MrSigner::new([0; 32]));
+        MeasurementLogEntry::new("teaclave_frontend_service", measurement)
+    }
+
+    pub fn run_tests() -> bool {
+        let key_pair = signature::Ed25519KeyPair::from_seed_unchecked(&[1; 32]).unwrap();
+        let public_key = signature::KeyPair::public_key(&key_pair).as_ref().to_vec();
+        let mut log = MeasurementLog::new();
+        assert!(log.inclusion_proof(&entry(0)).is_err());
+        for i in 0..7 {
+            log.append(entry(i), &key_pair).unwrap();
+            let tree_head = log.signed_tree_head.clone().unwrap();
+            assert!(tree_head.verify(&public_key).is_ok());
+            for j in 0..=i {
+                let proof = log.inclusion_proof(&entry(j)).unwrap();
+                assert!(proof.verify(&entry(j), &tree_head).is_ok());
+                if i > 0 {
+                    // The proof of another entry does not hold.
+                    let other = entry((j + 1) % (i + 1));
+                    assert!(proof.verify(&other, &tree_head).is_err());
+                }
+            }
+        }
+        assert!(log.append(entry(0), &key_pair).is_err());
+        assert!(log.inclusion_proof(&entry(9)).is_err());
+
+        let log = MeasurementLog::from_bytes(&log.to_vec().unwrap()).unwrap();
+        let mut tree_head = log.signed_tree_head.clone().unwrap();
+        tree_head.tree_size += 1;
+        assert!(tree_head.verify(&public_key).is_err());
+
+        let mut tampered = log;
+        tampered.entries[0] = entry(9);
+        assert!(MeasurementLog::from_bytes(&tampered.to_vec().unwrap()).is_err());
+        true
+    }
+}
@@ -0,0 +1,190 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{TaskResult, TaskStatus, UserID};
+use anyhow::{ensure, Result};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use url::Url;
+use uuid::Uuid;
+
+/// HTTP header carrying the signature of an event, `sha256=` followed by the
+/// hex-encoded HMAC-SHA256 of the body with the secret of the webhook.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Teaclave-Signature";
+/// Length of the secrets of webhooks, in bytes.
+pub const WEBHOOK_SECRET_LEN: usize = 32;
+
+/// The HTTPS endpoint of a user to which the events of the tasks the user
+/// participates in are posted.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Webhook {
+    pub owner: UserID,
+    pub url: Url,
+    /// Key of the signatures of the events.
+    pub secret: Vec<u8>,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+impl Webhook {
+    pub fn new(
+        owner: impl Into<UserID>,
+        url: Url,
+        secret: Vec<u8>,
+        created_at: u64,
+    ) -> Result<Self> {
+        ensure!(url.scheme() == "https", "webhook URL must be HTTPS");
+        ensure!(url.host_str().is_some(), "webhook URL without host");
+        ensure!(secret.len() == WEBHOOK_SECRET_LEN, "invalid webhook secret");
+        Ok(Self {
+            owner: owner.into(),
+            url,
+            secret,
+            created_at,
+        })
+    }
+}
+
+/// State changes of tasks posted to webhooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskEventKind {
+    Staged,
+    Running,
+    /// Finished with a return value.
+    Finished,
+    /// Finished with a failure.
+    Failed,
+}
+
+impl TaskEventKind {
+    /// The event of a task in the status, if any.
+    pub fn from_task(status: &TaskStatus, result: &TaskResult) -> Option<Self> {
+        match status {
+            TaskStatus::Staged => Some(TaskEventKind::Staged),
+            TaskStatus::Running => Some(TaskEventKind::Running),
+            TaskStatus::Finished if result.is_ok() => Some(TaskEventKind::Finished),
+            TaskStatus::Finished => Some(TaskEventKind::Failed),
+            _ => None,
+        }
+    }
+
+    pub fn is_final(self) -> bool {
+        match self {
+            TaskEventKind::Finished | TaskEventKind::Failed => true,
+            _ => false,
+        }
+    }
+}
+
+/// The JSON body posted to webhooks. Receivers should check the signature,
+/// and drop events already seen (by `event_id`), as failed posts are retried,
+/// or too old (by `timestamp`).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TaskEvent {
+    pub event_id: Uuid,
+    pub task_id: String,
+    pub kind: TaskEventKind,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl TaskEvent {
+    pub fn new(
+        event_id: Uuid,
+        task_id: impl ToString,
+        kind: TaskEventKind,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            event_id,
+            task_id: task_id.to_string(),
+            kind,
+            timestamp,
+        }
+    }
+
+    /// The body of the event and its signature with the secret.
+    pub fn sign(&self, secret: &[u8]) -> Result<(Vec<u8>, String)> {
+        let body = serde_json::to_vec(self)?;
+        let signature = signature(&body, secret);
+        Ok((body, signature))
+    }
+
+    /// The event of a body posted to a webhook, if the signature matches.
+    pub fn verify(body: &[u8], signature: &str, secret: &[u8]) -> Result<Self> {
+        let tag = if signature.starts_with("sha256=") {
+            hex::decode(&signature["sha256=".len()..])?
+        } else {
+            Vec::new()
+        };
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+        ensure!(
+            hmac::verify(&key, body, &tag).is_ok(),
+            "webhook signature mismatched"
+        );
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+fn signature(body: &[u8], secret: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    format!("sha256={}", hex::encode(hmac::sign(&key, body).as_ref()))
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::TaskOutputs;
+    use std::collections::HashMap;
+
+    pub fn run_tests() -> bool {
+        let url = Url::parse("https://example.com/hook").unwrap();
+        let secret = vec![7; WEBHOOK_SECRET_LEN];
+        assert!(Webhook::new("user", url, secret.clone(), 0).is_ok());
+        let url = Url::parse("http://example.com/hook").unwrap();
+        assert!(Webhook::new("user", url.clone(), secret.clone(), 0).is_err());
+        assert!(Webhook::new("user", url, vec![7; 8], 0).is_err());
+
+        let ok = TaskResult::Ok(TaskOutputs::new(Vec::new(), HashMap::new()));
+        assert_eq!(
+            TaskEventKind::from_task(&TaskStatus::Finished, &ok),
+            Some(TaskEventKind::Finished)
+        );
+        assert_eq!(
+            TaskEventKind::from_task(&TaskStatus::Approved, &TaskResult::NotReady),
+            None
+        );
+
+        let event = TaskEvent::new(
+            Uuid::from_u128(1),
+            "task-00000000-0000-0000-0000-000000000001",
+            TaskEventKind::Running,
+            1,
+        );
+        let (body, signature) = event.sign(&secret).unwrap();
+        assert_eq!(
+            TaskEvent::verify(&body, &signature, &secret).unwrap(),
+            event
+        );
+        assert!(TaskEvent::verify(&body, &signature, &[8; WEBHOOK_SECRET_LEN]).is_err());
+        assert!(TaskEvent::verify(&body, "sha256=00", &secret).is_err());
+        assert!(TaskEvent::verify(&body, &signature[7..], &secret).is_err());
+        true
+    }
+}
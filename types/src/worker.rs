@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::{FunctionArguments, FunctionRuntime, OutputsTags};
+use crate::{FunctionArguments, FunctionEnv, FunctionRuntime, OutputsTags};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::convert::TryInto;
@@ -25,6 +25,9 @@ use std::prelude::v1::*;
 pub trait TeaclaveRuntime {
     fn open_input(&self, identifier: &str) -> anyhow::Result<Box<dyn io::Read>>;
     fn create_output(&self, identifier: &str) -> anyhow::Result<Box<dyn io::Write>>;
+    fn env(&self) -> &FunctionEnv;
+    fn log(&self, message: &str);
+    fn emit_result(&self, chunk: &[u8]);
 }
 
 pub trait TeaclaveExecutor {
@@ -32,15 +35,31 @@ pub trait TeaclaveExecutor {
         &self,
         name: String,
         arguments: FunctionArguments,
-        payload: String,
+        payload: Vec<u8>,
         runtime: FunctionRuntime,
     ) -> anyhow::Result<String>;
+
+    /// Executes a function whose payload comes with a bundle of modules,
+    /// which only executors of Python functions support.
+    fn execute_with_bundle(
+        &self,
+        name: String,
+        arguments: FunctionArguments,
+        payload: Vec<u8>,
+        bundle: Vec<u8>,
+        runtime: FunctionRuntime,
+    ) -> anyhow::Result<String> {
+        anyhow::ensure!(bundle.is_empty(), "Bundles not supported by the executor");
+        self.execute(name, arguments, payload, runtime)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum ExecutorType {
     Builtin,
     Python,
+    /// WebAssembly modules, whose payload is the binary of the module.
+    Wasm,
 }
 
 impl std::default::Default for ExecutorType {
@@ -56,6 +75,7 @@ impl std::convert::TryFrom<&str> for ExecutorType {
         let executor_type = match selector {
             "python" => ExecutorType::Python,
             "builtin" => ExecutorType::Builtin,
+            "wasm" => ExecutorType::Wasm,
             _ => anyhow::bail!("Invalid executor type: {}", selector),
         };
         Ok(executor_type)
@@ -81,6 +101,7 @@ impl std::fmt::Display for ExecutorType {
         match self {
             ExecutorType::Builtin => write!(f, "builtin"),
             ExecutorType::Python => write!(f, "python"),
+            ExecutorType::Wasm => write!(f, "wasm"),
         }
     }
 }
@@ -89,6 +110,8 @@ impl std::fmt::Display for ExecutorType {
 pub enum Executor {
     MesaPy,
     Builtin,
+    /// WebAssembly Micro Runtime, interpreting WebAssembly modules.
+    WAMicroRuntime,
 }
 
 impl std::default::Default for Executor {
@@ -104,6 +127,7 @@ impl std::convert::TryFrom<&str> for Executor {
         let executor = match selector {
             "mesapy" => Executor::MesaPy,
             "builtin" => Executor::Builtin,
+            "wamr" => Executor::WAMicroRuntime,
             _ => anyhow::bail!("Unsupported executor: {}", selector),
         };
         Ok(executor)
@@ -123,6 +147,7 @@ impl std::fmt::Display for Executor {
         match self {
             Executor::MesaPy => write!(f, "mesapy"),
             Executor::Builtin => write!(f, "builtin"),
+            Executor::WAMicroRuntime => write!(f, "wamr"),
         }
     }
 }
@@ -143,7 +168,29 @@ pub struct ExecutionResult {
 pub mod tests {
     use super::*;
 
+    use std::convert::TryFrom;
+
     pub fn run_tests() -> bool {
+        for executor_type in &[
+            ExecutorType::Builtin,
+            ExecutorType::Python,
+            ExecutorType::Wasm,
+        ] {
+            let name = executor_type.to_string();
+            assert_eq!(
+                ExecutorType::try_from(name.as_str()).unwrap(),
+                *executor_type
+            );
+        }
+        for executor in &[
+            Executor::MesaPy,
+            Executor::Builtin,
+            Executor::WAMicroRuntime,
+        ] {
+            let name = executor.to_string();
+            assert_eq!(Executor::try_from(name.as_str()).unwrap(), *executor);
+        }
+        assert!(Executor::try_from("wasm").is_err());
         true
     }
 }
@@ -0,0 +1,116 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::io;
+
+use teaclave_types::{CancelToken, FunctionEnv, TaskPreempted, TeaclaveRuntime};
+
+type BoxedTeaclaveRuntime = Box<dyn TeaclaveRuntime + Send + Sync>;
+
+/// A runtime failing the I/O of a canceled function, so that a function
+/// left running in the background by the worker stops at its next read or
+/// write instead of finishing its work.
+pub(crate) struct CancelableRuntime {
+    inner: BoxedTeaclaveRuntime,
+    cancel_token: CancelToken,
+}
+
+impl CancelableRuntime {
+    pub(crate) fn new(inner: BoxedTeaclaveRuntime, cancel_token: CancelToken) -> Self {
+        Self {
+            inner,
+            cancel_token,
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.cancel_token.is_canceled() {
+            return Err(TaskPreempted.into());
+        }
+        Ok(())
+    }
+}
+
+impl TeaclaveRuntime for CancelableRuntime {
+    fn open_input(&self, identifier: &str) -> anyhow::Result<Box<dyn io::Read>> {
+        self.check()?;
+        let input = self.inner.open_input(identifier)?;
+        Ok(Box::new(CancelableIo {
+            inner: input,
+            cancel_token: self.cancel_token.clone(),
+        }))
+    }
+
+    fn create_output(&self, identifier: &str) -> anyhow::Result<Box<dyn io::Write>> {
+        self.check()?;
+        let output = self.inner.create_output(identifier)?;
+        Ok(Box::new(CancelableIo {
+            inner: output,
+            cancel_token: self.cancel_token.clone(),
+        }))
+    }
+
+    fn env(&self) -> &FunctionEnv {
+        self.inner.env()
+    }
+
+    fn log(&self, message: &str) {
+        self.inner.log(message)
+    }
+
+    fn emit_result(&self, chunk: &[u8]) {
+        if !self.cancel_token.is_canceled() {
+            self.inner.emit_result(chunk)
+        }
+    }
+}
+
+struct CancelableIo<T> {
+    inner: T,
+    cancel_token: CancelToken,
+}
+
+impl<T> CancelableIo<T> {
+    fn check(&self) -> io::Result<()> {
+        if self.cancel_token.is_canceled() {
+            return Err(io::Error::new(io::ErrorKind::Other, TaskPreempted));
+        }
+        Ok(())
+    }
+}
+
+impl io::Read for CancelableIo<Box<dyn io::Read>> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        self.inner.read(buf)
+    }
+}
+
+impl io::Write for CancelableIo<Box<dyn io::Write>> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.check()?;
+        self.inner.flush()
+    }
+}
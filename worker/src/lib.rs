@@ -19,7 +19,10 @@
 #[cfg(feature = "mesalock_sgx")]
 extern crate sgx_tstd as std;
 
+mod cancel;
+mod output_limit;
 mod worker;
+pub use teaclave_executor::{BuiltinFunction, BuiltinFunctionExecutor};
 pub use worker::Worker;
 
 #[cfg(feature = "enclave_unit_test")]
@@ -0,0 +1,113 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+#[cfg(feature = "mesalock_sgx")]
+use std::prelude::v1::*;
+
+use std::io;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+#[cfg(not(feature = "mesalock_sgx"))]
+use std::sync::Mutex;
+#[cfg(feature = "mesalock_sgx")]
+use std::sync::SgxMutex as Mutex;
+
+use teaclave_types::{FunctionEnv, TaskBudgetError, TeaclaveRuntime};
+
+type BoxedTeaclaveRuntime = Box<dyn TeaclaveRuntime + Send + Sync>;
+// Where the worker waits for the result of the function.
+type ResultSender = Arc<Mutex<Sender<anyhow::Result<String>>>>;
+
+/// A runtime limiting the bytes written to each output of the function. The
+/// write over the limit fails and is not passed to the output, and the worker
+/// is told to stop waiting for the function with `OutputTooLarge`.
+pub(crate) struct OutputLimitedRuntime {
+    inner: BoxedTeaclaveRuntime,
+    limit: u64,
+    result_sender: ResultSender,
+}
+
+impl OutputLimitedRuntime {
+    pub(crate) fn new(
+        inner: BoxedTeaclaveRuntime,
+        limit: u64,
+        result_sender: Sender<anyhow::Result<String>>,
+    ) -> Self {
+        Self {
+            inner,
+            limit,
+            result_sender: Arc::new(Mutex::new(result_sender)),
+        }
+    }
+}
+
+impl TeaclaveRuntime for OutputLimitedRuntime {
+    fn open_input(&self, identifier: &str) -> anyhow::Result<Box<dyn io::Read>> {
+        self.inner.open_input(identifier)
+    }
+
+    fn create_output(&self, identifier: &str) -> anyhow::Result<Box<dyn io::Write>> {
+        let output = self.inner.create_output(identifier)?;
+        Ok(Box::new(LimitedOutput {
+            inner: output,
+            identifier: identifier.to_string(),
+            written: 0,
+            limit: self.limit,
+            result_sender: self.result_sender.clone(),
+        }))
+    }
+
+    fn env(&self) -> &FunctionEnv {
+        self.inner.env()
+    }
+
+    fn log(&self, message: &str) {
+        self.inner.log(message)
+    }
+
+    fn emit_result(&self, chunk: &[u8]) {
+        self.inner.emit_result(chunk)
+    }
+}
+
+struct LimitedOutput {
+    inner: Box<dyn io::Write>,
+    identifier: String,
+    written: u64,
+    limit: u64,
+    result_sender: ResultSender,
+}
+
+impl io::Write for LimitedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written.saturating_add(buf.len() as u64) > self.limit {
+            log::warn!("Output {} exceeds {} bytes", self.identifier, self.limit);
+            if let Ok(sender) = self.result_sender.lock() {
+                let error = TaskBudgetError::OutputTooLarge(self.identifier.clone());
+                let _ = sender.send(Err(error.into()));
+            }
+            let error = TaskBudgetError::OutputTooLarge(self.identifier.clone());
+            return Err(io::Error::new(io::ErrorKind::Other, error));
+        }
+        let size = self.inner.write(buf)?;
+        self.written += size as u64;
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -20,21 +20,48 @@ use std::prelude::v1::*;
 
 use std::collections::HashMap;
 use std::format;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
 
-use teaclave_types::{Executor, ExecutorType, StagedFiles, StagedFunction};
+use teaclave_types::{
+    platform, CancelToken, Executor, ExecutorType, FunctionEnv, FunctionLog, ResultStream,
+    StagedFiles, StagedFunction, TaskBudgetError, TaskPreempted,
+};
 
+use crate::cancel::CancelableRuntime;
+use crate::output_limit::OutputLimitedRuntime;
+#[cfg(feature = "wamr")]
+use teaclave_executor::WAMicroRuntime;
 use teaclave_executor::{BuiltinFunctionExecutor, MesaPy};
 use teaclave_runtime::DefaultRuntime;
 use teaclave_types::{TeaclaveExecutor, TeaclaveRuntime};
 
 type BoxedTeaclaveExecutor = Box<dyn TeaclaveExecutor + Send + Sync>;
 type BoxedTeaclaveRuntime = Box<dyn TeaclaveRuntime + Send + Sync>;
-type ExecutorBuilder = fn() -> BoxedTeaclaveExecutor;
-type RuntimeBuilder = fn(StagedFiles, StagedFiles) -> BoxedTeaclaveRuntime;
+type ExecutorBuilder = Box<dyn Fn() -> BoxedTeaclaveExecutor + Send + Sync>;
+type RuntimeBuilder =
+    fn(StagedFiles, StagedFiles, FunctionEnv, FunctionLog, ResultStream) -> BoxedTeaclaveRuntime;
+
+// Interval of checking whether a cancelable function is canceled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Functions left running in the background, each holding a thread (TCS) of
+// the enclave until it returns. The worker stops running functions beyond
+// this, as the enclave would run out of threads.
+const MAX_ABANDONED_THREADS: usize = 3;
+
+// States of a function thread
+const THREAD_RUNNING: u8 = 0;
+const THREAD_ABANDONED: u8 = 1;
+const THREAD_EXITED: u8 = 2;
 
 pub struct Worker {
     runtimes: HashMap<String, RuntimeBuilder>,
     executors: HashMap<(ExecutorType, Executor), ExecutorBuilder>,
+    builtin_functions: Vec<String>,
+    abandoned_threads: Arc<AtomicUsize>,
 }
 
 impl Default for Worker {
@@ -42,21 +69,33 @@ impl Default for Worker {
         let mut worker = Worker::new();
 
         // Register supported runtimes
-        worker.register_runtime("default", |input, output| {
-            Box::new(DefaultRuntime::new(input, output))
+        worker.register_runtime("default", |input, output, env, log, result_stream| {
+            Box::new(
+                DefaultRuntime::new(input, output)
+                    .with_env(env)
+                    .with_log(log)
+                    .with_result_stream(result_stream),
+            )
         });
 
         #[cfg(test_mode)]
-        worker.register_runtime("raw-io", |input, output| {
-            Box::new(teaclave_runtime::RawIoRuntime::new(input, output))
+        worker.register_runtime("raw-io", |input, output, env, log, result_stream| {
+            Box::new(
+                teaclave_runtime::RawIoRuntime::new(input, output)
+                    .with_env(env)
+                    .with_log(log)
+                    .with_result_stream(result_stream),
+            )
         });
 
         // Register supported executors
         worker.register_executor((ExecutorType::Python, Executor::MesaPy), || {
             Box::new(MesaPy::default())
         });
-        worker.register_executor((ExecutorType::Builtin, Executor::Builtin), || {
-            Box::new(BuiltinFunctionExecutor::default())
+        worker.register_builtin_functions(BuiltinFunctionExecutor::default());
+        #[cfg(feature = "wamr")]
+        worker.register_executor((ExecutorType::Wasm, Executor::WAMicroRuntime), || {
+            Box::new(WAMicroRuntime::default())
         });
 
         worker
@@ -68,6 +107,8 @@ impl Worker {
         Self {
             runtimes: HashMap::new(),
             executors: HashMap::new(),
+            builtin_functions: Vec::new(),
+            abandoned_threads: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -75,18 +116,148 @@ impl Worker {
         self.runtimes.insert(name.to_string(), builder);
     }
 
-    pub fn register_executor(&mut self, key: (ExecutorType, Executor), builder: ExecutorBuilder) {
-        self.executors.insert(key, builder);
+    pub fn register_executor(
+        &mut self,
+        key: (ExecutorType, Executor),
+        builder: impl Fn() -> BoxedTeaclaveExecutor + Send + Sync + 'static,
+    ) {
+        self.executors.insert(key, Box::new(builder));
+    }
+
+    /// Registers the builtin executor with the functions of the registry,
+    /// replacing the functions registered before.
+    pub fn register_builtin_functions(&mut self, functions: BuiltinFunctionExecutor) {
+        self.builtin_functions = functions.names();
+        self.register_executor((ExecutorType::Builtin, Executor::Builtin), move || {
+            Box::new(functions.clone())
+        });
+    }
+
+    /// Names of the registered executors, e.g., `builtin` or `mesapy`.
+    pub fn executors(&self) -> Vec<String> {
+        let mut executors: Vec<String> = self
+            .executors
+            .keys()
+            .map(|(_, executor)| executor.to_string())
+            .collect();
+        executors.sort();
+        executors.dedup();
+        executors
+    }
+
+    pub fn builtin_functions(&self) -> Vec<String> {
+        self.builtin_functions.clone()
+    }
+
+    /// Functions which ran out of time, wrote too much to an output or were
+    /// canceled, and are still running in the background.
+    pub fn abandoned_threads(&self) -> usize {
+        self.abandoned_threads.load(Ordering::SeqCst)
+    }
+
+    /// Whether too many functions are left running to run any more with a
+    /// time or output limit, or canceled. Only a restart of the enclave frees
+    /// their threads.
+    pub fn is_exhausted(&self) -> bool {
+        self.abandoned_threads() >= MAX_ABANDONED_THREADS
     }
 
     pub fn invoke_function(&self, function: StagedFunction) -> anyhow::Result<String> {
+        self.invoke_function_holding(function, ())
+    }
+
+    /// Invokes the function, holding the guard (e.g., a reservation of the
+    /// resources of the function) until the function has returned, even if
+    /// it is left running in the background.
+    pub fn invoke_function_holding<G: Send + 'static>(
+        &self,
+        function: StagedFunction,
+        guard: G,
+    ) -> anyhow::Result<String> {
+        log::debug!(
+            "invoke_function: function={} executor={:?}",
+            function.name,
+            function.executor,
+        );
+        let cancel_token = function.cancel_token;
+        if cancel_token.iter().any(CancelToken::is_canceled) {
+            return Err(TaskPreempted.into());
+        }
         let executor = self.get_executor(function.executor_type, function.executor)?;
         let runtime = self.get_runtime(
             &function.runtime_name,
             function.input_files,
             function.output_files,
+            function.env,
+            function.log,
+            function.result_stream,
         )?;
-        executor.execute(function.name, function.arguments, function.payload, runtime)
+        let (sender, receiver) = channel();
+        let runtime: BoxedTeaclaveRuntime = match function.output_size_limit {
+            Some(limit) => Box::new(OutputLimitedRuntime::new(runtime, limit, sender.clone())),
+            None => runtime,
+        };
+        let runtime: BoxedTeaclaveRuntime = match &cancel_token {
+            Some(token) => Box::new(CancelableRuntime::new(runtime, token.clone())),
+            None => runtime,
+        };
+        let time_limit = function.time_limit;
+        if time_limit.is_none() && function.output_size_limit.is_none() && cancel_token.is_none() {
+            return executor.execute_with_bundle(
+                function.name,
+                function.arguments,
+                function.payload,
+                function.bundle,
+                runtime,
+            );
+        }
+
+        // A thread in the enclave cannot be killed, so a function running out
+        // of time, writing too much to an output or canceled is left running
+        // in the background and its result is dropped.
+        anyhow::ensure!(
+            !self.is_exhausted(),
+            "Too many functions left running, the enclave needs a restart"
+        );
+        let state = Arc::new(AtomicU8::new(THREAD_RUNNING));
+        let exit = ThreadExit {
+            state: state.clone(),
+            abandoned_threads: self.abandoned_threads.clone(),
+        };
+        std::thread::spawn(move || {
+            let _guard = guard;
+            let result = executor.execute_with_bundle(
+                function.name,
+                function.arguments,
+                function.payload,
+                function.bundle,
+                runtime,
+            );
+            drop(exit);
+            let _ = sender.send(result);
+        });
+        let result = wait_for_result(&receiver, time_limit, cancel_token.as_ref());
+
+        // Counted before marking the thread, so that it cannot be uncounted
+        // first when exiting meanwhile.
+        self.abandoned_threads.fetch_add(1, Ordering::SeqCst);
+        if state
+            .compare_exchange(
+                THREAD_RUNNING,
+                THREAD_ABANDONED,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+        {
+            log::warn!(
+                "Function left running, {} in total",
+                self.abandoned_threads()
+            );
+        } else {
+            self.abandoned_threads.fetch_sub(1, Ordering::SeqCst);
+        }
+        result
     }
 
     fn get_runtime(
@@ -94,13 +265,16 @@ impl Worker {
         name: &str,
         input_files: StagedFiles,
         output_files: StagedFiles,
+        env: FunctionEnv,
+        log: FunctionLog,
+        result_stream: ResultStream,
     ) -> anyhow::Result<BoxedTeaclaveRuntime> {
         let build_runtime = self
             .runtimes
             .get(name)
             .ok_or_else(|| anyhow::anyhow!(format!("Runtime {} not available.", name)))?;
 
-        let runtime = build_runtime(input_files, output_files);
+        let runtime = build_runtime(input_files, output_files, env, log, result_stream);
         Ok(runtime)
     }
 
@@ -120,3 +294,54 @@ impl Worker {
         Ok(executor)
     }
 }
+
+// Marks the thread of a function exited when dropped, uncounting it if it was
+// abandoned.
+struct ThreadExit {
+    state: Arc<AtomicU8>,
+    abandoned_threads: Arc<AtomicUsize>,
+}
+
+impl Drop for ThreadExit {
+    fn drop(&mut self) {
+        if self.state.swap(THREAD_EXITED, Ordering::SeqCst) == THREAD_ABANDONED {
+            self.abandoned_threads.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+// Waits for the result of the function until its time limit, checking the
+// cancel token every CANCEL_POLL_INTERVAL.
+fn wait_for_result(
+    receiver: &Receiver<anyhow::Result<String>>,
+    time_limit: Option<Duration>,
+    cancel_token: Option<&CancelToken>,
+) -> anyhow::Result<String> {
+    let started = platform::time::now();
+    loop {
+        let elapsed = platform::time::now()
+            .duration_since(started)
+            .unwrap_or_default();
+        let wait = match time_limit {
+            Some(time_limit) if elapsed >= time_limit => {
+                return Err(TaskBudgetError::ExecutionTimeout.into())
+            }
+            Some(time_limit) => time_limit - elapsed,
+            None => CANCEL_POLL_INTERVAL,
+        };
+        let wait = match cancel_token {
+            Some(_) => std::cmp::min(wait, CANCEL_POLL_INTERVAL),
+            None => wait,
+        };
+        match receiver.recv_timeout(wait) {
+            Ok(result) => return result,
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(anyhow::anyhow!("function exited unexpectedly"))
+            }
+        }
+        if cancel_token.map_or(false, CancelToken::is_canceled) {
+            return Err(TaskPreempted.into());
+        }
+    }
+}